@@ -19,19 +19,38 @@ use winit::event_loop::{
     EventLoop
 };
 use winit::dpi;
-use winit::window::{Fullscreen, WindowBuilder, Window};
+use winit::window::{WindowBuilder, Window};
 use winit::monitor::{MonitorHandle, VideoMode};
 
+use crate::constants;
+use crate::application::benchmark::{ BenchmarkData, BenchmarkSettings };
+use crate::application::console::{ Console, ConsoleCommandContext };
+use crate::application::engine::{ EngineConfig, RenderExtension };
+use crate::application::environment_settings;
+use crate::application::haptics::{ self, HapticsEvent, HapticsManager };
 use crate::application::scene_manager::{ SceneManagerData, ProjectSceneManagerBase };
 use crate::application::input;
-use crate::resource::resource::{Resources, ProjectResourcesBase};
+use crate::application::save_state;
+use crate::application::scene_file;
+use crate::application::soak_test::{ SoakData, SoakSettings };
+use crate::application::time_of_day;
+use crate::application::window_integration::{ self, TaskbarProgress, WindowConfig, WindowMode, WindowTitle };
+use crate::resource::asset_report::{ self, AssetReportSettings };
+use crate::resource::resource::{ Resources, ProjectResourcesBase, ResourceInitializeResult };
 use crate::renderer::renderer::{ RendererData, ProjectRendererBase };
+use crate::renderer::screenshot;
+use crate::renderer::tonemap;
 use crate::renderer::font::FontManager;
 use crate::renderer::ui::{ ProjectUIManagerBase, UIManagerData };
+use crate::renderer::viewport::{ self, ViewportRect };
 use crate::utilities::system::{ RcRefCell, newRcRefCell };
 use crate::utilities::logger;
+use crate::utilities::progress;
+use crate::utilities::telemetry::TelemetryReporter;
 use std::io::Write;
 
+pub const FIXED_TIME_STEP_EPSILON: f64 = 1e-6; // seconds, the rounding error of the accumulated delta time
+
 #[derive(Debug, Clone)]
 pub struct TimeData {
     pub _acc_frame_time: f64,
@@ -42,7 +61,11 @@ pub struct TimeData {
     pub _current_time: f64,
     pub _elapsed_time_prev: f64,
     pub _elapsed_time: f64,
-    pub _delta_time: f64
+    pub _delta_time: f64,
+    pub _fixed_time_accumulator: f64, // the real time not simulated yet, less than FIXED_TIME_STEP
+    pub _fixed_elapsed_time: f64, // the time of the last simulation step
+    pub _fixed_step_count: u32, // the simulation steps of this frame
+    pub _fixed_step_alpha: f32, // the render interpolation between the last two simulation steps
 }
 
 // Enumerate monitors and prompt user to choose one
@@ -98,7 +121,11 @@ pub fn create_time_data(elapsed_time: f64) -> TimeData {
         _elapsed_time_prev: elapsed_time,
         _current_time: elapsed_time,
         _elapsed_time: elapsed_time,
-        _delta_time: 0.0
+        _delta_time: 0.0,
+        _fixed_time_accumulator: 0.0,
+        _fixed_elapsed_time: elapsed_time,
+        _fixed_step_count: 0,
+        _fixed_step_alpha: 1.0,
     }
 }
 
@@ -130,6 +157,47 @@ impl TimeData {
         self._delta_time = delta_time;
         debug_text
     }
+
+    // the simulation steps of this frame from the accumulated delta time. the steps over MAX_FIXED_STEPS_PER_FRAME are dropped,
+    // so a long frame doesn't make the next frames longer.
+    pub fn update_fixed_time_step(&mut self) -> u32 {
+        let (fixed_time_step, max_fixed_steps) = unsafe { (constants::FIXED_TIME_STEP, constants::MAX_FIXED_STEPS_PER_FRAME.max(1)) };
+        if fixed_time_step <= 0.0 {
+            self._fixed_time_accumulator = 0.0;
+            self._fixed_step_count = 1;
+            self._fixed_step_alpha = 1.0;
+            return 1;
+        }
+        // the benchmark delta time of the same step always gives one step
+        self._fixed_time_accumulator += self._delta_time;
+        let step_count = ((self._fixed_time_accumulator + FIXED_TIME_STEP_EPSILON) / fixed_time_step).floor() as u32;
+        if max_fixed_steps < step_count {
+            self._fixed_time_accumulator = 0.0;
+            self._fixed_step_count = max_fixed_steps;
+        } else {
+            self._fixed_time_accumulator = (self._fixed_time_accumulator - step_count as f64 * fixed_time_step).max(0.0);
+            self._fixed_step_count = step_count;
+        }
+        self._fixed_step_alpha = (self._fixed_time_accumulator / fixed_time_step).min(1.0) as f32;
+        self._fixed_step_count
+    }
+
+    // the time data of the next simulation step, the delta time is the fixed step
+    pub fn next_fixed_step_time_data(&mut self) -> TimeData {
+        let fixed_time_step = unsafe { constants::FIXED_TIME_STEP };
+        let mut fixed_time_data = self.clone();
+        if 0.0 < fixed_time_step {
+            fixed_time_data._delta_time = fixed_time_step;
+            fixed_time_data._elapsed_time_prev = self._fixed_elapsed_time;
+            fixed_time_data._elapsed_time = self._fixed_elapsed_time + fixed_time_step;
+        }
+        self._fixed_elapsed_time = fixed_time_data._elapsed_time;
+        fixed_time_data
+    }
+
+    pub fn get_fixed_step_stats_text(&self) -> String {
+        format!("simulation steps: {} / alpha {:.2}", self._fixed_step_count, self._fixed_step_alpha)
+    }
 }
 
 pub trait ApplicationBase {
@@ -144,17 +212,42 @@ pub struct EngineApplication {
     pub _is_grab_mode: bool,
     pub _is_grab_mode_backup: bool,
     pub _window_size: Vector2<i32>,
+    pub _window_config: WindowConfig,
+    pub _windowed_size: (u32, u32), // restored by toggle_borderless_fullscreen
+    pub _is_window_mode_changed: bool, // the swapchain is recreated, the exclusive fullscreen can change the surface capabilities without resizing
+    pub _viewport_rect: ViewportRect, // letterboxed rect in the window, camera aspect, ui and mouse use this space.
     pub _time_data: TimeData,
     pub _camera_move_speed: f32,
+    pub _is_camera_look_enabled: bool, // false: the project rotates the main camera itself
     pub _keyboard_input_data: Box<input::KeyboardInputData>,
     pub _mouse_move_data: Box<input::MouseMoveData>,
     pub _mouse_input_data: Box<input::MouseInputData>,
+    pub _gamepad_input_data: Box<input::GamepadInputData>,
+    pub _benchmark_data: Option<BenchmarkData>,
+    pub _soak_data: Option<SoakData>,
+    pub _telemetry_reporter: TelemetryReporter,
+    pub _window_title: WindowTitle,
+    pub _haptics_manager: HapticsManager,
+    pub _console: Console,
     pub _scene_manager_data: RcRefCell<SceneManagerData>,
     pub _renderer_data: RcRefCell<RendererData>,
     pub _font_manager: RcRefCell<FontManager>,
     pub _ui_manager_data: RcRefCell<UIManagerData>,
     pub _resources: RcRefCell<Resources>,
     pub _application: *const dyn ApplicationBase,
+    pub _initialize_errors: Vec<String>, // not empty in the degraded mode, listed by the error screen
+    pub _is_managers_initialized: bool, // the ui, the scene and the application, skipped when the resources failed
+    pub _is_font_manager_initialized: bool,
+    pub _is_error_screen_text_enabled: bool,
+}
+
+pub fn get_error_screen_text(initialize_errors: &[String]) -> String {
+    let mut text = String::from("initialization failed, the engine runs in the degraded mode.");
+    for initialize_error in initialize_errors.iter() {
+        text.push_str(&format!("\n    - {}", initialize_error));
+    }
+    text.push_str("\n\nsee the log for the details. Ctrl+R: reload after fixing the contents, Escape: quit");
+    text
 }
 
 impl EngineApplication {
@@ -178,15 +271,65 @@ impl EngineApplication {
         resources: &mut Resources,
         renderer_data: &mut RendererData,
     ) {
-        scene_manager_data.close_scene_data(renderer_data.get_device());
+        if self._is_managers_initialized {
+            scene_manager_data.close_scene_data(renderer_data.get_device());
+        }
 
         // destroy managers
-        self.get_application_mut().terminate_application();
-        scene_manager_data.destroy_scene_manager_data(renderer_data.get_device());
-        ui_manager_data.destroy_ui_manager_data(renderer_data.get_device());
-        font_manager.destroy_font_manager(renderer_data.get_device());
+        if self._is_managers_initialized {
+            self.get_application_mut().terminate_application();
+            scene_manager_data.destroy_scene_manager_data(renderer_data.get_device());
+            ui_manager_data.destroy_ui_manager_data(renderer_data.get_device());
+        }
+        if self._is_font_manager_initialized {
+            font_manager.destroy_font_manager(renderer_data.get_device());
+        }
         resources.destroy_resources(renderer_data);
         renderer_data.destroy_renderer_data();
+        progress::clear_progress_callbacks();
+    }
+
+    // the resources or the scene failed to initialize, the render loop shows the error screen instead of the scene.
+    pub fn is_degraded_mode(&self) -> bool {
+        false == self._initialize_errors.is_empty()
+    }
+
+    pub fn get_error_screen_text(&self) -> String {
+        get_error_screen_text(&self._initialize_errors)
+    }
+
+    // the frame of the degraded mode, a failed resize or text pass falls back to the clear of the swapchain image.
+    pub fn render_error_screen(&mut self, renderer_data: &mut RendererData, font_manager: &mut FontManager) {
+        if renderer_data.get_need_recreate_swapchain() {
+            let mut recreate_result = ResourceInitializeResult::default();
+            if self._is_font_manager_initialized {
+                font_manager.destroy_font_descriptor_sets(&mut renderer_data._resources.borrow_mut());
+            }
+            let is_resized = recreate_result.run_step("resize window", || renderer_data.resize_window());
+            if is_resized && self._is_font_manager_initialized {
+                font_manager.create_font_descriptor_sets(renderer_data, &mut renderer_data._resources.borrow_mut());
+            } else {
+                self._is_error_screen_text_enabled = false;
+            }
+            renderer_data.set_need_recreate_swapchain(false);
+            return;
+        }
+
+        // If the resized event has not yet occurred, the window size may be 0.
+        if self._window_size.x <= 0 || self._window_size.y <= 0 {
+            return;
+        }
+
+        if self._is_error_screen_text_enabled {
+            font_manager.clear_logs();
+            font_manager.log(self.get_error_screen_text());
+            if false == renderer_data.render_error_screen(Some(font_manager)) {
+                self._is_error_screen_text_enabled = false;
+            }
+        } else {
+            renderer_data.render_error_screen(None);
+        }
+        self.update_window_title("initialization failed");
     }
 
     pub fn clear_input_events(&mut self) {
@@ -194,6 +337,17 @@ impl EngineApplication {
         self._mouse_input_data.clear_mouse_input();
         self._keyboard_input_data.clear_key_pressed();
         self._keyboard_input_data.clear_key_released();
+        self._keyboard_input_data.clear_key_repeat();
+        self._keyboard_input_data.clear_chars();
+    }
+
+    // the benchmark runs without the input, the held keys and buttons are dropped as well.
+    pub fn ignore_input_events(&mut self) {
+        let viewport_size = self._viewport_rect.get_size();
+        self._keyboard_input_data = input::create_keyboard_input_data();
+        self._mouse_move_data = input::create_mouse_move_data(viewport_size.x / 2, viewport_size.y / 2);
+        self._mouse_input_data = input::create_mouse_input_data();
+        self._gamepad_input_data.reset_gamepad_state();
     }
 
     pub fn set_grab_mode(&mut self, is_grab_mode: bool) {
@@ -202,10 +356,92 @@ impl EngineApplication {
         self.get_window().set_cursor_visible(!is_grab_mode);
     }
 
+    // the new size comes through WindowEvent::Resized, then the swapchain is recreated with the queried surface capabilities.
+    pub fn set_window_config(&mut self, window_config: &WindowConfig) {
+        if *window_config == self._window_config {
+            return;
+        }
+        log::info!("set_window_config: {:?}", window_config);
+        let window = self.get_window();
+        let fullscreen = window_integration::get_fullscreen(window_config, window.available_monitors().collect(), window.primary_monitor());
+        window.set_fullscreen(fullscreen);
+        if let WindowMode::Windowed { _width, _height } = window_config._window_mode {
+            window.set_inner_size(dpi::Size::Physical(dpi::PhysicalSize { width: _width, height: _height }));
+            self._windowed_size = (_width, _height);
+        }
+        self._window_config = window_config.clone();
+        self._is_window_mode_changed = true;
+    }
+
+    // Alt + Enter, the borderless fullscreen on the current monitor or the last windowed mode
+    pub fn toggle_borderless_fullscreen(&mut self) {
+        let window_mode = match self._window_config._window_mode {
+            WindowMode::BorderlessFullscreen => WindowMode::Windowed { _width: self._windowed_size.0, _height: self._windowed_size.1 },
+            _ => WindowMode::BorderlessFullscreen,
+        };
+        let window_config = WindowConfig {
+            _window_mode: window_mode,
+            _monitor_index: self._window_config._monitor_index,
+        };
+        self.set_window_config(&window_config);
+    }
+
+    // returns true when the letterbox rect has been changed by resizing or the target aspect.
+    pub fn update_viewport_rect(&mut self) -> bool {
+        let viewport_rect = viewport::get_letterbox_rect(&self._window_size);
+        let changed = viewport_rect != self._viewport_rect;
+        self._viewport_rect = viewport_rect;
+        changed
+    }
+
+    // the icon can be changed at runtime, ex) window_integration::WINDOW_ICON_FILE_PATH
+    pub fn set_window_icon(&self, icon_file_path: &str) {
+        let icon = window_integration::load_window_icon(&self._resources.borrow(), icon_file_path);
+        window_integration::set_window_icon(self.get_window(), icon);
+    }
+
+    pub fn set_window_title(&mut self, base_title: &str) {
+        self._window_title.set_base_title(base_title);
+    }
+
+    // ex) window_integration::WINDOW_TITLE_MARKER_RECORDING
+    pub fn set_window_title_marker(&mut self, marker: &str, enable: bool) {
+        self._window_title.set_marker(marker, enable);
+    }
+
+    pub fn update_window_title(&mut self, scene_name: &str) {
+        let window: &Window = unsafe { &*self._window };
+        self._window_title.set_scene_name(scene_name);
+        self._window_title.update_window_title(window, self._time_data._elapsed_time, self._time_data._average_fps);
+    }
+
+    // the mouse look of the main camera in the grab mode, with the right button or the touch drag.
+    // the sensitivity and the invert-y of InputSettings are applied by MouseMoveData::get_look_delta.
+    pub fn update_camera_look(&self, scene_manager_data: &SceneManagerData) {
+        let mouse_move_data = &self._mouse_move_data;
+        if false == self._is_camera_look_enabled || false == (self._is_grab_mode || self._mouse_input_data._btn_r_hold || mouse_move_data._is_touch_input) {
+            return;
+        }
+        let look_delta = mouse_move_data.get_look_delta();
+        if 0.0 != look_delta.x || 0.0 != look_delta.y {
+            let (camera_position, camera_rotation) = scene_manager_data.get_main_camera_transform();
+            scene_manager_data.set_main_camera_transform(&camera_position, &input::get_look_rotation(&camera_rotation, &look_delta));
+        }
+    }
+
+    // R: reload the shaders, ex) iterating on the post process shaders without the file watcher
     pub fn update_event(&self) {
+        if self._keyboard_input_data.get_key_pressed(VirtualKeyCode::R) && false == self._keyboard_input_data._modifier_keys_control {
+            self._resources.borrow_mut().request_reload_all_shaders();
+        }
         self.get_application_mut().update_event();
     }
 
+    // ex) engine_application.get_console_mut().get_command_registry_mut().register_command(..)
+    pub fn get_console_mut(&mut self) -> &mut Console {
+        &mut self._console
+    }
+
     pub fn update_application(&self) {
         self.get_application_mut().update_application();
     }
@@ -219,21 +455,57 @@ pub fn run_application(
     project_renderer: *const dyn ProjectRendererBase,
     project_ui_manager: *const dyn ProjectUIManagerBase,
 ) {
-    logger::initialize_logger(log_level);
+    let engine_config = EngineConfig {
+        _log_level: log_level,
+        ..Default::default()
+    };
+    run_application_with_config(
+        engine_config,
+        application,
+        project_resources,
+        project_scene_manager,
+        project_renderer,
+        project_ui_manager,
+        Vec::new(),
+    );
+}
+
+// the main loop of the engine, see engine::EngineBuilder for the order of the initialization.
+pub fn run_application_with_config(
+    engine_config: EngineConfig,
+    application: *const dyn ApplicationBase,
+    project_resources: *const dyn ProjectResourcesBase,
+    project_scene_manager: *const dyn ProjectSceneManagerBase,
+    project_renderer: *const dyn ProjectRendererBase,
+    project_ui_manager: *const dyn ProjectUIManagerBase,
+    render_extensions: Vec<*const dyn RenderExtension>,
+) {
+    logger::initialize_logger(engine_config._log_level);
 
     log::info!("run_application");
 
-    let app_name: &str = "RustEngine3D";
-    let app_version: u32 = 1;
-    let initial_window_size: Vector2<i32> = Vector2::new(1024, 768);
+    let app_name: String = engine_config._app_name.clone();
+    let app_version: u32 = engine_config._app_version;
+    let window_config: WindowConfig = engine_config._window_config.clone();
+    let windowed_size: (u32, u32) = window_config.get_windowed_size();
+    let benchmark_settings: Option<BenchmarkSettings> = BenchmarkSettings::create_benchmark_settings(&std::env::args().collect::<Vec<String>>());
+    if benchmark_settings.is_some() {
+        BenchmarkData::apply_benchmark_configs();
+    }
+    let soak_settings: Option<SoakSettings> = SoakSettings::create_soak_settings(&std::env::args().collect::<Vec<String>>());
+    if soak_settings.is_some() {
+        SoakData::apply_soak_test_configs();
+    }
+    if let Some(asset_report_settings) = AssetReportSettings::create_asset_report_settings(&std::env::args().collect::<Vec<String>>()) {
+        std::process::exit(asset_report::run_asset_report_standalone(project_resources, &asset_report_settings));
+    }
 
     let time_instance = time::Instant::now();
     let event_loop = EventLoop::new();
     let window: Window = WindowBuilder::new()
-        .with_title(app_name)
-        .with_inner_size(dpi::Size::Physical(dpi::PhysicalSize { width: initial_window_size.x as u32, height: initial_window_size.y as u32 }))
-        //.with_fullscreen(Some(Fullscreen::Exclusive(prompt_for_video_mode(&prompt_for_monitor(&event_loop)))))
-        //.with_fullscreen(Some(Fullscreen::Borderless(Some(prompt_for_monitor(&event_loop)))))
+        .with_title(&app_name)
+        .with_inner_size(dpi::Size::Physical(dpi::PhysicalSize { width: windowed_size.0, height: windowed_size.1 }))
+        .with_fullscreen(window_integration::get_fullscreen(&window_config, event_loop.available_monitors().collect(), event_loop.primary_monitor()))
         .build(&event_loop)
         .unwrap();
     let window_size: Vector2<i32> = Vector2::new(window.inner_size().width as i32, window.inner_size().height as i32);
@@ -273,48 +545,98 @@ pub fn run_application(
             // create managers
             let elapsed_time = time_instance.elapsed().as_secs_f64();
             let resources = newRcRefCell(Resources::create_resources(project_resources));
+            engine_config.mount_resource_mount_points(&mut resources.borrow()._virtual_file_system.borrow_mut());
             let font_manager = newRcRefCell(FontManager::create_font_manager());
             let ui_manager_data = newRcRefCell(UIManagerData::create_ui_manager_data(project_ui_manager));
-            let renderer_data = newRcRefCell(RendererData::create_renderer_data(app_name, app_version, &window_size, &window, &resources, project_renderer));
+            let renderer_data = newRcRefCell(RendererData::create_renderer_data(&app_name, app_version, &window_size, &window, &resources, project_renderer));
             let scene_manager_data = newRcRefCell(SceneManagerData::create_scene_manager_data(&renderer_data, &resources, project_scene_manager));
             let keyboard_input_data = input::create_keyboard_input_data();
-            let mouse_move_data = input::create_mouse_move_data(&window_size.x / 2, &window_size.y / 2);
+            let viewport_rect = viewport::get_letterbox_rect(&window_size);
+            let mouse_move_data = input::create_mouse_move_data(&viewport_rect._width / 2, &viewport_rect._height / 2);
             let mouse_input_data = input::create_mouse_input_data();
 
+            // the taskbar progress of the long operations, ex) resource load, scene load, impostor bake
+            let window_ptr: *const Window = &window;
+            let taskbar_progress = TaskbarProgress::create_taskbar_progress();
+            progress::add_progress_callback(Box::new(move |_progress_task, progress| {
+                taskbar_progress.set_taskbar_progress(unsafe { &*window_ptr }, progress);
+            }));
+
+            renderer_data.borrow_mut().set_render_extensions(&render_extensions);
+
             // initialize managers
             renderer_data.borrow_mut().initialize_renderer_data();
-            resources.borrow_mut().initialize_resources(&mut renderer_data.borrow_mut());
-            font_manager.borrow_mut().initialize_font_manager(&renderer_data.borrow(), &resources.borrow());
-            ui_manager_data.borrow_mut().initialize_ui_manager_data(&renderer_data.borrow(), &resources.borrow());
-            scene_manager_data.borrow_mut().initialize_scene_manager_data(
-                &window_size,
-                &renderer_data.borrow(),
-                &resources.borrow()
-            );
+            let mut initialize_result: ResourceInitializeResult = resources.borrow_mut().initialize_resources(&mut renderer_data.borrow_mut());
+            let is_managers_initialized = initialize_result.is_ok();
+            window_integration::set_window_icon(&window, window_integration::load_window_icon(&resources.borrow(), window_integration::WINDOW_ICON_FILE_PATH));
+            // the font is also used by the error screen, so it is initialized whenever its resources are loaded.
+            let is_font_manager_initialized = resources.borrow().is_font_resources_loaded() && initialize_result.run_step("font manager", || {
+                font_manager.borrow_mut().initialize_font_manager(&renderer_data.borrow(), &mut resources.borrow_mut());
+            });
+            if is_managers_initialized {
+                ui_manager_data.borrow_mut().initialize_ui_manager_data(&renderer_data.borrow(), &mut resources.borrow_mut());
+                scene_manager_data.borrow_mut().initialize_scene_manager_data(
+                    &viewport_rect.get_size(),
+                    &renderer_data.borrow(),
+                    &resources.borrow()
+                );
+            }
             let engine_application = newRcRefCell(EngineApplication {
                 _window: &window,
                 _window_size: window_size.into(),
+                _window_config: window_config.clone(),
+                _windowed_size: windowed_size,
+                _is_window_mode_changed: false,
+                _viewport_rect: viewport_rect,
                 _is_grab_mode: false,
                 _is_grab_mode_backup: false,
                 _time_data: create_time_data(elapsed_time),
                 _camera_move_speed: 1.0,
+                _is_camera_look_enabled: true,
                 _keyboard_input_data: keyboard_input_data,
                 _mouse_move_data: mouse_move_data,
                 _mouse_input_data: mouse_input_data,
+                _gamepad_input_data: input::create_gamepad_input_data(),
+                _benchmark_data: benchmark_settings.clone().map(BenchmarkData::create_benchmark_data),
+                _soak_data: soak_settings.clone().map(SoakData::create_soak_data),
+                _telemetry_reporter: TelemetryReporter::create_telemetry_reporter(elapsed_time),
+                _window_title: WindowTitle::create_window_title(&app_name),
+                _haptics_manager: HapticsManager::create_haptics_manager(haptics::create_default_haptics_backend(), &resources.borrow()),
+                _console: Console::create_console(),
                 _font_manager: font_manager.clone(),
                 _ui_manager_data: ui_manager_data.clone(),
                 _scene_manager_data: scene_manager_data.clone(),
                 _renderer_data: renderer_data.clone(),
                 _resources: resources.clone(),
                 _application: application,
+                _initialize_errors: Vec::new(),
+                _is_managers_initialized: is_managers_initialized,
+                _is_font_manager_initialized: is_font_manager_initialized,
+                _is_error_screen_text_enabled: is_font_manager_initialized,
             });
 
-            // initialize graphics data
-            renderer_data.borrow_mut().prepare_framebuffer_and_descriptors();
-            scene_manager_data.borrow_mut().initialize_scene_graphics_data();
+            if is_managers_initialized {
+                // initialize graphics data
+                renderer_data.borrow_mut().prepare_framebuffer_and_descriptors();
+                scene_manager_data.borrow_mut().initialize_scene_graphics_data();
 
-            // initialize application
-            engine_application.borrow().get_application_mut().initialize_application(&engine_application.borrow());
+                // initialize application, ex) a missing scene file
+                initialize_result.run_step("application", || {
+                    engine_application.borrow().get_application_mut().initialize_application(&engine_application.borrow());
+                });
+            }
+            engine_application.borrow_mut()._initialize_errors = initialize_result._errors;
+            let is_degraded_mode = engine_application.borrow().is_degraded_mode();
+
+            // benchmark, the degraded mode exits with the error in the main loop
+            if let Some(benchmark_data) = engine_application.borrow_mut()._benchmark_data.as_mut().filter(|_| false == is_degraded_mode) {
+                benchmark_data.begin_benchmark(&mut scene_manager_data.borrow_mut(), &renderer_data.borrow());
+            }
+
+            // soak test
+            if let Some(soak_data) = engine_application.borrow_mut()._soak_data.as_mut().filter(|_| false == is_degraded_mode) {
+                soak_data.begin_soak_test(&mut scene_manager_data.borrow_mut(), &renderer_data.borrow());
+            }
 
             // set managers
             maybe_resources = Some(resources);
@@ -359,6 +681,10 @@ pub fn run_application(
                     let mut font_manager: RefMut<FontManager> = maybe_font_manager.as_ref().unwrap().borrow_mut();
                     let mut ui_manager_data: RefMut<UIManagerData> = maybe_ui_manager_data.as_ref().unwrap().borrow_mut();
 
+                    if engine_application._benchmark_data.is_some() || engine_application._soak_data.is_some() {
+                        engine_application.ignore_input_events();
+                    }
+
                     // exit
                     if engine_application._keyboard_input_data.get_key_pressed(VirtualKeyCode::Escape) {
                         *control_flow = ControlFlow::Exit;
@@ -373,18 +699,270 @@ pub fn run_application(
                         return;
                     }
 
-                    // update event
+                    // degraded mode, the error screen until the contents are fixed and reloaded
+                    if engine_application.is_degraded_mode() {
+                        if engine_application._benchmark_data.is_some() || engine_application._soak_data.is_some() {
+                            log::error!("{}", engine_application.get_error_screen_text());
+                            engine_application.terminate_application(
+                                &mut font_manager,
+                                &mut ui_manager_data,
+                                &mut scene_manager_data,
+                                &mut maybe_resources.as_ref().unwrap().borrow_mut(),
+                                &mut renderer_data,
+                            );
+                            std::process::exit(1);
+                        }
+                        // Ctrl+R, F5 is the quicksave of the normal mode
+                        if engine_application._keyboard_input_data._modifier_keys_control && engine_application._keyboard_input_data.get_key_pressed(VirtualKeyCode::R) {
+                            log::info!("reload in the degraded mode");
+                            need_initialize = true;
+                            return;
+                        }
+                        engine_application._time_data.update_time_data(&time_instance);
+                        engine_application.render_error_screen(&mut renderer_data, &mut font_manager);
+                        return;
+                    }
+
+                    // screenshot, with shift capture next frame for graphics debuggers
+                    if engine_application._keyboard_input_data.get_key_pressed(VirtualKeyCode::F12) {
+                        if engine_application._keyboard_input_data._modifier_keys_shift {
+                            renderer_data.request_capture_next_frame();
+                        } else {
+                            renderer_data.request_screenshot(&screenshot::get_default_screenshot_file_path());
+                        }
+                    }
+
+                    let is_automated_run = engine_application._benchmark_data.is_some() || engine_application._soak_data.is_some();
+
+                    // Alt + Enter, the borderless fullscreen
+                    if false == is_automated_run && engine_application._keyboard_input_data._modifier_keys_alt && engine_application._keyboard_input_data.get_key_pressed(VirtualKeyCode::Return) {
+                        engine_application.toggle_borderless_fullscreen();
+                    }
+                    if engine_application._is_window_mode_changed {
+                        engine_application._is_window_mode_changed = false;
+                        renderer_data.set_need_recreate_swapchain(true);
+                    }
+
+                    // present mode, FIFO -> MAILBOX -> IMMEDIATE. the benchmark and the soak test keep IMMEDIATE
+                    if false == is_automated_run && engine_application._keyboard_input_data.get_key_pressed(VirtualKeyCode::V) {
+                        renderer_data.next_present_mode();
+                    }
+
+                    // exposure of the final pass, the tonemap operator and the auto exposure
+                    if false == is_automated_run {
+                        if engine_application._keyboard_input_data.get_key_pressed(VirtualKeyCode::Equals) {
+                            let exposure = renderer_data.get_tonemap_settings()._exposure * tonemap::EXPOSURE_STEP;
+                            renderer_data.set_exposure(exposure);
+                        } else if engine_application._keyboard_input_data.get_key_pressed(VirtualKeyCode::Minus) {
+                            let exposure = renderer_data.get_tonemap_settings()._exposure / tonemap::EXPOSURE_STEP;
+                            renderer_data.set_exposure(exposure);
+                        } else if engine_application._keyboard_input_data.get_key_pressed(VirtualKeyCode::T) {
+                            renderer_data.next_tonemapper();
+                        } else if engine_application._keyboard_input_data.get_key_pressed(VirtualKeyCode::X) {
+                            renderer_data.toggle_auto_exposure();
+                        }
+                    }
+
+                    // a/b comparison of the depth conventions, the standard depth <-> reversed-z
+                    if false == is_automated_run && engine_application._keyboard_input_data.get_key_pressed(VirtualKeyCode::Z) {
+                        let (reversed_z, infinite_far_plane) = unsafe { (constants::REVERSED_Z, constants::INFINITE_FAR_PLANE) };
+                        renderer_data.set_reversed_z(false == reversed_z, infinite_far_plane);
+                    }
+
+                    // anti aliasing of the main viewport, None -> FXAA -> TAA
+                    if false == is_automated_run && engine_application._keyboard_input_data.get_key_pressed(VirtualKeyCode::F7) {
+                        renderer_data.next_anti_aliasing_mode();
+                    }
+
+                    // debug view of the opaque passes, None -> Wireframe -> WorldNormal -> UV -> VertexColor
+                    if false == is_automated_run && engine_application._keyboard_input_data.get_key_pressed(VirtualKeyCode::F6) {
+                        renderer_data.next_debug_view_mode();
+                    }
+
+                    // sea state of the ocean, the wind speed of the spectrum. the spectrum textures are updated in place
+                    if false == is_automated_run {
+                        let wind_speed_step = if engine_application._keyboard_input_data.get_key_pressed(VirtualKeyCode::PageUp) {
+                            environment_settings::OCEAN_WIND_SPEED_STEP
+                        } else if engine_application._keyboard_input_data.get_key_pressed(VirtualKeyCode::PageDown) {
+                            -environment_settings::OCEAN_WIND_SPEED_STEP
+                        } else {
+                            0.0
+                        };
+                        if 0.0 != wind_speed_step {
+                            let mut settings = scene_manager_data.get_environment_settings().clone();
+                            let (wind_speed, omega, amplitude) = (settings._ocean._wind_speed + wind_speed_step, settings._ocean._omega, settings._ocean._amplitude);
+                            settings._ocean.set_sea_state(wind_speed, omega, amplitude);
+                            log::info!("ocean wind speed: {}", settings._ocean._wind_speed);
+                            scene_manager_data.set_environment_settings(&settings);
+                            renderer_data.request_ocean_spectrum_update(&scene_manager_data.get_environment_settings()._ocean);
+                        }
+                    }
+
+                    // time of day, the sun of the hour drives the main light and the atmosphere
+                    if false == is_automated_run {
+                        let hour_step = if engine_application._keyboard_input_data.get_key_pressed(VirtualKeyCode::Period) {
+                            time_of_day::TIME_OF_DAY_STEP
+                        } else if engine_application._keyboard_input_data.get_key_pressed(VirtualKeyCode::Comma) {
+                            -time_of_day::TIME_OF_DAY_STEP
+                        } else {
+                            0.0
+                        };
+                        if 0.0 != hour_step {
+                            let hour = scene_manager_data.get_time_of_day().get_hour() + hour_step;
+                            scene_manager_data.set_time_of_day(hour);
+                            log::info!("{}", scene_manager_data.get_time_of_day().get_stats_text());
+                        }
+                    }
+
+                    // msaa of the scene color and depth, 1x -> 2x -> 4x -> 8x
+                    if false == is_automated_run && engine_application._keyboard_input_data.get_key_pressed(VirtualKeyCode::M) {
+                        renderer_data.next_msaa_sample_count();
+                    }
+
+                    // visibility stats capture, the ranked report of the objects, the lights and the materials
+                    if false == is_automated_run && engine_application._keyboard_input_data.get_key_pressed(VirtualKeyCode::F8) {
+                        renderer_data.begin_visibility_stats_capture();
+                    }
+
+                    // debug render target, with shift the mip level
+                    let modifier_keys_shift = engine_application._keyboard_input_data._modifier_keys_shift;
+                    if engine_application._keyboard_input_data.get_key_pressed(VirtualKeyCode::RBracket) {
+                        if modifier_keys_shift {
+                            renderer_data.next_debug_render_target_mip();
+                        } else {
+                            renderer_data.next_debug_render_target();
+                        }
+                    } else if engine_application._keyboard_input_data.get_key_pressed(VirtualKeyCode::LBracket) {
+                        if modifier_keys_shift {
+                            renderer_data.prev_debug_render_target_mip();
+                        } else {
+                            renderer_data.prev_debug_render_target();
+                        }
+                    }
+
+                    // object picking, the object id under the cursor is read back in the next frame
+                    if false == is_automated_run && false == engine_application._is_grab_mode && engine_application._mouse_input_data._btn_l_pressed {
+                        let (pick_x, pick_y) = renderer_data.get_render_position(&engine_application._mouse_move_data._mouse_pos, &engine_application._viewport_rect.get_size());
+                        renderer_data.pick_object(pick_x, pick_y);
+                    }
+                    if let Some(picked_object_id) = renderer_data.take_picked_object_id() {
+                        match scene_manager_data.get_render_object_by_object_id(picked_object_id) {
+                            Some(render_object) => {
+                                log::info!("picked object: {}", render_object.borrow()._render_object_name);
+                                haptics::post_haptics_event(HapticsEvent::ObjectPicked, 1.0);
+                            },
+                            None => log::info!("picked object: none"),
+                        }
+                    }
+
+                    // quicksave, quickload. F5 / F9, with Ctrl the scene file
+                    let modifier_keys_control = engine_application._keyboard_input_data._modifier_keys_control;
+                    if false == is_automated_run && false == modifier_keys_control {
+                        if engine_application._keyboard_input_data.get_key_pressed(VirtualKeyCode::F5) {
+                            save_state::quick_save(&engine_application._time_data, &scene_manager_data);
+                        } else if engine_application._keyboard_input_data.get_key_pressed(VirtualKeyCode::F9) {
+                            save_state::quick_load(&mut engine_application._time_data, &mut scene_manager_data, &renderer_data);
+                        }
+                    }
+
+                    // scene file, Ctrl+F5 / Ctrl+F9. not Ctrl+S / Ctrl+L, the camera moves with S while Ctrl is held
+                    if false == is_automated_run && modifier_keys_control {
+                        let scene_file_path = scene_file::get_scene_file_path(&scene_manager_data._scene_data_name);
+                        if engine_application._keyboard_input_data.get_key_pressed(VirtualKeyCode::F5) {
+                            scene_manager_data.save_scene(&scene_file_path);
+                        } else if engine_application._keyboard_input_data.get_key_pressed(VirtualKeyCode::F9) {
+                            if scene_manager_data.open_scene_from_file(&scene_file_path) {
+                                renderer_data.invalidate_temporal_history();
+                            }
+                        }
+                    }
+
+                    // console, ` to toggle. the keys are hidden from the camera while the console is open
+                    if false == is_automated_run {
+                        let engine_application = &mut *engine_application;
+                        let mut console_command_context = ConsoleCommandContext {
+                            _renderer_data: &mut renderer_data,
+                            _scene_manager_data: &mut scene_manager_data,
+                            _haptics_manager: &mut engine_application._haptics_manager,
+                        };
+                        engine_application._console.update_console(&engine_application._keyboard_input_data, &mut console_command_context);
+                        let is_text_input_focused = engine_application._console.is_open() || ui_manager_data.is_text_input_focused();
+                        engine_application._keyboard_input_data.set_text_input_focused(is_text_input_focused);
+                    }
+
+                    // the gamepad events since the last frame, ex) the left stick moves and the right stick rotates the camera
+                    if false == is_automated_run {
+                        engine_application._gamepad_input_data.update_gamepad_input();
+                    }
+
+                    // update event, then the main camera is kept out of the collision geometry
+                    let camera_position_prev = scene_manager_data.get_main_camera_transform().0;
+                    if false == is_automated_run {
+                        engine_application.update_camera_look(&scene_manager_data);
+                    }
                     engine_application.update_event();
+                    if false == is_automated_run {
+                        scene_manager_data.update_camera_collision(&camera_position_prev);
+                    }
 
                     // update timer
                     if engine_application._time_data.update_time_data(&time_instance) {
-                        let text_fps = format!("{:.2}fps / {:.3}ms", engine_application._time_data._average_fps, engine_application._time_data._average_frame_time);
+                        let mut text_fps = format!("{:.2}fps / {:.3}ms", engine_application._time_data._average_fps, engine_application._time_data._average_frame_time);
+                        if let Some(gpu_frame_time) = renderer_data.get_gpu_frame_time() {
+                            text_fps.push_str(&format!(" / gpu {:.3}ms", gpu_frame_time));
+                        }
                         log::info!("{}", text_fps);
                     }
 
+                    // benchmark, the fixed timestep and the camera path
+                    let benchmark_exit_code: Option<i32> = match engine_application._benchmark_data.as_ref() {
+                        Some(benchmark_data) if benchmark_data.is_finished() => Some(benchmark_data.end_benchmark(&renderer_data)),
+                        _ => None,
+                    };
+                    if let Some(benchmark_exit_code) = benchmark_exit_code {
+                        engine_application.terminate_application(
+                            &mut font_manager,
+                            &mut ui_manager_data,
+                            &mut scene_manager_data,
+                            &mut maybe_resources.as_ref().unwrap().borrow_mut(),
+                            &mut renderer_data,
+                        );
+                        std::process::exit(benchmark_exit_code);
+                    }
+                    {
+                        let engine_application = &mut *engine_application;
+                        if let Some(benchmark_data) = engine_application._benchmark_data.as_mut() {
+                            benchmark_data.update_benchmark_frame(&mut engine_application._time_data, &scene_manager_data);
+                        }
+                    }
+
+                    // soak test, the report is written when all phases are done
+                    let soak_exit_code: Option<i32> = match engine_application._soak_data.as_ref() {
+                        Some(soak_data) if soak_data.is_finished() => Some(soak_data.end_soak_test()),
+                        _ => None,
+                    };
+                    if let Some(soak_exit_code) = soak_exit_code {
+                        engine_application.terminate_application(
+                            &mut font_manager,
+                            &mut ui_manager_data,
+                            &mut scene_manager_data,
+                            &mut maybe_resources.as_ref().unwrap().borrow_mut(),
+                            &mut renderer_data,
+                        );
+                        std::process::exit(soak_exit_code);
+                    }
+                    {
+                        let engine_application = &mut *engine_application;
+                        let window: &Window = unsafe { &*engine_application._window };
+                        if let Some(soak_data) = engine_application._soak_data.as_mut() {
+                            soak_data.update_soak_frame(window, &mut scene_manager_data, &renderer_data);
+                        }
+                    }
+
                     let elapsed_time = engine_application._time_data._elapsed_time;
                     let delta_time = engine_application._time_data._delta_time;
                     let elapsed_frame = engine_application._time_data._elapsed_frame;
+                    engine_application._keyboard_input_data.update_key_repeat(delta_time as f32);
 
                     if renderer_data.get_need_recreate_swapchain() {
                         #[cfg(target_os = "android")]
@@ -399,34 +977,82 @@ pub fn run_application(
 
                             // destroy
                             scene_manager_data.destroy_scene_graphics_data(renderer_data.get_device());
-                            ui_manager_data.destroy_ui_graphics_data();
-                            font_manager.destroy_font_descriptor_sets();
+                            ui_manager_data.destroy_ui_graphics_data(&mut renderer_data._resources.borrow_mut());
+                            font_manager.destroy_font_descriptor_sets(&mut renderer_data._resources.borrow_mut());
                             renderer_data.resize_window();
 
                             // recreate
-                            font_manager.create_font_descriptor_sets(&renderer_data, &renderer_data._resources.borrow());
-                            ui_manager_data.create_ui_graphics_data(&renderer_data, &renderer_data._resources.borrow());
+                            font_manager.create_font_descriptor_sets(&renderer_data, &mut renderer_data._resources.borrow_mut());
+                            ui_manager_data.create_ui_graphics_data(&renderer_data, &mut renderer_data._resources.borrow_mut());
                             scene_manager_data.initialize_scene_graphics_data();
                             renderer_data.set_need_recreate_swapchain(false);
 
                             log::info!("<<end recreate_swapchain>>");
                         }
+                    } else if renderer_data.get_need_recreate_render_targets() {
+                        // render scale, the swapchain is kept
+                        scene_manager_data.destroy_scene_graphics_data(renderer_data.get_device());
+                        ui_manager_data.destroy_ui_graphics_data(&mut renderer_data._resources.borrow_mut());
+                        font_manager.destroy_font_descriptor_sets(&mut renderer_data._resources.borrow_mut());
+                        renderer_data.recreate_render_targets();
+                        font_manager.create_font_descriptor_sets(&renderer_data, &mut renderer_data._resources.borrow_mut());
+                        ui_manager_data.create_ui_graphics_data(&renderer_data, &mut renderer_data._resources.borrow_mut());
+                        scene_manager_data.initialize_scene_graphics_data();
+                        renderer_data.set_need_recreate_render_targets(false);
                     } else {
                         // update & render, If the resized event has not yet occurred, the window size may be 0.
                         if 0 < engine_application._window_size.x && 0 < engine_application._window_size.y {
+                            if engine_application.update_viewport_rect() {
+                                let viewport_size = engine_application._viewport_rect.get_size();
+                                scene_manager_data.resized_window(viewport_size.x, viewport_size.y);
+                            }
+                            let viewport_size = engine_application._viewport_rect.get_size();
                             engine_application.update_application();
+                            engine_application._haptics_manager.update_haptics_manager(delta_time as f32);
                             renderer_data.update_post_process_datas();
-                            scene_manager_data.update_scene_manager_data(&engine_application._time_data, &mut font_manager);
+                            scene_manager_data.update_scene_manager_data(&mut engine_application._time_data, &mut font_manager);
+                            renderer_data.update_debug_histogram(&mut font_manager, &mut ui_manager_data);
+                            renderer_data.update_render_error(&mut font_manager, &mut ui_manager_data);
+                            renderer_data._resources.borrow_mut().update_reload_error_toasts(&mut font_manager, &mut ui_manager_data);
+                            renderer_data.update_shadow_atlas_stats(&mut font_manager);
+                            renderer_data.update_shadow_cache_stats(&mut font_manager);
+                            renderer_data.update_light_cluster_stats(&mut font_manager);
+                            renderer_data.update_gpu_profiler_stats(&mut font_manager);
+                            renderer_data.update_device_memory_stats(&mut font_manager);
+                            {
+                                let resources = renderer_data._resources.clone();
+                                let mut resources = resources.borrow_mut();
+                                resources.garbage_collect(&renderer_data);
+                                resources.update_resource_ref_stats(&mut font_manager);
+                                if resources.update_hot_reload(&renderer_data) {
+                                    renderer_data.set_need_recreate_render_targets(true);
+                                }
+                            }
+                            if renderer_data.set_atmosphere_settings(&scene_manager_data.get_environment_settings()._atmosphere) {
+                                renderer_data.set_need_recreate_render_targets(true);
+                            }
+                            engine_application._console.update_console_text(&mut font_manager);
+                            renderer_data.update_debug_render_target(&mut font_manager);
+                            renderer_data.update_present_mode(&mut font_manager);
+                            renderer_data.update_anti_aliasing_mode(&mut font_manager);
+                            renderer_data.update_visibility_stats(&scene_manager_data, &mut font_manager);
                             font_manager.update();
                             ui_manager_data.update(
                                 delta_time,
-                                &engine_application._window_size,
+                                &viewport_size,
                                 &engine_application._time_data,
                                 &engine_application._keyboard_input_data,
                                 &engine_application._mouse_move_data,
                                 &engine_application._mouse_input_data,
                                 &renderer_data._resources.borrow());
+                            let is_text_input_focused = ui_manager_data.is_text_input_focused() || engine_application._console.is_open();
+                            engine_application._keyboard_input_data.set_text_input_focused(is_text_input_focused);
                             renderer_data.render_scene(&scene_manager_data, &mut font_manager, &mut ui_manager_data, elapsed_time, delta_time, elapsed_frame);
+                            if let Some(benchmark_data) = engine_application._benchmark_data.as_mut() {
+                                benchmark_data.collect_frame_stats(&renderer_data);
+                            }
+                            engine_application._telemetry_reporter.update_telemetry_reporter(elapsed_time, &scene_manager_data.get_scene_content_key());
+                            engine_application.update_window_title(&scene_manager_data._scene_data_name);
                         }
                     }
                 }
@@ -435,8 +1061,9 @@ pub fn run_application(
                 DeviceEvent::MouseMotion { delta } => {
                     let mut engine_application: RefMut<EngineApplication> = maybe_engine_application.as_ref().unwrap().borrow_mut();
                     if engine_application._is_grab_mode {
-                        let window_size = engine_application._window_size.clone();
-                        engine_application._mouse_move_data.update_mouse_move(&(delta.0 as i32, delta.1 as i32), &window_size);
+                        let viewport_size = engine_application._viewport_rect.get_size();
+                        engine_application._mouse_move_data.set_touch_input(false);
+                        engine_application._mouse_move_data.update_mouse_move(&(delta.0 as i32, delta.1 as i32), &viewport_size);
                         // window.set_cursor_position(dpi::PhysicalPosition { x: window_size.x / 2, y: window_size.y / 2 }).expect("failed to set_cursor_position");
                     }
                 },
@@ -453,7 +1080,11 @@ pub fn run_application(
                         let mut renderer_data: RefMut<RendererData> = maybe_renderer_data.as_ref().unwrap().borrow_mut();
                         engine_application._window_size.x = size.width as i32;
                         engine_application._window_size.y = size.height as i32;
-                        scene_manager_data.resized_window(size.width as i32, size.height as i32);
+                        engine_application.update_viewport_rect();
+                        let viewport_size = engine_application._viewport_rect.get_size();
+                        if engine_application._is_managers_initialized {
+                            scene_manager_data.resized_window(viewport_size.x, viewport_size.y);
+                        }
                         let swapchain_extent = renderer_data._swapchain_data._swapchain_extent;
                         let need_recreate_swapchain = swapchain_extent.width != size.width || swapchain_extent.height != size.height;
                         log::info!("need_recreate_swapchain: {}, swapchain_extent: {:?}", need_recreate_swapchain, swapchain_extent);
@@ -476,7 +1107,10 @@ pub fn run_application(
                 WindowEvent::CursorMoved { position, .. } => {
                     let mut engine_application: RefMut<EngineApplication> = maybe_engine_application.as_ref().unwrap().borrow_mut();
                     if false == engine_application._is_grab_mode {
-                        engine_application._mouse_move_data.update_mouse_pos(&position.into(), &window_size);
+                        let viewport_rect = engine_application._viewport_rect;
+                        let mouse_pos = viewport_rect.to_local_position(&Vector2::new(position.x as i32, position.y as i32));
+                        engine_application._mouse_move_data.set_touch_input(false);
+                        engine_application._mouse_move_data.update_mouse_pos(&(mouse_pos.x, mouse_pos.y), &viewport_rect.get_size());
                     }
                 }
                 WindowEvent::MouseWheel { delta: MouseScrollDelta::LineDelta(scroll_x, scroll_y), .. } => {let mut engine_application: RefMut<EngineApplication> = maybe_engine_application.as_ref().unwrap().borrow_mut();
@@ -507,12 +1141,20 @@ pub fn run_application(
                         }
                     }
                 }
+                WindowEvent::ReceivedCharacter(c) => {
+                    if run_application {
+                        let mut engine_application: RefMut<EngineApplication> = maybe_engine_application.as_ref().unwrap().borrow_mut();
+                        engine_application._keyboard_input_data.push_char(c);
+                    }
+                }
                 WindowEvent::Touch(Touch { device_id: _device_id, phase, location, force: _force, id }) => {
                     let engine_application = &mut maybe_engine_application.as_ref().unwrap().borrow_mut();
 
                     if 0 == id {
-                        let window_size = engine_application._window_size.clone();
-                        engine_application._mouse_move_data.update_mouse_pos(&location.into(), &window_size);
+                        let viewport_rect = engine_application._viewport_rect;
+                        let touch_pos = viewport_rect.to_local_position(&Vector2::new(location.x as i32, location.y as i32));
+                        engine_application._mouse_move_data.set_touch_input(true);
+                        engine_application._mouse_move_data.update_mouse_pos(&(touch_pos.x, touch_pos.y), &viewport_rect.get_size());
 
                         if phase == TouchPhase::Started {
                             engine_application._mouse_input_data.btn_r_pressed(true);
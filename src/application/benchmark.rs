@@ -0,0 +1,640 @@
+use std::collections::BTreeMap;
+use std::ffi::CStr;
+use std::fs;
+use std::path::{ Path, PathBuf };
+
+use nalgebra::Vector3;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use serde::{ Serialize, Deserialize };
+use serde_json::{ self, Value };
+
+use crate::application::application::TimeData;
+use crate::application::scene_manager::SceneManagerData;
+use crate::constants;
+use crate::renderer::renderer::RendererData;
+use crate::vulkan_context::vulkan_context;
+
+// ex) --benchmark --benchmark_scene=sponza --benchmark_duration=60 --benchmark_compare=benchmark/report_prev.json
+pub const BENCHMARK_ARG: &str = "--benchmark";
+pub const BENCHMARK_SCENE_ARG: &str = "--benchmark_scene=";
+pub const BENCHMARK_DURATION_ARG: &str = "--benchmark_duration=";
+pub const BENCHMARK_OUTPUT_ARG: &str = "--benchmark_output=";
+pub const BENCHMARK_COMPARE_ARG: &str = "--benchmark_compare=";
+pub const BENCHMARK_CAMERA_PATH_KEY: &str = "benchmark_camera_path";
+pub const DEFAULT_BENCHMARK_SCENE_NAME: &str = "default";
+pub const DEFAULT_BENCHMARK_DURATION: f64 = 30.0; // seconds of the simulation time
+pub const DEFAULT_BENCHMARK_OUTPUT_PATH: &str = "benchmark/report";
+pub const BENCHMARK_FIXED_DELTA_TIME: f64 = 1.0 / 60.0;
+pub const BENCHMARK_WARMUP_FRAMES: u64 = 60; // shader compile, first uploads, not in the statistics
+pub const BENCHMARK_RANDOM_SEED: u64 = 0x5eed;
+pub const BENCHMARK_DELTA_THRESHOLD: f64 = 5.0; // percent, the larger changes are highlighted
+pub const BENCHMARK_EXIT_CODE_SUCCESS: i32 = 0;
+pub const BENCHMARK_EXIT_CODE_FAILED: i32 = 1;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BenchmarkSettings {
+    pub _scene_name: String,
+    pub _duration: f64,
+    pub _output_path: PathBuf, // without the extension, .json and .txt are written
+    pub _compare_report_path: Option<PathBuf>,
+}
+
+impl Default for BenchmarkSettings {
+    fn default() -> BenchmarkSettings {
+        BenchmarkSettings {
+            _scene_name: String::from(DEFAULT_BENCHMARK_SCENE_NAME),
+            _duration: DEFAULT_BENCHMARK_DURATION,
+            _output_path: PathBuf::from(DEFAULT_BENCHMARK_OUTPUT_PATH),
+            _compare_report_path: None,
+        }
+    }
+}
+
+impl BenchmarkSettings {
+    // None without --benchmark
+    pub fn create_benchmark_settings(args: &[String]) -> Option<BenchmarkSettings> {
+        if false == args.iter().any(|arg| BENCHMARK_ARG == arg) {
+            return None;
+        }
+
+        let mut benchmark_settings = BenchmarkSettings::default();
+        for arg in args.iter() {
+            if arg.starts_with(BENCHMARK_SCENE_ARG) {
+                benchmark_settings._scene_name = String::from(&arg[BENCHMARK_SCENE_ARG.len()..]);
+            } else if arg.starts_with(BENCHMARK_DURATION_ARG) {
+                match arg[BENCHMARK_DURATION_ARG.len()..].parse::<f64>() {
+                    Ok(duration) if 0.0 < duration => benchmark_settings._duration = duration,
+                    _ => log::warn!("invalid benchmark duration: {}", arg),
+                }
+            } else if arg.starts_with(BENCHMARK_OUTPUT_ARG) {
+                benchmark_settings._output_path = PathBuf::from(&arg[BENCHMARK_OUTPUT_ARG.len()..]);
+            } else if arg.starts_with(BENCHMARK_COMPARE_ARG) {
+                benchmark_settings._compare_report_path = Some(PathBuf::from(&arg[BENCHMARK_COMPARE_ARG.len()..]));
+            }
+        }
+        Some(benchmark_settings)
+    }
+}
+
+// the project uses this instead of rand::random while the benchmark runs, so two runs spawn the same things.
+pub fn create_benchmark_rng() -> StdRng {
+    StdRng::seed_from_u64(BENCHMARK_RANDOM_SEED)
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BenchmarkCameraKey {
+    pub _position: Vector3<f32>,
+    pub _rotation: Vector3<f32>, // pitch, yaw, roll radian
+}
+
+// "benchmark_camera_path" block of the scene, a closed catmull-rom spline played once over the duration.
+// ex) "benchmark_camera_path": [{ "_position": [0, 2, 10], "_rotation": [0, 0, 0] }, ...]
+#[derive(Clone, Debug, PartialEq)]
+pub struct BenchmarkCameraPath {
+    pub _keys: Vec<BenchmarkCameraKey>,
+}
+
+fn catmull_rom(p0: &Vector3<f32>, p1: &Vector3<f32>, p2: &Vector3<f32>, p3: &Vector3<f32>, t: f32) -> Vector3<f32> {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    (p1 * 2.0 + (p2 - p0) * t + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2 + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3) * 0.5
+}
+
+impl BenchmarkCameraPath {
+    // an orbit around the origin when the scene has no path
+    pub fn create_default_camera_path() -> BenchmarkCameraPath {
+        const ORBIT_KEY_COUNT: usize = 8;
+        const ORBIT_RADIUS: f32 = 20.0;
+        const ORBIT_HEIGHT: f32 = 5.0;
+        let keys = (0..ORBIT_KEY_COUNT).map(|i| {
+            let angle = std::f32::consts::PI * 2.0 * i as f32 / ORBIT_KEY_COUNT as f32;
+            BenchmarkCameraKey {
+                _position: Vector3::new(angle.sin() * ORBIT_RADIUS, ORBIT_HEIGHT, angle.cos() * ORBIT_RADIUS),
+                _rotation: Vector3::new(-(ORBIT_HEIGHT / ORBIT_RADIUS).atan(), angle, 0.0),
+            }
+        }).collect();
+        BenchmarkCameraPath { _keys: keys }
+    }
+
+    pub fn create_benchmark_camera_path(scene_data: &Value) -> BenchmarkCameraPath {
+        match scene_data.get(BENCHMARK_CAMERA_PATH_KEY) {
+            Some(camera_path) => match serde_json::from_value::<Vec<BenchmarkCameraKey>>(camera_path.clone()) {
+                Ok(keys) if false == keys.is_empty() => BenchmarkCameraPath { _keys: keys },
+                Ok(_) => BenchmarkCameraPath::create_default_camera_path(),
+                Err(e) => {
+                    log::error!("create_benchmark_camera_path error: {:?}", e);
+                    BenchmarkCameraPath::create_default_camera_path()
+                }
+            },
+            None => BenchmarkCameraPath::create_default_camera_path(),
+        }
+    }
+
+    pub fn load_benchmark_camera_path(scene_manager_data: &SceneManagerData) -> BenchmarkCameraPath {
+        let scene_file_path = scene_manager_data.get_scene_file_path();
+        let resources = scene_manager_data._resources.borrow();
+        let scene_data: Value = if resources.resolve_path(&scene_file_path).is_file() {
+            serde_json::from_reader(resources.read_bytes(&scene_file_path)).unwrap_or(Value::Null)
+        } else {
+            Value::Null
+        };
+        BenchmarkCameraPath::create_benchmark_camera_path(&scene_data)
+    }
+
+    // ratio: 0.0 ~ 1.0 of the whole path, the rotations are interpolated as the positions.
+    pub fn get_camera_transform(&self, ratio: f64) -> (Vector3<f32>, Vector3<f32>) {
+        let key_count = self._keys.len();
+        if key_count < 2 {
+            return match self._keys.first() {
+                Some(key) => (key._position.clone(), key._rotation.clone()),
+                None => (Vector3::zeros(), Vector3::zeros()),
+            };
+        }
+
+        let key_position = ratio.max(0.0).min(1.0) * key_count as f64;
+        let index = (key_position.floor() as usize).min(key_count - 1);
+        let t = (key_position - index as f64).min(1.0) as f32;
+        let key = |offset: usize| &self._keys[(index + key_count + offset - 1) % key_count];
+        (
+            catmull_rom(&key(0)._position, &key(1)._position, &key(2)._position, &key(3)._position, t),
+            catmull_rom(&key(0)._rotation, &key(1)._rotation, &key(2)._rotation, &key(3)._rotation, t)
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct FrameTimeStats {
+    pub _average: f64, // ms
+    pub _min: f64,
+    pub _max: f64,
+    pub _p50: f64,
+    pub _p95: f64,
+    pub _p99: f64,
+}
+
+// nearest rank
+pub fn get_percentile(sorted_values: &[f64], percentile: f64) -> f64 {
+    if sorted_values.is_empty() {
+        return 0.0;
+    }
+    let rank = (percentile / 100.0 * sorted_values.len() as f64).ceil() as usize;
+    sorted_values[rank.max(1).min(sorted_values.len()) - 1]
+}
+
+impl FrameTimeStats {
+    pub fn create_frame_time_stats(frame_times: &[f64]) -> FrameTimeStats {
+        if frame_times.is_empty() {
+            return FrameTimeStats::default();
+        }
+        let mut sorted_frame_times = frame_times.to_vec();
+        sorted_frame_times.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        FrameTimeStats {
+            _average: frame_times.iter().sum::<f64>() / frame_times.len() as f64,
+            _min: sorted_frame_times[0],
+            _max: sorted_frame_times[sorted_frame_times.len() - 1],
+            _p50: get_percentile(&sorted_frame_times, 50.0),
+            _p95: get_percentile(&sorted_frame_times, 95.0),
+            _p99: get_percentile(&sorted_frame_times, 99.0),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+pub struct BenchmarkReport {
+    pub _scene_name: String,
+    pub _device_name: String,
+    pub _date: String,
+    pub _duration: f64,
+    pub _frame_count: u64,
+    pub _average_fps: f64,
+    pub _frame_time: FrameTimeStats,
+    pub _gpu_pass_times: BTreeMap<String, f64>, // average ms of the passes timed by the project
+    pub _average_draw_call_count: f64,
+    pub _max_draw_call_count: u32,
+    pub _average_dispatch_count: f64,
+    pub _average_map_memory_count: f64, // vkMapMemory per frame, the uniform arena blocks have none
+    pub _average_uniform_upload_time: f64, // ms per frame
+    pub _peak_memory_mb: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BenchmarkDelta {
+    pub _name: String,
+    pub _previous: f64,
+    pub _current: f64,
+    pub _delta_percent: f64,
+    pub _is_regression: bool, // worse than BENCHMARK_DELTA_THRESHOLD
+    pub _is_improvement: bool,
+}
+
+impl BenchmarkDelta {
+    // higher_is_better: fps, otherwise times, counts and memory
+    pub fn create_benchmark_delta(name: &str, previous: f64, current: f64, higher_is_better: bool) -> BenchmarkDelta {
+        let delta_percent = if 0.0 != previous { (current - previous) / previous.abs() * 100.0 } else { 0.0 };
+        let signed_delta_percent = if higher_is_better { delta_percent } else { -delta_percent };
+        BenchmarkDelta {
+            _name: String::from(name),
+            _previous: previous,
+            _current: current,
+            _delta_percent: delta_percent,
+            _is_regression: signed_delta_percent < -BENCHMARK_DELTA_THRESHOLD,
+            _is_improvement: BENCHMARK_DELTA_THRESHOLD < signed_delta_percent,
+        }
+    }
+}
+
+// the passes missing in one of the reports are skipped.
+pub fn compare_benchmark_reports(previous: &BenchmarkReport, current: &BenchmarkReport) -> Vec<BenchmarkDelta> {
+    let mut deltas: Vec<BenchmarkDelta> = vec![
+        BenchmarkDelta::create_benchmark_delta("fps", previous._average_fps, current._average_fps, true),
+        BenchmarkDelta::create_benchmark_delta("frame time average", previous._frame_time._average, current._frame_time._average, false),
+        BenchmarkDelta::create_benchmark_delta("frame time p50", previous._frame_time._p50, current._frame_time._p50, false),
+        BenchmarkDelta::create_benchmark_delta("frame time p95", previous._frame_time._p95, current._frame_time._p95, false),
+        BenchmarkDelta::create_benchmark_delta("frame time p99", previous._frame_time._p99, current._frame_time._p99, false),
+        BenchmarkDelta::create_benchmark_delta("frame time max", previous._frame_time._max, current._frame_time._max, false),
+        BenchmarkDelta::create_benchmark_delta("draw calls", previous._average_draw_call_count, current._average_draw_call_count, false),
+        BenchmarkDelta::create_benchmark_delta("dispatches", previous._average_dispatch_count, current._average_dispatch_count, false),
+        BenchmarkDelta::create_benchmark_delta("map memory calls", previous._average_map_memory_count, current._average_map_memory_count, false),
+        BenchmarkDelta::create_benchmark_delta("uniform upload time", previous._average_uniform_upload_time, current._average_uniform_upload_time, false),
+        BenchmarkDelta::create_benchmark_delta("peak memory mb", previous._peak_memory_mb, current._peak_memory_mb, false),
+    ];
+    for (pass_name, gpu_time) in current._gpu_pass_times.iter() {
+        if let Some(previous_gpu_time) = previous._gpu_pass_times.get(pass_name) {
+            deltas.push(BenchmarkDelta::create_benchmark_delta(&format!("gpu {}", pass_name), *previous_gpu_time, *gpu_time, false));
+        }
+    }
+    deltas
+}
+
+pub fn get_comparison_text(deltas: &[BenchmarkDelta]) -> String {
+    let mut contents = format!("comparison(threshold {:.1}%)\n", BENCHMARK_DELTA_THRESHOLD);
+    for delta in deltas.iter() {
+        let highlight = if delta._is_regression { "  <<< REGRESSION" } else if delta._is_improvement { "  <<< improved" } else { "" };
+        contents.push_str(&format!("    {:<32} {:>12.3} -> {:>12.3} ({:+.2}%){}\n", delta._name, delta._previous, delta._current, delta._delta_percent, highlight));
+    }
+    contents
+}
+
+impl BenchmarkReport {
+    pub fn get_report_text(&self) -> String {
+        let mut contents = String::new();
+        contents.push_str(&format!("benchmark: {}\n", self._scene_name));
+        contents.push_str(&format!("    device: {}\n", self._device_name));
+        contents.push_str(&format!("    date: {}\n", self._date));
+        contents.push_str(&format!("    duration: {:.2}s, frames: {}\n", self._duration, self._frame_count));
+        contents.push_str(&format!("    fps: {:.2}\n", self._average_fps));
+        contents.push_str(&format!("    frame time(ms): average {:.3}, min {:.3}, max {:.3}, p50 {:.3}, p95 {:.3}, p99 {:.3}\n",
+            self._frame_time._average, self._frame_time._min, self._frame_time._max, self._frame_time._p50, self._frame_time._p95, self._frame_time._p99));
+        contents.push_str(&format!("    draw calls: average {:.1}, max {}\n", self._average_draw_call_count, self._max_draw_call_count));
+        contents.push_str(&format!("    dispatches: average {:.1}\n", self._average_dispatch_count));
+        contents.push_str(&format!("    uniform uploads: map memory calls {:.1}, {:.3}ms per frame\n", self._average_map_memory_count, self._average_uniform_upload_time));
+        contents.push_str(&format!("    peak memory: {:.1} MB\n", self._peak_memory_mb));
+        if false == self._gpu_pass_times.is_empty() {
+            contents.push_str("    gpu pass times(ms):\n");
+            for (pass_name, gpu_time) in self._gpu_pass_times.iter() {
+                contents.push_str(&format!("        {:<32} {:.3}\n", pass_name, gpu_time));
+            }
+        }
+        contents
+    }
+
+    pub fn load_benchmark_report(report_file_path: &Path) -> Option<BenchmarkReport> {
+        match fs::read_to_string(report_file_path).map(|contents| serde_json::from_str::<BenchmarkReport>(&contents)) {
+            Ok(Ok(benchmark_report)) => Some(benchmark_report),
+            _ => {
+                log::error!("load_benchmark_report failed: {:?}", report_file_path);
+                None
+            }
+        }
+    }
+
+    // output_path.json and output_path.txt, the comparison is appended to the text report.
+    pub fn write_benchmark_report(&self, output_path: &Path, previous_report: Option<&BenchmarkReport>) -> std::io::Result<()> {
+        if let Some(output_directory) = output_path.parent() {
+            if false == output_directory.as_os_str().is_empty() {
+                fs::create_dir_all(output_directory)?;
+            }
+        }
+        let mut contents = self.get_report_text();
+        if let Some(previous_report) = previous_report {
+            contents.push_str(&get_comparison_text(&compare_benchmark_reports(previous_report, self)));
+        }
+        fs::write(output_path.with_extension("json"), serde_json::to_string_pretty(self).unwrap())?;
+        fs::write(output_path.with_extension("txt"), &contents)?;
+        log::info!("{}", contents);
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct GpuPassTimeAccumulator {
+    _total_time: f64,
+    _sample_count: u32,
+}
+
+// the simulation runs with BENCHMARK_FIXED_DELTA_TIME and ignores the input, the frame times are the wall clock.
+// the camera jitter follows the frame index, so it repeats with the fixed timestep too.
+pub struct BenchmarkData {
+    pub _settings: BenchmarkSettings,
+    pub _camera_path: BenchmarkCameraPath,
+    pub _frame_index: u64,
+    pub _frame_times: Vec<f64>,
+    pub _draw_call_counts: Vec<u32>,
+    pub _dispatch_counts: Vec<u32>,
+    pub _map_memory_counts: Vec<u32>,
+    pub _uniform_upload_times: Vec<f64>,
+    _gpu_pass_times: BTreeMap<String, GpuPassTimeAccumulator>,
+}
+
+impl BenchmarkData {
+    pub fn create_benchmark_data(benchmark_settings: BenchmarkSettings) -> BenchmarkData {
+        BenchmarkData {
+            _settings: benchmark_settings,
+            _camera_path: BenchmarkCameraPath::create_default_camera_path(),
+            _frame_index: 0,
+            _frame_times: Vec::new(),
+            _draw_call_counts: Vec::new(),
+            _dispatch_counts: Vec::new(),
+            _map_memory_counts: Vec::new(),
+            _uniform_upload_times: Vec::new(),
+            _gpu_pass_times: BTreeMap::new(),
+        }
+    }
+
+    // before the renderer is created, the swapchain is created without vsync.
+    pub fn apply_benchmark_configs() {
+        unsafe {
+            constants::ENABLE_IMMEDIATE_MODE = true;
+            constants::ENABLE_FILE_WATCHER = false;
+        }
+    }
+
+    pub fn begin_benchmark(&mut self, scene_manager_data: &mut SceneManagerData, renderer_data: &RendererData) {
+        log::info!("begin_benchmark: {:?}", self._settings);
+        scene_manager_data.close_scene_data(renderer_data.get_device());
+        scene_manager_data._scene_data_name = self._settings._scene_name.clone();
+        scene_manager_data.open_scene_data();
+        renderer_data.invalidate_temporal_history();
+        self._camera_path = BenchmarkCameraPath::load_benchmark_camera_path(scene_manager_data);
+        vulkan_context::reset_peak_allocated_memory_size();
+    }
+
+    pub fn get_total_frame_count(&self) -> u64 {
+        BENCHMARK_WARMUP_FRAMES + (self._settings._duration / BENCHMARK_FIXED_DELTA_TIME).ceil() as u64
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.get_total_frame_count() <= self._frame_index
+    }
+
+    // replaces the wall clock time with the fixed timestep and moves the camera along the path.
+    pub fn update_benchmark_frame(&mut self, time_data: &mut TimeData, scene_manager_data: &SceneManagerData) {
+        let real_frame_time = time_data._delta_time * 1000.0;
+        if BENCHMARK_WARMUP_FRAMES < self._frame_index {
+            // the delta of this frame is the duration of the previous frame
+            self._frame_times.push(real_frame_time);
+        }
+
+        let measured_frame = self._frame_index.saturating_sub(BENCHMARK_WARMUP_FRAMES);
+        time_data._delta_time = BENCHMARK_FIXED_DELTA_TIME;
+        time_data._elapsed_time_prev = measured_frame.saturating_sub(1) as f64 * BENCHMARK_FIXED_DELTA_TIME;
+        time_data._elapsed_time = measured_frame as f64 * BENCHMARK_FIXED_DELTA_TIME;
+
+        let ratio = time_data._elapsed_time / self._settings._duration;
+        let (position, rotation) = self._camera_path.get_camera_transform(ratio);
+        scene_manager_data.set_main_camera_transform(&position, &rotation);
+    }
+
+    // after render_scene
+    pub fn collect_frame_stats(&mut self, renderer_data: &RendererData) {
+        if BENCHMARK_WARMUP_FRAMES <= self._frame_index {
+            self._draw_call_counts.push(renderer_data.get_draw_call_count());
+            self._dispatch_counts.push(renderer_data.get_dispatch_count());
+            let uniform_upload_stats = renderer_data.get_uniform_upload_stats();
+            self._map_memory_counts.push(uniform_upload_stats._map_memory_count);
+            self._uniform_upload_times.push(uniform_upload_stats._upload_time_ms);
+            for pass in renderer_data.get_frame_graph().borrow()._passes.iter() {
+                if let Some(gpu_time_ms) = pass._gpu_time_ms {
+                    let accumulator = self._gpu_pass_times.entry(pass._pass_name.clone()).or_default();
+                    accumulator._total_time += gpu_time_ms;
+                    accumulator._sample_count += 1;
+                }
+            }
+        }
+        self._frame_index += 1;
+    }
+
+    pub fn create_benchmark_report(&self, renderer_data: &RendererData) -> BenchmarkReport {
+        let frame_time = FrameTimeStats::create_frame_time_stats(&self._frame_times);
+        let get_average = |values: &[u32]| if values.is_empty() { 0.0 } else { values.iter().map(|value| *value as f64).sum::<f64>() / values.len() as f64 };
+        let device_name = unsafe { CStr::from_ptr(renderer_data.get_device_properties().device_name.as_ptr()) };
+        BenchmarkReport {
+            _scene_name: self._settings._scene_name.clone(),
+            _device_name: device_name.to_string_lossy().into_owned(),
+            _date: chrono::Local::now().to_rfc3339(),
+            _duration: self._settings._duration,
+            _frame_count: self._frame_times.len() as u64,
+            _average_fps: if 0.0 < frame_time._average { 1000.0 / frame_time._average } else { 0.0 },
+            _frame_time: frame_time,
+            _gpu_pass_times: self._gpu_pass_times.iter().map(|(pass_name, accumulator)| {
+                (pass_name.clone(), accumulator._total_time / accumulator._sample_count.max(1) as f64)
+            }).collect(),
+            _average_draw_call_count: get_average(&self._draw_call_counts),
+            _max_draw_call_count: self._draw_call_counts.iter().cloned().max().unwrap_or(0),
+            _average_dispatch_count: get_average(&self._dispatch_counts),
+            _average_map_memory_count: get_average(&self._map_memory_counts),
+            _average_uniform_upload_time: if self._uniform_upload_times.is_empty() { 0.0 } else { self._uniform_upload_times.iter().sum::<f64>() / self._uniform_upload_times.len() as f64 },
+            _peak_memory_mb: vulkan_context::get_peak_allocated_memory_size() as f64 / (1024.0 * 1024.0),
+        }
+    }
+
+    // returns the exit code of the process
+    pub fn end_benchmark(&self, renderer_data: &RendererData) -> i32 {
+        let benchmark_report = self.create_benchmark_report(renderer_data);
+        let previous_report = self._settings._compare_report_path.as_ref().and_then(|compare_report_path| {
+            BenchmarkReport::load_benchmark_report(compare_report_path)
+        });
+        match benchmark_report.write_benchmark_report(&self._settings._output_path, previous_report.as_ref()) {
+            Ok(_) => BENCHMARK_EXIT_CODE_SUCCESS,
+            Err(e) => {
+                log::error!("write_benchmark_report failed {:?}: {:?}", self._settings._output_path, e);
+                BENCHMARK_EXIT_CODE_FAILED
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    fn to_strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| String::from(*arg)).collect()
+    }
+
+    fn create_test_report(frame_times: &[f64], draw_call_count: f64) -> BenchmarkReport {
+        let frame_time = FrameTimeStats::create_frame_time_stats(frame_times);
+        let mut gpu_pass_times: BTreeMap<String, f64> = BTreeMap::new();
+        gpu_pass_times.insert(String::from("render_gbuffer"), 2.0);
+        gpu_pass_times.insert(String::from("render_shadow"), 1.0);
+        BenchmarkReport {
+            _scene_name: String::from("sponza"),
+            _device_name: String::from("test device"),
+            _date: String::from("2026-10-16 12:00:00"),
+            _duration: 30.0,
+            _frame_count: frame_times.len() as u64,
+            _average_fps: 1000.0 / frame_time._average,
+            _frame_time: frame_time,
+            _gpu_pass_times: gpu_pass_times,
+            _average_draw_call_count: draw_call_count,
+            _max_draw_call_count: draw_call_count as u32 + 10,
+            _average_dispatch_count: 12.0,
+            _average_map_memory_count: 0.0,
+            _average_uniform_upload_time: 0.1,
+            _peak_memory_mb: 512.0,
+        }
+    }
+
+    #[test]
+    fn test_benchmark_settings() {
+        assert_eq!(None, BenchmarkSettings::create_benchmark_settings(&to_strings(&["app", "--benchmark_scene=sponza"])));
+        assert_eq!(Some(BenchmarkSettings::default()), BenchmarkSettings::create_benchmark_settings(&to_strings(&["app", BENCHMARK_ARG])));
+        let args = to_strings(&["app", "--benchmark", "--benchmark_scene=sponza", "--benchmark_duration=60", "--benchmark_output=out/run", "--benchmark_compare=out/prev.json"]);
+        assert_eq!(Some(BenchmarkSettings {
+            _scene_name: String::from("sponza"),
+            _duration: 60.0,
+            _output_path: PathBuf::from("out/run"),
+            _compare_report_path: Some(PathBuf::from("out/prev.json")),
+        }), BenchmarkSettings::create_benchmark_settings(&args));
+        // the invalid durations keep the default
+        let args = to_strings(&["app", "--benchmark", "--benchmark_duration=-1"]);
+        assert_eq!(DEFAULT_BENCHMARK_DURATION, BenchmarkSettings::create_benchmark_settings(&args).unwrap()._duration);
+        let args = to_strings(&["app", "--benchmark", "--benchmark_duration=abc"]);
+        assert_eq!(DEFAULT_BENCHMARK_DURATION, BenchmarkSettings::create_benchmark_settings(&args).unwrap()._duration);
+    }
+
+    #[test]
+    fn test_frame_time_stats() {
+        assert_eq!(0.0, get_percentile(&[], 50.0));
+        let sorted_values: Vec<f64> = (1..101).map(|i| i as f64).collect();
+        assert_eq!(50.0, get_percentile(&sorted_values, 50.0));
+        assert_eq!(95.0, get_percentile(&sorted_values, 95.0));
+        assert_eq!(99.0, get_percentile(&sorted_values, 99.0));
+        assert_eq!(1.0, get_percentile(&sorted_values, 0.0));
+        assert_eq!(100.0, get_percentile(&sorted_values, 100.0));
+
+        // the order of the frames does not matter
+        let frame_times = vec![20.0, 10.0, 16.0, 16.0, 18.0];
+        assert_eq!(FrameTimeStats {
+            _average: 16.0,
+            _min: 10.0,
+            _max: 20.0,
+            _p50: 16.0,
+            _p95: 20.0,
+            _p99: 20.0,
+        }, FrameTimeStats::create_frame_time_stats(&frame_times));
+        assert_eq!(FrameTimeStats::default(), FrameTimeStats::create_frame_time_stats(&[]));
+    }
+
+    #[test]
+    fn test_benchmark_delta() {
+        // the times are better when lower
+        let delta = BenchmarkDelta::create_benchmark_delta("frame time average", 10.0, 11.0, false);
+        assert!((delta._delta_percent - 10.0).abs() < 0.0001);
+        assert!(delta._is_regression && false == delta._is_improvement);
+        let delta = BenchmarkDelta::create_benchmark_delta("frame time average", 10.0, 8.0, false);
+        assert!(false == delta._is_regression && delta._is_improvement);
+        // the fps is better when higher
+        let delta = BenchmarkDelta::create_benchmark_delta("fps", 60.0, 50.0, true);
+        assert!(delta._is_regression && false == delta._is_improvement);
+        // within the threshold
+        let delta = BenchmarkDelta::create_benchmark_delta("fps", 100.0, 96.0, true);
+        assert!(false == delta._is_regression && false == delta._is_improvement);
+        // no previous value
+        let delta = BenchmarkDelta::create_benchmark_delta("map memory calls", 0.0, 3.0, false);
+        assert_eq!(0.0, delta._delta_percent);
+        assert!(false == delta._is_regression);
+    }
+
+    #[test]
+    fn test_compare_benchmark_reports() {
+        let previous = create_test_report(&[16.0, 16.0, 16.0, 16.0], 1000.0);
+        let mut current = create_test_report(&[20.0, 20.0, 20.0, 20.0], 1000.0);
+        current._gpu_pass_times.insert(String::from("render_gbuffer"), 1.0);
+        current._gpu_pass_times.remove("render_shadow");
+        current._gpu_pass_times.insert(String::from("render_ssr"), 0.5);
+        let deltas = compare_benchmark_reports(&previous, &current);
+        let get_delta = |name: &str| deltas.iter().find(|delta| name == delta._name).unwrap();
+        assert!(get_delta("fps")._is_regression);
+        assert!((get_delta("fps")._delta_percent + 20.0).abs() < 0.0001);
+        assert!(get_delta("frame time p95")._is_regression);
+        assert!((get_delta("frame time p95")._delta_percent - 25.0).abs() < 0.0001);
+        assert_eq!(0.0, get_delta("draw calls")._delta_percent);
+        assert!(get_delta("gpu render_gbuffer")._is_improvement);
+        // the passes missing in one of the reports are skipped
+        assert!(false == deltas.iter().any(|delta| "gpu render_shadow" == delta._name || "gpu render_ssr" == delta._name));
+
+        let comparison_text = get_comparison_text(&deltas);
+        assert!(comparison_text.starts_with("comparison(threshold 5.0%)\n"));
+        let get_line = |name: &str| comparison_text.lines().find(|line| line.trim_start().starts_with(&format!("{} ", name))).unwrap();
+        assert!(get_line("fps").ends_with("(-20.00%)  <<< REGRESSION"));
+        assert!(get_line("gpu render_gbuffer").ends_with("(-50.00%)  <<< improved"));
+        assert!(get_line("draw calls").ends_with("(+0.00%)"));
+        assert_eq!(deltas.len() + 1, comparison_text.lines().count());
+    }
+
+    #[test]
+    fn test_write_benchmark_report() {
+        let output_directory = std::env::temp_dir().join(format!("benchmark_test_write_benchmark_report_{}", std::process::id()));
+        let output_path = output_directory.join("report");
+        let previous = create_test_report(&[16.0, 17.0, 15.0, 16.0], 1000.0);
+        let current = create_test_report(&[16.0, 18.0, 15.0, 16.0], 1200.0);
+        current.write_benchmark_report(&output_path, Some(&previous)).unwrap();
+
+        // the json report is loaded back as the previous report of the next run
+        assert_eq!(Some(current.clone()), BenchmarkReport::load_benchmark_report(&output_path.with_extension("json")));
+        let contents = fs::read_to_string(output_path.with_extension("txt")).unwrap();
+        assert!(contents.starts_with(&current.get_report_text()));
+        assert!(contents.contains("benchmark: sponza\n"));
+        assert!(contents.contains("    draw calls: average 1200.0, max 1210\n"));
+        assert!(contents.contains("        render_gbuffer                   2.000\n"));
+        assert!(contents.contains("<<< REGRESSION"));
+
+        // without the previous report
+        current.write_benchmark_report(&output_path, None).unwrap();
+        assert_eq!(current.get_report_text(), fs::read_to_string(output_path.with_extension("txt")).unwrap());
+        assert_eq!(None, BenchmarkReport::load_benchmark_report(&output_directory.join("missing.json")));
+        fs::remove_dir_all(&output_directory).unwrap();
+    }
+
+    #[test]
+    fn test_benchmark_determinism() {
+        let random_values: Vec<u32> = create_benchmark_rng().sample_iter(rand::distributions::Standard).take(8).collect();
+        assert_eq!(random_values, create_benchmark_rng().sample_iter(rand::distributions::Standard).take(8).collect::<Vec<u32>>());
+
+        // the camera path passes the keys and loops back to the first key
+        let camera_path = BenchmarkCameraPath::create_default_camera_path();
+        let key_count = camera_path._keys.len();
+        for (i, key) in camera_path._keys.iter().enumerate() {
+            let (position, _rotation) = camera_path.get_camera_transform(i as f64 / key_count as f64);
+            assert!((position - key._position).norm() < 0.001);
+        }
+        let (position, _rotation) = camera_path.get_camera_transform(1.0);
+        assert!((position - camera_path._keys[0]._position).norm() < 0.001);
+        assert_eq!(camera_path.get_camera_transform(0.37), camera_path.get_camera_transform(0.37));
+
+        // the scene path and the fallback
+        let scene_data = serde_json::json!({
+            BENCHMARK_CAMERA_PATH_KEY: [
+                { "_position": [0.0, 2.0, 10.0], "_rotation": [0.0, 0.0, 0.0] },
+                { "_position": [10.0, 2.0, 0.0], "_rotation": [0.0, 1.0, 0.0] }
+            ]
+        });
+        assert_eq!(2, BenchmarkCameraPath::create_benchmark_camera_path(&scene_data)._keys.len());
+        assert_eq!(camera_path, BenchmarkCameraPath::create_benchmark_camera_path(&serde_json::json!({ BENCHMARK_CAMERA_PATH_KEY: [] })));
+        assert_eq!(camera_path, BenchmarkCameraPath::create_benchmark_camera_path(&Value::Null));
+    }
+}
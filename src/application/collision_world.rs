@@ -0,0 +1,570 @@
+use std::sync::Arc;
+use std::sync::mpsc::{ self, Receiver, TryRecvError };
+use std::thread;
+use std::time;
+
+use nalgebra::{ Matrix4, Vector3, Vector4 };
+
+use crate::renderer::mesh::MeshCollisionData;
+
+pub const COLLISION_BVH_LEAF_TRIANGLE_COUNT: usize = 4;
+pub const COLLISION_ASYNC_BUILD_TRIANGLE_COUNT: usize = 65536; // the smaller scenes are built on the main thread
+pub const COLLISION_SLIDE_ITERATIONS: usize = 3;
+pub const COLLISION_SKIN_WIDTH: f32 = 0.001; // meters, the resolved sphere is kept off the surface
+const COLLISION_EPSILON: f32 = 0.000001;
+
+// the triangles of a render object, transformed into the world space by the build
+#[derive(Clone, Debug)]
+pub struct CollisionSource {
+    pub _collision_data: Arc<MeshCollisionData>,
+    pub _world_matrix: Matrix4<f32>,
+    pub _object_id: u32, // RenderObjectStorage::get_object_id_by_index
+}
+
+#[derive(Clone, Debug)]
+pub struct CollisionTriangle {
+    pub _positions: [Vector3<f32>; 3],
+    pub _object_id: u32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CollisionHit {
+    pub _position: Vector3<f32>, // the contact point on the surface
+    pub _normal: Vector3<f32>, // toward the query
+    pub _distance: f32, // along the direction, for the sweep the distance of the sphere center
+    pub _object_id: u32, // the object id of RendererData::pick_object, see SceneManagerData::get_render_object_by_object_id
+}
+
+// the bvh is building, the queries can't tell a hit from a miss yet
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CollisionNotReady;
+
+// the inner node has no triangles and its left child is the next node, _index is the right child.
+// the leaf node has _triangle_count triangles from _index.
+#[derive(Clone, Debug)]
+pub struct CollisionBvhNode {
+    pub _bound_min: Vector3<f32>,
+    pub _bound_max: Vector3<f32>,
+    pub _index: u32,
+    pub _triangle_count: u32,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct CollisionBvh {
+    pub _triangles: Vec<CollisionTriangle>,
+    pub _nodes: Vec<CollisionBvhNode>,
+}
+
+// The static triangle soup of the scene in a bvh, built by SceneManagerData::rebuild_collision_world at the scene load.
+// the render objects moved later keep their collision at the load time.
+#[derive(Default)]
+pub struct CollisionWorld {
+    pub _bvh: Option<Arc<CollisionBvh>>,
+    pub _build_receiver: Option<Receiver<CollisionBvh>>, // the build thread of a big scene
+}
+
+fn get_triangle_bound(triangle: &CollisionTriangle) -> (Vector3<f32>, Vector3<f32>) {
+    let [p0, p1, p2] = &triangle._positions;
+    (p0.inf(&p1.inf(p2)), p0.sup(&p1.sup(p2)))
+}
+
+fn get_triangle_centroid(triangle: &CollisionTriangle) -> Vector3<f32> {
+    (&triangle._positions[0] + &triangle._positions[1] + &triangle._positions[2]) / 3.0
+}
+
+fn build_bvh_node(nodes: &mut Vec<CollisionBvhNode>, triangles: &mut [CollisionTriangle], first_triangle: u32) {
+    let mut bound_min = Vector3::new(std::f32::MAX, std::f32::MAX, std::f32::MAX);
+    let mut bound_max = Vector3::new(std::f32::MIN, std::f32::MIN, std::f32::MIN);
+    let mut centroid_min = bound_min.clone();
+    let mut centroid_max = bound_max.clone();
+    for triangle in triangles.iter() {
+        let (triangle_min, triangle_max) = get_triangle_bound(triangle);
+        bound_min = bound_min.inf(&triangle_min);
+        bound_max = bound_max.sup(&triangle_max);
+        let centroid = get_triangle_centroid(triangle);
+        centroid_min = centroid_min.inf(&centroid);
+        centroid_max = centroid_max.sup(&centroid);
+    }
+    let node_index = nodes.len();
+    nodes.push(CollisionBvhNode {
+        _bound_min: bound_min,
+        _bound_max: bound_max,
+        _index: first_triangle,
+        _triangle_count: triangles.len() as u32,
+    });
+    // the median split along the longest axis of the centroids
+    let centroid_extent = centroid_max - centroid_min;
+    let axis = centroid_extent.imax();
+    if triangles.len() <= COLLISION_BVH_LEAF_TRIANGLE_COUNT || centroid_extent[axis] <= 0.0 {
+        return;
+    }
+    triangles.sort_unstable_by(|a, b| {
+        get_triangle_centroid(a)[axis].partial_cmp(&get_triangle_centroid(b)[axis]).unwrap_or(std::cmp::Ordering::Equal)
+    });
+    let middle = triangles.len() / 2;
+    let (left_triangles, right_triangles) = triangles.split_at_mut(middle);
+    nodes[node_index]._triangle_count = 0;
+    build_bvh_node(nodes, left_triangles, first_triangle);
+    nodes[node_index]._index = nodes.len() as u32;
+    build_bvh_node(nodes, right_triangles, first_triangle + middle as u32);
+}
+
+pub fn get_collision_triangle_count(collision_sources: &[CollisionSource]) -> usize {
+    collision_sources.iter().map(|collision_source| collision_source._collision_data._indices.len() / 3).sum()
+}
+
+pub fn build_collision_bvh(collision_sources: &[CollisionSource]) -> CollisionBvh {
+    let mut triangles: Vec<CollisionTriangle> = Vec::with_capacity(get_collision_triangle_count(collision_sources));
+    for collision_source in collision_sources.iter() {
+        let collision_data = &collision_source._collision_data;
+        let world_positions: Vec<Vector3<f32>> = collision_data._positions.iter().map(|position| {
+            let world_position: Vector4<f32> = &collision_source._world_matrix * Vector4::new(position.x, position.y, position.z, 1.0);
+            Vector3::new(world_position.x, world_position.y, world_position.z)
+        }).collect();
+        for indices in collision_data._indices.chunks_exact(3) {
+            triangles.push(CollisionTriangle {
+                _positions: [
+                    world_positions[indices[0] as usize].clone(),
+                    world_positions[indices[1] as usize].clone(),
+                    world_positions[indices[2] as usize].clone(),
+                ],
+                _object_id: collision_source._object_id,
+            });
+        }
+    }
+    let mut nodes: Vec<CollisionBvhNode> = Vec::new();
+    if false == triangles.is_empty() {
+        build_bvh_node(&mut nodes, &mut triangles, 0);
+    }
+    CollisionBvh {
+        _triangles: triangles,
+        _nodes: nodes,
+    }
+}
+
+// the slab test of the segment origin + direction * t, 0 <= t <= max_t
+fn intersect_segment_bound(origin: &Vector3<f32>, inv_direction: &Vector3<f32>, max_t: f32, bound_min: &Vector3<f32>, bound_max: &Vector3<f32>) -> bool {
+    let mut t_min: f32 = 0.0;
+    let mut t_max: f32 = max_t;
+    for i in 0..3 {
+        let t0 = (bound_min[i] - origin[i]) * inv_direction[i];
+        let t1 = (bound_max[i] - origin[i]) * inv_direction[i];
+        t_min = t_min.max(t0.min(t1));
+        t_max = t_max.min(t0.max(t1));
+        if t_max < t_min {
+            return false;
+        }
+    }
+    true
+}
+
+fn get_triangle_normal(positions: &[Vector3<f32>; 3]) -> Option<Vector3<f32>> {
+    (&positions[1] - &positions[0]).cross(&(&positions[2] - &positions[0])).try_normalize(COLLISION_EPSILON)
+}
+
+fn is_point_in_triangle(point: &Vector3<f32>, positions: &[Vector3<f32>; 3], normal: &Vector3<f32>) -> bool {
+    (0..3).all(|i| {
+        let (a, b) = (&positions[i], &positions[(i + 1) % 3]);
+        -COLLISION_EPSILON <= (b - a).cross(&(point - a)).dot(normal)
+    })
+}
+
+// the double sided moller-trumbore, the distance along the normalized direction
+fn intersect_ray_triangle(origin: &Vector3<f32>, direction: &Vector3<f32>, positions: &[Vector3<f32>; 3]) -> Option<f32> {
+    let edge1 = &positions[1] - &positions[0];
+    let edge2 = &positions[2] - &positions[0];
+    let p = direction.cross(&edge2);
+    let determinant = edge1.dot(&p);
+    if determinant.abs() < COLLISION_EPSILON {
+        return None;
+    }
+    let inv_determinant = 1.0 / determinant;
+    let s = origin - &positions[0];
+    let u = s.dot(&p) * inv_determinant;
+    if u < 0.0 || 1.0 < u {
+        return None;
+    }
+    let q = s.cross(&edge1);
+    let v = direction.dot(&q) * inv_determinant;
+    if v < 0.0 || 1.0 < u + v {
+        return None;
+    }
+    let distance = edge2.dot(&q) * inv_determinant;
+    if 0.0 <= distance { Some(distance) } else { None }
+}
+
+// the smaller root of a t^2 + b t + c = 0 in 0 ~ 1, c <= 0 is the overlap at the start
+fn get_lowest_root(a: f32, b: f32, c: f32) -> Option<f32> {
+    if c <= 0.0 {
+        return Some(0.0);
+    }
+    if a < COLLISION_EPSILON {
+        return None;
+    }
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let t = (-b - discriminant.sqrt()) / (2.0 * a);
+    if 0.0 <= t && t <= 1.0 { Some(t) } else { None }
+}
+
+// the sphere from + velocity * t, 0 <= t <= 1 against the face, then the edges and the vertices of the triangle.
+// (t, contact point, normal), the sphere moving away from the surface passes, so an overlapping sphere can get out.
+fn sweep_sphere_triangle(from: &Vector3<f32>, velocity: &Vector3<f32>, radius: f32, positions: &[Vector3<f32>; 3]) -> Option<(f32, Vector3<f32>, Vector3<f32>)> {
+    let mut normal = get_triangle_normal(positions)?;
+    if normal.dot(&(from - &positions[0])) < 0.0 {
+        normal = -normal;
+    }
+    let normal_velocity = normal.dot(velocity);
+    if 0.0 <= normal_velocity {
+        return None;
+    }
+    let plane_distance = normal.dot(&(from - &positions[0]));
+    let t = ((plane_distance - radius) / -normal_velocity).max(0.0);
+    if t <= 1.0 {
+        let center = from + velocity * t;
+        let contact = &center - &normal * normal.dot(&(&center - &positions[0]));
+        if is_point_in_triangle(&contact, positions, &normal) {
+            return Some((t, contact, normal));
+        }
+    }
+
+    let mut nearest_hit: Option<(f32, Vector3<f32>)> = None;
+    let mut update_nearest_hit = |t: f32, contact: Vector3<f32>| {
+        let contact_normal = (from + velocity * t) - &contact;
+        if contact_normal.dot(velocity) < 0.0 && nearest_hit.as_ref().map_or(true, |(nearest_t, _)| t < *nearest_t) {
+            nearest_hit = Some((t, contact));
+        }
+    };
+    let velocity_squared = velocity.dot(velocity);
+    for vertex in positions.iter() {
+        let offset = from - vertex;
+        if let Some(t) = get_lowest_root(velocity_squared, 2.0 * velocity.dot(&offset), offset.dot(&offset) - radius * radius) {
+            update_nearest_hit(t, vertex.clone());
+        }
+    }
+    for i in 0..3 {
+        let (edge_start, edge) = (&positions[i], &positions[(i + 1) % 3] - &positions[i]);
+        let edge_squared = edge.dot(&edge);
+        if edge_squared < COLLISION_EPSILON {
+            continue;
+        }
+        // the distance to the infinite line of the edge, then the contact is tested on the segment
+        let offset = from - edge_start;
+        let (edge_dot_velocity, edge_dot_offset) = (edge.dot(velocity), edge.dot(&offset));
+        let a = velocity_squared - edge_dot_velocity * edge_dot_velocity / edge_squared;
+        let b = 2.0 * (offset.dot(velocity) - edge_dot_offset * edge_dot_velocity / edge_squared);
+        let c = offset.dot(&offset) - edge_dot_offset * edge_dot_offset / edge_squared - radius * radius;
+        if let Some(t) = get_lowest_root(a, b, c) {
+            let f = (edge_dot_offset + edge_dot_velocity * t) / edge_squared;
+            if 0.0 <= f && f <= 1.0 {
+                update_nearest_hit(t, edge_start + &edge * f);
+            }
+        }
+    }
+    nearest_hit.map(|(t, contact)| {
+        let contact_normal = ((from + velocity * t) - &contact).try_normalize(COLLISION_EPSILON).unwrap_or(normal);
+        (t, contact, contact_normal)
+    })
+}
+
+impl CollisionBvh {
+    // the leaves of the bvh crossed by the segment, the bounds are expanded by the radius of the sweep
+    fn visit_leaves<F: FnMut(&[CollisionTriangle]) -> f32>(&self, origin: &Vector3<f32>, direction: &Vector3<f32>, max_t: f32, expand: f32, mut visit: F) {
+        if self._nodes.is_empty() {
+            return;
+        }
+        let inv_direction = Vector3::new(1.0 / direction.x, 1.0 / direction.y, 1.0 / direction.z);
+        let expand = Vector3::new(expand, expand, expand);
+        let mut max_t = max_t;
+        let mut node_stack: Vec<u32> = vec![0];
+        while let Some(node_index) = node_stack.pop() {
+            let node = &self._nodes[node_index as usize];
+            if false == intersect_segment_bound(origin, &inv_direction, max_t, &(&node._bound_min - &expand), &(&node._bound_max + &expand)) {
+                continue;
+            }
+            if 0 < node._triangle_count {
+                let first_triangle = node._index as usize;
+                // the nearer hit shortens the segment of the remaining nodes
+                max_t = max_t.min(visit(&self._triangles[first_triangle..(first_triangle + node._triangle_count as usize)]));
+            } else {
+                node_stack.push(node._index);
+                node_stack.push(node_index + 1);
+            }
+        }
+    }
+
+    pub fn raycast(&self, origin: &Vector3<f32>, direction: &Vector3<f32>, max_distance: f32) -> Option<CollisionHit> {
+        let direction = direction.try_normalize(COLLISION_EPSILON)?;
+        let mut nearest_hit: Option<CollisionHit> = None;
+        self.visit_leaves(origin, &direction, max_distance, 0.0, |triangles| {
+            for triangle in triangles.iter() {
+                if let Some(distance) = intersect_ray_triangle(origin, &direction, &triangle._positions) {
+                    if distance <= max_distance && nearest_hit.as_ref().map_or(true, |hit| distance < hit._distance) {
+                        let normal = get_triangle_normal(&triangle._positions).unwrap_or(-direction);
+                        nearest_hit = Some(CollisionHit {
+                            _position: origin + &direction * distance,
+                            _normal: if 0.0 < normal.dot(&direction) { -normal } else { normal },
+                            _distance: distance,
+                            _object_id: triangle._object_id,
+                        });
+                    }
+                }
+            }
+            nearest_hit.as_ref().map_or(max_distance, |hit| hit._distance)
+        });
+        nearest_hit
+    }
+
+    pub fn sweep_sphere(&self, from: &Vector3<f32>, to: &Vector3<f32>, radius: f32) -> Option<CollisionHit> {
+        let velocity = to - from;
+        let velocity_length = velocity.norm();
+        if velocity_length < COLLISION_EPSILON {
+            return None;
+        }
+        let mut nearest_hit: Option<(f32, CollisionHit)> = None;
+        self.visit_leaves(from, &velocity, 1.0, radius, |triangles| {
+            for triangle in triangles.iter() {
+                if let Some((t, contact, normal)) = sweep_sphere_triangle(from, &velocity, radius, &triangle._positions) {
+                    if nearest_hit.as_ref().map_or(true, |(nearest_t, _)| t < *nearest_t) {
+                        nearest_hit = Some((t, CollisionHit {
+                            _position: contact,
+                            _normal: normal,
+                            _distance: t * velocity_length,
+                            _object_id: triangle._object_id,
+                        }));
+                    }
+                }
+            }
+            nearest_hit.as_ref().map_or(1.0, |(t, _)| *t)
+        });
+        nearest_hit.map(|(_, hit)| hit)
+    }
+}
+
+impl CollisionWorld {
+    pub fn is_ready(&self) -> bool {
+        self._bvh.is_some()
+    }
+
+    pub fn clear_collision_world(&mut self) {
+        self._bvh = None;
+        self._build_receiver = None; // the running build sends to nobody
+    }
+
+    // the bvh of a big scene is built off the main thread, the queries are not ready until update_collision_world receives it
+    pub fn build_collision_world(&mut self, collision_sources: Vec<CollisionSource>) {
+        self.clear_collision_world();
+        let triangle_count = get_collision_triangle_count(&collision_sources);
+        if triangle_count < COLLISION_ASYNC_BUILD_TRIANGLE_COUNT {
+            let bvh = build_collision_bvh(&collision_sources);
+            log::info!("build_collision_world: {} triangles, {} nodes", bvh._triangles.len(), bvh._nodes.len());
+            self._bvh = Some(Arc::new(bvh));
+            return;
+        }
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let build_start_time = time::Instant::now();
+            let bvh = build_collision_bvh(&collision_sources);
+            log::info!("build_collision_world: {} triangles, {} nodes, {:?} on the build thread", bvh._triangles.len(), bvh._nodes.len(), build_start_time.elapsed());
+            let _ = sender.send(bvh);
+        });
+        self._build_receiver = Some(receiver);
+    }
+
+    // once per frame, takes the finished bvh of the build thread
+    pub fn update_collision_world(&mut self) {
+        let received = match self._build_receiver.as_ref() {
+            Some(build_receiver) => build_receiver.try_recv(),
+            None => return,
+        };
+        match received {
+            Ok(bvh) => {
+                self._bvh = Some(Arc::new(bvh));
+                self._build_receiver = None;
+            },
+            Err(TryRecvError::Empty) => (),
+            Err(TryRecvError::Disconnected) => {
+                log::error!("update_collision_world: the build thread is terminated");
+                self._build_receiver = None;
+            },
+        }
+    }
+
+    fn get_bvh(&self) -> Result<&CollisionBvh, CollisionNotReady> {
+        self._bvh.as_ref().map(|bvh| bvh.as_ref()).ok_or(CollisionNotReady)
+    }
+
+    // direction: need not be normalized, the hit distance is in meters
+    pub fn raycast(&self, origin: &Vector3<f32>, direction: &Vector3<f32>, max_distance: f32) -> Result<Option<CollisionHit>, CollisionNotReady> {
+        Ok(self.get_bvh()?.raycast(origin, direction, max_distance))
+    }
+
+    pub fn sweep_sphere(&self, from: &Vector3<f32>, to: &Vector3<f32>, radius: f32) -> Result<Option<CollisionHit>, CollisionNotReady> {
+        Ok(self.get_bvh()?.sweep_sphere(from, to, radius))
+    }
+
+    // the collide and slide of a sphere, ex) the camera. the movement along the surface remains after a hit.
+    pub fn resolve_sphere_movement(&self, from: &Vector3<f32>, to: &Vector3<f32>, radius: f32) -> Result<Vector3<f32>, CollisionNotReady> {
+        let bvh = self.get_bvh()?;
+        let mut position = from.clone();
+        let mut target = to.clone();
+        for _ in 0..COLLISION_SLIDE_ITERATIONS {
+            match bvh.sweep_sphere(&position, &target, radius) {
+                None => return Ok(target),
+                Some(hit) => {
+                    let movement = &target - &position;
+                    position += movement.normalize() * hit._distance + &hit._normal * COLLISION_SKIN_WIDTH;
+                    let remaining_movement = &target - &position;
+                    target = &position + &remaining_movement - &hit._normal * remaining_movement.dot(&hit._normal).min(0.0);
+                }
+            }
+        }
+        Ok(position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the quads of size x size meters on the xz plane, y up
+    fn create_grid_collision_data(quad_count: u32, size: f32) -> Arc<MeshCollisionData> {
+        let mut collision_data = MeshCollisionData::default();
+        let quad_size = size / quad_count as f32;
+        for z in 0..(quad_count + 1) {
+            for x in 0..(quad_count + 1) {
+                collision_data._positions.push(Vector3::new(x as f32 * quad_size - size * 0.5, 0.0, z as f32 * quad_size - size * 0.5));
+            }
+        }
+        let row = quad_count + 1;
+        for z in 0..quad_count {
+            for x in 0..quad_count {
+                let i = z * row + x;
+                collision_data._indices.extend_from_slice(&[i, i + row, i + 1, i + 1, i + row, i + row + 1]);
+            }
+        }
+        Arc::new(collision_data)
+    }
+
+    fn create_collision_source(collision_data: &Arc<MeshCollisionData>, world_matrix: Matrix4<f32>, object_id: u32) -> CollisionSource {
+        CollisionSource {
+            _collision_data: collision_data.clone(),
+            _world_matrix: world_matrix,
+            _object_id: object_id,
+        }
+    }
+
+    // the floor at y = 0 and the wall of x = 5 facing -x
+    fn create_test_collision_world() -> CollisionWorld {
+        let quad = create_grid_collision_data(1, 20.0);
+        let wall_matrix = Matrix4::new_translation(&Vector3::new(5.0, 0.0, 0.0)) * Matrix4::from_euler_angles(0.0, 0.0, std::f32::consts::FRAC_PI_2);
+        let mut collision_world = CollisionWorld::default();
+        collision_world.build_collision_world(vec![
+            create_collision_source(&quad, Matrix4::identity(), 1),
+            create_collision_source(&quad, wall_matrix, 2),
+        ]);
+        collision_world
+    }
+
+    fn assert_vector_eq(expected: &Vector3<f32>, actual: &Vector3<f32>) {
+        assert!((expected - actual).norm() < 0.001, "expected {:?}, actual {:?}", expected, actual);
+    }
+
+    #[test]
+    fn test_raycast_hit_and_miss() {
+        let collision_world = create_test_collision_world();
+        assert!(collision_world.is_ready());
+
+        let hit = collision_world.raycast(&Vector3::new(1.0, 5.0, 2.0), &Vector3::new(0.0, -2.0, 0.0), 100.0).unwrap().unwrap();
+        assert_vector_eq(&Vector3::new(1.0, 0.0, 2.0), &hit._position);
+        assert_vector_eq(&Vector3::new(0.0, 1.0, 0.0), &hit._normal);
+        assert!((hit._distance - 5.0).abs() < 0.001);
+        assert_eq!(1, hit._object_id);
+
+        // the normal faces the query from the back side
+        let hit = collision_world.raycast(&Vector3::new(1.0, -5.0, 2.0), &Vector3::new(0.0, 1.0, 0.0), 100.0).unwrap().unwrap();
+        assert_vector_eq(&Vector3::new(0.0, -1.0, 0.0), &hit._normal);
+
+        // the nearest of the floor and the wall
+        let hit = collision_world.raycast(&Vector3::new(0.0, 1.0, 0.0), &Vector3::new(1.0, -0.1, 0.0), 100.0).unwrap().unwrap();
+        assert_eq!(2, hit._object_id);
+        assert_vector_eq(&Vector3::new(5.0, 0.5, 0.0), &hit._position);
+        assert_vector_eq(&Vector3::new(-1.0, 0.0, 0.0), &hit._normal);
+
+        // the misses: too short, outside of the floor, parallel, away, zero direction
+        assert_eq!(Ok(None), collision_world.raycast(&Vector3::new(1.0, 5.0, 2.0), &Vector3::new(0.0, -1.0, 0.0), 4.9));
+        assert_eq!(Ok(None), collision_world.raycast(&Vector3::new(20.0, 5.0, 2.0), &Vector3::new(0.0, -1.0, 0.0), 100.0));
+        assert_eq!(Ok(None), collision_world.raycast(&Vector3::new(0.0, 1.0, 0.0), &Vector3::new(0.0, 0.0, 1.0), 100.0));
+        assert_eq!(Ok(None), collision_world.raycast(&Vector3::new(1.0, 5.0, 2.0), &Vector3::new(0.0, 1.0, 0.0), 100.0));
+        assert_eq!(Ok(None), collision_world.raycast(&Vector3::new(1.0, 5.0, 2.0), &Vector3::zeros(), 100.0));
+    }
+
+    #[test]
+    fn test_sphere_sweep() {
+        let collision_world = create_test_collision_world();
+        let radius = 0.5;
+
+        // the face of the floor
+        let hit = collision_world.sweep_sphere(&Vector3::new(1.0, 5.0, 2.0), &Vector3::new(1.0, -5.0, 2.0), radius).unwrap().unwrap();
+        assert_vector_eq(&Vector3::new(1.0, 0.0, 2.0), &hit._position);
+        assert_vector_eq(&Vector3::new(0.0, 1.0, 0.0), &hit._normal);
+        assert!((hit._distance - 4.5).abs() < 0.001);
+
+        // the edge of the floor, the sphere center passes outside of the triangles
+        let hit = collision_world.sweep_sphere(&Vector3::new(10.3, 5.0, 0.0), &Vector3::new(10.3, -5.0, 0.0), radius).unwrap().unwrap();
+        assert_vector_eq(&Vector3::new(10.0, 0.0, 0.0), &hit._position);
+        assert!((hit._distance - (5.0 - (0.25f32 - 0.09).sqrt())).abs() < 0.001);
+
+        // the misses: above the floor, stopping before the floor, far from the edge
+        assert_eq!(Ok(None), collision_world.sweep_sphere(&Vector3::new(-3.0, 1.0, 0.0), &Vector3::new(3.0, 1.0, 0.0), radius));
+        assert_eq!(Ok(None), collision_world.sweep_sphere(&Vector3::new(1.0, 5.0, 2.0), &Vector3::new(1.0, 0.6, 2.0), radius));
+        assert_eq!(Ok(None), collision_world.sweep_sphere(&Vector3::new(10.6, 5.0, 0.0), &Vector3::new(10.6, -5.0, 0.0), radius));
+
+        // the slide along the floor, the sphere stays above it
+        let position = collision_world.resolve_sphere_movement(&Vector3::new(0.0, 1.0, 0.0), &Vector3::new(3.0, -1.0, 0.0), radius).unwrap();
+        assert!((position.x - 3.0).abs() < 0.01, "{:?}", position);
+        assert!(radius <= position.y && position.y < radius + 0.01, "{:?}", position);
+        // stopped by the wall
+        let position = collision_world.resolve_sphere_movement(&Vector3::new(0.0, 1.0, 0.0), &Vector3::new(8.0, 1.0, 0.0), radius).unwrap();
+        assert!(position.x <= 5.0 - radius && 5.0 - radius - 0.01 < position.x, "{:?}", position);
+    }
+
+    #[test]
+    fn test_async_build_threshold() {
+        // under the threshold, ready at once
+        let small_grid = create_grid_collision_data(16, 20.0);
+        let small_sources = vec![create_collision_source(&small_grid, Matrix4::identity(), 1)];
+        assert!(get_collision_triangle_count(&small_sources) < COLLISION_ASYNC_BUILD_TRIANGLE_COUNT);
+        let mut collision_world = CollisionWorld::default();
+        assert_eq!(Err(CollisionNotReady), collision_world.raycast(&Vector3::new(0.0, 1.0, 0.0), &Vector3::new(0.0, -1.0, 0.0), 10.0));
+        collision_world.build_collision_world(small_sources);
+        assert!(collision_world.is_ready());
+        assert!(collision_world._build_receiver.is_none());
+
+        // over the threshold, not ready until the build thread is done
+        let big_grid = create_grid_collision_data(182, 20.0);
+        let big_sources = vec![create_collision_source(&big_grid, Matrix4::new_translation(&Vector3::new(0.0, 2.0, 0.0)), 3)];
+        assert!(COLLISION_ASYNC_BUILD_TRIANGLE_COUNT <= get_collision_triangle_count(&big_sources));
+        collision_world.build_collision_world(big_sources);
+        assert!(false == collision_world.is_ready());
+        assert_eq!(Err(CollisionNotReady), collision_world.sweep_sphere(&Vector3::new(0.0, 5.0, 0.0), &Vector3::new(0.0, -5.0, 0.0), 0.5));
+        assert_eq!(Err(CollisionNotReady), collision_world.resolve_sphere_movement(&Vector3::new(0.0, 5.0, 0.0), &Vector3::new(0.0, -5.0, 0.0), 0.5));
+        let end_time = time::Instant::now() + time::Duration::from_secs(60);
+        while false == collision_world.is_ready() && time::Instant::now() < end_time {
+            collision_world.update_collision_world();
+            thread::sleep(time::Duration::from_millis(10));
+        }
+        assert!(collision_world.is_ready());
+        let hit = collision_world.raycast(&Vector3::new(1.23, 5.0, -4.56), &Vector3::new(0.0, -1.0, 0.0), 10.0).unwrap().unwrap();
+        assert_eq!(3, hit._object_id);
+        assert!((hit._distance - 3.0).abs() < 0.001);
+
+        // the running build is dropped by the clear
+        collision_world.build_collision_world(vec![create_collision_source(&big_grid, Matrix4::identity(), 4)]);
+        collision_world.clear_collision_world();
+        collision_world.update_collision_world();
+        assert!(false == collision_world.is_ready());
+    }
+}
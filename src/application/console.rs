@@ -0,0 +1,282 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use winit::event::VirtualKeyCode;
+
+use crate::application::environment_settings::{ self, OCEAN_COMMAND };
+use crate::application::haptics::{ self, HapticsManager, RUMBLE_COMMAND };
+use crate::application::input::{ self, KeyboardInputData };
+use crate::application::scene_file;
+use crate::application::scene_manager::SceneManagerData;
+use crate::application::wind_system::{ self, WIND_COMMAND };
+use crate::renderer::font::FontManager;
+use crate::renderer::renderer::RendererData;
+use crate::utilities::logger;
+
+pub const CONSOLE_TOGGLE_KEY: VirtualKeyCode = VirtualKeyCode::Grave;
+pub const CONSOLE_LINE_COUNT: usize = 16; // the recent log lines above the input line
+pub const CONSOLE_HISTORY_SIZE: usize = 64;
+pub const CONSOLE_PROMPT: &str = "> ";
+pub const CONSOLE_CURSOR_CHAR: char = '_';
+
+// the engine systems of the main loop, borrowed while a command runs
+pub struct ConsoleCommandContext<'a> {
+    pub _renderer_data: &'a mut RendererData,
+    pub _scene_manager_data: &'a mut SceneManagerData,
+    pub _haptics_manager: &'a mut HapticsManager,
+}
+
+// Ok: the text printed into the console, empty for nothing. Err: the error message, the usage is appended.
+pub type ConsoleCommandResult = Result<String, String>;
+pub type ConsoleCommandCallback = Box<dyn FnMut(&mut ConsoleCommandContext, &[&str]) -> ConsoleCommandResult>;
+
+pub struct ConsoleCommand {
+    pub _usage: String, // ex) "r.exposure <exposure>"
+    pub _callback: ConsoleCommandCallback,
+}
+
+// the commands of the console by name, sorted for help.
+// ex) command_registry.register_command("r.exposure", "r.exposure <exposure>", |context, arguments| { ... })
+pub struct CommandRegistry {
+    pub _commands: BTreeMap<String, ConsoleCommand>,
+}
+
+pub struct Console {
+    pub _is_open: bool,
+    pub _input_text: String,
+    pub _cursor_index: usize,
+    pub _history: Vec<String>, // the oldest first
+    pub _history_index: Option<usize>, // None: the new input line
+    pub _command_registry: CommandRegistry,
+}
+
+// ex) let exposure: f32 = console::parse_argument(arguments, 0, "exposure")?;
+pub fn parse_argument<T: FromStr>(arguments: &[&str], index: usize, argument_name: &str) -> Result<T, String> {
+    match arguments.get(index) {
+        Some(argument) => argument.parse::<T>().map_err(|_| format!("invalid {}: {}", argument_name, argument)),
+        None => Err(format!("missing {}", argument_name)),
+    }
+}
+
+impl CommandRegistry {
+    pub fn create_command_registry() -> CommandRegistry {
+        CommandRegistry {
+            _commands: BTreeMap::new(),
+        }
+    }
+
+    // a command of the same name is replaced
+    pub fn register_command<F>(&mut self, command_name: &str, usage: &str, callback: F)
+        where F: FnMut(&mut ConsoleCommandContext, &[&str]) -> ConsoleCommandResult + 'static
+    {
+        if self._commands.contains_key(command_name) {
+            log::warn!("register_command: {} is replaced", command_name);
+        }
+        self._commands.insert(String::from(command_name), ConsoleCommand {
+            _usage: String::from(usage),
+            _callback: Box::new(callback),
+        });
+    }
+
+    pub fn unregister_command(&mut self, command_name: &str) {
+        self._commands.remove(command_name);
+    }
+
+    pub fn get_command_names(&self) -> Vec<String> {
+        self._commands.keys().cloned().collect()
+    }
+
+    // "help" lists the usages of the commands
+    pub fn execute_command(&mut self, context: &mut ConsoleCommandContext, command_line: &str) -> ConsoleCommandResult {
+        let tokens: Vec<&str> = command_line.split_whitespace().collect();
+        let command_name = match tokens.first() {
+            Some(command_name) => *command_name,
+            None => return Ok(String::new()),
+        };
+        if "help" == command_name {
+            let usages: Vec<&str> = self._commands.values().map(|command| command._usage.as_str()).collect();
+            return Ok(usages.join("\n"));
+        }
+        match self._commands.get_mut(command_name) {
+            Some(command) => (command._callback)(context, &tokens[1..]).map_err(|error| format!("{}\nusage: {}", error, command._usage)),
+            None => Err(format!("unknown command: {}, see help", command_name)),
+        }
+    }
+
+    pub fn register_engine_commands(&mut self) {
+        self.register_command("r.debug_target", "r.debug_target <render target name | off> [layer] [mip level]", |context, arguments| {
+            let renderer_data = &context._renderer_data;
+            let render_target_name = match arguments.first() {
+                Some(render_target_name) => *render_target_name,
+                None => return Ok(renderer_data.get_debug_render_target_names().join(" ")),
+            };
+            if "off" == render_target_name {
+                renderer_data.clear_debug_render_target();
+                return Ok(String::new());
+            }
+            if false == renderer_data.get_debug_render_target_names().iter().any(|name| name == render_target_name) {
+                return Err(format!("unknown render target: {}", render_target_name));
+            }
+            let layer: u32 = if 1 < arguments.len() { parse_argument(arguments, 1, "layer")? } else { 0 };
+            let mip_level: u32 = if 2 < arguments.len() { parse_argument(arguments, 2, "mip level")? } else { 0 };
+            renderer_data.set_debug_render_target(render_target_name, layer, mip_level);
+            Ok(String::new())
+        });
+
+        self.register_command("r.exposure", "r.exposure <exposure>", |context, arguments| {
+            let exposure: f32 = parse_argument(arguments, 0, "exposure")?;
+            context._renderer_data.set_exposure(exposure);
+            Ok(format!("exposure: {}", context._renderer_data.get_tonemap_settings()._exposure))
+        });
+
+        self.register_command(OCEAN_COMMAND, "ocean <parameter> <values>, ex) ocean choppy 2.3 2.1 1.3 0.9", |context, arguments| {
+            set_ocean_parameter(context, arguments)
+        });
+
+        self.register_command("ocean.wind", "ocean.wind <wind speed>", |context, arguments| {
+            let _wind_speed: f32 = parse_argument(arguments, 0, "wind speed")?;
+            set_ocean_parameter(context, &["wind", arguments[0]])
+        });
+
+        // the errors of the commands below are logged into the console
+        self.register_command(WIND_COMMAND, "wind <parameter> <values>, ex) wind speed 5, wind direction 1 0 0.5, wind volume add fan sphere 0 0 0 5 0 8 0", |context, arguments| {
+            wind_system::execute_wind_command(context._scene_manager_data, context._renderer_data, &get_command_line(WIND_COMMAND, arguments));
+            Ok(String::new())
+        });
+
+        self.register_command(RUMBLE_COMMAND, "rumble <test | play <low> <high> <duration> | pattern <pattern name> | intensity <intensity> | stop>", |context, arguments| {
+            haptics::execute_rumble_command(context._haptics_manager, &get_command_line(RUMBLE_COMMAND, arguments));
+            Ok(String::new())
+        });
+
+        self.register_command("scene.load", "scene.load <scene file path | scene name>", |context, arguments| {
+            let argument = arguments.first().ok_or(String::from("missing scene file path"))?;
+            let mut scene_file_path = PathBuf::from(argument);
+            if false == scene_file_path.is_file() {
+                scene_file_path = scene_file::get_scene_file_path(argument);
+            }
+            if context._scene_manager_data.open_scene_from_file(&scene_file_path) {
+                context._renderer_data.invalidate_temporal_history();
+                Ok(format!("scene loaded: {:?}", scene_file_path))
+            } else {
+                Err(format!("failed to load the scene: {:?}", scene_file_path))
+            }
+        });
+    }
+}
+
+// the command line of the execute_xxx_command functions, ex) "wind speed 5"
+fn get_command_line(command_name: &str, arguments: &[&str]) -> String {
+    let mut tokens: Vec<&str> = vec![command_name];
+    tokens.extend_from_slice(arguments);
+    tokens.join(" ")
+}
+
+// the spectrum textures are updated in place, same as PageUp / PageDown
+fn set_ocean_parameter(context: &mut ConsoleCommandContext, arguments: &[&str]) -> ConsoleCommandResult {
+    environment_settings::execute_ocean_command(context._scene_manager_data, context._renderer_data, &get_command_line(OCEAN_COMMAND, arguments));
+    Ok(String::new())
+}
+
+impl Console {
+    pub fn create_console() -> Console {
+        let mut command_registry = CommandRegistry::create_command_registry();
+        command_registry.register_engine_commands();
+        Console {
+            _is_open: false,
+            _input_text: String::new(),
+            _cursor_index: 0,
+            _history: Vec::new(),
+            _history_index: None,
+            _command_registry: command_registry,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self._is_open
+    }
+
+    pub fn set_open(&mut self, is_open: bool) {
+        self._is_open = is_open;
+        self._history_index = None;
+    }
+
+    pub fn get_command_registry_mut(&mut self) -> &mut CommandRegistry {
+        &mut self._command_registry
+    }
+
+    // the command is echoed, the result and the error are printed into the log ring buffer of the console
+    pub fn execute_command(&mut self, context: &mut ConsoleCommandContext, command_line: &str) {
+        logger::push_log_line(format!("{}{}", CONSOLE_PROMPT, command_line));
+        match self._command_registry.execute_command(context, command_line) {
+            Ok(text) => for line in text.lines() {
+                logger::push_log_line(String::from(line));
+            },
+            Err(error) => for line in error.lines() {
+                logger::push_log_line(format!("error: {}", line));
+            },
+        }
+    }
+
+    fn set_input_text_from_history(&mut self, history_index: Option<usize>) {
+        self._history_index = history_index;
+        self._input_text = match history_index {
+            Some(history_index) => self._history[history_index].clone(),
+            None => String::new(),
+        };
+        self._cursor_index = self._input_text.len();
+    }
+
+    // ` toggles the console, Escape closes it. Return runs the input line, Up / Down walk the history.
+    pub fn update_console(&mut self, keyboard_input_data: &KeyboardInputData, context: &mut ConsoleCommandContext) {
+        if keyboard_input_data.get_text_key_pressed(CONSOLE_TOGGLE_KEY) {
+            let is_open = false == self._is_open;
+            self.set_open(is_open);
+            return;
+        }
+
+        if false == self._is_open {
+            return;
+        }
+
+        if keyboard_input_data.get_text_key_pressed(VirtualKeyCode::Escape) {
+            self.set_open(false);
+        } else if keyboard_input_data.get_text_key_pressed(VirtualKeyCode::Return) || keyboard_input_data.get_text_key_pressed(VirtualKeyCode::NumpadEnter) {
+            let command_line = String::from(self._input_text.trim());
+            if false == command_line.is_empty() {
+                if self._history.last() != Some(&command_line) {
+                    self._history.push(command_line.clone());
+                    if CONSOLE_HISTORY_SIZE < self._history.len() {
+                        self._history.remove(0);
+                    }
+                }
+                self.execute_command(context, &command_line);
+            }
+            self.set_input_text_from_history(None);
+        } else if keyboard_input_data.get_key_repeat(VirtualKeyCode::Up) && false == self._history.is_empty() {
+            let history_index = match self._history_index {
+                Some(history_index) => history_index.saturating_sub(1),
+                None => self._history.len() - 1,
+            };
+            self.set_input_text_from_history(Some(history_index));
+        } else if keyboard_input_data.get_key_repeat(VirtualKeyCode::Down) && self._history_index.is_some() {
+            let history_index = self._history_index.unwrap() + 1;
+            self.set_input_text_from_history(if history_index < self._history.len() { Some(history_index) } else { None });
+        } else {
+            input::edit_text_input(&mut self._input_text, &mut self._cursor_index, keyboard_input_data);
+        }
+    }
+
+    // the recent logs and the input line with the caret through the debug text of the font manager
+    pub fn update_console_text(&self, font_manager: &mut FontManager) {
+        if self._is_open {
+            for line in logger::get_recent_logs(CONSOLE_LINE_COUNT) {
+                font_manager.log(line);
+            }
+            let mut input_text = self._input_text.clone();
+            input_text.insert(self._cursor_index.min(input_text.len()), CONSOLE_CURSOR_CHAR);
+            font_manager.log(format!("{}{}", CONSOLE_PROMPT, input_text));
+        }
+    }
+}
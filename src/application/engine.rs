@@ -0,0 +1,378 @@
+use std::path::PathBuf;
+
+use log::LevelFilter;
+
+use crate::application::application::{ self, ApplicationBase, EngineApplication };
+use crate::application::scene_manager::ProjectSceneManagerBase;
+use crate::application::window_integration::{ WindowConfig, WindowMode };
+use crate::renderer::renderer::{ RendererData, ProjectRendererBase };
+use crate::renderer::ui::ProjectUIManagerBase;
+use crate::resource::resource::{ Resources, ProjectResourcesBase };
+use crate::resource::virtual_file_system::VirtualFileSystem;
+use crate::vulkan_context::render_pass::RenderPassDataCreateInfo;
+
+// the embedding api for the downstream crates, the main loop stays in application::run_application_with_config.
+//
+// the order of the initialization:
+//     1. the resource mount points of EngineConfig, then the mount points of vfs_settings.json
+//     2. RendererData, then the render passes of the project renderer and RenderExtension::get_render_pass_data_create_infos
+//     3. textures, render passes, materials, meshes, models.. then RenderExtension::on_resources_loaded in the registration order
+//     4. ProjectResourcesBase::initialize_project_resources, the fonts, the ui and the scene manager
+//     5. the framebuffers and the descriptors, then SceneLogic::on_initialize, every resource is available here
+//     6. per frame: SceneLogic::on_event, then SceneLogic::on_update before the rendering
+//     7. SceneLogic::on_terminate before the scene manager and the resources are destroyed
+//
+// ex)
+//     EngineBuilder::new("MyGame")
+//         .with_window_size(1280, 720)
+//         .with_resource_mount_point("my_game", "my_game/resource")
+//         .with_project_resources(project_resources)
+//         .with_project_scene_manager(project_scene_manager)
+//         .with_project_renderer(project_renderer)
+//         .with_project_ui_manager(project_ui_manager)
+//         .with_scene_logic(Box::new(MyGameLogic::default()))
+//         .build()
+//         .unwrap()
+//         .run();
+
+#[derive(Clone, Debug)]
+pub struct EngineConfig {
+    pub _app_name: String,
+    pub _app_version: u32,
+    pub _window_config: WindowConfig,
+    pub _log_level: LevelFilter,
+    pub _resource_mount_points: Vec<(String, PathBuf)>, // layer name, root path. lowest priority first, above the engine layer
+}
+
+impl Default for EngineConfig {
+    fn default() -> EngineConfig {
+        EngineConfig {
+            _app_name: String::from("RustEngine3D"),
+            _app_version: 1,
+            _window_config: WindowConfig::default(),
+            _log_level: LevelFilter::Info,
+            _resource_mount_points: Vec::new(),
+        }
+    }
+}
+
+impl EngineConfig {
+    // 1. of the initialization order, Resources::initialize_virtual_file_system mounts vfs_settings.json above them
+    pub fn mount_resource_mount_points(&self, virtual_file_system: &mut VirtualFileSystem) {
+        for (layer_name, root_path) in self._resource_mount_points.iter() {
+            virtual_file_system.mount(layer_name, root_path);
+        }
+    }
+}
+
+// the game logic of the downstream crate, every method has the empty default.
+pub trait SceneLogic {
+    fn on_initialize(&mut self, _engine_application: &EngineApplication) {}
+    fn on_event(&mut self, _engine_application: &EngineApplication) {}
+    fn on_update(&mut self, _engine_application: &EngineApplication) {}
+    fn on_terminate(&mut self) {}
+}
+
+// the custom render passes and the resources of the downstream crate, without copying the project renderer.
+// the materials of the custom render passes are loaded from the .mat files like the others.
+pub trait RenderExtension {
+    fn get_render_pass_data_create_infos(&self, _renderer_data: &RendererData) -> Vec<RenderPassDataCreateInfo> { Vec::new() }
+    fn on_resources_loaded(&mut self, _resources: &Resources, _renderer_data: &RendererData) {}
+}
+
+// drives a SceneLogic through the ApplicationBase of the main loop.
+pub struct SceneLogicApplication {
+    pub _scene_logic: Box<dyn SceneLogic>,
+    pub _engine_application: *const EngineApplication,
+}
+
+impl SceneLogicApplication {
+    fn get_engine_application(&self) -> &EngineApplication {
+        unsafe { &*self._engine_application }
+    }
+}
+
+impl ApplicationBase for SceneLogicApplication {
+    fn initialize_application(&mut self, engine_application: &EngineApplication) {
+        self._engine_application = engine_application;
+        self._scene_logic.on_initialize(engine_application);
+    }
+
+    fn terminate_application(&mut self) {
+        self._scene_logic.on_terminate();
+    }
+
+    fn update_event(&mut self) {
+        let engine_application: *const EngineApplication = self.get_engine_application();
+        self._scene_logic.on_event(unsafe { &*engine_application });
+    }
+
+    fn update_application(&mut self) {
+        let engine_application: *const EngineApplication = self.get_engine_application();
+        self._scene_logic.on_update(unsafe { &*engine_application });
+    }
+}
+
+pub struct DefaultSceneLogic;
+
+impl SceneLogic for DefaultSceneLogic {}
+
+// the handle built by EngineBuilder, run never returns.
+pub struct Engine {
+    pub _engine_config: EngineConfig,
+    pub _application: *const dyn ApplicationBase,
+    pub _project_resources: *const dyn ProjectResourcesBase,
+    pub _project_scene_manager: *const dyn ProjectSceneManagerBase,
+    pub _project_renderer: *const dyn ProjectRendererBase,
+    pub _project_ui_manager: *const dyn ProjectUIManagerBase,
+    pub _render_extensions: Vec<*const dyn RenderExtension>,
+}
+
+impl Engine {
+    pub fn get_engine_config(&self) -> &EngineConfig {
+        &self._engine_config
+    }
+
+    pub fn run(self) {
+        application::run_application_with_config(
+            self._engine_config,
+            self._application,
+            self._project_resources,
+            self._project_scene_manager,
+            self._project_renderer,
+            self._project_ui_manager,
+            self._render_extensions,
+        );
+    }
+}
+
+pub struct EngineBuilder {
+    _engine_config: EngineConfig,
+    _application: Option<*const dyn ApplicationBase>,
+    _scene_logic: Option<Box<dyn SceneLogic>>,
+    _project_resources: Option<*const dyn ProjectResourcesBase>,
+    _project_scene_manager: Option<*const dyn ProjectSceneManagerBase>,
+    _project_renderer: Option<*const dyn ProjectRendererBase>,
+    _project_ui_manager: Option<*const dyn ProjectUIManagerBase>,
+    _render_extensions: Vec<*const dyn RenderExtension>,
+}
+
+impl EngineBuilder {
+    pub fn new(app_name: &str) -> EngineBuilder {
+        EngineBuilder {
+            _engine_config: EngineConfig {
+                _app_name: String::from(app_name),
+                ..Default::default()
+            },
+            _application: None,
+            _scene_logic: None,
+            _project_resources: None,
+            _project_scene_manager: None,
+            _project_renderer: None,
+            _project_ui_manager: None,
+            _render_extensions: Vec::new(),
+        }
+    }
+
+    pub fn with_engine_config(mut self, engine_config: EngineConfig) -> EngineBuilder {
+        self._engine_config = engine_config;
+        self
+    }
+
+    pub fn with_app_version(mut self, app_version: u32) -> EngineBuilder {
+        self._engine_config._app_version = app_version;
+        self
+    }
+
+    pub fn with_window_size(mut self, width: i32, height: i32) -> EngineBuilder {
+        self._engine_config._window_config._window_mode = WindowMode::Windowed { _width: width as u32, _height: height as u32 };
+        self
+    }
+
+    pub fn with_window_config(mut self, window_config: WindowConfig) -> EngineBuilder {
+        self._engine_config._window_config = window_config;
+        self
+    }
+
+    pub fn with_log_level(mut self, log_level: LevelFilter) -> EngineBuilder {
+        self._engine_config._log_level = log_level;
+        self
+    }
+
+    pub fn with_resource_mount_point(mut self, layer_name: &str, root_path: &str) -> EngineBuilder {
+        self._engine_config._resource_mount_points.push((String::from(layer_name), PathBuf::from(root_path)));
+        self
+    }
+
+    // the existing ApplicationBase of the project, exclusive with with_scene_logic.
+    pub fn with_application(mut self, application: *const dyn ApplicationBase) -> EngineBuilder {
+        self._application = Some(application);
+        self
+    }
+
+    pub fn with_scene_logic(mut self, scene_logic: Box<dyn SceneLogic>) -> EngineBuilder {
+        self._scene_logic = Some(scene_logic);
+        self
+    }
+
+    pub fn with_project_resources(mut self, project_resources: *const dyn ProjectResourcesBase) -> EngineBuilder {
+        self._project_resources = Some(project_resources);
+        self
+    }
+
+    pub fn with_project_scene_manager(mut self, project_scene_manager: *const dyn ProjectSceneManagerBase) -> EngineBuilder {
+        self._project_scene_manager = Some(project_scene_manager);
+        self
+    }
+
+    pub fn with_project_renderer(mut self, project_renderer: *const dyn ProjectRendererBase) -> EngineBuilder {
+        self._project_renderer = Some(project_renderer);
+        self
+    }
+
+    pub fn with_project_ui_manager(mut self, project_ui_manager: *const dyn ProjectUIManagerBase) -> EngineBuilder {
+        self._project_ui_manager = Some(project_ui_manager);
+        self
+    }
+
+    // called in the registration order
+    pub fn with_render_extension(mut self, render_extension: *const dyn RenderExtension) -> EngineBuilder {
+        self._render_extensions.push(render_extension);
+        self
+    }
+
+    pub fn build(self) -> Result<Engine, String> {
+        if self._application.is_some() && self._scene_logic.is_some() {
+            return Err(String::from("EngineBuilder: with_application and with_scene_logic are exclusive"));
+        }
+        let project_resources = self._project_resources.ok_or(String::from("EngineBuilder: project resources are missing"))?;
+        let project_scene_manager = self._project_scene_manager.ok_or(String::from("EngineBuilder: project scene manager is missing"))?;
+        let project_renderer = self._project_renderer.ok_or(String::from("EngineBuilder: project renderer is missing"))?;
+        let project_ui_manager = self._project_ui_manager.ok_or(String::from("EngineBuilder: project ui manager is missing"))?;
+        // the application lives as long as the main loop, which never returns.
+        let application: *const dyn ApplicationBase = match self._application {
+            Some(application) => application,
+            None => {
+                let scene_logic = self._scene_logic.unwrap_or(Box::new(DefaultSceneLogic));
+                Box::into_raw(Box::new(SceneLogicApplication {
+                    _scene_logic: scene_logic,
+                    _engine_application: std::ptr::null(),
+                }))
+            },
+        };
+        Ok(Engine {
+            _engine_config: self._engine_config,
+            _application: application,
+            _project_resources: project_resources,
+            _project_scene_manager: project_scene_manager,
+            _project_renderer: project_renderer,
+            _project_ui_manager: project_ui_manager,
+            _render_extensions: self._render_extensions,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    struct TestRenderExtension {
+        _render_pass_name: String,
+    }
+
+    impl RenderExtension for TestRenderExtension {}
+
+    fn create_scene_logic_application() -> SceneLogicApplication {
+        SceneLogicApplication {
+            _scene_logic: Box::new(DefaultSceneLogic),
+            _engine_application: std::ptr::null(),
+        }
+    }
+
+    fn is_same_render_extension(lhs: *const dyn RenderExtension, rhs: &TestRenderExtension) -> bool {
+        std::ptr::eq(lhs as *const u8, rhs as *const TestRenderExtension as *const u8)
+    }
+
+    #[test]
+    fn test_engine_builder_config() {
+        let engine_builder = EngineBuilder::new("MyGame")
+            .with_app_version(3)
+            .with_window_size(1280, 720)
+            .with_log_level(LevelFilter::Warn)
+            .with_resource_mount_point("my_game", "my_game/resource")
+            .with_resource_mount_point("my_dlc", "my_dlc/resource");
+        let engine_config = &engine_builder._engine_config;
+        assert_eq!("MyGame", engine_config._app_name);
+        assert_eq!(3, engine_config._app_version);
+        assert_eq!(WindowMode::Windowed { _width: 1280, _height: 720 }, engine_config._window_config._window_mode);
+        assert_eq!(LevelFilter::Warn, engine_config._log_level);
+        assert_eq!(vec![
+            (String::from("my_game"), PathBuf::from("my_game/resource")),
+            (String::from("my_dlc"), PathBuf::from("my_dlc/resource")),
+        ], engine_config._resource_mount_points);
+
+        // with_engine_config replaces the whole config
+        let engine_builder = engine_builder.with_engine_config(EngineConfig::default());
+        assert_eq!("RustEngine3D", engine_builder._engine_config._app_name);
+        assert!(engine_builder._engine_config._resource_mount_points.is_empty());
+    }
+
+    #[test]
+    fn test_render_extension_registration_order() {
+        let render_extensions: Vec<TestRenderExtension> = ["render_pass_outline", "render_pass_decal", "render_pass_debug"].iter().map(|render_pass_name| {
+            TestRenderExtension { _render_pass_name: String::from(*render_pass_name) }
+        }).collect();
+        let mut engine_builder = EngineBuilder::new("MyGame");
+        for render_extension in render_extensions.iter() {
+            engine_builder = engine_builder.with_render_extension(render_extension);
+        }
+        assert_eq!(render_extensions.len(), engine_builder._render_extensions.len());
+        for (registered_render_extension, render_extension) in engine_builder._render_extensions.iter().zip(render_extensions.iter()) {
+            assert!(is_same_render_extension(*registered_render_extension, render_extension), "{}", render_extension._render_pass_name);
+        }
+    }
+
+    #[test]
+    fn test_engine_builder_errors() {
+        let scene_logic_application = create_scene_logic_application();
+        let application: *const dyn ApplicationBase = &scene_logic_application;
+        let engine_builder = EngineBuilder::new("MyGame")
+            .with_application(application)
+            .with_scene_logic(Box::new(DefaultSceneLogic));
+        assert_eq!("EngineBuilder: with_application and with_scene_logic are exclusive", engine_builder.build().err().unwrap());
+
+        // the project parts are required, the default binary passes the ones of base_project
+        let engine_builder = EngineBuilder::new("MyGame").with_scene_logic(Box::new(DefaultSceneLogic));
+        assert_eq!("EngineBuilder: project resources are missing", engine_builder.build().err().unwrap());
+    }
+
+    #[test]
+    fn test_resource_mount_order() {
+        let test_directory = std::env::temp_dir().join(format!("engine_{}_{}", "resource_mount_order", std::process::id()));
+        for directory in ["my_game", "my_dlc", "my_mod"].iter() {
+            fs::create_dir_all(test_directory.join(directory)).unwrap();
+        }
+        let vfs_settings_file_path = test_directory.join("vfs_settings.json");
+        let vfs_settings = format!(r#"{{ "mount_points": [{{ "name": "my_mod", "path": {:?} }}] }}"#, test_directory.join("my_mod"));
+        fs::write(&vfs_settings_file_path, vfs_settings).unwrap();
+
+        let engine_config = EngineConfig {
+            _resource_mount_points: vec![
+                (String::from("my_game"), test_directory.join("my_game")),
+                (String::from("my_dlc"), test_directory.join("my_dlc")),
+                (String::from("not_exists"), test_directory.join("not_exists")),
+            ],
+            ..Default::default()
+        };
+        let mut virtual_file_system = VirtualFileSystem::create_virtual_file_system();
+        engine_config.mount_resource_mount_points(&mut virtual_file_system);
+        virtual_file_system.load_settings(&vfs_settings_file_path);
+
+        // lowest priority first: the engine layer, the mount points of EngineConfig, then vfs_settings.json
+        let layer_names: Vec<&str> = virtual_file_system.get_mount_points().iter().map(|mount_point| mount_point._layer_name.as_str()).collect();
+        assert_eq!(vec!["engine", "my_game", "my_dlc", "my_mod"], layer_names);
+        assert_eq!(test_directory.join("my_mod"), virtual_file_system.get_writable_mount_point()._root_path);
+        fs::remove_dir_all(&test_directory).unwrap();
+    }
+}
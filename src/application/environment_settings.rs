@@ -0,0 +1,366 @@
+use nalgebra::{ Vector2, Vector3, Vector4 };
+use serde::{ Serialize, Deserialize };
+use serde_json::{ self, Value };
+
+use crate::application::scene_manager::SceneManagerData;
+use crate::application::time_of_day::TimeOfDaySettings;
+use crate::application::wind_system::WindSettings;
+use crate::renderer::renderer::RendererData;
+use crate::renderer::terrain::TerrainSettings;
+use crate::utilities::math;
+
+pub const ENVIRONMENT_SETTINGS_KEY: &str = "environment_settings";
+pub const OCEAN_COMMAND: &str = "ocean";
+pub const OCEAN_WIND_SPEED_STEP: f32 = 1.0; // m/s, PageUp / PageDown
+
+#[derive(Serialize, Deserialize, Clone, Debug, Copy, PartialEq, Eq)]
+pub enum SkyMode {
+    Atmosphere,
+    Cubemap, // skybox::SkyboxData, the cubemap is the source of the light probe too
+    SolidColor,
+    None, // no sky is drawn, the light probe keeps the atmosphere
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Copy, PartialEq, Eq)]
+pub enum ExposureMode {
+    Auto,
+    Manual,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct SkySettings {
+    pub _sky_mode: SkyMode,
+    pub _sky_cubemap: String, // texture name for SkyMode::Cubemap
+    pub _sky_color: Vector3<f32>, // for SkyMode::SolidColor
+    pub _sky_intensity: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct FogSettings {
+    pub _enable_fog: bool,
+    pub _fog_color: Vector3<f32>,
+    pub _fog_density: f32,
+    pub _fog_start_distance: f32,
+    pub _fog_height_falloff: f32,
+    pub _fog_anisotropy: f32, // the henyey-greenstein g of the volumetric fog, 0: isotropic, toward 1: the light shafts around the sun
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct AmbientSettings {
+    pub _ambient_color: Vector3<f32>,
+    pub _ambient_intensity: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct PostProcessSettings {
+    pub _bloom_intensity: f32,
+    pub _exposure_mode: ExposureMode,
+    pub _exposure_value: f32, // for ExposureMode::Manual
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct OceanSettings {
+    pub _enable_ocean: bool,
+    pub _ocean_height: f32,
+    pub _wind_direction: Vector2<f32>,
+    pub _wind_speed: f32,
+    // the spectrum parameters, the project regenerates the spectrum textures when they are changed
+    pub _omega: f32, // inverse wave age
+    pub _amplitude: f32,
+    pub _choppy_factors: Vector4<f32>, // per grid
+    pub _simulation_wind: f32,
+    pub _simulation_amplitude: f32,
+    pub _simulation_scale: f32, // scales the grid sizes
+    pub _grid_sizes: Vector4<f32>, // meters of the 4 fft grids
+    pub _fft_seed: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct AtmosphereSettings {
+    pub _sun_pitch: f32, // degree
+    pub _sun_yaw: f32, // degree
+    pub _cloud_coverage: f32,
+    pub _cloud_density: f32,
+    pub _cloud_height: f32,
+    pub _cloud_speed: f32,
+    pub _use_combined_textures: bool, // false: the single mie scattering has its own texture, see renderer::atmosphere
+    pub _scattering_orders: u32, // 1: the single scattering only
+}
+
+// "environment_settings" block of the scene, the missing fields are the engine defaults.
+// ex) "environment_settings": { "_fog": { "_enable_fog": true }, "_time_of_day": 18.5 }
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+pub struct EnvironmentSettings {
+    pub _sky: SkySettings,
+    pub _fog: FogSettings,
+    pub _ambient: AmbientSettings,
+    pub _post_process: PostProcessSettings,
+    pub _ocean: OceanSettings,
+    pub _atmosphere: AtmosphereSettings,
+    pub _wind: WindSettings,
+    pub _time_of_day: f32, // hour
+    pub _time_of_day_settings: TimeOfDaySettings, // the sun of the hour drives the atmosphere and the main light
+    pub _terrain: TerrainSettings,
+}
+
+impl Default for SkySettings {
+    fn default() -> SkySettings {
+        SkySettings {
+            _sky_mode: SkyMode::Atmosphere,
+            _sky_cubemap: String::new(),
+            _sky_color: Vector3::new(0.5, 0.7, 1.0),
+            _sky_intensity: 1.0,
+        }
+    }
+}
+
+impl Default for FogSettings {
+    fn default() -> FogSettings {
+        FogSettings {
+            _enable_fog: false,
+            _fog_color: Vector3::new(0.6, 0.7, 0.8),
+            _fog_density: 0.01,
+            _fog_start_distance: 0.0,
+            _fog_height_falloff: 0.1,
+            _fog_anisotropy: 0.6,
+        }
+    }
+}
+
+impl Default for AmbientSettings {
+    fn default() -> AmbientSettings {
+        AmbientSettings {
+            _ambient_color: Vector3::new(1.0, 1.0, 1.0),
+            _ambient_intensity: 1.0,
+        }
+    }
+}
+
+impl Default for PostProcessSettings {
+    fn default() -> PostProcessSettings {
+        PostProcessSettings {
+            _bloom_intensity: 0.25,
+            _exposure_mode: ExposureMode::Auto,
+            _exposure_value: 1.0,
+        }
+    }
+}
+
+impl Default for OceanSettings {
+    fn default() -> OceanSettings {
+        OceanSettings {
+            _enable_ocean: false,
+            _ocean_height: 0.0,
+            _wind_direction: Vector2::new(1.0, 0.0),
+            _wind_speed: 10.0,
+            _omega: 0.84,
+            _amplitude: 0.5,
+            _choppy_factors: Vector4::new(2.3, 2.1, 1.3, 0.9),
+            _simulation_wind: 1.0,
+            _simulation_amplitude: 3.0,
+            _simulation_scale: 1.0,
+            _grid_sizes: Vector4::new(5488.0, 392.0, 28.0, 2.0),
+            _fft_seed: 1234,
+        }
+    }
+}
+
+impl OceanSettings {
+    // the height and the visibility are uniforms, the others are baked into the spectrum textures and the slope variance.
+    pub fn is_spectrum_changed(&self, other: &OceanSettings) -> bool {
+        self._wind_direction != other._wind_direction ||
+            self._wind_speed != other._wind_speed ||
+            self._omega != other._omega ||
+            self._amplitude != other._amplitude ||
+            self._choppy_factors != other._choppy_factors ||
+            self._simulation_wind != other._simulation_wind ||
+            self._simulation_amplitude != other._simulation_amplitude ||
+            self._simulation_scale != other._simulation_scale ||
+            self._grid_sizes != other._grid_sizes ||
+            self._fft_seed != other._fft_seed
+    }
+
+    // the sea state of the spectrum, the spectrum textures are updated in place. see ProjectRendererBase::update_ocean_spectrum
+    pub fn set_sea_state(&mut self, wind_speed: f32, omega: f32, amplitude: f32) {
+        self._wind_speed = wind_speed.max(0.0);
+        self._omega = omega.max(0.0);
+        self._amplitude = amplitude.max(0.0);
+    }
+
+    pub fn get_simulation_sizes(&self) -> Vector4<f32> {
+        self._grid_sizes * self._simulation_scale
+    }
+
+    // ex) ["wind", "12.5"], ["choppy", "2.3", "2.1", "1.3", "0.9"], ["enable", "true"]
+    pub fn set_ocean_parameter(&mut self, args: &[&str]) -> Result<(), String> {
+        let parameter_name = args.first().ok_or(String::from("ocean parameter name is missing"))?;
+        let values: Vec<f32> = args[1..].iter().map(|arg| match *arg {
+            "true" | "on" => Ok(1.0),
+            "false" | "off" => Ok(0.0),
+            _ => arg.parse::<f32>().map_err(|_| format!("invalid ocean parameter value: {}", arg)),
+        }).collect::<Result<Vec<f32>, String>>()?;
+        let value = |count: usize| -> Result<Vec<f32>, String> {
+            if count == values.len() {
+                Ok(values.clone())
+            } else {
+                Err(format!("ocean {} needs {} value(s), but {} given", parameter_name, count, values.len()))
+            }
+        };
+        match *parameter_name {
+            "enable" => self._enable_ocean = 0.0 != value(1)?[0],
+            "height" => self._ocean_height = value(1)?[0],
+            "wind" => self._wind_speed = value(1)?[0].max(0.0),
+            "wind_direction" => self._wind_direction = Vector2::from_column_slice(&value(2)?).try_normalize(0.0).unwrap_or(Vector2::new(1.0, 0.0)),
+            "omega" => self._omega = value(1)?[0].max(0.0),
+            "amplitude" => self._amplitude = value(1)?[0].max(0.0),
+            "choppy" => self._choppy_factors = Vector4::from_column_slice(&value(4)?),
+            "simulation_wind" => self._simulation_wind = value(1)?[0].max(0.0),
+            "simulation_amplitude" => self._simulation_amplitude = value(1)?[0].max(0.0),
+            "simulation_scale" => self._simulation_scale = value(1)?[0].max(std::f32::EPSILON),
+            "grid" => self._grid_sizes = Vector4::from_column_slice(&value(4)?),
+            "seed" => self._fft_seed = value(1)?[0] as u32,
+            _ => return Err(format!("unknown ocean parameter: {}", parameter_name)),
+        }
+        Ok(())
+    }
+}
+
+impl Default for AtmosphereSettings {
+    fn default() -> AtmosphereSettings {
+        AtmosphereSettings {
+            _sun_pitch: -45.0,
+            _sun_yaw: 0.0,
+            _cloud_coverage: 0.5,
+            _cloud_density: 0.5,
+            _cloud_height: 1500.0,
+            _cloud_speed: 1.0,
+            _use_combined_textures: true,
+            _scattering_orders: 4,
+        }
+    }
+}
+
+impl AtmosphereSettings {
+    pub fn is_same_precompute(&self, other: &AtmosphereSettings) -> bool {
+        self._use_combined_textures == other._use_combined_textures && self._scattering_orders == other._scattering_orders
+    }
+
+    // toward the sun, the z axis of the sun rotation
+    pub fn get_sun_direction(&self) -> Vector3<f32> {
+        let rotation_matrix = math::make_rotation_matrix(math::degree_to_radian(self._sun_pitch), math::degree_to_radian(self._sun_yaw), 0.0);
+        Vector3::new(rotation_matrix.m13, rotation_matrix.m23, rotation_matrix.m33).normalize()
+    }
+}
+
+impl EnvironmentSettings {
+    // scene_data: the whole scene contents, a scene without the block gets the engine defaults.
+    pub fn create_environment_settings(scene_data: &Value) -> EnvironmentSettings {
+        match scene_data.get(ENVIRONMENT_SETTINGS_KEY) {
+            Some(environment_settings) => match serde_json::from_value::<EnvironmentSettings>(environment_settings.clone()) {
+                Ok(environment_settings) => environment_settings,
+                Err(e) => {
+                    log::error!("create_environment_settings error: {:?}", e);
+                    EnvironmentSettings::default()
+                }
+            },
+            None => EnvironmentSettings::default(),
+        }
+    }
+
+    pub fn write_environment_settings(&self, scene_data: &mut Value) {
+        if let Value::Object(scene_data) = scene_data {
+            scene_data.insert(String::from(ENVIRONMENT_SETTINGS_KEY), serde_json::to_value(self).unwrap());
+        }
+    }
+}
+
+// ex) "ocean wind 12.5", "ocean choppy 2.3 2.1 1.3 0.9", returns false for the other commands.
+pub fn execute_ocean_command(scene_manager_data: &mut SceneManagerData, renderer_data: &mut RendererData, command: &str) -> bool {
+    let args: Vec<&str> = command.split_whitespace().collect();
+    if Some(&OCEAN_COMMAND) != args.first() {
+        return false;
+    }
+    let mut environment_settings = scene_manager_data.get_environment_settings().clone();
+    if let Err(e) = environment_settings._ocean.set_ocean_parameter(&args[1..]) {
+        log::error!("execute_ocean_command: {}", e);
+        return true;
+    }
+    let is_spectrum_changed = environment_settings._ocean.is_spectrum_changed(&scene_manager_data.get_environment_settings()._ocean);
+    scene_manager_data.set_environment_settings(&environment_settings);
+    if is_spectrum_changed {
+        renderer_data.request_ocean_spectrum_update(&environment_settings._ocean);
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_environment_settings_round_trip() {
+        let mut environment_settings = EnvironmentSettings::default();
+        environment_settings._sky._sky_mode = SkyMode::Cubemap;
+        environment_settings._sky._sky_cubemap = String::from("cubemaps/sky");
+        environment_settings._fog._enable_fog = true;
+        environment_settings._fog._fog_color = Vector3::new(0.1, 0.2, 0.3);
+        environment_settings._ambient._ambient_intensity = 0.5;
+        environment_settings._post_process._exposure_mode = ExposureMode::Manual;
+        environment_settings._post_process._exposure_value = 2.5;
+        environment_settings._ocean._enable_ocean = true;
+        environment_settings._ocean._ocean_height = -3.0;
+        environment_settings._ocean._wind_direction = Vector2::new(0.0, 1.0);
+        environment_settings._atmosphere._sun_pitch = -10.0;
+        environment_settings._atmosphere._cloud_coverage = 0.8;
+        environment_settings._time_of_day = 18.5;
+
+        // the other contents of the scene are kept
+        let mut scene_data = json!({ "_cameras": {}, "_static_objects": {} });
+        environment_settings.write_environment_settings(&mut scene_data);
+        assert!(scene_data.get("_cameras").is_some());
+        assert!(scene_data.get(ENVIRONMENT_SETTINGS_KEY).is_some());
+        assert_eq!(environment_settings, EnvironmentSettings::create_environment_settings(&scene_data));
+
+        // through the scene file contents
+        let contents = serde_json::to_string_pretty(&scene_data).unwrap();
+        let loaded_scene_data: Value = serde_json::from_str(&contents).unwrap();
+        assert_eq!(environment_settings, EnvironmentSettings::create_environment_settings(&loaded_scene_data));
+    }
+
+    #[test]
+    fn test_missing_environment_settings() {
+        assert_eq!(EnvironmentSettings::default(), EnvironmentSettings::create_environment_settings(&json!({ "_cameras": {} })));
+        assert_eq!(EnvironmentSettings::default(), EnvironmentSettings::create_environment_settings(&json!({ ENVIRONMENT_SETTINGS_KEY: {} })));
+        // the invalid block falls back to the engine defaults
+        assert_eq!(EnvironmentSettings::default(), EnvironmentSettings::create_environment_settings(&json!({ ENVIRONMENT_SETTINGS_KEY: { "_sky": { "_sky_mode": "Unknown" } } })));
+    }
+
+    #[test]
+    fn test_partial_environment_settings() {
+        let scene_data = json!({
+            ENVIRONMENT_SETTINGS_KEY: {
+                "_fog": { "_enable_fog": true },
+                "_post_process": { "_bloom_intensity": 0.5 },
+                "_time_of_day": 6.0
+            }
+        });
+        let environment_settings = EnvironmentSettings::create_environment_settings(&scene_data);
+        let mut expected = EnvironmentSettings::default();
+        expected._fog._enable_fog = true;
+        expected._post_process._bloom_intensity = 0.5;
+        expected._time_of_day = 6.0;
+        assert_eq!(expected, environment_settings);
+        // only the listed fields are overridden
+        assert_eq!(FogSettings::default()._fog_density, environment_settings._fog._fog_density);
+        assert_eq!(ExposureMode::Auto, environment_settings._post_process._exposure_mode);
+        assert_eq!(SkySettings::default(), environment_settings._sky);
+    }
+}
@@ -0,0 +1,451 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use serde::{ Serialize, Deserialize };
+
+use crate::constants;
+use crate::resource::resource::Resources;
+
+// ex) resource/rumble_patterns.json, the missing patterns are the engine defaults of get_default_rumble_patterns.
+// [
+//     { "_name": "landing", "_keys": [{ "_time": 0.0, "_low_frequency": 1.0, "_high_frequency": 0.3 }, { "_time": 0.25, "_low_frequency": 0.0, "_high_frequency": 0.0 }] }
+// ]
+pub const RUMBLE_PATTERNS_FILE_PATH: &str = "resource/rumble_patterns.json";
+pub const RUMBLE_COMMAND: &str = "rumble";
+pub const RUMBLE_TEST_PATTERN_NAME: &str = "test";
+pub const RUMBLE_EPSILON: f32 = 0.01; // the backend is updated when a motor changes more than this
+
+#[derive(Serialize, Deserialize, Clone, Debug, Copy, PartialEq)]
+pub struct RumbleKey {
+    pub _time: f32, // seconds from the start
+    pub _low_frequency: f32, // 0.0 ~ 1.0, the strong motor
+    pub _high_frequency: f32, // 0.0 ~ 1.0, the weak motor
+}
+
+// an envelope of the motor strengths, linear between the keys sorted by the time.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+pub struct RumblePattern {
+    pub _name: String,
+    pub _keys: Vec<RumbleKey>,
+}
+
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Hash)]
+pub enum HapticsEvent {
+    UIButtonClick,
+    ObjectPicked,
+    EffectSpawnedNearPlayer,
+    Landing, // posted by the physics of the project
+}
+
+// the motors of the connected gamepad, the devices without rumble do nothing.
+pub trait HapticsBackend {
+    fn is_rumble_supported(&self) -> bool;
+    fn set_rumble(&mut self, low_frequency: f32, high_frequency: f32);
+}
+
+#[derive(Default)]
+pub struct NullHapticsBackend;
+
+impl HapticsBackend for NullHapticsBackend {
+    fn is_rumble_supported(&self) -> bool { false }
+    fn set_rumble(&mut self, _low_frequency: f32, _high_frequency: f32) {}
+}
+
+#[derive(Clone, Debug)]
+pub struct ActiveRumble {
+    pub _pattern_name: Option<String>, // None: the constant strength of play_rumble
+    pub _low_frequency: f32,
+    pub _high_frequency: f32,
+    pub _duration: f32,
+    pub _elapsed_time: f32,
+    pub _intensity: f32,
+}
+
+// the overlapping rumbles are mixed by the max per motor, then scaled by the HAPTICS_INTENSITY cvar.
+pub struct HapticsManager {
+    pub _backend: Box<dyn HapticsBackend>,
+    pub _rumble_patterns: HashMap<String, RumblePattern>,
+    pub _event_bindings: HashMap<HapticsEvent, String>, // event -> pattern name
+    pub _active_rumbles: Vec<ActiveRumble>,
+    pub _current_rumble: (f32, f32), // low, high frequency sent to the backend
+}
+
+// the events of the ui, the gameplay and the physics, drained by HapticsManager::update_haptics_manager each frame.
+static mut HAPTICS_EVENTS: Vec<(HapticsEvent, f32)> = Vec::new();
+
+// intensity: 0.0 ~ 1.0, ex) by the distance of the spawned effect or the fall speed
+pub fn post_haptics_event(haptics_event: HapticsEvent, intensity: f32) {
+    unsafe { HAPTICS_EVENTS.push((haptics_event, intensity.max(0.0).min(1.0))); }
+}
+
+fn take_haptics_events() -> Vec<(HapticsEvent, f32)> {
+    unsafe { std::mem::replace(&mut HAPTICS_EVENTS, Vec::new()) }
+}
+
+impl RumblePattern {
+    pub fn create_rumble_pattern(pattern_name: &str, keys: &[(f32, f32, f32)]) -> RumblePattern {
+        RumblePattern {
+            _name: String::from(pattern_name),
+            _keys: keys.iter().map(|(time, low_frequency, high_frequency)| RumbleKey {
+                _time: *time,
+                _low_frequency: *low_frequency,
+                _high_frequency: *high_frequency,
+            }).collect(),
+        }
+    }
+
+    pub fn get_duration(&self) -> f32 {
+        self._keys.last().map(|key| key._time).unwrap_or(0.0)
+    }
+
+    // the motor strengths at the time, None after the last key
+    pub fn evaluate(&self, time: f32) -> Option<(f32, f32)> {
+        if self._keys.is_empty() || time < 0.0 || self.get_duration() < time {
+            return None;
+        }
+        let next_index = self._keys.iter().position(|key| time < key._time).unwrap_or(self._keys.len() - 1);
+        if 0 == next_index {
+            let key = &self._keys[0];
+            return Some((key._low_frequency, key._high_frequency));
+        }
+        let prev_key = &self._keys[next_index - 1];
+        let next_key = &self._keys[next_index];
+        let key_duration = next_key._time - prev_key._time;
+        let t = if 0.0 < key_duration { ((time - prev_key._time) / key_duration).min(1.0) } else { 1.0 };
+        Some((
+            prev_key._low_frequency + (next_key._low_frequency - prev_key._low_frequency) * t,
+            prev_key._high_frequency + (next_key._high_frequency - prev_key._high_frequency) * t,
+        ))
+    }
+}
+
+pub fn get_default_rumble_patterns() -> Vec<RumblePattern> {
+    vec![
+        RumblePattern::create_rumble_pattern("ui_click", &[(0.0, 0.0, 0.4), (0.05, 0.0, 0.0)]),
+        RumblePattern::create_rumble_pattern("object_picked", &[(0.0, 0.2, 0.6), (0.1, 0.0, 0.2), (0.15, 0.0, 0.0)]),
+        RumblePattern::create_rumble_pattern("effect_near", &[(0.0, 0.8, 0.5), (0.1, 0.5, 0.3), (0.4, 0.0, 0.0)]),
+        RumblePattern::create_rumble_pattern("landing", &[(0.0, 1.0, 0.3), (0.25, 0.0, 0.0)]),
+        RumblePattern::create_rumble_pattern(RUMBLE_TEST_PATTERN_NAME, &[(0.0, 1.0, 0.0), (0.5, 0.0, 0.0), (0.6, 0.0, 1.0), (1.1, 0.0, 0.0)]),
+    ]
+}
+
+pub fn load_rumble_patterns(resources: &Resources) -> Vec<RumblePattern> {
+    let rumble_patterns_file = PathBuf::from(RUMBLE_PATTERNS_FILE_PATH);
+    if false == resources._resource_filenames.contains(&rumble_patterns_file) && false == resources.resolve_path(&rumble_patterns_file).is_file() {
+        return Vec::new();
+    }
+    match serde_json::from_reader::<_, Vec<RumblePattern>>(resources.read_bytes(&rumble_patterns_file)) {
+        Ok(mut rumble_patterns) => {
+            for rumble_pattern in rumble_patterns.iter_mut() {
+                rumble_pattern._keys.sort_by(|a, b| a._time.partial_cmp(&b._time).unwrap_or(std::cmp::Ordering::Equal));
+            }
+            rumble_patterns
+        },
+        Err(e) => {
+            log::error!("load_rumble_patterns: {:?} {:?}", rumble_patterns_file, e);
+            Vec::new()
+        }
+    }
+}
+
+impl HapticsManager {
+    pub fn create_haptics_manager(backend: Box<dyn HapticsBackend>, resources: &Resources) -> HapticsManager {
+        let mut rumble_patterns: HashMap<String, RumblePattern> = HashMap::new();
+        for rumble_pattern in get_default_rumble_patterns().into_iter().chain(load_rumble_patterns(resources).into_iter()) {
+            rumble_patterns.insert(rumble_pattern._name.clone(), rumble_pattern);
+        }
+        let mut event_bindings: HashMap<HapticsEvent, String> = HashMap::new();
+        event_bindings.insert(HapticsEvent::UIButtonClick, String::from("ui_click"));
+        event_bindings.insert(HapticsEvent::ObjectPicked, String::from("object_picked"));
+        event_bindings.insert(HapticsEvent::EffectSpawnedNearPlayer, String::from("effect_near"));
+        event_bindings.insert(HapticsEvent::Landing, String::from("landing"));
+        log::info!("create_haptics_manager: rumble supported: {}, patterns: {}", backend.is_rumble_supported(), rumble_patterns.len());
+        HapticsManager {
+            _backend: backend,
+            _rumble_patterns: rumble_patterns,
+            _event_bindings: event_bindings,
+            _active_rumbles: Vec::new(),
+            _current_rumble: (0.0, 0.0),
+        }
+    }
+
+    pub fn is_rumble_supported(&self) -> bool {
+        self._backend.is_rumble_supported()
+    }
+
+    pub fn bind_event(&mut self, haptics_event: HapticsEvent, pattern_name: &str) {
+        self._event_bindings.insert(haptics_event, String::from(pattern_name));
+    }
+
+    pub fn unbind_event(&mut self, haptics_event: HapticsEvent) {
+        self._event_bindings.remove(&haptics_event);
+    }
+
+    pub fn regist_rumble_pattern(&mut self, rumble_pattern: RumblePattern) {
+        self._rumble_patterns.insert(rumble_pattern._name.clone(), rumble_pattern);
+    }
+
+    // the constant strength for the duration in seconds, 0.0 ~ 1.0 per motor
+    pub fn play_rumble(&mut self, low_frequency: f32, high_frequency: f32, duration: f32) {
+        self._active_rumbles.push(ActiveRumble {
+            _pattern_name: None,
+            _low_frequency: low_frequency.max(0.0).min(1.0),
+            _high_frequency: high_frequency.max(0.0).min(1.0),
+            _duration: duration.max(0.0),
+            _elapsed_time: 0.0,
+            _intensity: 1.0,
+        });
+    }
+
+    pub fn play_rumble_pattern(&mut self, pattern_name: &str, intensity: f32) {
+        match self._rumble_patterns.get(pattern_name) {
+            Some(rumble_pattern) => {
+                let duration = rumble_pattern.get_duration();
+                self._active_rumbles.push(ActiveRumble {
+                    _pattern_name: Some(String::from(pattern_name)),
+                    _low_frequency: 0.0,
+                    _high_frequency: 0.0,
+                    _duration: duration,
+                    _elapsed_time: 0.0,
+                    _intensity: intensity.max(0.0).min(1.0),
+                });
+            },
+            None => log::warn!("play_rumble_pattern: not found {}", pattern_name),
+        }
+    }
+
+    pub fn stop_rumble(&mut self) {
+        self._active_rumbles.clear();
+        self._current_rumble = (0.0, 0.0);
+        self._backend.set_rumble(0.0, 0.0);
+    }
+
+    fn get_active_rumble_strength(&self, active_rumble: &ActiveRumble) -> (f32, f32) {
+        let (low_frequency, high_frequency) = match active_rumble._pattern_name.as_ref() {
+            Some(pattern_name) => self._rumble_patterns.get(pattern_name).and_then(|rumble_pattern| rumble_pattern.evaluate(active_rumble._elapsed_time)).unwrap_or((0.0, 0.0)),
+            None => (active_rumble._low_frequency, active_rumble._high_frequency),
+        };
+        (low_frequency * active_rumble._intensity, high_frequency * active_rumble._intensity)
+    }
+
+    pub fn update_haptics_manager(&mut self, delta_time: f32) {
+        for (haptics_event, intensity) in take_haptics_events() {
+            if let Some(pattern_name) = self._event_bindings.get(&haptics_event).cloned() {
+                self.play_rumble_pattern(&pattern_name, intensity);
+            }
+        }
+
+        let mut rumble = (0.0f32, 0.0f32);
+        for active_rumble in self._active_rumbles.iter() {
+            let (low_frequency, high_frequency) = self.get_active_rumble_strength(active_rumble);
+            rumble.0 = rumble.0.max(low_frequency);
+            rumble.1 = rumble.1.max(high_frequency);
+        }
+        for active_rumble in self._active_rumbles.iter_mut() {
+            active_rumble._elapsed_time += delta_time;
+        }
+        self._active_rumbles.retain(|active_rumble| active_rumble._elapsed_time <= active_rumble._duration);
+
+        let haptics_intensity = unsafe { constants::HAPTICS_INTENSITY.max(0.0).min(1.0) };
+        let rumble = (rumble.0 * haptics_intensity, rumble.1 * haptics_intensity);
+        if RUMBLE_EPSILON < (rumble.0 - self._current_rumble.0).abs() || RUMBLE_EPSILON < (rumble.1 - self._current_rumble.1).abs() ||
+            (0.0 == rumble.0 && 0.0 == rumble.1 && rumble != self._current_rumble) {
+            self._current_rumble = rumble;
+            if self._backend.is_rumble_supported() {
+                self._backend.set_rumble(rumble.0, rumble.1);
+            }
+        }
+    }
+}
+
+// ex) "rumble test", "rumble play 1.0 0.5 0.3", "rumble pattern landing", "rumble intensity 0.5", "rumble stop", returns false for the other commands.
+pub fn execute_rumble_command(haptics_manager: &mut HapticsManager, command: &str) -> bool {
+    let args: Vec<&str> = command.split_whitespace().collect();
+    if Some(&RUMBLE_COMMAND) != args.first() {
+        return false;
+    }
+    let values: Result<Vec<f32>, String> = args.iter().skip(2).map(|arg| arg.parse::<f32>().map_err(|_| format!("invalid rumble value: {}", arg))).collect();
+    match (args.get(1).cloned(), values) {
+        (Some("test"), _) => {
+            if false == haptics_manager.is_rumble_supported() {
+                log::info!("rumble test: the gamepad has no rumble");
+            }
+            haptics_manager.play_rumble_pattern(RUMBLE_TEST_PATTERN_NAME, 1.0);
+        },
+        (Some("play"), Ok(values)) if 3 == values.len() => haptics_manager.play_rumble(values[0], values[1], values[2]),
+        (Some("pattern"), _) if 3 == args.len() => haptics_manager.play_rumble_pattern(args[2], 1.0),
+        (Some("intensity"), Ok(values)) if 1 == values.len() => unsafe { constants::HAPTICS_INTENSITY = values[0].max(0.0).min(1.0) },
+        (Some("stop"), _) => haptics_manager.stop_rumble(),
+        _ => log::error!("execute_rumble_command: invalid command {:?}", command),
+    }
+    true
+}
+
+// the gilrs force feedback, every connected gamepad with the force feedback gets the same rumble.
+#[cfg(feature = "haptics_gilrs")]
+pub struct GilrsHapticsBackend {
+    pub _gilrs: gilrs::Gilrs,
+    pub _effect: Option<gilrs::ff::Effect>,
+}
+
+#[cfg(feature = "haptics_gilrs")]
+impl GilrsHapticsBackend {
+    pub fn create_gilrs_haptics_backend() -> Option<GilrsHapticsBackend> {
+        match gilrs::Gilrs::new() {
+            Ok(gilrs) => Some(GilrsHapticsBackend { _gilrs: gilrs, _effect: None }),
+            Err(e) => {
+                log::warn!("create_gilrs_haptics_backend: {:?}", e);
+                None
+            }
+        }
+    }
+
+    fn get_ff_gamepad_ids(&self) -> Vec<gilrs::GamepadId> {
+        self._gilrs.gamepads().filter(|(_id, gamepad)| gamepad.is_ff_supported()).map(|(id, _gamepad)| id).collect()
+    }
+}
+
+#[cfg(feature = "haptics_gilrs")]
+impl HapticsBackend for GilrsHapticsBackend {
+    fn is_rumble_supported(&self) -> bool {
+        false == self.get_ff_gamepad_ids().is_empty()
+    }
+
+    // the effect repeats until it is replaced, the manager calls this only when a motor changes.
+    fn set_rumble(&mut self, low_frequency: f32, high_frequency: f32) {
+        use gilrs::ff::{ BaseEffect, BaseEffectType, EffectBuilder, Repeat, Replay, Ticks };
+        self._effect = None;
+        if 0.0 == low_frequency && 0.0 == high_frequency {
+            return;
+        }
+        let gamepad_ids = self.get_ff_gamepad_ids();
+        if gamepad_ids.is_empty() {
+            return;
+        }
+        let scheduling = Replay { play_for: Ticks::from_ms(1000), ..Default::default() };
+        let effect = EffectBuilder::new()
+            .add_effect(BaseEffect { kind: BaseEffectType::Strong { magnitude: (low_frequency * std::u16::MAX as f32) as u16 }, scheduling, ..Default::default() })
+            .add_effect(BaseEffect { kind: BaseEffectType::Weak { magnitude: (high_frequency * std::u16::MAX as f32) as u16 }, scheduling, ..Default::default() })
+            .repeat(Repeat::Infinitely)
+            .gamepads(&gamepad_ids)
+            .finish(&mut self._gilrs);
+        match effect {
+            Ok(effect) => {
+                if let Err(e) = effect.play() {
+                    log::warn!("set_rumble: {:?}", e);
+                }
+                self._effect = Some(effect);
+            },
+            Err(e) => log::warn!("set_rumble: {:?}", e),
+        }
+    }
+}
+
+pub fn create_default_haptics_backend() -> Box<dyn HapticsBackend> {
+    #[cfg(feature = "haptics_gilrs")]
+    {
+        if let Some(gilrs_haptics_backend) = GilrsHapticsBackend::create_gilrs_haptics_backend() {
+            return Box::new(gilrs_haptics_backend);
+        }
+    }
+    Box::new(NullHapticsBackend::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    // the last rumble sent to the gamepad
+    struct TestHapticsBackend {
+        _rumble: Rc<RefCell<(f32, f32)>>,
+    }
+
+    impl HapticsBackend for TestHapticsBackend {
+        fn is_rumble_supported(&self) -> bool { true }
+        fn set_rumble(&mut self, low_frequency: f32, high_frequency: f32) {
+            *self._rumble.borrow_mut() = (low_frequency, high_frequency);
+        }
+    }
+
+    fn create_test_haptics_manager() -> (HapticsManager, Rc<RefCell<(f32, f32)>>) {
+        let rumble = Rc::new(RefCell::new((0.0, 0.0)));
+        let haptics_manager = HapticsManager {
+            _backend: Box::new(TestHapticsBackend { _rumble: rumble.clone() }),
+            _rumble_patterns: get_default_rumble_patterns().into_iter().map(|rumble_pattern| (rumble_pattern._name.clone(), rumble_pattern)).collect(),
+            _event_bindings: HashMap::new(),
+            _active_rumbles: Vec::new(),
+            _current_rumble: (0.0, 0.0),
+        };
+        (haptics_manager, rumble)
+    }
+
+    #[test]
+    fn test_rumble_pattern_evaluate() {
+        let rumble_pattern = RumblePattern::create_rumble_pattern("test", &[(0.0, 1.0, 0.0), (0.5, 0.0, 1.0), (1.0, 0.0, 0.0)]);
+        assert_eq!(1.0, rumble_pattern.get_duration());
+        assert_eq!(Some((1.0, 0.0)), rumble_pattern.evaluate(0.0));
+        assert_eq!(Some((0.5, 0.5)), rumble_pattern.evaluate(0.25));
+        assert_eq!(Some((0.0, 1.0)), rumble_pattern.evaluate(0.5));
+        assert_eq!(Some((0.0, 0.0)), rumble_pattern.evaluate(1.0));
+        assert_eq!(None, rumble_pattern.evaluate(1.5));
+        assert_eq!(None, rumble_pattern.evaluate(-0.1));
+        assert_eq!(None, RumblePattern::default().evaluate(0.0));
+    }
+
+    #[test]
+    fn test_rumble_intensity_clamping() {
+        let (mut haptics_manager, rumble) = create_test_haptics_manager();
+        haptics_manager.play_rumble(2.0, -1.0, 1.0);
+        haptics_manager.update_haptics_manager(0.0);
+        assert_eq!((1.0, 0.0), *rumble.borrow());
+
+        // the intensity of the pattern scales the envelope
+        haptics_manager.stop_rumble();
+        haptics_manager.play_rumble_pattern(RUMBLE_TEST_PATTERN_NAME, 3.0);
+        assert_eq!(1.0, haptics_manager._active_rumbles[0]._intensity);
+        haptics_manager.play_rumble_pattern(RUMBLE_TEST_PATTERN_NAME, -3.0);
+        assert_eq!(0.0, haptics_manager._active_rumbles[1]._intensity);
+
+        // the cvar is clamped, 1.0 is the default
+        assert!(execute_rumble_command(&mut haptics_manager, "rumble intensity 2.0"));
+        assert_eq!(1.0, unsafe { constants::HAPTICS_INTENSITY });
+    }
+
+    #[test]
+    fn test_rumble_max_mixing() {
+        let (mut haptics_manager, rumble) = create_test_haptics_manager();
+        haptics_manager.play_rumble(0.8, 0.2, 1.0);
+        haptics_manager.play_rumble(0.3, 0.6, 1.0);
+        haptics_manager.update_haptics_manager(0.0);
+        assert_eq!((0.8, 0.6), *rumble.borrow());
+    }
+
+    #[test]
+    fn test_rumble_duration_expiry() {
+        let (mut haptics_manager, rumble) = create_test_haptics_manager();
+        haptics_manager.play_rumble(0.5, 0.5, 0.2);
+        haptics_manager.update_haptics_manager(0.1);
+        assert_eq!(1, haptics_manager._active_rumbles.len());
+        assert_eq!((0.5, 0.5), *rumble.borrow());
+        haptics_manager.update_haptics_manager(0.15);
+        assert!(haptics_manager._active_rumbles.is_empty());
+
+        // the motors are stopped in the next update after the expiry
+        haptics_manager.update_haptics_manager(0.1);
+        assert_eq!((0.0, 0.0), *rumble.borrow());
+    }
+
+    #[test]
+    fn test_rumble_test_command() {
+        let (mut haptics_manager, _rumble) = create_test_haptics_manager();
+        assert!(false == execute_rumble_command(&mut haptics_manager, "wind speed 5"));
+        assert!(execute_rumble_command(&mut haptics_manager, "rumble test"));
+        assert_eq!(1, haptics_manager._active_rumbles.len());
+        assert_eq!(Some(String::from(RUMBLE_TEST_PATTERN_NAME)), haptics_manager._active_rumbles[0]._pattern_name);
+        assert!(execute_rumble_command(&mut haptics_manager, "rumble stop"));
+        assert!(haptics_manager._active_rumbles.is_empty());
+    }
+}
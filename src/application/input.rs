@@ -3,9 +3,17 @@ use std::collections::HashMap;
 use winit::event::{
     VirtualKeyCode,
 };
-use nalgebra::{ Vector2 };
+use nalgebra::{ Vector2, Vector3 };
+
+use crate::constants;
 
 pub type KeyMap = HashMap<VirtualKeyCode, bool>;
+pub type KeyTimeMap = HashMap<VirtualKeyCode, f32>;
+pub type GamepadButtonMap = HashMap<GamepadButton, bool>;
+
+pub const GAMEPAD_TRIGGER_THRESHOLD: f32 = 0.5; // the trigger as a button
+pub const CAMERA_LOOK_SPEED: f32 = 0.003; // radian per pixel of the look delta
+pub const CAMERA_MAX_PITCH: f32 = 1.5; // radian, short of the poles
 
 #[derive(Clone, Debug)]
 pub struct KeyboardInputData {
@@ -19,6 +27,10 @@ pub struct KeyboardInputData {
     pub _modifier_keys_control: bool,
     pub _modifier_keys_alt: bool,
     pub _modifier_keys_super: bool,
+    pub _key_hold_time_map: KeyTimeMap, // seconds since the first press, without the repeats of the os
+    pub _key_repeat_map: KeyMap, // the first press and the repeats of this frame
+    pub _chars: Vec<char>, // ReceivedCharacter of this frame without the control characters
+    pub _is_text_input_focused: bool, // the keys are hidden from the camera and the hotkeys while typing
 }
 
 #[derive(Clone, Debug)]
@@ -26,6 +38,63 @@ pub struct MouseMoveData {
     pub _mouse_pos: Vector2<i32>,
     pub _mouse_pos_delta: Vector2<i32>,
     pub _scroll_delta: Vector2<i32>,
+    pub _is_touch_input: bool, // the delta comes from the touch, see get_look_delta
+}
+
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Hash)]
+pub enum GamepadButton {
+    South, // A, Cross
+    East, // B, Circle
+    North, // Y, Triangle
+    West, // X, Square
+    LeftShoulder,
+    RightShoulder,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    Mode,
+    LeftThumb,
+    RightThumb,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+// the first connected gamepad, polled once per frame by update_gamepad_input.
+// without the gamepad_gilrs feature or a gamepad, the sticks and the triggers stay zero.
+pub struct GamepadInputData {
+    #[cfg(feature = "gamepad_gilrs")]
+    pub _gilrs: Option<gilrs::Gilrs>,
+    #[cfg(feature = "gamepad_gilrs")]
+    pub _active_gamepad_id: Option<gilrs::GamepadId>,
+    pub _left_stick: Vector2<f32>, // raw [-1, 1], y is up
+    pub _right_stick: Vector2<f32>,
+    pub _left_trigger: f32, // [0, 1]
+    pub _right_trigger: f32,
+    pub _button_pressed_map: GamepadButtonMap,
+    pub _button_hold_map: GamepadButtonMap,
+    pub _button_released_map: GamepadButtonMap,
+}
+
+#[derive(Clone, Debug, Copy, PartialEq, Eq)]
+pub enum DeadZoneShape {
+    Radial, // the length of the stick, keeps the direction
+    PerAxis, // each axis separately, snaps to the axes near the center
+}
+
+// snapshot of the input cvars
+#[derive(Clone, Debug, PartialEq)]
+pub struct InputSettings {
+    pub _mouse_sensitivity: Vector2<f32>,
+    pub _mouse_invert_y: bool,
+    pub _touch_look_sensitivity: f32,
+    pub _gamepad_invert_y: bool,
+    pub _gamepad_dead_zone: f32,
+    pub _gamepad_dead_zone_shape: DeadZoneShape,
+    pub _gamepad_response_exponent: f32,
+    pub _gamepad_look_sensitivity: f32,
 }
 
 #[derive(Clone, Debug)]
@@ -69,6 +138,10 @@ pub fn create_keyboard_input_data() -> Box<KeyboardInputData> {
         _modifier_keys_control: false,
         _modifier_keys_alt: false,
         _modifier_keys_super: false,
+        _key_hold_time_map: KeyTimeMap::new(),
+        _key_repeat_map: KeyMap::new(),
+        _chars: Vec::new(),
+        _is_text_input_focused: false,
     })
 }
 
@@ -78,6 +151,7 @@ pub fn create_mouse_move_data(width: i32, height: i32) -> Box<MouseMoveData> {
         _mouse_pos: mouse_pos.clone(),
         _mouse_pos_delta: Vector2::new(0, 0),
         _scroll_delta: Vector2::new(0, 0),
+        _is_touch_input: false,
     })
 }
 
@@ -85,6 +159,196 @@ pub fn create_mouse_input_data() -> Box<MouseInputData> {
     Box::new(MouseInputData::default())
 }
 
+pub fn create_gamepad_input_data() -> Box<GamepadInputData> {
+    Box::new(GamepadInputData {
+        #[cfg(feature = "gamepad_gilrs")]
+        _gilrs: match gilrs::Gilrs::new() {
+            Ok(gilrs) => Some(gilrs),
+            Err(e) => {
+                log::warn!("create_gamepad_input_data: {:?}", e);
+                None
+            }
+        },
+        #[cfg(feature = "gamepad_gilrs")]
+        _active_gamepad_id: None,
+        _left_stick: Vector2::zeros(),
+        _right_stick: Vector2::zeros(),
+        _left_trigger: 0.0,
+        _right_trigger: 0.0,
+        _button_pressed_map: GamepadButtonMap::new(),
+        _button_hold_map: GamepadButtonMap::new(),
+        _button_released_map: GamepadButtonMap::new(),
+    })
+}
+
+#[cfg(feature = "gamepad_gilrs")]
+fn get_gamepad_button(button: gilrs::Button) -> Option<GamepadButton> {
+    match button {
+        gilrs::Button::South => Some(GamepadButton::South),
+        gilrs::Button::East => Some(GamepadButton::East),
+        gilrs::Button::North => Some(GamepadButton::North),
+        gilrs::Button::West => Some(GamepadButton::West),
+        gilrs::Button::LeftTrigger => Some(GamepadButton::LeftShoulder),
+        gilrs::Button::RightTrigger => Some(GamepadButton::RightShoulder),
+        gilrs::Button::LeftTrigger2 => Some(GamepadButton::LeftTrigger),
+        gilrs::Button::RightTrigger2 => Some(GamepadButton::RightTrigger),
+        gilrs::Button::Select => Some(GamepadButton::Select),
+        gilrs::Button::Start => Some(GamepadButton::Start),
+        gilrs::Button::Mode => Some(GamepadButton::Mode),
+        gilrs::Button::LeftThumb => Some(GamepadButton::LeftThumb),
+        gilrs::Button::RightThumb => Some(GamepadButton::RightThumb),
+        gilrs::Button::DPadUp => Some(GamepadButton::DPadUp),
+        gilrs::Button::DPadDown => Some(GamepadButton::DPadDown),
+        gilrs::Button::DPadLeft => Some(GamepadButton::DPadLeft),
+        gilrs::Button::DPadRight => Some(GamepadButton::DPadRight),
+        _ => None,
+    }
+}
+
+impl GamepadInputData {
+    #[cfg(feature = "gamepad_gilrs")]
+    pub fn is_connected(&self) -> bool {
+        self._active_gamepad_id.is_some()
+    }
+
+    #[cfg(not(feature = "gamepad_gilrs"))]
+    pub fn is_connected(&self) -> bool {
+        false
+    }
+
+    // the held buttons are released, ex) the gamepad is disconnected
+    pub fn reset_gamepad_state(&mut self) {
+        for (button, hold) in self._button_hold_map.iter() {
+            if *hold {
+                self._button_released_map.insert(*button, true);
+            }
+        }
+        self._button_hold_map.clear();
+        self._left_stick = Vector2::zeros();
+        self._right_stick = Vector2::zeros();
+        self._left_trigger = 0.0;
+        self._right_trigger = 0.0;
+    }
+
+    pub fn set_button_pressed(&mut self, button: GamepadButton) {
+        self._button_pressed_map.insert(button, true);
+        self._button_hold_map.insert(button, true);
+    }
+
+    pub fn set_button_released(&mut self, button: GamepadButton) {
+        self._button_released_map.insert(button, true);
+        self._button_hold_map.insert(button, false);
+    }
+
+    // Event::MainEventsCleared before update_event. the gamepad can be connected or disconnected at any time,
+    // a disconnected gamepad is never queried.
+    pub fn update_gamepad_input(&mut self) {
+        self._button_pressed_map.clear();
+        self._button_released_map.clear();
+
+        #[cfg(feature = "gamepad_gilrs")]
+        {
+            let mut events: Vec<gilrs::Event> = Vec::new();
+            match self._gilrs.as_mut() {
+                Some(gilrs) => while let Some(event) = gilrs.next_event() {
+                    events.push(event);
+                },
+                None => return,
+            }
+
+            for gilrs::Event { id, event, .. } in events {
+                match event {
+                    gilrs::EventType::Connected => {
+                        log::info!("gamepad connected: {:?}", id);
+                        if self._active_gamepad_id.is_none() {
+                            self._active_gamepad_id = Some(id);
+                        }
+                    },
+                    gilrs::EventType::Disconnected => {
+                        log::info!("gamepad disconnected: {:?}", id);
+                        if Some(id) == self._active_gamepad_id {
+                            self._active_gamepad_id = None;
+                            self.reset_gamepad_state();
+                        }
+                    },
+                    gilrs::EventType::ButtonPressed(button, _) if Some(id) == self._active_gamepad_id => {
+                        if let Some(button) = get_gamepad_button(button) {
+                            self.set_button_pressed(button);
+                        }
+                    },
+                    gilrs::EventType::ButtonReleased(button, _) if Some(id) == self._active_gamepad_id => {
+                        if let Some(button) = get_gamepad_button(button) {
+                            self.set_button_released(button);
+                        }
+                    },
+                    _ => {},
+                }
+            }
+
+            let gilrs = self._gilrs.as_ref().unwrap();
+
+            // the gamepad connected before the start, or the next one after a disconnection
+            if self._active_gamepad_id.is_none() {
+                self._active_gamepad_id = gilrs.gamepads().map(|(id, _gamepad)| id).next();
+            }
+
+            let gamepad = match self._active_gamepad_id.and_then(|id| gilrs.connected_gamepad(id)) {
+                Some(gamepad) => gamepad,
+                None => return,
+            };
+            let get_trigger = |button: gilrs::Button| -> f32 {
+                gamepad.button_data(button).map(|button_data| button_data.value()).unwrap_or(0.0)
+            };
+            self._left_stick = Vector2::new(gamepad.value(gilrs::Axis::LeftStickX), gamepad.value(gilrs::Axis::LeftStickY));
+            self._right_stick = Vector2::new(gamepad.value(gilrs::Axis::RightStickX), gamepad.value(gilrs::Axis::RightStickY));
+            self._left_trigger = get_trigger(gilrs::Button::LeftTrigger2);
+            self._right_trigger = get_trigger(gilrs::Button::RightTrigger2);
+        }
+    }
+
+    pub fn get_button_pressed(&self, button: GamepadButton) -> bool {
+        match self._button_pressed_map.get(&button) {
+            Some(a) => *a,
+            _ => false
+        }
+    }
+
+    pub fn get_button_hold(&self, button: GamepadButton) -> bool {
+        match self._button_hold_map.get(&button) {
+            Some(a) => *a,
+            _ => false
+        }
+    }
+
+    pub fn get_button_released(&self, button: GamepadButton) -> bool {
+        match self._button_released_map.get(&button) {
+            Some(a) => *a,
+            _ => false
+        }
+    }
+
+    pub fn get_left_stick(&self) -> &Vector2<f32> { &self._left_stick }
+    pub fn get_right_stick(&self) -> &Vector2<f32> { &self._right_stick }
+    pub fn get_left_trigger(&self) -> f32 { self._left_trigger }
+    pub fn get_right_trigger(&self) -> f32 { self._right_trigger }
+
+    // the camera translation of the left stick, x: right, y: forward. scaled by the move speed and the delta time like the keyboard.
+    pub fn get_move_axis(&self) -> Vector2<f32> {
+        let input_settings = InputSettings::get_input_settings();
+        apply_stick_response(&self._left_stick, input_settings._gamepad_dead_zone, input_settings._gamepad_dead_zone_shape, input_settings._gamepad_response_exponent)
+    }
+
+    // the camera rotation of the right stick in the space of the mouse delta, so y is down. scaled by the delta time.
+    pub fn get_look_delta(&self) -> Vector2<f32> {
+        get_gamepad_look_delta(&Vector2::new(self._right_stick.x, -self._right_stick.y))
+    }
+
+    // the speed modifier of the camera, the shift of the keyboard
+    pub fn get_speed_up(&self) -> bool {
+        self.get_button_hold(GamepadButton::LeftThumb) || GAMEPAD_TRIGGER_THRESHOLD < self._right_trigger
+    }
+}
+
 impl MouseMoveData {
     pub fn clear_mouse_move_delta(&mut self) {
         self._mouse_pos_delta.x = 0;
@@ -97,6 +361,21 @@ impl MouseMoveData {
         if pos < 0 { 0 } else if limit_pos <= pos { limit_pos - 1 } else { pos }
     }
 
+    pub fn set_touch_input(&mut self, is_touch_input: bool) {
+        self._is_touch_input = is_touch_input;
+    }
+
+    // camera look delta with the sensitivity and the invert-y of the current settings
+    pub fn get_look_delta(&self) -> Vector2<f32> {
+        let input_settings = InputSettings::get_input_settings();
+        let delta = Vector2::new(self._mouse_pos_delta.x as f32, self._mouse_pos_delta.y as f32);
+        if self._is_touch_input {
+            delta * input_settings._touch_look_sensitivity
+        } else {
+            apply_look_sensitivity(&delta, &input_settings._mouse_sensitivity, input_settings._mouse_invert_y)
+        }
+    }
+
     pub fn update_mouse_pos(&mut self, position: &(i32, i32), window_size: &Vector2<i32>) {
         self._mouse_pos_delta.x += position.0 - self._mouse_pos.x;
         self._mouse_pos_delta.y += position.1 - self._mouse_pos.y;
@@ -148,6 +427,9 @@ impl MouseInputData {
 
 impl KeyboardInputData {
     pub fn get_key_hold(&self, key: VirtualKeyCode) -> bool {
+        if self._is_text_input_focused {
+            return false;
+        }
         match self._key_hold_map.get(&key) {
             Some(a) => *a,
             _ => false
@@ -160,9 +442,13 @@ impl KeyboardInputData {
 
     pub fn clear_key_hold(&mut self) {
         self._key_hold_map.clear();
+        self._key_hold_time_map.clear();
     }
 
     pub fn get_key_pressed(&self, key: VirtualKeyCode) -> bool {
+        if self._is_text_input_focused {
+            return false;
+        }
         match self._key_pressed_map.get(&key) {
             Some(a) => *a,
             _ => false
@@ -172,6 +458,10 @@ impl KeyboardInputData {
     pub fn set_key_pressed(&mut self, key: VirtualKeyCode) {
         self._key_pressed_map.insert(key, true);
         self.set_key_hold(key, true);
+        if false == self._key_hold_time_map.contains_key(&key) {
+            self._key_hold_time_map.insert(key, 0.0);
+            self._key_repeat_map.insert(key, true);
+        }
     }
 
     pub fn clear_key_pressed(&mut self) {
@@ -179,6 +469,9 @@ impl KeyboardInputData {
     }
 
     pub fn get_key_released(&self, key: VirtualKeyCode) -> bool {
+        if self._is_text_input_focused {
+            return false;
+        }
         match self._key_released_map.get(&key) {
             Some(a) => *a,
             _ => false
@@ -188,9 +481,284 @@ impl KeyboardInputData {
     pub fn set_key_released(&mut self, key: VirtualKeyCode) {
         self._key_released_map.insert(key, true);
         self.set_key_hold(key, false);
+        self._key_hold_time_map.remove(&key);
     }
 
     pub fn clear_key_released(&mut self) {
         self._key_released_map.clear();
     }
+
+    // the first press, then every KEY_REPEAT_INTERVAL after KEY_REPEAT_DELAY. not hidden by the text input focus.
+    pub fn get_key_repeat(&self, key: VirtualKeyCode) -> bool {
+        match self._key_repeat_map.get(&key) {
+            Some(a) => *a,
+            _ => false
+        }
+    }
+
+    // the first press, not hidden by the text input focus. ex) the toggle key of the console
+    pub fn get_text_key_pressed(&self, key: VirtualKeyCode) -> bool {
+        match self._key_pressed_map.get(&key) {
+            Some(a) => *a,
+            _ => false
+        }
+    }
+
+    pub fn clear_key_repeat(&mut self) {
+        self._key_repeat_map.clear();
+    }
+
+    pub fn update_key_repeat(&mut self, delta_time: f32) {
+        let (repeat_delay, repeat_interval) = unsafe { (constants::KEY_REPEAT_DELAY, constants::KEY_REPEAT_INTERVAL.max(0.001)) };
+        let get_repeat_count = |hold_time: f32| -> u32 {
+            if hold_time < repeat_delay { 0 } else { ((hold_time - repeat_delay) / repeat_interval) as u32 + 1 }
+        };
+        for (key, hold_time) in self._key_hold_time_map.iter_mut() {
+            let prev_hold_time = *hold_time;
+            *hold_time += delta_time;
+            if get_repeat_count(prev_hold_time) < get_repeat_count(*hold_time) {
+                self._key_repeat_map.insert(*key, true);
+            }
+        }
+    }
+
+    pub fn push_char(&mut self, c: char) {
+        if false == c.is_control() {
+            self._chars.push(c);
+        }
+    }
+
+    pub fn get_chars(&self) -> &[char] {
+        &self._chars
+    }
+
+    pub fn clear_chars(&mut self) {
+        self._chars.clear();
+    }
+
+    pub fn is_text_input_focused(&self) -> bool {
+        self._is_text_input_focused
+    }
+
+    pub fn set_text_input_focused(&mut self, is_text_input_focused: bool) {
+        self._is_text_input_focused = is_text_input_focused;
+    }
+}
+
+// the chars of this frame are inserted at the caret, only the ascii chars of the font.
+// backspace, delete, left, right, home and end with the key repeat. returns true if the text or the caret is changed.
+pub fn edit_text_input(text: &mut String, cursor_index: &mut usize, keyboard_input_data: &KeyboardInputData) -> bool {
+    let prev_cursor_index = *cursor_index;
+    let mut is_text_changed = false;
+    *cursor_index = (*cursor_index).min(text.len());
+    for c in keyboard_input_data.get_chars().iter() {
+        if c.is_ascii() {
+            text.insert(*cursor_index, *c);
+            *cursor_index += 1;
+            is_text_changed = true;
+        }
+    }
+    if keyboard_input_data.get_key_repeat(VirtualKeyCode::Back) && 0 < *cursor_index {
+        *cursor_index -= 1;
+        text.remove(*cursor_index);
+        is_text_changed = true;
+    }
+    if keyboard_input_data.get_key_repeat(VirtualKeyCode::Delete) && *cursor_index < text.len() {
+        text.remove(*cursor_index);
+        is_text_changed = true;
+    }
+    if keyboard_input_data.get_key_repeat(VirtualKeyCode::Left) && 0 < *cursor_index {
+        *cursor_index -= 1;
+    }
+    if keyboard_input_data.get_key_repeat(VirtualKeyCode::Right) && *cursor_index < text.len() {
+        *cursor_index += 1;
+    }
+    if keyboard_input_data.get_key_repeat(VirtualKeyCode::Home) {
+        *cursor_index = 0;
+    }
+    if keyboard_input_data.get_key_repeat(VirtualKeyCode::End) {
+        *cursor_index = text.len();
+    }
+    is_text_changed || prev_cursor_index != *cursor_index
+}
+
+impl InputSettings {
+    pub fn get_input_settings() -> InputSettings {
+        unsafe {
+            InputSettings {
+                _mouse_sensitivity: Vector2::new(constants::MOUSE_SENSITIVITY_X, constants::MOUSE_SENSITIVITY_Y),
+                _mouse_invert_y: constants::MOUSE_INVERT_Y,
+                _touch_look_sensitivity: constants::TOUCH_LOOK_SENSITIVITY,
+                _gamepad_invert_y: constants::GAMEPAD_INVERT_Y,
+                _gamepad_dead_zone: constants::GAMEPAD_DEAD_ZONE,
+                _gamepad_dead_zone_shape: if constants::GAMEPAD_RADIAL_DEAD_ZONE { DeadZoneShape::Radial } else { DeadZoneShape::PerAxis },
+                _gamepad_response_exponent: constants::GAMEPAD_RESPONSE_EXPONENT,
+                _gamepad_look_sensitivity: constants::GAMEPAD_LOOK_SENSITIVITY,
+            }
+        }
+    }
+
+    pub fn set_input_settings(&self) {
+        unsafe {
+            constants::MOUSE_SENSITIVITY_X = self._mouse_sensitivity.x;
+            constants::MOUSE_SENSITIVITY_Y = self._mouse_sensitivity.y;
+            constants::MOUSE_INVERT_Y = self._mouse_invert_y;
+            constants::TOUCH_LOOK_SENSITIVITY = self._touch_look_sensitivity;
+            constants::GAMEPAD_INVERT_Y = self._gamepad_invert_y;
+            constants::GAMEPAD_DEAD_ZONE = self._gamepad_dead_zone;
+            constants::GAMEPAD_RADIAL_DEAD_ZONE = DeadZoneShape::Radial == self._gamepad_dead_zone_shape;
+            constants::GAMEPAD_RESPONSE_EXPONENT = self._gamepad_response_exponent;
+            constants::GAMEPAD_LOOK_SENSITIVITY = self._gamepad_look_sensitivity;
+        }
+    }
+}
+
+// [dead_zone, 1] -> [0, 1]
+pub fn apply_dead_zone(value: f32, dead_zone: f32) -> f32 {
+    let dead_zone = dead_zone.max(0.0).min(0.99);
+    let magnitude = value.abs();
+    if magnitude <= dead_zone {
+        0.0
+    } else {
+        value.signum() * ((magnitude - dead_zone) / (1.0 - dead_zone)).min(1.0)
+    }
+}
+
+pub fn apply_response_curve(value: f32, exponent: f32) -> f32 {
+    value.signum() * value.abs().min(1.0).powf(exponent.max(0.01))
+}
+
+pub fn apply_stick_dead_zone(stick: &Vector2<f32>, dead_zone: f32, dead_zone_shape: DeadZoneShape) -> Vector2<f32> {
+    match dead_zone_shape {
+        DeadZoneShape::Radial => {
+            let magnitude = stick.norm();
+            if magnitude <= std::f32::EPSILON {
+                Vector2::zeros()
+            } else {
+                stick * (apply_dead_zone(magnitude, dead_zone) / magnitude)
+            }
+        },
+        DeadZoneShape::PerAxis => Vector2::new(apply_dead_zone(stick.x, dead_zone), apply_dead_zone(stick.y, dead_zone)),
+    }
+}
+
+// dead zone, then the response curve on the magnitude, so the direction is kept
+pub fn apply_stick_response(stick: &Vector2<f32>, dead_zone: f32, dead_zone_shape: DeadZoneShape, exponent: f32) -> Vector2<f32> {
+    let stick = apply_stick_dead_zone(stick, dead_zone, dead_zone_shape);
+    let magnitude = stick.norm();
+    if magnitude <= std::f32::EPSILON {
+        Vector2::zeros()
+    } else {
+        stick * (apply_response_curve(magnitude.min(1.0), exponent) / magnitude)
+    }
+}
+
+pub fn apply_look_sensitivity(delta: &Vector2<f32>, sensitivity: &Vector2<f32>, invert_y: bool) -> Vector2<f32> {
+    let delta_y = if invert_y { -delta.y } else { delta.y };
+    Vector2::new(delta.x * sensitivity.x, delta_y * sensitivity.y)
+}
+
+// raw stick [-1, 1] -> look delta of the current settings
+pub fn get_gamepad_look_delta(stick: &Vector2<f32>) -> Vector2<f32> {
+    let input_settings = InputSettings::get_input_settings();
+    let stick = apply_stick_response(stick, input_settings._gamepad_dead_zone, input_settings._gamepad_dead_zone_shape, input_settings._gamepad_response_exponent);
+    let sensitivity = Vector2::new(input_settings._gamepad_look_sensitivity, input_settings._gamepad_look_sensitivity);
+    apply_look_sensitivity(&stick, &sensitivity, input_settings._gamepad_invert_y)
+}
+
+// the look delta in the space of the mouse delta, y is down: x turns the yaw, y turns the pitch
+pub fn get_look_rotation(rotation: &Vector3<f32>, look_delta: &Vector2<f32>) -> Vector3<f32> {
+    Vector3::new(
+        (rotation.x - look_delta.y * CAMERA_LOOK_SPEED).clamp(-CAMERA_MAX_PITCH, CAMERA_MAX_PITCH),
+        rotation.y - look_delta.x * CAMERA_LOOK_SPEED,
+        rotation.z
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(expected: f32, value: f32) {
+        assert!((expected - value).abs() < 0.0001, "{} {}", expected, value);
+    }
+
+    #[test]
+    fn test_dead_zone() {
+        assert_eq!(0.0, apply_dead_zone(0.1, 0.2));
+        assert_eq!(0.0, apply_dead_zone(-0.2, 0.2));
+        // rescaled from the edge of the dead zone, no jump
+        assert_near(0.0, apply_dead_zone(0.20001, 0.2));
+        assert_near(0.5, apply_dead_zone(0.6, 0.2));
+        assert_near(-0.5, apply_dead_zone(-0.6, 0.2));
+        assert_eq!(1.0, apply_dead_zone(1.0, 0.2));
+        assert_eq!(1.0, apply_dead_zone(1.5, 0.2));
+        // the dead zone is clamped
+        assert_eq!(0.5, apply_dead_zone(0.5, -1.0));
+        assert_eq!(0.0, apply_dead_zone(0.98, 2.0));
+    }
+
+    #[test]
+    fn test_response_curve() {
+        // the end points stay, the curve bends between them
+        for exponent in [0.5, 1.0, 2.0, 3.0].iter() {
+            assert_eq!(0.0, apply_response_curve(0.0, *exponent));
+            assert_eq!(1.0, apply_response_curve(1.0, *exponent));
+            assert_eq!(-1.0, apply_response_curve(-1.0, *exponent));
+        }
+        assert_near(0.25, apply_response_curve(0.5, 2.0));
+        assert_near(-0.25, apply_response_curve(-0.5, 2.0));
+        assert_near(0.5, apply_response_curve(0.5, 1.0));
+        assert_eq!(1.0, apply_response_curve(2.0, 2.0));
+    }
+
+    #[test]
+    fn test_stick_dead_zone_shape() {
+        let dead_zone = 0.2;
+        // radial: a diagonal inside the circle is dropped, the direction is kept outside
+        let stick = Vector2::new(0.1, 0.1);
+        assert_eq!(Vector2::zeros(), apply_stick_dead_zone(&stick, dead_zone, DeadZoneShape::Radial));
+        let stick = Vector2::new(0.6, 0.0);
+        assert_near(0.5, apply_stick_dead_zone(&stick, dead_zone, DeadZoneShape::Radial).x);
+        let stick = Vector2::new(0.3, 0.4);
+        let radial = apply_stick_dead_zone(&stick, dead_zone, DeadZoneShape::Radial);
+        assert_near(0.375, radial.norm());
+        assert_near(stick.y / stick.x, radial.y / radial.x);
+
+        // per axis: the small axis snaps to zero near the center line
+        let stick = Vector2::new(0.15, 0.6);
+        let per_axis = apply_stick_dead_zone(&stick, dead_zone, DeadZoneShape::PerAxis);
+        assert_eq!(0.0, per_axis.x);
+        assert_near(0.5, per_axis.y);
+        let radial = apply_stick_dead_zone(&stick, dead_zone, DeadZoneShape::Radial);
+        assert!(0.0 < radial.x);
+        // the edge of the dead zone in both shapes
+        assert_near(0.0, apply_stick_dead_zone(&Vector2::new(0.0, -0.20001), dead_zone, DeadZoneShape::Radial).norm());
+        assert_near(0.0, apply_stick_dead_zone(&Vector2::new(0.20001, 0.0), dead_zone, DeadZoneShape::PerAxis).norm());
+    }
+
+    #[test]
+    fn test_stick_response() {
+        let dead_zone = 0.2;
+        let stick = apply_stick_response(&Vector2::new(0.0, 0.6), dead_zone, DeadZoneShape::Radial, 2.0);
+        assert_near(0.0, stick.x);
+        assert_near(0.25, stick.y);
+        // the full deflection of a diagonal keeps the length 1.0 and the direction
+        let stick = apply_stick_response(&Vector2::new(1.0, 1.0), dead_zone, DeadZoneShape::Radial, 2.0);
+        assert_near(1.0, stick.norm());
+        assert_near(stick.x, stick.y);
+        assert_eq!(Vector2::zeros(), apply_stick_response(&Vector2::new(0.1, -0.1), dead_zone, DeadZoneShape::PerAxis, 2.0));
+    }
+
+    #[test]
+    fn test_look_rotation() {
+        let rotation = Vector3::new(0.1, 0.2, 0.3);
+        let look_delta = apply_look_sensitivity(&Vector2::new(10.0, 20.0), &Vector2::new(2.0, 1.0), true);
+        assert_eq!(Vector2::new(20.0, -20.0), look_delta);
+        let look_rotation = get_look_rotation(&rotation, &look_delta);
+        assert_near(0.1 + 20.0 * CAMERA_LOOK_SPEED, look_rotation.x);
+        assert_near(0.2 - 20.0 * CAMERA_LOOK_SPEED, look_rotation.y);
+        assert_eq!(0.3, look_rotation.z);
+        assert_eq!(CAMERA_MAX_PITCH, get_look_rotation(&rotation, &Vector2::new(0.0, -100000.0)).x);
+    }
 }
\ No newline at end of file
@@ -1,3 +1,15 @@
 pub mod application;
+pub mod collision_world;
 pub mod scene_manager;
-pub mod input;
\ No newline at end of file
+pub mod input;
+pub mod environment_settings;
+pub mod haptics;
+pub mod benchmark;
+pub mod console;
+pub mod engine;
+pub mod save_state;
+pub mod scene_file;
+pub mod soak_test;
+pub mod time_of_day;
+pub mod window_integration;
+pub mod wind_system;
\ No newline at end of file
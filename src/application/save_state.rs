@@ -0,0 +1,505 @@
+use std::collections::{ HashMap, HashSet };
+use std::fs;
+use std::path::{ Path, PathBuf };
+
+use bincode;
+use nalgebra::Vector3;
+use serde::{ Serialize, Deserialize };
+
+use crate::application::application::TimeData;
+use crate::application::environment_settings::EnvironmentSettings;
+use crate::application::scene_manager::SceneManagerData;
+use crate::constants;
+use crate::renderer::render_object::RenderObjectData;
+use crate::renderer::render_object_storage::RenderObjectStorage;
+use crate::renderer::renderer::RendererData;
+use crate::resource::resource::MeshDataMap;
+use crate::resource::resource_name::ResourceName;
+
+// F5 / F9 without Ctrl, the runtime state only, the resources and the scene file are not touched.
+pub const QUICKSAVE_FILE_PATH: &str = "save/quicksave.sav";
+pub const SAVE_STATE_VERSION: u32 = 3; // increase when the layout of SaveState is changed, bincode is not self describing
+
+// the cvars which can be changed at runtime without recreating the resources
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CvarSnapshot {
+    pub _near: f32,
+    pub _far: f32,
+    pub _fov: f32,
+    pub _letterbox_target_aspect: f32,
+    pub _letterbox_color: [f32; 4],
+    pub _shadow_samples: i32,
+    pub _shadow_exp: f32,
+    pub _shadow_bias: f32,
+    pub _shadow_distance: f32,
+    pub _shadow_depth: f32,
+    pub _shadow_cache_enable: bool,
+    pub _mouse_sensitivity_x: f32,
+    pub _mouse_sensitivity_y: f32,
+    pub _mouse_invert_y: bool,
+    pub _touch_look_sensitivity: f32,
+    pub _gamepad_invert_y: bool,
+    pub _gamepad_dead_zone: f32,
+    pub _gamepad_radial_dead_zone: bool,
+    pub _gamepad_response_exponent: f32,
+    pub _gamepad_look_sensitivity: f32,
+    pub _haptics_intensity: f32,
+    pub _wind_enable: bool,
+    pub _wind_strength_scale: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AnimationSaveState {
+    pub _animation_mesh: String,
+    pub _animation_loop: bool,
+    pub _animation_blend_time: f32,
+    pub _animation_elapsed_time: f32,
+    pub _animation_speed: f32,
+    pub _animation_frame: f32,
+    pub _animation_play_time: f32,
+    pub _animation_end_time: Option<f32>,
+    pub _is_animation_end: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RenderObjectSaveState {
+    pub _render_object_name: String,
+    pub _position: Vector3<f32>,
+    pub _rotation: Vector3<f32>,
+    pub _scale: Vector3<f32>,
+    pub _animation: Option<AnimationSaveState>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SaveState {
+    pub _version: u32,
+    pub _scene_data_name: String,
+    pub _elapsed_time: f64,
+    pub _camera_position: Vector3<f32>,
+    pub _camera_rotation: Vector3<f32>,
+    pub _environment_settings: EnvironmentSettings, // time of day, clouds and wind
+    pub _cvars: CvarSnapshot,
+    pub _render_objects: Vec<RenderObjectSaveState>,
+    pub _project_state: Vec<u8>, // camera controller mode, components, particle emitters, physics.. written by the project scene manager
+}
+
+// the names of the objects which could not be matched on load
+#[derive(Clone, Debug, Default)]
+pub struct SaveStateLoadReport {
+    pub _missing_objects: Vec<String>, // in the save file, not in the scene
+    pub _new_objects: Vec<String>, // in the scene, not in the save file
+    pub _missing_animation_meshes: Vec<String>,
+}
+
+impl CvarSnapshot {
+    pub fn create_cvar_snapshot() -> CvarSnapshot {
+        unsafe {
+            CvarSnapshot {
+                _near: constants::NEAR,
+                _far: constants::FAR,
+                _fov: constants::FOV,
+                _letterbox_target_aspect: constants::LETTERBOX_TARGET_ASPECT,
+                _letterbox_color: constants::LETTERBOX_COLOR,
+                _shadow_samples: constants::SHADOW_SAMPLES,
+                _shadow_exp: constants::SHADOW_EXP,
+                _shadow_bias: constants::SHADOW_BIAS,
+                _shadow_distance: constants::SHADOW_DISTANCE,
+                _shadow_depth: constants::SHADOW_DEPTH,
+                _shadow_cache_enable: constants::SHADOW_CACHE_ENABLE,
+                _mouse_sensitivity_x: constants::MOUSE_SENSITIVITY_X,
+                _mouse_sensitivity_y: constants::MOUSE_SENSITIVITY_Y,
+                _mouse_invert_y: constants::MOUSE_INVERT_Y,
+                _touch_look_sensitivity: constants::TOUCH_LOOK_SENSITIVITY,
+                _gamepad_invert_y: constants::GAMEPAD_INVERT_Y,
+                _gamepad_dead_zone: constants::GAMEPAD_DEAD_ZONE,
+                _gamepad_radial_dead_zone: constants::GAMEPAD_RADIAL_DEAD_ZONE,
+                _gamepad_response_exponent: constants::GAMEPAD_RESPONSE_EXPONENT,
+                _gamepad_look_sensitivity: constants::GAMEPAD_LOOK_SENSITIVITY,
+                _haptics_intensity: constants::HAPTICS_INTENSITY,
+                _wind_enable: constants::WIND_ENABLE,
+                _wind_strength_scale: constants::WIND_STRENGTH_SCALE,
+            }
+        }
+    }
+
+    pub fn apply_cvar_snapshot(&self) {
+        unsafe {
+            constants::NEAR = self._near;
+            constants::FAR = self._far;
+            constants::FOV = self._fov;
+            constants::LETTERBOX_TARGET_ASPECT = self._letterbox_target_aspect;
+            constants::LETTERBOX_COLOR = self._letterbox_color;
+            constants::SHADOW_SAMPLES = self._shadow_samples;
+            constants::SHADOW_EXP = self._shadow_exp;
+            constants::SHADOW_BIAS = self._shadow_bias;
+            constants::SHADOW_DISTANCE = self._shadow_distance;
+            constants::SHADOW_DEPTH = self._shadow_depth;
+            constants::SHADOW_CACHE_ENABLE = self._shadow_cache_enable;
+            constants::MOUSE_SENSITIVITY_X = self._mouse_sensitivity_x;
+            constants::MOUSE_SENSITIVITY_Y = self._mouse_sensitivity_y;
+            constants::MOUSE_INVERT_Y = self._mouse_invert_y;
+            constants::TOUCH_LOOK_SENSITIVITY = self._touch_look_sensitivity;
+            constants::GAMEPAD_INVERT_Y = self._gamepad_invert_y;
+            constants::GAMEPAD_DEAD_ZONE = self._gamepad_dead_zone;
+            constants::GAMEPAD_RADIAL_DEAD_ZONE = self._gamepad_radial_dead_zone;
+            constants::GAMEPAD_RESPONSE_EXPONENT = self._gamepad_response_exponent;
+            constants::GAMEPAD_LOOK_SENSITIVITY = self._gamepad_look_sensitivity;
+            constants::HAPTICS_INTENSITY = self._haptics_intensity;
+            constants::WIND_ENABLE = self._wind_enable;
+            constants::WIND_STRENGTH_SCALE = self._wind_strength_scale;
+        }
+    }
+}
+
+impl RenderObjectSaveState {
+    pub fn create_render_object_save_state(render_object_data: &RenderObjectData) -> RenderObjectSaveState {
+        let transform_object = &render_object_data._transform_object;
+        RenderObjectSaveState {
+            _render_object_name: render_object_data._render_object_name.clone(),
+            _position: transform_object.get_position().clone(),
+            _rotation: transform_object.get_rotation().clone(),
+            _scale: transform_object.get_scale().clone(),
+            _animation: render_object_data._animation_play_info.as_ref().map(|animation_play_info| AnimationSaveState {
+                _animation_mesh: match animation_play_info._animation_mesh.as_ref() {
+                    Some(animation_mesh) => animation_mesh.borrow()._name.clone(),
+                    None => String::new(),
+                },
+                _animation_loop: animation_play_info._animation_loop,
+                _animation_blend_time: animation_play_info._animation_blend_time,
+                _animation_elapsed_time: animation_play_info._animation_elapsed_time,
+                _animation_speed: animation_play_info._animation_speed,
+                _animation_frame: animation_play_info._animation_frame,
+                _animation_play_time: animation_play_info._animation_play_time,
+                _animation_end_time: animation_play_info._animation_end_time,
+                _is_animation_end: animation_play_info._is_animation_end,
+            }),
+        }
+    }
+
+    // the animation buffers are rebuilt by the next update, _last_animation_frame is invalidated for it.
+    pub fn apply_render_object_save_state(&self, render_object_data: &mut RenderObjectData, mesh_data_map: &MeshDataMap, report: &mut SaveStateLoadReport) {
+        let transform_object = &mut render_object_data._transform_object;
+        transform_object.set_position(&self._position);
+        transform_object.set_rotation(&self._rotation);
+        transform_object.set_scale(&self._scale);
+        if let (Some(animation), Some(animation_play_info)) = (self._animation.as_ref(), render_object_data._animation_play_info.as_mut()) {
+            if let Some(animation_mesh) = mesh_data_map.get(&ResourceName::from(&animation._animation_mesh)) {
+                animation_play_info._animation_mesh = Some(animation_mesh.clone());
+            } else if false == animation._animation_mesh.is_empty() {
+                report._missing_animation_meshes.push(animation._animation_mesh.clone());
+            }
+            animation_play_info._animation_loop = animation._animation_loop;
+            animation_play_info._animation_blend_time = animation._animation_blend_time;
+            animation_play_info._animation_elapsed_time = animation._animation_elapsed_time;
+            animation_play_info._animation_speed = animation._animation_speed;
+            animation_play_info._animation_frame = animation._animation_frame;
+            animation_play_info._animation_play_time = animation._animation_play_time;
+            animation_play_info._animation_end_time = animation._animation_end_time;
+            animation_play_info._is_animation_end = animation._is_animation_end;
+            animation_play_info._last_animation_frame = -1.0;
+        }
+    }
+}
+
+impl SaveState {
+    pub fn create_save_state(time_data: &TimeData, scene_manager_data: &SceneManagerData) -> SaveState {
+        let render_object_storage = scene_manager_data.get_render_object_storage();
+        let render_objects: Vec<RenderObjectSaveState> = render_object_storage.get_handles().iter().map(|object_handle| {
+            let render_object_data = render_object_storage.get_render_object(object_handle).unwrap().borrow();
+            RenderObjectSaveState::create_render_object_save_state(&render_object_data)
+        }).collect();
+        let (camera_position, camera_rotation) = scene_manager_data.get_main_camera_transform();
+        SaveState {
+            _version: SAVE_STATE_VERSION,
+            _scene_data_name: scene_manager_data._scene_data_name.clone(),
+            _elapsed_time: time_data._elapsed_time,
+            _camera_position: camera_position,
+            _camera_rotation: camera_rotation,
+            _environment_settings: scene_manager_data.get_environment_settings().clone(),
+            _cvars: CvarSnapshot::create_cvar_snapshot(),
+            _render_objects: render_objects,
+            _project_state: scene_manager_data.save_project_state(),
+        }
+    }
+
+    pub fn load_save_state_file(file_path: &Path) -> Result<SaveState, String> {
+        let contents = fs::read(file_path).map_err(|e| format!("{:?}: {}", file_path, e))?;
+        let save_state: SaveState = bincode::deserialize(&contents).map_err(|e| format!("{:?}: {}", file_path, e))?;
+        if SAVE_STATE_VERSION != save_state._version {
+            return Err(format!("{:?}: version mismatch {} != {}", file_path, save_state._version, SAVE_STATE_VERSION));
+        }
+        Ok(save_state)
+    }
+
+    pub fn write_save_state_file(&self, file_path: &Path) -> Result<(), String> {
+        if let Some(directory) = file_path.parent() {
+            fs::create_dir_all(directory).map_err(|e| format!("{:?}: {}", directory, e))?;
+        }
+        let contents: Vec<u8> = bincode::serialize(self).map_err(|e| format!("{:?}: {}", file_path, e))?;
+        fs::write(file_path, contents).map_err(|e| format!("{:?}: {}", file_path, e))
+    }
+
+    // the order matters: cvars, environment, transforms, then the project state(physics bodies follow the restored transforms),
+    // then the camera and the time, and the temporal history(TAA, SSR) restarts at the next render.
+    pub fn apply_save_state(&self, time_data: &mut TimeData, scene_manager_data: &mut SceneManagerData, renderer_data: &RendererData) -> SaveStateLoadReport {
+        let mut report = SaveStateLoadReport::default();
+        if self._scene_data_name != scene_manager_data._scene_data_name {
+            log::warn!("apply_save_state: saved in {}, loaded in {}", self._scene_data_name, scene_manager_data._scene_data_name);
+        }
+
+        self._cvars.apply_cvar_snapshot();
+        scene_manager_data.set_environment_settings(&self._environment_settings);
+
+        self.apply_render_object_save_states(scene_manager_data.get_render_object_storage_mut(), &renderer_data._resources.borrow()._mesh_data_map, &mut report);
+        scene_manager_data.load_project_state(&self._project_state);
+        scene_manager_data.set_main_camera_transform(&self._camera_position, &self._camera_rotation);
+        self.apply_time_save_state(time_data);
+
+        renderer_data.set_is_first_rendering(true);
+        report
+    }
+
+    // transforms, matched by the render object name
+    pub fn apply_render_object_save_states(&self, render_object_storage: &mut RenderObjectStorage, mesh_data_map: &MeshDataMap, report: &mut SaveStateLoadReport) {
+        let mut saved_objects: HashMap<&str, &RenderObjectSaveState> = HashMap::new();
+        for render_object in self._render_objects.iter() {
+            if saved_objects.insert(render_object._render_object_name.as_str(), render_object).is_some() {
+                log::warn!("apply_save_state: duplicated render object name {}", render_object._render_object_name);
+            }
+        }
+        let mut matched_objects: HashSet<&str> = HashSet::new();
+        for object_handle in render_object_storage.get_handles().iter() {
+            let mut render_object_data = render_object_storage.get_render_object(object_handle).unwrap().borrow_mut();
+            match saved_objects.get(render_object_data._render_object_name.as_str()) {
+                Some(render_object) => {
+                    render_object.apply_render_object_save_state(&mut render_object_data, mesh_data_map, report);
+                    matched_objects.insert(render_object._render_object_name.as_str());
+                },
+                None => report._new_objects.push(render_object_data._render_object_name.clone()),
+            }
+        }
+        report._missing_objects = self._render_objects.iter()
+            .filter(|render_object| false == matched_objects.contains(render_object._render_object_name.as_str()))
+            .map(|render_object| render_object._render_object_name.clone())
+            .collect();
+        render_object_storage.update_render_object_storage(0.0);
+        render_object_storage.reset_prev_world_matrices();
+    }
+
+    // no delta time at the first frame after the load
+    pub fn apply_time_save_state(&self, time_data: &mut TimeData) {
+        time_data._elapsed_time_prev = self._elapsed_time;
+        time_data._elapsed_time = self._elapsed_time;
+        time_data._delta_time = 0.0;
+    }
+}
+
+pub fn quick_save(time_data: &TimeData, scene_manager_data: &SceneManagerData) {
+    let save_state = SaveState::create_save_state(time_data, scene_manager_data);
+    match save_state.write_save_state_file(&PathBuf::from(QUICKSAVE_FILE_PATH)) {
+        Ok(()) => log::info!("quick_save: {} render objects, {}", save_state._render_objects.len(), QUICKSAVE_FILE_PATH),
+        Err(e) => log::error!("quick_save error: {}", e),
+    }
+}
+
+pub fn quick_load(time_data: &mut TimeData, scene_manager_data: &mut SceneManagerData, renderer_data: &RendererData) {
+    let save_state = match SaveState::load_save_state_file(&PathBuf::from(QUICKSAVE_FILE_PATH)) {
+        Ok(save_state) => save_state,
+        Err(e) => {
+            log::error!("quick_load error: {}", e);
+            return;
+        }
+    };
+    let report = save_state.apply_save_state(time_data, scene_manager_data, renderer_data);
+    for render_object_name in report._missing_objects.iter() {
+        log::warn!("quick_load: missing render object {}", render_object_name);
+    }
+    for render_object_name in report._new_objects.iter() {
+        log::warn!("quick_load: new render object {}", render_object_name);
+    }
+    for mesh_name in report._missing_animation_meshes.iter() {
+        log::warn!("quick_load: missing animation mesh {}", mesh_name);
+    }
+    log::info!("quick_load: {}", QUICKSAVE_FILE_PATH);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::application::create_time_data;
+    use crate::renderer::mesh::{ MeshData, MeshDataCreateInfo };
+    use crate::renderer::model::ModelData;
+    use crate::renderer::render_object::{ AnimationPlayInfo, RenderObjectCreateInfo };
+    use crate::utilities::system::{ newRcRefCell, RcRefCell };
+
+    fn create_test_render_object(render_object_name: &str, position: Vector3<f32>, rotation: Vector3<f32>, scale: Vector3<f32>) -> RcRefCell<RenderObjectData> {
+        let mesh_data = newRcRefCell(MeshData::create_mesh_data(&String::from("test_mesh"), MeshDataCreateInfo::default(), Vec::new()));
+        let model_data = newRcRefCell(ModelData::new_model_data(&String::from("test_model"), mesh_data, Vec::new()));
+        let mut render_object_data = RenderObjectData::create_render_object_data(&String::from(render_object_name), &model_data, &RenderObjectCreateInfo {
+            _position: position,
+            _rotation: rotation,
+            _scale: scale,
+            ..Default::default()
+        });
+        render_object_data._transform_object.update_transform_object();
+        newRcRefCell(render_object_data)
+    }
+
+    fn create_test_scene() -> RenderObjectStorage {
+        let mut render_object_storage = RenderObjectStorage::default();
+        render_object_storage.add_render_object(&create_test_render_object("box", Vector3::new(1.0, 2.0, 3.0), Vector3::new(0.1, 0.2, 0.3), Vector3::new(1.0, 1.0, 1.0)), 1);
+        render_object_storage.add_render_object(&create_test_render_object("tree", Vector3::new(-10.5, 0.0, 7.25), Vector3::new(0.0, 1.5, 0.0), Vector3::new(2.0, 3.0, 2.0)), 1);
+        render_object_storage.add_render_object(&create_test_render_object("rock", Vector3::new(0.001, -0.002, 1000.0), Vector3::new(-3.0, 0.0, 3.0), Vector3::new(0.5, 0.5, 0.5)), 1);
+        render_object_storage
+    }
+
+    fn create_test_save_state(render_object_storage: &RenderObjectStorage, elapsed_time: f64) -> SaveState {
+        SaveState {
+            _version: SAVE_STATE_VERSION,
+            _scene_data_name: String::from("test_scene"),
+            _elapsed_time: elapsed_time,
+            _camera_position: Vector3::new(1.0, 2.0, 3.0),
+            _camera_rotation: Vector3::new(0.0, 0.5, 0.0),
+            _environment_settings: EnvironmentSettings::default(),
+            _cvars: CvarSnapshot::create_cvar_snapshot(),
+            _render_objects: get_render_object_save_states(render_object_storage),
+            _project_state: vec![1, 2, 3, 4],
+        }
+    }
+
+    fn get_render_object_save_states(render_object_storage: &RenderObjectStorage) -> Vec<RenderObjectSaveState> {
+        render_object_storage.get_handles().iter().map(|object_handle| {
+            RenderObjectSaveState::create_render_object_save_state(&render_object_storage.get_render_object(object_handle).unwrap().borrow())
+        }).collect()
+    }
+
+    fn move_render_objects(render_object_storage: &RenderObjectStorage) {
+        for object_handle in render_object_storage.get_handles().iter() {
+            let mut render_object_data = render_object_storage.get_render_object(object_handle).unwrap().borrow_mut();
+            let transform_object = &mut render_object_data._transform_object;
+            transform_object.set_position(&Vector3::new(100.0, 200.0, 300.0));
+            transform_object.set_rotation(&Vector3::new(1.0, 1.0, 1.0));
+            transform_object.set_scale(&Vector3::new(4.0, 4.0, 4.0));
+        }
+    }
+
+    #[test]
+    fn test_restore_transforms() {
+        let mut render_object_storage = create_test_scene();
+        let save_state = create_test_save_state(&render_object_storage, 12.5);
+        let saved_render_objects = get_render_object_save_states(&render_object_storage);
+        move_render_objects(&render_object_storage);
+        assert_ne!(saved_render_objects, get_render_object_save_states(&render_object_storage));
+
+        let mut report = SaveStateLoadReport::default();
+        save_state.apply_render_object_save_states(&mut render_object_storage, &MeshDataMap::new(), &mut report);
+        assert_eq!(saved_render_objects, get_render_object_save_states(&render_object_storage));
+        assert!(report._missing_objects.is_empty());
+        assert!(report._new_objects.is_empty());
+        assert!(report._missing_animation_meshes.is_empty());
+
+        // the world matrix follows the restored position
+        let object_handle = render_object_storage.get_handles()[1];
+        let world_matrix = render_object_storage.get_world_matrix(&object_handle).unwrap();
+        assert_eq!(Vector3::new(-10.5, 0.0, 7.25), Vector3::new(world_matrix[(0, 3)], world_matrix[(1, 3)], world_matrix[(2, 3)]));
+    }
+
+    #[test]
+    fn test_restore_time() {
+        let save_state = create_test_save_state(&create_test_scene(), 1234.5678);
+        let mut time_data = create_time_data(0.0);
+        time_data._elapsed_time = 99.0;
+        time_data._delta_time = 0.016;
+        save_state.apply_time_save_state(&mut time_data);
+        assert_eq!(1234.5678, time_data._elapsed_time);
+        assert_eq!(1234.5678, time_data._elapsed_time_prev);
+        assert_eq!(0.0, time_data._delta_time);
+    }
+
+    #[test]
+    fn test_missing_and_new_objects() {
+        let mut render_object_storage = create_test_scene();
+        let mut save_state = create_test_save_state(&render_object_storage, 0.0);
+        save_state._render_objects.retain(|render_object| "tree" != render_object._render_object_name);
+        save_state._render_objects.push(RenderObjectSaveState {
+            _render_object_name: String::from("removed"),
+            _position: Vector3::zeros(),
+            _rotation: Vector3::zeros(),
+            _scale: Vector3::new(1.0, 1.0, 1.0),
+            _animation: None,
+        });
+        let tree_handle = render_object_storage.get_handles()[1];
+        let tree_render_object = RenderObjectSaveState::create_render_object_save_state(&render_object_storage.get_render_object(&tree_handle).unwrap().borrow());
+        move_render_objects(&render_object_storage);
+
+        let mut report = SaveStateLoadReport::default();
+        save_state.apply_render_object_save_states(&mut render_object_storage, &MeshDataMap::new(), &mut report);
+        assert_eq!(vec![String::from("removed")], report._missing_objects);
+        assert_eq!(vec![String::from("tree")], report._new_objects);
+
+        // the new object keeps its current transform
+        let tree_render_object_after = RenderObjectSaveState::create_render_object_save_state(&render_object_storage.get_render_object(&tree_handle).unwrap().borrow());
+        assert_ne!(tree_render_object, tree_render_object_after);
+        assert_eq!(Vector3::new(100.0, 200.0, 300.0), tree_render_object_after._position);
+    }
+
+    #[test]
+    fn test_restore_animation() {
+        let render_object = create_test_render_object("box", Vector3::zeros(), Vector3::zeros(), Vector3::new(1.0, 1.0, 1.0));
+        render_object.borrow_mut()._animation_play_info = Some(AnimationPlayInfo::default());
+        let mut saved_render_object = RenderObjectSaveState::create_render_object_save_state(&render_object.borrow());
+        {
+            let animation = saved_render_object._animation.as_mut().unwrap();
+            animation._animation_mesh = String::from("Characters/Walk.gltf");
+            animation._animation_elapsed_time = 3.25;
+            animation._animation_frame = 78.0;
+            animation._animation_speed = 0.5;
+            animation._animation_end_time = Some(4.0);
+        }
+
+        let mut report = SaveStateLoadReport::default();
+        saved_render_object.apply_render_object_save_state(&mut render_object.borrow_mut(), &MeshDataMap::new(), &mut report);
+        assert_eq!(vec![String::from("Characters/Walk.gltf")], report._missing_animation_meshes);
+        {
+            let render_object_data = render_object.borrow();
+            let animation_play_info = render_object_data._animation_play_info.as_ref().unwrap();
+            assert_eq!(3.25, animation_play_info._animation_elapsed_time);
+            assert_eq!(78.0, animation_play_info._animation_frame);
+            assert_eq!(0.5, animation_play_info._animation_speed);
+            assert_eq!(Some(4.0), animation_play_info._animation_end_time);
+            assert_eq!(-1.0, animation_play_info._last_animation_frame);
+        }
+
+        // the animation mesh is found with the normalized name
+        let mut mesh_data_map = MeshDataMap::new();
+        let animation_mesh = newRcRefCell(MeshData::create_mesh_data(&String::from("characters/walk"), MeshDataCreateInfo::default(), Vec::new()));
+        mesh_data_map.insert(ResourceName::from("characters/walk"), animation_mesh.clone());
+        let mut report = SaveStateLoadReport::default();
+        saved_render_object.apply_render_object_save_state(&mut render_object.borrow_mut(), &mesh_data_map, &mut report);
+        assert!(report._missing_animation_meshes.is_empty());
+        assert!(std::rc::Rc::ptr_eq(&animation_mesh, render_object.borrow()._animation_play_info.as_ref().unwrap()._animation_mesh.as_ref().unwrap()));
+    }
+
+    #[test]
+    fn test_save_state_file_round_trip() {
+        let render_object_storage = create_test_scene();
+        let save_state = create_test_save_state(&render_object_storage, 98765.4321);
+        let test_directory = std::env::temp_dir().join(format!("save_state_{}_{}", "file_round_trip", std::process::id()));
+        let file_path = test_directory.join("save").join("quicksave.sav");
+        save_state.write_save_state_file(&file_path).unwrap();
+        let loaded_save_state = SaveState::load_save_state_file(&file_path).unwrap();
+        assert_eq!(save_state, loaded_save_state);
+        assert_eq!(98765.4321, loaded_save_state._elapsed_time);
+
+        // bincode is not self describing, the other versions are rejected
+        let mut old_save_state = save_state.clone();
+        old_save_state._version = SAVE_STATE_VERSION - 1;
+        old_save_state.write_save_state_file(&file_path).unwrap();
+        assert!(SaveState::load_save_state_file(&file_path).unwrap_err().contains("version mismatch"));
+
+        fs::write(&file_path, [1u8, 2, 3]).unwrap();
+        assert!(SaveState::load_save_state_file(&file_path).is_err());
+        assert!(SaveState::load_save_state_file(&test_directory.join("not_exists.sav")).is_err());
+        fs::remove_dir_all(&test_directory).unwrap();
+    }
+}
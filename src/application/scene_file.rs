@@ -0,0 +1,106 @@
+use std::fs;
+use std::path::{ Path, PathBuf };
+
+use serde::{ Serialize, Deserialize };
+use serde_json;
+
+use crate::application::environment_settings::EnvironmentSettings;
+use crate::application::scene_manager::SceneManagerData;
+use crate::renderer::camera::CameraCreateInfo;
+use crate::renderer::render_object::RenderObjectCreateInfo;
+
+// Ctrl+F5 / Ctrl+F9, the placement of the render objects. unlike the quicksave the file is json and can be edited by hand.
+pub const SCENE_FILE_DIRECTORY: &str = "save";
+pub const SCENE_FILE_EXTENSION: &str = "scene";
+pub const SCENE_FILE_VERSION: u32 = 1; // increase when a field of SceneFileData is renamed or removed
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+pub struct SceneFileRenderObject {
+    pub _render_object_name: String,
+    pub _render_object_create_info: RenderObjectCreateInfo, // the model name and the transform
+    pub _material_instance_overrides: Vec<String>, // by the geometry index, empty: the material instances of the model
+}
+
+// the directional light follows the sun of the atmosphere settings, the ocean parameters are of the environment settings too.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct SceneFileData {
+    pub _version: u32,
+    pub _scene_data_name: String,
+    pub _camera: CameraCreateInfo,
+    pub _render_objects: Vec<SceneFileRenderObject>,
+    pub _environment_settings: EnvironmentSettings,
+}
+
+// ex) save/default.scene
+pub fn get_scene_file_path(scene_data_name: &str) -> PathBuf {
+    let mut scene_file_path = PathBuf::from(SCENE_FILE_DIRECTORY);
+    scene_file_path.push(scene_data_name);
+    scene_file_path.set_extension(SCENE_FILE_EXTENSION);
+    scene_file_path
+}
+
+impl SceneFileData {
+    // the procedural render objects whose model is not a resource can not be loaded again, they are skipped.
+    pub fn create_scene_file_data(scene_manager_data: &SceneManagerData) -> SceneFileData {
+        let resources = scene_manager_data._resources.borrow();
+        let render_object_storage = scene_manager_data.get_render_object_storage();
+        let mut render_objects: Vec<SceneFileRenderObject> = Vec::new();
+        for object_handle in render_object_storage.get_handles().iter() {
+            let render_object_data = render_object_storage.get_render_object(object_handle).unwrap().borrow();
+            let model_data = render_object_data._model_data.borrow();
+            if false == resources.has_model_data(&model_data._model_data_name) {
+                log::warn!("create_scene_file_data: skip {}, {} is not a model resource", render_object_data._render_object_name, model_data._model_data_name);
+                continue;
+            }
+            let resource_model_data = resources.get_model_data(&model_data._model_data_name).borrow();
+            let is_overridden = model_data._material_instance_datas.len() != resource_model_data._material_instance_datas.len() ||
+                model_data._material_instance_datas.iter().zip(resource_model_data._material_instance_datas.iter()).any(|(lhs, rhs)| false == std::rc::Rc::ptr_eq(lhs, rhs));
+            let transform_object = &render_object_data._transform_object;
+            render_objects.push(SceneFileRenderObject {
+                _render_object_name: render_object_data._render_object_name.clone(),
+                _render_object_create_info: RenderObjectCreateInfo {
+                    _model_data_name: model_data._model_data_name.clone(),
+                    _position: transform_object.get_position().clone(),
+                    _rotation: transform_object.get_rotation().clone(),
+                    _scale: transform_object.get_scale().clone(),
+                },
+                _material_instance_overrides: if is_overridden {
+                    model_data._material_instance_datas.iter().map(|material_instance_data| material_instance_data.borrow()._material_instance_data_name.clone()).collect()
+                } else {
+                    Vec::new()
+                },
+            });
+        }
+        let (camera_position, camera_rotation) = scene_manager_data.get_main_camera_transform();
+        SceneFileData {
+            _version: SCENE_FILE_VERSION,
+            _scene_data_name: scene_manager_data._scene_data_name.clone(),
+            _camera: CameraCreateInfo {
+                position: camera_position,
+                rotation: camera_rotation,
+                ..Default::default()
+            },
+            _render_objects: render_objects,
+            _environment_settings: scene_manager_data.get_environment_settings().clone(),
+        }
+    }
+
+    pub fn load_scene_file(file_path: &Path) -> Result<SceneFileData, String> {
+        let contents = fs::read_to_string(file_path).map_err(|e| format!("{:?}: {}", file_path, e))?;
+        let scene_file_data: SceneFileData = serde_json::from_str(&contents).map_err(|e| format!("{:?}: {}", file_path, e))?;
+        if SCENE_FILE_VERSION != scene_file_data._version {
+            return Err(format!("{:?}: version mismatch {} != {}", file_path, scene_file_data._version, SCENE_FILE_VERSION));
+        }
+        Ok(scene_file_data)
+    }
+
+    pub fn write_scene_file(&self, file_path: &Path) -> Result<(), String> {
+        if let Some(directory) = file_path.parent() {
+            fs::create_dir_all(directory).map_err(|e| format!("{:?}: {}", directory, e))?;
+        }
+        let contents = serde_json::to_string_pretty(self).map_err(|e| format!("{:?}: {}", file_path, e))?;
+        fs::write(file_path, contents).map_err(|e| format!("{:?}: {}", file_path, e))
+    }
+}
@@ -1,11 +1,36 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{ Path, PathBuf };
+use std::rc::Rc;
+
 use ash::Device;
-use nalgebra::Vector2;
+use nalgebra::{ Vector2, Vector3, Matrix4 };
+use serde_json::{ self, Value };
 
 use crate::application::application::TimeData;
+use crate::application::collision_world::{ CollisionHit, CollisionNotReady, CollisionSource, CollisionWorld };
+use crate::application::environment_settings::EnvironmentSettings;
+use crate::application::scene_file::SceneFileData;
+use crate::application::time_of_day::{ self, TimeOfDay };
+use crate::application::wind_system::WindSystem;
+use crate::constants;
 use crate::renderer::font::FontManager;
+use crate::renderer::gpu_skinning;
+use crate::renderer::light::{ LocalLightData, PointLightData, SpotLightData };
+use crate::renderer::light_cluster;
+use crate::renderer::occlusion_culling;
+use crate::renderer::render_element::{ RenderElementData, RenderElementGroupData };
+use crate::renderer::mesh;
+use crate::renderer::model::ModelData;
+use crate::renderer::render_object::RenderObjectData;
+use crate::renderer::render_object_storage::{ self, ObjectHandle, RenderObjectStorage };
+use crate::utilities::progress::{ self, ProgressTask };
+use crate::utilities::telemetry::SceneContentKey;
 use crate::renderer::renderer::RendererData;
-use crate::resource::resource::Resources;
-use crate::utilities::system::RcRefCell;
+use crate::renderer::shadow_atlas::LightId;
+use crate::renderer::visibility_stats;
+use crate::resource::resource::{ Resources, SCENE_FILE_PATH };
+use crate::utilities::system::{ newRcRefCell, RcRefCell };
 
 pub trait ProjectSceneManagerBase {
     fn initialize_project_scene_manager(
@@ -24,14 +49,84 @@ pub trait ProjectSceneManagerBase {
     fn open_scene_data(&mut self, scene_data_name: &str);
     fn close_scene_data(&mut self, device: &Device);
     fn save_scene_data(&mut self);
+    fn apply_environment_settings(&mut self, _environment_settings: &EnvironmentSettings) {} // sky, fog, post process.. through the cvar setters
+    fn set_main_camera_transform(&mut self, _position: &Vector3<f32>, _rotation: &Vector3<f32>) {} // the benchmark camera path
+    // position, rotation
+    fn get_main_camera_transform(&self) -> (Vector3<f32>, Vector3<f32>) {
+        (Vector3::zeros(), Vector3::zeros())
+    }
+    // the time of day, the rotation of the main DirectionalLightData in radian and its light color. zero color at the night.
+    // the atmosphere of the project follows the main light direction.
+    fn set_main_light(&mut self, _rotation: &Vector3<f32>, _light_color: &Vector3<f32>) {}
+    // quicksave, the runtime state owned by the project. ex) camera controller, components, particle emitters, physics
+    fn save_project_state(&self) -> Vec<u8> {
+        Vec::new()
+    }
+    fn load_project_state(&mut self, _project_state: &[u8]) {} // quickload, called after the render object transforms are restored
     fn destroy_project_scene_manager(&mut self, device: &Device);
-    fn update_project_scene_manager(&mut self, time_data: &TimeData, font_manager: &mut FontManager);
+    fn update_project_scene_manager(&mut self, time_data: &TimeData, font_manager: &mut FontManager); // once per frame with the frame delta time, ex) camera
+    // a simulation step with the delta time of FIXED_TIME_STEP, 0 ~ MAX_FIXED_STEPS_PER_FRAME times per frame. ex) effects, physics
+    fn update_project_simulation(&mut self, _time_data: &TimeData) {}
+}
+
+// of the last cull_scene, printed by the debug overlay
+#[derive(Clone, Debug, Default)]
+pub struct CullingStats {
+    pub _total_count: usize,
+    pub _visible_count: usize,
+    pub _shadow_visible_count: usize,
+}
+
+impl CullingStats {
+    pub fn get_culled_count(&self) -> usize {
+        self._total_count - self._visible_count
+    }
+
+    pub fn get_stats_text(&self) -> String {
+        format!("culling: visible {} / {}, culled {}, shadow casters {}",
+            self._visible_count,
+            self._total_count,
+            self.get_culled_count(),
+            self._shadow_visible_count
+        )
+    }
+}
+
+// the mesh and the material instances of the model, released when the render object is removed. the unloaded ones are loaded again.
+fn acquire_render_object_resources(renderer_data: &RendererData, resources: &mut Resources, render_object_data: &RenderObjectData) {
+    let mesh_data_name = render_object_data._mesh_data.borrow()._name.clone();
+    resources.acquire_mesh_data(renderer_data, &mesh_data_name);
+    for material_instance_data in render_object_data._model_data.borrow()._material_instance_datas.iter() {
+        let material_instance_data_name = material_instance_data.borrow()._material_instance_data_name.clone();
+        resources.acquire_material_instance_data(renderer_data, &material_instance_data_name);
+    }
+}
+
+fn release_render_object_resources(resources: &mut Resources, render_object_data: &RenderObjectData) {
+    resources.release_mesh_data(&render_object_data._mesh_data.borrow()._name);
+    for material_instance_data in render_object_data._model_data.borrow()._material_instance_datas.iter() {
+        resources.release_material_instance_data(&material_instance_data.borrow()._material_instance_data_name);
+    }
 }
 
 pub struct SceneManagerData {
     pub _renderer_data: RcRefCell<RendererData>,
     pub _resources: RcRefCell<Resources>,
     pub _project_scene_manager: *const dyn ProjectSceneManagerBase,
+    pub _scene_data_name: String,
+    pub _environment_settings: EnvironmentSettings,
+    pub _render_object_storage: RenderObjectStorage, // the project registers the spawned render objects by add_render_object
+    pub _visible_indices: Vec<u32>, // main camera, reused between the frames
+    pub _shadow_visible_indices: Vec<u32>, // orthographic volume of the main light
+    pub _static_draw_indices: Vec<u32>, // every alive object while the static batch is culled on the gpu, see get_static_draw_indices
+    pub _is_gpu_occlusion_culling: bool,
+    pub _culling_stats: CullingStats,
+    pub _visible_coverages: Vec<f32>, // by the object index, the screen fraction of the visible objects during the visibility stats capture
+    pub _wind_system: WindSystem, // the settings come from EnvironmentSettings::_wind
+    pub _time_of_day: TimeOfDay, // the settings come from EnvironmentSettings::_time_of_day_settings
+    pub _local_lights: HashMap<LightId, LocalLightData>, // the point and spot lights, the main light is of the project
+    pub _next_light_id: LightId,
+    pub _collision_world: CollisionWorld, // the static geometry of the scene load, see rebuild_collision_world
 }
 
 impl SceneManagerData {
@@ -44,6 +139,20 @@ impl SceneManagerData {
             _renderer_data: renderer_data.clone(),
             _resources: resources.clone(),
             _project_scene_manager: project_scene_manager,
+            _scene_data_name: String::from("default"),
+            _environment_settings: EnvironmentSettings::default(),
+            _render_object_storage: RenderObjectStorage::default(),
+            _visible_indices: Vec::new(),
+            _shadow_visible_indices: Vec::new(),
+            _static_draw_indices: Vec::new(),
+            _is_gpu_occlusion_culling: false,
+            _culling_stats: CullingStats::default(),
+            _visible_coverages: Vec::new(),
+            _wind_system: WindSystem::default(),
+            _time_of_day: TimeOfDay::default(),
+            _local_lights: HashMap::new(),
+            _next_light_id: 0,
+            _collision_world: CollisionWorld::default(),
         }
     }
 
@@ -70,15 +179,498 @@ impl SceneManagerData {
     }
 
     pub fn open_scene_data(&mut self) {
-        self.get_project_scene_manager_mut().open_scene_data("default");
+        let scene_data_name = self._scene_data_name.clone();
+        // the project can report the finer progress of ProgressTask::SceneLoad in its open_scene_data
+        progress::report_progress(ProgressTask::SceneLoad, 0.0);
+        self.get_project_scene_manager_mut().open_scene_data(&scene_data_name);
+        self.load_environment_settings();
+        self.rebuild_collision_world();
+        progress::end_progress(ProgressTask::SceneLoad);
     }
 
     pub fn close_scene_data(&mut self, device: &Device) {
         self.get_project_scene_manager_mut().close_scene_data(device);
+        self.clear_render_objects();
+        self._wind_system.clear_wind_system();
+        self._local_lights.clear();
+    }
+
+    fn clear_render_objects(&mut self) {
+        {
+            let mut resources = self._resources.borrow_mut();
+            for object_handle in self._render_object_storage.get_handles() {
+                if let Some(render_object_data) = self._render_object_storage.get_render_object(&object_handle) {
+                    release_render_object_resources(&mut resources, &render_object_data.borrow());
+                }
+            }
+        }
+        self._render_object_storage.clear_render_objects();
+        self._collision_world.clear_collision_world();
+        self._visible_indices.clear();
+        self._shadow_visible_indices.clear();
+        self._static_draw_indices.clear();
+        self._culling_stats = CullingStats::default();
+        self._visible_coverages.clear();
+    }
+
+    // the render objects, the main camera, the atmosphere and the ocean, see scene_file::SceneFileData
+    pub fn save_scene(&self, scene_file_path: &Path) -> bool {
+        let scene_file_data = SceneFileData::create_scene_file_data(self);
+        match scene_file_data.write_scene_file(scene_file_path) {
+            Ok(()) => {
+                log::info!("save_scene: {} render objects, {:?}", scene_file_data._render_objects.len(), scene_file_path);
+                true
+            },
+            Err(e) => {
+                log::error!("save_scene error: {}", e);
+                false
+            }
+        }
+    }
+
+    // replaces the render objects of the storage, the objects whose model or material instance is missing are skipped.
+    // the project keeps its own camera controller and main light, the temporal history is invalidated by the caller.
+    pub fn open_scene_from_file(&mut self, scene_file_path: &Path) -> bool {
+        let scene_file_data = match SceneFileData::load_scene_file(scene_file_path) {
+            Ok(scene_file_data) => scene_file_data,
+            Err(e) => {
+                log::error!("open_scene_from_file error: {}", e);
+                return false;
+            }
+        };
+        if scene_file_data._scene_data_name != self._scene_data_name {
+            log::warn!("open_scene_from_file: saved in {}, opened in {}", scene_file_data._scene_data_name, self._scene_data_name);
+        }
+
+        self.clear_render_objects();
+        let mut render_object_datas: Vec<RcRefCell<RenderObjectData>> = Vec::new();
+        {
+            let resources = self._resources.borrow();
+            for render_object in scene_file_data._render_objects.iter() {
+                let model_data_name = &render_object._render_object_create_info._model_data_name;
+                if false == resources.has_model_data(model_data_name) {
+                    log::warn!("open_scene_from_file: skip {}, missing model {}", render_object._render_object_name, model_data_name);
+                    continue;
+                }
+                let mut model_data = resources.get_model_data(model_data_name).clone();
+                if false == render_object._material_instance_overrides.is_empty() {
+                    let mut overridden_model_data: ModelData = model_data.borrow().clone();
+                    if render_object._material_instance_overrides.len() != overridden_model_data._material_instance_datas.len() {
+                        log::warn!("open_scene_from_file: skip {}, {} material instances for {} geometries", render_object._render_object_name, render_object._material_instance_overrides.len(), overridden_model_data._material_instance_datas.len());
+                        continue;
+                    }
+                    for (material_instance_data, material_instance_name) in overridden_model_data._material_instance_datas.iter_mut().zip(render_object._material_instance_overrides.iter()) {
+                        if resources.has_material_instance_data(material_instance_name) {
+                            *material_instance_data = resources.get_material_instance_data(material_instance_name).clone();
+                        } else {
+                            log::warn!("open_scene_from_file: {} keeps the material instance of the model, missing {}", render_object._render_object_name, material_instance_name);
+                        }
+                    }
+                    model_data = newRcRefCell(overridden_model_data);
+                }
+                render_object_datas.push(newRcRefCell(RenderObjectData::create_render_object_data(&render_object._render_object_name, &model_data, &render_object._render_object_create_info)));
+            }
+        }
+        for render_object_data in render_object_datas.iter() {
+            self.add_render_object(render_object_data, render_object_storage::LAYER_MASK_ALL);
+        }
+        self._render_object_storage.update_render_object_storage(0.0);
+        self._render_object_storage.reset_prev_world_matrices();
+        self.rebuild_collision_world();
+
+        unsafe {
+            constants::NEAR = scene_file_data._camera.near;
+            constants::FAR = scene_file_data._camera.far;
+            constants::FOV = scene_file_data._camera.fov;
+        }
+        self.set_main_camera_transform(&scene_file_data._camera.position, &scene_file_data._camera.rotation);
+        self.set_environment_settings(&scene_file_data._environment_settings);
+        log::info!("open_scene_from_file: {} / {} render objects, {:?}", self._render_object_storage.get_alive_count(), scene_file_data._render_objects.len(), scene_file_path);
+        true
+    }
+
+    // the telemetry reports the growth of the resources only with the same scene content
+    pub fn get_scene_content_key(&self) -> SceneContentKey {
+        (self._scene_data_name.clone(), self._render_object_storage.get_alive_count())
+    }
+
+    pub fn get_render_object_storage(&self) -> &RenderObjectStorage {
+        &self._render_object_storage
+    }
+
+    pub fn get_render_object_storage_mut(&mut self) -> &mut RenderObjectStorage {
+        &mut self._render_object_storage
+    }
+
+    // the render object acquires its mesh and material instances, use these instead of the storage to keep them from the garbage collection.
+    pub fn add_render_object(&mut self, render_object_data: &RcRefCell<RenderObjectData>, layer_mask: u32) -> ObjectHandle {
+        acquire_render_object_resources(&self._renderer_data.borrow(), &mut self._resources.borrow_mut(), &render_object_data.borrow());
+        render_object_data.borrow_mut().update_geometry_bound_boxes();
+        self._render_object_storage.add_render_object(render_object_data, layer_mask)
+    }
+
+    pub fn remove_render_object(&mut self, object_handle: &ObjectHandle) -> Option<RcRefCell<RenderObjectData>> {
+        let render_object_data = self._render_object_storage.remove_render_object(object_handle);
+        if let Some(render_object_data) = render_object_data.as_ref() {
+            release_render_object_resources(&mut self._resources.borrow_mut(), &render_object_data.borrow());
+        }
+        render_object_data
+    }
+
+    // the object id of RendererData::pick_object, None for the background and the removed objects
+    pub fn get_render_object_by_object_id(&self, object_id: u64) -> Option<&RcRefCell<RenderObjectData>> {
+        if (std::u32::MAX as u64) < object_id {
+            return None;
+        }
+        self._render_object_storage.get_handle_by_object_id(object_id as u32).and_then(|object_handle| self._render_object_storage.get_render_object(&object_handle))
+    }
+
+    // called by the project each frame before building the render element lists.
+    // view_projection: CameraObjectData::_view_projection, shadow_view_projection: DirectionalLightData::get_shadow_view_projection
+    pub fn cull_scene(&mut self, view_projection: &Matrix4<f32>, view_position: &Vector3<f32>, shadow_view_projection: &Matrix4<f32>) {
+        let layer_mask = render_object_storage::LAYER_MASK_ALL;
+        self._render_object_storage.cull_render_objects(view_projection, layer_mask, &mut self._visible_indices);
+        self._render_object_storage.sort_render_objects(view_position, &mut self._visible_indices);
+        self._render_object_storage.cull_render_objects(shadow_view_projection, layer_mask, &mut self._shadow_visible_indices);
+        self.update_visible_coverages(view_projection);
+        let renderer_data = self._renderer_data.borrow();
+        renderer_data.set_occlusion_culling_view_projection(view_projection);
+        self._is_gpu_occlusion_culling = renderer_data.is_gpu_occlusion_culling_enabled();
+        if self._is_gpu_occlusion_culling {
+            self._render_object_storage.gather_render_objects(layer_mask, &mut self._static_draw_indices);
+            self._render_object_storage.select_mesh_lods(view_position, &self._static_draw_indices);
+        } else {
+            self._static_draw_indices.clear();
+            self._render_object_storage.select_mesh_lods(view_position, &self._visible_indices);
+        }
+        // by the distance from the main camera, so a shadow caster out of the view switches with the same distances
+        self._render_object_storage.select_mesh_lods(view_position, &self._shadow_visible_indices);
+        renderer_data._terrain.borrow_mut().cull_terrain_chunks(view_projection, view_position);
+        self._culling_stats = CullingStats {
+            _total_count: self._render_object_storage.get_alive_count(),
+            _visible_count: self._visible_indices.len(),
+            _shadow_visible_count: self._shadow_visible_indices.len(),
+        };
+    }
+
+    // the bound spheres of the visible objects on the screen, the weights of the visibility stats heuristic
+    fn update_visible_coverages(&mut self, view_projection: &Matrix4<f32>) {
+        self._visible_coverages.clear();
+        if false == visibility_stats::is_visibility_stats_capturing() {
+            return;
+        }
+        let resolution = visibility_stats::VISIBILITY_STATS_COVERAGE_RESOLUTION;
+        let screen_area = (resolution * resolution) as f32;
+        self._visible_coverages.resize(self._render_object_storage.get_capacity(), 0.0);
+        for index in self._visible_indices.iter() {
+            let bound_sphere = &self._render_object_storage._bound_spheres[*index as usize];
+            let center = Vector3::new(bound_sphere.x, bound_sphere.y, bound_sphere.z);
+            if let Some((min_x, min_y, max_x, max_y)) = light_cluster::get_light_screen_rect(view_projection, &center, bound_sphere.w, resolution, resolution) {
+                self._visible_coverages[*index as usize] = ((max_x - min_x + 1) * (max_y - min_y + 1)) as f32 / screen_area;
+            }
+        }
+    }
+
+    // local lights
+    pub fn add_point_light(&mut self, light_name: &str, point_light_data: &PointLightData) -> LightId {
+        let local_light_data = LocalLightData::create_local_light_data(&String::from(light_name), &point_light_data.get_local_light_create_info(), &self._resources.borrow());
+        self.add_local_light(light_name, local_light_data)
+    }
+
+    pub fn add_spot_light(&mut self, light_name: &str, spot_light_data: &SpotLightData) -> LightId {
+        let local_light_data = LocalLightData::create_local_light_data(&String::from(light_name), &spot_light_data.get_local_light_create_info(), &self._resources.borrow());
+        self.add_local_light(light_name, local_light_data)
+    }
+
+    fn add_local_light(&mut self, light_name: &str, local_light_data: LocalLightData) -> LightId {
+        let light_id = self._next_light_id;
+        self._next_light_id += 1;
+        log::debug!("add_local_light: {} {}", light_name, light_id);
+        self._local_lights.insert(light_id, local_light_data);
+        light_id
+    }
+
+    pub fn remove_light(&mut self, light_id: LightId) -> bool {
+        self._local_lights.remove(&light_id).is_some()
+    }
+
+    pub fn get_local_light(&self, light_id: LightId) -> Option<&LocalLightData> {
+        self._local_lights.get(&light_id)
+    }
+
+    pub fn get_local_light_mut(&mut self, light_id: LightId) -> Option<&mut LocalLightData> {
+        self._local_lights.get_mut(&light_id)
+    }
+
+    pub fn get_local_lights(&self) -> &HashMap<LightId, LocalLightData> {
+        &self._local_lights
+    }
+
+    // called by the project each frame after cull_scene, the lights out of the frustum are skipped.
+    // screen_width, screen_height: the size of the gbuffer which composite_gbuffer reads.
+    pub fn update_light_cluster(&self, view_projection: &Matrix4<f32>, view_position: &Vector3<f32>, screen_width: u32, screen_height: u32) {
+        let mut lights: Vec<(LightId, &LocalLightData)> = self._local_lights.iter().map(|(light_id, light_data)| (*light_id, light_data)).collect();
+        lights.sort_by_key(|(light_id, _)| *light_id);
+        let renderer_data = self._renderer_data.borrow();
+        renderer_data.get_light_cluster().borrow_mut().build_light_cluster(&lights, view_projection, view_position, screen_width, screen_height);
+    }
+
+    pub fn get_visible_indices(&self) -> &Vec<u32> {
+        &self._visible_indices
+    }
+
+    pub fn get_shadow_visible_indices(&self) -> &Vec<u32> {
+        &self._shadow_visible_indices
+    }
+
+    // the indices of get_static_render_element_groups for the main camera: every alive object when the occlusion culling
+    // compute pass culls the static batch, the cpu frustum culled indices with constants::FORCE_CPU_OCCLUSION_CULLING.
+    pub fn get_static_draw_indices(&self) -> &Vec<u32> {
+        if self._is_gpu_occlusion_culling { &self._static_draw_indices } else { &self._visible_indices }
+    }
+
+    pub fn get_culling_stats(&self) -> &CullingStats {
+        &self._culling_stats
+    }
+
+    pub fn update_culling_stats(&self, font_manager: &mut FontManager) {
+        font_manager.log(self._culling_stats.get_stats_text());
+    }
+
+    // the static render elements of the visible objects grouped by (geometry, material instance) in the order of the first appearance.
+    // the objects with the animation are skipped, they are drawn one by one with get_skeletal_render_elements.
+    // the geometries are of the mesh lods selected by cull_scene.
+    pub fn get_static_render_element_groups(&self, visible_indices: &[u32]) -> Vec<RenderElementGroupData> {
+        self.get_static_render_element_groups_with_lod_bias(visible_indices, 0)
+    }
+
+    // the shadow passes with the coarser mesh lods, see constants::SHADOW_MESH_LOD_BIAS
+    pub fn get_shadow_static_render_element_groups(&self, shadow_visible_indices: &[u32]) -> Vec<RenderElementGroupData> {
+        self.get_static_render_element_groups_with_lod_bias(shadow_visible_indices, mesh::get_shadow_mesh_lod_bias())
+    }
+
+    fn get_static_render_element_groups_with_lod_bias(&self, visible_indices: &[u32], lod_bias: usize) -> Vec<RenderElementGroupData> {
+        let mut render_element_groups: Vec<RenderElementGroupData> = Vec::new();
+        let mut group_map: HashMap<(usize, usize), usize> = HashMap::new();
+        for index in visible_indices.iter() {
+            let render_object_data = self._render_object_storage.get_render_object_by_index(*index).borrow();
+            if render_object_data._mesh_data.borrow().has_animation_data() {
+                continue;
+            }
+            let world_matrix = &self._render_object_storage._world_matrices[*index as usize];
+            let model_data = render_object_data._model_data.borrow();
+            let mesh_data = model_data._mesh_data.borrow();
+            let lod_index = self._render_object_storage.get_mesh_lod_index(*index, lod_bias);
+            for (geometry_index, geometry_data) in mesh_data.get_lod_geometry_datas(lod_index).iter().enumerate() {
+                let material_instance_data = model_data.get_material_instance_data(geometry_index);
+                let group_key = (Rc::as_ptr(geometry_data) as usize, Rc::as_ptr(material_instance_data) as usize);
+                let group_index = *group_map.entry(group_key).or_insert_with(|| {
+                    render_element_groups.push(RenderElementGroupData {
+                        _geometry_data: geometry_data.clone(),
+                        _material_instance_data: material_instance_data.clone(),
+                        _world_matrices: Vec::new(),
+                        _object_ids: Vec::new(),
+                        _coverages: Vec::new(),
+                        _bound_spheres: Vec::new(),
+                        _material_parameter_overrides: Vec::new(),
+                        _instance_offset: 0,
+                        _draw_command_index: occlusion_culling::INVALID_DRAW_COMMAND_INDEX,
+                    });
+                    render_element_groups.len() - 1
+                });
+                render_element_groups[group_index]._world_matrices.push(world_matrix.clone());
+                render_element_groups[group_index]._object_ids.push(self._render_object_storage.get_object_id_by_index(*index));
+                render_element_groups[group_index]._bound_spheres.push(self._render_object_storage._bound_spheres[*index as usize]);
+                // the render objects without their own overrides use the uniform values of the material instance
+                let material_parameter_overrides = match render_object_data._material_parameter_overrides {
+                    Some(material_parameter_overrides) => material_parameter_overrides,
+                    None => material_instance_data.borrow().get_material_parameter_overrides(),
+                };
+                render_element_groups[group_index]._material_parameter_overrides.push(material_parameter_overrides);
+                if let Some(coverage) = self._visible_coverages.get(*index as usize) {
+                    render_element_groups[group_index]._coverages.push(*coverage);
+                }
+            }
+        }
+        render_element_groups
+    }
+
+    // the single instance path of the objects with the animation, the bone matrices are per object.
+    // the skinned vertices are shared by the main and the shadow passes, so the shadows keep the lod of the main camera.
+    pub fn get_skeletal_render_elements(&self, visible_indices: &[u32]) -> Vec<RenderElementData> {
+        let mut render_elements: Vec<RenderElementData> = Vec::new();
+        for index in visible_indices.iter() {
+            let render_object = self._render_object_storage.get_render_object_by_index(*index);
+            let render_object_data = render_object.borrow();
+            if false == render_object_data._mesh_data.borrow().has_animation_data() {
+                continue;
+            }
+            let model_data = render_object_data._model_data.borrow();
+            let lod_index = self._render_object_storage.get_mesh_lod_index(*index, 0);
+            for (geometry_index, geometry_data) in model_data._mesh_data.borrow().get_lod_geometry_datas(lod_index).iter().enumerate() {
+                render_elements.push(RenderElementData {
+                    _render_object: render_object.clone(),
+                    _geometry_data: geometry_data.clone(),
+                    _material_instance_data: model_data.get_material_instance_data(geometry_index).clone(),
+                    _object_id: self._render_object_storage.get_object_id_by_index(*index),
+                    _coverage: self._visible_coverages.get(*index as usize).cloned().unwrap_or(0.0),
+                    _bone_matrix_offset: gpu_skinning::INVALID_SKINNING_OFFSET,
+                    _prev_bone_matrix_offset: gpu_skinning::INVALID_SKINNING_OFFSET,
+                    _skinned_vertex_offset: gpu_skinning::INVALID_SKINNING_OFFSET,
+                    _material_parameter_overrides: render_object_data._material_parameter_overrides,
+                });
+            }
+        }
+        render_elements
     }
 
     pub fn save_scene_data(&mut self) {
         self.get_project_scene_manager_mut().save_scene_data();
+        self.save_environment_settings();
+    }
+
+    pub fn get_scene_file_path(&self) -> PathBuf {
+        let mut scene_file_path = PathBuf::from(SCENE_FILE_PATH);
+        scene_file_path.push(&self._scene_data_name);
+        scene_file_path.set_extension("scene");
+        scene_file_path
+    }
+
+    pub fn get_environment_settings(&self) -> &EnvironmentSettings {
+        &self._environment_settings
+    }
+
+    pub fn set_environment_settings(&mut self, environment_settings: &EnvironmentSettings) {
+        self._environment_settings = environment_settings.clone();
+        if environment_settings._wind._ocean_follows_wind {
+            self._environment_settings._ocean._simulation_wind = environment_settings._wind.get_ocean_simulation_wind();
+        }
+        self._wind_system.set_wind_settings(&self._environment_settings._wind);
+        self._time_of_day.set_time_of_day_settings(&self._environment_settings._time_of_day_settings, self._environment_settings._time_of_day);
+        let environment_settings = self._environment_settings.clone();
+        self.get_project_scene_manager_mut().apply_environment_settings(&environment_settings);
+    }
+
+    // the static meshes of the render objects at their current transforms. the skeletal meshes are not collided.
+    // called at the scene load, the project calls it again after placing the static objects later.
+    pub fn rebuild_collision_world(&mut self) {
+        let mut collision_sources: Vec<CollisionSource> = Vec::new();
+        for object_handle in self._render_object_storage.get_handles() {
+            if let Some(render_object_data) = self._render_object_storage.get_render_object(&object_handle) {
+                let render_object_data = render_object_data.borrow();
+                let mesh_data = render_object_data._mesh_data.borrow();
+                if let Some(collision_data) = mesh_data._collision_data.as_ref() {
+                    collision_sources.push(CollisionSource {
+                        _collision_data: collision_data.clone(),
+                        _world_matrix: render_object_data._transform_object.get_matrix().clone(),
+                        _object_id: object_handle.get_object_id(),
+                    });
+                }
+            }
+        }
+        self._collision_world.build_collision_world(collision_sources);
+    }
+
+    pub fn get_collision_world(&self) -> &CollisionWorld {
+        &self._collision_world
+    }
+
+    // the object of the hit is get_render_object_by_object_id, the same id as the object picking.
+    // ex) the ray of the cursor from the camera position, CollisionNotReady while the bvh is building
+    pub fn raycast(&self, origin: &Vector3<f32>, direction: &Vector3<f32>, max_distance: f32) -> Result<Option<CollisionHit>, CollisionNotReady> {
+        self._collision_world.raycast(origin, direction, max_distance)
+    }
+
+    // called after the update event of the application with the camera position before it.
+    // the camera moves freely while the collision world is building.
+    pub fn update_camera_collision(&self, camera_position_prev: &Vector3<f32>) {
+        if false == unsafe { constants::ENABLE_CAMERA_COLLISION } {
+            return;
+        }
+        let (camera_position, camera_rotation) = self.get_main_camera_transform();
+        if camera_position == *camera_position_prev {
+            return;
+        }
+        let radius = unsafe { constants::CAMERA_COLLISION_RADIUS.max(0.0) };
+        let mut resolved_position = self._collision_world.resolve_sphere_movement(camera_position_prev, &camera_position, radius).unwrap_or(camera_position.clone());
+        // the terrain is a height field, not in the collision world
+        if let Some(terrain_height) = self.get_terrain_height(&Vector2::new(resolved_position.x, resolved_position.z)) {
+            resolved_position.y = resolved_position.y.max(terrain_height + radius);
+        }
+        if resolved_position != camera_position {
+            self.set_main_camera_transform(&resolved_position, &camera_rotation);
+        }
+    }
+
+    // the ground height of the terrain at the world x, z. None outside the terrain or without the terrain.
+    // ex) camera_position.y = scene_manager_data.get_terrain_height(&Vector2::new(x, z)).unwrap_or(camera_position.y) + eye_height
+    pub fn get_terrain_height(&self, world_xz: &Vector2<f32>) -> Option<f32> {
+        self._renderer_data.borrow()._terrain.borrow().get_height(world_xz)
+    }
+
+    pub fn get_wind_system(&self) -> &WindSystem {
+        &self._wind_system
+    }
+
+    pub fn get_time_of_day(&self) -> &TimeOfDay {
+        &self._time_of_day
+    }
+
+    // hours: 0.0 ~ 24.0, the time of day is enabled. the sun is applied at the next update_scene_manager_data
+    pub fn set_time_of_day(&mut self, hours: f32) {
+        self._time_of_day.set_time(hours);
+        self._environment_settings._time_of_day = self._time_of_day.get_hour();
+        self._environment_settings._time_of_day_settings._enable_time_of_day = true;
+    }
+
+    // hours per second, 0.0 stops the time
+    pub fn set_time_of_day_auto_advance_speed(&mut self, auto_advance_speed: f32) {
+        self._time_of_day.set_auto_advance_speed(auto_advance_speed);
+        self._environment_settings._time_of_day_settings._auto_advance_speed = auto_advance_speed;
+    }
+
+    fn update_time_of_day(&mut self, delta_time: f32) {
+        if self._time_of_day.update_time_of_day(delta_time) {
+            let (sun_pitch, sun_yaw) = time_of_day::get_sun_pitch_yaw(self._time_of_day.get_sun_direction());
+            self._environment_settings._time_of_day = self._time_of_day.get_hour();
+            self._environment_settings._atmosphere._sun_pitch = sun_pitch;
+            self._environment_settings._atmosphere._sun_yaw = sun_yaw;
+            self.get_project_scene_manager_mut().set_main_light(&self._time_of_day.get_sun_rotation(), self._time_of_day.get_sun_light_color());
+        }
+    }
+
+    pub fn update_wind_stats(&self, font_manager: &mut FontManager) {
+        if self._wind_system.is_wind_enabled() {
+            font_manager.log(self._wind_system.get_stats_text());
+        }
+    }
+
+    // replaces the whole block, so the values of the previous scene never leak into the next scene.
+    pub fn load_environment_settings(&mut self) {
+        let scene_file_path = self.get_scene_file_path();
+        let resources = self._resources.clone();
+        let resources = resources.borrow();
+        let scene_data: Value = if resources.resolve_path(&scene_file_path).is_file() {
+            serde_json::from_reader(resources.read_bytes(&scene_file_path)).unwrap_or(Value::Null)
+        } else {
+            Value::Null
+        };
+        let environment_settings = EnvironmentSettings::create_environment_settings(&scene_data);
+        self.set_environment_settings(&environment_settings);
+    }
+
+    // writes the current values back into the scene file saved by the project scene manager.
+    pub fn save_environment_settings(&self) {
+        let scene_file_path = self._resources.borrow().resolve_path(&self.get_scene_file_path());
+        let mut scene_data: Value = match fs::read_to_string(&scene_file_path).map(|contents| serde_json::from_str::<Value>(&contents)) {
+            Ok(Ok(scene_data)) => scene_data,
+            _ => {
+                log::warn!("save_environment_settings: not a json scene {:?}", scene_file_path);
+                return;
+            }
+        };
+        self._environment_settings.write_environment_settings(&mut scene_data);
+        fs::write(&scene_file_path, serde_json::to_string_pretty(&scene_data).unwrap()).expect("Failed to write");
     }
 
     pub fn destroy_scene_manager_data(&mut self, device: &Device) {
@@ -97,7 +689,45 @@ impl SceneManagerData {
         self.get_project_scene_manager_mut().resized_window(width, height);
     }
 
-    pub fn update_scene_manager_data(&self, time_data: &TimeData, font_manager: &mut FontManager) {
+    pub fn set_main_camera_transform(&self, position: &Vector3<f32>, rotation: &Vector3<f32>) {
+        self.get_project_scene_manager_mut().set_main_camera_transform(position, rotation);
+    }
+
+    pub fn get_main_camera_transform(&self) -> (Vector3<f32>, Vector3<f32>) {
+        self.get_project_scene_manager().get_main_camera_transform()
+    }
+
+    pub fn save_project_state(&self) -> Vec<u8> {
+        self.get_project_scene_manager().save_project_state()
+    }
+
+    pub fn load_project_state(&self, project_state: &[u8]) {
+        self.get_project_scene_manager_mut().load_project_state(project_state);
+    }
+
+    // the render objects registered in the storage are updated here after the project, not by the project.
+    // the project runs once per frame, then the simulation steps of the fixed time step.
+    // the render objects are drawn between the last two steps, so the motion is smooth with any frame rate.
+    pub fn update_scene_manager_data(&mut self, time_data: &mut TimeData, font_manager: &mut FontManager) {
+        let fixed_step_count = time_data.update_fixed_time_step();
         self.get_project_scene_manager_mut().update_project_scene_manager(time_data, font_manager);
+        for _ in 0..fixed_step_count {
+            let fixed_time_data = time_data.next_fixed_step_time_data();
+            self.update_scene_simulation(&fixed_time_data);
+        }
+        self._render_object_storage.interpolate_world_matrices(time_data._fixed_step_alpha);
+        self._collision_world.update_collision_world();
+        for local_light_data in self._local_lights.values_mut() {
+            local_light_data.update_local_light_data();
+        }
+        font_manager.log(time_data.get_fixed_step_stats_text());
+        self._render_object_storage.clear_dirty_flags();
+    }
+
+    fn update_scene_simulation(&mut self, time_data: &TimeData) {
+        self.update_time_of_day(time_data._delta_time as f32);
+        self.get_project_scene_manager_mut().update_project_simulation(time_data);
+        self._render_object_storage.update_render_object_storage(time_data._delta_time as f32);
+        self._wind_system.update_wind_system(time_data._elapsed_time, &self._render_object_storage);
     }
 }
\ No newline at end of file
@@ -0,0 +1,402 @@
+use std::fs;
+use std::path::{ Path, PathBuf };
+
+use nalgebra::Vector3;
+use serde::{ Serialize, Deserialize };
+use winit::dpi;
+use winit::window::Window;
+
+use crate::application::scene_manager::SceneManagerData;
+use crate::constants;
+use crate::renderer::render_object::{ RenderObjectCreateInfo, RenderObjectData };
+use crate::renderer::render_object_storage::{ self, ObjectHandle };
+use crate::renderer::renderer::{ self, RendererData };
+use crate::resource::resource::DEFAULT_MODEL_NAME;
+use crate::utilities::system::newRcRefCell;
+use crate::utilities::telemetry::{ self, ResourceClass, Telemetry };
+
+// the scripted stress sequence of the resize, reload, deferred destruction and descriptor pooling paths.
+// it runs in the main loop like the benchmark, the ci runners with a gpu or lavapipe run it under a virtual display.
+// ex) --soak --soak_scene=default --soak_output=soak/report
+pub const SOAK_ARG: &str = "--soak";
+pub const SOAK_SCENE_ARG: &str = "--soak_scene=";
+pub const SOAK_OUTPUT_ARG: &str = "--soak_output=";
+pub const DEFAULT_SOAK_SCENE_NAME: &str = "default";
+pub const DEFAULT_SOAK_OUTPUT_PATH: &str = "soak/report";
+pub const SOAK_VALIDATION_LAYER: &str = "VK_LAYER_KHRONOS_validation";
+pub const SOAK_SCENE_RELOAD_COUNT: usize = 20;
+pub const SOAK_RENDER_OBJECT_COUNT: usize = 1000;
+pub const SOAK_EFFECT_COUNT: usize = 100;
+pub const SOAK_RESIZE_SIZES: [(u32, u32); 5] = [(1280, 720), (640, 360), (1920, 1080), (333, 777), (1024, 768)];
+// the frames after each step, the deferred destruction of the swapchain images and the async resize events are done in these.
+pub const SOAK_SETTLE_FRAMES: u32 = constants::SWAPCHAIN_IMAGE_COUNT as u32 + 4;
+pub const SOAK_EXIT_CODE_SUCCESS: i32 = 0;
+pub const SOAK_EXIT_CODE_FAILED: i32 = 1;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Copy, PartialEq, Eq)]
+pub enum SoakPhase {
+    SceneReload,
+    Resize,
+    PostProcessToggle,
+    RenderObjectSpawn,
+    EffectSpawn,
+    DebugRenderTarget,
+    QualityPreset,
+}
+
+pub const SOAK_PHASES: [SoakPhase; 7] = [
+    SoakPhase::SceneReload,
+    SoakPhase::Resize,
+    SoakPhase::PostProcessToggle,
+    SoakPhase::RenderObjectSpawn,
+    SoakPhase::EffectSpawn,
+    SoakPhase::DebugRenderTarget,
+    SoakPhase::QualityPreset,
+];
+
+// the engine has no particle system and no quality presets, the project runs these phases.
+// without the hooks the phases are skipped and marked in the report.
+pub trait SoakTestHooks {
+    fn spawn_effect(&mut self, _effect_index: usize) -> bool { false }
+    fn kill_effects(&mut self) {}
+    // the current preset first, it is applied again at the end of the phase.
+    fn get_quality_preset_names(&self) -> Vec<String> { Vec::new() }
+    fn apply_quality_preset(&mut self, _quality_preset_name: &str) {}
+}
+
+static mut SOAK_TEST_HOOKS: Option<Box<dyn SoakTestHooks>> = None;
+
+pub fn set_soak_test_hooks(soak_test_hooks: Box<dyn SoakTestHooks>) {
+    unsafe { SOAK_TEST_HOOKS = Some(soak_test_hooks); }
+}
+
+fn get_soak_test_hooks() -> Option<&'static mut Box<dyn SoakTestHooks>> {
+    unsafe { SOAK_TEST_HOOKS.as_mut() }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SoakSettings {
+    pub _scene_name: String,
+    pub _output_path: PathBuf, // without the extension, .json and .txt are written
+}
+
+impl Default for SoakSettings {
+    fn default() -> SoakSettings {
+        SoakSettings {
+            _scene_name: String::from(DEFAULT_SOAK_SCENE_NAME),
+            _output_path: PathBuf::from(DEFAULT_SOAK_OUTPUT_PATH),
+        }
+    }
+}
+
+impl SoakSettings {
+    // None without --soak
+    pub fn create_soak_settings(args: &[String]) -> Option<SoakSettings> {
+        if false == args.iter().any(|arg| SOAK_ARG == arg) {
+            return None;
+        }
+
+        let mut soak_settings = SoakSettings::default();
+        for arg in args.iter() {
+            if arg.starts_with(SOAK_SCENE_ARG) {
+                soak_settings._scene_name = String::from(&arg[SOAK_SCENE_ARG.len()..]);
+            } else if arg.starts_with(SOAK_OUTPUT_ARG) {
+                soak_settings._output_path = PathBuf::from(&arg[SOAK_OUTPUT_ARG.len()..]);
+            }
+        }
+        Some(soak_settings)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SoakPhaseResult {
+    pub _phase: SoakPhase,
+    pub _step_count: usize,
+    pub _is_skipped: bool,
+    pub _validation_error_count: u32,
+    pub _grown_resource_classes: Vec<ResourceClass>, // the outstanding counts over the baseline of the phase start
+    pub _telemetry: String,
+}
+
+impl SoakPhaseResult {
+    pub fn is_passed(&self) -> bool {
+        0 == self._validation_error_count && self._grown_resource_classes.is_empty()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SoakReport {
+    pub _scene_name: String,
+    pub _date: String,
+    pub _phase_results: Vec<SoakPhaseResult>,
+}
+
+impl SoakReport {
+    pub fn is_passed(&self) -> bool {
+        self._phase_results.iter().all(|phase_result| phase_result.is_passed())
+    }
+
+    pub fn get_report_text(&self) -> String {
+        let mut lines: Vec<String> = vec![
+            format!("soak test: {} {}", self._scene_name, self._date),
+            format!("result: {}", if self.is_passed() { "passed" } else { "FAILED" }),
+        ];
+        for phase_result in self._phase_results.iter() {
+            let status = if phase_result._is_skipped { "skipped" } else if phase_result.is_passed() { "ok" } else { "FAILED" };
+            lines.push(format!(
+                "    {:?}: {}, steps: {}, validation errors: {}, grown: {:?}",
+                phase_result._phase,
+                status,
+                phase_result._step_count,
+                phase_result._validation_error_count,
+                phase_result._grown_resource_classes
+            ));
+            if false == phase_result.is_passed() {
+                lines.push(format!("        {}", phase_result._telemetry));
+            }
+        }
+        lines.join("\n")
+    }
+
+    pub fn write_soak_report(&self, output_path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = output_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(output_path.with_extension("json"), serde_json::to_string_pretty(self).unwrap())?;
+        let report_text = self.get_report_text();
+        fs::write(output_path.with_extension("txt"), &report_text)?;
+        log::info!("{}", report_text);
+        Ok(())
+    }
+}
+
+// ssao, ssr, taa, bloom, auto exposure
+type PostProcessFlags = [bool; 5];
+
+fn get_post_process_flags() -> PostProcessFlags {
+    unsafe {
+        [
+            constants::POST_PROCESS_SSAO,
+            constants::POST_PROCESS_SSR,
+            constants::POST_PROCESS_TAA,
+            constants::POST_PROCESS_BLOOM,
+            constants::POST_PROCESS_AUTO_EXPOSURE,
+        ]
+    }
+}
+
+fn set_post_process_flags(post_process_flags: &PostProcessFlags) {
+    unsafe {
+        constants::POST_PROCESS_SSAO = post_process_flags[0];
+        constants::POST_PROCESS_SSR = post_process_flags[1];
+        constants::POST_PROCESS_TAA = post_process_flags[2];
+        constants::POST_PROCESS_BLOOM = post_process_flags[3];
+        constants::POST_PROCESS_AUTO_EXPOSURE = post_process_flags[4];
+    }
+}
+
+// each phase runs its steps with SOAK_SETTLE_FRAMES between them and leaves the state as it was,
+// so the outstanding resources at the end are compared with the start of the phase.
+pub struct SoakData {
+    pub _settings: SoakSettings,
+    pub _phase_index: usize,
+    pub _step_index: usize,
+    pub _settle_frames: u32,
+    pub _baseline: Option<(Telemetry, u32)>, // telemetry, validation error count at the phase start
+    pub _window_size: (u32, u32), // restored by the resize phase
+    pub _post_process_flags: PostProcessFlags, // restored by the post process phase
+    pub _debug_render_target_names: Vec<String>,
+    pub _quality_preset_names: Vec<String>,
+    pub _render_object_handles: Vec<ObjectHandle>,
+    pub _phase_results: Vec<SoakPhaseResult>,
+}
+
+impl SoakData {
+    pub fn create_soak_data(soak_settings: SoakSettings) -> SoakData {
+        SoakData {
+            _settings: soak_settings,
+            _phase_index: 0,
+            _step_index: 0,
+            _settle_frames: SOAK_SETTLE_FRAMES,
+            _baseline: None,
+            _window_size: (0, 0),
+            _post_process_flags: get_post_process_flags(),
+            _debug_render_target_names: Vec::new(),
+            _quality_preset_names: Vec::new(),
+            _render_object_handles: Vec::new(),
+            _phase_results: Vec::new(),
+        }
+    }
+
+    // before the renderer is created, the validation layer is forced on.
+    pub fn apply_soak_test_configs() {
+        unsafe {
+            constants::ENABLE_VALIDATION_LAYER = true;
+            if false == constants::VULKAN_LAYERS.iter().any(|layer_name| SOAK_VALIDATION_LAYER == layer_name) {
+                constants::VULKAN_LAYERS.push(String::from(SOAK_VALIDATION_LAYER));
+            }
+            constants::ENABLE_IMMEDIATE_MODE = true;
+            constants::ENABLE_FILE_WATCHER = false;
+        }
+    }
+
+    pub fn begin_soak_test(&mut self, scene_manager_data: &mut SceneManagerData, renderer_data: &RendererData) {
+        log::info!("begin_soak_test: {:?}", self._settings);
+        scene_manager_data.close_scene_data(renderer_data.get_device());
+        scene_manager_data._scene_data_name = self._settings._scene_name.clone();
+        scene_manager_data.open_scene_data();
+        renderer_data.invalidate_temporal_history();
+    }
+
+    pub fn is_finished(&self) -> bool {
+        SOAK_PHASES.len() <= self._phase_index
+    }
+
+    fn get_step_count(&self, soak_phase: SoakPhase) -> usize {
+        let has_soak_test_hooks = get_soak_test_hooks().is_some();
+        match soak_phase {
+            SoakPhase::SceneReload => SOAK_SCENE_RELOAD_COUNT,
+            SoakPhase::Resize => SOAK_RESIZE_SIZES.len() + 1,
+            SoakPhase::PostProcessToggle => self._post_process_flags.len() + 2,
+            SoakPhase::RenderObjectSpawn => 2,
+            SoakPhase::EffectSpawn => if has_soak_test_hooks { 2 } else { 0 },
+            SoakPhase::DebugRenderTarget => self._debug_render_target_names.len() + 1,
+            SoakPhase::QualityPreset => if self._quality_preset_names.is_empty() { 0 } else { self._quality_preset_names.len() + 1 },
+        }
+    }
+
+    fn begin_phase(&mut self, soak_phase: SoakPhase, window: &Window, renderer_data: &RendererData) {
+        log::info!("begin soak phase: {:?}", soak_phase);
+        let window_size = window.inner_size();
+        self._window_size = (window_size.width, window_size.height);
+        self._post_process_flags = get_post_process_flags();
+        self._debug_render_target_names = renderer_data.get_debug_render_target_names();
+        self._quality_preset_names = get_soak_test_hooks().map(|soak_test_hooks| soak_test_hooks.get_quality_preset_names()).unwrap_or_default();
+        self._baseline = Some((telemetry::get_telemetry(), renderer::get_validation_error_count()));
+    }
+
+    fn run_step(&mut self, soak_phase: SoakPhase, step_index: usize, window: &Window, scene_manager_data: &mut SceneManagerData, renderer_data: &RendererData) {
+        match soak_phase {
+            SoakPhase::SceneReload => {
+                scene_manager_data.close_scene_data(renderer_data.get_device());
+                scene_manager_data.open_scene_data();
+                renderer_data.invalidate_temporal_history();
+            },
+            SoakPhase::Resize => {
+                let (width, height) = SOAK_RESIZE_SIZES.get(step_index).cloned().unwrap_or(self._window_size);
+                window.set_inner_size(dpi::PhysicalSize::new(width, height));
+            },
+            SoakPhase::PostProcessToggle => {
+                let post_process_count = self._post_process_flags.len();
+                let mut post_process_flags = self._post_process_flags.clone();
+                if step_index < post_process_count {
+                    post_process_flags[step_index] = !post_process_flags[step_index];
+                } else if step_index == post_process_count {
+                    post_process_flags = [false; 5];
+                }
+                set_post_process_flags(&post_process_flags);
+            },
+            SoakPhase::RenderObjectSpawn => {
+                if 0 == step_index {
+                    let model_data = renderer_data._resources.borrow().get_model_data(DEFAULT_MODEL_NAME).clone();
+                    for i in 0..SOAK_RENDER_OBJECT_COUNT {
+                        let render_object_create_info = RenderObjectCreateInfo {
+                            _model_data_name: String::from(DEFAULT_MODEL_NAME),
+                            _position: Vector3::new((i % 32) as f32 * 2.0, 0.0, (i / 32) as f32 * 2.0),
+                            ..Default::default()
+                        };
+                        let render_object_data = newRcRefCell(RenderObjectData::create_render_object_data(&format!("soak_{}", i), &model_data, &render_object_create_info));
+                        let object_handle = scene_manager_data.add_render_object(&render_object_data, render_object_storage::LAYER_MASK_ALL);
+                        self._render_object_handles.push(object_handle);
+                    }
+                } else {
+                    for object_handle in self._render_object_handles.drain(..) {
+                        scene_manager_data.remove_render_object(&object_handle);
+                    }
+                }
+            },
+            SoakPhase::EffectSpawn => {
+                if let Some(soak_test_hooks) = get_soak_test_hooks() {
+                    if 0 == step_index {
+                        let spawned_count = (0..SOAK_EFFECT_COUNT).filter(|effect_index| soak_test_hooks.spawn_effect(*effect_index)).count();
+                        log::info!("soak effects: {} / {}", spawned_count, SOAK_EFFECT_COUNT);
+                    } else {
+                        soak_test_hooks.kill_effects();
+                    }
+                }
+            },
+            SoakPhase::DebugRenderTarget => {
+                match self._debug_render_target_names.get(step_index) {
+                    Some(render_target_name) => renderer_data.set_debug_render_target(render_target_name, 0, 0),
+                    None => renderer_data.clear_debug_render_target(),
+                }
+            },
+            SoakPhase::QualityPreset => {
+                if let Some(soak_test_hooks) = get_soak_test_hooks() {
+                    let quality_preset_name = &self._quality_preset_names[step_index % self._quality_preset_names.len()];
+                    soak_test_hooks.apply_quality_preset(quality_preset_name);
+                }
+                renderer_data.invalidate_temporal_history();
+            },
+        }
+    }
+
+    fn end_phase(&mut self, soak_phase: SoakPhase) {
+        let (baseline_telemetry, baseline_validation_error_count) = self._baseline.take().unwrap();
+        let current_telemetry = telemetry::get_telemetry();
+        let step_count = self.get_step_count(soak_phase);
+        let phase_result = SoakPhaseResult {
+            _phase: soak_phase,
+            _step_count: step_count,
+            _is_skipped: 0 == step_count,
+            _validation_error_count: renderer::get_validation_error_count() - baseline_validation_error_count,
+            _grown_resource_classes: current_telemetry.get_grown_resource_classes(&baseline_telemetry),
+            _telemetry: current_telemetry.to_log_string(),
+        };
+        if phase_result.is_passed() {
+            log::info!("end soak phase: {:?}", soak_phase);
+        } else {
+            log::error!("soak phase failed: {:?}", phase_result);
+        }
+        self._phase_results.push(phase_result);
+    }
+
+    // one step per call, the steps and the phase ends wait for SOAK_SETTLE_FRAMES.
+    pub fn update_soak_frame(&mut self, window: &Window, scene_manager_data: &mut SceneManagerData, renderer_data: &RendererData) {
+        if self.is_finished() {
+            return;
+        }
+        if 0 < self._settle_frames {
+            self._settle_frames -= 1;
+            return;
+        }
+
+        let soak_phase = SOAK_PHASES[self._phase_index];
+        if self._baseline.is_none() {
+            self.begin_phase(soak_phase, window, renderer_data);
+        }
+        if self._step_index < self.get_step_count(soak_phase) {
+            self.run_step(soak_phase, self._step_index, window, scene_manager_data, renderer_data);
+            self._step_index += 1;
+        } else {
+            self.end_phase(soak_phase);
+            self._phase_index += 1;
+            self._step_index = 0;
+        }
+        self._settle_frames = SOAK_SETTLE_FRAMES;
+    }
+
+    // returns the exit code of the process
+    pub fn end_soak_test(&self) -> i32 {
+        let soak_report = SoakReport {
+            _scene_name: self._settings._scene_name.clone(),
+            _date: chrono::Local::now().to_rfc3339(),
+            _phase_results: self._phase_results.clone(),
+        };
+        if let Err(e) = soak_report.write_soak_report(&self._settings._output_path) {
+            log::error!("write_soak_report failed {:?}: {:?}", self._settings._output_path, e);
+            return SOAK_EXIT_CODE_FAILED;
+        }
+        if soak_report.is_passed() { SOAK_EXIT_CODE_SUCCESS } else { SOAK_EXIT_CODE_FAILED }
+    }
+}
@@ -0,0 +1,192 @@
+use nalgebra::Vector3;
+use serde::{ Serialize, Deserialize };
+
+use crate::utilities::math;
+
+pub const HOURS_PER_DAY: f32 = 24.0;
+pub const DAYS_PER_YEAR: f32 = 365.0;
+pub const TIME_OF_DAY_STEP: f32 = 0.25; // hour, Comma / Period
+pub const AXIAL_TILT: f32 = 23.44; // degree
+pub const SUN_FADE_ELEVATION: f32 = 2.0; // degree, the sun fades out between +- this elevation around the horizon
+// the vertical optical depth of the rayleigh and the aerosol at the sea level, r g b. the analytic approximation of the transmittance.
+pub const SUN_OPTICAL_DEPTH: [f32; 3] = [0.15, 0.2, 0.34];
+
+// "_time_of_day_settings" block of the environment settings, the hour is EnvironmentSettings::_time_of_day.
+// ex) "_time_of_day_settings": { "_enable_time_of_day": true, "_latitude": 37.5, "_day_of_year": 172, "_auto_advance_speed": 0.1 }
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct TimeOfDaySettings {
+    pub _enable_time_of_day: bool, // false: the project keeps the main light and the sun of the atmosphere settings
+    pub _latitude: f32, // degree, north is positive
+    pub _day_of_year: f32, // 0 ~ 365, the solar declination
+    pub _north_yaw: f32, // degree, the rotation of the north around the y axis. 0: the north is +z, the east is +x
+    pub _auto_advance_speed: f32, // hours per second, 0: the time stays
+    pub _sun_color: Vector3<f32>,
+    pub _sun_intensity: f32, // the light color at the zenith without the atmosphere
+}
+
+// the sun of the time of day, the main DirectionalLightData of the project and the sun of the atmosphere follow this.
+#[derive(Clone, Debug)]
+pub struct TimeOfDay {
+    pub _settings: TimeOfDaySettings,
+    pub _hour: f32, // 0.0 ~ 24.0
+    pub _sun_direction: Vector3<f32>, // toward the sun
+    pub _sun_pitch: f32, // degree, AtmosphereSettings::_sun_pitch
+    pub _sun_yaw: f32, // degree, AtmosphereSettings::_sun_yaw
+    pub _sun_elevation: f32, // degree
+    pub _sun_light_color: Vector3<f32>, // zero at the night
+    pub _need_update: bool,
+}
+
+impl Default for TimeOfDaySettings {
+    fn default() -> TimeOfDaySettings {
+        TimeOfDaySettings {
+            _enable_time_of_day: false,
+            _latitude: 37.5,
+            _day_of_year: 172.0,
+            _north_yaw: 0.0,
+            _auto_advance_speed: 0.0,
+            _sun_color: Vector3::new(1.0, 1.0, 1.0),
+            _sun_intensity: 10.0,
+        }
+    }
+}
+
+impl Default for TimeOfDay {
+    fn default() -> TimeOfDay {
+        TimeOfDay::create_time_of_day(&TimeOfDaySettings::default(), 12.0)
+    }
+}
+
+pub fn wrap_hour(hour: f32) -> f32 {
+    hour.rem_euclid(HOURS_PER_DAY)
+}
+
+// ex) 14.5 -> "14:30"
+pub fn get_hour_text(hour: f32) -> String {
+    let minutes = (wrap_hour(hour) * 60.0).round() as u32 % (HOURS_PER_DAY as u32 * 60);
+    format!("{:02}:{:02}", minutes / 60, minutes % 60)
+}
+
+// the declination of the day, the summer solstice is around the day 172
+pub fn get_solar_declination(day_of_year: f32) -> f32 {
+    -AXIAL_TILT * (2.0 * std::f32::consts::PI * (day_of_year + 10.0) / DAYS_PER_YEAR).cos()
+}
+
+// toward the sun in the world space, the local solar time without the equation of time
+pub fn get_sun_direction(settings: &TimeOfDaySettings, hour: f32) -> Vector3<f32> {
+    let latitude = math::degree_to_radian(settings._latitude);
+    let declination = math::degree_to_radian(get_solar_declination(settings._day_of_year));
+    let hour_angle = math::degree_to_radian((wrap_hour(hour) - 12.0) * 15.0);
+    let east = -declination.cos() * hour_angle.sin();
+    let north = latitude.cos() * declination.sin() - latitude.sin() * declination.cos() * hour_angle.cos();
+    let up = latitude.sin() * declination.sin() + latitude.cos() * declination.cos() * hour_angle.cos();
+    let (sin_yaw, cos_yaw) = math::degree_to_radian(settings._north_yaw).sin_cos();
+    Vector3::new(east * cos_yaw + north * sin_yaw, up, north * cos_yaw - east * sin_yaw).normalize()
+}
+
+// the inverse of AtmosphereSettings::get_sun_direction, (pitch, yaw) in degree
+pub fn get_sun_pitch_yaw(sun_direction: &Vector3<f32>) -> (f32, f32) {
+    let pitch = -sun_direction.y.max(-1.0).min(1.0).asin();
+    let yaw = sun_direction.x.atan2(sun_direction.z);
+    (math::radian_to_degree(pitch), math::radian_to_degree(yaw))
+}
+
+// exp(-optical depth * air mass), the air mass of Kasten and Young. the zenith angle is clamped at the horizon, so it is finite.
+pub fn get_sun_transmittance(sun_elevation: f32) -> Vector3<f32> {
+    let zenith_angle = (90.0 - sun_elevation).max(0.0).min(90.0);
+    let air_mass = 1.0 / (math::degree_to_radian(zenith_angle).cos() + 0.50572 * (96.07995 - zenith_angle).powf(-1.6364));
+    Vector3::new(
+        (-SUN_OPTICAL_DEPTH[0] * air_mass).exp(),
+        (-SUN_OPTICAL_DEPTH[1] * air_mass).exp(),
+        (-SUN_OPTICAL_DEPTH[2] * air_mass).exp()
+    )
+}
+
+impl TimeOfDay {
+    pub fn create_time_of_day(settings: &TimeOfDaySettings, hour: f32) -> TimeOfDay {
+        let mut time_of_day = TimeOfDay {
+            _settings: settings.clone(),
+            _hour: wrap_hour(hour),
+            _sun_direction: Vector3::new(0.0, 1.0, 0.0),
+            _sun_pitch: -90.0,
+            _sun_yaw: 0.0,
+            _sun_elevation: 90.0,
+            _sun_light_color: Vector3::zeros(),
+            _need_update: true,
+        };
+        time_of_day.update_sun();
+        time_of_day
+    }
+
+    pub fn set_time_of_day_settings(&mut self, settings: &TimeOfDaySettings, hour: f32) {
+        if self._settings != *settings || self._hour != wrap_hour(hour) {
+            self._settings = settings.clone();
+            self._hour = wrap_hour(hour);
+            self.update_sun();
+        }
+    }
+
+    pub fn is_time_of_day_enabled(&self) -> bool {
+        self._settings._enable_time_of_day
+    }
+
+    pub fn get_hour(&self) -> f32 {
+        self._hour
+    }
+
+    // the manual time enables the time of day
+    pub fn set_time(&mut self, hours: f32) {
+        self._settings._enable_time_of_day = true;
+        self._hour = wrap_hour(hours);
+        self.update_sun();
+    }
+
+    pub fn set_auto_advance_speed(&mut self, auto_advance_speed: f32) {
+        self._settings._auto_advance_speed = auto_advance_speed;
+    }
+
+    pub fn get_sun_direction(&self) -> &Vector3<f32> {
+        &self._sun_direction
+    }
+
+    // the rotation of the main DirectionalLightData in radian, the front of the transform is toward the sun
+    pub fn get_sun_rotation(&self) -> Vector3<f32> {
+        Vector3::new(math::degree_to_radian(self._sun_pitch), math::degree_to_radian(self._sun_yaw), 0.0)
+    }
+
+    pub fn get_sun_light_color(&self) -> &Vector3<f32> {
+        &self._sun_light_color
+    }
+
+    // returns true if the sun is changed, then the caller applies it to the main light and the atmosphere.
+    pub fn update_time_of_day(&mut self, delta_time: f32) -> bool {
+        if false == self._settings._enable_time_of_day {
+            return false;
+        }
+        if 0.0 != self._settings._auto_advance_speed {
+            self._hour = wrap_hour(self._hour + self._settings._auto_advance_speed * delta_time);
+            self.update_sun();
+        }
+        let need_update = self._need_update;
+        self._need_update = false;
+        need_update
+    }
+
+    fn update_sun(&mut self) {
+        self._sun_direction = get_sun_direction(&self._settings, self._hour);
+        let (sun_pitch, sun_yaw) = get_sun_pitch_yaw(&self._sun_direction);
+        self._sun_pitch = sun_pitch;
+        self._sun_yaw = sun_yaw;
+        self._sun_elevation = -sun_pitch;
+        // the sun below the horizon still gives the direction, but the light color is zero
+        let fade = ((self._sun_elevation + SUN_FADE_ELEVATION) / (2.0 * SUN_FADE_ELEVATION)).max(0.0).min(1.0);
+        let fade = fade * fade * (3.0 - 2.0 * fade);
+        self._sun_light_color = get_sun_transmittance(self._sun_elevation).component_mul(&self._settings._sun_color) * (self._settings._sun_intensity * fade);
+        self._need_update = true;
+    }
+
+    pub fn get_stats_text(&self) -> String {
+        format!("time of day: {}, sun elevation {:.1}", get_hour_text(self._hour), self._sun_elevation)
+    }
+}
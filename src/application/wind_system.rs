@@ -0,0 +1,451 @@
+use nalgebra::{ Vector3, Vector4 };
+use rand::{ Rng, SeedableRng };
+use rand::rngs::StdRng;
+use serde::{ Serialize, Deserialize };
+
+use crate::application::scene_manager::SceneManagerData;
+use crate::constants;
+use crate::renderer::render_object_storage::RenderObjectStorage;
+use crate::renderer::renderer::RendererData;
+use crate::renderer::shader_hook;
+use crate::utilities::bounding_box::BoundingBox;
+
+pub const WIND_COMMAND: &str = "wind";
+pub const WIND_NOISE_TABLE_SIZE: usize = 256; // power of two
+pub const WIND_SHADER_HOOK_PARAMETER_INDEX: usize = 0; // xyz: the sampled wind of the object, w: the wind time
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum WindVolumeShape {
+    Sphere { _radius: f32 },
+    Box { _half_extents: Vector3<f32> },
+}
+
+// a local wind added to the global wind, the strength falls off linearly over the outer _falloff ratio of the shape.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct WindVolume {
+    pub _name: String,
+    pub _position: Vector3<f32>,
+    pub _shape: WindVolumeShape,
+    pub _wind: Vector3<f32>, // m/s
+    pub _falloff: f32, // 0.0 ~ 1.0
+}
+
+// "_wind" block of the environment settings.
+// ex) "_wind": { "_wind_direction": [1, 0, 0.3], "_wind_speed": 4.0, "_wind_volumes": [{ "_name": "fan", "_shape": { "Sphere": { "_radius": 5.0 } }, "_wind": [0, 8, 0] }] }
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct WindSettings {
+    pub _enable_wind: bool,
+    pub _wind_direction: Vector3<f32>, // normalized
+    pub _wind_speed: f32, // m/s
+    pub _gustiness: f32, // 0.0: constant, 1.0: the gusts range from calm to twice the wind speed
+    pub _gust_frequency: f32, // Hz
+    pub _wind_seed: u64,
+    pub _ocean_follows_wind: bool, // OceanSettings::_simulation_wind tracks _wind_speed * _ocean_wind_scale, the gusts are not included
+    pub _ocean_wind_scale: f32,
+    pub _wind_volumes: Vec<WindVolume>,
+}
+
+// the layout of the wind uniform, or the wind block of the scene constants of the project.
+#[repr(C)]
+#[derive(Clone, Debug, Copy, Default)]
+pub struct WindConstants {
+    pub _wind_direction: Vector3<f32>,
+    pub _wind_speed: f32, // including the gust
+    pub _gust: f32, // 0.0 ~ 1.0
+    pub _gustiness: f32,
+    pub _wind_time: f32,
+    pub _reserved0: f32,
+}
+
+// the cached overlap of an object and the wind volumes, refreshed only when the object is moved or the volumes are changed.
+#[derive(Clone, Debug, Default)]
+pub struct ObjectWindState {
+    pub _generation: u32,
+    pub _is_wind_receiver: bool, // a material instance of the model has a wind vertex offset hook
+    pub _volume_indices: Vec<u32>,
+    pub _is_valid: bool,
+}
+
+// the global wind and the local wind volumes, sampled per object and per emitter each frame.
+// the foliage sway and the flags are the vertex offset hooks WIND_SWAY_SHADER_HOOK_FILE, WIND_FLAG_SHADER_HOOK_FILE,
+// the sampled wind of the object is written to its shader hook parameters. the particle emitters of the project
+// add get_wind_acceleration to the update compute pass with their own wind influence.
+pub struct WindSystem {
+    pub _wind_settings: WindSettings,
+    pub _noise_table: Vec<f32>,
+    pub _wind_time: f32,
+    pub _gust: f32,
+    pub _global_wind: Vector3<f32>,
+    pub _volume_bound_boxes: Vec<BoundingBox>,
+    pub _object_wind_states: Vec<ObjectWindState>, // indexed by ObjectHandle::_index
+    pub _object_winds: Vec<Vector3<f32>>,
+    pub _volume_test_count: u32, // of the last frame
+}
+
+impl Default for WindVolume {
+    fn default() -> WindVolume {
+        WindVolume {
+            _name: String::new(),
+            _position: Vector3::zeros(),
+            _shape: WindVolumeShape::Sphere { _radius: 1.0 },
+            _wind: Vector3::zeros(),
+            _falloff: 0.5,
+        }
+    }
+}
+
+impl Default for WindSettings {
+    fn default() -> WindSettings {
+        WindSettings {
+            _enable_wind: true,
+            _wind_direction: Vector3::new(1.0, 0.0, 0.0),
+            _wind_speed: 2.0,
+            _gustiness: 0.3,
+            _gust_frequency: 0.2,
+            _wind_seed: 1234,
+            _ocean_follows_wind: false,
+            _ocean_wind_scale: 0.1,
+            _wind_volumes: Vec::new(),
+        }
+    }
+}
+
+impl WindVolume {
+    pub fn get_bound_box(&self) -> BoundingBox {
+        let half_extents = match &self._shape {
+            WindVolumeShape::Sphere { _radius } => Vector3::new(*_radius, *_radius, *_radius),
+            WindVolumeShape::Box { _half_extents } => _half_extents.clone(),
+        };
+        let min = &self._position - &half_extents;
+        let max = &self._position + &half_extents;
+        BoundingBox {
+            _min: min,
+            _max: max,
+            _center: self._position.clone(),
+            _size: &half_extents * 2.0,
+            _radius: half_extents.norm(),
+        }
+    }
+
+    // 1.0 inside, 0.0 outside, linear in the falloff band
+    pub fn get_weight(&self, position: &Vector3<f32>) -> f32 {
+        let relative_position = position - &self._position;
+        let distance_ratio = match &self._shape {
+            WindVolumeShape::Sphere { _radius } => relative_position.norm() / _radius.max(std::f32::EPSILON),
+            WindVolumeShape::Box { _half_extents } => (0..3).map(|i| relative_position[i].abs() / _half_extents[i].max(std::f32::EPSILON)).fold(0.0, f32::max),
+        };
+        if 1.0 <= distance_ratio {
+            return 0.0;
+        }
+        let falloff = self._falloff.max(0.0).min(1.0);
+        if 0.0 == falloff {
+            return 1.0;
+        }
+        ((1.0 - distance_ratio) / falloff).min(1.0)
+    }
+}
+
+impl WindSettings {
+    // ex) ["speed", "5.0"], ["direction", "1", "0", "0.5"], ["volume", "add", "fan", "sphere", "0", "0", "0", "5", "0", "8", "0"], ["volume", "remove", "fan"]
+    pub fn set_wind_parameter(&mut self, args: &[&str]) -> Result<(), String> {
+        let parameter_name = args.first().ok_or(String::from("wind parameter name is missing"))?;
+        if "volume" == *parameter_name {
+            return self.set_wind_volume_parameter(&args[1..]);
+        }
+        let values: Vec<f32> = parse_wind_values(&args[1..])?;
+        let value = |count: usize| -> Result<Vec<f32>, String> {
+            if count == values.len() {
+                Ok(values.clone())
+            } else {
+                Err(format!("wind {} needs {} value(s), but {} given", parameter_name, count, values.len()))
+            }
+        };
+        match *parameter_name {
+            "enable" => self._enable_wind = 0.0 != value(1)?[0],
+            "speed" => self._wind_speed = value(1)?[0].max(0.0),
+            "direction" => self._wind_direction = Vector3::from_column_slice(&value(3)?).try_normalize(0.0).unwrap_or(Vector3::new(1.0, 0.0, 0.0)),
+            "gustiness" => self._gustiness = value(1)?[0].max(0.0).min(1.0),
+            "gust_frequency" => self._gust_frequency = value(1)?[0].max(0.0),
+            "seed" => self._wind_seed = value(1)?[0] as u64,
+            "ocean" => self._ocean_follows_wind = 0.0 != value(1)?[0],
+            "ocean_scale" => self._ocean_wind_scale = value(1)?[0].max(0.0),
+            _ => return Err(format!("unknown wind parameter: {}", parameter_name)),
+        }
+        Ok(())
+    }
+
+    fn set_wind_volume_parameter(&mut self, args: &[&str]) -> Result<(), String> {
+        match args {
+            ["add", volume_name, shape_name, rest @ ..] => {
+                let values = parse_wind_values(rest)?;
+                let (shape, wind_offset) = match (*shape_name, values.len()) {
+                    ("sphere", 7) => (WindVolumeShape::Sphere { _radius: values[3].max(0.0) }, 4),
+                    ("box", 9) => (WindVolumeShape::Box { _half_extents: Vector3::new(values[3], values[4], values[5]).abs() }, 6),
+                    _ => return Err(format!("wind volume add {}: needs sphere x y z radius wx wy wz, or box x y z hx hy hz wx wy wz", volume_name)),
+                };
+                let wind_volume = WindVolume {
+                    _name: String::from(*volume_name),
+                    _position: Vector3::new(values[0], values[1], values[2]),
+                    _shape: shape,
+                    _wind: Vector3::from_column_slice(&values[wind_offset..wind_offset + 3]),
+                    ..Default::default()
+                };
+                self._wind_volumes.retain(|volume| volume._name != wind_volume._name);
+                self._wind_volumes.push(wind_volume);
+            },
+            ["remove", volume_name] => {
+                let volume_count = self._wind_volumes.len();
+                self._wind_volumes.retain(|volume| volume._name != *volume_name);
+                if volume_count == self._wind_volumes.len() {
+                    return Err(format!("wind volume not found: {}", volume_name));
+                }
+            },
+            ["clear"] => self._wind_volumes.clear(),
+            _ => return Err(format!("unknown wind volume command: {:?}", args)),
+        }
+        Ok(())
+    }
+
+    pub fn get_ocean_simulation_wind(&self) -> f32 {
+        if self._enable_wind { self._wind_speed * self._ocean_wind_scale } else { 0.0 }
+    }
+}
+
+fn parse_wind_values(args: &[&str]) -> Result<Vec<f32>, String> {
+    args.iter().map(|arg| match *arg {
+        "true" | "on" => Ok(1.0),
+        "false" | "off" => Ok(0.0),
+        _ => arg.parse::<f32>().map_err(|_| format!("invalid wind parameter value: {}", arg)),
+    }).collect()
+}
+
+fn smooth_step(t: f32) -> f32 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+impl Default for WindSystem {
+    fn default() -> WindSystem {
+        WindSystem::create_wind_system(&WindSettings::default())
+    }
+}
+
+impl WindSystem {
+    pub fn create_wind_system(wind_settings: &WindSettings) -> WindSystem {
+        let mut wind_system = WindSystem {
+            _wind_settings: WindSettings::default(),
+            _noise_table: Vec::new(),
+            _wind_time: 0.0,
+            _gust: 0.5,
+            _global_wind: Vector3::zeros(),
+            _volume_bound_boxes: Vec::new(),
+            _object_wind_states: Vec::new(),
+            _object_winds: Vec::new(),
+            _volume_test_count: 0,
+        };
+        wind_system.set_wind_settings(wind_settings);
+        wind_system
+    }
+
+    // the same seed gives the same gusts, so the replays and the benchmark runs match.
+    pub fn set_wind_settings(&mut self, wind_settings: &WindSettings) {
+        if self._noise_table.is_empty() || self._wind_settings._wind_seed != wind_settings._wind_seed {
+            let mut rng = StdRng::seed_from_u64(wind_settings._wind_seed);
+            self._noise_table = (0..WIND_NOISE_TABLE_SIZE).map(|_i| rng.gen::<f32>()).collect();
+        }
+        if self._wind_settings._wind_volumes != wind_settings._wind_volumes {
+            self._volume_bound_boxes = wind_settings._wind_volumes.iter().map(|volume| volume.get_bound_box()).collect();
+            self.invalidate_object_wind_states();
+        }
+        self._wind_settings = wind_settings.clone();
+    }
+
+    pub fn get_wind_settings(&self) -> &WindSettings {
+        &self._wind_settings
+    }
+
+    pub fn invalidate_object_wind_states(&mut self) {
+        for object_wind_state in self._object_wind_states.iter_mut() {
+            object_wind_state._is_valid = false;
+        }
+    }
+
+    pub fn clear_wind_system(&mut self) {
+        self._object_wind_states.clear();
+        self._object_winds.clear();
+    }
+
+    // 1d value noise of the table, 0.0 ~ 1.0
+    fn sample_noise(&self, t: f32) -> f32 {
+        let mask = WIND_NOISE_TABLE_SIZE - 1;
+        let i = t.floor();
+        let f = smooth_step(t - i);
+        let i = (i as i64 & mask as i64) as usize;
+        self._noise_table[i] * (1.0 - f) + self._noise_table[(i + 1) & mask] * f
+    }
+
+    pub fn is_wind_enabled(&self) -> bool {
+        self._wind_settings._enable_wind && unsafe { constants::WIND_ENABLE }
+    }
+
+    pub fn get_global_wind(&self) -> &Vector3<f32> {
+        &self._global_wind
+    }
+
+    pub fn get_wind_constants(&self) -> WindConstants {
+        let wind_speed = self._global_wind.norm();
+        WindConstants {
+            _wind_direction: self._global_wind.try_normalize(0.0).unwrap_or(self._wind_settings._wind_direction.clone()),
+            _wind_speed: wind_speed,
+            _gust: self._gust,
+            _gustiness: self._wind_settings._gustiness,
+            _wind_time: self._wind_time,
+            _reserved0: 0.0,
+        }
+    }
+
+    fn get_volume_wind(&self, volume_index: usize, position: &Vector3<f32>) -> Vector3<f32> {
+        let wind_volume = &self._wind_settings._wind_volumes[volume_index];
+        &wind_volume._wind * (wind_volume.get_weight(position) * unsafe { constants::WIND_STRENGTH_SCALE })
+    }
+
+    // every volume is evaluated, for the few emitters and the gameplay queries. the objects use the cached overlaps.
+    pub fn sample_wind(&self, position: &Vector3<f32>) -> Vector3<f32> {
+        if false == self.is_wind_enabled() {
+            return Vector3::zeros();
+        }
+        let mut wind = self._global_wind.clone();
+        for (volume_index, volume_bound_box) in self._volume_bound_boxes.iter().enumerate() {
+            if is_point_in_bound_box(volume_bound_box, position) {
+                wind += self.get_volume_wind(volume_index, position);
+            }
+        }
+        wind
+    }
+
+    // the acceleration term of the particle update compute pass, wind_influence: per emitter, 0.0 ignores the wind.
+    pub fn get_wind_acceleration(&self, position: &Vector3<f32>, wind_influence: f32) -> Vector3<f32> {
+        self.sample_wind(position) * wind_influence
+    }
+
+    pub fn get_object_wind(&self, index: u32) -> Vector3<f32> {
+        self._object_winds.get(index as usize).cloned().unwrap_or(Vector3::zeros())
+    }
+
+    pub fn get_stats_text(&self) -> String {
+        format!("wind: {:.2} m/s, gust {:.2}, volumes {}, volume tests {}",
+            self._global_wind.norm(),
+            self._gust,
+            self._wind_settings._wind_volumes.len(),
+            self._volume_test_count
+        )
+    }
+
+    // called after the render object storage is updated, its dirty flags tell the moved objects.
+    pub fn update_wind_system(&mut self, elapsed_time: f64, render_object_storage: &RenderObjectStorage) {
+        self._wind_time = elapsed_time as f32;
+        self._volume_test_count = 0;
+        if self.is_wind_enabled() {
+            let wind_settings = &self._wind_settings;
+            let noise_time = self._wind_time * wind_settings._gust_frequency;
+            self._gust = self.sample_noise(noise_time) * 0.7 + self.sample_noise(noise_time * 2.3 + 37.0) * 0.3;
+            let wind_scale = 1.0 + wind_settings._gustiness * (self._gust * 2.0 - 1.0);
+            self._global_wind = &wind_settings._wind_direction * (wind_settings._wind_speed * wind_scale * unsafe { constants::WIND_STRENGTH_SCALE });
+        } else {
+            self._gust = 0.0;
+            self._global_wind = Vector3::zeros();
+        }
+
+        let object_count = render_object_storage.get_capacity();
+        self._object_wind_states.resize(object_count, ObjectWindState::default());
+        self._object_winds.resize(object_count, Vector3::zeros());
+        for i in 0..object_count {
+            if false == render_object_storage._alives[i] {
+                continue;
+            }
+            let generation = render_object_storage._generations[i];
+            let object_wind_state = &self._object_wind_states[i];
+            if false == object_wind_state._is_valid || generation != object_wind_state._generation || render_object_storage._dirty_flags[i] {
+                let object_wind_state = self.create_object_wind_state(render_object_storage, i);
+                self._object_wind_states[i] = object_wind_state;
+            }
+            let object_wind_state = &self._object_wind_states[i];
+            if false == object_wind_state._is_wind_receiver {
+                continue;
+            }
+            let mut wind = self._global_wind.clone();
+            if self.is_wind_enabled() {
+                let bound_box = &render_object_storage._bound_boxes[i];
+                for volume_index in object_wind_state._volume_indices.iter() {
+                    wind += self.get_volume_wind(*volume_index as usize, &bound_box._center);
+                }
+            }
+            self._object_winds[i] = wind;
+            let render_object = render_object_storage.get_render_object_by_index(i as u32);
+            let mut render_object_data = render_object.borrow_mut();
+            let model_data = render_object_data._model_data.clone();
+            let model_data = model_data.borrow();
+            if let Some(material_instance_data) = model_data.get_material_instance_datas().iter().find(|material_instance_data| {
+                shader_hook::is_wind_shader_hook_set(&material_instance_data.borrow()._shader_hook_set)
+            }) {
+                let wind_parameter = Vector4::new(wind.x, wind.y, wind.z, self._wind_time);
+                render_object_data.set_shader_hook_parameter(WIND_SHADER_HOOK_PARAMETER_INDEX, &wind_parameter, &material_instance_data.borrow());
+            }
+        }
+    }
+
+    fn create_object_wind_state(&mut self, render_object_storage: &RenderObjectStorage, index: usize) -> ObjectWindState {
+        let is_wind_receiver = {
+            let render_object_data = render_object_storage.get_render_object_by_index(index as u32).borrow();
+            let model_data = render_object_data._model_data.borrow();
+            model_data.get_material_instance_datas().iter().any(|material_instance_data| {
+                shader_hook::is_wind_shader_hook_set(&material_instance_data.borrow()._shader_hook_set)
+            })
+        };
+        let mut volume_indices: Vec<u32> = Vec::new();
+        if is_wind_receiver {
+            let bound_box = &render_object_storage._bound_boxes[index];
+            for (volume_index, volume_bound_box) in self._volume_bound_boxes.iter().enumerate() {
+                self._volume_test_count += 1;
+                if is_bound_box_overlapped(volume_bound_box, bound_box) {
+                    volume_indices.push(volume_index as u32);
+                }
+            }
+        }
+        ObjectWindState {
+            _generation: render_object_storage._generations[index],
+            _is_wind_receiver: is_wind_receiver,
+            _volume_indices: volume_indices,
+            _is_valid: true,
+        }
+    }
+}
+
+fn is_point_in_bound_box(bound_box: &BoundingBox, position: &Vector3<f32>) -> bool {
+    (0..3).all(|i| bound_box._min[i] <= position[i] && position[i] <= bound_box._max[i])
+}
+
+fn is_bound_box_overlapped(a: &BoundingBox, b: &BoundingBox) -> bool {
+    (0..3).all(|i| a._min[i] <= b._max[i] && b._min[i] <= a._max[i])
+}
+
+// ex) "wind speed 5", "wind direction 1 0 0.5", "wind volume add fan sphere 0 0 0 5 0 8 0", returns false for the other commands.
+pub fn execute_wind_command(scene_manager_data: &mut SceneManagerData, renderer_data: &mut RendererData, command: &str) -> bool {
+    let args: Vec<&str> = command.split_whitespace().collect();
+    if Some(&WIND_COMMAND) != args.first() {
+        return false;
+    }
+    let mut environment_settings = scene_manager_data.get_environment_settings().clone();
+    if let Err(e) = environment_settings._wind.set_wind_parameter(&args[1..]) {
+        log::error!("execute_wind_command: {}", e);
+        return true;
+    }
+    let prev_ocean_settings = scene_manager_data.get_environment_settings()._ocean.clone();
+    scene_manager_data.set_environment_settings(&environment_settings);
+    let ocean_settings = &scene_manager_data.get_environment_settings()._ocean;
+    if ocean_settings.is_spectrum_changed(&prev_ocean_settings) {
+        renderer_data.request_ocean_spectrum_update(ocean_settings);
+    }
+    true
+}
@@ -0,0 +1,303 @@
+use std::path::PathBuf;
+
+use image::{ self, GenericImageView };
+use winit::monitor::{ MonitorHandle, VideoMode };
+use winit::window::{ Fullscreen, Icon, Window };
+
+use crate::constants;
+use crate::resource::resource::Resources;
+
+pub const WINDOW_ICON_FILE_PATH: &str = "resource/icon.png";
+pub const WINDOW_TITLE_UPDATE_INTERVAL: f64 = 0.5; // seconds, the title is set only when changed
+pub const WINDOW_TITLE_MARKER_RECORDING: &str = "RECORDING";
+pub const WINDOW_TITLE_MARKER_REPLAY: &str = "REPLAY";
+pub const DEFAULT_WINDOW_SIZE: (u32, u32) = (1024, 768);
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum WindowMode {
+    Windowed { _width: u32, _height: u32 }, // the inner size
+    BorderlessFullscreen, // the desktop resolution of the monitor
+    ExclusiveFullscreen { _video_mode_index: usize }, // of get_video_modes, the surface formats and extents can change
+}
+
+// EngineConfig::_window_config at the startup, EngineApplication::set_window_config at runtime
+#[derive(Clone, Debug, PartialEq)]
+pub struct WindowConfig {
+    pub _window_mode: WindowMode,
+    pub _monitor_index: usize, // of the available monitors, out of range is the primary monitor
+}
+
+impl Default for WindowConfig {
+    fn default() -> WindowConfig {
+        WindowConfig {
+            _window_mode: WindowMode::Windowed { _width: DEFAULT_WINDOW_SIZE.0, _height: DEFAULT_WINDOW_SIZE.1 },
+            _monitor_index: 0,
+        }
+    }
+}
+
+impl WindowConfig {
+    pub fn is_fullscreen(&self) -> bool {
+        match self._window_mode {
+            WindowMode::Windowed { .. } => false,
+            _ => true,
+        }
+    }
+
+    // the inner size of the windowed mode, DEFAULT_WINDOW_SIZE for the fullscreen
+    pub fn get_windowed_size(&self) -> (u32, u32) {
+        match self._window_mode {
+            WindowMode::Windowed { _width, _height } => (_width, _height),
+            _ => DEFAULT_WINDOW_SIZE,
+        }
+    }
+
+    pub fn is_exclusive_fullscreen(&self) -> bool {
+        match self._window_mode {
+            WindowMode::ExclusiveFullscreen { .. } => true,
+            _ => false,
+        }
+    }
+}
+
+// the order of MonitorHandle::video_modes, the index of WindowMode::ExclusiveFullscreen
+pub fn get_video_modes(monitor: &MonitorHandle) -> Vec<VideoMode> {
+    monitor.video_modes().collect()
+}
+
+// monitors: EventLoop::available_monitors at the startup, Window::available_monitors at runtime
+pub fn get_monitor(window_config: &WindowConfig, monitors: Vec<MonitorHandle>, primary_monitor: Option<MonitorHandle>) -> Option<MonitorHandle> {
+    let monitor_count = monitors.len();
+    match monitors.into_iter().nth(window_config._monitor_index) {
+        Some(monitor) => Some(monitor),
+        None => {
+            log::warn!("get_monitor: monitor index {} is out of range {}, the primary monitor is used.", window_config._monitor_index, monitor_count);
+            primary_monitor
+        }
+    }
+}
+
+// None for the windowed mode, Window::set_fullscreen or WindowBuilder::with_fullscreen
+pub fn get_fullscreen(window_config: &WindowConfig, monitors: Vec<MonitorHandle>, primary_monitor: Option<MonitorHandle>) -> Option<Fullscreen> {
+    match window_config._window_mode {
+        WindowMode::Windowed { .. } => None,
+        WindowMode::BorderlessFullscreen => Some(Fullscreen::Borderless(get_monitor(window_config, monitors, primary_monitor))),
+        WindowMode::ExclusiveFullscreen { _video_mode_index: video_mode_index } => {
+            let monitor = get_monitor(window_config, monitors, primary_monitor)?;
+            let mut video_modes = get_video_modes(&monitor);
+            if video_modes.is_empty() {
+                log::warn!("get_fullscreen: {:?} has no video mode, the borderless fullscreen is used.", monitor.name());
+                return Some(Fullscreen::Borderless(Some(monitor)));
+            }
+            let video_mode_index = if video_mode_index < video_modes.len() {
+                video_mode_index
+            } else {
+                log::warn!("get_fullscreen: video mode index {} is out of range {}, the first video mode is used.", video_mode_index, video_modes.len());
+                0
+            };
+            let video_mode = video_modes.swap_remove(video_mode_index);
+            log::info!("get_fullscreen: {:?} {}", monitor.name(), video_mode);
+            Some(Fullscreen::Exclusive(video_mode))
+        },
+    }
+}
+
+// png or any format of the image crate, through the virtual file system, so the project layer can override the engine icon.
+pub fn load_window_icon(resources: &Resources, icon_file_path: &str) -> Option<Icon> {
+    let icon_file_path = PathBuf::from(icon_file_path);
+    if false == resources._resource_filenames.contains(&icon_file_path) && false == resources.resolve_path(&icon_file_path).is_file() {
+        log::warn!("load_window_icon: not found {:?}", icon_file_path);
+        return None;
+    }
+    let loaded_contents = resources.read_bytes(&icon_file_path);
+    let dynamic_image = match image::load_from_memory(loaded_contents.get_ref()) {
+        Ok(dynamic_image) => dynamic_image,
+        Err(e) => {
+            log::error!("load_window_icon: {:?} {:?}", icon_file_path, e);
+            return None;
+        }
+    };
+    let (width, height) = dynamic_image.dimensions();
+    match Icon::from_rgba(dynamic_image.to_rgba8().into_raw(), width, height) {
+        Ok(icon) => Some(icon),
+        Err(e) => {
+            log::error!("load_window_icon: {:?} {:?}", icon_file_path, e);
+            None
+        }
+    }
+}
+
+// android has no window decoration, the window icon and the title are ignored.
+pub fn set_window_icon(window: &Window, icon: Option<Icon>) {
+    #[cfg(not(target_os = "android"))]
+    window.set_window_icon(icon);
+    #[cfg(target_os = "android")]
+    let _ = (window, icon);
+}
+
+// ex) "RustEngine3D - default - 60.0 fps [RECORDING]"
+pub struct WindowTitle {
+    pub _base_title: String,
+    pub _scene_name: String,
+    pub _markers: Vec<String>,
+    pub _last_title: String,
+    pub _last_update_time: f64,
+}
+
+impl WindowTitle {
+    pub fn create_window_title(base_title: &str) -> WindowTitle {
+        WindowTitle {
+            _base_title: String::from(base_title),
+            _scene_name: String::new(),
+            _markers: Vec::new(),
+            _last_title: String::from(base_title),
+            _last_update_time: 0.0,
+        }
+    }
+
+    pub fn set_base_title(&mut self, base_title: &str) {
+        self._base_title = String::from(base_title);
+    }
+
+    pub fn set_scene_name(&mut self, scene_name: &str) {
+        if scene_name != self._scene_name {
+            self._scene_name = String::from(scene_name);
+        }
+    }
+
+    // ex) WINDOW_TITLE_MARKER_RECORDING while the project records a replay
+    pub fn set_marker(&mut self, marker: &str, enable: bool) {
+        let index = self._markers.iter().position(|m| marker == m);
+        match (enable, index) {
+            (true, None) => self._markers.push(String::from(marker)),
+            (false, Some(index)) => { self._markers.remove(index); },
+            _ => (),
+        }
+    }
+
+    pub fn build_window_title(&self, average_fps: f64) -> String {
+        let mut window_title = self._base_title.clone();
+        if false == self._scene_name.is_empty() {
+            window_title.push_str(&format!(" - {}", self._scene_name));
+        }
+        if unsafe { constants::SHOW_FPS_IN_WINDOW_TITLE } {
+            window_title.push_str(&format!(" - {:.1} fps", average_fps));
+        }
+        for marker in self._markers.iter() {
+            window_title.push_str(&format!(" [{}]", marker));
+        }
+        window_title
+    }
+
+    // rate limited by WINDOW_TITLE_UPDATE_INTERVAL, the window system is called only when the title has been changed.
+    pub fn update_window_title(&mut self, window: &Window, elapsed_time: f64, average_fps: f64) {
+        if elapsed_time < self._last_update_time + WINDOW_TITLE_UPDATE_INTERVAL {
+            return;
+        }
+        self._last_update_time = elapsed_time;
+        let window_title = self.build_window_title(average_fps);
+        if window_title != self._last_title {
+            #[cfg(not(target_os = "android"))]
+            window.set_title(&window_title);
+            #[cfg(target_os = "android")]
+            let _ = window;
+            self._last_title = window_title;
+        }
+    }
+}
+
+// the progress bar on the taskbar button, only windows supports it, the other platforms are no-op.
+pub struct TaskbarProgress {
+    #[cfg(target_os = "windows")]
+    _taskbar_list: Option<windows_taskbar::TaskbarList>,
+}
+
+impl TaskbarProgress {
+    pub fn create_taskbar_progress() -> TaskbarProgress {
+        TaskbarProgress {
+            #[cfg(target_os = "windows")]
+            _taskbar_list: windows_taskbar::TaskbarList::create_taskbar_list(),
+        }
+    }
+
+    // progress: 0.0 ~ 1.0, None clears the progress bar
+    pub fn set_taskbar_progress(&self, window: &Window, progress: Option<f32>) {
+        #[cfg(target_os = "windows")]
+        {
+            if let Some(taskbar_list) = self._taskbar_list.as_ref() {
+                taskbar_list.set_progress(window, progress);
+            }
+        }
+        #[cfg(not(target_os = "windows"))]
+        let _ = (window, progress);
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod windows_taskbar {
+    use std::ptr;
+
+    use winapi::Interface;
+    use winapi::ctypes::c_void;
+    use winapi::shared::windef::HWND;
+    use winapi::shared::winerror::SUCCEEDED;
+    use winapi::shared::wtypesbase::CLSCTX_INPROC_SERVER;
+    use winapi::um::combaseapi::{ CoCreateInstance, CoInitializeEx };
+    use winapi::um::objbase::COINIT_APARTMENTTHREADED;
+    use winapi::um::shobjidl_core::{ CLSID_TaskbarList, ITaskbarList3, TBPF_NOPROGRESS, TBPF_NORMAL };
+    use winit::platform::windows::WindowExtWindows;
+    use winit::window::Window;
+
+    const PROGRESS_TOTAL: u64 = 1000;
+
+    pub struct TaskbarList {
+        _taskbar_list: *mut ITaskbarList3,
+    }
+
+    impl TaskbarList {
+        pub fn create_taskbar_list() -> Option<TaskbarList> {
+            unsafe {
+                // S_FALSE or RPC_E_CHANGED_MODE when COM has been initialized on this thread already, both are fine.
+                CoInitializeEx(ptr::null_mut(), COINIT_APARTMENTTHREADED);
+                let mut taskbar_list: *mut ITaskbarList3 = ptr::null_mut();
+                let result = CoCreateInstance(
+                    &CLSID_TaskbarList,
+                    ptr::null_mut(),
+                    CLSCTX_INPROC_SERVER,
+                    &ITaskbarList3::uuidof(),
+                    &mut taskbar_list as *mut *mut ITaskbarList3 as *mut *mut c_void,
+                );
+                if false == SUCCEEDED(result) || taskbar_list.is_null() {
+                    log::warn!("create_taskbar_list: failed {:#x}", result);
+                    return None;
+                }
+                if false == SUCCEEDED((*taskbar_list).HrInit()) {
+                    (*taskbar_list).Release();
+                    return None;
+                }
+                Some(TaskbarList { _taskbar_list: taskbar_list })
+            }
+        }
+
+        pub fn set_progress(&self, window: &Window, progress: Option<f32>) {
+            let hwnd = window.hwnd() as HWND;
+            unsafe {
+                match progress {
+                    Some(progress) => {
+                        (*self._taskbar_list).SetProgressState(hwnd, TBPF_NORMAL);
+                        (*self._taskbar_list).SetProgressValue(hwnd, (progress * PROGRESS_TOTAL as f32) as u64, PROGRESS_TOTAL);
+                    },
+                    None => {
+                        (*self._taskbar_list).SetProgressState(hwnd, TBPF_NOPROGRESS);
+                    },
+                }
+            }
+        }
+    }
+
+    impl Drop for TaskbarList {
+        fn drop(&mut self) {
+            unsafe { (*self._taskbar_list).Release(); }
+        }
+    }
+}
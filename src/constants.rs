@@ -1,6 +1,9 @@
 use std;
 use ash::vk;
 
+use crate::renderer::tonemap::TonemapOperator;
+use crate::vulkan_context::shading_rate::ShadingRateQuality;
+
 pub const ENGINE_NAME: &str = "RustEngine3D";
 pub const ENGINE_VERSION: u32 = vk::make_version(1, 0, 0);
 pub const DEPTH_FOMATS: [vk::Format; 5] = [
@@ -20,12 +23,18 @@ pub const CUBE_TEXTURE_FACES: [&str; CUBE_LAYER_COUNT] = ["right", "left", "top"
 pub const INVALID_QUEUE_INDEX: u32 = std::u32::MAX;
 pub const WHOLE_LAYERS: u32 = std::u32::MAX;
 pub const WHOLE_MIP_LEVELS: u32 = std::u32::MAX;
+pub const MAX_TEXTURE_MIP_DROP: u32 = 3;
 pub const SWAPCHAIN_IMAGE_COUNT: usize = 3;
 pub const SWAPCHAIN_IMAGE_INDICES: [usize; SWAPCHAIN_IMAGE_COUNT] = [0, 1, 2];
 pub const SWAPCHAIN_SURFACE_FORMATS: [vk::SurfaceFormatKHR; 2] = [
     vk::SurfaceFormatKHR { format: vk::Format::R8G8B8A8_SRGB, color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR },
     vk::SurfaceFormatKHR { format: vk::Format::B8G8R8A8_SRGB, color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR },
 ];
+// ENABLE_HDR_OUTPUT: the first supported format, the sdr formats are the fallback
+pub const HDR_SURFACE_FORMATS: [vk::SurfaceFormatKHR; 2] = [
+    vk::SurfaceFormatKHR { format: vk::Format::A2B10G10R10_UNORM_PACK32, color_space: vk::ColorSpaceKHR::HDR10_ST2084_EXT },
+    vk::SurfaceFormatKHR { format: vk::Format::R16G16B16A16_SFLOAT, color_space: vk::ColorSpaceKHR::EXTENDED_SRGB_LINEAR_EXT },
+];
 pub const MAX_FRAME_COUNT: usize = 2;
 pub const FRAME_INDICES: [usize; MAX_FRAME_COUNT] = [0, 1];
 
@@ -39,22 +48,101 @@ pub static mut DEBUG_MESSAGE_LEVEL: vk::DebugUtilsMessageSeverityFlagsEXT = vk::
 pub static mut VULKAN_LAYERS: Vec<String> = Vec::new(); // vec!["VK_LAYER_LUNARG_standard_validation".to_string()];
 pub static mut REQUIRE_DEVICE_EXTENSIONS: Vec<String> = Vec::new(); // vec!["VK_KHR_swapchain".to_string()];
 pub static mut MAX_DESCRIPTOR_POOL_ALLOC_COUNT: usize = 512;
+pub static mut DEVICE_MEMORY_BLOCK_SIZE: u64 = 64 * 1024 * 1024; // the vkAllocateMemory size of device_memory_allocator, the resources over the half get a dedicated block
+pub static mut ENABLE_BINDLESS_TEXTURES: bool = true; // when the device supports the descriptor indexing, false: the textures of the material instance descriptor sets
+pub static mut MAX_BINDLESS_TEXTURE_COUNT: u32 = 4096; // the cap of maxPerStageDescriptorSampledImages, see bindless_texture.rs
 pub static mut ENABLE_IMMEDIATE_MODE: bool = true;
+pub static mut ENABLE_HDR_OUTPUT: bool = false; // HDR_SURFACE_FORMATS when the surface supports, see RendererData::set_hdr_output
+pub static mut TONEMAP_OPERATOR: TonemapOperator = TonemapOperator::ACES;
+pub static mut TONEMAP_EXPOSURE: f32 = 1.0;
+pub static mut TONEMAP_GAMMA: f32 = 1.0; // the srgb swapchain formats encode the gamma
+pub static mut MSAA_SAMPLE_COUNT: u32 = 1; // 1, 2, 4, 8 samples of the scene color and depth, see RendererData::set_msaa_sample_count
+pub static mut RENDER_SCALE: f32 = 1.0; // the initial scene resolution scale of the swapchain size, see RendererData::set_render_scale
 pub static mut ENABLE_VALIDATION_LAYER: bool = true;
+pub static mut ENABLE_FILE_WATCHER: bool = true;
 pub static mut IS_CONCURRENT_MODE: bool = true;
 pub static mut METER_PER_UNIT: f32 = 1.0;
 pub static mut NEAR: f32 = 0.1;
 pub static mut FAR: f32 = 2000.0;
+pub static mut REVERSED_Z: bool = false; // depth 1.0 at the near plane and 0.0 at the far plane, RendererData::set_reversed_z recreates the pipelines
+pub static mut INFINITE_FAR_PLANE: bool = false; // reversed-z only, the camera projection has no far plane
 pub static mut FOV: f32 = 60.0;
 pub static mut MAX_FONT_INSTANCE_COUNT: usize = 1024; // must match with render_font_common.glsl
 pub static mut MAX_UI_INSTANCE_COUNT: usize = 1024; // must match with render_ui_common.glsl
+pub static mut MAX_INSTANCE_COUNT: usize = 4096; // model matrices of the instance matrix buffer per frame, must match with render_object_common.glsl
+pub static mut LETTERBOX_TARGET_ASPECT: f32 = 0.0; // ex) 21.0 / 9.0, 0.0 is off
+pub static mut LETTERBOX_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+pub static mut UI_TEST_PATTERN: bool = false; // grayscale ramp + color bars to verify the gamma of the ui pass
+pub static mut DEBUG_INJECT_RENDER_PANIC: bool = false; // debug build only, panics once inside render_scene to test the recovery
+pub static mut DEBUG_INJECT_DEVICE_LOST: bool = false; // "gpu_fault_injection" feature only, present_swapchain returns ERROR_DEVICE_LOST once to test the crash report
+pub static mut ENABLE_GPU_BREADCRUMBS: bool = true; // markers before and after each debug label scope, reported on device lost
+pub static mut ENABLE_GPU_PROFILER: bool = true; // timestamps of each debug label scope, see RendererData::get_gpu_timings
+pub static mut ENABLE_GPU_SKINNING: bool = true; // the compute skinning pre-pass, false: the skeletal pipelines skin in the vertex shader
+pub static mut FORCE_CPU_OCCLUSION_CULLING: bool = false; // debug, the static batch is culled by SceneManagerData::cull_scene instead of the occlusion culling compute pass
+pub static mut ENABLE_DEBUG_VIEW_PIPELINES: bool = cfg!(debug_assertions); // the wireframe and the visualize variants of the opaque material instances, see debug_view.rs
+pub static mut ENABLE_RENDER_TARGET_BARRIERS: bool = true; // the image barriers between the passes of FrameGraph::declare_pass_render_targets
+pub static mut VISIBILITY_STATS_CAPTURE_FRAMES: u32 = 120; // the frames of a visibility stats capture, see RendererData::begin_visibility_stats_capture
+pub static mut TEXTURE_MIP_DROP: i32 = -1; // -1: auto by the device local memory, 0 ~ MAX_TEXTURE_MIP_DROP: count of the dropped top mip levels
 pub static mut SHADOW_MAP_SIZE: u32 = 2048;
 pub static mut SHADOW_SAMPLES: i32 = 4;
 pub static mut SHADOW_EXP: f32 = 100.0;
 pub static mut SHADOW_BIAS: f32 = 0.005;
 pub static mut SHADOW_DISTANCE: f32 = 50.0;
 pub static mut SHADOW_DEPTH: f32 = 50.0;
+pub static mut SHADOW_ATLAS_SIZE: u32 = 4096; // local light shadows, power of two
+pub static mut POINT_LIGHT_DUAL_PARABOLOID_SHADOW: bool = false; // two tiles instead of six cube faces per point light
+pub static mut SHADOW_ATLAS_AREA_BUDGET: f32 = 0.75; // ratio of the atlas area, the lowest priority lights are downgraded first over it
+pub static mut SHADOW_ATLAS_DEBUG_MAP: bool = false; // the tile owners of the shadow atlas in the debug overlay
+pub static mut SHADOW_CACHE_ENABLE: bool = true; // false: every shadow region is rendered every frame
+pub static mut VOLUMETRIC_FOG_STEPS: u32 = 32; // the raymarch samples per pixel of the volumetric fog, see volumetric_fog.rs
+pub static mut VOLUMETRIC_FOG_RESOLUTION_DIVISOR: u32 = 2; // of the render extent, applied by the next create_render_targets
 pub static mut SSAO_NOISE_DIM: i32 = 4;
+pub static mut MAX_FUR_SHELL_COUNT: u32 = 32;
+pub static mut SHADING_RATE_QUALITY: ShadingRateQuality = ShadingRateQuality::Off;
+pub static mut ENABLE_SUBPASS_MERGING: bool = cfg!(target_os = "android"); // tile based gpus, see render_pass::create_merged_render_pass_data_create_info
+pub static mut IMPOSTOR_DISTANCE_SCALE: f32 = 1.0; // scales the impostor distance of the models, 0.0 disables the impostors
+pub static mut IMPOSTOR_LOD_HYSTERESIS: f32 = 0.1; // ratio of the impostor distance
+pub static mut MESH_LOD_DISTANCE_SCALE: f32 = 1.0; // scales the lod distances of the meshes, 0.0 draws the lod 0 only
+pub static mut MESH_LOD_HYSTERESIS: f32 = 0.1; // ratio of the lod distance
+pub static mut SHADOW_MESH_LOD_BIAS: u32 = 1; // the shadow casters use the coarser lods, see SceneManagerData::get_shadow_static_render_element_groups
+pub static mut SHOW_FPS_IN_WINDOW_TITLE: bool = true;
+pub static mut FIXED_TIME_STEP: f64 = 1.0 / 60.0; // seconds of a simulation step, 0.0: the simulation runs once per frame with the frame delta time
+pub static mut MAX_FIXED_STEPS_PER_FRAME: u32 = 5; // the catch-up cap of a long frame, the time over it is dropped
+pub static mut ENABLE_CAMERA_COLLISION: bool = true; // the main camera slides along the collision world and stays above the terrain
+pub static mut CAMERA_COLLISION_RADIUS: f32 = 0.3; // meters
+pub static mut WIND_ENABLE: bool = true; // false: no wind for every consumer, the scene settings are kept
+pub static mut WIND_STRENGTH_SCALE: f32 = 1.0;
+
+// post process profile of the main viewport, see post_process.rs
+pub static mut POST_PROCESS_SSAO: bool = true;
+pub static mut POST_PROCESS_SSR: bool = true;
+pub static mut POST_PROCESS_TAA: bool = true;
+pub static mut POST_PROCESS_FXAA: bool = true; // when the taa is disabled, see RendererData::set_anti_aliasing_mode
+pub static mut POST_PROCESS_BLOOM: bool = true;
+pub static mut POST_PROCESS_BLOOM_INTENSITY: f32 = 0.25;
+pub static mut POST_PROCESS_AUTO_EXPOSURE: bool = true;
+pub static mut POST_PROCESS_EXPOSURE_VALUE: f32 = 1.0; // when the auto exposure is disabled
+pub static mut AUTO_EXPOSURE_SPEED_UP: f32 = 3.0; // the adaptation speed to the bright scene, 1 / seconds, see auto_exposure.rs
+pub static mut AUTO_EXPOSURE_SPEED_DOWN: f32 = 1.0; // the adaptation speed to the dark scene
+
+// asset report, see asset_report.rs
+pub static mut ASSET_REPORT_MAX_TEXTURE_SIZE: u32 = 2048; // used when there is no on-screen size of the texture
+pub static mut ASSET_REPORT_TEXTURE_USAGE_RATIO: f32 = 2.0; // oversized when larger than the ratio of the largest on-screen size
+pub static mut ASSET_REPORT_MAX_MESH_VERTEX_COUNT: usize = 100000;
+
+// input, read every frame so the changes take effect immediately
+pub static mut MOUSE_SENSITIVITY_X: f32 = 1.0;
+pub static mut MOUSE_SENSITIVITY_Y: f32 = 1.0;
+pub static mut MOUSE_INVERT_Y: bool = false;
+pub static mut TOUCH_LOOK_SENSITIVITY: f32 = if cfg!(target_os = "android") { 0.5 } else { 1.0 };
+pub static mut GAMEPAD_INVERT_Y: bool = false;
+pub static mut GAMEPAD_DEAD_ZONE: f32 = 0.15;
+pub static mut GAMEPAD_RADIAL_DEAD_ZONE: bool = true; // false: per-axis dead zone
+pub static mut GAMEPAD_RESPONSE_EXPONENT: f32 = 2.0; // 1.0 is linear
+pub static mut GAMEPAD_LOOK_SENSITIVITY: f32 = 1.0;
+pub static mut HAPTICS_INTENSITY: f32 = 1.0; // 0.0 disables the rumble
+pub static mut KEY_REPEAT_DELAY: f32 = 0.5; // seconds, the first repeat of a held key, see KeyboardInputData::get_key_repeat
+pub static mut KEY_REPEAT_INTERVAL: f32 = 0.05; // seconds between the repeats
 
 // effect
 pub static mut MAX_EMITTER_COUNT: i32 = 1024;
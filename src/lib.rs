@@ -3,4 +3,5 @@ pub mod application;
 pub mod renderer;
 pub mod resource;
 pub mod utilities;
-pub mod vulkan_context;
\ No newline at end of file
+pub mod vulkan_context;
+pub mod network;
\ No newline at end of file
@@ -0,0 +1,2 @@
+pub mod packet;
+pub mod replication;
\ No newline at end of file
@@ -0,0 +1,184 @@
+use nalgebra::{ Vector2, Vector3 };
+use serde::{ Serialize, Deserialize };
+use bincode;
+
+pub const PROTOCOL_ID: u32 = 0x5233_4E45; // "R3NE"
+pub const MAX_PACKET_SIZE: usize = 1200; // keep below the common MTU
+pub const MAX_SNAPSHOT_TRANSFORM_STATE_COUNT: usize = 18; // a NetTransformState is 64 bytes in bincode, the snapshots of more objects are split into parts
+pub const ACK_BITS_COUNT: u16 = 32;
+pub type NetObjectId = u32;
+pub type SequenceNumber = u16;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct PacketHeader {
+    pub _protocol_id: u32,
+    pub _sequence: SequenceNumber,
+    pub _ack: SequenceNumber, // latest sequence received from the remote
+    pub _ack_bits: u32, // bit n: (_ack - 1 - n) is received too
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct NetTransformState {
+    pub _net_object_id: NetObjectId,
+    pub _position: Vector3<f32>,
+    pub _rotation: Vector3<f32>,
+    pub _scale: Vector3<f32>,
+}
+
+// client -> server, the state of the action map at the tick.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub struct NetInputState {
+    pub _tick: u32,
+    pub _move_direction: Vector3<f32>,
+    pub _rotation_delta: Vector2<f32>,
+    pub _buttons: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum PacketPayload {
+    Connect,
+    Accept { _client_id: u32 },
+    Disconnect,
+    Spawn { _net_object_id: NetObjectId, _object_name: String, _model_name: String },
+    Despawn { _net_object_id: NetObjectId },
+    Snapshot { _tick: u32, _transform_states: Vec<NetTransformState> }, // a part of the snapshot of the tick, see split_transform_states
+    Input { _input_state: NetInputState },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Packet {
+    pub _header: PacketHeader,
+    pub _payload: PacketPayload,
+}
+
+impl PacketPayload {
+    // resent until the remote acks them, the others are replaced by the next tick.
+    pub fn is_reliable(&self) -> bool {
+        match self {
+            PacketPayload::Spawn { .. } | PacketPayload::Despawn { .. } => true,
+            _ => false,
+        }
+    }
+}
+
+impl Packet {
+    pub fn create_packet(sequence: SequenceNumber, ack: SequenceNumber, ack_bits: u32, payload: PacketPayload) -> Packet {
+        Packet {
+            _header: PacketHeader {
+                _protocol_id: PROTOCOL_ID,
+                _sequence: sequence,
+                _ack: ack,
+                _ack_bits: ack_bits,
+            },
+            _payload: payload,
+        }
+    }
+
+    pub fn serialize_packet(&self) -> Vec<u8> {
+        bincode::serialize(self).unwrap()
+    }
+
+    // returns None for the packets of other protocols or corrupted packets.
+    pub fn deserialize_packet(bytes: &[u8]) -> Option<Packet> {
+        if MAX_PACKET_SIZE < bytes.len() {
+            return None;
+        }
+        match bincode::deserialize::<Packet>(bytes) {
+            Ok(packet) if PROTOCOL_ID == packet._header._protocol_id => Some(packet),
+            _ => None,
+        }
+    }
+}
+
+// sequence numbers wrap around, a is newer than b when it is ahead by less than half of the range.
+pub fn sequence_greater_than(a: SequenceNumber, b: SequenceNumber) -> bool {
+    const HALF: SequenceNumber = SequenceNumber::MAX / 2 + 1;
+    ((a > b) && (a - b < HALF)) || ((a < b) && (b - a > HALF))
+}
+
+// the transform states of a snapshot in parts that fit MAX_PACKET_SIZE, one empty part keeps the tick of the clients going.
+pub fn split_transform_states(transform_states: &[NetTransformState]) -> Vec<&[NetTransformState]> {
+    if transform_states.is_empty() {
+        return vec![transform_states];
+    }
+    transform_states.chunks(MAX_SNAPSHOT_TRANSFORM_STATE_COUNT).collect()
+}
+
+// the sequence is acked by the header of the remote.
+pub fn is_sequence_acked(sequence: SequenceNumber, ack: SequenceNumber, ack_bits: u32) -> bool {
+    if sequence == ack {
+        return true;
+    }
+    if false == sequence_greater_than(ack, sequence) {
+        return false;
+    }
+    let distance = ack.wrapping_sub(sequence);
+    distance <= ACK_BITS_COUNT && 0 != (ack_bits & (1 << (distance - 1)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_transform_states(count: usize) -> Vec<NetTransformState> {
+        (0..count).map(|i| NetTransformState {
+            _net_object_id: i as NetObjectId,
+            _position: Vector3::new(i as f32, 1.0, 2.0),
+            _rotation: Vector3::new(0.0, i as f32, 0.0),
+            _scale: Vector3::new(1.0, 1.0, 1.0),
+        }).collect()
+    }
+
+    #[test]
+    fn test_packet_serialization() {
+        let packet = Packet::create_packet(3, 2, 0b101, PacketPayload::Spawn { _net_object_id: 7, _object_name: String::from("cube"), _model_name: String::from("cube") });
+        assert_eq!(Some(packet.clone()), Packet::deserialize_packet(&packet.serialize_packet()));
+
+        // other protocols and truncated packets
+        let mut bytes = packet.serialize_packet();
+        bytes[0] ^= 0xff;
+        assert_eq!(None, Packet::deserialize_packet(&bytes));
+        let bytes = packet.serialize_packet();
+        assert_eq!(None, Packet::deserialize_packet(&bytes[..bytes.len() - 1]));
+        assert_eq!(None, Packet::deserialize_packet(&vec![0u8; MAX_PACKET_SIZE + 1]));
+    }
+
+    #[test]
+    fn test_snapshot_packet_size() {
+        let transform_states = create_transform_states(MAX_SNAPSHOT_TRANSFORM_STATE_COUNT * 3 + 1);
+        let parts = split_transform_states(&transform_states);
+        assert_eq!(4, parts.len());
+        assert_eq!(transform_states.len(), parts.iter().map(|part| part.len()).sum::<usize>());
+        for part in parts.iter() {
+            let packet = Packet::create_packet(SequenceNumber::MAX, SequenceNumber::MAX, u32::MAX, PacketPayload::Snapshot { _tick: u32::MAX, _transform_states: part.to_vec() });
+            assert!(packet.serialize_packet().len() <= MAX_PACKET_SIZE);
+        }
+        // one more state does not fit
+        let packet = Packet::create_packet(0, 0, 0, PacketPayload::Snapshot { _tick: 0, _transform_states: create_transform_states(MAX_SNAPSHOT_TRANSFORM_STATE_COUNT + 1) });
+        assert!(MAX_PACKET_SIZE < packet.serialize_packet().len());
+        assert_eq!(1, split_transform_states(&[]).len());
+    }
+
+    #[test]
+    fn test_sequence_greater_than() {
+        assert!(sequence_greater_than(2, 1));
+        assert!(false == sequence_greater_than(1, 2));
+        assert!(false == sequence_greater_than(1, 1));
+        // wrap around
+        assert!(sequence_greater_than(0, SequenceNumber::MAX));
+        assert!(sequence_greater_than(10, SequenceNumber::MAX - 10));
+        assert!(false == sequence_greater_than(SequenceNumber::MAX, 0));
+    }
+
+    #[test]
+    fn test_is_sequence_acked() {
+        assert!(is_sequence_acked(10, 10, 0));
+        assert!(false == is_sequence_acked(9, 10, 0));
+        assert!(is_sequence_acked(9, 10, 0b1));
+        assert!(is_sequence_acked(7, 10, 0b100));
+        assert!(false == is_sequence_acked(11, 10, u32::MAX));
+        assert!(is_sequence_acked(100 - ACK_BITS_COUNT, 100, u32::MAX));
+        assert!(false == is_sequence_acked(100 - ACK_BITS_COUNT - 1, 100, u32::MAX));
+        assert!(is_sequence_acked(SequenceNumber::MAX, 1, 0b10));
+    }
+}
@@ -0,0 +1,691 @@
+use std::collections::{ BTreeMap, BTreeSet, VecDeque };
+use std::io;
+use std::net::{ SocketAddr, UdpSocket };
+
+use crate::network::packet::{
+    is_sequence_acked,
+    sequence_greater_than,
+    split_transform_states,
+    NetInputState,
+    NetObjectId,
+    NetTransformState,
+    Packet,
+    PacketPayload,
+    SequenceNumber,
+    ACK_BITS_COUNT,
+    MAX_PACKET_SIZE,
+};
+use crate::renderer::render_object::RenderObjectData;
+use crate::utilities::system::RcRefCell;
+
+pub const DEFAULT_NET_PORT: u16 = 7777;
+pub const NET_TICK_RATE: f64 = 20.0;
+pub const NET_TICK_TIME: f64 = 1.0 / NET_TICK_RATE;
+pub const INTERPOLATION_DELAY: f64 = NET_TICK_TIME * 2.0; // render the clients two snapshots behind
+pub const MAX_SNAPSHOT_BUFFER_COUNT: usize = 32;
+pub const CONNECTION_TIMEOUT: f64 = 5.0;
+pub const RESEND_TIME: f64 = NET_TICK_TIME * 4.0; // the reliable payloads which are not acked are sent again
+
+#[derive(Clone, Debug, Copy, PartialEq, Eq)]
+pub enum NetRole {
+    ListenServer,
+    Client,
+}
+
+#[derive(Clone, Debug)]
+pub struct NetSnapshot {
+    pub _tick: u32,
+    pub _transform_states: Vec<NetTransformState>,
+}
+
+// client side snapshots ordered by tick, interpolated at (latest time - INTERPOLATION_DELAY).
+#[derive(Clone, Debug, Default)]
+pub struct SnapshotBuffer {
+    pub _snapshots: VecDeque<NetSnapshot>,
+}
+
+impl SnapshotBuffer {
+    // the parts of a snapshot have the same tick, they are merged.
+    pub fn push_snapshot(&mut self, snapshot: NetSnapshot) {
+        // drop duplicated states, udp can reorder packets.
+        if let Some(index) = self._snapshots.iter().position(|exists| snapshot._tick <= exists._tick) {
+            if self._snapshots[index]._tick == snapshot._tick {
+                let exists = &mut self._snapshots[index];
+                for transform_state in snapshot._transform_states.into_iter() {
+                    if false == exists._transform_states.iter().any(|exist_state| exist_state._net_object_id == transform_state._net_object_id) {
+                        exists._transform_states.push(transform_state);
+                    }
+                }
+                return;
+            }
+            self._snapshots.insert(index, snapshot);
+        } else {
+            self._snapshots.push_back(snapshot);
+        }
+        while MAX_SNAPSHOT_BUFFER_COUNT < self._snapshots.len() {
+            self._snapshots.pop_front();
+        }
+    }
+
+    pub fn get_latest_tick(&self) -> Option<u32> {
+        self._snapshots.back().map(|snapshot| snapshot._tick)
+    }
+
+    // render_tick is fractional, returns None until two snapshots surround it.
+    pub fn interpolate(&self, render_tick: f64) -> Option<Vec<NetTransformState>> {
+        for i in 1..self._snapshots.len() {
+            let prev = &self._snapshots[i - 1];
+            let next = &self._snapshots[i];
+            if (prev._tick as f64) <= render_tick && render_tick <= (next._tick as f64) {
+                let ratio = ((render_tick - prev._tick as f64) / (next._tick - prev._tick) as f64) as f32;
+                return Some(interpolate_transform_states(&prev._transform_states, &next._transform_states, ratio));
+            }
+        }
+        None
+    }
+}
+
+// objects which exist only in one of the snapshots snap to that state.
+pub fn interpolate_transform_states(prev_states: &[NetTransformState], next_states: &[NetTransformState], ratio: f32) -> Vec<NetTransformState> {
+    next_states.iter().map(|next_state| {
+        match prev_states.iter().find(|prev_state| prev_state._net_object_id == next_state._net_object_id) {
+            Some(prev_state) => NetTransformState {
+                _net_object_id: next_state._net_object_id,
+                _position: prev_state._position.lerp(&next_state._position, ratio),
+                _rotation: prev_state._rotation.lerp(&next_state._rotation, ratio),
+                _scale: prev_state._scale.lerp(&next_state._scale, ratio),
+            },
+            None => next_state.clone(),
+        }
+    }).collect()
+}
+
+pub struct ReliablePayload {
+    pub _sequence: SequenceNumber,
+    pub _payload: PacketPayload,
+    pub _send_time: f64,
+}
+
+pub struct NetConnection {
+    pub _address: SocketAddr,
+    pub _client_id: u32,
+    pub _local_sequence: SequenceNumber,
+    pub _remote_sequence: SequenceNumber,
+    pub _remote_ack_bits: u32, // the sequences before _remote_sequence which are received, see PacketHeader::_ack_bits
+    pub _last_receive_time: f64,
+    pub _input_state: NetInputState,
+    pub _reliable_payloads: Vec<ReliablePayload>, // sent and not acked yet
+}
+
+impl NetConnection {
+    pub fn create_connection(address: SocketAddr, client_id: u32, elapsed_time: f64) -> NetConnection {
+        NetConnection {
+            _address: address,
+            _client_id: client_id,
+            _local_sequence: 0,
+            _remote_sequence: 0,
+            _remote_ack_bits: 0,
+            _last_receive_time: elapsed_time,
+            _input_state: NetInputState::default(),
+            _reliable_payloads: Vec::new(),
+        }
+    }
+
+    // returns false for the stale packets, they must be ignored.
+    pub fn receive_sequence(&mut self, sequence: SequenceNumber, elapsed_time: f64) -> bool {
+        self._last_receive_time = elapsed_time;
+        if 0 == self._remote_sequence {
+            self._remote_sequence = sequence;
+            return true;
+        }
+        if sequence_greater_than(sequence, self._remote_sequence) {
+            let distance = sequence.wrapping_sub(self._remote_sequence);
+            self._remote_ack_bits = if distance < ACK_BITS_COUNT { self._remote_ack_bits << distance } else { 0 };
+            if distance <= ACK_BITS_COUNT {
+                self._remote_ack_bits |= 1 << (distance - 1);
+            }
+            self._remote_sequence = sequence;
+            return true;
+        }
+        let distance = self._remote_sequence.wrapping_sub(sequence);
+        if 0 < distance && distance <= ACK_BITS_COUNT {
+            self._remote_ack_bits |= 1 << (distance - 1);
+        }
+        false
+    }
+
+    // the reliable payloads acked by the header of the remote are done.
+    pub fn receive_acks(&mut self, ack: SequenceNumber, ack_bits: u32) {
+        self._reliable_payloads.retain(|reliable_payload| false == is_sequence_acked(reliable_payload._sequence, ack, ack_bits));
+    }
+
+    // the payloads to be sent again with the new sequences.
+    pub fn take_resend_payloads(&mut self, elapsed_time: f64) -> Vec<PacketPayload> {
+        let mut resend_payloads: Vec<PacketPayload> = Vec::new();
+        self._reliable_payloads.retain(|reliable_payload| {
+            if RESEND_TIME <= (elapsed_time - reliable_payload._send_time) {
+                resend_payloads.push(reliable_payload._payload.clone());
+                return false;
+            }
+            true
+        });
+        resend_payloads
+    }
+
+    pub fn next_sequence(&mut self) -> SequenceNumber {
+        self._local_sequence = self._local_sequence.wrapping_add(1);
+        self._local_sequence
+    }
+}
+
+pub struct ReplicatedObject {
+    pub _object_name: String,
+    pub _model_name: String,
+    pub _render_object: RcRefCell<RenderObjectData>,
+}
+
+// Prototype: server-authoritative transforms, no prediction and no lag compensation.
+pub struct NetworkManager {
+    pub _net_role: NetRole,
+    pub _socket: UdpSocket,
+    pub _connections: Vec<NetConnection>,
+    pub _server_address: Option<SocketAddr>,
+    pub _next_client_id: u32, // never reused, the clients which left keep their ids
+
+    pub _tick: u32,
+    pub _tick_accumulator: f64,
+    pub _elapsed_time: f64,
+    pub _next_net_object_id: NetObjectId,
+    pub _replicated_objects: BTreeMap<NetObjectId, ReplicatedObject>,
+    pub _snapshot_buffer: SnapshotBuffer,
+    pub _render_tick: f64,
+    pub _spawn_requests: Vec<(NetObjectId, String, String)>, // client: (net_object_id, object_name, model_name) to be spawned by the scene
+    pub _despawn_requests: Vec<NetObjectId>,
+    pub _despawned_net_object_ids: BTreeSet<NetObjectId>, // client: the spawns which arrive after the despawn are ignored
+    pub _local_input_state: NetInputState,
+}
+
+impl NetworkManager {
+    pub fn create_listen_server(port: u16) -> io::Result<NetworkManager> {
+        let socket = UdpSocket::bind(SocketAddr::from(([0, 0, 0, 0], port)))?;
+        log::info!("create_listen_server: {:?}", socket.local_addr());
+        NetworkManager::create_network_manager(NetRole::ListenServer, socket, None)
+    }
+
+    pub fn create_client(server_address: SocketAddr) -> io::Result<NetworkManager> {
+        let socket = UdpSocket::bind(SocketAddr::from(([0, 0, 0, 0], 0)))?;
+        log::info!("create_client: {:?} -> {:?}", socket.local_addr(), server_address);
+        let mut network_manager = NetworkManager::create_network_manager(NetRole::Client, socket, Some(server_address))?;
+        network_manager._connections.push(NetConnection::create_connection(server_address, 0, 0.0));
+        network_manager.send_packet(0, PacketPayload::Connect);
+        Ok(network_manager)
+    }
+
+    fn create_network_manager(net_role: NetRole, socket: UdpSocket, server_address: Option<SocketAddr>) -> io::Result<NetworkManager> {
+        socket.set_nonblocking(true)?;
+        Ok(NetworkManager {
+            _net_role: net_role,
+            _socket: socket,
+            _connections: Vec::new(),
+            _server_address: server_address,
+            _next_client_id: 1,
+            _tick: 0,
+            _tick_accumulator: 0.0,
+            _elapsed_time: 0.0,
+            _next_net_object_id: 1,
+            _replicated_objects: BTreeMap::new(),
+            _snapshot_buffer: SnapshotBuffer::default(),
+            _render_tick: 0.0,
+            _spawn_requests: Vec::new(),
+            _despawn_requests: Vec::new(),
+            _despawned_net_object_ids: BTreeSet::new(),
+            _local_input_state: NetInputState::default(),
+        })
+    }
+
+    pub fn destroy_network_manager(&mut self) {
+        for i in 0..self._connections.len() {
+            self.send_packet(i, PacketPayload::Disconnect);
+        }
+        self._connections.clear();
+        self._replicated_objects.clear();
+    }
+
+    pub fn is_server(&self) -> bool {
+        NetRole::ListenServer == self._net_role
+    }
+
+    // server: tag a render object to be replicated to the clients.
+    pub fn regist_replicated_object(&mut self, object_name: &str, model_name: &str, render_object: &RcRefCell<RenderObjectData>) -> NetObjectId {
+        let net_object_id = self._next_net_object_id;
+        self._next_net_object_id += 1;
+        self._replicated_objects.insert(net_object_id, ReplicatedObject {
+            _object_name: String::from(object_name),
+            _model_name: String::from(model_name),
+            _render_object: render_object.clone(),
+        });
+        self.broadcast_payload(PacketPayload::Spawn { _net_object_id: net_object_id, _object_name: String::from(object_name), _model_name: String::from(model_name) });
+        net_object_id
+    }
+
+    // server: despawn. client: the scene removed the spawned object.
+    pub fn unregist_replicated_object(&mut self, net_object_id: NetObjectId) {
+        self._replicated_objects.remove(&net_object_id);
+        if self.is_server() {
+            self.broadcast_payload(PacketPayload::Despawn { _net_object_id: net_object_id });
+        }
+    }
+
+    // client: bind the object spawned by the scene for a spawn request.
+    pub fn bind_replicated_object(&mut self, net_object_id: NetObjectId, object_name: &str, model_name: &str, render_object: &RcRefCell<RenderObjectData>) {
+        self._replicated_objects.insert(net_object_id, ReplicatedObject {
+            _object_name: String::from(object_name),
+            _model_name: String::from(model_name),
+            _render_object: render_object.clone(),
+        });
+    }
+
+    pub fn take_spawn_requests(&mut self) -> Vec<(NetObjectId, String, String)> {
+        std::mem::replace(&mut self._spawn_requests, Vec::new())
+    }
+
+    pub fn take_despawn_requests(&mut self) -> Vec<NetObjectId> {
+        std::mem::replace(&mut self._despawn_requests, Vec::new())
+    }
+
+    // client: the action map state forwarded to the server at the next tick.
+    pub fn set_local_input_state(&mut self, input_state: &NetInputState) {
+        self._local_input_state = input_state.clone();
+    }
+
+    // server: the latest input of the client for the server-authoritative camera-controlled object.
+    pub fn get_client_input_state(&self, client_id: u32) -> Option<&NetInputState> {
+        self._connections.iter().find(|connection| connection._client_id == client_id).map(|connection| &connection._input_state)
+    }
+
+    fn broadcast_payload(&mut self, payload: PacketPayload) {
+        if let PacketPayload::Despawn { _net_object_id } = payload {
+            // the spawn is not sent again, the clients which have not received it ignore the despawn
+            for connection in self._connections.iter_mut() {
+                connection._reliable_payloads.retain(|reliable_payload| match reliable_payload._payload {
+                    PacketPayload::Spawn { _net_object_id: spawn_net_object_id, .. } => spawn_net_object_id != _net_object_id,
+                    _ => true,
+                });
+            }
+        }
+        for i in 0..self._connections.len() {
+            self.send_packet(i, payload.clone());
+        }
+    }
+
+    fn send_snapshot(&mut self, connection_index: usize, tick: u32, transform_states: &[NetTransformState]) {
+        for transform_states_part in split_transform_states(transform_states) {
+            self.send_packet(connection_index, PacketPayload::Snapshot { _tick: tick, _transform_states: transform_states_part.to_vec() });
+        }
+    }
+
+    // the reliable payloads are kept until acked, see NetConnection::take_resend_payloads
+    fn send_packet(&mut self, connection_index: usize, payload: PacketPayload) {
+        let elapsed_time = self._elapsed_time;
+        let connection = &mut self._connections[connection_index];
+        let sequence = connection.next_sequence();
+        if payload.is_reliable() {
+            connection._reliable_payloads.push(ReliablePayload {
+                _sequence: sequence,
+                _payload: payload.clone(),
+                _send_time: elapsed_time,
+            });
+        }
+        let packet = Packet::create_packet(sequence, connection._remote_sequence, connection._remote_ack_bits, payload);
+        let bytes = packet.serialize_packet();
+        if MAX_PACKET_SIZE < bytes.len() {
+            log::warn!("send_packet: packet size({}) exceeds {}", bytes.len(), MAX_PACKET_SIZE);
+            return;
+        }
+        if let Err(e) = self._socket.send_to(&bytes, connection._address) {
+            log::warn!("send_packet: {:?} {:?}", connection._address, e);
+        }
+    }
+
+    fn receive_packets(&mut self) {
+        let mut buffer = [0u8; MAX_PACKET_SIZE];
+        loop {
+            let (size, address) = match self._socket.recv_from(&mut buffer) {
+                Ok(received) => received,
+                Err(ref e) if io::ErrorKind::WouldBlock == e.kind() => break,
+                Err(e) => {
+                    log::warn!("receive_packets: {:?}", e);
+                    break;
+                }
+            };
+            if let Some(packet) = Packet::deserialize_packet(&buffer[..size]) {
+                self.process_packet(address, packet);
+            }
+        }
+    }
+
+    fn process_packet(&mut self, address: SocketAddr, packet: Packet) {
+        let connection_index = match self._connections.iter().position(|connection| connection._address == address) {
+            Some(connection_index) => connection_index,
+            None => {
+                if self.is_server() && PacketPayload::Connect == packet._payload {
+                    let client_id = self._next_client_id;
+                    self._next_client_id += 1;
+                    log::info!("client connected: {} {:?}", client_id, address);
+                    self._connections.push(NetConnection::create_connection(address, client_id, self._elapsed_time));
+                    let connection_index = self._connections.len() - 1;
+                    self.send_packet(connection_index, PacketPayload::Accept { _client_id: client_id });
+                    let spawns: Vec<PacketPayload> = self._replicated_objects.iter().map(|(net_object_id, replicated_object)| {
+                        PacketPayload::Spawn { _net_object_id: *net_object_id, _object_name: replicated_object._object_name.clone(), _model_name: replicated_object._model_name.clone() }
+                    }).collect();
+                    for spawn in spawns {
+                        self.send_packet(connection_index, spawn);
+                    }
+                }
+                return;
+            }
+        };
+
+        let is_newer = self._connections[connection_index].receive_sequence(packet._header._sequence, self._elapsed_time);
+        self._connections[connection_index].receive_acks(packet._header._ack, packet._header._ack_bits);
+        match packet._payload {
+            PacketPayload::Accept { _client_id } => {
+                log::info!("connected to server: client_id {}", _client_id);
+                self._connections[connection_index]._client_id = _client_id;
+            },
+            PacketPayload::Disconnect => {
+                log::info!("disconnected: {:?}", address);
+                self._connections.remove(connection_index);
+            },
+            PacketPayload::Spawn { _net_object_id, _object_name, _model_name } => {
+                // the spawn is sent again when the ack is lost
+                let is_known = self._replicated_objects.contains_key(&_net_object_id)
+                    || self._despawned_net_object_ids.contains(&_net_object_id)
+                    || self._spawn_requests.iter().any(|spawn_request| spawn_request.0 == _net_object_id);
+                if false == is_known {
+                    self._spawn_requests.push((_net_object_id, _object_name, _model_name));
+                }
+            },
+            PacketPayload::Despawn { _net_object_id } => {
+                if self._despawned_net_object_ids.insert(_net_object_id) {
+                    self._spawn_requests.retain(|spawn_request| spawn_request.0 != _net_object_id);
+                    if self._replicated_objects.remove(&_net_object_id).is_some() {
+                        self._despawn_requests.push(_net_object_id);
+                    }
+                }
+            },
+            PacketPayload::Snapshot { _tick, _transform_states } => {
+                self._snapshot_buffer.push_snapshot(NetSnapshot { _tick, _transform_states });
+            },
+            PacketPayload::Input { _input_state } => {
+                if is_newer {
+                    self._connections[connection_index]._input_state = _input_state;
+                }
+            },
+            PacketPayload::Connect => (),
+        }
+    }
+
+    fn collect_transform_states(&self) -> Vec<NetTransformState> {
+        self._replicated_objects.iter().map(|(net_object_id, replicated_object)| {
+            let render_object = replicated_object._render_object.borrow();
+            let transform_object = render_object.get_transform_object_data();
+            NetTransformState {
+                _net_object_id: *net_object_id,
+                _position: transform_object.get_position().clone(),
+                _rotation: transform_object.get_rotation().clone(),
+                _scale: transform_object.get_scale().clone(),
+            }
+        }).collect()
+    }
+
+    fn apply_transform_states(&self, transform_states: &[NetTransformState]) {
+        for transform_state in transform_states.iter() {
+            if let Some(replicated_object) = self._replicated_objects.get(&transform_state._net_object_id) {
+                let transform_object = &mut replicated_object._render_object.borrow_mut()._transform_object;
+                transform_object.set_position(&transform_state._position);
+                transform_object.set_rotation(&transform_state._rotation);
+                transform_object.set_scale(&transform_state._scale);
+            }
+        }
+    }
+
+    fn update_tick(&mut self) {
+        self._tick += 1;
+        if self.is_server() {
+            let transform_states = self.collect_transform_states();
+            for i in 0..self._connections.len() {
+                self.send_snapshot(i, self._tick, &transform_states);
+            }
+        } else if false == self._connections.is_empty() {
+            let mut input_state = self._local_input_state.clone();
+            input_state._tick = self._tick;
+            self.send_packet(0, PacketPayload::Input { _input_state: input_state });
+        }
+    }
+
+    // call before updating the render objects, the client writes interpolated transforms to TransformObjectData.
+    pub fn update_network_manager(&mut self, delta_time: f64) {
+        self._elapsed_time += delta_time;
+        self.receive_packets();
+
+        // drop timed out connections
+        let elapsed_time = self._elapsed_time;
+        self._connections.retain(|connection| {
+            let is_alive = (elapsed_time - connection._last_receive_time) < CONNECTION_TIMEOUT;
+            if false == is_alive {
+                log::info!("connection timeout: {:?}", connection._address);
+            }
+            is_alive
+        });
+
+        for i in 0..self._connections.len() {
+            for payload in self._connections[i].take_resend_payloads(elapsed_time) {
+                self.send_packet(i, payload);
+            }
+        }
+
+        self._tick_accumulator += delta_time;
+        while NET_TICK_TIME <= self._tick_accumulator {
+            self._tick_accumulator -= NET_TICK_TIME;
+            self.update_tick();
+        }
+
+        if false == self.is_server() {
+            if let Some(latest_tick) = self._snapshot_buffer.get_latest_tick() {
+                let target_render_tick = latest_tick as f64 - INTERPOLATION_DELAY / NET_TICK_TIME;
+                // advance smoothly, snap when too far behind or ahead.
+                self._render_tick += delta_time / NET_TICK_TIME;
+                if 1.0 < (self._render_tick - target_render_tick).abs() {
+                    self._render_tick = target_render_tick;
+                }
+                if let Some(transform_states) = self._snapshot_buffer.interpolate(self._render_tick) {
+                    self.apply_transform_states(&transform_states);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+    use nalgebra::Vector3;
+    use super::*;
+
+    fn create_transform_states(count: usize, x: f32) -> Vec<NetTransformState> {
+        (0..count).map(|i| NetTransformState {
+            _net_object_id: i as NetObjectId,
+            _position: Vector3::new(x, i as f32, 0.0),
+            _rotation: Vector3::zeros(),
+            _scale: Vector3::new(1.0, 1.0, 1.0),
+        }).collect()
+    }
+
+    // the packets of the loopback arrive in a few milliseconds
+    fn receive_until(network_manager: &mut NetworkManager, condition: impl Fn(&NetworkManager) -> bool) -> bool {
+        for _ in 0..1000 {
+            network_manager.receive_packets();
+            if condition(network_manager) {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        false
+    }
+
+    // the packets on the way are lost
+    fn drop_packets(network_manager: &NetworkManager) {
+        thread::sleep(Duration::from_millis(20));
+        let mut buffer = [0u8; MAX_PACKET_SIZE];
+        while network_manager._socket.recv_from(&mut buffer).is_ok() {}
+    }
+
+    fn create_loopback_server() -> NetworkManager {
+        NetworkManager::create_listen_server(0).unwrap()
+    }
+
+    fn connect_client(server: &mut NetworkManager) -> NetworkManager {
+        let server_address = SocketAddr::from(([127, 0, 0, 1], server._socket.local_addr().unwrap().port()));
+        let mut client = NetworkManager::create_client(server_address).unwrap();
+        let connection_count = server._connections.len();
+        assert!(receive_until(server, |server| connection_count < server._connections.len()));
+        assert!(receive_until(&mut client, |client| 0 != client._connections[0]._client_id));
+        client
+    }
+
+    #[test]
+    fn test_snapshot_interpolation() {
+        let mut snapshot_buffer = SnapshotBuffer::default();
+        snapshot_buffer.push_snapshot(NetSnapshot { _tick: 2, _transform_states: create_transform_states(1, 2.0) });
+        assert_eq!(None, snapshot_buffer.interpolate(1.5));
+        // reordered
+        snapshot_buffer.push_snapshot(NetSnapshot { _tick: 1, _transform_states: create_transform_states(1, 0.0) });
+        let transform_states = snapshot_buffer.interpolate(1.25).unwrap();
+        assert_eq!(0.5, transform_states[0]._position.x);
+        assert_eq!(Some(2), snapshot_buffer.get_latest_tick());
+
+        for tick in 3..(MAX_SNAPSHOT_BUFFER_COUNT as u32 + 10) {
+            snapshot_buffer.push_snapshot(NetSnapshot { _tick: tick, _transform_states: Vec::new() });
+        }
+        assert_eq!(MAX_SNAPSHOT_BUFFER_COUNT, snapshot_buffer._snapshots.len());
+    }
+
+    #[test]
+    fn test_snapshot_parts_are_merged() {
+        let transform_states = create_transform_states(4, 1.0);
+        let mut snapshot_buffer = SnapshotBuffer::default();
+        snapshot_buffer.push_snapshot(NetSnapshot { _tick: 1, _transform_states: transform_states[..2].to_vec() });
+        snapshot_buffer.push_snapshot(NetSnapshot { _tick: 1, _transform_states: transform_states[1..].to_vec() });
+        assert_eq!(1, snapshot_buffer._snapshots.len());
+        assert_eq!(transform_states, snapshot_buffer._snapshots[0]._transform_states);
+    }
+
+    #[test]
+    fn test_receive_sequence_ack_bits() {
+        let address = SocketAddr::from(([127, 0, 0, 1], DEFAULT_NET_PORT));
+        let mut connection = NetConnection::create_connection(address, 1, 0.0);
+        assert!(connection.receive_sequence(1, 0.0));
+        assert!(connection.receive_sequence(3, 0.0));
+        assert_eq!(0b10, connection._remote_ack_bits);
+        // late and duplicated
+        assert!(false == connection.receive_sequence(2, 0.0));
+        assert!(false == connection.receive_sequence(2, 0.0));
+        assert_eq!(0b11, connection._remote_ack_bits);
+        assert!(connection.receive_sequence(3 + ACK_BITS_COUNT + 1, 0.0));
+        assert_eq!(0, connection._remote_ack_bits);
+
+        connection._reliable_payloads = (1..=3).map(|sequence| ReliablePayload {
+            _sequence: sequence,
+            _payload: PacketPayload::Despawn { _net_object_id: sequence as NetObjectId },
+            _send_time: 0.0,
+        }).collect();
+        connection.receive_acks(3, 0b10);
+        assert_eq!(1, connection._reliable_payloads.len());
+        assert_eq!(2, connection._reliable_payloads[0]._sequence);
+        assert!(connection.take_resend_payloads(RESEND_TIME * 0.5).is_empty());
+        assert_eq!(vec![PacketPayload::Despawn { _net_object_id: 2 }], connection.take_resend_payloads(RESEND_TIME));
+        assert!(connection._reliable_payloads.is_empty());
+    }
+
+    #[test]
+    fn test_loopback_client_id() {
+        let mut server = create_loopback_server();
+        let mut client_a = connect_client(&mut server);
+        let client_b = connect_client(&mut server);
+        assert_eq!(1, client_a._connections[0]._client_id);
+        assert_eq!(2, client_b._connections[0]._client_id);
+
+        client_a.destroy_network_manager();
+        assert!(receive_until(&mut server, |server| 1 == server._connections.len()));
+        let client_c = connect_client(&mut server);
+        assert_eq!(3, client_c._connections[0]._client_id);
+        let mut client_ids: Vec<u32> = server._connections.iter().map(|connection| connection._client_id).collect();
+        client_ids.sort();
+        assert_eq!(vec![2, 3], client_ids);
+    }
+
+    #[test]
+    fn test_loopback_snapshot_parts() {
+        let mut server = create_loopback_server();
+        let mut client = connect_client(&mut server);
+        let transform_states = create_transform_states(100, 1.0);
+        server.send_snapshot(0, 5, &transform_states);
+        assert!(receive_until(&mut client, |client| {
+            client._snapshot_buffer._snapshots.iter().any(|snapshot| 5 == snapshot._tick && transform_states.len() == snapshot._transform_states.len())
+        }));
+        let mut received_states = client._snapshot_buffer._snapshots.back().unwrap()._transform_states.clone();
+        received_states.sort_by_key(|transform_state| transform_state._net_object_id);
+        assert_eq!(transform_states, received_states);
+    }
+
+    #[test]
+    fn test_loopback_spawn_resend() {
+        let mut server = create_loopback_server();
+        let mut client = connect_client(&mut server);
+        let spawn = PacketPayload::Spawn { _net_object_id: 7, _object_name: String::from("cube"), _model_name: String::from("cube") };
+
+        // the spawn is lost, then sent again
+        server.broadcast_payload(spawn.clone());
+        drop_packets(&client);
+        assert!(client._spawn_requests.is_empty());
+        server.update_network_manager(RESEND_TIME);
+        assert!(receive_until(&mut client, |client| 1 == client._spawn_requests.len()));
+
+        // the ack is lost, the duplicated spawn is ignored
+        server.update_network_manager(RESEND_TIME);
+        thread::sleep(Duration::from_millis(20));
+        client.receive_packets();
+        assert_eq!(1, client._spawn_requests.len());
+
+        // the input of the client acks the spawn
+        client.update_network_manager(NET_TICK_TIME);
+        assert!(receive_until(&mut server, |server| server._connections[0]._reliable_payloads.is_empty()));
+        assert_eq!(vec![(7, String::from("cube"), String::from("cube"))], client.take_spawn_requests());
+    }
+
+    #[test]
+    fn test_loopback_despawn() {
+        let mut server = create_loopback_server();
+        let mut client = connect_client(&mut server);
+        let spawn = PacketPayload::Spawn { _net_object_id: 7, _object_name: String::from("cube"), _model_name: String::from("cube") };
+        server.broadcast_payload(spawn.clone());
+        assert!(receive_until(&mut client, |client| 1 == client._spawn_requests.len()));
+
+        // the despawn is lost, then sent again
+        server.broadcast_payload(PacketPayload::Despawn { _net_object_id: 7 });
+        drop_packets(&client);
+        assert_eq!(1, client._spawn_requests.len());
+        server.update_network_manager(RESEND_TIME);
+        assert!(receive_until(&mut client, |client| client._spawn_requests.is_empty()));
+        assert!(client._despawned_net_object_ids.contains(&7));
+
+        // the spawn which arrives after the despawn
+        server.send_packet(0, spawn);
+        thread::sleep(Duration::from_millis(20));
+        client.receive_packets();
+        assert!(client._spawn_requests.is_empty());
+        assert!(client.take_despawn_requests().is_empty());
+    }
+}
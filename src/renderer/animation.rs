@@ -195,7 +195,7 @@ impl AnimationData {
 
         unsafe {
             for (i, animation_node_create_info) in animation_node_create_infos.iter().enumerate() {
-                let animation_node = AnimationNodeData::create_animation_node_data(&(*animation_data._skeleton)._bones[i], animation_node_create_info);
+                let animation_node = AnimationNodeData::create_animation_node_data(&(&*animation_data._skeleton)._bones[i], animation_node_create_info);
                 let frame_count = animation_node._frame_times.len();
                 if animation_data._frame_count < frame_count {
                     animation_data._frame_count = frame_count;
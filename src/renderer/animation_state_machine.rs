@@ -0,0 +1,765 @@
+use std::collections::HashMap;
+
+use serde::{ Serialize, Deserialize };
+use serde_json;
+
+use crate::renderer::mesh::MeshData;
+use crate::renderer::render_object::AnimationPlayArgs;
+use crate::utilities::system::RcRefCell;
+
+pub const ANY_STATE: &str = "*"; // _from of the transitions which are evaluated in every state
+pub const INVALID_STATE_INDEX: usize = std::usize::MAX;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Copy, PartialEq, Eq)]
+pub enum AnimParameterType {
+    Float,
+    Bool,
+    Trigger, // true until a transition which uses it has been taken
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct AnimParameterCreateInfo {
+    pub _name: String,
+    pub _parameter_type: AnimParameterType,
+    pub _default_value: f32, // bool and trigger: 0.0 or 1.0
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct BlendTreeClip {
+    pub _clip: String, // animation mesh name
+    pub _threshold: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum AnimStateMotion {
+    Clip(String), // animation mesh name
+    BlendTree1D { _parameter: String, _clips: Vec<BlendTreeClip>, _blend_time: f32 }, // the clips in ascending order of the thresholds
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct AnimStateCreateInfo {
+    pub _name: String,
+    pub _motion: AnimStateMotion,
+    pub _speed: f32,
+    pub _loop: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Copy, PartialEq, Eq)]
+pub enum AnimConditionOp {
+    Greater,
+    Less,
+    Equal,
+    NotEqual,
+    IsTrue,
+    IsFalse,
+    Triggered,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct AnimCondition {
+    pub _parameter: String,
+    pub _op: AnimConditionOp,
+    pub _value: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct AnimTransitionCreateInfo {
+    pub _from: String, // state name or ANY_STATE
+    pub _to: String,
+    pub _conditions: Vec<AnimCondition>, // all of them, empty with _exit_time: leave at the exit time
+    pub _duration: f32, // cross-fade time, AnimationPlayArgs::_blend_time
+    pub _exit_time: Option<f32>, // normalized time of the source state, the conditions are checked after it
+    pub _interruptible: bool, // the transitions of the target state and the any state transitions can cut this one
+    pub _can_transition_to_self: bool, // any state transitions only
+}
+
+// ex) resource/animation_state_machines/player.asm
+// { "_parameters": [{ "_name": "speed", "_parameter_type": "Float" }, { "_name": "jump", "_parameter_type": "Trigger" }],
+//   "_states": [{ "_name": "locomotion", "_motion": { "BlendTree1D": { "_parameter": "speed", "_clips": [{ "_clip": "player/idle", "_threshold": 0.0 }, { "_clip": "player/run", "_threshold": 5.0 }], "_blend_time": 0.2 } } },
+//               { "_name": "jump", "_motion": { "Clip": "player/jump" }, "_loop": false }],
+//   "_transitions": [{ "_from": "*", "_to": "jump", "_conditions": [{ "_parameter": "jump", "_op": "Triggered" }], "_duration": 0.1 },
+//                    { "_from": "jump", "_to": "locomotion", "_exit_time": 0.9, "_duration": 0.2 }],
+//   "_default_state": "locomotion" }
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+pub struct AnimStateMachineCreateInfo {
+    pub _parameters: Vec<AnimParameterCreateInfo>,
+    pub _states: Vec<AnimStateCreateInfo>,
+    pub _transitions: Vec<AnimTransitionCreateInfo>,
+    pub _default_state: String, // empty: the first state
+}
+
+// the asset, shared by the render objects. the hot reload replaces the contents in place and increases the revision.
+#[derive(Clone, Debug)]
+pub struct AnimStateMachineData {
+    pub _name: String,
+    pub _revision: u32,
+    pub _create_info: AnimStateMachineCreateInfo,
+    pub _state_indices: HashMap<String, usize>,
+    pub _clip_meshes: HashMap<String, RcRefCell<MeshData>>, // resolved by Resources::load_anim_state_machine_datas
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnimParameter {
+    pub _parameter_type: AnimParameterType,
+    pub _value: f32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnimActiveTransition {
+    pub _transition_index: usize,
+    pub _elapsed_time: f32,
+    pub _duration: f32,
+}
+
+// what the render object plays after the update
+#[derive(Clone, Debug)]
+pub struct AnimStateMachineMotion {
+    pub _animation_mesh: RcRefCell<MeshData>,
+    pub _animation_args: AnimationPlayArgs,
+}
+
+// the runtime component on RenderObjectData, see RenderObjectData::set_anim_state_machine
+#[derive(Clone, Debug)]
+pub struct AnimStateMachine {
+    pub _state_machine_data: RcRefCell<AnimStateMachineData>,
+    pub _revision: u32,
+    pub _parameters: HashMap<String, AnimParameter>,
+    pub _current_state: usize,
+    pub _current_state_name: String, // the name in the asset of _revision, the hot reload re-resolves the state by it
+    pub _state_elapsed_time: f32, // seconds in the current state, scaled by the speed
+    pub _active_transition: Option<AnimActiveTransition>,
+    pub _current_clip: String, // the playing clip, the dominant clip of the blend tree
+    pub _blend_weights: Vec<f32>, // blend tree of the current state, for the debug
+    pub _need_restart: bool,
+}
+
+impl Default for AnimParameterCreateInfo {
+    fn default() -> AnimParameterCreateInfo {
+        AnimParameterCreateInfo {
+            _name: String::new(),
+            _parameter_type: AnimParameterType::Float,
+            _default_value: 0.0,
+        }
+    }
+}
+
+impl Default for BlendTreeClip {
+    fn default() -> BlendTreeClip {
+        BlendTreeClip {
+            _clip: String::new(),
+            _threshold: 0.0,
+        }
+    }
+}
+
+impl Default for AnimStateCreateInfo {
+    fn default() -> AnimStateCreateInfo {
+        AnimStateCreateInfo {
+            _name: String::new(),
+            _motion: AnimStateMotion::Clip(String::new()),
+            _speed: 1.0,
+            _loop: true,
+        }
+    }
+}
+
+impl Default for AnimCondition {
+    fn default() -> AnimCondition {
+        AnimCondition {
+            _parameter: String::new(),
+            _op: AnimConditionOp::IsTrue,
+            _value: 0.0,
+        }
+    }
+}
+
+impl Default for AnimTransitionCreateInfo {
+    fn default() -> AnimTransitionCreateInfo {
+        AnimTransitionCreateInfo {
+            _from: String::from(ANY_STATE),
+            _to: String::new(),
+            _conditions: Vec::new(),
+            _duration: 0.2,
+            _exit_time: None,
+            _interruptible: false,
+            _can_transition_to_self: false,
+        }
+    }
+}
+
+impl AnimStateMotion {
+    pub fn get_clip_names(&self) -> Vec<&String> {
+        match self {
+            AnimStateMotion::Clip(clip) => vec![clip],
+            AnimStateMotion::BlendTree1D { _clips, .. } => _clips.iter().map(|blend_tree_clip| &blend_tree_clip._clip).collect(),
+        }
+    }
+}
+
+// the weights of the two neighboring clips, the others are zero
+pub fn get_blend_tree_weights(clips: &[BlendTreeClip], value: f32) -> Vec<f32> {
+    let mut weights = vec![0.0; clips.len()];
+    if clips.is_empty() {
+        return weights;
+    }
+    let last = clips.len() - 1;
+    if value <= clips[0]._threshold {
+        weights[0] = 1.0;
+    } else if clips[last]._threshold <= value {
+        weights[last] = 1.0;
+    } else {
+        let upper = clips.iter().position(|clip| value < clip._threshold).unwrap_or(last);
+        let lower = upper - 1;
+        let range = clips[upper]._threshold - clips[lower]._threshold;
+        let ratio = if 0.0 < range { (value - clips[lower]._threshold) / range } else { 0.0 };
+        weights[lower] = 1.0 - ratio;
+        weights[upper] = ratio;
+    }
+    weights
+}
+
+pub fn evaluate_condition(condition: &AnimCondition, parameters: &HashMap<String, AnimParameter>) -> bool {
+    let value = match parameters.get(&condition._parameter) {
+        Some(parameter) => parameter._value,
+        None => return false,
+    };
+    match condition._op {
+        AnimConditionOp::Greater => condition._value < value,
+        AnimConditionOp::Less => value < condition._value,
+        AnimConditionOp::Equal => value == condition._value,
+        AnimConditionOp::NotEqual => value != condition._value,
+        AnimConditionOp::IsTrue | AnimConditionOp::Triggered => 0.0 != value,
+        AnimConditionOp::IsFalse => 0.0 == value,
+    }
+}
+
+pub fn evaluate_conditions(conditions: &[AnimCondition], parameters: &HashMap<String, AnimParameter>) -> bool {
+    conditions.iter().all(|condition| evaluate_condition(condition, parameters))
+}
+
+// the triggers are consumed only by the transition which has been taken
+pub fn consume_triggers(conditions: &[AnimCondition], parameters: &mut HashMap<String, AnimParameter>) {
+    for condition in conditions.iter() {
+        if let Some(parameter) = parameters.get_mut(&condition._parameter) {
+            if AnimParameterType::Trigger == parameter._parameter_type {
+                parameter._value = 0.0;
+            }
+        }
+    }
+}
+
+impl AnimStateMachineData {
+    pub fn create_anim_state_machine_data(name: &str, create_info: AnimStateMachineCreateInfo, clip_meshes: HashMap<String, RcRefCell<MeshData>>) -> AnimStateMachineData {
+        let state_indices: HashMap<String, usize> = create_info._states.iter().enumerate().map(|(index, state)| (state._name.clone(), index)).collect();
+        for transition in create_info._transitions.iter() {
+            if ANY_STATE != transition._from && false == state_indices.contains_key(&transition._from) {
+                log::warn!("{}: unknown transition source state {}", name, transition._from);
+            }
+            if false == state_indices.contains_key(&transition._to) {
+                log::warn!("{}: unknown transition target state {}", name, transition._to);
+            }
+        }
+        AnimStateMachineData {
+            _name: String::from(name),
+            _revision: 0,
+            _create_info: create_info,
+            _state_indices: state_indices,
+            _clip_meshes: clip_meshes,
+        }
+    }
+
+    pub fn parse_anim_state_machine_create_info(contents: &[u8]) -> Result<AnimStateMachineCreateInfo, String> {
+        serde_json::from_slice::<AnimStateMachineCreateInfo>(contents).map_err(|e| format!("{:?}", e))
+    }
+
+    // hot reload, the components re-resolve the states by name at the next update
+    pub fn reload_anim_state_machine_data(&mut self, create_info: AnimStateMachineCreateInfo, clip_meshes: HashMap<String, RcRefCell<MeshData>>) {
+        let revision = self._revision.wrapping_add(1);
+        *self = AnimStateMachineData::create_anim_state_machine_data(&self._name.clone(), create_info, clip_meshes);
+        self._revision = revision;
+    }
+
+    pub fn get_state_index(&self, state_name: &str) -> usize {
+        *self._state_indices.get(state_name).unwrap_or(&INVALID_STATE_INDEX)
+    }
+
+    pub fn get_default_state_index(&self) -> usize {
+        if self._create_info._states.is_empty() {
+            INVALID_STATE_INDEX
+        } else if self._create_info._default_state.is_empty() {
+            0
+        } else {
+            match self.get_state_index(&self._create_info._default_state) {
+                INVALID_STATE_INDEX => 0,
+                state_index => state_index,
+            }
+        }
+    }
+
+    pub fn get_clip_length(&self, clip_name: &str) -> f32 {
+        match self._clip_meshes.get(clip_name) {
+            Some(mesh_data) => mesh_data.borrow()._animation_datas.first().map(|animation_data| animation_data._animation_length).unwrap_or(0.0),
+            None => 0.0,
+        }
+    }
+}
+
+impl AnimStateMachine {
+    pub fn create_anim_state_machine(state_machine_data: &RcRefCell<AnimStateMachineData>) -> AnimStateMachine {
+        let mut anim_state_machine = AnimStateMachine {
+            _state_machine_data: state_machine_data.clone(),
+            _revision: state_machine_data.borrow()._revision,
+            _parameters: HashMap::new(),
+            _current_state: INVALID_STATE_INDEX,
+            _current_state_name: String::new(),
+            _state_elapsed_time: 0.0,
+            _active_transition: None,
+            _current_clip: String::new(),
+            _blend_weights: Vec::new(),
+            _need_restart: true,
+        };
+        anim_state_machine.update_parameter_definitions();
+        anim_state_machine._current_state = state_machine_data.borrow().get_default_state_index();
+        anim_state_machine._current_state_name = anim_state_machine.get_current_state_name();
+        anim_state_machine
+    }
+
+    // keeps the current values, adds the new parameters with the defaults and drops the removed ones
+    fn update_parameter_definitions(&mut self) {
+        let state_machine_data = self._state_machine_data.borrow();
+        let mut parameters: HashMap<String, AnimParameter> = HashMap::new();
+        for parameter_create_info in state_machine_data._create_info._parameters.iter() {
+            let value = match self._parameters.get(&parameter_create_info._name) {
+                Some(parameter) if parameter._parameter_type == parameter_create_info._parameter_type => parameter._value,
+                _ => parameter_create_info._default_value,
+            };
+            parameters.insert(parameter_create_info._name.clone(), AnimParameter { _parameter_type: parameter_create_info._parameter_type, _value: value });
+        }
+        self._parameters = parameters;
+    }
+
+    // after the hot reload: the same state by name or the default state
+    fn resolve_reloaded_states(&mut self, prev_state_name: &str) {
+        self.update_parameter_definitions();
+        let state_machine_data = self._state_machine_data.borrow();
+        self._revision = state_machine_data._revision;
+        self._active_transition = None;
+        self._current_state = match state_machine_data.get_state_index(prev_state_name) {
+            INVALID_STATE_INDEX => {
+                log::warn!("{}: the state {} has been removed", state_machine_data._name, prev_state_name);
+                self._need_restart = true;
+                state_machine_data.get_default_state_index()
+            },
+            state_index => state_index,
+        };
+        self._current_state_name = state_machine_data._create_info._states.get(self._current_state).map(|state| state._name.clone()).unwrap_or_default();
+    }
+
+    pub fn set_param(&mut self, name: &str, value: f32) {
+        match self._parameters.get_mut(name) {
+            Some(parameter) => parameter._value = value,
+            None => log::warn!("set_param: unknown parameter {}", name),
+        }
+    }
+
+    pub fn set_bool(&mut self, name: &str, value: bool) {
+        self.set_param(name, if value { 1.0 } else { 0.0 });
+    }
+
+    pub fn set_trigger(&mut self, name: &str) {
+        self.set_param(name, 1.0);
+    }
+
+    pub fn reset_trigger(&mut self, name: &str) {
+        self.set_param(name, 0.0);
+    }
+
+    pub fn get_param(&self, name: &str) -> f32 {
+        self._parameters.get(name).map(|parameter| parameter._value).unwrap_or(0.0)
+    }
+
+    pub fn get_current_state_name(&self) -> String {
+        let state_machine_data = self._state_machine_data.borrow();
+        match state_machine_data._create_info._states.get(self._current_state) {
+            Some(state) => state._name.clone(),
+            None => String::new(),
+        }
+    }
+
+    pub fn is_in_transition(&self) -> bool {
+        self._active_transition.is_some()
+    }
+
+    // ex) "locomotion (player/run 0.35) -> jump 0.05/0.10"
+    pub fn get_debug_text(&self) -> String {
+        let state_machine_data = self._state_machine_data.borrow();
+        let mut debug_text = format!("{} ({} {:.2})", self.get_current_state_name(), self._current_clip, self.get_normalized_time(&state_machine_data));
+        if let Some(active_transition) = self._active_transition.as_ref() {
+            let transition = &state_machine_data._create_info._transitions[active_transition._transition_index];
+            debug_text.push_str(&format!(" <- {} {:.2}/{:.2}", transition._from, active_transition._elapsed_time, active_transition._duration));
+        }
+        debug_text
+    }
+
+    fn get_normalized_time(&self, state_machine_data: &AnimStateMachineData) -> f32 {
+        let clip_length = state_machine_data.get_clip_length(&self._current_clip);
+        if 0.0 < clip_length { self._state_elapsed_time / clip_length } else { 0.0 }
+    }
+
+    // interruption rules: nothing cuts a transition in progress unless it is interruptible,
+    // then the any state transitions and the transitions of the target state are checked. the first one in the asset order wins.
+    fn find_transition(&self, state_machine_data: &AnimStateMachineData) -> Option<usize> {
+        if let Some(active_transition) = self._active_transition.as_ref() {
+            if false == state_machine_data._create_info._transitions[active_transition._transition_index]._interruptible {
+                return None;
+            }
+        }
+        let current_state_name = match state_machine_data._create_info._states.get(self._current_state) {
+            Some(state) => &state._name,
+            None => return None,
+        };
+        let normalized_time = self.get_normalized_time(state_machine_data);
+        for (transition_index, transition) in state_machine_data._create_info._transitions.iter().enumerate() {
+            let is_any_state = ANY_STATE == transition._from;
+            if false == is_any_state && transition._from != *current_state_name {
+                continue;
+            }
+            if let Some(active_transition) = self._active_transition.as_ref() {
+                if transition_index == active_transition._transition_index {
+                    continue;
+                }
+            }
+            let target_state = state_machine_data.get_state_index(&transition._to);
+            if INVALID_STATE_INDEX == target_state || (is_any_state && target_state == self._current_state && false == transition._can_transition_to_self) {
+                continue;
+            }
+            if let Some(exit_time) = transition._exit_time {
+                if normalized_time < exit_time {
+                    continue;
+                }
+            } else if transition._conditions.is_empty() {
+                continue;
+            }
+            if evaluate_conditions(&transition._conditions, &self._parameters) {
+                return Some(transition_index);
+            }
+        }
+        None
+    }
+
+    // the motion to play when the state or the dominant clip has been changed
+    fn get_state_motion(&mut self, state_machine_data: &AnimStateMachineData, blend_time: f32, keep_phase: bool) -> Option<AnimStateMachineMotion> {
+        let state = state_machine_data._create_info._states.get(self._current_state)?;
+        let clip_name = match &state._motion {
+            AnimStateMotion::Clip(clip) => {
+                self._blend_weights.clear();
+                clip.clone()
+            },
+            AnimStateMotion::BlendTree1D { _parameter, _clips, .. } => {
+                // one clip is played at a time with the cross-fade, the blend tree picks the clip of the highest weight
+                let value = self._parameters.get(_parameter).map(|parameter| parameter._value).unwrap_or(0.0);
+                self._blend_weights = get_blend_tree_weights(_clips, value);
+                let dominant = self._blend_weights.iter().enumerate().fold(0, |dominant, (index, weight)| if self._blend_weights[dominant] < *weight { index } else { dominant });
+                match _clips.get(dominant) {
+                    Some(blend_tree_clip) => blend_tree_clip._clip.clone(),
+                    None => return None,
+                }
+            },
+        };
+        if false == self._need_restart && clip_name == self._current_clip {
+            return None;
+        }
+        let animation_mesh = match state_machine_data._clip_meshes.get(&clip_name) {
+            Some(animation_mesh) => animation_mesh.clone(),
+            None => {
+                log::warn!("{}: not found clip {}", state_machine_data._name, clip_name);
+                return None;
+            }
+        };
+        // the blend tree keeps the phase between the clips
+        let start_time = if keep_phase {
+            let normalized_time = self.get_normalized_time(state_machine_data);
+            let clip_length = state_machine_data.get_clip_length(&clip_name);
+            (normalized_time * clip_length) % clip_length.max(std::f32::EPSILON)
+        } else {
+            0.0
+        };
+        self._state_elapsed_time = start_time;
+        self._current_clip = clip_name;
+        self._need_restart = false;
+        Some(AnimStateMachineMotion {
+            _animation_mesh: animation_mesh,
+            _animation_args: AnimationPlayArgs {
+                _speed: state._speed,
+                _loop: state._loop,
+                _start_time: start_time,
+                _blend_time: blend_time,
+                _force: true,
+                _reset: true,
+                ..Default::default()
+            },
+        })
+    }
+
+    pub fn update_anim_state_machine(&mut self, delta_time: f32) -> Option<AnimStateMachineMotion> {
+        let state_machine_data_ref = self._state_machine_data.clone();
+        if self._revision != state_machine_data_ref.borrow()._revision {
+            let prev_state_name = self._current_state_name.clone();
+            self.resolve_reloaded_states(&prev_state_name);
+        }
+        let state_machine_data = state_machine_data_ref.borrow();
+        if INVALID_STATE_INDEX == self._current_state {
+            return None;
+        }
+
+        if let Some(state) = state_machine_data._create_info._states.get(self._current_state) {
+            self._state_elapsed_time += delta_time * state._speed;
+        }
+        if let Some(active_transition) = self._active_transition.as_mut() {
+            active_transition._elapsed_time += delta_time;
+            if active_transition._duration <= active_transition._elapsed_time {
+                self._active_transition = None;
+            }
+        }
+
+        if let Some(transition_index) = self.find_transition(&state_machine_data) {
+            let transition = &state_machine_data._create_info._transitions[transition_index];
+            consume_triggers(&transition._conditions, &mut self._parameters);
+            self._current_state = state_machine_data.get_state_index(&transition._to);
+            self._current_state_name = transition._to.clone();
+            self._state_elapsed_time = 0.0;
+            self._active_transition = Some(AnimActiveTransition {
+                _transition_index: transition_index,
+                _elapsed_time: 0.0,
+                _duration: transition._duration,
+            });
+            self._need_restart = true;
+            return self.get_state_motion(&state_machine_data, transition._duration, false);
+        }
+
+        let blend_time = match &state_machine_data._create_info._states[self._current_state]._motion {
+            AnimStateMotion::BlendTree1D { _blend_time, .. } => *_blend_time,
+            _ => 0.0,
+        };
+        let need_restart = self._need_restart;
+        self.get_state_motion(&state_machine_data, blend_time, false == need_restart)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::mesh::MeshDataCreateInfo;
+    use crate::utilities::system::newRcRefCell;
+
+    const TEST_STATE_MACHINE: &str = r#"{
+        "_parameters": [
+            { "_name": "speed", "_parameter_type": "Float" },
+            { "_name": "grounded", "_parameter_type": "Bool", "_default_value": 1.0 },
+            { "_name": "jump", "_parameter_type": "Trigger" },
+            { "_name": "hit", "_parameter_type": "Trigger" }
+        ],
+        "_states": [
+            { "_name": "locomotion", "_motion": { "BlendTree1D": { "_parameter": "speed", "_clips": [{ "_clip": "idle", "_threshold": 0.0 }, { "_clip": "walk", "_threshold": 2.0 }, { "_clip": "run", "_threshold": 6.0 }], "_blend_time": 0.2 } } },
+            { "_name": "jump", "_motion": { "Clip": "jump" }, "_loop": false },
+            { "_name": "fall", "_motion": { "Clip": "fall" } },
+            { "_name": "hit", "_motion": { "Clip": "hit" }, "_loop": false }
+        ],
+        "_transitions": [
+            { "_from": "*", "_to": "hit", "_conditions": [{ "_parameter": "hit", "_op": "Triggered" }], "_duration": 0.1 },
+            { "_from": "locomotion", "_to": "jump", "_conditions": [{ "_parameter": "jump", "_op": "Triggered" }, { "_parameter": "grounded", "_op": "IsTrue" }], "_duration": 0.1 },
+            { "_from": "jump", "_to": "fall", "_conditions": [{ "_parameter": "grounded", "_op": "IsFalse" }], "_duration": 0.5, "_interruptible": true },
+            { "_from": "fall", "_to": "locomotion", "_conditions": [{ "_parameter": "grounded", "_op": "IsTrue" }], "_duration": 0.2 },
+            { "_from": "hit", "_to": "locomotion", "_exit_time": 0.0, "_duration": 0.3 }
+        ],
+        "_default_state": "locomotion"
+    }"#;
+
+    fn create_test_state_machine_data(contents: &str) -> RcRefCell<AnimStateMachineData> {
+        let create_info = AnimStateMachineData::parse_anim_state_machine_create_info(contents.as_bytes()).unwrap();
+        newRcRefCell(AnimStateMachineData::create_anim_state_machine_data("test_state_machine", create_info.clone(), create_test_clip_meshes(&create_info)))
+    }
+
+    fn create_test_clip_meshes(create_info: &AnimStateMachineCreateInfo) -> HashMap<String, RcRefCell<MeshData>> {
+        let mut clip_meshes: HashMap<String, RcRefCell<MeshData>> = HashMap::new();
+        for state in create_info._states.iter() {
+            for clip_name in state._motion.get_clip_names() {
+                clip_meshes.insert(clip_name.clone(), newRcRefCell(MeshData::create_mesh_data(clip_name, MeshDataCreateInfo::default(), Vec::new())));
+            }
+        }
+        clip_meshes
+    }
+
+    fn create_parameters(parameters: &[(&str, AnimParameterType, f32)]) -> HashMap<String, AnimParameter> {
+        parameters.iter().map(|(name, parameter_type, value)| (String::from(*name), AnimParameter { _parameter_type: *parameter_type, _value: *value })).collect()
+    }
+
+    fn create_condition(parameter: &str, op: AnimConditionOp, value: f32) -> AnimCondition {
+        AnimCondition { _parameter: String::from(parameter), _op: op, _value: value }
+    }
+
+    #[test]
+    fn test_evaluate_condition() {
+        let parameters = create_parameters(&[
+            ("speed", AnimParameterType::Float, 3.2),
+            ("grounded", AnimParameterType::Bool, 1.0),
+            ("jump", AnimParameterType::Trigger, 0.0),
+        ]);
+        assert!(evaluate_condition(&create_condition("speed", AnimConditionOp::Greater, 3.0), &parameters));
+        assert!(false == evaluate_condition(&create_condition("speed", AnimConditionOp::Greater, 3.2), &parameters));
+        assert!(evaluate_condition(&create_condition("speed", AnimConditionOp::Less, 4.0), &parameters));
+        assert!(false == evaluate_condition(&create_condition("speed", AnimConditionOp::Less, 3.2), &parameters));
+        assert!(evaluate_condition(&create_condition("speed", AnimConditionOp::Equal, 3.2), &parameters));
+        assert!(evaluate_condition(&create_condition("speed", AnimConditionOp::NotEqual, 0.0), &parameters));
+        assert!(evaluate_condition(&create_condition("grounded", AnimConditionOp::IsTrue, 0.0), &parameters));
+        assert!(false == evaluate_condition(&create_condition("grounded", AnimConditionOp::IsFalse, 0.0), &parameters));
+        assert!(false == evaluate_condition(&create_condition("jump", AnimConditionOp::Triggered, 0.0), &parameters));
+        // the unknown parameters never pass
+        assert!(false == evaluate_condition(&create_condition("unknown", AnimConditionOp::IsFalse, 0.0), &parameters));
+
+        assert!(evaluate_conditions(&[], &parameters));
+        assert!(evaluate_conditions(&[create_condition("speed", AnimConditionOp::Greater, 1.0), create_condition("grounded", AnimConditionOp::IsTrue, 0.0)], &parameters));
+        assert!(false == evaluate_conditions(&[create_condition("speed", AnimConditionOp::Greater, 1.0), create_condition("jump", AnimConditionOp::Triggered, 0.0)], &parameters));
+    }
+
+    #[test]
+    fn test_consume_triggers() {
+        let mut parameters = create_parameters(&[
+            ("grounded", AnimParameterType::Bool, 1.0),
+            ("jump", AnimParameterType::Trigger, 1.0),
+            ("hit", AnimParameterType::Trigger, 1.0),
+        ]);
+        consume_triggers(&[create_condition("jump", AnimConditionOp::Triggered, 0.0), create_condition("grounded", AnimConditionOp::IsTrue, 0.0)], &mut parameters);
+        assert_eq!(0.0, parameters["jump"]._value);
+        // the bools and the triggers of the other transitions are kept
+        assert_eq!(1.0, parameters["grounded"]._value);
+        assert_eq!(1.0, parameters["hit"]._value);
+    }
+
+    #[test]
+    fn test_blend_tree_weights() {
+        let clips = vec![
+            BlendTreeClip { _clip: String::from("idle"), _threshold: 0.0 },
+            BlendTreeClip { _clip: String::from("walk"), _threshold: 2.0 },
+            BlendTreeClip { _clip: String::from("run"), _threshold: 6.0 },
+        ];
+        assert_eq!(vec![1.0, 0.0, 0.0], get_blend_tree_weights(&clips, -1.0));
+        assert_eq!(vec![0.5, 0.5, 0.0], get_blend_tree_weights(&clips, 1.0));
+        assert_eq!(vec![0.0, 1.0, 0.0], get_blend_tree_weights(&clips, 2.0));
+        assert_eq!(vec![0.0, 0.75, 0.25], get_blend_tree_weights(&clips, 3.0));
+        assert_eq!(vec![0.0, 0.0, 1.0], get_blend_tree_weights(&clips, 10.0));
+        assert!(get_blend_tree_weights(&[], 1.0).is_empty());
+    }
+
+    #[test]
+    fn test_transitions_and_trigger_consumption() {
+        let state_machine_data = create_test_state_machine_data(TEST_STATE_MACHINE);
+        let mut anim_state_machine = AnimStateMachine::create_anim_state_machine(&state_machine_data);
+        assert_eq!("locomotion", anim_state_machine.get_current_state_name());
+        assert_eq!(1.0, anim_state_machine.get_param("grounded"));
+
+        // the first update starts the default state, the blend tree picks the dominant clip
+        anim_state_machine.set_param("speed", 5.0);
+        let motion = anim_state_machine.update_anim_state_machine(0.016).unwrap();
+        assert_eq!("run", motion._animation_mesh.borrow()._name);
+        assert_eq!("run", anim_state_machine._current_clip);
+        assert!(anim_state_machine.update_anim_state_machine(0.016).is_none());
+
+        // a trigger with an unmet condition is kept until the transition is taken
+        anim_state_machine.set_bool("grounded", false);
+        anim_state_machine.set_trigger("jump");
+        anim_state_machine.update_anim_state_machine(0.016);
+        assert_eq!("locomotion", anim_state_machine.get_current_state_name());
+        assert_eq!(1.0, anim_state_machine.get_param("jump"));
+
+        anim_state_machine.set_bool("grounded", true);
+        let motion = anim_state_machine.update_anim_state_machine(0.016).unwrap();
+        assert_eq!("jump", anim_state_machine.get_current_state_name());
+        assert_eq!("jump", motion._animation_mesh.borrow()._name);
+        assert_eq!(0.1, motion._animation_args._blend_time);
+        assert!(false == motion._animation_args._loop);
+        assert_eq!(0.0, anim_state_machine.get_param("jump"));
+        assert!(anim_state_machine.is_in_transition());
+
+        // the transition ends after its duration
+        anim_state_machine.update_anim_state_machine(0.2);
+        assert!(false == anim_state_machine.is_in_transition());
+        assert_eq!("jump", anim_state_machine.get_current_state_name());
+    }
+
+    #[test]
+    fn test_interruption_rules() {
+        let state_machine_data = create_test_state_machine_data(TEST_STATE_MACHINE);
+        let mut anim_state_machine = AnimStateMachine::create_anim_state_machine(&state_machine_data);
+        anim_state_machine.update_anim_state_machine(0.016);
+
+        // locomotion -> jump is not interruptible, the hit trigger waits until the cross-fade ends
+        anim_state_machine.set_trigger("jump");
+        anim_state_machine.update_anim_state_machine(0.016);
+        assert_eq!("jump", anim_state_machine.get_current_state_name());
+        anim_state_machine.set_trigger("hit");
+        anim_state_machine.update_anim_state_machine(0.016);
+        assert_eq!("jump", anim_state_machine.get_current_state_name());
+        assert_eq!(1.0, anim_state_machine.get_param("hit"));
+        anim_state_machine.update_anim_state_machine(0.1);
+        assert_eq!("hit", anim_state_machine.get_current_state_name());
+        assert_eq!(0.0, anim_state_machine.get_param("hit"));
+
+        // the any state transition does not go to the current state without _can_transition_to_self
+        anim_state_machine.set_trigger("hit");
+        anim_state_machine.update_anim_state_machine(0.2);
+        assert_eq!("locomotion", anim_state_machine.get_current_state_name());
+
+        // jump -> fall is interruptible, the any state transition cuts it
+        anim_state_machine.reset_trigger("hit");
+        anim_state_machine.update_anim_state_machine(0.5);
+        anim_state_machine.set_trigger("jump");
+        anim_state_machine.update_anim_state_machine(0.016);
+        anim_state_machine.update_anim_state_machine(0.2);
+        anim_state_machine.set_bool("grounded", false);
+        anim_state_machine.update_anim_state_machine(0.016);
+        assert_eq!("fall", anim_state_machine.get_current_state_name());
+        assert!(anim_state_machine.is_in_transition());
+        anim_state_machine.set_trigger("hit");
+        let motion = anim_state_machine.update_anim_state_machine(0.016).unwrap();
+        assert_eq!("hit", anim_state_machine.get_current_state_name());
+        assert_eq!(0.1, motion._animation_args._blend_time);
+    }
+
+    #[test]
+    fn test_hot_reload_keeps_parameters() {
+        let state_machine_data = create_test_state_machine_data(TEST_STATE_MACHINE);
+        let mut anim_state_machine = AnimStateMachine::create_anim_state_machine(&state_machine_data);
+        anim_state_machine.update_anim_state_machine(0.016);
+        anim_state_machine.set_param("speed", 3.2);
+        anim_state_machine.set_bool("grounded", false);
+
+        // the same states by name, the parameters of the same type keep the values
+        let contents = TEST_STATE_MACHINE.replace(r#"{ "_name": "grounded", "_parameter_type": "Bool", "_default_value": 1.0 },"#, r#"{ "_name": "crouch", "_parameter_type": "Bool" },"#);
+        let create_info = AnimStateMachineData::parse_anim_state_machine_create_info(contents.as_bytes()).unwrap();
+        let clip_meshes = create_test_clip_meshes(&create_info);
+        state_machine_data.borrow_mut().reload_anim_state_machine_data(create_info, clip_meshes);
+        assert_eq!(1, state_machine_data.borrow()._revision);
+        anim_state_machine.update_anim_state_machine(0.016);
+        assert_eq!(1, anim_state_machine._revision);
+        assert_eq!("locomotion", anim_state_machine.get_current_state_name());
+        assert_eq!(3.2, anim_state_machine.get_param("speed"));
+        assert_eq!(0.0, anim_state_machine.get_param("crouch"));
+        assert!(false == anim_state_machine._parameters.contains_key("grounded"));
+
+        // the removed state falls back to the default state
+        let contents = TEST_STATE_MACHINE.replace(r#""_default_state": "locomotion""#, r#""_default_state": "fall""#).replace(r#""_name": "locomotion""#, r#""_name": "move""#);
+        let create_info = AnimStateMachineData::parse_anim_state_machine_create_info(contents.as_bytes()).unwrap();
+        let clip_meshes = create_test_clip_meshes(&create_info);
+        state_machine_data.borrow_mut().reload_anim_state_machine_data(create_info, clip_meshes);
+        let motion = anim_state_machine.update_anim_state_machine(0.016).unwrap();
+        assert_eq!("fall", anim_state_machine.get_current_state_name());
+        assert_eq!("fall", motion._animation_mesh.borrow()._name);
+    }
+}
@@ -0,0 +1,1089 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use ash::{ vk, Device };
+use ash::version::DeviceV1_0;
+
+use crate::application::environment_settings::AtmosphereSettings;
+use crate::constants;
+use crate::renderer::light_probe;
+use crate::renderer::renderer::RendererData;
+use crate::renderer::utility;
+use crate::resource::resource::Resources;
+use crate::vulkan_context::descriptor::{ self, DescriptorDataCreateInfo, DescriptorResourceInfo, DescriptorResourceType };
+use crate::vulkan_context::framebuffer::{ self, FramebufferData };
+use crate::vulkan_context::render_pass::{ DepthStencilStateCreateInfo, ImageAttachmentDescription, PipelineDataCreateInfo, RenderPassDataCreateInfo };
+use crate::vulkan_context::shader::SHADER_DIRECTORY;
+use crate::vulkan_context::texture::{ TextureCreateInfo, TextureData };
+use crate::vulkan_context::vulkan_context::{ self, BlendMode, SwapchainArray };
+
+pub const ATMOSPHERE_RENDER_PASS_NAME: &str = "precompute_atmosphere";
+pub const ATMOSPHERE_ACCUMULATE_RENDER_PASS_NAME: &str = "precompute_atmosphere_accumulate"; // the additive passes keep the previous orders
+pub const ATMOSPHERE_TRANSMITTANCE_PIPELINE_NAME: &str = "compute_transmittance";
+pub const ATMOSPHERE_DIRECT_IRRADIANCE_PIPELINE_NAME: &str = "compute_direct_irradiance";
+pub const ATMOSPHERE_SINGLE_SCATTERING_PIPELINE_NAME: &str = "compute_single_scattering";
+pub const ATMOSPHERE_SCATTERING_DENSITY_PIPELINE_NAME: &str = "compute_scattering_density";
+pub const ATMOSPHERE_INDIRECT_IRRADIANCE_PIPELINE_NAME: &str = "compute_indirect_irradiance";
+pub const ATMOSPHERE_MULTIPLE_SCATTERING_PIPELINE_NAME: &str = "compute_multiple_scattering";
+pub const ATMOSPHERE_ACCUMULATE_INDIRECT_IRRADIANCE_PIPELINE_NAME: &str = "accumulate_indirect_irradiance";
+pub const ATMOSPHERE_ACCUMULATE_MULTIPLE_SCATTERING_PIPELINE_NAME: &str = "accumulate_multiple_scattering";
+// the precomputed textures, the light probe and the materials of the project bind these names as RenderTarget descriptors
+pub const ATMOSPHERE_TRANSMITTANCE_TEXTURE_NAME: &str = "AtmosphereTransmittance";
+pub const ATMOSPHERE_IRRADIANCE_TEXTURE_NAME: &str = "AtmosphereIrradiance";
+pub const ATMOSPHERE_SCATTERING_TEXTURE_NAME: &str = "AtmosphereScattering"; // rgb: rayleigh and the multiple scattering, a: the red of the single mie when combined
+pub const ATMOSPHERE_SINGLE_MIE_SCATTERING_TEXTURE_NAME: &str = "AtmosphereSingleMieScattering"; // only without AtmosphereSettings::_use_combined_textures
+// the intermediate textures of the scattering orders
+pub const ATMOSPHERE_DELTA_IRRADIANCE_TEXTURE_NAME: &str = "AtmosphereDeltaIrradiance";
+pub const ATMOSPHERE_DELTA_RAYLEIGH_SCATTERING_TEXTURE_NAME: &str = "AtmosphereDeltaRayleighScattering"; // the delta multiple scattering after the single scattering
+pub const ATMOSPHERE_DELTA_MIE_SCATTERING_TEXTURE_NAME: &str = "AtmosphereDeltaMieScattering";
+pub const ATMOSPHERE_DELTA_SCATTERING_DENSITY_TEXTURE_NAME: &str = "AtmosphereDeltaScatteringDensity";
+pub const ATMOSPHERE_FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT; // blendable on every device, the accumulate passes are additive
+pub const TRANSMITTANCE_TEXTURE_WIDTH: u32 = 256;
+pub const TRANSMITTANCE_TEXTURE_HEIGHT: u32 = 64;
+pub const IRRADIANCE_TEXTURE_WIDTH: u32 = 64;
+pub const IRRADIANCE_TEXTURE_HEIGHT: u32 = 16;
+// the 4d scattering of (r, mu, mu_s, nu) in a 2d array: x is nu and mu_s, y is mu, the layer is r
+pub const SCATTERING_TEXTURE_R_SIZE: u32 = 32;
+pub const SCATTERING_TEXTURE_MU_SIZE: u32 = 128;
+pub const SCATTERING_TEXTURE_MU_S_SIZE: u32 = 32;
+pub const SCATTERING_TEXTURE_NU_SIZE: u32 = 8;
+pub const ATMOSPHERE_BOTTOM_RADIUS: f32 = 6360.0; // km
+pub const ATMOSPHERE_TOP_RADIUS: f32 = 6420.0; // km
+pub const ATMOSPHERE_MU_S_MIN: f32 = -0.2; // the cosine of the lowest sun zenith angle of the precompute
+pub const ATMOSPHERE_FRAGMENT_SHADER_FILE: &str = "common/precompute_atmosphere.frag";
+pub const ATMOSPHERE_SHADER_FILE: &str = "common/atmosphere.glsl";
+pub const ATMOSPHERE_FRAGMENT_SHADER_SOURCE: &str = r#"#version 450
+#extension GL_GOOGLE_include_directive : enable
+
+// ATMOSPHERE_TRANSMITTANCE, ATMOSPHERE_DIRECT_IRRADIANCE, ATMOSPHERE_SINGLE_SCATTERING, ATMOSPHERE_SCATTERING_DENSITY,
+// ATMOSPHERE_INDIRECT_IRRADIANCE or ATMOSPHERE_MULTIPLE_SCATTERING by the pipeline, and ATMOSPHERE_ACCUMULATE for the additive pipelines
+#include "atmosphere.glsl"
+
+layout(push_constant) uniform PushConstant_Atmosphere
+{
+    uint layer; // r of the scattering textures
+    uint scattering_order;
+    uint output_index;
+    uint reserved0;
+} pushConstant;
+
+#if defined(ATMOSPHERE_DIRECT_IRRADIANCE) || defined(ATMOSPHERE_SINGLE_SCATTERING) || defined(ATMOSPHERE_SCATTERING_DENSITY) || defined(ATMOSPHERE_MULTIPLE_SCATTERING)
+layout(binding = 0) uniform sampler2D texture_transmittance;
+#endif
+#if defined(ATMOSPHERE_SCATTERING_DENSITY)
+layout(binding = 1) uniform sampler2D texture_delta_irradiance;
+#endif
+#if defined(ATMOSPHERE_SCATTERING_DENSITY) || defined(ATMOSPHERE_INDIRECT_IRRADIANCE)
+layout(binding = 2) uniform sampler2DArray texture_delta_rayleigh_scattering; // the delta multiple scattering after the single scattering
+layout(binding = 3) uniform sampler2DArray texture_delta_mie_scattering;
+#endif
+#if defined(ATMOSPHERE_MULTIPLE_SCATTERING)
+layout(binding = 4) uniform sampler2DArray texture_delta_scattering_density;
+#endif
+
+layout(location = 0) out vec4 outColor;
+
+void main()
+{
+    vec2 frag_coord = gl_FragCoord.xy;
+#if defined(ATMOSPHERE_TRANSMITTANCE)
+    float r;
+    float mu;
+    get_r_mu_from_transmittance_texture_uv(frag_coord / vec2(TRANSMITTANCE_TEXTURE_WIDTH, TRANSMITTANCE_TEXTURE_HEIGHT), r, mu);
+    outColor = vec4(compute_transmittance_to_top_atmosphere_boundary(r, mu), 1.0);
+#elif defined(ATMOSPHERE_DIRECT_IRRADIANCE) || defined(ATMOSPHERE_INDIRECT_IRRADIANCE)
+    float r;
+    float mu_s;
+    get_r_mu_s_from_irradiance_texture_uv(frag_coord / vec2(IRRADIANCE_TEXTURE_WIDTH, IRRADIANCE_TEXTURE_HEIGHT), r, mu_s);
+#if defined(ATMOSPHERE_DIRECT_IRRADIANCE)
+    // 0: the delta irradiance, 1: the irradiance starts from zero, the direct irradiance is added by the renderer
+    outColor = (0u == pushConstant.output_index) ? vec4(compute_direct_irradiance(texture_transmittance, r, mu_s), 1.0) : vec4(0.0);
+#else
+    outColor = vec4(compute_indirect_irradiance(texture_delta_rayleigh_scattering, texture_delta_mie_scattering, texture_delta_rayleigh_scattering, r, mu_s, int(pushConstant.scattering_order)), 1.0);
+#endif
+#else
+    float r;
+    float mu;
+    float mu_s;
+    float nu;
+    bool ray_r_mu_intersects_ground;
+    get_r_mu_mu_s_nu_from_scattering_texture_frag_coord(vec3(frag_coord, float(pushConstant.layer) + 0.5), r, mu, mu_s, nu, ray_r_mu_intersects_ground);
+#if defined(ATMOSPHERE_SINGLE_SCATTERING)
+    vec3 delta_rayleigh;
+    vec3 delta_mie;
+    compute_single_scattering(texture_transmittance, r, mu, mu_s, nu, ray_r_mu_intersects_ground, delta_rayleigh, delta_mie);
+    // 0: the delta rayleigh, 1: the delta mie, 2: the scattering with the red of mie in alpha, 3: the single mie scattering
+    if(0u == pushConstant.output_index)
+    {
+        outColor = vec4(delta_rayleigh, 1.0);
+    }
+    else if(2u == pushConstant.output_index)
+    {
+        outColor = vec4(delta_rayleigh, delta_mie.r);
+    }
+    else
+    {
+        outColor = vec4(delta_mie, 1.0);
+    }
+#elif defined(ATMOSPHERE_SCATTERING_DENSITY)
+    vec3 scattering_density = compute_scattering_density(
+        texture_transmittance,
+        texture_delta_rayleigh_scattering,
+        texture_delta_mie_scattering,
+        texture_delta_rayleigh_scattering,
+        texture_delta_irradiance,
+        r, mu, mu_s, nu,
+        int(pushConstant.scattering_order)
+    );
+    outColor = vec4(scattering_density, 1.0);
+#else
+    vec3 delta_multiple_scattering = compute_multiple_scattering(texture_transmittance, texture_delta_scattering_density, r, mu, mu_s, nu, ray_r_mu_intersects_ground);
+#if defined(ATMOSPHERE_ACCUMULATE)
+    // the alpha of the single mie is kept, the color write mask of the additive blend is rgb
+    outColor = vec4(delta_multiple_scattering / rayleigh_phase_function(nu), 0.0);
+#else
+    outColor = vec4(delta_multiple_scattering, 1.0);
+#endif
+#endif
+#endif
+}
+"#;
+// the functions of the precompute and the rendering, the constants are prepended by get_atmosphere_shader_header.
+// a port of the precomputed atmospheric scattering of Eric Bruneton with the exponential rayleigh and mie and the ozone layer.
+pub const ATMOSPHERE_FUNCTIONS_SOURCE: &str = r#"
+#define ATMOSPHERE_PI 3.14159265359
+#define RAYLEIGH_SCALE_HEIGHT 8.0
+#define MIE_SCALE_HEIGHT 1.2
+#define MIE_PHASE_FUNCTION_G 0.8
+#define OZONE_CENTER_ALTITUDE 25.0
+#define OZONE_HALF_WIDTH 15.0
+#define SUN_ANGULAR_RADIUS 0.004675
+
+// per km
+const vec3 RAYLEIGH_SCATTERING = vec3(5.802e-3, 13.558e-3, 33.1e-3);
+const vec3 MIE_SCATTERING = vec3(3.996e-3);
+const vec3 MIE_EXTINCTION = vec3(4.44e-3);
+const vec3 ABSORPTION_EXTINCTION = vec3(0.650e-3, 1.881e-3, 0.085e-3);
+const vec3 GROUND_ALBEDO = vec3(0.3);
+const vec3 SOLAR_IRRADIANCE = vec3(1.0); // the sun intensity is applied by the renderer
+
+float clamp_cosine(float mu)
+{
+    return clamp(mu, -1.0, 1.0);
+}
+
+float clamp_radius(float r)
+{
+    return clamp(r, BOTTOM_RADIUS, TOP_RADIUS);
+}
+
+float safe_sqrt(float a)
+{
+    return sqrt(max(a, 0.0));
+}
+
+float distance_to_top_atmosphere_boundary(float r, float mu)
+{
+    float discriminant = r * r * (mu * mu - 1.0) + TOP_RADIUS * TOP_RADIUS;
+    return max(-r * mu + safe_sqrt(discriminant), 0.0);
+}
+
+float distance_to_bottom_atmosphere_boundary(float r, float mu)
+{
+    float discriminant = r * r * (mu * mu - 1.0) + BOTTOM_RADIUS * BOTTOM_RADIUS;
+    return max(-r * mu - safe_sqrt(discriminant), 0.0);
+}
+
+bool ray_intersects_ground(float r, float mu)
+{
+    return mu < 0.0 && 0.0 <= r * r * (mu * mu - 1.0) + BOTTOM_RADIUS * BOTTOM_RADIUS;
+}
+
+float distance_to_nearest_atmosphere_boundary(float r, float mu, bool ray_r_mu_intersects_ground)
+{
+    return ray_r_mu_intersects_ground ? distance_to_bottom_atmosphere_boundary(r, mu) : distance_to_top_atmosphere_boundary(r, mu);
+}
+
+float get_texture_coord_from_unit_range(float x, int texture_size)
+{
+    return 0.5 / float(texture_size) + x * (1.0 - 1.0 / float(texture_size));
+}
+
+float get_unit_range_from_texture_coord(float u, int texture_size)
+{
+    return (u - 0.5 / float(texture_size)) / (1.0 - 1.0 / float(texture_size));
+}
+
+float rayleigh_phase_function(float nu)
+{
+    return 3.0 / (16.0 * ATMOSPHERE_PI) * (1.0 + nu * nu);
+}
+
+float mie_phase_function(float g, float nu)
+{
+    float k = 3.0 / (8.0 * ATMOSPHERE_PI) * (1.0 - g * g) / (2.0 + g * g);
+    return k * (1.0 + nu * nu) / pow(max(1.0 + g * g - 2.0 * g * nu, 0.0001), 1.5);
+}
+
+vec3 get_extinction(float altitude)
+{
+    float rayleigh_density = exp(-altitude / RAYLEIGH_SCALE_HEIGHT);
+    float mie_density = exp(-altitude / MIE_SCALE_HEIGHT);
+    float ozone_density = max(1.0 - abs(altitude - OZONE_CENTER_ALTITUDE) / OZONE_HALF_WIDTH, 0.0);
+    return RAYLEIGH_SCATTERING * rayleigh_density + MIE_EXTINCTION * mie_density + ABSORPTION_EXTINCTION * ozone_density;
+}
+
+// transmittance
+vec3 compute_transmittance_to_top_atmosphere_boundary(float r, float mu)
+{
+    const int SAMPLE_COUNT = 500;
+    float dx = distance_to_top_atmosphere_boundary(r, mu) / float(SAMPLE_COUNT);
+    vec3 optical_length = vec3(0.0);
+    for(int i = 0; i <= SAMPLE_COUNT; ++i)
+    {
+        float d_i = float(i) * dx;
+        float r_i = sqrt(d_i * d_i + 2.0 * r * mu * d_i + r * r);
+        float weight_i = (0 == i || SAMPLE_COUNT == i) ? 0.5 : 1.0;
+        optical_length += get_extinction(r_i - BOTTOM_RADIUS) * weight_i * dx;
+    }
+    return exp(-optical_length);
+}
+
+vec2 get_transmittance_texture_uv_from_r_mu(float r, float mu)
+{
+    float H = sqrt(TOP_RADIUS * TOP_RADIUS - BOTTOM_RADIUS * BOTTOM_RADIUS);
+    float rho = safe_sqrt(r * r - BOTTOM_RADIUS * BOTTOM_RADIUS);
+    float d = distance_to_top_atmosphere_boundary(r, mu);
+    float d_min = TOP_RADIUS - r;
+    float d_max = rho + H;
+    float x_mu = (d - d_min) / (d_max - d_min);
+    float x_r = rho / H;
+    return vec2(get_texture_coord_from_unit_range(x_mu, TRANSMITTANCE_TEXTURE_WIDTH), get_texture_coord_from_unit_range(x_r, TRANSMITTANCE_TEXTURE_HEIGHT));
+}
+
+void get_r_mu_from_transmittance_texture_uv(vec2 uv, out float r, out float mu)
+{
+    float x_mu = get_unit_range_from_texture_coord(uv.x, TRANSMITTANCE_TEXTURE_WIDTH);
+    float x_r = get_unit_range_from_texture_coord(uv.y, TRANSMITTANCE_TEXTURE_HEIGHT);
+    float H = sqrt(TOP_RADIUS * TOP_RADIUS - BOTTOM_RADIUS * BOTTOM_RADIUS);
+    float rho = H * x_r;
+    r = sqrt(rho * rho + BOTTOM_RADIUS * BOTTOM_RADIUS);
+    float d_min = TOP_RADIUS - r;
+    float d_max = rho + H;
+    float d = d_min + x_mu * (d_max - d_min);
+    mu = (0.0 == d) ? 1.0 : clamp_cosine((H * H - rho * rho - d * d) / (2.0 * r * d));
+}
+
+vec3 get_transmittance_to_top_atmosphere_boundary(sampler2D texture_transmittance, float r, float mu)
+{
+    return texture(texture_transmittance, get_transmittance_texture_uv_from_r_mu(r, mu)).xyz;
+}
+
+// between the point of r, mu and the point at the distance d along the ray
+vec3 get_transmittance(sampler2D texture_transmittance, float r, float mu, float d, bool ray_r_mu_intersects_ground)
+{
+    float r_d = clamp_radius(sqrt(d * d + 2.0 * r * mu * d + r * r));
+    float mu_d = clamp_cosine((r * mu + d) / r_d);
+    if(ray_r_mu_intersects_ground)
+    {
+        return min(get_transmittance_to_top_atmosphere_boundary(texture_transmittance, r_d, -mu_d) / get_transmittance_to_top_atmosphere_boundary(texture_transmittance, r, -mu), vec3(1.0));
+    }
+    return min(get_transmittance_to_top_atmosphere_boundary(texture_transmittance, r, mu) / get_transmittance_to_top_atmosphere_boundary(texture_transmittance, r_d, mu_d), vec3(1.0));
+}
+
+// the fraction of the sun disc above the horizon
+vec3 get_transmittance_to_sun(sampler2D texture_transmittance, float r, float mu_s)
+{
+    float sin_theta_h = BOTTOM_RADIUS / r;
+    float cos_theta_h = -sqrt(max(1.0 - sin_theta_h * sin_theta_h, 0.0));
+    float visible_sun = smoothstep(-sin_theta_h * SUN_ANGULAR_RADIUS, sin_theta_h * SUN_ANGULAR_RADIUS, mu_s - cos_theta_h);
+    return get_transmittance_to_top_atmosphere_boundary(texture_transmittance, r, mu_s) * visible_sun;
+}
+
+// single scattering
+void compute_single_scattering_integrand(
+    sampler2D texture_transmittance, float r, float mu, float mu_s, float nu, float d, bool ray_r_mu_intersects_ground, out vec3 rayleigh, out vec3 mie)
+{
+    float r_d = clamp_radius(sqrt(d * d + 2.0 * r * mu * d + r * r));
+    float mu_s_d = clamp_cosine((r * mu_s + d * nu) / r_d);
+    vec3 transmittance = get_transmittance(texture_transmittance, r, mu, d, ray_r_mu_intersects_ground) * get_transmittance_to_sun(texture_transmittance, r_d, mu_s_d);
+    rayleigh = transmittance * exp(-(r_d - BOTTOM_RADIUS) / RAYLEIGH_SCALE_HEIGHT);
+    mie = transmittance * exp(-(r_d - BOTTOM_RADIUS) / MIE_SCALE_HEIGHT);
+}
+
+void compute_single_scattering(
+    sampler2D texture_transmittance, float r, float mu, float mu_s, float nu, bool ray_r_mu_intersects_ground, out vec3 rayleigh, out vec3 mie)
+{
+    const int SAMPLE_COUNT = 50;
+    float dx = distance_to_nearest_atmosphere_boundary(r, mu, ray_r_mu_intersects_ground) / float(SAMPLE_COUNT);
+    vec3 rayleigh_sum = vec3(0.0);
+    vec3 mie_sum = vec3(0.0);
+    for(int i = 0; i <= SAMPLE_COUNT; ++i)
+    {
+        vec3 rayleigh_i;
+        vec3 mie_i;
+        compute_single_scattering_integrand(texture_transmittance, r, mu, mu_s, nu, float(i) * dx, ray_r_mu_intersects_ground, rayleigh_i, mie_i);
+        float weight_i = (0 == i || SAMPLE_COUNT == i) ? 0.5 : 1.0;
+        rayleigh_sum += rayleigh_i * weight_i;
+        mie_sum += mie_i * weight_i;
+    }
+    rayleigh = rayleigh_sum * dx * SOLAR_IRRADIANCE * RAYLEIGH_SCATTERING;
+    mie = mie_sum * dx * SOLAR_IRRADIANCE * MIE_SCATTERING;
+}
+
+// scattering textures
+vec4 get_scattering_texture_uvwz_from_r_mu_mu_s_nu(float r, float mu, float mu_s, float nu, bool ray_r_mu_intersects_ground)
+{
+    float H = sqrt(TOP_RADIUS * TOP_RADIUS - BOTTOM_RADIUS * BOTTOM_RADIUS);
+    float rho = safe_sqrt(r * r - BOTTOM_RADIUS * BOTTOM_RADIUS);
+    float u_r = get_texture_coord_from_unit_range(rho / H, SCATTERING_TEXTURE_R_SIZE);
+    float r_mu = r * mu;
+    float discriminant = r_mu * r_mu - r * r + BOTTOM_RADIUS * BOTTOM_RADIUS;
+    float u_mu;
+    if(ray_r_mu_intersects_ground)
+    {
+        float d = -r_mu - safe_sqrt(discriminant);
+        float d_min = r - BOTTOM_RADIUS;
+        float d_max = rho;
+        u_mu = 0.5 - 0.5 * get_texture_coord_from_unit_range((d_max == d_min) ? 0.0 : (d - d_min) / (d_max - d_min), SCATTERING_TEXTURE_MU_SIZE / 2);
+    }
+    else
+    {
+        float d = -r_mu + safe_sqrt(discriminant + H * H);
+        float d_min = TOP_RADIUS - r;
+        float d_max = rho + H;
+        u_mu = 0.5 + 0.5 * get_texture_coord_from_unit_range((d - d_min) / (d_max - d_min), SCATTERING_TEXTURE_MU_SIZE / 2);
+    }
+    float d = distance_to_top_atmosphere_boundary(BOTTOM_RADIUS, mu_s);
+    float d_min = TOP_RADIUS - BOTTOM_RADIUS;
+    float d_max = H;
+    float a = (d - d_min) / (d_max - d_min);
+    float A = (distance_to_top_atmosphere_boundary(BOTTOM_RADIUS, MU_S_MIN) - d_min) / (d_max - d_min);
+    float u_mu_s = get_texture_coord_from_unit_range(max(1.0 - a / A, 0.0) / (1.0 + a), SCATTERING_TEXTURE_MU_S_SIZE);
+    float u_nu = (nu + 1.0) * 0.5;
+    return vec4(u_nu, u_mu_s, u_mu, u_r);
+}
+
+void get_r_mu_mu_s_nu_from_scattering_texture_uvwz(vec4 uvwz, out float r, out float mu, out float mu_s, out float nu, out bool ray_r_mu_intersects_ground)
+{
+    float H = sqrt(TOP_RADIUS * TOP_RADIUS - BOTTOM_RADIUS * BOTTOM_RADIUS);
+    float rho = H * get_unit_range_from_texture_coord(uvwz.w, SCATTERING_TEXTURE_R_SIZE);
+    r = sqrt(rho * rho + BOTTOM_RADIUS * BOTTOM_RADIUS);
+    if(uvwz.z < 0.5)
+    {
+        float d_min = r - BOTTOM_RADIUS;
+        float d_max = rho;
+        float d = d_min + (d_max - d_min) * get_unit_range_from_texture_coord(1.0 - 2.0 * uvwz.z, SCATTERING_TEXTURE_MU_SIZE / 2);
+        mu = (0.0 == d) ? -1.0 : clamp_cosine(-(rho * rho + d * d) / (2.0 * r * d));
+        ray_r_mu_intersects_ground = true;
+    }
+    else
+    {
+        float d_min = TOP_RADIUS - r;
+        float d_max = rho + H;
+        float d = d_min + (d_max - d_min) * get_unit_range_from_texture_coord(2.0 * uvwz.z - 1.0, SCATTERING_TEXTURE_MU_SIZE / 2);
+        mu = (0.0 == d) ? 1.0 : clamp_cosine((H * H - rho * rho - d * d) / (2.0 * r * d));
+        ray_r_mu_intersects_ground = false;
+    }
+    float x_mu_s = get_unit_range_from_texture_coord(uvwz.y, SCATTERING_TEXTURE_MU_S_SIZE);
+    float d_min = TOP_RADIUS - BOTTOM_RADIUS;
+    float d_max = H;
+    float A = (distance_to_top_atmosphere_boundary(BOTTOM_RADIUS, MU_S_MIN) - d_min) / (d_max - d_min);
+    float a = (A - x_mu_s * A) / (1.0 + x_mu_s * A);
+    float d = d_min + min(a, A) * (d_max - d_min);
+    mu_s = (0.0 == d) ? 1.0 : clamp_cosine((H * H - d * d) / (2.0 * BOTTOM_RADIUS * d));
+    nu = clamp_cosine(uvwz.x * 2.0 - 1.0);
+}
+
+// frag_coord: x is nu and mu_s, y is mu, z is the layer of r
+void get_r_mu_mu_s_nu_from_scattering_texture_frag_coord(vec3 frag_coord, out float r, out float mu, out float mu_s, out float nu, out bool ray_r_mu_intersects_ground)
+{
+    const vec4 SCATTERING_TEXTURE_SIZE = vec4(SCATTERING_TEXTURE_NU_SIZE - 1, SCATTERING_TEXTURE_MU_S_SIZE, SCATTERING_TEXTURE_MU_SIZE, SCATTERING_TEXTURE_R_SIZE);
+    float frag_coord_nu = floor(frag_coord.x / float(SCATTERING_TEXTURE_MU_S_SIZE));
+    float frag_coord_mu_s = mod(frag_coord.x, float(SCATTERING_TEXTURE_MU_S_SIZE));
+    vec4 uvwz = vec4(frag_coord_nu, frag_coord_mu_s, frag_coord.y, frag_coord.z) / SCATTERING_TEXTURE_SIZE;
+    get_r_mu_mu_s_nu_from_scattering_texture_uvwz(uvwz, r, mu, mu_s, nu, ray_r_mu_intersects_ground);
+    // the valid range of nu for mu and mu_s
+    float cos_sin = sqrt((1.0 - mu * mu) * (1.0 - mu_s * mu_s));
+    nu = clamp(nu, mu * mu_s - cos_sin, mu * mu_s + cos_sin);
+}
+
+// the linear interpolation of nu and r, the filtering of the sampler interpolates mu_s and mu
+vec4 get_scattering(sampler2DArray texture_scattering, float r, float mu, float mu_s, float nu, bool ray_r_mu_intersects_ground)
+{
+    vec4 uvwz = get_scattering_texture_uvwz_from_r_mu_mu_s_nu(r, mu, mu_s, nu, ray_r_mu_intersects_ground);
+    float tex_coord_x = uvwz.x * float(SCATTERING_TEXTURE_NU_SIZE - 1);
+    float tex_x = floor(tex_coord_x);
+    float lerp_x = tex_coord_x - tex_x;
+    float layer = uvwz.w * float(SCATTERING_TEXTURE_R_SIZE) - 0.5;
+    float layer0 = clamp(floor(layer), 0.0, float(SCATTERING_TEXTURE_R_SIZE - 1));
+    float layer1 = min(layer0 + 1.0, float(SCATTERING_TEXTURE_R_SIZE - 1));
+    float lerp_layer = clamp(layer - layer0, 0.0, 1.0);
+    vec2 uv0 = vec2((tex_x + uvwz.y) / float(SCATTERING_TEXTURE_NU_SIZE), uvwz.z);
+    vec2 uv1 = vec2((tex_x + 1.0 + uvwz.y) / float(SCATTERING_TEXTURE_NU_SIZE), uvwz.z);
+    vec4 scattering0 = mix(texture(texture_scattering, vec3(uv0, layer0)), texture(texture_scattering, vec3(uv0, layer1)), lerp_layer);
+    vec4 scattering1 = mix(texture(texture_scattering, vec3(uv1, layer0)), texture(texture_scattering, vec3(uv1, layer1)), lerp_layer);
+    return mix(scattering0, scattering1, lerp_x);
+}
+
+// the combined textures keep the red of the single mie in alpha
+vec3 get_extrapolated_single_mie_scattering(vec4 scattering)
+{
+    if(scattering.x <= 0.0)
+    {
+        return vec3(0.0);
+    }
+    return scattering.xyz * scattering.w / scattering.x * (RAYLEIGH_SCATTERING.x / MIE_SCATTERING.x) * (MIE_SCATTERING / RAYLEIGH_SCATTERING);
+}
+
+// the radiance scattered scattering_order times, the single scattering is without the phase functions
+vec3 get_scattering_of_order(
+    sampler2DArray texture_single_rayleigh_scattering,
+    sampler2DArray texture_single_mie_scattering,
+    sampler2DArray texture_multiple_scattering,
+    float r, float mu, float mu_s, float nu,
+    bool ray_r_mu_intersects_ground,
+    int scattering_order)
+{
+    if(1 == scattering_order)
+    {
+        vec3 rayleigh = get_scattering(texture_single_rayleigh_scattering, r, mu, mu_s, nu, ray_r_mu_intersects_ground).xyz;
+        vec3 mie = get_scattering(texture_single_mie_scattering, r, mu, mu_s, nu, ray_r_mu_intersects_ground).xyz;
+        return rayleigh * rayleigh_phase_function(nu) + mie * mie_phase_function(MIE_PHASE_FUNCTION_G, nu);
+    }
+    return get_scattering(texture_multiple_scattering, r, mu, mu_s, nu, ray_r_mu_intersects_ground).xyz;
+}
+
+// irradiance
+vec2 get_irradiance_texture_uv_from_r_mu_s(float r, float mu_s)
+{
+    float x_r = (r - BOTTOM_RADIUS) / (TOP_RADIUS - BOTTOM_RADIUS);
+    float x_mu_s = mu_s * 0.5 + 0.5;
+    return vec2(get_texture_coord_from_unit_range(x_mu_s, IRRADIANCE_TEXTURE_WIDTH), get_texture_coord_from_unit_range(x_r, IRRADIANCE_TEXTURE_HEIGHT));
+}
+
+void get_r_mu_s_from_irradiance_texture_uv(vec2 uv, out float r, out float mu_s)
+{
+    float x_mu_s = get_unit_range_from_texture_coord(uv.x, IRRADIANCE_TEXTURE_WIDTH);
+    float x_r = get_unit_range_from_texture_coord(uv.y, IRRADIANCE_TEXTURE_HEIGHT);
+    r = BOTTOM_RADIUS + x_r * (TOP_RADIUS - BOTTOM_RADIUS);
+    mu_s = clamp_cosine(2.0 * x_mu_s - 1.0);
+}
+
+vec3 get_irradiance(sampler2D texture_irradiance, float r, float mu_s)
+{
+    return texture(texture_irradiance, get_irradiance_texture_uv_from_r_mu_s(r, mu_s)).xyz;
+}
+
+vec3 compute_direct_irradiance(sampler2D texture_transmittance, float r, float mu_s)
+{
+    float alpha_s = SUN_ANGULAR_RADIUS;
+    float average_cosine_factor = (mu_s < -alpha_s) ? 0.0 : ((alpha_s < mu_s) ? mu_s : (mu_s + alpha_s) * (mu_s + alpha_s) / (4.0 * alpha_s));
+    return SOLAR_IRRADIANCE * get_transmittance_to_top_atmosphere_boundary(texture_transmittance, r, mu_s) * average_cosine_factor;
+}
+
+// the ground irradiance of the scattering of the order
+vec3 compute_indirect_irradiance(
+    sampler2DArray texture_single_rayleigh_scattering,
+    sampler2DArray texture_single_mie_scattering,
+    sampler2DArray texture_multiple_scattering,
+    float r, float mu_s,
+    int scattering_order)
+{
+    const int SAMPLE_COUNT = 32;
+    const float dphi = ATMOSPHERE_PI / float(SAMPLE_COUNT);
+    const float dtheta = ATMOSPHERE_PI / float(SAMPLE_COUNT);
+    vec3 omega_s = vec3(sqrt(1.0 - mu_s * mu_s), 0.0, mu_s);
+    vec3 irradiance = vec3(0.0);
+    for(int j = 0; j < SAMPLE_COUNT / 2; ++j)
+    {
+        float theta = (float(j) + 0.5) * dtheta;
+        for(int i = 0; i < 2 * SAMPLE_COUNT; ++i)
+        {
+            float phi = (float(i) + 0.5) * dphi;
+            vec3 omega = vec3(cos(phi) * sin(theta), sin(phi) * sin(theta), cos(theta));
+            float domega = dtheta * dphi * sin(theta);
+            float nu = dot(omega, omega_s);
+            irradiance += get_scattering_of_order(
+                texture_single_rayleigh_scattering,
+                texture_single_mie_scattering,
+                texture_multiple_scattering,
+                r, omega.z, mu_s, nu,
+                false,
+                scattering_order
+            ) * omega.z * domega;
+        }
+    }
+    return irradiance;
+}
+
+// the radiance scattered at the point of r, mu toward the view, from the scattering of the previous order and the ground
+vec3 compute_scattering_density(
+    sampler2D texture_transmittance,
+    sampler2DArray texture_single_rayleigh_scattering,
+    sampler2DArray texture_single_mie_scattering,
+    sampler2DArray texture_multiple_scattering,
+    sampler2D texture_irradiance,
+    float r, float mu, float mu_s, float nu,
+    int scattering_order)
+{
+    vec3 zenith_direction = vec3(0.0, 0.0, 1.0);
+    vec3 omega = vec3(sqrt(1.0 - mu * mu), 0.0, mu);
+    float sun_dir_x = (0.0 == omega.x) ? 0.0 : (nu - mu * mu_s) / omega.x;
+    float sun_dir_y = sqrt(max(1.0 - sun_dir_x * sun_dir_x - mu_s * mu_s, 0.0));
+    vec3 omega_s = vec3(sun_dir_x, sun_dir_y, mu_s);
+    const int SAMPLE_COUNT = 16;
+    const float dphi = ATMOSPHERE_PI / float(SAMPLE_COUNT);
+    const float dtheta = ATMOSPHERE_PI / float(SAMPLE_COUNT);
+    float altitude = r - BOTTOM_RADIUS;
+    vec3 rayleigh_scattering = RAYLEIGH_SCATTERING * exp(-altitude / RAYLEIGH_SCALE_HEIGHT);
+    vec3 mie_scattering = MIE_SCATTERING * exp(-altitude / MIE_SCALE_HEIGHT);
+    vec3 scattering_density = vec3(0.0);
+    for(int l = 0; l < SAMPLE_COUNT; ++l)
+    {
+        float theta = (float(l) + 0.5) * dtheta;
+        float cos_theta = cos(theta);
+        float sin_theta = sin(theta);
+        bool ray_r_theta_intersects_ground = ray_intersects_ground(r, cos_theta);
+        float distance_to_ground = 0.0;
+        vec3 transmittance_to_ground = vec3(0.0);
+        if(ray_r_theta_intersects_ground)
+        {
+            distance_to_ground = distance_to_bottom_atmosphere_boundary(r, cos_theta);
+            transmittance_to_ground = get_transmittance(texture_transmittance, r, cos_theta, distance_to_ground, true);
+        }
+        for(int m = 0; m < 2 * SAMPLE_COUNT; ++m)
+        {
+            float phi = (float(m) + 0.5) * dphi;
+            vec3 omega_i = vec3(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta);
+            float domega_i = dtheta * dphi * sin_theta;
+            float nu1 = dot(omega_s, omega_i);
+            vec3 incident_radiance = get_scattering_of_order(
+                texture_single_rayleigh_scattering,
+                texture_single_mie_scattering,
+                texture_multiple_scattering,
+                r, omega_i.z, mu_s, nu1,
+                ray_r_theta_intersects_ground,
+                scattering_order - 1
+            );
+            // the light reflected by the ground, lambert
+            vec3 ground_normal = normalize(zenith_direction * r + omega_i * distance_to_ground);
+            vec3 ground_irradiance = get_irradiance(texture_irradiance, BOTTOM_RADIUS, dot(ground_normal, omega_s));
+            incident_radiance += transmittance_to_ground * GROUND_ALBEDO * (1.0 / ATMOSPHERE_PI) * ground_irradiance;
+            float nu2 = dot(omega, omega_i);
+            scattering_density += incident_radiance * (rayleigh_scattering * rayleigh_phase_function(nu2) + mie_scattering * mie_phase_function(MIE_PHASE_FUNCTION_G, nu2)) * domega_i;
+        }
+    }
+    return scattering_density;
+}
+
+vec3 compute_multiple_scattering(
+    sampler2D texture_transmittance,
+    sampler2DArray texture_scattering_density,
+    float r, float mu, float mu_s, float nu,
+    bool ray_r_mu_intersects_ground)
+{
+    const int SAMPLE_COUNT = 50;
+    float dx = distance_to_nearest_atmosphere_boundary(r, mu, ray_r_mu_intersects_ground) / float(SAMPLE_COUNT);
+    vec3 rayleigh_mie_sum = vec3(0.0);
+    for(int i = 0; i <= SAMPLE_COUNT; ++i)
+    {
+        float d_i = float(i) * dx;
+        float r_i = clamp_radius(sqrt(d_i * d_i + 2.0 * r * mu * d_i + r * r));
+        float mu_i = clamp_cosine((r * mu + d_i) / r_i);
+        float mu_s_i = clamp_cosine((r * mu_s + d_i * nu) / r_i);
+        vec3 rayleigh_mie_i = get_scattering(texture_scattering_density, r_i, mu_i, mu_s_i, nu, ray_r_mu_intersects_ground).xyz
+            * get_transmittance(texture_transmittance, r, mu, d_i, ray_r_mu_intersects_ground) * dx;
+        float weight_i = (0 == i || SAMPLE_COUNT == i) ? 0.5 : 1.0;
+        rayleigh_mie_sum += rayleigh_mie_i * weight_i;
+    }
+    return rayleigh_mie_sum;
+}
+"#;
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone)]
+#[repr(C)]
+pub struct PushConstant_Atmosphere {
+    pub _layer: u32,
+    pub _scattering_order: u32,
+    pub _output_index: u32,
+    pub _reserved0: u32,
+}
+
+crate::impl_push_constant!(PushConstant_Atmosphere, 16);
+
+// a draw of every layer of the target, see get_atmosphere_precompute_passes
+#[derive(Clone, Debug, PartialEq)]
+pub struct AtmospherePrecomputePass {
+    pub _pipeline_name: &'static str,
+    pub _target_name: &'static str,
+    pub _is_accumulate: bool,
+    pub _scattering_order: u32,
+    pub _output_index: u32,
+}
+
+// The precomputed atmosphere of the environment settings: the transmittance, the scattering and the irradiance
+// of every scattering order are rendered once into the lookup textures, the light probe captures the sky from them.
+#[derive(Default)]
+pub struct Atmosphere {
+    pub _settings: AtmosphereSettings,
+    pub _textures: HashMap<&'static str, TextureData>,
+    pub _framebuffers: HashMap<&'static str, Vec<FramebufferData>>, // per texture, per layer
+    pub _accumulate_framebuffers: HashMap<&'static str, Vec<FramebufferData>>, // the render pass of the additive pipelines
+    pub _descriptor_sets: HashMap<&'static str, SwapchainArray<vk::DescriptorSet>>, // per pipeline
+    pub _is_precomputed: bool,
+}
+
+// the textures of the descriptors of the pipeline, by the binding index
+pub fn get_pipeline_input_texture_names(pipeline_name: &str) -> Vec<(u32, &'static str)> {
+    match pipeline_name {
+        ATMOSPHERE_DIRECT_IRRADIANCE_PIPELINE_NAME | ATMOSPHERE_SINGLE_SCATTERING_PIPELINE_NAME => vec![
+            (0, ATMOSPHERE_TRANSMITTANCE_TEXTURE_NAME)
+        ],
+        ATMOSPHERE_SCATTERING_DENSITY_PIPELINE_NAME => vec![
+            (0, ATMOSPHERE_TRANSMITTANCE_TEXTURE_NAME),
+            (1, ATMOSPHERE_DELTA_IRRADIANCE_TEXTURE_NAME),
+            (2, ATMOSPHERE_DELTA_RAYLEIGH_SCATTERING_TEXTURE_NAME),
+            (3, ATMOSPHERE_DELTA_MIE_SCATTERING_TEXTURE_NAME),
+        ],
+        ATMOSPHERE_INDIRECT_IRRADIANCE_PIPELINE_NAME | ATMOSPHERE_ACCUMULATE_INDIRECT_IRRADIANCE_PIPELINE_NAME => vec![
+            (2, ATMOSPHERE_DELTA_RAYLEIGH_SCATTERING_TEXTURE_NAME),
+            (3, ATMOSPHERE_DELTA_MIE_SCATTERING_TEXTURE_NAME),
+        ],
+        ATMOSPHERE_MULTIPLE_SCATTERING_PIPELINE_NAME | ATMOSPHERE_ACCUMULATE_MULTIPLE_SCATTERING_PIPELINE_NAME => vec![
+            (0, ATMOSPHERE_TRANSMITTANCE_TEXTURE_NAME),
+            (4, ATMOSPHERE_DELTA_SCATTERING_DENSITY_TEXTURE_NAME),
+        ],
+        _ => Vec::new(),
+    }
+}
+
+// the order of the precompute, the delta rayleigh texture holds the delta multiple scattering after the single scattering.
+pub fn get_atmosphere_precompute_passes(scattering_orders: u32, use_combined_textures: bool) -> Vec<AtmospherePrecomputePass> {
+    let precompute_pass = |pipeline_name: &'static str, target_name: &'static str, is_accumulate: bool, scattering_order: u32, output_index: u32| AtmospherePrecomputePass {
+        _pipeline_name: pipeline_name,
+        _target_name: target_name,
+        _is_accumulate: is_accumulate,
+        _scattering_order: scattering_order,
+        _output_index: output_index,
+    };
+    let mut precompute_passes = vec![
+        precompute_pass(ATMOSPHERE_TRANSMITTANCE_PIPELINE_NAME, ATMOSPHERE_TRANSMITTANCE_TEXTURE_NAME, false, 0, 0),
+        precompute_pass(ATMOSPHERE_DIRECT_IRRADIANCE_PIPELINE_NAME, ATMOSPHERE_DELTA_IRRADIANCE_TEXTURE_NAME, false, 0, 0),
+        precompute_pass(ATMOSPHERE_DIRECT_IRRADIANCE_PIPELINE_NAME, ATMOSPHERE_IRRADIANCE_TEXTURE_NAME, false, 0, 1),
+        precompute_pass(ATMOSPHERE_SINGLE_SCATTERING_PIPELINE_NAME, ATMOSPHERE_DELTA_RAYLEIGH_SCATTERING_TEXTURE_NAME, false, 1, 0),
+        precompute_pass(ATMOSPHERE_SINGLE_SCATTERING_PIPELINE_NAME, ATMOSPHERE_DELTA_MIE_SCATTERING_TEXTURE_NAME, false, 1, 1),
+        precompute_pass(ATMOSPHERE_SINGLE_SCATTERING_PIPELINE_NAME, ATMOSPHERE_SCATTERING_TEXTURE_NAME, false, 1, 2),
+    ];
+    if false == use_combined_textures {
+        precompute_passes.push(precompute_pass(ATMOSPHERE_SINGLE_SCATTERING_PIPELINE_NAME, ATMOSPHERE_SINGLE_MIE_SCATTERING_TEXTURE_NAME, false, 1, 3));
+    }
+    for scattering_order in 2..=scattering_orders {
+        precompute_passes.push(precompute_pass(ATMOSPHERE_SCATTERING_DENSITY_PIPELINE_NAME, ATMOSPHERE_DELTA_SCATTERING_DENSITY_TEXTURE_NAME, false, scattering_order, 0));
+        precompute_passes.push(precompute_pass(ATMOSPHERE_INDIRECT_IRRADIANCE_PIPELINE_NAME, ATMOSPHERE_DELTA_IRRADIANCE_TEXTURE_NAME, false, scattering_order - 1, 0));
+        precompute_passes.push(precompute_pass(ATMOSPHERE_ACCUMULATE_INDIRECT_IRRADIANCE_PIPELINE_NAME, ATMOSPHERE_IRRADIANCE_TEXTURE_NAME, true, scattering_order - 1, 0));
+        precompute_passes.push(precompute_pass(ATMOSPHERE_MULTIPLE_SCATTERING_PIPELINE_NAME, ATMOSPHERE_DELTA_RAYLEIGH_SCATTERING_TEXTURE_NAME, false, scattering_order, 0));
+        precompute_passes.push(precompute_pass(ATMOSPHERE_ACCUMULATE_MULTIPLE_SCATTERING_PIPELINE_NAME, ATMOSPHERE_SCATTERING_TEXTURE_NAME, true, scattering_order, 0));
+    }
+    precompute_passes
+}
+
+pub fn get_render_pass_data_create_info(is_accumulate: bool) -> RenderPassDataCreateInfo {
+    let pipeline_data_create_info = |pipeline_name: &str, shader_define: &str| {
+        let mut shader_defines = vec![format!("{}=1", shader_define)];
+        let mut blend_mode = BlendMode::None;
+        if is_accumulate {
+            shader_defines.push(String::from("ATMOSPHERE_ACCUMULATE=1"));
+            blend_mode = BlendMode::Additive;
+        }
+        PipelineDataCreateInfo {
+            _pipeline_data_create_info_name: String::from(pipeline_name),
+            _pipeline_vertex_shader_file: PathBuf::from(light_probe::LIGHT_PROBE_VERTEX_SHADER_FILE),
+            _pipeline_fragment_shader_file: PathBuf::from(ATMOSPHERE_FRAGMENT_SHADER_FILE),
+            _pipeline_shader_defines: shader_defines,
+            _pipeline_color_blend_modes: vec![vulkan_context::get_color_blend_mode(blend_mode)],
+            _depth_stencil_state_create_info: DepthStencilStateCreateInfo {
+                _depth_test_enable: false,
+                _depth_write_enable: false,
+                ..Default::default()
+            },
+            _push_constant_ranges: vec![vk::PushConstantRange {
+                stage_flags: vk::ShaderStageFlags::FRAGMENT,
+                offset: 0,
+                size: std::mem::size_of::<PushConstant_Atmosphere>() as u32,
+            }],
+            _descriptor_data_create_infos: get_pipeline_input_texture_names(pipeline_name).iter().map(|(binding_index, texture_name)| DescriptorDataCreateInfo {
+                _descriptor_binding_index: *binding_index,
+                _descriptor_name: String::from(*texture_name),
+                _descriptor_resource_type: DescriptorResourceType::RenderTarget,
+                _descriptor_shader_stage: vk::ShaderStageFlags::FRAGMENT,
+                ..Default::default()
+            }).collect(),
+            ..Default::default()
+        }
+    };
+
+    if is_accumulate {
+        RenderPassDataCreateInfo {
+            _render_pass_create_info_name: String::from(ATMOSPHERE_ACCUMULATE_RENDER_PASS_NAME),
+            _color_attachment_descriptions: vec![ImageAttachmentDescription {
+                _attachment_image_format: ATMOSPHERE_FORMAT,
+                _attachment_load_operation: vk::AttachmentLoadOp::LOAD,
+                _attachment_store_operation: vk::AttachmentStoreOp::STORE,
+                _attachment_initial_layout: vk::ImageLayout::GENERAL,
+                ..Default::default()
+            }],
+            _pipeline_data_create_infos: vec![
+                pipeline_data_create_info(ATMOSPHERE_ACCUMULATE_INDIRECT_IRRADIANCE_PIPELINE_NAME, "ATMOSPHERE_INDIRECT_IRRADIANCE"),
+                pipeline_data_create_info(ATMOSPHERE_ACCUMULATE_MULTIPLE_SCATTERING_PIPELINE_NAME, "ATMOSPHERE_MULTIPLE_SCATTERING"),
+            ],
+            ..Default::default()
+        }
+    } else {
+        RenderPassDataCreateInfo {
+            _render_pass_create_info_name: String::from(ATMOSPHERE_RENDER_PASS_NAME),
+            _color_attachment_descriptions: vec![ImageAttachmentDescription {
+                _attachment_image_format: ATMOSPHERE_FORMAT,
+                _attachment_store_operation: vk::AttachmentStoreOp::STORE,
+                ..Default::default()
+            }],
+            _pipeline_data_create_infos: vec![
+                pipeline_data_create_info(ATMOSPHERE_TRANSMITTANCE_PIPELINE_NAME, "ATMOSPHERE_TRANSMITTANCE"),
+                pipeline_data_create_info(ATMOSPHERE_DIRECT_IRRADIANCE_PIPELINE_NAME, "ATMOSPHERE_DIRECT_IRRADIANCE"),
+                pipeline_data_create_info(ATMOSPHERE_SINGLE_SCATTERING_PIPELINE_NAME, "ATMOSPHERE_SINGLE_SCATTERING"),
+                pipeline_data_create_info(ATMOSPHERE_SCATTERING_DENSITY_PIPELINE_NAME, "ATMOSPHERE_SCATTERING_DENSITY"),
+                pipeline_data_create_info(ATMOSPHERE_INDIRECT_IRRADIANCE_PIPELINE_NAME, "ATMOSPHERE_INDIRECT_IRRADIANCE"),
+                pipeline_data_create_info(ATMOSPHERE_MULTIPLE_SCATTERING_PIPELINE_NAME, "ATMOSPHERE_MULTIPLE_SCATTERING"),
+            ],
+            ..Default::default()
+        }
+    }
+}
+
+// the descriptors of the materials of the project which render the sky or the aerial perspective
+pub fn get_atmosphere_descriptor_data_create_infos(transmittance_binding_index: u32, scattering_binding_index: u32, irradiance_binding_index: u32) -> Vec<DescriptorDataCreateInfo> {
+    [
+        (transmittance_binding_index, ATMOSPHERE_TRANSMITTANCE_TEXTURE_NAME),
+        (scattering_binding_index, ATMOSPHERE_SCATTERING_TEXTURE_NAME),
+        (irradiance_binding_index, ATMOSPHERE_IRRADIANCE_TEXTURE_NAME),
+    ].iter().map(|(binding_index, texture_name)| DescriptorDataCreateInfo {
+        _descriptor_binding_index: *binding_index,
+        _descriptor_name: String::from(*texture_name),
+        _descriptor_resource_type: DescriptorResourceType::RenderTarget,
+        _descriptor_shader_stage: vk::ShaderStageFlags::FRAGMENT,
+        ..Default::default()
+    }).collect()
+}
+
+// the texture sizes of the precompute are the constants of the header, the functions follow.
+pub fn get_atmosphere_shader_header() -> String {
+    format!(r#"// generated by the engine, atmosphere.rs - get_atmosphere_shader_header
+#ifndef ATMOSPHERE_GLSL
+#define ATMOSPHERE_GLSL
+
+#define TRANSMITTANCE_TEXTURE_WIDTH {}
+#define TRANSMITTANCE_TEXTURE_HEIGHT {}
+#define IRRADIANCE_TEXTURE_WIDTH {}
+#define IRRADIANCE_TEXTURE_HEIGHT {}
+#define SCATTERING_TEXTURE_R_SIZE {}
+#define SCATTERING_TEXTURE_MU_SIZE {}
+#define SCATTERING_TEXTURE_MU_S_SIZE {}
+#define SCATTERING_TEXTURE_NU_SIZE {}
+#define BOTTOM_RADIUS {:?}
+#define TOP_RADIUS {:?}
+#define MU_S_MIN {:?}
+{}
+#endif // ATMOSPHERE_GLSL
+"#,
+        TRANSMITTANCE_TEXTURE_WIDTH,
+        TRANSMITTANCE_TEXTURE_HEIGHT,
+        IRRADIANCE_TEXTURE_WIDTH,
+        IRRADIANCE_TEXTURE_HEIGHT,
+        SCATTERING_TEXTURE_R_SIZE,
+        SCATTERING_TEXTURE_MU_SIZE,
+        SCATTERING_TEXTURE_MU_S_SIZE,
+        SCATTERING_TEXTURE_NU_SIZE,
+        ATMOSPHERE_BOTTOM_RADIUS,
+        ATMOSPHERE_TOP_RADIUS,
+        ATMOSPHERE_MU_S_MIN,
+        ATMOSPHERE_FUNCTIONS_SOURCE,
+    )
+}
+
+// the header is written again when the engine has changed it, the light probe includes it too.
+#[cfg(not(target_os = "android"))]
+pub fn generate_atmosphere_shaders() {
+    let shader_sources = [
+        (ATMOSPHERE_FRAGMENT_SHADER_FILE, String::from(ATMOSPHERE_FRAGMENT_SHADER_SOURCE)),
+        (ATMOSPHERE_SHADER_FILE, get_atmosphere_shader_header()),
+    ];
+    for (shader_file, shader_source) in shader_sources.iter() {
+        let mut shader_file_path = PathBuf::from(SHADER_DIRECTORY);
+        shader_file_path.push(shader_file);
+        if Some(shader_source.as_str()) != fs::read_to_string(&shader_file_path).ok().as_deref() {
+            fs::create_dir_all(shader_file_path.parent().unwrap()).expect("Failed to create directories.");
+            fs::write(&shader_file_path, shader_source).expect("Failed to write");
+            log::info!("generate_atmosphere_shaders: {:?}", shader_file_path);
+        }
+    }
+}
+
+impl Atmosphere {
+    pub fn is_combined_textures(&self) -> bool {
+        false == self._textures.contains_key(ATMOSPHERE_SINGLE_MIE_SCATTERING_TEXTURE_NAME)
+    }
+
+    // the separate mie texture is created with the settings of the last set_atmosphere_settings
+    pub fn create_atmosphere_textures(&mut self, renderer_data: &RendererData) {
+        let create_info = |name: &str, width: u32, height: u32, layers: u32| -> TextureCreateInfo<u8> {
+            TextureCreateInfo {
+                _texture_name: String::from(name),
+                _texture_width: width,
+                _texture_height: height,
+                _texture_layers: layers,
+                _texture_format: ATMOSPHERE_FORMAT,
+                _texture_view_type: if 1 < layers { vk::ImageViewType::TYPE_2D_ARRAY } else { vk::ImageViewType::TYPE_2D },
+                _texture_min_filter: vk::Filter::LINEAR,
+                _texture_mag_filter: vk::Filter::LINEAR,
+                _texture_wrap_mode: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                ..Default::default()
+            }
+        };
+        let scattering_width = SCATTERING_TEXTURE_NU_SIZE * SCATTERING_TEXTURE_MU_S_SIZE;
+        let mut texture_infos: Vec<(&'static str, u32, u32, u32)> = vec![
+            (ATMOSPHERE_TRANSMITTANCE_TEXTURE_NAME, TRANSMITTANCE_TEXTURE_WIDTH, TRANSMITTANCE_TEXTURE_HEIGHT, 1),
+            (ATMOSPHERE_IRRADIANCE_TEXTURE_NAME, IRRADIANCE_TEXTURE_WIDTH, IRRADIANCE_TEXTURE_HEIGHT, 1),
+            (ATMOSPHERE_DELTA_IRRADIANCE_TEXTURE_NAME, IRRADIANCE_TEXTURE_WIDTH, IRRADIANCE_TEXTURE_HEIGHT, 1),
+            (ATMOSPHERE_SCATTERING_TEXTURE_NAME, scattering_width, SCATTERING_TEXTURE_MU_SIZE, SCATTERING_TEXTURE_R_SIZE),
+            (ATMOSPHERE_DELTA_RAYLEIGH_SCATTERING_TEXTURE_NAME, scattering_width, SCATTERING_TEXTURE_MU_SIZE, SCATTERING_TEXTURE_R_SIZE),
+            (ATMOSPHERE_DELTA_MIE_SCATTERING_TEXTURE_NAME, scattering_width, SCATTERING_TEXTURE_MU_SIZE, SCATTERING_TEXTURE_R_SIZE),
+            (ATMOSPHERE_DELTA_SCATTERING_DENSITY_TEXTURE_NAME, scattering_width, SCATTERING_TEXTURE_MU_SIZE, SCATTERING_TEXTURE_R_SIZE),
+        ];
+        if false == self._settings._use_combined_textures {
+            texture_infos.push((ATMOSPHERE_SINGLE_MIE_SCATTERING_TEXTURE_NAME, scattering_width, SCATTERING_TEXTURE_MU_SIZE, SCATTERING_TEXTURE_R_SIZE));
+        }
+        for (texture_name, width, height, layers) in texture_infos.iter() {
+            self._textures.insert(texture_name, renderer_data.create_render_target::<u8>(&create_info(texture_name, *width, *height, *layers)));
+        }
+        self._is_precomputed = false;
+    }
+
+    pub fn destroy_atmosphere_textures(&mut self, renderer_data: &RendererData) {
+        for (_, texture_data) in self._textures.drain() {
+            renderer_data.destroy_texture(&texture_data);
+        }
+        self._is_precomputed = false;
+    }
+
+    pub fn get_render_target(&self, render_target_name: &str) -> Option<&TextureData> {
+        self._textures.get(render_target_name)
+    }
+
+    // the framebuffers per layer with utility::create_framebuffer, the descriptor sets of the pipelines read the previous passes
+    pub fn create_framebuffers_and_descriptor_sets(&mut self, renderer_data: &RendererData, resources: &Resources) {
+        if self._textures.is_empty() {
+            return;
+        }
+        let device = renderer_data.get_device();
+        let create_layer_framebuffers = |render_pass_name: &str, texture_data: &TextureData| -> Vec<FramebufferData> {
+            let render_pass_data = resources.get_render_pass_data(render_pass_name).borrow();
+            (0..texture_data._image_layers).map(|layer| utility::create_framebuffer(device, &render_pass_data, texture_data, layer, 0, None)).collect()
+        };
+        for (texture_name, texture_data) in self._textures.iter() {
+            self._framebuffers.insert(texture_name, create_layer_framebuffers(ATMOSPHERE_RENDER_PASS_NAME, texture_data));
+        }
+        for texture_name in [ATMOSPHERE_IRRADIANCE_TEXTURE_NAME, ATMOSPHERE_SCATTERING_TEXTURE_NAME].iter() {
+            self._accumulate_framebuffers.insert(texture_name, create_layer_framebuffers(ATMOSPHERE_ACCUMULATE_RENDER_PASS_NAME, &self._textures[texture_name]));
+        }
+
+        let precompute_pipelines = [
+            (ATMOSPHERE_RENDER_PASS_NAME, ATMOSPHERE_DIRECT_IRRADIANCE_PIPELINE_NAME),
+            (ATMOSPHERE_RENDER_PASS_NAME, ATMOSPHERE_SINGLE_SCATTERING_PIPELINE_NAME),
+            (ATMOSPHERE_RENDER_PASS_NAME, ATMOSPHERE_SCATTERING_DENSITY_PIPELINE_NAME),
+            (ATMOSPHERE_RENDER_PASS_NAME, ATMOSPHERE_INDIRECT_IRRADIANCE_PIPELINE_NAME),
+            (ATMOSPHERE_RENDER_PASS_NAME, ATMOSPHERE_MULTIPLE_SCATTERING_PIPELINE_NAME),
+            (ATMOSPHERE_ACCUMULATE_RENDER_PASS_NAME, ATMOSPHERE_ACCUMULATE_INDIRECT_IRRADIANCE_PIPELINE_NAME),
+            (ATMOSPHERE_ACCUMULATE_RENDER_PASS_NAME, ATMOSPHERE_ACCUMULATE_MULTIPLE_SCATTERING_PIPELINE_NAME),
+        ];
+        for (render_pass_name, pipeline_name) in precompute_pipelines.iter() {
+            let render_pass_pipeline_data = resources.get_render_pass_pipeline_data(render_pass_name, pipeline_name);
+            let pipeline_data = render_pass_pipeline_data._pipeline_data.borrow();
+            let descriptor_data = &pipeline_data._descriptor_data;
+            let descriptor_binding_indices: Vec<u32> = descriptor_data._descriptor_data_create_infos.iter().map(|descriptor_data_create_info| {
+                descriptor_data_create_info._descriptor_binding_index
+            }).collect();
+            let descriptor_resource_infos: Vec<DescriptorResourceInfo> = descriptor_data._descriptor_data_create_infos.iter().map(|descriptor_data_create_info| {
+                let texture_data = &self._textures[descriptor_data_create_info._descriptor_name.as_str()];
+                DescriptorResourceInfo::DescriptorImageInfo(texture_data.get_default_image_info().clone())
+            }).collect();
+            let descriptor_resource_infos_list: SwapchainArray<Vec<DescriptorResourceInfo>> = constants::SWAPCHAIN_IMAGE_INDICES.iter().map(|_swapchain_index| {
+                descriptor_resource_infos.clone()
+            }).collect();
+            let descriptor_sets = descriptor::create_descriptor_sets(device, descriptor_data, pipeline_name);
+            let _write_descriptor_sets = descriptor::create_write_descriptor_sets_with_update(
+                device,
+                &descriptor_sets,
+                &descriptor_binding_indices,
+                &descriptor_data._descriptor_set_layout_bindings,
+                &descriptor_resource_infos_list,
+            );
+            self._descriptor_sets.insert(pipeline_name, descriptor_sets);
+        }
+        self._is_precomputed = false;
+    }
+
+    pub fn destroy_framebuffers_and_descriptor_sets(&mut self, device: &Device) {
+        for framebuffer_data in self._framebuffers.drain().chain(self._accumulate_framebuffers.drain()).flat_map(|(_, framebuffers)| framebuffers) {
+            framebuffer::destroy_framebuffer_data(device, &framebuffer_data);
+        }
+        self._descriptor_sets.clear();
+        self._is_precomputed = false;
+    }
+
+    // returns true when the textures are changed, then the render targets are created again.
+    pub fn set_atmosphere_settings(&mut self, settings: &AtmosphereSettings) -> bool {
+        if self._settings.is_same_precompute(settings) {
+            return false;
+        }
+        let is_textures_changed = false == self._textures.is_empty() && settings._use_combined_textures != self.is_combined_textures();
+        self._settings = settings.clone();
+        self._is_precomputed = false;
+        is_textures_changed
+    }
+
+    pub fn need_precompute(&self) -> bool {
+        false == self._framebuffers.is_empty() && false == self._is_precomputed
+    }
+
+    // records every scattering order, the textures are left in GENERAL for the light probe and the materials.
+    pub fn precompute_atmosphere(&mut self, command_buffer: vk::CommandBuffer, renderer_data: &RendererData, resources: &Resources) {
+        if self._framebuffers.is_empty() {
+            return;
+        }
+        let quad_mesh_data = resources.get_mesh_data(light_probe::LIGHT_PROBE_QUAD_MESH_NAME).borrow();
+        let quad_geometry_data = quad_mesh_data.get_default_geometry_data().borrow();
+        // the next pass reads the target of the previous pass or writes the texture which the previous pass has read
+        let memory_barrier = vk::MemoryBarrier {
+            src_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+            dst_access_mask: vk::AccessFlags::SHADER_READ | vk::AccessFlags::COLOR_ATTACHMENT_READ | vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+            ..Default::default()
+        };
+        let barrier_stage_mask = vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT | vk::PipelineStageFlags::FRAGMENT_SHADER;
+
+        renderer_data.begin_debug_label(command_buffer, ATMOSPHERE_RENDER_PASS_NAME);
+        for precompute_pass in get_atmosphere_precompute_passes(self._settings._scattering_orders.max(1), self.is_combined_textures()).iter() {
+            let (render_pass_name, framebuffers) = if precompute_pass._is_accumulate {
+                (ATMOSPHERE_ACCUMULATE_RENDER_PASS_NAME, &self._accumulate_framebuffers[precompute_pass._target_name])
+            } else {
+                (ATMOSPHERE_RENDER_PASS_NAME, &self._framebuffers[precompute_pass._target_name])
+            };
+            let render_pass_pipeline_data = resources.get_render_pass_pipeline_data(render_pass_name, precompute_pass._pipeline_name);
+            let render_pass_data = render_pass_pipeline_data._render_pass_data.borrow();
+            let pipeline_data = render_pass_pipeline_data._pipeline_data.borrow();
+            for (layer, framebuffer_data) in framebuffers.iter().enumerate() {
+                let push_constant_data = PushConstant_Atmosphere {
+                    _layer: layer as u32,
+                    _scattering_order: precompute_pass._scattering_order,
+                    _output_index: precompute_pass._output_index,
+                    _reserved0: 0,
+                };
+                renderer_data.begin_render_pass_pipeline(command_buffer, 0, &render_pass_data, &pipeline_data, Some(framebuffer_data));
+                if let Some(descriptor_sets) = self._descriptor_sets.get(precompute_pass._pipeline_name) {
+                    unsafe {
+                        renderer_data.get_device().cmd_bind_descriptor_sets(
+                            command_buffer,
+                            pipeline_data._pipeline_bind_point,
+                            pipeline_data._pipeline_layout,
+                            0,
+                            &[descriptor_sets[0]],
+                            &[]
+                        );
+                    }
+                }
+                renderer_data.upload_push_constant_data(command_buffer, &pipeline_data, &push_constant_data);
+                renderer_data.draw_elements(command_buffer, &quad_geometry_data);
+                renderer_data.end_render_pass(command_buffer);
+            }
+            renderer_data.pipeline_barrier(command_buffer, barrier_stage_mask, barrier_stage_mask, vk::DependencyFlags::empty(), &[memory_barrier], &[], &[]);
+        }
+        renderer_data.pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            vk::PipelineStageFlags::FRAGMENT_SHADER | vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::DependencyFlags::empty(),
+            &[vk::MemoryBarrier {
+                src_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                dst_access_mask: vk::AccessFlags::SHADER_READ,
+                ..Default::default()
+            }],
+            &[],
+            &[]
+        );
+        renderer_data.end_debug_label(command_buffer);
+        self._is_precomputed = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_written_texture_names(precompute_passes: &[AtmospherePrecomputePass]) -> Vec<&'static str> {
+        precompute_passes.iter().map(|precompute_pass| precompute_pass._target_name).collect()
+    }
+
+    #[test]
+    fn test_precompute_passes_read_after_write() {
+        for use_combined_textures in [true, false].iter() {
+            let precompute_passes = get_atmosphere_precompute_passes(4, *use_combined_textures);
+            for (pass_index, precompute_pass) in precompute_passes.iter().enumerate() {
+                let written_texture_names = get_written_texture_names(&precompute_passes[..pass_index]);
+                for (_, input_texture_name) in get_pipeline_input_texture_names(precompute_pass._pipeline_name).iter() {
+                    assert!(written_texture_names.contains(input_texture_name), "{} reads {} before it is written", precompute_pass._pipeline_name, input_texture_name);
+                    assert_ne!(*input_texture_name, precompute_pass._target_name);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_precompute_passes_of_scattering_orders() {
+        let count_pipeline = |precompute_passes: &[AtmospherePrecomputePass], pipeline_name: &str| {
+            precompute_passes.iter().filter(|precompute_pass| pipeline_name == precompute_pass._pipeline_name).count()
+        };
+        let single_scattering_passes = get_atmosphere_precompute_passes(1, true);
+        assert_eq!(0, count_pipeline(&single_scattering_passes, ATMOSPHERE_SCATTERING_DENSITY_PIPELINE_NAME));
+        assert!(single_scattering_passes.iter().all(|precompute_pass| false == precompute_pass._is_accumulate));
+
+        let precompute_passes = get_atmosphere_precompute_passes(4, true);
+        assert_eq!(3, count_pipeline(&precompute_passes, ATMOSPHERE_SCATTERING_DENSITY_PIPELINE_NAME));
+        assert_eq!(3, count_pipeline(&precompute_passes, ATMOSPHERE_ACCUMULATE_MULTIPLE_SCATTERING_PIPELINE_NAME));
+        // only the final textures are accumulated
+        for precompute_pass in precompute_passes.iter().filter(|precompute_pass| precompute_pass._is_accumulate) {
+            assert!(ATMOSPHERE_IRRADIANCE_TEXTURE_NAME == precompute_pass._target_name || ATMOSPHERE_SCATTERING_TEXTURE_NAME == precompute_pass._target_name);
+        }
+        // the indirect irradiance of the order n is of the scattering of the order n - 1
+        for precompute_pass in precompute_passes.iter().filter(|precompute_pass| ATMOSPHERE_INDIRECT_IRRADIANCE_PIPELINE_NAME == precompute_pass._pipeline_name) {
+            assert!(1 <= precompute_pass._scattering_order && precompute_pass._scattering_order < 4);
+        }
+    }
+
+    #[test]
+    fn test_single_mie_scattering_texture() {
+        let is_single_mie_written = |use_combined_textures: bool| {
+            get_written_texture_names(&get_atmosphere_precompute_passes(4, use_combined_textures)).contains(&ATMOSPHERE_SINGLE_MIE_SCATTERING_TEXTURE_NAME)
+        };
+        assert!(false == is_single_mie_written(true));
+        assert!(is_single_mie_written(false));
+    }
+
+    #[test]
+    fn test_atmosphere_shader_header() {
+        let shader_header = get_atmosphere_shader_header();
+        assert!(shader_header.contains(&format!("#define SCATTERING_TEXTURE_R_SIZE {}", SCATTERING_TEXTURE_R_SIZE)));
+        assert!(shader_header.contains("#define BOTTOM_RADIUS 6360.0"));
+        assert!(shader_header.trim_end().ends_with("#endif // ATMOSPHERE_GLSL"));
+    }
+}
@@ -0,0 +1,492 @@
+use std::fs;
+use std::path::PathBuf;
+
+use ash::{ vk, Device };
+use ash::version::DeviceV1_0;
+use nalgebra::Vector2;
+
+use crate::constants;
+use crate::renderer::post_process::PostProcessProfile;
+use crate::renderer::renderer::RendererData;
+use crate::resource::resource::Resources;
+use crate::vulkan_context::buffer::{ self, ShaderBufferData };
+use crate::vulkan_context::descriptor::{ self, DescriptorDataCreateInfo, DescriptorResourceInfo, DescriptorResourceType };
+use crate::vulkan_context::render_pass::{ PipelineDataCreateInfo, RenderPassDataCreateInfo };
+use crate::vulkan_context::shader::SHADER_DIRECTORY;
+use crate::vulkan_context::texture::TextureData;
+use crate::vulkan_context::vulkan_context::SwapchainArray;
+
+pub const AUTO_EXPOSURE_BIN_COUNT: usize = 256; // must match with auto_exposure.comp
+pub const AUTO_EXPOSURE_WORK_GROUP_SIZE: u32 = 16; // must match with auto_exposure.comp, 16 x 16 threads: a thread per bin in the resolve
+pub const AUTO_EXPOSURE_MODE_HISTOGRAM: u32 = 0; // must match with auto_exposure.comp
+pub const AUTO_EXPOSURE_MODE_RESOLVE: u32 = 1; // must match with auto_exposure.comp
+pub const AUTO_EXPOSURE_FLAG_MANUAL: u32 = 1 << 0; // must match with auto_exposure.comp
+pub const AUTO_EXPOSURE_FLAG_RESET_HISTORY: u32 = 1 << 1; // must match with auto_exposure.comp
+pub const AUTO_EXPOSURE_RENDER_PASS_NAME: &str = "auto_exposure";
+pub const AUTO_EXPOSURE_HISTOGRAM_BUFFER_NAME: &str = "AutoExposureHistogramBuffer";
+// render_final of the project binds this name as a UniformBuffer descriptor, see get_auto_exposure_descriptor_data_create_info
+pub const AUTO_EXPOSURE_BUFFER_NAME: &str = "AutoExposureBuffer";
+pub const AUTO_EXPOSURE_SHADER_FILE: &str = "common/auto_exposure.comp";
+pub const AUTO_EXPOSURE_SHADER_SOURCE: &str = r#"#version 450
+
+#define AUTO_EXPOSURE_BIN_COUNT 256
+#define AUTO_EXPOSURE_WORK_GROUP_SIZE 16
+#define AUTO_EXPOSURE_MODE_HISTOGRAM 0
+#define AUTO_EXPOSURE_MODE_RESOLVE 1
+#define AUTO_EXPOSURE_FLAG_MANUAL 1
+#define AUTO_EXPOSURE_FLAG_RESET_HISTORY 2
+#define AUTO_EXPOSURE_LUMINANCE_EPSILON 0.000001
+
+layout(local_size_x = AUTO_EXPOSURE_WORK_GROUP_SIZE, local_size_y = AUTO_EXPOSURE_WORK_GROUP_SIZE, local_size_z = 1) in;
+
+layout(binding = 0) uniform sampler2D texture_scene_color;
+layout(std430, binding = 1) buffer AutoExposureHistogramBuffer
+{
+    uint bins[AUTO_EXPOSURE_BIN_COUNT];
+};
+// the uniform block of render_final has the same layout
+layout(std430, binding = 2) buffer AutoExposureBuffer
+{
+    float exposure;
+    float average_luminance;
+    float target_exposure;
+    float reserved0;
+};
+
+layout(push_constant) uniform PushConstant_AutoExposure
+{
+    ivec2 image_size;
+    uint mode;
+    uint flags;
+    float min_log_luminance;
+    float max_log_luminance;
+    float delta_time;
+    float speed_up;
+    float speed_down;
+    float key_value;
+    float min_exposure;
+    float max_exposure;
+    float manual_exposure;
+    float reserved0;
+    float reserved1;
+    float reserved2;
+} pushConstant;
+
+shared uint shared_bins[AUTO_EXPOSURE_BIN_COUNT];
+shared float shared_weights[AUTO_EXPOSURE_BIN_COUNT];
+
+// the bin 0 is the black pixels, they do not darken the average
+uint get_luminance_bin(float luminance)
+{
+    if(luminance < AUTO_EXPOSURE_LUMINANCE_EPSILON)
+    {
+        return 0u;
+    }
+    float log_luminance_range = max(pushConstant.max_log_luminance - pushConstant.min_log_luminance, AUTO_EXPOSURE_LUMINANCE_EPSILON);
+    float mapped_luminance = clamp((log2(luminance) - pushConstant.min_log_luminance) / log_luminance_range, 0.0, 1.0);
+    return uint(mapped_luminance * float(AUTO_EXPOSURE_BIN_COUNT - 2) + 1.0);
+}
+
+void build_histogram(uint local_index)
+{
+    shared_bins[local_index] = 0u;
+    barrier();
+
+    ivec2 pixel = ivec2(gl_GlobalInvocationID.xy);
+    if(all(lessThan(pixel, pushConstant.image_size)))
+    {
+        vec3 color = texelFetch(texture_scene_color, pixel, 0).xyz;
+        float luminance = dot(color, vec3(0.2126, 0.7152, 0.0722));
+        atomicAdd(shared_bins[get_luminance_bin(luminance)], 1u);
+    }
+    barrier();
+
+    if(0u != shared_bins[local_index])
+    {
+        atomicAdd(bins[local_index], shared_bins[local_index]);
+    }
+}
+
+void resolve_exposure(uint local_index)
+{
+    uint bin_count = bins[local_index];
+    shared_weights[local_index] = float(bin_count) * float(local_index);
+    barrier();
+
+    for(uint stride = AUTO_EXPOSURE_BIN_COUNT / 2; 0u < stride; stride >>= 1)
+    {
+        if(local_index < stride)
+        {
+            shared_weights[local_index] += shared_weights[local_index + stride];
+        }
+        barrier();
+    }
+
+    if(0u == local_index)
+    {
+        float pixel_count = float(pushConstant.image_size.x * pushConstant.image_size.y);
+        float black_pixel_count = float(bin_count);
+        float weighted_bin = shared_weights[0] / max(pixel_count - black_pixel_count, 1.0) - 1.0;
+        float log_luminance_range = pushConstant.max_log_luminance - pushConstant.min_log_luminance;
+        float log_average_luminance = pushConstant.min_log_luminance + weighted_bin / float(AUTO_EXPOSURE_BIN_COUNT - 2) * log_luminance_range;
+        average_luminance = exp2(log_average_luminance);
+        target_exposure = clamp(pushConstant.key_value / max(average_luminance, AUTO_EXPOSURE_LUMINANCE_EPSILON), pushConstant.min_exposure, pushConstant.max_exposure);
+
+        if(0u != (pushConstant.flags & AUTO_EXPOSURE_FLAG_MANUAL))
+        {
+            exposure = pushConstant.manual_exposure;
+        }
+        else if(0u != (pushConstant.flags & AUTO_EXPOSURE_FLAG_RESET_HISTORY))
+        {
+            exposure = target_exposure;
+        }
+        else
+        {
+            // the adaptation is in the log space, the eye adapts to the dark slower than to the bright
+            float speed = (exposure < target_exposure) ? pushConstant.speed_down : pushConstant.speed_up;
+            float adaptation = 1.0 - exp(-pushConstant.delta_time * speed);
+            exposure = exp2(mix(log2(max(exposure, AUTO_EXPOSURE_LUMINANCE_EPSILON)), log2(target_exposure), adaptation));
+        }
+    }
+}
+
+void main()
+{
+    uint local_index = gl_LocalInvocationIndex;
+    if(AUTO_EXPOSURE_MODE_HISTOGRAM == pushConstant.mode)
+    {
+        build_histogram(local_index);
+    }
+    else
+    {
+        resolve_exposure(local_index);
+    }
+}
+"#;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AutoExposureSettings {
+    pub _target_name: String,
+    pub _min_log_luminance: f32, // the log2 luminance range of the histogram
+    pub _max_log_luminance: f32,
+    pub _speed_up: f32, // the adaptation speed to the bright scene, 1 / seconds
+    pub _speed_down: f32, // the adaptation speed to the dark scene
+    pub _key_value: f32, // the middle gray of the average luminance
+    pub _min_exposure: f32,
+    pub _max_exposure: f32,
+}
+
+impl Default for AutoExposureSettings {
+    fn default() -> AutoExposureSettings {
+        unsafe {
+            AutoExposureSettings {
+                _target_name: String::from("SceneColor"),
+                _min_log_luminance: -10.0,
+                _max_log_luminance: 12.0,
+                _speed_up: constants::AUTO_EXPOSURE_SPEED_UP,
+                _speed_down: constants::AUTO_EXPOSURE_SPEED_DOWN,
+                _key_value: 0.18,
+                _min_exposure: 1.0 / 1024.0,
+                _max_exposure: 64.0,
+            }
+        }
+    }
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone)]
+#[repr(C)]
+pub struct PushConstant_AutoExposure {
+    pub _image_size: Vector2<i32>,
+    pub _mode: u32,
+    pub _flags: u32,
+    pub _min_log_luminance: f32,
+    pub _max_log_luminance: f32,
+    pub _delta_time: f32,
+    pub _speed_up: f32,
+    pub _speed_down: f32,
+    pub _key_value: f32,
+    pub _min_exposure: f32,
+    pub _max_exposure: f32,
+    pub _manual_exposure: f32,
+    pub _reserved0: f32,
+    pub _reserved1: f32,
+    pub _reserved2: f32,
+}
+
+crate::impl_push_constant!(PushConstant_AutoExposure, 64);
+
+// must match with AutoExposureBuffer of auto_exposure.comp and the uniform block of render_final
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct AutoExposureBufferData {
+    pub _exposure: f32, // multiplied with TonemapSettings::_exposure, PostProcessProfile::_exposure_value in the manual exposure
+    pub _average_luminance: f32,
+    pub _target_exposure: f32,
+    pub _reserved0: f32,
+}
+
+// The exposure of SceneColor: histogram dispatch -> resolve dispatch -> AutoExposureBuffer of render_final.
+// the buffers are device local and shared by the swapchain images, the exposure of the previous frame is adapted.
+// PostProcessProfile::_enable_auto_exposure of the main viewport selects the auto or the manual exposure.
+pub struct AutoExposure {
+    pub _settings: AutoExposureSettings,
+    pub _histogram_buffer: Option<ShaderBufferData>,
+    pub _exposure_buffer: Option<ShaderBufferData>,
+    pub _descriptor_sets: SwapchainArray<vk::DescriptorSet>, // created in prepare_framebuffer_and_descriptors
+    pub _is_history_valid: bool,
+}
+
+impl Default for AutoExposure {
+    fn default() -> AutoExposure {
+        AutoExposure {
+            _settings: AutoExposureSettings::default(),
+            _histogram_buffer: None,
+            _exposure_buffer: None,
+            _descriptor_sets: Vec::new(),
+            _is_history_valid: false,
+        }
+    }
+}
+
+pub fn get_render_pass_data_create_info() -> RenderPassDataCreateInfo {
+    let pipeline_data_create_infos = vec![
+        PipelineDataCreateInfo {
+            _pipeline_data_create_info_name: String::from(AUTO_EXPOSURE_RENDER_PASS_NAME),
+            _pipeline_bind_point: vk::PipelineBindPoint::COMPUTE,
+            _pipeline_compute_shader_file: PathBuf::from(AUTO_EXPOSURE_SHADER_FILE),
+            _push_constant_ranges: vec![vk::PushConstantRange {
+                stage_flags: vk::ShaderStageFlags::COMPUTE,
+                offset: 0,
+                size: std::mem::size_of::<PushConstant_AutoExposure>() as u32,
+            }],
+            _descriptor_data_create_infos: vec![
+                DescriptorDataCreateInfo {
+                    _descriptor_binding_index: 0,
+                    _descriptor_name: String::from("texture_scene_color"),
+                    _descriptor_resource_type: DescriptorResourceType::RenderTarget,
+                    _descriptor_shader_stage: vk::ShaderStageFlags::COMPUTE,
+                    ..Default::default()
+                },
+                DescriptorDataCreateInfo {
+                    _descriptor_binding_index: 1,
+                    _descriptor_name: String::from(AUTO_EXPOSURE_HISTOGRAM_BUFFER_NAME),
+                    _descriptor_resource_type: DescriptorResourceType::StorageBuffer,
+                    _descriptor_shader_stage: vk::ShaderStageFlags::COMPUTE,
+                    ..Default::default()
+                },
+                DescriptorDataCreateInfo {
+                    _descriptor_binding_index: 2,
+                    _descriptor_name: String::from(AUTO_EXPOSURE_BUFFER_NAME),
+                    _descriptor_resource_type: DescriptorResourceType::StorageBuffer,
+                    _descriptor_shader_stage: vk::ShaderStageFlags::COMPUTE,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        }
+    ];
+
+    RenderPassDataCreateInfo {
+        _render_pass_create_info_name: String::from(AUTO_EXPOSURE_RENDER_PASS_NAME),
+        _pipeline_data_create_infos: pipeline_data_create_infos,
+        ..Default::default()
+    }
+}
+
+// the descriptor of the render_final pipeline, ex) layout(binding = N) uniform AutoExposureBuffer { float exposure; ... };
+pub fn get_auto_exposure_descriptor_data_create_info(descriptor_binding_index: u32) -> DescriptorDataCreateInfo {
+    DescriptorDataCreateInfo {
+        _descriptor_binding_index: descriptor_binding_index,
+        _descriptor_name: String::from(AUTO_EXPOSURE_BUFFER_NAME),
+        _descriptor_resource_type: DescriptorResourceType::UniformBuffer,
+        _descriptor_shader_stage: vk::ShaderStageFlags::FRAGMENT,
+        ..Default::default()
+    }
+}
+
+#[cfg(not(target_os = "android"))]
+pub fn generate_auto_exposure_shader() {
+    let mut shader_file_path = PathBuf::from(SHADER_DIRECTORY);
+    shader_file_path.push(AUTO_EXPOSURE_SHADER_FILE);
+    if false == shader_file_path.is_file() {
+        fs::create_dir_all(shader_file_path.parent().unwrap()).expect("Failed to create directories.");
+        fs::write(&shader_file_path, AUTO_EXPOSURE_SHADER_SOURCE).expect("Failed to write");
+        log::info!("generate_auto_exposure_shader: {:?}", shader_file_path);
+    }
+}
+
+impl AutoExposure {
+    pub fn create_auto_exposure_buffers(&mut self, renderer_data: &RendererData) {
+        // cleared by cmd_fill_buffer before each histogram dispatch
+        self._histogram_buffer = Some(buffer::create_shader_buffer_data(
+            renderer_data.get_device(),
+            renderer_data.get_device_memory_properties(),
+            &String::from(AUTO_EXPOSURE_HISTOGRAM_BUFFER_NAME),
+            vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::TRANSFER_DST,
+            (std::mem::size_of::<u32>() * AUTO_EXPOSURE_BIN_COUNT) as vk::DeviceSize,
+            true,
+            false,
+            true,
+        ));
+        // written by the resolve dispatch, read by render_final
+        self._exposure_buffer = Some(buffer::create_shader_buffer_data(
+            renderer_data.get_device(),
+            renderer_data.get_device_memory_properties(),
+            &String::from(AUTO_EXPOSURE_BUFFER_NAME),
+            vk::BufferUsageFlags::UNIFORM_BUFFER | vk::BufferUsageFlags::STORAGE_BUFFER,
+            std::mem::size_of::<AutoExposureBufferData>() as vk::DeviceSize,
+            true,
+            false,
+            true,
+        ));
+        self._is_history_valid = false;
+    }
+
+    pub fn destroy_auto_exposure_buffers(&mut self, device: &Device) {
+        if let Some(mut histogram_buffer) = self._histogram_buffer.take() {
+            buffer::destroy_shader_buffer_data(device, &mut histogram_buffer);
+        }
+        if let Some(mut exposure_buffer) = self._exposure_buffer.take() {
+            buffer::destroy_shader_buffer_data(device, &mut exposure_buffer);
+        }
+    }
+
+    pub fn get_exposure_buffer(&self) -> Option<&ShaderBufferData> {
+        self._exposure_buffer.as_ref()
+    }
+
+    pub fn create_descriptor_sets(&mut self, renderer_data: &RendererData, resources: &Resources, target: &TextureData) {
+        let render_pass_pipeline_data = resources.get_render_pass_pipeline_data(AUTO_EXPOSURE_RENDER_PASS_NAME, AUTO_EXPOSURE_RENDER_PASS_NAME);
+        let pipeline_data = render_pass_pipeline_data._pipeline_data.borrow();
+        let descriptor_data = &pipeline_data._descriptor_data;
+        let descriptor_binding_indices: Vec<u32> = descriptor_data._descriptor_data_create_infos.iter().map(|descriptor_data_create_info| {
+            descriptor_data_create_info._descriptor_binding_index
+        }).collect();
+        let histogram_buffer = self._histogram_buffer.as_ref().unwrap();
+        let exposure_buffer = self._exposure_buffer.as_ref().unwrap();
+        let descriptor_resource_infos_list: SwapchainArray<Vec<DescriptorResourceInfo>> = constants::SWAPCHAIN_IMAGE_INDICES.iter().map(|swapchain_index| {
+            vec![
+                DescriptorResourceInfo::DescriptorImageInfo(target.get_default_image_info().clone()),
+                histogram_buffer._descriptor_buffer_infos[*swapchain_index].clone(),
+                exposure_buffer._descriptor_buffer_infos[*swapchain_index].clone(),
+            ]
+        }).collect();
+        let descriptor_sets = descriptor::create_descriptor_sets(renderer_data.get_device(), descriptor_data, AUTO_EXPOSURE_RENDER_PASS_NAME);
+        let _write_descriptor_sets = descriptor::create_write_descriptor_sets_with_update(
+            renderer_data.get_device(),
+            &descriptor_sets,
+            &descriptor_binding_indices,
+            &descriptor_data._descriptor_set_layout_bindings,
+            &descriptor_resource_infos_list,
+        );
+        self._descriptor_sets = descriptor_sets;
+    }
+
+    pub fn destroy_descriptor_sets(&mut self) {
+        self._descriptor_sets.clear();
+    }
+
+    // the exposure jumps to the target at the next dispatch, ex) camera cut, scene load
+    pub fn invalidate_history(&mut self) {
+        self._is_history_valid = false;
+    }
+
+    pub fn set_auto_exposure_settings(&mut self, settings: &AutoExposureSettings) {
+        if self._settings._target_name != settings._target_name {
+            self._descriptor_sets.clear();
+        }
+        self._settings = settings.clone();
+    }
+
+    pub fn dispatch_auto_exposure(&mut self, command_buffer: vk::CommandBuffer, swapchain_index: u32, delta_time: f32, profile: &PostProcessProfile, renderer_data: &RendererData, resources: &Resources) {
+        if self._descriptor_sets.is_empty() {
+            return;
+        }
+        let index = swapchain_index as usize;
+        let target = renderer_data.get_render_target_from_str(&self._settings._target_name);
+        let device = renderer_data.get_device();
+        let histogram_buffer = self._histogram_buffer.as_ref().unwrap()._buffers[index]._buffer;
+        let exposure_buffer = self._exposure_buffer.as_ref().unwrap()._buffers[index]._buffer;
+        let buffer_memory_barrier = |buffer: vk::Buffer, src_access_mask: vk::AccessFlags, dst_access_mask: vk::AccessFlags| vk::BufferMemoryBarrier {
+            src_access_mask,
+            dst_access_mask,
+            src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            buffer,
+            offset: 0,
+            size: vk::WHOLE_SIZE,
+            ..Default::default()
+        };
+
+        // the histogram of the previous frame is read by its resolve
+        renderer_data.pipeline_barrier(command_buffer, vk::PipelineStageFlags::COMPUTE_SHADER, vk::PipelineStageFlags::TRANSFER, vk::DependencyFlags::empty(), &[], &[
+            buffer_memory_barrier(histogram_buffer, vk::AccessFlags::SHADER_READ, vk::AccessFlags::TRANSFER_WRITE),
+        ], &[]);
+        unsafe {
+            device.cmd_fill_buffer(command_buffer, histogram_buffer, 0, vk::WHOLE_SIZE, 0);
+        }
+        renderer_data.pipeline_barrier(command_buffer, vk::PipelineStageFlags::TRANSFER, vk::PipelineStageFlags::COMPUTE_SHADER, vk::DependencyFlags::empty(), &[], &[
+            buffer_memory_barrier(histogram_buffer, vk::AccessFlags::TRANSFER_WRITE, vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE),
+        ], &[]);
+
+        let mut flags: u32 = 0;
+        if false == profile._enable_auto_exposure { flags |= AUTO_EXPOSURE_FLAG_MANUAL; }
+        if false == self._is_history_valid { flags |= AUTO_EXPOSURE_FLAG_RESET_HISTORY; }
+        let mut push_constant_data = PushConstant_AutoExposure {
+            _image_size: Vector2::new(target._image_width as i32, target._image_height as i32),
+            _mode: AUTO_EXPOSURE_MODE_HISTOGRAM,
+            _flags: flags,
+            _min_log_luminance: self._settings._min_log_luminance,
+            _max_log_luminance: self._settings._max_log_luminance,
+            _delta_time: delta_time,
+            _speed_up: self._settings._speed_up,
+            _speed_down: self._settings._speed_down,
+            _key_value: self._settings._key_value,
+            _min_exposure: self._settings._min_exposure,
+            _max_exposure: self._settings._max_exposure,
+            _manual_exposure: profile._exposure_value,
+            _reserved0: 0.0,
+            _reserved1: 0.0,
+            _reserved2: 0.0,
+        };
+        let render_pass_pipeline_data = resources.get_render_pass_pipeline_data(AUTO_EXPOSURE_RENDER_PASS_NAME, AUTO_EXPOSURE_RENDER_PASS_NAME);
+        let pipeline_data = render_pass_pipeline_data._pipeline_data.borrow();
+        renderer_data.begin_debug_label(command_buffer, AUTO_EXPOSURE_RENDER_PASS_NAME);
+        renderer_data.begin_compute_pipeline(command_buffer, &pipeline_data);
+        unsafe {
+            device.cmd_bind_descriptor_sets(
+                command_buffer,
+                pipeline_data._pipeline_bind_point,
+                pipeline_data._pipeline_layout,
+                0,
+                &[self._descriptor_sets[index]],
+                &[]
+            );
+        }
+        if profile._enable_auto_exposure {
+            renderer_data.upload_push_constant_data(command_buffer, &pipeline_data, &push_constant_data);
+            renderer_data.dispatch_compute_pipeline(
+                command_buffer,
+                (target._image_width + AUTO_EXPOSURE_WORK_GROUP_SIZE - 1) / AUTO_EXPOSURE_WORK_GROUP_SIZE,
+                (target._image_height + AUTO_EXPOSURE_WORK_GROUP_SIZE - 1) / AUTO_EXPOSURE_WORK_GROUP_SIZE,
+                1
+            );
+        }
+
+        // the exposure of the previous frame is read by its render_final
+        renderer_data.pipeline_barrier(command_buffer, vk::PipelineStageFlags::COMPUTE_SHADER | vk::PipelineStageFlags::FRAGMENT_SHADER, vk::PipelineStageFlags::COMPUTE_SHADER, vk::DependencyFlags::empty(), &[], &[
+            buffer_memory_barrier(histogram_buffer, vk::AccessFlags::SHADER_WRITE, vk::AccessFlags::SHADER_READ),
+            buffer_memory_barrier(exposure_buffer, vk::AccessFlags::UNIFORM_READ, vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE),
+        ], &[]);
+        push_constant_data._mode = AUTO_EXPOSURE_MODE_RESOLVE;
+        renderer_data.upload_push_constant_data(command_buffer, &pipeline_data, &push_constant_data);
+        renderer_data.dispatch_compute_pipeline(command_buffer, 1, 1, 1);
+        renderer_data.end_debug_label(command_buffer);
+
+        renderer_data.pipeline_barrier(command_buffer, vk::PipelineStageFlags::COMPUTE_SHADER, vk::PipelineStageFlags::FRAGMENT_SHADER, vk::DependencyFlags::empty(), &[], &[
+            buffer_memory_barrier(exposure_buffer, vk::AccessFlags::SHADER_WRITE, vk::AccessFlags::UNIFORM_READ),
+        ], &[]);
+        self._is_history_valid = profile._enable_auto_exposure;
+    }
+}
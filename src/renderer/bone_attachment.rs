@@ -0,0 +1,92 @@
+use nalgebra::{ Matrix4, Vector3 };
+use serde::{ Serialize, Deserialize };
+
+use crate::renderer::render_object::RenderObjectData;
+use crate::utilities::math;
+use crate::utilities::system::{ RcRefCell, WeakRefCell, intoWeakRefCell };
+
+// "_bone_attachment" block of the effect create info of the project.
+// ex) "_bone_attachment": { "_bone_name": "mixamorig:RightHand", "_local_position": [0.0, 0.1, 0.0] }
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct BoneAttachmentCreateInfo {
+    pub _bone_name: String, // empty: the root of the render object
+    pub _local_position: Vector3<f32>, // the offset in the bind pose space of the mesh
+    pub _local_rotation: Vector3<f32>, // radian, pitch yaw roll
+    pub _die_with_parent: bool, // false: the effect stays at the last world matrix when the render object is removed
+}
+
+// the emitter of the project holds this and uploads get_world_matrix as its emitter transform every frame.
+#[derive(Clone, Debug)]
+pub struct BoneAttachment {
+    pub _parent_object: WeakRefCell<RenderObjectData>,
+    pub _bone_name: String,
+    pub _bone_index: Option<usize>,
+    pub _local_matrix: Matrix4<f32>,
+    pub _die_with_parent: bool,
+    pub _is_detached: bool,
+    pub _world_matrix: Matrix4<f32>,
+}
+
+impl Default for BoneAttachmentCreateInfo {
+    fn default() -> BoneAttachmentCreateInfo {
+        BoneAttachmentCreateInfo {
+            _bone_name: String::new(),
+            _local_position: Vector3::zeros(),
+            _local_rotation: Vector3::zeros(),
+            _die_with_parent: true,
+        }
+    }
+}
+
+impl BoneAttachment {
+    pub fn create_bone_attachment(parent_object: &RcRefCell<RenderObjectData>, create_info: &BoneAttachmentCreateInfo) -> BoneAttachment {
+        let bone_index = if create_info._bone_name.is_empty() {
+            None
+        } else {
+            let bone_index = parent_object.borrow().find_bone_index(&create_info._bone_name);
+            if bone_index.is_none() {
+                log::warn!("create_bone_attachment: not found bone {:?} of {:?}, attached to the root", create_info._bone_name, parent_object.borrow()._render_object_name);
+            }
+            bone_index
+        };
+        let mut bone_attachment = BoneAttachment {
+            _parent_object: intoWeakRefCell(parent_object),
+            _bone_name: create_info._bone_name.clone(),
+            _bone_index: bone_index,
+            _local_matrix: math::make_srt_transform(&create_info._local_position, &create_info._local_rotation, &Vector3::new(1.0, 1.0, 1.0)),
+            _die_with_parent: create_info._die_with_parent,
+            _is_detached: false,
+            _world_matrix: Matrix4::identity(),
+        };
+        bone_attachment.update_bone_attachment();
+        bone_attachment
+    }
+
+    pub fn is_detached(&self) -> bool {
+        self._is_detached
+    }
+
+    pub fn get_world_matrix(&self) -> &Matrix4<f32> {
+        &self._world_matrix
+    }
+
+    // call after the animation update of the render objects and before the upload of the emitter transform.
+    // returns false if the effect must be removed with the removed render object.
+    pub fn update_bone_attachment(&mut self) -> bool {
+        if self._is_detached {
+            return false == self._die_with_parent;
+        }
+        match self._parent_object.upgrade() {
+            Some(parent_object) => {
+                self._world_matrix = parent_object.borrow().get_bone_world_matrix(self._bone_index) * &self._local_matrix;
+                true
+            },
+            None => {
+                // keeps the last world matrix
+                self._is_detached = true;
+                false == self._die_with_parent
+            }
+        }
+    }
+}
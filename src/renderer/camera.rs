@@ -77,7 +77,10 @@ pub struct CameraObjectData {
     pub _jitter_delta: Vector2<f32>,
     pub _jitter_frame: i32,
     pub _enable_jitter: bool,
+    pub _is_jitter_active: bool, // of the last update, the jitter is zero without TAA
     pub _updated_projection: bool,
+    pub _reversed_z: bool, // the depth convention of the projection, rebuilt when the cvars are changed
+    pub _infinite_far_plane: bool,
 }
 
 impl CameraObjectData {
@@ -120,7 +123,10 @@ impl CameraObjectData {
             _jitter_delta: Vector2::new(0.0, 0.0),
             _jitter_frame: 0,
             _enable_jitter: camera_create_info.enable_jitter,
+            _is_jitter_active: false,
             _updated_projection: true,
+            _reversed_z: false,
+            _infinite_far_plane: false,
         };
 
         // initialize
@@ -160,7 +166,13 @@ impl CameraObjectData {
     }
 
     pub fn update_projection(&mut self) {
-        self._projection = math::get_clip_space_matrix() * math::perspective(self._aspect, self._fov, self._near, self._far);
+        self._reversed_z = math::is_reversed_z();
+        self._infinite_far_plane = math::is_infinite_far_plane();
+        self._projection = if self._infinite_far_plane {
+            math::perspective_infinite_reversed(self._aspect, self._fov, self._near)
+        } else {
+            math::get_clip_space_matrix() * math::perspective(self._aspect, self._fov, self._near, self._far)
+        };
         self._projection_jitter.copy_from(&self._projection);
         linalg::try_invert_to(self._projection.into(), &mut self._inv_projection);
         self._inv_projection_jitter.copy_from(&self._inv_projection);
@@ -193,8 +205,20 @@ impl CameraObjectData {
         self._view_frustum_planes[3] = -self._transform_object.get_left().cross(&self._view_frustum_planes[3].normalize());
     }
 
+    // the jitter of the main viewport follows POST_PROCESS_TAA, see RendererData::set_anti_aliasing_mode
+    pub fn is_jitter_enabled(&self) -> bool {
+        self._enable_jitter && unsafe { constants::POST_PROCESS_TAA }
+    }
+
     pub fn update_camera_object_data(&mut self) {
-        if self._enable_jitter {
+        if self._reversed_z != math::is_reversed_z() || self._infinite_far_plane != math::is_infinite_far_plane() {
+            self.update_projection();
+        }
+
+        let is_jitter_active = self.is_jitter_enabled();
+        let is_jitter_changed = is_jitter_active != self._is_jitter_active;
+        self._is_jitter_active = is_jitter_active;
+        if is_jitter_active {
             self._jitter_frame = (self._jitter_frame + 1) % self._jitter_mode_hammersley16x.len() as i32;
             // offset of camera projection matrix. NDC Space -1.0 ~ 1.0
             self._jitter_prev = self._jitter.into();
@@ -203,6 +227,11 @@ impl CameraObjectData {
             self._jitter[1] /= self._window_size.y as f32;
             // Multiplies by 0.5 because it is in screen coordinate system. 0.0 ~ 1.0
             self._jitter_delta = (&self._jitter - &self._jitter_prev) * 0.5;
+        } else {
+            // the motion vectors and the reprojection of FXAA or no anti aliasing must not see the jitter of the last TAA frame
+            self._jitter = Vector2::zeros();
+            self._jitter_prev = Vector2::zeros();
+            self._jitter_delta = Vector2::zeros();
         }
 
         // copy prev matrices
@@ -231,14 +260,16 @@ impl CameraObjectData {
         }
 
         // Update projection jitter
-        if self._enable_jitter {
+        if is_jitter_active {
             self._projection_jitter.column_mut(2)[0] = -self._jitter[0];
             self._projection_jitter.column_mut(2)[1] = -self._jitter[1];
             linalg::try_invert_to(self._projection_jitter.into(), &mut self._inv_projection_jitter);
             self._view_projection_jitter = &self._projection_jitter * &self._view;
             self._view_origin_projection_jitter = &self._projection_jitter * &self._view_origin;
             linalg::try_invert_to(self._view_origin_projection_jitter.into(), &mut self._inv_view_origin_projection_jitter);
-        } else if updated {
+        } else if updated || is_jitter_changed {
+            self._projection_jitter.copy_from(&self._projection);
+            self._inv_projection_jitter.copy_from(&self._inv_projection);
             self._view_projection_jitter = self._view_projection.into();
             self._view_origin_projection_jitter = self._view_origin_projection.into();
             self._inv_view_origin_projection_jitter = self._inv_view_origin_projection.into();
@@ -0,0 +1,59 @@
+use crate::vulkan_context::texture::TextureData;
+
+// the render target shown by the debug view of the project renderer.
+// the candidates: ProjectRendererBase::get_render_target_names in the declaration order, then the dynamic render targets.
+// '[' and ']' cycle the render targets, with the shift key the mip levels.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct DebugRenderTarget {
+    pub _is_enabled: bool,
+    pub _render_target_name: String,
+    pub _layer: u32,
+    pub _mip_level: u32,
+}
+
+impl DebugRenderTarget {
+    // offset: 1 is the next, -1 is the previous. a name which no longer exists restarts from the first.
+    pub fn cycle_render_target(&mut self, render_target_names: &[String], offset: i32) {
+        if render_target_names.is_empty() {
+            self._is_enabled = false;
+            return;
+        }
+        let count = render_target_names.len() as i32;
+        let index = match render_target_names.iter().position(|render_target_name| *render_target_name == self._render_target_name) {
+            Some(index) if self._is_enabled => (index as i32 + offset).rem_euclid(count),
+            _ => if offset < 0 { count - 1 } else { 0 },
+        };
+        self._is_enabled = true;
+        self._render_target_name = render_target_names[index as usize].clone();
+        self._layer = 0;
+        self._mip_level = 0;
+    }
+
+    pub fn cycle_mip_level(&mut self, texture_data: &TextureData, offset: i32) {
+        let mip_levels = texture_data._image_mip_levels.max(1) as i32;
+        self._mip_level = (self._mip_level as i32 + offset).rem_euclid(mip_levels) as u32;
+    }
+
+    // after a swapchain recreate the target may have the fewer layers or mip levels.
+    pub fn clamp_to_render_target(&mut self, texture_data: &TextureData) {
+        self._layer = self._layer.min(texture_data._image_layers.max(1) - 1);
+        self._mip_level = self._mip_level.min(texture_data._image_mip_levels.max(1) - 1);
+    }
+
+    pub fn get_overlay_text(&self, texture_data: &TextureData) -> String {
+        let (mip_width, mip_height) = texture_data.get_image_size(self._mip_level);
+        format!(
+            "debug render target: {} {}x{} {:?}, layer {}/{}, mip {}/{} ({}x{})",
+            self._render_target_name,
+            texture_data._image_width,
+            texture_data._image_height,
+            texture_data._image_format,
+            self._layer,
+            texture_data._image_layers,
+            self._mip_level,
+            texture_data._image_mip_levels,
+            mip_width,
+            mip_height,
+        )
+    }
+}
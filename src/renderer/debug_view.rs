@@ -0,0 +1,98 @@
+use std::fs;
+use std::path::PathBuf;
+
+use crate::vulkan_context::shader::SHADER_DIRECTORY;
+
+// the opaque passes of the project which get the debug view variants of the material instance pipelines
+pub const DEBUG_VIEW_RENDER_PASS_NAMES: [&str; 2] = ["render_pass_static_opaque", "render_pass_skeletal_opaque"];
+pub const DEBUG_VIEW_WIREFRAME_NAME: &str = "wireframe"; // vk::PolygonMode::LINE, needs fillModeNonSolid
+pub const DEBUG_VIEW_VISUALIZE_NAME: &str = "visualize"; // compiled with DEBUG_VIEW_VISUALIZE_DEFINE
+pub const DEBUG_VIEW_VISUALIZE_DEFINE: &str = "DEBUG_VIEW_VISUALIZE=1";
+pub const DEBUG_VIEW_SHADER_FILE: &str = "common/debug_view.glsl";
+pub const DEBUG_VIEW_SHADER_SOURCE: &str = r#"// generated by the engine, debug_view.rs - DEBUG_VIEW_SHADER_SOURCE
+#ifndef DEBUG_VIEW_GLSL
+#define DEBUG_VIEW_GLSL
+
+// must match with DebugViewMode, the mode is PushConstant_RenderInstanced::debug_view_mode on the instanced path
+#define DEBUG_VIEW_MODE_NONE 0
+#define DEBUG_VIEW_MODE_WIREFRAME 1
+#define DEBUG_VIEW_MODE_WORLD_NORMAL 2
+#define DEBUG_VIEW_MODE_UV 3
+#define DEBUG_VIEW_MODE_VERTEX_COLOR 4
+
+// the opaque fragment shaders of the project write this as the unlit color under #if DEBUG_VIEW_VISUALIZE
+vec3 get_debug_view_color(uint debug_view_mode, vec3 world_normal, vec2 texcoord, vec4 vertex_color)
+{
+    if(DEBUG_VIEW_MODE_WORLD_NORMAL == debug_view_mode)
+    {
+        return normalize(world_normal) * 0.5 + 0.5;
+    }
+    else if(DEBUG_VIEW_MODE_UV == debug_view_mode)
+    {
+        return vec3(fract(texcoord), 0.0);
+    }
+    else if(DEBUG_VIEW_MODE_VERTEX_COLOR == debug_view_mode)
+    {
+        return vertex_color.xyz;
+    }
+    return vec3(1.0);
+}
+
+#endif // DEBUG_VIEW_GLSL
+"#;
+
+#[derive(Clone, Debug, Copy, PartialEq, Eq)]
+pub enum DebugViewMode {
+    None = 0,
+    Wireframe = 1,
+    WorldNormal = 2,
+    UV = 3,
+    VertexColor = 4,
+}
+
+impl Default for DebugViewMode {
+    fn default() -> DebugViewMode {
+        DebugViewMode::None
+    }
+}
+
+impl DebugViewMode {
+    pub fn next(&self) -> DebugViewMode {
+        match self {
+            DebugViewMode::None => DebugViewMode::Wireframe,
+            DebugViewMode::Wireframe => DebugViewMode::WorldNormal,
+            DebugViewMode::WorldNormal => DebugViewMode::UV,
+            DebugViewMode::UV => DebugViewMode::VertexColor,
+            DebugViewMode::VertexColor => DebugViewMode::None,
+        }
+    }
+
+    // the pipeline variant of the mode, None draws with the pipeline of the material instance
+    pub fn get_debug_view_name(&self) -> Option<&'static str> {
+        match self {
+            DebugViewMode::None => None,
+            DebugViewMode::Wireframe => Some(DEBUG_VIEW_WIREFRAME_NAME),
+            _ => Some(DEBUG_VIEW_VISUALIZE_NAME),
+        }
+    }
+}
+
+pub fn is_debug_view_render_pass(render_pass_data_name: &str) -> bool {
+    DEBUG_VIEW_RENDER_PASS_NAMES.contains(&render_pass_data_name)
+}
+
+// ex) render_pass_static_opaque/render_object/wireframe, the key of the pipeline binding data in the material instance
+pub fn get_debug_view_render_pass_pipeline_data_name(render_pass_pipeline_data_name: &str, debug_view_name: &str) -> String {
+    format!("{}/{}", render_pass_pipeline_data_name, debug_view_name)
+}
+
+#[cfg(not(target_os = "android"))]
+pub fn generate_debug_view_shader() {
+    let mut shader_file_path = PathBuf::from(SHADER_DIRECTORY);
+    shader_file_path.push(DEBUG_VIEW_SHADER_FILE);
+    if false == shader_file_path.is_file() {
+        fs::create_dir_all(shader_file_path.parent().unwrap()).expect("Failed to create directories.");
+        fs::write(&shader_file_path, DEBUG_VIEW_SHADER_SOURCE).expect("Failed to write");
+        log::info!("generate_debug_view_shader: {:?}", shader_file_path);
+    }
+}
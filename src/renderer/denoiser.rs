@@ -0,0 +1,647 @@
+use std::fs;
+use std::path::PathBuf;
+
+use ash::vk;
+use ash::version::DeviceV1_0;
+use nalgebra::Vector2;
+
+use crate::constants;
+use crate::renderer::history_confidence;
+use crate::renderer::renderer::RendererData;
+use crate::resource::resource::Resources;
+use crate::utilities::math;
+use crate::vulkan_context::descriptor::{ self, DescriptorDataCreateInfo, DescriptorResourceInfo, DescriptorResourceType };
+use crate::vulkan_context::render_pass::{ PipelineDataCreateInfo, RenderPassDataCreateInfo };
+use crate::vulkan_context::shader::SHADER_DIRECTORY;
+use crate::vulkan_context::texture::{ TextureCreateInfo, TextureData };
+use crate::vulkan_context::vulkan_context::SwapchainArray;
+
+pub const DENOISER_WORK_GROUP_SIZE: u32 = 8; // must match with denoiser.comp
+pub const DENOISER_MODE_TEMPORAL: u32 = 0; // must match with denoiser.comp
+pub const DENOISER_MODE_ATROUS: u32 = 1; // must match with denoiser.comp
+pub const DENOISER_MODE_COPY: u32 = 2; // must match with denoiser.comp
+pub const DENOISER_FLAG_HISTORY_VALID: u32 = 1 << 0; // must match with denoiser.comp
+pub const DENOISER_FLAG_NORMAL: u32 = 1 << 1; // must match with denoiser.comp
+pub const DENOISER_FLAG_CONFIDENCE: u32 = 1 << 2; // must match with denoiser.comp
+pub const DENOISER_MAX_ITERATIONS: u32 = 5;
+pub const DENOISER_RENDER_PASS_NAME: &str = "denoiser";
+pub const DENOISER_SHADER_FILE: &str = "common/denoiser.comp";
+pub const DENOISER_SHADER_SOURCE: &str = r#"#version 450
+
+#define DENOISER_WORK_GROUP_SIZE 8
+#define DENOISER_MODE_TEMPORAL 0
+#define DENOISER_MODE_ATROUS 1
+#define DENOISER_MODE_COPY 2
+#define DENOISER_FLAG_HISTORY_VALID 1
+#define DENOISER_FLAG_NORMAL 2
+#define DENOISER_FLAG_CONFIDENCE 4
+
+layout(local_size_x = DENOISER_WORK_GROUP_SIZE, local_size_y = DENOISER_WORK_GROUP_SIZE, local_size_z = 1) in;
+
+layout(binding = 0) uniform sampler2D texture_input;
+layout(binding = 1) uniform sampler2D texture_depth;
+layout(binding = 2) uniform sampler2D texture_normal; // [0, 1] encoded world normal
+layout(binding = 3) uniform sampler2D texture_history_confidence;
+layout(binding = 4) uniform sampler2D texture_history;
+// the format of the output is the format of the effect, written without the format qualifier
+layout(binding = 5) uniform writeonly image2D image_output;
+
+layout(push_constant) uniform PushConstant_Denoiser
+{
+    ivec2 image_size;
+    uint mode;
+    int step_size;
+    float sigma_depth;
+    float sigma_normal;
+    float sigma_value;
+    float temporal_weight;
+    uint flags;
+    uint reserved0;
+    vec2 linear_depth_params; // math::get_linear_depth_params
+} pushConstant;
+
+const float kernel_weights[3] = float[3](3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0);
+
+float get_luminance(vec4 value)
+{
+    return dot(value.xyz, vec3(0.2126, 0.7152, 0.0722)) + value.w;
+}
+
+float get_linear_depth(ivec2 pixel)
+{
+    float depth = texelFetch(texture_depth, pixel, 0).x;
+    return 1.0 / max(depth * pushConstant.linear_depth_params.x + pushConstant.linear_depth_params.y, 0.000001);
+}
+
+vec3 get_normal(ivec2 pixel)
+{
+    return normalize(texelFetch(texture_normal, pixel, 0).xyz * 2.0 - 1.0);
+}
+
+void main()
+{
+    ivec2 pixel = ivec2(gl_GlobalInvocationID.xy);
+    if(any(greaterThanEqual(pixel, pushConstant.image_size)))
+    {
+        return;
+    }
+
+    vec4 center_value = texelFetch(texture_input, pixel, 0);
+    if(DENOISER_MODE_COPY == pushConstant.mode)
+    {
+        imageStore(image_output, pixel, center_value);
+        return;
+    }
+
+    if(DENOISER_MODE_TEMPORAL == pushConstant.mode)
+    {
+        float history_weight = 0.0;
+        if(0u != (pushConstant.flags & DENOISER_FLAG_HISTORY_VALID))
+        {
+            history_weight = pushConstant.temporal_weight;
+            if(0u != (pushConstant.flags & DENOISER_FLAG_CONFIDENCE))
+            {
+                history_weight *= texelFetch(texture_history_confidence, pixel, 0).x;
+            }
+        }
+        imageStore(image_output, pixel, mix(center_value, texelFetch(texture_history, pixel, 0), history_weight));
+        return;
+    }
+
+    // a-trous: 5x5 B3 spline taps spread by the step size, the depth, the normal and the value stop the weights at the edges
+    float center_depth = get_linear_depth(pixel);
+    vec3 center_normal = (0u != (pushConstant.flags & DENOISER_FLAG_NORMAL)) ? get_normal(pixel) : vec3(0.0);
+    float center_luminance = get_luminance(center_value);
+    vec4 sum_value = vec4(0.0);
+    float sum_weight = 0.0;
+    for(int y = -2; y <= 2; ++y)
+    {
+        for(int x = -2; x <= 2; ++x)
+        {
+            ivec2 sample_pixel = clamp(pixel + ivec2(x, y) * pushConstant.step_size, ivec2(0), pushConstant.image_size - 1);
+            vec4 sample_value = texelFetch(texture_input, sample_pixel, 0);
+            float weight = kernel_weights[abs(x)] * kernel_weights[abs(y)];
+            float sample_depth = get_linear_depth(sample_pixel);
+            float depth_error = abs(sample_depth - center_depth) / max(abs(center_depth), 0.000001);
+            weight *= exp(-depth_error / max(pushConstant.sigma_depth, 0.000001));
+            if(0u != (pushConstant.flags & DENOISER_FLAG_NORMAL))
+            {
+                weight *= pow(max(0.0, dot(center_normal, get_normal(sample_pixel))), pushConstant.sigma_normal);
+            }
+            if(0.0 < pushConstant.sigma_value)
+            {
+                weight *= exp(-abs(get_luminance(sample_value) - center_luminance) / pushConstant.sigma_value);
+            }
+            sum_value += sample_value * weight;
+            sum_weight += weight;
+        }
+    }
+    imageStore(image_output, pixel, (0.0 < sum_weight) ? (sum_value / sum_weight) : center_value);
+}
+"#;
+
+// must match with denoiser.comp
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Default)]
+#[repr(C)]
+pub struct PushConstant_Denoiser {
+    pub _image_size: Vector2<i32>,
+    pub _mode: u32,
+    pub _step_size: i32,
+    pub _sigma_depth: f32,
+    pub _sigma_normal: f32,
+    pub _sigma_value: f32,
+    pub _temporal_weight: f32,
+    pub _flags: u32,
+    pub _reserved0: u32,
+    pub _linear_depth_params: Vector2<f32>,
+}
+
+crate::impl_push_constant!(PushConstant_Denoiser, 48);
+
+// the render targets are the names of RendererData::get_render_target_from_str, the input and the output must differ.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DenoiserCreateInfo {
+    pub _denoiser_name: String,
+    pub _input_target_name: String,
+    pub _output_target_name: String,
+    pub _depth_target_name: String,
+    pub _normal_target_name: String, // empty: no normal edge stopping
+    pub _iterations: u32, // a-trous passes, the step size doubles every pass
+    pub _sigma_depth: f32, // relative depth error
+    pub _sigma_normal: f32, // power of the normal dot product
+    pub _sigma_value: f32, // luminance difference, 0.0: no value edge stopping
+    pub _temporal_weight: f32, // the history weight of the temporal pre-accumulation, 0.0: no temporal pass
+    pub _use_history_confidence: bool, // history_confidence::HISTORY_CONFIDENCE_TEXTURE_NAME scales the history weight
+}
+
+impl Default for DenoiserCreateInfo {
+    fn default() -> DenoiserCreateInfo {
+        DenoiserCreateInfo {
+            _denoiser_name: String::new(),
+            _input_target_name: String::new(),
+            _output_target_name: String::new(),
+            _depth_target_name: String::from("SceneDepth"),
+            _normal_target_name: String::from("SceneNormal"),
+            _iterations: 3,
+            _sigma_depth: 0.02,
+            _sigma_normal: 32.0,
+            _sigma_value: 0.0,
+            _temporal_weight: 0.0,
+            _use_history_confidence: true,
+        }
+    }
+}
+
+// ex) DenoiserInstanceBuilder::new("ssao", "SSAO", "SSAOTemp").with_iterations(2).build()
+pub struct DenoiserInstanceBuilder {
+    _create_info: DenoiserCreateInfo,
+}
+
+impl DenoiserInstanceBuilder {
+    pub fn new(denoiser_name: &str, input_target_name: &str, output_target_name: &str) -> DenoiserInstanceBuilder {
+        DenoiserInstanceBuilder {
+            _create_info: DenoiserCreateInfo {
+                _denoiser_name: String::from(denoiser_name),
+                _input_target_name: String::from(input_target_name),
+                _output_target_name: String::from(output_target_name),
+                ..Default::default()
+            }
+        }
+    }
+
+    pub fn with_guide_targets(mut self, depth_target_name: &str, normal_target_name: &str) -> DenoiserInstanceBuilder {
+        self._create_info._depth_target_name = String::from(depth_target_name);
+        self._create_info._normal_target_name = String::from(normal_target_name);
+        self
+    }
+
+    pub fn with_iterations(mut self, iterations: u32) -> DenoiserInstanceBuilder {
+        self._create_info._iterations = iterations.min(DENOISER_MAX_ITERATIONS);
+        self
+    }
+
+    pub fn with_sigmas(mut self, sigma_depth: f32, sigma_normal: f32, sigma_value: f32) -> DenoiserInstanceBuilder {
+        self._create_info._sigma_depth = sigma_depth;
+        self._create_info._sigma_normal = sigma_normal;
+        self._create_info._sigma_value = sigma_value;
+        self
+    }
+
+    pub fn with_temporal(mut self, temporal_weight: f32, use_history_confidence: bool) -> DenoiserInstanceBuilder {
+        self._create_info._temporal_weight = temporal_weight.max(0.0).min(1.0);
+        self._create_info._use_history_confidence = use_history_confidence;
+        self
+    }
+
+    pub fn build(self) -> Result<DenoiserInstance, String> {
+        let mut create_info = self._create_info;
+        // the confidence only scales the history of the temporal pass
+        create_info._use_history_confidence &= is_temporal_denoiser(&create_info);
+        if create_info._input_target_name == create_info._output_target_name {
+            return Err(format!("denoiser {}: the input and the output are the same {}", create_info._denoiser_name, create_info._input_target_name));
+        }
+        if create_info._depth_target_name.is_empty() {
+            return Err(format!("denoiser {}: no depth target", create_info._denoiser_name));
+        }
+        Ok(DenoiserInstance {
+            _create_info: create_info,
+            ..Default::default()
+        })
+    }
+}
+
+pub fn is_temporal_denoiser(create_info: &DenoiserCreateInfo) -> bool {
+    0.0 < create_info._temporal_weight
+}
+
+pub fn get_render_pass_data_create_info() -> RenderPassDataCreateInfo {
+    let render_target = |binding_index: u32, descriptor_name: &str, descriptor_resource_type: DescriptorResourceType| DescriptorDataCreateInfo {
+        _descriptor_binding_index: binding_index,
+        _descriptor_name: String::from(descriptor_name),
+        _descriptor_resource_type: descriptor_resource_type,
+        _descriptor_shader_stage: vk::ShaderStageFlags::COMPUTE,
+        ..Default::default()
+    };
+    let pipeline_data_create_infos = vec![
+        PipelineDataCreateInfo {
+            _pipeline_data_create_info_name: String::from(DENOISER_RENDER_PASS_NAME),
+            _pipeline_bind_point: vk::PipelineBindPoint::COMPUTE,
+            _pipeline_compute_shader_file: PathBuf::from(DENOISER_SHADER_FILE),
+            _push_constant_ranges: vec![vk::PushConstantRange {
+                stage_flags: vk::ShaderStageFlags::COMPUTE,
+                offset: 0,
+                size: std::mem::size_of::<PushConstant_Denoiser>() as u32,
+            }],
+            _descriptor_data_create_infos: vec![
+                render_target(0, "texture_input", DescriptorResourceType::RenderTarget),
+                render_target(1, "texture_depth", DescriptorResourceType::RenderTarget),
+                render_target(2, "texture_normal", DescriptorResourceType::RenderTarget),
+                render_target(3, history_confidence::HISTORY_CONFIDENCE_TEXTURE_NAME, DescriptorResourceType::RenderTarget),
+                render_target(4, "texture_history", DescriptorResourceType::RenderTarget),
+                render_target(5, "image_output", DescriptorResourceType::StorageRenderTarget),
+            ],
+            ..Default::default()
+        }
+    ];
+
+    RenderPassDataCreateInfo {
+        _render_pass_create_info_name: String::from(DENOISER_RENDER_PASS_NAME),
+        _pipeline_data_create_infos: pipeline_data_create_infos,
+        ..Default::default()
+    }
+}
+
+#[cfg(not(target_os = "android"))]
+pub fn generate_denoiser_shader() {
+    let mut shader_file_path = PathBuf::from(SHADER_DIRECTORY);
+    shader_file_path.push(DENOISER_SHADER_FILE);
+    if false == shader_file_path.is_file() {
+        fs::create_dir_all(shader_file_path.parent().unwrap()).expect("Failed to create directories.");
+        fs::write(&shader_file_path, DENOISER_SHADER_SOURCE).expect("Failed to write");
+        log::info!("generate_denoiser_shader: {:?}", shader_file_path);
+    }
+}
+
+#[derive(Clone, Debug, Copy, PartialEq, Eq)]
+pub enum DenoiserTarget {
+    Input,
+    Output,
+    Temp,
+    History,
+}
+
+// a dispatch of the denoiser, the output of a step is the input of the next step
+#[derive(Clone, Debug, Copy, PartialEq)]
+pub struct DenoiserStep {
+    pub _mode: u32,
+    pub _step_size: i32,
+    pub _source: DenoiserTarget,
+    pub _destination: DenoiserTarget,
+}
+
+// temporal -> a-trous ping-pong between the temp target and the output, the last pass writes the output -> the copy into the history.
+pub fn get_denoiser_steps(create_info: &DenoiserCreateInfo) -> Vec<DenoiserStep> {
+    let mut steps: Vec<DenoiserStep> = Vec::new();
+    let iterations = create_info._iterations.min(DENOISER_MAX_ITERATIONS);
+    let get_atrous_destination = |iteration: u32| if 0 == (iterations - 1 - iteration) % 2 { DenoiserTarget::Output } else { DenoiserTarget::Temp };
+    let mut source = DenoiserTarget::Input;
+    if is_temporal_denoiser(create_info) {
+        let destination = if 0 == iterations {
+            DenoiserTarget::Output
+        } else if DenoiserTarget::Output == get_atrous_destination(0) {
+            DenoiserTarget::Temp
+        } else {
+            DenoiserTarget::Output
+        };
+        steps.push(DenoiserStep { _mode: DENOISER_MODE_TEMPORAL, _step_size: 1, _source: source, _destination: destination });
+        source = destination;
+    }
+    for iteration in 0..iterations {
+        let destination = get_atrous_destination(iteration);
+        steps.push(DenoiserStep { _mode: DENOISER_MODE_ATROUS, _step_size: 1 << iteration, _source: source, _destination: destination });
+        source = destination;
+    }
+    if steps.is_empty() {
+        steps.push(DenoiserStep { _mode: DENOISER_MODE_COPY, _step_size: 1, _source: DenoiserTarget::Input, _destination: DenoiserTarget::Output });
+    }
+    if is_temporal_denoiser(create_info) {
+        steps.push(DenoiserStep { _mode: DENOISER_MODE_COPY, _step_size: 1, _source: DenoiserTarget::Output, _destination: DenoiserTarget::History });
+    }
+    steps
+}
+
+// an edge-aware denoise of an effect, ex) SSAO, SSR resolve, volumetrics. the temp and the history targets are owned by the instance
+// and created with the size and the format of the output at the first dispatch.
+#[derive(Default)]
+pub struct DenoiserInstance {
+    pub _create_info: DenoiserCreateInfo,
+    pub _temp_texture: Option<TextureData>,
+    pub _history_texture: Option<TextureData>,
+    pub _step_descriptor_sets: Vec<SwapchainArray<vk::DescriptorSet>>, // per step of get_denoiser_steps, created on demand
+    pub _is_history_valid: bool,
+}
+
+impl DenoiserInstance {
+    pub fn get_denoiser_name(&self) -> &str {
+        &self._create_info._denoiser_name
+    }
+
+    fn create_denoiser_textures(&mut self, renderer_data: &RendererData) {
+        let output_target = renderer_data.get_render_target_from_str(&self._create_info._output_target_name);
+        let create_info = |name: String| -> TextureCreateInfo<u8> {
+            TextureCreateInfo {
+                _texture_name: name,
+                _texture_width: output_target._image_width,
+                _texture_height: output_target._image_height,
+                _texture_format: output_target._image_format,
+                _texture_min_filter: vk::Filter::NEAREST,
+                _texture_mag_filter: vk::Filter::NEAREST,
+                _texture_wrap_mode: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                _enable_mipmap: false,
+                ..Default::default()
+            }
+        };
+        let denoiser_name = &self._create_info._denoiser_name;
+        self._temp_texture = Some(renderer_data.create_render_target::<u8>(&create_info(format!("{}/DenoiserTemp", denoiser_name))));
+        if is_temporal_denoiser(&self._create_info) {
+            self._history_texture = Some(renderer_data.create_render_target::<u8>(&create_info(format!("{}/DenoiserHistory", denoiser_name))));
+        }
+        self._is_history_valid = false;
+    }
+
+    pub fn destroy_denoiser_textures(&mut self, renderer_data: &RendererData) {
+        for texture in [&mut self._temp_texture, &mut self._history_texture].iter_mut() {
+            if let Some(texture_data) = texture.take() {
+                renderer_data.destroy_texture(&texture_data);
+            }
+        }
+        self._step_descriptor_sets.clear();
+        self._is_history_valid = false;
+    }
+
+    pub fn destroy_descriptor_sets(&mut self) {
+        self._step_descriptor_sets.clear();
+    }
+
+    pub fn invalidate_history(&mut self) {
+        self._is_history_valid = false;
+    }
+
+    fn get_target<'a>(&'a self, renderer_data: &'a RendererData, target: DenoiserTarget) -> &'a TextureData {
+        match target {
+            DenoiserTarget::Input => renderer_data.get_render_target_from_str(&self._create_info._input_target_name),
+            DenoiserTarget::Output => renderer_data.get_render_target_from_str(&self._create_info._output_target_name),
+            DenoiserTarget::Temp => self._temp_texture.as_ref().unwrap(),
+            DenoiserTarget::History => self._history_texture.as_ref().unwrap(),
+        }
+    }
+
+    fn create_step_descriptor_sets(&self, renderer_data: &RendererData, resources: &Resources, steps: &[DenoiserStep]) -> Vec<SwapchainArray<vk::DescriptorSet>> {
+        let render_pass_pipeline_data = resources.get_render_pass_pipeline_data(DENOISER_RENDER_PASS_NAME, DENOISER_RENDER_PASS_NAME);
+        let pipeline_data = render_pass_pipeline_data._pipeline_data.borrow();
+        let create_info = &self._create_info;
+        let depth_target = renderer_data.get_render_target_from_str(&create_info._depth_target_name);
+        // the unused bindings are bound to the input
+        let input_target = self.get_target(renderer_data, DenoiserTarget::Input);
+        let normal_target = if create_info._normal_target_name.is_empty() { input_target } else { renderer_data.get_render_target_from_str(&create_info._normal_target_name) };
+        let confidence_target = if create_info._use_history_confidence { renderer_data.get_render_target_from_str(history_confidence::HISTORY_CONFIDENCE_TEXTURE_NAME) } else { input_target };
+        let history_target = self._history_texture.as_ref().unwrap_or(input_target);
+        let descriptor_data = &pipeline_data._descriptor_data;
+        let descriptor_binding_indices: Vec<u32> = descriptor_data._descriptor_data_create_infos.iter().map(|descriptor_data_create_info| {
+            descriptor_data_create_info._descriptor_binding_index
+        }).collect();
+        steps.iter().enumerate().map(|(step_index, step)| {
+            let source_target = self.get_target(renderer_data, step._source);
+            let destination_target = self.get_target(renderer_data, step._destination);
+            let descriptor_resource_infos_list: SwapchainArray<Vec<DescriptorResourceInfo>> = constants::SWAPCHAIN_IMAGE_INDICES.iter().map(|_swapchain_index| {
+                vec![
+                    DescriptorResourceInfo::DescriptorImageInfo(source_target.get_default_image_info().clone()),
+                    DescriptorResourceInfo::DescriptorImageInfo(depth_target.get_default_image_info().clone()),
+                    DescriptorResourceInfo::DescriptorImageInfo(normal_target.get_default_image_info().clone()),
+                    DescriptorResourceInfo::DescriptorImageInfo(confidence_target.get_default_image_info().clone()),
+                    DescriptorResourceInfo::DescriptorImageInfo(history_target.get_default_image_info().clone()),
+                    DescriptorResourceInfo::DescriptorImageInfo(destination_target.get_default_image_info().clone()),
+                ]
+            }).collect();
+            let descriptor_sets_name = format!("{}/{}/{}", DENOISER_RENDER_PASS_NAME, create_info._denoiser_name, step_index);
+            let descriptor_sets = descriptor::create_descriptor_sets(renderer_data.get_device(), descriptor_data, &descriptor_sets_name);
+            let _write_descriptor_sets = descriptor::create_write_descriptor_sets_with_update(
+                renderer_data.get_device(),
+                &descriptor_sets,
+                &descriptor_binding_indices,
+                &descriptor_data._descriptor_set_layout_bindings,
+                &descriptor_resource_infos_list,
+            );
+            descriptor_sets
+        }).collect()
+    }
+
+    pub fn dispatch_denoiser(&mut self, command_buffer: vk::CommandBuffer, swapchain_index: u32, renderer_data: &RendererData, resources: &Resources) {
+        if false == resources.has_render_pass_data(DENOISER_RENDER_PASS_NAME) {
+            return;
+        }
+        if self._temp_texture.is_none() {
+            self.create_denoiser_textures(renderer_data);
+        }
+        let steps = get_denoiser_steps(&self._create_info);
+        if self._step_descriptor_sets.len() != steps.len() {
+            self._step_descriptor_sets = self.create_step_descriptor_sets(renderer_data, resources, &steps);
+        }
+        let output_target = renderer_data.get_render_target_from_str(&self._create_info._output_target_name);
+        let (image_width, image_height) = (output_target._image_width, output_target._image_height);
+        let mut flags: u32 = 0;
+        if self._is_history_valid { flags |= DENOISER_FLAG_HISTORY_VALID; }
+        if false == self._create_info._normal_target_name.is_empty() { flags |= DENOISER_FLAG_NORMAL; }
+        if self._create_info._use_history_confidence { flags |= DENOISER_FLAG_CONFIDENCE; }
+        let render_pass_pipeline_data = resources.get_render_pass_pipeline_data(DENOISER_RENDER_PASS_NAME, DENOISER_RENDER_PASS_NAME);
+        let pipeline_data = render_pass_pipeline_data._pipeline_data.borrow();
+        // every step reads the whole output of the previous step
+        let step_barrier = [vk::MemoryBarrier {
+            src_access_mask: vk::AccessFlags::SHADER_WRITE,
+            dst_access_mask: vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE,
+            ..Default::default()
+        }];
+
+        renderer_data.begin_debug_label(command_buffer, &format!("{}/{}", DENOISER_RENDER_PASS_NAME, self._create_info._denoiser_name));
+        renderer_data.pipeline_barrier(command_buffer, vk::PipelineStageFlags::ALL_GRAPHICS | vk::PipelineStageFlags::COMPUTE_SHADER, vk::PipelineStageFlags::COMPUTE_SHADER, vk::DependencyFlags::empty(), &[vk::MemoryBarrier {
+            src_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE | vk::AccessFlags::SHADER_WRITE,
+            ..step_barrier[0]
+        }], &[], &[]);
+        renderer_data.begin_compute_pipeline(command_buffer, &pipeline_data);
+        for (step_index, step) in steps.iter().enumerate() {
+            if 0 < step_index {
+                renderer_data.pipeline_barrier(command_buffer, vk::PipelineStageFlags::COMPUTE_SHADER, vk::PipelineStageFlags::COMPUTE_SHADER, vk::DependencyFlags::empty(), &step_barrier, &[], &[]);
+            }
+            unsafe {
+                renderer_data.get_device().cmd_bind_descriptor_sets(
+                    command_buffer,
+                    pipeline_data._pipeline_bind_point,
+                    pipeline_data._pipeline_layout,
+                    0,
+                    &[self._step_descriptor_sets[step_index][swapchain_index as usize]],
+                    &[]
+                );
+            }
+            let push_constant_data = PushConstant_Denoiser {
+                _image_size: Vector2::new(image_width as i32, image_height as i32),
+                _mode: step._mode,
+                _step_size: step._step_size,
+                _sigma_depth: self._create_info._sigma_depth,
+                _sigma_normal: self._create_info._sigma_normal,
+                _sigma_value: self._create_info._sigma_value,
+                _temporal_weight: self._create_info._temporal_weight,
+                _flags: flags,
+                _linear_depth_params: unsafe { math::get_linear_depth_params(constants::NEAR, constants::FAR) },
+                ..Default::default()
+            };
+            renderer_data.upload_push_constant_data(command_buffer, &pipeline_data, &push_constant_data);
+            renderer_data.dispatch_compute_pipeline(
+                command_buffer,
+                (image_width + DENOISER_WORK_GROUP_SIZE - 1) / DENOISER_WORK_GROUP_SIZE,
+                (image_height + DENOISER_WORK_GROUP_SIZE - 1) / DENOISER_WORK_GROUP_SIZE,
+                1
+            );
+        }
+        renderer_data.end_debug_label(command_buffer);
+
+        // the passes after the denoiser sample the output
+        renderer_data.pipeline_barrier(command_buffer, vk::PipelineStageFlags::COMPUTE_SHADER, vk::PipelineStageFlags::FRAGMENT_SHADER | vk::PipelineStageFlags::COMPUTE_SHADER, vk::DependencyFlags::empty(), &[vk::MemoryBarrier {
+            src_access_mask: vk::AccessFlags::SHADER_WRITE,
+            dst_access_mask: vk::AccessFlags::SHADER_READ,
+            ..Default::default()
+        }], &[], &[]);
+        self._is_history_valid = is_temporal_denoiser(&self._create_info);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IMAGE_WIDTH: i32 = 16;
+    const IMAGE_HEIGHT: i32 = 8;
+    const EDGE_X: i32 = 8; // the near surface on the left, the far surface from here
+    const KERNEL_WEIGHTS: [f32; 3] = [3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+
+    // the linear depth of denoiser.comp
+    fn get_linear_depth(device_depth: f32, linear_depth_params: &Vector2<f32>) -> f32 {
+        1.0 / (device_depth * linear_depth_params.x + linear_depth_params.y).max(0.000001)
+    }
+
+    // the a-trous pass of denoiser.comp on the cpu, a scalar value and the depth edge stopping without the normal
+    fn apply_atrous_step(input: &[f32], linear_depths: &[f32], step_size: i32, create_info: &DenoiserCreateInfo) -> Vec<f32> {
+        let mut output = vec![0.0; input.len()];
+        for y in 0..IMAGE_HEIGHT {
+            for x in 0..IMAGE_WIDTH {
+                let index = (y * IMAGE_WIDTH + x) as usize;
+                let center_depth = linear_depths[index];
+                let mut sum_value = 0.0;
+                let mut sum_weight = 0.0;
+                for tap_y in -2i32..=2 {
+                    for tap_x in -2i32..=2 {
+                        let sample_x = (x + tap_x * step_size).max(0).min(IMAGE_WIDTH - 1);
+                        let sample_y = (y + tap_y * step_size).max(0).min(IMAGE_HEIGHT - 1);
+                        let sample_index = (sample_y * IMAGE_WIDTH + sample_x) as usize;
+                        let mut weight = KERNEL_WEIGHTS[tap_x.abs() as usize] * KERNEL_WEIGHTS[tap_y.abs() as usize];
+                        let depth_error = (linear_depths[sample_index] - center_depth).abs() / center_depth.abs().max(0.000001);
+                        weight *= (-depth_error / create_info._sigma_depth.max(0.000001)).exp();
+                        if 0.0 < create_info._sigma_value {
+                            weight *= (-(input[sample_index] - input[index]).abs() / create_info._sigma_value).exp();
+                        }
+                        sum_value += input[sample_index] * weight;
+                        sum_weight += weight;
+                    }
+                }
+                output[index] = if 0.0 < sum_weight { sum_value / sum_weight } else { input[index] };
+            }
+        }
+        output
+    }
+
+    fn denoise(input: &[f32], linear_depths: &[f32], create_info: &DenoiserCreateInfo) -> Vec<f32> {
+        let mut value = input.to_vec();
+        for step in get_denoiser_steps(create_info).iter() {
+            assert_eq!(DENOISER_MODE_ATROUS, step._mode);
+            value = apply_atrous_step(&value, linear_depths, step._step_size, create_info);
+        }
+        value
+    }
+
+    // the max error of the pixels of a side against the clean value of the side
+    fn get_max_error(value: &[f32], is_near_side: bool, clean_value: f32) -> f32 {
+        let mut max_error: f32 = 0.0;
+        for (index, pixel_value) in value.iter().enumerate() {
+            if is_near_side == ((index as i32 % IMAGE_WIDTH) < EDGE_X) {
+                max_error = max_error.max((pixel_value - clean_value).abs());
+            }
+        }
+        max_error
+    }
+
+    #[test]
+    fn test_depth_edge_without_bleeding() {
+        let (near, far) = (0.1, 1000.0);
+        let (near_distance, far_distance) = (5.0, 50.0);
+        let (near_value, far_value) = (0.2, 0.8);
+        let linear_depth_params = math::get_linear_depth_params(near, far);
+        let mut linear_depths: Vec<f32> = Vec::new();
+        let mut noisy_input: Vec<f32> = Vec::new();
+        for y in 0..IMAGE_HEIGHT {
+            for x in 0..IMAGE_WIDTH {
+                let (distance, clean_value) = if x < EDGE_X { (near_distance, near_value) } else { (far_distance, far_value) };
+                let linear_depth = get_linear_depth(math::get_device_depth(distance, near, far), &linear_depth_params);
+                assert!((distance - linear_depth).abs() < distance * 0.001, "{} {}", distance, linear_depth);
+                linear_depths.push(linear_depth);
+                // a deterministic noise of +-0.1
+                noisy_input.push(clean_value + ((x * 7 + y * 13) % 5 - 2) as f32 * 0.05);
+            }
+        }
+
+        let create_info = DenoiserCreateInfo {
+            _normal_target_name: String::new(),
+            ..Default::default()
+        };
+        let denoised = denoise(&noisy_input, &linear_depths, &create_info);
+        // the noise is reduced on both sides and the pixels next to the edge keep the value of their side
+        for (is_near_side, clean_value) in [(true, near_value), (false, far_value)].iter() {
+            let noisy_error = get_max_error(&noisy_input, *is_near_side, *clean_value);
+            let denoised_error = get_max_error(&denoised, *is_near_side, *clean_value);
+            assert!(denoised_error < noisy_error * 0.5, "{} {}", noisy_error, denoised_error);
+        }
+        for y in 0..IMAGE_HEIGHT {
+            let row = (y * IMAGE_WIDTH) as usize;
+            assert!((denoised[row + EDGE_X as usize - 1] - near_value).abs() < 0.05, "{:?}", &denoised[row..row + IMAGE_WIDTH as usize]);
+            assert!((denoised[row + EDGE_X as usize] - far_value).abs() < 0.05, "{:?}", &denoised[row..row + IMAGE_WIDTH as usize]);
+        }
+
+        // without the depth edge stopping the sides are blended at the edge
+        let create_info = DenoiserCreateInfo {
+            _sigma_depth: 1000.0,
+            ..create_info
+        };
+        let blurred = denoise(&noisy_input, &linear_depths, &create_info);
+        assert!(0.1 < (blurred[EDGE_X as usize - 1] - near_value).abs());
+        assert!(0.1 < (blurred[EDGE_X as usize] - far_value).abs());
+    }
+}
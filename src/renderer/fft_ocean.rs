@@ -0,0 +1,320 @@
+use std::f32::consts::PI;
+
+use nalgebra::Vector4;
+
+use crate::vulkan_context::framebuffer::FramebufferData;
+use crate::vulkan_context::texture::TextureData;
+
+pub const FFT_MIN_SIZE: u32 = 16;
+pub const FFT_MAX_SIZE: u32 = 1024;
+pub const FFT_DEFAULT_SIZE: u32 = 256;
+pub const FFT_WORK_GROUP_SIZE: u32 = 16; // must match with fft_ocean.comp
+pub const FFT_GRID_COUNT: u32 = 4; // layers of FFT_A, FFT_B, must match with fft_ocean.glsl
+
+pub fn is_power_of_two(value: u32) -> bool {
+    0 != value && 0 == (value & (value - 1))
+}
+
+pub fn get_fft_passes(fft_size: u32) -> u32 {
+    31 - fft_size.leading_zeros()
+}
+
+pub fn bit_reverse(index: u32, fft_size: u32) -> u32 {
+    index.reverse_bits() >> (32 - get_fft_passes(fft_size))
+}
+
+// the fft size of the FFT_A render target, the render target may come from render_targets.json or a quality preset.
+// a non-square or a non-power-of-two target is clamped to the largest power of two square that fits in it.
+pub fn get_fft_size_from_dimensions(width: u32, height: u32) -> u32 {
+    let mut fft_size = width.min(height);
+    if width != height {
+        log::error!("FFT_A must be square, width: {}, height: {}. clamped to {}.", width, height, fft_size);
+    }
+    if false == is_power_of_two(fft_size) {
+        let clamped_size = if 0 == fft_size { FFT_MIN_SIZE } else { 1 << get_fft_passes(fft_size) };
+        log::error!("FFT_A size must be power of two: {}. clamped to {}.", fft_size, clamped_size);
+        fft_size = clamped_size;
+    }
+    if fft_size < FFT_MIN_SIZE || FFT_MAX_SIZE < fft_size {
+        let clamped_size = fft_size.max(FFT_MIN_SIZE).min(FFT_MAX_SIZE);
+        log::error!("FFT_A size is out of range({} ~ {}): {}. clamped to {}.", FFT_MIN_SIZE, FFT_MAX_SIZE, fft_size, clamped_size);
+        fft_size = clamped_size;
+    }
+    fft_size
+}
+
+// every size of the ocean derived from the FFT_A render target at prepare_framebuffer_and_descriptors.
+// the butterfly texture, the spectrum textures, the framebuffers and the dispatches of simulate_fft_waves use only this.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FFTOceanLayout {
+    pub _fft_size: u32,
+    pub _passes: u32,
+    pub _butterfly_width: u32, // fft size
+    pub _butterfly_height: u32, // passes
+    pub _spectrum_size: u32,
+    pub _mip_levels: u32,
+    pub _dispatch_group_counts: Vec<u32>, // per mip level, x and y
+}
+
+impl Default for FFTOceanLayout {
+    fn default() -> FFTOceanLayout {
+        FFTOceanLayout::create_fft_ocean_layout(FFT_DEFAULT_SIZE)
+    }
+}
+
+impl FFTOceanLayout {
+    pub fn create_fft_ocean_layout(fft_size: u32) -> FFTOceanLayout {
+        let fft_size = get_fft_size_from_dimensions(fft_size, fft_size);
+        let passes = get_fft_passes(fft_size);
+        let mip_levels = passes + 1;
+        let dispatch_group_counts: Vec<u32> = (0..mip_levels).map(|mip_level| {
+            let mip_size = fft_size >> mip_level;
+            ((mip_size + FFT_WORK_GROUP_SIZE - 1) / FFT_WORK_GROUP_SIZE).max(1)
+        }).collect();
+        FFTOceanLayout {
+            _fft_size: fft_size,
+            _passes: passes,
+            _butterfly_width: fft_size,
+            _butterfly_height: passes,
+            _spectrum_size: fft_size,
+            _mip_levels: mip_levels,
+            _dispatch_group_counts: dispatch_group_counts,
+        }
+    }
+
+    pub fn create_fft_ocean_layout_from_render_target(fft_a: &TextureData) -> FFTOceanLayout {
+        let fft_size = get_fft_size_from_dimensions(fft_a._image_width, fft_a._image_height);
+        log::info!("create_fft_ocean_layout_from_render_target: {} {}x{}, fft size: {}", fft_a._texture_data_name, fft_a._image_width, fft_a._image_height, fft_size);
+        FFTOceanLayout::create_fft_ocean_layout(fft_size)
+    }
+
+    pub fn get_dispatch_group_count(&self, mip_level: u32) -> u32 {
+        self._dispatch_group_counts[mip_level.min(self._mip_levels - 1) as usize]
+    }
+
+    // butterfly lookup, width: fft size, height: passes, rgba32f.
+    // xy: the uv of the two inputs, zw: the twiddle factor. the first pass reads the inputs in bit reversed order.
+    pub fn generate_butterfly_data(&self) -> Vec<Vector4<f32>> {
+        let fft_size = self._fft_size;
+        let mut butterfly_data: Vec<Vector4<f32>> = vec![Vector4::zeros(); (self._butterfly_width * self._butterfly_height) as usize];
+        for pass in 0..self._passes {
+            let block_count = 1 << (self._passes - 1 - pass);
+            let half_inputs = 1 << pass;
+            for block in 0..block_count {
+                for k in 0..half_inputs {
+                    let i1 = block * half_inputs * 2 + k;
+                    let i2 = i1 + half_inputs;
+                    let (j1, j2) = if 0 == pass {
+                        (bit_reverse(i1, fft_size), bit_reverse(i2, fft_size))
+                    } else {
+                        (i1, i2)
+                    };
+                    let angle = 2.0 * PI * (k * block_count) as f32 / fft_size as f32;
+                    let (wi, wr) = angle.sin_cos();
+                    let u1 = (j1 as f32 + 0.5) / fft_size as f32;
+                    let u2 = (j2 as f32 + 0.5) / fft_size as f32;
+                    butterfly_data[(i1 + pass * fft_size) as usize] = Vector4::new(u1, u2, wr, wi);
+                    butterfly_data[(i2 + pass * fft_size) as usize] = Vector4::new(u1, u2, -wr, -wi);
+                }
+            }
+        }
+        butterfly_data
+    }
+
+    pub fn check_butterfly_texture(&self, butterfly: &TextureData) -> Result<(), String> {
+        if butterfly._image_width != self._butterfly_width || butterfly._image_height != self._butterfly_height {
+            return Err(format!(
+                "{} is {}x{}, but the fft size {} needs {}x{}",
+                butterfly._texture_data_name, butterfly._image_width, butterfly._image_height, self._fft_size, self._butterfly_width, self._butterfly_height
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn check_fft_render_target(&self, render_target: &TextureData) -> Result<(), String> {
+        if render_target._image_width != self._fft_size || render_target._image_height != self._fft_size {
+            return Err(format!("{} is {}x{}, but the fft size is {}", render_target._texture_data_name, render_target._image_width, render_target._image_height, self._fft_size));
+        }
+        if render_target._image_layers < FFT_GRID_COUNT {
+            return Err(format!("{} has {} layers, but needs {} grids", render_target._texture_data_name, render_target._image_layers, FFT_GRID_COUNT));
+        }
+        if render_target._image_mip_levels < self._mip_levels {
+            return Err(format!("{} has {} mip levels, but needs {}", render_target._texture_data_name, render_target._image_mip_levels, self._mip_levels));
+        }
+        Ok(())
+    }
+
+    pub fn check_framebuffer(&self, framebuffer_data: &FramebufferData, mip_level: u32) -> Result<(), String> {
+        let mip_size = (self._fft_size >> mip_level).max(1);
+        let framebuffer_info = &framebuffer_data._framebuffer_info;
+        if framebuffer_info._framebuffer_width != mip_size || framebuffer_info._framebuffer_height != mip_size {
+            return Err(format!(
+                "framebuffer {} is {}x{}, but mip {} of the fft size {} is {}",
+                framebuffer_data._framebuffer_name, framebuffer_info._framebuffer_width, framebuffer_info._framebuffer_height, mip_level, self._fft_size, mip_size
+            ));
+        }
+        Ok(())
+    }
+
+    // the one place to check the ocean resources after prepare_framebuffer_and_descriptors or reinitialize_ocean.
+    // descriptor_image_sizes: the sizes of the images bound to the descriptor sets of fft_ocean.comp, per mip level.
+    pub fn check_consistency(
+        &self,
+        fft_a: &TextureData,
+        fft_b: &TextureData,
+        butterfly: &TextureData,
+        framebuffer_datas: &[&FramebufferData],
+        descriptor_image_sizes: &[u32],
+    ) -> Result<(), String> {
+        self.check_fft_render_target(fft_a)?;
+        self.check_fft_render_target(fft_b)?;
+        self.check_butterfly_texture(butterfly)?;
+        for (mip_level, framebuffer_data) in framebuffer_datas.iter().enumerate() {
+            self.check_framebuffer(framebuffer_data, mip_level as u32)?;
+        }
+        for (mip_level, image_size) in descriptor_image_sizes.iter().enumerate() {
+            let group_count = self.get_dispatch_group_count(mip_level as u32);
+            if group_count * FFT_WORK_GROUP_SIZE < *image_size || (*image_size + FFT_WORK_GROUP_SIZE - 1) / FFT_WORK_GROUP_SIZE != group_count {
+                return Err(format!(
+                    "the dispatch group count {} of mip {} does not cover the descriptor image size {}",
+                    group_count, mip_level, image_size
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_texture_data(texture_data_name: &str, width: u32, height: u32, layers: u32, mip_levels: u32) -> TextureData {
+        TextureData {
+            _texture_data_name: String::from(texture_data_name),
+            _image_width: width,
+            _image_height: height,
+            _image_layers: layers,
+            _image_mip_levels: mip_levels,
+            ..Default::default()
+        }
+    }
+
+    fn create_framebuffer_data(framebuffer_name: &str, size: u32) -> FramebufferData {
+        let mut framebuffer_data = FramebufferData {
+            _framebuffer_name: String::from(framebuffer_name),
+            ..Default::default()
+        };
+        framebuffer_data._framebuffer_info._framebuffer_width = size;
+        framebuffer_data._framebuffer_info._framebuffer_height = size;
+        framebuffer_data
+    }
+
+    // the cpu side of the butterfly passes of fft_ocean.comp
+    fn run_butterfly_passes(fft_ocean_layout: &FFTOceanLayout, butterfly_data: &[Vector4<f32>], input: &[(f32, f32)]) -> Vec<(f32, f32)> {
+        let fft_size = fft_ocean_layout._fft_size;
+        let mut values: Vec<(f32, f32)> = input.to_vec();
+        for pass in 0..fft_ocean_layout._passes {
+            values = (0..fft_size).map(|i| {
+                let butterfly = &butterfly_data[(i + pass * fft_size) as usize];
+                let a = values[(butterfly.x * fft_size as f32) as usize];
+                let b = values[(butterfly.y * fft_size as f32) as usize];
+                (a.0 + butterfly.z * b.0 - butterfly.w * b.1, a.1 + butterfly.z * b.1 + butterfly.w * b.0)
+            }).collect();
+        }
+        values
+    }
+
+    #[test]
+    fn test_fft_size_from_dimensions() {
+        assert_eq!(256, get_fft_size_from_dimensions(256, 256));
+        assert_eq!(256, get_fft_size_from_dimensions(512, 256));
+        assert_eq!(256, get_fft_size_from_dimensions(300, 300));
+        assert_eq!(128, get_fft_size_from_dimensions(200, 255));
+        assert_eq!(FFT_MIN_SIZE, get_fft_size_from_dimensions(0, 0));
+        assert_eq!(FFT_MIN_SIZE, get_fft_size_from_dimensions(4, 4));
+        assert_eq!(FFT_MAX_SIZE, get_fft_size_from_dimensions(4096, 4096));
+        assert_eq!(FFT_DEFAULT_SIZE, FFTOceanLayout::default()._fft_size);
+        assert_eq!(5, bit_reverse(5, 8)); // 101
+        assert_eq!(4, bit_reverse(1, 8)); // 001 -> 100
+    }
+
+    #[test]
+    fn test_fft_ocean_layout() {
+        for (fft_size, passes) in [(128u32, 7u32), (256, 8), (512, 9)].iter() {
+            let fft_ocean_layout = FFTOceanLayout::create_fft_ocean_layout(*fft_size);
+            assert_eq!(*fft_size, fft_ocean_layout._fft_size);
+            assert_eq!(*passes, fft_ocean_layout._passes);
+            assert_eq!((*fft_size, *passes), (fft_ocean_layout._butterfly_width, fft_ocean_layout._butterfly_height));
+            assert_eq!((fft_size * passes) as usize, fft_ocean_layout.generate_butterfly_data().len());
+
+            // the dispatches cover every mip level of FFT_A
+            assert_eq!(passes + 1, fft_ocean_layout._mip_levels);
+            assert_eq!(fft_ocean_layout._mip_levels as usize, fft_ocean_layout._dispatch_group_counts.len());
+            assert_eq!(fft_size / FFT_WORK_GROUP_SIZE, fft_ocean_layout.get_dispatch_group_count(0));
+            for mip_level in 0..fft_ocean_layout._mip_levels {
+                let mip_size = fft_size >> mip_level;
+                let group_count = fft_ocean_layout.get_dispatch_group_count(mip_level);
+                assert!(mip_size <= group_count * FFT_WORK_GROUP_SIZE);
+                assert!((group_count - 1) * FFT_WORK_GROUP_SIZE < mip_size);
+            }
+            assert_eq!(1, fft_ocean_layout.get_dispatch_group_count(fft_ocean_layout._mip_levels + 1));
+
+            // the resources of the same size are consistent
+            let mip_levels = fft_ocean_layout._mip_levels;
+            let fft_a = create_texture_data("fft_a", *fft_size, *fft_size, FFT_GRID_COUNT, mip_levels);
+            let fft_b = create_texture_data("fft_b", *fft_size, *fft_size, FFT_GRID_COUNT, mip_levels);
+            let butterfly = create_texture_data("fft_butterfly", *fft_size, *passes, 1, 1);
+            let framebuffer_datas: Vec<FramebufferData> = (0..mip_levels).map(|mip_level| create_framebuffer_data("fft_a", fft_size >> mip_level)).collect();
+            let framebuffer_data_refs: Vec<&FramebufferData> = framebuffer_datas.iter().collect();
+            let descriptor_image_sizes: Vec<u32> = (0..mip_levels).map(|mip_level| fft_size >> mip_level).collect();
+            assert_eq!(fft_ocean_layout, FFTOceanLayout::create_fft_ocean_layout_from_render_target(&fft_a));
+            assert!(fft_ocean_layout.check_consistency(&fft_a, &fft_b, &butterfly, &framebuffer_data_refs, &descriptor_image_sizes).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_inconsistent_resources() {
+        let fft_ocean_layout = FFTOceanLayout::create_fft_ocean_layout(256);
+        let fft_a = create_texture_data("fft_a", 256, 256, FFT_GRID_COUNT, 9);
+        let butterfly = create_texture_data("fft_butterfly", 256, 8, 1, 1);
+        let framebuffer_data = create_framebuffer_data("fft_a", 256);
+
+        // the render target of a quality preset has been changed to 512, the butterfly of 256 is left
+        let fft_b = create_texture_data("fft_b", 512, 512, FFT_GRID_COUNT, 10);
+        assert!(fft_ocean_layout.check_consistency(&fft_a, &fft_b, &butterfly, &[], &[]).unwrap_err().starts_with("fft_b is 512x512"));
+        let fft_ocean_layout_512 = FFTOceanLayout::create_fft_ocean_layout_from_render_target(&fft_b);
+        assert!(fft_ocean_layout_512.check_butterfly_texture(&butterfly).unwrap_err().contains("needs 512x9"));
+
+        let fft_b = create_texture_data("fft_b", 256, 256, 1, 9);
+        assert!(fft_ocean_layout.check_fft_render_target(&fft_b).unwrap_err().contains("layers"));
+        let fft_b = create_texture_data("fft_b", 256, 256, FFT_GRID_COUNT, 1);
+        assert!(fft_ocean_layout.check_fft_render_target(&fft_b).unwrap_err().contains("mip levels"));
+        let fft_b = create_texture_data("fft_b", 256, 256, FFT_GRID_COUNT, 9);
+        assert!(fft_ocean_layout.check_consistency(&fft_a, &fft_b, &butterfly, &[&framebuffer_data], &[256]).is_ok());
+        assert!(fft_ocean_layout.check_framebuffer(&framebuffer_data, 1).unwrap_err().contains("mip 1"));
+        assert!(fft_ocean_layout.check_consistency(&fft_a, &fft_b, &butterfly, &[], &[512]).unwrap_err().contains("does not cover"));
+    }
+
+    #[test]
+    fn test_butterfly_matches_dft() {
+        for fft_size in [16u32, 128].iter() {
+            let fft_ocean_layout = FFTOceanLayout::create_fft_ocean_layout(*fft_size);
+            let butterfly_data = fft_ocean_layout.generate_butterfly_data();
+            let input: Vec<(f32, f32)> = (0..*fft_size).map(|i| ((i as f32 * 0.37).sin(), (i as f32 * 1.3).cos() * 0.5)).collect();
+            let output = run_butterfly_passes(&fft_ocean_layout, &butterfly_data, &input);
+            // the inverse transform without the normalization, the ocean goes from the spectrum to the heights
+            for k in 0..*fft_size {
+                let mut expected = (0.0f32, 0.0f32);
+                for (n, value) in input.iter().enumerate() {
+                    let angle = 2.0 * PI * (k as usize * n) as f32 / *fft_size as f32;
+                    let (s, c) = angle.sin_cos();
+                    expected.0 += value.0 * c - value.1 * s;
+                    expected.1 += value.0 * s + value.1 * c;
+                }
+                let tolerance = 1e-3 * *fft_size as f32;
+                assert!((expected.0 - output[k as usize].0).abs() < tolerance && (expected.1 - output[k as usize].1).abs() < tolerance, "{}: {:?} != {:?}", k, expected, output[k as usize]);
+            }
+        }
+    }
+}
@@ -12,6 +12,7 @@ use crate::renderer::utility;
 use crate::utilities::system::{ newRcRefCell, RcRefCell };
 use crate::vulkan_context::buffer::{ self, BufferData };
 use crate::vulkan_context::descriptor::DescriptorResourceInfo;
+use crate::vulkan_context::framebuffer::FramebufferData;
 use crate::vulkan_context::texture::TextureData;
 use crate::vulkan_context::geometry_buffer::{ self, VertexData };
 use crate::vulkan_context::vulkan_context::SwapchainArray;
@@ -40,6 +41,7 @@ impl Default for RenderTextInfo {
 
 #[allow(non_camel_case_types)]
 #[derive(Debug, Clone)]
+#[repr(C)]
 pub struct PushConstant_RenderFont {
     pub _inv_canvas_size: Vector2<f32>,
     pub _offset: Vector2<f32>,
@@ -48,6 +50,8 @@ pub struct PushConstant_RenderFont {
     pub _reserved0: u32,
 }
 
+crate::impl_push_constant!(PushConstant_RenderFont, 32);
+
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct FontDataCreateInfo {
@@ -157,6 +161,7 @@ pub struct FontManager {
     pub _font_mesh_vertex_buffer: BufferData,
     pub _font_mesh_index_buffer: BufferData,
     pub _font_mesh_index_count: u32,
+    pub _render_font_framebuffer_data: Option<RcRefCell<FramebufferData>>, // acquired with the descriptor sets, recreated with the swapchain
 }
 
 
@@ -293,22 +298,28 @@ impl FontManager {
             _font_mesh_vertex_buffer: BufferData::default(),
             _font_mesh_index_buffer: BufferData::default(),
             _font_mesh_index_count: 0,
+            _render_font_framebuffer_data: None,
         }
     }
 
-    pub fn initialize_font_manager(&mut self, renderer_data: &RendererData, resources: &Resources) {
-        let ascii_font_data = resources.get_font_data(DEFAULT_FONT_NAME);
+    pub fn initialize_font_manager(&mut self, renderer_data: &RendererData, resources: &mut Resources) {
+        let ascii_font_data = resources.get_font_data(DEFAULT_FONT_NAME).clone();
         self._ascii = ascii_font_data.clone();
         self._text_render_data = TextRenderData::create_text_render_data(renderer_data.get_device(), resources, &ascii_font_data);
+        self._render_font_framebuffer_data = Some(resources.acquire_framebuffer_data("render_font"));
         self.create_font_vertex_data(renderer_data.get_device(), renderer_data.get_command_pool(), renderer_data.get_graphics_queue(), renderer_data.get_device_memory_properties());
     }
 
-    pub fn create_font_descriptor_sets(&mut self, renderer_data: &RendererData, resources: &Resources) {
+    pub fn create_font_descriptor_sets(&mut self, renderer_data: &RendererData, resources: &mut Resources) {
         self._text_render_data.create_texture_render_data_descriptor_sets(renderer_data.get_device(), resources);
+        self._render_font_framebuffer_data = Some(resources.acquire_framebuffer_data("render_font"));
     }
 
-    pub fn destroy_font_descriptor_sets(&mut self) {
+    pub fn destroy_font_descriptor_sets(&mut self, resources: &mut Resources) {
         self._text_render_data.destroy_text_render_data_descriptor_sets();
+        if self._render_font_framebuffer_data.take().is_some() {
+            resources.release_framebuffer_data("render_font");
+        }
     }
 
     pub fn destroy_font_manager(&mut self, device: &Device) {
@@ -386,7 +397,7 @@ impl FontManager {
                 skip_check
             );
             let font_data = self._ascii.borrow();
-            let framebuffer_data = resources.get_framebuffer_data("render_font").borrow();
+            let framebuffer_data = self._render_font_framebuffer_data.as_ref().unwrap().borrow();
             let material_instance_data = resources.get_material_instance_data("system/render_font").borrow();
             let pipeline_binding_data = material_instance_data.get_default_pipeline_binding_data();
             let render_pass_data = &pipeline_binding_data.get_render_pass_data().borrow();
@@ -0,0 +1,498 @@
+use std::collections::HashMap;
+use std::fs::{ self, File };
+use std::io::prelude::*;
+use std::path::Path;
+
+use ash::vk;
+
+use crate::vulkan_context::descriptor::DescriptorResourceType;
+use crate::vulkan_context::render_pass::{ RenderPassDataCreateInfo, ImageAttachmentDescription, MergedRenderPassInfo };
+
+#[derive(Clone, Debug, Copy, PartialEq, Eq)]
+pub enum FrameGraphPassType {
+    Raster,
+    Compute,
+    Copy,
+}
+
+#[derive(Clone, Debug, Copy, PartialEq, Eq)]
+pub enum FrameGraphResourceType {
+    Image,
+    Buffer,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FrameGraphResourceAccess {
+    pub _resource_name: String,
+    pub _resource_type: FrameGraphResourceType,
+    pub _format: vk::Format,
+    pub _layout: vk::ImageLayout,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FrameGraphPass {
+    pub _pass_name: String,
+    pub _pass_type: FrameGraphPassType,
+    pub _is_async_compute: bool,
+    pub _reads: Vec<FrameGraphResourceAccess>,
+    pub _writes: Vec<FrameGraphResourceAccess>,
+    pub _gpu_time_ms: Option<f64>,
+}
+
+#[derive(Clone, Debug, Copy, PartialEq, Eq)]
+pub enum RenderTargetAccessType {
+    ShaderRead, // sampled or input attachment
+    ShaderWrite, // storage image of a compute pass
+    ColorAttachmentWrite,
+    DepthAttachmentWrite,
+}
+
+// the last access of a render target in the current frame
+#[derive(Clone, Debug, Copy, PartialEq)]
+pub struct RenderTargetState {
+    pub _layout: vk::ImageLayout,
+    pub _access_mask: vk::AccessFlags,
+    pub _stage_mask: vk::PipelineStageFlags,
+    pub _is_write: bool,
+    pub _default_layout: vk::ImageLayout, // the layout of the descriptors, restored at the end of the frame
+}
+
+// the render target names(RenderTargetType of the project as str) read and written by a pass
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FrameGraphPassRenderTargets {
+    pub _reads: Vec<String>,
+    pub _writes: Vec<String>,
+}
+
+// declared passes in the order of submission, shared by the graph exporter and the barrier generation.
+#[derive(Clone, Debug, Default)]
+pub struct FrameGraph {
+    pub _passes: Vec<FrameGraphPass>,
+    pub _pass_render_targets: HashMap<String, FrameGraphPassRenderTargets>, // kept by the rebuild, see declare_pass_render_targets
+    pub _render_target_states: HashMap<String, RenderTargetState>, // reset by begin_frame_barriers
+    pub _last_barrier_pass_name: String, // the draws of a pass in a row share the barriers of the first draw
+    pub _merged_pass_names: HashMap<String, String>, // the first render pass -> the second render pass of the merged render pass
+}
+
+fn get_attachment_access(resource_name: String, attachment_description: &ImageAttachmentDescription) -> FrameGraphResourceAccess {
+    FrameGraphResourceAccess {
+        _resource_name: resource_name,
+        _resource_type: FrameGraphResourceType::Image,
+        _format: attachment_description._attachment_image_format,
+        _layout: attachment_description._attachment_reference_layout,
+    }
+}
+
+impl FrameGraphPass {
+    // reads and storage writes come from the descriptors, attachments are named by the render pass
+    // until the project declares the render target names with FrameGraph::set_attachment_names.
+    pub fn create_frame_graph_pass(render_pass_data_create_info: &RenderPassDataCreateInfo) -> FrameGraphPass {
+        let pass_name = &render_pass_data_create_info._render_pass_create_info_name;
+        let is_compute = render_pass_data_create_info._pipeline_data_create_infos.iter().all(|pipeline_data_create_info| {
+            vk::PipelineBindPoint::COMPUTE == pipeline_data_create_info._pipeline_bind_point
+        });
+        let mut reads: Vec<FrameGraphResourceAccess> = Vec::new();
+        let mut writes: Vec<FrameGraphResourceAccess> = Vec::new();
+        for pipeline_data_create_info in render_pass_data_create_info._pipeline_data_create_infos.iter() {
+            for descriptor_data_create_info in pipeline_data_create_info._descriptor_data_create_infos.iter() {
+                let (resource_type, layout, is_write) = match descriptor_data_create_info._descriptor_resource_type {
+                    DescriptorResourceType::UniformBuffer => (FrameGraphResourceType::Buffer, vk::ImageLayout::UNDEFINED, false),
+                    DescriptorResourceType::StorageBuffer => (FrameGraphResourceType::Buffer, vk::ImageLayout::UNDEFINED, true),
+                    DescriptorResourceType::Texture => (FrameGraphResourceType::Image, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL, false),
+                    DescriptorResourceType::RenderTarget => (FrameGraphResourceType::Image, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL, false),
+                    DescriptorResourceType::StorageTexture => (FrameGraphResourceType::Image, vk::ImageLayout::GENERAL, true),
+                    DescriptorResourceType::StorageRenderTarget => (FrameGraphResourceType::Image, vk::ImageLayout::GENERAL, true),
+                    // written by the earlier subpass of the same render pass
+                    DescriptorResourceType::InputAttachment => continue,
+                };
+                let access = FrameGraphResourceAccess {
+                    _resource_name: descriptor_data_create_info._descriptor_name.clone(),
+                    _resource_type: resource_type,
+                    _format: vk::Format::UNDEFINED,
+                    _layout: layout,
+                };
+                let accesses = if is_write { &mut writes } else { &mut reads };
+                if false == accesses.contains(&access) {
+                    accesses.push(access);
+                }
+            }
+        }
+
+        for (index, attachment_description) in render_pass_data_create_info._color_attachment_descriptions.iter().enumerate() {
+            writes.push(get_attachment_access(format!("{}/color{}", pass_name, index), attachment_description));
+        }
+        for (index, attachment_description) in render_pass_data_create_info._depth_attachment_descriptions.iter().enumerate() {
+            writes.push(get_attachment_access(format!("{}/depth{}", pass_name, index), attachment_description));
+        }
+        for (index, attachment_description) in render_pass_data_create_info._resolve_attachment_descriptions.iter().enumerate() {
+            writes.push(get_attachment_access(format!("{}/resolve{}", pass_name, index), attachment_description));
+        }
+
+        FrameGraphPass {
+            _pass_name: pass_name.clone(),
+            _pass_type: if is_compute { FrameGraphPassType::Compute } else { FrameGraphPassType::Raster },
+            _is_async_compute: false,
+            _reads: reads,
+            _writes: writes,
+            _gpu_time_ms: None,
+        }
+    }
+}
+
+pub fn get_render_target_access_state(access_type: RenderTargetAccessType, is_compute: bool, layout: vk::ImageLayout) -> RenderTargetState {
+    let shader_stage_mask = if is_compute {
+        vk::PipelineStageFlags::COMPUTE_SHADER
+    } else {
+        vk::PipelineStageFlags::VERTEX_SHADER | vk::PipelineStageFlags::FRAGMENT_SHADER
+    };
+    let (access_mask, stage_mask, is_write) = match access_type {
+        RenderTargetAccessType::ShaderRead => (vk::AccessFlags::SHADER_READ | vk::AccessFlags::INPUT_ATTACHMENT_READ, shader_stage_mask, false),
+        RenderTargetAccessType::ShaderWrite => (vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE, shader_stage_mask, true),
+        RenderTargetAccessType::ColorAttachmentWrite => (
+            vk::AccessFlags::COLOR_ATTACHMENT_READ | vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+            vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            true
+        ),
+        RenderTargetAccessType::DepthAttachmentWrite => (
+            vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+            vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS,
+            true
+        ),
+    };
+    RenderTargetState {
+        _layout: layout,
+        _access_mask: access_mask,
+        _stage_mask: stage_mask,
+        _is_write: is_write,
+        _default_layout: layout,
+    }
+}
+
+impl FrameGraph {
+    pub fn create_frame_graph(render_pass_data_create_infos: &[RenderPassDataCreateInfo]) -> FrameGraph {
+        FrameGraph {
+            _passes: render_pass_data_create_infos.iter().map(|render_pass_data_create_info| {
+                FrameGraphPass::create_frame_graph_pass(render_pass_data_create_info)
+            }).collect(),
+            ..Default::default()
+        }
+    }
+
+    // called by the project renderer once, the barriers between the declared passes are inserted by RendererData
+    pub fn declare_pass_render_targets(&mut self, pass_name: &str, read_render_target_names: &[&str], write_render_target_names: &[&str]) {
+        self._pass_render_targets.insert(String::from(pass_name), FrameGraphPassRenderTargets {
+            _reads: read_render_target_names.iter().map(|name| String::from(*name)).collect(),
+            _writes: write_render_target_names.iter().map(|name| String::from(*name)).collect(),
+        });
+    }
+
+    pub fn get_pass_render_targets(&self, pass_name: &str) -> Option<&FrameGraphPassRenderTargets> {
+        self._pass_render_targets.get(pass_name)
+    }
+
+    pub fn set_merged_passes(&mut self, merged_render_pass_infos: &[MergedRenderPassInfo]) {
+        self._merged_pass_names = merged_render_pass_infos.iter().map(|merged_render_pass_info| {
+            (merged_render_pass_info._first_render_pass_name.clone(), merged_render_pass_info._second_render_pass_name.clone())
+        }).collect();
+    }
+
+    // the barriers at the beginning of the render pass. the merged render pass has no barrier between the subpasses,
+    // so it waits for the reads of both passes except the attachments of the first pass, the subpass dependency covers them.
+    pub fn get_barrier_render_targets(&self, pass_name: &str) -> Option<FrameGraphPassRenderTargets> {
+        let first = self._pass_render_targets.get(pass_name);
+        let second = self._merged_pass_names.get(pass_name).and_then(|second_pass_name| self._pass_render_targets.get(second_pass_name));
+        match (first, second) {
+            (first, None) => first.cloned(),
+            (first, Some(second)) => {
+                let mut render_targets = first.cloned().unwrap_or_default();
+                for render_target_name in second._reads.iter() {
+                    if false == render_targets._writes.contains(render_target_name) && false == render_targets._reads.contains(render_target_name) {
+                        render_targets._reads.push(render_target_name.clone());
+                    }
+                }
+                for render_target_name in second._writes.iter() {
+                    if false == render_targets._writes.contains(render_target_name) {
+                        render_targets._writes.push(render_target_name.clone());
+                    }
+                }
+                Some(render_targets)
+            }
+        }
+    }
+
+    pub fn begin_frame_barriers(&mut self) {
+        self._render_target_states.clear();
+        self._last_barrier_pass_name.clear();
+    }
+
+    // returns the source and the destination of the barrier, None: the read after a read in the same layout.
+    // the first access in the frame waits for all the commands before it, ex) the passes which are not declared.
+    pub fn transition_render_target(&mut self, render_target_name: &str, dst_state: &RenderTargetState) -> Option<(RenderTargetState, RenderTargetState)> {
+        let src_state = match self._render_target_states.get(render_target_name) {
+            Some(src_state) => *src_state,
+            None => RenderTargetState {
+                _layout: dst_state._default_layout,
+                _access_mask: vk::AccessFlags::MEMORY_WRITE,
+                _stage_mask: vk::PipelineStageFlags::ALL_COMMANDS,
+                _is_write: true,
+                _default_layout: dst_state._default_layout,
+            },
+        };
+        if false == src_state._is_write && false == dst_state._is_write && src_state._layout == dst_state._layout {
+            // the next write waits for all the reads
+            let state = self._render_target_states.get_mut(render_target_name).unwrap();
+            state._access_mask |= dst_state._access_mask;
+            state._stage_mask |= dst_state._stage_mask;
+            return None;
+        }
+        self._render_target_states.insert(String::from(render_target_name), *dst_state);
+        Some((src_state, *dst_state))
+    }
+
+    // the render targets which are not in the layout of their descriptors
+    pub fn get_render_target_names_to_restore(&self) -> Vec<String> {
+        self._render_target_states.iter().filter(|(_, state)| state._layout != state._default_layout).map(|(render_target_name, _)| render_target_name.clone()).collect()
+    }
+
+    pub fn get_pass_mut(&mut self, pass_name: &str) -> Option<&mut FrameGraphPass> {
+        self._passes.iter_mut().find(|pass| pass._pass_name == pass_name)
+    }
+
+    pub fn declare_pass(&mut self, pass: FrameGraphPass) {
+        match self._passes.iter().position(|declared_pass| declared_pass._pass_name == pass._pass_name) {
+            Some(index) => self._passes[index] = pass,
+            None => self._passes.push(pass),
+        }
+    }
+
+    // replace the generated attachment names with the render target names in declaration order.
+    pub fn set_attachment_names(&mut self, pass_name: &str, attachment_names: &[&str]) {
+        if let Some(pass) = self.get_pass_mut(pass_name) {
+            let prefix = format!("{}/", pass_name);
+            let mut attachment_names = attachment_names.iter();
+            for write in pass._writes.iter_mut() {
+                if write._resource_name.starts_with(&prefix) {
+                    match attachment_names.next() {
+                        Some(attachment_name) => write._resource_name = String::from(*attachment_name),
+                        None => break,
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn set_pass_gpu_time(&mut self, pass_name: &str, gpu_time_ms: f64) {
+        if let Some(pass) = self.get_pass_mut(pass_name) {
+            pass._gpu_time_ms = Some(gpu_time_ms);
+        }
+    }
+
+    pub fn get_resource_names(&self) -> Vec<String> {
+        let mut resource_names: Vec<String> = Vec::new();
+        for pass in self._passes.iter() {
+            for access in pass._reads.iter().chain(pass._writes.iter()) {
+                if false == resource_names.contains(&access._resource_name) {
+                    resource_names.push(access._resource_name.clone());
+                }
+            }
+        }
+        resource_names
+    }
+
+    pub fn get_edge_count(&self) -> usize {
+        self._passes.iter().map(|pass| pass._reads.len() + pass._writes.len()).sum()
+    }
+
+    // passes are boxes colored by type, resources are ellipses, edges are labeled with format and layout.
+    pub fn export_dot(&self) -> String {
+        let get_pass_node_name = |pass: &FrameGraphPass| -> String { format!("\"pass:{}\"", pass._pass_name) };
+        let get_resource_node_name = |resource_name: &str| -> String { format!("\"resource:{}\"", resource_name) };
+        let get_pass_node = |pass: &FrameGraphPass| -> String {
+            let color = match pass._pass_type {
+                FrameGraphPassType::Raster => "lightblue",
+                FrameGraphPassType::Compute => "palegreen",
+                FrameGraphPassType::Copy => "khaki",
+            };
+            let label = match pass._gpu_time_ms {
+                Some(gpu_time_ms) => format!("{}\\n{:?}\\n{:.3}ms", pass._pass_name, pass._pass_type, gpu_time_ms),
+                None => format!("{}\\n{:?}", pass._pass_name, pass._pass_type),
+            };
+            format!("    {} [shape=box, style=filled, fillcolor={}, label=\"{}\"];\n", get_pass_node_name(pass), color, label)
+        };
+        let get_edge_label = |access: &FrameGraphResourceAccess| -> String {
+            match access._resource_type {
+                FrameGraphResourceType::Image => format!("{:?}\\n{:?}", access._format, access._layout),
+                FrameGraphResourceType::Buffer => String::from("buffer"),
+            }
+        };
+
+        let mut contents = String::from("digraph frame_graph {\n    rankdir=LR;\n");
+        for pass in self._passes.iter().filter(|pass| false == pass._is_async_compute) {
+            contents.push_str(&get_pass_node(pass));
+        }
+        if self._passes.iter().any(|pass| pass._is_async_compute) {
+            contents.push_str("    subgraph cluster_async_compute {\n    label=\"async compute\";\n");
+            for pass in self._passes.iter().filter(|pass| pass._is_async_compute) {
+                contents.push_str(&get_pass_node(pass));
+            }
+            contents.push_str("    }\n");
+        }
+        for resource_name in self.get_resource_names().iter() {
+            contents.push_str(&format!("    {} [shape=ellipse, label=\"{}\"];\n", get_resource_node_name(resource_name), resource_name));
+        }
+        for pass in self._passes.iter() {
+            for access in pass._reads.iter() {
+                contents.push_str(&format!("    {} -> {} [label=\"{}\"];\n", get_resource_node_name(&access._resource_name), get_pass_node_name(pass), get_edge_label(access)));
+            }
+            for access in pass._writes.iter() {
+                contents.push_str(&format!("    {} -> {} [label=\"{}\"];\n", get_pass_node_name(pass), get_resource_node_name(&access._resource_name), get_edge_label(access)));
+            }
+        }
+        contents.push_str("}\n");
+        contents
+    }
+
+    pub fn write_dot(&self, dot_file_path: &Path) {
+        if let Some(parent) = dot_file_path.parent() {
+            if false == parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent).expect("Failed to create directories.");
+            }
+        }
+        let mut write_file = File::create(dot_file_path).expect("Failed to create file");
+        write_file.write_all(self.export_dot().as_bytes()).expect("Failed to write");
+        log::info!("write_dot: {:?} passes({}) resources({}) edges({})", dot_file_path, self._passes.len(), self.get_resource_names().len(), self.get_edge_count());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_strings(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| String::from(*name)).collect()
+    }
+
+    // the merged render pass transitions the render targets of both render passes once, before the first subpass
+    #[test]
+    fn test_merged_pass_barrier_render_targets() {
+        let mut frame_graph = FrameGraph::create_frame_graph(&[]);
+        frame_graph.declare_pass_render_targets("render_gbuffer", &["SceneShadow"], &["SceneAlbedo", "SceneNormal", "SceneDepth"]);
+        frame_graph.declare_pass_render_targets("composite_gbuffer", &["SceneAlbedo", "SceneNormal", "SceneDepth", "SceneShadow", "SceneSSAO"], &["SceneColor"]);
+        let separated = frame_graph.get_barrier_render_targets("render_gbuffer").unwrap();
+        assert_eq!(frame_graph.get_pass_render_targets("render_gbuffer").unwrap(), &separated);
+
+        frame_graph.set_merged_passes(&[MergedRenderPassInfo {
+            _first_render_pass_name: String::from("render_gbuffer"),
+            _second_render_pass_name: String::from("composite_gbuffer"),
+            _input_attachment_names: to_strings(&["SceneAlbedo", "SceneNormal", "SceneDepth"]),
+        }]);
+        let merged = frame_graph.get_barrier_render_targets("render_gbuffer").unwrap();
+        assert_eq!(to_strings(&["SceneShadow", "SceneSSAO"]), merged._reads);
+        assert_eq!(to_strings(&["SceneAlbedo", "SceneNormal", "SceneDepth", "SceneColor"]), merged._writes);
+        assert_eq!(None, frame_graph.get_barrier_render_targets("unknown"));
+    }
+
+    fn create_image_access(resource_name: &str, format: vk::Format, layout: vk::ImageLayout) -> FrameGraphResourceAccess {
+        FrameGraphResourceAccess {
+            _resource_name: String::from(resource_name),
+            _resource_type: FrameGraphResourceType::Image,
+            _format: format,
+            _layout: layout,
+        }
+    }
+
+    fn create_pass(pass_name: &str, pass_type: FrameGraphPassType, is_async_compute: bool, reads: Vec<FrameGraphResourceAccess>, writes: Vec<FrameGraphResourceAccess>) -> FrameGraphPass {
+        FrameGraphPass {
+            _pass_name: String::from(pass_name),
+            _pass_type: pass_type,
+            _is_async_compute: is_async_compute,
+            _reads: reads,
+            _writes: writes,
+            _gpu_time_ms: None,
+        }
+    }
+
+    fn create_test_frame_graph() -> FrameGraph {
+        let mut frame_graph = FrameGraph::create_frame_graph(&[]);
+        frame_graph.declare_pass(create_pass("render_gbuffer", FrameGraphPassType::Raster, false, vec![], vec![
+            create_image_access("render_gbuffer/color0", vk::Format::R8G8B8A8_UNORM, vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL),
+            create_image_access("render_gbuffer/depth0", vk::Format::D32_SFLOAT, vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL),
+        ]));
+        frame_graph.set_attachment_names("render_gbuffer", &["SceneAlbedo", "SceneDepth"]);
+        frame_graph.declare_pass(create_pass("render_ssao", FrameGraphPassType::Compute, true, vec![
+            create_image_access("SceneDepth", vk::Format::UNDEFINED, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL),
+        ], vec![
+            create_image_access("SceneSSAO", vk::Format::UNDEFINED, vk::ImageLayout::GENERAL),
+        ]));
+        frame_graph.declare_pass(create_pass("composite_gbuffer", FrameGraphPassType::Raster, false, vec![
+            create_image_access("SceneAlbedo", vk::Format::UNDEFINED, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL),
+            create_image_access("SceneSSAO", vk::Format::UNDEFINED, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL),
+            FrameGraphResourceAccess {
+                _resource_name: String::from("view_constants"),
+                _resource_type: FrameGraphResourceType::Buffer,
+                _format: vk::Format::UNDEFINED,
+                _layout: vk::ImageLayout::UNDEFINED,
+            },
+        ], vec![
+            create_image_access("SceneColor", vk::Format::R16G16B16A16_SFLOAT, vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL),
+        ]));
+        frame_graph.declare_pass(create_pass("copy_scene_color", FrameGraphPassType::Copy, false, vec![
+            create_image_access("SceneColor", vk::Format::UNDEFINED, vk::ImageLayout::TRANSFER_SRC_OPTIMAL),
+        ], vec![
+            create_image_access("SceneColorCopy", vk::Format::UNDEFINED, vk::ImageLayout::TRANSFER_DST_OPTIMAL),
+        ]));
+        frame_graph
+    }
+
+    #[test]
+    fn test_export_dot() {
+        let mut frame_graph = create_test_frame_graph();
+        assert_eq!(to_strings(&["SceneAlbedo", "SceneDepth", "SceneSSAO", "view_constants", "SceneColor", "SceneColorCopy"]), frame_graph.get_resource_names());
+        assert_eq!(10, frame_graph.get_edge_count());
+
+        frame_graph.set_pass_gpu_time("composite_gbuffer", 0.25);
+        let contents = frame_graph.export_dot();
+        assert!(contents.starts_with("digraph frame_graph {"));
+        assert!(contents.trim_end().ends_with('}'));
+        let mut depth: i32 = 0;
+        for c in contents.chars() {
+            match c {
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                _ => (),
+            }
+            assert!(0 <= depth);
+        }
+        assert_eq!(0, depth);
+        assert_eq!(frame_graph._passes.len(), contents.matches("shape=box").count());
+        assert_eq!(frame_graph.get_resource_names().len(), contents.matches("shape=ellipse").count());
+        assert_eq!(frame_graph.get_edge_count(), contents.matches(" -> ").count());
+
+        // the node annotations and the colors by pass type
+        assert!(contents.contains("\"pass:render_gbuffer\" [shape=box, style=filled, fillcolor=lightblue, label=\"render_gbuffer\\nRaster\"]"));
+        assert!(contents.contains("fillcolor=palegreen, label=\"render_ssao\\nCompute\"]"));
+        assert!(contents.contains("fillcolor=khaki, label=\"copy_scene_color\\nCopy\"]"));
+        assert!(contents.contains("label=\"composite_gbuffer\\nRaster\\n0.250ms\""));
+        assert!(contents.contains("\"pass:render_gbuffer\" -> \"resource:SceneDepth\" [label=\"D32_SFLOAT\\nDEPTH_STENCIL_ATTACHMENT_OPTIMAL\"]"));
+        assert!(contents.contains("\"resource:view_constants\" -> \"pass:composite_gbuffer\" [label=\"buffer\"]"));
+
+        // only the async compute pass is in the cluster
+        let cluster_begin = contents.find("subgraph cluster_async_compute {").unwrap();
+        let cluster_end = cluster_begin + contents[cluster_begin..].find('}').unwrap();
+        let cluster = &contents[cluster_begin..cluster_end];
+        assert!(cluster.contains("pass:render_ssao"));
+        assert_eq!(1, cluster.matches("shape=box").count());
+
+        let no_async_compute = FrameGraph::create_frame_graph(&[]);
+        assert_eq!("digraph frame_graph {\n    rankdir=LR;\n}\n", no_async_compute.export_dot());
+    }
+
+    #[test]
+    fn test_write_dot() {
+        let frame_graph = create_test_frame_graph();
+        let dot_file_path = std::env::temp_dir().join(format!("frame_graph_test_write_dot_{}", std::process::id())).join("frame_graph.dot");
+        frame_graph.write_dot(&dot_file_path);
+        assert_eq!(frame_graph.export_dot(), fs::read_to_string(&dot_file_path).unwrap());
+        fs::remove_dir_all(dot_file_path.parent().unwrap()).unwrap();
+    }
+}
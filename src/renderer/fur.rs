@@ -0,0 +1,150 @@
+use nalgebra::{ Vector3, Matrix4 };
+use serde::{ Serialize, Deserialize };
+use serde_json::Value;
+use ash::vk;
+
+use crate::constants;
+use crate::renderer::material_instance::PipelineBindingData;
+use crate::renderer::renderer::RendererData;
+use crate::vulkan_context::geometry_buffer::GeometryData;
+use crate::vulkan_context::vulkan_context::{ self, BlendMode };
+
+pub const FUR_SHELL_COUNT_NAME: &str = "fur_shell_count";
+pub const FUR_SHADOW_SHELL_COUNT_NAME: &str = "fur_shadow_shell_count";
+pub const FUR_SHELL_SPACING_NAME: &str = "fur_shell_spacing";
+pub const FUR_GRAVITY_BEND_NAME: &str = "fur_gravity_bend";
+pub const FUR_DENSITY_TEXTURE_NAME: &str = "textureFurDensity";
+
+// must match with render_fur_shell.glsl
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone)]
+#[repr(C)]
+pub struct PushConstant_FurShell {
+    pub _model_matrix: Matrix4<f32>,
+    pub _gravity_bend: Vector3<f32>,
+    pub _shell_count: u32,
+    pub _wind_direction: Vector3<f32>,
+    pub _shell_spacing: f32,
+}
+
+crate::impl_push_constant!(PushConstant_FurShell, 96);
+
+impl Default for PushConstant_FurShell {
+    fn default() -> PushConstant_FurShell {
+        PushConstant_FurShell {
+            _model_matrix: Matrix4::identity(),
+            _gravity_bend: Vector3::new(0.0, -1.0, 0.0),
+            _shell_count: 0,
+            _wind_direction: Vector3::zeros(),
+            _shell_spacing: 0.0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FurShellData {
+    pub _shell_count: u32,
+    pub _shadow_shell_count: u32,
+    pub _shell_spacing: f32,
+    pub _gravity_bend: Vector3<f32>,
+}
+
+impl Default for FurShellData {
+    fn default() -> FurShellData {
+        FurShellData {
+            _shell_count: 16,
+            _shadow_shell_count: 0,
+            _shell_spacing: 0.005,
+            _gravity_bend: Vector3::new(0.0, -0.5, 0.0),
+        }
+    }
+}
+
+impl FurShellData {
+    // fur is enabled by declaring fur_shell_count in the material parameters.
+    pub fn create_fur_shell_data(material_parameter_map: &Value) -> Option<FurShellData> {
+        let shell_count = match material_parameter_map.get(FUR_SHELL_COUNT_NAME) {
+            Some(Value::Number(shell_count)) => shell_count.as_u64().unwrap_or(0) as u32,
+            _ => return None,
+        };
+
+        if 0 == shell_count {
+            return None;
+        }
+
+        let default_fur_shell_data = FurShellData::default();
+        let get_f32 = |name: &str, default_value: f32| -> f32 {
+            match material_parameter_map.get(name) {
+                Some(Value::Number(value)) => value.as_f64().unwrap_or(default_value as f64) as f32,
+                _ => default_value,
+            }
+        };
+        let shadow_shell_count = match material_parameter_map.get(FUR_SHADOW_SHELL_COUNT_NAME) {
+            Some(Value::Number(shadow_shell_count)) => shadow_shell_count.as_u64().unwrap_or(0) as u32,
+            _ => default_fur_shell_data._shadow_shell_count,
+        };
+        let gravity_bend = match material_parameter_map.get(FUR_GRAVITY_BEND_NAME) {
+            Some(Value::Array(gravity_bend)) if 3 == gravity_bend.len() => Vector3::new(
+                gravity_bend[0].as_f64().unwrap_or(0.0) as f32,
+                gravity_bend[1].as_f64().unwrap_or(0.0) as f32,
+                gravity_bend[2].as_f64().unwrap_or(0.0) as f32,
+            ),
+            _ => default_fur_shell_data._gravity_bend.clone(),
+        };
+
+        Some(FurShellData {
+            _shell_count: shell_count,
+            _shadow_shell_count: shadow_shell_count.min(shell_count),
+            _shell_spacing: get_f32(FUR_SHELL_SPACING_NAME, default_fur_shell_data._shell_spacing),
+            _gravity_bend: gravity_bend,
+        })
+    }
+
+    pub fn get_shell_count(&self) -> u32 {
+        unsafe { self._shell_count.min(constants::MAX_FUR_SHELL_COUNT) }
+    }
+
+    pub fn get_shadow_shell_count(&self) -> u32 {
+        unsafe { self._shadow_shell_count.min(constants::MAX_FUR_SHELL_COUNT) }
+    }
+}
+
+// shells are alpha blended over the opaque base mesh, so the fur pipeline must be rendered after opaque and before translucent.
+pub fn get_fur_shell_color_blend_mode() -> vk::PipelineColorBlendAttachmentState {
+    vulkan_context::get_color_blend_mode(BlendMode::AlphaBlend)
+}
+
+pub fn render_fur_shells(
+    renderer_data: &RendererData,
+    command_buffer: vk::CommandBuffer,
+    pipeline_binding_data: &PipelineBindingData,
+    geometry_data: &GeometryData,
+    fur_shell_data: &FurShellData,
+    model_matrix: &Matrix4<f32>,
+    wind_direction: &Vector3<f32>,
+    is_shadow_pass: bool,
+) {
+    // the base mesh is rendered by the opaque pass, shell index is gl_InstanceIndex + 1.
+    let shell_count = if is_shadow_pass { fur_shell_data.get_shadow_shell_count() } else { fur_shell_data.get_shell_count() };
+    if 0 == shell_count {
+        return;
+    }
+
+    let push_constant_data = PushConstant_FurShell {
+        _model_matrix: model_matrix.clone(),
+        _gravity_bend: fur_shell_data._gravity_bend.clone(),
+        _shell_count: shell_count,
+        _wind_direction: wind_direction.clone(),
+        _shell_spacing: fur_shell_data._shell_spacing,
+    };
+    let pipeline_data = &pipeline_binding_data.get_pipeline_data().borrow();
+    renderer_data.upload_push_constant_data(command_buffer, pipeline_data, &push_constant_data);
+    renderer_data.draw_indexed(
+        command_buffer,
+        &[geometry_data._vertex_buffer_data._buffer],
+        &[],
+        shell_count,
+        geometry_data._index_buffer_data._buffer,
+        geometry_data._vertex_index_count,
+    );
+}
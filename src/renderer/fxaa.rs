@@ -0,0 +1,395 @@
+use std::fs;
+use std::path::PathBuf;
+
+use ash::vk;
+use ash::version::DeviceV1_0;
+use nalgebra::Vector2;
+
+use crate::constants;
+use crate::renderer::post_process::AntiAliasingMode;
+use crate::renderer::renderer::RendererData;
+use crate::resource::resource::Resources;
+use crate::vulkan_context::descriptor::{ self, DescriptorDataCreateInfo, DescriptorResourceInfo, DescriptorResourceType };
+use crate::vulkan_context::render_pass::{ PipelineDataCreateInfo, RenderPassDataCreateInfo };
+use crate::vulkan_context::shader::SHADER_DIRECTORY;
+use crate::vulkan_context::texture::TextureData;
+use crate::vulkan_context::vulkan_context::SwapchainArray;
+
+pub const FXAA_WORK_GROUP_SIZE: u32 = 8; // must match with render_fxaa.comp
+pub const FXAA_MODE_FXAA: u32 = 0; // must match with render_fxaa.comp
+pub const FXAA_MODE_COPY: u32 = 1; // must match with render_fxaa.comp
+pub const FXAA_RENDER_PASS_NAME: &str = "render_fxaa";
+pub const FXAA_SHADER_FILE: &str = "common/render_fxaa.comp";
+pub const FXAA_SHADER_SOURCE: &str = r#"#version 450
+
+#define FXAA_WORK_GROUP_SIZE 8
+#define FXAA_MODE_FXAA 0
+#define FXAA_MODE_COPY 1
+#define FXAA_SEARCH_STEPS 12
+
+layout(local_size_x = FXAA_WORK_GROUP_SIZE, local_size_y = FXAA_WORK_GROUP_SIZE, local_size_z = 1) in;
+
+layout(binding = 0) uniform sampler2D texture_source;
+// must match with the format of the resolve target of TAA
+layout(binding = 1, rgba16f) uniform writeonly image2D image_resolve;
+
+layout(push_constant) uniform PushConstant_FXAA
+{
+    ivec2 image_size;
+    uint mode;
+    float subpixel_quality;
+    float edge_threshold;
+    float edge_threshold_min;
+    float reserved0;
+    float reserved1;
+} pushConstant;
+
+float get_luma(vec3 color)
+{
+    return sqrt(dot(color, vec3(0.299, 0.587, 0.114)));
+}
+
+float sample_luma(vec2 texcoord)
+{
+    return get_luma(textureLod(texture_source, texcoord, 0.0).xyz);
+}
+
+vec4 apply_fxaa(vec2 texcoord, vec2 inv_size)
+{
+    vec4 color_center = textureLod(texture_source, texcoord, 0.0);
+    float luma_center = get_luma(color_center.xyz);
+    float luma_down = sample_luma(texcoord + vec2(0.0, -inv_size.y));
+    float luma_up = sample_luma(texcoord + vec2(0.0, inv_size.y));
+    float luma_left = sample_luma(texcoord + vec2(-inv_size.x, 0.0));
+    float luma_right = sample_luma(texcoord + vec2(inv_size.x, 0.0));
+    float luma_min = min(luma_center, min(min(luma_down, luma_up), min(luma_left, luma_right)));
+    float luma_max = max(luma_center, max(max(luma_down, luma_up), max(luma_left, luma_right)));
+    float luma_range = luma_max - luma_min;
+    if(luma_range < max(pushConstant.edge_threshold_min, luma_max * pushConstant.edge_threshold))
+    {
+        return color_center;
+    }
+
+    float luma_down_left = sample_luma(texcoord + vec2(-inv_size.x, -inv_size.y));
+    float luma_up_right = sample_luma(texcoord + vec2(inv_size.x, inv_size.y));
+    float luma_up_left = sample_luma(texcoord + vec2(-inv_size.x, inv_size.y));
+    float luma_down_right = sample_luma(texcoord + vec2(inv_size.x, -inv_size.y));
+    float luma_down_up = luma_down + luma_up;
+    float luma_left_right = luma_left + luma_right;
+    float luma_left_corners = luma_down_left + luma_up_left;
+    float luma_down_corners = luma_down_left + luma_down_right;
+    float luma_right_corners = luma_down_right + luma_up_right;
+    float luma_up_corners = luma_up_right + luma_up_left;
+    float edge_horizontal = abs(-2.0 * luma_left + luma_left_corners) + abs(-2.0 * luma_center + luma_down_up) * 2.0 + abs(-2.0 * luma_right + luma_right_corners);
+    float edge_vertical = abs(-2.0 * luma_up + luma_up_corners) + abs(-2.0 * luma_center + luma_left_right) * 2.0 + abs(-2.0 * luma_down + luma_down_corners);
+    bool is_horizontal = (edge_horizontal >= edge_vertical);
+
+    // the side of the edge with the larger gradient
+    float luma_negative = is_horizontal ? luma_down : luma_left;
+    float luma_positive = is_horizontal ? luma_up : luma_right;
+    float gradient_negative = abs(luma_negative - luma_center);
+    float gradient_positive = abs(luma_positive - luma_center);
+    float step_length = is_horizontal ? inv_size.y : inv_size.x;
+    float luma_local_average = 0.0;
+    float gradient_scaled = 0.0;
+    if(gradient_negative >= gradient_positive)
+    {
+        step_length = -step_length;
+        luma_local_average = 0.5 * (luma_negative + luma_center);
+        gradient_scaled = 0.25 * gradient_negative;
+    }
+    else
+    {
+        luma_local_average = 0.5 * (luma_positive + luma_center);
+        gradient_scaled = 0.25 * gradient_positive;
+    }
+
+    // search the both ends of the edge
+    vec2 edge_texcoord = texcoord;
+    if(is_horizontal)
+    {
+        edge_texcoord.y += step_length * 0.5;
+    }
+    else
+    {
+        edge_texcoord.x += step_length * 0.5;
+    }
+    vec2 edge_step = is_horizontal ? vec2(inv_size.x, 0.0) : vec2(0.0, inv_size.y);
+    vec2 texcoord_negative = edge_texcoord - edge_step;
+    vec2 texcoord_positive = edge_texcoord + edge_step;
+    float luma_end_negative = sample_luma(texcoord_negative) - luma_local_average;
+    float luma_end_positive = sample_luma(texcoord_positive) - luma_local_average;
+    bool reached_negative = abs(luma_end_negative) >= gradient_scaled;
+    bool reached_positive = abs(luma_end_positive) >= gradient_scaled;
+    for(int i = 0; i < FXAA_SEARCH_STEPS && !(reached_negative && reached_positive); ++i)
+    {
+        if(!reached_negative)
+        {
+            texcoord_negative -= edge_step * (1.0 + float(i / 4));
+            luma_end_negative = sample_luma(texcoord_negative) - luma_local_average;
+            reached_negative = abs(luma_end_negative) >= gradient_scaled;
+        }
+        if(!reached_positive)
+        {
+            texcoord_positive += edge_step * (1.0 + float(i / 4));
+            luma_end_positive = sample_luma(texcoord_positive) - luma_local_average;
+            reached_positive = abs(luma_end_positive) >= gradient_scaled;
+        }
+    }
+
+    float distance_negative = is_horizontal ? (texcoord.x - texcoord_negative.x) : (texcoord.y - texcoord_negative.y);
+    float distance_positive = is_horizontal ? (texcoord_positive.x - texcoord.x) : (texcoord_positive.y - texcoord.y);
+    bool is_negative_closer = distance_negative < distance_positive;
+    float distance_final = min(distance_negative, distance_positive);
+    float edge_length = distance_negative + distance_positive;
+    bool is_luma_center_smaller = luma_center < luma_local_average;
+    bool is_correct_variation = ((is_negative_closer ? luma_end_negative : luma_end_positive) < 0.0) != is_luma_center_smaller;
+    float pixel_offset = is_correct_variation ? (-distance_final / edge_length + 0.5) : 0.0;
+
+    // the subpixel aliasing of the thin lines
+    float luma_average = (1.0 / 12.0) * (2.0 * (luma_down_up + luma_left_right) + luma_left_corners + luma_right_corners);
+    float subpixel_offset = clamp(abs(luma_average - luma_center) / luma_range, 0.0, 1.0);
+    subpixel_offset = (-2.0 * subpixel_offset + 3.0) * subpixel_offset * subpixel_offset;
+    subpixel_offset = subpixel_offset * subpixel_offset * pushConstant.subpixel_quality;
+    pixel_offset = max(pixel_offset, subpixel_offset);
+
+    vec2 final_texcoord = texcoord;
+    if(is_horizontal)
+    {
+        final_texcoord.y += pixel_offset * step_length;
+    }
+    else
+    {
+        final_texcoord.x += pixel_offset * step_length;
+    }
+    return textureLod(texture_source, final_texcoord, 0.0);
+}
+
+void main()
+{
+    ivec2 pixel = ivec2(gl_GlobalInvocationID.xy);
+    if(any(greaterThanEqual(pixel, pushConstant.image_size)))
+    {
+        return;
+    }
+
+    vec2 inv_size = 1.0 / vec2(pushConstant.image_size);
+    vec2 texcoord = (vec2(pixel) + 0.5) * inv_size;
+    vec4 color = (FXAA_MODE_COPY == pushConstant.mode) ? textureLod(texture_source, texcoord, 0.0) : apply_fxaa(texcoord, inv_size);
+    imageStore(image_resolve, pixel, color);
+}
+"#;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FXAASettings {
+    pub _source_target_name: String,
+    pub _resolve_target_name: String, // the same target as the resolve of TAA, the following passes do not know which one wrote it
+    pub _subpixel_quality: f32, // 0.0: sharp ~ 1.0: soft
+    pub _edge_threshold: f32, // the local contrast of an edge, ratio of the max luma
+    pub _edge_threshold_min: f32, // the dark areas are skipped
+}
+
+impl Default for FXAASettings {
+    fn default() -> FXAASettings {
+        FXAASettings {
+            _source_target_name: String::from("SceneColor"),
+            _resolve_target_name: String::from("TAAResolve"),
+            _subpixel_quality: 0.75,
+            _edge_threshold: 0.166,
+            _edge_threshold_min: 0.0833,
+        }
+    }
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone)]
+#[repr(C)]
+pub struct PushConstant_FXAA {
+    pub _image_size: Vector2<i32>,
+    pub _mode: u32,
+    pub _subpixel_quality: f32,
+    pub _edge_threshold: f32,
+    pub _edge_threshold_min: f32,
+    pub _reserved0: f32,
+    pub _reserved1: f32,
+}
+
+crate::impl_push_constant!(PushConstant_FXAA, 32);
+
+// The anti aliasing without TAA: FXAA or a copy of AntiAliasingMode::None into the resolve target of TAA.
+pub struct FXAA {
+    pub _settings: FXAASettings,
+    pub _descriptor_sets: SwapchainArray<vk::DescriptorSet>, // created in prepare_framebuffer_and_descriptors
+}
+
+impl Default for FXAA {
+    fn default() -> FXAA {
+        FXAA {
+            _settings: FXAASettings::default(),
+            _descriptor_sets: Vec::new(),
+        }
+    }
+}
+
+pub fn get_render_pass_data_create_info() -> RenderPassDataCreateInfo {
+    let pipeline_data_create_infos = vec![
+        PipelineDataCreateInfo {
+            _pipeline_data_create_info_name: String::from(FXAA_RENDER_PASS_NAME),
+            _pipeline_bind_point: vk::PipelineBindPoint::COMPUTE,
+            _pipeline_compute_shader_file: PathBuf::from(FXAA_SHADER_FILE),
+            _push_constant_ranges: vec![vk::PushConstantRange {
+                stage_flags: vk::ShaderStageFlags::COMPUTE,
+                offset: 0,
+                size: std::mem::size_of::<PushConstant_FXAA>() as u32,
+            }],
+            _descriptor_data_create_infos: vec![
+                DescriptorDataCreateInfo {
+                    _descriptor_binding_index: 0,
+                    _descriptor_name: String::from("texture_source"),
+                    _descriptor_resource_type: DescriptorResourceType::RenderTarget,
+                    _descriptor_shader_stage: vk::ShaderStageFlags::COMPUTE,
+                    ..Default::default()
+                },
+                DescriptorDataCreateInfo {
+                    _descriptor_binding_index: 1,
+                    _descriptor_name: String::from("image_resolve"),
+                    _descriptor_resource_type: DescriptorResourceType::StorageRenderTarget,
+                    _descriptor_shader_stage: vk::ShaderStageFlags::COMPUTE,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        }
+    ];
+
+    RenderPassDataCreateInfo {
+        _render_pass_create_info_name: String::from(FXAA_RENDER_PASS_NAME),
+        _pipeline_data_create_infos: pipeline_data_create_infos,
+        ..Default::default()
+    }
+}
+
+#[cfg(not(target_os = "android"))]
+pub fn generate_fxaa_shader() {
+    let mut shader_file_path = PathBuf::from(SHADER_DIRECTORY);
+    shader_file_path.push(FXAA_SHADER_FILE);
+    if false == shader_file_path.is_file() {
+        fs::create_dir_all(shader_file_path.parent().unwrap()).expect("Failed to create directories.");
+        fs::write(&shader_file_path, FXAA_SHADER_SOURCE).expect("Failed to write");
+        log::info!("generate_fxaa_shader: {:?}", shader_file_path);
+    }
+}
+
+impl FXAA {
+    pub fn create_descriptor_sets(&mut self, renderer_data: &RendererData, resources: &Resources, source_target: &TextureData, resolve_target: &TextureData) {
+        let render_pass_pipeline_data = resources.get_render_pass_pipeline_data(FXAA_RENDER_PASS_NAME, FXAA_RENDER_PASS_NAME);
+        let pipeline_data = render_pass_pipeline_data._pipeline_data.borrow();
+        let descriptor_data = &pipeline_data._descriptor_data;
+        let descriptor_binding_indices: Vec<u32> = descriptor_data._descriptor_data_create_infos.iter().map(|descriptor_data_create_info| {
+            descriptor_data_create_info._descriptor_binding_index
+        }).collect();
+        let descriptor_resource_infos_list: SwapchainArray<Vec<DescriptorResourceInfo>> = constants::SWAPCHAIN_IMAGE_INDICES.iter().map(|_swapchain_index| {
+            vec![
+                DescriptorResourceInfo::DescriptorImageInfo(source_target.get_default_image_info().clone()),
+                DescriptorResourceInfo::DescriptorImageInfo(resolve_target.get_default_image_info().clone()),
+            ]
+        }).collect();
+        let descriptor_sets = descriptor::create_descriptor_sets(renderer_data.get_device(), descriptor_data, FXAA_RENDER_PASS_NAME);
+        let _write_descriptor_sets = descriptor::create_write_descriptor_sets_with_update(
+            renderer_data.get_device(),
+            &descriptor_sets,
+            &descriptor_binding_indices,
+            &descriptor_data._descriptor_set_layout_bindings,
+            &descriptor_resource_infos_list,
+        );
+        self._descriptor_sets = descriptor_sets;
+    }
+
+    pub fn destroy_descriptor_sets(&mut self) {
+        self._descriptor_sets.clear();
+    }
+
+    pub fn set_fxaa_settings(&mut self, settings: &FXAASettings) {
+        if self._settings._source_target_name != settings._source_target_name || self._settings._resolve_target_name != settings._resolve_target_name {
+            self._descriptor_sets.clear();
+        }
+        self._settings = settings.clone();
+    }
+
+    pub fn dispatch_fxaa(&self, command_buffer: vk::CommandBuffer, swapchain_index: u32, anti_aliasing_mode: AntiAliasingMode, renderer_data: &RendererData, resources: &Resources) {
+        if self._descriptor_sets.is_empty() || AntiAliasingMode::TAA == anti_aliasing_mode {
+            return;
+        }
+        let resolve_target = renderer_data.get_render_target_from_str(&self._settings._resolve_target_name);
+        let (image_width, image_height, resolve_image) = (resolve_target._image_width, resolve_target._image_height, resolve_target._image);
+        let push_constant_data = PushConstant_FXAA {
+            _image_size: Vector2::new(image_width as i32, image_height as i32),
+            _mode: if AntiAliasingMode::FXAA == anti_aliasing_mode { FXAA_MODE_FXAA } else { FXAA_MODE_COPY },
+            _subpixel_quality: self._settings._subpixel_quality,
+            _edge_threshold: self._settings._edge_threshold,
+            _edge_threshold_min: self._settings._edge_threshold_min,
+            _reserved0: 0.0,
+            _reserved1: 0.0,
+        };
+        let image_memory_barrier = |src_access_mask: vk::AccessFlags, dst_access_mask: vk::AccessFlags| vk::ImageMemoryBarrier {
+            src_access_mask,
+            dst_access_mask,
+            old_layout: vk::ImageLayout::GENERAL,
+            new_layout: vk::ImageLayout::GENERAL,
+            src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            image: resolve_image,
+            subresource_range: vk::ImageSubresourceRange {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                base_mip_level: 0,
+                level_count: 1,
+                base_array_layer: 0,
+                layer_count: 1,
+            },
+            ..Default::default()
+        };
+
+        // the resolve target of the previous frame is read by the post process and render_final
+        renderer_data.pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::FRAGMENT_SHADER | vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[image_memory_barrier(vk::AccessFlags::SHADER_READ, vk::AccessFlags::SHADER_WRITE)]
+        );
+        let render_pass_pipeline_data = resources.get_render_pass_pipeline_data(FXAA_RENDER_PASS_NAME, FXAA_RENDER_PASS_NAME);
+        let pipeline_data = render_pass_pipeline_data._pipeline_data.borrow();
+        renderer_data.begin_debug_label(command_buffer, FXAA_RENDER_PASS_NAME);
+        renderer_data.begin_compute_pipeline(command_buffer, &pipeline_data);
+        unsafe {
+            renderer_data.get_device().cmd_bind_descriptor_sets(
+                command_buffer,
+                pipeline_data._pipeline_bind_point,
+                pipeline_data._pipeline_layout,
+                0,
+                &[self._descriptor_sets[swapchain_index as usize]],
+                &[]
+            );
+        }
+        renderer_data.upload_push_constant_data(command_buffer, &pipeline_data, &push_constant_data);
+        renderer_data.dispatch_compute_pipeline(
+            command_buffer,
+            (image_width + FXAA_WORK_GROUP_SIZE - 1) / FXAA_WORK_GROUP_SIZE,
+            (image_height + FXAA_WORK_GROUP_SIZE - 1) / FXAA_WORK_GROUP_SIZE,
+            1
+        );
+        renderer_data.end_debug_label(command_buffer);
+
+        renderer_data.pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::PipelineStageFlags::FRAGMENT_SHADER | vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[image_memory_barrier(vk::AccessFlags::SHADER_WRITE, vk::AccessFlags::SHADER_READ)]
+        );
+    }
+}
@@ -0,0 +1,492 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use ash::{ vk, Device };
+use ash::version::DeviceV1_0;
+use nalgebra::Matrix4;
+
+use crate::constants;
+use crate::renderer::renderer::RendererData;
+use crate::resource::resource::Resources;
+use crate::vulkan_context::buffer::{ self, BufferData, ShaderBufferData };
+use crate::vulkan_context::descriptor::{ self, DescriptorDataCreateInfo, DescriptorResourceInfo, DescriptorResourceType };
+use crate::vulkan_context::geometry_buffer::{ GeometryData, StaticVertexData };
+use crate::vulkan_context::render_pass::{ PipelineDataCreateInfo, RenderPassDataCreateInfo };
+use crate::vulkan_context::shader::SHADER_DIRECTORY;
+use crate::vulkan_context::vulkan_context::SwapchainArray;
+
+pub const GPU_SKINNING_WORK_GROUP_SIZE: u32 = 64; // must match with gpu_skinning.comp
+pub const GPU_SKINNING_MAX_BONE_MATRIX_COUNT: u32 = 16384; // the current and the previous matrices of all the skinned objects of a frame
+pub const GPU_SKINNING_MAX_VERTEX_COUNT: u32 = 262144; // the skinned vertices of all the skinned objects of a frame
+pub const GPU_SKINNING_RENDER_PASS_NAME: &str = "gpu_skinning";
+// the skeletal shaders of the project read the bone matrices of RenderElementData::_bone_matrix_offset from this buffer
+pub const BONE_MATRIX_BUFFER_NAME: &str = "BoneMatrixStorageBuffer";
+pub const SKINNED_VERTEX_BUFFER_NAME: &str = "SkinnedVertexBuffer";
+pub const INVALID_SKINNING_OFFSET: u32 = std::u32::MAX;
+pub const GPU_SKINNING_SHADER_FILE: &str = "common/gpu_skinning.comp";
+pub const GPU_SKINNING_SHADER_SOURCE: &str = r#"#version 450
+
+#define GPU_SKINNING_WORK_GROUP_SIZE 64
+#define SKELETAL_VERTEX_STRIDE 20
+#define STATIC_VERTEX_STRIDE 12
+
+layout(local_size_x = GPU_SKINNING_WORK_GROUP_SIZE, local_size_y = 1, local_size_z = 1) in;
+
+// SkeletalVertexData: position, normal, tangent, color, texcoord, bone_indices, bone_weights
+layout(std430, binding = 0) readonly buffer SkeletalVertexBuffer
+{
+    uint skeletal_vertices[];
+};
+
+layout(std430, binding = 1) readonly buffer BoneMatrixStorageBuffer
+{
+    mat4 bone_matrices[];
+};
+
+// StaticVertexData: position, normal, tangent, color, texcoord
+layout(std430, binding = 2) writeonly buffer SkinnedVertexBuffer
+{
+    uint skinned_vertices[];
+};
+
+layout(push_constant) uniform PushConstant_GpuSkinning
+{
+    uint vertex_count;
+    uint bone_matrix_offset;
+    uint skinned_vertex_offset;
+    uint reserved0;
+} pushConstant;
+
+vec3 load_vec3(uint index)
+{
+    return vec3(uintBitsToFloat(skeletal_vertices[index]), uintBitsToFloat(skeletal_vertices[index + 1]), uintBitsToFloat(skeletal_vertices[index + 2]));
+}
+
+void store_vec3(uint index, vec3 value)
+{
+    skinned_vertices[index] = floatBitsToUint(value.x);
+    skinned_vertices[index + 1] = floatBitsToUint(value.y);
+    skinned_vertices[index + 2] = floatBitsToUint(value.z);
+}
+
+void main()
+{
+    uint vertex_index = gl_GlobalInvocationID.x;
+    if(pushConstant.vertex_count <= vertex_index)
+    {
+        return;
+    }
+
+    uint src = vertex_index * SKELETAL_VERTEX_STRIDE;
+    uvec4 bone_indices = uvec4(skeletal_vertices[src + 12], skeletal_vertices[src + 13], skeletal_vertices[src + 14], skeletal_vertices[src + 15]);
+    vec4 bone_weights = uintBitsToFloat(uvec4(skeletal_vertices[src + 16], skeletal_vertices[src + 17], skeletal_vertices[src + 18], skeletal_vertices[src + 19]));
+    mat4 skin_matrix = mat4(0.0);
+    for(int i = 0; i < 4; ++i)
+    {
+        skin_matrix += bone_matrices[pushConstant.bone_matrix_offset + bone_indices[i]] * bone_weights[i];
+    }
+
+    // model space, the world matrix is applied by the static pipeline
+    uint dst = (pushConstant.skinned_vertex_offset + vertex_index) * STATIC_VERTEX_STRIDE;
+    store_vec3(dst, (skin_matrix * vec4(load_vec3(src), 1.0)).xyz);
+    store_vec3(dst + 3, normalize((skin_matrix * vec4(load_vec3(src + 3), 0.0)).xyz));
+    store_vec3(dst + 6, normalize((skin_matrix * vec4(load_vec3(src + 6), 0.0)).xyz));
+    skinned_vertices[dst + 9] = skeletal_vertices[src + 9];
+    skinned_vertices[dst + 10] = skeletal_vertices[src + 10];
+    skinned_vertices[dst + 11] = skeletal_vertices[src + 11];
+}
+"#;
+
+// must match with gpu_skinning.comp
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Default)]
+#[repr(C)]
+pub struct PushConstant_GpuSkinning {
+    pub _vertex_count: u32,
+    pub _bone_matrix_offset: u32,
+    pub _skinned_vertex_offset: u32,
+    pub _reserved0: u32,
+}
+
+crate::impl_push_constant!(PushConstant_GpuSkinning, 16);
+
+// the bone matrices of an object in the bone matrix buffer, shared by its geometries
+#[derive(Clone, Debug, Copy, PartialEq, Eq)]
+pub struct BoneMatrixAllocation {
+    pub _bone_matrix_offset: u32,
+    pub _prev_bone_matrix_offset: u32, // the previous frame for the velocity
+}
+
+// a geometry of a skinned object, the static vertices of SKINNED_VERTEX_BUFFER_NAME from _skinned_vertex_offset
+#[derive(Clone, Debug)]
+pub struct SkinnedGeometry {
+    pub _geometry_vertex_buffer: vk::Buffer,
+    pub _vertex_count: u32,
+    pub _bone_matrix_allocation: BoneMatrixAllocation,
+    pub _skinned_vertex_offset: u32,
+}
+
+pub fn get_render_pass_data_create_info() -> RenderPassDataCreateInfo {
+    let storage_buffer = |binding_index: u32, descriptor_name: &str| DescriptorDataCreateInfo {
+        _descriptor_binding_index: binding_index,
+        _descriptor_name: String::from(descriptor_name),
+        _descriptor_resource_type: DescriptorResourceType::StorageBuffer,
+        _descriptor_shader_stage: vk::ShaderStageFlags::COMPUTE,
+        ..Default::default()
+    };
+    let pipeline_data_create_infos = vec![
+        PipelineDataCreateInfo {
+            _pipeline_data_create_info_name: String::from(GPU_SKINNING_RENDER_PASS_NAME),
+            _pipeline_bind_point: vk::PipelineBindPoint::COMPUTE,
+            _pipeline_compute_shader_file: PathBuf::from(GPU_SKINNING_SHADER_FILE),
+            _push_constant_ranges: vec![vk::PushConstantRange {
+                stage_flags: vk::ShaderStageFlags::COMPUTE,
+                offset: 0,
+                size: std::mem::size_of::<PushConstant_GpuSkinning>() as u32,
+            }],
+            _descriptor_data_create_infos: vec![
+                storage_buffer(0, "SkeletalVertexBuffer"),
+                storage_buffer(1, BONE_MATRIX_BUFFER_NAME),
+                storage_buffer(2, SKINNED_VERTEX_BUFFER_NAME),
+            ],
+            ..Default::default()
+        }
+    ];
+
+    RenderPassDataCreateInfo {
+        _render_pass_create_info_name: String::from(GPU_SKINNING_RENDER_PASS_NAME),
+        _pipeline_data_create_infos: pipeline_data_create_infos,
+        ..Default::default()
+    }
+}
+
+#[cfg(not(target_os = "android"))]
+pub fn generate_gpu_skinning_shader() {
+    let mut shader_file_path = PathBuf::from(SHADER_DIRECTORY);
+    shader_file_path.push(GPU_SKINNING_SHADER_FILE);
+    if false == shader_file_path.is_file() {
+        fs::create_dir_all(shader_file_path.parent().unwrap()).expect("Failed to create directories.");
+        fs::write(&shader_file_path, GPU_SKINNING_SHADER_SOURCE).expect("Failed to write");
+        log::info!("generate_gpu_skinning_shader: {:?}", shader_file_path);
+    }
+}
+
+pub fn is_gpu_skinning_enabled() -> bool {
+    unsafe { constants::ENABLE_GPU_SKINNING }
+}
+
+// the skinning pre-pass before the shadow and the opaque passes: the bone matrices of every skinned object have their own range
+// of the bone matrix buffer, the compute pass writes the skinned vertices into ranges of a single vertex buffer,
+// so both passes draw the skinned geometries like the static geometries and the skinning runs once per frame.
+#[derive(Default)]
+pub struct GpuSkinning {
+    pub _bone_matrix_buffer: Option<ShaderBufferData>,
+    pub _skinned_vertex_buffer: Option<BufferData>,
+    pub _descriptor_sets_map: HashMap<vk::Buffer, SwapchainArray<vk::DescriptorSet>>, // the vertex buffer of the geometry -> descriptor sets, created on demand
+    pub _bone_matrices: Vec<Matrix4<f32>>,
+    pub _bone_matrix_allocations: HashMap<u32, BoneMatrixAllocation>, // object id
+    pub _skinned_geometries: HashMap<(u32, vk::Buffer), SkinnedGeometry>, // object id, the vertex buffer of the geometry
+    pub _skinned_vertex_count: u32,
+    pub _is_dispatched: bool,
+}
+
+impl GpuSkinning {
+    pub fn create_gpu_skinning_buffers(&mut self, renderer_data: &RendererData) {
+        // host visible, written every frame like the instance matrices
+        self._bone_matrix_buffer = Some(buffer::create_shader_buffer_data(
+            renderer_data.get_device(),
+            renderer_data.get_device_memory_properties(),
+            &String::from(BONE_MATRIX_BUFFER_NAME),
+            vk::BufferUsageFlags::STORAGE_BUFFER,
+            (std::mem::size_of::<Matrix4<f32>>() as u32 * GPU_SKINNING_MAX_BONE_MATRIX_COUNT) as vk::DeviceSize,
+            false,
+            false,
+            false,
+        ));
+        let skinned_vertex_buffer = buffer::create_buffer_data(
+            renderer_data.get_device(),
+            renderer_data.get_device_memory_properties(),
+            (std::mem::size_of::<StaticVertexData>() as u32 * GPU_SKINNING_MAX_VERTEX_COUNT) as vk::DeviceSize,
+            vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::VERTEX_BUFFER,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL
+        );
+        buffer::set_buffer_object_names(std::slice::from_ref(&skinned_vertex_buffer), SKINNED_VERTEX_BUFFER_NAME, true);
+        self._skinned_vertex_buffer = Some(skinned_vertex_buffer);
+    }
+
+    pub fn destroy_gpu_skinning_buffers(&mut self, device: &Device) {
+        if let Some(mut bone_matrix_buffer) = self._bone_matrix_buffer.take() {
+            buffer::destroy_shader_buffer_data(device, &mut bone_matrix_buffer);
+        }
+        if let Some(skinned_vertex_buffer) = self._skinned_vertex_buffer.take() {
+            buffer::destroy_buffer_data(device, &skinned_vertex_buffer);
+        }
+        self.begin_frame();
+    }
+
+    pub fn destroy_descriptor_sets(&mut self) {
+        self._descriptor_sets_map.clear();
+    }
+
+    pub fn begin_frame(&mut self) {
+        self._bone_matrices.clear();
+        self._bone_matrix_allocations.clear();
+        self._skinned_geometries.clear();
+        self._skinned_vertex_count = 0;
+        self._is_dispatched = false;
+    }
+
+    // once per object and frame, None when the bone matrix buffer is full
+    pub fn allocate_bone_matrices(&mut self, object_id: u32, bone_matrices: &[Matrix4<f32>], prev_bone_matrices: &[Matrix4<f32>]) -> Option<BoneMatrixAllocation> {
+        if let Some(bone_matrix_allocation) = self._bone_matrix_allocations.get(&object_id) {
+            return Some(*bone_matrix_allocation);
+        }
+        let bone_count = bone_matrices.len();
+        if (GPU_SKINNING_MAX_BONE_MATRIX_COUNT as usize) < self._bone_matrices.len() + bone_count * 2 {
+            return None;
+        }
+        let bone_matrix_allocation = BoneMatrixAllocation {
+            _bone_matrix_offset: self._bone_matrices.len() as u32,
+            _prev_bone_matrix_offset: (self._bone_matrices.len() + bone_count) as u32,
+        };
+        self._bone_matrices.extend_from_slice(bone_matrices);
+        // the first frame of an object has no previous matrices
+        if prev_bone_matrices.len() == bone_count {
+            self._bone_matrices.extend_from_slice(prev_bone_matrices);
+        } else {
+            self._bone_matrices.extend_from_slice(bone_matrices);
+        }
+        self._bone_matrix_allocations.insert(object_id, bone_matrix_allocation);
+        Some(bone_matrix_allocation)
+    }
+
+    // None when the skinned vertex buffer is full, the geometry is drawn with the skeletal pipeline then
+    pub fn allocate_skinned_geometry(&mut self, object_id: u32, geometry_data: &GeometryData, bone_matrix_allocation: &BoneMatrixAllocation) -> Option<&SkinnedGeometry> {
+        let key = (object_id, geometry_data._vertex_buffer_data._buffer);
+        if false == self._skinned_geometries.contains_key(&key) {
+            let vertex_count = geometry_data._vertex_count;
+            if GPU_SKINNING_MAX_VERTEX_COUNT < self._skinned_vertex_count + vertex_count {
+                return None;
+            }
+            self._skinned_geometries.insert(key, SkinnedGeometry {
+                _geometry_vertex_buffer: geometry_data._vertex_buffer_data._buffer,
+                _vertex_count: vertex_count,
+                _bone_matrix_allocation: *bone_matrix_allocation,
+                _skinned_vertex_offset: self._skinned_vertex_count,
+            });
+            self._skinned_vertex_count += vertex_count;
+        }
+        self._skinned_geometries.get(&key)
+    }
+
+    pub fn get_bone_matrix_allocation(&self, object_id: u32) -> Option<&BoneMatrixAllocation> {
+        self._bone_matrix_allocations.get(&object_id)
+    }
+
+    // only after the dispatch of this frame
+    pub fn get_skinned_geometry(&self, object_id: u32, geometry_data: &GeometryData) -> Option<&SkinnedGeometry> {
+        if self._is_dispatched {
+            self._skinned_geometries.get(&(object_id, geometry_data._vertex_buffer_data._buffer))
+        } else {
+            None
+        }
+    }
+
+    fn get_descriptor_sets(&mut self, renderer_data: &RendererData, resources: &Resources, geometry_vertex_buffer: vk::Buffer) -> SwapchainArray<vk::DescriptorSet> {
+        if let Some(descriptor_sets) = self._descriptor_sets_map.get(&geometry_vertex_buffer) {
+            return descriptor_sets.clone();
+        }
+        let render_pass_pipeline_data = resources.get_render_pass_pipeline_data(GPU_SKINNING_RENDER_PASS_NAME, GPU_SKINNING_RENDER_PASS_NAME);
+        let pipeline_data = render_pass_pipeline_data._pipeline_data.borrow();
+        let descriptor_data = &pipeline_data._descriptor_data;
+        let descriptor_binding_indices: Vec<u32> = descriptor_data._descriptor_data_create_infos.iter().map(|descriptor_data_create_info| {
+            descriptor_data_create_info._descriptor_binding_index
+        }).collect();
+        let bone_matrix_buffer = self._bone_matrix_buffer.as_ref().unwrap();
+        let skinned_vertex_buffer = self._skinned_vertex_buffer.as_ref().unwrap()._buffer;
+        let descriptor_resource_infos_list: SwapchainArray<Vec<DescriptorResourceInfo>> = constants::SWAPCHAIN_IMAGE_INDICES.iter().map(|swapchain_index| {
+            vec![
+                DescriptorResourceInfo::DescriptorBufferInfo(vk::DescriptorBufferInfo { buffer: geometry_vertex_buffer, offset: 0, range: vk::WHOLE_SIZE }),
+                bone_matrix_buffer._descriptor_buffer_infos[*swapchain_index].clone(),
+                DescriptorResourceInfo::DescriptorBufferInfo(vk::DescriptorBufferInfo { buffer: skinned_vertex_buffer, offset: 0, range: vk::WHOLE_SIZE }),
+            ]
+        }).collect();
+        let descriptor_sets = descriptor::create_descriptor_sets(renderer_data.get_device(), descriptor_data, GPU_SKINNING_RENDER_PASS_NAME);
+        let _write_descriptor_sets = descriptor::create_write_descriptor_sets_with_update(
+            renderer_data.get_device(),
+            &descriptor_sets,
+            &descriptor_binding_indices,
+            &descriptor_data._descriptor_set_layout_bindings,
+            &descriptor_resource_infos_list,
+        );
+        self._descriptor_sets_map.insert(geometry_vertex_buffer, descriptor_sets.clone());
+        descriptor_sets
+    }
+
+    // the allocations of this frame: upload the bone matrices, a dispatch per skinned geometry, then the barrier for the vertex input.
+    pub fn dispatch_gpu_skinning(&mut self, command_buffer: vk::CommandBuffer, swapchain_index: u32, renderer_data: &RendererData, resources: &Resources) {
+        if self._bone_matrices.is_empty() {
+            return;
+        }
+        let index = swapchain_index as usize;
+        renderer_data.upload_shader_buffer_datas(command_buffer, swapchain_index, self._bone_matrix_buffer.as_ref().unwrap(), &self._bone_matrices);
+        let bone_matrix_barriers = [vk::BufferMemoryBarrier {
+            src_access_mask: vk::AccessFlags::HOST_WRITE,
+            dst_access_mask: vk::AccessFlags::SHADER_READ,
+            src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            buffer: self._bone_matrix_buffer.as_ref().unwrap()._buffers[index]._buffer,
+            offset: 0,
+            size: vk::WHOLE_SIZE,
+            ..Default::default()
+        }];
+        renderer_data.pipeline_barrier(command_buffer, vk::PipelineStageFlags::HOST, vk::PipelineStageFlags::COMPUTE_SHADER | vk::PipelineStageFlags::VERTEX_SHADER, vk::DependencyFlags::empty(), &[], &bone_matrix_barriers, &[]);
+
+        let mut skinned_geometries: Vec<SkinnedGeometry> = self._skinned_geometries.values().cloned().collect();
+        skinned_geometries.sort_by_key(|skinned_geometry| skinned_geometry._skinned_vertex_offset);
+        let render_pass_pipeline_data = resources.get_render_pass_pipeline_data(GPU_SKINNING_RENDER_PASS_NAME, GPU_SKINNING_RENDER_PASS_NAME);
+        let pipeline_data = render_pass_pipeline_data._pipeline_data.borrow();
+        renderer_data.begin_debug_label(command_buffer, GPU_SKINNING_RENDER_PASS_NAME);
+        renderer_data.begin_compute_pipeline(command_buffer, &pipeline_data);
+        for skinned_geometry in skinned_geometries.iter() {
+            let descriptor_sets = self.get_descriptor_sets(renderer_data, resources, skinned_geometry._geometry_vertex_buffer);
+            unsafe {
+                renderer_data.get_device().cmd_bind_descriptor_sets(
+                    command_buffer,
+                    pipeline_data._pipeline_bind_point,
+                    pipeline_data._pipeline_layout,
+                    0,
+                    &[descriptor_sets[index]],
+                    &[]
+                );
+            }
+            let push_constant_data = PushConstant_GpuSkinning {
+                _vertex_count: skinned_geometry._vertex_count,
+                _bone_matrix_offset: skinned_geometry._bone_matrix_allocation._bone_matrix_offset,
+                _skinned_vertex_offset: skinned_geometry._skinned_vertex_offset,
+                ..Default::default()
+            };
+            renderer_data.upload_push_constant_data(command_buffer, &pipeline_data, &push_constant_data);
+            renderer_data.dispatch_compute_pipeline(command_buffer, (skinned_geometry._vertex_count + GPU_SKINNING_WORK_GROUP_SIZE - 1) / GPU_SKINNING_WORK_GROUP_SIZE, 1, 1);
+        }
+        renderer_data.end_debug_label(command_buffer);
+
+        let skinned_vertex_barriers = [vk::BufferMemoryBarrier {
+            src_access_mask: vk::AccessFlags::SHADER_WRITE,
+            dst_access_mask: vk::AccessFlags::VERTEX_ATTRIBUTE_READ,
+            src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            buffer: self._skinned_vertex_buffer.as_ref().unwrap()._buffer,
+            offset: 0,
+            size: vk::WHOLE_SIZE,
+            ..Default::default()
+        }];
+        renderer_data.pipeline_barrier(command_buffer, vk::PipelineStageFlags::COMPUTE_SHADER, vk::PipelineStageFlags::VERTEX_INPUT, vk::DependencyFlags::empty(), &[], &skinned_vertex_barriers, &[]);
+        self._is_dispatched = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ash::vk::Handle;
+    use nalgebra::{ Vector3, Vector4 };
+    use crate::vulkan_context::geometry_buffer::SkeletalVertexData;
+
+    fn create_test_geometry_data(vertex_buffer: u64, vertex_count: u32) -> GeometryData {
+        let mut geometry_data = GeometryData {
+            _geometry_name: String::from("test_geometry"),
+            _vertex_buffer_data: BufferData::default(),
+            _index_buffer_data: BufferData::default(),
+            _vertex_index_count: 0,
+            _vertex_count: vertex_count,
+            _geometry_bounding_box: Default::default(),
+        };
+        geometry_data._vertex_buffer_data._buffer = vk::Buffer::from_raw(vertex_buffer);
+        geometry_data
+    }
+
+    // the position of gpu_skinning.comp
+    fn skin_position(bone_matrices: &[Matrix4<f32>], bone_matrix_offset: u32, vertex: &SkeletalVertexData) -> Vector3<f32> {
+        let mut skin_matrix = Matrix4::zeros();
+        for i in 0..4 {
+            skin_matrix += bone_matrices[(bone_matrix_offset + vertex._bone_indices[i]) as usize] * vertex._bone_weights[i];
+        }
+        (skin_matrix * vertex._position.push(1.0)).xyz()
+    }
+
+    #[test]
+    fn test_bone_matrices_of_multiple_objects() {
+        let mut gpu_skinning = GpuSkinning::default();
+        let pose_a = vec![Matrix4::new_translation(&Vector3::new(1.0, 0.0, 0.0)), Matrix4::new_translation(&Vector3::new(2.0, 0.0, 0.0))];
+        let pose_b = vec![Matrix4::new_translation(&Vector3::new(0.0, 10.0, 0.0)), Matrix4::new_translation(&Vector3::new(0.0, 20.0, 0.0))];
+        let prev_pose_b = vec![Matrix4::identity(); 2];
+        let allocation_a = gpu_skinning.allocate_bone_matrices(1, &pose_a, &[]).unwrap();
+        let allocation_b = gpu_skinning.allocate_bone_matrices(2, &pose_b, &prev_pose_b).unwrap();
+        assert_eq!(BoneMatrixAllocation { _bone_matrix_offset: 0, _prev_bone_matrix_offset: 2 }, allocation_a);
+        assert_eq!(BoneMatrixAllocation { _bone_matrix_offset: 4, _prev_bone_matrix_offset: 6 }, allocation_b);
+        // the geometries of an object share the allocation
+        assert_eq!(Some(allocation_a), gpu_skinning.allocate_bone_matrices(1, &pose_b, &[]));
+        assert_eq!(8, gpu_skinning._bone_matrices.len());
+        // the first frame of an object uses the current matrices as the previous
+        assert_eq!(&pose_a[..], &gpu_skinning._bone_matrices[2..4]);
+        assert_eq!(&prev_pose_b[..], &gpu_skinning._bone_matrices[6..8]);
+
+        // the same vertex of both objects is skinned with its own matrices, the second object doesn't stomp the first
+        let vertex = SkeletalVertexData {
+            _bone_indices: Vector4::new(0, 1, 0, 0),
+            _bone_weights: Vector4::new(0.5, 0.5, 0.0, 0.0),
+            ..Default::default()
+        };
+        assert_eq!(Vector3::new(1.5, 0.0, 0.0), skin_position(&gpu_skinning._bone_matrices, allocation_a._bone_matrix_offset, &vertex));
+        assert_eq!(Vector3::new(0.0, 15.0, 0.0), skin_position(&gpu_skinning._bone_matrices, allocation_b._bone_matrix_offset, &vertex));
+        assert_eq!(Vector3::zeros(), skin_position(&gpu_skinning._bone_matrices, allocation_b._prev_bone_matrix_offset, &vertex));
+
+        gpu_skinning.begin_frame();
+        assert!(gpu_skinning._bone_matrices.is_empty());
+        assert_eq!(None, gpu_skinning.get_bone_matrix_allocation(1));
+        assert_eq!(Some(allocation_a), gpu_skinning.allocate_bone_matrices(2, &pose_b, &prev_pose_b));
+    }
+
+    #[test]
+    fn test_bone_matrix_buffer_is_full() {
+        let mut gpu_skinning = GpuSkinning::default();
+        let bone_matrices = vec![Matrix4::identity(); (GPU_SKINNING_MAX_BONE_MATRIX_COUNT / 4) as usize];
+        assert!(gpu_skinning.allocate_bone_matrices(1, &bone_matrices, &[]).is_some());
+        assert!(gpu_skinning.allocate_bone_matrices(2, &bone_matrices, &[]).is_some());
+        assert_eq!(GPU_SKINNING_MAX_BONE_MATRIX_COUNT as usize, gpu_skinning._bone_matrices.len());
+        assert_eq!(None, gpu_skinning.allocate_bone_matrices(3, &bone_matrices[..1], &[]));
+    }
+
+    #[test]
+    fn test_skinned_geometries() {
+        let mut gpu_skinning = GpuSkinning::default();
+        let bone_matrices = vec![Matrix4::identity(); 2];
+        let allocation_a = gpu_skinning.allocate_bone_matrices(1, &bone_matrices, &[]).unwrap();
+        let allocation_b = gpu_skinning.allocate_bone_matrices(2, &bone_matrices, &[]).unwrap();
+        let body = create_test_geometry_data(1, 100);
+        let head = create_test_geometry_data(2, 50);
+
+        // the same geometry of two objects has two ranges of the skinned vertices
+        assert_eq!(0, gpu_skinning.allocate_skinned_geometry(1, &body, &allocation_a).unwrap()._skinned_vertex_offset);
+        assert_eq!(100, gpu_skinning.allocate_skinned_geometry(1, &head, &allocation_a).unwrap()._skinned_vertex_offset);
+        let skinned_geometry = gpu_skinning.allocate_skinned_geometry(2, &body, &allocation_b).unwrap().clone();
+        assert_eq!(150, skinned_geometry._skinned_vertex_offset);
+        assert_eq!(allocation_b, skinned_geometry._bone_matrix_allocation);
+        assert_eq!(0, gpu_skinning.allocate_skinned_geometry(1, &body, &allocation_a).unwrap()._skinned_vertex_offset);
+        assert_eq!(250, gpu_skinning._skinned_vertex_count);
+
+        // the passes draw the skeletal pipeline until the dispatch of this frame
+        assert!(gpu_skinning.get_skinned_geometry(2, &body).is_none());
+        gpu_skinning._is_dispatched = true;
+        assert_eq!(150, gpu_skinning.get_skinned_geometry(2, &body).unwrap()._skinned_vertex_offset);
+        assert!(gpu_skinning.get_skinned_geometry(3, &body).is_none());
+
+        // the skinned vertex buffer is full
+        let large = create_test_geometry_data(3, GPU_SKINNING_MAX_VERTEX_COUNT - 250 + 1);
+        assert!(gpu_skinning.allocate_skinned_geometry(1, &large, &allocation_a).is_none());
+        let large = create_test_geometry_data(3, GPU_SKINNING_MAX_VERTEX_COUNT - 250);
+        assert!(gpu_skinning.allocate_skinned_geometry(1, &large, &allocation_a).is_some());
+    }
+}
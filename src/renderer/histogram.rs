@@ -0,0 +1,658 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use ash::{ vk, Device };
+use ash::version::DeviceV1_0;
+use nalgebra::Vector2;
+
+use crate::constants;
+use crate::renderer::renderer::RendererData;
+use crate::resource::resource::Resources;
+use crate::utilities::math;
+use crate::vulkan_context::buffer::{ self, ShaderBufferData };
+use crate::vulkan_context::descriptor::{ self, DescriptorDataCreateInfo, DescriptorResourceInfo, DescriptorResourceType };
+use crate::vulkan_context::render_pass::{ PipelineDataCreateInfo, RenderPassDataCreateInfo };
+use crate::vulkan_context::shader::SHADER_DIRECTORY;
+use crate::vulkan_context::texture::TextureData;
+use crate::vulkan_context::vulkan_context::SwapchainArray;
+
+pub const HISTOGRAM_BIN_COUNT: usize = 256; // must match with debug_histogram.comp
+pub const HISTOGRAM_WORK_GROUP_SIZE: u32 = 16; // must match with debug_histogram.comp
+pub const HISTOGRAM_FLAG_LOG_SCALE: u32 = 1 << 0; // must match with debug_histogram.comp
+pub const HISTOGRAM_FLAG_LINEARIZE_DEPTH: u32 = 1 << 1; // must match with debug_histogram.comp
+pub const HISTOGRAM_LOG_SCALE_EPSILON: f32 = 0.000001;
+pub const HISTOGRAM_RENDER_PASS_NAME: &str = "debug_histogram";
+pub const HISTOGRAM_BUFFER_NAME: &str = "DebugHistogramBuffer";
+pub const HISTOGRAM_SHADER_FILE: &str = "debug/debug_histogram.comp";
+pub const HISTOGRAM_SHADER_SOURCE: &str = r#"#version 450
+
+#define HISTOGRAM_BIN_COUNT 256
+#define HISTOGRAM_WORK_GROUP_SIZE 16
+#define HISTOGRAM_FLAG_LOG_SCALE 1
+#define HISTOGRAM_FLAG_LINEARIZE_DEPTH 2
+#define HISTOGRAM_LOG_SCALE_EPSILON 0.000001
+
+layout(local_size_x = HISTOGRAM_WORK_GROUP_SIZE, local_size_y = HISTOGRAM_WORK_GROUP_SIZE, local_size_z = 1) in;
+
+layout(binding = 0) uniform sampler2D texture_target;
+layout(std430, binding = 1) buffer DebugHistogramBuffer
+{
+    uint bins[HISTOGRAM_BIN_COUNT];
+    uint min_value;
+    uint max_value;
+    uint pixel_count;
+    uint reserved0;
+};
+
+layout(push_constant) uniform PushConstant_DebugHistogram
+{
+    ivec2 image_size;
+    uint channel;
+    uint flags;
+    float range_min;
+    float range_max;
+    float near;
+    float far;
+} pushConstant;
+
+shared uint shared_bins[HISTOGRAM_BIN_COUNT];
+
+uint float_to_ordered_bits(float value)
+{
+    uint bits = floatBitsToUint(value);
+    return (0u != (bits & 0x80000000u)) ? ~bits : (bits | 0x80000000u);
+}
+
+float map_value(float value)
+{
+    if(0u != (pushConstant.flags & HISTOGRAM_FLAG_LOG_SCALE))
+    {
+        float log_min = log2(max(pushConstant.range_min, HISTOGRAM_LOG_SCALE_EPSILON));
+        float log_max = log2(max(pushConstant.range_max, HISTOGRAM_LOG_SCALE_EPSILON));
+        return (log2(max(value, HISTOGRAM_LOG_SCALE_EPSILON)) - log_min) / max(log_max - log_min, HISTOGRAM_LOG_SCALE_EPSILON);
+    }
+    return (value - pushConstant.range_min) / max(pushConstant.range_max - pushConstant.range_min, HISTOGRAM_LOG_SCALE_EPSILON);
+}
+
+void main()
+{
+    uint local_index = gl_LocalInvocationIndex;
+    for(uint i = local_index; i < HISTOGRAM_BIN_COUNT; i += HISTOGRAM_WORK_GROUP_SIZE * HISTOGRAM_WORK_GROUP_SIZE)
+    {
+        shared_bins[i] = 0u;
+    }
+    barrier();
+
+    ivec2 pixel = ivec2(gl_GlobalInvocationID.xy);
+    if(all(lessThan(pixel, pushConstant.image_size)))
+    {
+        vec4 color = texelFetch(texture_target, pixel, 0);
+        float value = (0u == pushConstant.channel) ? dot(color.xyz, vec3(0.2126, 0.7152, 0.0722)) : color[pushConstant.channel - 1u];
+        if(0u != (pushConstant.flags & HISTOGRAM_FLAG_LINEARIZE_DEPTH))
+        {
+            // must match with math::linearize_depth
+#if defined(INFINITE_FAR_PLANE)
+            value = (0.0 < value) ? (pushConstant.near / value) : 3.402823e+38;
+#elif defined(REVERSED_Z)
+            value = pushConstant.near * pushConstant.far / (pushConstant.near + value * (pushConstant.far - pushConstant.near));
+#else
+            value = pushConstant.near * pushConstant.far / (pushConstant.far - value * (pushConstant.far - pushConstant.near));
+#endif
+        }
+        uint bin = uint(clamp(map_value(value), 0.0, 1.0) * float(HISTOGRAM_BIN_COUNT - 1) + 0.5);
+        atomicAdd(shared_bins[bin], 1u);
+        atomicMin(min_value, float_to_ordered_bits(value));
+        atomicMax(max_value, float_to_ordered_bits(value));
+        atomicAdd(pixel_count, 1u);
+    }
+    barrier();
+
+    for(uint i = local_index; i < HISTOGRAM_BIN_COUNT; i += HISTOGRAM_WORK_GROUP_SIZE * HISTOGRAM_WORK_GROUP_SIZE)
+    {
+        if(0u != shared_bins[i])
+        {
+            atomicAdd(bins[i], shared_bins[i]);
+        }
+    }
+}
+"#;
+
+#[derive(Clone, Debug, Copy, PartialEq, Eq)]
+pub enum HistogramChannel {
+    Luminance,
+    Red,
+    Green,
+    Blue,
+    Alpha,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct HistogramSettings {
+    pub _target_name: String,
+    pub _channel: HistogramChannel,
+    pub _range_min: f32, // float targets are mapped from [range_min, range_max] to the bins
+    pub _range_max: f32,
+    pub _log_scale: bool,
+    pub _linearize_depth: bool, // depth targets, the values are in the view space distance
+    pub _near: f32,
+    pub _far: f32,
+}
+
+impl Default for HistogramSettings {
+    fn default() -> HistogramSettings {
+        HistogramSettings {
+            _target_name: String::new(),
+            _channel: HistogramChannel::Luminance,
+            _range_min: 0.0,
+            _range_max: 1.0,
+            _log_scale: false,
+            _linearize_depth: false,
+            _near: unsafe { constants::NEAR },
+            _far: unsafe { constants::FAR },
+        }
+    }
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone)]
+#[repr(C)]
+pub struct PushConstant_DebugHistogram {
+    pub _image_size: Vector2<i32>,
+    pub _channel: u32,
+    pub _flags: u32,
+    pub _range_min: f32,
+    pub _range_max: f32,
+    pub _near: f32,
+    pub _far: f32,
+}
+
+crate::impl_push_constant!(PushConstant_DebugHistogram, 32);
+
+// must match with DebugHistogramBuffer of debug_histogram.comp
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct HistogramBufferData {
+    pub _bins: [u32; HISTOGRAM_BIN_COUNT],
+    pub _min_value: u32, // ordered float bits, see float_to_ordered_bits
+    pub _max_value: u32,
+    pub _pixel_count: u32,
+    pub _reserved0: u32,
+}
+
+impl Default for HistogramBufferData {
+    fn default() -> HistogramBufferData {
+        HistogramBufferData {
+            _bins: [0; HISTOGRAM_BIN_COUNT],
+            _min_value: std::u32::MAX,
+            _max_value: 0,
+            _pixel_count: 0,
+            _reserved0: 0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct HistogramResult {
+    pub _bins: Vec<u32>,
+    pub _max_bin_count: u32,
+    pub _min_value: f32,
+    pub _max_value: f32,
+    pub _mean_value: f32, // accurate to the width of a bin
+    pub _pixel_count: u32,
+}
+
+// the unsigned integer order of the bits is the order of the floats, so atomicMin/atomicMax work on floats.
+pub fn float_to_ordered_bits(value: f32) -> u32 {
+    let bits = value.to_bits();
+    if 0 != (bits & 0x8000_0000) { !bits } else { bits | 0x8000_0000 }
+}
+
+pub fn ordered_bits_to_float(bits: u32) -> f32 {
+    if 0 != (bits & 0x8000_0000) { f32::from_bits(bits & 0x7FFF_FFFF) } else { f32::from_bits(!bits) }
+}
+
+pub fn linearize_depth(depth: f32, near: f32, far: f32) -> f32 {
+    math::linearize_depth(depth, near, far)
+}
+
+pub fn get_channel_value(color: &[f32; 4], channel: HistogramChannel) -> f32 {
+    match channel {
+        HistogramChannel::Luminance => color[0] * 0.2126 + color[1] * 0.7152 + color[2] * 0.0722,
+        HistogramChannel::Red => color[0],
+        HistogramChannel::Green => color[1],
+        HistogramChannel::Blue => color[2],
+        HistogramChannel::Alpha => color[3],
+    }
+}
+
+// value -> [0, 1] of the histogram range
+pub fn map_histogram_value(value: f32, settings: &HistogramSettings) -> f32 {
+    if settings._log_scale {
+        let log_min = settings._range_min.max(HISTOGRAM_LOG_SCALE_EPSILON).log2();
+        let log_max = settings._range_max.max(HISTOGRAM_LOG_SCALE_EPSILON).log2();
+        (value.max(HISTOGRAM_LOG_SCALE_EPSILON).log2() - log_min) / (log_max - log_min).max(HISTOGRAM_LOG_SCALE_EPSILON)
+    } else {
+        (value - settings._range_min) / (settings._range_max - settings._range_min).max(HISTOGRAM_LOG_SCALE_EPSILON)
+    }
+}
+
+pub fn unmap_histogram_value(mapped_value: f32, settings: &HistogramSettings) -> f32 {
+    if settings._log_scale {
+        let log_min = settings._range_min.max(HISTOGRAM_LOG_SCALE_EPSILON).log2();
+        let log_max = settings._range_max.max(HISTOGRAM_LOG_SCALE_EPSILON).log2();
+        (log_min + mapped_value * (log_max - log_min)).exp2()
+    } else {
+        settings._range_min + mapped_value * (settings._range_max - settings._range_min)
+    }
+}
+
+pub fn get_histogram_bin(value: f32, settings: &HistogramSettings) -> usize {
+    let mapped_value = map_histogram_value(value, settings).max(0.0).min(1.0);
+    (mapped_value * (HISTOGRAM_BIN_COUNT - 1) as f32 + 0.5) as usize
+}
+
+pub fn get_histogram_bin_value(bin: usize, settings: &HistogramSettings) -> f32 {
+    unmap_histogram_value(bin as f32 / (HISTOGRAM_BIN_COUNT - 1) as f32, settings)
+}
+
+// cpu reference of debug_histogram.comp
+pub fn build_histogram(colors: &[[f32; 4]], settings: &HistogramSettings) -> HistogramBufferData {
+    let mut histogram_buffer_data = HistogramBufferData::default();
+    for color in colors.iter() {
+        let mut value = get_channel_value(color, settings._channel);
+        if settings._linearize_depth {
+            value = linearize_depth(value, settings._near, settings._far);
+        }
+        histogram_buffer_data._bins[get_histogram_bin(value, settings)] += 1;
+        histogram_buffer_data._min_value = histogram_buffer_data._min_value.min(float_to_ordered_bits(value));
+        histogram_buffer_data._max_value = histogram_buffer_data._max_value.max(float_to_ordered_bits(value));
+        histogram_buffer_data._pixel_count += 1;
+    }
+    histogram_buffer_data
+}
+
+impl HistogramResult {
+    pub fn create_histogram_result(histogram_buffer_data: &HistogramBufferData, settings: &HistogramSettings) -> HistogramResult {
+        let pixel_count: u64 = histogram_buffer_data._bins.iter().map(|count| *count as u64).sum();
+        let weighted_sum: f64 = histogram_buffer_data._bins.iter().enumerate().map(|(bin, count)| {
+            get_histogram_bin_value(bin, settings) as f64 * *count as f64
+        }).sum();
+        let is_empty = 0 == histogram_buffer_data._pixel_count;
+        HistogramResult {
+            _bins: histogram_buffer_data._bins.to_vec(),
+            _max_bin_count: histogram_buffer_data._bins.iter().cloned().max().unwrap_or(0),
+            _min_value: if is_empty { 0.0 } else { ordered_bits_to_float(histogram_buffer_data._min_value) },
+            _max_value: if is_empty { 0.0 } else { ordered_bits_to_float(histogram_buffer_data._max_value) },
+            _mean_value: if 0 < pixel_count { (weighted_sum / pixel_count as f64) as f32 } else { 0.0 },
+            _pixel_count: histogram_buffer_data._pixel_count,
+        }
+    }
+
+    // bar heights for the overlay graph, the largest bin is 1.0
+    pub fn get_normalized_bars(&self) -> Vec<f32> {
+        let max_bin_count = self._max_bin_count.max(1) as f32;
+        self._bins.iter().map(|count| *count as f32 / max_bin_count).collect()
+    }
+
+    pub fn get_summary_text(&self, settings: &HistogramSettings) -> String {
+        format!("histogram {} {:?}{}: min {:.4} max {:.4} mean {:.4} pixels {}",
+            settings._target_name,
+            settings._channel,
+            if settings._log_scale { " log" } else { "" },
+            self._min_value,
+            self._max_value,
+            self._mean_value,
+            self._pixel_count
+        )
+    }
+}
+
+// Analysis of a render target: compute pass -> storage buffer -> readback of the same swapchain index in the next use.
+pub struct DebugHistogram {
+    pub _is_enabled: bool,
+    pub _settings: HistogramSettings,
+    pub _histogram_buffer: Option<ShaderBufferData>,
+    pub _descriptor_sets_map: HashMap<String, SwapchainArray<vk::DescriptorSet>>, // target name -> descriptor sets, created on demand
+    pub _is_dispatched: SwapchainArray<bool>,
+    pub _histogram_result: Option<HistogramResult>,
+}
+
+impl Default for DebugHistogram {
+    fn default() -> DebugHistogram {
+        DebugHistogram {
+            _is_enabled: false,
+            _settings: HistogramSettings::default(),
+            _histogram_buffer: None,
+            _descriptor_sets_map: HashMap::new(),
+            _is_dispatched: vec![false; constants::SWAPCHAIN_IMAGE_COUNT],
+            _histogram_result: None,
+        }
+    }
+}
+
+pub fn get_render_pass_data_create_info() -> RenderPassDataCreateInfo {
+    let pipeline_data_create_infos = vec![
+        PipelineDataCreateInfo {
+            _pipeline_data_create_info_name: String::from(HISTOGRAM_RENDER_PASS_NAME),
+            _pipeline_bind_point: vk::PipelineBindPoint::COMPUTE,
+            _pipeline_compute_shader_file: PathBuf::from(HISTOGRAM_SHADER_FILE),
+            _push_constant_ranges: vec![vk::PushConstantRange {
+                stage_flags: vk::ShaderStageFlags::COMPUTE,
+                offset: 0,
+                size: std::mem::size_of::<PushConstant_DebugHistogram>() as u32,
+            }],
+            _descriptor_data_create_infos: vec![
+                DescriptorDataCreateInfo {
+                    _descriptor_binding_index: 0,
+                    _descriptor_name: String::from("texture_target"),
+                    _descriptor_resource_type: DescriptorResourceType::RenderTarget,
+                    _descriptor_shader_stage: vk::ShaderStageFlags::COMPUTE,
+                    ..Default::default()
+                },
+                DescriptorDataCreateInfo {
+                    _descriptor_binding_index: 1,
+                    _descriptor_name: String::from(HISTOGRAM_BUFFER_NAME),
+                    _descriptor_resource_type: DescriptorResourceType::StorageBuffer,
+                    _descriptor_shader_stage: vk::ShaderStageFlags::COMPUTE,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        }
+    ];
+
+    RenderPassDataCreateInfo {
+        _render_pass_create_info_name: String::from(HISTOGRAM_RENDER_PASS_NAME),
+        _pipeline_data_create_infos: pipeline_data_create_infos,
+        ..Default::default()
+    }
+}
+
+#[cfg(not(target_os = "android"))]
+pub fn generate_histogram_shader() {
+    let mut shader_file_path = PathBuf::from(SHADER_DIRECTORY);
+    shader_file_path.push(HISTOGRAM_SHADER_FILE);
+    if false == shader_file_path.is_file() {
+        fs::create_dir_all(shader_file_path.parent().unwrap()).expect("Failed to create directories.");
+        fs::write(&shader_file_path, HISTOGRAM_SHADER_SOURCE).expect("Failed to write");
+        log::info!("generate_histogram_shader: {:?}", shader_file_path);
+    }
+}
+
+impl DebugHistogram {
+    pub fn create_histogram_buffer(&mut self, renderer_data: &RendererData) {
+        // host visible, the buffer of each swapchain image is read after its previous frame is done.
+        self._histogram_buffer = Some(buffer::create_shader_buffer_data(
+            renderer_data.get_device(),
+            renderer_data.get_device_memory_properties(),
+            &String::from(HISTOGRAM_BUFFER_NAME),
+            vk::BufferUsageFlags::STORAGE_BUFFER,
+            std::mem::size_of::<HistogramBufferData>() as vk::DeviceSize,
+            false,
+            false,
+            false,
+        ));
+        self._is_dispatched = vec![false; constants::SWAPCHAIN_IMAGE_COUNT];
+    }
+
+    pub fn destroy_histogram_buffer(&mut self, device: &Device) {
+        if let Some(mut histogram_buffer) = self._histogram_buffer.take() {
+            buffer::destroy_shader_buffer_data(device, &mut histogram_buffer);
+        }
+        self._histogram_result = None;
+    }
+
+    pub fn destroy_descriptor_sets(&mut self) {
+        self._descriptor_sets_map.clear();
+        self._is_dispatched = vec![false; constants::SWAPCHAIN_IMAGE_COUNT];
+    }
+
+    // the buffers are not read until they are written again
+    pub fn reset_readback(&mut self) {
+        self._is_dispatched = vec![false; constants::SWAPCHAIN_IMAGE_COUNT];
+    }
+
+    pub fn set_histogram_settings(&mut self, settings: &HistogramSettings) {
+        if self._settings != *settings {
+            self._settings = settings.clone();
+            self._histogram_result = None;
+            self._is_dispatched = vec![false; constants::SWAPCHAIN_IMAGE_COUNT];
+        }
+    }
+
+    pub fn get_histogram_result(&self) -> Option<&HistogramResult> {
+        if self._is_enabled { self._histogram_result.as_ref() } else { None }
+    }
+
+    fn get_descriptor_sets(&mut self, renderer_data: &RendererData, resources: &Resources, target: &TextureData) -> SwapchainArray<vk::DescriptorSet> {
+        if let Some(descriptor_sets) = self._descriptor_sets_map.get(&target._texture_data_name) {
+            return descriptor_sets.clone();
+        }
+        let render_pass_pipeline_data = resources.get_render_pass_pipeline_data(HISTOGRAM_RENDER_PASS_NAME, HISTOGRAM_RENDER_PASS_NAME);
+        let pipeline_data = render_pass_pipeline_data._pipeline_data.borrow();
+        let descriptor_data = &pipeline_data._descriptor_data;
+        let descriptor_binding_indices: Vec<u32> = descriptor_data._descriptor_data_create_infos.iter().map(|descriptor_data_create_info| {
+            descriptor_data_create_info._descriptor_binding_index
+        }).collect();
+        let histogram_buffer = self._histogram_buffer.as_ref().unwrap();
+        let descriptor_resource_infos_list: SwapchainArray<Vec<DescriptorResourceInfo>> = constants::SWAPCHAIN_IMAGE_INDICES.iter().map(|swapchain_index| {
+            vec![
+                DescriptorResourceInfo::DescriptorImageInfo(target.get_default_image_info().clone()),
+                histogram_buffer._descriptor_buffer_infos[*swapchain_index].clone(),
+            ]
+        }).collect();
+        let descriptor_sets = descriptor::create_descriptor_sets(renderer_data.get_device(), descriptor_data, HISTOGRAM_RENDER_PASS_NAME);
+        let _write_descriptor_sets = descriptor::create_write_descriptor_sets_with_update(
+            renderer_data.get_device(),
+            &descriptor_sets,
+            &descriptor_binding_indices,
+            &descriptor_data._descriptor_set_layout_bindings,
+            &descriptor_resource_infos_list,
+        );
+        self._descriptor_sets_map.insert(target._texture_data_name.clone(), descriptor_sets.clone());
+        descriptor_sets
+    }
+
+    pub fn dispatch_histogram(&mut self, command_buffer: vk::CommandBuffer, swapchain_index: u32, renderer_data: &RendererData, resources: &Resources) {
+        if false == self._is_enabled || self._settings._target_name.is_empty() || false == resources.has_render_pass_data(HISTOGRAM_RENDER_PASS_NAME) {
+            return;
+        }
+        if self._histogram_buffer.is_none() {
+            self.create_histogram_buffer(renderer_data);
+        }
+
+        // readback the result of the previous use of this swapchain image
+        let index = swapchain_index as usize;
+        if self._is_dispatched[index] {
+            let mut histogram_buffer_data = [HistogramBufferData::default()];
+            renderer_data.read_shader_buffer_datas(swapchain_index, self._histogram_buffer.as_ref().unwrap(), 0, &mut histogram_buffer_data);
+            self._histogram_result = Some(HistogramResult::create_histogram_result(&histogram_buffer_data[0], &self._settings));
+        }
+
+        let target = renderer_data.get_render_target_from_str(&self._settings._target_name);
+        let descriptor_sets = self.get_descriptor_sets(renderer_data, resources, target);
+        let histogram_buffer = self._histogram_buffer.as_ref().unwrap();
+        renderer_data.upload_shader_buffer_data(command_buffer, swapchain_index, histogram_buffer, &HistogramBufferData::default());
+        let buffer_memory_barriers = [vk::BufferMemoryBarrier {
+            src_access_mask: vk::AccessFlags::HOST_WRITE,
+            dst_access_mask: vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE,
+            src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            buffer: histogram_buffer._buffers[index]._buffer,
+            offset: 0,
+            size: vk::WHOLE_SIZE,
+            ..Default::default()
+        }];
+        renderer_data.pipeline_barrier(command_buffer, vk::PipelineStageFlags::HOST, vk::PipelineStageFlags::COMPUTE_SHADER, vk::DependencyFlags::empty(), &[], &buffer_memory_barriers, &[]);
+
+        let mut flags: u32 = 0;
+        if self._settings._log_scale { flags |= HISTOGRAM_FLAG_LOG_SCALE; }
+        if self._settings._linearize_depth { flags |= HISTOGRAM_FLAG_LINEARIZE_DEPTH; }
+        let push_constant_data = PushConstant_DebugHistogram {
+            _image_size: Vector2::new(target._image_width as i32, target._image_height as i32),
+            _channel: self._settings._channel as u32,
+            _flags: flags,
+            _range_min: self._settings._range_min,
+            _range_max: self._settings._range_max,
+            _near: self._settings._near,
+            _far: self._settings._far,
+        };
+        let render_pass_pipeline_data = resources.get_render_pass_pipeline_data(HISTOGRAM_RENDER_PASS_NAME, HISTOGRAM_RENDER_PASS_NAME);
+        let pipeline_data = render_pass_pipeline_data._pipeline_data.borrow();
+        renderer_data.begin_debug_label(command_buffer, HISTOGRAM_RENDER_PASS_NAME);
+        renderer_data.begin_compute_pipeline(command_buffer, &pipeline_data);
+        unsafe {
+            renderer_data.get_device().cmd_bind_descriptor_sets(
+                command_buffer,
+                pipeline_data._pipeline_bind_point,
+                pipeline_data._pipeline_layout,
+                0,
+                &[descriptor_sets[index]],
+                &[]
+            );
+        }
+        renderer_data.upload_push_constant_data(command_buffer, &pipeline_data, &push_constant_data);
+        renderer_data.dispatch_compute_pipeline(
+            command_buffer,
+            (target._image_width + HISTOGRAM_WORK_GROUP_SIZE - 1) / HISTOGRAM_WORK_GROUP_SIZE,
+            (target._image_height + HISTOGRAM_WORK_GROUP_SIZE - 1) / HISTOGRAM_WORK_GROUP_SIZE,
+            1
+        );
+        renderer_data.end_debug_label(command_buffer);
+
+        let buffer_memory_barriers = [vk::BufferMemoryBarrier {
+            src_access_mask: vk::AccessFlags::SHADER_WRITE,
+            dst_access_mask: vk::AccessFlags::HOST_READ,
+            ..buffer_memory_barriers[0]
+        }];
+        renderer_data.pipeline_barrier(command_buffer, vk::PipelineStageFlags::COMPUTE_SHADER, vk::PipelineStageFlags::HOST, vk::DependencyFlags::empty(), &[], &buffer_memory_barriers, &[]);
+        self._is_dispatched[index] = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the synthetic gradient texture of the headless mode, a pixel per bin value
+    fn create_gradient_colors(settings: &HistogramSettings) -> Vec<[f32; 4]> {
+        (0..HISTOGRAM_BIN_COUNT).map(|bin| {
+            let value = get_histogram_bin_value(bin, settings);
+            [value, value, value, 1.0]
+        }).collect()
+    }
+
+    #[test]
+    fn test_ordered_bits() {
+        let values = [f32::MIN, -1000.0, -1.0, -0.0001, 0.0, 0.0001, 0.5, 1.0, 1000.0, f32::MAX];
+        for (value, next_value) in values.iter().zip(values[1..].iter()) {
+            assert!(float_to_ordered_bits(*value) < float_to_ordered_bits(*next_value));
+        }
+        for value in values.iter() {
+            assert_eq!(*value, ordered_bits_to_float(float_to_ordered_bits(*value)));
+        }
+    }
+
+    #[test]
+    fn test_histogram_of_gradient() {
+        let settings = HistogramSettings::default();
+        let colors = create_gradient_colors(&settings);
+        let histogram_result = HistogramResult::create_histogram_result(&build_histogram(&colors, &settings), &settings);
+        assert!(histogram_result._bins.iter().all(|count| 1 == *count));
+        assert_eq!(1, histogram_result._max_bin_count);
+        assert_eq!(HISTOGRAM_BIN_COUNT as u32, histogram_result._pixel_count);
+        assert_eq!(0.0, histogram_result._min_value);
+        assert_eq!(1.0, histogram_result._max_value);
+        assert!((histogram_result._mean_value - 0.5).abs() < 0.0001);
+        assert!(histogram_result.get_normalized_bars().iter().all(|bar| 1.0 == *bar));
+        assert_eq!("histogram  Luminance: min 0.0000 max 1.0000 mean 0.5000 pixels 256", histogram_result.get_summary_text(&settings));
+
+        // the values out of the range are clamped to the first and the last bins, min and max are not clamped
+        let colors = vec![[-1.0, -1.0, -1.0, 1.0], [0.25, 0.25, 0.25, 1.0], [4.0, 4.0, 4.0, 1.0], [4.0, 4.0, 4.0, 1.0]];
+        let histogram_result = HistogramResult::create_histogram_result(&build_histogram(&colors, &settings), &settings);
+        assert_eq!(1, histogram_result._bins[0]);
+        assert_eq!(1, histogram_result._bins[64]);
+        assert_eq!(2, histogram_result._bins[HISTOGRAM_BIN_COUNT - 1]);
+        assert_eq!(-1.0, histogram_result._min_value);
+        assert_eq!(4.0, histogram_result._max_value);
+        assert_eq!(vec![0.5, 0.5, 1.0], histogram_result.get_normalized_bars().into_iter().filter(|bar| 0.0 < *bar).collect::<Vec<f32>>());
+
+        // empty
+        let histogram_result = HistogramResult::create_histogram_result(&HistogramBufferData::default(), &settings);
+        assert_eq!((0.0, 0.0, 0.0, 0), (histogram_result._min_value, histogram_result._max_value, histogram_result._mean_value, histogram_result._pixel_count));
+    }
+
+    #[test]
+    fn test_histogram_channels() {
+        let color = [1.0, 0.5, 0.25, 0.75];
+        assert!((get_channel_value(&color, HistogramChannel::Luminance) - (0.2126 + 0.5 * 0.7152 + 0.25 * 0.0722)).abs() < 0.0001);
+        assert_eq!(1.0, get_channel_value(&color, HistogramChannel::Red));
+        assert_eq!(0.5, get_channel_value(&color, HistogramChannel::Green));
+        assert_eq!(0.25, get_channel_value(&color, HistogramChannel::Blue));
+        assert_eq!(0.75, get_channel_value(&color, HistogramChannel::Alpha));
+        // gray has the same luminance
+        assert!((get_channel_value(&[0.3, 0.3, 0.3, 1.0], HistogramChannel::Luminance) - 0.3).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_histogram_float_range_and_log_scale() {
+        let settings = HistogramSettings {
+            _range_min: -2.0,
+            _range_max: 6.0,
+            ..Default::default()
+        };
+        assert_eq!(0, get_histogram_bin(-2.0, &settings));
+        assert_eq!(HISTOGRAM_BIN_COUNT / 2, get_histogram_bin(2.0, &settings));
+        assert_eq!(HISTOGRAM_BIN_COUNT - 1, get_histogram_bin(6.0, &settings));
+
+        // the decades of the hdr values have the same number of bins
+        let settings = HistogramSettings {
+            _range_min: 0.01,
+            _range_max: 100.0,
+            _log_scale: true,
+            ..Default::default()
+        };
+        assert_eq!(0, get_histogram_bin(0.01, &settings));
+        assert_eq!(0, get_histogram_bin(0.0, &settings));
+        assert_eq!(HISTOGRAM_BIN_COUNT / 2, get_histogram_bin(1.0, &settings));
+        assert_eq!(HISTOGRAM_BIN_COUNT - 1, get_histogram_bin(100.0, &settings));
+        let upper_decade_bins = (get_histogram_bin(10.0, &settings) - get_histogram_bin(1.0, &settings)) as i32;
+        let lower_decade_bins = (get_histogram_bin(1.0, &settings) - get_histogram_bin(0.1, &settings)) as i32;
+        assert!((upper_decade_bins - lower_decade_bins).abs() <= 1);
+        for bin in 0..HISTOGRAM_BIN_COUNT {
+            assert_eq!(bin, get_histogram_bin(get_histogram_bin_value(bin, &settings), &settings));
+        }
+
+        let colors = create_gradient_colors(&settings);
+        let histogram_result = HistogramResult::create_histogram_result(&build_histogram(&colors, &settings), &settings);
+        assert!(histogram_result._bins.iter().all(|count| 1 == *count));
+        assert!((histogram_result._min_value - 0.01).abs() < 0.0001);
+        assert!((histogram_result._max_value - 100.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_histogram_linearize_depth() {
+        let settings = HistogramSettings {
+            _channel: HistogramChannel::Red,
+            _linearize_depth: true,
+            _near: 0.1,
+            _far: 100.0,
+            _range_min: 0.1,
+            _range_max: 100.0,
+            ..Default::default()
+        };
+        let depths: Vec<f32> = (1..HISTOGRAM_BIN_COUNT).map(|i| i as f32 / (HISTOGRAM_BIN_COUNT - 1) as f32).collect();
+        let colors: Vec<[f32; 4]> = depths.iter().map(|depth| [*depth, 0.0, 0.0, 1.0]).collect();
+        let distances: Vec<f32> = depths.iter().map(|depth| linearize_depth(*depth, settings._near, settings._far)).collect();
+        let histogram_buffer_data = build_histogram(&colors, &settings);
+        let histogram_result = HistogramResult::create_histogram_result(&histogram_buffer_data, &settings);
+        // the view space distances are binned, not the device depths
+        assert_eq!(distances.iter().cloned().fold(f32::MAX, f32::min), histogram_result._min_value);
+        assert_eq!(distances.iter().cloned().fold(f32::MIN, f32::max), histogram_result._max_value);
+        for distance in distances.iter() {
+            assert!(0 < histogram_buffer_data._bins[get_histogram_bin(*distance, &settings)]);
+        }
+        assert_eq!(depths.len() as u32, histogram_result._pixel_count);
+    }
+}
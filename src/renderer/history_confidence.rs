@@ -0,0 +1,531 @@
+use std::fs;
+use std::path::PathBuf;
+
+use ash::vk;
+use ash::version::DeviceV1_0;
+use nalgebra::Vector2;
+
+use crate::constants;
+use crate::renderer::renderer::RendererData;
+use crate::resource::resource::Resources;
+use crate::utilities::math;
+use crate::vulkan_context::descriptor::{ self, DescriptorDataCreateInfo, DescriptorResourceInfo, DescriptorResourceType };
+use crate::vulkan_context::render_pass::{ PipelineDataCreateInfo, RenderPassDataCreateInfo };
+use crate::vulkan_context::shader::SHADER_DIRECTORY;
+use crate::vulkan_context::texture::{ TextureCreateInfo, TextureData };
+use crate::vulkan_context::vulkan_context::SwapchainArray;
+
+pub const HISTORY_CONFIDENCE_WORK_GROUP_SIZE: u32 = 8; // must match with history_confidence.comp
+pub const HISTORY_CONFIDENCE_MODE_CONFIDENCE: u32 = 0; // must match with history_confidence.comp
+pub const HISTORY_CONFIDENCE_MODE_COPY_DEPTH: u32 = 1; // must match with history_confidence.comp
+pub const HISTORY_CONFIDENCE_RENDER_PASS_NAME: &str = "history_confidence";
+// the temporal passes of the project bind this name as a RenderTarget descriptor, ex) TAA, SSR resolve, volumetric reprojection
+pub const HISTORY_CONFIDENCE_TEXTURE_NAME: &str = "HistoryConfidence";
+pub const HISTORY_CONFIDENCE_PREV_DEPTH_TEXTURE_NAME: &str = "HistoryConfidencePrevDepth";
+pub const HISTORY_CONFIDENCE_FORMAT: vk::Format = vk::Format::R8_UNORM;
+pub const HISTORY_CONFIDENCE_PREV_DEPTH_FORMAT: vk::Format = vk::Format::R32_SFLOAT;
+pub const HISTORY_CONFIDENCE_SHADER_FILE: &str = "common/history_confidence.comp";
+pub const HISTORY_CONFIDENCE_SHADER_SOURCE: &str = r#"#version 450
+
+#define HISTORY_CONFIDENCE_WORK_GROUP_SIZE 8
+#define HISTORY_CONFIDENCE_MODE_CONFIDENCE 0
+#define HISTORY_CONFIDENCE_MODE_COPY_DEPTH 1
+
+layout(local_size_x = HISTORY_CONFIDENCE_WORK_GROUP_SIZE, local_size_y = HISTORY_CONFIDENCE_WORK_GROUP_SIZE, local_size_z = 1) in;
+
+// velocity: texcoord - prev_texcoord
+layout(binding = 0) uniform sampler2D texture_velocity;
+layout(binding = 1) uniform sampler2D texture_depth;
+layout(binding = 2, r32f) uniform image2D image_prev_depth;
+layout(binding = 3, r8) uniform writeonly image2D image_history_confidence;
+
+layout(push_constant) uniform PushConstant_HistoryConfidence
+{
+    ivec2 image_size;
+    uint mode;
+    uint is_history_valid;
+    float velocity_threshold;
+    float depth_threshold;
+    vec2 linear_depth_params; // math::get_linear_depth_params
+} pushConstant;
+
+// the relative error of the view distance is the same for the near and the far with reversed-z
+float linearize_depth(float depth)
+{
+    return 1.0 / max(depth * pushConstant.linear_depth_params.x + pushConstant.linear_depth_params.y, 0.000001);
+}
+
+void main()
+{
+    ivec2 pixel = ivec2(gl_GlobalInvocationID.xy);
+    if(any(greaterThanEqual(pixel, pushConstant.image_size)))
+    {
+        return;
+    }
+
+    vec2 texcoord = (vec2(pixel) + 0.5) / vec2(pushConstant.image_size);
+    float depth = linearize_depth(textureLod(texture_depth, texcoord, 0.0).x);
+    if(HISTORY_CONFIDENCE_MODE_COPY_DEPTH == pushConstant.mode)
+    {
+        imageStore(image_prev_depth, pixel, vec4(depth));
+        return;
+    }
+
+    float confidence = 0.0;
+    if(0u != pushConstant.is_history_valid)
+    {
+        vec2 velocity = textureLod(texture_velocity, texcoord, 0.0).xy;
+        vec2 prev_texcoord = texcoord - velocity;
+        // off-screen in the previous frame
+        if(all(greaterThanEqual(prev_texcoord, vec2(0.0))) && all(lessThanEqual(prev_texcoord, vec2(1.0))))
+        {
+            ivec2 prev_pixel = clamp(ivec2(prev_texcoord * vec2(pushConstant.image_size)), ivec2(0), pushConstant.image_size - 1);
+            float prev_depth = imageLoad(image_prev_depth, prev_pixel).x;
+            float depth_error = abs(prev_depth - depth) / max(max(abs(depth), abs(prev_depth)), 0.000001);
+            float depth_confidence = 1.0 - smoothstep(pushConstant.depth_threshold * 0.5, pushConstant.depth_threshold, depth_error);
+            float velocity_confidence = 1.0 - clamp(length(velocity) / pushConstant.velocity_threshold, 0.0, 1.0);
+            confidence = depth_confidence * velocity_confidence;
+        }
+    }
+    imageStore(image_history_confidence, pixel, vec4(confidence));
+}
+"#;
+
+// must match with history_confidence.comp
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone)]
+#[repr(C)]
+pub struct PushConstant_HistoryConfidence {
+    pub _image_size: Vector2<i32>,
+    pub _mode: u32,
+    pub _is_history_valid: u32,
+    pub _velocity_threshold: f32,
+    pub _depth_threshold: f32,
+    pub _linear_depth_params: Vector2<f32>,
+}
+
+crate::impl_push_constant!(PushConstant_HistoryConfidence, 32);
+
+// the inputs are the render targets of the project renderer
+#[derive(Clone, Debug, PartialEq)]
+pub struct HistoryConfidenceSettings {
+    pub _velocity_target_name: String,
+    pub _depth_target_name: String,
+    pub _velocity_threshold: f32, // the texcoord distance of the zero confidence
+    pub _depth_threshold: f32, // the relative depth error of the zero confidence, the disocclusion
+}
+
+impl Default for HistoryConfidenceSettings {
+    fn default() -> HistoryConfidenceSettings {
+        HistoryConfidenceSettings {
+            _velocity_target_name: String::from("SceneVelocity"),
+            _depth_target_name: String::from("SceneDepth"),
+            _velocity_threshold: 0.1,
+            _depth_threshold: 0.01,
+        }
+    }
+}
+
+pub fn get_render_pass_data_create_info() -> RenderPassDataCreateInfo {
+    let pipeline_data_create_infos = vec![
+        PipelineDataCreateInfo {
+            _pipeline_data_create_info_name: String::from(HISTORY_CONFIDENCE_RENDER_PASS_NAME),
+            _pipeline_bind_point: vk::PipelineBindPoint::COMPUTE,
+            _pipeline_compute_shader_file: PathBuf::from(HISTORY_CONFIDENCE_SHADER_FILE),
+            _push_constant_ranges: vec![vk::PushConstantRange {
+                stage_flags: vk::ShaderStageFlags::COMPUTE,
+                offset: 0,
+                size: std::mem::size_of::<PushConstant_HistoryConfidence>() as u32,
+            }],
+            _descriptor_data_create_infos: vec![
+                DescriptorDataCreateInfo {
+                    _descriptor_binding_index: 0,
+                    _descriptor_name: String::from("texture_velocity"),
+                    _descriptor_resource_type: DescriptorResourceType::RenderTarget,
+                    _descriptor_shader_stage: vk::ShaderStageFlags::COMPUTE,
+                    ..Default::default()
+                },
+                DescriptorDataCreateInfo {
+                    _descriptor_binding_index: 1,
+                    _descriptor_name: String::from("texture_depth"),
+                    _descriptor_resource_type: DescriptorResourceType::RenderTarget,
+                    _descriptor_shader_stage: vk::ShaderStageFlags::COMPUTE,
+                    ..Default::default()
+                },
+                DescriptorDataCreateInfo {
+                    _descriptor_binding_index: 2,
+                    _descriptor_name: String::from(HISTORY_CONFIDENCE_PREV_DEPTH_TEXTURE_NAME),
+                    _descriptor_resource_type: DescriptorResourceType::StorageRenderTarget,
+                    _descriptor_shader_stage: vk::ShaderStageFlags::COMPUTE,
+                    ..Default::default()
+                },
+                DescriptorDataCreateInfo {
+                    _descriptor_binding_index: 3,
+                    _descriptor_name: String::from(HISTORY_CONFIDENCE_TEXTURE_NAME),
+                    _descriptor_resource_type: DescriptorResourceType::StorageRenderTarget,
+                    _descriptor_shader_stage: vk::ShaderStageFlags::COMPUTE,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        }
+    ];
+
+    RenderPassDataCreateInfo {
+        _render_pass_create_info_name: String::from(HISTORY_CONFIDENCE_RENDER_PASS_NAME),
+        _pipeline_data_create_infos: pipeline_data_create_infos,
+        ..Default::default()
+    }
+}
+
+#[cfg(not(target_os = "android"))]
+pub fn generate_history_confidence_shader() {
+    let mut shader_file_path = PathBuf::from(SHADER_DIRECTORY);
+    shader_file_path.push(HISTORY_CONFIDENCE_SHADER_FILE);
+    if false == shader_file_path.is_file() {
+        fs::create_dir_all(shader_file_path.parent().unwrap()).expect("Failed to create directories.");
+        fs::write(&shader_file_path, HISTORY_CONFIDENCE_SHADER_SOURCE).expect("Failed to write");
+        log::info!("generate_history_confidence_shader: {:?}", shader_file_path);
+    }
+}
+
+// The per pixel confidence of the reprojected history shared by the temporal passes: 1 is the valid history, 0 is the rejected history.
+// velocity magnitude, the depth reprojection error against the previous depth and the off-screen check.
+// the project renderer dispatches it after the velocity and the depth are rendered, before the temporal passes.
+#[derive(Default)]
+pub struct HistoryConfidence {
+    pub _settings: HistoryConfidenceSettings,
+    pub _confidence_texture: Option<TextureData>,
+    pub _prev_depth_texture: Option<TextureData>, // the depth of the previous dispatch, copied after the confidence is computed
+    pub _descriptor_sets: SwapchainArray<vk::DescriptorSet>, // created on demand
+    pub _is_prev_depth_valid: bool, // false after the creation and invalidate_history, the confidence is zero for a frame
+}
+
+impl HistoryConfidence {
+    pub fn create_history_confidence_textures(&mut self, renderer_data: &RendererData) {
+        let render_extent = renderer_data.get_render_extent();
+        let create_info = |name: &str, format: vk::Format| -> TextureCreateInfo<u8> {
+            TextureCreateInfo {
+                _texture_name: String::from(name),
+                _texture_width: render_extent.width,
+                _texture_height: render_extent.height,
+                _texture_format: format,
+                _texture_min_filter: vk::Filter::NEAREST,
+                _texture_mag_filter: vk::Filter::NEAREST,
+                _texture_wrap_mode: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                _enable_mipmap: false,
+                ..Default::default()
+            }
+        };
+        self._confidence_texture = Some(renderer_data.create_render_target::<u8>(&create_info(HISTORY_CONFIDENCE_TEXTURE_NAME, HISTORY_CONFIDENCE_FORMAT)));
+        self._prev_depth_texture = Some(renderer_data.create_render_target::<u8>(&create_info(HISTORY_CONFIDENCE_PREV_DEPTH_TEXTURE_NAME, HISTORY_CONFIDENCE_PREV_DEPTH_FORMAT)));
+        self._is_prev_depth_valid = false;
+    }
+
+    pub fn destroy_history_confidence_textures(&mut self, renderer_data: &RendererData) {
+        for texture in [&mut self._confidence_texture, &mut self._prev_depth_texture].iter_mut() {
+            if let Some(texture_data) = texture.take() {
+                renderer_data.destroy_texture(&texture_data);
+            }
+        }
+        self._is_prev_depth_valid = false;
+    }
+
+    pub fn destroy_descriptor_sets(&mut self) {
+        self._descriptor_sets.clear();
+    }
+
+    pub fn get_render_target(&self, render_target_name: &str) -> Option<&TextureData> {
+        match render_target_name {
+            HISTORY_CONFIDENCE_TEXTURE_NAME => self._confidence_texture.as_ref(),
+            HISTORY_CONFIDENCE_PREV_DEPTH_TEXTURE_NAME => self._prev_depth_texture.as_ref(),
+            _ => None,
+        }
+    }
+
+    pub fn set_history_confidence_settings(&mut self, settings: &HistoryConfidenceSettings) {
+        if self._settings != *settings {
+            self._settings = settings.clone();
+            self._descriptor_sets.clear();
+            self._is_prev_depth_valid = false;
+        }
+    }
+
+    // the confidence of the next dispatch is zero, ex) camera cut, teleport, scene load
+    pub fn invalidate_history(&mut self) {
+        self._is_prev_depth_valid = false;
+    }
+
+    // the push constant of the next dispatch, which copies the depth, so the history of the dispatch after it is valid.
+    pub fn next_push_constant_data(&mut self, image_width: u32, image_height: u32) -> PushConstant_HistoryConfidence {
+        let push_constant_data = PushConstant_HistoryConfidence {
+            _image_size: Vector2::new(image_width as i32, image_height as i32),
+            _mode: HISTORY_CONFIDENCE_MODE_CONFIDENCE,
+            _is_history_valid: if self._is_prev_depth_valid { 1 } else { 0 },
+            _velocity_threshold: self._settings._velocity_threshold.max(0.000001),
+            _depth_threshold: self._settings._depth_threshold.max(0.000001),
+            _linear_depth_params: unsafe { math::get_linear_depth_params(constants::NEAR, constants::FAR) },
+        };
+        self._is_prev_depth_valid = true;
+        push_constant_data
+    }
+
+    fn get_descriptor_sets(&mut self, renderer_data: &RendererData, resources: &Resources) -> SwapchainArray<vk::DescriptorSet> {
+        if false == self._descriptor_sets.is_empty() {
+            return self._descriptor_sets.clone();
+        }
+        let render_pass_pipeline_data = resources.get_render_pass_pipeline_data(HISTORY_CONFIDENCE_RENDER_PASS_NAME, HISTORY_CONFIDENCE_RENDER_PASS_NAME);
+        let pipeline_data = render_pass_pipeline_data._pipeline_data.borrow();
+        let descriptor_data = &pipeline_data._descriptor_data;
+        let descriptor_binding_indices: Vec<u32> = descriptor_data._descriptor_data_create_infos.iter().map(|descriptor_data_create_info| {
+            descriptor_data_create_info._descriptor_binding_index
+        }).collect();
+        let velocity_target = renderer_data.get_render_target_from_str(&self._settings._velocity_target_name);
+        let depth_target = renderer_data.get_render_target_from_str(&self._settings._depth_target_name);
+        let descriptor_resource_infos_list: SwapchainArray<Vec<DescriptorResourceInfo>> = constants::SWAPCHAIN_IMAGE_INDICES.iter().map(|_swapchain_index| {
+            vec![
+                DescriptorResourceInfo::DescriptorImageInfo(velocity_target.get_default_image_info().clone()),
+                DescriptorResourceInfo::DescriptorImageInfo(depth_target.get_default_image_info().clone()),
+                DescriptorResourceInfo::DescriptorImageInfo(self._prev_depth_texture.as_ref().unwrap().get_default_image_info().clone()),
+                DescriptorResourceInfo::DescriptorImageInfo(self._confidence_texture.as_ref().unwrap().get_default_image_info().clone()),
+            ]
+        }).collect();
+        let descriptor_sets = descriptor::create_descriptor_sets(renderer_data.get_device(), descriptor_data, HISTORY_CONFIDENCE_RENDER_PASS_NAME);
+        let _write_descriptor_sets = descriptor::create_write_descriptor_sets_with_update(
+            renderer_data.get_device(),
+            &descriptor_sets,
+            &descriptor_binding_indices,
+            &descriptor_data._descriptor_set_layout_bindings,
+            &descriptor_resource_infos_list,
+        );
+        self._descriptor_sets = descriptor_sets.clone();
+        descriptor_sets
+    }
+
+    // the confidence from the previous depth, then the current depth becomes the previous depth of the next frame.
+    pub fn dispatch_history_confidence(&mut self, command_buffer: vk::CommandBuffer, swapchain_index: u32, renderer_data: &RendererData, resources: &Resources) {
+        if self._confidence_texture.is_none() || false == resources.has_render_pass_data(HISTORY_CONFIDENCE_RENDER_PASS_NAME) {
+            return;
+        }
+        let descriptor_sets = self.get_descriptor_sets(renderer_data, resources);
+        let (image_width, image_height, confidence_image, prev_depth_image) = {
+            let confidence_texture = self._confidence_texture.as_ref().unwrap();
+            (confidence_texture._image_width, confidence_texture._image_height, confidence_texture._image, self._prev_depth_texture.as_ref().unwrap()._image)
+        };
+        let render_pass_pipeline_data = resources.get_render_pass_pipeline_data(HISTORY_CONFIDENCE_RENDER_PASS_NAME, HISTORY_CONFIDENCE_RENDER_PASS_NAME);
+        let pipeline_data = render_pass_pipeline_data._pipeline_data.borrow();
+        let mut push_constant_data = self.next_push_constant_data(image_width, image_height);
+        let group_count_x = (image_width + HISTORY_CONFIDENCE_WORK_GROUP_SIZE - 1) / HISTORY_CONFIDENCE_WORK_GROUP_SIZE;
+        let group_count_y = (image_height + HISTORY_CONFIDENCE_WORK_GROUP_SIZE - 1) / HISTORY_CONFIDENCE_WORK_GROUP_SIZE;
+        let subresource_range = vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        };
+        let image_memory_barrier = |image: vk::Image, src_access_mask: vk::AccessFlags, dst_access_mask: vk::AccessFlags| vk::ImageMemoryBarrier {
+            src_access_mask,
+            dst_access_mask,
+            old_layout: vk::ImageLayout::GENERAL,
+            new_layout: vk::ImageLayout::GENERAL,
+            src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            image,
+            subresource_range,
+            ..Default::default()
+        };
+
+        renderer_data.begin_debug_label(command_buffer, HISTORY_CONFIDENCE_RENDER_PASS_NAME);
+        renderer_data.begin_compute_pipeline(command_buffer, &pipeline_data);
+        unsafe {
+            renderer_data.get_device().cmd_bind_descriptor_sets(
+                command_buffer,
+                pipeline_data._pipeline_bind_point,
+                pipeline_data._pipeline_layout,
+                0,
+                &[descriptor_sets[swapchain_index as usize]],
+                &[]
+            );
+        }
+        renderer_data.upload_push_constant_data(command_buffer, &pipeline_data, &push_constant_data);
+        renderer_data.dispatch_compute_pipeline(command_buffer, group_count_x, group_count_y, 1);
+
+        // every pixel reads the previous depth at its reprojected pixel before it is overwritten
+        renderer_data.pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[image_memory_barrier(prev_depth_image, vk::AccessFlags::SHADER_READ, vk::AccessFlags::SHADER_WRITE)]
+        );
+        push_constant_data._mode = HISTORY_CONFIDENCE_MODE_COPY_DEPTH;
+        renderer_data.upload_push_constant_data(command_buffer, &pipeline_data, &push_constant_data);
+        renderer_data.dispatch_compute_pipeline(command_buffer, group_count_x, group_count_y, 1);
+        renderer_data.end_debug_label(command_buffer);
+
+        // the temporal passes sample the confidence, the next frame reads the previous depth
+        renderer_data.pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::PipelineStageFlags::FRAGMENT_SHADER | vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[
+                image_memory_barrier(confidence_image, vk::AccessFlags::SHADER_WRITE, vk::AccessFlags::SHADER_READ),
+                image_memory_barrier(prev_depth_image, vk::AccessFlags::SHADER_WRITE, vk::AccessFlags::SHADER_READ),
+            ]
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_IMAGE_SIZE: usize = 8;
+
+    // a frame of the velocity and the view distance per pixel
+    struct TestFrame {
+        _velocities: Vec<Vector2<f32>>,
+        _distances: Vec<f32>,
+    }
+
+    fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+        let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+        t * t * (3.0 - 2.0 * t)
+    }
+
+    fn linearize_depth(push_constant_data: &PushConstant_HistoryConfidence, depth: f32) -> f32 {
+        1.0 / (depth * push_constant_data._linear_depth_params.x + push_constant_data._linear_depth_params.y).max(0.000001)
+    }
+
+    // the device depth of the view distance, the inverse of linearize_depth
+    fn get_device_depth(push_constant_data: &PushConstant_HistoryConfidence, distance: f32) -> f32 {
+        (1.0 / distance - push_constant_data._linear_depth_params.y) / push_constant_data._linear_depth_params.x
+    }
+
+    // the cpu side of history_confidence.comp, both modes of a dispatch
+    fn run_history_confidence(history_confidence: &mut HistoryConfidence, prev_depths: &mut Vec<f32>, frame: &TestFrame) -> Vec<f32> {
+        let push_constant_data = history_confidence.next_push_constant_data(TEST_IMAGE_SIZE as u32, TEST_IMAGE_SIZE as u32);
+        let image_size = push_constant_data._image_size;
+        let depths: Vec<f32> = frame._distances.iter().map(|distance| linearize_depth(&push_constant_data, get_device_depth(&push_constant_data, *distance))).collect();
+        let confidences: Vec<f32> = (0..(TEST_IMAGE_SIZE * TEST_IMAGE_SIZE)).map(|pixel_index| {
+            if 0 == push_constant_data._is_history_valid {
+                return 0.0;
+            }
+            let pixel = Vector2::new((pixel_index % TEST_IMAGE_SIZE) as i32, (pixel_index / TEST_IMAGE_SIZE) as i32);
+            let texcoord = Vector2::new((pixel.x as f32 + 0.5) / image_size.x as f32, (pixel.y as f32 + 0.5) / image_size.y as f32);
+            let velocity = &frame._velocities[pixel_index];
+            let prev_texcoord = texcoord - velocity;
+            if prev_texcoord.x < 0.0 || prev_texcoord.y < 0.0 || 1.0 < prev_texcoord.x || 1.0 < prev_texcoord.y {
+                return 0.0;
+            }
+            let prev_pixel_x = ((prev_texcoord.x * image_size.x as f32) as i32).max(0).min(image_size.x - 1);
+            let prev_pixel_y = ((prev_texcoord.y * image_size.y as f32) as i32).max(0).min(image_size.y - 1);
+            let prev_depth = prev_depths[(prev_pixel_y * image_size.x + prev_pixel_x) as usize];
+            let depth = depths[pixel_index];
+            let depth_error = (prev_depth - depth).abs() / depth.abs().max(prev_depth.abs()).max(0.000001);
+            let depth_confidence = 1.0 - smoothstep(push_constant_data._depth_threshold * 0.5, push_constant_data._depth_threshold, depth_error);
+            let velocity_confidence = 1.0 - (velocity.norm() / push_constant_data._velocity_threshold).clamp(0.0, 1.0);
+            depth_confidence * velocity_confidence
+        }).collect();
+        *prev_depths = depths;
+        confidences
+    }
+
+    fn create_static_frame(distance: f32) -> TestFrame {
+        TestFrame {
+            _velocities: vec![Vector2::zeros(); TEST_IMAGE_SIZE * TEST_IMAGE_SIZE],
+            _distances: vec![distance; TEST_IMAGE_SIZE * TEST_IMAGE_SIZE],
+        }
+    }
+
+    #[test]
+    fn test_camera_teleport() {
+        let mut history_confidence = HistoryConfidence::default();
+        let mut prev_depths: Vec<f32> = vec![0.0; TEST_IMAGE_SIZE * TEST_IMAGE_SIZE];
+
+        // no previous depth at the first frame
+        let confidences = run_history_confidence(&mut history_confidence, &mut prev_depths, &create_static_frame(10.0));
+        assert!(confidences.iter().all(|confidence| 0.0 == *confidence));
+        let confidences = run_history_confidence(&mut history_confidence, &mut prev_depths, &create_static_frame(10.0));
+        assert!(confidences.iter().all(|confidence| 1.0 == *confidence));
+
+        // the camera teleports to a place of the same depth, the velocity of the cut is zero.
+        // without the invalidation the history of the old place passes every check and smears.
+        let confidences = run_history_confidence(&mut history_confidence, &mut prev_depths, &create_static_frame(10.0));
+        assert!(confidences.iter().all(|confidence| 1.0 == *confidence));
+        history_confidence.invalidate_history();
+        let confidences = run_history_confidence(&mut history_confidence, &mut prev_depths, &create_static_frame(10.0));
+        assert!(confidences.iter().all(|confidence| 0.0 == *confidence));
+
+        // the history of the new place is valid from the next frame
+        let confidences = run_history_confidence(&mut history_confidence, &mut prev_depths, &create_static_frame(10.0));
+        assert!(confidences.iter().all(|confidence| 1.0 == *confidence));
+    }
+
+    #[test]
+    fn test_rejection() {
+        let mut history_confidence = HistoryConfidence::default();
+        let mut prev_depths: Vec<f32> = vec![0.0; TEST_IMAGE_SIZE * TEST_IMAGE_SIZE];
+        run_history_confidence(&mut history_confidence, &mut prev_depths, &create_static_frame(10.0));
+
+        // disocclusion: an object in front has moved away from the left half
+        let mut frame = create_static_frame(10.0);
+        for (pixel_index, distance) in frame._distances.iter_mut().enumerate() {
+            if (pixel_index % TEST_IMAGE_SIZE) < TEST_IMAGE_SIZE / 2 {
+                *distance = 50.0;
+            }
+        }
+        // the top row comes from off-screen, the bottom row moves fast
+        for x in 0..TEST_IMAGE_SIZE {
+            frame._velocities[x] = Vector2::new(0.0, 0.2);
+            frame._velocities[(TEST_IMAGE_SIZE - 1) * TEST_IMAGE_SIZE + x] = Vector2::new(0.0, history_confidence._settings._velocity_threshold * 0.5);
+        }
+        let confidences = run_history_confidence(&mut history_confidence, &mut prev_depths, &frame);
+        for y in 0..TEST_IMAGE_SIZE {
+            for x in 0..TEST_IMAGE_SIZE {
+                let confidence = confidences[y * TEST_IMAGE_SIZE + x];
+                if 0 == y || x < TEST_IMAGE_SIZE / 2 {
+                    assert_eq!(0.0, confidence, "{} {}", x, y);
+                } else if (TEST_IMAGE_SIZE - 1) == y {
+                    assert!((confidence - 0.5).abs() < 1e-5, "{} {}: {}", x, y, confidence);
+                } else {
+                    assert_eq!(1.0, confidence, "{} {}", x, y);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalidation() {
+        let mut history_confidence = HistoryConfidence::default();
+        assert_eq!(0, history_confidence.next_push_constant_data(64, 32)._is_history_valid);
+        let push_constant_data = history_confidence.next_push_constant_data(64, 32);
+        assert_eq!(1, push_constant_data._is_history_valid);
+        assert_eq!(Vector2::new(64, 32), push_constant_data._image_size);
+        assert_eq!(HISTORY_CONFIDENCE_MODE_CONFIDENCE, push_constant_data._mode);
+
+        // the changed settings invalidate the history and the descriptor sets, the same settings don't
+        history_confidence.set_history_confidence_settings(&HistoryConfidenceSettings::default());
+        assert_eq!(1, history_confidence.next_push_constant_data(64, 32)._is_history_valid);
+        history_confidence.set_history_confidence_settings(&HistoryConfidenceSettings {
+            _depth_threshold: 0.05,
+            ..Default::default()
+        });
+        assert_eq!(0, history_confidence.next_push_constant_data(64, 32)._is_history_valid);
+
+        // the thresholds are never zero in the shader
+        history_confidence.set_history_confidence_settings(&HistoryConfidenceSettings {
+            _velocity_threshold: 0.0,
+            _depth_threshold: 0.0,
+            ..Default::default()
+        });
+        let push_constant_data = history_confidence.next_push_constant_data(64, 32);
+        assert!(0.0 < push_constant_data._velocity_threshold && 0.0 < push_constant_data._depth_threshold);
+    }
+}
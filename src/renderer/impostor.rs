@@ -0,0 +1,370 @@
+use std::fs;
+use std::path::PathBuf;
+
+use ash::vk;
+use nalgebra::{ Point3, Vector2, Vector3, Vector4, Matrix4 };
+use serde::{ Serialize, Deserialize };
+
+use crate::renderer::renderer::RendererData;
+use crate::renderer::viewport::ViewportRect;
+use crate::utilities::bounding_box::BoundingBox;
+use crate::utilities::math;
+use crate::vulkan_context::shader::SHADER_DIRECTORY;
+use crate::vulkan_context::texture::{ TextureCreateInfo, TextureData };
+use crate::constants;
+
+pub const IMPOSTOR_SHADER_FILE: &str = "common/impostor.glsl";
+pub const MIN_IMPOSTOR_VIEW_COUNT: u32 = 8;
+pub const MAX_IMPOSTOR_VIEW_COUNT: u32 = 16;
+pub const DEFAULT_IMPOSTOR_VIEW_COUNT: u32 = 12;
+pub const DEFAULT_IMPOSTOR_FRAME_SIZE: u32 = 128;
+pub const IMPOSTOR_ALBEDO_FORMAT: vk::Format = vk::Format::R8G8B8A8_UNORM; // a: coverage
+pub const IMPOSTOR_NORMAL_FORMAT: vk::Format = vk::Format::R8G8B8A8_UNORM; // xyz: object space normal * 0.5 + 0.5
+pub const IMPOSTOR_DEPTH_FORMAT: vk::Format = vk::Format::D32_SFLOAT;
+
+pub const IMPOSTOR_SHADER_SOURCE: &str = r#"// generated by the engine, impostor.rs - IMPOSTOR_SHADER_SOURCE
+#ifndef IMPOSTOR_GLSL
+#define IMPOSTOR_GLSL
+
+// must match with impostor.rs - ImpostorConstants
+struct IMPOSTOR_CONSTANTS
+{
+    mat4 _normal_matrix; // object space -> world space, rotation only
+    vec3 _world_center;
+    float _blend_weight; // 0.0: frame0, 1.0: frame1
+    vec3 _billboard_right; // world space, scaled by the half extent
+    float _reserved0;
+    vec3 _billboard_up;
+    float _reserved1;
+    vec4 _frame_uv_rect0; // xy: offset, zw: scale in the atlas
+    vec4 _frame_uv_rect1;
+};
+
+// quad_position: -1.0 ~ 1.0, the billboard turns around the up axis of the object toward the camera.
+vec3 get_impostor_vertex_position(const in IMPOSTOR_CONSTANTS impostor_constants, vec2 quad_position)
+{
+    return impostor_constants._world_center + impostor_constants._billboard_right * quad_position.x + impostor_constants._billboard_up * quad_position.y;
+}
+
+// the texcoord in a frame, the captured views are framed around the same center with the same extent.
+vec2 get_impostor_texcoord(vec2 quad_position)
+{
+    return vec2(0.5 + 0.5 * quad_position.x, 0.5 - 0.5 * quad_position.y);
+}
+
+vec2 get_impostor_atlas_texcoord(vec4 frame_uv_rect, vec2 texcoord)
+{
+    return frame_uv_rect.xy + clamp(texcoord, vec2(0.0), vec2(1.0)) * frame_uv_rect.zw;
+}
+
+// the two nearest captured views are blended, the caller discards the fragment by the coverage in albedo.a.
+void sample_impostor(
+    sampler2D texture_albedo,
+    sampler2D texture_normal,
+    const in IMPOSTOR_CONSTANTS impostor_constants,
+    vec2 texcoord,
+    out vec4 albedo,
+    out vec3 world_normal)
+{
+    vec2 texcoord0 = get_impostor_atlas_texcoord(impostor_constants._frame_uv_rect0, texcoord);
+    vec2 texcoord1 = get_impostor_atlas_texcoord(impostor_constants._frame_uv_rect1, texcoord);
+    albedo = mix(texture(texture_albedo, texcoord0), texture(texture_albedo, texcoord1), impostor_constants._blend_weight);
+    vec3 object_normal = mix(texture(texture_normal, texcoord0).xyz, texture(texture_normal, texcoord1).xyz, impostor_constants._blend_weight);
+    object_normal = object_normal * 2.0 - 1.0;
+    // the captured normals are lit like the mesh, so the time of day changes still look plausible.
+    world_normal = normalize(mat3(impostor_constants._normal_matrix) * object_normal);
+}
+
+#endif // IMPOSTOR_GLSL
+"#;
+
+// "impostor" of the model file, ex) "impostor": { "_view_count": 12, "_frame_size": 128, "_distance": 300.0 }
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct ImpostorCreateInfo {
+    pub _view_count: u32, // MIN_IMPOSTOR_VIEW_COUNT ~ MAX_IMPOSTOR_VIEW_COUNT, around the up axis of the model
+    pub _frame_size: u32, // pixels of a captured view
+    pub _distance: f32, // the render object is swapped to the impostor beyond this distance, scaled by constants::IMPOSTOR_DISTANCE_SCALE
+}
+
+impl Default for ImpostorCreateInfo {
+    fn default() -> ImpostorCreateInfo {
+        ImpostorCreateInfo {
+            _view_count: DEFAULT_IMPOSTOR_VIEW_COUNT,
+            _frame_size: DEFAULT_IMPOSTOR_FRAME_SIZE,
+            _distance: 300.0,
+        }
+    }
+}
+
+// the frames in rows from the top-left, the atlas is the smallest grid holding every view.
+#[derive(Serialize, Deserialize, Clone, Debug, Copy, PartialEq, Eq)]
+pub struct ImpostorAtlasLayout {
+    pub _view_count: u32,
+    pub _frame_size: u32,
+    pub _columns: u32,
+    pub _rows: u32,
+}
+
+impl ImpostorAtlasLayout {
+    pub fn create_impostor_atlas_layout(view_count: u32, frame_size: u32) -> ImpostorAtlasLayout {
+        let view_count = view_count.max(MIN_IMPOSTOR_VIEW_COUNT).min(MAX_IMPOSTOR_VIEW_COUNT);
+        let columns = (view_count as f32).sqrt().ceil() as u32;
+        let rows = (view_count + columns - 1) / columns;
+        ImpostorAtlasLayout {
+            _view_count: view_count,
+            _frame_size: frame_size.max(1),
+            _columns: columns,
+            _rows: rows,
+        }
+    }
+
+    pub fn get_atlas_width(&self) -> u32 { self._columns * self._frame_size }
+    pub fn get_atlas_height(&self) -> u32 { self._rows * self._frame_size }
+
+    pub fn get_frame_rect(&self, view_index: u32) -> ViewportRect {
+        let frame_size = self._frame_size as i32;
+        ViewportRect::create_viewport_rect(
+            (view_index % self._columns) as i32 * frame_size,
+            (view_index / self._columns) as i32 * frame_size,
+            frame_size,
+            frame_size,
+        )
+    }
+
+    // xy: offset, zw: scale in the atlas texcoord
+    pub fn get_frame_uv_rect(&self, view_index: u32) -> Vector4<f32> {
+        let inv_columns = 1.0 / self._columns as f32;
+        let inv_rows = 1.0 / self._rows as f32;
+        Vector4::new(
+            (view_index % self._columns) as f32 * inv_columns,
+            (view_index / self._columns) as f32 * inv_rows,
+            inv_columns,
+            inv_rows,
+        )
+    }
+}
+
+// the yaw of the view, the view direction is from the center of the model to the capture camera.
+pub fn get_impostor_view_yaw(view_index: u32, view_count: u32) -> f32 {
+    view_index as f32 * std::f32::consts::PI * 2.0 / view_count as f32
+}
+
+pub fn get_impostor_view_direction(view_index: u32, view_count: u32) -> Vector3<f32> {
+    let yaw = get_impostor_view_yaw(view_index, view_count);
+    Vector3::new(yaw.sin(), 0.0, yaw.cos())
+}
+
+// the two nearest captured views of the direction to the camera in object space, the weight of the second one and the yaw.
+pub fn get_impostor_view_blend(to_camera: &Vector3<f32>, view_count: u32) -> (u32, u32, f32, f32) {
+    let two_pi = std::f32::consts::PI * 2.0;
+    let yaw = to_camera.x.atan2(to_camera.z).rem_euclid(two_pi);
+    let view_position = yaw * view_count as f32 / two_pi;
+    let view_index = (view_position.floor() as u32) % view_count;
+    let next_view_index = (view_index + 1) % view_count;
+    (view_index, next_view_index, view_position - view_position.floor(), yaw)
+}
+
+// square frames of the horizontal and the vertical half extent, large enough for every yaw.
+pub fn get_impostor_half_extent(bound_box: &BoundingBox) -> Vector2<f32> {
+    let horizontal_radius = (bound_box._size.x * bound_box._size.x + bound_box._size.z * bound_box._size.z).sqrt() * 0.5;
+    let half_extent = horizontal_radius.max(bound_box._size.y * 0.5).max(0.001);
+    Vector2::new(half_extent, half_extent)
+}
+
+// the capture of a view: draw the model with the identity world matrix, the view projection and the viewport of the frame rect.
+#[derive(Clone, Debug)]
+pub struct ImpostorCaptureView {
+    pub _view_index: u32,
+    pub _view_direction: Vector3<f32>,
+    pub _view_projection: Matrix4<f32>,
+    pub _frame_rect: ViewportRect,
+}
+
+// orthogonal views around the bound box, the camera looks at -front like the other cameras.
+pub fn create_impostor_capture_views(bound_box: &BoundingBox, atlas_layout: &ImpostorAtlasLayout) -> Vec<ImpostorCaptureView> {
+    let half_extent = get_impostor_half_extent(bound_box);
+    let radius = bound_box._radius.max(half_extent.x);
+    let near = radius;
+    let far = radius * 3.0;
+    let one = Vector3::new(1.0, 1.0, 1.0);
+    let projection = math::get_clip_space_matrix() * math::orthogonal(-half_extent.x, half_extent.x, -half_extent.y, half_extent.y, near, far);
+    (0..atlas_layout._view_count).map(|view_index| {
+        let view_direction = get_impostor_view_direction(view_index, atlas_layout._view_count);
+        let eye = &bound_box._center + &view_direction * (radius * 2.0);
+        let rotation_matrix = math::make_rotation_matrix(0.0, get_impostor_view_yaw(view_index, atlas_layout._view_count), 0.0);
+        let view = math::inverse_transform_matrix(&eye, &rotation_matrix, &one);
+        ImpostorCaptureView {
+            _view_index: view_index,
+            _view_direction: view_direction,
+            _view_projection: &projection * view,
+            _frame_rect: atlas_layout.get_frame_rect(view_index),
+        }
+    }).collect()
+}
+
+// hysteresis around the impostor distance, so the objects near the threshold do not flicker between the mesh and the impostor.
+pub fn select_impostor_lod(is_impostor: bool, distance: f32, impostor_distance: f32, hysteresis: f32) -> bool {
+    if impostor_distance <= 0.0 {
+        return false;
+    }
+    if is_impostor {
+        impostor_distance * (1.0 - hysteresis) < distance
+    } else {
+        impostor_distance * (1.0 + hysteresis) < distance
+    }
+}
+
+pub fn get_impostor_distance_scale() -> f32 {
+    unsafe { constants::IMPOSTOR_DISTANCE_SCALE }
+}
+
+pub fn get_impostor_lod_hysteresis() -> f32 {
+    unsafe { constants::IMPOSTOR_LOD_HYSTERESIS.max(0.0).min(0.9) }
+}
+
+#[cfg(not(target_os = "android"))]
+pub fn generate_impostor_shader() {
+    let mut shader_file_path = PathBuf::from(SHADER_DIRECTORY);
+    shader_file_path.push(IMPOSTOR_SHADER_FILE);
+    if false == shader_file_path.is_file() {
+        fs::create_dir_all(shader_file_path.parent().unwrap()).expect("Failed to create directories.");
+        fs::write(&shader_file_path, IMPOSTOR_SHADER_SOURCE).expect("Failed to write");
+        log::info!("generate_impostor_shader: {:?}", shader_file_path);
+    }
+}
+
+// impostor.glsl - struct IMPOSTOR_CONSTANTS, per render object in the impostor pass
+#[repr(C)]
+#[derive(Clone, Debug, Copy)]
+pub struct ImpostorConstants {
+    pub _normal_matrix: Matrix4<f32>,
+    pub _world_center: Vector3<f32>,
+    pub _blend_weight: f32,
+    pub _billboard_right: Vector3<f32>,
+    pub _reserved0: f32,
+    pub _billboard_up: Vector3<f32>,
+    pub _reserved1: f32,
+    pub _frame_uv_rect0: Vector4<f32>,
+    pub _frame_uv_rect1: Vector4<f32>,
+}
+
+// the render targets of the capture, created by the engine in the atlas size.
+// the project renders all views into them and leaves the color targets in TRANSFER_SRC_OPTIMAL to be read back.
+pub struct ImpostorCaptureTargets {
+    pub _albedo_texture: TextureData,
+    pub _normal_texture: TextureData,
+    pub _depth_texture: TextureData,
+}
+
+impl ImpostorCaptureTargets {
+    pub fn create_impostor_capture_targets(renderer_data: &RendererData, atlas_layout: &ImpostorAtlasLayout) -> ImpostorCaptureTargets {
+        let create_target = |name: &str, format: vk::Format| {
+            renderer_data.create_render_target::<u8>(&TextureCreateInfo {
+                _texture_name: String::from(name),
+                _texture_width: atlas_layout.get_atlas_width(),
+                _texture_height: atlas_layout.get_atlas_height(),
+                _texture_format: format,
+                _texture_min_filter: vk::Filter::NEAREST,
+                _texture_mag_filter: vk::Filter::NEAREST,
+                _texture_wrap_mode: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                _enable_mipmap: false,
+                ..Default::default()
+            })
+        };
+        ImpostorCaptureTargets {
+            _albedo_texture: create_target("impostor_capture_albedo", IMPOSTOR_ALBEDO_FORMAT),
+            _normal_texture: create_target("impostor_capture_normal", IMPOSTOR_NORMAL_FORMAT),
+            _depth_texture: create_target("impostor_capture_depth", IMPOSTOR_DEPTH_FORMAT),
+        }
+    }
+
+    pub fn destroy_impostor_capture_targets(&self, renderer_data: &RendererData) {
+        renderer_data.destroy_texture(&self._albedo_texture);
+        renderer_data.destroy_texture(&self._normal_texture);
+        renderer_data.destroy_texture(&self._depth_texture);
+    }
+}
+
+// the result of the bake, saved in the impostor cache.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ImpostorBakeData {
+    pub _atlas_layout: ImpostorAtlasLayout,
+    pub _bound_center: Vector3<f32>, // object space
+    pub _half_extent: Vector2<f32>,
+    pub _albedo_datas: Vec<u8>, // IMPOSTOR_ALBEDO_FORMAT, the atlas size
+    pub _normal_datas: Vec<u8>, // IMPOSTOR_NORMAL_FORMAT
+}
+
+#[derive(Clone, Debug)]
+pub struct ImpostorData {
+    pub _model_name: String,
+    pub _atlas_layout: ImpostorAtlasLayout,
+    pub _bound_center: Vector3<f32>,
+    pub _half_extent: Vector2<f32>,
+    pub _albedo_texture: TextureData,
+    pub _normal_texture: TextureData,
+}
+
+impl ImpostorData {
+    pub fn create_impostor_data(renderer_data: &RendererData, model_name: &str, bake_data: ImpostorBakeData) -> ImpostorData {
+        log::debug!("create_impostor_data: {}", model_name);
+        let atlas_layout = bake_data._atlas_layout;
+        let create_texture = |name: String, format: vk::Format, datas: Vec<u8>| {
+            renderer_data.create_texture(&TextureCreateInfo {
+                _texture_name: name,
+                _texture_width: atlas_layout.get_atlas_width(),
+                _texture_height: atlas_layout.get_atlas_height(),
+                _texture_format: format,
+                _texture_min_filter: vk::Filter::LINEAR,
+                _texture_mag_filter: vk::Filter::LINEAR,
+                _texture_wrap_mode: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                _texture_initial_datas: datas,
+                _enable_mipmap: true,
+                ..Default::default()
+            })
+        };
+        ImpostorData {
+            _model_name: String::from(model_name),
+            _atlas_layout: atlas_layout,
+            _bound_center: bake_data._bound_center,
+            _half_extent: bake_data._half_extent,
+            _albedo_texture: create_texture(format!("impostor/{}/albedo", model_name), IMPOSTOR_ALBEDO_FORMAT, bake_data._albedo_datas),
+            _normal_texture: create_texture(format!("impostor/{}/normal", model_name), IMPOSTOR_NORMAL_FORMAT, bake_data._normal_datas),
+        }
+    }
+
+    pub fn destroy_impostor_data(&self, renderer_data: &RendererData) {
+        renderer_data.destroy_texture(&self._albedo_texture);
+        renderer_data.destroy_texture(&self._normal_texture);
+    }
+
+    // the billboard faces the camera around the up axis of the object, the frames of the two nearest views are blended.
+    pub fn get_impostor_constants(&self, world_matrix: &Matrix4<f32>, view_position: &Vector3<f32>) -> ImpostorConstants {
+        let get_axis = |column: usize| Vector3::new(world_matrix[(0, column)], world_matrix[(1, column)], world_matrix[(2, column)]);
+        let (axis_x, axis_y, axis_z) = (get_axis(0), get_axis(1), get_axis(2));
+        let (unit_x, unit_y, unit_z) = (axis_x.normalize(), axis_y.normalize(), axis_z.normalize());
+        let world_center = world_matrix.transform_point(&Point3::from(self._bound_center)).coords;
+        let to_camera = view_position - &world_center;
+        let to_camera = Vector3::new(to_camera.dot(&unit_x), to_camera.dot(&unit_y), to_camera.dot(&unit_z));
+        let (view_index, next_view_index, blend_weight, yaw) = get_impostor_view_blend(&to_camera, self._atlas_layout._view_count);
+        let billboard_right = &axis_x * (yaw.cos() * self._half_extent.x) - &axis_z * (yaw.sin() * self._half_extent.x);
+        let normal_matrix = Matrix4::from_columns(&[
+            Vector4::new(unit_x.x, unit_x.y, unit_x.z, 0.0),
+            Vector4::new(unit_y.x, unit_y.y, unit_y.z, 0.0),
+            Vector4::new(unit_z.x, unit_z.y, unit_z.z, 0.0),
+            Vector4::new(0.0, 0.0, 0.0, 1.0),
+        ]);
+        ImpostorConstants {
+            _normal_matrix: normal_matrix,
+            _world_center: world_center,
+            _blend_weight: blend_weight,
+            _billboard_right: billboard_right,
+            _reserved0: 0.0,
+            _billboard_up: &axis_y * self._half_extent.y,
+            _reserved1: 0.0,
+            _frame_uv_rect0: self._atlas_layout.get_frame_uv_rect(view_index),
+            _frame_uv_rect1: self._atlas_layout.get_frame_uv_rect(next_view_index),
+        }
+    }
+}
@@ -1,13 +1,97 @@
+use std::fs;
+use std::path::PathBuf;
+
 use nalgebra::{Vector3, Vector4, Matrix4};
 use serde::{ Serialize, Deserialize };
 
+use crate::renderer::shadow_atlas::LocalLightType;
 use crate::renderer::transform_object::TransformObjectData;
+use crate::resource::resource::{ Resources, INVALID_IES_PROFILE_INDEX };
 use crate::utilities::math::{
+    self,
     get_clip_space_matrix,
     orthogonal,
 };
+use crate::utilities::bounding_box::BoundingBox;
+use crate::vulkan_context::shader::SHADER_DIRECTORY;
 use crate::constants;
 
+pub const LOCAL_LIGHT_SHADER_FILE: &str = "common/local_light.glsl";
+pub const MIN_LIGHT_DISTANCE: f32 = 0.01; // the inverse square is clamped near the light
+pub const LOCAL_LIGHT_SHADER_SOURCE: &str = r#"// generated by the engine, light.rs - LOCAL_LIGHT_SHADER_SOURCE
+#ifndef LOCAL_LIGHT_GLSL
+#define LOCAL_LIGHT_GLSL
+
+#define LOCAL_LIGHT_TYPE_SPOT 0
+#define LOCAL_LIGHT_TYPE_POINT 1
+#define MIN_LIGHT_DISTANCE 0.01
+
+// light.rs - struct LocalLightConstants
+struct LOCAL_LIGHT_CONSTANTS
+{
+    vec3 _light_position;
+    float _light_radius;
+    vec3 _light_direction;
+    float _spot_cos_inner;
+    vec3 _light_color;
+    float _spot_cos_outer;
+    vec3 _light_left;
+    int _light_type;
+    int _ies_profile_index;
+    float _reserved0;
+    float _reserved1;
+    float _reserved2;
+};
+
+// inverse square with a smooth window reaching exactly zero at the radius
+float get_distance_attenuation(float light_distance, float light_radius)
+{
+    float distance_square = max(light_distance * light_distance, MIN_LIGHT_DISTANCE * MIN_LIGHT_DISTANCE);
+    float ratio = light_distance / max(light_radius, MIN_LIGHT_DISTANCE);
+    float ratio4 = ratio * ratio * ratio * ratio;
+    float window = clamp(1.0 - ratio4, 0.0, 1.0);
+    return (window * window) / distance_square;
+}
+
+float get_spot_cone_attenuation(float cos_angle, float spot_cos_inner, float spot_cos_outer)
+{
+    float t = clamp((cos_angle - spot_cos_outer) / max(spot_cos_inner - spot_cos_outer, 0.0001), 0.0, 1.0);
+    return t * t;
+}
+
+// light_to_surface: normalized, from the light position
+float get_ies_attenuation(sampler2DArray ies_profiles, in LOCAL_LIGHT_CONSTANTS light, vec3 light_to_surface)
+{
+    float cos_vertical = dot(light_to_surface, light._light_direction);
+    vec3 light_up = cross(light._light_direction, light._light_left);
+    float horizontal_angle = atan(dot(light_to_surface, light_up), dot(light_to_surface, light._light_left));
+    vec2 texcoord = vec2(acos(clamp(cos_vertical, -1.0, 1.0)) / 3.141592, fract(horizontal_angle / 6.283185 + 1.0));
+    return texture(ies_profiles, vec3(texcoord, float(light._ies_profile_index))).x;
+}
+
+float get_local_light_attenuation(sampler2DArray ies_profiles, in LOCAL_LIGHT_CONSTANTS light, vec3 world_position)
+{
+    vec3 light_vector = world_position - light._light_position;
+    float light_distance = length(light_vector);
+    vec3 light_to_surface = light_vector / max(light_distance, MIN_LIGHT_DISTANCE);
+    float attenuation = get_distance_attenuation(light_distance, light._light_radius);
+    if(LOCAL_LIGHT_TYPE_SPOT == light._light_type)
+    {
+        if(0 <= light._ies_profile_index)
+        {
+            attenuation *= get_ies_attenuation(ies_profiles, light, light_to_surface);
+        }
+        else
+        {
+            attenuation *= get_spot_cone_attenuation(dot(light_to_surface, light._light_direction), light._spot_cos_inner, light._spot_cos_outer);
+        }
+    }
+    return attenuation;
+}
+
+#endif // LOCAL_LIGHT_GLSL
+"#;
+
 // scene_constants.glsl - struct LIGHT_CONSTANTS
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct LightConstants {
@@ -131,4 +215,292 @@ impl DirectionalLightData {
         }
         self._updated_light_data = false;
     }
-}
\ No newline at end of file
+}
+
+// inverse square with a smooth window reaching exactly zero at the radius, must match with local_light.glsl
+pub fn get_distance_attenuation(light_distance: f32, light_radius: f32) -> f32 {
+    let distance_square = light_distance.max(MIN_LIGHT_DISTANCE).powi(2);
+    let ratio = light_distance / light_radius.max(MIN_LIGHT_DISTANCE);
+    let window = (1.0 - ratio.powi(4)).max(0.0).min(1.0);
+    window * window / distance_square
+}
+
+pub fn get_spot_cone_attenuation(cos_angle: f32, spot_cos_inner: f32, spot_cos_outer: f32) -> f32 {
+    let t = ((cos_angle - spot_cos_outer) / (spot_cos_inner - spot_cos_outer).max(0.0001)).max(0.0).min(1.0);
+    t * t
+}
+
+#[cfg(not(target_os = "android"))]
+pub fn generate_local_light_shader() {
+    let mut shader_file_path = PathBuf::from(SHADER_DIRECTORY);
+    shader_file_path.push(LOCAL_LIGHT_SHADER_FILE);
+    if false == shader_file_path.is_file() {
+        fs::create_dir_all(shader_file_path.parent().unwrap()).expect("Failed to create directories.");
+        fs::write(&shader_file_path, LOCAL_LIGHT_SHADER_SOURCE).expect("Failed to write");
+        log::info!("generate_local_light_shader: {:?}", shader_file_path);
+    }
+}
+
+// local_light.glsl - struct LOCAL_LIGHT_CONSTANTS, an element of the light list buffer
+#[repr(C)]
+#[derive(Clone, Debug, Copy)]
+pub struct LocalLightConstants {
+    pub _light_position: Vector3<f32>,
+    pub _light_radius: f32, // the cutoff, the attenuation is zero at the radius
+    pub _light_direction: Vector3<f32>,
+    pub _spot_cos_inner: f32,
+    pub _light_color: Vector3<f32>, // multiplied by the intensity
+    pub _spot_cos_outer: f32,
+    pub _light_left: Vector3<f32>, // the horizontal angle 0 of the ies profile
+    pub _light_type: i32, // LocalLightType
+    pub _ies_profile_index: i32, // the layer of resource::IES_PROFILES_TEXTURE_NAME, INVALID_IES_PROFILE_INDEX uses the cone falloff
+    pub _reserved0: f32,
+    pub _reserved1: f32,
+    pub _reserved2: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct LocalLightCreateInfo {
+    pub _light_type: LocalLightType,
+    pub _position: Vector3<f32>,
+    pub _rotation: Vector3<f32>,
+    pub _light_color: Vector3<f32>,
+    pub _light_intensity: f32,
+    pub _light_radius: f32,
+    pub _spot_inner_angle: f32, // degree, full cone angle
+    pub _spot_outer_angle: f32,
+    pub _ies_profile: String, // resource name in resource/externals/ies, spot light only
+}
+
+impl Default for LocalLightCreateInfo {
+    fn default() -> LocalLightCreateInfo {
+        LocalLightCreateInfo {
+            _light_type: LocalLightType::PointLight,
+            _position: Vector3::zeros(),
+            _rotation: Vector3::new(std::f32::consts::PI * -0.5, 0.0, 0.0),
+            _light_color: Vector3::new(1.0, 1.0, 1.0),
+            _light_intensity: 10.0,
+            _light_radius: 10.0,
+            _spot_inner_angle: 30.0,
+            _spot_outer_angle: 45.0,
+            _ies_profile: String::new(),
+        }
+    }
+}
+
+// the registration data of SceneManagerData::add_point_light, the attenuation reaches zero at the radius.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct PointLightData {
+    pub _position: Vector3<f32>,
+    pub _radius: f32,
+    pub _color: Vector3<f32>,
+    pub _intensity: f32,
+}
+
+impl Default for PointLightData {
+    fn default() -> PointLightData {
+        PointLightData {
+            _position: Vector3::zeros(),
+            _radius: 10.0,
+            _color: Vector3::new(1.0, 1.0, 1.0),
+            _intensity: 10.0,
+        }
+    }
+}
+
+impl PointLightData {
+    pub fn get_local_light_create_info(&self) -> LocalLightCreateInfo {
+        LocalLightCreateInfo {
+            _light_type: LocalLightType::PointLight,
+            _position: self._position.clone(),
+            _light_color: self._color.clone(),
+            _light_intensity: self._intensity,
+            _light_radius: self._radius,
+            ..Default::default()
+        }
+    }
+}
+
+// the registration data of SceneManagerData::add_spot_light, the cone attenuation is between the inner and the outer angle.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct SpotLightData {
+    pub _position: Vector3<f32>,
+    pub _rotation: Vector3<f32>,
+    pub _radius: f32,
+    pub _color: Vector3<f32>,
+    pub _intensity: f32,
+    pub _inner_angle: f32, // degree, full cone angle
+    pub _outer_angle: f32,
+    pub _ies_profile: String,
+}
+
+impl Default for SpotLightData {
+    fn default() -> SpotLightData {
+        SpotLightData {
+            _position: Vector3::zeros(),
+            _rotation: Vector3::new(std::f32::consts::PI * -0.5, 0.0, 0.0),
+            _radius: 10.0,
+            _color: Vector3::new(1.0, 1.0, 1.0),
+            _intensity: 10.0,
+            _inner_angle: 30.0,
+            _outer_angle: 45.0,
+            _ies_profile: String::new(),
+        }
+    }
+}
+
+impl SpotLightData {
+    pub fn get_local_light_create_info(&self) -> LocalLightCreateInfo {
+        LocalLightCreateInfo {
+            _light_type: LocalLightType::SpotLight,
+            _position: self._position.clone(),
+            _rotation: self._rotation.clone(),
+            _light_color: self._color.clone(),
+            _light_intensity: self._intensity,
+            _light_radius: self._radius,
+            _spot_inner_angle: self._inner_angle,
+            _spot_outer_angle: self._outer_angle,
+            _ies_profile: self._ies_profile.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct LocalLightData {
+    pub _light_name: String,
+    pub _light_type: LocalLightType,
+    pub _light_color: Vector3<f32>,
+    pub _light_intensity: f32,
+    pub _light_radius: f32,
+    pub _spot_inner_angle: f32,
+    pub _spot_outer_angle: f32,
+    pub _ies_profile: String,
+    pub _ies_profile_index: i32,
+    pub _transform_object: TransformObjectData,
+}
+
+impl LocalLightData {
+    pub fn create_local_light_data(light_name: &String, light_create_info: &LocalLightCreateInfo, resources: &Resources) -> LocalLightData {
+        log::debug!("create_local_light_data: {}", light_name);
+        let mut light_data = LocalLightData {
+            _light_name: light_name.clone(),
+            _light_type: light_create_info._light_type,
+            _light_color: light_create_info._light_color.clone(),
+            _light_intensity: light_create_info._light_intensity,
+            _light_radius: light_create_info._light_radius,
+            _spot_inner_angle: light_create_info._spot_inner_angle.min(light_create_info._spot_outer_angle),
+            _spot_outer_angle: light_create_info._spot_outer_angle,
+            _ies_profile: light_create_info._ies_profile.clone(),
+            _ies_profile_index: INVALID_IES_PROFILE_INDEX,
+            _transform_object: TransformObjectData::new_transform_object_data(),
+        };
+        light_data._transform_object.set_position(&light_create_info._position);
+        light_data._transform_object.set_rotation(&light_create_info._rotation);
+        light_data._transform_object.update_transform_object();
+        light_data.set_ies_profile(&light_create_info._ies_profile, resources);
+        light_data
+    }
+
+    pub fn set_ies_profile(&mut self, ies_profile: &str, resources: &Resources) {
+        self._ies_profile = String::from(ies_profile);
+        self._ies_profile_index = if LocalLightType::SpotLight == self._light_type {
+            resources.get_ies_profile_index(ies_profile)
+        } else {
+            INVALID_IES_PROFILE_INDEX
+        };
+    }
+
+    pub fn get_light_position(&self) -> &Vector3<f32> { self._transform_object.get_position() }
+    pub fn get_light_direction(&self) -> &Vector3<f32> { self._transform_object.get_front() }
+
+    pub fn update_local_light_data(&mut self) -> bool {
+        self._transform_object.update_transform_object()
+    }
+
+    pub fn get_local_light_constants(&self) -> LocalLightConstants {
+        LocalLightConstants {
+            _light_position: self.get_light_position().clone(),
+            _light_radius: self._light_radius,
+            _light_direction: self.get_light_direction().clone(),
+            _spot_cos_inner: math::degree_to_radian(self._spot_inner_angle * 0.5).cos(),
+            _light_color: &self._light_color * self._light_intensity,
+            _spot_cos_outer: math::degree_to_radian(self._spot_outer_angle * 0.5).cos(),
+            _light_left: self._transform_object.get_left().clone(),
+            _light_type: match self._light_type {
+                LocalLightType::SpotLight => 0,
+                LocalLightType::PointLight => 1,
+            },
+            _ies_profile_index: self._ies_profile_index,
+            _reserved0: 0.0,
+            _reserved1: 0.0,
+            _reserved2: 0.0,
+        }
+    }
+
+    // the cutoff radius, a sphere for the point lights and a cone for the spot lights
+    pub fn get_light_bound_box(&self) -> BoundingBox {
+        let position = self.get_light_position();
+        let extent = Vector3::new(self._light_radius, self._light_radius, self._light_radius);
+        let min = position - &extent;
+        let max = position + &extent;
+        BoundingBox {
+            _min: min.clone(),
+            _max: max.clone(),
+            _center: position.clone(),
+            _size: &max - &min,
+            _radius: extent.norm(),
+        }
+    }
+
+    // the world matrix of the debug light volume mesh, a unit sphere or a unit cone(apex at the origin, base at +z 1.0 with radius 1.0).
+    // the volume is scaled by the cutoff radius, so it shows where the light reaches exactly zero.
+    pub fn get_light_volume_matrix(&self) -> Matrix4<f32> {
+        let scale = match self._light_type {
+            LocalLightType::PointLight => Vector3::new(self._light_radius, self._light_radius, self._light_radius),
+            LocalLightType::SpotLight => {
+                let base_radius = self._light_radius * math::degree_to_radian(self._spot_outer_angle * 0.5).tan();
+                Vector3::new(base_radius, base_radius, self._light_radius)
+            },
+        };
+        math::combinate_matrix(self.get_light_position(), &self._transform_object._rotation_matrix, &scale)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_distance_attenuation() {
+        let light_radius = 10.0;
+        // exactly zero at the cutoff radius and beyond
+        assert_eq!(0.0, get_distance_attenuation(light_radius, light_radius));
+        assert_eq!(0.0, get_distance_attenuation(light_radius * 2.0, light_radius));
+        // close to the inverse square far from the radius
+        assert!((get_distance_attenuation(1.0, light_radius) - 1.0).abs() < 0.001);
+        assert!((get_distance_attenuation(2.0, light_radius) - 0.25).abs() < 0.01);
+        // clamped near the light
+        assert_eq!(get_distance_attenuation(MIN_LIGHT_DISTANCE, light_radius), get_distance_attenuation(0.0, light_radius));
+        assert!(get_distance_attenuation(0.0, light_radius).is_finite());
+        // decreasing to the radius
+        let attenuations: Vec<f32> = (1..100).map(|i| get_distance_attenuation(i as f32 * 0.1, light_radius)).collect();
+        assert!(attenuations.windows(2).all(|pair| pair[1] < pair[0]));
+    }
+
+    #[test]
+    fn test_spot_cone_attenuation() {
+        let spot_cos_inner = math::degree_to_radian(20.0f32).cos();
+        let spot_cos_outer = math::degree_to_radian(30.0f32).cos();
+        assert_eq!(1.0, get_spot_cone_attenuation(1.0, spot_cos_inner, spot_cos_outer));
+        assert_eq!(1.0, get_spot_cone_attenuation(spot_cos_inner, spot_cos_inner, spot_cos_outer));
+        assert_eq!(0.0, get_spot_cone_attenuation(spot_cos_outer, spot_cos_inner, spot_cos_outer));
+        assert_eq!(0.0, get_spot_cone_attenuation(0.0, spot_cos_inner, spot_cos_outer));
+        let half = get_spot_cone_attenuation((spot_cos_inner + spot_cos_outer) * 0.5, spot_cos_inner, spot_cos_outer);
+        assert!((half - 0.25).abs() < 0.001);
+        // the hard edge of the same inner and outer angles
+        assert_eq!(1.0, get_spot_cone_attenuation(spot_cos_outer + 0.001, spot_cos_outer, spot_cos_outer));
+    }
+}
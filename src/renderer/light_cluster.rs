@@ -0,0 +1,266 @@
+use std::fs;
+use std::path::PathBuf;
+
+use ash::vk;
+use nalgebra::{ Vector3, Vector4, Matrix4 };
+
+use crate::renderer::light::{ LocalLightConstants, LocalLightData };
+use crate::renderer::render_object_storage::{ get_frustum_planes, is_sphere_in_frustum };
+use crate::renderer::renderer::RendererData;
+use crate::renderer::shadow_atlas::LightId;
+use crate::vulkan_context::buffer::{ self, ShaderBufferData };
+use crate::vulkan_context::shader::SHADER_DIRECTORY;
+
+pub const LIGHT_CLUSTER_SHADER_FILE: &str = "common/light_cluster.glsl";
+// the storage buffers of the project renderer, resolved by get_shader_buffer_data_from_str
+pub const LIGHT_BUFFER_NAME: &str = "light_buffer";
+pub const LIGHT_TILE_BUFFER_NAME: &str = "light_tile_buffer";
+pub const MAX_POINT_LIGHTS: usize = 256; // point and spot lights per frame, must match with light_cluster.glsl
+pub const LIGHT_TILE_SIZE: u32 = 16; // pixels, doubled until the screen fits in MAX_LIGHT_TILE_COUNT
+pub const MAX_LIGHT_TILE_COUNT: usize = 16384; // must match with light_cluster.glsl
+pub const MAX_LIGHT_TILE_INDEX_COUNT: usize = 131072;
+pub const LIGHT_TILE_HEADER_SIZE: usize = 4; // u32, tile size, tile count x, tile count y, light count
+pub const LIGHT_CLUSTER_SHADER_SOURCE: &str = r#"// generated by the engine, light_cluster.rs - LIGHT_CLUSTER_SHADER_SOURCE
+#ifndef LIGHT_CLUSTER_GLSL
+#define LIGHT_CLUSTER_GLSL
+
+#define MAX_POINT_LIGHTS 256
+#define MAX_LIGHT_TILE_COUNT 16384
+
+// the buffers of composite_gbuffer, include common/local_light.glsl first.
+// layout(std430, binding = ..) readonly buffer LIGHT_BUFFER { LOCAL_LIGHT_CONSTANTS _lights[MAX_POINT_LIGHTS]; } light_buffer;
+// layout(std430, binding = ..) readonly buffer LIGHT_TILE_BUFFER { uvec4 _light_tile_header; uvec2 _light_tiles[MAX_LIGHT_TILE_COUNT]; uint _light_indices[]; } light_tile_buffer;
+//
+// _light_tile_header: x: tile size in pixels, y: tile count x, z: tile count y, w: light count
+// _light_tiles: x: the first index of _light_indices, y: the light count of the tile
+//
+// uvec2 light_tile = light_tile_buffer._light_tiles[get_light_tile_index(light_tile_buffer._light_tile_header, gl_FragCoord.xy)];
+// for(uint i = 0; i < light_tile.y; ++i)
+// {
+//     LOCAL_LIGHT_CONSTANTS light = light_buffer._lights[light_tile_buffer._light_indices[light_tile.x + i]];
+// }
+uint get_light_tile_index(uvec4 light_tile_header, vec2 pixel_position)
+{
+    uvec2 tile = min(uvec2(pixel_position) / max(light_tile_header.x, 1u), max(light_tile_header.yz, uvec2(1u)) - 1u);
+    return tile.y * light_tile_header.y + tile.x;
+}
+
+#endif // LIGHT_CLUSTER_GLSL
+"#;
+
+#[derive(Clone, Debug, Default, Copy)]
+pub struct LightClusterStats {
+    pub _total_light_count: usize,
+    pub _visible_light_count: usize,
+    pub _dropped_light_count: usize, // over MAX_POINT_LIGHTS
+    pub _dropped_index_count: usize, // over MAX_LIGHT_TILE_INDEX_COUNT
+}
+
+impl LightClusterStats {
+    pub fn get_stats_text(&self) -> String {
+        format!(
+            "lights: {} / {}, dropped: {}, dropped tile indices: {}",
+            self._visible_light_count,
+            self._total_light_count,
+            self._dropped_light_count,
+            self._dropped_index_count
+        )
+    }
+}
+
+// the screen space tile light lists of the point and spot lights, built on the cpu each frame.
+#[derive(Default)]
+pub struct LightCluster {
+    pub _light_ids: Vec<LightId>, // the visible lights in the order of _light_constants
+    pub _light_constants: Vec<LocalLightConstants>,
+    pub _tile_size: u32,
+    pub _tile_count_x: u32,
+    pub _tile_count_y: u32,
+    pub _tile_light_lists: Vec<Vec<u32>>, // reused between the frames
+    pub _light_tile_buffer_data: Vec<u32>, // header, (first index, count) per tile, then the light indices
+    pub _stats: LightClusterStats,
+}
+
+#[cfg(not(target_os = "android"))]
+pub fn generate_light_cluster_shader() {
+    let mut shader_file_path = PathBuf::from(SHADER_DIRECTORY);
+    shader_file_path.push(LIGHT_CLUSTER_SHADER_FILE);
+    if false == shader_file_path.is_file() {
+        fs::create_dir_all(shader_file_path.parent().unwrap()).expect("Failed to create directories.");
+        fs::write(&shader_file_path, LIGHT_CLUSTER_SHADER_SOURCE).expect("Failed to write");
+        log::info!("generate_light_cluster_shader: {:?}", shader_file_path);
+    }
+}
+
+// the project renderer creates these with its shader buffer datas, host visible like the other per frame buffers.
+pub fn create_light_buffer(renderer_data: &RendererData) -> ShaderBufferData {
+    buffer::create_shader_buffer_data(
+        renderer_data.get_device(),
+        renderer_data.get_device_memory_properties(),
+        &String::from(LIGHT_BUFFER_NAME),
+        vk::BufferUsageFlags::STORAGE_BUFFER,
+        (std::mem::size_of::<LocalLightConstants>() * MAX_POINT_LIGHTS) as vk::DeviceSize,
+        false,
+        false,
+        false,
+    )
+}
+
+pub fn create_light_tile_buffer(renderer_data: &RendererData) -> ShaderBufferData {
+    buffer::create_shader_buffer_data(
+        renderer_data.get_device(),
+        renderer_data.get_device_memory_properties(),
+        &String::from(LIGHT_TILE_BUFFER_NAME),
+        vk::BufferUsageFlags::STORAGE_BUFFER,
+        (std::mem::size_of::<u32>() * (LIGHT_TILE_HEADER_SIZE + MAX_LIGHT_TILE_COUNT * 2 + MAX_LIGHT_TILE_INDEX_COUNT)) as vk::DeviceSize,
+        false,
+        false,
+        false,
+    )
+}
+
+// the pixel rect of the bound sphere, None when it is out of the screen.
+// the whole screen when the sphere reaches behind the camera, the projected corners are not reliable.
+pub fn get_light_screen_rect(view_projection: &Matrix4<f32>, center: &Vector3<f32>, radius: f32, screen_width: u32, screen_height: u32) -> Option<(u32, u32, u32, u32)> {
+    let full_screen = Some((0, 0, screen_width - 1, screen_height - 1));
+    let mut min = (std::f32::MAX, std::f32::MAX);
+    let mut max = (std::f32::MIN, std::f32::MIN);
+    for i in 0..8 {
+        let corner = Vector4::new(
+            center.x + if 0 == (i & 1) { -radius } else { radius },
+            center.y + if 0 == (i & 2) { -radius } else { radius },
+            center.z + if 0 == (i & 4) { -radius } else { radius },
+            1.0
+        );
+        let clip_position = view_projection * corner;
+        if clip_position.w <= std::f32::EPSILON {
+            return full_screen;
+        }
+        let x = (clip_position.x / clip_position.w * 0.5 + 0.5) * screen_width as f32;
+        let y = (clip_position.y / clip_position.w * 0.5 + 0.5) * screen_height as f32;
+        min = (min.0.min(x), min.1.min(y));
+        max = (max.0.max(x), max.1.max(y));
+    }
+    if max.0 < 0.0 || max.1 < 0.0 || (screen_width as f32) <= min.0 || (screen_height as f32) <= min.1 {
+        return None;
+    }
+    Some((
+        min.0.max(0.0) as u32,
+        min.1.max(0.0) as u32,
+        (max.0 as u32).min(screen_width - 1),
+        (max.1 as u32).min(screen_height - 1),
+    ))
+}
+
+impl LightCluster {
+    pub fn get_light_constants(&self) -> &Vec<LocalLightConstants> { &self._light_constants }
+    pub fn get_light_tile_buffer_data(&self) -> &Vec<u32> { &self._light_tile_buffer_data }
+    pub fn get_stats(&self) -> &LightClusterStats { &self._stats }
+
+    pub fn get_tile_light_count(&self, tile_x: u32, tile_y: u32) -> u32 {
+        let tile_index = (tile_y * self._tile_count_x + tile_x) as usize;
+        self._light_tile_buffer_data[LIGHT_TILE_HEADER_SIZE + tile_index * 2 + 1]
+    }
+
+    // the estimated pixels shaded by each light of the last build, the tiles of the light times the tile area
+    pub fn get_light_shaded_pixel_counts(&self) -> Vec<(LightId, u64)> {
+        let tile_area = (self._tile_size * self._tile_size) as u64;
+        let mut tile_counts: Vec<u64> = vec![0; self._light_ids.len()];
+        for tile_light_list in self._tile_light_lists.iter() {
+            for light_index in tile_light_list.iter() {
+                tile_counts[*light_index as usize] += 1;
+            }
+        }
+        self._light_ids.iter().zip(tile_counts.iter()).map(|(light_id, tile_count)| (*light_id, tile_count * tile_area)).collect()
+    }
+
+    fn update_tile_grid(&mut self, screen_width: u32, screen_height: u32) {
+        let mut tile_size = LIGHT_TILE_SIZE;
+        while MAX_LIGHT_TILE_COUNT < (((screen_width + tile_size - 1) / tile_size) * ((screen_height + tile_size - 1) / tile_size)) as usize {
+            tile_size *= 2;
+        }
+        self._tile_size = tile_size;
+        self._tile_count_x = (screen_width + tile_size - 1) / tile_size;
+        self._tile_count_y = (screen_height + tile_size - 1) / tile_size;
+        let tile_count = (self._tile_count_x * self._tile_count_y) as usize;
+        self._tile_light_lists.resize(tile_count, Vec::new());
+        self._tile_light_lists.truncate(tile_count);
+        self._tile_light_lists.iter_mut().for_each(|tile_light_list| tile_light_list.clear());
+    }
+
+    // lights: sorted by the LightId, the nearer lights win when there are more than MAX_POINT_LIGHTS.
+    pub fn build_light_cluster(&mut self, lights: &[(LightId, &LocalLightData)], view_projection: &Matrix4<f32>, view_position: &Vector3<f32>, screen_width: u32, screen_height: u32) {
+        let screen_width = screen_width.max(1);
+        let screen_height = screen_height.max(1);
+        self.update_tile_grid(screen_width, screen_height);
+        self._light_ids.clear();
+        self._light_constants.clear();
+
+        // frustum culling, then the nearest lights first
+        let frustum_planes = get_frustum_planes(view_projection);
+        let mut visible_lights: Vec<(f32, LightId, &LocalLightData)> = lights.iter().filter_map(|(light_id, light_data)| {
+            let position = light_data.get_light_position();
+            let bound_sphere = Vector4::new(position.x, position.y, position.z, light_data._light_radius);
+            if is_sphere_in_frustum(&frustum_planes, &bound_sphere) {
+                Some(((position - view_position).norm_squared(), *light_id, *light_data))
+            } else {
+                None
+            }
+        }).collect();
+        visible_lights.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal).then(a.1.cmp(&b.1)));
+        let dropped_light_count = visible_lights.len().saturating_sub(MAX_POINT_LIGHTS);
+        visible_lights.truncate(MAX_POINT_LIGHTS);
+
+        for (_, light_id, light_data) in visible_lights.iter() {
+            let light_index = self._light_constants.len() as u32;
+            let screen_rect = get_light_screen_rect(view_projection, light_data.get_light_position(), light_data._light_radius, screen_width, screen_height);
+            if let Some((min_x, min_y, max_x, max_y)) = screen_rect {
+                for tile_y in (min_y / self._tile_size)..=(max_y / self._tile_size) {
+                    for tile_x in (min_x / self._tile_size)..=(max_x / self._tile_size) {
+                        self._tile_light_lists[(tile_y * self._tile_count_x + tile_x) as usize].push(light_index);
+                    }
+                }
+                self._light_ids.push(*light_id);
+                self._light_constants.push(light_data.get_local_light_constants());
+            }
+        }
+
+        // header, tiles, indices. the indices start after MAX_LIGHT_TILE_COUNT tiles like the glsl array.
+        let light_indices_offset = LIGHT_TILE_HEADER_SIZE + MAX_LIGHT_TILE_COUNT * 2;
+        self._light_tile_buffer_data.clear();
+        self._light_tile_buffer_data.extend_from_slice(&[self._tile_size, self._tile_count_x, self._tile_count_y, self._light_constants.len() as u32]);
+        self._light_tile_buffer_data.resize(light_indices_offset, 0);
+        let mut dropped_index_count: usize = 0;
+        for (tile_index, tile_light_list) in self._tile_light_lists.iter().enumerate() {
+            let first_index = self._light_tile_buffer_data.len() - light_indices_offset;
+            let light_count = tile_light_list.len().min(MAX_LIGHT_TILE_INDEX_COUNT - first_index);
+            dropped_index_count += tile_light_list.len() - light_count;
+            self._light_tile_buffer_data[LIGHT_TILE_HEADER_SIZE + tile_index * 2] = first_index as u32;
+            self._light_tile_buffer_data[LIGHT_TILE_HEADER_SIZE + tile_index * 2 + 1] = light_count as u32;
+            self._light_tile_buffer_data.extend_from_slice(&tile_light_list[..light_count]);
+        }
+        if 0 < dropped_index_count && 0 == self._stats._dropped_index_count {
+            log::warn!("build_light_cluster: over MAX_LIGHT_TILE_INDEX_COUNT, {} tile indices are dropped", dropped_index_count);
+        }
+
+        self._stats = LightClusterStats {
+            _total_light_count: lights.len(),
+            _visible_light_count: self._light_constants.len(),
+            _dropped_light_count: dropped_light_count,
+            _dropped_index_count: dropped_index_count,
+        };
+    }
+
+    // the tile header and lists are always uploaded, so an empty frame clears the lights of the previous one.
+    pub fn upload_light_cluster(&self, command_buffer: vk::CommandBuffer, swapchain_index: u32, renderer_data: &RendererData) {
+        if self._light_tile_buffer_data.is_empty() {
+            return;
+        }
+        if false == self._light_constants.is_empty() {
+            let light_buffer = renderer_data.get_shader_buffer_data_from_str(LIGHT_BUFFER_NAME);
+            renderer_data.upload_shader_buffer_datas(command_buffer, swapchain_index, light_buffer, &self._light_constants);
+        }
+        let light_tile_buffer = renderer_data.get_shader_buffer_data_from_str(LIGHT_TILE_BUFFER_NAME);
+        renderer_data.upload_shader_buffer_datas(command_buffer, swapchain_index, light_tile_buffer, &self._light_tile_buffer_data);
+    }
+}
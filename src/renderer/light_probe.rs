@@ -0,0 +1,712 @@
+use std::fs;
+use std::path::PathBuf;
+
+use ash::{ vk, Device };
+use ash::version::DeviceV1_0;
+use nalgebra::{ Vector3, Vector4 };
+
+use crate::constants;
+use crate::renderer::atmosphere::{ self, Atmosphere };
+use crate::renderer::renderer::RendererData;
+use crate::renderer::utility;
+use crate::resource::resource::Resources;
+use crate::utilities::math;
+use crate::vulkan_context::descriptor::{ self, DescriptorDataCreateInfo, DescriptorResourceInfo, DescriptorResourceType };
+use crate::vulkan_context::framebuffer::{ self, FramebufferData };
+use crate::vulkan_context::render_pass::{ DepthStencilStateCreateInfo, ImageAttachmentDescription, PipelineDataCreateInfo, RenderPassDataCreateInfo };
+use crate::vulkan_context::shader::SHADER_DIRECTORY;
+use crate::vulkan_context::texture::{ TextureCreateInfo, TextureData };
+use crate::vulkan_context::vulkan_context::{ self, BlendMode, SwapchainArray };
+
+pub const LIGHT_PROBE_RENDER_PASS_NAME: &str = "light_probe";
+pub const LIGHT_PROBE_ATMOSPHERE_PIPELINE_NAME: &str = "capture_atmosphere";
+pub const LIGHT_PROBE_PREFILTER_PIPELINE_NAME: &str = "prefilter_specular";
+pub const LIGHT_PROBE_IRRADIANCE_PIPELINE_NAME: &str = "convolve_irradiance";
+pub const LIGHT_PROBE_CUBEMAP_PIPELINE_NAME: &str = "capture_cubemap"; // the sky cubemap of SkyMode::Cubemap instead of the atmosphere
+// composite_gbuffer of the project binds these names as RenderTarget descriptors, see get_light_probe_descriptor_data_create_infos
+pub const LIGHT_PROBE_SPECULAR_TEXTURE_NAME: &str = "LightProbeSpecular";
+pub const LIGHT_PROBE_IRRADIANCE_TEXTURE_NAME: &str = "LightProbeIrradiance";
+pub const LIGHT_PROBE_CAPTURE_TEXTURE_NAME: &str = "LightProbeCapture";
+pub const LIGHT_PROBE_FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+pub const LIGHT_PROBE_QUAD_MESH_NAME: &str = "quad"; // see Resources::load_mesh_datas
+pub const LIGHT_PROBE_VERTEX_SHADER_FILE: &str = "common/light_probe.vert";
+pub const LIGHT_PROBE_FRAGMENT_SHADER_FILE: &str = "common/light_probe.frag";
+pub const LIGHT_PROBE_SHADER_FILE: &str = "common/light_probe.glsl";
+pub const LIGHT_PROBE_VERTEX_SHADER_SOURCE: &str = r#"#version 450
+
+layout(location = 0) in vec3 inPosition;
+
+void main()
+{
+    gl_Position = vec4(inPosition.xy, 0.0, 1.0);
+}
+"#;
+pub const LIGHT_PROBE_FRAGMENT_SHADER_SOURCE: &str = r#"#version 450
+#extension GL_GOOGLE_include_directive : enable
+
+// LIGHT_PROBE_ATMOSPHERE, LIGHT_PROBE_CUBEMAP, LIGHT_PROBE_PREFILTER or LIGHT_PROBE_IRRADIANCE by the pipeline
+#define LIGHT_PROBE_PI 3.14159265359
+
+#if defined(LIGHT_PROBE_ATMOSPHERE)
+#include "atmosphere.glsl"
+#endif
+
+layout(push_constant) uniform PushConstant_LightProbe
+{
+    vec4 sun_direction; // xyz: toward the sun, w: sun intensity
+    vec4 probe_position; // xyz: world position, w: the intensity of the sky cubemap
+    uint face_index;
+    uint face_size;
+    uint sample_count;
+    float roughness;
+    uint use_combined_textures; // the single mie scattering is extrapolated from the alpha of the scattering
+} pushConstant;
+
+#if defined(LIGHT_PROBE_ATMOSPHERE)
+// the precomputed textures of renderer::atmosphere
+layout(binding = 0) uniform sampler2D texture_transmittance;
+layout(binding = 1) uniform sampler2DArray texture_scattering;
+layout(binding = 2) uniform sampler2DArray texture_single_mie_scattering; // the scattering texture when combined
+layout(binding = 3) uniform sampler2D texture_irradiance;
+#elif defined(LIGHT_PROBE_PREFILTER) || defined(LIGHT_PROBE_IRRADIANCE)
+layout(binding = 0) uniform samplerCube texture_capture;
+#elif defined(LIGHT_PROBE_CUBEMAP)
+layout(binding = 0) uniform samplerCube texture_sky_cubemap;
+#endif
+
+layout(location = 0) out vec4 outColor;
+
+// the face order of vulkan: +x, -x, +y, -y, +z, -z, texcoord (0, 0) is the top-left texel of the face
+vec3 get_cube_face_direction(uint face_index, vec2 texcoord)
+{
+    vec2 st = texcoord * 2.0 - 1.0;
+    vec3 direction;
+    if(0u == face_index) direction = vec3(1.0, -st.y, -st.x);
+    else if(1u == face_index) direction = vec3(-1.0, -st.y, st.x);
+    else if(2u == face_index) direction = vec3(st.x, 1.0, st.y);
+    else if(3u == face_index) direction = vec3(st.x, -1.0, -st.y);
+    else if(4u == face_index) direction = vec3(st.x, -st.y, 1.0);
+    else direction = vec3(-st.x, -st.y, -1.0);
+    return normalize(direction);
+}
+
+#if defined(LIGHT_PROBE_ATMOSPHERE)
+// the sky of every scattering order, the ground below the horizon is lambert with the sun and the sky irradiance
+vec3 render_atmosphere(vec3 view_direction)
+{
+    vec3 sun_direction = normalize(pushConstant.sun_direction.xyz);
+    float sun_intensity = pushConstant.sun_direction.w;
+    vec3 camera = vec3(0.0, BOTTOM_RADIUS + max(pushConstant.probe_position.y, 1.0) * 0.001, 0.0); // km
+    float r = clamp_radius(length(camera));
+    float mu = dot(camera, view_direction) / r;
+    float mu_s = dot(camera, sun_direction) / r;
+    float nu = dot(view_direction, sun_direction);
+    bool ray_r_mu_intersects_ground = ray_intersects_ground(r, mu);
+
+    vec4 scattering = get_scattering(texture_scattering, r, mu, mu_s, nu, ray_r_mu_intersects_ground);
+    vec3 single_mie_scattering = (0u != pushConstant.use_combined_textures)
+        ? get_extrapolated_single_mie_scattering(scattering)
+        : get_scattering(texture_single_mie_scattering, r, mu, mu_s, nu, ray_r_mu_intersects_ground).xyz;
+    vec3 color = scattering.xyz * rayleigh_phase_function(nu) + single_mie_scattering * mie_phase_function(MIE_PHASE_FUNCTION_G, nu);
+    if(ray_r_mu_intersects_ground)
+    {
+        float d = distance_to_bottom_atmosphere_boundary(r, mu);
+        vec3 ground_normal = normalize(camera + view_direction * d);
+        float ground_mu_s = dot(ground_normal, sun_direction);
+        vec3 sun_irradiance = get_transmittance_to_sun(texture_transmittance, BOTTOM_RADIUS, ground_mu_s) * max(ground_mu_s, 0.0);
+        vec3 sky_irradiance = get_irradiance(texture_irradiance, BOTTOM_RADIUS, ground_mu_s);
+        vec3 transmittance = get_transmittance(texture_transmittance, r, mu, d, true);
+        color += transmittance * GROUND_ALBEDO * (1.0 / ATMOSPHERE_PI) * (sun_irradiance + sky_irradiance);
+    }
+    return color * sun_intensity;
+}
+#else
+vec2 hammersley_2d(uint i, uint n)
+{
+    return vec2(float(i) / float(n), float(bitfieldReverse(i)) * 2.3283064365386963e-10);
+}
+
+// the tangent frame around the normal
+vec3 tangent_to_world(vec3 tangent_direction, vec3 normal)
+{
+    vec3 up = abs(normal.z) < 0.999 ? vec3(0.0, 0.0, 1.0) : vec3(1.0, 0.0, 0.0);
+    vec3 tangent = normalize(cross(up, normal));
+    vec3 bitangent = cross(normal, tangent);
+    return normalize(tangent * tangent_direction.x + bitangent * tangent_direction.y + normal * tangent_direction.z);
+}
+#endif
+
+#if defined(LIGHT_PROBE_PREFILTER)
+// the split sum approximation, the view is the normal.
+// the capture has no mips, the sample count trades the noise of the rough mips.
+vec3 prefilter_specular(vec3 normal, float roughness)
+{
+    if(roughness <= 0.0)
+    {
+        return textureLod(texture_capture, normal, 0.0).xyz;
+    }
+
+    float alpha = roughness * roughness;
+    vec3 color = vec3(0.0);
+    float total_weight = 0.0;
+    for(uint i = 0u; i < pushConstant.sample_count; ++i)
+    {
+        vec2 xi = hammersley_2d(i, pushConstant.sample_count);
+        float phi = 2.0 * LIGHT_PROBE_PI * xi.x;
+        float cos_theta = sqrt((1.0 - xi.y) / (1.0 + (alpha * alpha - 1.0) * xi.y));
+        float sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+        vec3 half_vector = tangent_to_world(vec3(sin_theta * cos(phi), sin_theta * sin(phi), cos_theta), normal);
+        vec3 light_direction = normalize(2.0 * dot(normal, half_vector) * half_vector - normal);
+        float n_dot_l = dot(normal, light_direction);
+        if(0.0 < n_dot_l)
+        {
+            color += textureLod(texture_capture, light_direction, 0.0).xyz * n_dot_l;
+            total_weight += n_dot_l;
+        }
+    }
+    return color / max(total_weight, 0.0001);
+}
+#endif
+
+#if defined(LIGHT_PROBE_IRRADIANCE)
+// the cosine weighted hemisphere, the result is the irradiance divided by pi: diffuse = albedo * irradiance
+vec3 convolve_irradiance(vec3 normal)
+{
+    vec3 color = vec3(0.0);
+    for(uint i = 0u; i < pushConstant.sample_count; ++i)
+    {
+        vec2 xi = hammersley_2d(i, pushConstant.sample_count);
+        float phi = 2.0 * LIGHT_PROBE_PI * xi.x;
+        float cos_theta = sqrt(1.0 - xi.y);
+        float sin_theta = sqrt(xi.y);
+        vec3 light_direction = tangent_to_world(vec3(sin_theta * cos(phi), sin_theta * sin(phi), cos_theta), normal);
+        color += textureLod(texture_capture, light_direction, 0.0).xyz;
+    }
+    return color / float(max(pushConstant.sample_count, 1u));
+}
+#endif
+
+void main()
+{
+    vec2 texcoord = gl_FragCoord.xy / float(pushConstant.face_size);
+    vec3 direction = get_cube_face_direction(pushConstant.face_index, texcoord);
+#if defined(LIGHT_PROBE_ATMOSPHERE)
+    outColor = vec4(render_atmosphere(direction), 1.0);
+#elif defined(LIGHT_PROBE_CUBEMAP)
+    // the mip of the capture size, the sky cubemap is usually larger than the capture
+    float lod = max(log2(float(textureSize(texture_sky_cubemap, 0).x) / float(pushConstant.face_size)), 0.0);
+    outColor = vec4(textureLod(texture_sky_cubemap, direction, lod).xyz * pushConstant.probe_position.w, 1.0);
+#elif defined(LIGHT_PROBE_PREFILTER)
+    outColor = vec4(prefilter_specular(direction, pushConstant.roughness), 1.0);
+#else
+    outColor = vec4(convolve_irradiance(direction), 1.0);
+#endif
+}
+"#;
+pub const LIGHT_PROBE_SHADER_SOURCE: &str = r#"// generated by the engine, light_probe.rs - LIGHT_PROBE_SHADER_SOURCE
+#ifndef LIGHT_PROBE_GLSL
+#define LIGHT_PROBE_GLSL
+
+// the cubes of composite_gbuffer, see light_probe::get_light_probe_descriptor_data_create_infos
+// layout(binding = ..) uniform samplerCube LightProbeSpecular;
+// layout(binding = ..) uniform samplerCube LightProbeIrradiance;
+//
+// vec3 ambient_light = diffuse_color * sample_light_probe_irradiance(LightProbeIrradiance, N)
+//     + sample_light_probe_specular(LightProbeSpecular, reflect(-V, N), roughness) * (specular_color * env_brdf.x + env_brdf.y);
+
+// the roughness of the prefiltered mip levels is linear, mip 0 is the mirror
+vec3 sample_light_probe_specular(samplerCube texture_specular, vec3 reflect_direction, float roughness)
+{
+    float max_lod = float(textureQueryLevels(texture_specular) - 1);
+    return textureLod(texture_specular, reflect_direction, clamp(roughness, 0.0, 1.0) * max_lod).xyz;
+}
+
+vec3 sample_light_probe_irradiance(samplerCube texture_irradiance, vec3 normal)
+{
+    return textureLod(texture_irradiance, normal, 0.0).xyz;
+}
+
+#endif // LIGHT_PROBE_GLSL
+"#;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LightProbeSettings {
+    pub _capture_size: u32, // the face size of the captured atmosphere and the specular mip 0
+    pub _specular_mip_count: u32, // the roughness 0.0 ~ 1.0 over the mips
+    pub _irradiance_size: u32,
+    pub _specular_sample_count: u32,
+    pub _irradiance_sample_count: u32,
+    pub _sun_intensity: f32,
+    pub _recapture_sun_angle: f32, // degree, the probe is captured again when the sun moves more than this
+}
+
+impl Default for LightProbeSettings {
+    fn default() -> LightProbeSettings {
+        LightProbeSettings {
+            _capture_size: 128,
+            _specular_mip_count: 5,
+            _irradiance_size: 32,
+            _specular_sample_count: 64,
+            _irradiance_sample_count: 128,
+            _sun_intensity: 20.0,
+            _recapture_sun_angle: 1.0,
+        }
+    }
+}
+
+impl LightProbeSettings {
+    pub fn is_same_textures(&self, other: &LightProbeSettings) -> bool {
+        self._capture_size == other._capture_size && self._specular_mip_count == other._specular_mip_count && self._irradiance_size == other._irradiance_size
+    }
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone)]
+#[repr(C)]
+pub struct PushConstant_LightProbe {
+    pub _sun_direction: Vector4<f32>, // xyz: toward the sun, w: sun intensity
+    pub _probe_position: Vector4<f32>, // xyz: world position, w: the intensity of the sky cubemap
+    pub _face_index: u32,
+    pub _face_size: u32,
+    pub _sample_count: u32,
+    pub _roughness: f32,
+    pub _use_combined_textures: u32,
+}
+
+crate::impl_push_constant!(PushConstant_LightProbe, 52);
+
+// The sky light of the image based lighting: the atmosphere is rendered into the six faces of the capture cube,
+// then prefiltered per mip level with the increasing roughness and convolved into the small irradiance cube.
+// the opaque scene is not captured, the probe is the sky and the ground of the atmosphere only, or the sky cubemap of SkyMode::Cubemap.
+#[derive(Default)]
+pub struct LightProbe {
+    pub _settings: LightProbeSettings,
+    pub _capture_texture: Option<TextureData>,
+    pub _specular_texture: Option<TextureData>,
+    pub _irradiance_texture: Option<TextureData>,
+    pub _capture_framebuffers: Vec<FramebufferData>, // per face
+    pub _specular_framebuffers: Vec<Vec<FramebufferData>>, // per mip level, per face
+    pub _irradiance_framebuffers: Vec<FramebufferData>, // per face
+    pub _prefilter_descriptor_sets: SwapchainArray<vk::DescriptorSet>, // created in prepare_framebuffer_and_descriptors
+    pub _irradiance_descriptor_sets: SwapchainArray<vk::DescriptorSet>,
+    pub _probe_position: Vector3<f32>,
+    pub _sun_direction: Vector3<f32>, // toward the sun
+    pub _captured_sun_direction: Option<Vector3<f32>>, // None: not captured since the textures were created
+    pub _sky_cubemap_descriptor_sets: SwapchainArray<vk::DescriptorSet>, // empty: the atmosphere is captured
+    pub _atmosphere_descriptor_sets: SwapchainArray<vk::DescriptorSet>, // the precomputed textures of RendererData::_atmosphere
+    pub _use_combined_textures: bool,
+    pub _sky_cubemap_name: String,
+    pub _sky_intensity: f32,
+}
+
+pub fn get_render_pass_data_create_info() -> RenderPassDataCreateInfo {
+    let pipeline_data_create_info = |pipeline_name: &str, shader_define: &str, descriptor_data_create_infos: Vec<DescriptorDataCreateInfo>| PipelineDataCreateInfo {
+        _pipeline_data_create_info_name: String::from(pipeline_name),
+        _pipeline_vertex_shader_file: PathBuf::from(LIGHT_PROBE_VERTEX_SHADER_FILE),
+        _pipeline_fragment_shader_file: PathBuf::from(LIGHT_PROBE_FRAGMENT_SHADER_FILE),
+        _pipeline_shader_defines: vec![format!("{}=1", shader_define)],
+        _pipeline_color_blend_modes: vec![vulkan_context::get_color_blend_mode(BlendMode::None)],
+        _depth_stencil_state_create_info: DepthStencilStateCreateInfo {
+            _depth_test_enable: false,
+            _depth_write_enable: false,
+            ..Default::default()
+        },
+        _push_constant_ranges: vec![vk::PushConstantRange {
+            stage_flags: vk::ShaderStageFlags::FRAGMENT,
+            offset: 0,
+            size: std::mem::size_of::<PushConstant_LightProbe>() as u32,
+        }],
+        _descriptor_data_create_infos: descriptor_data_create_infos,
+        ..Default::default()
+    };
+    let texture_capture = || vec![DescriptorDataCreateInfo {
+        _descriptor_binding_index: 0,
+        _descriptor_name: String::from("texture_capture"),
+        _descriptor_resource_type: DescriptorResourceType::RenderTarget,
+        _descriptor_shader_stage: vk::ShaderStageFlags::FRAGMENT,
+        ..Default::default()
+    }];
+    // the single mie scattering is the scattering texture when combined, see create_framebuffers_and_descriptor_sets
+    let texture_atmosphere: Vec<DescriptorDataCreateInfo> = [
+        (0, atmosphere::ATMOSPHERE_TRANSMITTANCE_TEXTURE_NAME),
+        (1, atmosphere::ATMOSPHERE_SCATTERING_TEXTURE_NAME),
+        (2, atmosphere::ATMOSPHERE_SINGLE_MIE_SCATTERING_TEXTURE_NAME),
+        (3, atmosphere::ATMOSPHERE_IRRADIANCE_TEXTURE_NAME),
+    ].iter().map(|(binding_index, texture_name)| DescriptorDataCreateInfo {
+        _descriptor_binding_index: *binding_index,
+        _descriptor_name: String::from(*texture_name),
+        _descriptor_resource_type: DescriptorResourceType::RenderTarget,
+        _descriptor_shader_stage: vk::ShaderStageFlags::FRAGMENT,
+        ..Default::default()
+    }).collect();
+    let texture_sky_cubemap = vec![DescriptorDataCreateInfo {
+        _descriptor_binding_index: 0,
+        _descriptor_name: String::from("texture_sky_cubemap"),
+        _descriptor_resource_type: DescriptorResourceType::Texture,
+        _descriptor_shader_stage: vk::ShaderStageFlags::FRAGMENT,
+        ..Default::default()
+    }];
+
+    RenderPassDataCreateInfo {
+        _render_pass_create_info_name: String::from(LIGHT_PROBE_RENDER_PASS_NAME),
+        _color_attachment_descriptions: vec![ImageAttachmentDescription {
+            _attachment_image_format: LIGHT_PROBE_FORMAT,
+            _attachment_store_operation: vk::AttachmentStoreOp::STORE,
+            ..Default::default()
+        }],
+        _pipeline_data_create_infos: vec![
+            pipeline_data_create_info(LIGHT_PROBE_ATMOSPHERE_PIPELINE_NAME, "LIGHT_PROBE_ATMOSPHERE", texture_atmosphere),
+            pipeline_data_create_info(LIGHT_PROBE_CUBEMAP_PIPELINE_NAME, "LIGHT_PROBE_CUBEMAP", texture_sky_cubemap),
+            pipeline_data_create_info(LIGHT_PROBE_PREFILTER_PIPELINE_NAME, "LIGHT_PROBE_PREFILTER", texture_capture()),
+            pipeline_data_create_info(LIGHT_PROBE_IRRADIANCE_PIPELINE_NAME, "LIGHT_PROBE_IRRADIANCE", texture_capture()),
+        ],
+        ..Default::default()
+    }
+}
+
+// the descriptors of composite_gbuffer, the cubes are resolved by RendererData::get_render_target_from_str
+pub fn get_light_probe_descriptor_data_create_infos(specular_binding_index: u32, irradiance_binding_index: u32) -> Vec<DescriptorDataCreateInfo> {
+    vec![
+        DescriptorDataCreateInfo {
+            _descriptor_binding_index: specular_binding_index,
+            _descriptor_name: String::from(LIGHT_PROBE_SPECULAR_TEXTURE_NAME),
+            _descriptor_resource_type: DescriptorResourceType::RenderTarget,
+            _descriptor_shader_stage: vk::ShaderStageFlags::FRAGMENT,
+            ..Default::default()
+        },
+        DescriptorDataCreateInfo {
+            _descriptor_binding_index: irradiance_binding_index,
+            _descriptor_name: String::from(LIGHT_PROBE_IRRADIANCE_TEXTURE_NAME),
+            _descriptor_resource_type: DescriptorResourceType::RenderTarget,
+            _descriptor_shader_stage: vk::ShaderStageFlags::FRAGMENT,
+            ..Default::default()
+        },
+    ]
+}
+
+#[cfg(not(target_os = "android"))]
+pub fn generate_light_probe_shaders() {
+    let shader_sources = [
+        (LIGHT_PROBE_VERTEX_SHADER_FILE, LIGHT_PROBE_VERTEX_SHADER_SOURCE),
+        (LIGHT_PROBE_FRAGMENT_SHADER_FILE, LIGHT_PROBE_FRAGMENT_SHADER_SOURCE),
+        (LIGHT_PROBE_SHADER_FILE, LIGHT_PROBE_SHADER_SOURCE),
+    ];
+    for (shader_file, shader_source) in shader_sources.iter() {
+        let mut shader_file_path = PathBuf::from(SHADER_DIRECTORY);
+        shader_file_path.push(shader_file);
+        // the atmosphere of the engine has changed the capture, the previous shader files are written again
+        if Some(*shader_source) != fs::read_to_string(&shader_file_path).ok().as_deref() {
+            fs::create_dir_all(shader_file_path.parent().unwrap()).expect("Failed to create directories.");
+            fs::write(&shader_file_path, shader_source).expect("Failed to write");
+            log::info!("generate_light_probe_shaders: {:?}", shader_file_path);
+        }
+    }
+}
+
+impl LightProbe {
+    pub fn create_light_probe_textures(&mut self, renderer_data: &RendererData) {
+        let create_info = |name: &str, size: u32, mip_count: u32| -> TextureCreateInfo<u8> {
+            TextureCreateInfo {
+                _texture_name: String::from(name),
+                _texture_width: size,
+                _texture_height: size,
+                _texture_layers: constants::CUBE_LAYER_COUNT as u32,
+                _texture_format: LIGHT_PROBE_FORMAT,
+                _texture_view_type: vk::ImageViewType::CUBE,
+                _texture_min_filter: vk::Filter::LINEAR,
+                _texture_mag_filter: vk::Filter::LINEAR,
+                _texture_wrap_mode: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                _enable_mipmap: 1 < mip_count,
+                _max_mip_levels: mip_count,
+                ..Default::default()
+            }
+        };
+        let capture_size = self._settings._capture_size.max(1);
+        self._capture_texture = Some(renderer_data.create_render_target::<u8>(&create_info(LIGHT_PROBE_CAPTURE_TEXTURE_NAME, capture_size, 1)));
+        self._specular_texture = Some(renderer_data.create_render_target::<u8>(&create_info(LIGHT_PROBE_SPECULAR_TEXTURE_NAME, capture_size, self._settings._specular_mip_count.max(1))));
+        self._irradiance_texture = Some(renderer_data.create_render_target::<u8>(&create_info(LIGHT_PROBE_IRRADIANCE_TEXTURE_NAME, self._settings._irradiance_size.max(1), 1)));
+        self._captured_sun_direction = None;
+    }
+
+    pub fn destroy_light_probe_textures(&mut self, renderer_data: &RendererData) {
+        for texture in [&mut self._capture_texture, &mut self._specular_texture, &mut self._irradiance_texture].iter_mut() {
+            if let Some(texture_data) = texture.take() {
+                renderer_data.destroy_texture(&texture_data);
+            }
+        }
+        self._captured_sun_direction = None;
+    }
+
+    pub fn get_render_target(&self, render_target_name: &str) -> Option<&TextureData> {
+        match render_target_name {
+            LIGHT_PROBE_SPECULAR_TEXTURE_NAME => self._specular_texture.as_ref(),
+            LIGHT_PROBE_IRRADIANCE_TEXTURE_NAME => self._irradiance_texture.as_ref(),
+            LIGHT_PROBE_CAPTURE_TEXTURE_NAME => self._capture_texture.as_ref(),
+            _ => None,
+        }
+    }
+
+    // the framebuffers per face and per mip level with utility::create_framebuffer, the descriptor sets read the capture cube
+    pub fn create_framebuffers_and_descriptor_sets(&mut self, renderer_data: &RendererData, resources: &Resources) {
+        let (capture_texture, specular_texture, irradiance_texture) = match (&self._capture_texture, &self._specular_texture, &self._irradiance_texture) {
+            (Some(capture_texture), Some(specular_texture), Some(irradiance_texture)) => (capture_texture, specular_texture, irradiance_texture),
+            _ => return,
+        };
+        let device = renderer_data.get_device();
+        let render_pass_data = resources.get_render_pass_data(LIGHT_PROBE_RENDER_PASS_NAME).borrow();
+        let create_face_framebuffers = |texture_data: &TextureData, mip_level: u32| -> Vec<FramebufferData> {
+            (0..constants::CUBE_LAYER_COUNT as u32).map(|face_index| {
+                utility::create_framebuffer(device, &render_pass_data, texture_data, face_index, mip_level, None)
+            }).collect()
+        };
+        self._capture_framebuffers = create_face_framebuffers(capture_texture, 0);
+        self._specular_framebuffers = (0..specular_texture._image_mip_levels).map(|mip_level| create_face_framebuffers(specular_texture, mip_level)).collect();
+        self._irradiance_framebuffers = create_face_framebuffers(irradiance_texture, 0);
+
+        let create_descriptor_sets = |pipeline_name: &str| -> SwapchainArray<vk::DescriptorSet> {
+            let render_pass_pipeline_data = resources.get_render_pass_pipeline_data(LIGHT_PROBE_RENDER_PASS_NAME, pipeline_name);
+            let pipeline_data = render_pass_pipeline_data._pipeline_data.borrow();
+            let descriptor_data = &pipeline_data._descriptor_data;
+            let descriptor_binding_indices: Vec<u32> = descriptor_data._descriptor_data_create_infos.iter().map(|descriptor_data_create_info| {
+                descriptor_data_create_info._descriptor_binding_index
+            }).collect();
+            let descriptor_resource_infos_list: SwapchainArray<Vec<DescriptorResourceInfo>> = constants::SWAPCHAIN_IMAGE_INDICES.iter().map(|_swapchain_index| {
+                vec![DescriptorResourceInfo::DescriptorImageInfo(capture_texture.get_default_image_info().clone())]
+            }).collect();
+            let descriptor_sets = descriptor::create_descriptor_sets(device, descriptor_data, pipeline_name);
+            let _write_descriptor_sets = descriptor::create_write_descriptor_sets_with_update(
+                device,
+                &descriptor_sets,
+                &descriptor_binding_indices,
+                &descriptor_data._descriptor_set_layout_bindings,
+                &descriptor_resource_infos_list,
+            );
+            descriptor_sets
+        };
+        self._prefilter_descriptor_sets = create_descriptor_sets(LIGHT_PROBE_PREFILTER_PIPELINE_NAME);
+        self._irradiance_descriptor_sets = create_descriptor_sets(LIGHT_PROBE_IRRADIANCE_PIPELINE_NAME);
+        self.create_atmosphere_descriptor_sets(renderer_data, resources, &renderer_data._atmosphere.borrow());
+        self._captured_sun_direction = None;
+    }
+
+    fn create_atmosphere_descriptor_sets(&mut self, renderer_data: &RendererData, resources: &Resources, atmosphere: &Atmosphere) {
+        self._use_combined_textures = atmosphere.is_combined_textures();
+        let get_atmosphere_texture = |texture_name: &str| -> Option<&TextureData> {
+            match texture_name {
+                atmosphere::ATMOSPHERE_SINGLE_MIE_SCATTERING_TEXTURE_NAME if self._use_combined_textures => atmosphere.get_render_target(atmosphere::ATMOSPHERE_SCATTERING_TEXTURE_NAME),
+                _ => atmosphere.get_render_target(texture_name),
+            }
+        };
+        let device = renderer_data.get_device();
+        let render_pass_pipeline_data = resources.get_render_pass_pipeline_data(LIGHT_PROBE_RENDER_PASS_NAME, LIGHT_PROBE_ATMOSPHERE_PIPELINE_NAME);
+        let pipeline_data = render_pass_pipeline_data._pipeline_data.borrow();
+        let descriptor_data = &pipeline_data._descriptor_data;
+        let descriptor_binding_indices: Vec<u32> = descriptor_data._descriptor_data_create_infos.iter().map(|descriptor_data_create_info| {
+            descriptor_data_create_info._descriptor_binding_index
+        }).collect();
+        let mut descriptor_resource_infos: Vec<DescriptorResourceInfo> = Vec::new();
+        for descriptor_data_create_info in descriptor_data._descriptor_data_create_infos.iter() {
+            match get_atmosphere_texture(&descriptor_data_create_info._descriptor_name) {
+                Some(texture_data) => descriptor_resource_infos.push(DescriptorResourceInfo::DescriptorImageInfo(texture_data.get_default_image_info().clone())),
+                None => return,
+            }
+        }
+        let descriptor_resource_infos_list: SwapchainArray<Vec<DescriptorResourceInfo>> = constants::SWAPCHAIN_IMAGE_INDICES.iter().map(|_swapchain_index| {
+            descriptor_resource_infos.clone()
+        }).collect();
+        let descriptor_sets = descriptor::create_descriptor_sets(device, descriptor_data, LIGHT_PROBE_ATMOSPHERE_PIPELINE_NAME);
+        let _write_descriptor_sets = descriptor::create_write_descriptor_sets_with_update(
+            device,
+            &descriptor_sets,
+            &descriptor_binding_indices,
+            &descriptor_data._descriptor_set_layout_bindings,
+            &descriptor_resource_infos_list,
+        );
+        self._atmosphere_descriptor_sets = descriptor_sets;
+    }
+
+    pub fn destroy_framebuffers_and_descriptor_sets(&mut self, device: &Device) {
+        for framebuffer_data in self._capture_framebuffers.drain(..)
+            .chain(self._specular_framebuffers.drain(..).flatten())
+            .chain(self._irradiance_framebuffers.drain(..)) {
+            framebuffer::destroy_framebuffer_data(device, &framebuffer_data);
+        }
+        self._prefilter_descriptor_sets.clear();
+        self._irradiance_descriptor_sets.clear();
+        self._sky_cubemap_descriptor_sets.clear();
+        self._atmosphere_descriptor_sets.clear();
+    }
+
+    // None: the atmosphere is captured. called after create_framebuffers_and_descriptor_sets and when the sky cubemap is changed.
+    pub fn set_sky_cubemap(&mut self, renderer_data: &RendererData, resources: &Resources, sky_cubemap: Option<&TextureData>, sky_intensity: f32) {
+        self._sky_cubemap_name = sky_cubemap.map(|texture_data| texture_data._texture_data_name.clone()).unwrap_or_default();
+        self._sky_intensity = sky_intensity;
+        self._sky_cubemap_descriptor_sets.clear();
+        self._captured_sun_direction = None;
+        if let Some(sky_cubemap) = sky_cubemap {
+            let device = renderer_data.get_device();
+            let render_pass_pipeline_data = resources.get_render_pass_pipeline_data(LIGHT_PROBE_RENDER_PASS_NAME, LIGHT_PROBE_CUBEMAP_PIPELINE_NAME);
+            let pipeline_data = render_pass_pipeline_data._pipeline_data.borrow();
+            let descriptor_data = &pipeline_data._descriptor_data;
+            let descriptor_binding_indices: Vec<u32> = descriptor_data._descriptor_data_create_infos.iter().map(|descriptor_data_create_info| {
+                descriptor_data_create_info._descriptor_binding_index
+            }).collect();
+            let descriptor_resource_infos_list: SwapchainArray<Vec<DescriptorResourceInfo>> = constants::SWAPCHAIN_IMAGE_INDICES.iter().map(|_swapchain_index| {
+                vec![DescriptorResourceInfo::DescriptorImageInfo(sky_cubemap.get_default_image_info().clone())]
+            }).collect();
+            let descriptor_sets = descriptor::create_descriptor_sets(device, descriptor_data, LIGHT_PROBE_CUBEMAP_PIPELINE_NAME);
+            let _write_descriptor_sets = descriptor::create_write_descriptor_sets_with_update(
+                device,
+                &descriptor_sets,
+                &descriptor_binding_indices,
+                &descriptor_data._descriptor_set_layout_bindings,
+                &descriptor_resource_infos_list,
+            );
+            self._sky_cubemap_descriptor_sets = descriptor_sets;
+        }
+    }
+
+    // the texture sizes are applied by the next create_light_probe_textures
+    pub fn set_light_probe_settings(&mut self, settings: &LightProbeSettings) {
+        if self._settings != *settings {
+            self._settings = settings.clone();
+            self._captured_sun_direction = None;
+        }
+    }
+
+    pub fn set_sky_intensity(&mut self, sky_intensity: f32) {
+        if sky_intensity != self._sky_intensity {
+            self._sky_intensity = sky_intensity;
+            if false == self._sky_cubemap_descriptor_sets.is_empty() {
+                self._captured_sun_direction = None;
+            }
+        }
+    }
+
+    pub fn set_sun_direction(&mut self, sun_direction: &Vector3<f32>) {
+        self._sun_direction = sun_direction.normalize();
+    }
+
+    // the atmosphere is precomputed again
+    pub fn invalidate_light_probe(&mut self) {
+        self._captured_sun_direction = None;
+    }
+
+    pub fn need_capture(&self) -> bool {
+        if self._capture_framebuffers.is_empty() {
+            return false;
+        }
+        match self._captured_sun_direction {
+            // the sky cubemap does not follow the sun
+            Some(_) if false == self._sky_cubemap_descriptor_sets.is_empty() => false,
+            Some(captured_sun_direction) => {
+                let cos_angle = captured_sun_direction.dot(&self._sun_direction).max(-1.0).min(1.0);
+                math::degree_to_radian(self._settings._recapture_sun_angle).cos() > cos_angle
+            },
+            None => true,
+        }
+    }
+
+    // records the capture, the prefilter and the irradiance, the cubes are left in GENERAL for the fragment and the compute shaders.
+    pub fn render_light_probe(&mut self, command_buffer: vk::CommandBuffer, renderer_data: &RendererData, resources: &Resources, probe_position: &Vector3<f32>) {
+        if self._capture_framebuffers.is_empty() {
+            return;
+        }
+        let quad_mesh_data = resources.get_mesh_data(LIGHT_PROBE_QUAD_MESH_NAME).borrow();
+        let quad_geometry_data = quad_mesh_data.get_default_geometry_data().borrow();
+        let mut push_constant_data = PushConstant_LightProbe {
+            _sun_direction: Vector4::new(self._sun_direction.x, self._sun_direction.y, self._sun_direction.z, self._settings._sun_intensity),
+            _probe_position: Vector4::new(probe_position.x, probe_position.y, probe_position.z, self._sky_intensity),
+            _face_index: 0,
+            _face_size: 1,
+            _sample_count: 0,
+            _roughness: 0.0,
+            _use_combined_textures: self._use_combined_textures as u32,
+        };
+        let render_faces = |pipeline_name: &str, framebuffers: &[FramebufferData], descriptor_sets: &SwapchainArray<vk::DescriptorSet>, push_constant_data: &mut PushConstant_LightProbe| {
+            let render_pass_pipeline_data = resources.get_render_pass_pipeline_data(LIGHT_PROBE_RENDER_PASS_NAME, pipeline_name);
+            let render_pass_data = render_pass_pipeline_data._render_pass_data.borrow();
+            let pipeline_data = render_pass_pipeline_data._pipeline_data.borrow();
+            for (face_index, framebuffer_data) in framebuffers.iter().enumerate() {
+                push_constant_data._face_index = face_index as u32;
+                push_constant_data._face_size = framebuffer_data._framebuffer_info._framebuffer_width;
+                renderer_data.begin_render_pass_pipeline(command_buffer, 0, &render_pass_data, &pipeline_data, Some(framebuffer_data));
+                if false == descriptor_sets.is_empty() {
+                    unsafe {
+                        renderer_data.get_device().cmd_bind_descriptor_sets(
+                            command_buffer,
+                            pipeline_data._pipeline_bind_point,
+                            pipeline_data._pipeline_layout,
+                            0,
+                            &[descriptor_sets[0]],
+                            &[]
+                        );
+                    }
+                }
+                renderer_data.upload_push_constant_data(command_buffer, &pipeline_data, &*push_constant_data);
+                renderer_data.draw_elements(command_buffer, &quad_geometry_data);
+                renderer_data.end_render_pass(command_buffer);
+            }
+        };
+        let image_memory_barrier = |texture_data: &TextureData, src_access_mask: vk::AccessFlags, dst_access_mask: vk::AccessFlags| vk::ImageMemoryBarrier {
+            src_access_mask,
+            dst_access_mask,
+            old_layout: vk::ImageLayout::GENERAL,
+            new_layout: vk::ImageLayout::GENERAL,
+            src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            image: texture_data._image,
+            subresource_range: vk::ImageSubresourceRange {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                base_mip_level: 0,
+                level_count: texture_data._image_mip_levels,
+                base_array_layer: 0,
+                layer_count: texture_data._image_layers,
+            },
+            ..Default::default()
+        };
+
+        renderer_data.begin_debug_label(command_buffer, LIGHT_PROBE_RENDER_PASS_NAME);
+        if self._sky_cubemap_descriptor_sets.is_empty() {
+            render_faces(LIGHT_PROBE_ATMOSPHERE_PIPELINE_NAME, &self._capture_framebuffers, &self._atmosphere_descriptor_sets, &mut push_constant_data);
+        } else {
+            render_faces(LIGHT_PROBE_CUBEMAP_PIPELINE_NAME, &self._capture_framebuffers, &self._sky_cubemap_descriptor_sets, &mut push_constant_data);
+        }
+        renderer_data.pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            vk::PipelineStageFlags::FRAGMENT_SHADER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[image_memory_barrier(self._capture_texture.as_ref().unwrap(), vk::AccessFlags::COLOR_ATTACHMENT_WRITE, vk::AccessFlags::SHADER_READ)]
+        );
+
+        let specular_mip_count = self._specular_framebuffers.len();
+        push_constant_data._sample_count = self._settings._specular_sample_count.max(1);
+        for (mip_level, framebuffers) in self._specular_framebuffers.iter().enumerate() {
+            push_constant_data._roughness = if 1 < specular_mip_count { mip_level as f32 / (specular_mip_count - 1) as f32 } else { 0.0 };
+            render_faces(LIGHT_PROBE_PREFILTER_PIPELINE_NAME, framebuffers, &self._prefilter_descriptor_sets, &mut push_constant_data);
+        }
+        push_constant_data._sample_count = self._settings._irradiance_sample_count.max(1);
+        push_constant_data._roughness = 1.0;
+        render_faces(LIGHT_PROBE_IRRADIANCE_PIPELINE_NAME, &self._irradiance_framebuffers, &self._irradiance_descriptor_sets, &mut push_constant_data);
+
+        renderer_data.pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            vk::PipelineStageFlags::FRAGMENT_SHADER | vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[
+                image_memory_barrier(self._specular_texture.as_ref().unwrap(), vk::AccessFlags::COLOR_ATTACHMENT_WRITE, vk::AccessFlags::SHADER_READ),
+                image_memory_barrier(self._irradiance_texture.as_ref().unwrap(), vk::AccessFlags::COLOR_ATTACHMENT_WRITE, vk::AccessFlags::SHADER_READ),
+            ]
+        );
+        renderer_data.end_debug_label(command_buffer);
+
+        self._probe_position = probe_position.clone();
+        self._captured_sun_direction = Some(self._sun_direction.clone());
+    }
+}
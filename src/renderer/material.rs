@@ -1,15 +1,49 @@
-use serde_json;
+use std::fs;
+use std::path::PathBuf;
 
+use serde_json::{ self, Value };
+
+use crate::renderer::fur::FurShellData;
+use crate::vulkan_context::shader::SHADER_DIRECTORY;
 use crate::vulkan_context::render_pass::{
     RenderPassPipelineData,
     RenderPassPipelineDataMap,
 };
 
+// "material_parameters": { "vertex_ambient_occlusion": true }, multiplies the ao baked at import into the ao input of the material.
+pub const VERTEX_AMBIENT_OCCLUSION_NAME: &str = "vertex_ambient_occlusion";
+pub const VERTEX_AMBIENT_OCCLUSION_SHADER_FILE: &str = "common/vertex_ambient_occlusion.glsl";
+pub const VERTEX_AMBIENT_OCCLUSION_SHADER_SOURCE: &str = r#"// generated by the engine, material.rs - VERTEX_AMBIENT_OCCLUSION_SHADER_SOURCE
+#ifndef VERTEX_AMBIENT_OCCLUSION_GLSL
+#define VERTEX_AMBIENT_OCCLUSION_GLSL
+
+// must match with mesh_ambient_occlusion.rs - encode_vertex_color, b: ambient occlusion, a: cavity
+float apply_vertex_ambient_occlusion(float material_ambient_occlusion, vec4 vertex_color)
+{
+    return material_ambient_occlusion * vertex_color.z * vertex_color.w;
+}
+
+#endif // VERTEX_AMBIENT_OCCLUSION_GLSL
+"#;
+
+#[cfg(not(target_os = "android"))]
+pub fn generate_vertex_ambient_occlusion_shader() {
+    let mut shader_file_path = PathBuf::from(SHADER_DIRECTORY);
+    shader_file_path.push(VERTEX_AMBIENT_OCCLUSION_SHADER_FILE);
+    if false == shader_file_path.is_file() {
+        fs::create_dir_all(shader_file_path.parent().unwrap()).expect("Failed to create directories.");
+        fs::write(&shader_file_path, VERTEX_AMBIENT_OCCLUSION_SHADER_SOURCE).expect("Failed to write");
+        log::info!("generate_vertex_ambient_occlusion_shader: {:?}", shader_file_path);
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct MaterialData {
     pub _material_data_name: String,
     pub _render_pass_pipeline_data_map: RenderPassPipelineDataMap,
     pub _material_parameter_map: serde_json::Value,
+    pub _fur_shell_data: Option<FurShellData>,
+    pub _use_vertex_ambient_occlusion: bool,
 }
 
 impl MaterialData {
@@ -33,7 +67,12 @@ impl MaterialData {
         MaterialData {
             _material_data_name: material_data_name.clone(),
             _render_pass_pipeline_data_map: render_pass_pipeline_data_map,
-            _material_parameter_map: material_parameter_map.clone()
+            _material_parameter_map: material_parameter_map.clone(),
+            _fur_shell_data: FurShellData::create_fur_shell_data(material_parameter_map),
+            _use_vertex_ambient_occlusion: match material_parameter_map.get(VERTEX_AMBIENT_OCCLUSION_NAME) {
+                Some(Value::Bool(use_vertex_ambient_occlusion)) => *use_vertex_ambient_occlusion,
+                _ => false,
+            },
         }
     }
 
@@ -41,6 +80,10 @@ impl MaterialData {
         log::debug!("create_material: {}", self._material_data_name);
     }
 
+    pub fn has_fur_shell_data(&self) -> bool {
+        self._fur_shell_data.is_some()
+    }
+
     pub fn get_render_pass_pipeline_data(
         &self,
         render_pass_pipeline_data_name: &str
@@ -1,16 +1,166 @@
 use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
 
 use ash::{
     vk,
     Device,
 };
+use ash::version::DeviceV1_0;
+use nalgebra::{ Vector2, Vector4 };
+use serde::{ Serialize, Deserialize };
+use serde_json::Value;
 
+use crate::constants;
 use crate::renderer::material::MaterialData;
-use crate::vulkan_context::descriptor::{ self, DescriptorResourceInfo };
-use crate::vulkan_context::vulkan_context::SwapchainArray;
+use crate::renderer::renderer::RendererData;
+use crate::renderer::shader_hook::{ ShaderHookSet, ShaderHookParameters, MAX_SHADER_HOOK_PARAMETER_COUNT };
+use crate::resource::resource::{ Resources, DEFAULT_TEXTURE_NAME };
+use crate::vulkan_context::bindless_texture::{ self, BindlessMaterialData };
+use crate::vulkan_context::descriptor::{ self, DescriptorResourceInfo, DescriptorResourceType };
+use crate::vulkan_context::vulkan_context::{ BlendMode, SwapchainArray };
 use crate::vulkan_context::render_pass::{self, RenderPassPipelineData, RenderPassData, PipelineData};
+use crate::vulkan_context::shader::SHADER_DIRECTORY;
 use crate::utilities::system::RcRefCell;
 
+// the per instance MaterialParameterOverrides of RendererData::render_solid_instanced, indexed as the instance matrices
+pub const MATERIAL_OVERRIDE_BUFFER_NAME: &str = "MaterialOverrideBuffer";
+pub const MATERIAL_OVERRIDE_SHADER_FILE: &str = "common/material_override.glsl";
+// "material_parameter_overrides" of the material instance file, the defaults of the render objects which use the material instance
+pub const MATERIAL_PARAMETER_OVERRIDES_NAME: &str = "material_parameter_overrides";
+pub const MATERIAL_OVERRIDE_SHADER_SOURCE: &str = r#"// generated by the engine, material_instance.rs - MATERIAL_OVERRIDE_SHADER_SOURCE
+#ifndef MATERIAL_OVERRIDE_GLSL
+#define MATERIAL_OVERRIDE_GLSL
+
+// must match with MaterialParameterOverrides, the defaults leave the material unchanged
+struct MaterialOverride
+{
+    vec4 base_color_factor;
+    vec4 emissive; // rgb: added to the emissive of the material
+    float metallic_factor;
+    float roughness_factor;
+    vec2 uv_tiling;
+};
+
+vec2 apply_material_override_uv(const in MaterialOverride material_override, vec2 texcoord)
+{
+    return texcoord * material_override.uv_tiling;
+}
+
+void apply_material_override(const in MaterialOverride material_override, inout vec4 base_color, inout float metallic, inout float roughness, inout vec3 emissive)
+{
+    base_color *= material_override.base_color_factor;
+    metallic = clamp(metallic * material_override.metallic_factor, 0.0, 1.0);
+    roughness = clamp(roughness * material_override.roughness_factor, 0.0, 1.0);
+    emissive += material_override.emissive.xyz;
+}
+
+#endif // MATERIAL_OVERRIDE_GLSL
+"#;
+
+#[cfg(not(target_os = "android"))]
+pub fn generate_material_override_shader() {
+    let mut shader_file_path = PathBuf::from(SHADER_DIRECTORY);
+    shader_file_path.push(MATERIAL_OVERRIDE_SHADER_FILE);
+    if false == shader_file_path.is_file() {
+        fs::create_dir_all(shader_file_path.parent().unwrap()).expect("Failed to create directories.");
+        fs::write(&shader_file_path, MATERIAL_OVERRIDE_SHADER_SOURCE).expect("Failed to write");
+        log::info!("generate_material_override_shader: {:?}", shader_file_path);
+    }
+}
+
+// the per object tint of a shared material instance, ex) one red crate.
+// the static objects upload it into MATERIAL_OVERRIDE_BUFFER_NAME, the single instance path reads RenderElementData::_material_parameter_overrides.
+#[derive(Serialize, Deserialize, Clone, Debug, Copy, PartialEq)]
+#[serde(default)]
+#[repr(C)]
+pub struct MaterialParameterOverrides {
+    pub _base_color_factor: Vector4<f32>,
+    pub _emissive: Vector4<f32>, // rgb: added to the emissive of the material
+    pub _metallic_factor: f32,
+    pub _roughness_factor: f32,
+    pub _uv_tiling: Vector2<f32>,
+}
+
+impl Default for MaterialParameterOverrides {
+    fn default() -> MaterialParameterOverrides {
+        MaterialParameterOverrides {
+            _base_color_factor: Vector4::new(1.0, 1.0, 1.0, 1.0),
+            _emissive: Vector4::zeros(),
+            _metallic_factor: 1.0,
+            _roughness_factor: 1.0,
+            _uv_tiling: Vector2::new(1.0, 1.0),
+        }
+    }
+}
+
+impl MaterialParameterOverrides {
+    pub fn create_material_parameter_overrides(material_parameter_overrides: Option<&Value>) -> MaterialParameterOverrides {
+        match material_parameter_overrides {
+            None => MaterialParameterOverrides::default(),
+            Some(material_parameter_overrides) => match serde_json::from_value(material_parameter_overrides.clone()) {
+                Ok(material_parameter_overrides) => material_parameter_overrides,
+                Err(e) => {
+                    log::warn!("create_material_parameter_overrides: invalid overrides {:?}", e);
+                    MaterialParameterOverrides::default()
+                }
+            }
+        }
+    }
+
+    pub fn get_parameter_names() -> Vec<String> {
+        match serde_json::to_value(MaterialParameterOverrides::default()) {
+            Ok(Value::Object(parameter_map)) => parameter_map.keys().cloned().collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    // ex) set_parameter("_metallic_factor", &json!(0.5)), the value keeps the type of the field.
+    pub fn set_parameter(&mut self, parameter_name: &str, value: &Value) -> Result<(), String> {
+        let mut parameter_map = match serde_json::to_value(&*self) {
+            Ok(Value::Object(parameter_map)) => parameter_map,
+            _ => return Err(String::from("failed to serialize the material parameter overrides")),
+        };
+        if false == parameter_map.contains_key(parameter_name) {
+            return Err(format!("uniform value is not found: {}, the uniform values are {:?}", parameter_name, MaterialParameterOverrides::get_parameter_names()));
+        }
+        parameter_map.insert(String::from(parameter_name), value.clone());
+        *self = serde_json::from_value(Value::Object(parameter_map)).map_err(|e| format!("invalid value of {}: {} {}", parameter_name, value, e))?;
+        Ok(())
+    }
+}
+
+// the texture of the texture slot, the parameters of the material instance override the defaults of the material
+pub fn get_texture_data_name(material_parameter_map: &serde_json::Map<String, Value>, default_material_parameter_map: &Value, material_parameter_name: &str) -> String {
+    let maybe_material_parameter = match material_parameter_map.get(material_parameter_name) {
+        None => default_material_parameter_map.get(material_parameter_name),
+        value => value,
+    };
+    match maybe_material_parameter {
+        Some(Value::String(texture_data_name)) => texture_data_name.clone(),
+        _ => String::from(DEFAULT_TEXTURE_NAME),
+    }
+}
+
+// the textures of all the texture slots of the material, acquired by the material instance
+pub fn get_texture_data_names(material_data: &MaterialData, material_parameter_map: &serde_json::Map<String, Value>) -> Vec<String> {
+    let mut texture_data_names: Vec<String> = Vec::new();
+    for render_pass_pipeline_data in material_data._render_pass_pipeline_data_map.values() {
+        let pipeline_data = render_pass_pipeline_data._pipeline_data.borrow();
+        for descriptor_data_create_info in pipeline_data._descriptor_data._descriptor_data_create_infos.iter() {
+            match descriptor_data_create_info._descriptor_resource_type {
+                DescriptorResourceType::Texture | DescriptorResourceType::StorageTexture => texture_data_names.push(
+                    get_texture_data_name(material_parameter_map, &material_data._material_parameter_map, &descriptor_data_create_info._descriptor_name)
+                ),
+                _ => (),
+            }
+        }
+    }
+    texture_data_names.sort();
+    texture_data_names.dedup();
+    texture_data_names
+}
+
 #[derive(Clone, Debug)]
 pub struct PipelineBindingData {
     pub _render_pass_pipeline_data: RenderPassPipelineData,
@@ -20,14 +170,111 @@ pub struct PipelineBindingData {
     pub _descriptor_resource_infos_list: SwapchainArray<Vec<DescriptorResourceInfo>>,
 }
 
+// the descriptor of set_texture_binding which is not written yet to the descriptor sets of some swapchain images
+#[derive(Clone, Debug)]
+pub struct PendingDescriptorUpdate {
+    pub _pipeline_binding_name: String,
+    pub _descriptor_index: usize,
+    pub _descriptor_resource_info: DescriptorResourceInfo,
+    pub _pending_swapchain_indices: Vec<usize>,
+}
+
 type PipelineBindingDataMap = HashMap<String, PipelineBindingData>;
 
+// "blend_mode" of the material instance file, Opaque keeps the blend state of the pipeline.
+// the other modes select the blend mode variant of the pipelines with _pipeline_blend_mode_variants.
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Hash)]
+pub enum MaterialBlendMode {
+    Opaque,
+    AlphaBlend,
+    Additive,
+    PremultipliedAlpha,
+    Multiply,
+}
+
+impl Default for MaterialBlendMode {
+    fn default() -> MaterialBlendMode {
+        MaterialBlendMode::Opaque
+    }
+}
+
+impl MaterialBlendMode {
+    pub fn from_blend_mode_name(blend_mode_name: &str) -> Option<MaterialBlendMode> {
+        match blend_mode_name {
+            "opaque" => Some(MaterialBlendMode::Opaque),
+            "alpha_blend" => Some(MaterialBlendMode::AlphaBlend),
+            "additive" => Some(MaterialBlendMode::Additive),
+            "premultiplied_alpha" => Some(MaterialBlendMode::PremultipliedAlpha),
+            "multiply" => Some(MaterialBlendMode::Multiply),
+            _ => None,
+        }
+    }
+
+    pub fn get_blend_mode_name(&self) -> &str {
+        match self {
+            MaterialBlendMode::Opaque => "opaque",
+            MaterialBlendMode::AlphaBlend => "alpha_blend",
+            MaterialBlendMode::Additive => "additive",
+            MaterialBlendMode::PremultipliedAlpha => "premultiplied_alpha",
+            MaterialBlendMode::Multiply => "multiply",
+        }
+    }
+
+    pub fn create_material_blend_mode(blend_mode: Option<&Value>) -> MaterialBlendMode {
+        match blend_mode {
+            None => MaterialBlendMode::Opaque,
+            Some(Value::String(blend_mode_name)) => match MaterialBlendMode::from_blend_mode_name(blend_mode_name.as_str()) {
+                Some(material_blend_mode) => material_blend_mode,
+                None => {
+                    log::warn!("create_material_blend_mode: invalid blend mode {}", blend_mode_name);
+                    MaterialBlendMode::Opaque
+                }
+            },
+            Some(blend_mode) => {
+                log::warn!("create_material_blend_mode: invalid blend mode {:?}", blend_mode);
+                MaterialBlendMode::Opaque
+            }
+        }
+    }
+
+    pub fn get_blend_mode(&self) -> BlendMode {
+        match self {
+            MaterialBlendMode::Opaque => BlendMode::None,
+            MaterialBlendMode::AlphaBlend => BlendMode::AlphaBlend,
+            MaterialBlendMode::Additive => BlendMode::Additive,
+            MaterialBlendMode::PremultipliedAlpha => BlendMode::PreMultipliedAlpha,
+            MaterialBlendMode::Multiply => BlendMode::Multiply,
+        }
+    }
+
+    pub fn is_translucent(&self) -> bool {
+        MaterialBlendMode::Opaque != *self
+    }
+
+    // additive and multiply are commutative, the result doesn't depend on the draw order
+    pub fn requires_sorting(&self) -> bool {
+        match self {
+            MaterialBlendMode::AlphaBlend | MaterialBlendMode::PremultipliedAlpha => true,
+            _ => false,
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct MaterialInstanceData {
     pub _material_instance_data_name: String,
     pub _material_data: RcRefCell<MaterialData>,
     pub _pipeline_binding_data_map: PipelineBindingDataMap,
     pub _default_pipeline_binding_name: String,
+    pub _shader_hook_set: ShaderHookSet,
+    pub _shader_hook_parameters: ShaderHookParameters, // default parameters, render objects can override them.
+    pub _blend_mode: MaterialBlendMode,
+    pub _bindless_material_data: BindlessMaterialData, // the texture indices of the pipelines with _use_bindless_textures
+    pub _material_instance_file_path: PathBuf,
+    pub _material_instance_create_info: serde_json::Map<String, Value>, // the contents of the file with the runtime edits, see serialize_overrides
+    pub _texture_data_names: Vec<String>, // acquired by Resources at the creation, released by destroy_material_instance
+    pub _material_parameter_overrides: MaterialParameterOverrides, // the uniform values, see set_uniform_value
+    pub _pending_descriptor_updates: Vec<PendingDescriptorUpdate>, // see apply_pending_descriptor_updates
 }
 
 impl PipelineBindingData {
@@ -55,6 +302,13 @@ impl MaterialInstanceData {
         material_instance_data_name: &String,
         material_data: RcRefCell<MaterialData>,
         pipeline_bind_create_infos: Vec<(render_pass::RenderPassPipelineData, SwapchainArray<Vec<descriptor::DescriptorResourceInfo>>)>,
+        shader_hook_set: ShaderHookSet,
+        shader_hook_parameters: ShaderHookParameters,
+        blend_mode: MaterialBlendMode,
+        bindless_material_data: BindlessMaterialData,
+        material_instance_file_path: &PathBuf,
+        material_instance_create_info: serde_json::Map<String, Value>,
+        texture_data_names: Vec<String>,
     ) -> MaterialInstanceData {
         log::debug!("create_material_instance: {}", material_instance_data_name);
         log::trace!("    material_data: {}", material_data.borrow()._material_data_name);
@@ -73,7 +327,7 @@ impl MaterialInstanceData {
 
             log::trace!("        renderpass/pipeline: {}", render_pass_pipeline_data_name);
             let descriptor_data = &render_pass_pipeline_data._pipeline_data.borrow()._descriptor_data;
-            let descriptor_sets = descriptor::create_descriptor_sets(device, descriptor_data);
+            let descriptor_sets = descriptor::create_descriptor_sets(device, descriptor_data, &format!("{}/{}", material_instance_data_name, render_pass_pipeline_data_name));
             let descriptor_binding_indices: Vec<u32> = descriptor_data._descriptor_data_create_infos.iter().map(|descriptor_data_create_info| {
                 descriptor_data_create_info._descriptor_binding_index
             }).collect();
@@ -96,16 +350,82 @@ impl MaterialInstanceData {
             pipeline_binding_data_map.insert(render_pass_pipeline_data_name, pipeline_binding_data);
         }
 
+        let material_parameter_overrides = MaterialParameterOverrides::create_material_parameter_overrides(material_instance_create_info.get(MATERIAL_PARAMETER_OVERRIDES_NAME));
         MaterialInstanceData {
             _material_instance_data_name: material_instance_data_name.clone(),
             _material_data: material_data.clone(),
             _pipeline_binding_data_map: pipeline_binding_data_map,
             _default_pipeline_binding_name: default_pipeline_binding_name,
+            _shader_hook_set: shader_hook_set,
+            _shader_hook_parameters: shader_hook_parameters,
+            _blend_mode: blend_mode,
+            _bindless_material_data: bindless_material_data,
+            _material_instance_file_path: material_instance_file_path.clone(),
+            _material_instance_create_info: material_instance_create_info,
+            _texture_data_names: texture_data_names,
+            _material_parameter_overrides: material_parameter_overrides,
+            _pending_descriptor_updates: Vec::new(),
         }
     }
 
-    pub fn destroy_material_instance(&self) {
+    // the descriptor sets stay in the pools of the pipelines, the textures are collected after their release.
+    pub fn destroy_material_instance(&mut self, resources: &mut Resources) {
         log::debug!("destroy_material_instance: {}", self._material_instance_data_name);
+        for texture_data_name in self._texture_data_names.drain(..) {
+            resources.release_texture_data(&texture_data_name);
+        }
+    }
+
+    pub fn get_material_parameter_map(&self) -> serde_json::Map<String, Value> {
+        match self._material_instance_create_info.get("material_parameters") {
+            Some(Value::Object(material_parameter_map)) => material_parameter_map.clone(),
+            _ => serde_json::Map::new(),
+        }
+    }
+
+    // rewrites the texture slots of all the pipelines after the textures are loaded again, ex) Resources::reload_material_instance_data
+    // the material instance was unloaded for the frames in flight, so the descriptor sets are not in use.
+    pub fn update_texture_descriptors(&mut self, device: &Device, resources: &Resources) {
+        self._pending_descriptor_updates.clear();
+        let material_parameter_map = self.get_material_parameter_map();
+        let material_data = self._material_data.clone();
+        for pipeline_binding_data in self._pipeline_binding_data_map.values_mut() {
+            let descriptor_data_create_infos = pipeline_binding_data.get_pipeline_data().borrow()._descriptor_data._descriptor_data_create_infos.clone();
+            for (descriptor_index, descriptor_data_create_info) in descriptor_data_create_infos.iter().enumerate() {
+                match descriptor_data_create_info._descriptor_resource_type {
+                    DescriptorResourceType::Texture | DescriptorResourceType::StorageTexture => (),
+                    _ => continue,
+                }
+                let texture_data_name = get_texture_data_name(&material_parameter_map, &material_data.borrow()._material_parameter_map, &descriptor_data_create_info._descriptor_name);
+                let texture_data = resources.get_texture_data(&texture_data_name).borrow();
+                let descriptor_resource_info = if descriptor_data_create_info.use_sub_image() {
+                    DescriptorResourceInfo::DescriptorImageInfo(texture_data.get_sub_image_info(
+                        descriptor_data_create_info._descriptor_image_layer,
+                        descriptor_data_create_info._descriptor_image_mip_level,
+                    ))
+                } else {
+                    DescriptorResourceInfo::DescriptorImageInfo(texture_data.get_default_image_info())
+                };
+                for swapchain_index in constants::SWAPCHAIN_IMAGE_INDICES.iter() {
+                    let descriptor_resource_infos = &mut pipeline_binding_data._descriptor_resource_infos_list[*swapchain_index];
+                    descriptor_resource_infos[descriptor_index] = descriptor_resource_info.clone();
+                    let write_descriptor_sets = &mut pipeline_binding_data._write_descriptor_sets[*swapchain_index];
+                    descriptor::update_write_descriptor_set(write_descriptor_sets, descriptor_index, &descriptor_resource_infos[descriptor_index]);
+                    let descriptor_copies: &[vk::CopyDescriptorSet] = &[];
+                    unsafe {
+                        device.update_descriptor_sets(&[write_descriptor_sets[descriptor_index]], descriptor_copies);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn has_shader_hooks(&self) -> bool {
+        false == self._shader_hook_set.is_empty()
+    }
+
+    pub fn get_blend_mode(&self) -> MaterialBlendMode {
+        self._blend_mode
     }
 
     pub fn get_default_pipeline_binding_data(
@@ -127,10 +447,226 @@ impl MaterialInstanceData {
         self._pipeline_binding_data_map.get(render_pass_pipeline_data_name).unwrap()
     }
 
+    pub fn has_pipeline_binding_data(&self, render_pass_pipeline_data_name: &str) -> bool {
+        self._pipeline_binding_data_map.contains_key(render_pass_pipeline_data_name)
+    }
+
+    // the variant shares the descriptor sets of the base pipeline, the descriptor set layouts are the same. ex) the debug view pipelines
+    pub fn add_pipeline_binding_variant(&mut self, render_pass_pipeline_data_name: &str, variant_name: &str, render_pass_pipeline_data: RenderPassPipelineData) {
+        let pipeline_binding_data = match self._pipeline_binding_data_map.get(render_pass_pipeline_data_name) {
+            Some(pipeline_binding_data) => PipelineBindingData {
+                _render_pass_pipeline_data: render_pass_pipeline_data,
+                ..pipeline_binding_data.clone()
+            },
+            None => return,
+        };
+        self._pipeline_binding_data_map.insert(String::from(variant_name), pipeline_binding_data);
+    }
+
     pub fn get_pipeline_binding_data_mut(
         &mut self,
         render_pass_pipeline_data_name: &str,
     ) -> &mut PipelineBindingData {
         self._pipeline_binding_data_map.get_mut(render_pass_pipeline_data_name).unwrap()
     }
-}
\ No newline at end of file
+    // the texture slots of all the pipelines, ex) the slot list of the material editor
+    pub fn get_texture_binding_names(&self) -> Vec<String> {
+        let mut texture_binding_names: Vec<String> = Vec::new();
+        for pipeline_binding_data in self._pipeline_binding_data_map.values() {
+            let pipeline_data = pipeline_binding_data.get_pipeline_data().borrow();
+            for descriptor_data_create_info in pipeline_data._descriptor_data._descriptor_data_create_infos.iter() {
+                if let DescriptorResourceType::Texture = descriptor_data_create_info._descriptor_resource_type {
+                    texture_binding_names.push(descriptor_data_create_info._descriptor_name.clone());
+                }
+            }
+        }
+        texture_binding_names.sort();
+        texture_binding_names.dedup();
+        texture_binding_names
+    }
+
+    // rebinds the texture slot of all the pipelines which have it, ex) Resources::set_material_instance_texture_binding
+    // the new texture is acquired before the previous textures are released. the descriptor sets are used by the frames in flight,
+    // so each swapchain image rewrites its descriptor sets at the beginning of its next frame, see apply_pending_descriptor_updates.
+    pub fn set_texture_binding(&mut self, renderer_data: &RendererData, resources: &mut Resources, binding_name: &str, texture_name: &str) -> Result<(), String> {
+        if false == resources.has_texture_data(texture_name) {
+            return Err(format!("set_texture_binding {}: texture is not found: {}", self._material_instance_data_name, texture_name));
+        }
+
+        // the pipeline binding name and the descriptor index of the slot
+        let mut binding_slots: Vec<(String, usize)> = Vec::new();
+        for (pipeline_binding_name, pipeline_binding_data) in self._pipeline_binding_data_map.iter() {
+            let pipeline_data = pipeline_binding_data.get_pipeline_data().borrow();
+            let descriptor_data_create_infos = &pipeline_data._descriptor_data._descriptor_data_create_infos;
+            if let Some(descriptor_index) = descriptor_data_create_infos.iter().position(|descriptor_data_create_info| descriptor_data_create_info._descriptor_name == binding_name) {
+                match descriptor_data_create_infos[descriptor_index]._descriptor_resource_type {
+                    DescriptorResourceType::Texture => binding_slots.push((pipeline_binding_name.clone(), descriptor_index)),
+                    ref descriptor_resource_type => return Err(format!(
+                        "set_texture_binding {}: {} of {} is not a texture slot: {:?}",
+                        self._material_instance_data_name, binding_name, pipeline_binding_name, descriptor_resource_type
+                    )),
+                }
+            }
+        }
+        if binding_slots.is_empty() {
+            return Err(format!(
+                "set_texture_binding {}: texture slot is not found: {}, the texture slots are {:?}",
+                self._material_instance_data_name, binding_name, self.get_texture_binding_names()
+            ));
+        }
+
+        let material_parameters = self._material_instance_create_info.entry("material_parameters").or_insert_with(|| Value::Object(serde_json::Map::new()));
+        if let Value::Object(material_parameter_map) = material_parameters {
+            material_parameter_map.insert(String::from(binding_name), Value::String(String::from(texture_name)));
+        }
+
+        // the textures of all the slots with the new one, the shared textures are counted again before the release
+        let material_parameter_map = self.get_material_parameter_map();
+        let texture_data_names = resources.acquire_material_instance_textures(renderer_data, &self._material_data.borrow(), &material_parameter_map);
+        for texture_data_name in std::mem::replace(&mut self._texture_data_names, texture_data_names) {
+            resources.release_texture_data(&texture_data_name);
+        }
+
+        let texture_data = resources.get_texture_data(texture_name).borrow();
+        for (pipeline_binding_name, descriptor_index) in binding_slots.into_iter() {
+            let descriptor_data_create_info = self._pipeline_binding_data_map.get(&pipeline_binding_name).unwrap().get_pipeline_data().borrow()._descriptor_data._descriptor_data_create_infos[descriptor_index].clone();
+            let descriptor_resource_info = if descriptor_data_create_info.use_sub_image() {
+                DescriptorResourceInfo::DescriptorImageInfo(texture_data.get_sub_image_info(
+                    descriptor_data_create_info._descriptor_image_layer,
+                    descriptor_data_create_info._descriptor_image_mip_level,
+                ))
+            } else {
+                DescriptorResourceInfo::DescriptorImageInfo(texture_data.get_default_image_info())
+            };
+            // the last binding of the slot wins over the updates which are not applied yet
+            self._pending_descriptor_updates.retain(|pending_descriptor_update| {
+                pending_descriptor_update._pipeline_binding_name != pipeline_binding_name || pending_descriptor_update._descriptor_index != descriptor_index
+            });
+            self._pending_descriptor_updates.push(PendingDescriptorUpdate {
+                _pipeline_binding_name: pipeline_binding_name,
+                _descriptor_index: descriptor_index,
+                _descriptor_resource_info: descriptor_resource_info,
+                _pending_swapchain_indices: constants::SWAPCHAIN_IMAGE_INDICES.to_vec(),
+            });
+        }
+
+        // the pipelines with _use_bindless_textures read the indices every frame
+        let bindless_texture_index = texture_data._bindless_texture_index;
+        match binding_name {
+            bindless_texture::BINDLESS_ALBEDO_TEXTURE_PARAMETER_NAME => self._bindless_material_data._albedo_texture_index = bindless_texture_index,
+            bindless_texture::BINDLESS_NORMAL_TEXTURE_PARAMETER_NAME => self._bindless_material_data._normal_texture_index = bindless_texture_index,
+            bindless_texture::BINDLESS_MATERIAL_TEXTURE_PARAMETER_NAME => self._bindless_material_data._material_texture_index = bindless_texture_index,
+            _ => (),
+        }
+        log::info!("set_texture_binding {}: {} = {}", self._material_instance_data_name, binding_name, texture_name);
+        Ok(())
+    }
+
+    pub fn has_pending_descriptor_updates(&self) -> bool {
+        false == self._pending_descriptor_updates.is_empty()
+    }
+
+    // called before the command buffer of the swapchain image is recorded, the previous frame of the image is finished.
+    // the variants share the descriptor sets, their write descriptor sets point to their own resource infos after the update
+    pub fn apply_pending_descriptor_updates(&mut self, device: &Device, swapchain_index: usize) {
+        let pipeline_binding_data_map = &mut self._pipeline_binding_data_map;
+        for pending_descriptor_update in self._pending_descriptor_updates.iter_mut() {
+            if false == pending_descriptor_update._pending_swapchain_indices.contains(&swapchain_index) {
+                continue;
+            }
+            pending_descriptor_update._pending_swapchain_indices.retain(|pending_swapchain_index| *pending_swapchain_index != swapchain_index);
+            let descriptor_index = pending_descriptor_update._descriptor_index;
+            let pipeline_binding_data = match pipeline_binding_data_map.get_mut(&pending_descriptor_update._pipeline_binding_name) {
+                Some(pipeline_binding_data) => pipeline_binding_data,
+                None => continue,
+            };
+            let descriptor_resource_infos = &mut pipeline_binding_data._descriptor_resource_infos_list[swapchain_index];
+            descriptor_resource_infos[descriptor_index] = pending_descriptor_update._descriptor_resource_info.clone();
+            let write_descriptor_sets = &mut pipeline_binding_data._write_descriptor_sets[swapchain_index];
+            descriptor::update_write_descriptor_set(write_descriptor_sets, descriptor_index, &descriptor_resource_infos[descriptor_index]);
+            let descriptor_copies: &[vk::CopyDescriptorSet] = &[];
+            unsafe {
+                device.update_descriptor_sets(&[write_descriptor_sets[descriptor_index]], descriptor_copies);
+            }
+        }
+        self._pending_descriptor_updates.retain(|pending_descriptor_update| false == pending_descriptor_update._pending_swapchain_indices.is_empty());
+    }
+
+    // the uniform values of the material instance, ex) set_uniform_value("_base_color_factor", &json!([1.0, 0.0, 0.0, 1.0]))
+    // the render objects without their own MaterialParameterOverrides use them from the next frame.
+    pub fn set_uniform_value(&mut self, parameter_name: &str, value: &Value) -> Result<(), String> {
+        self._material_parameter_overrides.set_parameter(parameter_name, value).map_err(|error| format!("set_uniform_value {}: {}", self._material_instance_data_name, error))?;
+        self._material_instance_create_info.insert(String::from(MATERIAL_PARAMETER_OVERRIDES_NAME), serde_json::to_value(&self._material_parameter_overrides).unwrap());
+        log::info!("set_uniform_value {}: {} = {}", self._material_instance_data_name, parameter_name, value);
+        Ok(())
+    }
+
+    pub fn get_material_parameter_overrides(&self) -> MaterialParameterOverrides {
+        self._material_parameter_overrides
+    }
+
+    // the uniform values of the material instance, the push constants of the shader hooks. the render objects without their own parameters use them from the next draw.
+    pub fn set_shader_hook_parameter(&mut self, index: usize, value: &Vector4<f32>) -> Result<(), String> {
+        if MAX_SHADER_HOOK_PARAMETER_COUNT <= index {
+            return Err(format!(
+                "set_shader_hook_parameter {}: index {} is out of the shader hook parameters, the count is {}",
+                self._material_instance_data_name, index, MAX_SHADER_HOOK_PARAMETER_COUNT
+            ));
+        }
+        self._shader_hook_parameters[index] = value.clone();
+        let shader_hook_parameters: Vec<Value> = self._shader_hook_parameters.iter().map(|parameter| {
+            Value::Array(parameter.iter().map(|component| Value::from(*component as f64)).collect())
+        }).collect();
+        self._material_instance_create_info.insert(String::from("shader_hook_parameters"), Value::Array(shader_hook_parameters));
+        Ok(())
+    }
+
+    // the contents of the material instance file with the runtime edits, see Resources::save_material_instance_data
+    pub fn serialize_overrides(&self) -> String {
+        serde_json::to_string_pretty(&self._material_instance_create_info).unwrap()
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_uniform_value() {
+        let mut material_parameter_overrides = MaterialParameterOverrides::default();
+        material_parameter_overrides.set_parameter("_metallic_factor", &serde_json::json!(0.25)).unwrap();
+        material_parameter_overrides.set_parameter("_base_color_factor", &serde_json::json!([1.0, 0.0, 0.0, 1.0])).unwrap();
+        assert_eq!(0.25, material_parameter_overrides._metallic_factor);
+        assert_eq!(Vector4::new(1.0, 0.0, 0.0, 1.0), material_parameter_overrides._base_color_factor);
+        assert_eq!(MaterialParameterOverrides::default()._uv_tiling, material_parameter_overrides._uv_tiling);
+
+        // the invalid edits keep the values
+        assert!(material_parameter_overrides.set_parameter("_not_exists", &serde_json::json!(1.0)).is_err());
+        assert!(material_parameter_overrides.set_parameter("_roughness_factor", &serde_json::json!("rough")).is_err());
+        assert!(material_parameter_overrides.set_parameter("_uv_tiling", &serde_json::json!([1.0, 2.0, 3.0])).is_err());
+        assert_eq!(1.0, material_parameter_overrides._roughness_factor);
+        assert_eq!(0.25, material_parameter_overrides._metallic_factor);
+    }
+
+    #[test]
+    fn test_create_material_parameter_overrides() {
+        let material_parameter_overrides = MaterialParameterOverrides::create_material_parameter_overrides(Some(&serde_json::json!({ "_roughness_factor": 0.5 })));
+        assert_eq!(0.5, material_parameter_overrides._roughness_factor);
+        assert_eq!(MaterialParameterOverrides::default()._base_color_factor, material_parameter_overrides._base_color_factor);
+        assert_eq!(MaterialParameterOverrides::default(), MaterialParameterOverrides::create_material_parameter_overrides(None));
+    }
+
+    #[test]
+    fn test_create_material_blend_mode() {
+        // the material instance files without "blend_mode" are opaque
+        assert_eq!(MaterialBlendMode::Opaque, MaterialBlendMode::create_material_blend_mode(None));
+        assert_eq!(MaterialBlendMode::Additive, MaterialBlendMode::create_material_blend_mode(Some(&serde_json::json!("additive"))));
+        assert_eq!(MaterialBlendMode::Opaque, MaterialBlendMode::create_material_blend_mode(Some(&serde_json::json!("Additive"))));
+        assert_eq!(MaterialBlendMode::Opaque, MaterialBlendMode::create_material_blend_mode(Some(&serde_json::json!(1))));
+        for blend_mode in [MaterialBlendMode::Opaque, MaterialBlendMode::AlphaBlend, MaterialBlendMode::Additive, MaterialBlendMode::PremultipliedAlpha, MaterialBlendMode::Multiply].iter() {
+            assert_eq!(Some(*blend_mode), MaterialBlendMode::from_blend_mode_name(blend_mode.get_blend_mode_name()));
+            assert_eq!(MaterialBlendMode::Opaque != *blend_mode, blend_mode.is_translucent());
+        }
+        assert!(MaterialBlendMode::AlphaBlend.requires_sorting() && MaterialBlendMode::PremultipliedAlpha.requires_sorting());
+        assert!(false == MaterialBlendMode::Additive.requires_sorting() && false == MaterialBlendMode::Multiply.requires_sorting());
+    }
+}
@@ -1,15 +1,25 @@
+use std::sync::Arc;
+
+use bincode;
 use serde::{ Serialize, Deserialize };
-use nalgebra::{ Vector3 };
+use nalgebra::{ Vector3, Vector4, Matrix4 };
 
+use crate::constants;
 use crate::renderer::animation::{
     AnimationNodeCreateInfo,
     AnimationData,
     SkeletonDataCreateInfo,
     SkeletonData,
 };
+use crate::resource::mesh_cache;
 use crate::vulkan_context::geometry_buffer::{ GeometryData, GeometryCreateInfo };
 use crate::utilities::system::{ RcRefCell };
-use crate::utilities::bounding_box::{ BoundingBox };
+use crate::utilities::bounding_box::{ BoundingBox, calc_bounding_box };
+
+// the lods are the meshes of the suffix, ex) "tree_lod1", "tree_lod2" for "tree". see Resources::link_mesh_lod_datas
+pub const MESH_LOD_SUFFIX: &str = "_lod";
+pub const MAX_MESH_LOD_COUNT: usize = 4; // with the lod 0
+pub const DEFAULT_MESH_LOD_DISTANCE_RATIO: f32 = 10.0; // the lod n switches at the bound radius * ratio * n without MeshImportSettings::_lod_distances
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct MeshDataCreateInfo {
@@ -26,6 +36,33 @@ pub struct MeshData {
     pub _skeleton_datas: Vec<SkeletonData>,
     pub _animation_datas: Vec<AnimationData>,
     pub _geometry_datas: Vec<RcRefCell<GeometryData>>,
+    pub _bone_bound_boxes: Vec<BoneBoundBox>, // bind pose, the skinned vertices grouped by the bones
+    pub _content_hash: u64, // of the create info, the key of the baked datas like the impostor cache
+    pub _lod_datas: Vec<MeshLodData>, // the lod 1.., the lod 0 is _geometry_datas
+    pub _collision_data: Option<Arc<MeshCollisionData>>, // None for the skeletal meshes
+}
+
+// the triangles of the static geometries in the mesh space, the source of CollisionWorld.
+// shared with the build thread of the collision world, so it is not copied per render object.
+#[derive(Clone, Debug, Default)]
+pub struct MeshCollisionData {
+    pub _positions: Vec<Vector3<f32>>,
+    pub _indices: Vec<u32>,
+}
+
+// the geometries of a coarser lod, in the order of the material instances of the lod 0.
+// the geometry buffers are owned by the lod mesh in the mesh map, so they are destroyed once.
+#[derive(Clone, Debug)]
+pub struct MeshLodData {
+    pub _geometry_datas: Vec<RcRefCell<GeometryData>>,
+    pub _lod_distance: f32, // the distance from the camera to switch to this lod
+}
+
+// the bind pose bound of the vertices influenced by a bone, transformed by the animation palette at runtime
+#[derive(Clone, Debug, PartialEq)]
+pub struct BoneBoundBox {
+    pub _bone_index: usize,
+    pub _bound_box: BoundingBox,
 }
 
 impl Default for MeshDataCreateInfo {
@@ -66,6 +103,24 @@ impl MeshDataCreateInfo {
 }
 
 
+// the geometries of the skeletal vertices move with the animation, they are not collided
+pub fn create_mesh_collision_data(geometry_create_infos: &[GeometryCreateInfo]) -> Option<Arc<MeshCollisionData>> {
+    let mut collision_data = MeshCollisionData::default();
+    for geometry_create_info in geometry_create_infos.iter() {
+        if geometry_create_info._vertex_datas.is_empty() {
+            continue;
+        }
+        let first_index = collision_data._positions.len() as u32;
+        collision_data._positions.extend(geometry_create_info._vertex_datas.iter().map(|vertex_data| vertex_data._position.clone()));
+        collision_data._indices.extend(geometry_create_info._indices.iter().map(|index| first_index + index));
+    }
+    if collision_data._indices.is_empty() {
+        None
+    } else {
+        Some(Arc::new(collision_data))
+    }
+}
+
 impl MeshData {
     pub fn create_mesh_data(
         mesh_name: &String,
@@ -73,6 +128,7 @@ impl MeshData {
         geometry_datas: Vec<RcRefCell<GeometryData>>
     ) -> MeshData {
         log::debug!("create_mesh_data: {}", mesh_name);
+        let content_hash = mesh_cache::compute_hash(mesh_cache::HASH_OFFSET_BASIS, &bincode::serialize(&mesh_data_create_info).unwrap());
         let mut mesh_data = MeshData {
             _name: mesh_name.clone(),
             _bound_box: mesh_data_create_info._bound_box,
@@ -84,6 +140,10 @@ impl MeshData {
                 }).collect(),
             _animation_datas: Vec::new(),
             _geometry_datas: geometry_datas,
+            _bone_bound_boxes: compute_bone_bound_boxes(&mesh_data_create_info._geometry_create_infos),
+            _content_hash: content_hash,
+            _lod_datas: Vec::new(),
+            _collision_data: create_mesh_collision_data(&mesh_data_create_info._geometry_create_infos),
         };
 
         for (i, animation_node_create_info) in mesh_data_create_info._animation_node_create_infos.iter().enumerate() {
@@ -118,6 +178,278 @@ impl MeshData {
         &self._geometry_datas[index]
     }
 
+    pub fn get_lod_count(&self) -> usize {
+        1 + self._lod_datas.len()
+    }
+
+    // the last lod for the lod index over the lod count
+    pub fn get_lod_geometry_datas(&self, lod_index: usize) -> &Vec<RcRefCell<GeometryData>> {
+        match lod_index.min(self._lod_datas.len()) {
+            0 => &self._geometry_datas,
+            lod_index => &self._lod_datas[lod_index - 1]._geometry_datas,
+        }
+    }
+
+    pub fn set_lod_datas(&mut self, lod_datas: Vec<MeshLodData>) {
+        self._lod_datas = lod_datas;
+    }
+
     pub fn update_mesh_data(&self) {
     }
-}
\ No newline at end of file
+
+    // the pose of the palette, see RenderObjectData::update_animated_bound_box
+    pub fn get_animated_bound_box(&self, animation_buffer: &[Matrix4<f32>]) -> BoundingBox {
+        compute_animated_bound_box(&self._bone_bound_boxes, animation_buffer, &self._bound_box)
+    }
+}
+
+// ex) "tree_lod2" -> ("tree", 2), None for the lod 0
+pub fn get_mesh_lod_base_name(mesh_name: &str) -> Option<(String, usize)> {
+    let suffix_index = mesh_name.rfind(MESH_LOD_SUFFIX)?;
+    let lod_index: usize = mesh_name[(suffix_index + MESH_LOD_SUFFIX.len())..].parse().ok()?;
+    if 0 < suffix_index && 0 < lod_index && lod_index < MAX_MESH_LOD_COUNT {
+        Some((String::from(&mesh_name[..suffix_index]), lod_index))
+    } else {
+        None
+    }
+}
+
+// the distances are increasing, the current lod keeps its range widened by the hysteresis so the object doesn't flicker at the boundary.
+pub fn select_mesh_lod(lod_index: usize, distance: f32, mesh_data: &MeshData, distance_scale: f32, hysteresis: f32) -> usize {
+    if distance_scale <= 0.0 {
+        return 0;
+    }
+    let mut selected_lod_index: usize = 0;
+    for (i, lod_data) in mesh_data._lod_datas.iter().enumerate() {
+        let lod_distance = lod_data._lod_distance * distance_scale;
+        let switch_distance = if i < lod_index { lod_distance * (1.0 - hysteresis) } else { lod_distance * (1.0 + hysteresis) };
+        if distance <= switch_distance {
+            break;
+        }
+        selected_lod_index = i + 1;
+    }
+    selected_lod_index
+}
+
+pub fn get_mesh_lod_distance_scale() -> f32 {
+    unsafe { constants::MESH_LOD_DISTANCE_SCALE }
+}
+
+pub fn get_mesh_lod_hysteresis() -> f32 {
+    unsafe { constants::MESH_LOD_HYSTERESIS.max(0.0).min(0.9) }
+}
+
+pub fn get_shadow_mesh_lod_bias() -> usize {
+    unsafe { constants::SHADOW_MESH_LOD_BIAS as usize }
+}
+
+pub fn get_max_weight_bone_index(bone_indices: &Vector4<u32>, bone_weights: &Vector4<f32>) -> usize {
+    let mut max_weight_index: usize = 0;
+    for i in 1..4 {
+        if bone_weights[max_weight_index] < bone_weights[i] {
+            max_weight_index = i;
+        }
+    }
+    bone_indices[max_weight_index] as usize
+}
+
+// each vertex goes to the bone of the max weight, and to the other influencing bones,
+// because a blended vertex lies between the positions of its bones and the union has to contain it.
+pub fn compute_bone_bound_boxes(geometry_create_infos: &[GeometryCreateInfo]) -> Vec<BoneBoundBox> {
+    let mut bone_positions: Vec<Vec<Vector3<f32>>> = Vec::new();
+    let mut add_bone_position = |bone_index: usize, position: &Vector3<f32>| {
+        if bone_positions.len() <= bone_index {
+            bone_positions.resize(bone_index + 1, Vec::new());
+        }
+        bone_positions[bone_index].push(position.clone());
+    };
+    for geometry_create_info in geometry_create_infos.iter() {
+        for vertex_data in geometry_create_info._skeletal_vertex_datas.iter() {
+            let max_weight_bone_index = get_max_weight_bone_index(&vertex_data._bone_indices, &vertex_data._bone_weights);
+            add_bone_position(max_weight_bone_index, &vertex_data._position);
+            for i in 0..4 {
+                let bone_index = vertex_data._bone_indices[i] as usize;
+                if 0.0 < vertex_data._bone_weights[i] && bone_index != max_weight_bone_index {
+                    add_bone_position(bone_index, &vertex_data._position);
+                }
+            }
+        }
+    }
+    bone_positions.iter().enumerate().filter(|(_, positions)| false == positions.is_empty()).map(|(bone_index, positions)| {
+        BoneBoundBox {
+            _bone_index: bone_index,
+            _bound_box: calc_bounding_box(positions),
+        }
+    }).collect()
+}
+
+// the union of the bone bounds transformed by the skinning matrices, the bind pose bound without the bone bounds.
+pub fn compute_animated_bound_box(bone_bound_boxes: &[BoneBoundBox], animation_buffer: &[Matrix4<f32>], bind_pose_bound_box: &BoundingBox) -> BoundingBox {
+    let mut animated_bound_box: Option<BoundingBox> = None;
+    for bone_bound_box in bone_bound_boxes.iter() {
+        if let Some(bone_matrix) = animation_buffer.get(bone_bound_box._bone_index) {
+            let bound_box = bone_bound_box._bound_box.get_transformed_bound_box(bone_matrix);
+            match animated_bound_box.as_mut() {
+                Some(animated_bound_box) => animated_bound_box.union_bound_box(&bound_box),
+                None => animated_bound_box = Some(bound_box),
+            }
+        }
+    }
+    match animated_bound_box {
+        Some(animated_bound_box) if is_valid_bound_box(&animated_bound_box) => animated_bound_box,
+        _ => bind_pose_bound_box.clone(),
+    }
+}
+
+fn is_valid_bound_box(bound_box: &BoundingBox) -> bool {
+    bound_box._min.iter().chain(bound_box._max.iter()).all(|value| value.is_finite())
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vulkan_context::geometry_buffer::SkeletalVertexData;
+
+    fn create_test_mesh_data(lod_distances: &[f32]) -> MeshData {
+        let mut mesh_data = MeshData::create_mesh_data(&String::from("tree"), MeshDataCreateInfo::default(), Vec::new());
+        mesh_data.set_lod_datas(lod_distances.iter().map(|lod_distance| MeshLodData {
+            _geometry_datas: Vec::new(),
+            _lod_distance: *lod_distance,
+        }).collect());
+        mesh_data
+    }
+
+    #[test]
+    fn test_select_mesh_lod_by_distance() {
+        let mesh_data = create_test_mesh_data(&[10.0, 20.0, 40.0]);
+        assert_eq!(4, mesh_data.get_lod_count());
+        assert_eq!(0, select_mesh_lod(0, 0.0, &mesh_data, 1.0, 0.0));
+        assert_eq!(0, select_mesh_lod(0, 10.0, &mesh_data, 1.0, 0.0));
+        assert_eq!(1, select_mesh_lod(0, 15.0, &mesh_data, 1.0, 0.0));
+        assert_eq!(2, select_mesh_lod(0, 30.0, &mesh_data, 1.0, 0.0));
+        // the lods are skipped in one frame, ex) teleport
+        assert_eq!(3, select_mesh_lod(0, 1000.0, &mesh_data, 1.0, 0.0));
+        assert_eq!(0, select_mesh_lod(3, 1.0, &mesh_data, 1.0, 0.0));
+
+        // the distance scale, 0.0 draws the lod 0 only
+        assert_eq!(0, select_mesh_lod(0, 15.0, &mesh_data, 2.0, 0.0));
+        assert_eq!(1, select_mesh_lod(0, 25.0, &mesh_data, 2.0, 0.0));
+        assert_eq!(0, select_mesh_lod(3, 1000.0, &mesh_data, 0.0, 0.0));
+
+        // no lod
+        assert_eq!(0, select_mesh_lod(0, 1000.0, &create_test_mesh_data(&[]), 1.0, 0.1));
+    }
+
+    #[test]
+    fn test_select_mesh_lod_hysteresis() {
+        let mesh_data = create_test_mesh_data(&[10.0, 20.0, 40.0]);
+        let hysteresis = 0.1;
+        // moving away, the lod 1 from 11.0
+        assert_eq!(0, select_mesh_lod(0, 10.5, &mesh_data, 1.0, hysteresis));
+        assert_eq!(1, select_mesh_lod(0, 11.5, &mesh_data, 1.0, hysteresis));
+        // coming back, the lod 0 from 9.0
+        assert_eq!(1, select_mesh_lod(1, 10.0, &mesh_data, 1.0, hysteresis));
+        assert_eq!(1, select_mesh_lod(1, 9.5, &mesh_data, 1.0, hysteresis));
+        assert_eq!(0, select_mesh_lod(1, 8.5, &mesh_data, 1.0, hysteresis));
+
+        // no flicker while the distance oscillates around the boundary
+        let mut lod_index = 0;
+        let mut lod_changes = 0;
+        for frame in 0..100 {
+            let distance = 20.0 + if 0 == frame % 2 { 1.5 } else { -1.5 };
+            let new_lod_index = select_mesh_lod(lod_index, distance, &mesh_data, 1.0, hysteresis);
+            if new_lod_index != lod_index {
+                lod_changes += 1;
+            }
+            lod_index = new_lod_index;
+        }
+        assert_eq!(1, lod_changes);
+        assert_eq!(1, lod_index);
+
+        // the band of the farther lods scales with the distance
+        assert_eq!(3, select_mesh_lod(3, 37.0, &mesh_data, 1.0, hysteresis));
+        assert_eq!(2, select_mesh_lod(3, 35.0, &mesh_data, 1.0, hysteresis));
+        assert_eq!(2, select_mesh_lod(2, 43.0, &mesh_data, 1.0, hysteresis));
+        assert_eq!(3, select_mesh_lod(2, 45.0, &mesh_data, 1.0, hysteresis));
+    }
+
+    #[test]
+    fn test_mesh_lod_base_name() {
+        assert_eq!(Some((String::from("tree"), 1)), get_mesh_lod_base_name("tree_lod1"));
+        assert_eq!(Some((String::from("big_tree"), 3)), get_mesh_lod_base_name("big_tree_lod3"));
+        assert_eq!(None, get_mesh_lod_base_name("tree"));
+        assert_eq!(None, get_mesh_lod_base_name("tree_lod0"));
+        assert_eq!(None, get_mesh_lod_base_name("tree_lod4"));
+        assert_eq!(None, get_mesh_lod_base_name("tree_lodx"));
+        assert_eq!(None, get_mesh_lod_base_name("_lod1"));
+    }
+
+    fn create_skeletal_vertex_data(position: Vector3<f32>, bone_indices: Vector4<u32>, bone_weights: Vector4<f32>) -> SkeletalVertexData {
+        SkeletalVertexData {
+            _position: position,
+            _bone_indices: bone_indices,
+            _bone_weights: bone_weights,
+            ..Default::default()
+        }
+    }
+
+    fn get_skinned_position(vertex_data: &SkeletalVertexData, animation_buffer: &[Matrix4<f32>]) -> Vector3<f32> {
+        let position = Vector4::new(vertex_data._position.x, vertex_data._position.y, vertex_data._position.z, 1.0);
+        let skinned_position: Vector4<f32> = (0..4).map(|i| {
+            animation_buffer[vertex_data._bone_indices[i] as usize] * position * vertex_data._bone_weights[i]
+        }).sum();
+        skinned_position.xyz()
+    }
+
+    fn is_inside(bound_box: &BoundingBox, position: &Vector3<f32>) -> bool {
+        (0..3).all(|i| bound_box._min[i] - 0.0001 <= position[i] && position[i] <= bound_box._max[i] + 0.0001)
+    }
+
+    #[test]
+    fn test_animated_bound_box_of_stretched_bones() {
+        // an arm along the x axis, the bone 0 from 0.0 to 1.0 and the bone 1 from 1.0 to 2.0, blended around the joint
+        let bone_0 = Vector4::new(0, 0, 0, 0);
+        let bone_1 = Vector4::new(1, 0, 0, 0);
+        let blended_bones = Vector4::new(0, 1, 0, 0);
+        let vertex_datas = vec![
+            create_skeletal_vertex_data(Vector3::new(0.0, -0.5, -0.5), bone_0, Vector4::new(1.0, 0.0, 0.0, 0.0)),
+            create_skeletal_vertex_data(Vector3::new(0.5, 0.5, 0.5), bone_0, Vector4::new(1.0, 0.0, 0.0, 0.0)),
+            create_skeletal_vertex_data(Vector3::new(1.0, 0.5, -0.5), blended_bones, Vector4::new(0.5, 0.5, 0.0, 0.0)),
+            create_skeletal_vertex_data(Vector3::new(1.5, -0.5, 0.5), blended_bones, Vector4::new(0.3, 0.7, 0.0, 0.0)),
+            create_skeletal_vertex_data(Vector3::new(2.0, 0.5, 0.5), bone_1, Vector4::new(1.0, 0.0, 0.0, 0.0)),
+        ];
+        let bind_pose_bound_box = calc_bounding_box(&vertex_datas.iter().map(|vertex_data| vertex_data._position).collect());
+        let geometry_create_infos = vec![GeometryCreateInfo {
+            _skeletal_vertex_datas: vertex_datas.clone(),
+            _bounding_box: bind_pose_bound_box.clone(),
+            ..Default::default()
+        }];
+        let bone_bound_boxes = compute_bone_bound_boxes(&geometry_create_infos);
+        assert_eq!(vec![0, 1], bone_bound_boxes.iter().map(|bone_bound_box| bone_bound_box._bone_index).collect::<Vec<usize>>());
+        // the blended vertices are in the bounds of both bones
+        assert_eq!(Vector3::new(1.5, 0.5, 0.5), bone_bound_boxes[0]._bound_box._max);
+        assert_eq!(Vector3::new(1.0, -0.5, -0.5), bone_bound_boxes[1]._bound_box._min);
+
+        // the bind pose
+        let bind_pose_animation_buffer = vec![Matrix4::identity(); 2];
+        let animated_bound_box = compute_animated_bound_box(&bone_bound_boxes, &bind_pose_animation_buffer, &bind_pose_bound_box);
+        assert_eq!(bind_pose_bound_box._min, animated_bound_box._min);
+        assert_eq!(bind_pose_bound_box._max, animated_bound_box._max);
+
+        // the bone 1 is stretched twice along the x axis from the joint and moved away by 1.0
+        let stretch_matrix = Matrix4::new_translation(&Vector3::new(2.0, 0.0, 0.0))
+            * Matrix4::new_nonuniform_scaling(&Vector3::new(2.0, 1.0, 1.0))
+            * Matrix4::new_translation(&Vector3::new(-1.0, 0.0, 0.0));
+        let stretched_animation_buffer = vec![Matrix4::identity(), stretch_matrix];
+        let stretched_positions: Vec<Vector3<f32>> = vertex_datas.iter().map(|vertex_data| get_skinned_position(vertex_data, &stretched_animation_buffer)).collect();
+        assert!(stretched_positions.iter().any(|position| false == is_inside(&bind_pose_bound_box, position)));
+        let animated_bound_box = compute_animated_bound_box(&bone_bound_boxes, &stretched_animation_buffer, &bind_pose_bound_box);
+        for position in stretched_positions.iter() {
+            assert!(is_inside(&animated_bound_box, position), "{:?} {:?}", position, animated_bound_box);
+        }
+        assert_eq!(4.0, animated_bound_box._max.x);
+
+        // without the bone bounds, ex) an old mesh cache
+        let animated_bound_box = compute_animated_bound_box(&[], &stretched_animation_buffer, &bind_pose_bound_box);
+        assert_eq!(bind_pose_bound_box._max, animated_bound_box._max);
+    }
+}
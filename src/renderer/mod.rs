@@ -1,15 +1,49 @@
 pub mod animation;
+pub mod animation_state_machine;
+pub mod atmosphere;
+pub mod auto_exposure;
+pub mod bone_attachment;
 pub mod camera;
+pub mod fft_ocean;
 pub mod image_sampler;
+pub mod debug_render_target;
+pub mod debug_view;
+pub mod denoiser;
 pub mod font;
+pub mod frame_graph;
+pub mod fur;
+pub mod fxaa;
+pub mod gpu_skinning;
+pub mod histogram;
+pub mod history_confidence;
+pub mod impostor;
 pub mod light;
+pub mod light_cluster;
+pub mod light_probe;
 pub mod material;
 pub mod material_instance;
 pub mod mesh;
 pub mod model;
+pub mod object_picking;
+pub mod ocean_height_field;
+pub mod occlusion_culling;
+pub mod particle_collision;
+pub mod post_process;
 pub mod render_element;
 pub mod render_object;
+pub mod render_object_storage;
+pub mod render_target;
+pub mod screenshot;
 pub mod renderer;
+pub mod shader_hook;
+pub mod shadow_atlas;
+pub mod shadow_cache;
+pub mod skybox;
+pub mod terrain;
+pub mod tonemap;
 pub mod transform_object;
 pub mod ui;
-pub mod utility;
\ No newline at end of file
+pub mod utility;
+pub mod viewport;
+pub mod visibility_stats;
+pub mod volumetric_fog;
\ No newline at end of file
@@ -1,4 +1,5 @@
 
+use crate::renderer::impostor::{ ImpostorCreateInfo, ImpostorData };
 use crate::renderer::material_instance::MaterialInstanceData;
 use crate::renderer::mesh::MeshData;
 use crate::utilities::system::RcRefCell;
@@ -15,6 +16,8 @@ pub struct ModelData {
     pub _model_data_name: String,
     pub _mesh_data: RcRefCell<MeshData>,
     pub _material_instance_datas: Vec<RcRefCell<MaterialInstanceData>>,
+    pub _impostor_create_info: Option<ImpostorCreateInfo>, // None: the model has no impostor
+    pub _impostor_data: Option<RcRefCell<ImpostorData>>, // None until the impostor is baked or loaded from the cache
 }
 
 impl ModelData {
@@ -28,6 +31,8 @@ impl ModelData {
             _model_data_name: model_name.clone(),
             _mesh_data: mesh_data,
             _material_instance_datas: material_instance_datas,
+            _impostor_create_info: None,
+            _impostor_data: None,
         }
     }
 
@@ -54,6 +59,17 @@ impl ModelData {
         &self._material_instance_datas[index]
     }
 
+    pub fn has_impostor_data(&self) -> bool {
+        self._impostor_data.is_some()
+    }
+
+    pub fn get_impostor_distance(&self) -> f32 {
+        match self._impostor_create_info.as_ref() {
+            Some(impostor_create_info) => impostor_create_info._distance,
+            None => 0.0,
+        }
+    }
+
     pub fn update_model_data(&self) {
     }
 }
\ No newline at end of file
@@ -0,0 +1,263 @@
+use ash::{ vk, Device };
+use ash::version::DeviceV1_0;
+use nalgebra::Vector2;
+
+use crate::renderer::render_object_storage;
+use crate::vulkan_context::buffer::{ self, BufferData };
+
+// R32_UINT render target of the project, the solid passes write the object id of the push constant or of INSTANCE_OBJECT_ID_BUFFER_NAME.
+pub const OBJECT_ID_RENDER_TARGET_NAME: &str = "ObjectId";
+
+// the render target of the object ids at the end of the frame
+#[derive(Clone, Debug)]
+pub struct ObjectIdSource {
+    pub _image: vk::Image,
+    pub _image_layout: vk::ImageLayout, // the layout at the end of the frame, restored after the copy
+    pub _image_format: vk::Format,
+    pub _width: u32,
+    pub _height: u32,
+}
+
+// the position of the letterboxed viewport to the pixel of the render extent
+pub fn get_render_position(viewport_position: &Vector2<i32>, viewport_size: &Vector2<i32>, render_extent: &vk::Extent2D) -> (u32, u32) {
+    let to_render_position = |position: i32, viewport_size: i32, render_size: u32| -> u32 {
+        let position = (position.max(0) as f32 + 0.5) * render_size as f32 / viewport_size.max(1) as f32;
+        (position as u32).min(render_size.max(1) - 1)
+    };
+    (
+        to_render_position(viewport_position.x, viewport_size.x, render_extent.width),
+        to_render_position(viewport_position.y, viewport_size.y, render_extent.height)
+    )
+}
+
+// a texel of the object id render target is copied at the end of the frame and read back at the beginning of the next frame,
+// so the pick never waits for the gpu. render_object_storage::INVALID_OBJECT_ID is the background.
+#[derive(Default)]
+pub struct ObjectPicking {
+    pub _readback_buffer: Option<BufferData>, // 4 bytes, created by the first pick
+    pub _requested_position: Option<Vector2<u32>>, // the pixel of the render extent
+    pub _is_copy_recorded: bool,
+    pub _is_copy_submitted: bool,
+    pub _picked_object_id: Option<u64>,
+    pub _is_picked: bool, // a new result, consumed by take_picked_object_id
+}
+
+impl ObjectPicking {
+    pub fn request_object_pick(&mut self, x: u32, y: u32) {
+        self._requested_position = Some(Vector2::new(x, y));
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self._requested_position.is_some()
+    }
+
+    pub fn get_picked_object_id(&self) -> Option<u64> {
+        self._picked_object_id
+    }
+
+    pub fn take_picked_object_id(&mut self) -> Option<u64> {
+        if self._is_picked {
+            self._is_picked = false;
+            self._picked_object_id
+        } else {
+            None
+        }
+    }
+
+    // the copy of the previous frame, present_swapchain has waited for its submit
+    pub fn read_object_pick(&mut self, device: &Device) {
+        if false == self._is_copy_submitted {
+            return;
+        }
+        self._is_copy_submitted = false;
+        if let Some(readback_buffer) = self._readback_buffer.as_ref() {
+            let mut object_ids: [u32; 1] = [render_object_storage::INVALID_OBJECT_ID];
+            buffer::read_buffer_data(device, readback_buffer, 0, &mut object_ids);
+            self.set_picked_object_id(object_ids[0]);
+        }
+    }
+
+    pub fn set_picked_object_id(&mut self, object_id: u32) {
+        self._picked_object_id = Some(object_id as u64);
+        self._is_picked = true;
+    }
+
+    // the texel of the request, the request is dropped when the source can't be copied
+    pub fn get_object_pick_texel(&mut self, source: &ObjectIdSource) -> Option<vk::Offset3D> {
+        let requested_position = self._requested_position?;
+        if vk::Format::R32_UINT != source._image_format || 0 == source._width || 0 == source._height {
+            log::error!("record_object_pick_copy: {} must be R32_UINT, not {:?}", OBJECT_ID_RENDER_TARGET_NAME, source._image_format);
+            self._requested_position = None;
+            return None;
+        }
+        Some(vk::Offset3D {
+            x: requested_position.x.min(source._width - 1) as i32,
+            y: requested_position.y.min(source._height - 1) as i32,
+            z: 0
+        })
+    }
+
+    // the last command of the frame: the source -> TRANSFER_SRC_OPTIMAL -> a texel of the readback buffer -> the layout of the source.
+    pub fn record_object_pick_copy(
+        &mut self,
+        device: &Device,
+        memory_properties: &vk::PhysicalDeviceMemoryProperties,
+        command_buffer: vk::CommandBuffer,
+        source: &ObjectIdSource
+    ) {
+        let image_offset = match self.get_object_pick_texel(source) {
+            Some(image_offset) => image_offset,
+            None => return,
+        };
+        if self._readback_buffer.is_none() {
+            self._readback_buffer = Some(buffer::create_buffer_data(
+                device,
+                memory_properties,
+                std::mem::size_of::<u32>() as vk::DeviceSize,
+                vk::BufferUsageFlags::TRANSFER_DST,
+                vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT
+            ));
+        }
+        let readback_buffer = self._readback_buffer.as_ref().unwrap()._buffer;
+        let subresource_range = vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        };
+        let to_transfer_src = vk::ImageMemoryBarrier {
+            src_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE | vk::AccessFlags::SHADER_WRITE,
+            dst_access_mask: vk::AccessFlags::TRANSFER_READ,
+            old_layout: source._image_layout,
+            new_layout: vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            image: source._image,
+            subresource_range,
+            ..Default::default()
+        };
+        let to_source_layout = vk::ImageMemoryBarrier {
+            src_access_mask: vk::AccessFlags::TRANSFER_READ,
+            dst_access_mask: vk::AccessFlags::empty(),
+            old_layout: vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            new_layout: source._image_layout,
+            ..to_transfer_src
+        };
+        let readback_barrier = vk::BufferMemoryBarrier {
+            src_access_mask: vk::AccessFlags::TRANSFER_WRITE,
+            dst_access_mask: vk::AccessFlags::HOST_READ,
+            src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            buffer: readback_buffer,
+            offset: 0,
+            size: vk::WHOLE_SIZE,
+            ..Default::default()
+        };
+        let region = vk::BufferImageCopy {
+            buffer_offset: 0,
+            buffer_row_length: 0,
+            buffer_image_height: 0,
+            image_subresource: vk::ImageSubresourceLayers {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                mip_level: 0,
+                base_array_layer: 0,
+                layer_count: 1,
+            },
+            image_offset,
+            image_extent: vk::Extent3D { width: 1, height: 1, depth: 1 },
+        };
+        unsafe {
+            device.cmd_pipeline_barrier(command_buffer, vk::PipelineStageFlags::ALL_COMMANDS, vk::PipelineStageFlags::TRANSFER, vk::DependencyFlags::empty(), &[], &[], &[to_transfer_src]);
+            device.cmd_copy_image_to_buffer(command_buffer, source._image, vk::ImageLayout::TRANSFER_SRC_OPTIMAL, readback_buffer, &[region]);
+            device.cmd_pipeline_barrier(command_buffer, vk::PipelineStageFlags::TRANSFER, vk::PipelineStageFlags::BOTTOM_OF_PIPE, vk::DependencyFlags::empty(), &[], &[], &[to_source_layout]);
+            device.cmd_pipeline_barrier(command_buffer, vk::PipelineStageFlags::TRANSFER, vk::PipelineStageFlags::HOST, vk::DependencyFlags::empty(), &[], &[readback_barrier], &[]);
+        }
+        self._is_copy_recorded = true;
+    }
+
+    // the copy of a frame which failed to submit is dropped, the request is kept for the next frame
+    pub fn end_frame(&mut self, is_submitted: bool) {
+        if self._is_copy_recorded {
+            self._is_copy_recorded = false;
+            if is_submitted {
+                self._requested_position = None;
+                self._is_copy_submitted = true;
+            }
+        }
+    }
+
+    pub fn destroy_object_picking(&mut self, device: &Device) {
+        if let Some(readback_buffer) = self._readback_buffer.take() {
+            buffer::destroy_buffer_data(device, &readback_buffer);
+        }
+        self._requested_position = None;
+        self._is_copy_recorded = false;
+        self._is_copy_submitted = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_object_id_source(image_format: vk::Format) -> ObjectIdSource {
+        ObjectIdSource {
+            _image: vk::Image::null(),
+            _image_layout: vk::ImageLayout::GENERAL,
+            _image_format: image_format,
+            _width: 64,
+            _height: 32,
+        }
+    }
+
+    #[test]
+    fn test_object_pick_deferred_by_a_frame() {
+        let mut object_picking = ObjectPicking::default();
+        let source = create_object_id_source(vk::Format::R32_UINT);
+        assert_eq!(None, object_picking.get_object_pick_texel(&source));
+        object_picking.request_object_pick(100, 10);
+        assert!(object_picking.is_requested());
+        assert_eq!(Some(vk::Offset3D { x: 63, y: 10, z: 0 }), object_picking.get_object_pick_texel(&source));
+
+        // the frame failed to submit, the request is kept for the next frame
+        object_picking._is_copy_recorded = true;
+        object_picking.end_frame(false);
+        assert!(object_picking.is_requested());
+        assert!(false == object_picking._is_copy_submitted);
+
+        // submitted, the result is read back at the beginning of the next frame
+        object_picking._is_copy_recorded = true;
+        object_picking.end_frame(true);
+        assert!(false == object_picking.is_requested());
+        assert!(object_picking._is_copy_submitted);
+        assert_eq!(None, object_picking.take_picked_object_id());
+        object_picking.set_picked_object_id(5);
+        assert_eq!(Some(5), object_picking.take_picked_object_id());
+        assert_eq!(None, object_picking.take_picked_object_id());
+        assert_eq!(Some(5), object_picking.get_picked_object_id());
+
+        // no copy is recorded for a frame without the request
+        object_picking.end_frame(true);
+        assert!(false == object_picking.is_requested());
+    }
+
+    #[test]
+    fn test_invalid_object_id_source() {
+        let mut object_picking = ObjectPicking::default();
+        object_picking.request_object_pick(1, 1);
+        assert_eq!(None, object_picking.get_object_pick_texel(&create_object_id_source(vk::Format::R8G8B8A8_UNORM)));
+        assert!(false == object_picking.is_requested());
+    }
+
+    #[test]
+    fn test_render_position() {
+        let render_extent = vk::Extent2D { width: 1344, height: 756 };
+        let viewport_size = Vector2::new(1920, 1080);
+        assert_eq!((0, 0), get_render_position(&Vector2::new(0, 0), &viewport_size, &render_extent));
+        assert_eq!((672, 378), get_render_position(&Vector2::new(960, 540), &viewport_size, &render_extent));
+        assert_eq!((1343, 755), get_render_position(&Vector2::new(1919, 1079), &viewport_size, &render_extent));
+        // outside of the viewport
+        assert_eq!((0, 755), get_render_position(&Vector2::new(-10, 5000), &viewport_size, &render_extent));
+    }
+}
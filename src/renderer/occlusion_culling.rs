@@ -0,0 +1,614 @@
+use std::fs;
+use std::os::raw::c_void;
+use std::path::PathBuf;
+
+use ash::{ vk, Device, Instance };
+use ash::version::{ DeviceV1_0, InstanceV1_1 };
+use nalgebra::{ Matrix4, Vector2, Vector4 };
+
+use crate::constants;
+use crate::renderer::render_element::RenderElementGroupData;
+use crate::renderer::render_object_storage;
+use crate::renderer::renderer::RendererData;
+use crate::resource::resource::Resources;
+use crate::utilities::math;
+use crate::vulkan_context::buffer::{ self, ShaderBufferData };
+use crate::vulkan_context::descriptor::{ self, DescriptorDataCreateInfo, DescriptorResourceInfo, DescriptorResourceType };
+use crate::vulkan_context::render_pass::{ PipelineDataCreateInfo, RenderPassDataCreateInfo };
+use crate::vulkan_context::shader::SHADER_DIRECTORY;
+use crate::vulkan_context::texture::TextureData;
+use crate::vulkan_context::vulkan_context::SwapchainArray;
+
+pub const OCCLUSION_CULLING_WORK_GROUP_SIZE: u32 = 64; // must match with occlusion_culling.comp
+pub const OCCLUSION_CULLING_FLAG_HIZ: u32 = 1 << 0; // must match with occlusion_culling.comp
+pub const INVALID_DRAW_COMMAND_INDEX: u32 = u32::MAX;
+pub const OCCLUSION_CULLING_RENDER_PASS_NAME: &str = "occlusion_culling";
+pub const OCCLUSION_CULLING_CONSTANTS_BUFFER_NAME: &str = "OcclusionCullingConstants";
+pub const OCCLUSION_CULLING_INSTANCE_BUFFER_NAME: &str = "OcclusionCullingInstanceBuffer";
+pub const OCCLUSION_CULLING_DRAW_COMMAND_BUFFER_NAME: &str = "OcclusionCullingDrawCommandBuffer";
+pub const OCCLUSION_CULLING_DRAW_COUNT_BUFFER_NAME: &str = "OcclusionCullingDrawCountBuffer";
+pub const OCCLUSION_CULLING_STATS_BUFFER_NAME: &str = "OcclusionCullingStatsBuffer";
+// render_solid of the project binds this name as a StorageBuffer descriptor, see get_visible_instance_descriptor_data_create_info
+pub const VISIBLE_INSTANCE_BUFFER_NAME: &str = "VisibleInstanceBuffer";
+pub const OCCLUSION_CULLING_SHADER_FILE: &str = "common/occlusion_culling.comp";
+pub const OCCLUSION_CULLING_SHADER_SOURCE: &str = r#"#version 450
+
+#define OCCLUSION_CULLING_WORK_GROUP_SIZE 64
+#define OCCLUSION_CULLING_FLAG_HIZ 1
+
+layout(local_size_x = OCCLUSION_CULLING_WORK_GROUP_SIZE, local_size_y = 1, local_size_z = 1) in;
+
+struct OcclusionCullingInstance
+{
+    vec4 bound_sphere;
+    uint group_index;
+    uint group_instance_offset;
+    uint reserved0;
+    uint reserved1;
+};
+
+// VkDrawIndexedIndirectCommand
+struct DrawCommand
+{
+    uint index_count;
+    uint instance_count;
+    uint first_index;
+    int vertex_offset;
+    uint first_instance;
+};
+
+layout(binding = 0) uniform OcclusionCullingConstants
+{
+    vec4 frustum_planes[6];
+    mat4 hiz_view_projection;
+    vec2 hiz_size;
+    uint hiz_mip_count;
+    uint instance_count;
+    uint flags;
+    uint reserved0;
+    uint reserved1;
+    uint reserved2;
+} constants;
+layout(std430, binding = 1) readonly buffer OcclusionCullingInstanceBuffer
+{
+    OcclusionCullingInstance instances[];
+};
+layout(binding = 2) uniform sampler2D texture_hiz;
+layout(std430, binding = 3) buffer OcclusionCullingDrawCommandBuffer
+{
+    DrawCommand draw_commands[];
+};
+layout(std430, binding = 4) buffer OcclusionCullingDrawCountBuffer
+{
+    uint draw_counts[];
+};
+layout(std430, binding = 5) writeonly buffer VisibleInstanceBuffer
+{
+    uint visible_instances[];
+};
+layout(std430, binding = 6) buffer OcclusionCullingStatsBuffer
+{
+    uint visible_count;
+    uint culled_count;
+    uint stats_reserved0;
+    uint stats_reserved1;
+    uint visibility_bits[];
+};
+
+bool is_sphere_in_frustum(vec4 bound_sphere)
+{
+    for(int i = 0; i < 6; ++i)
+    {
+        if(dot(constants.frustum_planes[i].xyz, bound_sphere.xyz) + constants.frustum_planes[i].w < -bound_sphere.w)
+        {
+            return false;
+        }
+    }
+    return true;
+}
+
+// the min-z pyramid keeps the farthest occluder only in the reversed-z, the frustum test alone in the other convention.
+bool is_sphere_occluded(vec4 bound_sphere)
+{
+#if defined(REVERSED_Z)
+    if(0u == (constants.flags & OCCLUSION_CULLING_FLAG_HIZ))
+    {
+        return false;
+    }
+
+    vec2 min_uv = vec2(1.0);
+    vec2 max_uv = vec2(0.0);
+    float nearest_depth = 0.0;
+    for(int i = 0; i < 8; ++i)
+    {
+        vec3 corner_sign = vec3((0 != (i & 1)) ? 1.0 : -1.0, (0 != (i & 2)) ? 1.0 : -1.0, (0 != (i & 4)) ? 1.0 : -1.0);
+        vec4 clip_position = constants.hiz_view_projection * vec4(bound_sphere.xyz + corner_sign * bound_sphere.w, 1.0);
+        if(clip_position.w <= 0.0)
+        {
+            // crosses the near plane of the previous frame
+            return false;
+        }
+        vec3 ndc = clip_position.xyz / clip_position.w;
+        vec2 uv = ndc.xy * 0.5 + 0.5;
+        min_uv = min(min_uv, uv);
+        max_uv = max(max_uv, uv);
+        nearest_depth = max(nearest_depth, ndc.z);
+    }
+    min_uv = clamp(min_uv, vec2(0.0), vec2(1.0));
+    max_uv = clamp(max_uv, vec2(0.0), vec2(1.0));
+
+    // the rect covers at most 2 x 2 texels of the mip
+    vec2 rect_size = (max_uv - min_uv) * constants.hiz_size;
+    int mip = clamp(int(ceil(log2(max(max(rect_size.x, rect_size.y), 1.0)))), 0, int(constants.hiz_mip_count) - 1);
+    ivec2 mip_size = max(ivec2(constants.hiz_size) >> mip, ivec2(1));
+    ivec2 p0 = clamp(ivec2(min_uv * vec2(mip_size)), ivec2(0), mip_size - 1);
+    ivec2 p1 = clamp(ivec2(max_uv * vec2(mip_size)), ivec2(0), mip_size - 1);
+    float occluder_depth = min(
+        min(texelFetch(texture_hiz, p0, mip).x, texelFetch(texture_hiz, ivec2(p1.x, p0.y), mip).x),
+        min(texelFetch(texture_hiz, ivec2(p0.x, p1.y), mip).x, texelFetch(texture_hiz, p1, mip).x)
+    );
+    return nearest_depth < occluder_depth;
+#else
+    return false;
+#endif
+}
+
+void main()
+{
+    uint instance_index = gl_GlobalInvocationID.x;
+    if(constants.instance_count <= instance_index)
+    {
+        return;
+    }
+
+    OcclusionCullingInstance instance = instances[instance_index];
+    if(is_sphere_in_frustum(instance.bound_sphere) && false == is_sphere_occluded(instance.bound_sphere))
+    {
+        uint slot = atomicAdd(draw_commands[instance.group_index].instance_count, 1u);
+        visible_instances[instance.group_instance_offset + slot] = instance_index;
+        draw_counts[instance.group_index] = 1u;
+        atomicOr(visibility_bits[instance_index / 32u], 1u << (instance_index % 32u));
+        atomicAdd(visible_count, 1u);
+    }
+    else
+    {
+        atomicAdd(culled_count, 1u);
+    }
+}
+"#;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct OcclusionCullingSettings {
+    pub _hiz_target_name: String, // the min-z pyramid of RendererData_HierachicalMinZ, built from the depth of the previous frame
+}
+
+impl Default for OcclusionCullingSettings {
+    fn default() -> OcclusionCullingSettings {
+        OcclusionCullingSettings {
+            _hiz_target_name: String::from("HierarchicalMinZ"),
+        }
+    }
+}
+
+// must match with OcclusionCullingConstants of occlusion_culling.comp
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct OcclusionCullingConstants {
+    pub _frustum_planes: [Vector4<f32>; 6],
+    pub _hiz_view_projection: Matrix4<f32>, // the view projection of the frame which the pyramid was built from
+    pub _hiz_size: Vector2<f32>,
+    pub _hiz_mip_count: u32,
+    pub _instance_count: u32,
+    pub _flags: u32,
+    pub _reserved0: u32,
+    pub _reserved1: u32,
+    pub _reserved2: u32,
+}
+
+// must match with OcclusionCullingInstance of occlusion_culling.comp
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct OcclusionCullingInstance {
+    pub _bound_sphere: Vector4<f32>,
+    pub _group_index: u32,
+    pub _group_instance_offset: u32,
+    pub _reserved0: u32,
+    pub _reserved1: u32,
+}
+
+// must match with OcclusionCullingStatsBuffer of occlusion_culling.comp, followed by the visibility bits of the instances
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct OcclusionCullingStats {
+    pub _visible_count: u32,
+    pub _culled_count: u32,
+    pub _reserved0: u32,
+    pub _reserved1: u32,
+}
+
+// The static batch culled on the gpu: the bound spheres of the instances -> frustum planes and the min-z pyramid
+// -> the compacted VisibleInstanceBuffer and one indexed indirect command per RenderElementGroupData.
+// the buffers are host visible and per swapchain image, the commands and the counters are reset by the host each frame.
+pub struct OcclusionCulling {
+    pub _settings: OcclusionCullingSettings,
+    pub _constants_buffer: Option<ShaderBufferData>,
+    pub _instance_buffer: Option<ShaderBufferData>,
+    pub _draw_command_buffer: Option<ShaderBufferData>,
+    pub _draw_count_buffer: Option<ShaderBufferData>,
+    pub _visible_instance_buffer: Option<ShaderBufferData>,
+    pub _stats_buffer: Option<ShaderBufferData>,
+    pub _descriptor_sets: SwapchainArray<vk::DescriptorSet>, // created in prepare_framebuffer_and_descriptors
+    pub _view_projection: Matrix4<f32>,
+    pub _prev_view_projection: Matrix4<f32>,
+    pub _is_history_valid: bool, // the pyramid holds the depth of _prev_view_projection
+    pub _is_stats_recorded: SwapchainArray<bool>,
+    pub _stats: OcclusionCullingStats, // read back from the previous use of the swapchain image
+}
+
+impl Default for OcclusionCulling {
+    fn default() -> OcclusionCulling {
+        OcclusionCulling {
+            _settings: OcclusionCullingSettings::default(),
+            _constants_buffer: None,
+            _instance_buffer: None,
+            _draw_command_buffer: None,
+            _draw_count_buffer: None,
+            _visible_instance_buffer: None,
+            _stats_buffer: None,
+            _descriptor_sets: Vec::new(),
+            _view_projection: Matrix4::identity(),
+            _prev_view_projection: Matrix4::identity(),
+            _is_history_valid: false,
+            _is_stats_recorded: vec![false; constants::SWAPCHAIN_IMAGE_COUNT],
+            _stats: OcclusionCullingStats::default(),
+        }
+    }
+}
+
+pub fn query_draw_indirect_count_support(instance: &Instance, physical_device: vk::PhysicalDevice, device_properties: &vk::PhysicalDeviceProperties) -> bool {
+    if device_properties.api_version < vk::make_version(1, 2, 0) {
+        return false;
+    }
+    let mut vulkan12_features = vk::PhysicalDeviceVulkan12Features::default();
+    let mut physical_device_features2 = vk::PhysicalDeviceFeatures2 {
+        p_next: &mut vulkan12_features as *mut vk::PhysicalDeviceVulkan12Features as *mut c_void,
+        ..Default::default()
+    };
+    unsafe {
+        instance.get_physical_device_features2(physical_device, &mut physical_device_features2);
+    }
+    let is_supported = vk::TRUE == vulkan12_features.draw_indirect_count;
+    log::info!("DrawIndirectCount: supported({})", is_supported);
+    is_supported
+}
+
+pub fn get_render_pass_data_create_info() -> RenderPassDataCreateInfo {
+    let storage_buffer_descriptor = |descriptor_binding_index: u32, descriptor_name: &str| DescriptorDataCreateInfo {
+        _descriptor_binding_index: descriptor_binding_index,
+        _descriptor_name: String::from(descriptor_name),
+        _descriptor_resource_type: DescriptorResourceType::StorageBuffer,
+        _descriptor_shader_stage: vk::ShaderStageFlags::COMPUTE,
+        ..Default::default()
+    };
+    let pipeline_data_create_infos = vec![
+        PipelineDataCreateInfo {
+            _pipeline_data_create_info_name: String::from(OCCLUSION_CULLING_RENDER_PASS_NAME),
+            _pipeline_bind_point: vk::PipelineBindPoint::COMPUTE,
+            _pipeline_compute_shader_file: PathBuf::from(OCCLUSION_CULLING_SHADER_FILE),
+            _descriptor_data_create_infos: vec![
+                DescriptorDataCreateInfo {
+                    _descriptor_binding_index: 0,
+                    _descriptor_name: String::from(OCCLUSION_CULLING_CONSTANTS_BUFFER_NAME),
+                    _descriptor_resource_type: DescriptorResourceType::UniformBuffer,
+                    _descriptor_shader_stage: vk::ShaderStageFlags::COMPUTE,
+                    ..Default::default()
+                },
+                storage_buffer_descriptor(1, OCCLUSION_CULLING_INSTANCE_BUFFER_NAME),
+                DescriptorDataCreateInfo {
+                    _descriptor_binding_index: 2,
+                    _descriptor_name: String::from("texture_hiz"),
+                    _descriptor_resource_type: DescriptorResourceType::RenderTarget,
+                    _descriptor_shader_stage: vk::ShaderStageFlags::COMPUTE,
+                    ..Default::default()
+                },
+                storage_buffer_descriptor(3, OCCLUSION_CULLING_DRAW_COMMAND_BUFFER_NAME),
+                storage_buffer_descriptor(4, OCCLUSION_CULLING_DRAW_COUNT_BUFFER_NAME),
+                storage_buffer_descriptor(5, VISIBLE_INSTANCE_BUFFER_NAME),
+                storage_buffer_descriptor(6, OCCLUSION_CULLING_STATS_BUFFER_NAME),
+            ],
+            ..Default::default()
+        }
+    ];
+
+    RenderPassDataCreateInfo {
+        _render_pass_create_info_name: String::from(OCCLUSION_CULLING_RENDER_PASS_NAME),
+        _pipeline_data_create_infos: pipeline_data_create_infos,
+        ..Default::default()
+    }
+}
+
+// the descriptor of the render_solid pipeline, ex) layout(std430, binding = N) readonly buffer VisibleInstanceBuffer { uint visible_instances[]; };
+pub fn get_visible_instance_descriptor_data_create_info(descriptor_binding_index: u32) -> DescriptorDataCreateInfo {
+    DescriptorDataCreateInfo {
+        _descriptor_binding_index: descriptor_binding_index,
+        _descriptor_name: String::from(VISIBLE_INSTANCE_BUFFER_NAME),
+        _descriptor_resource_type: DescriptorResourceType::StorageBuffer,
+        _descriptor_shader_stage: vk::ShaderStageFlags::VERTEX,
+        ..Default::default()
+    }
+}
+
+#[cfg(not(target_os = "android"))]
+pub fn generate_occlusion_culling_shader() {
+    let mut shader_file_path = PathBuf::from(SHADER_DIRECTORY);
+    shader_file_path.push(OCCLUSION_CULLING_SHADER_FILE);
+    if false == shader_file_path.is_file() {
+        fs::create_dir_all(shader_file_path.parent().unwrap()).expect("Failed to create directories.");
+        fs::write(&shader_file_path, OCCLUSION_CULLING_SHADER_SOURCE).expect("Failed to write");
+        log::info!("generate_occlusion_culling_shader: {:?}", shader_file_path);
+    }
+}
+
+fn get_visibility_bits_size(max_instance_count: usize) -> usize {
+    std::mem::size_of::<u32>() * ((max_instance_count + 31) / 32)
+}
+
+impl OcclusionCulling {
+    pub fn create_occlusion_culling_buffers(&mut self, renderer_data: &RendererData) {
+        // a group has one instance at least, so the group count is bounded by the instance count
+        let max_instance_count = unsafe { constants::MAX_INSTANCE_COUNT };
+        let create_buffer = |buffer_name: &str, buffer_usage: vk::BufferUsageFlags, buffer_size: usize| Some(buffer::create_shader_buffer_data(
+            renderer_data.get_device(),
+            renderer_data.get_device_memory_properties(),
+            &String::from(buffer_name),
+            buffer_usage,
+            buffer_size as vk::DeviceSize,
+            false,
+            false,
+            false,
+        ));
+        self._constants_buffer = create_buffer(
+            OCCLUSION_CULLING_CONSTANTS_BUFFER_NAME,
+            vk::BufferUsageFlags::UNIFORM_BUFFER,
+            std::mem::size_of::<OcclusionCullingConstants>()
+        );
+        self._instance_buffer = create_buffer(
+            OCCLUSION_CULLING_INSTANCE_BUFFER_NAME,
+            vk::BufferUsageFlags::STORAGE_BUFFER,
+            std::mem::size_of::<OcclusionCullingInstance>() * max_instance_count
+        );
+        self._draw_command_buffer = create_buffer(
+            OCCLUSION_CULLING_DRAW_COMMAND_BUFFER_NAME,
+            vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::INDIRECT_BUFFER,
+            std::mem::size_of::<vk::DrawIndexedIndirectCommand>() * max_instance_count
+        );
+        self._draw_count_buffer = create_buffer(
+            OCCLUSION_CULLING_DRAW_COUNT_BUFFER_NAME,
+            vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::INDIRECT_BUFFER,
+            std::mem::size_of::<u32>() * max_instance_count
+        );
+        self._visible_instance_buffer = create_buffer(
+            VISIBLE_INSTANCE_BUFFER_NAME,
+            vk::BufferUsageFlags::STORAGE_BUFFER,
+            std::mem::size_of::<u32>() * max_instance_count
+        );
+        self._stats_buffer = create_buffer(
+            OCCLUSION_CULLING_STATS_BUFFER_NAME,
+            vk::BufferUsageFlags::STORAGE_BUFFER,
+            std::mem::size_of::<OcclusionCullingStats>() + get_visibility_bits_size(max_instance_count)
+        );
+        self._is_stats_recorded = vec![false; constants::SWAPCHAIN_IMAGE_COUNT];
+    }
+
+    pub fn destroy_occlusion_culling_buffers(&mut self, device: &Device) {
+        for shader_buffer_data in [
+            &mut self._constants_buffer,
+            &mut self._instance_buffer,
+            &mut self._draw_command_buffer,
+            &mut self._draw_count_buffer,
+            &mut self._visible_instance_buffer,
+            &mut self._stats_buffer,
+        ].iter_mut() {
+            if let Some(mut shader_buffer_data) = shader_buffer_data.take() {
+                buffer::destroy_shader_buffer_data(device, &mut shader_buffer_data);
+            }
+        }
+    }
+
+    pub fn get_visible_instance_buffer(&self) -> Option<&ShaderBufferData> {
+        self._visible_instance_buffer.as_ref()
+    }
+
+    pub fn create_descriptor_sets(&mut self, renderer_data: &RendererData, resources: &Resources, hiz_target: &TextureData) {
+        let render_pass_pipeline_data = resources.get_render_pass_pipeline_data(OCCLUSION_CULLING_RENDER_PASS_NAME, OCCLUSION_CULLING_RENDER_PASS_NAME);
+        let pipeline_data = render_pass_pipeline_data._pipeline_data.borrow();
+        let descriptor_data = &pipeline_data._descriptor_data;
+        let descriptor_binding_indices: Vec<u32> = descriptor_data._descriptor_data_create_infos.iter().map(|descriptor_data_create_info| {
+            descriptor_data_create_info._descriptor_binding_index
+        }).collect();
+        let descriptor_resource_infos_list: SwapchainArray<Vec<DescriptorResourceInfo>> = constants::SWAPCHAIN_IMAGE_INDICES.iter().map(|swapchain_index| {
+            vec![
+                self._constants_buffer.as_ref().unwrap()._descriptor_buffer_infos[*swapchain_index].clone(),
+                self._instance_buffer.as_ref().unwrap()._descriptor_buffer_infos[*swapchain_index].clone(),
+                DescriptorResourceInfo::DescriptorImageInfo(hiz_target.get_default_image_info().clone()),
+                self._draw_command_buffer.as_ref().unwrap()._descriptor_buffer_infos[*swapchain_index].clone(),
+                self._draw_count_buffer.as_ref().unwrap()._descriptor_buffer_infos[*swapchain_index].clone(),
+                self._visible_instance_buffer.as_ref().unwrap()._descriptor_buffer_infos[*swapchain_index].clone(),
+                self._stats_buffer.as_ref().unwrap()._descriptor_buffer_infos[*swapchain_index].clone(),
+            ]
+        }).collect();
+        let descriptor_sets = descriptor::create_descriptor_sets(renderer_data.get_device(), descriptor_data, OCCLUSION_CULLING_RENDER_PASS_NAME);
+        let _write_descriptor_sets = descriptor::create_write_descriptor_sets_with_update(
+            renderer_data.get_device(),
+            &descriptor_sets,
+            &descriptor_binding_indices,
+            &descriptor_data._descriptor_set_layout_bindings,
+            &descriptor_resource_infos_list,
+        );
+        self._descriptor_sets = descriptor_sets;
+        self._is_history_valid = false;
+        if false == math::is_reversed_z() {
+            log::info!("create_descriptor_sets: the occlusion culling tests the frustum only, the min-z pyramid is conservative with the reversed-z.");
+        }
+    }
+
+    pub fn destroy_descriptor_sets(&mut self) {
+        self._descriptor_sets.clear();
+    }
+
+    pub fn is_descriptor_sets_created(&self) -> bool {
+        false == self._descriptor_sets.is_empty()
+    }
+
+    pub fn set_occlusion_culling_settings(&mut self, settings: &OcclusionCullingSettings) {
+        if self._settings._hiz_target_name != settings._hiz_target_name {
+            self._descriptor_sets.clear();
+        }
+        self._settings = settings.clone();
+    }
+
+    // the pyramid of the next frame is not from the previous view, ex) camera cut, scene load, resize
+    pub fn invalidate_history(&mut self) {
+        self._is_history_valid = false;
+    }
+
+    // called once per frame by SceneManagerData::cull_scene with the main camera
+    pub fn set_view_projection(&mut self, view_projection: &Matrix4<f32>) {
+        self._prev_view_projection = self._view_projection;
+        self._view_projection = *view_projection;
+    }
+
+    pub fn get_stats(&self) -> &OcclusionCullingStats {
+        &self._stats
+    }
+
+    pub fn get_stats_text(&self) -> String {
+        format!("gpu culling: visible {} / {}, culled {}",
+            self._stats._visible_count,
+            self._stats._visible_count + self._stats._culled_count,
+            self._stats._culled_count
+        )
+    }
+
+    // the instance offsets of the groups must be assigned, the groups get the index of their draw command.
+    pub fn dispatch_occlusion_culling(
+        &mut self,
+        command_buffer: vk::CommandBuffer,
+        swapchain_index: u32,
+        render_element_groups: &mut [RenderElementGroupData],
+        renderer_data: &RendererData,
+        resources: &Resources
+    ) {
+        if self._descriptor_sets.is_empty() {
+            return;
+        }
+        // the instances of a group without the bound spheres would shift the instance indices of the next groups
+        if render_element_groups.iter().any(|render_element_group| render_element_group._bound_spheres.len() != render_element_group._world_matrices.len()) {
+            log::warn!("dispatch_occlusion_culling: the bound spheres of the groups are missing, the static batch is not culled on the gpu.");
+            return;
+        }
+        let index = swapchain_index as usize;
+        let stats_buffer = self._stats_buffer.as_ref().unwrap();
+        if self._is_stats_recorded[index] {
+            // the fence of the swapchain image has been waited before the recording
+            let mut stats = [OcclusionCullingStats::default()];
+            renderer_data.read_shader_buffer_datas(swapchain_index, stats_buffer, 0, &mut stats);
+            self._stats = stats[0];
+        }
+
+        let mut instances: Vec<OcclusionCullingInstance> = Vec::new();
+        let mut draw_commands: Vec<vk::DrawIndexedIndirectCommand> = Vec::new();
+        for (group_index, render_element_group) in render_element_groups.iter_mut().enumerate() {
+            render_element_group._draw_command_index = group_index as u32;
+            for bound_sphere in render_element_group._bound_spheres.iter() {
+                instances.push(OcclusionCullingInstance {
+                    _bound_sphere: *bound_sphere,
+                    _group_index: group_index as u32,
+                    _group_instance_offset: render_element_group._instance_offset,
+                    ..Default::default()
+                });
+            }
+            draw_commands.push(vk::DrawIndexedIndirectCommand {
+                index_count: render_element_group._geometry_data.borrow()._vertex_index_count,
+                instance_count: 0,
+                first_index: 0,
+                vertex_offset: 0,
+                first_instance: 0,
+            });
+        }
+        if instances.is_empty() {
+            return;
+        }
+
+        let hiz_target = renderer_data.get_render_target_from_str(&self._settings._hiz_target_name);
+        let use_hiz = self._is_history_valid && math::is_reversed_z();
+        let culling_constants = OcclusionCullingConstants {
+            _frustum_planes: render_object_storage::get_frustum_planes(&self._view_projection),
+            _hiz_view_projection: self._prev_view_projection,
+            _hiz_size: Vector2::new(hiz_target._image_width as f32, hiz_target._image_height as f32),
+            _hiz_mip_count: hiz_target._image_mip_levels,
+            _instance_count: instances.len() as u32,
+            _flags: if use_hiz { OCCLUSION_CULLING_FLAG_HIZ } else { 0 },
+            _reserved0: 0,
+            _reserved1: 0,
+            _reserved2: 0,
+        };
+        let draw_counts: Vec<u32> = vec![0; draw_commands.len()];
+        let stats_and_visibility_bits: Vec<u32> = vec![0; (std::mem::size_of::<OcclusionCullingStats>() + get_visibility_bits_size(instances.len())) / std::mem::size_of::<u32>()];
+        renderer_data.upload_shader_buffer_data(command_buffer, swapchain_index, self._constants_buffer.as_ref().unwrap(), &culling_constants);
+        renderer_data.upload_shader_buffer_datas(command_buffer, swapchain_index, self._instance_buffer.as_ref().unwrap(), &instances);
+        renderer_data.upload_shader_buffer_datas(command_buffer, swapchain_index, self._draw_command_buffer.as_ref().unwrap(), &draw_commands);
+        renderer_data.upload_shader_buffer_datas(command_buffer, swapchain_index, self._draw_count_buffer.as_ref().unwrap(), &draw_counts);
+        renderer_data.upload_shader_buffer_datas(command_buffer, swapchain_index, stats_buffer, &stats_and_visibility_bits);
+
+        let buffer_memory_barrier = |shader_buffer_data: &Option<ShaderBufferData>, src_access_mask: vk::AccessFlags, dst_access_mask: vk::AccessFlags| vk::BufferMemoryBarrier {
+            src_access_mask,
+            dst_access_mask,
+            src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            buffer: shader_buffer_data.as_ref().unwrap()._buffers[index]._buffer,
+            offset: 0,
+            size: vk::WHOLE_SIZE,
+            ..Default::default()
+        };
+        renderer_data.pipeline_barrier(command_buffer, vk::PipelineStageFlags::HOST, vk::PipelineStageFlags::COMPUTE_SHADER, vk::DependencyFlags::empty(), &[], &[
+            buffer_memory_barrier(&self._instance_buffer, vk::AccessFlags::HOST_WRITE, vk::AccessFlags::SHADER_READ),
+            buffer_memory_barrier(&self._draw_command_buffer, vk::AccessFlags::HOST_WRITE, vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE),
+            buffer_memory_barrier(&self._draw_count_buffer, vk::AccessFlags::HOST_WRITE, vk::AccessFlags::SHADER_WRITE),
+            buffer_memory_barrier(&self._stats_buffer, vk::AccessFlags::HOST_WRITE, vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE),
+        ], &[]);
+
+        let render_pass_pipeline_data = resources.get_render_pass_pipeline_data(OCCLUSION_CULLING_RENDER_PASS_NAME, OCCLUSION_CULLING_RENDER_PASS_NAME);
+        let pipeline_data = render_pass_pipeline_data._pipeline_data.borrow();
+        renderer_data.begin_debug_label(command_buffer, OCCLUSION_CULLING_RENDER_PASS_NAME);
+        renderer_data.begin_compute_pipeline(command_buffer, &pipeline_data);
+        unsafe {
+            renderer_data.get_device().cmd_bind_descriptor_sets(
+                command_buffer,
+                pipeline_data._pipeline_bind_point,
+                pipeline_data._pipeline_layout,
+                0,
+                &[self._descriptor_sets[index]],
+                &[]
+            );
+        }
+        renderer_data.dispatch_compute_pipeline(
+            command_buffer,
+            (instances.len() as u32 + OCCLUSION_CULLING_WORK_GROUP_SIZE - 1) / OCCLUSION_CULLING_WORK_GROUP_SIZE,
+            1,
+            1
+        );
+        renderer_data.end_debug_label(command_buffer);
+
+        renderer_data.pipeline_barrier(command_buffer, vk::PipelineStageFlags::COMPUTE_SHADER, vk::PipelineStageFlags::DRAW_INDIRECT | vk::PipelineStageFlags::VERTEX_SHADER, vk::DependencyFlags::empty(), &[], &[
+            buffer_memory_barrier(&self._draw_command_buffer, vk::AccessFlags::SHADER_WRITE, vk::AccessFlags::INDIRECT_COMMAND_READ),
+            buffer_memory_barrier(&self._draw_count_buffer, vk::AccessFlags::SHADER_WRITE, vk::AccessFlags::INDIRECT_COMMAND_READ),
+            buffer_memory_barrier(&self._visible_instance_buffer, vk::AccessFlags::SHADER_WRITE, vk::AccessFlags::SHADER_READ),
+        ], &[]);
+        renderer_data.pipeline_barrier(command_buffer, vk::PipelineStageFlags::COMPUTE_SHADER, vk::PipelineStageFlags::HOST, vk::DependencyFlags::empty(), &[], &[
+            buffer_memory_barrier(&self._stats_buffer, vk::AccessFlags::SHADER_WRITE, vk::AccessFlags::HOST_READ),
+        ], &[]);
+        self._is_stats_recorded[index] = true;
+        self._is_history_valid = true;
+    }
+}
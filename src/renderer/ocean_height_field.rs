@@ -0,0 +1,252 @@
+use std::cell::RefCell;
+use std::f32::consts::PI;
+
+use nalgebra::{ Complex, Vector2, Vector3, Vector4 };
+
+use crate::application::environment_settings::OceanSettings;
+use crate::renderer::fft_ocean;
+
+// the cpu copy of the ocean surface for the buoyancy and the camera collision, there is no readback so no frame latency.
+// only the lowest frequencies of the two largest grids are evaluated, the two smaller grids are ignored.
+pub const OCEAN_HEIGHT_FIELD_SIZE: u32 = 64; // the frequencies of a grid, so the samples of a grid period
+pub const OCEAN_HEIGHT_FIELD_GRID_COUNT: usize = 2;
+pub const OCEAN_CHOPPY_ITERATIONS: u32 = 2; // the horizontal displacement is inverted by the fixed point iteration
+pub const OCEAN_GRAVITY: f32 = 9.81; // must match with fft_ocean.glsl
+pub const OCEAN_CAPILLARY_WAVE_NUMBER: f32 = 370.0; // must match with fft_ocean.glsl
+
+#[derive(Clone, Debug, Default)]
+struct OceanHeightFieldGrid {
+    _grid_size: f32, // meters, OceanSettings::get_simulation_sizes
+    _choppy_factor: f32,
+    _wave_vectors: Vec<Vector2<f32>>,
+    _h0: Vec<Complex<f32>>, // h0(k)
+    _h0_conj: Vec<Complex<f32>>, // conj(h0(-k))
+    _heights: Vec<f32>,
+    _displacements: Vec<Vector2<f32>>,
+}
+
+// the surface at the evaluated time, the grids are evaluated again when the time of the query changes.
+#[derive(Clone, Debug, Default)]
+struct OceanHeightFieldState {
+    _time: Option<f32>,
+    _grids: Vec<OceanHeightFieldGrid>,
+}
+
+// FFTOcean creates this from _spectrum12_data at initialize_fft_ocean and set_sea_state.
+// the spectrum texel (x, y) is the wave vector 2pi * (x', y') / grid_size, x' = x - fft_size if fft_size / 2 <= x.
+// xy: h0 of the first grid, zw: h0 of the second grid. the world xz is the uv of the grids, same as the ocean shader.
+#[derive(Clone, Debug, Default)]
+pub struct OceanHeightField {
+    _size: u32,
+    _state: RefCell<OceanHeightFieldState>,
+}
+
+// in place radix 2, without the 1 / n normalization same as the gpu fft. f(x) = sum h(k) * e^(ikx)
+fn inverse_fft(datas: &mut [Complex<f32>]) {
+    let size = datas.len() as u32;
+    for index in 0..size {
+        let reversed_index = fft_ocean::bit_reverse(index, size);
+        if index < reversed_index {
+            datas.swap(index as usize, reversed_index as usize);
+        }
+    }
+    let mut half_size: usize = 1;
+    while half_size < size as usize {
+        let angle = PI / half_size as f32;
+        for block in (0..size as usize).step_by(half_size * 2) {
+            for k in 0..half_size {
+                let twiddle = Complex::new((angle * k as f32).cos(), (angle * k as f32).sin());
+                let a = datas[block + k];
+                let b = datas[block + k + half_size] * twiddle;
+                datas[block + k] = a + b;
+                datas[block + k + half_size] = a - b;
+            }
+        }
+        half_size *= 2;
+    }
+}
+
+// rows, then columns
+fn inverse_fft_2d(datas: &mut [Complex<f32>], size: usize) {
+    let mut column: Vec<Complex<f32>> = vec![Complex::new(0.0, 0.0); size];
+    for row in datas.chunks_mut(size) {
+        inverse_fft(row);
+    }
+    for x in 0..size {
+        for y in 0..size {
+            column[y] = datas[x + y * size];
+        }
+        inverse_fft(&mut column);
+        for y in 0..size {
+            datas[x + y * size] = column[y];
+        }
+    }
+}
+
+// the signed frequency of the index, ex) size 4: 0, 1, -2, -1
+fn get_signed_frequency(index: u32, size: u32) -> i32 {
+    if index < size / 2 { index as i32 } else { index as i32 - size as i32 }
+}
+
+fn get_wrapped_index(frequency: i32, size: u32) -> u32 {
+    frequency.rem_euclid(size as i32) as u32
+}
+
+impl OceanHeightFieldGrid {
+    fn create_ocean_height_field_grid(
+        fft_size: u32,
+        size: u32,
+        spectrum12_data: &[Vector4<f32>],
+        grid_index: usize,
+        grid_size: f32,
+        choppy_factor: f32,
+    ) -> OceanHeightFieldGrid {
+        let texel_count = (size * size) as usize;
+        let mut wave_vectors: Vec<Vector2<f32>> = Vec::with_capacity(texel_count);
+        let mut h0: Vec<Complex<f32>> = Vec::with_capacity(texel_count);
+        let mut h0_conj: Vec<Complex<f32>> = Vec::with_capacity(texel_count);
+        let get_h0 = |frequency_x: i32, frequency_y: i32| -> Complex<f32> {
+            let texel_index = get_wrapped_index(frequency_x, fft_size) + get_wrapped_index(frequency_y, fft_size) * fft_size;
+            let spectrum = &spectrum12_data[texel_index as usize];
+            match grid_index {
+                0 => Complex::new(spectrum.x, spectrum.y),
+                _ => Complex::new(spectrum.z, spectrum.w),
+            }
+        };
+        for y in 0..size {
+            for x in 0..size {
+                let (frequency_x, frequency_y) = (get_signed_frequency(x, size), get_signed_frequency(y, size));
+                wave_vectors.push(Vector2::new(frequency_x as f32, frequency_y as f32) * (2.0 * PI / grid_size));
+                h0.push(get_h0(frequency_x, frequency_y));
+                h0_conj.push(get_h0(-frequency_x, -frequency_y).conj());
+            }
+        }
+        OceanHeightFieldGrid {
+            _grid_size: grid_size,
+            _choppy_factor: choppy_factor,
+            _wave_vectors: wave_vectors,
+            _h0: h0,
+            _h0_conj: h0_conj,
+            _heights: vec![0.0; texel_count],
+            _displacements: vec![Vector2::zeros(); texel_count],
+        }
+    }
+
+    // h(k, t) = h0(k) * e^(iwt) + conj(h0(-k)) * e^(-iwt), the displacement is -i * k / |k| * h(k, t)
+    fn evaluate(&mut self, size: u32, time: f32) {
+        let texel_count = (size * size) as usize;
+        let mut heights: Vec<Complex<f32>> = Vec::with_capacity(texel_count);
+        let mut displacements_x: Vec<Complex<f32>> = Vec::with_capacity(texel_count);
+        let mut displacements_z: Vec<Complex<f32>> = Vec::with_capacity(texel_count);
+        for index in 0..texel_count {
+            let wave_vector = &self._wave_vectors[index];
+            let wave_number = wave_vector.norm();
+            let omega = (OCEAN_GRAVITY * wave_number * (1.0 + (wave_number / OCEAN_CAPILLARY_WAVE_NUMBER).powi(2))).sqrt();
+            let phase = Complex::new((omega * time).cos(), (omega * time).sin()); // from_polar needs the std feature of num-complex
+            let height = self._h0[index] * phase + self._h0_conj[index] * phase.conj();
+            let (direction_x, direction_z) = if 0.0 < wave_number { (wave_vector.x / wave_number, wave_vector.y / wave_number) } else { (0.0, 0.0) };
+            heights.push(height);
+            displacements_x.push(Complex::new(0.0, -direction_x) * height);
+            displacements_z.push(Complex::new(0.0, -direction_z) * height);
+        }
+        inverse_fft_2d(&mut heights, size as usize);
+        inverse_fft_2d(&mut displacements_x, size as usize);
+        inverse_fft_2d(&mut displacements_z, size as usize);
+        for index in 0..texel_count {
+            self._heights[index] = heights[index].re;
+            self._displacements[index] = Vector2::new(displacements_x[index].re, displacements_z[index].re) * self._choppy_factor;
+        }
+    }
+
+    // bilinear, the grid repeats with the period of the grid size
+    fn sample<T>(&self, size: u32, datas: &[T], world_xz: &Vector2<f32>) -> T
+        where T: Copy + std::ops::Mul<f32, Output = T> + std::ops::Add<Output = T>
+    {
+        let texel_x = world_xz.x / self._grid_size * size as f32;
+        let texel_y = world_xz.y / self._grid_size * size as f32;
+        let (floor_x, floor_y) = (texel_x.floor(), texel_y.floor());
+        let (fract_x, fract_y) = (texel_x - floor_x, texel_y - floor_y);
+        let x0 = get_wrapped_index(floor_x as i32, size);
+        let y0 = get_wrapped_index(floor_y as i32, size);
+        let x1 = (x0 + 1) % size;
+        let y1 = (y0 + 1) % size;
+        let fetch = |x: u32, y: u32| -> T { datas[(x + y * size) as usize] };
+        let top = fetch(x0, y0) * (1.0 - fract_x) + fetch(x1, y0) * fract_x;
+        let bottom = fetch(x0, y1) * (1.0 - fract_x) + fetch(x1, y1) * fract_x;
+        top * (1.0 - fract_y) + bottom * fract_y
+    }
+}
+
+impl OceanHeightField {
+    // spectrum12_data: fft_size x fft_size of FFTOceanLayout::_spectrum_size
+    pub fn create_ocean_height_field(fft_size: u32, spectrum12_data: &[Vector4<f32>], ocean_settings: &OceanSettings) -> OceanHeightField {
+        if spectrum12_data.len() != (fft_size * fft_size) as usize {
+            log::error!("create_ocean_height_field: spectrum12 has {} texels, but the fft size is {}", spectrum12_data.len(), fft_size);
+            return OceanHeightField::default();
+        }
+        let size = OCEAN_HEIGHT_FIELD_SIZE.min(fft_size);
+        let simulation_sizes = ocean_settings.get_simulation_sizes();
+        let grids: Vec<OceanHeightFieldGrid> = (0..OCEAN_HEIGHT_FIELD_GRID_COUNT).map(|grid_index| {
+            OceanHeightFieldGrid::create_ocean_height_field_grid(
+                fft_size,
+                size,
+                spectrum12_data,
+                grid_index,
+                simulation_sizes[grid_index],
+                ocean_settings._choppy_factors[grid_index],
+            )
+        }).collect();
+        OceanHeightField {
+            _size: size,
+            _state: RefCell::new(OceanHeightFieldState {
+                _time: None,
+                _grids: grids,
+            }),
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        0 < self._size
+    }
+
+    // time: the simulation time of the ocean shader, so the cpu surface matches the rendered surface.
+    fn update_state(&self, time: f32) {
+        let mut state = self._state.borrow_mut();
+        if Some(time) != state._time {
+            for grid in state._grids.iter_mut() {
+                grid.evaluate(self._size, time);
+            }
+            state._time = Some(time);
+        }
+    }
+
+    // the height above the sea level, add OceanSettings::_ocean_height for the world height.
+    // the surface is moved horizontally by the choppy displacement, so the source position of the world xz is searched first.
+    pub fn get_ocean_height(&self, world_xz: &Vector2<f32>, time: f32) -> f32 {
+        if false == self.is_valid() {
+            return 0.0;
+        }
+        self.update_state(time);
+        let state = self._state.borrow();
+        let mut source_xz = world_xz.clone();
+        for _ in 0..OCEAN_CHOPPY_ITERATIONS {
+            let displacement: Vector2<f32> = state._grids.iter().fold(Vector2::zeros(), |displacement, grid| {
+                displacement + grid.sample(self._size, &grid._displacements, &source_xz)
+            });
+            source_xz = world_xz - displacement;
+        }
+        state._grids.iter().map(|grid| grid.sample(self._size, &grid._heights, &source_xz)).sum()
+    }
+
+    // the central difference of get_ocean_height with the half texel of the smaller grid
+    pub fn get_ocean_normal(&self, world_xz: &Vector2<f32>, time: f32) -> Vector3<f32> {
+        if false == self.is_valid() {
+            return Vector3::new(0.0, 1.0, 0.0);
+        }
+        let min_grid_size = self._state.borrow()._grids.iter().fold(std::f32::MAX, |min_grid_size, grid| min_grid_size.min(grid._grid_size));
+        let delta = 0.5 * min_grid_size / self._size as f32;
+        let height_dx = self.get_ocean_height(&(world_xz + Vector2::new(delta, 0.0)), time) - self.get_ocean_height(&(world_xz - Vector2::new(delta, 0.0)), time);
+        let height_dz = self.get_ocean_height(&(world_xz + Vector2::new(0.0, delta)), time) - self.get_ocean_height(&(world_xz - Vector2::new(0.0, delta)), time);
+        Vector3::new(-height_dx, 2.0 * delta, -height_dz).normalize()
+    }
+}
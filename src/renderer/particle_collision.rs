@@ -0,0 +1,146 @@
+use std::fs;
+use std::path::PathBuf;
+
+use ash::vk;
+use serde::{ Serialize, Deserialize };
+
+use crate::vulkan_context::descriptor::{ DescriptorDataCreateInfo, DescriptorResourceType };
+use crate::vulkan_context::shader::SHADER_DIRECTORY;
+
+// the depth buffer collision of the particle update compute pass and the soft particle fade of the particle pixel shader.
+// the particle system of the project includes the file, embeds ParticleCollisionConstants in its static constants
+// and binds the descriptors of get_particle_collision_descriptor_data_create_infos.
+pub const PARTICLE_COLLISION_SHADER_FILE: &str = "common/particle_collision.glsl";
+pub const PARTICLE_COLLISION_DEPTH_TARGET_NAME: &str = "SceneDepth";
+pub const PARTICLE_COLLISION_NORMAL_TARGET_NAME: &str = "SceneNormal"; // encoded as normal * 0.5 + 0.5
+pub const PARTICLE_COLLISION_SHADER_SOURCE: &str = r#"// generated by the engine, particle_collision.rs - PARTICLE_COLLISION_SHADER_SOURCE
+#ifndef PARTICLE_COLLISION_GLSL
+#define PARTICLE_COLLISION_GLSL
+
+// particle_collision.rs - struct ParticleCollisionConstants
+struct PARTICLE_COLLISION_CONSTANTS
+{
+    float _restitution; // 0: slide along the surface, 1: elastic bounce
+    float _friction; // the tangential velocity lost by a collision
+    float _collision_thickness; // meters, a particle further behind the depth is occluded, not colliding
+    float _soft_fade_distance; // meters, 0: no soft particle fade
+};
+
+// false behind the camera or off-screen, the collision lookup is skipped
+bool get_particle_screen_position(mat4 view_projection, vec3 world_position, out vec3 ndc_position)
+{
+    vec4 clip_position = view_projection * vec4(world_position, 1.0);
+    ndc_position = vec3(0.0);
+    if(clip_position.w <= 0.0)
+    {
+        return false;
+    }
+    ndc_position = clip_position.xyz / clip_position.w;
+    return all(greaterThanEqual(ndc_position.xy, vec2(-1.0))) && all(lessThanEqual(ndc_position.xy, vec2(1.0)));
+}
+
+// the bounce and the slide against the depth buffer of the previous frame, the particle is pushed out onto the surface.
+void apply_particle_depth_collision(
+    sampler2D texture_depth,
+    sampler2D texture_normal,
+    mat4 view_projection,
+    mat4 inv_view_projection,
+    PARTICLE_COLLISION_CONSTANTS collision,
+    inout vec3 position,
+    inout vec3 velocity)
+{
+    vec3 ndc_position;
+    if(false == get_particle_screen_position(view_projection, position, ndc_position))
+    {
+        return;
+    }
+    vec2 texcoord = ndc_position.xy * 0.5 + 0.5;
+    float scene_depth = textureLod(texture_depth, texcoord, 0.0).x;
+    vec4 surface_position = inv_view_projection * vec4(ndc_position.xy, scene_depth, 1.0);
+    surface_position.xyz /= surface_position.w;
+    vec3 surface_normal = normalize(textureLod(texture_normal, texcoord, 0.0).xyz * 2.0 - 1.0);
+    // the depth of the particle behind the surface along the normal
+    float penetration = dot(surface_position.xyz - position, surface_normal);
+    if(penetration < 0.0 || collision._collision_thickness < penetration)
+    {
+        return;
+    }
+    float normal_speed = dot(velocity, surface_normal);
+    if(normal_speed < 0.0)
+    {
+        vec3 normal_velocity = surface_normal * normal_speed;
+        vec3 tangent_velocity = velocity - normal_velocity;
+        velocity = tangent_velocity * (1.0 - collision._friction) - normal_velocity * collision._restitution;
+    }
+    position += surface_normal * penetration;
+}
+
+// the linear depths in meters, see math::get_linear_depth_params. 1 is opaque, 0 at the scene depth.
+float get_soft_particle_fade(float scene_linear_depth, float particle_linear_depth, float soft_fade_distance)
+{
+    if(soft_fade_distance <= 0.0)
+    {
+        return 1.0;
+    }
+    return clamp((scene_linear_depth - particle_linear_depth) / soft_fade_distance, 0.0, 1.0);
+}
+
+#endif // PARTICLE_COLLISION_GLSL
+"#;
+
+#[cfg(not(target_os = "android"))]
+pub fn generate_particle_collision_shader() {
+    let mut shader_file_path = PathBuf::from(SHADER_DIRECTORY);
+    shader_file_path.push(PARTICLE_COLLISION_SHADER_FILE);
+    if false == shader_file_path.is_file() {
+        fs::create_dir_all(shader_file_path.parent().unwrap()).expect("Failed to create directories.");
+        fs::write(&shader_file_path, PARTICLE_COLLISION_SHADER_SOURCE).expect("Failed to write");
+        log::info!("generate_particle_collision_shader: {:?}", shader_file_path);
+    }
+}
+
+// particle_collision.glsl - struct PARTICLE_COLLISION_CONSTANTS, ex) a member of GpuParticleStaticConstants of the project
+#[repr(C)]
+#[derive(Serialize, Deserialize, Clone, Debug, Copy, PartialEq)]
+#[serde(default)]
+pub struct ParticleCollisionConstants {
+    pub _restitution: f32,
+    pub _friction: f32,
+    pub _collision_thickness: f32,
+    pub _soft_fade_distance: f32, // ex) PushConstant_RenderParticle of the project
+}
+
+impl Default for ParticleCollisionConstants {
+    fn default() -> ParticleCollisionConstants {
+        ParticleCollisionConstants {
+            _restitution: 0.3,
+            _friction: 0.2,
+            _collision_thickness: 0.5,
+            _soft_fade_distance: 0.5,
+        }
+    }
+}
+
+// the descriptors of the particle update compute pipeline: texture_depth at first_binding_index, texture_normal at the next.
+pub fn get_particle_collision_descriptor_data_create_infos(first_binding_index: u32) -> Vec<DescriptorDataCreateInfo> {
+    [PARTICLE_COLLISION_DEPTH_TARGET_NAME, PARTICLE_COLLISION_NORMAL_TARGET_NAME].iter().enumerate().map(|(index, target_name)| {
+        DescriptorDataCreateInfo {
+            _descriptor_binding_index: first_binding_index + index as u32,
+            _descriptor_name: String::from(*target_name),
+            _descriptor_resource_type: DescriptorResourceType::RenderTarget,
+            _descriptor_shader_stage: vk::ShaderStageFlags::COMPUTE,
+            ..Default::default()
+        }
+    }).collect()
+}
+
+// the scene depth of the soft particle fade in the particle pixel shader
+pub fn get_soft_particle_descriptor_data_create_info(descriptor_binding_index: u32) -> DescriptorDataCreateInfo {
+    DescriptorDataCreateInfo {
+        _descriptor_binding_index: descriptor_binding_index,
+        _descriptor_name: String::from(PARTICLE_COLLISION_DEPTH_TARGET_NAME),
+        _descriptor_resource_type: DescriptorResourceType::RenderTarget,
+        _descriptor_shader_stage: vk::ShaderStageFlags::FRAGMENT,
+        ..Default::default()
+    }
+}
@@ -0,0 +1,262 @@
+use std::collections::BTreeMap;
+
+use ash::vk;
+use nalgebra::Vector2;
+use serde::{ Serialize, Deserialize };
+
+use crate::constants;
+use crate::renderer::renderer::RendererData;
+use crate::vulkan_context::texture::{ TextureCreateInfo, TextureData };
+
+// the consumers of the post process chain, the main viewport, split-screen viewports and scene captures.
+// the main viewport uses the cvars as its profile and the history render targets of the project renderer.
+pub type PostProcessConsumerId = u32;
+pub const MAIN_POST_PROCESS_CONSUMER: PostProcessConsumerId = 0;
+pub const MAIN_POST_PROCESS_CONSUMER_NAME: &str = "main_viewport";
+pub const TAA_HISTORY_FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+pub const SSR_HISTORY_FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+pub const EXPOSURE_HISTORY_FORMAT: vk::Format = vk::Format::R32_SFLOAT; // 1x1, the adapted luminance
+
+#[derive(Clone, Debug, Copy, PartialEq, Eq)]
+pub enum AntiAliasingMode {
+    None,
+    FXAA,
+    TAA,
+}
+
+impl AntiAliasingMode {
+    pub fn get_next_anti_aliasing_mode(&self) -> AntiAliasingMode {
+        match self {
+            AntiAliasingMode::None => AntiAliasingMode::FXAA,
+            AntiAliasingMode::FXAA => AntiAliasingMode::TAA,
+            AntiAliasingMode::TAA => AntiAliasingMode::None,
+        }
+    }
+}
+
+// ex) "post_process_profile": { "_enable_bloom": false, "_enable_taa": false } for a minimap capture
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct PostProcessProfile {
+    pub _enable_ssao: bool,
+    pub _enable_ssr: bool,
+    pub _enable_taa: bool,
+    pub _enable_fxaa: bool, // when the taa is disabled
+    pub _enable_bloom: bool,
+    pub _bloom_intensity: f32,
+    pub _enable_auto_exposure: bool,
+    pub _exposure_value: f32, // when the auto exposure is disabled
+}
+
+impl Default for PostProcessProfile {
+    fn default() -> PostProcessProfile {
+        PostProcessProfile {
+            _enable_ssao: true,
+            _enable_ssr: true,
+            _enable_taa: true,
+            _enable_fxaa: true,
+            _enable_bloom: true,
+            _bloom_intensity: 0.25,
+            _enable_auto_exposure: true,
+            _exposure_value: 1.0,
+        }
+    }
+}
+
+impl PostProcessProfile {
+    pub fn create_main_post_process_profile() -> PostProcessProfile {
+        unsafe {
+            PostProcessProfile {
+                _enable_ssao: constants::POST_PROCESS_SSAO,
+                _enable_ssr: constants::POST_PROCESS_SSR,
+                _enable_taa: constants::POST_PROCESS_TAA,
+                _enable_fxaa: constants::POST_PROCESS_FXAA,
+                _enable_bloom: constants::POST_PROCESS_BLOOM,
+                _bloom_intensity: constants::POST_PROCESS_BLOOM_INTENSITY,
+                _enable_auto_exposure: constants::POST_PROCESS_AUTO_EXPOSURE,
+                _exposure_value: constants::POST_PROCESS_EXPOSURE_VALUE,
+            }
+        }
+    }
+
+    // tone mapping only, ex) minimap captures
+    pub fn create_minimal_post_process_profile() -> PostProcessProfile {
+        PostProcessProfile {
+            _enable_ssao: false,
+            _enable_ssr: false,
+            _enable_taa: false,
+            _enable_fxaa: false,
+            _enable_bloom: false,
+            _enable_auto_exposure: false,
+            ..Default::default()
+        }
+    }
+
+    pub fn get_anti_aliasing_mode(&self) -> AntiAliasingMode {
+        if self._enable_taa {
+            AntiAliasingMode::TAA
+        } else if self._enable_fxaa {
+            AntiAliasingMode::FXAA
+        } else {
+            AntiAliasingMode::None
+        }
+    }
+
+    pub fn has_history_pass(&self) -> bool {
+        self._enable_taa || self._enable_ssr || self._enable_auto_exposure
+    }
+}
+
+// allocated only for the passes enabled by the profile of the consumer.
+#[derive(Clone, Debug, Default)]
+pub struct PostProcessHistory {
+    pub _taa_history: Option<TextureData>,
+    pub _ssr_history: Option<TextureData>,
+    pub _exposure_history: Option<TextureData>,
+    pub _is_history_valid: bool, // false after (re)allocation, the project renderer sets it after the first rendering
+}
+
+impl PostProcessHistory {
+    fn update_history_texture(renderer_data: &RendererData, history_texture: &mut Option<TextureData>, is_enabled: bool, texture_create_info: &TextureCreateInfo<u8>) -> bool {
+        let is_size_changed = match history_texture.as_ref() {
+            Some(texture_data) => texture_data._image_width != texture_create_info._texture_width || texture_data._image_height != texture_create_info._texture_height,
+            None => false,
+        };
+        if false == is_enabled || is_size_changed {
+            if let Some(texture_data) = history_texture.take() {
+                renderer_data.destroy_texture(&texture_data);
+            }
+        }
+        if is_enabled && history_texture.is_none() {
+            *history_texture = Some(renderer_data.create_render_target::<u8>(texture_create_info));
+            return true;
+        }
+        false
+    }
+
+    pub fn update_post_process_history(&mut self, renderer_data: &RendererData, consumer_name: &str, profile: &PostProcessProfile, size: &Vector2<i32>) {
+        let create_info = |name: &str, width: i32, height: i32, format: vk::Format| -> TextureCreateInfo<u8> {
+            TextureCreateInfo {
+                _texture_name: format!("{}_{}", consumer_name, name),
+                _texture_width: width.max(1) as u32,
+                _texture_height: height.max(1) as u32,
+                _texture_format: format,
+                _texture_min_filter: vk::Filter::LINEAR,
+                _texture_mag_filter: vk::Filter::LINEAR,
+                _texture_wrap_mode: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                _enable_mipmap: false,
+                ..Default::default()
+            }
+        };
+        let mut is_reallocated = false;
+        is_reallocated |= PostProcessHistory::update_history_texture(renderer_data, &mut self._taa_history, profile._enable_taa, &create_info("taa_history", size.x, size.y, TAA_HISTORY_FORMAT));
+        is_reallocated |= PostProcessHistory::update_history_texture(renderer_data, &mut self._ssr_history, profile._enable_ssr, &create_info("ssr_history", size.x, size.y, SSR_HISTORY_FORMAT));
+        is_reallocated |= PostProcessHistory::update_history_texture(renderer_data, &mut self._exposure_history, profile._enable_auto_exposure, &create_info("exposure_history", 1, 1, EXPOSURE_HISTORY_FORMAT));
+        if is_reallocated {
+            self._is_history_valid = false;
+        }
+    }
+
+    pub fn destroy_post_process_history(&mut self, renderer_data: &RendererData) {
+        for history_texture in [&mut self._taa_history, &mut self._ssr_history, &mut self._exposure_history].iter_mut() {
+            if let Some(texture_data) = history_texture.take() {
+                renderer_data.destroy_texture(&texture_data);
+            }
+        }
+        self._is_history_valid = false;
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PostProcessConsumer {
+    pub _name: String,
+    pub _size: Vector2<i32>,
+    pub _profile: PostProcessProfile,
+    pub _history: PostProcessHistory,
+}
+
+// a viewport or a scene capture regists itself and keeps the id, the history is freed on unregist.
+#[derive(Debug)]
+pub struct PostProcessConsumers {
+    pub _consumers: BTreeMap<PostProcessConsumerId, PostProcessConsumer>,
+    pub _next_consumer_id: PostProcessConsumerId,
+}
+
+impl Default for PostProcessConsumers {
+    fn default() -> PostProcessConsumers {
+        let mut consumers: BTreeMap<PostProcessConsumerId, PostProcessConsumer> = BTreeMap::new();
+        consumers.insert(MAIN_POST_PROCESS_CONSUMER, PostProcessConsumer {
+            _name: String::from(MAIN_POST_PROCESS_CONSUMER_NAME),
+            _size: Vector2::zeros(),
+            _profile: PostProcessProfile::create_main_post_process_profile(),
+            _history: PostProcessHistory::default(),
+        });
+        PostProcessConsumers {
+            _consumers: consumers,
+            _next_consumer_id: MAIN_POST_PROCESS_CONSUMER + 1,
+        }
+    }
+}
+
+impl PostProcessConsumers {
+    pub fn regist_post_process_consumer(&mut self, renderer_data: &RendererData, name: &str, size: &Vector2<i32>, profile: &PostProcessProfile) -> PostProcessConsumerId {
+        let consumer_id = self._next_consumer_id;
+        self._next_consumer_id += 1;
+        let mut consumer = PostProcessConsumer {
+            _name: String::from(name),
+            _size: size.clone(),
+            _profile: profile.clone(),
+            _history: PostProcessHistory::default(),
+        };
+        consumer._history.update_post_process_history(renderer_data, name, profile, size);
+        self._consumers.insert(consumer_id, consumer);
+        log::info!("regist_post_process_consumer: {} {} {:?}", consumer_id, name, profile);
+        consumer_id
+    }
+
+    pub fn unregist_post_process_consumer(&mut self, renderer_data: &RendererData, consumer_id: PostProcessConsumerId) {
+        if MAIN_POST_PROCESS_CONSUMER == consumer_id {
+            log::warn!("unregist_post_process_consumer: the main viewport can not be unregisted.");
+            return;
+        }
+        if let Some(mut consumer) = self._consumers.remove(&consumer_id) {
+            consumer._history.destroy_post_process_history(renderer_data);
+            log::info!("unregist_post_process_consumer: {} {}", consumer_id, consumer._name);
+        }
+    }
+
+    pub fn get_post_process_consumer(&self, consumer_id: PostProcessConsumerId) -> Option<&PostProcessConsumer> {
+        self._consumers.get(&consumer_id)
+    }
+
+    // the history follows the enabled passes and the size, ex) a split-screen viewport after resizing
+    pub fn update_post_process_consumer(&mut self, renderer_data: &RendererData, consumer_id: PostProcessConsumerId, size: &Vector2<i32>, profile: &PostProcessProfile) {
+        if let Some(consumer) = self._consumers.get_mut(&consumer_id) {
+            consumer._size = size.clone();
+            consumer._profile = profile.clone();
+            if MAIN_POST_PROCESS_CONSUMER != consumer_id {
+                consumer._history.update_post_process_history(renderer_data, &consumer._name, profile, size);
+            }
+        }
+    }
+
+    pub fn update_main_post_process_profile(&mut self, size: &Vector2<i32>) {
+        let consumer = self._consumers.get_mut(&MAIN_POST_PROCESS_CONSUMER).unwrap();
+        consumer._size = size.clone();
+        consumer._profile = PostProcessProfile::create_main_post_process_profile();
+    }
+
+    // the histories are invalid after the swapchain has been recreated or the capture frame
+    pub fn invalidate_post_process_histories(&mut self) {
+        for consumer in self._consumers.values_mut() {
+            consumer._history._is_history_valid = false;
+        }
+    }
+
+    pub fn destroy_post_process_consumers(&mut self, renderer_data: &RendererData) {
+        let consumer_ids: Vec<PostProcessConsumerId> = self._consumers.keys().filter(|consumer_id| MAIN_POST_PROCESS_CONSUMER != **consumer_id).cloned().collect();
+        for consumer_id in consumer_ids.iter() {
+            self.unregist_post_process_consumer(renderer_data, *consumer_id);
+        }
+    }
+}
@@ -1,11 +1,228 @@
+use std::cmp::Ordering;
+
+use nalgebra::{ Matrix4, Vector3, Vector4 };
+
 use crate::renderer::render_object::RenderObjectData;
-use crate::renderer::material_instance::MaterialInstanceData;
+use crate::renderer::material_instance::{ MaterialBlendMode, MaterialInstanceData, MaterialParameterOverrides };
 use crate::vulkan_context::geometry_buffer::GeometryData;
 use crate::utilities::system::RcRefCell;
 
+pub const INSTANCE_MATRIX_BUFFER_NAME: &str = "InstanceMatrixBuffer"; // per-frame storage buffer of the project, must match with render_object_common.glsl
+pub const INSTANCE_OBJECT_ID_BUFFER_NAME: &str = "InstanceObjectIdBuffer"; // the object ids of the instance matrices, written to the ObjectId render target
+// selection outline: the mask pipeline writes the reference with DepthStencilStateCreateInfo::create_stencil_write_state,
+// the outline pipeline draws the extruded mesh with create_stencil_test_state(NOT_EQUAL). both need the dynamic STENCIL_REFERENCE.
+pub const SELECTION_OUTLINE_MASK_PIPELINE_NAME: &str = "render_selection_outline/mask";
+pub const SELECTION_OUTLINE_PIPELINE_NAME: &str = "render_selection_outline/outline";
+pub const SELECTION_OUTLINE_STENCIL_REFERENCE: u32 = 1;
+pub const SELECTION_OUTLINE_STENCIL_MASK: u32 = 0x01;
+
+// must match with render_object_common.glsl, the model matrix is instance_matrices[instance_offset + gl_InstanceIndex],
+// or instance_matrices[visible_instances[instance_offset + gl_InstanceIndex]] with _use_visible_instances, see occlusion_culling::VISIBLE_INSTANCE_BUFFER_NAME
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Default)]
+#[repr(C)]
+pub struct PushConstant_RenderInstanced {
+    pub _instance_offset: u32,
+    pub _use_visible_instances: u32,
+    pub _debug_view_mode: u32, // debug_view::DebugViewMode, read by the shaders compiled with DEBUG_VIEW_VISUALIZE
+    pub _reserved2: u32,
+}
+
+crate::impl_push_constant!(PushConstant_RenderInstanced, 16);
+
 #[derive(Clone, Debug)]
 pub struct RenderElementData {
     pub _render_object: RcRefCell<RenderObjectData>,
     pub _geometry_data: RcRefCell<GeometryData>,
     pub _material_instance_data: RcRefCell<MaterialInstanceData>,
-}
\ No newline at end of file
+    pub _object_id: u32, // the push constant of the single instance path, see render_object_storage::INVALID_OBJECT_ID
+    pub _coverage: f32, // the screen coverage of the visibility stats capture, 0.0 otherwise
+    // gpu_skinning::INVALID_SKINNING_OFFSET until RendererData::apply_gpu_skinning, the matrices of BONE_MATRIX_BUFFER_NAME
+    pub _bone_matrix_offset: u32,
+    pub _prev_bone_matrix_offset: u32,
+    pub _skinned_vertex_offset: u32, // the vertex of SKINNED_VERTEX_BUFFER_NAME, drawn by RendererData::draw_render_element
+    pub _material_parameter_overrides: Option<MaterialParameterOverrides>, // of the render object, the sorting still uses _material_instance_data
+}
+
+// the static render elements sharing the geometry and the material instance, drawn with a single cmd_draw_indexed.
+// _instance_offset: the first matrix of the group in the instance matrix buffer, filled by render_solid_instanced.
+// _draw_command_index: the indirect command of RendererData::dispatch_occlusion_culling, occlusion_culling::INVALID_DRAW_COMMAND_INDEX on the cpu path.
+#[derive(Clone, Debug)]
+pub struct RenderElementGroupData {
+    pub _geometry_data: RcRefCell<GeometryData>,
+    pub _material_instance_data: RcRefCell<MaterialInstanceData>,
+    pub _world_matrices: Vec<Matrix4<f32>>,
+    pub _object_ids: Vec<u32>, // parallel to _world_matrices
+    pub _coverages: Vec<f32>, // parallel to _world_matrices during the visibility stats capture, empty otherwise
+    pub _bound_spheres: Vec<Vector4<f32>>, // parallel to _world_matrices, xyz: world center, w: radius
+    pub _material_parameter_overrides: Vec<MaterialParameterOverrides>, // parallel to _world_matrices, the default for the objects without the overrides
+    pub _instance_offset: u32,
+    pub _draw_command_index: u32,
+}
+
+impl RenderElementGroupData {
+    pub fn get_instance_count(&self) -> u32 {
+        self._world_matrices.len() as u32
+    }
+}
+
+// the draw order of the translucent passes: the sorted blend modes back to front,
+// then the additive and the multiply elements in the submitted order, they don't need the sorting.
+// blend_modes: the blend mode and the squared view distance per render element
+pub fn get_translucent_draw_order(blend_modes: &[(MaterialBlendMode, f32)]) -> Vec<usize> {
+    let mut sort_keys: Vec<(bool, f32, usize)> = blend_modes.iter().enumerate().map(|(index, (blend_mode, distance))| {
+        let requires_sorting = blend_mode.requires_sorting();
+        (requires_sorting, if requires_sorting { *distance } else { 0.0 }, index)
+    }).collect();
+    sort_keys.sort_by(|a, b| {
+        match (a.0, b.0) {
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+            (true, true) => b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal).then(a.2.cmp(&b.2)),
+            (false, false) => a.2.cmp(&b.2),
+        }
+    });
+    sort_keys.iter().map(|sort_key| sort_key.2).collect()
+}
+
+pub fn sort_translucent_render_elements(render_elements: &mut Vec<RenderElementData>, view_position: &Vector3<f32>) {
+    let blend_modes: Vec<(MaterialBlendMode, f32)> = render_elements.iter().map(|render_element| {
+        let blend_mode = render_element._material_instance_data.borrow().get_blend_mode();
+        let distance = if blend_mode.requires_sorting() {
+            (render_element._render_object.borrow()._bound_box._center - view_position).norm_squared()
+        } else {
+            0.0
+        };
+        (blend_mode, distance)
+    }).collect();
+    let sorted_render_elements: Vec<RenderElementData> = get_translucent_draw_order(&blend_modes).iter().map(|index| render_elements[*index].clone()).collect();
+    *render_elements = sorted_render_elements;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ash::vk;
+    use crate::vulkan_context::vulkan_context;
+
+    // a quad of the test scene, the squared view distance and the linear rgba output of the fragment shader
+    struct TestQuad {
+        _blend_mode: MaterialBlendMode,
+        _distance: f32,
+        _color: [f32; 4],
+        _pixels: std::ops::Range<usize>,
+    }
+
+    fn get_blend_factor(blend_factor: vk::BlendFactor, src: &[f32; 4], dst: &[f32; 4], channel: usize) -> f32 {
+        match blend_factor {
+            vk::BlendFactor::ZERO => 0.0,
+            vk::BlendFactor::ONE => 1.0,
+            vk::BlendFactor::SRC_ALPHA => src[3],
+            vk::BlendFactor::ONE_MINUS_SRC_ALPHA => 1.0 - src[3],
+            vk::BlendFactor::DST_COLOR => dst[channel],
+            _ => panic!("not implemented blend factor {:?}", blend_factor),
+        }
+    }
+
+    // the fixed function blending of the color attachment, see vulkan_context::get_color_blend_mode
+    fn blend_pixel(color_blend_state: &vk::PipelineColorBlendAttachmentState, src: &[f32; 4], dst: &mut [f32; 4]) {
+        assert_eq!(vk::TRUE, color_blend_state.blend_enable);
+        let prev_dst = *dst;
+        for channel in 0..4 {
+            let (color_write, src_factor, dst_factor, blend_op) = if channel < 3 {
+                let color_write = [vk::ColorComponentFlags::R, vk::ColorComponentFlags::G, vk::ColorComponentFlags::B][channel];
+                (color_blend_state.color_write_mask.contains(color_write), color_blend_state.src_color_blend_factor, color_blend_state.dst_color_blend_factor, color_blend_state.color_blend_op)
+            } else {
+                (color_blend_state.color_write_mask.contains(vk::ColorComponentFlags::A), color_blend_state.src_alpha_blend_factor, color_blend_state.dst_alpha_blend_factor, color_blend_state.alpha_blend_op)
+            };
+            assert_eq!(vk::BlendOp::ADD, blend_op);
+            if color_write {
+                dst[channel] = src[channel] * get_blend_factor(src_factor, src, &prev_dst, channel) + prev_dst[channel] * get_blend_factor(dst_factor, src, &prev_dst, channel);
+            }
+        }
+    }
+
+    // renders the quads over a gray background in the translucent draw order
+    fn render_test_scene(quads: &[TestQuad]) -> Vec<[f32; 4]> {
+        let mut image: Vec<[f32; 4]> = vec![[0.5, 0.5, 0.5, 1.0]; 4];
+        let blend_modes: Vec<(MaterialBlendMode, f32)> = quads.iter().map(|quad| (quad._blend_mode, quad._distance)).collect();
+        for quad_index in get_translucent_draw_order(&blend_modes) {
+            let quad = &quads[quad_index];
+            let color_blend_state = vulkan_context::get_color_blend_mode(quad._blend_mode.get_blend_mode());
+            for pixel in image[quad._pixels.clone()].iter_mut() {
+                blend_pixel(&color_blend_state, &quad._color, pixel);
+            }
+        }
+        image
+    }
+
+    fn assert_image_eq(golden_image: &[[f32; 3]], image: &[[f32; 4]]) {
+        assert_eq!(golden_image.len(), image.len());
+        for (pixel_index, (golden_pixel, pixel)) in golden_image.iter().zip(image.iter()).enumerate() {
+            for channel in 0..3 {
+                assert!((golden_pixel[channel] - pixel[channel]).abs() < 1e-5, "pixel {}: {:?} != {:?}", pixel_index, golden_pixel, pixel);
+            }
+        }
+    }
+
+    fn create_test_quads() -> Vec<TestQuad> {
+        vec![
+            TestQuad { _blend_mode: MaterialBlendMode::Additive, _distance: 1.0, _color: [0.25, 0.0, 0.0, 1.0], _pixels: 1..4 },
+            TestQuad { _blend_mode: MaterialBlendMode::AlphaBlend, _distance: 1.0, _color: [0.0, 0.0, 1.0, 0.5], _pixels: 2..4 }, // near
+            TestQuad { _blend_mode: MaterialBlendMode::AlphaBlend, _distance: 9.0, _color: [0.0, 1.0, 0.0, 0.5], _pixels: 0..3 }, // far
+            TestQuad { _blend_mode: MaterialBlendMode::Additive, _distance: 4.0, _color: [0.0, 0.125, 0.0, 1.0], _pixels: 3..4 },
+        ]
+    }
+
+    #[test]
+    fn test_translucent_draw_order() {
+        let blend_modes: Vec<(MaterialBlendMode, f32)> = create_test_quads().iter().map(|quad| (quad._blend_mode, quad._distance)).collect();
+        // back to front, then the additive quads in the submitted order
+        assert_eq!(vec![2, 1, 0, 3], get_translucent_draw_order(&blend_modes));
+        let blend_modes = vec![
+            (MaterialBlendMode::Multiply, 1.0),
+            (MaterialBlendMode::PremultipliedAlpha, 2.0),
+            (MaterialBlendMode::AlphaBlend, 2.0),
+            (MaterialBlendMode::Additive, 100.0),
+            (MaterialBlendMode::AlphaBlend, 3.0),
+        ];
+        assert_eq!(vec![4, 1, 2, 0, 3], get_translucent_draw_order(&blend_modes));
+        assert!(get_translucent_draw_order(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_overlapping_quads_golden_image() {
+        // far green over gray, then near blue, then the additive red and green on top
+        let golden_image: [[f32; 3]; 4] = [
+            [0.25, 0.75, 0.25],
+            [0.5, 0.75, 0.25],
+            [0.375, 0.375, 0.625],
+            [0.5, 0.375, 0.75],
+        ];
+        let mut quads = create_test_quads();
+        assert_image_eq(&golden_image, &render_test_scene(&quads));
+
+        // the submission order doesn't change the image
+        quads.reverse();
+        assert_image_eq(&golden_image, &render_test_scene(&quads));
+        quads.swap(0, 2);
+        assert_image_eq(&golden_image, &render_test_scene(&quads));
+    }
+
+    #[test]
+    fn test_premultiplied_alpha_and_multiply() {
+        // the premultiplied color is the alpha blended color
+        let alpha_blend_image = render_test_scene(&[TestQuad { _blend_mode: MaterialBlendMode::AlphaBlend, _distance: 1.0, _color: [1.0, 0.5, 0.0, 0.25], _pixels: 0..4 }]);
+        let premultiplied_image = render_test_scene(&[TestQuad { _blend_mode: MaterialBlendMode::PremultipliedAlpha, _distance: 1.0, _color: [0.25, 0.125, 0.0, 0.25], _pixels: 0..4 }]);
+        assert_image_eq(&[[0.625, 0.5, 0.375]; 4], &alpha_blend_image);
+        assert_image_eq(&[[0.625, 0.5, 0.375]; 4], &premultiplied_image);
+
+        let multiply_image = render_test_scene(&[
+            TestQuad { _blend_mode: MaterialBlendMode::Multiply, _distance: 1.0, _color: [0.5, 1.0, 0.0, 1.0], _pixels: 0..2 },
+            TestQuad { _blend_mode: MaterialBlendMode::Multiply, _distance: 2.0, _color: [0.5, 0.5, 0.5, 1.0], _pixels: 1..3 },
+        ]);
+        assert_image_eq(&[[0.25, 0.5, 0.0], [0.125, 0.25, 0.0], [0.25, 0.25, 0.25], [0.5, 0.5, 0.5]], &multiply_image);
+        // the color attachment keeps its alpha
+        assert!(multiply_image.iter().all(|pixel| 1.0 == pixel[3]));
+    }
+}
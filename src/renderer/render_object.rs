@@ -1,5 +1,6 @@
 use nalgebra::{
     Vector3,
+    Vector4,
     Matrix4,
 };
 use serde::{ Serialize, Deserialize };
@@ -7,6 +8,9 @@ use serde::{ Serialize, Deserialize };
 use crate::renderer::mesh::MeshData;
 use crate::renderer::model::ModelData;
 use crate::renderer::animation::AnimationData;
+use crate::renderer::animation_state_machine::{ AnimStateMachine, AnimStateMachineData };
+use crate::renderer::material_instance::{ MaterialInstanceData, MaterialParameterOverrides };
+use crate::renderer::shader_hook::ShaderHookParameters;
 use crate::renderer::transform_object::TransformObjectData;
 use crate::utilities::system::RcRefCell;
 use crate::utilities::bounding_box::BoundingBox;
@@ -37,8 +41,13 @@ pub struct RenderObjectData {
     pub _model_data: RcRefCell<ModelData>,
     pub _bound_box: BoundingBox,
     pub _geometry_bound_boxes: Vec<BoundingBox>,
+    pub _animated_bound_box: Option<BoundingBox>, // local, the pose of the animation buffers. None: the bind pose bound of the mesh
+    pub _is_bound_box_updated: bool, // by the transform or the animation in the last update_render_object_data
     pub _transform_object: TransformObjectData,
     pub _animation_play_info: Option<AnimationPlayInfo>,
+    pub _anim_state_machine: Option<AnimStateMachine>, // drives the animation play info with set_param from gameplay
+    pub _shader_hook_parameters: Option<ShaderHookParameters>, // None uses the parameters of the material instance
+    pub _material_parameter_overrides: Option<MaterialParameterOverrides>, // None renders the material instance as is
 }
 
 #[derive(Clone, Debug)]
@@ -135,21 +144,35 @@ impl RenderObjectData {
         let mesh_data = model_data.borrow()._mesh_data.clone();
         let bound_box = mesh_data.borrow()._bound_box.clone();
         let has_animation_data = mesh_data.borrow().has_animation_data();
-        let geometry_bound_boxes = mesh_data.borrow()._geometry_datas.iter().map(|geometry_data| geometry_data.borrow()._geometry_bounding_box.clone()).collect();
+        let geometry_bound_boxes = RenderObjectData::get_geometry_bound_boxes(&mesh_data.borrow());
         let mut render_object_data = RenderObjectData {
             _render_object_name: render_object_name.clone(),
             _model_data: model_data.clone(),
             _mesh_data: mesh_data,
             _bound_box: bound_box,
             _geometry_bound_boxes: geometry_bound_boxes,
+            _animated_bound_box: None,
+            _is_bound_box_updated: false,
             _transform_object: transform_object_data,
             _animation_play_info: None,
+            _anim_state_machine: None,
+            _shader_hook_parameters: None,
+            _material_parameter_overrides: None,
         };
 
         render_object_data.initialize_animation_play_info(has_animation_data);
         render_object_data
     }
 
+    fn get_geometry_bound_boxes(mesh_data: &MeshData) -> Vec<BoundingBox> {
+        mesh_data._geometry_datas.iter().map(|geometry_data| geometry_data.borrow()._geometry_bounding_box.clone()).collect()
+    }
+
+    // the mesh of the previous scene is unloaded until the render object acquires it, see Resources::acquire_mesh_data
+    pub fn update_geometry_bound_boxes(&mut self) {
+        self._geometry_bound_boxes = RenderObjectData::get_geometry_bound_boxes(&self._mesh_data.borrow());
+    }
+
     pub fn initialize_animation_play_info(&mut self, has_animation_data: bool) {
         if has_animation_data {
             let mut animation_play_info = AnimationPlayInfo::default();
@@ -162,6 +185,7 @@ impl RenderObjectData {
             }
             animation_play_info._animation_mesh = Some(self._mesh_data.clone());
             self._animation_play_info = Some(animation_play_info);
+            self.update_animated_bound_box();
         }
     }
 
@@ -177,6 +201,37 @@ impl RenderObjectData {
         &self._transform_object
     }
 
+    pub fn get_shader_hook_parameters(&self, material_instance_data: &MaterialInstanceData) -> ShaderHookParameters {
+        match self._shader_hook_parameters {
+            Some(shader_hook_parameters) => shader_hook_parameters,
+            None => material_instance_data._shader_hook_parameters,
+        }
+    }
+
+    // ex) animate the dissolve threshold from gameplay: set_shader_hook_parameter(0, &Vector4::new(threshold, 0.0, 0.0, 0.0), ...)
+    pub fn set_shader_hook_parameter(&mut self, index: usize, value: &Vector4<f32>, material_instance_data: &MaterialInstanceData) {
+        let mut shader_hook_parameters = self.get_shader_hook_parameters(material_instance_data);
+        shader_hook_parameters[index] = value.clone();
+        self._shader_hook_parameters = Some(shader_hook_parameters);
+    }
+
+    pub fn reset_shader_hook_parameters(&mut self) {
+        self._shader_hook_parameters = None;
+    }
+
+    // ex) tint one crate: set_material_parameter_overrides(&MaterialParameterOverrides { _base_color_factor: Vector4::new(1.0, 0.0, 0.0, 1.0), ..Default::default() })
+    pub fn set_material_parameter_overrides(&mut self, material_parameter_overrides: &MaterialParameterOverrides) {
+        self._material_parameter_overrides = Some(material_parameter_overrides.clone());
+    }
+
+    pub fn reset_material_parameter_overrides(&mut self) {
+        self._material_parameter_overrides = None;
+    }
+
+    pub fn get_material_parameter_overrides(&self) -> MaterialParameterOverrides {
+        self._material_parameter_overrides.unwrap_or_default()
+    }
+
     pub fn has_animation_play_info(&self) -> bool {
         self._animation_play_info.is_some()
     }
@@ -191,6 +246,25 @@ impl RenderObjectData {
         }
     }
 
+    pub fn set_anim_state_machine(&mut self, state_machine_data: Option<&RcRefCell<AnimStateMachineData>>) {
+        self._anim_state_machine = match state_machine_data {
+            Some(state_machine_data) if self.has_animation_play_info() => Some(AnimStateMachine::create_anim_state_machine(state_machine_data)),
+            Some(state_machine_data) => {
+                log::warn!("set_anim_state_machine: {} has no animation, {}", self._render_object_name, state_machine_data.borrow()._name);
+                None
+            },
+            None => None,
+        };
+    }
+
+    pub fn get_anim_state_machine(&self) -> Option<&AnimStateMachine> {
+        self._anim_state_machine.as_ref()
+    }
+
+    pub fn get_anim_state_machine_mut(&mut self) -> Option<&mut AnimStateMachine> {
+        self._anim_state_machine.as_mut()
+    }
+
     pub fn get_prev_animation_buffer(&self, index: usize) -> &Vec<Matrix4<f32>>{
         &self._animation_play_info.as_ref().unwrap()._prev_animation_buffers[index]
     }
@@ -199,18 +273,72 @@ impl RenderObjectData {
         &self._animation_play_info.as_ref().unwrap()._animation_buffers[index]
     }
 
+    // the bone of the first skeleton, None for a static mesh or an unknown bone name
+    pub fn find_bone_index(&self, bone_name: &str) -> Option<usize> {
+        self._mesh_data.borrow()._skeleton_datas.first().and_then(|skeleton_data| {
+            skeleton_data._bone_names.iter().position(|name| name == bone_name)
+        })
+    }
+
+    // the model matrix * the animation matrix of the bone, which moves a point of the bind pose of the mesh with the bone.
+    // the model matrix only without the animation or the bone.
+    pub fn get_bone_world_matrix(&self, bone_index: Option<usize>) -> Matrix4<f32> {
+        let transform_matrix = self._transform_object.get_matrix();
+        match (bone_index, self._animation_play_info.as_ref()) {
+            (Some(bone_index), Some(animation_play_info)) if animation_play_info._animation_buffers.first().map_or(false, |animation_buffer| bone_index < animation_buffer.len()) => {
+                transform_matrix * &animation_play_info._animation_buffers[0][bone_index]
+            },
+            _ => transform_matrix.clone(),
+        }
+    }
+
     pub fn update_bound_box(&mut self) {
         let transform_matrix = self._transform_object.get_matrix();
-        self._bound_box.update_with_matrix(&self._mesh_data.borrow()._bound_box, transform_matrix);
-        for (i, geometry_data) in self._mesh_data.borrow()._geometry_datas.iter().enumerate() {
-            self._geometry_bound_boxes.get_mut(i).unwrap().update_with_matrix(&geometry_data.borrow()._geometry_bounding_box, transform_matrix);
+        match self._animated_bound_box.as_ref() {
+            Some(animated_bound_box) => {
+                // the geometries share the animated bound, the bones move across them
+                self._bound_box = animated_bound_box.get_transformed_bound_box(transform_matrix);
+                for geometry_bound_box in self._geometry_bound_boxes.iter_mut() {
+                    *geometry_bound_box = self._bound_box.clone();
+                }
+            },
+            None => {
+                self._bound_box.update_with_matrix(&self._mesh_data.borrow()._bound_box, transform_matrix);
+                for (i, geometry_data) in self._mesh_data.borrow()._geometry_datas.iter().enumerate() {
+                    self._geometry_bound_boxes.get_mut(i).unwrap().update_with_matrix(&geometry_data.borrow()._geometry_bounding_box, transform_matrix);
+                }
+            }
         }
     }
 
-    pub fn update_render_object_data(&mut self, delta_time: f32) {
+    // the bone bounds of the mesh by the first animation buffer, the culling, the shadow casters and the picking use the result.
+    pub fn update_animated_bound_box(&mut self) {
+        let animated_bound_box = match self._animation_play_info.as_ref() {
+            Some(animation_play_info) if false == animation_play_info._animation_buffers.is_empty() => {
+                let mesh_data = self._mesh_data.borrow();
+                if mesh_data._bone_bound_boxes.is_empty() {
+                    None
+                } else {
+                    Some(mesh_data.get_animated_bound_box(&animation_play_info._animation_buffers[0]))
+                }
+            },
+            _ => None,
+        };
+        self._animated_bound_box = animated_bound_box;
+    }
+
+    // returns true when the transform has been updated
+    pub fn update_render_object_data(&mut self, delta_time: f32) -> bool {
         let updated_transform = self._transform_object.update_transform_object();
-        if updated_transform {
-            self.update_bound_box();
+        let mut updated_animation = false;
+
+        // the state machine changes the clip before the animation update
+        let anim_state_machine_motion = match self._anim_state_machine.as_mut() {
+            Some(anim_state_machine) => anim_state_machine.update_anim_state_machine(delta_time),
+            None => None,
+        };
+        if let Some(anim_state_machine_motion) = anim_state_machine_motion {
+            self.set_animation(&anim_state_machine_motion._animation_mesh, &anim_state_machine_motion._animation_args);
         }
 
         // update animation
@@ -257,6 +385,7 @@ impl RenderObjectData {
 
                 // update animation buffers
                 if animation_play_info._last_animation_frame != animation_play_info._animation_frame {
+                    updated_animation = true;
                     animation_play_info._last_animation_frame = animation_play_info._animation_frame;
                     animation.update_animation_transforms(animation_play_info._animation_frame, &mut animation_play_info._animation_buffers[i]);
 
@@ -269,5 +398,14 @@ impl RenderObjectData {
                 }
             }
         }
+
+        if updated_animation {
+            self.update_animated_bound_box();
+        }
+        self._is_bound_box_updated = updated_transform || updated_animation;
+        if self._is_bound_box_updated {
+            self.update_bound_box();
+        }
+        updated_transform
     }
 }
\ No newline at end of file
@@ -0,0 +1,458 @@
+use nalgebra::{ Vector3, Vector4, Matrix4 };
+
+use crate::renderer::impostor;
+use crate::renderer::mesh;
+use crate::renderer::render_object::RenderObjectData;
+use crate::utilities::bounding_box::BoundingBox;
+use crate::utilities::system::RcRefCell;
+use crate::utilities::telemetry::{ self, ResourceClass };
+
+pub const LAYER_MASK_ALL: u32 = std::u32::MAX;
+pub const LAYER_MASK_DEFAULT: u32 = 1;
+// the object id of the object id render target: the index + 1 in the low 24 bits and the low 8 bits of the generation,
+// so the id read back a frame later doesn't resolve to a new object of the same slot. 0 is the background.
+pub const INVALID_OBJECT_ID: u32 = 0;
+const OBJECT_ID_INDEX_BITS: u32 = 24;
+const OBJECT_ID_INDEX_MASK: u32 = (1 << OBJECT_ID_INDEX_BITS) - 1;
+
+// the generation is bumped when the slot is freed, so the stale handles of the removed objects never match the new objects.
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Hash)]
+pub struct ObjectHandle {
+    pub _index: u32,
+    pub _generation: u32,
+}
+
+impl ObjectHandle {
+    pub fn get_object_id(&self) -> u32 {
+        ((self._generation & 0xff) << OBJECT_ID_INDEX_BITS) | ((self._index + 1) & OBJECT_ID_INDEX_MASK)
+    }
+}
+
+// hot data in parallel arrays indexed by ObjectHandle::_index, the culling, sorting and the push constant gather iterate them.
+// cold data(name, mesh, material, animation state) stays in RenderObjectData.
+// the free slots keep their old values with false in _alives, so the arrays never shift and the handles stay valid.
+#[derive(Default)]
+pub struct RenderObjectStorage {
+    pub _world_matrices: Vec<Matrix4<f32>>,
+    pub _prev_world_matrices: Vec<Matrix4<f32>>,
+    pub _bound_boxes: Vec<BoundingBox>,
+    pub _bound_spheres: Vec<Vector4<f32>>, // center, radius of the bound boxes, for the culling
+    pub _layer_masks: Vec<u32>,
+    pub _impostor_distances: Vec<f32>, // 0.0: the model has no impostor
+    pub _impostor_lods: Vec<bool>, // the impostor is selected, kept between the frames for the hysteresis
+    pub _mesh_lod_counts: Vec<u32>, // 1: the mesh has no lod
+    pub _mesh_lod_indices: Vec<u32>, // the selected lod of the mesh, kept between the frames for the hysteresis
+    pub _dirty_flags: Vec<bool>, // the transform has been changed by any simulation step of this frame, see clear_dirty_flags
+    pub _alives: Vec<bool>,
+    pub _generations: Vec<u32>,
+    pub _render_objects: Vec<Option<RcRefCell<RenderObjectData>>>,
+    pub _free_indices: Vec<u32>,
+    pub _alive_count: usize,
+}
+
+// xyz: normal toward the inside, w: distance, from the rows of the view projection(Gribb-Hartmann)
+pub fn get_frustum_planes(view_projection: &Matrix4<f32>) -> [Vector4<f32>; 6] {
+    let row = |i: usize| Vector4::new(view_projection[(i, 0)], view_projection[(i, 1)], view_projection[(i, 2)], view_projection[(i, 3)]);
+    let (row0, row1, row2, row3) = (row(0), row(1), row(2), row(3));
+    // the far plane of the infinite projection has no normal, it is always passed.
+    let normalize_plane = |plane: Vector4<f32>| {
+        let normal_length = Vector3::new(plane.x, plane.y, plane.z).norm();
+        if normal_length <= std::f32::EPSILON { Vector4::new(0.0, 0.0, 0.0, 1.0) } else { plane / normal_length }
+    };
+    [
+        normalize_plane(&row3 + &row0), // left
+        normalize_plane(&row3 - &row0), // right
+        normalize_plane(&row3 + &row1), // bottom
+        normalize_plane(&row3 - &row1), // top
+        normalize_plane(row2.clone()), // near, depth range 0 ~ 1. the far plane with reversed-z
+        normalize_plane(&row3 - &row2), // far. the near plane with reversed-z
+    ]
+}
+
+pub fn is_sphere_in_frustum(frustum_planes: &[Vector4<f32>; 6], bound_sphere: &Vector4<f32>) -> bool {
+    frustum_planes.iter().all(|plane| {
+        -bound_sphere.w <= plane.x * bound_sphere.x + plane.y * bound_sphere.y + plane.z * bound_sphere.z + plane.w
+    })
+}
+
+// the corner of the box farthest along the plane normal is tested, ex) the chunks of the terrain
+pub fn is_box_in_frustum(frustum_planes: &[Vector4<f32>; 6], bound_box: &BoundingBox) -> bool {
+    frustum_planes.iter().all(|plane| {
+        let x = if 0.0 <= plane.x { bound_box._max.x } else { bound_box._min.x };
+        let y = if 0.0 <= plane.y { bound_box._max.y } else { bound_box._min.y };
+        let z = if 0.0 <= plane.z { bound_box._max.z } else { bound_box._min.z };
+        0.0 <= plane.x * x + plane.y * y + plane.z * z + plane.w
+    })
+}
+
+fn get_bound_sphere(bound_box: &BoundingBox) -> Vector4<f32> {
+    Vector4::new(bound_box._center.x, bound_box._center.y, bound_box._center.z, bound_box._radius)
+}
+
+impl RenderObjectStorage {
+    pub fn get_alive_count(&self) -> usize { self._alive_count }
+    pub fn get_capacity(&self) -> usize { self._alives.len() }
+
+    pub fn is_valid_handle(&self, object_handle: &ObjectHandle) -> bool {
+        let index = object_handle._index as usize;
+        index < self._alives.len() && self._alives[index] && object_handle._generation == self._generations[index]
+    }
+
+    pub fn add_render_object(&mut self, render_object_data: &RcRefCell<RenderObjectData>, layer_mask: u32) -> ObjectHandle {
+        let (world_matrix, bound_box, impostor_distance, mesh_lod_count) = {
+            let mut render_object_data = render_object_data.borrow_mut();
+            // no interpolation from the identity before the first simulation step
+            render_object_data._transform_object.store_step_matrix();
+            let impostor_distance = render_object_data._model_data.borrow().get_impostor_distance();
+            let mesh_lod_count = render_object_data._mesh_data.borrow().get_lod_count() as u32;
+            (render_object_data._transform_object.get_matrix().clone(), render_object_data._bound_box.clone(), impostor_distance, mesh_lod_count)
+        };
+        let bound_sphere = get_bound_sphere(&bound_box);
+        self._alive_count += 1;
+        telemetry::record_created(ResourceClass::RenderObject, 1);
+        match self._free_indices.pop() {
+            Some(index) => {
+                let i = index as usize;
+                self._prev_world_matrices[i] = world_matrix.clone();
+                self._world_matrices[i] = world_matrix;
+                self._bound_boxes[i] = bound_box;
+                self._bound_spheres[i] = bound_sphere;
+                self._layer_masks[i] = layer_mask;
+                self._impostor_distances[i] = impostor_distance;
+                self._impostor_lods[i] = false;
+                self._mesh_lod_counts[i] = mesh_lod_count;
+                self._mesh_lod_indices[i] = 0;
+                self._dirty_flags[i] = true;
+                self._alives[i] = true;
+                self._render_objects[i] = Some(render_object_data.clone());
+                ObjectHandle { _index: index, _generation: self._generations[i] }
+            },
+            None => {
+                let index = self._alives.len() as u32;
+                self._prev_world_matrices.push(world_matrix.clone());
+                self._world_matrices.push(world_matrix);
+                self._bound_boxes.push(bound_box);
+                self._bound_spheres.push(bound_sphere);
+                self._layer_masks.push(layer_mask);
+                self._impostor_distances.push(impostor_distance);
+                self._impostor_lods.push(false);
+                self._mesh_lod_counts.push(mesh_lod_count);
+                self._mesh_lod_indices.push(0);
+                self._dirty_flags.push(true);
+                self._alives.push(true);
+                self._generations.push(0);
+                self._render_objects.push(Some(render_object_data.clone()));
+                ObjectHandle { _index: index, _generation: 0 }
+            }
+        }
+    }
+
+    pub fn remove_render_object(&mut self, object_handle: &ObjectHandle) -> Option<RcRefCell<RenderObjectData>> {
+        if false == self.is_valid_handle(object_handle) {
+            return None;
+        }
+        let i = object_handle._index as usize;
+        self._alives[i] = false;
+        self._dirty_flags[i] = false;
+        self._generations[i] = self._generations[i].wrapping_add(1);
+        self._free_indices.push(object_handle._index);
+        self._alive_count -= 1;
+        telemetry::record_destroyed(ResourceClass::RenderObject, 1);
+        self._render_objects[i].take()
+    }
+
+    pub fn clear_render_objects(&mut self) {
+        telemetry::record_destroyed(ResourceClass::RenderObject, self._alive_count as u64);
+        *self = RenderObjectStorage::default();
+    }
+
+    pub fn get_render_object(&self, object_handle: &ObjectHandle) -> Option<&RcRefCell<RenderObjectData>> {
+        if self.is_valid_handle(object_handle) {
+            self._render_objects[object_handle._index as usize].as_ref()
+        } else {
+            None
+        }
+    }
+
+    pub fn get_world_matrix(&self, object_handle: &ObjectHandle) -> Option<&Matrix4<f32>> {
+        if self.is_valid_handle(object_handle) { Some(&self._world_matrices[object_handle._index as usize]) } else { None }
+    }
+
+    pub fn get_bound_box(&self, object_handle: &ObjectHandle) -> Option<&BoundingBox> {
+        if self.is_valid_handle(object_handle) { Some(&self._bound_boxes[object_handle._index as usize]) } else { None }
+    }
+
+    pub fn set_layer_mask(&mut self, object_handle: &ObjectHandle, layer_mask: u32) {
+        if self.is_valid_handle(object_handle) {
+            self._layer_masks[object_handle._index as usize] = layer_mask;
+        }
+    }
+
+    pub fn get_handles(&self) -> Vec<ObjectHandle> {
+        (0..self._alives.len()).filter(|i| self._alives[*i]).map(|i| ObjectHandle { _index: i as u32, _generation: self._generations[i] }).collect()
+    }
+
+    // a simulation step, the cold data is touched once per object for the transform and the animation.
+    // the world matrices are written by interpolate_world_matrices once per frame.
+    // the dirty flags are accumulated over the steps, a change of the first step is kept after the second step.
+    pub fn update_render_object_storage(&mut self, delta_time: f32) {
+        for i in 0..self._alives.len() {
+            if false == self._alives[i] {
+                continue;
+            }
+            let mut render_object_data = self._render_objects[i].as_ref().unwrap().borrow_mut();
+            render_object_data._transform_object.store_step_matrix();
+            if render_object_data.update_render_object_data(delta_time) {
+                self._dirty_flags[i] = true;
+            }
+            // the skeletal objects follow the animated pose without moving
+            if render_object_data._is_bound_box_updated {
+                self._bound_boxes[i] = render_object_data._bound_box.clone();
+                self._bound_spheres[i] = get_bound_sphere(&self._bound_boxes[i]);
+            }
+        }
+    }
+
+    // once per rendered frame after the consumers of the dirty flags, also with no simulation step in the frame
+    pub fn clear_dirty_flags(&mut self) {
+        for dirty_flag in self._dirty_flags.iter_mut() {
+            *dirty_flag = false;
+        }
+    }
+
+    // the render matrices between the last two simulation steps, alpha: TimeData::_fixed_step_alpha
+    pub fn interpolate_world_matrices(&mut self, alpha: f32) {
+        self._prev_world_matrices.copy_from_slice(&self._world_matrices);
+        for i in 0..self._alives.len() {
+            if self._alives[i] {
+                let render_object_data = self._render_objects[i].as_ref().unwrap().borrow();
+                self._world_matrices[i] = render_object_data._transform_object.get_interpolated_matrix(alpha);
+            }
+        }
+    }
+
+    // no motion between the frames and the simulation steps, after teleporting the objects
+    pub fn reset_prev_world_matrices(&mut self) {
+        for i in 0..self._alives.len() {
+            if self._alives[i] {
+                let mut render_object_data = self._render_objects[i].as_ref().unwrap().borrow_mut();
+                render_object_data._transform_object.store_step_matrix();
+                self._world_matrices[i].copy_from(render_object_data._transform_object.get_matrix());
+            }
+        }
+        self._prev_world_matrices.copy_from_slice(&self._world_matrices);
+    }
+
+    // indices of the visible objects, reuses the given vector to avoid the allocation per frame.
+    pub fn cull_render_objects(&self, view_projection: &Matrix4<f32>, layer_mask: u32, out_visible_indices: &mut Vec<u32>) {
+        let frustum_planes = get_frustum_planes(view_projection);
+        out_visible_indices.clear();
+        for (i, bound_sphere) in self._bound_spheres.iter().enumerate() {
+            if self._alives[i] && 0 != (self._layer_masks[i] & layer_mask) && is_sphere_in_frustum(&frustum_planes, bound_sphere) {
+                out_visible_indices.push(i as u32);
+            }
+        }
+    }
+
+    // indices of every alive object of the layers, the frustum and the occlusion are tested later on the gpu.
+    pub fn gather_render_objects(&self, layer_mask: u32, out_indices: &mut Vec<u32>) {
+        out_indices.clear();
+        for i in 0..self._bound_spheres.len() {
+            if self._alives[i] && 0 != (self._layer_masks[i] & layer_mask) {
+                out_indices.push(i as u32);
+            }
+        }
+    }
+
+    // front to back by the distance of the bound sphere centers
+    pub fn sort_render_objects(&self, view_position: &Vector3<f32>, visible_indices: &mut Vec<u32>) {
+        let get_distance = |index: u32| {
+            let bound_sphere = &self._bound_spheres[index as usize];
+            Vector3::new(bound_sphere.x - view_position.x, bound_sphere.y - view_position.y, bound_sphere.z - view_position.z).norm_squared()
+        };
+        visible_indices.sort_by(|a, b| get_distance(*a).partial_cmp(&get_distance(*b)).unwrap_or(std::cmp::Ordering::Equal));
+    }
+
+    // the world and the prev world matrices of the visible objects, in the order of the indices, for the push constants or the instance buffer.
+    pub fn gather_world_matrices(&self, visible_indices: &[u32], out_world_matrices: &mut Vec<Matrix4<f32>>, out_prev_world_matrices: &mut Vec<Matrix4<f32>>) {
+        out_world_matrices.clear();
+        out_prev_world_matrices.clear();
+        for index in visible_indices.iter() {
+            out_world_matrices.push(self._world_matrices[*index as usize]);
+            out_prev_world_matrices.push(self._prev_world_matrices[*index as usize]);
+        }
+    }
+
+    // the mesh lods of the objects by the distance of the bound sphere centers, the objects without the lods are skipped.
+    pub fn select_mesh_lods(&mut self, view_position: &Vector3<f32>, visible_indices: &[u32]) {
+        let distance_scale = mesh::get_mesh_lod_distance_scale();
+        let hysteresis = mesh::get_mesh_lod_hysteresis();
+        for index in visible_indices.iter() {
+            let i = *index as usize;
+            if self._mesh_lod_counts[i] <= 1 {
+                continue;
+            }
+            let bound_sphere = &self._bound_spheres[i];
+            let distance = Vector3::new(bound_sphere.x - view_position.x, bound_sphere.y - view_position.y, bound_sphere.z - view_position.z).norm();
+            let render_object_data = self._render_objects[i].as_ref().unwrap().borrow();
+            let lod_index = mesh::select_mesh_lod(self._mesh_lod_indices[i] as usize, distance, &render_object_data._mesh_data.borrow(), distance_scale, hysteresis);
+            self._mesh_lod_indices[i] = lod_index as u32;
+        }
+    }
+
+    // the mesh lod of the shadow casters, coarser by the lod bias
+    pub fn get_mesh_lod_index(&self, index: u32, lod_bias: usize) -> usize {
+        let i = index as usize;
+        (self._mesh_lod_indices[i] as usize + lod_bias).min(self._mesh_lod_counts[i].max(1) as usize - 1)
+    }
+
+    // the impostor is the level after the last mesh lod, the baked state is checked only when an object crosses the impostor distance.
+    pub fn select_impostor_lods(&mut self, view_position: &Vector3<f32>, visible_indices: &[u32], out_mesh_indices: &mut Vec<u32>, out_impostor_indices: &mut Vec<u32>) {
+        let distance_scale = impostor::get_impostor_distance_scale();
+        let hysteresis = impostor::get_impostor_lod_hysteresis();
+        out_mesh_indices.clear();
+        out_impostor_indices.clear();
+        for index in visible_indices.iter() {
+            let i = *index as usize;
+            let bound_sphere = &self._bound_spheres[i];
+            let distance = Vector3::new(bound_sphere.x - view_position.x, bound_sphere.y - view_position.y, bound_sphere.z - view_position.z).norm();
+            let mut is_impostor = impostor::select_impostor_lod(self._impostor_lods[i], distance, self._impostor_distances[i] * distance_scale, hysteresis);
+            if is_impostor && false == self._impostor_lods[i] {
+                is_impostor = self.get_render_object_by_index(*index).borrow()._model_data.borrow().has_impostor_data();
+            }
+            self._impostor_lods[i] = is_impostor;
+            if is_impostor {
+                out_impostor_indices.push(*index);
+            } else {
+                out_mesh_indices.push(*index);
+            }
+        }
+    }
+
+    pub fn get_object_id_by_index(&self, index: u32) -> u32 {
+        ObjectHandle { _index: index, _generation: self._generations[index as usize] }.get_object_id()
+    }
+
+    // None for the background and the removed objects
+    pub fn get_handle_by_object_id(&self, object_id: u32) -> Option<ObjectHandle> {
+        let index_plus_one = object_id & OBJECT_ID_INDEX_MASK;
+        if 0 == index_plus_one {
+            return None;
+        }
+        let index = (index_plus_one - 1) as usize;
+        if self._alives.len() <= index || false == self._alives[index] || (self._generations[index] & 0xff) != (object_id >> OBJECT_ID_INDEX_BITS) {
+            return None;
+        }
+        Some(ObjectHandle { _index: index as u32, _generation: self._generations[index] })
+    }
+
+    pub fn get_render_object_by_index(&self, index: u32) -> &RcRefCell<RenderObjectData> {
+        self._render_objects[index as usize].as_ref().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::mesh::{ MeshData, MeshDataCreateInfo };
+    use crate::renderer::model::ModelData;
+    use crate::renderer::render_object::RenderObjectCreateInfo;
+    use crate::utilities::system::newRcRefCell;
+
+    fn create_test_render_object(render_object_name: &str, position: Vector3<f32>) -> RcRefCell<RenderObjectData> {
+        let mesh_data = newRcRefCell(MeshData::create_mesh_data(&String::from("test_mesh"), MeshDataCreateInfo::default(), Vec::new()));
+        let model_data = newRcRefCell(ModelData::new_model_data(&String::from("test_model"), mesh_data, Vec::new()));
+        let mut render_object_data = RenderObjectData::create_render_object_data(&String::from(render_object_name), &model_data, &RenderObjectCreateInfo {
+            _position: position,
+            ..Default::default()
+        });
+        render_object_data._transform_object.update_transform_object();
+        newRcRefCell(render_object_data)
+    }
+
+    fn get_position(storage: &RenderObjectStorage, object_handle: &ObjectHandle) -> Vector3<f32> {
+        let world_matrix = storage.get_world_matrix(object_handle).unwrap();
+        Vector3::new(world_matrix[(0, 3)], world_matrix[(1, 3)], world_matrix[(2, 3)])
+    }
+
+    #[test]
+    fn test_insert_remove_index_stability() {
+        let mut storage = RenderObjectStorage::default();
+        let handles: Vec<ObjectHandle> = (0..4).map(|i| {
+            storage.add_render_object(&create_test_render_object(&format!("object_{}", i), Vector3::new(i as f32, 0.0, 0.0)), LAYER_MASK_DEFAULT)
+        }).collect();
+        assert_eq!(vec![0, 1, 2, 3], handles.iter().map(|handle| handle._index).collect::<Vec<u32>>());
+        assert_eq!(4, storage.get_alive_count());
+
+        // the removed slot is not filled by the last object, the others keep their indices and their data
+        let removed_render_object = storage.remove_render_object(&handles[1]).unwrap();
+        assert_eq!("object_1", removed_render_object.borrow()._render_object_name);
+        assert!(storage.remove_render_object(&handles[1]).is_none());
+        assert_eq!(3, storage.get_alive_count());
+        assert_eq!(4, storage.get_capacity());
+        for i in [0, 2, 3].iter() {
+            assert!(storage.is_valid_handle(&handles[*i]));
+            assert_eq!(Vector3::new(*i as f32, 0.0, 0.0), get_position(&storage, &handles[*i]));
+            assert_eq!(format!("object_{}", i), storage.get_render_object(&handles[*i]).unwrap().borrow()._render_object_name);
+        }
+        assert_eq!(vec![handles[0], handles[2], handles[3]], storage.get_handles());
+        let mut indices: Vec<u32> = Vec::new();
+        storage.gather_render_objects(LAYER_MASK_ALL, &mut indices);
+        assert_eq!(vec![0, 2, 3], indices);
+
+        // the free slot is reused with a new generation, the stale handle doesn't resolve to the new object
+        let new_handle = storage.add_render_object(&create_test_render_object("object_4", Vector3::new(4.0, 0.0, 0.0)), LAYER_MASK_DEFAULT);
+        assert_eq!(1, new_handle._index);
+        assert_eq!(handles[1]._generation + 1, new_handle._generation);
+        assert!(false == storage.is_valid_handle(&handles[1]));
+        assert!(storage.get_render_object(&handles[1]).is_none());
+        assert!(storage.get_world_matrix(&handles[1]).is_none());
+        assert_eq!(Vector3::new(4.0, 0.0, 0.0), get_position(&storage, &new_handle));
+        assert_eq!(4, storage.get_capacity());
+
+        // the object id of the stale handle, read back a frame later
+        assert_eq!(Some(new_handle), storage.get_handle_by_object_id(new_handle.get_object_id()));
+        assert_eq!(None, storage.get_handle_by_object_id(handles[1].get_object_id()));
+        assert_eq!(None, storage.get_handle_by_object_id(INVALID_OBJECT_ID));
+
+        // the layer masks of the slots
+        storage.set_layer_mask(&handles[2], 2);
+        storage.gather_render_objects(LAYER_MASK_DEFAULT, &mut indices);
+        assert_eq!(vec![0, 1, 3], indices);
+
+        storage.clear_render_objects();
+        assert_eq!(0, storage.get_alive_count());
+        assert!(false == storage.is_valid_handle(&handles[0]));
+    }
+
+    #[test]
+    fn test_dirty_flags_of_simulation_steps() {
+        let mut storage = RenderObjectStorage::default();
+        let render_object = create_test_render_object("object", Vector3::zeros());
+        let handle = storage.add_render_object(&render_object, LAYER_MASK_DEFAULT);
+        let i = handle._index as usize;
+        assert!(storage._dirty_flags[i]);
+        storage.clear_dirty_flags();
+
+        // moved by the first step, kept after the second step of the frame
+        render_object.borrow_mut()._transform_object.set_position(&Vector3::new(2.0, 0.0, 0.0));
+        storage.update_render_object_storage(0.1);
+        storage.update_render_object_storage(0.1);
+        assert!(storage._dirty_flags[i]);
+
+        // the interpolated matrix between the last two steps
+        storage.interpolate_world_matrices(0.5);
+        assert_eq!(Vector3::new(2.0, 0.0, 0.0), get_position(&storage, &handle));
+        render_object.borrow_mut()._transform_object.set_position(&Vector3::new(4.0, 0.0, 0.0));
+        storage.update_render_object_storage(0.1);
+        storage.interpolate_world_matrices(0.5);
+        assert_eq!(Vector3::new(3.0, 0.0, 0.0), get_position(&storage, &handle));
+        assert_eq!(2.0, storage._prev_world_matrices[i][(0, 3)]);
+
+        // no step in the frame
+        storage.clear_dirty_flags();
+        storage.interpolate_world_matrices(1.0);
+        assert!(false == storage._dirty_flags[i]);
+        assert_eq!(Vector3::new(4.0, 0.0, 0.0), get_position(&storage, &handle));
+    }
+}
@@ -0,0 +1,407 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use ash::vk;
+use ash::version::InstanceV1_0;
+use nalgebra::Vector2;
+use serde::{ Serialize, Deserialize };
+
+use crate::constants;
+use crate::renderer::renderer::RendererData;
+use crate::resource::resource::Resources;
+use crate::utilities::math;
+use crate::vulkan_context::texture::{ TextureCreateInfo, TextureData };
+use crate::vulkan_context::vulkan_context;
+
+// the render targets of the effects, defined without the engine changes. the targets of the engine core stay in the project renderer.
+// ex) resource/render_targets.json
+// [
+//     {
+//         "_name": "bloom_half",
+//         "_formats": ["B10G11R11_UFLOAT_PACK32", "R16G16B16A16_SFLOAT"],
+//         "_size": { "RenderRelative": { "_scale": 0.5 } },
+//         "_min_filter": "linear", "_wrap_mode": "clamp",
+//         "_clear_value": [0.0, 0.0, 0.0, 1.0]
+//     },
+//     { "_name": "bloom_quarter", "_formats": ["R16G16B16A16_SFLOAT"], "_size": { "TargetRelative": { "_target": "bloom_half", "_scale": 0.5 } } },
+//     { "_name": "outline_depth", "_formats": ["D24_UNORM_S8_UINT", "D32_SFLOAT_S8_UINT", "D32_SFLOAT"], "_stencil": true, "_clear_value": [1.0, 0.0] }
+// ]
+pub const RENDER_TARGETS_FILE_PATH: &str = "resource/render_targets.json";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum RenderTargetSize {
+    Absolute { _width: u32, _height: u32 },
+    SwapchainRelative { _scale: f32 },
+    RenderRelative { _scale: f32 }, // the scene resolution, RendererData::get_render_extent
+    TargetRelative { _target: String, _scale: f32 }, // another target of this file
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct RenderTargetDefinition {
+    pub _name: String,
+    pub _formats: Vec<String>, // the first supported format is used
+    pub _size: RenderTargetSize,
+    pub _layers: u32,
+    pub _enable_mipmap: bool,
+    pub _max_mip_levels: u32,
+    pub _view_type: String, // 2d, 2d_array, 3d, cube
+    pub _min_filter: String, // linear, nearest
+    pub _mag_filter: String,
+    pub _wrap_mode: String, // repeat, mirror, clamp, border
+    pub _clear_value: Vec<f32>, // color: [r, g, b, a], depth: [depth, stencil], for the render passes of the project
+    pub _stencil: bool, // depth: prefer the depth stencil formats of _formats, the depth only format without the stencil support
+}
+
+impl Default for RenderTargetDefinition {
+    fn default() -> RenderTargetDefinition {
+        RenderTargetDefinition {
+            _name: String::new(),
+            _formats: Vec::new(),
+            _size: RenderTargetSize::SwapchainRelative { _scale: 1.0 },
+            _layers: 1,
+            _enable_mipmap: false,
+            _max_mip_levels: constants::WHOLE_MIP_LEVELS,
+            _view_type: String::from("2d"),
+            _min_filter: String::from("linear"),
+            _mag_filter: String::from("linear"),
+            _wrap_mode: String::from("clamp"),
+            _clear_value: vec![0.0, 0.0, 0.0, 0.0],
+            _stencil: false,
+        }
+    }
+}
+
+pub fn get_format_from_name(format_name: &str) -> Option<vk::Format> {
+    match format_name {
+        "R8_UNORM" => Some(vk::Format::R8_UNORM),
+        "R8G8_UNORM" => Some(vk::Format::R8G8_UNORM),
+        "R8G8B8A8_UNORM" => Some(vk::Format::R8G8B8A8_UNORM),
+        "R8G8B8A8_SRGB" => Some(vk::Format::R8G8B8A8_SRGB),
+        "B8G8R8A8_UNORM" => Some(vk::Format::B8G8R8A8_UNORM),
+        "A2B10G10R10_UNORM_PACK32" => Some(vk::Format::A2B10G10R10_UNORM_PACK32),
+        "B10G11R11_UFLOAT_PACK32" => Some(vk::Format::B10G11R11_UFLOAT_PACK32),
+        "R16_SFLOAT" => Some(vk::Format::R16_SFLOAT),
+        "R16G16_SFLOAT" => Some(vk::Format::R16G16_SFLOAT),
+        "R16G16B16A16_SFLOAT" => Some(vk::Format::R16G16B16A16_SFLOAT),
+        "R32_SFLOAT" => Some(vk::Format::R32_SFLOAT),
+        "R32G32_SFLOAT" => Some(vk::Format::R32G32_SFLOAT),
+        "R32G32B32A32_SFLOAT" => Some(vk::Format::R32G32B32A32_SFLOAT),
+        "R32_UINT" => Some(vk::Format::R32_UINT),
+        "D16_UNORM" => Some(vk::Format::D16_UNORM),
+        "D32_SFLOAT" => Some(vk::Format::D32_SFLOAT),
+        "D24_UNORM_S8_UINT" => Some(vk::Format::D24_UNORM_S8_UINT),
+        "D32_SFLOAT_S8_UINT" => Some(vk::Format::D32_SFLOAT_S8_UINT),
+        _ => None,
+    }
+}
+
+fn get_view_type_from_name(view_type_name: &str) -> Option<vk::ImageViewType> {
+    match view_type_name {
+        "2d" => Some(vk::ImageViewType::TYPE_2D),
+        "2d_array" => Some(vk::ImageViewType::TYPE_2D_ARRAY),
+        "3d" => Some(vk::ImageViewType::TYPE_3D),
+        "cube" => Some(vk::ImageViewType::CUBE),
+        _ => None,
+    }
+}
+
+fn get_filter_from_name(filter_name: &str) -> Option<vk::Filter> {
+    match filter_name {
+        "linear" => Some(vk::Filter::LINEAR),
+        "nearest" => Some(vk::Filter::NEAREST),
+        _ => None,
+    }
+}
+
+fn get_wrap_mode_from_name(wrap_mode_name: &str) -> Option<vk::SamplerAddressMode> {
+    match wrap_mode_name {
+        "repeat" => Some(vk::SamplerAddressMode::REPEAT),
+        "mirror" => Some(vk::SamplerAddressMode::MIRRORED_REPEAT),
+        "clamp" => Some(vk::SamplerAddressMode::CLAMP_TO_EDGE),
+        "border" => Some(vk::SamplerAddressMode::CLAMP_TO_BORDER),
+        _ => None,
+    }
+}
+
+// every error names the offending entry, the invalid entries are skipped.
+pub fn validate_render_target_definitions(definitions: &[RenderTargetDefinition]) -> (Vec<RenderTargetDefinition>, Vec<String>) {
+    let mut errors: Vec<String> = Vec::new();
+    let mut valid_definitions: Vec<RenderTargetDefinition> = Vec::new();
+    let mut names: Vec<&str> = Vec::new();
+    for (index, definition) in definitions.iter().enumerate() {
+        let mut entry_errors: Vec<String> = Vec::new();
+        if definition._name.is_empty() {
+            entry_errors.push(String::from("empty name"));
+        } else if names.contains(&definition._name.as_str()) {
+            entry_errors.push(String::from("duplicated name"));
+        }
+        names.push(definition._name.as_str());
+        if definition._formats.is_empty() {
+            entry_errors.push(String::from("no formats"));
+        }
+        for format_name in definition._formats.iter() {
+            if get_format_from_name(format_name).is_none() {
+                entry_errors.push(format!("unknown format {}", format_name));
+            }
+        }
+        match &definition._size {
+            RenderTargetSize::Absolute { _width, _height } if 0 == *_width || 0 == *_height => entry_errors.push(format!("invalid size {}x{}", _width, _height)),
+            RenderTargetSize::SwapchainRelative { _scale } if *_scale <= 0.0 => entry_errors.push(format!("invalid scale {}", _scale)),
+            RenderTargetSize::RenderRelative { _scale } if *_scale <= 0.0 => entry_errors.push(format!("invalid scale {}", _scale)),
+            RenderTargetSize::TargetRelative { _target, _scale } => {
+                if *_scale <= 0.0 {
+                    entry_errors.push(format!("invalid scale {}", _scale));
+                }
+                if *_target == definition._name {
+                    entry_errors.push(String::from("relative to itself"));
+                } else if false == definitions.iter().any(|other| other._name == *_target) {
+                    entry_errors.push(format!("unknown target {}", _target));
+                }
+            },
+            _ => (),
+        }
+        if 0 == definition._layers {
+            entry_errors.push(String::from("zero layers"));
+        }
+        if get_view_type_from_name(&definition._view_type).is_none() {
+            entry_errors.push(format!("unknown view type {}", definition._view_type));
+        }
+        if get_filter_from_name(&definition._min_filter).is_none() || get_filter_from_name(&definition._mag_filter).is_none() {
+            entry_errors.push(format!("unknown filter {} {}", definition._min_filter, definition._mag_filter));
+        }
+        if get_wrap_mode_from_name(&definition._wrap_mode).is_none() {
+            entry_errors.push(format!("unknown wrap mode {}", definition._wrap_mode));
+        }
+
+        if entry_errors.is_empty() {
+            valid_definitions.push(definition.clone());
+        } else {
+            for entry_error in entry_errors.iter() {
+                errors.push(format!("{}[{}] \"{}\": {}", RENDER_TARGETS_FILE_PATH, index, definition._name, entry_error));
+            }
+        }
+    }
+    (valid_definitions, errors)
+}
+
+// the sizes in the dependency order, the cyclic or the broken references are errors.
+pub fn resolve_render_target_sizes(
+    definitions: &[RenderTargetDefinition],
+    swapchain_size: &Vector2<u32>,
+    render_size: &Vector2<u32>,
+) -> (HashMap<String, Vector2<u32>>, Vec<String>) {
+    let scale_size = |size: &Vector2<u32>, scale: f32| -> Vector2<u32> {
+        Vector2::new(((size.x as f32 * scale).round() as u32).max(1), ((size.y as f32 * scale).round() as u32).max(1))
+    };
+    let mut sizes: HashMap<String, Vector2<u32>> = HashMap::new();
+    loop {
+        let mut is_resolved_any = false;
+        for definition in definitions.iter() {
+            if sizes.contains_key(&definition._name) {
+                continue;
+            }
+            let size = match &definition._size {
+                RenderTargetSize::Absolute { _width, _height } => Some(Vector2::new(*_width, *_height)),
+                RenderTargetSize::SwapchainRelative { _scale } => Some(scale_size(swapchain_size, *_scale)),
+                RenderTargetSize::RenderRelative { _scale } => Some(scale_size(render_size, *_scale)),
+                RenderTargetSize::TargetRelative { _target, _scale } => sizes.get(_target).map(|target_size| scale_size(target_size, *_scale)),
+            };
+            if let Some(size) = size {
+                sizes.insert(definition._name.clone(), size);
+                is_resolved_any = true;
+            }
+        }
+        if false == is_resolved_any {
+            break;
+        }
+    }
+    let errors = definitions.iter().enumerate().filter(|(_, definition)| false == sizes.contains_key(&definition._name)).map(|(index, definition)| {
+        format!("{}[{}] \"{}\": cyclic or unresolved relative size", RENDER_TARGETS_FILE_PATH, index, definition._name)
+    }).collect();
+    (sizes, errors)
+}
+
+fn find_supported_render_target_format(renderer_data: &RendererData, format_names: &[String]) -> Option<vk::Format> {
+    format_names.iter().filter_map(|format_name| get_format_from_name(format_name)).find(|format| {
+        let required_features = if constants::DEPTH_FOMATS.contains(format) {
+            vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT | vk::FormatFeatureFlags::SAMPLED_IMAGE
+        } else {
+            vk::FormatFeatureFlags::COLOR_ATTACHMENT | vk::FormatFeatureFlags::SAMPLED_IMAGE
+        };
+        let format_properties = unsafe { renderer_data.get_instance().get_physical_device_format_properties(renderer_data.get_physical_device(), *format) };
+        (format_properties.optimal_tiling_features & required_features) == required_features
+    })
+}
+
+fn find_render_target_format(renderer_data: &RendererData, definition: &RenderTargetDefinition) -> Option<vk::Format> {
+    if definition._stencil {
+        let stencil_format_names: Vec<String> = definition._formats.iter().filter(|format_name| {
+            get_format_from_name(format_name).map_or(false, |format| constants::DEPTH_STENCIL_FORMATS.contains(&format))
+        }).cloned().collect();
+        if let Some(format) = find_supported_render_target_format(renderer_data, &stencil_format_names) {
+            return Some(format);
+        }
+        log::warn!("{} \"{}\": no supported stencil format in {:?}, the stencil is disabled.", RENDER_TARGETS_FILE_PATH, definition._name, definition._formats);
+    }
+    find_supported_render_target_format(renderer_data, &definition._formats)
+}
+
+pub fn load_render_target_definitions(resources: &Resources) -> Vec<RenderTargetDefinition> {
+    let render_targets_file = PathBuf::from(RENDER_TARGETS_FILE_PATH);
+    if false == resources._resource_filenames.contains(&render_targets_file) && false == resources.resolve_path(&render_targets_file).is_file() {
+        return Vec::new();
+    }
+    match serde_json::from_reader(resources.read_bytes(&render_targets_file)) {
+        Ok(definitions) => definitions,
+        Err(e) => {
+            log::error!("load_render_target_definitions: {:?} {:?}", render_targets_file, e);
+            Vec::new()
+        }
+    }
+}
+
+// the registry of the data driven render targets, addressable by the name or the id.
+// the textures are boxed, so the references stay valid until destroy_dynamic_render_targets.
+#[derive(Default)]
+pub struct DynamicRenderTargets {
+    pub _definitions: Vec<RenderTargetDefinition>,
+    pub _render_targets: Vec<Box<TextureData>>,
+    pub _render_target_ids: HashMap<String, usize>,
+}
+
+impl DynamicRenderTargets {
+    pub fn get_render_target_id(&self, render_target_name: &str) -> Option<usize> {
+        self._render_target_ids.get(render_target_name).cloned()
+    }
+
+    pub fn get_render_target(&self, render_target_id: usize) -> *const TextureData {
+        self._render_targets[render_target_id].as_ref() as *const TextureData
+    }
+
+    pub fn get_render_target_names(&self) -> Vec<String> {
+        self._definitions.iter().map(|definition| definition._name.clone()).collect()
+    }
+
+    pub fn get_clear_value(&self, render_target_name: &str) -> Option<&[f32]> {
+        self.get_render_target_id(render_target_name).map(|render_target_id| self._definitions[render_target_id]._clear_value.as_slice())
+    }
+
+    // the depth of _clear_value is authored in the standard convention, 1.0 is the far plane.
+    pub fn get_depth_stencil_clear_value(&self, render_target_name: &str) -> Option<vk::ClearValue> {
+        self.get_clear_value(render_target_name).map(|clear_value| {
+            let depth = clear_value.get(0).cloned().unwrap_or(1.0);
+            let stencil = clear_value.get(1).cloned().unwrap_or(0.0);
+            vulkan_context::get_depth_stencil_clear_value(math::convert_standard_depth(depth), stencil as u32)
+        })
+    }
+
+    pub fn add_render_target(&mut self, definition: &RenderTargetDefinition, texture_data: TextureData) {
+        self._render_target_ids.insert(definition._name.clone(), self._render_targets.len());
+        self._render_targets.push(Box::new(texture_data));
+        self._definitions.push(definition.clone());
+    }
+
+    // parsed every time, the file is picked up on the graphics reload and the resize
+    pub fn create_dynamic_render_targets(&mut self, renderer_data: &RendererData, definitions: &[RenderTargetDefinition]) {
+        let swapchain_extent = &renderer_data._swapchain_data._swapchain_extent;
+        let render_extent = renderer_data.get_render_extent();
+        let (definitions, mut errors) = validate_render_target_definitions(definitions);
+        let (sizes, size_errors) = resolve_render_target_sizes(
+            &definitions,
+            &Vector2::new(swapchain_extent.width, swapchain_extent.height),
+            &Vector2::new(render_extent.width, render_extent.height)
+        );
+        errors.extend(size_errors);
+        for definition in definitions.iter() {
+            let size = match sizes.get(&definition._name) {
+                Some(size) => size,
+                None => continue,
+            };
+            let format = match find_render_target_format(renderer_data, definition) {
+                Some(format) => format,
+                None => {
+                    errors.push(format!("{} \"{}\": no supported format in {:?}", RENDER_TARGETS_FILE_PATH, definition._name, definition._formats));
+                    continue;
+                }
+            };
+            let texture_data = renderer_data.create_render_target::<u8>(&TextureCreateInfo {
+                _texture_name: definition._name.clone(),
+                _texture_width: size.x,
+                _texture_height: size.y,
+                _texture_layers: definition._layers,
+                _texture_format: format,
+                _texture_view_type: get_view_type_from_name(&definition._view_type).unwrap(),
+                _texture_min_filter: get_filter_from_name(&definition._min_filter).unwrap(),
+                _texture_mag_filter: get_filter_from_name(&definition._mag_filter).unwrap(),
+                _texture_wrap_mode: get_wrap_mode_from_name(&definition._wrap_mode).unwrap(),
+                _max_mip_levels: definition._max_mip_levels,
+                _enable_mipmap: definition._enable_mipmap,
+                ..Default::default()
+            });
+            self.add_render_target(definition, texture_data);
+        }
+        for error in errors.iter() {
+            log::error!("create_dynamic_render_targets: {}", error);
+        }
+        log::info!("create_dynamic_render_targets: {:?}", self.get_render_target_names());
+    }
+
+    pub fn destroy_dynamic_render_targets(&mut self, renderer_data: &RendererData) {
+        for render_target in self._render_targets.iter() {
+            renderer_data.destroy_texture(render_target);
+        }
+        self._render_targets.clear();
+        self._render_target_ids.clear();
+        self._definitions.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_definition(name: &str, size: RenderTargetSize) -> RenderTargetDefinition {
+        RenderTargetDefinition {
+            _name: String::from(name),
+            _formats: vec![String::from("R16G16B16A16_SFLOAT")],
+            _size: size,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_render_relative_sizes() {
+        let definitions = vec![
+            create_definition("bloom_quarter", RenderTargetSize::TargetRelative { _target: String::from("bloom_half"), _scale: 0.5 }),
+            create_definition("bloom_half", RenderTargetSize::RenderRelative { _scale: 0.5 }),
+            create_definition("ui", RenderTargetSize::SwapchainRelative { _scale: 1.0 }),
+            create_definition("lut", RenderTargetSize::Absolute { _width: 32, _height: 32 }),
+        ];
+        let (definitions, errors) = validate_render_target_definitions(&definitions);
+        assert!(errors.is_empty(), "{:?}", errors);
+
+        // the scene targets follow the render scale, the swapchain targets are kept
+        let swapchain_size = Vector2::new(1920, 1080);
+        let (sizes, errors) = resolve_render_target_sizes(&definitions, &swapchain_size, &swapchain_size);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(Vector2::new(960, 540), sizes["bloom_half"]);
+        assert_eq!(Vector2::new(480, 270), sizes["bloom_quarter"]);
+        assert_eq!(swapchain_size, sizes["ui"]);
+
+        let (sizes, errors) = resolve_render_target_sizes(&definitions, &swapchain_size, &Vector2::new(1344, 756));
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(Vector2::new(672, 378), sizes["bloom_half"]);
+        assert_eq!(Vector2::new(336, 189), sizes["bloom_quarter"]);
+        assert_eq!(swapchain_size, sizes["ui"]);
+        assert_eq!(Vector2::new(32, 32), sizes["lut"]);
+    }
+
+    #[test]
+    fn test_invalid_render_relative_size() {
+        let definitions = vec![create_definition("bloom_half", RenderTargetSize::RenderRelative { _scale: 0.0 })];
+        let (definitions, errors) = validate_render_target_definitions(&definitions);
+        assert!(definitions.is_empty());
+        assert_eq!(vec![format!("{}[0] \"bloom_half\": invalid scale 0", RENDER_TARGETS_FILE_PATH)], errors);
+    }
+}
@@ -1,6 +1,11 @@
-use std::cell::Ref;
+use std::cell::{ Cell, Ref, RefCell };
+use std::collections::HashMap;
 use std::borrow::Cow;
-use std::ffi::CStr;
+use std::ffi::{ CStr, CString };
+use std::panic::{ self, AssertUnwindSafe };
+use std::path::Path;
+use std::sync::atomic::{ AtomicU32, Ordering };
+use std::time;
 use std::vec::Vec;
 use ash::{
     vk,
@@ -14,37 +19,87 @@ use ash::extensions::khr::{
     Surface,
     Swapchain,
 };
-use ash::version::{InstanceV1_0, DeviceV1_0};
+use ash::version::{InstanceV1_0, DeviceV1_0, DeviceV1_2};
 use ash::vk::CommandBuffer;
-use nalgebra::Vector2;
+use nalgebra::{ Vector2, Vector3, Matrix4 };
 use winit;
 use winit::window::{ Window };
 
 use crate::constants;
+use crate::application::engine::RenderExtension;
+use crate::application::environment_settings::{ AtmosphereSettings, OceanSettings, SkyMode, SkySettings };
 use crate::application::scene_manager::SceneManagerData;
-use crate::renderer::font::FontManager;
+use crate::renderer::atmosphere::{ self, Atmosphere };
+use crate::renderer::auto_exposure::{ self, AutoExposure, AutoExposureSettings };
+use crate::renderer::camera::CameraObjectData;
+use crate::renderer::debug_render_target::DebugRenderTarget;
+use crate::renderer::denoiser::{ self, DenoiserInstance };
+use crate::renderer::font::{ FontManager, RenderTextInfo };
+use crate::renderer::frame_graph::{ self, FrameGraph, RenderTargetAccessType, RenderTargetState };
+use crate::renderer::debug_view::{ self, DebugViewMode };
+use crate::renderer::fxaa::{ self, FXAA, FXAASettings };
+use crate::renderer::gpu_skinning::{ self, GpuSkinning };
+use crate::renderer::histogram::{ self, DebugHistogram, HistogramSettings };
+use crate::renderer::history_confidence::{ self, HistoryConfidence, HistoryConfidenceSettings };
 use crate::renderer::image_sampler::{ self, ImageSamplerData };
-use crate::renderer::material_instance::{ PipelineBindingData, MaterialInstanceData };
+use crate::renderer::impostor::{ self, ImpostorAtlasLayout, ImpostorBakeData, ImpostorCaptureTargets, ImpostorCaptureView };
+use crate::renderer::light::LightConstants;
+use crate::renderer::light_cluster::LightCluster;
+use crate::renderer::light_probe::{ self, LightProbe, LightProbeSettings };
+use crate::renderer::material_instance::{ self, PipelineBindingData, MaterialInstanceData, MaterialParameterOverrides };
+use crate::renderer::model::ModelData;
+use crate::renderer::post_process::{ self, AntiAliasingMode, PostProcessConsumerId, PostProcessConsumers, PostProcessHistory, PostProcessProfile };
+use crate::renderer::render_element::{ self, PushConstant_RenderInstanced, RenderElementData, RenderElementGroupData };
+use crate::renderer::render_target::{ self, DynamicRenderTargets };
+use crate::renderer::object_picking::{ self, ObjectIdSource, ObjectPicking };
+use crate::renderer::occlusion_culling::{ self, OcclusionCulling, OcclusionCullingSettings };
+use crate::renderer::render_object_storage;
+use crate::renderer::ocean_height_field::OceanHeightField;
+use crate::renderer::screenshot::{ self, ScreenshotCapture, ScreenshotSource };
+use crate::renderer::tonemap::{ self, PushConstant_RenderFinal, TonemapOperator, TonemapSettings };
+use crate::renderer::shadow_atlas::{ ShadowAtlas, ShadowAtlasRequest, ShadowAtlasTile };
+use crate::renderer::shadow_cache::ShadowCache;
+use crate::renderer::skybox::{ self, SkyboxData };
+use crate::renderer::terrain::{ self, Terrain, TerrainRenderSettings, TerrainSettings };
 use crate::renderer::ui::{ UIManagerData };
+use crate::renderer::viewport::{ self, ViewportRect };
+use crate::renderer::visibility_stats::{ self, VisibilityStats, VisibilityStatsReport };
+use crate::renderer::volumetric_fog::{ self, VolumetricFog, VolumetricFogSettings };
+use crate::resource::impostor_cache;
 use crate::resource::resource::Resources;
-use crate::utilities::system::{ self, RcRefCell };
+use crate::utilities::math;
+use crate::utilities::progress::{ self, ProgressTask };
+use crate::utilities::system::{ self, RcRefCell, newRcRefCell };
 use crate::vulkan_context::{
     buffer,
     command_buffer,
     device,
     queue,
+    shading_rate,
     sync,
-    texture,
 };
-use crate::vulkan_context::buffer::{ ShaderBufferData };
+use crate::vulkan_context::bindless_texture::{ self, BindlessMaterialData };
+use crate::vulkan_context::buffer::{ ShaderBufferData, StagingBufferPool };
 use crate::vulkan_context::descriptor::{ self, DescriptorResourceInfo };
+use crate::vulkan_context::device_memory_allocator;
 use crate::vulkan_context::framebuffer::FramebufferData;
 use crate::vulkan_context::geometry_buffer::{ self, GeometryData };
-use crate::vulkan_context::render_pass::{ RenderPassDataCreateInfo, RenderPassData, PipelineData };
-use crate::vulkan_context::swapchain::{ self, SwapchainData };
-use crate::vulkan_context::texture::{ TextureCreateInfo, TextureData };
-use crate::vulkan_context::vulkan_context::{ RenderFeatures, SwapchainArray, FrameArray };
-
+use crate::vulkan_context::gpu_breadcrumb::{ self, GpuBreadcrumbs };
+use crate::vulkan_context::gpu_profiler::GpuProfiler;
+use crate::vulkan_context::object_name;
+use crate::vulkan_context::push_constant::PushConstant;
+use crate::vulkan_context::render_pass::{ self, RenderPassDataCreateInfo, RenderPassData, PipelineData, ImageAttachmentDescription, MergedRenderPassInfo };
+use crate::vulkan_context::swapchain::{ self, PresentMode, SwapchainData };
+use crate::vulkan_context::texture::{ self, TextureCreateInfo, TextureData, TextureUploadBatch };
+use crate::vulkan_context::uniform_arena::{ self, UniformArena };
+use crate::vulkan_context::vulkan_context::{ self, RenderFeatures, SwapchainArray, FrameArray };
+
+// the error messages of the validation layer since the start, the soak test fails a phase which adds any.
+static VALIDATION_ERROR_COUNT: AtomicU32 = AtomicU32::new(0);
+
+pub fn get_validation_error_count() -> u32 {
+    VALIDATION_ERROR_COUNT.load(Ordering::Relaxed)
+}
 
 pub unsafe extern "system" fn vulkan_debug_callback(
     message_severity: vk::DebugUtilsMessageSeverityFlagsEXT,
@@ -52,6 +107,9 @@ pub unsafe extern "system" fn vulkan_debug_callback(
     p_callback_data: *const vk::DebugUtilsMessengerCallbackDataEXT,
     _user_data: *mut std::os::raw::c_void,
 ) -> vk::Bool32 {
+    if message_severity.contains(vk::DebugUtilsMessageSeverityFlagsEXT::ERROR) {
+        VALIDATION_ERROR_COUNT.fetch_add(1, Ordering::Relaxed);
+    }
     let callback_data = *p_callback_data;
     let message_id_number: i32 = callback_data.message_id_number as i32;
     let message_id_name = if callback_data.p_message_id_name.is_null() {
@@ -64,13 +122,17 @@ pub unsafe extern "system" fn vulkan_debug_callback(
     } else {
         CStr::from_ptr(callback_data.p_message).to_string_lossy()
     };
+    // the names of the objects in the message, also in the release build without vkSetDebugUtilsObjectNameEXT
+    let object_names = object_name::get_callback_object_names(&callback_data);
+    let object_names = if object_names.is_empty() { String::new() } else { format!(" [objects: {}]", object_names.join(", ")) };
     println!(
-        "[{:?}]:{:?} [{} ({})] : {}",
+        "[{:?}]:{:?} [{} ({})] : {}{}",
         message_severity,
         message_type,
         message_id_name,
         &message_id_number.to_string(),
         message,
+        object_names,
     );
     vk::FALSE
 }
@@ -98,6 +160,54 @@ pub fn get_debug_message_level(debug_message_level: vk::DebugUtilsMessageSeverit
     }
 }
 
+// CaptureFrame renders exactly one self-contained frame for graphics debuggers.
+// wait idle before and after, a dedicated command buffer, debug labels and no temporal amortization.
+#[derive(Clone, Debug, Copy, PartialEq, Eq)]
+pub enum RenderMode {
+    Normal,
+    CaptureFrame,
+}
+
+// the per frame uniform uploads, the arena blocks are written without vkMapMemory.
+#[derive(Clone, Debug, Copy, PartialEq, Default)]
+pub struct UniformUploadStats {
+    pub _map_memory_count: u32,
+    pub _arena_write_count: u32,
+    pub _upload_time_ms: f64,
+}
+
+pub const RENDER_ERROR_CLEAR_COLOR: [f32; 4] = [0.5, 0.0, 0.0, 1.0];
+pub const RENDER_ERROR_BANNER_FRAMES: u32 = 300; // the banner stays for this many frames after the last render error
+pub const DEBUG_INJECT_RENDER_PANIC_MESSAGE: &str = "DEBUG_INJECT_RENDER_PANIC";
+pub const ERROR_SCREEN_FONT_SIZE: u32 = 16;
+pub const PRESENT_MODE_OVERLAY_FRAMES: u32 = 180; // the active present mode is shown for this many frames after the toggle
+pub const MIN_RENDER_SCALE: f32 = 0.25;
+pub const MAX_RENDER_SCALE: f32 = 2.0;
+
+// the current subpass of the merged render pass, see RendererData::begin_render_pass_pipeline
+#[derive(Clone, Debug, Copy, PartialEq)]
+pub struct MergedRenderPassState {
+    pub _render_pass: vk::RenderPass,
+    pub _subpass_index: u32,
+    pub _subpass_count: u32,
+}
+
+// the clamped scale, None if the render targets are kept
+pub fn get_changed_render_scale(render_scale: f32, new_render_scale: f32) -> Option<f32> {
+    let new_render_scale = new_render_scale.clamp(MIN_RENDER_SCALE, MAX_RENDER_SCALE);
+    if new_render_scale == render_scale {
+        return None;
+    }
+    Some(new_render_scale)
+}
+
+pub fn get_render_extent(swapchain_extent: &vk::Extent2D, render_scale: f32) -> vk::Extent2D {
+    vk::Extent2D {
+        width: ((swapchain_extent.width as f32 * render_scale).round() as u32).max(1),
+        height: ((swapchain_extent.height as f32 * render_scale).round() as u32).max(1),
+    }
+}
+
 pub trait ProjectRendererBase {
     fn initialize_project_renderer(&mut self, renderer_data: &RendererData);
     fn is_first_rendering(&self) -> bool;
@@ -105,12 +215,56 @@ pub trait ProjectRendererBase {
     fn prepare_framebuffer_and_descriptors(&mut self, device: &Device, resources: &Resources);
     fn destroy_framebuffer_and_descriptors(&mut self, device: &Device);
     fn update_post_process_datas(&mut self);
+    // the per frame constants(scene, view, light, ssao, bone matrices..) should be RendererData::create_uniform_arena_buffer_data.
     fn get_shader_buffer_data_from_str(&self, buffer_data_name: &str) -> &ShaderBufferData;
     fn get_render_target_from_str(&self, render_target_type_str: &str) -> &TextureData;
+    // the names of RenderTargetType in the declaration order, the candidates of the debug render target.
+    fn get_render_target_names(&self) -> Vec<String> {
+        Vec::new()
+    }
     fn get_render_pass_data_create_infos(&self) -> Vec<RenderPassDataCreateInfo>;
+    // the render passes which are merged into the subpasses of one render pass with constants::ENABLE_SUBPASS_MERGING, ex) render_gbuffer + composite_gbuffer
+    fn get_merged_render_pass_infos(&self) -> Vec<MergedRenderPassInfo> {
+        Vec::new()
+    }
+    // the scene targets(SceneColor, GBuffer, SSAO, SSR, bloom..) are RendererData::get_render_extent,
+    // the final pass samples the scaled SceneColor with the linear filter and writes at the swapchain extent.
     fn create_render_targets(&mut self, renderer_data: &RendererData);
     fn destroy_render_targets(&mut self, device: &Device);
     fn destroy_uniform_buffers(&mut self, device: &Device);
+    // draw the model with the identity world matrix per capture view, with set_impostor_capture_viewport and its view projection.
+    // albedo with the coverage in alpha and the object space normal, the color targets are left in TRANSFER_SRC_OPTIMAL.
+    fn render_impostor_capture(
+        &mut self,
+        command_buffer: CommandBuffer,
+        renderer_data: &RendererData,
+        model_data: &ModelData,
+        capture_views: &[ImpostorCaptureView],
+        capture_targets: &ImpostorCaptureTargets,
+    );
+    // the post process chain of a consumer, the passes disabled by the profile are skipped.
+    // history: the main viewport uses the history render targets of the project, the others use the given history.
+    fn render_post_process(
+        &mut self,
+        command_buffer: CommandBuffer,
+        swapchain_index: u32,
+        renderer_data: &RendererData,
+        viewport_rect: &ViewportRect,
+        profile: &PostProcessProfile,
+        history: &PostProcessHistory,
+    );
+    // regenerates the spectrum textures and the slope variance, then rebuilds only the framebuffers and the descriptor sets of the ocean.
+    // called at the beginning of a frame after the device is idle, so the previous textures can be destroyed immediately.
+    // the sizes come from fft_ocean::FFTOceanLayout of the FFT_A render target, checked by FFTOceanLayout::check_consistency.
+    fn reinitialize_ocean(&mut self, _renderer_data: &RendererData, _ocean_settings: &OceanSettings) {}
+    // FFTOcean::set_sea_state, the spectrum datas are generated again with OceanSettings::get_simulation_sizes and
+    // re-uploaded in place by RendererData::update_texture, the slope variance is computed again at the next frame.
+    // the textures are not recreated, so the framebuffers and the descriptor sets of the ocean are kept.
+    fn update_ocean_spectrum(&mut self, _renderer_data: &RendererData, _ocean_settings: &OceanSettings) {}
+    // FFTOcean::_ocean_height_field, created again with the spectrum datas. None if the ocean is disabled.
+    fn get_ocean_height_field(&self) -> Option<&OceanHeightField> {
+        None
+    }
     fn render_scene(
         &mut self,
         command_buffer: CommandBuffer,
@@ -131,6 +285,18 @@ pub struct RendererData {
     _frame_index: i32,
     _swapchain_index: u32,
     _need_recreate_swapchain: bool,
+    _present_mode: PresentMode, // the requested mode, see SwapchainData::_swapchain_present_mode for the active mode
+    _present_mode_overlay_frames: u32,
+    _render_scale: f32, // the scene resolution of the swapchain size, the final pass upscales
+    _msaa_sample_count: vk::SampleCountFlags, // of the scene color and depth, TYPE_1: no msaa
+    _tonemap_settings: TonemapSettings, // the push constant of render_final, changed without the swapchain recreation
+    _is_hdr_output_requested: bool, // see SwapchainData::_swapchain_color_space for the active output
+    _need_recreate_render_targets: bool,
+    _render_mode: RenderMode,
+    _capture_next_frame: bool,
+    _pending_ocean_settings: Option<OceanSettings>, // applied at the next frame boundary
+    _pending_ocean_spectrum_settings: Option<OceanSettings>, // the sea state, updated in place at the next frame boundary
+    _render_error_state: RefCell<RenderErrorState>,
     pub _entry: Entry,
     pub _instance: Instance,
     pub _device: Device,
@@ -149,11 +315,49 @@ pub struct RendererData {
     pub _queue_family_datas: queue::QueueFamilyDatas,
     pub _frame_fences: Vec<vk::Fence>,
     pub _command_pool: vk::CommandPool,
+    pub _transfer_command_pool: vk::CommandPool,
+    pub _texture_upload_batch: RefCell<Option<TextureUploadBatch>>, // between begin_texture_uploads and end_texture_uploads
+    pub _staging_buffer_pool: RcRefCell<StagingBufferPool>, // the staging regions of the texture and the geometry uploads
     pub _command_buffers: SwapchainArray<vk::CommandBuffer>,
+    pub _capture_command_buffer: vk::CommandBuffer,
+    pub _frame_graph: RcRefCell<FrameGraph>,
+    pub _debug_histogram: RcRefCell<DebugHistogram>,
+    pub _gpu_skinning: RefCell<GpuSkinning>,
+    pub _denoisers: RefCell<HashMap<String, DenoiserInstance>>, // created by the project renderer, see create_denoiser_instance
+    pub _history_confidence: RcRefCell<HistoryConfidence>,
+    pub _auto_exposure: RefCell<AutoExposure>,
+    pub _occlusion_culling: RefCell<OcclusionCulling>,
+    pub _fxaa: RefCell<FXAA>,
+    pub _atmosphere: RefCell<Atmosphere>, // the precomputed scattering of the light probe and the sky materials
+    pub _light_probe: RefCell<LightProbe>, // the sky light of composite_gbuffer, captured again when the sun moves
+    pub _skybox: RefCell<SkyboxData>, // the sky of SkyMode::Cubemap
+    pub _volumetric_fog: RefCell<VolumetricFog>, // the light shafts of the main light, see render_volumetric_fog
+    pub _terrain: RefCell<Terrain>, // the heightmap terrain of the environment settings, see render_terrain
+    pub _bindless_material_buffer: RefCell<Option<ShaderBufferData>>,
+    pub _material_override_buffer: RefCell<Option<ShaderBufferData>>,
+    pub _shadow_atlas: RcRefCell<ShadowAtlas>,
+    pub _shadow_cache: RcRefCell<ShadowCache>,
+    pub _light_cluster: RcRefCell<LightCluster>, // built by SceneManagerData::update_light_cluster
+    pub _post_process_consumers: RcRefCell<PostProcessConsumers>,
+    pub _dynamic_render_targets: RcRefCell<DynamicRenderTargets>,
+    pub _gpu_breadcrumbs: RcRefCell<GpuBreadcrumbs>,
+    pub _gpu_profiler: RcRefCell<GpuProfiler>,
+    pub _visibility_stats: RefCell<VisibilityStats>,
+    pub _debug_render_target: RefCell<DebugRenderTarget>,
+    pub _screenshot_capture: RefCell<ScreenshotCapture>,
+    pub _object_picking: RefCell<ObjectPicking>,
+    pub _debug_view_mode: Cell<DebugViewMode>,
+    pub _debug_view_fallback_logged: Cell<bool>, // the missing debug view pipelines are logged once
+    pub _draw_call_count: Cell<u32>, // of the last frame, reset by render_scene
+    pub _merged_render_pass_state: Cell<Option<MergedRenderPassState>>, // the merged render pass which is kept open by end_render_pass
+    pub _dispatch_count: Cell<u32>,
+    pub _uniform_arena: RcRefCell<UniformArena>,
+    pub _uniform_upload_stats: Cell<UniformUploadStats>, // of the last frame
     pub _render_features: RenderFeatures,
     pub _image_samplers: ImageSamplerData,
     pub _resources: RcRefCell<Resources>,
     pub _project_renderer: *const dyn ProjectRendererBase,
+    pub _render_extensions: Vec<*const dyn RenderExtension>, // in the registration order, after the project renderer
 }
 
 impl RendererData {
@@ -182,8 +386,10 @@ impl RendererData {
             log::info!("    driver_version: {}.{}.{}", vk::version_major(device_properties.driver_version), vk::version_minor(device_properties.driver_version), vk::version_patch(device_properties.driver_version));
             log::info!("    device: {:?} {:?} vecdor_id: {:?} device_id: {:?}", device_name, device_properties.device_type, device_properties.vendor_id, device_properties.device_id);
             log::info!("    limits: {:?}", device_properties.limits);
+            log::info!("    device local memory: {} MB", texture::get_device_local_memory_size(&device_memory_properties) / (1024 * 1024));
 
             let msaa_samples = device::get_max_usable_sample_count(&device_properties);
+            let msaa_sample_count = device::get_msaa_sample_count_flags(constants::MSAA_SAMPLE_COUNT, msaa_samples);
 
             let queue_family_indices = queue::get_queue_family_indices(
                 &instance,
@@ -192,10 +398,26 @@ impl RendererData {
                 physical_device,
                 constants::IS_CONCURRENT_MODE
             );
+            let available_device_extensions = device::get_device_extension_supports(&instance, physical_device);
+            let (is_fragment_shading_rate_supported, shading_rate_texel_size) = shading_rate::query_fragment_shading_rate_support(&instance, physical_device, &available_device_extensions);
+            let (is_descriptor_indexing_supported, max_bindless_texture_count) = bindless_texture::query_descriptor_indexing_support(&instance, physical_device, &device_properties);
+            let is_draw_indirect_count_supported = occlusion_culling::query_draw_indirect_count_support(&instance, physical_device, &device_properties);
             let render_features = RenderFeatures {
                 _physical_device_features: physical_device_features.clone(),
                 _msaa_samples: msaa_samples,
+                _is_fragment_shading_rate_supported: is_fragment_shading_rate_supported,
+                _shading_rate_texel_size: shading_rate_texel_size,
+                _is_buffer_marker_supported: available_device_extensions.contains(&CString::new(gpu_breadcrumb::BUFFER_MARKER_EXTENSION_NAME).unwrap()),
+                _is_descriptor_indexing_supported: is_descriptor_indexing_supported,
+                _max_bindless_texture_count: max_bindless_texture_count,
+                _is_draw_indirect_count_supported: is_draw_indirect_count_supported,
+                _is_fill_mode_non_solid_supported: vk::TRUE == physical_device_features.fill_mode_non_solid,
+                _depth_stencil_format: texture::find_supported_depth_stencil_format(&instance, physical_device),
             };
+            match render_features._depth_stencil_format {
+                Some(depth_stencil_format) => log::info!("depth stencil format: {:?}", depth_stencil_format),
+                None => log::warn!("no depth stencil format, the stencil features(selection outline, ..) are disabled."),
+            }
             let graphics_queue_index = queue_family_indices._graphics_queue_index;
             let present_queue_index = queue_family_indices._present_queue_index;
             let queue_family_index_set: Vec<u32> = if graphics_queue_index == present_queue_index {
@@ -203,53 +425,84 @@ impl RendererData {
             } else {
                 vec![graphics_queue_index, present_queue_index]
             };
-            let device = device::create_device(&instance, physical_device, &render_features, &queue_family_index_set);
-            let queue_map = queue::create_queues(&device, &queue_family_index_set);
+            // the dedicated transfer queue is not shared with the swapchain
+            let dedicated_transfer_queue_index = queue_family_indices._dedicated_transfer_queue_index;
+            let mut device_queue_family_index_set: Vec<u32> = queue_family_index_set.clone();
+            if constants::INVALID_QUEUE_INDEX != dedicated_transfer_queue_index && false == device_queue_family_index_set.contains(&dedicated_transfer_queue_index) {
+                device_queue_family_index_set.push(dedicated_transfer_queue_index);
+            }
+            let device = device::create_device(&instance, physical_device, &render_features, &device_queue_family_index_set);
+
+            // debug utils, the object names of the registry are set from here
+            let debug_call_back: vk::DebugUtilsMessengerEXT;
+            let debug_util_interface: Option<DebugUtils>;
+            if constants::ENABLE_VALIDATION_LAYER {
+                let debug_message_level = get_debug_message_level(constants::DEBUG_MESSAGE_LEVEL);
+                let debug_info = vk::DebugUtilsMessengerCreateInfoEXT {
+                    message_severity: debug_message_level,
+                    message_type: vk::DebugUtilsMessageTypeFlagsEXT::all(),
+                    pfn_user_callback: Some(vulkan_debug_callback),
+                    ..Default::default()
+                };
+                debug_util_interface = Some(DebugUtils::new(&entry, &instance));
+                debug_call_back = debug_util_interface.as_ref().unwrap().create_debug_utils_messenger(&debug_info, None).unwrap();
+            } else {
+                debug_util_interface = None;
+                debug_call_back = vk::DebugUtilsMessengerEXT::null();
+            }
+            object_name::create_object_name_registry(device.handle(), debug_util_interface.as_ref());
+            device_memory_allocator::create_device_memory_allocator(&device_memory_properties, &device_properties.limits);
+
+            let queue_map = queue::create_queues(&device, &device_queue_family_index_set);
             let default_queue: &vk::Queue = queue_map.get(&queue_family_index_set[0]).unwrap();
             let queue_family_datas = queue::QueueFamilyDatas {
                 _graphics_queue: queue_map.get(&graphics_queue_index).unwrap_or(default_queue).clone(),
                 _present_queue: queue_map.get(&present_queue_index).unwrap_or(default_queue).clone(),
+                _transfer_queue: queue_map.get(&dedicated_transfer_queue_index).unwrap_or(default_queue).clone(),
                 _queue_family_index_list: queue_family_index_set.clone(),
                 _queue_family_count: queue_map.len() as u32,
                 _queue_family_indices: queue_family_indices.clone()
             };
             let swapchain_interface = Swapchain::new(&instance, &device);
+            let present_mode = if constants::ENABLE_IMMEDIATE_MODE { PresentMode::Immediate } else { PresentMode::Fifo };
             let swapchain_data: swapchain::SwapchainData = swapchain::create_swapchain_data(
                 &device,
                 &swapchain_interface,
                 surface,
                 &swapchain_support_details,
                 &queue_family_datas,
-                constants::ENABLE_IMMEDIATE_MODE
+                present_mode,
+                constants::ENABLE_HDR_OUTPUT
             );
             let image_available_semaphores = sync::create_semaphores(&device);
             let render_finished_semaphores = sync::create_semaphores(&device);
             let frame_fences = sync::create_fences(&device);
             let command_pool = command_buffer::create_command_pool(&device, &queue_family_datas);
+            let transfer_command_pool = command_buffer::create_command_pool_with_queue_family_index(&device, queue_family_datas.get_transfer_queue_family_index());
+            let staging_buffer_pool = StagingBufferPool::create_staging_buffer_pool(&device, &device_memory_properties, command_pool);
+            let uniform_arena = UniformArena::create_uniform_arena(&device, &device_memory_properties, &device_properties.limits, uniform_arena::UNIFORM_ARENA_SIZE);
             let command_buffers = command_buffer::create_command_buffers(&device, command_pool, constants::SWAPCHAIN_IMAGE_COUNT as u32);
-
-            // debug utils
-            let debug_call_back: vk::DebugUtilsMessengerEXT;
-            let debug_util_interface: Option<DebugUtils>;
-            if constants::ENABLE_VALIDATION_LAYER {
-                let debug_message_level = get_debug_message_level(constants::DEBUG_MESSAGE_LEVEL);
-                let debug_info = vk::DebugUtilsMessengerCreateInfoEXT {
-                    message_severity: debug_message_level,
-                    message_type: vk::DebugUtilsMessageTypeFlagsEXT::all(),
-                    pfn_user_callback: Some(vulkan_debug_callback),
-                    ..Default::default()
-                };
-                debug_util_interface = Some(DebugUtils::new(&entry, &instance));
-                debug_call_back = debug_util_interface.as_ref().unwrap().create_debug_utils_messenger(&debug_info, None).unwrap();
-            } else {
-                debug_util_interface = None;
-                debug_call_back = vk::DebugUtilsMessengerEXT::null();
-            }
+            let capture_command_buffer = command_buffer::create_command_buffers(&device, command_pool, 1)[0];
+            let gpu_breadcrumbs = GpuBreadcrumbs::create_gpu_breadcrumbs(&instance, &device, &device_memory_properties, render_features._is_buffer_marker_supported);
+            let gpu_profiler = GpuProfiler::create_gpu_profiler(&device, &device_properties.limits);
+            let visibility_stats = VisibilityStats::create_visibility_stats(&render_features._physical_device_features);
 
             RendererData {
                 _frame_index: 0,
                 _swapchain_index: 0,
                 _need_recreate_swapchain: false,
+                _present_mode: present_mode,
+                _present_mode_overlay_frames: 0,
+                _render_scale: constants::RENDER_SCALE.clamp(MIN_RENDER_SCALE, MAX_RENDER_SCALE),
+                _msaa_sample_count: msaa_sample_count,
+                _tonemap_settings: TonemapSettings::default(),
+                _is_hdr_output_requested: constants::ENABLE_HDR_OUTPUT,
+                _need_recreate_render_targets: false,
+                _render_mode: RenderMode::Normal,
+                _capture_next_frame: false,
+                _pending_ocean_settings: None,
+                _pending_ocean_spectrum_settings: None,
+                _render_error_state: RefCell::new(RenderErrorState::default()),
                 _entry: entry,
                 _instance: instance,
                 _device: device,
@@ -268,11 +521,49 @@ impl RendererData {
                 _queue_family_datas: queue_family_datas,
                 _frame_fences: frame_fences,
                 _command_pool: command_pool,
+                _transfer_command_pool: transfer_command_pool,
+                _texture_upload_batch: RefCell::new(None),
+                _staging_buffer_pool: newRcRefCell(staging_buffer_pool),
                 _command_buffers: command_buffers,
+                _capture_command_buffer: capture_command_buffer,
+                _frame_graph: newRcRefCell(FrameGraph::default()),
+                _debug_histogram: newRcRefCell(DebugHistogram::default()),
+                _gpu_skinning: RefCell::new(GpuSkinning::default()),
+                _denoisers: RefCell::new(HashMap::new()),
+                _history_confidence: newRcRefCell(HistoryConfidence::default()),
+                _auto_exposure: RefCell::new(AutoExposure::default()),
+                _occlusion_culling: RefCell::new(OcclusionCulling::default()),
+                _fxaa: RefCell::new(FXAA::default()),
+                _atmosphere: RefCell::new(Atmosphere::default()),
+                _light_probe: RefCell::new(LightProbe::default()),
+                _skybox: RefCell::new(SkyboxData::default()),
+                _volumetric_fog: RefCell::new(VolumetricFog::default()),
+                _terrain: RefCell::new(Terrain::default()),
+                _bindless_material_buffer: RefCell::new(None),
+                _material_override_buffer: RefCell::new(None),
+                _shadow_atlas: newRcRefCell(ShadowAtlas::create_shadow_atlas(constants::SHADOW_ATLAS_SIZE)),
+                _shadow_cache: newRcRefCell(ShadowCache::default()),
+                _light_cluster: newRcRefCell(LightCluster::default()),
+                _post_process_consumers: newRcRefCell(PostProcessConsumers::default()),
+                _dynamic_render_targets: newRcRefCell(DynamicRenderTargets::default()),
+                _gpu_breadcrumbs: newRcRefCell(gpu_breadcrumbs),
+                _gpu_profiler: newRcRefCell(gpu_profiler),
+                _visibility_stats: RefCell::new(visibility_stats),
+                _debug_render_target: RefCell::new(DebugRenderTarget::default()),
+                _screenshot_capture: RefCell::new(ScreenshotCapture::default()),
+                _object_picking: RefCell::new(ObjectPicking::default()),
+                _debug_view_mode: Cell::new(DebugViewMode::None),
+                _debug_view_fallback_logged: Cell::new(false),
+                _draw_call_count: Cell::new(0),
+                _merged_render_pass_state: Cell::new(None),
+                _dispatch_count: Cell::new(0),
+                _uniform_arena: newRcRefCell(uniform_arena),
+                _uniform_upload_stats: Cell::new(UniformUploadStats::default()),
                 _render_features: render_features,
                 _image_samplers: ImageSamplerData::default(),
                 _resources: resources.clone(),
                 _project_renderer: project_renderer,
+                _render_extensions: Vec::new(),
             }
         }
     }
@@ -281,12 +572,189 @@ impl RendererData {
         self._swapchain_index = 0;
         self._frame_index = 0;
         self._need_recreate_swapchain = false;
+        self._need_recreate_render_targets = false;
         self._image_samplers = image_sampler::create_image_samplers(self.get_device());
+        self.create_bindless_material_buffer();
+        self.create_material_override_buffer();
         self.get_project_renderer_mut().initialize_project_renderer(self);
     }
     pub fn get_project_renderer(&self) -> &dyn ProjectRendererBase { unsafe { &*(self._project_renderer) } }
     pub fn get_project_renderer_mut(&self) -> &mut dyn ProjectRendererBase { unsafe { &mut *(self._project_renderer as *mut dyn ProjectRendererBase) } }
+    pub fn set_render_extensions(&mut self, render_extensions: &[*const (dyn RenderExtension + 'static)]) { self._render_extensions = render_extensions.to_vec(); }
+    pub fn get_render_extensions(&self) -> Vec<&mut dyn RenderExtension> {
+        self._render_extensions.iter().map(|render_extension| unsafe { &mut *(*render_extension as *mut dyn RenderExtension) }).collect()
+    }
     pub fn get_need_recreate_swapchain(&self) -> bool { self._need_recreate_swapchain }
+    pub fn get_render_mode(&self) -> RenderMode { self._render_mode }
+    pub fn is_capture_mode(&self) -> bool { RenderMode::CaptureFrame == self._render_mode }
+    pub fn request_capture_next_frame(&mut self) {
+        log::info!("request_capture_next_frame");
+        self._capture_next_frame = true;
+    }
+    // the backbuffer of the next rendered frame is written to a png after its present
+    pub fn request_screenshot(&self, file_path: &Path) {
+        self._screenshot_capture.borrow_mut().request_screenshot(file_path);
+    }
+    // x, y: the pixel of the render extent. the texel of object_picking::OBJECT_ID_RENDER_TARGET_NAME is read back in the next frame,
+    // returns the object id of the last completed pick, see SceneManagerData::get_render_object_by_object_id
+    pub fn pick_object(&self, x: u32, y: u32) -> Option<u64> {
+        let mut object_picking = self._object_picking.borrow_mut();
+        object_picking.request_object_pick(x, y);
+        object_picking.get_picked_object_id()
+    }
+    // the result of a pick once, None until the readback of the next frame
+    pub fn take_picked_object_id(&self) -> Option<u64> {
+        self._object_picking.borrow_mut().take_picked_object_id()
+    }
+    // the mouse position to the pixel of pick_object, see object_picking::get_render_position
+    pub fn get_render_position(&self, viewport_position: &Vector2<i32>, viewport_size: &Vector2<i32>) -> (u32, u32) {
+        object_picking::get_render_position(viewport_position, viewport_size, &self.get_render_extent())
+    }
+    // the command buffers in flight still reference the spectrum textures, so the swap waits for the next frame.
+    pub fn request_ocean_reinitialize(&mut self, ocean_settings: &OceanSettings) {
+        log::info!("request_ocean_reinitialize");
+        self._pending_ocean_settings = Some(ocean_settings.clone());
+    }
+    // the world height of the ocean surface for the buoyancy and the camera collision, evaluated on the cpu.
+    // time: the simulation time of the ocean, the largest two grids only.
+    pub fn get_ocean_height(&self, ocean_settings: &OceanSettings, world_xz: &Vector2<f32>, time: f32) -> f32 {
+        match self.get_project_renderer().get_ocean_height_field() {
+            Some(ocean_height_field) => ocean_settings._ocean_height + ocean_height_field.get_ocean_height(world_xz, time),
+            None => ocean_settings._ocean_height,
+        }
+    }
+    pub fn get_ocean_normal(&self, world_xz: &Vector2<f32>, time: f32) -> Vector3<f32> {
+        match self.get_project_renderer().get_ocean_height_field() {
+            Some(ocean_height_field) => ocean_height_field.get_ocean_normal(world_xz, time),
+            None => Vector3::new(0.0, 1.0, 0.0),
+        }
+    }
+    // the wind, the omega, the amplitude.. of the spectrum, the consecutive requests of a frame are merged.
+    pub fn request_ocean_spectrum_update(&mut self, ocean_settings: &OceanSettings) {
+        log::debug!("request_ocean_spectrum_update: wind {}, omega {}, amplitude {}", ocean_settings._wind_speed, ocean_settings._omega, ocean_settings._amplitude);
+        self._pending_ocean_spectrum_settings = Some(ocean_settings.clone());
+    }
+    // the a/b comparison of the depth conventions, the pipelines and the render targets are recreated with the swapchain.
+    pub fn set_reversed_z(&mut self, reversed_z: bool, infinite_far_plane: bool) {
+        unsafe {
+            if constants::REVERSED_Z == reversed_z && constants::INFINITE_FAR_PLANE == infinite_far_plane {
+                return;
+            }
+            constants::REVERSED_Z = reversed_z;
+            constants::INFINITE_FAR_PLANE = infinite_far_plane;
+            let (near, far) = (constants::NEAR, constants::FAR);
+            let distance = far * 0.9;
+            log::info!(
+                "set_reversed_z: {}, infinite far plane: {}, depth resolution at {}m: {}m",
+                reversed_z,
+                math::is_infinite_far_plane(),
+                distance,
+                math::get_depth_resolution(distance, near, far)
+            );
+        }
+        self.set_need_recreate_swapchain(true);
+    }
+
+    pub fn get_present_mode(&self) -> PresentMode { self._present_mode }
+    pub fn get_active_present_mode(&self) -> vk::PresentModeKHR { self._swapchain_data._swapchain_present_mode }
+    // the swapchain is recreated with the requested mode at the next frame boundary
+    pub fn set_present_mode(&mut self, present_mode: PresentMode) {
+        if present_mode == self._present_mode {
+            return;
+        }
+        log::info!("set_present_mode: {:?}", present_mode);
+        self._present_mode = present_mode;
+        self._present_mode_overlay_frames = PRESENT_MODE_OVERLAY_FRAMES;
+        self.set_need_recreate_swapchain(true);
+    }
+    // FIFO -> MAILBOX -> IMMEDIATE
+    pub fn next_present_mode(&mut self) {
+        let present_mode = self._present_mode.get_next_present_mode();
+        self.set_present_mode(present_mode);
+    }
+
+    pub fn get_tonemap_settings(&self) -> &TonemapSettings { &self._tonemap_settings }
+    pub fn set_exposure(&mut self, exposure: f32) {
+        self._tonemap_settings._exposure = exposure.max(tonemap::MIN_EXPOSURE).min(tonemap::MAX_EXPOSURE);
+    }
+    pub fn set_tonemapper(&mut self, tonemap_operator: TonemapOperator) {
+        log::info!("set_tonemapper: {:?}", tonemap_operator);
+        self._tonemap_settings._operator = tonemap_operator;
+    }
+    pub fn set_tonemap_gamma(&mut self, gamma: f32) {
+        self._tonemap_settings._gamma = gamma.max(0.1);
+    }
+    // None -> Reinhard -> ACES -> Uncharted2
+    pub fn next_tonemapper(&mut self) {
+        let tonemap_operator = self._tonemap_settings._operator.get_next_tonemap_operator();
+        self.set_tonemapper(tonemap_operator);
+    }
+    pub fn is_hdr_output(&self) -> bool { swapchain::is_hdr_color_space(self._swapchain_data._swapchain_color_space) }
+    // the swapchain is recreated with constants::HDR_SURFACE_FORMATS, VK_EXT_swapchain_colorspace is enabled only with constants::ENABLE_HDR_OUTPUT.
+    pub fn set_hdr_output(&mut self, is_hdr_output: bool) {
+        if is_hdr_output == self._is_hdr_output_requested {
+            return;
+        }
+        log::info!("set_hdr_output: {}", is_hdr_output);
+        self._is_hdr_output_requested = is_hdr_output;
+        self.set_need_recreate_swapchain(true);
+    }
+    // uploaded by the project with the render_final pipeline, see tonemap::get_push_constant_range_render_final
+    pub fn get_push_constant_render_final(&self) -> PushConstant_RenderFinal {
+        tonemap::create_push_constant_render_final(&self._tonemap_settings, self._swapchain_data._swapchain_color_space)
+    }
+
+    pub fn get_render_scale(&self) -> f32 { self._render_scale }
+    // only the render targets and their framebuffers are recreated at the next frame boundary, the swapchain is kept.
+    pub fn set_render_scale(&mut self, render_scale: f32) {
+        if let Some(render_scale) = get_changed_render_scale(self._render_scale, render_scale) {
+            log::info!("set_render_scale: {}", render_scale);
+            self._render_scale = render_scale;
+            self.set_need_recreate_render_targets(true);
+        }
+    }
+    // the project creates SceneColor and SceneDepth with this as TextureCreateInfo::_texture_samples,
+    // and the forward passes with RenderPassDataCreateInfo::enable_msaa, resolving into the single sample targets of the post process.
+    pub fn get_msaa_sample_count(&self) -> vk::SampleCountFlags { self._msaa_sample_count }
+    pub fn is_msaa_enabled(&self) -> bool { vk::SampleCountFlags::TYPE_1 != self._msaa_sample_count }
+    // 1, 2, 4, 8: the render targets, the render passes and the pipelines are recreated at the next frame boundary like set_render_scale.
+    pub fn set_msaa_sample_count(&mut self, sample_count: u32) {
+        let msaa_sample_count = device::get_msaa_sample_count_flags(sample_count, self._render_features._msaa_samples);
+        if msaa_sample_count == self._msaa_sample_count {
+            return;
+        }
+        log::info!("set_msaa_sample_count: {:?}", msaa_sample_count);
+        self._msaa_sample_count = msaa_sample_count;
+        self.set_need_recreate_render_targets(true);
+    }
+    // 1x -> 2x -> 4x -> 8x -> 1x, the counts over the device limit are skipped
+    pub fn next_msaa_sample_count(&mut self) {
+        let sample_count = self._msaa_sample_count.as_raw() * 2;
+        if self._render_features._msaa_samples.as_raw() < sample_count {
+            self.set_msaa_sample_count(1);
+        } else {
+            self.set_msaa_sample_count(sample_count);
+        }
+    }
+    pub fn get_need_recreate_render_targets(&self) -> bool { self._need_recreate_render_targets }
+    pub fn set_need_recreate_render_targets(&mut self, value: bool) {
+        log::info!("set_need_recreate_render_targets: {}", value);
+        self._need_recreate_render_targets = value;
+    }
+    // the size of the scene render targets, SceneConstants::_screen_size
+    pub fn get_render_extent(&self) -> vk::Extent2D {
+        get_render_extent(&self._swapchain_data._swapchain_extent, self._render_scale)
+    }
+    pub fn get_screen_size(&self) -> Vector2<f32> {
+        let render_extent = self.get_render_extent();
+        Vector2::new(render_extent.width as f32, render_extent.height as f32)
+    }
+    // the size of the swapchain images, SceneConstants::_backbuffer_size
+    pub fn get_backbuffer_size(&self) -> Vector2<f32> {
+        let swapchain_extent = &self._swapchain_data._swapchain_extent;
+        Vector2::new(swapchain_extent.width as f32, swapchain_extent.height as f32)
+    }
+
     pub fn set_need_recreate_swapchain(&mut self, value: bool) {
         log::info!("set_need_recreate_swapchain: {}", value);
         self._need_recreate_swapchain = value;
@@ -297,6 +765,7 @@ impl RendererData {
     pub fn get_device_memory_properties(&self) -> &vk::PhysicalDeviceMemoryProperties { &self._device_memory_properties }
     pub fn get_physical_device(&self) -> vk::PhysicalDevice { self._physical_device }
     pub fn get_swap_chain_data(&self) -> &SwapchainData { &self._swapchain_data }
+    pub fn is_swapchain_srgb(&self) -> bool { vulkan_context::is_srgb_format(self._swapchain_data._swapchain_image_format) }
     pub fn get_swap_chain_image_views(&self) -> &SwapchainArray<vk::ImageView> { &self._swapchain_data._swapchain_image_views }
     pub fn get_swap_chain_support_details(&self) -> &swapchain::SwapchainSupportDetails { &self._swapchain_support_details }
     pub fn get_swap_chain_index(&self) -> u32 { self._swapchain_index }
@@ -306,40 +775,154 @@ impl RendererData {
     pub fn get_current_command_buffer(&self) -> vk::CommandBuffer { self._command_buffers[self._swapchain_index as usize] }
     pub fn get_graphics_queue(&self) -> vk::Queue { self._queue_family_datas._graphics_queue }
     pub fn get_present_queue(&self) -> vk::Queue { self._queue_family_datas._present_queue }
+    pub fn get_draw_call_count(&self) -> u32 { self._draw_call_count.get() }
+    pub fn get_dispatch_count(&self) -> u32 { self._dispatch_count.get() }
+    pub fn get_uniform_upload_stats(&self) -> UniformUploadStats { self._uniform_upload_stats.get() }
+    pub fn get_uniform_arena(&self) -> &RcRefCell<UniformArena> { &self._uniform_arena }
+    // the per frame constants of the project, written without vkMapMemory by upload_shader_buffer_data.
+    // the block keeps the offset over destroy_uniform_buffers and the swapchain recreation.
+    pub fn create_uniform_arena_buffer_data(&self, buffer_name: &str, buffer_size: vk::DeviceSize) -> ShaderBufferData {
+        self._uniform_arena.borrow_mut().create_shader_buffer_data(buffer_name, buffer_size).unwrap_or_else(|e| panic!("create_uniform_arena_buffer_data: {}", e))
+    }
+    pub fn get_shading_rate_quality(&self) -> shading_rate::ShadingRateQuality {
+        if self._render_features._is_fragment_shading_rate_supported {
+            unsafe { constants::SHADING_RATE_QUALITY }
+        } else {
+            shading_rate::ShadingRateQuality::Off
+        }
+    }
+    pub fn is_variable_rate_shading_enabled(&self) -> bool { shading_rate::ShadingRateQuality::Off != self.get_shading_rate_quality() }
+    pub fn is_stencil_supported(&self) -> bool { self._render_features._depth_stencil_format.is_some() }
+    pub fn is_bindless_textures_supported(&self) -> bool { self._render_features._is_descriptor_indexing_supported }
+    pub fn get_max_bindless_texture_count(&self) -> u32 { self._render_features._max_bindless_texture_count }
+    pub fn is_draw_indirect_count_supported(&self) -> bool { self._render_features._is_draw_indirect_count_supported }
+    pub fn is_fill_mode_non_solid_supported(&self) -> bool { self._render_features._is_fill_mode_non_solid_supported }
+    pub fn get_depth_stencil_format(&self) -> Option<vk::Format> { self._render_features._depth_stencil_format }
+    pub fn get_shading_rate_texel_size(&self) -> vk::Extent2D { self._render_features._shading_rate_texel_size }
+    pub fn get_shading_rate_attachment_description(&self) -> Option<ImageAttachmentDescription> {
+        // project render passes can always use this, it is None when the device does not support it.
+        if self.is_variable_rate_shading_enabled() {
+            Some(shading_rate::get_shading_rate_attachment_description())
+        } else {
+            None
+        }
+    }
     pub fn create_render_target<T: Copy>(&self, texture_create_info: &TextureCreateInfo<T>) -> TextureData {
         texture::create_render_target(
             self.get_instance(),
             self.get_device(),
             self.get_physical_device(),
             self.get_device_memory_properties(),
-            self.get_command_pool(),
+            &mut self._staging_buffer_pool.borrow_mut(),
             self.get_graphics_queue(),
             texture_create_info
         )
     }
+    // the geometries and the textures created until end_staging_uploads are submitted once, they can not be used before it.
+    pub fn begin_staging_uploads(&self) {
+        self._staging_buffer_pool.borrow_mut().begin_upload_batch();
+    }
+    pub fn end_staging_uploads(&self) {
+        self._staging_buffer_pool.borrow_mut().end_upload_batch(self.get_device(), self.get_graphics_queue());
+    }
+    // the textures created until end_texture_uploads are uploaded on the transfer queue, they can not be sampled before it.
+    pub fn begin_texture_uploads(&self) {
+        let upload_batch = TextureUploadBatch::create_texture_upload_batch(
+            self.get_device(),
+            self._transfer_command_pool,
+            self._queue_family_datas._transfer_queue,
+            self._queue_family_datas.get_transfer_queue_family_index(),
+            self.get_command_pool(),
+            self.get_graphics_queue(),
+            self._queue_family_datas._queue_family_indices._graphics_queue_index,
+        );
+        *self._texture_upload_batch.borrow_mut() = Some(upload_batch);
+        self.begin_staging_uploads();
+    }
+    // submits the uploads recorded since begin_texture_uploads and waits once.
+    pub fn end_texture_uploads(&self) {
+        if let Some(upload_batch) = self._texture_upload_batch.borrow_mut().take() {
+            upload_batch.submit_and_wait(self.get_device());
+        }
+        // the staging regions of the batch are released after its wait
+        self.end_staging_uploads();
+    }
     pub fn create_texture<T: Copy>(&self, texture_create_info: &TextureCreateInfo<T>) -> TextureData {
+        if let Some(upload_batch) = self._texture_upload_batch.borrow_mut().as_mut() {
+            return texture::create_texture_data_async(
+                self.get_instance(),
+                self.get_device(),
+                self.get_physical_device(),
+                self.get_device_memory_properties(),
+                &mut self._staging_buffer_pool.borrow_mut(),
+                upload_batch,
+                texture_create_info
+            );
+        }
         texture::create_texture_data(
             self.get_instance(),
             self.get_device(),
             self.get_physical_device(),
             self.get_device_memory_properties(),
-            self.get_command_pool(),
+            &mut self._staging_buffer_pool.borrow_mut(),
             self.get_graphics_queue(),
             texture_create_info
         )
     }
+    // the spectrum textures of the ocean are updated in place, the descriptor sets referencing the texture stay valid.
+    pub fn update_texture<T: Copy>(&self, texture_data: &TextureData, datas: &[T]) -> bool {
+        texture::update_texture_data(
+            self.get_instance(),
+            self.get_device(),
+            self.get_physical_device(),
+            self.get_device_memory_properties(),
+            &mut self._staging_buffer_pool.borrow_mut(),
+            self.get_graphics_queue(),
+            texture_data,
+            datas
+        )
+    }
     pub fn destroy_texture(&self, texture_data: &TextureData) {
         texture::destroy_texture_data(self.get_device(), texture_data);
     }
+    pub fn get_texture_mip_drop(&self) -> u32 {
+        let texture_mip_drop = unsafe { constants::TEXTURE_MIP_DROP };
+        if texture_mip_drop < 0 {
+            texture::get_auto_texture_mip_drop(texture::get_device_local_memory_size(self.get_device_memory_properties()))
+        } else {
+            std::cmp::min(texture_mip_drop as u32, constants::MAX_TEXTURE_MIP_DROP)
+        }
+    }
+    // texture_mip_drop: -1 is auto, the textures and the graphics datas referencing them are reloaded.
+    pub fn set_texture_mip_drop(&mut self, texture_mip_drop: i32) {
+        let prev_texture_mip_drop = self.get_texture_mip_drop();
+        unsafe { constants::TEXTURE_MIP_DROP = texture_mip_drop; }
+        if prev_texture_mip_drop == self.get_texture_mip_drop() {
+            return;
+        }
+        log::info!("set_texture_mip_drop: {} -> {}", prev_texture_mip_drop, self.get_texture_mip_drop());
+        self.device_wait_idle();
+        let resources = self._resources.clone();
+        self.destroy_framebuffer_and_descriptors();
+        resources.borrow_mut().unload_graphics_datas(self);
+        resources.borrow_mut().reload_texture_datas(self);
+        resources.borrow_mut().load_graphics_datas(self);
+        self.prepare_framebuffer_and_descriptors();
+        self.set_is_first_rendering(true);
+    }
     pub fn create_geometry_buffer(&self, geometry_name: &String, geometry_create_info: &geometry_buffer::GeometryCreateInfo) -> geometry_buffer::GeometryData {
-        geometry_buffer::create_geometry_data(
+        let mut staging_buffer_pool = self._staging_buffer_pool.borrow_mut();
+        let geometry_data = geometry_buffer::create_geometry_data(
             self.get_device(),
-            self.get_command_pool(),
-            self.get_graphics_queue(),
             self.get_device_memory_properties(),
+            &mut staging_buffer_pool,
             geometry_name,
             geometry_create_info
-        )
+        );
+        if false == staging_buffer_pool.is_upload_batch() {
+            staging_buffer_pool.flush_uploads(self.get_device(), self.get_graphics_queue());
+        }
+        geometry_data
     }
     pub fn destroy_geomtry_buffer(&self, geometry_data: &geometry_buffer::GeometryData) {
         geometry_buffer::destroy_geometry_data(self.get_device(), geometry_data);
@@ -347,15 +930,28 @@ impl RendererData {
     pub fn destroy_renderer_data(&mut self) {
         unsafe {
             self.destroy_framebuffer_and_descriptors();
+            self._post_process_consumers.borrow_mut().destroy_post_process_consumers(self);
+            self._terrain.borrow_mut().destroy_terrain_chunks(self);
             self.destroy_uniform_buffers();
+            self._uniform_arena.borrow_mut().destroy_uniform_arena(&self._device);
+            self._staging_buffer_pool.borrow_mut().destroy_staging_buffer_pool(&self._device);
             image_sampler::destroy_image_samplers(self.get_device(), &self._image_samplers);
+            self._gpu_breadcrumbs.borrow_mut().destroy_gpu_breadcrumbs(&self._device);
+            self._gpu_profiler.borrow_mut().destroy_gpu_profiler(&self._device);
+            self._visibility_stats.borrow_mut().destroy_visibility_stats(&self._device);
+            self._screenshot_capture.borrow_mut().destroy_screenshot_capture(&self._device);
+            self._object_picking.borrow_mut().destroy_object_picking(&self._device);
             self.destroy_render_targets();
             sync::destroy_semaphores(&self._device, &self._image_available_semaphores);
             sync::destroy_semaphores(&self._device, &self._render_finished_semaphores);
             sync::destroy_fences(&self._device, &self._frame_fences);
             command_buffer::destroy_command_buffers(&self._device, self._command_pool, &self._command_buffers);
+            command_buffer::destroy_command_buffers(&self._device, self._command_pool, &vec![self._capture_command_buffer]);
             command_buffer::destroy_command_pool(&self._device, self._command_pool);
+            command_buffer::destroy_command_pool(&self._device, self._transfer_command_pool);
             swapchain::destroy_swapchain_data(&self._device, &self._swapchain_interface, &self._swapchain_data);
+            device_memory_allocator::destroy_device_memory_allocator(&self._device);
+            object_name::destroy_object_name_registry();
             device::destroy_device(&self._device);
             device::destroy_vk_surface(&self._surface_interface, self._surface);
             if self._debug_util_interface.is_some() {
@@ -387,7 +983,7 @@ impl RendererData {
         }
     }
 
-    pub fn dispatch_material_instance<T>(
+    pub fn dispatch_material_instance<T: PushConstant>(
         &self,
         command_buffer: vk::CommandBuffer,
         swapchain_index: u32,
@@ -409,7 +1005,7 @@ impl RendererData {
         self.dispatch_render_pass_pipeline(command_buffer, swapchain_index, pipeline_binding_data, group_count_x, group_count_y, group_count_z, custom_descriptor_sets, push_constant_data);
     }
 
-    pub fn dispatch_render_pass_pipeline<T>(
+    pub fn dispatch_render_pass_pipeline<T: PushConstant>(
         &self,
         command_buffer: vk::CommandBuffer,
         swapchain_index: u32,
@@ -421,6 +1017,7 @@ impl RendererData {
         push_constant_data: Option<&T>,
     ) {
         let pipeline_data = &pipeline_binding_data.get_pipeline_data().borrow();
+        self.insert_pass_barriers(command_buffer, pipeline_binding_data.get_render_pass_data().borrow().get_render_pass_data_name(), true);
         self.begin_compute_pipeline(command_buffer, pipeline_data);
         self.bind_descriptor_sets(command_buffer, swapchain_index, pipeline_binding_data, custom_descriptor_sets);
         if let Some(push_constant_data) = push_constant_data {
@@ -433,7 +1030,7 @@ impl RendererData {
         self.dispatch_compute_pipeline(command_buffer, group_count_x, group_count_y, group_count_z);
     }
 
-    pub fn render_material_instance<T>(
+    pub fn render_material_instance<T: PushConstant>(
         &self,
         command_buffer: vk::CommandBuffer,
         swapchain_index: u32,
@@ -454,7 +1051,7 @@ impl RendererData {
         self.render_render_pass_pipeline(command_buffer, swapchain_index, pipeline_binding_data, geometry_data, custom_framebuffer_data, custom_descriptor_sets, push_constant_data);
     }
 
-    pub fn render_render_pass_pipeline<T>(
+    pub fn render_render_pass_pipeline<T: PushConstant>(
         &self,
         command_buffer: vk::CommandBuffer,
         swapchain_index: u32,
@@ -479,6 +1076,141 @@ impl RendererData {
         self.end_render_pass(command_buffer);
     }
 
+    // the instance offsets of the groups in the instance matrix buffer, the groups over MAX_INSTANCE_COUNT are truncated.
+    fn assign_instance_offsets(render_element_groups: &mut [RenderElementGroupData]) -> usize {
+        let max_instance_count = unsafe { constants::MAX_INSTANCE_COUNT };
+        let mut total_instance_count: usize = 0;
+        for render_element_group in render_element_groups.iter_mut() {
+            let instance_count = render_element_group._world_matrices.len().min(max_instance_count - total_instance_count);
+            if instance_count < render_element_group._world_matrices.len() {
+                log::warn!("assign_instance_offsets: exceeded MAX_INSTANCE_COUNT {}, {} instances are dropped", max_instance_count, render_element_group._world_matrices.len() - instance_count);
+                render_element_group._world_matrices.truncate(instance_count);
+            }
+            render_element_group._object_ids.resize(instance_count, render_object_storage::INVALID_OBJECT_ID);
+            render_element_group._coverages.truncate(instance_count);
+            render_element_group._bound_spheres.truncate(instance_count);
+            render_element_group._material_parameter_overrides.resize(instance_count, MaterialParameterOverrides::default());
+            render_element_group._instance_offset = total_instance_count as u32;
+            total_instance_count += instance_count;
+        }
+        total_instance_count
+    }
+
+    // one draw per (geometry, material instance) group of the static objects, see SceneManagerData::get_static_render_element_groups.
+    // the model matrices of every group are uploaded once into the instance matrix buffer, the groups over MAX_INSTANCE_COUNT are truncated.
+    // the pipelines with _use_bindless_textures bind the descriptor sets once, the textures are indexed by the bindless material buffer.
+    // the groups of dispatch_occlusion_culling are drawn by cmd_draw_indexed_indirect_count with the visible instances of the gpu.
+    pub fn render_solid_instanced(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        swapchain_index: u32,
+        render_pass_pipeline_data_name: &str,
+        render_element_groups: &mut [RenderElementGroupData],
+        custom_framebuffer_data: Option<&FramebufferData>,
+    ) {
+        RendererData::assign_instance_offsets(render_element_groups);
+        let mut instance_matrices: Vec<Matrix4<f32>> = Vec::new();
+        let mut instance_object_ids: Vec<u32> = Vec::new();
+        let mut instance_bindless_materials: Vec<BindlessMaterialData> = Vec::new();
+        let mut instance_material_overrides: Vec<MaterialParameterOverrides> = Vec::new();
+        for render_element_group in render_element_groups.iter() {
+            instance_matrices.extend_from_slice(&render_element_group._world_matrices);
+            instance_object_ids.extend_from_slice(&render_element_group._object_ids);
+            instance_material_overrides.extend_from_slice(&render_element_group._material_parameter_overrides);
+            let bindless_material_data = render_element_group._material_instance_data.borrow()._bindless_material_data;
+            instance_bindless_materials.resize(instance_matrices.len(), bindless_material_data);
+        }
+        if instance_matrices.is_empty() {
+            return;
+        }
+
+        let instance_matrix_buffer = self.get_project_renderer().get_shader_buffer_data_from_str(render_element::INSTANCE_MATRIX_BUFFER_NAME);
+        self.upload_shader_buffer_datas(command_buffer, swapchain_index, instance_matrix_buffer, &instance_matrices);
+        let instance_object_id_buffer = self.get_project_renderer().get_shader_buffer_data_from_str(render_element::INSTANCE_OBJECT_ID_BUFFER_NAME);
+        self.upload_shader_buffer_datas(command_buffer, swapchain_index, instance_object_id_buffer, &instance_object_ids);
+        if self.is_bindless_textures_supported() {
+            let bindless_material_buffer = self.get_shader_buffer_data_from_str(bindless_texture::BINDLESS_MATERIAL_BUFFER_NAME);
+            self.upload_shader_buffer_datas(command_buffer, swapchain_index, bindless_material_buffer, &instance_bindless_materials);
+        }
+        let material_override_buffer = self.get_shader_buffer_data_from_str(material_instance::MATERIAL_OVERRIDE_BUFFER_NAME);
+        self.upload_shader_buffer_datas(command_buffer, swapchain_index, material_override_buffer, &instance_material_overrides);
+        let mut bound_bindless_pipeline: vk::Pipeline = vk::Pipeline::null();
+        for render_element_group in render_element_groups.iter() {
+            if 0 == render_element_group.get_instance_count() {
+                continue;
+            }
+            let material_instance_data = render_element_group._material_instance_data.borrow();
+            let pipeline_binding_data = self.get_debug_view_pipeline_binding_data(&material_instance_data, render_pass_pipeline_data_name);
+            let render_pass_data = &pipeline_binding_data.get_render_pass_data().borrow();
+            let pipeline_data = &pipeline_binding_data.get_pipeline_data().borrow();
+            let is_gpu_culled = occlusion_culling::INVALID_DRAW_COMMAND_INDEX != render_element_group._draw_command_index;
+            let push_constant_data = PushConstant_RenderInstanced {
+                _instance_offset: render_element_group._instance_offset,
+                _use_visible_instances: is_gpu_culled as u32,
+                _debug_view_mode: self.get_debug_view_mode() as u32,
+                ..Default::default()
+            };
+            self.begin_render_pass_pipeline(command_buffer, swapchain_index, render_pass_data, pipeline_data, custom_framebuffer_data);
+            if false == pipeline_data._use_bindless_textures {
+                self.bind_descriptor_sets(command_buffer, swapchain_index, pipeline_binding_data, None);
+                bound_bindless_pipeline = vk::Pipeline::null();
+            } else if bound_bindless_pipeline != pipeline_data._pipeline {
+                // the other bindings of set 0 are the same for every material instance of the pipeline
+                self.bind_descriptor_sets(command_buffer, swapchain_index, pipeline_binding_data, None);
+                self.bind_bindless_texture_descriptor_set(command_buffer, pipeline_data);
+                bound_bindless_pipeline = pipeline_data._pipeline;
+            }
+            self.upload_push_constant_data(command_buffer, pipeline_data, &push_constant_data);
+            let geometry_data = render_element_group._geometry_data.borrow();
+            if self.is_visibility_stats_recording() {
+                let triangle_count = geometry_data._vertex_index_count / 3;
+                let weights: Vec<f64> = (0..render_element_group._object_ids.len()).map(|i| {
+                    visibility_stats::get_heuristic_weight(render_element_group._coverages.get(i).cloned().unwrap_or(0.0), triangle_count)
+                }).collect();
+                self.begin_draw_stats(command_buffer, &render_element_group._object_ids, &weights, &material_instance_data._material_instance_data_name);
+            }
+            if is_gpu_culled {
+                self.draw_elements_indirect_count(command_buffer, swapchain_index, &geometry_data, render_element_group._draw_command_index);
+            } else {
+                self.draw_elements_instanced(command_buffer, &geometry_data, &[], render_element_group.get_instance_count());
+            }
+            self.end_draw_stats(command_buffer);
+            self.end_render_pass(command_buffer);
+        }
+    }
+
+    // the selected objects: the mask pass writes the stencil, then the outline pass draws where the stencil is not written.
+    // the pipelines must be in the same render pass with a depth stencil attachment, see SELECTION_OUTLINE_MASK_PIPELINE_NAME.
+    pub fn render_selection_outline<T: PushConstant>(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        swapchain_index: u32,
+        selected_render_elements: &[RenderElementData],
+        push_constant_datas: &[T],
+        custom_framebuffer_data: Option<&FramebufferData>,
+    ) {
+        if false == self.is_stencil_supported() || selected_render_elements.is_empty() {
+            return;
+        }
+        for (index, pipeline_name) in [render_element::SELECTION_OUTLINE_MASK_PIPELINE_NAME, render_element::SELECTION_OUTLINE_PIPELINE_NAME].iter().enumerate() {
+            for (element_index, (render_element, push_constant_data)) in selected_render_elements.iter().zip(push_constant_datas.iter()).enumerate() {
+                let material_instance_data = render_element._material_instance_data.borrow();
+                let pipeline_binding_data = material_instance_data.get_pipeline_binding_data(pipeline_name);
+                let render_pass_data = &pipeline_binding_data.get_render_pass_data().borrow();
+                let pipeline_data = &pipeline_binding_data.get_pipeline_data().borrow();
+                self.begin_render_pass_pipeline(command_buffer, swapchain_index, render_pass_data, pipeline_data, custom_framebuffer_data);
+                if 0 == index && 0 == element_index {
+                    self.clear_stencil(command_buffer, render_pass_data, custom_framebuffer_data, 0);
+                }
+                self.set_stencil_reference(command_buffer, pipeline_data, render_element::SELECTION_OUTLINE_STENCIL_REFERENCE);
+                self.bind_descriptor_sets(command_buffer, swapchain_index, pipeline_binding_data, None);
+                self.upload_push_constant_data(command_buffer, pipeline_data, push_constant_data);
+                self.draw_elements(command_buffer, &render_element._geometry_data.borrow());
+                self.end_render_pass(command_buffer);
+            }
+        }
+    }
+
     pub fn begin_compute_pipeline(
         &self,
         command_buffer: vk::CommandBuffer,
@@ -502,12 +1234,43 @@ impl RendererData {
             Some(custom_framebuffer) => custom_framebuffer,
             None => resources.get_framebuffer_data(render_pass_data.get_render_pass_data_name().as_str()).as_ptr()
         };
+        let subpass_index = pipeline_data._pipeline_subpass_index;
+        // the merged render pass kept open by end_render_pass continues from its current subpass
+        let is_continued = match self._merged_render_pass_state.get() {
+            Some(state) if state._render_pass == render_pass_data._render_pass && state._subpass_index <= subpass_index => true,
+            Some(_) => {
+                log::warn!("begin_render_pass_pipeline: {} begins before the last subpass of the merged render pass", render_pass_data.get_render_pass_data_name());
+                self.end_merged_render_pass(command_buffer);
+                false
+            },
+            None => false,
+        };
+        if false == is_continued {
+            self.insert_pass_barriers(command_buffer, render_pass_data.get_render_pass_data_name(), false);
+        }
+        self.begin_debug_label(command_buffer, render_pass_data.get_render_pass_data_name());
         unsafe {
-            let render_pass_begin_info = (*framebuffer_data)._render_pass_begin_infos[swapchain_index as usize];
-            let pipeline_bind_point = pipeline_data._pipeline_bind_point;
-            let pipeline_dynamic_states = &pipeline_data._pipeline_dynamic_states;
-            self._device.cmd_begin_render_pass(command_buffer, &render_pass_begin_info, vk::SubpassContents::INLINE);
+            if is_continued {
+                let state = self._merged_render_pass_state.get().unwrap();
+                for _ in state._subpass_index..subpass_index {
+                    self._device.cmd_next_subpass(command_buffer, vk::SubpassContents::INLINE);
+                }
+            } else {
+                let render_pass_begin_info = (&*framebuffer_data)._render_pass_begin_infos[swapchain_index as usize];
+                self._device.cmd_begin_render_pass(command_buffer, &render_pass_begin_info, vk::SubpassContents::INLINE);
+                for _ in 0..subpass_index {
+                    self._device.cmd_next_subpass(command_buffer, vk::SubpassContents::INLINE);
+                }
+            }
+            if 1 < render_pass_data._subpass_count {
+                self._merged_render_pass_state.set(Some(MergedRenderPassState {
+                    _render_pass: render_pass_data._render_pass,
+                    _subpass_index: subpass_index,
+                    _subpass_count: render_pass_data._subpass_count,
+                }));
+            }
 
+            let pipeline_dynamic_states = &pipeline_data._pipeline_dynamic_states;
             if pipeline_dynamic_states.contains(&vk::DynamicState::VIEWPORT) {
                 self._device.cmd_set_viewport(command_buffer, 0, &[(*framebuffer_data)._framebuffer_info._framebuffer_view_port]);
             }
@@ -516,7 +1279,176 @@ impl RendererData {
                 self._device.cmd_set_scissor(command_buffer, 0, &[(*framebuffer_data)._framebuffer_info._framebuffer_scissor_rect]);
             }
 
-            self._device.cmd_bind_pipeline(command_buffer, pipeline_bind_point, pipeline_data._pipeline);
+            self._device.cmd_bind_pipeline(command_buffer, pipeline_data._pipeline_bind_point, pipeline_data._pipeline);
+        }
+    }
+
+    // merged render pass: moves to the next subpass and binds the pipeline of it, the dynamic viewport and scissor are kept.
+    pub fn next_subpass_pipeline(&self, command_buffer: vk::CommandBuffer, pipeline_data: &PipelineData) {
+        if let Some(mut state) = self._merged_render_pass_state.get() {
+            state._subpass_index += 1;
+            self._merged_render_pass_state.set(Some(state));
+        }
+        unsafe {
+            self._device.cmd_next_subpass(command_buffer, vk::SubpassContents::INLINE);
+            self._device.cmd_bind_pipeline(command_buffer, pipeline_data._pipeline_bind_point, pipeline_data._pipeline);
+        }
+    }
+
+    // the remaining subpasses of the merged render pass are empty, ex) the second pass is disabled in this frame
+    pub fn end_merged_render_pass(&self, command_buffer: vk::CommandBuffer) {
+        if let Some(state) = self._merged_render_pass_state.take() {
+            unsafe {
+                for _ in (state._subpass_index + 1)..state._subpass_count {
+                    self._device.cmd_next_subpass(command_buffer, vk::SubpassContents::INLINE);
+                }
+                self._device.cmd_end_render_pass(command_buffer);
+            }
+        }
+    }
+
+    pub fn get_letterbox_rect(&self, framebuffer_data: &FramebufferData) -> ViewportRect {
+        let framebuffer_info = &framebuffer_data._framebuffer_info;
+        viewport::get_letterbox_rect(&Vector2::new(framebuffer_info._framebuffer_width as i32, framebuffer_info._framebuffer_height as i32))
+    }
+
+    // final pass: fill the bars outside of the target aspect, must be called in the render pass.
+    pub fn clear_letterbox_bars(&self, command_buffer: vk::CommandBuffer, framebuffer_data: &FramebufferData) {
+        let framebuffer_info = &framebuffer_data._framebuffer_info;
+        let region = ViewportRect::create_viewport_rect(0, 0, framebuffer_info._framebuffer_width as i32, framebuffer_info._framebuffer_height as i32);
+        let inner_rect = self.get_letterbox_rect(framebuffer_data);
+        let clear_rects: Vec<vk::ClearRect> = viewport::get_letterbox_bar_rects(&region, &inner_rect).iter().map(|bar_rect| {
+            vk::ClearRect {
+                rect: bar_rect.to_vk_rect_2d(),
+                base_array_layer: 0,
+                layer_count: 1,
+            }
+        }).collect();
+        if false == clear_rects.is_empty() {
+            let letterbox_color = unsafe { constants::LETTERBOX_COLOR };
+            let clear_attachments = [vk::ClearAttachment {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                color_attachment: 0,
+                clear_value: vulkan_context::get_color_clear_value(letterbox_color[0], letterbox_color[1], letterbox_color[2], letterbox_color[3]),
+            }];
+            unsafe {
+                self._device.cmd_clear_attachments(command_buffer, &clear_attachments, &clear_rects);
+            }
+        }
+    }
+
+    // final pass and ui pass: render into the inner rect of the target aspect.
+    pub fn set_letterbox_viewport(&self, command_buffer: vk::CommandBuffer, framebuffer_data: &FramebufferData) {
+        if 0.0 < viewport::get_letterbox_target_aspect() {
+            let inner_rect = self.get_letterbox_rect(framebuffer_data);
+            unsafe {
+                self._device.cmd_set_viewport(command_buffer, 0, &[inner_rect.to_vk_viewport()]);
+                self._device.cmd_set_scissor(command_buffer, 0, &[inner_rect.to_vk_rect_2d()]);
+            }
+        }
+    }
+
+    // shadow atlas: one tile per draw in the single shadow render pass, the pipeline must have the dynamic viewport and scissor.
+    pub fn set_shadow_atlas_tile_viewport(&self, command_buffer: vk::CommandBuffer, shadow_atlas_tile: &ShadowAtlasTile) {
+        let tile_rect = shadow_atlas_tile.get_viewport_rect();
+        unsafe {
+            self._device.cmd_set_viewport(command_buffer, 0, &[tile_rect.to_vk_viewport()]);
+            self._device.cmd_set_scissor(command_buffer, 0, &[tile_rect.to_vk_rect_2d()]);
+        }
+    }
+
+    // impostor capture: one frame of the atlas per view, the pipeline must have the dynamic viewport and scissor.
+    pub fn set_impostor_capture_viewport(&self, command_buffer: vk::CommandBuffer, capture_view: &ImpostorCaptureView) {
+        unsafe {
+            self._device.cmd_set_viewport(command_buffer, 0, &[capture_view._frame_rect.to_vk_viewport()]);
+            self._device.cmd_set_scissor(command_buffer, 0, &[capture_view._frame_rect.to_vk_rect_2d()]);
+        }
+    }
+
+    // renders the requested models into the impostor atlases outside of the frame, reads them back and saves the cache.
+    pub fn bake_impostors(&self) {
+        let bake_requests = self._resources.borrow_mut().take_impostor_bake_requests();
+        self.device_wait_idle();
+        let bake_count = bake_requests.len();
+        for (bake_index, model_name) in bake_requests.iter().enumerate() {
+            progress::report_progress(ProgressTask::ImpostorBake, bake_index as f32 / bake_count as f32);
+            let bake_start_time = time::Instant::now();
+            let model_data = self._resources.borrow().get_model_data(model_name).clone();
+            let (impostor_create_info, bound_box, content_hash) = {
+                let model_data = model_data.borrow();
+                let bound_box = model_data._mesh_data.borrow()._bound_box.clone();
+                (model_data._impostor_create_info.clone().unwrap(), bound_box, self._resources.borrow().get_impostor_content_hash(&model_data))
+            };
+            let atlas_layout = ImpostorAtlasLayout::create_impostor_atlas_layout(impostor_create_info._view_count, impostor_create_info._frame_size);
+            let capture_views = impostor::create_impostor_capture_views(&bound_box, &atlas_layout);
+            let capture_targets = ImpostorCaptureTargets::create_impostor_capture_targets(self, &atlas_layout);
+            vulkan_context::run_commands_once(self.get_device(), self.get_command_pool(), self.get_graphics_queue(), |_device: &Device, command_buffer: vk::CommandBuffer| {
+                self.get_project_renderer_mut().render_impostor_capture(command_buffer, self, &model_data.borrow(), &capture_views, &capture_targets);
+            });
+
+            let atlas_data_size = (atlas_layout.get_atlas_width() * atlas_layout.get_atlas_height() * 4) as usize;
+            let mut albedo_datas: Vec<u8> = vec![0; atlas_data_size];
+            let mut normal_datas: Vec<u8> = vec![0; atlas_data_size];
+            texture::read_texture_data(self.get_device(), self.get_command_pool(), self.get_graphics_queue(), self.get_device_memory_properties(), &capture_targets._albedo_texture, &mut albedo_datas);
+            texture::read_texture_data(self.get_device(), self.get_command_pool(), self.get_graphics_queue(), self.get_device_memory_properties(), &capture_targets._normal_texture, &mut normal_datas);
+            capture_targets.destroy_impostor_capture_targets(self);
+
+            let bake_data = ImpostorBakeData {
+                _atlas_layout: atlas_layout,
+                _bound_center: bound_box._center.clone(),
+                _half_extent: impostor::get_impostor_half_extent(&bound_box),
+                _albedo_datas: albedo_datas,
+                _normal_datas: normal_datas,
+            };
+            impostor_cache::save_impostor_cache(&impostor_cache::get_impostor_cache_file_path(model_name), content_hash, &bake_data);
+            self._resources.borrow().regist_impostor_data(self, model_name, bake_data);
+            log::info!("bake impostor {}: {} views, {:.3}ms", model_name, atlas_layout._view_count, bake_start_time.elapsed().as_secs_f64() * 1000.0);
+        }
+        progress::end_progress(ProgressTask::ImpostorBake);
+    }
+
+    // shadow cache: the LOAD_OP_LOAD shadow pass clears only the regions to render, must be called in the render pass.
+    pub fn clear_shadow_region(&self, command_buffer: vk::CommandBuffer, region_rect: &ViewportRect) {
+        self.clear_depth_stencil_region(command_buffer, region_rect, vk::ImageAspectFlags::DEPTH, math::get_far_depth(), 0);
+    }
+
+    // aspect_mask: DEPTH, STENCIL or both, the STENCIL needs a depth stencil attachment. must be called in the render pass.
+    pub fn clear_depth_stencil_region(&self, command_buffer: vk::CommandBuffer, region_rect: &ViewportRect, aspect_mask: vk::ImageAspectFlags, depth: f32, stencil: u32) {
+        let clear_attachments = [vk::ClearAttachment {
+            aspect_mask,
+            color_attachment: 0,
+            clear_value: vulkan_context::get_depth_stencil_clear_value(depth, stencil),
+        }];
+        let clear_rects = [vk::ClearRect {
+            rect: region_rect.to_vk_rect_2d(),
+            base_array_layer: 0,
+            layer_count: 1,
+        }];
+        unsafe {
+            self._device.cmd_clear_attachments(command_buffer, &clear_attachments, &clear_rects);
+        }
+    }
+
+    // the whole framebuffer of the render pass, the depth is kept. must be called in the render pass.
+    pub fn clear_stencil(&self, command_buffer: vk::CommandBuffer, render_pass_data: &RenderPassData, custom_framebuffer_data: Option<&FramebufferData>, stencil: u32) {
+        let resources: Ref<Resources> = self._resources.borrow();
+        let framebuffer_data: *const FramebufferData = match custom_framebuffer_data {
+            Some(custom_framebuffer_data) => custom_framebuffer_data,
+            None => resources.get_framebuffer_data(render_pass_data.get_render_pass_data_name().as_str()).as_ptr()
+        };
+        let framebuffer_info = unsafe { &(*framebuffer_data)._framebuffer_info };
+        let region_rect = ViewportRect::create_viewport_rect(0, 0, framebuffer_info._framebuffer_width as i32, framebuffer_info._framebuffer_height as i32);
+        self.clear_depth_stencil_region(command_buffer, &region_rect, vk::ImageAspectFlags::STENCIL, 1.0, stencil);
+    }
+
+    // the pipeline must declare vk::DynamicState::STENCIL_REFERENCE, otherwise the static reference of the pipeline is used.
+    pub fn set_stencil_reference(&self, command_buffer: vk::CommandBuffer, pipeline_data: &PipelineData, reference: u32) {
+        if pipeline_data.has_dynamic_state(vk::DynamicState::STENCIL_REFERENCE) {
+            unsafe {
+                self._device.cmd_set_stencil_reference(command_buffer, vk::StencilFaceFlags::FRONT_AND_BACK, reference);
+            }
+        } else {
+            log::warn!("set_stencil_reference: {} has no dynamic stencil reference", pipeline_data._pipeline_data_name);
         }
     }
 
@@ -572,10 +1504,24 @@ impl RendererData {
         }
     }
 
-    pub fn upload_push_constant_data<T>(&self, command_buffer: vk::CommandBuffer, pipeline_data: &PipelineData, push_constant_data: &T) {
+    pub fn upload_push_constant_data<T: PushConstant>(&self, command_buffer: vk::CommandBuffer, pipeline_data: &PipelineData, push_constant_data: &T) {
+        let push_constant_layout = &pipeline_data._push_constant_layout;
+        debug_assert!({
+            let result = push_constant_layout.validate_push_constant::<T>(&pipeline_data._pipeline_data_name, self._device_properties.limits.max_push_constants_size);
+            if let Err(e) = result.as_ref() {
+                log::error!("upload_push_constant_data: {}", e);
+            }
+            result.is_ok()
+        }, "upload_push_constant_data: invalid push constant of {}", pipeline_data._pipeline_data_name);
         let constants: &[u8] = system::to_bytes(push_constant_data);
-        unsafe {
-            self._device.cmd_push_constants(command_buffer, pipeline_data._pipeline_layout, vk::ShaderStageFlags::ALL, 0, constants);
+        for push_constant_range in push_constant_layout._push_constant_ranges.iter() {
+            let offset = push_constant_range.offset as usize;
+            let end = constants.len().min(offset + push_constant_range.size as usize);
+            if offset < end {
+                unsafe {
+                    self._device.cmd_push_constants(command_buffer, pipeline_data._pipeline_layout, push_constant_range.stage_flags, push_constant_range.offset, &constants[offset..end]);
+                }
+            }
         }
     }
 
@@ -586,6 +1532,7 @@ impl RendererData {
         group_count_y: u32,
         group_count_z: u32
     ) {
+        self._dispatch_count.set(self._dispatch_count.get() + 1);
         unsafe {
             self._device.cmd_dispatch(command_buffer, 1u32.max(group_count_x), 1u32.max(group_count_y), 1u32.max(group_count_z));
         }
@@ -613,7 +1560,33 @@ impl RendererData {
         );
     }
 
-    pub fn draw_indexed(
+    // the command and the count of the group written by the occlusion culling compute pass, the count is 0 when every instance is culled.
+    pub fn draw_elements_indirect_count(&self, command_buffer: vk::CommandBuffer, swapchain_index: u32, geometry_data: &GeometryData, draw_command_index: u32) {
+        let occlusion_culling = self._occlusion_culling.borrow();
+        let index = swapchain_index as usize;
+        let draw_command_buffer = occlusion_culling._draw_command_buffer.as_ref().unwrap()._buffers[index]._buffer;
+        let draw_count_buffer = occlusion_culling._draw_count_buffer.as_ref().unwrap()._buffers[index]._buffer;
+        let draw_command_stride = std::mem::size_of::<vk::DrawIndexedIndirectCommand>() as u32;
+        const MAX_DRAW_COUNT: u32 = 1;
+        unsafe {
+            let offsets: &[vk::DeviceSize] = &[0];
+            const VERTEX_BUFFER_BINDING_INDEX: u32 = 0;
+            self._device.cmd_bind_vertex_buffers(command_buffer, VERTEX_BUFFER_BINDING_INDEX, &[geometry_data._vertex_buffer_data._buffer], offsets);
+            self._device.cmd_bind_index_buffer(command_buffer, geometry_data._index_buffer_data._buffer, 0, vk::IndexType::UINT32);
+            self._device.cmd_draw_indexed_indirect_count(
+                command_buffer,
+                draw_command_buffer,
+                (draw_command_index * draw_command_stride) as vk::DeviceSize,
+                draw_count_buffer,
+                (draw_command_index as usize * std::mem::size_of::<u32>()) as vk::DeviceSize,
+                MAX_DRAW_COUNT,
+                draw_command_stride,
+            );
+            self._draw_call_count.set(self._draw_call_count.get() + 1);
+        }
+    }
+
+    pub fn draw_indexed(
         &self,
         command_buffer: vk::CommandBuffer,
         vertex_buffers: &[vk::Buffer],
@@ -635,12 +1608,69 @@ impl RendererData {
             }
             self._device.cmd_bind_index_buffer(command_buffer, index_buffer, 0, vk::IndexType::UINT32);
             self._device.cmd_draw_indexed(command_buffer, index_count, instance_count, FIRST_INDEX, VERTEX_OFFSET, FIRST_INSTANCE);
+            self._draw_call_count.set(self._draw_call_count.get() + 1);
         }
     }
 
+    // the merged render pass stays open until its last subpass, the next begin_render_pass_pipeline continues it.
     pub fn end_render_pass(&self, command_buffer: vk::CommandBuffer) {
-        unsafe {
-            self._device.cmd_end_render_pass(command_buffer);
+        match self._merged_render_pass_state.get() {
+            Some(state) if state._subpass_index + 1 < state._subpass_count => (),
+            Some(_) => self.end_merged_render_pass(command_buffer),
+            None => unsafe {
+                self._device.cmd_end_render_pass(command_buffer);
+            },
+        }
+        self.end_debug_label(command_buffer);
+    }
+
+    // debug labels are recorded only in capture mode to keep the fast path free.
+    // the gpu breadcrumbs share the scopes, two tiny buffer writes per scope.
+    pub fn begin_debug_label(&self, command_buffer: vk::CommandBuffer, label_name: &str) {
+        if gpu_breadcrumb::is_gpu_breadcrumbs_enabled() {
+            self._gpu_breadcrumbs.borrow_mut().begin_scope(&self._device, command_buffer, label_name);
+        }
+        self._gpu_profiler.borrow_mut().begin_scope(&self._device, command_buffer, label_name);
+        if self.is_capture_mode() {
+            if let Some(debug_util_interface) = self._debug_util_interface.as_ref() {
+                let label_name = CString::new(label_name).unwrap();
+                let label = vk::DebugUtilsLabelEXT {
+                    p_label_name: label_name.as_ptr(),
+                    ..Default::default()
+                };
+                unsafe {
+                    debug_util_interface.cmd_begin_debug_utils_label(command_buffer, &label);
+                }
+            }
+        }
+    }
+
+    pub fn end_debug_label(&self, command_buffer: vk::CommandBuffer) {
+        if gpu_breadcrumb::is_gpu_breadcrumbs_enabled() {
+            self._gpu_breadcrumbs.borrow_mut().end_scope(&self._device, command_buffer);
+        }
+        self._gpu_profiler.borrow_mut().end_scope(&self._device, command_buffer);
+        if self.is_capture_mode() {
+            if let Some(debug_util_interface) = self._debug_util_interface.as_ref() {
+                unsafe {
+                    debug_util_interface.cmd_end_debug_utils_label(command_buffer);
+                }
+            }
+        }
+    }
+
+    pub fn insert_debug_label(&self, command_buffer: vk::CommandBuffer, label_name: &str) {
+        if self.is_capture_mode() {
+            if let Some(debug_util_interface) = self._debug_util_interface.as_ref() {
+                let label_name = CString::new(label_name).unwrap();
+                let label = vk::DebugUtilsLabelEXT {
+                    p_label_name: label_name.as_ptr(),
+                    ..Default::default()
+                };
+                unsafe {
+                    debug_util_interface.cmd_insert_debug_utils_label(command_buffer, &label);
+                }
+            }
         }
     }
 
@@ -667,6 +1697,25 @@ impl RendererData {
         resources.borrow_mut().load_graphics_datas(self);
         self.prepare_framebuffer_and_descriptors();
         self.set_is_first_rendering(true);
+        self.validate_debug_render_target();
+        self._need_recreate_render_targets = false;
+    }
+
+    // set_render_scale: resize_window without the swapchain, the framebuffers and the pipelines follow the new render targets.
+    pub fn recreate_render_targets(&mut self) {
+        log::info!("<< recreateRenderTargets >> render_scale: {}, render_extent: {:?}", self._render_scale, self.get_render_extent());
+        self.device_wait_idle();
+
+        let resources = self._resources.clone();
+        self.destroy_framebuffer_and_descriptors();
+        resources.borrow_mut().unload_graphics_datas(self);
+        self.destroy_render_targets();
+
+        self.create_render_targets();
+        resources.borrow_mut().load_graphics_datas(self);
+        self.prepare_framebuffer_and_descriptors();
+        self.set_is_first_rendering(true);
+        self.validate_debug_render_target();
     }
 
     pub fn recreate_swapchain(&mut self) {
@@ -681,7 +1730,8 @@ impl RendererData {
             self._surface,
             &self._swapchain_support_details,
             &self._queue_family_datas,
-            unsafe { constants::ENABLE_IMMEDIATE_MODE }
+            self._present_mode,
+            self._is_hdr_output_requested
         );
         self._command_buffers = command_buffer::create_command_buffers(&self._device, self._command_pool, constants::SWAPCHAIN_IMAGE_COUNT as u32);
     }
@@ -712,11 +1762,23 @@ impl RendererData {
             self._device.reset_fences(fences).expect("failed to reset_fences");
 
             let waiting_for_fence = false;
-            self._device.queue_submit(
+            #[cfg(feature = "gpu_fault_injection")]
+            {
+                if constants::DEBUG_INJECT_DEVICE_LOST {
+                    constants::DEBUG_INJECT_DEVICE_LOST = false;
+                    return VkResult::Err(vk::Result::ERROR_DEVICE_LOST);
+                }
+            }
+
+            let submit_result = self._device.queue_submit(
                 self._queue_family_datas._graphics_queue,
                 &[submit_info],
                 if waiting_for_fence { fence } else { vk::Fence::null() }
-            ).expect("vkQueueSubmit failed!");
+            );
+            if let Err(e) = submit_result {
+                log::error!("vkQueueSubmit failed: {:?}", e);
+                return VkResult::Err(e);
+            }
 
             if waiting_for_fence {
                 self._device.wait_for_fences(fences, true, std::u64::MAX).expect("vkWaitForFences failed!");
@@ -752,7 +1814,26 @@ impl RendererData {
         }
     }
 
+    // the arena blocks are written into the mapped memory, the others map and copy per call.
+    fn write_uniform_arena(&self, swapchain_index: u32, shader_buffer_data: &ShaderBufferData, bytes: &[u8], offset: vk::DeviceSize) -> bool {
+        match shader_buffer_data._uniform_arena_handle.as_ref() {
+            Some(uniform_arena_handle) => {
+                self._uniform_arena.borrow_mut().write_bytes(swapchain_index, uniform_arena_handle, offset, bytes);
+                true
+            },
+            None => false,
+        }
+    }
+
     pub fn upload_shader_buffer_data<T>(&self, command_buffer: vk::CommandBuffer, swapchain_index: u32, shader_buffer_data: &ShaderBufferData, upload_data: &T) {
+        let upload_start_time = time::Instant::now();
+        if false == self.write_uniform_arena(swapchain_index, shader_buffer_data, system::to_bytes(upload_data), 0) {
+            self.upload_shader_buffer_data_mapped(command_buffer, swapchain_index, shader_buffer_data, upload_data);
+        }
+        self.add_uniform_upload_time(upload_start_time);
+    }
+
+    fn upload_shader_buffer_data_mapped<T>(&self, command_buffer: vk::CommandBuffer, swapchain_index: u32, shader_buffer_data: &ShaderBufferData, upload_data: &T) {
         let buffer_data = &shader_buffer_data._buffers[swapchain_index as usize];
         if shader_buffer_data._staging_buffers.is_some() {
             let staging_buffer_data = &shader_buffer_data._staging_buffers.as_ref().unwrap()[swapchain_index as usize];
@@ -765,6 +1846,14 @@ impl RendererData {
     }
 
     pub fn upload_shader_buffer_data_offset<T>(&self, command_buffer: vk::CommandBuffer, swapchain_index: u32, shader_buffer_data: &ShaderBufferData, upload_data: &T, offset: vk::DeviceSize) {
+        let upload_start_time = time::Instant::now();
+        if false == self.write_uniform_arena(swapchain_index, shader_buffer_data, system::to_bytes(upload_data), offset) {
+            self.upload_shader_buffer_data_offset_mapped(command_buffer, swapchain_index, shader_buffer_data, upload_data, offset);
+        }
+        self.add_uniform_upload_time(upload_start_time);
+    }
+
+    fn upload_shader_buffer_data_offset_mapped<T>(&self, command_buffer: vk::CommandBuffer, swapchain_index: u32, shader_buffer_data: &ShaderBufferData, upload_data: &T, offset: vk::DeviceSize) {
         let buffer_data = &shader_buffer_data._buffers[swapchain_index as usize];
         if shader_buffer_data._staging_buffers.is_some() {
             let staging_buffer_data = &shader_buffer_data._staging_buffers.as_ref().unwrap()[swapchain_index as usize];
@@ -777,6 +1866,14 @@ impl RendererData {
     }
 
     pub fn upload_shader_buffer_datas<T: Copy>(&self, command_buffer: vk::CommandBuffer, swapchain_index: u32, shader_buffer_data: &ShaderBufferData, upload_data: &[T]) {
+        let upload_start_time = time::Instant::now();
+        if false == self.write_uniform_arena(swapchain_index, shader_buffer_data, system::slice_to_bytes(upload_data), 0) {
+            self.upload_shader_buffer_datas_mapped(command_buffer, swapchain_index, shader_buffer_data, upload_data);
+        }
+        self.add_uniform_upload_time(upload_start_time);
+    }
+
+    fn upload_shader_buffer_datas_mapped<T: Copy>(&self, command_buffer: vk::CommandBuffer, swapchain_index: u32, shader_buffer_data: &ShaderBufferData, upload_data: &[T]) {
         let buffer_data = &shader_buffer_data._buffers[swapchain_index as usize];
         if shader_buffer_data._staging_buffers.is_some() {
             let staging_buffer_data = &shader_buffer_data._staging_buffers.as_ref().unwrap()[swapchain_index as usize];
@@ -794,6 +1891,20 @@ impl RendererData {
     }
 
     pub fn upload_shader_buffer_datas_offset<T: Copy>(&self, command_buffer: vk::CommandBuffer, swapchain_index: u32, shader_buffer_data: &ShaderBufferData, upload_data: &[T], offset: vk::DeviceSize) {
+        let upload_start_time = time::Instant::now();
+        if false == self.write_uniform_arena(swapchain_index, shader_buffer_data, system::slice_to_bytes(upload_data), offset) {
+            self.upload_shader_buffer_datas_offset_mapped(command_buffer, swapchain_index, shader_buffer_data, upload_data, offset);
+        }
+        self.add_uniform_upload_time(upload_start_time);
+    }
+
+    fn add_uniform_upload_time(&self, upload_start_time: time::Instant) {
+        let mut uniform_upload_stats = self._uniform_upload_stats.get();
+        uniform_upload_stats._upload_time_ms += upload_start_time.elapsed().as_secs_f64() * 1000.0;
+        self._uniform_upload_stats.set(uniform_upload_stats);
+    }
+
+    fn upload_shader_buffer_datas_offset_mapped<T: Copy>(&self, command_buffer: vk::CommandBuffer, swapchain_index: u32, shader_buffer_data: &ShaderBufferData, upload_data: &[T], offset: vk::DeviceSize) {
         let buffer_data = &shader_buffer_data._buffers[swapchain_index as usize];
         if shader_buffer_data._staging_buffers.is_some() {
             let staging_buffer_data = &shader_buffer_data._staging_buffers.as_ref().unwrap()[swapchain_index as usize];
@@ -814,6 +1925,51 @@ impl RendererData {
         delta_time: f64,
         elapsed_frame: u64
     ) {
+        // begin capture frame
+        if self._capture_next_frame {
+            self._capture_next_frame = false;
+            self._render_mode = RenderMode::CaptureFrame;
+            self.device_wait_idle();
+            // history dependent passes(TAA, temporal amortization) are reset, so the captured frame is self-contained.
+            self.set_is_first_rendering(true);
+            log::info!("begin capture frame: {}", elapsed_frame);
+        }
+
+        // ocean reinitialize requested by the environment settings or the ocean command
+        if let Some(ocean_settings) = self._pending_ocean_settings.take() {
+            self._pending_ocean_spectrum_settings = None; // the reinitialize regenerates the spectrum too
+            self.device_wait_idle();
+            self.get_project_renderer_mut().reinitialize_ocean(&self, &ocean_settings);
+        } else if let Some(ocean_settings) = self._pending_ocean_spectrum_settings.take() {
+            // the upload waits for the previous submissions reading the spectrum textures, no device_wait_idle
+            self.get_project_renderer_mut().update_ocean_spectrum(&self, &ocean_settings);
+        }
+
+        // impostors requested at load time or by the bake command
+        if self._resources.borrow().has_impostor_bake_requests() {
+            self.bake_impostors();
+        }
+
+        // the sky mode of the environment settings, the cubemap replaces the atmosphere of the light probe
+        self.set_sky_settings(&scene_manager_data._environment_settings._sky);
+        self._volumetric_fog.borrow_mut().set_fog_settings(&scene_manager_data._environment_settings._fog);
+        self.set_terrain_settings(&scene_manager_data._environment_settings._terrain);
+
+        // the scattering orders of the environment settings, the light probe captures the precomputed textures
+        if self._atmosphere.borrow().need_precompute() {
+            self.precompute_atmosphere();
+        }
+
+        // the light probe follows the sun of the environment settings
+        self._light_probe.borrow_mut().set_sun_direction(&scene_manager_data._environment_settings._atmosphere.get_sun_direction());
+        if self._light_probe.borrow().need_capture() {
+            let probe_position = self._light_probe.borrow()._probe_position.clone();
+            self.render_light_probe(&probe_position);
+        }
+
+        // the pick recorded in the previous frame
+        self._object_picking.borrow_mut().read_object_pick(&self._device);
+
         unsafe {
             // frame index
             let frame_index = self._frame_index as usize;
@@ -838,29 +1994,79 @@ impl RendererData {
             self._swapchain_index = swapchain_index;
 
             let present_result: vk::Result = if swapchain_index < constants::SWAPCHAIN_IMAGE_COUNT as u32 && false == failed_acquire_next_image {
+                // the texture bindings of the material editor, the descriptor sets of this swapchain image are not in use
+                self._resources.borrow_mut().apply_pending_descriptor_updates(&self._device, swapchain_index as usize);
+
                 // Begin command buffer
-                let command_buffer = self._command_buffers[swapchain_index as usize];
+                let (command_buffer, command_buffer_usage_flags) = if self.is_capture_mode() {
+                    (self._capture_command_buffer, vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT)
+                } else {
+                    (self._command_buffers[swapchain_index as usize], vk::CommandBufferUsageFlags::SIMULTANEOUS_USE)
+                };
                 let command_buffer_begin_info = vk::CommandBufferBeginInfo {
-                    flags: vk::CommandBufferUsageFlags::SIMULTANEOUS_USE,
+                    flags: command_buffer_usage_flags,
                     ..Default::default()
                 };
                 self._device.begin_command_buffer(command_buffer, &command_buffer_begin_info).expect("vkBeginCommandBuffer failed!");
+                self.begin_gpu_breadcrumbs(command_buffer, swapchain_index);
+                self._frame_graph.borrow_mut().begin_frame_barriers();
+                self._draw_call_count.set(0);
+                self._dispatch_count.set(0);
+                self._merged_render_pass_state.set(None);
+                let map_memory_count_begin = buffer::get_map_memory_count();
+                self._uniform_upload_stats.set(UniformUploadStats::default());
+
+                // a panic discards everything recorded in this command buffer, waits idle
+                // and resets the temporal history, so nothing written by the aborted frame is used again.
+                record_frame_with_recovery(&mut self._render_error_state.borrow_mut(), || {
+                    inject_debug_render_panic();
+
+                    // the skinned vertices for the shadow and the opaque passes
+                    self.dispatch_gpu_skinning(command_buffer, swapchain_index, &scene_manager_data);
+
+                    // renderer - render_scene
+                    self.get_project_renderer_mut().render_scene(
+                        command_buffer,
+                        frame_index,
+                        swapchain_index,
+                        &self,
+                        &scene_manager_data,
+                        font_manager,
+                        ui_manager_data,
+                        elapsed_time,
+                        delta_time,
+                        elapsed_frame
+                    );
+                    self.end_merged_render_pass(command_buffer);
+                    self.end_frame_barriers(command_buffer);
+
+                    // debug histogram of the render target
+                    self._debug_histogram.borrow_mut().dispatch_histogram(command_buffer, swapchain_index, &self, &self._resources.borrow());
+                }, |render_error_message| {
+                    log::error!("render_scene panic(frame: {}): {}", elapsed_frame, render_error_message);
+                    self.recover_render_error(command_buffer, command_buffer_begin_info, swapchain_index);
+                });
+
+                // the constants written in this frame, flushed once before the submit
+                {
+                    let mut uniform_arena = self._uniform_arena.borrow_mut();
+                    uniform_arena.flush_uniform_arena(&self._device, swapchain_index);
+                    let mut uniform_upload_stats = self._uniform_upload_stats.get();
+                    uniform_upload_stats._map_memory_count = (buffer::get_map_memory_count() - map_memory_count_begin) as u32;
+                    uniform_upload_stats._arena_write_count = uniform_arena.take_write_count();
+                    self._uniform_upload_stats.set(uniform_upload_stats);
+                }
 
-                // renderer - render_scene
-                self.get_project_renderer_mut().render_scene(
-                    command_buffer,
-                    frame_index,
-                    swapchain_index,
-                    &self,
-                    &scene_manager_data,
-                    font_manager,
-                    ui_manager_data,
-                    elapsed_time,
-                    delta_time,
-                    elapsed_frame
-                );
+                // the copy of the backbuffer after all the passes of the frame
+                if self._screenshot_capture.borrow().is_requested() {
+                    self.record_screenshot_copy(command_buffer, swapchain_index);
+                }
+                if self._object_picking.borrow().is_requested() {
+                    self.record_object_pick_copy(command_buffer);
+                }
 
                 // End command buffer
+                self._gpu_profiler.borrow_mut().end_frame(&self._device, command_buffer);
                 self._device.end_command_buffer(command_buffer).expect("vkEndCommandBuffer failed!");
 
                 // End Render
@@ -868,6 +2074,14 @@ impl RendererData {
                     self.set_is_first_rendering(false);
                 }
                 let present_swapchain_result = self.present_swapchain(&[command_buffer], frame_fence, image_available_semaphore, render_finished_semaphore);
+                // present_swapchain waits for the device idle, the readback buffer is written
+                if present_swapchain_result.is_ok() {
+                    self._screenshot_capture.borrow_mut().complete_screenshot(&self._device);
+                } else {
+                    self._screenshot_capture.borrow_mut().discard_screenshot(&self._device);
+                }
+                self._object_picking.borrow_mut().end_frame(present_swapchain_result.is_ok());
+                self._visibility_stats.borrow_mut().complete_frame(&self._device, present_swapchain_result.is_ok());
                 match present_swapchain_result {
                     Ok(is_swapchain_suboptimal) => if is_swapchain_suboptimal { vk::Result::SUBOPTIMAL_KHR } else { vk::Result::SUCCESS },
                     Err(err) => err,
@@ -881,12 +2095,319 @@ impl RendererData {
                 log::error!("present swapchain result: {:?}", present_result);
             }
 
+            if vk::Result::ERROR_DEVICE_LOST == present_result {
+                let crash_report = self.create_device_lost_report(swapchain_index, elapsed_frame);
+                log::error!("{}", crash_report);
+                panic!("{}", crash_report);
+            }
+
+            if vk::Result::ERROR_OUT_OF_DATE_KHR == present_result || vk::Result::SUBOPTIMAL_KHR == present_result {
+                self.set_need_recreate_swapchain(true);
+            }
+
+            self._frame_index = (self._frame_index + 1) % (constants::MAX_FRAME_COUNT as i32);
+        }
+
+        // end capture frame, return to the normal path.
+        if self.is_capture_mode() {
+            self.device_wait_idle();
+            self._render_mode = RenderMode::Normal;
+            log::info!("end capture frame: {}", elapsed_frame);
+        }
+    }
+
+    // the swapchain image without TRANSFER_SRC usage falls back to the render target before the tonemap
+    fn record_screenshot_copy(&self, command_buffer: vk::CommandBuffer, swapchain_index: u32) {
+        let screenshot_source = if self._swapchain_data._swapchain_image_usage.contains(vk::ImageUsageFlags::TRANSFER_SRC) {
+            Some(ScreenshotSource {
+                _image: self._swapchain_data._swapchain_images[swapchain_index as usize],
+                _image_layout: vk::ImageLayout::PRESENT_SRC_KHR,
+                _image_format: self._swapchain_data._swapchain_image_format,
+                _width: self._swapchain_data._swapchain_extent.width,
+                _height: self._swapchain_data._swapchain_extent.height,
+            })
+        } else {
+            self.find_debug_render_target_data(screenshot::SCREENSHOT_FALLBACK_RENDER_TARGET_NAME).map(|texture_data| {
+                let texture_data = unsafe { &*texture_data };
+                ScreenshotSource {
+                    _image: texture_data._image,
+                    _image_layout: texture_data._image_info.image_layout, // GENERAL for the render targets
+                    _image_format: texture_data._image_format,
+                    _width: texture_data._image_width,
+                    _height: texture_data._image_height,
+                }
+            })
+        };
+        let mut screenshot_capture = self._screenshot_capture.borrow_mut();
+        match screenshot_source {
+            Some(screenshot_source) => screenshot_capture.record_screenshot_copy(&self._device, &self._device_memory_properties, command_buffer, &screenshot_source),
+            None => {
+                log::error!("record_screenshot_copy: the swapchain has no TRANSFER_SRC usage and {} is not found", screenshot::SCREENSHOT_FALLBACK_RENDER_TARGET_NAME);
+                screenshot_capture._requested_file_path = None;
+            }
+        }
+    }
+
+    fn record_object_pick_copy(&self, command_buffer: vk::CommandBuffer) {
+        let object_id_source = self.find_debug_render_target_data(object_picking::OBJECT_ID_RENDER_TARGET_NAME).map(|texture_data| {
+            let texture_data = unsafe { &*texture_data };
+            ObjectIdSource {
+                _image: texture_data._image,
+                _image_layout: texture_data._image_info.image_layout, // GENERAL for the render targets
+                _image_format: texture_data._image_format,
+                _width: texture_data._image_width,
+                _height: texture_data._image_height,
+            }
+        });
+        let mut object_picking = self._object_picking.borrow_mut();
+        match object_id_source {
+            Some(object_id_source) => object_picking.record_object_pick_copy(&self._device, &self._device_memory_properties, command_buffer, &object_id_source),
+            None => {
+                log::error!("record_object_pick_copy: {} is not found", object_picking::OBJECT_ID_RENDER_TARGET_NAME);
+                object_picking._requested_position = None;
+            }
+        }
+    }
+
+    // the gpu profiler reads back the timestamps of the previous use of the swapchain index first
+    fn begin_gpu_breadcrumbs(&self, command_buffer: vk::CommandBuffer, swapchain_index: u32) {
+        if gpu_breadcrumb::is_gpu_breadcrumbs_enabled() {
+            self._gpu_breadcrumbs.borrow_mut().begin_frame(&self._device, command_buffer, swapchain_index);
+        }
+        self._gpu_profiler.borrow_mut().begin_frame(&self._device, command_buffer, swapchain_index);
+        self._visibility_stats.borrow_mut().begin_frame(&self._device, command_buffer, swapchain_index);
+    }
+
+    // the statistics mode of the content, VISIBILITY_STATS_CAPTURE_FRAMES frames then the report of update_visibility_stats
+    pub fn begin_visibility_stats_capture(&self) {
+        self._visibility_stats.borrow_mut().begin_capture(&self._device, unsafe { constants::VISIBILITY_STATS_CAPTURE_FRAMES });
+    }
+
+    pub fn is_visibility_stats_recording(&self) -> bool {
+        self._visibility_stats.borrow()._is_recording
+    }
+
+    // around a single draw inside the render pass, ex) the skeletal render elements of the project with RenderElementData::_object_id.
+    // weights: visibility_stats::get_heuristic_weight per instance, they split the cost of the instanced draws.
+    pub fn begin_draw_stats(&self, command_buffer: vk::CommandBuffer, object_ids: &[u32], weights: &[f64], material_name: &str) {
+        self._visibility_stats.borrow_mut().begin_draw(&self._device, command_buffer, object_ids, weights, material_name);
+    }
+
+    pub fn end_draw_stats(&self, command_buffer: vk::CommandBuffer) {
+        self._visibility_stats.borrow_mut().end_draw(&self._device, command_buffer);
+    }
+
+    // the shaded pixels of the lights per frame during the capture, the report and its summary overlay after the capture
+    pub fn update_visibility_stats(&self, scene_manager_data: &SceneManagerData, font_manager: &mut FontManager) {
+        let mut visibility_stats = self._visibility_stats.borrow_mut();
+        if visibility_stats.is_capturing() {
+            for (light_id, shaded_pixel_count) in self._light_cluster.borrow().get_light_shaded_pixel_counts().iter() {
+                if let Some(local_light_data) = scene_manager_data.get_local_light(*light_id) {
+                    visibility_stats._accumulator.add_light_sample(&local_light_data._light_name, *shaded_pixel_count as f64);
+                }
+            }
+            font_manager.log(format!("visibility stats capture: {} frames remaining", visibility_stats._remaining_frames));
+        }
+        if let Some(accumulator) = visibility_stats.take_finished_accumulator() {
+            let report = VisibilityStatsReport::create_visibility_stats_report(&accumulator, visibility_stats.get_cost_source(), |object_id| {
+                scene_manager_data.get_render_object_by_object_id(object_id as u64).map(|render_object| render_object.borrow()._render_object_name.clone())
+            });
+            if let Err(e) = report.write_visibility_stats_report(Path::new(visibility_stats::VISIBILITY_STATS_OUTPUT_PATH)) {
+                log::error!("write_visibility_stats_report failed: {:?}", e);
+            }
+            visibility_stats._report_summary = Some(report.get_summary_text());
+            visibility_stats._overlay_frames = visibility_stats::VISIBILITY_STATS_OVERLAY_FRAMES;
+        }
+        if 0 < visibility_stats._overlay_frames {
+            visibility_stats._overlay_frames -= 1;
+            if let Some(report_summary) = visibility_stats._report_summary.as_ref() {
+                font_manager.log(report_summary.clone());
+            }
+        }
+    }
+
+    // (scope name, milliseconds), the first is the whole frame and the nested scopes are indented
+    pub fn get_gpu_timings(&self) -> Vec<(String, f32)> {
+        self._gpu_profiler.borrow().get_gpu_timings().clone()
+    }
+
+    pub fn get_gpu_frame_time(&self) -> Option<f32> {
+        self._gpu_profiler.borrow().get_gpu_frame_time()
+    }
+
+    pub fn update_gpu_profiler_stats(&self, font_manager: &mut FontManager) {
+        let gpu_profiler = self._gpu_profiler.borrow();
+        if false == gpu_profiler.get_gpu_timings().is_empty() {
+            font_manager.log(gpu_profiler.get_gpu_timings_text());
+        }
+        if self.is_gpu_occlusion_culling_enabled() {
+            font_manager.log(self._occlusion_culling.borrow().get_stats_text());
+        }
+    }
+
+    pub fn create_device_lost_report(&self, swapchain_index: u32, elapsed_frame: u64) -> String {
+        let breadcrumb_report = if gpu_breadcrumb::is_gpu_breadcrumbs_enabled() {
+            self._gpu_breadcrumbs.borrow().create_gpu_breadcrumb_report(&self._device, swapchain_index).get_report_text()
+        } else {
+            String::from("gpu breadcrumbs are disabled")
+        };
+        format!("device lost(frame: {}): {}\n{}", elapsed_frame, breadcrumb_report, object_name::get_object_name_summary())
+    }
+
+    // the half-recorded command buffer is replaced by a clear of the acquired swapchain image,
+    // so the image and its semaphore are still consumed by the present of this frame.
+    fn recover_render_error(&self, command_buffer: vk::CommandBuffer, command_buffer_begin_info: vk::CommandBufferBeginInfo, swapchain_index: u32) {
+        unsafe {
+            let _ = self._device.end_command_buffer(command_buffer);
+            self.device_wait_idle();
+            self._device.reset_command_buffer(command_buffer, vk::CommandBufferResetFlags::RELEASE_RESOURCES).expect("vkResetCommandBuffer failed!");
+            self._device.begin_command_buffer(command_buffer, &command_buffer_begin_info).expect("vkBeginCommandBuffer failed!");
+        }
+        self._gpu_profiler.borrow_mut().discard_frame();
+        self._visibility_stats.borrow_mut().discard_frame();
+        self.begin_gpu_breadcrumbs(command_buffer, swapchain_index);
+
+        // reset the history dependent passes(TAA, SSR) and the pending readbacks
+        self.set_is_first_rendering(true);
+        self._debug_histogram.borrow_mut().reset_readback();
+
+        // the per frame allocators start over, the constants written by the aborted frame are not flushed
+        self._uniform_arena.borrow_mut().discard_frame(swapchain_index);
+        self._gpu_skinning.borrow_mut().begin_frame();
+        self._frame_graph.borrow_mut().begin_frame_barriers();
+        self._merged_render_pass_state.set(None);
+        self._draw_call_count.set(0);
+        self._dispatch_count.set(0);
+
+        self.record_clear_swapchain_image(command_buffer, swapchain_index, RENDER_ERROR_CLEAR_COLOR);
+    }
+
+    // the swapchain image is cleared and left in PRESENT_SRC_KHR
+    fn record_clear_swapchain_image(&self, command_buffer: vk::CommandBuffer, swapchain_index: u32, clear_color: [f32; 4]) {
+        let swapchain_image = self._swapchain_data._swapchain_images[swapchain_index as usize];
+        let subresource_range = vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        };
+        let mut image_memory_barrier = vk::ImageMemoryBarrier {
+            src_access_mask: vk::AccessFlags::empty(),
+            dst_access_mask: vk::AccessFlags::TRANSFER_WRITE,
+            old_layout: vk::ImageLayout::UNDEFINED,
+            new_layout: vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            image: swapchain_image,
+            subresource_range,
+            ..Default::default()
+        };
+        self.pipeline_barrier(command_buffer, vk::PipelineStageFlags::TOP_OF_PIPE, vk::PipelineStageFlags::TRANSFER, vk::DependencyFlags::empty(), &[], &[], &[image_memory_barrier]);
+        unsafe {
+            self._device.cmd_clear_color_image(
+                command_buffer,
+                swapchain_image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &vk::ClearColorValue { float32: clear_color },
+                &[subresource_range],
+            );
+        }
+        image_memory_barrier.src_access_mask = vk::AccessFlags::TRANSFER_WRITE;
+        image_memory_barrier.dst_access_mask = vk::AccessFlags::empty();
+        image_memory_barrier.old_layout = vk::ImageLayout::TRANSFER_DST_OPTIMAL;
+        image_memory_barrier.new_layout = vk::ImageLayout::PRESENT_SRC_KHR;
+        self.pipeline_barrier(command_buffer, vk::PipelineStageFlags::TRANSFER, vk::PipelineStageFlags::BOTTOM_OF_PIPE, vk::DependencyFlags::empty(), &[], &[], &[image_memory_barrier]);
+    }
+
+    // the frame of the degraded mode without the scene: the clear of the swapchain image and the errors of FontManager.
+    // returns false when the text could not be rendered, the caller keeps only the clear for the next frames.
+    pub fn render_error_screen(&mut self, font_manager: Option<&mut FontManager>) -> bool {
+        let mut is_text_rendered = true;
+        unsafe {
+            let frame_index = self._frame_index as usize;
+            let frame_fence = self._frame_fences[frame_index];
+            let image_available_semaphore = self._image_available_semaphores[frame_index];
+            let render_finished_semaphore = self._render_finished_semaphores[frame_index];
+            let acquire_next_image_result: VkResult<(u32, bool)> = self._swapchain_interface.acquire_next_image(
+                self._swapchain_data._swapchain,
+                std::u64::MAX,
+                image_available_semaphore,
+                vk::Fence::null()
+            );
+            let (swapchain_index, failed_acquire_next_image) = match acquire_next_image_result {
+                Ok(acquire_next_image_result) => acquire_next_image_result,
+                Err(_) => (self._swapchain_index, true),
+            };
+            self._swapchain_index = swapchain_index;
+
+            let present_result: vk::Result = if swapchain_index < constants::SWAPCHAIN_IMAGE_COUNT as u32 && false == failed_acquire_next_image {
+                let command_buffer = self._command_buffers[swapchain_index as usize];
+                let command_buffer_begin_info = vk::CommandBufferBeginInfo {
+                    flags: vk::CommandBufferUsageFlags::SIMULTANEOUS_USE,
+                    ..Default::default()
+                };
+                self._device.begin_command_buffer(command_buffer, &command_buffer_begin_info).expect("vkBeginCommandBuffer failed!");
+                self.record_clear_swapchain_image(command_buffer, swapchain_index, RENDER_ERROR_CLEAR_COLOR);
+                if let Some(font_manager) = font_manager {
+                    let resources = self._resources.clone();
+                    let render_text_info = RenderTextInfo {
+                        _render_font_size: ERROR_SCREEN_FONT_SIZE,
+                        ..Default::default()
+                    };
+                    let render_result = panic::catch_unwind(AssertUnwindSafe(|| {
+                        font_manager.render_text(command_buffer, swapchain_index, &self, &resources.borrow(), &render_text_info);
+                    }));
+                    if let Err(panic_payload) = render_result {
+                        log::error!("render_error_screen: {}", get_panic_message(&panic_payload));
+                        self.recover_render_error(command_buffer, command_buffer_begin_info, swapchain_index);
+                        is_text_rendered = false;
+                    }
+                }
+                self._device.end_command_buffer(command_buffer).expect("vkEndCommandBuffer failed!");
+                match self.present_swapchain(&[command_buffer], frame_fence, image_available_semaphore, render_finished_semaphore) {
+                    Ok(is_swapchain_suboptimal) => if is_swapchain_suboptimal { vk::Result::SUBOPTIMAL_KHR } else { vk::Result::SUCCESS },
+                    Err(err) => err,
+                }
+            } else {
+                vk::Result::ERROR_OUT_OF_DATE_KHR
+            };
+
             if vk::Result::ERROR_OUT_OF_DATE_KHR == present_result || vk::Result::SUBOPTIMAL_KHR == present_result {
                 self.set_need_recreate_swapchain(true);
+            } else if vk::Result::SUCCESS != present_result {
+                log::error!("render_error_screen: present swapchain result: {:?}", present_result);
             }
 
             self._frame_index = (self._frame_index + 1) % (constants::MAX_FRAME_COUNT as i32);
         }
+        is_text_rendered
+    }
+
+    pub fn get_render_error_message(&self) -> Option<String> {
+        self._render_error_state.borrow().get_render_error_message().cloned()
+    }
+
+    // "render error" banner of the frames after a caught panic in render_scene
+    pub fn update_render_error(&self, font_manager: &mut FontManager, ui_manager_data: &mut UIManagerData) {
+        match self.get_render_error_message() {
+            Some(render_error_message) => {
+                font_manager.log(format!("render error: {}", render_error_message));
+                ui_manager_data.set_render_error_banner(true);
+            },
+            None => ui_manager_data.set_render_error_banner(false),
+        }
+    }
+
+    pub fn update_anti_aliasing_mode(&self, font_manager: &mut FontManager) {
+        font_manager.log(format!("anti aliasing: {:?}", self.get_anti_aliasing_mode()));
+    }
+
+    pub fn update_present_mode(&mut self, font_manager: &mut FontManager) {
+        if 0 < self._present_mode_overlay_frames {
+            self._present_mode_overlay_frames -= 1;
+            font_manager.log(format!("present mode: {:?} (requested {:?})", self.get_active_present_mode(), self._present_mode));
+        }
     }
 
     // renderer interface
@@ -896,45 +2417,1325 @@ impl RendererData {
 
     pub fn set_is_first_rendering(&self, is_first_rendering: bool) {
         self.get_project_renderer_mut().set_is_first_rendering(is_first_rendering);
+        if is_first_rendering {
+            self._post_process_consumers.borrow_mut().invalidate_post_process_histories();
+            self._history_confidence.borrow_mut().invalidate_history();
+            self._auto_exposure.borrow_mut().invalidate_history();
+            self._occlusion_culling.borrow_mut().invalidate_history();
+            self._volumetric_fog.borrow_mut().invalidate_history();
+            self.invalidate_denoiser_histories();
+        }
     }
 
     pub fn prepare_framebuffer_and_descriptors(&self) {
         log::info!("RendererData::prepare_framebuffer_and_descriptors");
+        // before the project renderer, render_final binds auto_exposure::AUTO_EXPOSURE_BUFFER_NAME
+        self.prepare_auto_exposure_descriptors();
+        // before the project renderer, render_solid binds occlusion_culling::VISIBLE_INSTANCE_BUFFER_NAME
+        self.prepare_occlusion_culling_descriptors();
+        self.prepare_fxaa_descriptors();
+        // before the light probe, the precomputed textures are bound to the capture
+        self.prepare_atmosphere_framebuffers();
+        self.prepare_light_probe_framebuffers();
+        // after the light probe, the sky cubemap is bound to both
+        self.prepare_skybox_framebuffer();
         self.get_project_renderer_mut().prepare_framebuffer_and_descriptors(&self._device, &self._resources.borrow());
     }
 
+    fn prepare_atmosphere_framebuffers(&self) {
+        let resources = self._resources.borrow();
+        if false == resources.has_render_pass_data(atmosphere::ATMOSPHERE_RENDER_PASS_NAME) {
+            return;
+        }
+        self._atmosphere.borrow_mut().create_framebuffers_and_descriptor_sets(self, &resources);
+    }
+
+    fn prepare_light_probe_framebuffers(&self) {
+        let resources = self._resources.borrow();
+        if false == resources.has_render_pass_data(light_probe::LIGHT_PROBE_RENDER_PASS_NAME) {
+            return;
+        }
+        self._light_probe.borrow_mut().create_framebuffers_and_descriptor_sets(self, &resources);
+    }
+
+    fn prepare_skybox_framebuffer(&self) {
+        let resources = self._resources.borrow();
+        if false == resources.has_render_pass_data(skybox::SKYBOX_RENDER_PASS_NAME) {
+            return;
+        }
+        let mut skybox = self._skybox.borrow_mut();
+        let color_target = self.find_debug_render_target_data(&skybox._settings._color_target_name);
+        let depth_target = self.find_debug_render_target_data(&skybox._settings._depth_target_name);
+        match (color_target, depth_target) {
+            (Some(color_target), Some(depth_target)) => skybox.create_framebuffer(self, &resources, unsafe { &*color_target }, unsafe { &*depth_target }),
+            _ => log::error!("prepare_skybox_framebuffer: {} or {} does not exist.", skybox._settings._color_target_name, skybox._settings._depth_target_name),
+        }
+        drop(skybox);
+        self.prepare_sky_cubemap_descriptors(&resources);
+    }
+
+    // the descriptor sets of the skybox and the light probe, empty when the sky mode is not SkyMode::Cubemap
+    fn prepare_sky_cubemap_descriptors(&self, resources: &Resources) {
+        let mut skybox = self._skybox.borrow_mut();
+        skybox._descriptor_sets.clear();
+        let sky_cubemap_name = skybox.get_sky_cubemap_name().map(String::from);
+        let sky_cubemap = match sky_cubemap_name {
+            Some(sky_cubemap_name) if resources.has_texture_data(&sky_cubemap_name) => {
+                let texture_data = resources.get_texture_data(&sky_cubemap_name).borrow();
+                if vk::ImageViewType::CUBE == texture_data._image_view_type {
+                    Some(texture_data)
+                } else {
+                    log::error!("prepare_sky_cubemap_descriptors: {} is not a cube texture, {:?}", sky_cubemap_name, texture_data._image_view_type);
+                    None
+                }
+            },
+            Some(sky_cubemap_name) => {
+                log::error!("prepare_sky_cubemap_descriptors: {} does not exist.", sky_cubemap_name);
+                None
+            },
+            None => None,
+        };
+        if let Some(sky_cubemap) = sky_cubemap.as_ref() {
+            skybox.create_descriptor_sets(self, resources, sky_cubemap);
+        }
+        if resources.has_render_pass_data(light_probe::LIGHT_PROBE_RENDER_PASS_NAME) {
+            self._light_probe.borrow_mut().set_sky_cubemap(self, resources, sky_cubemap.as_deref(), skybox._sky_intensity);
+        }
+    }
+
+    fn prepare_fxaa_descriptors(&self) {
+        let resources = self._resources.borrow();
+        if false == resources.has_render_pass_data(fxaa::FXAA_RENDER_PASS_NAME) {
+            return;
+        }
+        let mut fxaa = self._fxaa.borrow_mut();
+        let source_target = self.find_debug_render_target_data(&fxaa._settings._source_target_name);
+        let resolve_target = self.find_debug_render_target_data(&fxaa._settings._resolve_target_name);
+        match (source_target, resolve_target) {
+            (Some(source_target), Some(resolve_target)) => fxaa.create_descriptor_sets(self, &resources, unsafe { &*source_target }, unsafe { &*resolve_target }),
+            _ => log::error!("prepare_fxaa_descriptors: {} or {} does not exist.", fxaa._settings._source_target_name, fxaa._settings._resolve_target_name),
+        }
+    }
+
+    fn prepare_occlusion_culling_descriptors(&self) {
+        let resources = self._resources.borrow();
+        if false == resources.has_render_pass_data(occlusion_culling::OCCLUSION_CULLING_RENDER_PASS_NAME) {
+            return;
+        }
+        let mut occlusion_culling = self._occlusion_culling.borrow_mut();
+        if occlusion_culling._visible_instance_buffer.is_none() {
+            occlusion_culling.create_occlusion_culling_buffers(self);
+        }
+        match self.find_debug_render_target_data(&occlusion_culling._settings._hiz_target_name) {
+            Some(texture_data) => occlusion_culling.create_descriptor_sets(self, &resources, unsafe { &*texture_data }),
+            None => log::warn!("prepare_occlusion_culling_descriptors: {} does not exist, the static batch is culled on the cpu.", occlusion_culling._settings._hiz_target_name),
+        }
+    }
+
+    fn prepare_auto_exposure_descriptors(&self) {
+        let resources = self._resources.borrow();
+        if false == resources.has_render_pass_data(auto_exposure::AUTO_EXPOSURE_RENDER_PASS_NAME) {
+            return;
+        }
+        let mut auto_exposure = self._auto_exposure.borrow_mut();
+        if auto_exposure._exposure_buffer.is_none() {
+            auto_exposure.create_auto_exposure_buffers(self);
+        }
+        match self.find_debug_render_target_data(&auto_exposure._settings._target_name) {
+            Some(texture_data) => auto_exposure.create_descriptor_sets(self, &resources, unsafe { &*texture_data }),
+            None => log::error!("prepare_auto_exposure_descriptors: {} does not exist.", auto_exposure._settings._target_name),
+        }
+    }
+
     pub fn destroy_framebuffer_and_descriptors(&self) {
         log::info!("RendererData::destroy_framebuffer_and_descriptors");
         self.get_project_renderer_mut().destroy_framebuffer_and_descriptors(&self._device);
+        self._debug_histogram.borrow_mut().destroy_descriptor_sets();
+        self._history_confidence.borrow_mut().destroy_descriptor_sets();
+        self._auto_exposure.borrow_mut().destroy_descriptor_sets();
+        self._occlusion_culling.borrow_mut().destroy_descriptor_sets();
+        self._fxaa.borrow_mut().destroy_descriptor_sets();
+        self._atmosphere.borrow_mut().destroy_framebuffers_and_descriptor_sets(&self._device);
+        self._light_probe.borrow_mut().destroy_framebuffers_and_descriptor_sets(&self._device);
+        self._skybox.borrow_mut().destroy_framebuffer_and_descriptor_sets(&self._device);
+        self._volumetric_fog.borrow_mut().destroy_framebuffer_and_descriptor_sets(&self._device);
+        self._terrain.borrow_mut().destroy_framebuffer_and_descriptor_sets(&self._device);
+        self._gpu_skinning.borrow_mut().destroy_descriptor_sets();
+        for denoiser_instance in self._denoisers.borrow_mut().values_mut() {
+            denoiser_instance.destroy_descriptor_sets();
+        }
     }
 
     pub fn update_post_process_datas(&self) {
+        let render_extent = self.get_render_extent();
+        self._post_process_consumers.borrow_mut().update_main_post_process_profile(&Vector2::new(render_extent.width as i32, render_extent.height as i32));
         self.get_project_renderer_mut().update_post_process_datas();
     }
 
-    pub fn get_shader_buffer_data_from_str(&self, buffer_data_name: &str) -> &ShaderBufferData {
-        self.get_project_renderer().get_shader_buffer_data_from_str(buffer_data_name)
-    }
+    pub fn get_post_process_consumers(&self) -> &RcRefCell<PostProcessConsumers> { &self._post_process_consumers }
 
-    pub fn get_render_target_from_str(&self, render_target_type_str: &str) -> &TextureData {
-        self.get_project_renderer().get_render_target_from_str(render_target_type_str)
+    // split-screen viewports and scene captures, ex) a minimap capture with PostProcessProfile::create_minimal_post_process_profile
+    pub fn regist_post_process_consumer(&self, name: &str, size: &Vector2<i32>, profile: &PostProcessProfile) -> PostProcessConsumerId {
+        self._post_process_consumers.borrow_mut().regist_post_process_consumer(self, name, size, profile)
     }
 
-    pub fn get_render_pass_data_create_infos(&self) -> Vec<RenderPassDataCreateInfo> {
-        self.get_project_renderer().get_render_pass_data_create_infos()
+    pub fn unregist_post_process_consumer(&self, consumer_id: PostProcessConsumerId) {
+        self.device_wait_idle();
+        self._post_process_consumers.borrow_mut().unregist_post_process_consumer(self, consumer_id);
     }
 
-    pub fn create_render_targets(&self) {
-        log::info!("create_render_targets");
-        self.get_project_renderer_mut().create_render_targets(self);
+    pub fn update_post_process_consumer(&self, consumer_id: PostProcessConsumerId, size: &Vector2<i32>, profile: &PostProcessProfile) {
+        let is_changed = match self._post_process_consumers.borrow().get_post_process_consumer(consumer_id) {
+            Some(consumer) => consumer._size != *size || consumer._profile != *profile,
+            None => false,
+        };
+        if is_changed {
+            self.device_wait_idle();
+            self._post_process_consumers.borrow_mut().update_post_process_consumer(self, consumer_id, size, profile);
+        }
     }
 
-    pub fn destroy_render_targets(&self) {
-        log::info!("destroy_render_targets");
-        self.get_project_renderer_mut().destroy_render_targets(self.get_device());
+    // called by the project renderer for each consumer, a debug label per consumer shows the cost of its chain in the captures.
+    pub fn render_post_process(&self, command_buffer: vk::CommandBuffer, swapchain_index: u32, consumer_id: PostProcessConsumerId, viewport_rect: &ViewportRect) {
+        // cloned, the project renderer may update the consumers while rendering
+        let consumer = match self._post_process_consumers.borrow().get_post_process_consumer(consumer_id) {
+            Some(consumer) => consumer.clone(),
+            None => {
+                log::error!("render_post_process: not registed consumer {}", consumer_id);
+                return;
+            }
+        };
+        self.begin_debug_label(command_buffer, &format!("post_process: {}", consumer._name));
+        self.get_project_renderer_mut().render_post_process(command_buffer, swapchain_index, self, viewport_rect, &consumer._profile, &consumer._history);
+        self.end_debug_label(command_buffer);
     }
 
-    pub fn destroy_uniform_buffers(&self) {
-        self.get_project_renderer_mut().destroy_uniform_buffers(self.get_device());
+    // the project renderer marks the history valid after rendering the first frame of the consumer
+    pub fn set_post_process_history_valid(&self, consumer_id: PostProcessConsumerId) {
+        if let Some(consumer) = self._post_process_consumers.borrow_mut()._consumers.get_mut(&consumer_id) {
+            consumer._history._is_history_valid = true;
+        }
     }
-}
\ No newline at end of file
+
+    // the engine buffers first, then the shader_buffer_datas of the project renderer.
+    pub fn get_shader_buffer_data_from_str(&self, buffer_data_name: &str) -> &ShaderBufferData {
+        if auto_exposure::AUTO_EXPOSURE_BUFFER_NAME == buffer_data_name {
+            if let Some(exposure_buffer) = self._auto_exposure.borrow().get_exposure_buffer() {
+                return unsafe { &*(exposure_buffer as *const ShaderBufferData) };
+            }
+        }
+        if occlusion_culling::VISIBLE_INSTANCE_BUFFER_NAME == buffer_data_name {
+            if let Some(visible_instance_buffer) = self._occlusion_culling.borrow().get_visible_instance_buffer() {
+                return unsafe { &*(visible_instance_buffer as *const ShaderBufferData) };
+            }
+        }
+        if bindless_texture::BINDLESS_MATERIAL_BUFFER_NAME == buffer_data_name {
+            if let Some(bindless_material_buffer) = self._bindless_material_buffer.borrow().as_ref() {
+                return unsafe { &*(bindless_material_buffer as *const ShaderBufferData) };
+            }
+        }
+        if material_instance::MATERIAL_OVERRIDE_BUFFER_NAME == buffer_data_name {
+            if let Some(material_override_buffer) = self._material_override_buffer.borrow().as_ref() {
+                return unsafe { &*(material_override_buffer as *const ShaderBufferData) };
+            }
+        }
+        self.get_project_renderer().get_shader_buffer_data_from_str(buffer_data_name)
+    }
+
+    // the engine targets and the data driven targets of render_targets.json first, then the engine core targets of the project renderer.
+    pub fn get_render_target_from_str(&self, render_target_type_str: &str) -> &TextureData {
+        if let Some(texture_data) = self._history_confidence.borrow().get_render_target(render_target_type_str) {
+            return unsafe { &*(texture_data as *const TextureData) };
+        }
+        if let Some(texture_data) = self._atmosphere.borrow().get_render_target(render_target_type_str) {
+            return unsafe { &*(texture_data as *const TextureData) };
+        }
+        if let Some(texture_data) = self._light_probe.borrow().get_render_target(render_target_type_str) {
+            return unsafe { &*(texture_data as *const TextureData) };
+        }
+        if let Some(texture_data) = self._volumetric_fog.borrow().get_render_target(render_target_type_str) {
+            return unsafe { &*(texture_data as *const TextureData) };
+        }
+        let dynamic_render_targets = self._dynamic_render_targets.borrow();
+        if let Some(render_target_id) = dynamic_render_targets.get_render_target_id(render_target_type_str) {
+            return unsafe { &*dynamic_render_targets.get_render_target(render_target_id) };
+        }
+        self.get_project_renderer().get_render_target_from_str(render_target_type_str)
+    }
+
+    pub fn get_dynamic_render_targets(&self) -> &RcRefCell<DynamicRenderTargets> { &self._dynamic_render_targets }
+
+    pub fn get_render_pass_data_create_infos(&self) -> Vec<RenderPassDataCreateInfo> {
+        let mut render_pass_data_create_infos = self.get_project_renderer().get_render_pass_data_create_infos();
+        for render_extension in self.get_render_extensions() {
+            render_pass_data_create_infos.extend(render_extension.get_render_pass_data_create_infos(self));
+        }
+        render_pass_data_create_infos.push(histogram::get_render_pass_data_create_info());
+        render_pass_data_create_infos.push(history_confidence::get_render_pass_data_create_info());
+        render_pass_data_create_infos.push(gpu_skinning::get_render_pass_data_create_info());
+        render_pass_data_create_infos.push(denoiser::get_render_pass_data_create_info());
+        render_pass_data_create_infos.push(auto_exposure::get_render_pass_data_create_info());
+        render_pass_data_create_infos.push(fxaa::get_render_pass_data_create_info());
+        render_pass_data_create_infos.push(occlusion_culling::get_render_pass_data_create_info());
+        render_pass_data_create_infos.push(atmosphere::get_render_pass_data_create_info(false));
+        render_pass_data_create_infos.push(atmosphere::get_render_pass_data_create_info(true));
+        render_pass_data_create_infos.push(light_probe::get_render_pass_data_create_info());
+        let skybox_settings = self._skybox.borrow()._settings.clone();
+        match (self.find_debug_render_target_data(&skybox_settings._color_target_name), self.find_debug_render_target_data(&skybox_settings._depth_target_name)) {
+            (Some(color_target), Some(depth_target)) => render_pass_data_create_infos.push(skybox::get_render_pass_data_create_info(unsafe { &*color_target }, unsafe { &*depth_target })),
+            _ => log::warn!("get_render_pass_data_create_infos: {} or {} does not exist, the skybox is not rendered.", skybox_settings._color_target_name, skybox_settings._depth_target_name),
+        }
+        let volumetric_fog_settings = self._volumetric_fog.borrow()._settings.clone();
+        let volumetric_fog_targets = [&volumetric_fog_settings._color_target_name, &volumetric_fog_settings._depth_target_name, &volumetric_fog_settings._shadow_target_name];
+        if volumetric_fog_targets.iter().all(|target_name| self.find_debug_render_target_data(target_name).is_some()) {
+            let color_target = self.find_debug_render_target_data(&volumetric_fog_settings._color_target_name).unwrap();
+            render_pass_data_create_infos.push(volumetric_fog::get_render_pass_data_create_info());
+            render_pass_data_create_infos.push(volumetric_fog::get_composite_render_pass_data_create_info(unsafe { &*color_target }));
+        } else {
+            log::warn!("get_render_pass_data_create_infos: {:?} does not exist, the volumetric fog is not rendered.", volumetric_fog_targets);
+        }
+        let terrain_render_settings = self._terrain.borrow()._render_settings.clone();
+        let terrain_color_targets: Vec<*const TextureData> = terrain_render_settings._color_target_names.iter().filter_map(|target_name| self.find_debug_render_target_data(target_name)).collect();
+        match self.find_debug_render_target_data(&terrain_render_settings._depth_target_name) {
+            Some(depth_target) if terrain_color_targets.len() == terrain_render_settings._color_target_names.len() => {
+                let terrain_color_targets: Vec<&TextureData> = terrain_color_targets.iter().map(|color_target| unsafe { &**color_target }).collect();
+                render_pass_data_create_infos.push(terrain::get_render_pass_data_create_info(&terrain_color_targets, unsafe { &*depth_target }));
+            },
+            _ => log::warn!("get_render_pass_data_create_infos: {:?} or {} does not exist, the terrain is not rendered.", terrain_render_settings._color_target_names, terrain_render_settings._depth_target_name),
+        }
+        let merged_render_pass_infos = self.get_merged_render_pass_infos();
+        if false == merged_render_pass_infos.is_empty() {
+            render_pass_data_create_infos = render_pass::merge_render_pass_data_create_infos(render_pass_data_create_infos, &merged_render_pass_infos);
+        }
+        for render_pass_data_create_info in render_pass_data_create_infos.iter_mut() {
+            render_pass_data_create_info.disable_stencil_without_stencil_attachment();
+        }
+        render_pass_data_create_infos
+    }
+
+    // empty without constants::ENABLE_SUBPASS_MERGING, the render passes are separated.
+    pub fn get_merged_render_pass_infos(&self) -> Vec<MergedRenderPassInfo> {
+        if render_pass::is_subpass_merging_enabled() {
+            self.get_project_renderer().get_merged_render_pass_infos()
+        } else {
+            Vec::new()
+        }
+    }
+
+    pub fn get_frame_graph(&self) -> &RcRefCell<FrameGraph> { &self._frame_graph }
+
+    pub fn get_shadow_atlas(&self) -> &RcRefCell<ShadowAtlas> { &self._shadow_atlas }
+
+    pub fn get_shadow_cache(&self) -> &RcRefCell<ShadowCache> { &self._shadow_cache }
+
+    pub fn get_light_cluster(&self) -> &RcRefCell<LightCluster> { &self._light_cluster }
+
+    // called by the project renderer before composite_gbuffer, the buffers are light_cluster::LIGHT_BUFFER_NAME and LIGHT_TILE_BUFFER_NAME.
+    pub fn upload_light_cluster(&self, command_buffer: vk::CommandBuffer, swapchain_index: u32) {
+        self._light_cluster.borrow().upload_light_cluster(command_buffer, swapchain_index, self);
+    }
+
+    pub fn update_light_cluster_stats(&self, font_manager: &mut FontManager) {
+        let light_cluster = self._light_cluster.borrow();
+        if 0 < light_cluster.get_stats()._total_light_count {
+            font_manager.log(light_cluster.get_stats().get_stats_text());
+        }
+    }
+
+    pub fn update_device_memory_stats(&self, font_manager: &mut FontManager) {
+        let device_memory_stats = device_memory_allocator::get_device_memory_stats();
+        if 0 < device_memory_stats._block_count {
+            font_manager.log(device_memory_stats.get_stats_text());
+        }
+    }
+
+    pub fn update_shadow_cache_stats(&self, font_manager: &mut FontManager) {
+        let shadow_cache = self._shadow_cache.borrow();
+        if 0 < shadow_cache.get_stats()._region_count {
+            font_manager.log(shadow_cache.get_stats_text());
+        }
+    }
+
+    // the project render targets first, then the dynamic render targets which are created now.
+    pub fn get_debug_render_target_names(&self) -> Vec<String> {
+        let mut render_target_names = self.get_project_renderer().get_render_target_names();
+        if self._history_confidence.borrow().get_render_target(history_confidence::HISTORY_CONFIDENCE_TEXTURE_NAME).is_some() {
+            render_target_names.push(String::from(history_confidence::HISTORY_CONFIDENCE_TEXTURE_NAME));
+        }
+        for render_target_name in [atmosphere::ATMOSPHERE_TRANSMITTANCE_TEXTURE_NAME, atmosphere::ATMOSPHERE_IRRADIANCE_TEXTURE_NAME].iter() {
+            if self._atmosphere.borrow().get_render_target(render_target_name).is_some() {
+                render_target_names.push(String::from(*render_target_name));
+            }
+        }
+        for render_target_name in [light_probe::LIGHT_PROBE_SPECULAR_TEXTURE_NAME, light_probe::LIGHT_PROBE_IRRADIANCE_TEXTURE_NAME].iter() {
+            if self._light_probe.borrow().get_render_target(render_target_name).is_some() {
+                render_target_names.push(String::from(*render_target_name));
+            }
+        }
+        if self._volumetric_fog.borrow().get_render_target(volumetric_fog::VOLUMETRIC_FOG_TEXTURE_NAME).is_some() {
+            render_target_names.push(String::from(volumetric_fog::VOLUMETRIC_FOG_TEXTURE_NAME));
+        }
+        let dynamic_render_targets = self._dynamic_render_targets.borrow();
+        for render_target_name in dynamic_render_targets.get_render_target_names() {
+            if self.find_debug_render_target_data(&render_target_name).is_some() && false == render_target_names.contains(&render_target_name) {
+                render_target_names.push(render_target_name);
+            }
+        }
+        render_target_names
+    }
+
+    // unlike get_render_target_from_str, None for a render target which does not exist.
+    fn find_debug_render_target_data(&self, render_target_name: &str) -> Option<*const TextureData> {
+        if let Some(texture_data) = self._history_confidence.borrow().get_render_target(render_target_name) {
+            return Some(texture_data as *const TextureData);
+        }
+        if let Some(texture_data) = self._atmosphere.borrow().get_render_target(render_target_name) {
+            return Some(texture_data as *const TextureData);
+        }
+        if let Some(texture_data) = self._light_probe.borrow().get_render_target(render_target_name) {
+            return Some(texture_data as *const TextureData);
+        }
+        if let Some(texture_data) = self._volumetric_fog.borrow().get_render_target(render_target_name) {
+            return Some(texture_data as *const TextureData);
+        }
+        let dynamic_render_targets = self._dynamic_render_targets.borrow();
+        if let Some(render_target_id) = dynamic_render_targets.get_render_target_id(render_target_name) {
+            if render_target_id < dynamic_render_targets._render_targets.len() {
+                return Some(dynamic_render_targets.get_render_target(render_target_id));
+            }
+            return None;
+        }
+        let project_renderer = self.get_project_renderer();
+        if project_renderer.get_render_target_names().iter().any(|name| name == render_target_name) {
+            return Some(project_renderer.get_render_target_from_str(render_target_name));
+        }
+        None
+    }
+
+    pub fn get_debug_render_target(&self) -> Ref<DebugRenderTarget> { self._debug_render_target.borrow() }
+
+    // the render target shown by the debug view, None when the debug view is off.
+    pub fn get_debug_render_target_data(&self) -> Option<&TextureData> {
+        let debug_render_target = self._debug_render_target.borrow();
+        if false == debug_render_target._is_enabled {
+            return None;
+        }
+        self.find_debug_render_target_data(&debug_render_target._render_target_name).map(|texture_data| unsafe { &*texture_data })
+    }
+
+    // SHADER_READ_ONLY_OPTIMAL, see transition_debug_render_target
+    pub fn get_debug_render_target_image_info(&self) -> Option<vk::DescriptorImageInfo> {
+        let debug_render_target = self._debug_render_target.borrow();
+        self.get_debug_render_target_data().map(|texture_data| vk::DescriptorImageInfo {
+            image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            ..texture_data.get_sub_image_info(debug_render_target._layer, debug_render_target._mip_level)
+        })
+    }
+
+    // render_target_name: RenderTargetType of the project as str, or a dynamic render target. the layer and the mip level are clamped.
+    pub fn set_debug_render_target(&self, render_target_name: &str, layer: u32, mip_level: u32) {
+        match self.find_debug_render_target_data(render_target_name) {
+            Some(texture_data) => {
+                let mut debug_render_target = self._debug_render_target.borrow_mut();
+                debug_render_target._is_enabled = true;
+                debug_render_target._render_target_name = String::from(render_target_name);
+                debug_render_target._layer = layer;
+                debug_render_target._mip_level = mip_level;
+                debug_render_target.clamp_to_render_target(unsafe { &*texture_data });
+                self.invalidate_temporal_history();
+            },
+            None => log::error!("set_debug_render_target: {} does not exist.", render_target_name),
+        }
+    }
+
+    pub fn clear_debug_render_target(&self) {
+        self._debug_render_target.borrow_mut()._is_enabled = false;
+        self.invalidate_temporal_history();
+    }
+
+    pub fn next_debug_render_target(&self) {
+        let render_target_names = self.get_debug_render_target_names();
+        self._debug_render_target.borrow_mut().cycle_render_target(&render_target_names, 1);
+        self.invalidate_temporal_history();
+    }
+
+    pub fn prev_debug_render_target(&self) {
+        let render_target_names = self.get_debug_render_target_names();
+        self._debug_render_target.borrow_mut().cycle_render_target(&render_target_names, -1);
+        self.invalidate_temporal_history();
+    }
+
+    pub fn next_debug_render_target_mip(&self) {
+        if let Some(texture_data) = self.get_debug_render_target_data() {
+            self._debug_render_target.borrow_mut().cycle_mip_level(texture_data, 1);
+        }
+    }
+
+    pub fn prev_debug_render_target_mip(&self) {
+        if let Some(texture_data) = self.get_debug_render_target_data() {
+            self._debug_render_target.borrow_mut().cycle_mip_level(texture_data, -1);
+        }
+    }
+
+    // after a swapchain recreate, a removed render target moves to the next existing one.
+    pub fn validate_debug_render_target(&self) {
+        if false == self._debug_render_target.borrow()._is_enabled {
+            return;
+        }
+        match self.get_debug_render_target_data() {
+            Some(texture_data) => self._debug_render_target.borrow_mut().clamp_to_render_target(texture_data),
+            None => {
+                log::info!("debug render target {} no longer exists.", self._debug_render_target.borrow()._render_target_name);
+                self.next_debug_render_target();
+            },
+        }
+    }
+
+    pub fn update_debug_render_target(&self, font_manager: &mut FontManager) {
+        if let Some(texture_data) = self.get_debug_render_target_data() {
+            font_manager.log(self._debug_render_target.borrow().get_overlay_text(texture_data));
+        }
+    }
+
+    // called by the project renderer each frame with the shadow casting local lights, before the shadow pass.
+    pub fn update_shadow_atlas(&self, requests: &[ShadowAtlasRequest], view_position: &Vector3<f32>, fov: f32) {
+        let atlas_size = unsafe { constants::SHADOW_ATLAS_SIZE };
+        if atlas_size.next_power_of_two() != self._shadow_atlas.borrow().get_atlas_size() {
+            *self._shadow_atlas.borrow_mut() = ShadowAtlas::create_shadow_atlas(atlas_size);
+        }
+        let mut shadow_atlas = self._shadow_atlas.borrow_mut();
+        shadow_atlas.update_shadow_atlas(requests, view_position, fov);
+        let mut shadow_cache = self._shadow_cache.borrow_mut();
+        for light_id in shadow_atlas.get_invalidated_static_lights().iter() {
+            shadow_cache.invalidate_atlas_tile_regions(*light_id);
+        }
+    }
+
+    pub fn update_shadow_atlas_stats(&self, font_manager: &mut FontManager) {
+        let shadow_atlas = self._shadow_atlas.borrow();
+        if shadow_atlas.get_allocations().is_empty() && shadow_atlas.get_unshadowed_lights().is_empty() {
+            return;
+        }
+        font_manager.log(shadow_atlas.get_stats_text());
+        if unsafe { constants::SHADOW_ATLAS_DEBUG_MAP } {
+            for line in shadow_atlas.get_atlas_map_text() {
+                font_manager.log(line);
+            }
+        }
+    }
+
+    pub fn rebuild_frame_graph(&self, render_pass_data_create_infos: &[RenderPassDataCreateInfo]) {
+        let mut frame_graph = self._frame_graph.borrow_mut();
+        let pass_render_targets = std::mem::take(&mut frame_graph._pass_render_targets);
+        *frame_graph = FrameGraph::create_frame_graph(render_pass_data_create_infos);
+        frame_graph._pass_render_targets = pass_render_targets;
+        frame_graph.set_merged_passes(&self.get_merged_render_pass_infos());
+    }
+
+    // ex) declare_pass_render_targets("render_ssao", &["SceneDepth", "SceneNormal"], &["SSAO"])
+    // the render targets are transitioned from their last access in the frame before the pass begins.
+    pub fn declare_pass_render_targets(&self, pass_name: &str, read_render_target_names: &[&str], write_render_target_names: &[&str]) {
+        for render_target_name in read_render_target_names.iter().chain(write_render_target_names.iter()) {
+            if self.find_debug_render_target_data(render_target_name).is_none() {
+                log::warn!("declare_pass_render_targets: {} of {} does not exist yet.", render_target_name, pass_name);
+            }
+        }
+        self._frame_graph.borrow_mut().declare_pass_render_targets(pass_name, read_render_target_names, write_render_target_names);
+    }
+
+    // one vkCmdPipelineBarrier for all the transitions, the render targets which do not exist are skipped.
+    fn transition_render_targets(&self, command_buffer: vk::CommandBuffer, transitions: &[(String, RenderTargetState)]) {
+        let mut src_stage_mask = vk::PipelineStageFlags::empty();
+        let mut dst_stage_mask = vk::PipelineStageFlags::empty();
+        let mut image_memory_barriers: Vec<vk::ImageMemoryBarrier> = Vec::new();
+        let mut frame_graph = self._frame_graph.borrow_mut();
+        for (render_target_name, dst_state) in transitions.iter() {
+            let texture_data = match self.find_debug_render_target_data(render_target_name) {
+                Some(texture_data) => unsafe { &*texture_data },
+                None => continue,
+            };
+            if let Some((src_state, dst_state)) = frame_graph.transition_render_target(render_target_name, dst_state) {
+                let aspect_mask = if constants::DEPTH_STENCIL_FORMATS.contains(&texture_data._image_format) {
+                    vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL
+                } else if constants::DEPTH_FOMATS.contains(&texture_data._image_format) {
+                    vk::ImageAspectFlags::DEPTH
+                } else {
+                    vk::ImageAspectFlags::COLOR
+                };
+                // a read after a write needs the memory dependency, a write after a read needs only the execution dependency
+                let src_access_mask = if src_state._is_write { src_state._access_mask } else { vk::AccessFlags::empty() };
+                let mut image_memory_barrier = texture::image_barrier_struct(
+                    texture_data._image,
+                    aspect_mask,
+                    0,
+                    texture_data._image_layers.max(1),
+                    src_state._layout,
+                    dst_state._layout,
+                    src_access_mask,
+                    dst_state._access_mask,
+                );
+                image_memory_barrier.subresource_range.level_count = texture_data._image_mip_levels.max(1);
+                image_memory_barriers.push(image_memory_barrier);
+                src_stage_mask |= src_state._stage_mask;
+                dst_stage_mask |= dst_state._stage_mask;
+            }
+        }
+        if false == image_memory_barriers.is_empty() {
+            self.pipeline_barrier(command_buffer, src_stage_mask, dst_stage_mask, vk::DependencyFlags::empty(), &[], &[], &image_memory_barriers);
+        }
+    }
+
+    // before the render pass or the dispatch of a pass declared with declare_pass_render_targets
+    fn insert_pass_barriers(&self, command_buffer: vk::CommandBuffer, pass_name: &str, is_compute: bool) {
+        if false == unsafe { constants::ENABLE_RENDER_TARGET_BARRIERS } {
+            return;
+        }
+        let pass_render_targets = {
+            let mut frame_graph = self._frame_graph.borrow_mut();
+            if frame_graph._last_barrier_pass_name == pass_name {
+                return;
+            }
+            frame_graph._last_barrier_pass_name = String::from(pass_name);
+            match frame_graph.get_barrier_render_targets(pass_name) {
+                Some(pass_render_targets) => pass_render_targets,
+                None => return,
+            }
+        };
+        let get_transition = |render_target_name: &String, access_type: RenderTargetAccessType| -> Option<(String, RenderTargetState)> {
+            self.find_debug_render_target_data(render_target_name).map(|texture_data| {
+                let texture_data = unsafe { &*texture_data };
+                (render_target_name.clone(), frame_graph::get_render_target_access_state(access_type, is_compute, texture_data._image_info.image_layout))
+            })
+        };
+        let mut transitions: Vec<(String, RenderTargetState)> = Vec::new();
+        for render_target_name in pass_render_targets._reads.iter() {
+            transitions.extend(get_transition(render_target_name, RenderTargetAccessType::ShaderRead));
+        }
+        for render_target_name in pass_render_targets._writes.iter() {
+            let is_depth = self.find_debug_render_target_data(render_target_name).map_or(false, |texture_data| {
+                constants::DEPTH_FOMATS.contains(unsafe { &(*texture_data)._image_format })
+            });
+            let access_type = if is_compute {
+                RenderTargetAccessType::ShaderWrite
+            } else if is_depth {
+                RenderTargetAccessType::DepthAttachmentWrite
+            } else {
+                RenderTargetAccessType::ColorAttachmentWrite
+            };
+            transitions.extend(get_transition(render_target_name, access_type));
+        }
+        self.transition_render_targets(command_buffer, &transitions);
+    }
+
+    // the render targets in the other layout than their descriptors go back, so the next frame begins from the default layouts.
+    fn end_frame_barriers(&self, command_buffer: vk::CommandBuffer) {
+        let render_target_names = self._frame_graph.borrow().get_render_target_names_to_restore();
+        if render_target_names.is_empty() {
+            return;
+        }
+        let transitions: Vec<(String, RenderTargetState)> = render_target_names.into_iter().filter_map(|render_target_name| {
+            self.find_debug_render_target_data(&render_target_name).map(|texture_data| {
+                let default_layout = unsafe { (*texture_data)._image_info.image_layout };
+                (render_target_name, frame_graph::get_render_target_access_state(RenderTargetAccessType::ShaderRead, false, default_layout))
+            })
+        }).collect();
+        self.transition_render_targets(command_buffer, &transitions);
+    }
+
+    // called by the project before the debug view samples get_debug_render_target_image_info, the layout is restored at the end of the frame.
+    pub fn transition_debug_render_target(&self, command_buffer: vk::CommandBuffer) {
+        let default_layout = match self.get_debug_render_target_data() {
+            Some(texture_data) => texture_data._image_info.image_layout,
+            None => return,
+        };
+        let render_target_name = self._debug_render_target.borrow()._render_target_name.clone();
+        let mut dst_state = frame_graph::get_render_target_access_state(RenderTargetAccessType::ShaderRead, false, default_layout);
+        dst_state._layout = vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL;
+        self._frame_graph.borrow_mut()._last_barrier_pass_name.clear();
+        self.transition_render_targets(command_buffer, &[(render_target_name, dst_state)]);
+    }
+
+    // graphdump dot <path>
+    pub fn dump_frame_graph_dot(&self, dot_file_path: &Path) {
+        self._frame_graph.borrow().write_dot(dot_file_path);
+    }
+
+    pub fn create_render_targets(&self) {
+        log::info!("create_render_targets");
+        self.get_project_renderer_mut().create_render_targets(self);
+        let render_target_definitions = render_target::load_render_target_definitions(&self._resources.borrow());
+        self._dynamic_render_targets.borrow_mut().create_dynamic_render_targets(self, &render_target_definitions);
+        self._history_confidence.borrow_mut().create_history_confidence_textures(self);
+        self._atmosphere.borrow_mut().create_atmosphere_textures(self);
+        self._light_probe.borrow_mut().create_light_probe_textures(self);
+        self._volumetric_fog.borrow_mut().create_volumetric_fog_textures(self);
+        self._shadow_atlas.borrow_mut().invalidate_shadow_atlas();
+        self._shadow_cache.borrow_mut().invalidate_shadow_cache();
+        if cfg!(debug_assertions) {
+            self.check_render_target_object_names();
+        }
+    }
+
+    // the render targets created without texture::create_render_target have no name in the validation messages and the captures
+    fn check_render_target_object_names(&self) {
+        for render_target_name in self.get_debug_render_target_names() {
+            if let Some(texture_data) = self.find_debug_render_target_data(&render_target_name) {
+                let image = unsafe { (*texture_data)._image };
+                if false == object_name::has_object_name(image) {
+                    log::error!("check_render_target_object_names: {} has no object name", render_target_name);
+                }
+            }
+        }
+    }
+
+    pub fn destroy_render_targets(&self) {
+        log::info!("destroy_render_targets");
+        self._dynamic_render_targets.borrow_mut().destroy_dynamic_render_targets(self);
+        self._history_confidence.borrow_mut().destroy_history_confidence_textures(self);
+        self._atmosphere.borrow_mut().destroy_atmosphere_textures(self);
+        self._light_probe.borrow_mut().destroy_light_probe_textures(self);
+        self._volumetric_fog.borrow_mut().destroy_volumetric_fog_textures(self);
+        for denoiser_instance in self._denoisers.borrow_mut().values_mut() {
+            denoiser_instance.destroy_denoiser_textures(self);
+        }
+        self.get_project_renderer_mut().destroy_render_targets(self.get_device());
+    }
+
+    // created without the descriptor indexing too, so the material descriptors of the fallback path stay valid
+    fn create_bindless_material_buffer(&self) {
+        let max_instance_count = unsafe { constants::MAX_INSTANCE_COUNT };
+        *self._bindless_material_buffer.borrow_mut() = Some(buffer::create_shader_buffer_data(
+            self.get_device(),
+            self.get_device_memory_properties(),
+            &String::from(bindless_texture::BINDLESS_MATERIAL_BUFFER_NAME),
+            vk::BufferUsageFlags::STORAGE_BUFFER,
+            (std::mem::size_of::<BindlessMaterialData>() * max_instance_count) as vk::DeviceSize,
+            false,
+            false,
+            false,
+        ));
+    }
+
+    // the defaults of the objects without the overrides are uploaded too, so the opaque pipelines always read the buffer
+    fn create_material_override_buffer(&self) {
+        let max_instance_count = unsafe { constants::MAX_INSTANCE_COUNT };
+        *self._material_override_buffer.borrow_mut() = Some(buffer::create_shader_buffer_data(
+            self.get_device(),
+            self.get_device_memory_properties(),
+            &String::from(material_instance::MATERIAL_OVERRIDE_BUFFER_NAME),
+            vk::BufferUsageFlags::STORAGE_BUFFER,
+            (std::mem::size_of::<MaterialParameterOverrides>() * max_instance_count) as vk::DeviceSize,
+            false,
+            false,
+            false,
+        ));
+    }
+
+    // set 1 of the pipelines with _use_bindless_textures
+    pub fn bind_bindless_texture_descriptor_set(&self, command_buffer: vk::CommandBuffer, pipeline_data: &PipelineData) {
+        if let Some(bindless_texture_array) = self._resources.borrow().get_bindless_texture_array() {
+            bindless_texture_array.bind_descriptor_set(&self._device, command_buffer, pipeline_data._pipeline_bind_point, pipeline_data._pipeline_layout);
+        }
+    }
+
+    pub fn destroy_uniform_buffers(&self) {
+        self.get_project_renderer_mut().destroy_uniform_buffers(self.get_device());
+        self._debug_histogram.borrow_mut().destroy_histogram_buffer(self.get_device());
+        self._auto_exposure.borrow_mut().destroy_auto_exposure_buffers(self.get_device());
+        self._occlusion_culling.borrow_mut().destroy_occlusion_culling_buffers(self.get_device());
+        self._volumetric_fog.borrow_mut().destroy_volumetric_fog_buffers(self.get_device());
+        self._terrain.borrow_mut().destroy_terrain_buffers(self.get_device());
+        if let Some(mut bindless_material_buffer) = self._bindless_material_buffer.borrow_mut().take() {
+            buffer::destroy_shader_buffer_data(self.get_device(), &mut bindless_material_buffer);
+        }
+        if let Some(mut material_override_buffer) = self._material_override_buffer.borrow_mut().take() {
+            buffer::destroy_shader_buffer_data(self.get_device(), &mut material_override_buffer);
+        }
+        self._gpu_skinning.borrow_mut().destroy_gpu_skinning_buffers(self.get_device());
+    }
+
+    // the skeletal objects of the visible and the shadow visible objects, the bone matrices of each object get their own range.
+    // the skinned geometries are drawn by draw_render_element after apply_gpu_skinning in the same frame.
+    pub fn dispatch_gpu_skinning(&self, command_buffer: vk::CommandBuffer, swapchain_index: u32, scene_manager_data: &SceneManagerData) {
+        let mut gpu_skinning = self._gpu_skinning.borrow_mut();
+        gpu_skinning.begin_frame();
+        let resources = self._resources.borrow();
+        if false == gpu_skinning::is_gpu_skinning_enabled() || false == resources.has_render_pass_data(gpu_skinning::GPU_SKINNING_RENDER_PASS_NAME) {
+            return;
+        }
+        if gpu_skinning._bone_matrix_buffer.is_none() {
+            gpu_skinning.create_gpu_skinning_buffers(self);
+        }
+
+        let mut skinned_indices: Vec<u32> = scene_manager_data.get_visible_indices().clone();
+        skinned_indices.extend_from_slice(scene_manager_data.get_shadow_visible_indices());
+        skinned_indices.sort();
+        skinned_indices.dedup();
+        for render_element in scene_manager_data.get_skeletal_render_elements(&skinned_indices).iter() {
+            let render_object_data = render_element._render_object.borrow();
+            if render_object_data._animation_play_info.as_ref().map_or(true, |animation_play_info| animation_play_info._animation_buffers.is_empty()) {
+                continue;
+            }
+            let bone_matrix_allocation = gpu_skinning.allocate_bone_matrices(
+                render_element._object_id,
+                render_object_data.get_animation_buffer(0),
+                render_object_data.get_prev_animation_buffer(0)
+            );
+            match bone_matrix_allocation {
+                Some(bone_matrix_allocation) => {
+                    if gpu_skinning.allocate_skinned_geometry(render_element._object_id, &render_element._geometry_data.borrow(), &bone_matrix_allocation).is_none() {
+                        log::warn!("dispatch_gpu_skinning: {} vertices are full, {} is skinned by the skeletal pipeline", gpu_skinning::GPU_SKINNING_MAX_VERTEX_COUNT, render_object_data._render_object_name);
+                    }
+                },
+                None => log::warn!("dispatch_gpu_skinning: {} bone matrices are full, {} is skinned by the skeletal pipeline", gpu_skinning::GPU_SKINNING_MAX_BONE_MATRIX_COUNT, render_object_data._render_object_name),
+            }
+        }
+        gpu_skinning.dispatch_gpu_skinning(command_buffer, swapchain_index, self, &resources);
+    }
+
+    // the offsets of dispatch_gpu_skinning for the skeletal render elements of the project, INVALID_SKINNING_OFFSET when they are not skinned
+    pub fn apply_gpu_skinning(&self, render_elements: &mut [RenderElementData]) {
+        let gpu_skinning = self._gpu_skinning.borrow();
+        for render_element in render_elements.iter_mut() {
+            match gpu_skinning.get_bone_matrix_allocation(render_element._object_id) {
+                Some(bone_matrix_allocation) => {
+                    render_element._bone_matrix_offset = bone_matrix_allocation._bone_matrix_offset;
+                    render_element._prev_bone_matrix_offset = bone_matrix_allocation._prev_bone_matrix_offset;
+                },
+                None => {
+                    render_element._bone_matrix_offset = gpu_skinning::INVALID_SKINNING_OFFSET;
+                    render_element._prev_bone_matrix_offset = gpu_skinning::INVALID_SKINNING_OFFSET;
+                },
+            }
+            render_element._skinned_vertex_offset = match gpu_skinning.get_skinned_geometry(render_element._object_id, &render_element._geometry_data.borrow()) {
+                Some(skinned_geometry) => skinned_geometry._skinned_vertex_offset,
+                None => gpu_skinning::INVALID_SKINNING_OFFSET,
+            };
+        }
+    }
+
+    // the skinned vertices with the index buffer of the geometry when the element is skinned, so the static pipelines can draw it
+    pub fn draw_render_element(&self, command_buffer: vk::CommandBuffer, render_element: &RenderElementData) {
+        let geometry_data = render_element._geometry_data.borrow();
+        if gpu_skinning::INVALID_SKINNING_OFFSET == render_element._skinned_vertex_offset {
+            self.draw_elements(command_buffer, &geometry_data);
+            return;
+        }
+        let gpu_skinning = self._gpu_skinning.borrow();
+        let skinned_vertex_buffer = match gpu_skinning._skinned_vertex_buffer.as_ref() {
+            Some(skinned_vertex_buffer) => skinned_vertex_buffer._buffer,
+            None => return,
+        };
+        let vertex_offset = render_element._skinned_vertex_offset as vk::DeviceSize * std::mem::size_of::<geometry_buffer::StaticVertexData>() as vk::DeviceSize;
+        unsafe {
+            self._device.cmd_bind_vertex_buffers(command_buffer, 0, &[skinned_vertex_buffer], &[vertex_offset]);
+            self._device.cmd_bind_index_buffer(command_buffer, geometry_data._index_buffer_data._buffer, 0, vk::IndexType::UINT32);
+            self._device.cmd_draw_indexed(command_buffer, geometry_data._vertex_index_count, 1, 0, 0, 0);
+        }
+        self._draw_call_count.set(self._draw_call_count.get() + 1);
+    }
+
+    // called by the project renderer after the velocity and the depth, before TAA, SSR and the other temporal passes.
+    // the temporal passes bind history_confidence::HISTORY_CONFIDENCE_TEXTURE_NAME as a render target.
+    pub fn dispatch_history_confidence(&self, command_buffer: vk::CommandBuffer, swapchain_index: u32) {
+        let settings = self._history_confidence.borrow()._settings.clone();
+        if self.find_debug_render_target_data(&settings._velocity_target_name).is_none() || self.find_debug_render_target_data(&settings._depth_target_name).is_none() {
+            log::error!("dispatch_history_confidence: {} or {} does not exist.", settings._velocity_target_name, settings._depth_target_name);
+            return;
+        }
+        self._history_confidence.borrow_mut().dispatch_history_confidence(command_buffer, swapchain_index, self, &self._resources.borrow());
+    }
+
+    // the velocity and the depth targets of the project renderer, the thresholds of the rejection
+    pub fn set_history_confidence_settings(&self, history_confidence_settings: &HistoryConfidenceSettings) {
+        self._history_confidence.borrow_mut().set_history_confidence_settings(history_confidence_settings);
+        log::info!("set_history_confidence_settings: {:?}", history_confidence_settings);
+    }
+
+    // called by the project renderer after the lighting and the transparents, before render_final.
+    // render_final multiplies AUTO_EXPOSURE_BUFFER_NAME.exposure with PushConstant_RenderFinal::_exposure.
+    pub fn dispatch_auto_exposure(&self, command_buffer: vk::CommandBuffer, swapchain_index: u32, delta_time: f64) {
+        if false == self._resources.borrow().has_render_pass_data(auto_exposure::AUTO_EXPOSURE_RENDER_PASS_NAME) {
+            return;
+        }
+        let profile = self._post_process_consumers.borrow().get_post_process_consumer(post_process::MAIN_POST_PROCESS_CONSUMER).unwrap()._profile.clone();
+        self._auto_exposure.borrow_mut().dispatch_auto_exposure(command_buffer, swapchain_index, delta_time as f32, &profile, self, &self._resources.borrow());
+    }
+
+    // the cpu frustum culling of SceneManagerData::cull_scene remains for the shadow casters and the skeletal objects.
+    pub fn is_gpu_occlusion_culling_enabled(&self) -> bool {
+        false == unsafe { constants::FORCE_CPU_OCCLUSION_CULLING }
+            && self.is_draw_indirect_count_supported()
+            && self._occlusion_culling.borrow().is_descriptor_sets_created()
+    }
+
+    pub fn set_occlusion_culling_view_projection(&self, view_projection: &Matrix4<f32>) {
+        self._occlusion_culling.borrow_mut().set_view_projection(view_projection);
+    }
+
+    // once per frame before render_solid_instanced of the main camera with the groups of SceneManagerData::get_static_draw_indices,
+    // the groups are drawn with the indirect commands of this frame. the other groups, ex) the shadow casters stay on the cpu path.
+    pub fn dispatch_occlusion_culling(&self, command_buffer: vk::CommandBuffer, swapchain_index: u32, render_element_groups: &mut [RenderElementGroupData]) {
+        if false == self.is_gpu_occlusion_culling_enabled() {
+            return;
+        }
+        RendererData::assign_instance_offsets(render_element_groups);
+        self._occlusion_culling.borrow_mut().dispatch_occlusion_culling(command_buffer, swapchain_index, render_element_groups, self, &self._resources.borrow());
+    }
+
+    pub fn set_occlusion_culling_settings(&self, occlusion_culling_settings: &OcclusionCullingSettings) {
+        self._occlusion_culling.borrow_mut().set_occlusion_culling_settings(occlusion_culling_settings);
+        log::info!("set_occlusion_culling_settings: {:?}", occlusion_culling_settings);
+    }
+
+    pub fn is_auto_exposure_enabled(&self) -> bool { unsafe { constants::POST_PROCESS_AUTO_EXPOSURE } }
+
+    // false: PostProcessProfile::_exposure_value of the main viewport, the exposure key of TonemapSettings works in both modes
+    pub fn set_auto_exposure_enabled(&self, is_enabled: bool) {
+        if is_enabled != self.is_auto_exposure_enabled() {
+            unsafe {
+                constants::POST_PROCESS_AUTO_EXPOSURE = is_enabled;
+            }
+            self.update_post_process_datas();
+            log::info!("set_auto_exposure_enabled: {}", is_enabled);
+        }
+    }
+
+    pub fn toggle_auto_exposure(&self) {
+        self.set_auto_exposure_enabled(false == self.is_auto_exposure_enabled());
+    }
+
+    // the descriptor sets of a new target are created at the next prepare_framebuffer_and_descriptors
+    pub fn set_auto_exposure_settings(&self, auto_exposure_settings: &AutoExposureSettings) {
+        self._auto_exposure.borrow_mut().set_auto_exposure_settings(auto_exposure_settings);
+        log::info!("set_auto_exposure_settings: {:?}", auto_exposure_settings);
+    }
+
+    // of the main viewport, the camera jitter is zero when it is not AntiAliasingMode::TAA
+    pub fn get_anti_aliasing_mode(&self) -> AntiAliasingMode {
+        self._post_process_consumers.borrow().get_post_process_consumer(post_process::MAIN_POST_PROCESS_CONSUMER).unwrap()._profile.get_anti_aliasing_mode()
+    }
+
+    pub fn set_anti_aliasing_mode(&self, anti_aliasing_mode: AntiAliasingMode) {
+        if anti_aliasing_mode == self.get_anti_aliasing_mode() {
+            return;
+        }
+        log::info!("set_anti_aliasing_mode: {:?}", anti_aliasing_mode);
+        unsafe {
+            constants::POST_PROCESS_TAA = AntiAliasingMode::TAA == anti_aliasing_mode;
+            constants::POST_PROCESS_FXAA = AntiAliasingMode::FXAA == anti_aliasing_mode;
+        }
+        self.update_post_process_datas();
+        // the taa history is stale after FXAA or no anti aliasing
+        self._post_process_consumers.borrow_mut().invalidate_post_process_histories();
+    }
+
+    // None -> FXAA -> TAA
+    pub fn next_anti_aliasing_mode(&self) {
+        let anti_aliasing_mode = self.get_anti_aliasing_mode().get_next_anti_aliasing_mode();
+        self.set_anti_aliasing_mode(anti_aliasing_mode);
+    }
+
+    pub fn get_debug_view_mode(&self) -> DebugViewMode {
+        self._debug_view_mode.get()
+    }
+
+    // None -> Wireframe -> WorldNormal -> UV -> VertexColor
+    pub fn next_debug_view_mode(&self) {
+        let debug_view_mode = self.get_debug_view_mode().next();
+        self._debug_view_mode.set(debug_view_mode);
+        log::info!("debug view mode: {:?}", debug_view_mode);
+    }
+
+    // the debug view variant of the opaque pipelines while a debug view mode is active, the pipeline of the material instance otherwise.
+    // the skeletal pass of the project draws with this too, and uploads get_debug_view_mode in its push constant.
+    pub fn get_debug_view_pipeline_binding_data<'a>(&self, material_instance_data: &'a MaterialInstanceData, render_pass_pipeline_data_name: &str) -> &'a PipelineBindingData {
+        if let Some(debug_view_name) = self.get_debug_view_mode().get_debug_view_name() {
+            let debug_view_pipeline_data_name = debug_view::get_debug_view_render_pass_pipeline_data_name(render_pass_pipeline_data_name, debug_view_name);
+            if material_instance_data.has_pipeline_binding_data(&debug_view_pipeline_data_name) {
+                return material_instance_data.get_pipeline_binding_data(&debug_view_pipeline_data_name);
+            }
+            if false == self._debug_view_fallback_logged.get() && debug_view::is_debug_view_render_pass(render_pass_pipeline_data_name.split('/').next().unwrap_or("")) {
+                self._debug_view_fallback_logged.set(true);
+                if debug_view::DEBUG_VIEW_WIREFRAME_NAME == debug_view_name && false == self.is_fill_mode_non_solid_supported() {
+                    log::warn!("get_debug_view_pipeline_binding_data: fillModeNonSolid is not supported, the wireframe falls back to the normal pipeline.");
+                } else {
+                    log::warn!("get_debug_view_pipeline_binding_data: {} does not exist, see constants::ENABLE_DEBUG_VIEW_PIPELINES", debug_view_pipeline_data_name);
+                }
+            }
+        }
+        material_instance_data.get_pipeline_binding_data(render_pass_pipeline_data_name)
+    }
+
+    // called by the project renderer instead of TAA when get_anti_aliasing_mode is not AntiAliasingMode::TAA,
+    // AntiAliasingMode::None copies the source so the resolve target is always written.
+    pub fn dispatch_fxaa(&self, command_buffer: vk::CommandBuffer, swapchain_index: u32) {
+        if false == self._resources.borrow().has_render_pass_data(fxaa::FXAA_RENDER_PASS_NAME) {
+            return;
+        }
+        self._fxaa.borrow().dispatch_fxaa(command_buffer, swapchain_index, self.get_anti_aliasing_mode(), self, &self._resources.borrow());
+    }
+
+    // renders every scattering order outside of the frame, then the light probe captures the new sky.
+    pub fn precompute_atmosphere(&self) {
+        if false == self._resources.borrow().has_render_pass_data(atmosphere::ATMOSPHERE_RENDER_PASS_NAME) {
+            return;
+        }
+        self.device_wait_idle();
+        vulkan_context::run_commands_once(self.get_device(), self.get_command_pool(), self.get_graphics_queue(), |_device: &Device, command_buffer: vk::CommandBuffer| {
+            self._atmosphere.borrow_mut().precompute_atmosphere(command_buffer, self, &self._resources.borrow());
+        });
+        self._light_probe.borrow_mut().invalidate_light_probe();
+        log::info!("precompute_atmosphere: {:?}", self._atmosphere.borrow()._settings);
+    }
+
+    // returns true when the render targets must be created again for the separate single mie scattering texture,
+    // the other changes are precomputed at the next render_scene.
+    pub fn set_atmosphere_settings(&self, atmosphere_settings: &AtmosphereSettings) -> bool {
+        self._atmosphere.borrow_mut().set_atmosphere_settings(atmosphere_settings)
+    }
+
+    // captures the atmosphere at the position outside of the frame, then prefilters the specular mips and convolves the irradiance.
+    pub fn render_light_probe(&self, probe_position: &Vector3<f32>) {
+        if false == self._resources.borrow().has_render_pass_data(light_probe::LIGHT_PROBE_RENDER_PASS_NAME) {
+            return;
+        }
+        self.device_wait_idle();
+        vulkan_context::run_commands_once(self.get_device(), self.get_command_pool(), self.get_graphics_queue(), |_device: &Device, command_buffer: vk::CommandBuffer| {
+            self._light_probe.borrow_mut().render_light_probe(command_buffer, self, &self._resources.borrow(), probe_position);
+        });
+        log::info!("render_light_probe: {:?}", probe_position);
+    }
+
+    // the sky mode of the environment settings, called by render_scene. the project renderer skips its atmosphere
+    // when get_sky_mode is not SkyMode::Atmosphere, and calls render_skybox after the opaque passes.
+    pub fn set_sky_settings(&self, sky_settings: &SkySettings) {
+        if self._skybox.borrow().is_same_sky_settings(sky_settings) {
+            return;
+        }
+        let is_sky_cubemap_changed = self._skybox.borrow_mut().set_sky_settings(sky_settings);
+        log::info!("set_sky_settings: {:?}", sky_settings);
+        let resources = self._resources.borrow();
+        if is_sky_cubemap_changed && resources.has_render_pass_data(skybox::SKYBOX_RENDER_PASS_NAME) {
+            self.prepare_sky_cubemap_descriptors(&resources);
+        } else {
+            self._light_probe.borrow_mut().set_sky_intensity(sky_settings._sky_intensity);
+        }
+    }
+
+    pub fn get_sky_mode(&self) -> SkyMode {
+        self._skybox.borrow()._sky_mode.unwrap_or(SkyMode::Atmosphere)
+    }
+
+    // the sky cubemap behind the opaque geometry, the depth test EQUAL with the far depth keeps the covered pixels.
+    // inv_view_origin_projection: CameraObjectData::_inv_view_origin_projection_jitter of the opaque passes
+    pub fn render_skybox(&self, command_buffer: vk::CommandBuffer, swapchain_index: u32, inv_view_origin_projection: &Matrix4<f32>) {
+        let resources = self._resources.borrow();
+        if false == resources.has_render_pass_data(skybox::SKYBOX_RENDER_PASS_NAME) {
+            return;
+        }
+        self._skybox.borrow().render_skybox(command_buffer, swapchain_index, self, &resources, inv_view_origin_projection);
+    }
+
+    // called by the project renderer after the lighting and the transparents, before bloom. the fog is FogSettings of the environment settings,
+    // light_constants: the main DirectionalLightData whose shadow map is VolumetricFogSettings::_shadow_target_name
+    pub fn render_volumetric_fog(&self, command_buffer: vk::CommandBuffer, swapchain_index: u32, camera: &CameraObjectData, light_constants: &LightConstants) {
+        let resources = self._resources.borrow();
+        if false == resources.has_render_pass_data(volumetric_fog::VOLUMETRIC_FOG_RENDER_PASS_NAME) {
+            return;
+        }
+        let settings = self._volumetric_fog.borrow()._settings.clone();
+        let targets = (
+            self.find_debug_render_target_data(&settings._color_target_name),
+            self.find_debug_render_target_data(&settings._depth_target_name),
+            self.find_debug_render_target_data(&settings._shadow_target_name),
+        );
+        match targets {
+            (Some(color_target), Some(depth_target), Some(shadow_target)) => {
+                let targets = unsafe { (&*color_target, &*depth_target, &*shadow_target) };
+                self._volumetric_fog.borrow_mut().render_volumetric_fog(command_buffer, swapchain_index, self, &resources, targets, camera, light_constants);
+            },
+            _ => log::error!("render_volumetric_fog: {}, {} or {} does not exist.", settings._color_target_name, settings._depth_target_name, settings._shadow_target_name),
+        }
+    }
+
+    // called by the project renderer after its gbuffer pass, before the decals and composite_gbuffer.
+    // the chunks are culled by SceneManagerData::cull_scene with the same camera.
+    pub fn render_terrain(&self, command_buffer: vk::CommandBuffer, swapchain_index: u32, camera: &CameraObjectData) {
+        let resources = self._resources.borrow();
+        if false == resources.has_render_pass_data(terrain::TERRAIN_RENDER_PASS_NAME) {
+            return;
+        }
+        let render_settings = self._terrain.borrow()._render_settings.clone();
+        let color_targets: Vec<*const TextureData> = render_settings._color_target_names.iter().filter_map(|target_name| self.find_debug_render_target_data(target_name)).collect();
+        match self.find_debug_render_target_data(&render_settings._depth_target_name) {
+            Some(depth_target) if color_targets.len() == render_settings._color_target_names.len() => {
+                let color_targets: Vec<&TextureData> = color_targets.iter().map(|color_target| unsafe { &**color_target }).collect();
+                let targets = (color_targets.as_slice(), unsafe { &*depth_target });
+                self._terrain.borrow_mut().render_terrain(command_buffer, swapchain_index, self, &resources, targets, camera);
+            },
+            _ => log::error!("render_terrain: {:?} or {} does not exist.", render_settings._color_target_names, render_settings._depth_target_name),
+        }
+    }
+
+    // the terrain of the environment settings, called by render_scene. the chunks are built again after the device is idle.
+    pub fn set_terrain_settings(&self, terrain_settings: &TerrainSettings) {
+        if self._terrain.borrow()._settings == *terrain_settings {
+            return;
+        }
+        if self._terrain.borrow()._settings.is_geometry_changed(terrain_settings) {
+            self.device_wait_idle();
+        }
+        self._terrain.borrow_mut().set_terrain_settings(self, &self._resources.borrow(), terrain_settings);
+        log::info!("set_terrain_settings: {:?}", terrain_settings);
+    }
+
+    // the gbuffer targets of the project renderer, the render pass of new targets is created by the next resource reload
+    pub fn set_terrain_render_settings(&self, terrain_render_settings: &TerrainRenderSettings) {
+        self._terrain.borrow_mut().set_terrain_render_settings(self, terrain_render_settings);
+        log::info!("set_terrain_render_settings: {:?}", terrain_render_settings);
+    }
+
+    // the targets of the project renderer, the render passes of a new color target are created by the next resource reload
+    pub fn set_volumetric_fog_settings(&self, volumetric_fog_settings: &VolumetricFogSettings) {
+        self._volumetric_fog.borrow_mut().set_volumetric_fog_settings(self, volumetric_fog_settings);
+        log::info!("set_volumetric_fog_settings: {:?}", volumetric_fog_settings);
+    }
+
+    // the texture sizes are applied by the next create_render_targets
+    pub fn set_light_probe_settings(&self, light_probe_settings: &LightProbeSettings) {
+        self._light_probe.borrow_mut().set_light_probe_settings(light_probe_settings);
+        log::info!("set_light_probe_settings: {:?}", light_probe_settings);
+    }
+
+    // the descriptor sets of new targets are created at the next prepare_framebuffer_and_descriptors
+    pub fn set_fxaa_settings(&self, fxaa_settings: &FXAASettings) {
+        self._fxaa.borrow_mut().set_fxaa_settings(fxaa_settings);
+        log::info!("set_fxaa_settings: {:?}", fxaa_settings);
+    }
+
+    // the history confidence is zero for the next frame, ex) camera cut, teleport, scene load, quality preset change
+    pub fn invalidate_temporal_history(&self) {
+        self._history_confidence.borrow_mut().invalidate_history();
+        self._auto_exposure.borrow_mut().invalidate_history();
+        self._occlusion_culling.borrow_mut().invalidate_history();
+        self._volumetric_fog.borrow_mut().invalidate_history();
+        self.invalidate_denoiser_histories();
+    }
+
+    // ex) the project renderer replaces render_ssao_blur:
+    // create_denoiser_instance(DenoiserInstanceBuilder::new("ssao", "SSAO", "SSAOTemp").with_iterations(2).build())
+    // then dispatch_denoiser(command_buffer, swapchain_index, "ssao") after render_ssao.
+    pub fn create_denoiser_instance(&self, denoiser_instance: Result<DenoiserInstance, String>) -> bool {
+        let denoiser_instance = match denoiser_instance {
+            Ok(denoiser_instance) => denoiser_instance,
+            Err(error) => {
+                log::error!("create_denoiser_instance: {}", error);
+                return false;
+            }
+        };
+        let denoiser_name = String::from(denoiser_instance.get_denoiser_name());
+        log::info!("create_denoiser_instance: {:?}", denoiser_instance._create_info);
+        if let Some(mut prev_denoiser_instance) = self._denoisers.borrow_mut().insert(denoiser_name, denoiser_instance) {
+            prev_denoiser_instance.destroy_denoiser_textures(self);
+        }
+        true
+    }
+
+    pub fn destroy_denoiser_instance(&self, denoiser_name: &str) {
+        if let Some(mut denoiser_instance) = self._denoisers.borrow_mut().remove(denoiser_name) {
+            denoiser_instance.destroy_denoiser_textures(self);
+        }
+    }
+
+    // the temporal pre-accumulation restarts from the current frame
+    pub fn invalidate_denoiser_histories(&self) {
+        for denoiser_instance in self._denoisers.borrow_mut().values_mut() {
+            denoiser_instance.invalidate_history();
+        }
+    }
+
+    // the input, the guides and the output are render targets of get_render_target_from_str
+    pub fn dispatch_denoiser(&self, command_buffer: vk::CommandBuffer, swapchain_index: u32, denoiser_name: &str) {
+        let mut denoisers = self._denoisers.borrow_mut();
+        let denoiser_instance = match denoisers.get_mut(denoiser_name) {
+            Some(denoiser_instance) => denoiser_instance,
+            None => {
+                log::error!("dispatch_denoiser: {} does not exist.", denoiser_name);
+                return;
+            }
+        };
+        let create_info = &denoiser_instance._create_info;
+        let mut target_names = vec![&create_info._input_target_name, &create_info._output_target_name, &create_info._depth_target_name];
+        if false == create_info._normal_target_name.is_empty() {
+            target_names.push(&create_info._normal_target_name);
+        }
+        let history_confidence_target_name = String::from(history_confidence::HISTORY_CONFIDENCE_TEXTURE_NAME);
+        if create_info._use_history_confidence {
+            target_names.push(&history_confidence_target_name);
+        }
+        if let Some(target_name) = target_names.iter().find(|target_name| self.find_debug_render_target_data(target_name).is_none()) {
+            log::error!("dispatch_denoiser: {} of {} does not exist.", target_name, denoiser_name);
+            return;
+        }
+        denoiser_instance.dispatch_denoiser(command_buffer, swapchain_index, self, &self._resources.borrow());
+    }
+
+    // debug histogram, the target is a render target name of the project renderer.
+    pub fn set_debug_histogram(&self, is_enabled: bool, histogram_settings: &HistogramSettings) {
+        let mut debug_histogram = self._debug_histogram.borrow_mut();
+        debug_histogram._is_enabled = is_enabled;
+        debug_histogram.set_histogram_settings(histogram_settings);
+        log::info!("set_debug_histogram: {} {:?}", is_enabled, histogram_settings);
+    }
+
+    // the result is a few frames behind the dispatch, see DebugHistogram::dispatch_histogram.
+    pub fn update_debug_histogram(&self, font_manager: &mut FontManager, ui_manager_data: &mut UIManagerData) {
+        let debug_histogram = self._debug_histogram.borrow();
+        match debug_histogram.get_histogram_result() {
+            Some(histogram_result) => {
+                font_manager.log(histogram_result.get_summary_text(&debug_histogram._settings));
+                ui_manager_data.set_debug_histogram_bars(&histogram_result.get_normalized_bars());
+            },
+            None => ui_manager_data.set_debug_histogram_bars(&[]),
+        }
+    }
+}
+
+// the last caught panic of render_scene and the remaining frames of the banner
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RenderErrorState {
+    pub _render_error_message: Option<String>,
+    pub _render_error_banner_frames: u32,
+}
+
+impl RenderErrorState {
+    pub fn set_render_error(&mut self, render_error_message: String) {
+        self._render_error_message = Some(render_error_message);
+        self._render_error_banner_frames = RENDER_ERROR_BANNER_FRAMES;
+    }
+
+    // a frame rendered without the panic
+    pub fn update_render_error_banner(&mut self) {
+        if 0 < self._render_error_banner_frames {
+            self._render_error_banner_frames -= 1;
+        }
+    }
+
+    pub fn get_render_error_message(&self) -> Option<&String> {
+        if 0 < self._render_error_banner_frames { self._render_error_message.as_ref() } else { None }
+    }
+}
+
+// the recording of a frame, a panic aborts the recording and recover_frame replaces what was recorded.
+// the frame is presented either way, the message stays on the banner for RENDER_ERROR_BANNER_FRAMES.
+pub fn record_frame_with_recovery<R: FnOnce(), E: FnOnce(&str)>(render_error_state: &mut RenderErrorState, record_frame: R, recover_frame: E) {
+    match panic::catch_unwind(AssertUnwindSafe(record_frame)) {
+        Ok(_) => render_error_state.update_render_error_banner(),
+        Err(panic_payload) => {
+            let render_error_message = get_panic_message(&panic_payload);
+            recover_frame(&render_error_message);
+            render_error_state.set_render_error(render_error_message);
+        }
+    }
+}
+
+// debug build only, panics once when DEBUG_INJECT_RENDER_PANIC is set
+pub fn inject_debug_render_panic() {
+    unsafe {
+        if cfg!(debug_assertions) && constants::DEBUG_INJECT_RENDER_PANIC {
+            constants::DEBUG_INJECT_RENDER_PANIC = false;
+            panic!("{}", DEBUG_INJECT_RENDER_PANIC_MESSAGE);
+        }
+    }
+}
+
+pub fn get_panic_message(panic_payload: &Box<dyn std::any::Any + Send>) -> String {
+    if let Some(message) = panic_payload.downcast_ref::<&str>() {
+        String::from(*message)
+    } else if let Some(message) = panic_payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        String::from("unknown panic")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // render_scene of a headless frame: the recording is aborted by the injected panic, the frame is presented either way.
+    fn render_frame(render_error_state: &mut RenderErrorState, recorded_frame_count: &mut u32, presented_frame_count: &mut u32) {
+        record_frame_with_recovery(render_error_state, || {
+            inject_debug_render_panic();
+            *recorded_frame_count += 1;
+        }, |render_error_message| {
+            assert_eq!(DEBUG_INJECT_RENDER_PANIC_MESSAGE, render_error_message);
+        });
+        *presented_frame_count += 1;
+    }
+
+    #[test]
+    fn test_render_panic_recovery() {
+        let mut render_error_state = RenderErrorState::default();
+        let mut recorded_frame_count: u32 = 0;
+        let mut presented_frame_count: u32 = 0;
+        render_frame(&mut render_error_state, &mut recorded_frame_count, &mut presented_frame_count);
+        assert_eq!((1, 1), (recorded_frame_count, presented_frame_count));
+        assert_eq!(None, render_error_state.get_render_error_message());
+
+        unsafe { constants::DEBUG_INJECT_RENDER_PANIC = true; }
+        render_frame(&mut render_error_state, &mut recorded_frame_count, &mut presented_frame_count);
+        assert!(false == unsafe { constants::DEBUG_INJECT_RENDER_PANIC });
+        if cfg!(debug_assertions) {
+            assert_eq!((1, 2), (recorded_frame_count, presented_frame_count));
+            assert_eq!(Some(&String::from(DEBUG_INJECT_RENDER_PANIC_MESSAGE)), render_error_state.get_render_error_message());
+        } else {
+            assert_eq!((2, 2), (recorded_frame_count, presented_frame_count));
+            return;
+        }
+
+        // the next frames are rendered and presented with the banner, until the banner frames are over
+        render_frame(&mut render_error_state, &mut recorded_frame_count, &mut presented_frame_count);
+        assert_eq!((2, 3), (recorded_frame_count, presented_frame_count));
+        assert_eq!(RENDER_ERROR_BANNER_FRAMES - 1, render_error_state._render_error_banner_frames);
+        for _ in 1..RENDER_ERROR_BANNER_FRAMES {
+            assert!(render_error_state.get_render_error_message().is_some());
+            render_frame(&mut render_error_state, &mut recorded_frame_count, &mut presented_frame_count);
+        }
+        assert_eq!(None, render_error_state.get_render_error_message());
+        assert_eq!(RENDER_ERROR_BANNER_FRAMES + 2, presented_frame_count);
+    }
+
+    #[test]
+    fn test_panic_message() {
+        let panic_payload = panic::catch_unwind(|| panic!("render error")).unwrap_err();
+        assert_eq!("render error", get_panic_message(&panic_payload));
+        let panic_payload = panic::catch_unwind(|| panic!("render error {}", 1)).unwrap_err();
+        assert_eq!("render error 1", get_panic_message(&panic_payload));
+        let panic_payload = panic::catch_unwind(|| std::panic::panic_any(1)).unwrap_err();
+        assert_eq!("unknown panic", get_panic_message(&panic_payload));
+    }
+
+    #[test]
+    fn test_render_scale() {
+        assert_eq!(None, get_changed_render_scale(1.0, 1.0));
+        assert_eq!(Some(0.7), get_changed_render_scale(1.0, 0.7));
+        assert_eq!(None, get_changed_render_scale(0.7, 0.7));
+        assert_eq!(Some(MIN_RENDER_SCALE), get_changed_render_scale(1.0, 0.0));
+        assert_eq!(Some(MAX_RENDER_SCALE), get_changed_render_scale(1.0, 10.0));
+        // the clamped scale is the same, the render targets are kept
+        assert_eq!(None, get_changed_render_scale(MAX_RENDER_SCALE, 4.0));
+    }
+
+    #[test]
+    fn test_render_extent() {
+        let swapchain_extent = vk::Extent2D { width: 1920, height: 1080 };
+        assert_eq!(swapchain_extent, get_render_extent(&swapchain_extent, 1.0));
+        assert_eq!(vk::Extent2D { width: 1344, height: 756 }, get_render_extent(&swapchain_extent, 0.7));
+        assert_eq!(vk::Extent2D { width: 3840, height: 2160 }, get_render_extent(&swapchain_extent, 2.0));
+        // never zero, the window is minimized
+        assert_eq!(vk::Extent2D { width: 1, height: 1 }, get_render_extent(&vk::Extent2D { width: 1, height: 1 }, MIN_RENDER_SCALE));
+        assert_eq!(vk::Extent2D { width: 1, height: 1 }, get_render_extent(&vk::Extent2D { width: 0, height: 0 }, 1.0));
+    }
+}
@@ -0,0 +1,252 @@
+use std::path::{ Path, PathBuf };
+use std::thread::{ self, JoinHandle };
+
+use ash::{ vk, Device };
+use ash::version::DeviceV1_0;
+
+use crate::vulkan_context::buffer::{ self, BufferData };
+
+pub const SCREENSHOT_DIRECTORY: &str = "screenshots";
+// the render target before the tonemap, when the swapchain images can't be the source of a copy
+pub const SCREENSHOT_FALLBACK_RENDER_TARGET_NAME: &str = "SceneColor";
+
+// the source of the copy recorded at the end of the frame
+#[derive(Clone, Debug)]
+pub struct ScreenshotSource {
+    pub _image: vk::Image,
+    pub _image_layout: vk::ImageLayout, // the layout at the end of the frame, restored after the copy
+    pub _image_format: vk::Format,
+    pub _width: u32,
+    pub _height: u32,
+}
+
+pub struct ScreenshotReadback {
+    pub _file_path: PathBuf,
+    pub _readback_buffer: BufferData,
+    pub _image_format: vk::Format,
+    pub _width: u32,
+    pub _height: u32,
+    pub _row_pitch: u32, // bytes per row of the readback buffer
+}
+
+#[derive(Default)]
+pub struct ScreenshotCapture {
+    pub _requested_file_path: Option<PathBuf>,
+    pub _pending_readback: Option<ScreenshotReadback>,
+    pub _writer_threads: Vec<JoinHandle<()>>,
+}
+
+pub fn get_default_screenshot_file_path() -> PathBuf {
+    let mut file_path = PathBuf::from(SCREENSHOT_DIRECTORY);
+    file_path.push(format!("screenshot_{}.png", chrono::Local::now().format("%Y%m%d_%H%M%S_%3f")));
+    file_path
+}
+
+// 4 bytes per texel of the png, None for the formats which can't be converted
+pub fn get_screenshot_texel_size(format: vk::Format) -> Option<u32> {
+    match format {
+        vk::Format::R8G8B8A8_UNORM | vk::Format::R8G8B8A8_SRGB |
+        vk::Format::B8G8R8A8_UNORM | vk::Format::B8G8R8A8_SRGB |
+        vk::Format::A2B10G10R10_UNORM_PACK32 => Some(4),
+        vk::Format::R16G16B16A16_SFLOAT => Some(8),
+        vk::Format::R32G32B32A32_SFLOAT => Some(16),
+        _ => None,
+    }
+}
+
+fn half_to_f32(half: u16) -> f32 {
+    let sign = if 0 != (half & 0x8000) { -1.0 } else { 1.0 };
+    let exponent = ((half >> 10) & 0x1f) as i32;
+    let mantissa = (half & 0x3ff) as f32;
+    match exponent {
+        0 => sign * mantissa * (2.0f32).powi(-24),
+        0x1f => if 0.0 == mantissa { sign * std::f32::INFINITY } else { std::f32::NAN },
+        _ => sign * (1.0 + mantissa / 1024.0) * (2.0f32).powi(exponent - 15),
+    }
+}
+
+// the float render targets are linear, clamped without the tonemap
+fn linear_to_srgb_u8(linear: f32) -> u8 {
+    let linear = if linear.is_nan() { 0.0 } else { linear.max(0.0).min(1.0) };
+    let srgb = if linear <= 0.0031308 { linear * 12.92 } else { 1.055 * linear.powf(1.0 / 2.4) - 0.055 };
+    (srgb * 255.0 + 0.5) as u8
+}
+
+// rgba8 rows of width * 4 bytes from the rows of row_pitch bytes, the swizzle of the bgra formats
+pub fn convert_to_rgba8(format: vk::Format, width: u32, height: u32, row_pitch: u32, texels: &[u8]) -> Vec<u8> {
+    let mut rgba8: Vec<u8> = Vec::with_capacity((width * height * 4) as usize);
+    let texel_size = get_screenshot_texel_size(format).unwrap() as usize;
+    for y in 0..height as usize {
+        let row = &texels[(y * row_pitch as usize)..(y * row_pitch as usize + width as usize * texel_size)];
+        for texel in row.chunks_exact(texel_size) {
+            match format {
+                vk::Format::B8G8R8A8_UNORM | vk::Format::B8G8R8A8_SRGB => rgba8.extend_from_slice(&[texel[2], texel[1], texel[0], 255]),
+                vk::Format::A2B10G10R10_UNORM_PACK32 => {
+                    let packed = u32::from_le_bytes([texel[0], texel[1], texel[2], texel[3]]);
+                    let to_u8 = |value: u32| ((value & 0x3ff) * 255 / 1023) as u8;
+                    rgba8.extend_from_slice(&[to_u8(packed), to_u8(packed >> 10), to_u8(packed >> 20), 255]);
+                },
+                vk::Format::R16G16B16A16_SFLOAT => {
+                    let channel = |i: usize| half_to_f32(u16::from_le_bytes([texel[i * 2], texel[i * 2 + 1]]));
+                    rgba8.extend_from_slice(&[linear_to_srgb_u8(channel(0)), linear_to_srgb_u8(channel(1)), linear_to_srgb_u8(channel(2)), 255]);
+                },
+                vk::Format::R32G32B32A32_SFLOAT => {
+                    let channel = |i: usize| f32::from_le_bytes([texel[i * 4], texel[i * 4 + 1], texel[i * 4 + 2], texel[i * 4 + 3]]);
+                    rgba8.extend_from_slice(&[linear_to_srgb_u8(channel(0)), linear_to_srgb_u8(channel(1)), linear_to_srgb_u8(channel(2)), 255]);
+                },
+                // the alpha of the backbuffer is not the coverage, the png is opaque
+                _ => rgba8.extend_from_slice(&[texel[0], texel[1], texel[2], 255]),
+            }
+        }
+    }
+    rgba8
+}
+
+impl ScreenshotCapture {
+    pub fn request_screenshot(&mut self, file_path: &Path) {
+        log::info!("request_screenshot: {:?}", file_path);
+        self._requested_file_path = Some(PathBuf::from(file_path));
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self._requested_file_path.is_some()
+    }
+
+    // the last command of the frame: the source -> TRANSFER_SRC_OPTIMAL -> the readback buffer -> the layout of the source.
+    pub fn record_screenshot_copy(
+        &mut self,
+        device: &Device,
+        memory_properties: &vk::PhysicalDeviceMemoryProperties,
+        command_buffer: vk::CommandBuffer,
+        source: &ScreenshotSource
+    ) {
+        let file_path = match self._requested_file_path.take() {
+            Some(file_path) => file_path,
+            None => return,
+        };
+        let texel_size = match get_screenshot_texel_size(source._image_format) {
+            Some(texel_size) => texel_size,
+            None => {
+                log::error!("record_screenshot_copy: not supported format {:?}, {:?} is skipped", source._image_format, file_path);
+                return;
+            }
+        };
+        if let Some(pending_readback) = self._pending_readback.take() {
+            buffer::destroy_buffer_data(device, &pending_readback._readback_buffer);
+        }
+
+        // buffer_row_length is in texels, any width including the odd widths is tightly packed by it
+        let row_pitch = source._width * texel_size;
+        let readback_buffer = buffer::create_buffer_data(
+            device,
+            memory_properties,
+            (row_pitch * source._height) as vk::DeviceSize,
+            vk::BufferUsageFlags::TRANSFER_DST,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT
+        );
+        let subresource_range = vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        };
+        let to_transfer_src = vk::ImageMemoryBarrier {
+            src_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE | vk::AccessFlags::SHADER_WRITE,
+            dst_access_mask: vk::AccessFlags::TRANSFER_READ,
+            old_layout: source._image_layout,
+            new_layout: vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            image: source._image,
+            subresource_range,
+            ..Default::default()
+        };
+        let to_source_layout = vk::ImageMemoryBarrier {
+            src_access_mask: vk::AccessFlags::TRANSFER_READ,
+            dst_access_mask: vk::AccessFlags::empty(),
+            old_layout: vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            new_layout: source._image_layout,
+            ..to_transfer_src
+        };
+        let readback_barrier = vk::BufferMemoryBarrier {
+            src_access_mask: vk::AccessFlags::TRANSFER_WRITE,
+            dst_access_mask: vk::AccessFlags::HOST_READ,
+            src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            buffer: readback_buffer._buffer,
+            offset: 0,
+            size: vk::WHOLE_SIZE,
+            ..Default::default()
+        };
+        let region = vk::BufferImageCopy {
+            buffer_offset: 0,
+            buffer_row_length: source._width,
+            buffer_image_height: source._height,
+            image_subresource: vk::ImageSubresourceLayers {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                mip_level: 0,
+                base_array_layer: 0,
+                layer_count: 1,
+            },
+            image_offset: vk::Offset3D { x: 0, y: 0, z: 0 },
+            image_extent: vk::Extent3D { width: source._width, height: source._height, depth: 1 },
+        };
+        unsafe {
+            device.cmd_pipeline_barrier(command_buffer, vk::PipelineStageFlags::ALL_COMMANDS, vk::PipelineStageFlags::TRANSFER, vk::DependencyFlags::empty(), &[], &[], &[to_transfer_src]);
+            device.cmd_copy_image_to_buffer(command_buffer, source._image, vk::ImageLayout::TRANSFER_SRC_OPTIMAL, readback_buffer._buffer, &[region]);
+            device.cmd_pipeline_barrier(command_buffer, vk::PipelineStageFlags::TRANSFER, vk::PipelineStageFlags::BOTTOM_OF_PIPE, vk::DependencyFlags::empty(), &[], &[], &[to_source_layout]);
+            device.cmd_pipeline_barrier(command_buffer, vk::PipelineStageFlags::TRANSFER, vk::PipelineStageFlags::HOST, vk::DependencyFlags::empty(), &[], &[readback_barrier], &[]);
+        }
+        self._pending_readback = Some(ScreenshotReadback {
+            _file_path: file_path,
+            _readback_buffer: readback_buffer,
+            _image_format: source._image_format,
+            _width: source._width,
+            _height: source._height,
+            _row_pitch: row_pitch,
+        });
+    }
+
+    // after the submit of the frame is complete, the png is written on a worker thread.
+    pub fn complete_screenshot(&mut self, device: &Device) {
+        let readback = match self._pending_readback.take() {
+            Some(readback) => readback,
+            None => return,
+        };
+        let mut texels: Vec<u8> = vec![0; (readback._row_pitch * readback._height) as usize];
+        buffer::read_buffer_data(device, &readback._readback_buffer, 0, &mut texels);
+        buffer::destroy_buffer_data(device, &readback._readback_buffer);
+
+        let (file_path, image_format, width, height, row_pitch) = (readback._file_path, readback._image_format, readback._width, readback._height, readback._row_pitch);
+        self._writer_threads.push(thread::spawn(move || {
+            let rgba8 = convert_to_rgba8(image_format, width, height, row_pitch, &texels);
+            if let Some(directory) = file_path.parent() {
+                if false == directory.as_os_str().is_empty() && false == directory.is_dir() {
+                    let _ = std::fs::create_dir_all(directory);
+                }
+            }
+            match image::save_buffer(&file_path, &rgba8, width, height, image::ColorType::Rgba8) {
+                Ok(_) => log::info!("screenshot: {:?} {}x{} {:?}", file_path, width, height, image_format),
+                Err(e) => log::error!("screenshot: failed to write {:?}: {:?}", file_path, e),
+            }
+        }));
+    }
+
+    // the copy of a frame which failed to submit is dropped, the request is kept for the next frame
+    pub fn discard_screenshot(&mut self, device: &Device) {
+        if let Some(readback) = self._pending_readback.take() {
+            buffer::destroy_buffer_data(device, &readback._readback_buffer);
+            self._requested_file_path = Some(readback._file_path);
+        }
+    }
+
+    pub fn destroy_screenshot_capture(&mut self, device: &Device) {
+        if let Some(readback) = self._pending_readback.take() {
+            buffer::destroy_buffer_data(device, &readback._readback_buffer);
+        }
+        for writer_thread in self._writer_threads.drain(..) {
+            let _ = writer_thread.join();
+        }
+    }
+}
@@ -0,0 +1,210 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use nalgebra::Vector4;
+use serde_json::Value;
+
+use crate::resource::mesh_cache::{ compute_hash, HASH_OFFSET_BASIS };
+use crate::vulkan_context::shader::{ SHADER_CACHE_DIRECTORY, SHADER_DIRECTORY };
+
+pub const SHADER_HOOK_DIRECTORY: &str = "hooks"; // relative to SHADER_DIRECTORY
+pub const MAX_SHADER_HOOK_PARAMETER_COUNT: usize = 2; // must match with shader_hook.glsl
+pub const DISSOLVE_SHADER_HOOK_FILE: &str = "hooks/dissolve.glsl";
+pub const DISSOLVE_SHADER_HOOK_SOURCE: &str = r#"// shader_hook_parameters[0].x : dissolve threshold
+float hook_opacity_modifier(float opacity, vec2 texcoord, vec3 world_position, vec4 shader_hook_parameters[2])
+{
+    float noise = fract(sin(dot(floor(world_position.xz * 16.0), vec2(12.9898, 78.233))) * 43758.5453);
+    if(noise < shader_hook_parameters[0].x)
+    {
+        discard;
+    }
+    return opacity;
+}
+"#;
+// shader_hook_parameters[0] is written by the wind system per object, see wind_system::WIND_SHADER_HOOK_PARAMETER_INDEX
+pub const WIND_SWAY_SHADER_HOOK_FILE: &str = "hooks/wind_sway.glsl";
+pub const WIND_SWAY_SHADER_HOOK_SOURCE: &str = r#"// shader_hook_parameters[0].xyz : sampled wind, w : wind time
+// shader_hook_parameters[1].x : sway strength, y : sway frequency, z : height of the full sway in the local space
+vec3 hook_vertex_offset(vec3 local_position, vec3 world_position, vec4 shader_hook_parameters[2])
+{
+    vec3 wind = shader_hook_parameters[0].xyz;
+    float wind_time = shader_hook_parameters[0].w;
+    float bend = clamp(local_position.y / max(shader_hook_parameters[1].z, 0.001), 0.0, 1.0);
+    bend *= bend;
+    float phase = dot(world_position.xz, vec2(0.37, 0.71));
+    float flutter = 0.8 + 0.2 * sin(wind_time * shader_hook_parameters[1].y + phase);
+    return vec3(wind.x, 0.0, wind.z) * (bend * flutter * shader_hook_parameters[1].x);
+}
+"#;
+pub const WIND_FLAG_SHADER_HOOK_FILE: &str = "hooks/wind_flag.glsl";
+pub const WIND_FLAG_SHADER_HOOK_SOURCE: &str = r#"// shader_hook_parameters[0].xyz : sampled wind, w : wind time
+// shader_hook_parameters[1].x : wave amplitude, y : wave frequency, z : length of the flag from the pole in the local space
+vec3 hook_vertex_offset(vec3 local_position, vec3 world_position, vec4 shader_hook_parameters[2])
+{
+    vec3 wind = shader_hook_parameters[0].xyz;
+    float wind_speed = length(wind);
+    float wind_time = shader_hook_parameters[0].w;
+    float along = clamp(local_position.x / max(shader_hook_parameters[1].z, 0.001), 0.0, 1.0);
+    float wave = sin(local_position.x * 6.0 - wind_time * shader_hook_parameters[1].y * (1.0 + wind_speed * 0.1));
+    vec3 wave_offset = vec3(0.0, 0.0, wave * shader_hook_parameters[1].x * along);
+    return wave_offset + wind * (0.02 * along);
+}
+"#;
+pub const WIND_SHADER_HOOK_FILES: [&str; 2] = [WIND_SWAY_SHADER_HOOK_FILE, WIND_FLAG_SHADER_HOOK_FILE];
+
+pub type ShaderHookParameters = [Vector4<f32>; MAX_SHADER_HOOK_PARAMETER_COUNT];
+
+// The standard object shaders include "hook_xxx.glsl" when HOOK_XXX is defined, the hooked variant
+// compiles with an include directory which has the snippets of the material instance.
+#[derive(Clone, Debug, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ShaderHookType {
+    VertexOffset,
+    AlbedoModifier,
+    OpacityModifier,
+}
+
+impl ShaderHookType {
+    pub fn from_hook_name(hook_name: &str) -> Option<ShaderHookType> {
+        match hook_name {
+            "vertex_offset" => Some(ShaderHookType::VertexOffset),
+            "albedo_modifier" => Some(ShaderHookType::AlbedoModifier),
+            "opacity_modifier" => Some(ShaderHookType::OpacityModifier),
+            _ => None,
+        }
+    }
+
+    pub fn get_hook_name(&self) -> &str {
+        match self {
+            ShaderHookType::VertexOffset => "vertex_offset",
+            ShaderHookType::AlbedoModifier => "albedo_modifier",
+            ShaderHookType::OpacityModifier => "opacity_modifier",
+        }
+    }
+
+    pub fn get_shader_define(&self) -> String {
+        format!("HOOK_{}", self.get_hook_name().to_uppercase())
+    }
+
+    pub fn get_include_file_name(&self) -> String {
+        format!("hook_{}.glsl", self.get_hook_name())
+    }
+}
+
+// material instance
+// "shader_hooks": { "opacity_modifier": "hooks/dissolve.glsl" },
+// "shader_hook_parameters": [[0.5, 0.0, 0.0, 0.0]]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ShaderHookSet {
+    pub _hook_files: BTreeMap<ShaderHookType, PathBuf>, // hook -> snippet file relative to SHADER_DIRECTORY
+}
+
+impl ShaderHookSet {
+    pub fn create_shader_hook_set(shader_hooks: Option<&Value>) -> ShaderHookSet {
+        let mut hook_files: BTreeMap<ShaderHookType, PathBuf> = BTreeMap::new();
+        if let Some(Value::Object(shader_hooks)) = shader_hooks {
+            for (hook_name, hook_file) in shader_hooks.iter() {
+                match (ShaderHookType::from_hook_name(hook_name), hook_file) {
+                    (Some(hook_type), Value::String(hook_file)) => {
+                        hook_files.insert(hook_type, PathBuf::from(hook_file));
+                    },
+                    _ => log::warn!("create_shader_hook_set: invalid shader hook {}: {:?}", hook_name, hook_file),
+                }
+            }
+        }
+        ShaderHookSet {
+            _hook_files: hook_files,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self._hook_files.is_empty()
+    }
+
+    pub fn get_hook_file(&self, hook_type: ShaderHookType) -> Option<&PathBuf> {
+        self._hook_files.get(&hook_type)
+    }
+
+    pub fn get_hook_set_key(&self) -> String {
+        let mut hash = HASH_OFFSET_BASIS;
+        for (hook_type, hook_file) in self._hook_files.iter() {
+            hash = compute_hash(hash, hook_type.get_hook_name().as_bytes());
+            hash = compute_hash(hash, hook_file.to_string_lossy().replace("\\", "/").as_bytes());
+        }
+        format!("{:016x}", hash)
+    }
+
+    pub fn get_include_directory(&self) -> PathBuf {
+        let mut include_directory = PathBuf::from(SHADER_CACHE_DIRECTORY);
+        include_directory.push(SHADER_HOOK_DIRECTORY);
+        include_directory.push(self.get_hook_set_key());
+        include_directory
+    }
+
+    // copy the snippets to the include directory of this hook set, missing snippets fall back to the base shader.
+    // returns (shader_defines, include_directory), the defines are empty when no snippet is available.
+    pub fn prepare_shader_hooks(&self) -> (Vec<String>, PathBuf) {
+        let include_directory = self.get_include_directory();
+        let mut shader_defines: Vec<String> = Vec::new();
+        for (hook_type, hook_file) in self._hook_files.iter() {
+            let mut hook_file_path = PathBuf::from(SHADER_DIRECTORY);
+            hook_file_path.push(hook_file);
+            let hook_source = match fs::read_to_string(&hook_file_path) {
+                Ok(hook_source) => hook_source,
+                Err(_) => {
+                    log::warn!("prepare_shader_hooks: not found {:?}, {} falls back to the base shader.", hook_file_path, hook_type.get_hook_name());
+                    continue;
+                }
+            };
+            fs::create_dir_all(&include_directory).expect("Failed to create directories.");
+            fs::write(include_directory.join(hook_type.get_include_file_name()), hook_source).expect("Failed to write");
+            shader_defines.push(hook_type.get_shader_define());
+        }
+
+        if false == shader_defines.is_empty() {
+            // makes the spirv cache file unique per hook set.
+            shader_defines.push(format!("SHADER_HOOK_SET={}", self.get_hook_set_key()));
+        }
+        (shader_defines, include_directory)
+    }
+}
+
+pub fn create_shader_hook_parameters(shader_hook_parameters: Option<&Value>) -> ShaderHookParameters {
+    let mut parameters: ShaderHookParameters = [Vector4::zeros(); MAX_SHADER_HOOK_PARAMETER_COUNT];
+    if let Some(Value::Array(values)) = shader_hook_parameters {
+        for (i, value) in values.iter().take(MAX_SHADER_HOOK_PARAMETER_COUNT).enumerate() {
+            if let Value::Array(components) = value {
+                for (j, component) in components.iter().take(4).enumerate() {
+                    parameters[i][j] = component.as_f64().unwrap_or(0.0) as f32;
+                }
+            }
+        }
+    }
+    parameters
+}
+
+// the tagged meshes of the wind system: a material instance with a wind vertex offset hook
+pub fn is_wind_shader_hook_set(shader_hook_set: &ShaderHookSet) -> bool {
+    match shader_hook_set.get_hook_file(ShaderHookType::VertexOffset) {
+        Some(hook_file) => WIND_SHADER_HOOK_FILES.iter().any(|wind_hook_file| PathBuf::from(wind_hook_file) == *hook_file),
+        None => false,
+    }
+}
+
+#[cfg(not(target_os = "android"))]
+pub fn generate_shader_hooks() {
+    let shader_hooks = [
+        (DISSOLVE_SHADER_HOOK_FILE, DISSOLVE_SHADER_HOOK_SOURCE),
+        (WIND_SWAY_SHADER_HOOK_FILE, WIND_SWAY_SHADER_HOOK_SOURCE),
+        (WIND_FLAG_SHADER_HOOK_FILE, WIND_FLAG_SHADER_HOOK_SOURCE),
+    ];
+    for (hook_file, hook_source) in shader_hooks.iter() {
+        let mut hook_file_path = PathBuf::from(SHADER_DIRECTORY);
+        hook_file_path.push(hook_file);
+        if false == hook_file_path.is_file() {
+            fs::create_dir_all(hook_file_path.parent().unwrap()).expect("Failed to create directories.");
+            fs::write(&hook_file_path, hook_source).expect("Failed to write");
+            log::info!("generate_shader_hooks: {:?}", hook_file_path);
+        }
+    }
+}
@@ -0,0 +1,886 @@
+use std::collections::{ HashMap, VecDeque };
+
+use nalgebra::{ Vector3, Vector4, Matrix4 };
+use serde::{ Serialize, Deserialize };
+
+use crate::constants;
+use crate::renderer::viewport::ViewportRect;
+use crate::utilities::math;
+
+pub const SHADOW_ATLAS_MIN_TILE_SIZE: u32 = 64;
+pub const SHADOW_ATLAS_MAX_TILE_SIZE: u32 = 1024;
+pub const MAX_SHADOW_ATLAS_TILE_COUNT: usize = 64; // must match with shadow_atlas.glsl
+pub const POINT_LIGHT_CUBE_FACE_COUNT: usize = 6;
+pub const POINT_LIGHT_DUAL_PARABOLOID_COUNT: usize = 2;
+pub const INVALID_SHADOW_TILE_INDEX: i32 = -1;
+pub const SHADOW_TILE_SIZE_HISTORY_FRAMES: usize = 30; // the rolling window of the requested tile sizes
+pub const SHADOW_TILE_SIZE_HYSTERESIS: f32 = 0.75; // log2 steps of the smoothed request away from the allocated size before it changes
+pub const SHADOW_ATLAS_DEBUG_MAP_SIZE: u32 = 32; // cells per row of get_atlas_map_text
+
+pub type LightId = u64;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Copy, PartialEq, Eq)]
+pub enum LocalLightType {
+    SpotLight,
+    PointLight,
+}
+
+#[derive(Clone, Debug, Copy, PartialEq, Eq)]
+pub enum PointLightShadowMode {
+    CubeFaces, // six perspective tiles
+    DualParaboloid, // two tiles, cheaper, the paraboloid projection is done in the shader
+}
+
+// a shadow casting local light of this frame
+#[derive(Clone, Debug)]
+pub struct ShadowAtlasRequest {
+    pub _light_id: LightId,
+    pub _light_type: LocalLightType,
+    pub _light_position: Vector3<f32>,
+    pub _light_rotation_matrix: Matrix4<f32>, // spot light only
+    pub _light_range: f32,
+    pub _light_intensity: f32,
+    pub _spot_angle: f32, // degree, full cone angle of the spot light
+    pub _is_static: bool, // static lights keep their tiles without redraw while the tiles are reused
+}
+
+impl Default for ShadowAtlasRequest {
+    fn default() -> ShadowAtlasRequest {
+        ShadowAtlasRequest {
+            _light_id: 0,
+            _light_type: LocalLightType::SpotLight,
+            _light_position: Vector3::zeros(),
+            _light_rotation_matrix: Matrix4::identity(),
+            _light_range: 10.0,
+            _light_intensity: 1.0,
+            _spot_angle: 45.0,
+            _is_static: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Hash)]
+pub struct ShadowAtlasTile {
+    pub _x: u32,
+    pub _y: u32,
+    pub _size: u32,
+}
+
+// shadow_atlas.glsl - struct SHADOW_ATLAS_TILE_CONSTANTS, uploaded with the light list
+#[derive(Clone, Debug, Copy)]
+pub struct ShadowAtlasTileConstants {
+    pub _shadow_view_projection: Matrix4<f32>,
+    pub _atlas_uv_transform: Vector4<f32>, // xy: scale, zw: offset
+}
+
+#[derive(Clone, Debug)]
+pub struct ShadowAtlasAllocation {
+    pub _light_id: LightId,
+    pub _priority: f32,
+    pub _tiles: Vec<ShadowAtlasTile>,
+    pub _tile_constants: Vec<ShadowAtlasTileConstants>,
+    pub _first_tile_index: i32, // index into the tile constants of the frame
+    pub _is_reused: bool, // the same tiles as the previous frame
+    pub _need_to_redraw: bool,
+    pub _is_static: bool,
+    pub _requested_tile_size: u32, // after the smoothing and the hysteresis, before the area budget
+}
+
+// the requested tile sizes of a light over the rolling window, as log2 so the average is the geometric mean
+#[derive(Clone, Debug, Default)]
+pub struct ShadowTileSizeHistory {
+    pub _requested_tile_levels: VecDeque<f32>,
+}
+
+impl ShadowTileSizeHistory {
+    pub fn push_requested_tile_size(&mut self, tile_size: u32) {
+        if SHADOW_TILE_SIZE_HISTORY_FRAMES <= self._requested_tile_levels.len() {
+            self._requested_tile_levels.pop_front();
+        }
+        self._requested_tile_levels.push_back((tile_size.max(1) as f32).log2());
+    }
+
+    pub fn get_smoothed_tile_level(&self) -> f32 {
+        if self._requested_tile_levels.is_empty() {
+            return (SHADOW_ATLAS_MIN_TILE_SIZE as f32).log2();
+        }
+        self._requested_tile_levels.iter().sum::<f32>() / self._requested_tile_levels.len() as f32
+    }
+
+    // a new light takes the smoothed size, an allocated light keeps its size inside the hysteresis band
+    pub fn get_hysteresis_tile_size(&self, allocated_tile_size: Option<u32>) -> u32 {
+        let smoothed_tile_level = self.get_smoothed_tile_level();
+        if let Some(allocated_tile_size) = allocated_tile_size {
+            let allocated_tile_level = (allocated_tile_size as f32).log2();
+            if (smoothed_tile_level - allocated_tile_level).abs() <= SHADOW_TILE_SIZE_HYSTERESIS {
+                return allocated_tile_size;
+            }
+        }
+        let tile_size = 2.0f32.powi(smoothed_tile_level.round() as i32) as u32;
+        tile_size.max(SHADOW_ATLAS_MIN_TILE_SIZE).min(SHADOW_ATLAS_MAX_TILE_SIZE)
+    }
+}
+
+// a light of the frame before the allocation, the budget lowers the target size
+#[derive(Clone, Debug)]
+struct ShadowAtlasBudgetEntry {
+    _tile_count: usize,
+    _requested_tile_size: u32,
+    _target_tile_size: u32, // 0: evicted
+    _is_pinned: bool, // a static light which keeps the tiles of the previous frame
+}
+
+impl ShadowAtlasBudgetEntry {
+    fn get_area(&self) -> u64 {
+        self._tile_count as u64 * self._target_tile_size as u64 * self._target_tile_size as u64
+    }
+}
+
+// the lowest priority lights are halved first down to the min tile size, the pinned static lights only when the others are all at the min size.
+// then the lowest priority lights are evicted. a pinned light which is changed loses its cached tiles.
+fn apply_shadow_atlas_area_budget(entries: &mut [ShadowAtlasBudgetEntry], area_budget: u64) {
+    let mut total_area: u64 = entries.iter().map(|entry| entry.get_area()).sum();
+    // the pinned lights become unpinned by the first halving, so the last pass continues with them
+    for is_pinned in [false, true, false].iter() {
+        while area_budget < total_area {
+            let downgrade_index = (0..entries.len()).rev().find(|i| {
+                entries[*i]._is_pinned == *is_pinned && SHADOW_ATLAS_MIN_TILE_SIZE < entries[*i]._target_tile_size
+            });
+            match downgrade_index {
+                Some(i) => {
+                    total_area -= entries[i].get_area();
+                    entries[i]._target_tile_size /= 2;
+                    entries[i]._is_pinned = false;
+                    total_area += entries[i].get_area();
+                },
+                None => break,
+            }
+        }
+    }
+    while area_budget < total_area {
+        match (0..entries.len()).rev().find(|i| 0 < entries[*i]._target_tile_size) {
+            Some(i) => {
+                total_area -= entries[i].get_area();
+                entries[i]._target_tile_size = 0;
+                entries[i]._is_pinned = false;
+            },
+            None => break,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Copy, PartialEq, Eq)]
+enum QuadTreeNodeState {
+    Free, // the whole subtree is free
+    Split,
+    Allocated,
+}
+
+// power of two tiles, level 0 is the whole atlas and each level has 2^level x 2^level nodes.
+pub struct ShadowAtlasQuadTree {
+    _atlas_size: u32,
+    _min_tile_size: u32,
+    _node_states: Vec<Vec<QuadTreeNodeState>>,
+}
+
+impl ShadowAtlasQuadTree {
+    pub fn create_quad_tree(atlas_size: u32, min_tile_size: u32) -> ShadowAtlasQuadTree {
+        let atlas_size = atlas_size.next_power_of_two();
+        let min_tile_size = min_tile_size.next_power_of_two().min(atlas_size);
+        let level_count = (atlas_size / min_tile_size).trailing_zeros() + 1;
+        ShadowAtlasQuadTree {
+            _atlas_size: atlas_size,
+            _min_tile_size: min_tile_size,
+            _node_states: (0..level_count).map(|level| vec![QuadTreeNodeState::Free; 1 << (level * 2)]).collect(),
+        }
+    }
+
+    pub fn get_atlas_size(&self) -> u32 { self._atlas_size }
+    pub fn get_min_tile_size(&self) -> u32 { self._min_tile_size }
+    pub fn get_max_level(&self) -> u32 { self._node_states.len() as u32 - 1 }
+
+    pub fn clear(&mut self) {
+        for node_states in self._node_states.iter_mut() {
+            for node_state in node_states.iter_mut() {
+                *node_state = QuadTreeNodeState::Free;
+            }
+        }
+    }
+
+    fn get_level(&self, tile_size: u32) -> u32 {
+        (self._atlas_size / tile_size.next_power_of_two().max(self._min_tile_size).min(self._atlas_size)).trailing_zeros()
+    }
+
+    fn get_node_index(level: u32, x: u32, y: u32) -> usize {
+        ((y << level) + x) as usize
+    }
+
+    fn set_children_free(&mut self, level: u32, x: u32, y: u32) {
+        let child_level = level + 1;
+        for child in 0..4 {
+            let index = ShadowAtlasQuadTree::get_node_index(child_level, x * 2 + (child & 1), y * 2 + (child >> 1));
+            self._node_states[child_level as usize][index] = QuadTreeNodeState::Free;
+        }
+    }
+
+    // deterministic: split nodes are tried before free nodes to keep the large free nodes, then morton order.
+    fn allocate_node(&mut self, level: u32, x: u32, y: u32, target_level: u32) -> Option<(u32, u32)> {
+        let index = ShadowAtlasQuadTree::get_node_index(level, x, y);
+        match self._node_states[level as usize][index] {
+            QuadTreeNodeState::Allocated => None,
+            QuadTreeNodeState::Free if level == target_level => {
+                self._node_states[level as usize][index] = QuadTreeNodeState::Allocated;
+                Some((x, y))
+            },
+            QuadTreeNodeState::Split if level == target_level => None,
+            node_state => {
+                if QuadTreeNodeState::Free == node_state {
+                    self._node_states[level as usize][index] = QuadTreeNodeState::Split;
+                    self.set_children_free(level, x, y);
+                }
+                let child_level = level + 1;
+                for try_split_nodes in [true, false].iter() {
+                    for child in 0..4 {
+                        let (child_x, child_y) = (x * 2 + (child & 1), y * 2 + (child >> 1));
+                        let child_state = self._node_states[child_level as usize][ShadowAtlasQuadTree::get_node_index(child_level, child_x, child_y)];
+                        if (QuadTreeNodeState::Split == child_state) == *try_split_nodes {
+                            if let Some(node) = self.allocate_node(child_level, child_x, child_y, target_level) {
+                                return Some(node);
+                            }
+                        }
+                    }
+                }
+                self.merge_node(level, x, y);
+                None
+            }
+        }
+    }
+
+    fn merge_node(&mut self, level: u32, x: u32, y: u32) {
+        let child_level = level + 1;
+        if (self._node_states.len() as u32) <= child_level {
+            return;
+        }
+        let all_children_free = (0..4).all(|child| {
+            let index = ShadowAtlasQuadTree::get_node_index(child_level, x * 2 + (child & 1), y * 2 + (child >> 1));
+            QuadTreeNodeState::Free == self._node_states[child_level as usize][index]
+        });
+        if all_children_free {
+            self._node_states[level as usize][ShadowAtlasQuadTree::get_node_index(level, x, y)] = QuadTreeNodeState::Free;
+        }
+    }
+
+    pub fn allocate_tile(&mut self, tile_size: u32) -> Option<ShadowAtlasTile> {
+        let target_level = self.get_level(tile_size);
+        let node_size = self._atlas_size >> target_level;
+        self.allocate_node(0, 0, 0, target_level).map(|(x, y)| ShadowAtlasTile {
+            _x: x * node_size,
+            _y: y * node_size,
+            _size: node_size,
+        })
+    }
+
+    // reserve the exact tile of the previous frame, fails when any part of it is already used.
+    pub fn reserve_tile(&mut self, tile: &ShadowAtlasTile) -> bool {
+        let target_level = self.get_level(tile._size);
+        if (self._atlas_size >> target_level) != tile._size || 0 != (tile._x % tile._size) || 0 != (tile._y % tile._size) {
+            return false;
+        }
+        let (target_x, target_y) = (tile._x / tile._size, tile._y / tile._size);
+        for level in 0..target_level {
+            let shift = target_level - level;
+            let index = ShadowAtlasQuadTree::get_node_index(level, target_x >> shift, target_y >> shift);
+            match self._node_states[level as usize][index] {
+                QuadTreeNodeState::Allocated => return false,
+                QuadTreeNodeState::Free => {
+                    self._node_states[level as usize][index] = QuadTreeNodeState::Split;
+                    self.set_children_free(level, target_x >> shift, target_y >> shift);
+                },
+                QuadTreeNodeState::Split => (),
+            }
+        }
+        let index = ShadowAtlasQuadTree::get_node_index(target_level, target_x, target_y);
+        if QuadTreeNodeState::Free != self._node_states[target_level as usize][index] {
+            self.free_parents(target_level, target_x, target_y);
+            return false;
+        }
+        self._node_states[target_level as usize][index] = QuadTreeNodeState::Allocated;
+        true
+    }
+
+    pub fn free_tile(&mut self, tile: &ShadowAtlasTile) {
+        let level = self.get_level(tile._size);
+        let (x, y) = (tile._x / tile._size, tile._y / tile._size);
+        let index = ShadowAtlasQuadTree::get_node_index(level, x, y);
+        if QuadTreeNodeState::Allocated == self._node_states[level as usize][index] {
+            self._node_states[level as usize][index] = QuadTreeNodeState::Free;
+            self.free_parents(level, x, y);
+        }
+    }
+
+    fn free_parents(&mut self, level: u32, x: u32, y: u32) {
+        let (mut level, mut x, mut y) = (level, x, y);
+        while 0 < level {
+            level -= 1;
+            x /= 2;
+            y /= 2;
+            if QuadTreeNodeState::Split == self._node_states[level as usize][ShadowAtlasQuadTree::get_node_index(level, x, y)] {
+                self.merge_node(level, x, y);
+            }
+        }
+    }
+}
+
+impl ShadowAtlasTile {
+    pub fn get_atlas_uv_transform(&self, atlas_size: u32) -> Vector4<f32> {
+        let inv_atlas_size = 1.0 / atlas_size as f32;
+        Vector4::new(
+            self._size as f32 * inv_atlas_size,
+            self._size as f32 * inv_atlas_size,
+            self._x as f32 * inv_atlas_size,
+            self._y as f32 * inv_atlas_size,
+        )
+    }
+
+    pub fn get_viewport_rect(&self) -> ViewportRect {
+        ViewportRect::create_viewport_rect(self._x as i32, self._y as i32, self._size as i32, self._size as i32)
+    }
+}
+
+// the solid angle of the light range sphere seen from the view, 4pi inside the range
+pub fn get_light_solid_angle(request: &ShadowAtlasRequest, view_position: &Vector3<f32>) -> f32 {
+    let distance = (request._light_position - view_position).norm();
+    if distance <= request._light_range {
+        return 4.0 * std::f32::consts::PI;
+    }
+    let sin_half_angle = request._light_range / distance;
+    2.0 * std::f32::consts::PI * (1.0 - (1.0 - sin_half_angle * sin_half_angle).max(0.0).sqrt())
+}
+
+// intensity x solid angle, the area budget downgrades and the lights out of the atlas fall back to unshadowed in this order.
+pub fn get_shadow_priority(request: &ShadowAtlasRequest, view_position: &Vector3<f32>) -> f32 {
+    request._light_intensity.max(0.0) * get_light_solid_angle(request, view_position)
+}
+
+// ratio of the screen height covered by the light range sphere
+pub fn get_light_screen_coverage(request: &ShadowAtlasRequest, view_position: &Vector3<f32>, fov: f32) -> f32 {
+    let distance = (request._light_position - view_position).norm();
+    if distance <= request._light_range {
+        return 1.0;
+    }
+    let half_height = distance * math::degree_to_radian(fov * 0.5).tan();
+    (request._light_range / half_height.max(std::f32::EPSILON)).min(1.0)
+}
+
+pub fn get_shadow_tile_size(screen_coverage: f32, tile_count: usize) -> u32 {
+    let tile_size = ((SHADOW_ATLAS_MAX_TILE_SIZE as f32 * screen_coverage) as u32).max(1).next_power_of_two();
+    // the faces of a point light share the budget of one tile
+    let tile_size = if 1 < tile_count { tile_size / 2 } else { tile_size };
+    tile_size.max(SHADOW_ATLAS_MIN_TILE_SIZE).min(SHADOW_ATLAS_MAX_TILE_SIZE)
+}
+
+pub fn get_shadow_atlas_area_budget(atlas_size: u32) -> u64 {
+    let area_budget = unsafe { constants::SHADOW_ATLAS_AREA_BUDGET }.max(0.0).min(1.0);
+    (atlas_size as f64 * atlas_size as f64 * area_budget as f64) as u64
+}
+
+pub fn get_point_light_shadow_mode() -> PointLightShadowMode {
+    if unsafe { constants::POINT_LIGHT_DUAL_PARABOLOID_SHADOW } {
+        PointLightShadowMode::DualParaboloid
+    } else {
+        PointLightShadowMode::CubeFaces
+    }
+}
+
+pub fn get_shadow_tile_count(light_type: LocalLightType, point_light_shadow_mode: PointLightShadowMode) -> usize {
+    match (light_type, point_light_shadow_mode) {
+        (LocalLightType::SpotLight, _) => 1,
+        (LocalLightType::PointLight, PointLightShadowMode::CubeFaces) => POINT_LIGHT_CUBE_FACE_COUNT,
+        (LocalLightType::PointLight, PointLightShadowMode::DualParaboloid) => POINT_LIGHT_DUAL_PARABOLOID_COUNT,
+    }
+}
+
+// pitch, yaw of the faces +X, -X, +Y, -Y, +Z, -Z, the view looks at -front like the camera.
+pub fn get_point_light_face_rotation_matrices() -> [Matrix4<f32>; POINT_LIGHT_CUBE_FACE_COUNT] {
+    let half_pi = std::f32::consts::PI * 0.5;
+    [
+        math::make_rotation_matrix(0.0, -half_pi, 0.0),
+        math::make_rotation_matrix(0.0, half_pi, 0.0),
+        math::make_rotation_matrix(half_pi, 0.0, 0.0),
+        math::make_rotation_matrix(-half_pi, 0.0, 0.0),
+        math::make_rotation_matrix(0.0, std::f32::consts::PI, 0.0),
+        math::make_rotation_matrix(0.0, 0.0, 0.0),
+    ]
+}
+
+pub fn get_shadow_view_projections(request: &ShadowAtlasRequest, point_light_shadow_mode: PointLightShadowMode) -> Vec<Matrix4<f32>> {
+    let near = unsafe { constants::NEAR };
+    let far = request._light_range.max(near * 2.0);
+    let one = Vector3::new(1.0, 1.0, 1.0);
+    let get_view = |rotation_matrix: &Matrix4<f32>| math::inverse_transform_matrix(&request._light_position, rotation_matrix, &one);
+    match (request._light_type, point_light_shadow_mode) {
+        (LocalLightType::SpotLight, _) => {
+            let projection = math::get_clip_space_matrix() * math::perspective(1.0, request._spot_angle, near, far);
+            vec![projection * get_view(&request._light_rotation_matrix)]
+        },
+        (LocalLightType::PointLight, PointLightShadowMode::CubeFaces) => {
+            let projection = math::get_clip_space_matrix() * math::perspective(1.0, 90.0, near, far);
+            get_point_light_face_rotation_matrices().iter().map(|rotation_matrix| &projection * get_view(rotation_matrix)).collect()
+        },
+        (LocalLightType::PointLight, PointLightShadowMode::DualParaboloid) => {
+            // front and back hemisphere, view only
+            let rotation_matrices = get_point_light_face_rotation_matrices();
+            vec![get_view(&rotation_matrices[4]), get_view(&rotation_matrices[5])]
+        },
+    }
+}
+
+// the atlas of the local light shadows, the render target itself is created by the project renderer with get_atlas_size().
+// each frame: update_shadow_atlas -> per tile set_shadow_atlas_tile_viewport + draw in a single shadow render pass.
+pub struct ShadowAtlas {
+    pub _quad_tree: ShadowAtlasQuadTree,
+    pub _allocations: Vec<ShadowAtlasAllocation>, // sorted by priority
+    pub _allocation_map: HashMap<LightId, usize>,
+    pub _unshadowed_lights: Vec<LightId>,
+    pub _tile_constants: Vec<ShadowAtlasTileConstants>,
+    pub _tile_size_histories: HashMap<LightId, ShadowTileSizeHistory>,
+    pub _invalidated_static_lights: Vec<LightId>, // the static lights which lost the tiles of the previous frame, their cached shadows are stale
+}
+
+impl ShadowAtlas {
+    pub fn create_shadow_atlas(atlas_size: u32) -> ShadowAtlas {
+        log::info!("create_shadow_atlas: {}", atlas_size);
+        ShadowAtlas {
+            _quad_tree: ShadowAtlasQuadTree::create_quad_tree(atlas_size, SHADOW_ATLAS_MIN_TILE_SIZE),
+            _allocations: Vec::new(),
+            _allocation_map: HashMap::new(),
+            _unshadowed_lights: Vec::new(),
+            _tile_constants: Vec::new(),
+            _tile_size_histories: HashMap::new(),
+            _invalidated_static_lights: Vec::new(),
+        }
+    }
+
+    pub fn get_atlas_size(&self) -> u32 { self._quad_tree.get_atlas_size() }
+    pub fn get_allocations(&self) -> &Vec<ShadowAtlasAllocation> { &self._allocations }
+    pub fn get_tile_constants(&self) -> &Vec<ShadowAtlasTileConstants> { &self._tile_constants }
+    pub fn get_unshadowed_lights(&self) -> &Vec<LightId> { &self._unshadowed_lights }
+    pub fn get_invalidated_static_lights(&self) -> &Vec<LightId> { &self._invalidated_static_lights }
+    pub fn get_allocation(&self, light_id: LightId) -> Option<&ShadowAtlasAllocation> {
+        self._allocation_map.get(&light_id).map(|index| &self._allocations[*index])
+    }
+
+    // INVALID_SHADOW_TILE_INDEX: unshadowed
+    pub fn get_first_tile_index(&self, light_id: LightId) -> i32 {
+        match self.get_allocation(light_id) {
+            Some(allocation) => allocation._first_tile_index,
+            None => INVALID_SHADOW_TILE_INDEX,
+        }
+    }
+
+    // the result depends only on the requests, the view and the previous frames.
+    pub fn update_shadow_atlas(&mut self, requests: &[ShadowAtlasRequest], view_position: &Vector3<f32>, fov: f32) {
+        let point_light_shadow_mode = get_point_light_shadow_mode();
+        let prev_allocations: HashMap<LightId, ShadowAtlasAllocation> = self._allocations.drain(..).map(|allocation| (allocation._light_id, allocation)).collect();
+        self._quad_tree.clear();
+        self._allocation_map.clear();
+        self._unshadowed_lights.clear();
+        self._tile_constants.clear();
+        self._invalidated_static_lights.clear();
+
+        let mut sorted_requests: Vec<(f32, &ShadowAtlasRequest)> = requests.iter().map(|request| (get_shadow_priority(request, view_position), request)).collect();
+        sorted_requests.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal).then(a.1._light_id.cmp(&b.1._light_id)));
+
+        // the smoothed request with the hysteresis, the static lights stay pinned at the size of the previous frame
+        self._tile_size_histories.retain(|light_id, _| requests.iter().any(|request| *light_id == request._light_id));
+        let mut budget_entries: Vec<ShadowAtlasBudgetEntry> = sorted_requests.iter().map(|(_, request)| {
+            let tile_count = get_shadow_tile_count(request._light_type, point_light_shadow_mode);
+            let tile_size_history = self._tile_size_histories.entry(request._light_id).or_default();
+            tile_size_history.push_requested_tile_size(get_shadow_tile_size(get_light_screen_coverage(request, view_position, fov), tile_count));
+            let prev_tile_size = prev_allocations.get(&request._light_id)
+                .filter(|prev_allocation| prev_allocation._tiles.len() == tile_count)
+                .map(|prev_allocation| prev_allocation._tiles[0]._size);
+            let is_pinned = request._is_static && prev_tile_size.is_some();
+            let requested_tile_size = if is_pinned { prev_tile_size.unwrap() } else { tile_size_history.get_hysteresis_tile_size(prev_tile_size) };
+            ShadowAtlasBudgetEntry {
+                _tile_count: tile_count,
+                _requested_tile_size: requested_tile_size,
+                _target_tile_size: requested_tile_size,
+                _is_pinned: is_pinned,
+            }
+        }).collect();
+        apply_shadow_atlas_area_budget(&mut budget_entries, get_shadow_atlas_area_budget(self.get_atlas_size()));
+
+        // keep the tiles of the previous frame, so the static lights can skip the redraw
+        let mut tiles_list: Vec<Option<(Vec<ShadowAtlasTile>, bool)>> = vec![None; sorted_requests.len()];
+        for (i, (_, request)) in sorted_requests.iter().enumerate() {
+            if let Some(prev_allocation) = prev_allocations.get(&request._light_id) {
+                let budget_entry = &budget_entries[i];
+                let (tile_size, tile_count) = (budget_entry._target_tile_size, budget_entry._tile_count);
+                if prev_allocation._tiles.len() == tile_count && prev_allocation._tiles.iter().all(|tile| tile_size == tile._size) {
+                    let mut reserved_tiles: Vec<ShadowAtlasTile> = Vec::new();
+                    for tile in prev_allocation._tiles.iter() {
+                        if false == self._quad_tree.reserve_tile(tile) {
+                            break;
+                        }
+                        reserved_tiles.push(tile.clone());
+                    }
+                    if tile_count == reserved_tiles.len() {
+                        tiles_list[i] = Some((reserved_tiles, true));
+                    } else {
+                        reserved_tiles.iter().for_each(|tile| self._quad_tree.free_tile(tile));
+                    }
+                }
+            }
+        }
+
+        // new tiles by priority, the lower priority lights are evicted or get smaller tiles when the atlas is fragmented
+        for i in 0..sorted_requests.len() {
+            let budget_entry = &budget_entries[i];
+            if tiles_list[i].is_some() || 0 == budget_entry._target_tile_size {
+                continue;
+            }
+            loop {
+                if let Some(tiles) = self.allocate_tiles(budget_entry._target_tile_size, budget_entry._tile_count) {
+                    tiles_list[i] = Some((tiles, false));
+                    break;
+                }
+                let evict_index = (i + 1..sorted_requests.len()).rev().find(|j| tiles_list[*j].is_some());
+                match evict_index {
+                    Some(evict_index) => {
+                        let (evicted_tiles, _) = tiles_list[evict_index].take().unwrap();
+                        evicted_tiles.iter().for_each(|tile| self._quad_tree.free_tile(tile));
+                    },
+                    None => break,
+                }
+            }
+        }
+
+        for (i, (priority, request)) in sorted_requests.iter().enumerate() {
+            match tiles_list[i].take() {
+                Some((tiles, is_reused)) if self._tile_constants.len() + tiles.len() <= MAX_SHADOW_ATLAS_TILE_COUNT => {
+                    let atlas_size = self.get_atlas_size();
+                    let tile_constants: Vec<ShadowAtlasTileConstants> = get_shadow_view_projections(request, point_light_shadow_mode).iter().zip(tiles.iter()).map(|(view_projection, tile)| {
+                        ShadowAtlasTileConstants {
+                            _shadow_view_projection: view_projection.clone(),
+                            _atlas_uv_transform: tile.get_atlas_uv_transform(atlas_size),
+                        }
+                    }).collect();
+                    let first_tile_index = self._tile_constants.len() as i32;
+                    self._tile_constants.extend_from_slice(&tile_constants);
+                    self._allocation_map.insert(request._light_id, self._allocations.len());
+                    self._allocations.push(ShadowAtlasAllocation {
+                        _light_id: request._light_id,
+                        _priority: *priority,
+                        _tiles: tiles,
+                        _tile_constants: tile_constants,
+                        _first_tile_index: first_tile_index,
+                        _is_reused: is_reused,
+                        _need_to_redraw: false == (is_reused && request._is_static),
+                        _is_static: request._is_static,
+                        _requested_tile_size: budget_entries[i]._requested_tile_size,
+                    });
+                },
+                Some((tiles, _)) => {
+                    tiles.iter().for_each(|tile| self._quad_tree.free_tile(tile));
+                    self._unshadowed_lights.push(request._light_id);
+                },
+                None => self._unshadowed_lights.push(request._light_id),
+            }
+            let is_reused = self.get_allocation(request._light_id).map_or(false, |allocation| allocation._is_reused);
+            if request._is_static && false == is_reused && prev_allocations.contains_key(&request._light_id) {
+                self._invalidated_static_lights.push(request._light_id);
+            }
+        }
+    }
+
+    // all or nothing, from the desired size down to the min tile size
+    fn allocate_tiles(&mut self, desired_tile_size: u32, tile_count: usize) -> Option<Vec<ShadowAtlasTile>> {
+        let mut tile_size = desired_tile_size;
+        while SHADOW_ATLAS_MIN_TILE_SIZE <= tile_size {
+            let mut tiles: Vec<ShadowAtlasTile> = Vec::new();
+            while tiles.len() < tile_count {
+                match self._quad_tree.allocate_tile(tile_size) {
+                    Some(tile) => tiles.push(tile),
+                    None => break,
+                }
+            }
+            if tile_count == tiles.len() {
+                return Some(tiles);
+            }
+            tiles.iter().for_each(|tile| self._quad_tree.free_tile(tile));
+            tile_size /= 2;
+        }
+        None
+    }
+
+    // the whole atlas must be redrawn, ex) the atlas render target is recreated
+    pub fn invalidate_shadow_atlas(&mut self) {
+        for allocation in self._allocations.iter_mut() {
+            allocation._is_reused = false;
+            allocation._need_to_redraw = true;
+        }
+    }
+
+    pub fn get_allocated_area(&self) -> u64 {
+        self._allocations.iter().map(|allocation| allocation._tiles.iter().map(|tile| tile._size as u64 * tile._size as u64).sum::<u64>()).sum()
+    }
+
+    pub fn get_stats_text(&self) -> String {
+        let atlas_area = self.get_atlas_size() as f64 * self.get_atlas_size() as f64;
+        format!("shadow atlas: lights {}, unshadowed {}, area {:.0}% / budget {:.0}%",
+            self._allocations.len(),
+            self._unshadowed_lights.len(),
+            self.get_allocated_area() as f64 / atlas_area * 100.0,
+            get_shadow_atlas_area_budget(self.get_atlas_size()) as f64 / atlas_area * 100.0
+        )
+    }
+
+    // the owner of each cell by the order of the allocations, '.' is free. the tiles smaller than a cell still mark it.
+    pub fn get_atlas_map_text(&self) -> Vec<String> {
+        const OWNER_CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+        let map_size = SHADOW_ATLAS_DEBUG_MAP_SIZE;
+        let cell_size = (self.get_atlas_size() / map_size).max(1);
+        let mut cells: Vec<Vec<u8>> = vec![vec![b'.'; map_size as usize]; map_size as usize];
+        for (i, allocation) in self._allocations.iter().enumerate() {
+            let owner_char = OWNER_CHARS[i % OWNER_CHARS.len()];
+            for tile in allocation._tiles.iter() {
+                let (min_x, min_y) = (tile._x / cell_size, tile._y / cell_size);
+                let max_x = ((tile._x + tile._size + cell_size - 1) / cell_size).min(map_size);
+                let max_y = ((tile._y + tile._size + cell_size - 1) / cell_size).min(map_size);
+                for y in min_y..max_y {
+                    for x in min_x..max_x {
+                        cells[y as usize][x as usize] = owner_char;
+                    }
+                }
+            }
+        }
+        let mut lines: Vec<String> = cells.iter().map(|row| String::from_utf8_lossy(row).into_owned()).collect();
+        for (i, allocation) in self._allocations.iter().enumerate() {
+            lines.push(format!("{}: light {} {}x{} requested {}{}{}",
+                OWNER_CHARS[i % OWNER_CHARS.len()] as char,
+                allocation._light_id,
+                allocation._tiles.len(),
+                allocation._tiles.first().map_or(0, |tile| tile._size),
+                allocation._requested_tile_size,
+                if allocation._is_static { " static" } else { "" },
+                if allocation._is_reused { " reused" } else { "" }
+            ));
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_overlapped(a: &ShadowAtlasTile, b: &ShadowAtlasTile) -> bool {
+        a._x < b._x + b._size && b._x < a._x + a._size && a._y < b._y + b._size && b._y < a._y + a._size
+    }
+
+    // fov 90 from the origin, a light of range 1 at the distance 2 requests 512 and at the distance 4 requests 256
+    fn create_light_request(light_id: LightId, light_type: LocalLightType, light_position: Vector3<f32>, light_intensity: f32, is_static: bool) -> ShadowAtlasRequest {
+        ShadowAtlasRequest {
+            _light_id: light_id,
+            _light_type: light_type,
+            _light_position: light_position,
+            _light_range: 1.0,
+            _light_intensity: light_intensity,
+            _is_static: is_static,
+            ..Default::default()
+        }
+    }
+
+    fn get_tile_size(shadow_atlas: &ShadowAtlas, light_id: LightId) -> u32 {
+        shadow_atlas.get_allocation(light_id).unwrap()._tiles[0]._size
+    }
+
+    #[test]
+    fn test_quad_tree_allocate() {
+        let mut quad_tree = ShadowAtlasQuadTree::create_quad_tree(1000, 100);
+        assert_eq!(1024, quad_tree.get_atlas_size());
+        assert_eq!(128, quad_tree.get_min_tile_size());
+        assert_eq!(3, quad_tree.get_max_level());
+
+        // the sizes are rounded up to the power of two and clamped
+        let tile = quad_tree.allocate_tile(300).unwrap();
+        assert_eq!(ShadowAtlasTile { _x: 0, _y: 0, _size: 512 }, tile);
+        let min_tile = quad_tree.allocate_tile(1).unwrap();
+        assert_eq!(ShadowAtlasTile { _x: 512, _y: 0, _size: 128 }, min_tile);
+        assert!(quad_tree.allocate_tile(4096).is_none());
+
+        // the small tiles fill the split node before the free nodes
+        let mut tiles = vec![tile, min_tile];
+        tiles.push(quad_tree.allocate_tile(128).unwrap());
+        assert_eq!(ShadowAtlasTile { _x: 640, _y: 0, _size: 128 }, tiles[2]);
+        while let Some(tile) = quad_tree.allocate_tile(256) {
+            tiles.push(tile);
+        }
+        while let Some(tile) = quad_tree.allocate_tile(128) {
+            tiles.push(tile);
+        }
+        let allocated_area: u32 = tiles.iter().map(|tile| tile._size * tile._size).sum();
+        assert_eq!(1024 * 1024, allocated_area);
+        for i in 0..tiles.len() {
+            assert_eq!(0, tiles[i]._x % tiles[i]._size);
+            assert_eq!(0, tiles[i]._y % tiles[i]._size);
+            assert!(tiles[i]._x + tiles[i]._size <= 1024 && tiles[i]._y + tiles[i]._size <= 1024);
+            for j in (i + 1)..tiles.len() {
+                assert!(false == is_overlapped(&tiles[i], &tiles[j]), "{:?} {:?}", tiles[i], tiles[j]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_quad_tree_free_and_coalescing() {
+        let mut quad_tree = ShadowAtlasQuadTree::create_quad_tree(512, 128);
+        let tiles: Vec<ShadowAtlasTile> = (0..16).map(|_| quad_tree.allocate_tile(128).unwrap()).collect();
+        assert!(quad_tree.allocate_tile(128).is_none());
+
+        // the freed tiles are reused, the larger tile needs all four siblings
+        quad_tree.free_tile(&tiles[5]);
+        assert!(quad_tree.allocate_tile(256).is_none());
+        assert_eq!(tiles[5], quad_tree.allocate_tile(128).unwrap());
+        for tile in tiles[0..4].iter() {
+            quad_tree.free_tile(tile);
+        }
+        assert_eq!(ShadowAtlasTile { _x: 0, _y: 0, _size: 256 }, quad_tree.allocate_tile(256).unwrap());
+
+        // freeing a tile twice or a tile which is not allocated does nothing
+        quad_tree.free_tile(&tiles[0]);
+        quad_tree.free_tile(&ShadowAtlasTile { _x: 256, _y: 256, _size: 256 });
+        assert!(quad_tree.allocate_tile(128).is_none());
+
+        // everything freed merges up to the root
+        quad_tree.free_tile(&ShadowAtlasTile { _x: 0, _y: 0, _size: 256 });
+        for tile in tiles[4..].iter() {
+            quad_tree.free_tile(tile);
+        }
+        assert_eq!(ShadowAtlasTile { _x: 0, _y: 0, _size: 512 }, quad_tree.allocate_tile(512).unwrap());
+    }
+
+    #[test]
+    fn test_quad_tree_reserve_tile() {
+        let mut quad_tree = ShadowAtlasQuadTree::create_quad_tree(512, 128);
+        let tile = ShadowAtlasTile { _x: 256, _y: 128, _size: 128 };
+        assert!(quad_tree.reserve_tile(&tile));
+        assert!(false == quad_tree.reserve_tile(&tile));
+        // the parent of the reserved tile and the misaligned tiles
+        assert!(false == quad_tree.reserve_tile(&ShadowAtlasTile { _x: 256, _y: 0, _size: 256 }));
+        assert!(false == quad_tree.reserve_tile(&ShadowAtlasTile { _x: 64, _y: 0, _size: 128 }));
+        assert!(false == quad_tree.reserve_tile(&ShadowAtlasTile { _x: 0, _y: 0, _size: 100 }));
+        // the failed reservation of the parent is rolled back, the siblings are still allocatable
+        assert!(quad_tree.reserve_tile(&ShadowAtlasTile { _x: 0, _y: 0, _size: 256 }));
+        let remaining_area: u32 = std::iter::from_fn(|| quad_tree.allocate_tile(128)).map(|tile| tile._size * tile._size).sum();
+        assert_eq!(512 * 512 - 256 * 256 - 128 * 128, remaining_area);
+        quad_tree.clear();
+        assert!(quad_tree.reserve_tile(&ShadowAtlasTile { _x: 0, _y: 0, _size: 512 }));
+    }
+
+    #[test]
+    fn test_tile_size_hysteresis() {
+        let mut shadow_atlas = ShadowAtlas::create_shadow_atlas(1024);
+        let view_position = Vector3::zeros();
+        let far_requests = [create_light_request(1, LocalLightType::SpotLight, Vector3::new(0.0, 0.0, -4.0), 1.0, false)];
+        let near_requests = [create_light_request(1, LocalLightType::SpotLight, Vector3::new(0.0, 0.0, -2.0), 1.0, false)];
+        for _ in 0..10 {
+            shadow_atlas.update_shadow_atlas(&far_requests, &view_position, 90.0);
+            assert_eq!(256, get_tile_size(&shadow_atlas, 1));
+        }
+        let prev_tile = shadow_atlas.get_allocation(1).unwrap()._tiles[0];
+
+        // the smoothed request moves inside the band, the light keeps its size and its tile
+        shadow_atlas.update_shadow_atlas(&near_requests, &view_position, 90.0);
+        let allocation = shadow_atlas.get_allocation(1).unwrap();
+        assert_eq!(256, allocation._requested_tile_size);
+        assert_eq!(prev_tile, allocation._tiles[0]);
+        assert!(allocation._is_reused);
+
+        // the history window is filled with the new size, the light leaves the band
+        for _ in 0..SHADOW_TILE_SIZE_HISTORY_FRAMES {
+            shadow_atlas.update_shadow_atlas(&near_requests, &view_position, 90.0);
+        }
+        assert_eq!(512, get_tile_size(&shadow_atlas, 1));
+        shadow_atlas.update_shadow_atlas(&far_requests, &view_position, 90.0);
+        assert_eq!(512, get_tile_size(&shadow_atlas, 1));
+    }
+
+    #[test]
+    fn test_budget_downgrades_lowest_priority_first() {
+        let mut shadow_atlas = ShadowAtlas::create_shadow_atlas(1024);
+        let view_position = Vector3::zeros();
+        // four 512 tiles are over the 75% area budget, the priority is the intensity at the same distance
+        let requests = [
+            create_light_request(1, LocalLightType::SpotLight, Vector3::new(0.0, 0.0, -2.0), 4.0, false),
+            create_light_request(2, LocalLightType::SpotLight, Vector3::new(2.0, 0.0, 0.0), 3.0, false),
+            create_light_request(3, LocalLightType::SpotLight, Vector3::new(-2.0, 0.0, 0.0), 2.0, false),
+            create_light_request(4, LocalLightType::SpotLight, Vector3::new(0.0, 2.0, 0.0), 1.0, false),
+        ];
+        // shuffled, the order of the requests does not matter
+        let shuffled_requests = [requests[2].clone(), requests[0].clone(), requests[3].clone(), requests[1].clone()];
+        shadow_atlas.update_shadow_atlas(&shuffled_requests, &view_position, 90.0);
+        assert!(shadow_atlas.get_unshadowed_lights().is_empty());
+        assert!(shadow_atlas.get_allocated_area() <= get_shadow_atlas_area_budget(1024));
+        let light_ids: Vec<LightId> = shadow_atlas.get_allocations().iter().map(|allocation| allocation._light_id).collect();
+        assert_eq!(vec![1, 2, 3, 4], light_ids);
+        for allocation in shadow_atlas.get_allocations().iter() {
+            assert_eq!(512, allocation._requested_tile_size);
+        }
+        // the lowest priority light goes down to the min tile size before the next one is halved
+        assert_eq!(512, get_tile_size(&shadow_atlas, 1));
+        assert_eq!(512, get_tile_size(&shadow_atlas, 2));
+        assert_eq!(256, get_tile_size(&shadow_atlas, 3));
+        assert_eq!(SHADOW_ATLAS_MIN_TILE_SIZE, get_tile_size(&shadow_atlas, 4));
+    }
+
+    #[test]
+    fn test_pinned_static_light_eviction() {
+        let mut shadow_atlas = ShadowAtlas::create_shadow_atlas(1024);
+        let view_position = Vector3::zeros();
+        // 512 + 512 + 256 pinned, the slack of the budget is 8 point lights at the min tile size
+        let static_requests = vec![
+            create_light_request(1, LocalLightType::SpotLight, Vector3::new(0.0, 0.0, -2.0), 3.0, true),
+            create_light_request(2, LocalLightType::SpotLight, Vector3::new(2.0, 0.0, 0.0), 2.0, true),
+            create_light_request(3, LocalLightType::SpotLight, Vector3::new(0.0, 0.0, 4.0), 1.0, true),
+        ];
+        let get_requests = |point_light_count: usize| -> Vec<ShadowAtlasRequest> {
+            let mut requests = static_requests.clone();
+            for i in 0..point_light_count {
+                let light_position = Vector3::new(i as f32 * 10.0, 100.0, 0.0);
+                requests.push(create_light_request(10 + i as LightId, LocalLightType::PointLight, light_position, 100000.0, false));
+            }
+            requests
+        };
+        assert_eq!(6, get_shadow_tile_count(LocalLightType::PointLight, get_point_light_shadow_mode()));
+        assert!(get_shadow_priority(&static_requests[0], &view_position) < get_shadow_priority(&get_requests(1)[3], &view_position));
+
+        shadow_atlas.update_shadow_atlas(&get_requests(0), &view_position, 90.0);
+        let static_tiles: Vec<ShadowAtlasTile> = (1..4).map(|light_id| shadow_atlas.get_allocation(light_id).unwrap()._tiles[0]).collect();
+        assert_eq!(vec![512, 512, 256], static_tiles.iter().map(|tile| tile._size).collect::<Vec<u32>>());
+
+        // over the budget, the higher priority dynamic lights are downgraded instead of the pinned lights
+        shadow_atlas.update_shadow_atlas(&get_requests(8), &view_position, 90.0);
+        assert!(shadow_atlas.get_unshadowed_lights().is_empty());
+        assert!(shadow_atlas.get_invalidated_static_lights().is_empty());
+        for (light_id, static_tile) in (1..4).zip(static_tiles.iter()) {
+            let allocation = shadow_atlas.get_allocation(light_id).unwrap();
+            assert_eq!(*static_tile, allocation._tiles[0]);
+            assert!(allocation._is_reused && false == allocation._need_to_redraw);
+        }
+        for light_id in 10..18 {
+            assert_eq!(SHADOW_ATLAS_MIN_TILE_SIZE, get_tile_size(&shadow_atlas, light_id));
+        }
+
+        // the dynamic lights are all at the min tile size, the budget forces the lowest priority pinned light out of its tiles
+        shadow_atlas.update_shadow_atlas(&get_requests(9), &view_position, 90.0);
+        assert!(shadow_atlas.get_allocated_area() <= get_shadow_atlas_area_budget(1024));
+        assert_eq!(&vec![3], shadow_atlas.get_invalidated_static_lights());
+        let allocation = shadow_atlas.get_allocation(3).unwrap();
+        assert_eq!(128, allocation._tiles[0]._size);
+        assert!(false == allocation._is_reused && allocation._need_to_redraw);
+        for (light_id, static_tile) in (1..3).zip(static_tiles.iter()) {
+            assert_eq!(*static_tile, shadow_atlas.get_allocation(light_id).unwrap()._tiles[0]);
+        }
+    }
+}
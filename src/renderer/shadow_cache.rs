@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+
+use ash::vk;
+use nalgebra::{ Vector4, Matrix4 };
+
+use crate::constants;
+use crate::utilities::bounding_box::BoundingBox;
+use crate::utilities::math;
+use crate::vulkan_context::render_pass::RenderPassDataCreateInfo;
+
+pub const SHADOW_CACHE_LOAD_RENDER_PASS_SUFFIX: &str = "_load";
+pub const SHADOW_CACHE_MATRIX_EPSILON: f32 = 0.00001;
+
+pub type ShadowCacheRegionKey = u64;
+
+pub fn get_cascade_region_key(cascade_index: u32) -> ShadowCacheRegionKey {
+    cascade_index as ShadowCacheRegionKey
+}
+
+// the high bit separates the atlas tiles from the cascades
+pub fn get_atlas_tile_region_key(light_id: u64, tile_index: u32) -> ShadowCacheRegionKey {
+    (1 << 63) | (light_id << 3) | (tile_index as ShadowCacheRegionKey & 0x7)
+}
+
+// a cascade or an atlas tile of this frame
+#[derive(Clone, Debug)]
+pub struct ShadowCacheRegion {
+    pub _region_key: ShadowCacheRegionKey,
+    pub _shadow_view_projection: Matrix4<f32>,
+    pub _is_far_cascade: bool, // refreshed by the round-robin budget when only the casters changed
+}
+
+// a shadow caster that moved or animated this frame, the bound boxes before and after the change.
+#[derive(Clone, Debug)]
+pub struct ShadowCasterChange {
+    pub _prev_bound_box: BoundingBox,
+    pub _bound_box: BoundingBox,
+}
+
+#[derive(Clone, Debug, Copy, PartialEq, Eq)]
+pub enum ShadowCacheDecision {
+    Cached, // keep the depth contents of the last render
+    Render, // clear the region and render it
+}
+
+#[derive(Clone, Debug)]
+struct ShadowCacheEntry {
+    _shadow_view_projection: Matrix4<f32>,
+    _is_dirty: bool,
+    _last_rendered_frame: u64,
+}
+
+#[derive(Clone, Debug, Default, Copy, PartialEq, Eq)]
+pub struct ShadowCacheStats {
+    pub _region_count: u32,
+    pub _cache_hit_count: u32,
+    pub _render_count: u32,
+    pub _deferred_far_cascade_count: u32, // dirty far cascades waiting for the round-robin refresh
+}
+
+// the dirty casters are tested against the volume of each region, the light moving changes the view projection and invalidates its regions.
+// no BVH in the engine yet, so the test is regions x changed casters which stays small as only the moved casters are passed.
+pub struct ShadowCache {
+    _entries: HashMap<ShadowCacheRegionKey, ShadowCacheEntry>,
+    _far_cascade_round_robin_index: usize,
+    _stats: ShadowCacheStats,
+}
+
+impl Default for ShadowCache {
+    fn default() -> ShadowCache {
+        ShadowCache {
+            _entries: HashMap::new(),
+            _far_cascade_round_robin_index: 0,
+            _stats: ShadowCacheStats::default(),
+        }
+    }
+}
+
+pub fn is_shadow_cache_enabled() -> bool {
+    unsafe { constants::SHADOW_CACHE_ENABLE }
+}
+
+pub fn is_same_view_projection(a: &Matrix4<f32>, b: &Matrix4<f32>) -> bool {
+    a.iter().zip(b.iter()).all(|(a, b)| (a - b).abs() <= SHADOW_CACHE_MATRIX_EPSILON)
+}
+
+// conservative, the clip space box of the corners against the depth range of the shadow map
+pub fn is_bound_box_in_shadow_volume(bound_box: &BoundingBox, shadow_view_projection: &Matrix4<f32>) -> bool {
+    let mut clip_min = Vector4::new(std::f32::MAX, std::f32::MAX, std::f32::MAX, 0.0);
+    let mut clip_max = Vector4::new(std::f32::MIN, std::f32::MIN, std::f32::MIN, 0.0);
+    for i in 0..8 {
+        let corner = Vector4::new(
+            if 0 == (i & 1) { bound_box._min.x } else { bound_box._max.x },
+            if 0 == (i & 2) { bound_box._min.y } else { bound_box._max.y },
+            if 0 == (i & 4) { bound_box._min.z } else { bound_box._max.z },
+            1.0
+        );
+        let clip_position = shadow_view_projection * corner;
+        if clip_position.w <= 0.0 {
+            // behind a perspective light, the box crosses the light plane
+            return true;
+        }
+        for axis in 0..3 {
+            let ndc = clip_position[axis] / clip_position.w;
+            clip_min[axis] = clip_min[axis].min(ndc);
+            clip_max[axis] = clip_max[axis].max(ndc);
+        }
+    }
+    // the casters in front of the near plane still cast shadows, so z is only tested with the far plane
+    let is_in_far_plane = if math::is_reversed_z() { math::get_far_depth() <= clip_max.z } else { clip_min.z <= math::get_far_depth() };
+    -1.0 <= clip_max.x && clip_min.x <= 1.0 && -1.0 <= clip_max.y && clip_min.y <= 1.0 && is_in_far_plane
+}
+
+// the LOAD_OP_LOAD variant of a shadow render pass, the cached regions keep their depth and the rendered regions are cleared per rect.
+pub fn create_shadow_cache_render_pass_data_create_info(render_pass_data_create_info: &RenderPassDataCreateInfo) -> RenderPassDataCreateInfo {
+    let mut load_render_pass_data_create_info = render_pass_data_create_info.clone();
+    load_render_pass_data_create_info._render_pass_create_info_name = get_shadow_cache_render_pass_name(&render_pass_data_create_info._render_pass_create_info_name);
+    for depth_attachment_description in load_render_pass_data_create_info._depth_attachment_descriptions.iter_mut() {
+        depth_attachment_description._attachment_load_operation = vk::AttachmentLoadOp::LOAD;
+        depth_attachment_description._attachment_store_operation = vk::AttachmentStoreOp::STORE;
+        depth_attachment_description._attachment_initial_layout = depth_attachment_description._attachment_final_layout;
+    }
+    load_render_pass_data_create_info
+}
+
+pub fn get_shadow_cache_render_pass_name(render_pass_name: &str) -> String {
+    format!("{}{}", render_pass_name, SHADOW_CACHE_LOAD_RENDER_PASS_SUFFIX)
+}
+
+impl ShadowCache {
+    pub fn get_stats(&self) -> &ShadowCacheStats { &self._stats }
+
+    pub fn invalidate_shadow_cache(&mut self) {
+        self._entries.clear();
+    }
+
+    // the atlas tiles of a light moved or were evicted, the depth of its regions is gone
+    pub fn invalidate_atlas_tile_regions(&mut self, light_id: u64) {
+        self._entries.retain(|region_key, _| {
+            (0..8).all(|tile_index| *region_key != get_atlas_tile_region_key(light_id, tile_index))
+        });
+    }
+
+    // the decisions are in the order of the regions, the regions missing this frame are forgotten.
+    pub fn update_shadow_cache(&mut self, frame: u64, regions: &[ShadowCacheRegion], caster_changes: &[ShadowCasterChange]) -> Vec<ShadowCacheDecision> {
+        let is_cache_enabled = is_shadow_cache_enabled();
+        self._entries.retain(|region_key, _| regions.iter().any(|region| *region_key == region._region_key));
+        let mut decisions: Vec<ShadowCacheDecision> = vec![ShadowCacheDecision::Render; regions.len()];
+        let mut dirty_far_cascades: Vec<usize> = Vec::new();
+        for (i, region) in regions.iter().enumerate() {
+            let is_valid = is_cache_enabled && match self._entries.get(&region._region_key) {
+                Some(entry) => is_same_view_projection(&entry._shadow_view_projection, &region._shadow_view_projection),
+                None => false,
+            };
+            if false == is_valid {
+                continue;
+            }
+
+            let entry = self._entries.get_mut(&region._region_key).unwrap();
+            entry._is_dirty |= caster_changes.iter().any(|caster_change| {
+                is_bound_box_in_shadow_volume(&caster_change._prev_bound_box, &region._shadow_view_projection) ||
+                is_bound_box_in_shadow_volume(&caster_change._bound_box, &region._shadow_view_projection)
+            });
+            if false == entry._is_dirty {
+                decisions[i] = ShadowCacheDecision::Cached;
+            } else if region._is_far_cascade {
+                decisions[i] = ShadowCacheDecision::Cached;
+                dirty_far_cascades.push(i);
+            }
+        }
+
+        // at most one dirty far cascade per frame
+        if false == dirty_far_cascades.is_empty() {
+            let get_far_cascade_order = |index: usize| regions[0..index].iter().filter(|region| region._is_far_cascade).count();
+            let refresh_index = *dirty_far_cascades.iter().find(|i| {
+                self._far_cascade_round_robin_index <= get_far_cascade_order(**i)
+            }).unwrap_or(&dirty_far_cascades[0]);
+            decisions[refresh_index] = ShadowCacheDecision::Render;
+            self._far_cascade_round_robin_index = get_far_cascade_order(refresh_index) + 1;
+        }
+
+        let mut stats = ShadowCacheStats {
+            _region_count: regions.len() as u32,
+            ..Default::default()
+        };
+        for (region, decision) in regions.iter().zip(decisions.iter()) {
+            match decision {
+                ShadowCacheDecision::Cached => {
+                    stats._cache_hit_count += 1;
+                    if self._entries.get(&region._region_key).unwrap()._is_dirty {
+                        stats._deferred_far_cascade_count += 1;
+                    }
+                },
+                ShadowCacheDecision::Render => {
+                    stats._render_count += 1;
+                    self._entries.insert(region._region_key, ShadowCacheEntry {
+                        _shadow_view_projection: region._shadow_view_projection.clone(),
+                        _is_dirty: false,
+                        _last_rendered_frame: frame,
+                    });
+                },
+            }
+        }
+        self._stats = stats;
+        decisions
+    }
+
+    pub fn get_last_rendered_frame(&self, region_key: ShadowCacheRegionKey) -> Option<u64> {
+        self._entries.get(&region_key).map(|entry| entry._last_rendered_frame)
+    }
+
+    pub fn get_stats_text(&self) -> String {
+        format!("shadow cache{}: hit {} / {}, deferred far cascades {}",
+            if is_shadow_cache_enabled() { "" } else { "(disabled)" },
+            self._stats._cache_hit_count,
+            self._stats._region_count,
+            self._stats._deferred_far_cascade_count
+        )
+    }
+}
@@ -0,0 +1,350 @@
+use std::fs;
+use std::path::PathBuf;
+
+use ash::{ vk, Device };
+use ash::version::DeviceV1_0;
+use nalgebra::{ Matrix4, Vector3 };
+
+use crate::application::environment_settings::{ SkyMode, SkySettings };
+use crate::constants;
+use crate::renderer::renderer::RendererData;
+use crate::renderer::utility;
+use crate::resource::resource::Resources;
+use crate::utilities::math;
+use crate::vulkan_context::descriptor::{ self, DescriptorDataCreateInfo, DescriptorResourceInfo, DescriptorResourceType };
+use crate::vulkan_context::framebuffer::{ self, FramebufferData, RenderTargetInfo };
+use crate::vulkan_context::render_pass::{ DepthStencilStateCreateInfo, ImageAttachmentDescription, PipelineDataCreateInfo, RenderPassDataCreateInfo };
+use crate::vulkan_context::shader::SHADER_DIRECTORY;
+use crate::vulkan_context::texture::TextureData;
+use crate::vulkan_context::vulkan_context::{ self, BlendMode, SwapchainArray };
+
+pub const SKYBOX_RENDER_PASS_NAME: &str = "render_skybox";
+pub const SKYBOX_QUAD_MESH_NAME: &str = "quad"; // see Resources::load_mesh_datas
+pub const SKYBOX_CUBEMAP_FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT; // of the equirectangular hdr, the face images keep their format
+pub const SKYBOX_VERTEX_SHADER_FILE: &str = "common/render_skybox.vert";
+pub const SKYBOX_FRAGMENT_SHADER_FILE: &str = "common/render_skybox.frag";
+pub const SKYBOX_VERTEX_SHADER_SOURCE: &str = r#"#version 450
+
+layout(push_constant) uniform PushConstant_Skybox
+{
+    mat4 inv_view_origin_projection;
+    float far_depth;
+    float near_depth;
+    float intensity;
+    float reserved0;
+} pushConstant;
+
+layout(location = 0) in vec3 inPosition;
+
+layout(location = 0) out vec4 vs_view_direction;
+
+void main()
+{
+    // the depth test is EQUAL, only the pixels still at the cleared far depth are drawn
+    gl_Position = vec4(inPosition.xy, pushConstant.far_depth, 1.0);
+    // unprojected at the near depth, the far depth of the infinite projection is at w = 0
+    vs_view_direction = pushConstant.inv_view_origin_projection * vec4(inPosition.xy, pushConstant.near_depth, 1.0);
+}
+"#;
+pub const SKYBOX_FRAGMENT_SHADER_SOURCE: &str = r#"#version 450
+
+layout(push_constant) uniform PushConstant_Skybox
+{
+    mat4 inv_view_origin_projection;
+    float far_depth;
+    float near_depth;
+    float intensity;
+    float reserved0;
+} pushConstant;
+
+layout(binding = 0) uniform samplerCube texture_sky_cubemap;
+
+layout(location = 0) in vec4 vs_view_direction;
+
+layout(location = 0) out vec4 outColor;
+
+void main()
+{
+    vec3 view_direction = normalize(vs_view_direction.xyz / vs_view_direction.w);
+    outColor = vec4(textureLod(texture_sky_cubemap, view_direction, 0.0).xyz * pushConstant.intensity, 1.0);
+}
+"#;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SkyboxSettings {
+    pub _color_target_name: String,
+    pub _depth_target_name: String, // the depth of the opaque passes, cleared with math::get_far_depth
+}
+
+impl Default for SkyboxSettings {
+    fn default() -> SkyboxSettings {
+        SkyboxSettings {
+            _color_target_name: String::from("SceneColor"),
+            _depth_target_name: String::from("SceneDepth"),
+        }
+    }
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone)]
+#[repr(C)]
+pub struct PushConstant_Skybox {
+    pub _inv_view_origin_projection: Matrix4<f32>,
+    pub _far_depth: f32,
+    pub _near_depth: f32,
+    pub _intensity: f32,
+    pub _reserved0: f32,
+}
+
+crate::impl_push_constant!(PushConstant_Skybox, 80);
+
+// The sky of SkyMode::Cubemap without the atmosphere precompute: a cube texture of the resources drawn behind the opaque geometry.
+// the cube is a .cube of six face images or an equirectangular .hdr converted by Resources::load_equirectangular_texture.
+#[derive(Default)]
+pub struct SkyboxData {
+    pub _settings: SkyboxSettings,
+    pub _sky_mode: Option<SkyMode>, // None: not set by the environment settings yet
+    pub _sky_cubemap_name: String,
+    pub _sky_intensity: f32,
+    pub _framebuffer_data: Option<FramebufferData>,
+    pub _descriptor_sets: SwapchainArray<vk::DescriptorSet>, // empty when the cubemap does not exist
+}
+
+pub fn get_render_pass_data_create_info(color_target: &TextureData, depth_target: &TextureData) -> RenderPassDataCreateInfo {
+    let pipeline_data_create_info = PipelineDataCreateInfo {
+        _pipeline_data_create_info_name: String::from(SKYBOX_RENDER_PASS_NAME),
+        _pipeline_vertex_shader_file: PathBuf::from(SKYBOX_VERTEX_SHADER_FILE),
+        _pipeline_fragment_shader_file: PathBuf::from(SKYBOX_FRAGMENT_SHADER_FILE),
+        _pipeline_color_blend_modes: vec![vulkan_context::get_color_blend_mode(BlendMode::None)],
+        _pipeline_sample_count: color_target._image_sample_count,
+        _depth_stencil_state_create_info: DepthStencilStateCreateInfo {
+            _depth_test_enable: true,
+            _depth_write_enable: false,
+            _depth_compare_op: vk::CompareOp::EQUAL,
+            ..Default::default()
+        },
+        _push_constant_ranges: vec![vk::PushConstantRange {
+            stage_flags: vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+            offset: 0,
+            size: std::mem::size_of::<PushConstant_Skybox>() as u32,
+        }],
+        _descriptor_data_create_infos: vec![DescriptorDataCreateInfo {
+            _descriptor_binding_index: 0,
+            _descriptor_name: String::from("texture_sky_cubemap"),
+            _descriptor_resource_type: DescriptorResourceType::Texture,
+            _descriptor_shader_stage: vk::ShaderStageFlags::FRAGMENT,
+            ..Default::default()
+        }],
+        ..Default::default()
+    };
+
+    RenderPassDataCreateInfo {
+        _render_pass_create_info_name: String::from(SKYBOX_RENDER_PASS_NAME),
+        _color_attachment_descriptions: vec![ImageAttachmentDescription {
+            _attachment_image_format: color_target._image_format,
+            _attachment_image_samples: color_target._image_sample_count,
+            _attachment_load_operation: vk::AttachmentLoadOp::LOAD,
+            _attachment_store_operation: vk::AttachmentStoreOp::STORE,
+            _attachment_initial_layout: vk::ImageLayout::GENERAL,
+            ..Default::default()
+        }],
+        _depth_attachment_descriptions: vec![ImageAttachmentDescription {
+            _attachment_image_format: depth_target._image_format,
+            _attachment_image_samples: depth_target._image_sample_count,
+            _attachment_load_operation: vk::AttachmentLoadOp::LOAD,
+            _attachment_store_operation: vk::AttachmentStoreOp::STORE,
+            _attachment_stencil_load_operation: vk::AttachmentLoadOp::LOAD,
+            _attachment_stencil_store_operation: vk::AttachmentStoreOp::STORE,
+            _attachment_initial_layout: vk::ImageLayout::GENERAL,
+            ..Default::default()
+        }],
+        _pipeline_data_create_infos: vec![pipeline_data_create_info],
+        ..Default::default()
+    }
+}
+
+#[cfg(not(target_os = "android"))]
+pub fn generate_skybox_shaders() {
+    let shader_sources = [
+        (SKYBOX_VERTEX_SHADER_FILE, SKYBOX_VERTEX_SHADER_SOURCE),
+        (SKYBOX_FRAGMENT_SHADER_FILE, SKYBOX_FRAGMENT_SHADER_SOURCE),
+    ];
+    for (shader_file, shader_source) in shader_sources.iter() {
+        let mut shader_file_path = PathBuf::from(SHADER_DIRECTORY);
+        shader_file_path.push(shader_file);
+        if false == shader_file_path.is_file() {
+            fs::create_dir_all(shader_file_path.parent().unwrap()).expect("Failed to create directories.");
+            fs::write(&shader_file_path, shader_source).expect("Failed to write");
+            log::info!("generate_skybox_shaders: {:?}", shader_file_path);
+        }
+    }
+}
+
+// the face order of vulkan: +x, -x, +y, -y, +z, -z, texcoord (0, 0) is the top-left texel of the face. same as light_probe.frag
+fn get_cube_face_direction(face_index: usize, u: f32, v: f32) -> Vector3<f32> {
+    let (s, t) = (u * 2.0 - 1.0, v * 2.0 - 1.0);
+    let direction = match face_index {
+        0 => Vector3::new(1.0, -t, -s),
+        1 => Vector3::new(-1.0, -t, s),
+        2 => Vector3::new(s, 1.0, t),
+        3 => Vector3::new(s, -1.0, -t),
+        4 => Vector3::new(s, -t, 1.0),
+        _ => Vector3::new(-s, -t, -1.0),
+    };
+    direction.normalize()
+}
+
+// the six faces of the rgb float pixels of a latitude longitude image, the half float rgba of SKYBOX_CUBEMAP_FORMAT in the layer order.
+// the center of the image is +z, the top row is +y. the bilinear filter wraps the longitude and clamps the latitude.
+pub fn convert_equirectangular_to_cube(width: u32, height: u32, pixels: &[[f32; 3]], face_size: u32) -> Vec<u16> {
+    let (width, height) = (width.max(1) as usize, height.max(1) as usize);
+    let fetch = |x: i64, y: i64| -> [f32; 3] {
+        let x = x.rem_euclid(width as i64) as usize;
+        let y = y.max(0).min(height as i64 - 1) as usize;
+        pixels[y * width + x]
+    };
+    let face_size = face_size.max(1) as usize;
+    let mut face_datas: Vec<u16> = Vec::with_capacity(constants::CUBE_LAYER_COUNT * face_size * face_size * 4);
+    for face_index in 0..constants::CUBE_LAYER_COUNT {
+        for y in 0..face_size {
+            for x in 0..face_size {
+                let direction = get_cube_face_direction(face_index, (x as f32 + 0.5) / face_size as f32, (y as f32 + 0.5) / face_size as f32);
+                let longitude = direction.x.atan2(direction.z);
+                let latitude = direction.y.max(-1.0).min(1.0).acos();
+                let image_x = (longitude / (2.0 * std::f32::consts::PI) + 0.5) * width as f32 - 0.5;
+                let image_y = (latitude / std::f32::consts::PI) * height as f32 - 0.5;
+                let (x0, y0) = (image_x.floor(), image_y.floor());
+                let (fx, fy) = (image_x - x0, image_y - y0);
+                let (x0, y0) = (x0 as i64, y0 as i64);
+                for channel in 0..3 {
+                    let top = math::lerp(fetch(x0, y0)[channel], fetch(x0 + 1, y0)[channel], fx);
+                    let bottom = math::lerp(fetch(x0, y0 + 1)[channel], fetch(x0 + 1, y0 + 1)[channel], fx);
+                    face_datas.push(math::f32_to_f16(math::lerp(top, bottom, fy)));
+                }
+                face_datas.push(math::f32_to_f16(1.0));
+            }
+        }
+    }
+    face_datas
+}
+
+impl SkyboxData {
+    // true when the cubemap of the skybox and the light probe is changed, the descriptor sets are created again
+    pub fn set_sky_settings(&mut self, sky_settings: &SkySettings) -> bool {
+        let prev_sky_cubemap_name = self.get_sky_cubemap_name().map(String::from);
+        self._sky_mode = Some(sky_settings._sky_mode);
+        self._sky_cubemap_name = sky_settings._sky_cubemap.clone();
+        self._sky_intensity = sky_settings._sky_intensity;
+        prev_sky_cubemap_name.as_ref().map(String::as_str) != self.get_sky_cubemap_name()
+    }
+
+    pub fn is_same_sky_settings(&self, sky_settings: &SkySettings) -> bool {
+        Some(sky_settings._sky_mode) == self._sky_mode && sky_settings._sky_cubemap == self._sky_cubemap_name && sky_settings._sky_intensity == self._sky_intensity
+    }
+
+    // the cubemap texture of SkyMode::Cubemap, None for the other sky modes
+    pub fn get_sky_cubemap_name(&self) -> Option<&str> {
+        match self._sky_mode {
+            Some(SkyMode::Cubemap) if false == self._sky_cubemap_name.is_empty() => Some(self._sky_cubemap_name.as_str()),
+            _ => None,
+        }
+    }
+
+    pub fn create_framebuffer(&mut self, renderer_data: &RendererData, resources: &Resources, color_target: &TextureData, depth_target: &TextureData) {
+        let render_pass_data = resources.get_render_pass_data(SKYBOX_RENDER_PASS_NAME).borrow();
+        let color_render_target_info = RenderTargetInfo {
+            _texture_data: color_target,
+            _target_layer: 0,
+            _target_mip_level: 0,
+            _clear_value: None,
+        };
+        let depth_render_target_info = RenderTargetInfo {
+            _texture_data: depth_target,
+            _target_layer: 0,
+            _target_mip_level: 0,
+            _clear_value: None,
+        };
+        self._framebuffer_data = Some(utility::create_framebuffers(
+            renderer_data.get_device(),
+            &render_pass_data,
+            SKYBOX_RENDER_PASS_NAME,
+            &[color_render_target_info],
+            &[depth_render_target_info],
+            &[]
+        ));
+    }
+
+    pub fn create_descriptor_sets(&mut self, renderer_data: &RendererData, resources: &Resources, sky_cubemap: &TextureData) {
+        let render_pass_pipeline_data = resources.get_render_pass_pipeline_data(SKYBOX_RENDER_PASS_NAME, SKYBOX_RENDER_PASS_NAME);
+        let pipeline_data = render_pass_pipeline_data._pipeline_data.borrow();
+        let descriptor_data = &pipeline_data._descriptor_data;
+        let descriptor_binding_indices: Vec<u32> = descriptor_data._descriptor_data_create_infos.iter().map(|descriptor_data_create_info| {
+            descriptor_data_create_info._descriptor_binding_index
+        }).collect();
+        let descriptor_resource_infos_list: SwapchainArray<Vec<DescriptorResourceInfo>> = constants::SWAPCHAIN_IMAGE_INDICES.iter().map(|_swapchain_index| {
+            vec![DescriptorResourceInfo::DescriptorImageInfo(sky_cubemap.get_default_image_info().clone())]
+        }).collect();
+        let descriptor_sets = descriptor::create_descriptor_sets(renderer_data.get_device(), descriptor_data, SKYBOX_RENDER_PASS_NAME);
+        let _write_descriptor_sets = descriptor::create_write_descriptor_sets_with_update(
+            renderer_data.get_device(),
+            &descriptor_sets,
+            &descriptor_binding_indices,
+            &descriptor_data._descriptor_set_layout_bindings,
+            &descriptor_resource_infos_list,
+        );
+        self._descriptor_sets = descriptor_sets;
+    }
+
+    pub fn destroy_framebuffer_and_descriptor_sets(&mut self, device: &Device) {
+        if let Some(framebuffer_data) = self._framebuffer_data.take() {
+            framebuffer::destroy_framebuffer_data(device, &framebuffer_data);
+        }
+        self._descriptor_sets.clear();
+    }
+
+    // after the opaque passes, before the translucent passes. the color and the depth stay in GENERAL.
+    pub fn render_skybox(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        swapchain_index: u32,
+        renderer_data: &RendererData,
+        resources: &Resources,
+        inv_view_origin_projection: &Matrix4<f32>
+    ) {
+        if Some(SkyMode::Cubemap) != self._sky_mode || self._descriptor_sets.is_empty() {
+            return;
+        }
+        let framebuffer_data = match &self._framebuffer_data {
+            Some(framebuffer_data) => framebuffer_data,
+            None => return,
+        };
+        let quad_mesh_data = resources.get_mesh_data(SKYBOX_QUAD_MESH_NAME).borrow();
+        let quad_geometry_data = quad_mesh_data.get_default_geometry_data().borrow();
+        let render_pass_pipeline_data = resources.get_render_pass_pipeline_data(SKYBOX_RENDER_PASS_NAME, SKYBOX_RENDER_PASS_NAME);
+        let render_pass_data = render_pass_pipeline_data._render_pass_data.borrow();
+        let pipeline_data = render_pass_pipeline_data._pipeline_data.borrow();
+        let push_constant_data = PushConstant_Skybox {
+            _inv_view_origin_projection: inv_view_origin_projection.clone(),
+            _far_depth: math::get_far_depth(),
+            _near_depth: math::get_near_depth(),
+            _intensity: self._sky_intensity,
+            _reserved0: 0.0,
+        };
+        renderer_data.begin_debug_label(command_buffer, SKYBOX_RENDER_PASS_NAME);
+        renderer_data.begin_render_pass_pipeline(command_buffer, swapchain_index, &render_pass_data, &pipeline_data, Some(framebuffer_data));
+        unsafe {
+            renderer_data.get_device().cmd_bind_descriptor_sets(
+                command_buffer,
+                pipeline_data._pipeline_bind_point,
+                pipeline_data._pipeline_layout,
+                0,
+                &[self._descriptor_sets[swapchain_index as usize]],
+                &[]
+            );
+        }
+        renderer_data.upload_push_constant_data(command_buffer, &pipeline_data, &push_constant_data);
+        renderer_data.draw_elements(command_buffer, &quad_geometry_data);
+        renderer_data.end_render_pass(command_buffer);
+        renderer_data.end_debug_label(command_buffer);
+    }
+}
@@ -0,0 +1,677 @@
+use std::fs;
+use std::path::PathBuf;
+
+use ash::{ vk, Device };
+use ash::version::DeviceV1_0;
+use nalgebra::{ Matrix4, Vector2, Vector3, Vector4 };
+use serde::{ Serialize, Deserialize };
+
+use crate::constants;
+use crate::renderer::camera::CameraObjectData;
+use crate::renderer::mesh;
+use crate::renderer::render_object_storage;
+use crate::renderer::renderer::RendererData;
+use crate::renderer::utility;
+use crate::resource::resource::Resources;
+use crate::utilities::bounding_box::{ self, BoundingBox };
+use crate::vulkan_context::buffer::{ self, ShaderBufferData };
+use crate::vulkan_context::descriptor::{ self, DescriptorDataCreateInfo, DescriptorResourceInfo, DescriptorResourceType };
+use crate::vulkan_context::framebuffer::{ self, FramebufferData, RenderTargetInfo };
+use crate::vulkan_context::geometry_buffer::{ GeometryCreateInfo, GeometryData, StaticVertexData };
+use crate::vulkan_context::render_pass::{ ImageAttachmentDescription, PipelineDataCreateInfo, RenderPassDataCreateInfo };
+use crate::vulkan_context::shader::SHADER_DIRECTORY;
+use crate::vulkan_context::texture::TextureData;
+use crate::vulkan_context::vulkan_context::{ self, BlendMode, SwapchainArray };
+
+pub const TERRAIN_RENDER_PASS_NAME: &str = "render_pass_terrain";
+pub const TERRAIN_CONSTANTS_BUFFER_NAME: &str = "TerrainConstants";
+pub const TERRAIN_CHUNK_QUAD_COUNT: u32 = 64; // quads per chunk side at the lod 0
+pub const TERRAIN_LOD_COUNT: usize = 4; // the quad step of the lod n is 2^n
+pub const TERRAIN_LAYER_COUNT: usize = 4; // the rgba weights of the splat map
+pub const TERRAIN_VERTEX_SHADER_FILE: &str = "common/render_terrain.vert";
+pub const TERRAIN_FRAGMENT_SHADER_FILE: &str = "common/render_terrain.frag";
+pub const TERRAIN_VERTEX_SHADER_SOURCE: &str = r#"#version 450
+
+// terrain.rs - struct TerrainConstants
+layout(binding = 0) uniform TerrainConstants
+{
+    mat4 view_origin_projection_jitter;
+    mat4 view_origin_projection;
+    mat4 view_origin_projection_prev;
+    vec4 camera_position;
+    vec4 camera_position_prev;
+    vec4 layer_tilings;
+    vec4 layer_roughnesses;
+} terrain_constants;
+
+layout(location = 0) in vec3 inPosition;
+layout(location = 1) in vec3 inNormal;
+layout(location = 2) in vec3 inTangent;
+layout(location = 3) in vec4 inColor;
+layout(location = 4) in vec2 inTexCoord;
+
+layout(location = 0) out vec3 vs_normal;
+layout(location = 1) out vec2 vs_texcoord;
+layout(location = 2) out vec4 vs_projection_pos;
+layout(location = 3) out vec4 vs_projection_pos_prev;
+
+void main()
+{
+    // the vertices are in the world space, the camera relative positions keep the precision of the large terrains
+    vec3 relative_position = inPosition - terrain_constants.camera_position.xyz;
+    gl_Position = terrain_constants.view_origin_projection_jitter * vec4(relative_position, 1.0);
+    vs_projection_pos = terrain_constants.view_origin_projection * vec4(relative_position, 1.0);
+    vs_projection_pos_prev = terrain_constants.view_origin_projection_prev * vec4(inPosition - terrain_constants.camera_position_prev.xyz, 1.0);
+    vs_normal = inNormal;
+    vs_texcoord = inTexCoord;
+}
+"#;
+pub const TERRAIN_FRAGMENT_SHADER_SOURCE: &str = r#"#version 450
+
+// terrain.rs - struct TerrainConstants
+layout(binding = 0) uniform TerrainConstants
+{
+    mat4 view_origin_projection_jitter;
+    mat4 view_origin_projection;
+    mat4 view_origin_projection_prev;
+    vec4 camera_position;
+    vec4 camera_position_prev;
+    vec4 layer_tilings;
+    vec4 layer_roughnesses;
+} terrain_constants;
+
+layout(binding = 1) uniform sampler2D texture_splat_map;
+layout(binding = 2) uniform sampler2D texture_layer0;
+layout(binding = 3) uniform sampler2D texture_layer1;
+layout(binding = 4) uniform sampler2D texture_layer2;
+layout(binding = 5) uniform sampler2D texture_layer3;
+
+layout(location = 0) in vec3 vs_normal;
+layout(location = 1) in vec2 vs_texcoord;
+layout(location = 2) in vec4 vs_projection_pos;
+layout(location = 3) in vec4 vs_projection_pos_prev;
+
+// the gbuffer layout of render_object.frag of the project
+layout(location = 0) out vec4 outAlbedo;
+layout(location = 1) out vec4 outMaterial; // roughness, metallic, reflectance
+layout(location = 2) out vec4 outNormal; // normal * 0.5 + 0.5
+layout(location = 3) out vec2 outVelocity; // texcoord - prev_texcoord
+
+void main()
+{
+    vec4 splat_weights = texture(texture_splat_map, vs_texcoord);
+    float weight_sum = dot(splat_weights, vec4(1.0));
+    splat_weights = (0.0 < weight_sum) ? (splat_weights / weight_sum) : vec4(1.0, 0.0, 0.0, 0.0);
+    vec4 tilings = terrain_constants.layer_tilings;
+    vec3 albedo = texture(texture_layer0, vs_texcoord * tilings.x).xyz * splat_weights.x;
+    albedo += texture(texture_layer1, vs_texcoord * tilings.y).xyz * splat_weights.y;
+    albedo += texture(texture_layer2, vs_texcoord * tilings.z).xyz * splat_weights.z;
+    albedo += texture(texture_layer3, vs_texcoord * tilings.w).xyz * splat_weights.w;
+    float roughness = dot(splat_weights, terrain_constants.layer_roughnesses);
+
+    vec2 texcoord = (vs_projection_pos.xy / vs_projection_pos.w) * 0.5 + 0.5;
+    vec2 prev_texcoord = (vs_projection_pos_prev.xy / vs_projection_pos_prev.w) * 0.5 + 0.5;
+    outAlbedo = vec4(albedo, 1.0);
+    outMaterial = vec4(roughness, 0.0, 0.5, 0.0);
+    outNormal = vec4(normalize(vs_normal) * 0.5 + 0.5, 0.0);
+    outVelocity = texcoord - prev_texcoord;
+}
+"#;
+
+// "_terrain" block of the environment settings, the heightmap is a 16 bit gray png of resource/externals/terrains.
+// the heightmap row 0 is at the min z, the column 0 is at the min x. the splat map covers the whole terrain.
+// ex) "_terrain": { "_enable_terrain": true, "_heightmap_name": "island", "_terrain_size": [1024.0, 1024.0], "_height_scale": 200.0 }
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct TerrainSettings {
+    pub _enable_terrain: bool,
+    pub _heightmap_name: String,
+    pub _terrain_position: Vector3<f32>, // the min corner, the height 0 of the heightmap
+    pub _terrain_size: Vector2<f32>, // meters of x and z
+    pub _height_scale: f32, // meters of the max heightmap value
+    pub _lod_distance: f32, // meters, the lod n is used beyond _lod_distance * 2^(n-1)
+    pub _skirt_depth: f32, // meters, the skirts below the chunk borders hide the cracks between the lods
+    pub _splat_map_name: String, // rgba: the weights of the 4 layers
+    pub _layer_texture_names: Vec<String>, // the albedo of the layers
+    pub _layer_tilings: Vector4<f32>, // the repeat count of the layer textures over the terrain
+    pub _layer_roughnesses: Vector4<f32>,
+}
+
+impl Default for TerrainSettings {
+    fn default() -> TerrainSettings {
+        TerrainSettings {
+            _enable_terrain: false,
+            _heightmap_name: String::new(),
+            _terrain_position: Vector3::new(-512.0, 0.0, -512.0),
+            _terrain_size: Vector2::new(1024.0, 1024.0),
+            _height_scale: 100.0,
+            _lod_distance: 100.0,
+            _skirt_depth: 2.0,
+            _splat_map_name: String::new(),
+            _layer_texture_names: Vec::new(),
+            _layer_tilings: Vector4::new(64.0, 64.0, 64.0, 64.0),
+            _layer_roughnesses: Vector4::new(0.8, 0.8, 0.8, 0.8),
+        }
+    }
+}
+
+impl TerrainSettings {
+    // the material settings are applied by the descriptor sets, the others rebuild the chunks
+    pub fn is_geometry_changed(&self, other: &TerrainSettings) -> bool {
+        self._enable_terrain != other._enable_terrain ||
+            self._heightmap_name != other._heightmap_name ||
+            self._terrain_position != other._terrain_position ||
+            self._terrain_size != other._terrain_size ||
+            self._height_scale != other._height_scale ||
+            self._skirt_depth != other._skirt_depth
+    }
+
+    // the missing layers are the default texture of the resources
+    pub fn get_layer_texture_name(&self, layer_index: usize) -> &str {
+        self._layer_texture_names.get(layer_index).map_or("", String::as_str)
+    }
+}
+
+// the gbuffer targets of the project renderer, see RendererData::set_terrain_render_settings
+#[derive(Clone, Debug, PartialEq)]
+pub struct TerrainRenderSettings {
+    pub _color_target_names: [String; 4], // albedo, material, normal, velocity
+    pub _depth_target_name: String,
+}
+
+impl Default for TerrainRenderSettings {
+    fn default() -> TerrainRenderSettings {
+        TerrainRenderSettings {
+            _color_target_names: [
+                String::from("SceneAlbedo"),
+                String::from("SceneMaterial"),
+                String::from("SceneNormal"),
+                String::from("SceneVelocity"),
+            ],
+            _depth_target_name: String::from("SceneDepth"),
+        }
+    }
+}
+
+// render_terrain.vert, render_terrain.frag - uniform TerrainConstants
+#[repr(C)]
+#[derive(Clone, Debug)]
+pub struct TerrainConstants {
+    pub _view_origin_projection_jitter: Matrix4<f32>,
+    pub _view_origin_projection: Matrix4<f32>,
+    pub _view_origin_projection_prev: Matrix4<f32>,
+    pub _camera_position: Vector4<f32>,
+    pub _camera_position_prev: Vector4<f32>,
+    pub _layer_tilings: Vector4<f32>,
+    pub _layer_roughnesses: Vector4<f32>,
+}
+
+// the 16 bit samples of the heightmap, see Resources::get_terrain_heightmap
+#[derive(Clone, Debug, Default)]
+pub struct TerrainHeightmap {
+    pub _width: u32,
+    pub _height: u32,
+    pub _heights: Vec<u16>,
+}
+
+impl TerrainHeightmap {
+    // 0.0 ~ 1.0, the coordinates are clamped to the edges
+    pub fn get_sample(&self, x: i32, z: i32) -> f32 {
+        let x = x.max(0).min(self._width as i32 - 1) as usize;
+        let z = z.max(0).min(self._height as i32 - 1) as usize;
+        self._heights[z * self._width as usize + x] as f32 / std::u16::MAX as f32
+    }
+}
+
+pub struct TerrainChunk {
+    pub _bounding_box: BoundingBox, // includes the skirts
+    pub _lod_geometry_datas: Vec<GeometryData>,
+}
+
+// The heightmap terrain of the environment settings: chunks of TERRAIN_CHUNK_QUAD_COUNT quads with a geometry per lod,
+// culled and lod selected by SceneManagerData::cull_scene, drawn into the gbuffer by render_terrain.
+#[derive(Default)]
+pub struct Terrain {
+    pub _settings: TerrainSettings,
+    pub _render_settings: TerrainRenderSettings,
+    pub _heightmap: Option<TerrainHeightmap>,
+    pub _chunks: Vec<TerrainChunk>,
+    pub _lod_indices: Vec<usize>, // per chunk, kept between the frames for the hysteresis
+    pub _visible_chunk_indices: Vec<u32>,
+    pub _constants_buffer: Option<ShaderBufferData>,
+    pub _framebuffer_data: Option<FramebufferData>,
+    pub _descriptor_sets: SwapchainArray<vk::DescriptorSet>,
+}
+
+pub fn get_render_pass_data_create_info(color_targets: &[&TextureData], depth_target: &TextureData) -> RenderPassDataCreateInfo {
+    let sample_count = depth_target._image_sample_count;
+    let mut descriptor_data_create_infos = vec![DescriptorDataCreateInfo {
+        _descriptor_binding_index: 0,
+        _descriptor_name: String::from(TERRAIN_CONSTANTS_BUFFER_NAME),
+        _descriptor_resource_type: DescriptorResourceType::UniformBuffer,
+        _descriptor_shader_stage: vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+        ..Default::default()
+    }, DescriptorDataCreateInfo {
+        _descriptor_binding_index: 1,
+        _descriptor_name: String::from("texture_splat_map"),
+        _descriptor_resource_type: DescriptorResourceType::Texture,
+        _descriptor_shader_stage: vk::ShaderStageFlags::FRAGMENT,
+        ..Default::default()
+    }];
+    for layer_index in 0..TERRAIN_LAYER_COUNT {
+        descriptor_data_create_infos.push(DescriptorDataCreateInfo {
+            _descriptor_binding_index: 2 + layer_index as u32,
+            _descriptor_name: format!("texture_layer{}", layer_index),
+            _descriptor_resource_type: DescriptorResourceType::Texture,
+            _descriptor_shader_stage: vk::ShaderStageFlags::FRAGMENT,
+            ..Default::default()
+        });
+    }
+    let pipeline_data_create_info = PipelineDataCreateInfo {
+        _pipeline_data_create_info_name: String::from(TERRAIN_RENDER_PASS_NAME),
+        _pipeline_vertex_shader_file: PathBuf::from(TERRAIN_VERTEX_SHADER_FILE),
+        _pipeline_fragment_shader_file: PathBuf::from(TERRAIN_FRAGMENT_SHADER_FILE),
+        _pipeline_color_blend_modes: color_targets.iter().map(|_| vulkan_context::get_color_blend_mode(BlendMode::None)).collect(),
+        _pipeline_sample_count: sample_count,
+        _descriptor_data_create_infos: descriptor_data_create_infos,
+        ..Default::default()
+    };
+
+    // the gbuffer pass of the project cleared the targets, the terrain is drawn after it
+    RenderPassDataCreateInfo {
+        _render_pass_create_info_name: String::from(TERRAIN_RENDER_PASS_NAME),
+        _color_attachment_descriptions: color_targets.iter().map(|color_target| ImageAttachmentDescription {
+            _attachment_image_format: color_target._image_format,
+            _attachment_image_samples: color_target._image_sample_count,
+            _attachment_load_operation: vk::AttachmentLoadOp::LOAD,
+            _attachment_store_operation: vk::AttachmentStoreOp::STORE,
+            _attachment_initial_layout: vk::ImageLayout::GENERAL,
+            ..Default::default()
+        }).collect(),
+        _depth_attachment_descriptions: vec![ImageAttachmentDescription {
+            _attachment_image_format: depth_target._image_format,
+            _attachment_image_samples: depth_target._image_sample_count,
+            _attachment_load_operation: vk::AttachmentLoadOp::LOAD,
+            _attachment_store_operation: vk::AttachmentStoreOp::STORE,
+            _attachment_stencil_load_operation: vk::AttachmentLoadOp::LOAD,
+            _attachment_stencil_store_operation: vk::AttachmentStoreOp::STORE,
+            _attachment_initial_layout: vk::ImageLayout::GENERAL,
+            ..Default::default()
+        }],
+        _pipeline_data_create_infos: vec![pipeline_data_create_info],
+        ..Default::default()
+    }
+}
+
+#[cfg(not(target_os = "android"))]
+pub fn generate_terrain_shaders() {
+    let shader_sources = [
+        (TERRAIN_VERTEX_SHADER_FILE, TERRAIN_VERTEX_SHADER_SOURCE),
+        (TERRAIN_FRAGMENT_SHADER_FILE, TERRAIN_FRAGMENT_SHADER_SOURCE),
+    ];
+    for (shader_file, shader_source) in shader_sources.iter() {
+        let mut shader_file_path = PathBuf::from(SHADER_DIRECTORY);
+        shader_file_path.push(shader_file);
+        if false == shader_file_path.is_file() {
+            fs::create_dir_all(shader_file_path.parent().unwrap()).expect("Failed to create directories.");
+            fs::write(&shader_file_path, shader_source).expect("Failed to write");
+            log::info!("generate_terrain_shaders: {:?}", shader_file_path);
+        }
+    }
+}
+
+// the lod n is used beyond lod_distance * 2^(n-1), the hysteresis keeps the current lod around the switch distances
+pub fn select_terrain_lod(lod_index: usize, distance: f32, lod_distance: f32, hysteresis: f32) -> usize {
+    if lod_distance <= 0.0 {
+        return 0;
+    }
+    let mut selected_lod_index: usize = 0;
+    for i in 0..(TERRAIN_LOD_COUNT - 1) {
+        let switch_distance = lod_distance * (1 << i) as f32;
+        let switch_distance = if i < lod_index { switch_distance * (1.0 - hysteresis) } else { switch_distance * (1.0 + hysteresis) };
+        if distance <= switch_distance {
+            break;
+        }
+        selected_lod_index = i + 1;
+    }
+    selected_lod_index
+}
+
+// the distance to the nearest point of the box, zero inside
+fn get_distance_to_bounding_box(position: &Vector3<f32>, bound_box: &BoundingBox) -> f32 {
+    let nearest_position = Vector3::new(
+        position.x.max(bound_box._min.x).min(bound_box._max.x),
+        position.y.max(bound_box._min.y).min(bound_box._max.y),
+        position.z.max(bound_box._min.z).min(bound_box._max.z),
+    );
+    (position - nearest_position).norm()
+}
+
+fn get_terrain_vertex(heightmap: &TerrainHeightmap, settings: &TerrainSettings, x: i32, z: i32) -> StaticVertexData {
+    let max_x = (heightmap._width - 1).max(1) as f32;
+    let max_z = (heightmap._height - 1).max(1) as f32;
+    let cell_size = Vector2::new(settings._terrain_size.x / max_x, settings._terrain_size.y / max_z);
+    let height = heightmap.get_sample(x, z) * settings._height_scale;
+    // the central differences, the edges use the clamped samples
+    let slope_x = (heightmap.get_sample(x + 1, z) - heightmap.get_sample(x - 1, z)) * settings._height_scale / (2.0 * cell_size.x);
+    let slope_z = (heightmap.get_sample(x, z + 1) - heightmap.get_sample(x, z - 1)) * settings._height_scale / (2.0 * cell_size.y);
+    let texcoord = Vector2::new(x as f32 / max_x, z as f32 / max_z);
+    StaticVertexData {
+        _position: Vector3::new(
+            settings._terrain_position.x + texcoord.x * settings._terrain_size.x,
+            settings._terrain_position.y + height,
+            settings._terrain_position.z + texcoord.y * settings._terrain_size.y
+        ),
+        _normal: Vector3::new(-slope_x, 1.0, -slope_z).normalize(),
+        _tangent: Vector3::new(1.0, slope_x, 0.0).normalize(),
+        _color: vulkan_context::get_color32(255, 255, 255, 255),
+        _texcoord: texcoord,
+    }
+}
+
+// the grid of the chunk with the quad step of the lod, then the skirts hanging down from the four borders
+fn create_chunk_geometry_create_info(
+    heightmap: &TerrainHeightmap,
+    settings: &TerrainSettings,
+    chunk_origin: (u32, u32),
+    quad_counts: (u32, u32),
+    lod_index: usize
+) -> GeometryCreateInfo {
+    let step = 1u32 << lod_index;
+    let get_offsets = |quad_count: u32| -> Vec<u32> {
+        let mut offsets: Vec<u32> = (0..quad_count).step_by(step as usize).collect();
+        offsets.push(quad_count);
+        offsets
+    };
+    let offsets_x = get_offsets(quad_counts.0);
+    let offsets_z = get_offsets(quad_counts.1);
+    let (column_count, row_count) = (offsets_x.len() as u32, offsets_z.len() as u32);
+    let mut vertex_datas: Vec<StaticVertexData> = Vec::new();
+    for offset_z in offsets_z.iter() {
+        for offset_x in offsets_x.iter() {
+            vertex_datas.push(get_terrain_vertex(heightmap, settings, (chunk_origin.0 + offset_x) as i32, (chunk_origin.1 + offset_z) as i32));
+        }
+    }
+    let mut indices: Vec<u32> = Vec::new();
+    for row in 0..(row_count - 1) {
+        for column in 0..(column_count - 1) {
+            let index = row * column_count + column;
+            let (index_right, index_down) = (index + 1, index + column_count);
+            indices.extend_from_slice(&[index, index_down, index_right, index_right, index_down, index_down + 1]);
+        }
+    }
+    let borders: [Vec<u32>; 4] = [
+        (0..column_count).collect(),
+        (0..column_count).map(|column| (row_count - 1) * column_count + column).collect(),
+        (0..row_count).map(|row| row * column_count).collect(),
+        (0..row_count).map(|row| row * column_count + column_count - 1).collect(),
+    ];
+    for border in borders.iter() {
+        let skirt_first_index = vertex_datas.len() as u32;
+        for index in border.iter() {
+            let mut skirt_vertex_data = vertex_datas[*index as usize].clone();
+            skirt_vertex_data._position.y -= settings._skirt_depth;
+            vertex_datas.push(skirt_vertex_data);
+        }
+        for i in 0..(border.len() - 1) {
+            let (index, index_next) = (border[i], border[i + 1]);
+            let (skirt_index, skirt_index_next) = (skirt_first_index + i as u32, skirt_first_index + i as u32 + 1);
+            indices.extend_from_slice(&[index, skirt_index, index_next, index_next, skirt_index, skirt_index_next]);
+        }
+    }
+    let positions: Vec<Vector3<f32>> = vertex_datas.iter().map(|vertex_data| vertex_data._position.clone()).collect();
+    GeometryCreateInfo {
+        _vertex_datas: vertex_datas,
+        _indices: indices,
+        _bounding_box: bounding_box::calc_bounding_box(&positions),
+        ..Default::default()
+    }
+}
+
+impl Terrain {
+    pub fn is_terrain_enabled(&self) -> bool {
+        self._settings._enable_terrain && false == self._chunks.is_empty()
+    }
+
+    // the chunks are built again when the geometry is changed, the caller waits for the device idle
+    pub fn set_terrain_settings(&mut self, renderer_data: &RendererData, resources: &Resources, terrain_settings: &TerrainSettings) {
+        let is_geometry_changed = self._settings.is_geometry_changed(terrain_settings);
+        self._settings = terrain_settings.clone();
+        self._descriptor_sets.clear();
+        if is_geometry_changed {
+            self.destroy_terrain_chunks(renderer_data);
+            if terrain_settings._enable_terrain {
+                self.create_terrain_chunks(renderer_data, resources);
+            }
+        }
+    }
+
+    pub fn set_terrain_render_settings(&mut self, renderer_data: &RendererData, render_settings: &TerrainRenderSettings) {
+        if self._render_settings != *render_settings {
+            self._render_settings = render_settings.clone();
+            self.destroy_framebuffer_and_descriptor_sets(renderer_data.get_device());
+        }
+    }
+
+    fn create_terrain_chunks(&mut self, renderer_data: &RendererData, resources: &Resources) {
+        let heightmap = match resources.get_terrain_heightmap(&self._settings._heightmap_name) {
+            Some(heightmap) if 2 <= heightmap._width && 2 <= heightmap._height => heightmap,
+            _ => {
+                log::error!("create_terrain_chunks: invalid heightmap {}", self._settings._heightmap_name);
+                return;
+            }
+        };
+        let quad_count_x = heightmap._width - 1;
+        let quad_count_z = heightmap._height - 1;
+        let chunk_count_x = (quad_count_x + TERRAIN_CHUNK_QUAD_COUNT - 1) / TERRAIN_CHUNK_QUAD_COUNT;
+        let chunk_count_z = (quad_count_z + TERRAIN_CHUNK_QUAD_COUNT - 1) / TERRAIN_CHUNK_QUAD_COUNT;
+        for chunk_z in 0..chunk_count_z {
+            for chunk_x in 0..chunk_count_x {
+                let chunk_origin = (chunk_x * TERRAIN_CHUNK_QUAD_COUNT, chunk_z * TERRAIN_CHUNK_QUAD_COUNT);
+                let quad_counts = (
+                    TERRAIN_CHUNK_QUAD_COUNT.min(quad_count_x - chunk_origin.0),
+                    TERRAIN_CHUNK_QUAD_COUNT.min(quad_count_z - chunk_origin.1)
+                );
+                let mut bounding_box = BoundingBox::default();
+                let lod_geometry_datas: Vec<GeometryData> = (0..TERRAIN_LOD_COUNT).map(|lod_index| {
+                    let geometry_create_info = create_chunk_geometry_create_info(&heightmap, &self._settings, chunk_origin, quad_counts, lod_index);
+                    if 0 == lod_index {
+                        bounding_box = geometry_create_info._bounding_box.clone();
+                    }
+                    let geometry_name = format!("terrain_{}_{}_lod{}", chunk_x, chunk_z, lod_index);
+                    renderer_data.create_geometry_buffer(&geometry_name, &geometry_create_info)
+                }).collect();
+                self._chunks.push(TerrainChunk {
+                    _bounding_box: bounding_box,
+                    _lod_geometry_datas: lod_geometry_datas,
+                });
+            }
+        }
+        self._lod_indices = vec![0; self._chunks.len()];
+        self._heightmap = Some(heightmap);
+        log::info!("create_terrain_chunks: {} {}x{} chunks", self._settings._heightmap_name, chunk_count_x, chunk_count_z);
+    }
+
+    pub fn destroy_terrain_chunks(&mut self, renderer_data: &RendererData) {
+        for chunk in self._chunks.drain(..) {
+            for geometry_data in chunk._lod_geometry_datas.iter() {
+                renderer_data.destroy_geomtry_buffer(geometry_data);
+            }
+        }
+        self._lod_indices.clear();
+        self._visible_chunk_indices.clear();
+        self._heightmap = None;
+    }
+
+    pub fn destroy_terrain_buffers(&mut self, device: &Device) {
+        if let Some(mut constants_buffer) = self._constants_buffer.take() {
+            buffer::destroy_shader_buffer_data(device, &mut constants_buffer);
+        }
+    }
+
+    pub fn destroy_framebuffer_and_descriptor_sets(&mut self, device: &Device) {
+        if let Some(framebuffer_data) = self._framebuffer_data.take() {
+            framebuffer::destroy_framebuffer_data(device, &framebuffer_data);
+        }
+        self._descriptor_sets.clear();
+    }
+
+    // the height of the lod 0 surface, None outside the terrain or without the heightmap.
+    // the triangles of the quads are interpolated, so the objects are placed on the rendered ground.
+    pub fn get_height(&self, world_xz: &Vector2<f32>) -> Option<f32> {
+        let heightmap = self._heightmap.as_ref()?;
+        let settings = &self._settings;
+        let u = (world_xz.x - settings._terrain_position.x) / settings._terrain_size.x;
+        let v = (world_xz.y - settings._terrain_position.z) / settings._terrain_size.y;
+        if false == (0.0 <= u && u <= 1.0 && 0.0 <= v && v <= 1.0) {
+            return None;
+        }
+        let sample_x = u * (heightmap._width - 1) as f32;
+        let sample_z = v * (heightmap._height - 1) as f32;
+        let (x, z) = (sample_x.floor().min((heightmap._width - 2) as f32), sample_z.floor().min((heightmap._height - 2) as f32));
+        let (fx, fz) = (sample_x - x, sample_z - z);
+        let (x, z) = (x as i32, z as i32);
+        // the diagonal of create_chunk_geometry_create_info is from (x + 1, z) to (x, z + 1)
+        let height = if fx + fz <= 1.0 {
+            let height00 = heightmap.get_sample(x, z);
+            height00 + (heightmap.get_sample(x + 1, z) - height00) * fx + (heightmap.get_sample(x, z + 1) - height00) * fz
+        } else {
+            let height11 = heightmap.get_sample(x + 1, z + 1);
+            height11 + (heightmap.get_sample(x, z + 1) - height11) * (1.0 - fx) + (heightmap.get_sample(x + 1, z) - height11) * (1.0 - fz)
+        };
+        Some(settings._terrain_position.y + height * settings._height_scale)
+    }
+
+    // the frustum culling and the lod selection of the main view, called by SceneManagerData::cull_scene
+    pub fn cull_terrain_chunks(&mut self, view_projection: &Matrix4<f32>, view_position: &Vector3<f32>) {
+        self._visible_chunk_indices.clear();
+        if false == self.is_terrain_enabled() {
+            return;
+        }
+        let frustum_planes = render_object_storage::get_frustum_planes(view_projection);
+        let hysteresis = mesh::get_mesh_lod_hysteresis();
+        let lod_distance = self._settings._lod_distance * mesh::get_mesh_lod_distance_scale();
+        for (i, chunk) in self._chunks.iter().enumerate() {
+            if render_object_storage::is_box_in_frustum(&frustum_planes, &chunk._bounding_box) {
+                let distance = get_distance_to_bounding_box(view_position, &chunk._bounding_box);
+                self._lod_indices[i] = select_terrain_lod(self._lod_indices[i], distance, lod_distance, hysteresis);
+                self._visible_chunk_indices.push(i as u32);
+            }
+        }
+    }
+
+    fn prepare_framebuffer_and_descriptor_sets(&mut self, renderer_data: &RendererData, resources: &Resources, color_targets: &[&TextureData], depth_target: &TextureData) {
+        if self._constants_buffer.is_none() {
+            self._constants_buffer = Some(buffer::create_shader_buffer_data(
+                renderer_data.get_device(),
+                renderer_data.get_device_memory_properties(),
+                &String::from(TERRAIN_CONSTANTS_BUFFER_NAME),
+                vk::BufferUsageFlags::UNIFORM_BUFFER,
+                std::mem::size_of::<TerrainConstants>() as vk::DeviceSize,
+                false,
+                false,
+                false,
+            ));
+        }
+        let render_pass_pipeline_data = resources.get_render_pass_pipeline_data(TERRAIN_RENDER_PASS_NAME, TERRAIN_RENDER_PASS_NAME);
+        if self._framebuffer_data.is_none() {
+            let render_pass_data = render_pass_pipeline_data._render_pass_data.borrow();
+            let color_render_target_infos: Vec<RenderTargetInfo> = color_targets.iter().map(|color_target| RenderTargetInfo {
+                _texture_data: color_target,
+                _target_layer: 0,
+                _target_mip_level: 0,
+                _clear_value: None,
+            }).collect();
+            let depth_render_target_info = RenderTargetInfo {
+                _texture_data: depth_target,
+                _target_layer: 0,
+                _target_mip_level: 0,
+                _clear_value: None,
+            };
+            self._framebuffer_data = Some(utility::create_framebuffers(
+                renderer_data.get_device(),
+                &render_pass_data,
+                TERRAIN_RENDER_PASS_NAME,
+                &color_render_target_infos,
+                &[depth_render_target_info],
+                &[]
+            ));
+        }
+        if false == self._descriptor_sets.is_empty() {
+            return;
+        }
+        let pipeline_data = render_pass_pipeline_data._pipeline_data.borrow();
+        let descriptor_data = &pipeline_data._descriptor_data;
+        let descriptor_binding_indices: Vec<u32> = descriptor_data._descriptor_data_create_infos.iter().map(|descriptor_data_create_info| {
+            descriptor_data_create_info._descriptor_binding_index
+        }).collect();
+        let mut texture_image_infos = vec![resources.get_texture_data(&self._settings._splat_map_name).borrow().get_default_image_info().clone()];
+        for layer_index in 0..TERRAIN_LAYER_COUNT {
+            texture_image_infos.push(resources.get_texture_data(self._settings.get_layer_texture_name(layer_index)).borrow().get_default_image_info().clone());
+        }
+        let descriptor_resource_infos_list: SwapchainArray<Vec<DescriptorResourceInfo>> = constants::SWAPCHAIN_IMAGE_INDICES.iter().map(|swapchain_index| {
+            let mut descriptor_resource_infos = vec![self._constants_buffer.as_ref().unwrap()._descriptor_buffer_infos[*swapchain_index].clone()];
+            descriptor_resource_infos.extend(texture_image_infos.iter().map(|image_info| DescriptorResourceInfo::DescriptorImageInfo(image_info.clone())));
+            descriptor_resource_infos
+        }).collect();
+        let descriptor_sets = descriptor::create_descriptor_sets(renderer_data.get_device(), descriptor_data, TERRAIN_RENDER_PASS_NAME);
+        let _write_descriptor_sets = descriptor::create_write_descriptor_sets_with_update(
+            renderer_data.get_device(),
+            &descriptor_sets,
+            &descriptor_binding_indices,
+            &descriptor_data._descriptor_set_layout_bindings,
+            &descriptor_resource_infos_list,
+        );
+        self._descriptor_sets = descriptor_sets;
+    }
+
+    // the visible chunks of cull_terrain_chunks with their lods into the gbuffer. the targets stay in GENERAL.
+    pub fn render_terrain(
+        &mut self,
+        command_buffer: vk::CommandBuffer,
+        swapchain_index: u32,
+        renderer_data: &RendererData,
+        resources: &Resources,
+        (color_targets, depth_target): (&[&TextureData], &TextureData),
+        camera: &CameraObjectData
+    ) {
+        if false == self.is_terrain_enabled() || self._visible_chunk_indices.is_empty() {
+            return;
+        }
+        self.prepare_framebuffer_and_descriptor_sets(renderer_data, resources, color_targets, depth_target);
+        let camera_position = camera.get_camera_position();
+        let camera_position_prev = camera.get_camera_position_prev();
+        let terrain_constants = TerrainConstants {
+            _view_origin_projection_jitter: camera._view_origin_projection_jitter.clone(),
+            _view_origin_projection: camera._view_origin_projection.clone(),
+            _view_origin_projection_prev: camera._view_origin_projection_prev.clone(),
+            _camera_position: Vector4::new(camera_position.x, camera_position.y, camera_position.z, 1.0),
+            _camera_position_prev: Vector4::new(camera_position_prev.x, camera_position_prev.y, camera_position_prev.z, 1.0),
+            _layer_tilings: self._settings._layer_tilings.clone(),
+            _layer_roughnesses: self._settings._layer_roughnesses.clone(),
+        };
+        renderer_data.upload_shader_buffer_data(command_buffer, swapchain_index, self._constants_buffer.as_ref().unwrap(), &terrain_constants);
+
+        let render_pass_pipeline_data = resources.get_render_pass_pipeline_data(TERRAIN_RENDER_PASS_NAME, TERRAIN_RENDER_PASS_NAME);
+        let render_pass_data = render_pass_pipeline_data._render_pass_data.borrow();
+        let pipeline_data = render_pass_pipeline_data._pipeline_data.borrow();
+        renderer_data.begin_debug_label(command_buffer, TERRAIN_RENDER_PASS_NAME);
+        renderer_data.begin_render_pass_pipeline(command_buffer, swapchain_index, &render_pass_data, &pipeline_data, self._framebuffer_data.as_ref());
+        unsafe {
+            renderer_data.get_device().cmd_bind_descriptor_sets(
+                command_buffer,
+                pipeline_data._pipeline_bind_point,
+                pipeline_data._pipeline_layout,
+                0,
+                &[self._descriptor_sets[swapchain_index as usize]],
+                &[]
+            );
+        }
+        for chunk_index in self._visible_chunk_indices.iter() {
+            let chunk_index = *chunk_index as usize;
+            renderer_data.draw_elements(command_buffer, &self._chunks[chunk_index]._lod_geometry_datas[self._lod_indices[chunk_index]]);
+        }
+        renderer_data.end_render_pass(command_buffer);
+        renderer_data.end_debug_label(command_buffer);
+    }
+}
@@ -0,0 +1,213 @@
+use std::fs;
+use std::path::PathBuf;
+
+use ash::vk;
+
+use crate::constants;
+use crate::vulkan_context::shader::SHADER_DIRECTORY;
+
+pub const TONEMAP_OPERATOR_NONE: u32 = 0; // must match with tonemap.glsl
+pub const TONEMAP_OPERATOR_REINHARD: u32 = 1; // must match with tonemap.glsl
+pub const TONEMAP_OPERATOR_ACES: u32 = 2; // must match with tonemap.glsl
+pub const TONEMAP_OPERATOR_UNCHARTED2: u32 = 3; // must match with tonemap.glsl
+pub const OUTPUT_COLOR_SPACE_SRGB: u32 = 0; // must match with tonemap.glsl
+pub const OUTPUT_COLOR_SPACE_HDR10: u32 = 1; // must match with tonemap.glsl
+pub const OUTPUT_COLOR_SPACE_SCRGB: u32 = 2; // must match with tonemap.glsl
+pub const MIN_EXPOSURE: f32 = 1.0 / 64.0;
+pub const MAX_EXPOSURE: f32 = 64.0;
+pub const EXPOSURE_STEP: f32 = 1.25; // the exposure key multiplies or divides by this
+pub const HDR10_PAPER_WHITE_NITS: f32 = 200.0;
+pub const TONEMAP_SHADER_FILE: &str = "common/tonemap.glsl";
+// the final pass of the project includes this and applies apply_tonemap with PushConstant_RenderFinal
+pub const TONEMAP_SHADER_SOURCE: &str = r#"#ifndef TONEMAP_GLSL
+#define TONEMAP_GLSL
+
+#define TONEMAP_OPERATOR_NONE 0
+#define TONEMAP_OPERATOR_REINHARD 1
+#define TONEMAP_OPERATOR_ACES 2
+#define TONEMAP_OPERATOR_UNCHARTED2 3
+#define OUTPUT_COLOR_SPACE_SRGB 0
+#define OUTPUT_COLOR_SPACE_HDR10 1
+#define OUTPUT_COLOR_SPACE_SCRGB 2
+
+vec3 tonemap_aces(vec3 color)
+{
+    const float a = 2.51;
+    const float b = 0.03;
+    const float c = 2.43;
+    const float d = 0.59;
+    const float e = 0.14;
+    return clamp((color * (a * color + b)) / (color * (c * color + d) + e), 0.0, 1.0);
+}
+
+vec3 uncharted2_curve(vec3 x)
+{
+    const float A = 0.15;
+    const float B = 0.50;
+    const float C = 0.10;
+    const float D = 0.20;
+    const float E = 0.02;
+    const float F = 0.30;
+    return ((x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F)) - E / F;
+}
+
+vec3 tonemap_uncharted2(vec3 color)
+{
+    const float W = 11.2;
+    return uncharted2_curve(color * 2.0) / uncharted2_curve(vec3(W));
+}
+
+vec3 linear_to_pq(vec3 color, float paper_white_nits)
+{
+    const float m1 = 0.1593017578125;
+    const float m2 = 78.84375;
+    const float c1 = 0.8359375;
+    const float c2 = 18.8515625;
+    const float c3 = 18.6875;
+    const mat3 rec709_to_rec2020 = mat3(
+        0.6274, 0.0691, 0.0164,
+        0.3293, 0.9195, 0.0880,
+        0.0433, 0.0114, 0.8956
+    );
+    vec3 y = pow(max(vec3(0.0), rec709_to_rec2020 * color) * (paper_white_nits / 10000.0), vec3(m1));
+    return pow((c1 + c2 * y) / (1.0 + c3 * y), vec3(m2));
+}
+
+// color: the linear scene color, the result is written to the swapchain image
+vec3 apply_tonemap(vec3 color, uint tonemap_operator, float exposure, float gamma, uint output_color_space, float paper_white_nits)
+{
+    color *= exposure;
+    if(TONEMAP_OPERATOR_REINHARD == tonemap_operator)
+    {
+        color = color / (1.0 + color);
+    }
+    else if(TONEMAP_OPERATOR_ACES == tonemap_operator)
+    {
+        color = tonemap_aces(color);
+    }
+    else if(TONEMAP_OPERATOR_UNCHARTED2 == tonemap_operator)
+    {
+        color = tonemap_uncharted2(color);
+    }
+
+    if(OUTPUT_COLOR_SPACE_HDR10 == output_color_space)
+    {
+        return linear_to_pq(color, paper_white_nits);
+    }
+    else if(OUTPUT_COLOR_SPACE_SCRGB == output_color_space)
+    {
+        // 1.0 is 80 nits
+        return color * (paper_white_nits / 80.0);
+    }
+    // the srgb swapchain format encodes the gamma, 1.0: no gamma in the shader
+    return pow(max(vec3(0.0), color), vec3(1.0 / gamma));
+}
+
+#endif // TONEMAP_GLSL
+"#;
+
+#[derive(Clone, Debug, Copy, PartialEq, Eq)]
+pub enum TonemapOperator {
+    None,
+    Reinhard,
+    ACES,
+    Uncharted2,
+}
+
+impl TonemapOperator {
+    pub fn get_next_tonemap_operator(&self) -> TonemapOperator {
+        match self {
+            TonemapOperator::None => TonemapOperator::Reinhard,
+            TonemapOperator::Reinhard => TonemapOperator::ACES,
+            TonemapOperator::ACES => TonemapOperator::Uncharted2,
+            TonemapOperator::Uncharted2 => TonemapOperator::None,
+        }
+    }
+
+    pub fn to_shader_operator(&self) -> u32 {
+        match self {
+            TonemapOperator::None => TONEMAP_OPERATOR_NONE,
+            TonemapOperator::Reinhard => TONEMAP_OPERATOR_REINHARD,
+            TonemapOperator::ACES => TONEMAP_OPERATOR_ACES,
+            TonemapOperator::Uncharted2 => TONEMAP_OPERATOR_UNCHARTED2,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Copy, PartialEq)]
+pub struct TonemapSettings {
+    pub _operator: TonemapOperator,
+    pub _exposure: f32, // multiplied before the tonemap, after the auto exposure of the post process
+    pub _gamma: f32, // 1.0 with the srgb swapchain formats
+}
+
+impl Default for TonemapSettings {
+    fn default() -> TonemapSettings {
+        unsafe {
+            TonemapSettings {
+                _operator: constants::TONEMAP_OPERATOR,
+                _exposure: constants::TONEMAP_EXPOSURE,
+                _gamma: constants::TONEMAP_GAMMA,
+            }
+        }
+    }
+}
+
+// must match with the final pass of the project, see tonemap.glsl
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Default)]
+#[repr(C)]
+pub struct PushConstant_RenderFinal {
+    pub _tonemap_operator: u32,
+    pub _exposure: f32,
+    pub _gamma: f32,
+    pub _output_color_space: u32,
+    pub _paper_white_nits: f32,
+    pub _reserved0: u32,
+    pub _reserved1: u32,
+    pub _reserved2: u32,
+}
+
+crate::impl_push_constant!(PushConstant_RenderFinal, 32);
+
+// the hdr swapchain skips the sdr tonemap, the scene color is encoded for the display by the color space.
+pub fn create_push_constant_render_final(tonemap_settings: &TonemapSettings, color_space: vk::ColorSpaceKHR) -> PushConstant_RenderFinal {
+    let output_color_space = get_output_color_space(color_space);
+    let is_hdr_output = OUTPUT_COLOR_SPACE_SRGB != output_color_space;
+    PushConstant_RenderFinal {
+        _tonemap_operator: if is_hdr_output { TONEMAP_OPERATOR_NONE } else { tonemap_settings._operator.to_shader_operator() },
+        _exposure: tonemap_settings._exposure,
+        _gamma: if is_hdr_output { 1.0 } else { tonemap_settings._gamma },
+        _output_color_space: output_color_space,
+        _paper_white_nits: HDR10_PAPER_WHITE_NITS,
+        ..Default::default()
+    }
+}
+
+pub fn get_output_color_space(color_space: vk::ColorSpaceKHR) -> u32 {
+    match color_space {
+        vk::ColorSpaceKHR::HDR10_ST2084_EXT => OUTPUT_COLOR_SPACE_HDR10,
+        vk::ColorSpaceKHR::EXTENDED_SRGB_LINEAR_EXT => OUTPUT_COLOR_SPACE_SCRGB,
+        _ => OUTPUT_COLOR_SPACE_SRGB,
+    }
+}
+
+// the push constant range of the render_final pipeline
+pub fn get_push_constant_range_render_final() -> vk::PushConstantRange {
+    vk::PushConstantRange {
+        stage_flags: vk::ShaderStageFlags::FRAGMENT,
+        offset: 0,
+        size: std::mem::size_of::<PushConstant_RenderFinal>() as u32,
+    }
+}
+
+#[cfg(not(target_os = "android"))]
+pub fn generate_tonemap_shader() {
+    let mut shader_file_path = PathBuf::from(SHADER_DIRECTORY);
+    shader_file_path.push(TONEMAP_SHADER_FILE);
+    if false == shader_file_path.is_file() {
+        fs::create_dir_all(shader_file_path.parent().unwrap()).expect("Failed to create directories.");
+        fs::write(&shader_file_path, TONEMAP_SHADER_SOURCE).expect("Failed to write");
+        log::info!("generate_tonemap_shader: {:?}", shader_file_path);
+    }
+}
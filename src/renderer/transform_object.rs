@@ -8,6 +8,7 @@ use nalgebra::{
 use crate::utilities::math::{
     TWO_PI,
     make_rotation_matrix,
+    interpolate_matrix,
     combinate_matrix,
     extract_location,
     extract_scale,
@@ -41,6 +42,7 @@ pub struct TransformObjectData {
     pub _inverse_matrix_store: Matrix4<f32>,
     pub _prev_matrix: Matrix4<f32>,
     pub _prev_inverse_matrix: Matrix4<f32>,
+    pub _step_matrix: Matrix4<f32>, // the matrix of the previous simulation step, see get_interpolated_matrix
 }
 
 impl TransformObjectData {
@@ -69,6 +71,7 @@ impl TransformObjectData {
             _inverse_matrix_store: Matrix4::identity(),
             _prev_matrix: Matrix4::identity(),
             _prev_inverse_matrix: Matrix4::identity(),
+            _step_matrix: Matrix4::identity(),
         }
     }
     pub fn get_matrix(&self) -> &Matrix4<f32> { &self._matrix }
@@ -217,6 +220,20 @@ impl TransformObjectData {
         updated
     }
 
+    // before the simulation step
+    pub fn store_step_matrix(&mut self) {
+        self._step_matrix.copy_from(&self._matrix);
+    }
+
+    // alpha: the accumulated time of the fixed step, 0.0 is the previous step and 1.0 is the current step
+    pub fn get_interpolated_matrix(&self, alpha: f32) -> Matrix4<f32> {
+        if 1.0 <= alpha || self._step_matrix == self._matrix {
+            self._matrix.clone()
+        } else {
+            interpolate_matrix(&self._step_matrix, &self._matrix, alpha.max(0.0))
+        }
+    }
+
     pub fn set_position_rotation_scale(&mut self, matrix: &Matrix4<f32>) {
         self.set_position(&extract_location(matrix));
         self.set_rotation(&matrix_decompose_pitch_yaw_roll(matrix));
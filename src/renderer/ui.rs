@@ -2,10 +2,13 @@ use std::boxed::Box;
 use serde::{ Serialize, Deserialize };
 use nalgebra::{ Vector2, Vector3, Vector4, Matrix4 };
 use ash::{ vk, Device };
+use winit::event::VirtualKeyCode;
 
 use crate::constants;
 use crate::application::application::TimeData;
+use crate::application::haptics::{ self, HapticsEvent };
 use crate::application::input::{
+    self,
     KeyboardInputData,
     MouseMoveData,
     MouseInputData,
@@ -17,17 +20,31 @@ use crate::renderer::renderer::{ RendererData };
 use crate::renderer::transform_object::TransformObjectData;
 use crate::utilities::system::{ self, RcRefCell };
 use crate::vulkan_context::buffer::{ self, BufferData };
+use crate::vulkan_context::framebuffer::FramebufferData;
 use crate::vulkan_context::geometry_buffer::{ self, VertexData };
 use crate::vulkan_context::render_pass::{ PipelineData };
-use crate::vulkan_context::vulkan_context::{ get_color32 };
+use crate::vulkan_context::vulkan_context::{ self, get_color32 };
 
 pub const UI_RENDER_FONT_PADDING_RATIO: f32 = 0.7;
+pub const UI_TEXT_INPUT_CURSOR_BLINK_TIME: f32 = 0.5; // seconds, on and off
+pub const UI_TEXT_INPUT_CURSOR_CHAR: u8 = b'|';
 
 pub const UI_RENDER_FLAG_NONE: u32 = 0;
 pub const UI_RENDER_FLAG_RENDER_TEXT: u32 = 1 << 0;
 pub const UI_RENDER_FLAG_RENDER_TEXTURE: u32 = 1 << 1;
 pub const UI_RENDER_FLAG_TOUCHED: u32 = 1 << 2;
 
+pub const UI_TEST_PATTERN_RAMP_STEPS: u32 = 16;
+pub const UI_TEST_PATTERN_COLOR_BARS: [u32; 8] = [
+    0xFFFFFFFF, 0xFF00FFFF, 0xFFFFFF00, 0xFF00FF00, 0xFFFF00FF, 0xFF0000FF, 0xFFFF0000, 0xFF000000
+]; // white, yellow, cyan, green, magenta, red, blue, black (ABGR)
+pub const UI_DEBUG_HISTOGRAM_SIZE: (f32, f32) = (512.0, 160.0);
+pub const UI_DEBUG_HISTOGRAM_MARGIN: f32 = 10.0;
+pub const UI_DEBUG_HISTOGRAM_BACKGROUND_COLOR: u32 = 0xA0000000; // ABGR
+pub const UI_DEBUG_HISTOGRAM_BAR_COLOR: u32 = 0xFFFFFFFF;
+pub const UI_RENDER_ERROR_BANNER_HEIGHT: f32 = 24.0;
+pub const UI_RENDER_ERROR_BANNER_COLOR: u32 = 0xC00000FF; // ABGR
+
 pub const UI_INDEX_LEFT: usize = 0; // x
 pub const UI_INDEX_TOP: usize = 1; // y
 pub const UI_INDEX_RIGHT: usize = 2; // z
@@ -42,15 +59,19 @@ pub const DEFAILT_VERTICAL_ALIGN: VerticalAlign = VerticalAlign::TOP;
 
 #[allow(non_camel_case_types)]
 #[derive(Debug, Clone)]
+#[repr(C)]
 pub struct PushConstant_RenderUI {
     pub _inv_canvas_size: Vector2<f32>,
     pub _instance_id_offset: u32,
-    pub _reserved0: u32,
+    pub _is_srgb_target: u32, // ui colors are authored in srgb, linearize them in the shader when the target encodes srgb on write.
 }
 
+crate::impl_push_constant!(PushConstant_RenderUI, 16);
+
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum UIWidgetTypes {
-    Default
+    Default,
+    TextInput,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
@@ -67,6 +88,25 @@ pub enum VerticalAlign {
     TOP,
 }
 
+// the anchor of a child of the float layout in the contents area of the parent, the pivot of the child is the same relative point.
+// ex) RIGHT: _pos.x is the offset from the right side of the parent to the right side of the child
+// STRETCH fills the parent along the axis and the margines are the insets, a box layout stretches only the cross axis.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum HorizontalAnchor {
+    LEFT,
+    CENTER,
+    RIGHT,
+    STRETCH,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum VerticalAnchor {
+    TOP,
+    CENTER,
+    BOTTOM,
+    STRETCH,
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum Orientation {
     HORIZONTAL,
@@ -148,6 +188,9 @@ pub struct UIComponentData {
     pub _pos_hint_y: Option<f32>,
     pub _size_hint_x: Option<f32>,
     pub _size_hint_y: Option<f32>,
+    pub _anchor_x: Option<HorizontalAnchor>, // None: _pos_hint_x or _pos_x
+    pub _anchor_y: Option<VerticalAnchor>, // None: _pos_hint_y or _pos_y
+    pub _min_size: Vector2<f32>, // the minimum ui size including the margines, after the size hint and the stretch
     pub _padding: Vector4<f32>,
     pub _margine: Vector4<f32>,
     pub _texcoord: Vector4<f32>,
@@ -203,6 +246,9 @@ pub struct UIComponentInstance {
     pub _touched_offset: Vector2<f32>,
     pub _text: String,
     pub _render_text_count: u32,
+    pub _is_focused: bool, // the text input of the has_cursor component, click to focus and Escape / Tab to unfocus
+    pub _cursor_index: usize, // byte index of the caret in _text
+    pub _cursor_blink_time: f32,
     pub _callback_touch_down: Option<*const fn(widget: *const dyn Widget)>,
     pub _callback_touch_move: Option<*const fn(widget: *const dyn Widget)>,
     pub _callback_touch_up: Option<*const fn(widget: *const dyn Widget)>,
@@ -237,6 +283,10 @@ pub struct UIManagerData {
     pub _render_ui_count: u32,
     pub _render_ui_group: Vec<UIRenderGroupData>,
     pub _default_render_ui_material: Option<RcRefCell<MaterialInstanceData>>,
+    pub _render_ui_framebuffer_data: Option<RcRefCell<FramebufferData>>, // acquired with the graphics data, recreated with the swapchain
+    pub _debug_histogram_bars: Vec<f32>, // normalized bar heights of the debug histogram, empty when hidden
+    pub _render_error_banner: bool,
+    pub _is_text_input_focused: bool, // a focused text input of the last update
 }
 
 //////////////////////////////////////////
@@ -256,6 +306,9 @@ impl Default for UIComponentData {
             _pos_hint_y: None,
             _size_hint_x: None,
             _size_hint_y: None,
+            _anchor_x: None,
+            _anchor_y: None,
+            _min_size: Vector2::zeros(),
             _padding: Vector4::zeros(),
             _margine: Vector4::zeros(),
             _round: 0.0,
@@ -315,6 +368,9 @@ impl UIComponentInstance {
             _text: String::new(),
             _text_counts: Vec::new(),
             _render_text_count: 0,
+            _is_focused: false,
+            _cursor_index: 0,
+            _cursor_blink_time: 0.0,
             _callback_touch_down: None,
             _callback_touch_move: None,
             _callback_touch_up: None,
@@ -371,6 +427,9 @@ impl UIComponentInstance {
     pub fn on_touch_up(&mut self, touched_pos: &Vector2<f32>) {
         if self._touched {
             self._touched = false;
+            if self._callback_touch_up.is_some() {
+                haptics::post_haptics_event(HapticsEvent::UIButtonClick, 1.0);
+            }
             if self.get_dragable() {
                 self.set_pos(touched_pos.x + self._touched_offset.x, touched_pos.y + self._touched_offset.y);
                 if let Some(callback_touch_up) = self._callback_touch_up.as_ref() {
@@ -391,9 +450,28 @@ impl UIComponentInstance {
     pub fn get_layout_type(&self) -> UILayoutType {
         self._ui_component_data._layout_type
     }
-    pub fn set_layout_type(&mut self, layout_type: UILayoutType) { self._ui_component_data._layout_type = layout_type; }
+    pub fn set_layout_type(&mut self, layout_type: UILayoutType) {
+        if layout_type != self._ui_component_data._layout_type {
+            self._ui_component_data._layout_type = layout_type;
+            self.set_changed_layout(true);
+        }
+    }
     pub fn get_layout_orientation(&self) -> Orientation { self._ui_component_data._layout_orientation }
-    pub fn set_layout_orientation(&mut self, layout_orientation: Orientation) { self._ui_component_data._layout_orientation = layout_orientation; }
+    pub fn set_layout_orientation(&mut self, layout_orientation: Orientation) {
+        if layout_orientation != self._ui_component_data._layout_orientation {
+            self._ui_component_data._layout_orientation = layout_orientation;
+            self.set_changed_layout(true);
+        }
+    }
+    // the children are stacked from the top, a nested box is a child with its own box layout
+    pub fn set_vertical_box(&mut self) {
+        self.set_layout_type(UILayoutType::BoxLayout);
+        self.set_layout_orientation(Orientation::VERTICAL);
+    }
+    pub fn set_horizontal_box(&mut self) {
+        self.set_layout_type(UILayoutType::BoxLayout);
+        self.set_layout_orientation(Orientation::HORIZONTAL);
+    }
     pub fn get_pos_x(&self) -> f32 { self._ui_component_data._pos.x }
     pub fn get_pos_y(&self) -> f32 { self._ui_component_data._pos.y }
     pub fn get_pos(&self) -> &Vector2<f32> { &self._ui_component_data._pos }
@@ -491,6 +569,42 @@ impl UIComponentInstance {
             self.set_changed_layout(true);
         }
     }
+    pub fn get_anchor_x(&self) -> Option<HorizontalAnchor> { self._ui_component_data._anchor_x }
+    pub fn get_anchor_y(&self) -> Option<VerticalAnchor> { self._ui_component_data._anchor_y }
+    pub fn set_anchor(&mut self, anchor_x: Option<HorizontalAnchor>, anchor_y: Option<VerticalAnchor>) {
+        self.set_anchor_x(anchor_x);
+        self.set_anchor_y(anchor_y);
+    }
+    pub fn set_anchor_x(&mut self, anchor_x: Option<HorizontalAnchor>) {
+        if anchor_x != self._ui_component_data._anchor_x {
+            self._ui_component_data._anchor_x = anchor_x;
+            self.set_changed_layout(true);
+        }
+    }
+    pub fn set_anchor_y(&mut self, anchor_y: Option<VerticalAnchor>) {
+        if anchor_y != self._ui_component_data._anchor_y {
+            self._ui_component_data._anchor_y = anchor_y;
+            self.set_changed_layout(true);
+        }
+    }
+    pub fn get_min_size(&self) -> &Vector2<f32> { &self._ui_component_data._min_size }
+    pub fn set_min_size(&mut self, min_size_x: f32, min_size_y: f32) {
+        let min_size = Vector2::new(min_size_x, min_size_y);
+        if min_size != self._ui_component_data._min_size {
+            self._ui_component_data._min_size = min_size;
+            self.set_changed_layout(true);
+        }
+    }
+    // a box layout stacks along its orientation, so only the cross axis stretches
+    fn get_stretch(&self, parent_layout_type: UILayoutType, parent_layout_orientation: Orientation) -> (bool, bool) {
+        let stretch_x = Some(HorizontalAnchor::STRETCH) == self.get_anchor_x();
+        let stretch_y = Some(VerticalAnchor::STRETCH) == self.get_anchor_y();
+        match (parent_layout_type, parent_layout_orientation) {
+            (UILayoutType::FloatLayout, _) => (stretch_x, stretch_y),
+            (UILayoutType::BoxLayout, Orientation::HORIZONTAL) => (false, stretch_y),
+            (UILayoutType::BoxLayout, Orientation::VERTICAL) => (stretch_x, false),
+        }
+    }
     pub fn set_margine(&mut self, margine: f32) { self.set_margines(Vector4::new(margine, margine, margine, margine)); }
     pub fn set_margines(&mut self, margine: Vector4<f32>) {
         if margine != self._ui_component_data._margine {
@@ -694,6 +808,43 @@ impl UIComponentInstance {
         }
     }
 
+    pub fn get_focused(&self) -> bool { self._is_focused }
+    pub fn set_focused(&mut self, is_focused: bool) {
+        if is_focused != self._is_focused {
+            self._is_focused = is_focused;
+            self._cursor_index = self._text.len();
+            self._cursor_blink_time = 0.0;
+            self._changed_text = true;
+        }
+    }
+    pub fn get_cursor_index(&self) -> usize { self._cursor_index }
+    pub fn is_cursor_visible(&self) -> bool {
+        self._is_focused && (self._cursor_blink_time / UI_TEXT_INPUT_CURSOR_BLINK_TIME) as u32 % 2 == 0
+    }
+
+    // Escape or Tab releases the focus
+    fn update_text_input(&mut self, delta_time: f32, keyboard_input_data: &KeyboardInputData) {
+        if keyboard_input_data.get_key_repeat(VirtualKeyCode::Escape) || keyboard_input_data.get_key_repeat(VirtualKeyCode::Tab) {
+            self.set_focused(false);
+            return;
+        }
+
+        let mut text = self._text.clone();
+        let mut cursor_index = self._cursor_index;
+        if input::edit_text_input(&mut text, &mut cursor_index, keyboard_input_data) {
+            self.set_text(&text);
+            self._cursor_index = cursor_index;
+            self._cursor_blink_time = 0.0;
+            self._changed_text = true;
+        } else {
+            let is_cursor_visible = self.is_cursor_visible();
+            self._cursor_blink_time += delta_time;
+            if is_cursor_visible != self.is_cursor_visible() {
+                self._changed_text = true;
+            }
+        }
+    }
+
     pub fn compute_text_contents_size(&mut self, font_data: &FontData) -> Vector2<f32>{
         self._text_counts.clear();
 
@@ -763,10 +914,12 @@ impl UIComponentInstance {
                 HorizontalAlign::RIGHT => contents_area_x + contents_area_size_x - text_render_size_x,
             }
         };
-        let mut text_render_area_x: f32 = get_text_render_area_x(self.get_halign(), self._contents_area_size.x, self._contents_area.x, self._text_counts[0]);
+        let text_counts = &self._text_counts;
+        let get_text_count = |row: usize| -> usize { text_counts.get(row).cloned().unwrap_or(0) };
+        let mut text_render_area_x: f32 = get_text_render_area_x(self.get_halign(), self._contents_area_size.x, self._contents_area.x, get_text_count(0));
 
         // text_render_area_y
-        let row_count = self._text_counts.len();
+        let row_count = self._text_counts.len().max(1);
         let text_render_size_y = row_count as f32 * font_size.y;
         let text_render_area_y: f32 = match self.get_valign() {
             VerticalAlign::TOP => self._contents_area.y,
@@ -780,7 +933,7 @@ impl UIComponentInstance {
             if '\n' == ch {
                 column = 0;
                 row += 1;
-                text_render_area_x = get_text_render_area_x(self.get_halign(), self._contents_area_size.x, self._contents_area.x, self._text_counts[row as usize]);
+                text_render_area_x = get_text_render_area_x(self.get_halign(), self._contents_area_size.x, self._contents_area.x, get_text_count(row as usize));
             } else if '\t' == ch {
                 column += 4;
             } else if ' ' == ch {
@@ -817,6 +970,40 @@ impl UIComponentInstance {
                 column += 1;
             }
         }
+
+        // caret, centered on the boundary of the chars
+        if self.is_cursor_visible() && (render_ui_index as usize) < render_ui_instance_datas.len() {
+            let mut cursor_column: i32 = 0;
+            let mut cursor_row: usize = 0;
+            for c in self._text.as_bytes()[..self._cursor_index.min(self._text.len())].iter() {
+                match (*c) as char {
+                    '\n' => {
+                        cursor_column = 0;
+                        cursor_row += 1;
+                    },
+                    '\t' => cursor_column += 4,
+                    _ => cursor_column += 1,
+                }
+            }
+            let index: u32 = 0i32.max(UI_TEXT_INPUT_CURSOR_CHAR as i32 - font_data._range_min as i32) as u32;
+            let texcoord_x = (index % count_of_side) as f32 * inv_count_of_side;
+            let texcoord_y = (index / count_of_side) as f32 * inv_count_of_side;
+            ui_render_area.x = get_text_render_area_x(self.get_halign(), self._contents_area_size.x, self._contents_area.x, get_text_count(cursor_row)) + (cursor_column as f32 - 0.5) * font_size.x;
+            ui_render_area.y = text_render_area_y + cursor_row as f32 * font_size.y;
+            ui_render_area.z = ui_render_area.x + font_size.x;
+            ui_render_area.w = ui_render_area.y + font_size.y;
+            let render_ui_instance_data = &mut render_ui_instance_datas[render_ui_index as usize];
+            render_ui_instance_data._ui_texcoord = Vector4::new(texcoord_x, texcoord_y, texcoord_x + inv_count_of_side, texcoord_y + inv_count_of_side);
+            render_ui_instance_data._ui_render_area = ui_render_area;
+            render_ui_instance_data._ui_renderable_area.clone_from(&text_renderable_area);
+            render_ui_instance_data._ui_color = self.get_font_color();
+            render_ui_instance_data._ui_round = 0.0;
+            render_ui_instance_data._ui_border = 0.0;
+            render_ui_instance_data._ui_border_color = 0;
+            render_ui_instance_data._ui_render_flags = UI_RENDER_FLAG_RENDER_TEXT;
+            render_ui_instance_data._ui_opacity = opacity;
+            self._render_text_count += 1;
+        }
     }
 
     pub fn collect_ui_render_data(
@@ -873,7 +1060,7 @@ impl UIComponentInstance {
 
             // collect font render data
             if need_to_collect_render_data || self._changed_text {
-                if self._text.is_empty() {
+                if self._text.is_empty() && false == self.is_cursor_visible() {
                     self._render_text_count = 0;
                 } else {
                     self.collect_ui_font_render_data(
@@ -907,7 +1094,14 @@ impl UIComponentInstance {
         }
     }
 
-    fn update_layout_size(&mut self, mut inherit_changed_layout: bool, parent_contents_size: &Vector2<f32>, font_data: &FontData) {
+    fn update_layout_size(
+        &mut self,
+        mut inherit_changed_layout: bool,
+        parent_layout_type: UILayoutType,
+        parent_layout_orientation: Orientation,
+        parent_contents_size: &Vector2<f32>,
+        font_data: &FontData
+    ) {
         if inherit_changed_layout || self._changed_layout {
             inherit_changed_layout = true;
 
@@ -922,6 +1116,13 @@ impl UIComponentInstance {
             if size_hint_y.is_some() {
                 ui_size.y = parent_contents_size.y * size_hint_y.unwrap();
             }
+            let (stretch_x, stretch_y) = self.get_stretch(parent_layout_type, parent_layout_orientation);
+            if stretch_x {
+                ui_size.x = parent_contents_size.x;
+            }
+            if stretch_y {
+                ui_size.y = parent_contents_size.y;
+            }
 
             // update contents area
             if self._changed_text {
@@ -936,6 +1137,8 @@ impl UIComponentInstance {
             if self.get_expandable_y() {
                 ui_size.y = ui_size.y.max(self._text_contents_size.y + spaces.y + spaces.w);
             }
+            ui_size.x = ui_size.x.max(self.get_min_size().x);
+            ui_size.y = ui_size.y.max(self.get_min_size().y);
 
             self._spaces.clone_from(&spaces);
             self._ui_size.clone_from(&ui_size);
@@ -948,7 +1151,7 @@ impl UIComponentInstance {
             let mut required_contents_size = Vector2::<f32>::zeros();
             for child in self._children.iter() {
                 let child_ui_instance = unsafe { child.as_mut().unwrap() };
-                child_ui_instance.update_layout_size(inherit_changed_layout, &self._contents_area_size, font_data);
+                child_ui_instance.update_layout_size(inherit_changed_layout, self.get_layout_type(), self.get_layout_orientation(), &self._contents_area_size, font_data);
 
                 // accumulate required_contents_size
                 if UILayoutType::BoxLayout == self.get_layout_type() {
@@ -995,12 +1198,26 @@ impl UIComponentInstance {
             UILayoutType::FloatLayout => {
                 let pos_hint_x = self.get_pos_hint_x();
                 let pos_hint_y = self.get_pos_hint_y();
-                if pos_hint_x.is_some() {
+                if let Some(anchor_x) = self.get_anchor_x() {
+                    self._ui_area.x = parent_contents_area.x + match anchor_x {
+                        HorizontalAnchor::LEFT => self.get_pos_x(),
+                        HorizontalAnchor::CENTER => (parent_contents_area_size.x - self._ui_size.x) * 0.5 + self.get_pos_x(),
+                        HorizontalAnchor::RIGHT => parent_contents_area_size.x - self._ui_size.x + self.get_pos_x(),
+                        HorizontalAnchor::STRETCH => 0.0,
+                    };
+                } else if pos_hint_x.is_some() {
                     self._ui_area.x = parent_contents_area.x + parent_contents_area_size.x * pos_hint_x.unwrap();
                 } else {
                     self._ui_area.x = parent_contents_area.x + self.get_pos_x();
                 }
-                if pos_hint_y.is_some() {
+                if let Some(anchor_y) = self.get_anchor_y() {
+                    self._ui_area.y = parent_contents_area.y + match anchor_y {
+                        VerticalAnchor::TOP => self.get_pos_y(),
+                        VerticalAnchor::CENTER => (parent_contents_area_size.y - self._ui_size.y) * 0.5 + self.get_pos_y(),
+                        VerticalAnchor::BOTTOM => parent_contents_area_size.y - self._ui_size.y + self.get_pos_y(),
+                        VerticalAnchor::STRETCH => 0.0,
+                    };
+                } else if pos_hint_y.is_some() {
                     self._ui_area.y = parent_contents_area.y + parent_contents_area_size.y * pos_hint_y.unwrap();
                 } else {
                     self._ui_area.y = parent_contents_area.y + self.get_pos_y();
@@ -1138,6 +1355,7 @@ impl UIComponentInstance {
         mouse_moved: bool,
         mouse_input_data: &MouseInputData,
         touch_event: &mut bool,
+        text_input_focused: &mut bool,
     ) {
         let mut child_index: isize = self._children.len() as isize - 1;
         while 0 <= child_index {
@@ -1151,6 +1369,7 @@ impl UIComponentInstance {
                 mouse_moved,
                 mouse_input_data,
                 touch_event,
+                text_input_focused,
             );
             if child_ui_instance.get_changed_layout() {
                 self._changed_child_layout = true;
@@ -1169,21 +1388,28 @@ impl UIComponentInstance {
                     }
                 } else {
                     self.on_touch_up(mouse_pos);
-                    if false == self.get_has_cursor() {
-                        //self._viewport_manager.focused_widget = None;
-                    }
                 }
             }
             else if mouse_input_data._btn_l_pressed {
                 if self.check_collide(mouse_pos) {
-                    //self._viewport_manager.focused_widget = self
+                    if self.get_has_cursor() {
+                        self.set_focused(true);
+                    }
                     self.on_touch_down(mouse_pos)
-                } else if self.get_has_cursor() {
-                    //self._viewport_manager.focused_widget = None
                 }
             }
         }
 
+        // a click outside releases the focus, even if another widget takes the touch
+        if self._is_focused {
+            if mouse_input_data._btn_l_pressed && false == self.check_collide(mouse_pos) {
+                self.set_focused(false);
+            } else {
+                self.update_text_input(delta_time as f32, keyboard_input_data);
+            }
+            *text_input_focused = *text_input_focused || self._is_focused;
+        }
+
         if self._touched {
             *touch_event = true;
         }
@@ -1205,15 +1431,20 @@ pub trait Widget {
 }
 
 impl WidgetDefault {
-    fn create_widget(widget_name: &str) -> Box<dyn Widget> {
+    fn create_widget(widget_name: &str, widget_type: UIWidgetTypes) -> Box<dyn Widget> {
         let mut widget = Box::new(WidgetDefault {
             _ui_widget_name: String::from(widget_name),
-            _ui_widget_type: UIWidgetTypes::Default,
+            _ui_widget_type: widget_type,
             _ui_component: UIComponentInstance::create_ui_component(),
             _parent: None,
             _widgets: Vec::new(),
         });
         widget._ui_component._owner_widget = Some(&mut (*widget));
+        if UIWidgetTypes::TextInput == widget_type {
+            widget._ui_component.set_touchable(true);
+            widget._ui_component.set_has_cursor(true);
+            widget._ui_component.set_valign(VerticalAlign::CENTER);
+        }
         widget
     }
 }
@@ -1223,7 +1454,7 @@ impl Widget for WidgetDefault {
     fn get_ui_widget_type(&self) -> UIWidgetTypes {
         self._ui_widget_type
     }
-    fn has_cursor(&self) -> bool { false }
+    fn has_cursor(&self) -> bool { self._ui_component.get_has_cursor() }
     fn get_ui_component(&self) -> &UIComponentInstance { &self._ui_component }
     fn get_ui_component_mut(&mut self) -> &mut UIComponentInstance { &mut self._ui_component }
     fn get_changed_layout(&self) -> bool { self._ui_component._changed_layout }
@@ -1336,6 +1567,10 @@ impl UIManagerData {
                 _render_ui_count: 0,
                 _render_ui_group: Vec::new(),
                 _default_render_ui_material: None,
+                _render_ui_framebuffer_data: None,
+                _debug_histogram_bars: Vec::new(),
+                _render_error_banner: false,
+                _is_text_input_focused: false,
             };
             ui_manager_data._ui_render_datas.resize(constants::MAX_UI_INSTANCE_COUNT, UIRenderData::default());
             ui_manager_data._root.get_ui_component_mut().set_layout_type(UILayoutType::FloatLayout);
@@ -1346,7 +1581,7 @@ impl UIManagerData {
         }
     }
 
-    pub fn initialize_ui_manager_data(&mut self, renderer_data: &RendererData, resources: &Resources) {
+    pub fn initialize_ui_manager_data(&mut self, renderer_data: &RendererData, resources: &mut Resources) {
         self._font_data = resources.get_default_font_data().clone();
         self.create_ui_vertex_data(renderer_data.get_device(), renderer_data.get_command_pool(), renderer_data.get_graphics_queue(), renderer_data.get_device_memory_properties());
         self.create_ui_graphics_data(renderer_data, resources);
@@ -1354,12 +1589,16 @@ impl UIManagerData {
         self.get_project_ui_manager_mut().build_ui(renderer_data, resources);
     }
 
-    pub fn create_ui_graphics_data(&mut self, _renderer_data: &RendererData, resources: &Resources) {
+    pub fn create_ui_graphics_data(&mut self, _renderer_data: &RendererData, resources: &mut Resources) {
         self._default_render_ui_material = Some(resources.get_material_instance_data("system/render_ui").clone());
+        self._render_ui_framebuffer_data = Some(resources.acquire_framebuffer_data("render_ui"));
     }
 
-    pub fn destroy_ui_graphics_data(&mut self) {
+    pub fn destroy_ui_graphics_data(&mut self, resources: &mut Resources) {
         self._default_render_ui_material = None;
+        if self._render_ui_framebuffer_data.take().is_some() {
+            resources.release_framebuffer_data("render_ui");
+        }
     }
 
     pub fn get_project_ui_manager(&self) -> &dyn ProjectUIManagerBase {
@@ -1412,10 +1651,132 @@ impl UIManagerData {
 
     pub fn create_widget(widget_name: &str, widget_type: UIWidgetTypes) -> *mut dyn Widget {
         Box::into_raw(match widget_type {
-            UIWidgetTypes::Default => WidgetDefault::create_widget(widget_name),
+            UIWidgetTypes::Default | UIWidgetTypes::TextInput => WidgetDefault::create_widget(widget_name, widget_type),
         })
     }
 
+    // upper half: grayscale ramp, lower half: color bars. each ramp step must look evenly spaced on both UNORM and SRGB swapchains.
+    pub fn collect_test_pattern_render_data(
+        window_size: &Vector2<i32>,
+        render_ui_count: &mut u32,
+        render_ui_group: &mut Vec<UIRenderGroupData>,
+        prev_render_group_data: &mut UIRenderGroupData,
+        render_ui_instance_datas: &mut [UIRenderData],
+    ) {
+        let pattern_count = UI_TEST_PATTERN_RAMP_STEPS as usize + UI_TEST_PATTERN_COLOR_BARS.len();
+        if render_ui_instance_datas.len() < (*render_ui_count as usize + pattern_count) {
+            return;
+        }
+
+        if 0 < *render_ui_count && false == prev_render_group_data._material_instance.is_null() {
+            UIRenderGroupData::add_ui_render_group_data(render_ui_group, *render_ui_count, prev_render_group_data, std::ptr::null());
+        }
+
+        let width = window_size.x as f32;
+        let half_height = window_size.y as f32 * 0.5;
+        let mut add_pattern = |index: u32, count: u32, top: f32, color: u32| {
+            let step = width / count as f32;
+            let render_area = Vector4::new(step * index as f32, top, step * (index + 1) as f32, top + half_height);
+            render_ui_instance_datas[*render_ui_count as usize] = UIRenderData {
+                _ui_render_area: render_area.clone(),
+                _ui_renderable_area: render_area,
+                _ui_color: color,
+                ..Default::default()
+            };
+            *render_ui_count += 1;
+        };
+
+        for i in 0..UI_TEST_PATTERN_RAMP_STEPS {
+            let gray = (i * 255 / (UI_TEST_PATTERN_RAMP_STEPS - 1)) as u32;
+            add_pattern(i, UI_TEST_PATTERN_RAMP_STEPS, 0.0, get_color32(gray, gray, gray, 255));
+        }
+
+        let color_bar_count = UI_TEST_PATTERN_COLOR_BARS.len() as u32;
+        for (i, color) in UI_TEST_PATTERN_COLOR_BARS.iter().enumerate() {
+            add_pattern(i as u32, color_bar_count, half_height, *color);
+        }
+    }
+
+    pub fn set_debug_histogram_bars(&mut self, debug_histogram_bars: &[f32]) {
+        self._debug_histogram_bars.clear();
+        self._debug_histogram_bars.extend_from_slice(debug_histogram_bars);
+    }
+
+    pub fn is_text_input_focused(&self) -> bool {
+        self._is_text_input_focused
+    }
+
+    pub fn set_render_error_banner(&mut self, render_error_banner: bool) {
+        self._render_error_banner = render_error_banner;
+    }
+
+    // red strip at the top, the message is logged by FontManager.
+    pub fn collect_render_error_banner_render_data(
+        window_size: &Vector2<i32>,
+        render_ui_count: &mut u32,
+        render_ui_group: &mut Vec<UIRenderGroupData>,
+        prev_render_group_data: &mut UIRenderGroupData,
+        render_ui_instance_datas: &mut [UIRenderData],
+    ) {
+        if render_ui_instance_datas.len() <= *render_ui_count as usize {
+            return;
+        }
+
+        if 0 < *render_ui_count && false == prev_render_group_data._material_instance.is_null() {
+            UIRenderGroupData::add_ui_render_group_data(render_ui_group, *render_ui_count, prev_render_group_data, std::ptr::null());
+        }
+
+        let render_area = Vector4::new(0.0, 0.0, window_size.x as f32, UI_RENDER_ERROR_BANNER_HEIGHT);
+        render_ui_instance_datas[*render_ui_count as usize] = UIRenderData {
+            _ui_render_area: render_area.clone(),
+            _ui_renderable_area: render_area,
+            _ui_color: UI_RENDER_ERROR_BANNER_COLOR,
+            ..Default::default()
+        };
+        *render_ui_count += 1;
+    }
+
+    // bottom-left graph of the debug histogram, one bar per bin.
+    pub fn collect_debug_histogram_render_data(
+        window_size: &Vector2<i32>,
+        debug_histogram_bars: &[f32],
+        render_ui_count: &mut u32,
+        render_ui_group: &mut Vec<UIRenderGroupData>,
+        prev_render_group_data: &mut UIRenderGroupData,
+        render_ui_instance_datas: &mut [UIRenderData],
+    ) {
+        if debug_histogram_bars.is_empty() || render_ui_instance_datas.len() < (*render_ui_count as usize + debug_histogram_bars.len() + 1) {
+            return;
+        }
+
+        if 0 < *render_ui_count && false == prev_render_group_data._material_instance.is_null() {
+            UIRenderGroupData::add_ui_render_group_data(render_ui_group, *render_ui_count, prev_render_group_data, std::ptr::null());
+        }
+
+        let (width, height) = UI_DEBUG_HISTOGRAM_SIZE;
+        let left = UI_DEBUG_HISTOGRAM_MARGIN;
+        let bottom = window_size.y as f32 - UI_DEBUG_HISTOGRAM_MARGIN;
+        let mut add_rect = |render_area: Vector4<f32>, color: u32| {
+            render_ui_instance_datas[*render_ui_count as usize] = UIRenderData {
+                _ui_render_area: render_area.clone(),
+                _ui_renderable_area: render_area,
+                _ui_color: color,
+                ..Default::default()
+            };
+            *render_ui_count += 1;
+        };
+
+        add_rect(Vector4::new(left, bottom - height, left + width, bottom), UI_DEBUG_HISTOGRAM_BACKGROUND_COLOR);
+        let step = width / debug_histogram_bars.len() as f32;
+        for (i, bar) in debug_histogram_bars.iter().enumerate() {
+            let bar_height = bar.max(0.0).min(1.0) * height;
+            if 0.0 < bar_height {
+                let bar_left = left + step * i as f32;
+                add_rect(Vector4::new(bar_left, bottom - bar_height, bar_left + step, bottom), UI_DEBUG_HISTOGRAM_BAR_COLOR);
+            }
+        }
+    }
+
     pub fn render_ui(
         &mut self,
         command_buffer: vk::CommandBuffer,
@@ -1424,11 +1785,13 @@ impl UIManagerData {
         resources: &Resources
     ) {
         if 0 < self._render_ui_count {
-            let framebuffer_data = resources.get_framebuffer_data("render_ui").borrow();
+            let framebuffer_data = self._render_ui_framebuffer_data.as_ref().unwrap().borrow();
+            let is_srgb_target = framebuffer_data._framebuffer_info._framebuffer_color_attachment_formats.iter().any(|format| vulkan_context::is_srgb_format(*format));
+            let canvas_rect = renderer_data.get_letterbox_rect(&framebuffer_data);
             let mut push_constant_data = PushConstant_RenderUI {
-                _inv_canvas_size: Vector2::new(1.0 / framebuffer_data._framebuffer_info._framebuffer_width as f32, 1.0 / framebuffer_data._framebuffer_info._framebuffer_height as f32),
+                _inv_canvas_size: Vector2::new(1.0 / canvas_rect._width as f32, 1.0 / canvas_rect._height as f32),
                 _instance_id_offset: 0,
-                _reserved0: 0,
+                _is_srgb_target: if is_srgb_target { 1 } else { 0 },
             };
 
             // upload storage buffer
@@ -1461,6 +1824,7 @@ impl UIManagerData {
                                 renderer_data.end_render_pass(command_buffer);
                             }
                             renderer_data.begin_render_pass_pipeline(command_buffer, swapchain_index, &render_pass_data, pipeline_data, None);
+                            renderer_data.set_letterbox_viewport(command_buffer, &framebuffer_data);
                             prev_pipeline_data = pipeline_data_ptr;
                         }
 
@@ -1507,6 +1871,7 @@ impl UIManagerData {
 
         // update ui component
         let mut touch_event: bool = false;
+        let mut text_input_focused: bool = false;
         let mouse_pos: Vector2<f32> = Vector2::new(mouse_move_data._mouse_pos.x as f32, mouse_move_data._mouse_pos.y as f32);
         let mouse_moved: bool = 0 != mouse_move_data._mouse_pos_delta.x || 0 != mouse_move_data._mouse_pos_delta.y;
         root_ui_component.update_ui_component(
@@ -1518,7 +1883,9 @@ impl UIManagerData {
             mouse_moved,
             mouse_input_data,
             &mut touch_event,
+            &mut text_input_focused,
         );
+        self._is_text_input_focused = text_input_focused;
 
         // updatge ui layout
         let contents_area = Vector4::new(0.0, 0.0, window_size.x as f32, window_size.y as f32);
@@ -1529,7 +1896,7 @@ impl UIManagerData {
         let update_depth: u32 = 0;
 
         if root_ui_component.get_changed_layout() || root_ui_component.get_changed_deep_child_layout() {
-            root_ui_component.update_layout_size(inherit_changed_layout, &contents_area_size, &self._font_data.borrow());
+            root_ui_component.update_layout_size(inherit_changed_layout, UILayoutType::FloatLayout, Orientation::HORIZONTAL, &contents_area_size, &self._font_data.borrow());
             root_ui_component.update_layout_area(
                 UILayoutType::FloatLayout,
                 Orientation::HORIZONTAL,
@@ -1566,6 +1933,35 @@ impl UIManagerData {
             opacity
         );
 
+        if unsafe { constants::UI_TEST_PATTERN } {
+            UIManagerData::collect_test_pattern_render_data(
+                window_size,
+                &mut render_ui_count,
+                &mut render_ui_group,
+                &mut prev_render_group_data,
+                &mut self._ui_render_datas,
+            );
+        }
+
+        UIManagerData::collect_debug_histogram_render_data(
+            window_size,
+            &self._debug_histogram_bars,
+            &mut render_ui_count,
+            &mut render_ui_group,
+            &mut prev_render_group_data,
+            &mut self._ui_render_datas,
+        );
+
+        if self._render_error_banner {
+            UIManagerData::collect_render_error_banner_render_data(
+                window_size,
+                &mut render_ui_count,
+                &mut render_ui_group,
+                &mut prev_render_group_data,
+                &mut self._ui_render_datas,
+            );
+        }
+
         // last render count
         if 0 < render_ui_count {
             UIRenderGroupData::add_ui_render_group_data(&mut render_ui_group, render_ui_count, &mut prev_render_group_data, std::ptr::null());
@@ -1573,4 +1969,83 @@ impl UIManagerData {
         self._render_ui_count = render_ui_count;
         self._render_ui_group = render_ui_group;
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collect_test_pattern(window_size: &Vector2<i32>, render_ui_count: u32, max_count: usize) -> (u32, Vec<UIRenderGroupData>, Vec<UIRenderData>) {
+        let mut render_ui_count = render_ui_count;
+        let mut render_ui_group: Vec<UIRenderGroupData> = Vec::new();
+        let mut prev_render_group_data = UIRenderGroupData {
+            _accumulated_render_count: 0,
+            _material_instance: std::ptr::null(),
+        };
+        let mut render_ui_instance_datas = vec![UIRenderData::default(); max_count];
+        UIManagerData::collect_test_pattern_render_data(
+            window_size,
+            &mut render_ui_count,
+            &mut render_ui_group,
+            &mut prev_render_group_data,
+            &mut render_ui_instance_datas,
+        );
+        (render_ui_count, render_ui_group, render_ui_instance_datas)
+    }
+
+    #[test]
+    fn test_ui_test_pattern() {
+        let window_size = Vector2::new(1600, 900);
+        let pattern_count = UI_TEST_PATTERN_RAMP_STEPS as usize + UI_TEST_PATTERN_COLOR_BARS.len();
+        let (render_ui_count, render_ui_group, render_ui_instance_datas) = collect_test_pattern(&window_size, 0, pattern_count);
+        assert_eq!(pattern_count as u32, render_ui_count);
+        assert!(render_ui_group.is_empty());
+
+        // the upper half: the grayscale ramp of the even steps in the authored srgb values
+        for (i, render_data) in render_ui_instance_datas.iter().take(UI_TEST_PATTERN_RAMP_STEPS as usize).enumerate() {
+            let gray = i as u32 * 17;
+            assert_eq!(get_color32(gray, gray, gray, 255), render_data._ui_color);
+            assert_eq!(Vector4::new(i as f32 * 100.0, 0.0, (i + 1) as f32 * 100.0, 450.0), render_data._ui_render_area);
+            assert_eq!(render_data._ui_render_area, render_data._ui_renderable_area);
+            assert_eq!(UI_RENDER_FLAG_NONE, render_data._ui_render_flags);
+        }
+        assert_eq!(0xFF000000, render_ui_instance_datas[0]._ui_color);
+        assert_eq!(0xFFFFFFFF, render_ui_instance_datas[UI_TEST_PATTERN_RAMP_STEPS as usize - 1]._ui_color);
+
+        // the lower half: the color bars
+        for (i, color) in UI_TEST_PATTERN_COLOR_BARS.iter().enumerate() {
+            let render_data = &render_ui_instance_datas[UI_TEST_PATTERN_RAMP_STEPS as usize + i];
+            assert_eq!(*color, render_data._ui_color);
+            assert_eq!(Vector4::new(i as f32 * 200.0, 450.0, (i + 1) as f32 * 200.0, 900.0), render_data._ui_render_area);
+        }
+        assert_eq!(get_color32(255, 255, 0, 255), UI_TEST_PATTERN_COLOR_BARS[1]); // yellow
+        assert_eq!(get_color32(255, 0, 0, 255), UI_TEST_PATTERN_COLOR_BARS[5]); // red
+        assert_eq!(get_color32(0, 0, 255, 255), UI_TEST_PATTERN_COLOR_BARS[6]); // blue
+    }
+
+    #[test]
+    fn test_ui_test_pattern_capacity() {
+        let window_size = Vector2::new(1600, 900);
+        let pattern_count = UI_TEST_PATTERN_RAMP_STEPS as usize + UI_TEST_PATTERN_COLOR_BARS.len();
+        // appended after the widgets
+        let (render_ui_count, _, render_ui_instance_datas) = collect_test_pattern(&window_size, 3, pattern_count + 3);
+        assert_eq!(pattern_count as u32 + 3, render_ui_count);
+        assert_eq!(0xFFFFFFFF, render_ui_instance_datas[0]._ui_color);
+        assert_eq!(0xFF000000, render_ui_instance_datas[3]._ui_color);
+        // skipped when the instance buffer is full
+        let (render_ui_count, _, _) = collect_test_pattern(&window_size, 3, pattern_count + 2);
+        assert_eq!(3, render_ui_count);
+    }
+
+    // the ui colors are linearized in the shader only when the target encodes srgb on write,
+    // so the stored values of the test pattern are the same on both swapchain configurations.
+    #[test]
+    fn test_srgb_swapchain_formats() {
+        for format in [vk::Format::B8G8R8A8_SRGB, vk::Format::R8G8B8A8_SRGB, vk::Format::A8B8G8R8_SRGB_PACK32].iter() {
+            assert!(vulkan_context::is_srgb_format(*format));
+        }
+        for format in [vk::Format::B8G8R8A8_UNORM, vk::Format::R8G8B8A8_UNORM, vk::Format::A2B10G10R10_UNORM_PACK32, vk::Format::R16G16B16A16_SFLOAT].iter() {
+            assert!(false == vulkan_context::is_srgb_format(*format));
+        }
+    }
+}
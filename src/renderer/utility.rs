@@ -107,7 +107,8 @@ pub fn create_descriptor_sets(
             }
         }
     }
-    let descriptor_sets = descriptor::create_descriptor_sets(device, descriptor_data);
+    let descriptor_sets_name = format!("{}/{}", pipeline_binding_data.get_render_pass_data().borrow()._render_pass_data_name, pipeline_data._pipeline_data_name);
+    let descriptor_sets = descriptor::create_descriptor_sets(device, descriptor_data, &descriptor_sets_name);
     let _write_descriptor_sets: SwapchainArray<Vec<vk::WriteDescriptorSet>> = descriptor::create_write_descriptor_sets_with_update(
         device,
         &descriptor_sets,
@@ -0,0 +1,200 @@
+use ash::vk;
+use nalgebra::Vector2;
+
+use crate::constants;
+use crate::vulkan_context::vulkan_context;
+
+// pixel rect of a viewport in the window, origin is top-left.
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Default)]
+pub struct ViewportRect {
+    pub _x: i32,
+    pub _y: i32,
+    pub _width: i32,
+    pub _height: i32,
+}
+
+impl ViewportRect {
+    pub fn create_viewport_rect(x: i32, y: i32, width: i32, height: i32) -> ViewportRect {
+        ViewportRect {
+            _x: x,
+            _y: y,
+            _width: width,
+            _height: height,
+        }
+    }
+
+    pub fn get_size(&self) -> Vector2<i32> {
+        Vector2::new(self._width, self._height)
+    }
+
+    pub fn get_aspect(&self) -> f32 {
+        if 0 < self._height { self._width as f32 / self._height as f32 } else { 1.0 }
+    }
+
+    pub fn contains(&self, pos: &Vector2<i32>) -> bool {
+        self._x <= pos.x && pos.x < (self._x + self._width) && self._y <= pos.y && pos.y < (self._y + self._height)
+    }
+
+    // window position -> viewport local position, clamped to the viewport.
+    pub fn to_local_position(&self, pos: &Vector2<i32>) -> Vector2<i32> {
+        Vector2::new(
+            (pos.x - self._x).max(0).min((self._width - 1).max(0)),
+            (pos.y - self._y).max(0).min((self._height - 1).max(0)),
+        )
+    }
+
+    pub fn to_vk_viewport(&self) -> vk::Viewport {
+        vulkan_context::create_viewport(self._x as u32, self._y as u32, self._width as u32, self._height as u32, 0.0, 1.0)
+    }
+
+    pub fn to_vk_rect_2d(&self) -> vk::Rect2D {
+        vulkan_context::create_rect_2d(self._x, self._y, self._width as u32, self._height as u32)
+    }
+}
+
+// centered rect of the target aspect inside the region, target_aspect <= 0.0 means no letterboxing.
+// split-screen viewports pass their own region, so each viewport is letterboxed within it.
+pub fn compute_letterbox_rect(region: &ViewportRect, target_aspect: f32) -> ViewportRect {
+    if target_aspect <= 0.0 || region._width <= 0 || region._height <= 0 {
+        return region.clone();
+    }
+
+    let region_aspect = region.get_aspect();
+    let (width, height) = if target_aspect < region_aspect {
+        // pillarbox: bars at left and right
+        (((region._height as f32 * target_aspect).round() as i32).max(1).min(region._width), region._height)
+    } else {
+        // letterbox: bars at top and bottom
+        (region._width, ((region._width as f32 / target_aspect).round() as i32).max(1).min(region._height))
+    };
+    ViewportRect {
+        _x: region._x + (region._width - width) / 2,
+        _y: region._y + (region._height - height) / 2,
+        _width: width,
+        _height: height,
+    }
+}
+
+// the bars between the region and the inner rect, empty bars are skipped.
+pub fn get_letterbox_bar_rects(region: &ViewportRect, inner_rect: &ViewportRect) -> Vec<ViewportRect> {
+    let bar_rects = [
+        ViewportRect::create_viewport_rect(region._x, region._y, region._width, inner_rect._y - region._y),
+        ViewportRect::create_viewport_rect(region._x, inner_rect._y + inner_rect._height, region._width, (region._y + region._height) - (inner_rect._y + inner_rect._height)),
+        ViewportRect::create_viewport_rect(region._x, inner_rect._y, inner_rect._x - region._x, inner_rect._height),
+        ViewportRect::create_viewport_rect(inner_rect._x + inner_rect._width, inner_rect._y, (region._x + region._width) - (inner_rect._x + inner_rect._width), inner_rect._height),
+    ];
+    bar_rects.iter().filter(|bar_rect| 0 < bar_rect._width && 0 < bar_rect._height).cloned().collect()
+}
+
+pub fn get_letterbox_target_aspect() -> f32 {
+    unsafe { constants::LETTERBOX_TARGET_ASPECT }
+}
+
+pub fn get_letterbox_rect(window_size: &Vector2<i32>) -> ViewportRect {
+    let region = ViewportRect::create_viewport_rect(0, 0, window_size.x, window_size.y);
+    compute_letterbox_rect(&region, get_letterbox_target_aspect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW_SIZES: [(i32, i32); 7] = [(1920, 1080), (2560, 1080), (3440, 1440), (1280, 1024), (800, 600), (1080, 1920), (1001, 333)];
+    const TARGET_ASPECTS: [f32; 5] = [16.0 / 9.0, 21.0 / 9.0, 4.0 / 3.0, 1.0, 9.0 / 16.0];
+
+    fn check_letterbox_rect(region: &ViewportRect, target_aspect: f32) {
+        let inner_rect = compute_letterbox_rect(region, target_aspect);
+        // inside the region, filling one of the axes and centered
+        assert!(region._x <= inner_rect._x && (inner_rect._x + inner_rect._width) <= (region._x + region._width));
+        assert!(region._y <= inner_rect._y && (inner_rect._y + inner_rect._height) <= (region._y + region._height));
+        assert!(inner_rect._width == region._width || inner_rect._height == region._height);
+        let left = inner_rect._x - region._x;
+        let right = (region._x + region._width) - (inner_rect._x + inner_rect._width);
+        let top = inner_rect._y - region._y;
+        let bottom = (region._y + region._height) - (inner_rect._y + inner_rect._height);
+        assert!((left - right).abs() <= 1 && (top - bottom).abs() <= 1);
+        // the aspect within the rounding of a pixel
+        let aspect_error = (inner_rect.get_aspect() - target_aspect).abs() / target_aspect;
+        assert!(aspect_error <= 1.0 / inner_rect._width.min(inner_rect._height) as f32, "{:?} {:?} {}", region, inner_rect, target_aspect);
+
+        // the bars and the inner rect cover the region without overlaps
+        let bar_rects = get_letterbox_bar_rects(region, &inner_rect);
+        let area = |rect: &ViewportRect| rect._width * rect._height;
+        assert_eq!(area(region), area(&inner_rect) + bar_rects.iter().map(area).sum::<i32>());
+        for bar_rect in bar_rects.iter() {
+            assert!(false == inner_rect.contains(&Vector2::new(bar_rect._x, bar_rect._y)));
+            assert!(region.contains(&Vector2::new(bar_rect._x, bar_rect._y)));
+            assert!(region.contains(&Vector2::new(bar_rect._x + bar_rect._width - 1, bar_rect._y + bar_rect._height - 1)));
+        }
+        assert!(bar_rects.len() <= 2);
+    }
+
+    #[test]
+    fn test_letterbox_rect_matrix() {
+        for (width, height) in WINDOW_SIZES.iter() {
+            for target_aspect in TARGET_ASPECTS.iter() {
+                check_letterbox_rect(&ViewportRect::create_viewport_rect(0, 0, *width, *height), *target_aspect);
+            }
+        }
+    }
+
+    #[test]
+    fn test_letterbox_rect() {
+        let region = ViewportRect::create_viewport_rect(0, 0, 1920, 1080);
+        // 21:9 cinematics on 16:9: bars at top and bottom
+        let inner_rect = compute_letterbox_rect(&region, 21.0 / 9.0);
+        assert_eq!(ViewportRect::create_viewport_rect(0, 128, 1920, 823), inner_rect);
+        assert_eq!(vec![
+            ViewportRect::create_viewport_rect(0, 0, 1920, 128),
+            ViewportRect::create_viewport_rect(0, 951, 1920, 129),
+        ], get_letterbox_bar_rects(&region, &inner_rect));
+        // 16:9 on 21:9: bars at left and right
+        let region = ViewportRect::create_viewport_rect(0, 0, 2560, 1080);
+        let inner_rect = compute_letterbox_rect(&region, 16.0 / 9.0);
+        assert_eq!(ViewportRect::create_viewport_rect(320, 0, 1920, 1080), inner_rect);
+        assert_eq!(vec![
+            ViewportRect::create_viewport_rect(0, 0, 320, 1080),
+            ViewportRect::create_viewport_rect(2240, 0, 320, 1080),
+        ], get_letterbox_bar_rects(&region, &inner_rect));
+        // the same aspect has no bars
+        let region = ViewportRect::create_viewport_rect(0, 0, 1920, 1080);
+        let inner_rect = compute_letterbox_rect(&region, 16.0 / 9.0);
+        assert_eq!(region, inner_rect);
+        assert!(get_letterbox_bar_rects(&region, &inner_rect).is_empty());
+        // off and the empty region
+        assert_eq!(region, compute_letterbox_rect(&region, 0.0));
+        let empty_region = ViewportRect::create_viewport_rect(0, 0, 0, 1080);
+        assert_eq!(empty_region, compute_letterbox_rect(&empty_region, 21.0 / 9.0));
+    }
+
+    // each viewport of the split-screen is letterboxed within its own region
+    #[test]
+    fn test_letterbox_split_screen() {
+        let left_region = ViewportRect::create_viewport_rect(0, 0, 960, 1080);
+        let right_region = ViewportRect::create_viewport_rect(960, 0, 960, 1080);
+        let left_rect = compute_letterbox_rect(&left_region, 16.0 / 9.0);
+        let right_rect = compute_letterbox_rect(&right_region, 16.0 / 9.0);
+        assert_eq!(ViewportRect::create_viewport_rect(0, 270, 960, 540), left_rect);
+        assert_eq!(ViewportRect::create_viewport_rect(960, 270, 960, 540), right_rect);
+        for target_aspect in TARGET_ASPECTS.iter() {
+            check_letterbox_rect(&left_region, *target_aspect);
+            check_letterbox_rect(&right_region, *target_aspect);
+            check_letterbox_rect(&ViewportRect::create_viewport_rect(640, 540, 1280, 540), *target_aspect);
+        }
+    }
+
+    // the mouse position of the window -> the inner viewport space of the ui and the picking
+    #[test]
+    fn test_letterbox_mouse_position() {
+        let inner_rect = compute_letterbox_rect(&ViewportRect::create_viewport_rect(0, 0, 2560, 1080), 16.0 / 9.0);
+        assert!(inner_rect.contains(&Vector2::new(320, 0)));
+        assert!(inner_rect.contains(&Vector2::new(2239, 1079)));
+        assert!(false == inner_rect.contains(&Vector2::new(319, 500)));
+        assert!(false == inner_rect.contains(&Vector2::new(2240, 500)));
+        assert_eq!(Vector2::new(0, 0), inner_rect.to_local_position(&Vector2::new(320, 0)));
+        assert_eq!(Vector2::new(960, 540), inner_rect.to_local_position(&Vector2::new(1280, 540)));
+        // clamped to the viewport on the bars
+        assert_eq!(Vector2::new(0, 500), inner_rect.to_local_position(&Vector2::new(100, 500)));
+        assert_eq!(Vector2::new(1919, 1079), inner_rect.to_local_position(&Vector2::new(2500, 1200)));
+    }
+}
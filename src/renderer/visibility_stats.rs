@@ -0,0 +1,487 @@
+use std::collections::HashMap;
+use std::fs;
+use std::hash::Hash;
+use std::os::raw::c_void;
+use std::path::Path;
+
+use ash::{ vk, Device };
+use ash::version::DeviceV1_0;
+use serde::{ Serialize, Deserialize };
+
+use crate::constants;
+use crate::vulkan_context::vulkan_context::SwapchainArray;
+
+pub const VISIBILITY_STATS_MAX_QUERY_COUNT: u32 = 1024; // draws per frame, the draws over it are counted without the cost
+pub const VISIBILITY_STATS_RANKING_COUNT: usize = 50;
+pub const VISIBILITY_STATS_SUMMARY_COUNT: usize = 5;
+pub const VISIBILITY_STATS_OVERLAY_FRAMES: u32 = 600;
+pub const VISIBILITY_STATS_OUTPUT_PATH: &str = "visibility_stats/report";
+pub const VISIBILITY_STATS_COVERAGE_RESOLUTION: u32 = 256; // the screen coverage of the heuristic, independent of the render extent
+
+const INVALID_QUERY: u32 = std::u32::MAX;
+
+// read by SceneManagerData::cull_scene, the coverages of the visible objects are computed only during the capture.
+static mut IS_VISIBILITY_STATS_CAPTURING: bool = false;
+
+pub fn is_visibility_stats_capturing() -> bool {
+    unsafe { IS_VISIBILITY_STATS_CAPTURING }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VisibilityCostSource {
+    FragmentInvocations, // pipeline statistics queries per draw
+    CoverageHeuristic, // screen coverage x triangles, the relative units without the pipeline statistics query
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct VisibilityStatsEntry {
+    pub _name: String,
+    pub _cost: f64, // the sum over the capture, see VisibilityCostSource. the shaded pixels for the lights
+    pub _draw_count: u64,
+}
+
+// the samples of a capture, the objects by the object id of render_object_storage, the lights and the materials by the name.
+#[derive(Clone, Debug, Default)]
+pub struct VisibilityStatsAccumulator {
+    pub _object_samples: HashMap<u32, VisibilityStatsEntry>,
+    pub _light_samples: HashMap<String, VisibilityStatsEntry>,
+    pub _material_samples: HashMap<String, VisibilityStatsEntry>,
+    pub _frame_count: u32,
+}
+
+fn add_sample<K: Eq + Hash>(samples: &mut HashMap<K, VisibilityStatsEntry>, key: K, name: &str, cost: f64, draw_count: u64) {
+    let entry = samples.entry(key).or_insert_with(|| VisibilityStatsEntry {
+        _name: String::from(name),
+        ..Default::default()
+    });
+    entry._cost += cost;
+    entry._draw_count += draw_count;
+}
+
+impl VisibilityStatsAccumulator {
+    pub fn add_object_sample(&mut self, object_id: u32, cost: f64, draw_count: u64) {
+        add_sample(&mut self._object_samples, object_id, "", cost, draw_count);
+    }
+
+    pub fn add_light_sample(&mut self, light_name: &str, shaded_pixel_count: f64) {
+        add_sample(&mut self._light_samples, String::from(light_name), light_name, shaded_pixel_count, 0);
+    }
+
+    pub fn add_material_sample(&mut self, material_name: &str, cost: f64, draw_count: u64) {
+        add_sample(&mut self._material_samples, String::from(material_name), material_name, cost, draw_count);
+    }
+}
+
+// the higher cost first, then the more draws, then the name for the stable order of the same costs
+pub fn get_ranked_entries<'a, I: Iterator<Item = &'a VisibilityStatsEntry>>(entries: I, count: usize) -> Vec<VisibilityStatsEntry> {
+    let mut ranked_entries: Vec<VisibilityStatsEntry> = entries.cloned().collect();
+    ranked_entries.sort_by(|a, b| {
+        b._cost.partial_cmp(&a._cost).unwrap_or(std::cmp::Ordering::Equal)
+            .then(b._draw_count.cmp(&a._draw_count))
+            .then(a._name.cmp(&b._name))
+    });
+    ranked_entries.truncate(count);
+    ranked_entries
+}
+
+// the cost of an instanced draw is split by the weights of the instances, evenly when the weights are all zero.
+pub fn split_draw_cost(cost: f64, weights: &[f64]) -> Vec<f64> {
+    let total_weight: f64 = weights.iter().sum();
+    if total_weight <= 0.0 {
+        let instance_cost = if weights.is_empty() { 0.0 } else { cost / weights.len() as f64 };
+        return vec![instance_cost; weights.len()];
+    }
+    weights.iter().map(|weight| cost * weight / total_weight).collect()
+}
+
+// coverage: the fraction of the screen of the bound sphere
+pub fn get_heuristic_weight(coverage: f32, triangle_count: u32) -> f64 {
+    coverage.max(0.0) as f64 * triangle_count.max(1) as f64
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct VisibilityStatsReport {
+    pub _frame_count: u32,
+    pub _cost_source: VisibilityCostSource,
+    pub _objects: Vec<VisibilityStatsEntry>,
+    pub _lights: Vec<VisibilityStatsEntry>,
+    pub _materials: Vec<VisibilityStatsEntry>,
+}
+
+impl VisibilityStatsReport {
+    // get_object_name: the objects removed during the capture have no name
+    pub fn create_visibility_stats_report<F: Fn(u32) -> Option<String>>(
+        accumulator: &VisibilityStatsAccumulator,
+        cost_source: VisibilityCostSource,
+        get_object_name: F
+    ) -> VisibilityStatsReport {
+        let object_entries: Vec<VisibilityStatsEntry> = accumulator._object_samples.iter().map(|(object_id, entry)| {
+            VisibilityStatsEntry {
+                _name: get_object_name(*object_id).unwrap_or_else(|| format!("removed object {:#x}", object_id)),
+                ..entry.clone()
+            }
+        }).collect();
+        VisibilityStatsReport {
+            _frame_count: accumulator._frame_count,
+            _cost_source: cost_source,
+            _objects: get_ranked_entries(object_entries.iter(), VISIBILITY_STATS_RANKING_COUNT),
+            _lights: get_ranked_entries(accumulator._light_samples.values(), VISIBILITY_STATS_RANKING_COUNT),
+            _materials: get_ranked_entries(accumulator._material_samples.values(), VISIBILITY_STATS_RANKING_COUNT),
+        }
+    }
+
+    pub fn get_summary_text(&self) -> String {
+        let frame_count = self._frame_count.max(1) as f64;
+        let mut text = format!("visibility stats: {} frames, {:?}, per frame", self._frame_count, self._cost_source);
+        let sections: [(&str, &Vec<VisibilityStatsEntry>); 3] = [("objects", &self._objects), ("lights", &self._lights), ("materials", &self._materials)];
+        for (section_name, entries) in sections.iter() {
+            text.push_str(&format!("\n    {}:", section_name));
+            for entry in entries.iter().take(VISIBILITY_STATS_SUMMARY_COUNT) {
+                text.push_str(&format!("\n        {}: {:.1}, draws {:.1}", entry._name, entry._cost / frame_count, entry._draw_count as f64 / frame_count));
+            }
+        }
+        text
+    }
+
+    // output_path.json
+    pub fn write_visibility_stats_report(&self, output_path: &Path) -> std::io::Result<()> {
+        if let Some(output_directory) = output_path.parent() {
+            if false == output_directory.as_os_str().is_empty() {
+                fs::create_dir_all(output_directory)?;
+            }
+        }
+        fs::write(output_path.with_extension("json"), serde_json::to_string_pretty(self).unwrap())?;
+        log::info!("{}", self.get_summary_text());
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct DrawStatsSample {
+    pub _object_ids: Vec<u32>,
+    pub _weights: Vec<f64>, // get_heuristic_weight per instance
+    pub _material_name: String,
+    pub _query: u32, // INVALID_QUERY: over the query count or without the pipeline statistics query
+}
+
+// the samples of a frame. query_results: the fragment invocations per query, None when the query is unavailable.
+// without the pipeline statistics query the cost is the heuristic weight.
+pub fn accumulate_draw_samples(accumulator: &mut VisibilityStatsAccumulator, draw_samples: &[DrawStatsSample], query_results: &[Option<u64>], is_supported: bool) {
+    for draw_sample in draw_samples.iter() {
+        let draw_cost: Option<f64> = if is_supported {
+            query_results.get(draw_sample._query as usize).cloned().flatten().map(|fragment_invocations| fragment_invocations as f64)
+        } else {
+            Some(draw_sample._weights.iter().sum())
+        };
+        let draw_cost = draw_cost.unwrap_or(0.0);
+        for (object_id, object_cost) in draw_sample._object_ids.iter().zip(split_draw_cost(draw_cost, &draw_sample._weights).iter()) {
+            accumulator.add_object_sample(*object_id, *object_cost, 1);
+        }
+        accumulator.add_material_sample(&draw_sample._material_name, draw_cost, 1);
+    }
+    accumulator._frame_count += 1;
+}
+
+// opt-in, the query pools exist only during the capture, so the normal path only checks _is_recording per draw.
+// the results of a frame are read after its present, present_swapchain has waited for the device idle.
+pub struct VisibilityStats {
+    pub _query_pools: SwapchainArray<vk::QueryPool>,
+    pub _draw_samples: Vec<DrawStatsSample>,
+    pub _query_count: u32,
+    pub _recording_swapchain_index: u32,
+    pub _is_recording: bool,
+    pub _is_draw_open: bool,
+    pub _is_supported: bool, // VkPhysicalDeviceFeatures::pipelineStatisticsQuery
+    pub _remaining_frames: u32,
+    pub _accumulator: VisibilityStatsAccumulator,
+    pub _is_finished: bool, // the report is written by RendererData::update_visibility_stats
+    pub _report_summary: Option<String>,
+    pub _overlay_frames: u32,
+}
+
+impl VisibilityStats {
+    pub fn create_visibility_stats(physical_device_features: &vk::PhysicalDeviceFeatures) -> VisibilityStats {
+        VisibilityStats {
+            _query_pools: Vec::new(),
+            _draw_samples: Vec::new(),
+            _query_count: 0,
+            _recording_swapchain_index: 0,
+            _is_recording: false,
+            _is_draw_open: false,
+            _is_supported: vk::TRUE == physical_device_features.pipeline_statistics_query,
+            _remaining_frames: 0,
+            _accumulator: VisibilityStatsAccumulator::default(),
+            _is_finished: false,
+            _report_summary: None,
+            _overlay_frames: 0,
+        }
+    }
+
+    pub fn get_cost_source(&self) -> VisibilityCostSource {
+        if self._is_supported { VisibilityCostSource::FragmentInvocations } else { VisibilityCostSource::CoverageHeuristic }
+    }
+
+    pub fn is_capturing(&self) -> bool {
+        0 < self._remaining_frames
+    }
+
+    pub fn begin_capture(&mut self, device: &Device, frame_count: u32) {
+        if self.is_capturing() || 0 == frame_count {
+            return;
+        }
+        log::info!("begin visibility stats capture: {} frames, {:?}", frame_count, self.get_cost_source());
+        if self._is_supported && self._query_pools.is_empty() {
+            let query_pool_create_info = vk::QueryPoolCreateInfo {
+                query_type: vk::QueryType::PIPELINE_STATISTICS,
+                query_count: VISIBILITY_STATS_MAX_QUERY_COUNT,
+                pipeline_statistics: vk::QueryPipelineStatisticFlags::FRAGMENT_SHADER_INVOCATIONS,
+                ..Default::default()
+            };
+            self._query_pools = (0..constants::SWAPCHAIN_IMAGE_COUNT).map(|_i| unsafe {
+                device.create_query_pool(&query_pool_create_info, None).expect("vkCreateQueryPool failed!")
+            }).collect();
+        }
+        self._accumulator = VisibilityStatsAccumulator::default();
+        self._remaining_frames = frame_count;
+        self._is_finished = false;
+        unsafe { IS_VISIBILITY_STATS_CAPTURING = true; }
+    }
+
+    fn destroy_query_pools(&mut self, device: &Device) {
+        for query_pool in self._query_pools.iter() {
+            unsafe {
+                device.destroy_query_pool(*query_pool, None);
+            }
+        }
+        self._query_pools.clear();
+    }
+
+    pub fn destroy_visibility_stats(&mut self, device: &Device) {
+        self.destroy_query_pools(device);
+        self._remaining_frames = 0;
+        self._is_recording = false;
+        unsafe { IS_VISIBILITY_STATS_CAPTURING = false; }
+    }
+
+    // at the beginning of the command buffer, outside of the render passes
+    pub fn begin_frame(&mut self, device: &Device, command_buffer: vk::CommandBuffer, swapchain_index: u32) {
+        self._is_recording = false;
+        if false == self.is_capturing() {
+            return;
+        }
+        self._recording_swapchain_index = swapchain_index;
+        self._is_recording = true;
+        self._is_draw_open = false;
+        self._draw_samples.clear();
+        self._query_count = 0;
+        if false == self._query_pools.is_empty() {
+            unsafe {
+                device.cmd_reset_query_pool(command_buffer, self._query_pools[swapchain_index as usize], 0, VISIBILITY_STATS_MAX_QUERY_COUNT);
+            }
+        }
+    }
+
+    // inside the render pass around a single draw, the queries can't span the subpasses
+    pub fn begin_draw(&mut self, device: &Device, command_buffer: vk::CommandBuffer, object_ids: &[u32], weights: &[f64], material_name: &str) {
+        if false == self._is_recording {
+            return;
+        }
+        let query = if false == self._query_pools.is_empty() && self._query_count < VISIBILITY_STATS_MAX_QUERY_COUNT {
+            let query = self._query_count;
+            unsafe {
+                device.cmd_begin_query(command_buffer, self._query_pools[self._recording_swapchain_index as usize], query, vk::QueryControlFlags::empty());
+            }
+            self._query_count += 1;
+            query
+        } else {
+            INVALID_QUERY
+        };
+        self._is_draw_open = INVALID_QUERY != query;
+        self._draw_samples.push(DrawStatsSample {
+            _object_ids: object_ids.to_vec(),
+            _weights: weights.to_vec(),
+            _material_name: String::from(material_name),
+            _query: query,
+        });
+    }
+
+    pub fn end_draw(&mut self, device: &Device, command_buffer: vk::CommandBuffer) {
+        if self._is_draw_open {
+            unsafe {
+                device.cmd_end_query(command_buffer, self._query_pools[self._recording_swapchain_index as usize], self._query_count - 1);
+            }
+            self._is_draw_open = false;
+        }
+    }
+
+    // the command buffer of the frame is re-recorded, the queries of the aborted recording were never submitted
+    pub fn discard_frame(&mut self) {
+        self._draw_samples.clear();
+        self._query_count = 0;
+        self._is_draw_open = false;
+        self._is_recording = false;
+    }
+
+    fn read_query_results(&self, device: &Device) -> Vec<Option<u64>> {
+        let mut query_results: Vec<[u64; 2]> = vec![[0, 0]; self._query_count as usize]; // [fragment shader invocations, availability]
+        if 0 < self._query_count {
+            let read_result = unsafe {
+                device.fp_v1_0().get_query_pool_results(
+                    device.handle(),
+                    self._query_pools[self._recording_swapchain_index as usize],
+                    0,
+                    self._query_count,
+                    std::mem::size_of_val(query_results.as_slice()),
+                    query_results.as_mut_ptr() as *mut c_void,
+                    std::mem::size_of::<[u64; 2]>() as vk::DeviceSize,
+                    vk::QueryResultFlags::TYPE_64 | vk::QueryResultFlags::WITH_AVAILABILITY
+                )
+            };
+            if vk::Result::SUCCESS != read_result && vk::Result::NOT_READY != read_result {
+                log::warn!("visibility stats read_query_results: {:?}", read_result);
+            }
+        }
+        query_results.iter().map(|query_result| if 0 != query_result[1] { Some(query_result[0]) } else { None }).collect()
+    }
+
+    // after the present of the recorded frame
+    pub fn complete_frame(&mut self, device: &Device, is_submitted: bool) {
+        if false == self._is_recording {
+            return;
+        }
+        self._is_recording = false;
+        if false == is_submitted {
+            self.discard_frame();
+            return;
+        }
+
+        let query_results = self.read_query_results(device);
+        accumulate_draw_samples(&mut self._accumulator, &self._draw_samples, &query_results, self._is_supported);
+        self._draw_samples.clear();
+        self._remaining_frames -= 1;
+        if 0 == self._remaining_frames {
+            log::info!("end visibility stats capture: {} frames", self._accumulator._frame_count);
+            self.destroy_query_pools(device);
+            self._is_finished = true;
+            unsafe { IS_VISIBILITY_STATS_CAPTURING = false; }
+        }
+    }
+
+    pub fn take_finished_accumulator(&mut self) -> Option<VisibilityStatsAccumulator> {
+        if self._is_finished {
+            self._is_finished = false;
+            Some(std::mem::take(&mut self._accumulator))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_draw_sample(object_ids: &[u32], weights: &[f64], material_name: &str, query: u32) -> DrawStatsSample {
+        DrawStatsSample {
+            _object_ids: object_ids.to_vec(),
+            _weights: weights.to_vec(),
+            _material_name: String::from(material_name),
+            _query: query,
+        }
+    }
+
+    fn create_entry(name: &str, cost: f64, draw_count: u64) -> VisibilityStatsEntry {
+        VisibilityStatsEntry {
+            _name: String::from(name),
+            _cost: cost,
+            _draw_count: draw_count,
+        }
+    }
+
+    #[test]
+    fn test_ranked_entries() {
+        let entries = [
+            create_entry("c", 10.0, 1),
+            create_entry("a", 30.0, 1),
+            create_entry("b", 10.0, 5),
+            create_entry("e", 10.0, 1),
+            create_entry("d", 0.0, 9),
+        ];
+        // the cost, then the draw count, then the name
+        let names: Vec<String> = get_ranked_entries(entries.iter(), 10).iter().map(|entry| entry._name.clone()).collect();
+        assert_eq!(vec!["a", "b", "c", "e", "d"], names);
+        let names: Vec<String> = get_ranked_entries(entries.iter(), 2).iter().map(|entry| entry._name.clone()).collect();
+        assert_eq!(vec!["a", "b"], names);
+        assert!(get_ranked_entries(entries.iter(), 0).is_empty());
+    }
+
+    #[test]
+    fn test_split_draw_cost() {
+        assert_eq!(vec![25.0, 75.0], split_draw_cost(100.0, &[1.0, 3.0]));
+        assert_eq!(vec![50.0, 50.0], split_draw_cost(100.0, &[0.0, 0.0]));
+        assert!(split_draw_cost(100.0, &[]).is_empty());
+        assert_eq!(0.0, get_heuristic_weight(-1.0, 100));
+        assert_eq!(0.5, get_heuristic_weight(0.5, 0));
+    }
+
+    #[test]
+    fn test_accumulate_draw_samples() {
+        // the query 2 is not available, the draw over the query count has no query
+        let draw_samples = vec![
+            create_draw_sample(&[1, 2], &[1.0, 3.0], "rock", 0),
+            create_draw_sample(&[3], &[1.0], "grass", 1),
+            create_draw_sample(&[1], &[1.0], "rock", 2),
+            create_draw_sample(&[4], &[1.0], "grass", INVALID_QUERY),
+        ];
+        let query_results = vec![Some(400), Some(50), None];
+        let mut accumulator = VisibilityStatsAccumulator::default();
+        for _ in 0..2 {
+            accumulate_draw_samples(&mut accumulator, &draw_samples, &query_results, true);
+        }
+        assert_eq!(2, accumulator._frame_count);
+        assert_eq!((200.0, 4), (accumulator._object_samples[&1]._cost, accumulator._object_samples[&1]._draw_count));
+        assert_eq!((600.0, 2), (accumulator._object_samples[&2]._cost, accumulator._object_samples[&2]._draw_count));
+        assert_eq!((100.0, 2), (accumulator._object_samples[&3]._cost, accumulator._object_samples[&3]._draw_count));
+        assert_eq!((0.0, 2), (accumulator._object_samples[&4]._cost, accumulator._object_samples[&4]._draw_count));
+        assert_eq!((800.0, 4), (accumulator._material_samples["rock"]._cost, accumulator._material_samples["rock"]._draw_count));
+        assert_eq!((100.0, 4), (accumulator._material_samples["grass"]._cost, accumulator._material_samples["grass"]._draw_count));
+
+        // the heuristic weights are the cost without the query
+        let mut accumulator = VisibilityStatsAccumulator::default();
+        accumulate_draw_samples(&mut accumulator, &draw_samples, &[], false);
+        assert_eq!(2.0, accumulator._object_samples[&1]._cost);
+        assert_eq!(3.0, accumulator._object_samples[&2]._cost);
+        assert_eq!(5.0, accumulator._material_samples["rock"]._cost);
+    }
+
+    #[test]
+    fn test_visibility_stats_report() {
+        let mut accumulator = VisibilityStatsAccumulator::default();
+        for object_id in 0..(VISIBILITY_STATS_RANKING_COUNT as u32 + 10) {
+            accumulator.add_object_sample(object_id, object_id as f64, 1);
+        }
+        accumulator.add_light_sample("sun", 1000.0);
+        accumulator.add_light_sample("lamp", 300.0);
+        accumulator.add_light_sample("sun", 1000.0);
+        accumulator.add_material_sample("rock", 10.0, 2);
+        accumulator._frame_count = 2;
+
+        // the objects removed during the capture keep their cost
+        let report = VisibilityStatsReport::create_visibility_stats_report(&accumulator, VisibilityCostSource::CoverageHeuristic, |object_id| {
+            if 0 == object_id % 2 { Some(format!("object_{}", object_id)) } else { None }
+        });
+        assert_eq!(VISIBILITY_STATS_RANKING_COUNT, report._objects.len());
+        assert_eq!("removed object 0x3b", report._objects[0]._name);
+        assert_eq!(create_entry("object_58", 58.0, 1), report._objects[1]);
+        assert_eq!(vec![create_entry("sun", 2000.0, 0), create_entry("lamp", 300.0, 0)], report._lights);
+        assert_eq!(vec![create_entry("rock", 10.0, 2)], report._materials);
+
+        // the summary is per frame
+        let summary_text = report.get_summary_text();
+        assert!(summary_text.starts_with("visibility stats: 2 frames, CoverageHeuristic, per frame"));
+        assert!(summary_text.contains("sun: 1000.0, draws 0.0"));
+        assert!(summary_text.contains("rock: 5.0, draws 1.0"));
+        let objects_text = summary_text.split("\n    lights:").next().unwrap();
+        assert_eq!(VISIBILITY_STATS_SUMMARY_COUNT, objects_text.matches("\n        ").count());
+    }
+}
@@ -0,0 +1,762 @@
+use std::fs;
+use std::path::PathBuf;
+
+use ash::{ vk, Device };
+use ash::version::DeviceV1_0;
+use nalgebra::{ Matrix4, Vector2, Vector3 };
+
+use crate::application::environment_settings::FogSettings;
+use crate::constants;
+use crate::renderer::camera::CameraObjectData;
+use crate::renderer::light::LightConstants;
+use crate::renderer::renderer::RendererData;
+use crate::renderer::utility;
+use crate::resource::resource::Resources;
+use crate::utilities::math;
+use crate::vulkan_context::buffer::{ self, ShaderBufferData };
+use crate::vulkan_context::descriptor::{ self, DescriptorDataCreateInfo, DescriptorResourceInfo, DescriptorResourceType };
+use crate::vulkan_context::framebuffer::{ self, FramebufferData, RenderTargetInfo };
+use crate::vulkan_context::render_pass::{ ImageAttachmentDescription, PipelineDataCreateInfo, RenderPassDataCreateInfo };
+use crate::vulkan_context::shader::SHADER_DIRECTORY;
+use crate::vulkan_context::texture::{ TextureCreateInfo, TextureData };
+use crate::vulkan_context::vulkan_context::SwapchainArray;
+
+pub const VOLUMETRIC_FOG_WORK_GROUP_SIZE: u32 = 8; // must match with volumetric_fog.comp
+pub const VOLUMETRIC_FOG_MODE_RAYMARCH: u32 = 0; // must match with volumetric_fog.comp
+pub const VOLUMETRIC_FOG_MODE_COPY_HISTORY: u32 = 1; // must match with volumetric_fog.comp
+pub const VOLUMETRIC_FOG_RENDER_PASS_NAME: &str = "volumetric_fog";
+pub const VOLUMETRIC_FOG_COMPOSITE_RENDER_PASS_NAME: &str = "composite_volumetric_fog";
+pub const VOLUMETRIC_FOG_QUAD_MESH_NAME: &str = "quad"; // see Resources::load_mesh_datas
+pub const VOLUMETRIC_FOG_CONSTANTS_BUFFER_NAME: &str = "VolumetricFogConstants";
+// rgb: the inscattered light, a: the transmittance of the scene behind the fog
+pub const VOLUMETRIC_FOG_TEXTURE_NAME: &str = "VolumetricFog";
+pub const VOLUMETRIC_FOG_HISTORY_TEXTURE_NAME: &str = "VolumetricFogHistory";
+pub const VOLUMETRIC_FOG_FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+pub const VOLUMETRIC_FOG_SHADER_FILE: &str = "common/volumetric_fog.comp";
+pub const VOLUMETRIC_FOG_COMPOSITE_VERTEX_SHADER_FILE: &str = "common/composite_volumetric_fog.vert";
+pub const VOLUMETRIC_FOG_COMPOSITE_FRAGMENT_SHADER_FILE: &str = "common/composite_volumetric_fog.frag";
+pub const VOLUMETRIC_FOG_SHADER_SOURCE: &str = r#"#version 450
+
+#define VOLUMETRIC_FOG_WORK_GROUP_SIZE 8
+#define VOLUMETRIC_FOG_MODE_RAYMARCH 0
+#define VOLUMETRIC_FOG_MODE_COPY_HISTORY 1
+#define PI 3.14159265358979
+
+layout(local_size_x = VOLUMETRIC_FOG_WORK_GROUP_SIZE, local_size_y = VOLUMETRIC_FOG_WORK_GROUP_SIZE, local_size_z = 1) in;
+
+layout(binding = 0) uniform VolumetricFogConstants
+{
+    mat4 inv_view_origin_projection;
+    mat4 view_origin_projection_prev;
+    mat4 shadow_view_projection;
+    vec3 camera_position;
+    float fog_density;
+    vec3 camera_position_prev;
+    float fog_height_falloff;
+    vec3 light_direction; // toward the light
+    float fog_anisotropy;
+    vec3 light_color;
+    float fog_start_distance;
+    vec3 fog_color;
+    float shadow_bias;
+    ivec2 image_size;
+    uint step_count;
+    uint frame_index;
+    float max_distance;
+    float near_depth;
+    float far_depth;
+    float history_weight;
+    uint is_history_valid;
+    uint reserved0;
+    uint reserved1;
+    uint reserved2;
+} fog;
+layout(binding = 1) uniform sampler2D texture_depth;
+layout(binding = 2) uniform sampler2D texture_shadow;
+layout(binding = 3) uniform sampler2D texture_history;
+layout(binding = 4, rgba16f) uniform image2D image_history;
+layout(binding = 5, rgba16f) uniform image2D image_volumetric_fog;
+
+layout(push_constant) uniform PushConstant_VolumetricFog
+{
+    uint mode;
+    uint reserved0;
+    uint reserved1;
+    uint reserved2;
+} pushConstant;
+
+// henyey-greenstein, the forward scattering toward the light is g > 0
+float get_phase(float cos_theta, float g)
+{
+    float g2 = g * g;
+    return (1.0 - g2) / (4.0 * PI * pow(max(1.0 + g2 - 2.0 * g * cos_theta, 0.000001), 1.5));
+}
+
+// the start offset of the steps, different every frame so the history converges the banding
+float get_interleaved_gradient_noise(vec2 pixel, uint frame_index)
+{
+    pixel += 5.588238 * float(frame_index % 64u);
+    return fract(52.9829189 * fract(0.06711056 * pixel.x + 0.00583715 * pixel.y));
+}
+
+// 1 is lit, the shadow map of the main light is in the standard depth convention. outside of the shadow map is lit.
+float get_shadow(vec3 world_position)
+{
+    vec4 shadow_position = fog.shadow_view_projection * vec4(world_position, 1.0);
+    shadow_position.xyz /= shadow_position.w;
+    vec2 shadow_texcoord = shadow_position.xy * 0.5 + 0.5;
+    if(any(lessThan(shadow_texcoord, vec2(0.0))) || any(greaterThan(shadow_texcoord, vec2(1.0))) || 1.0 < shadow_position.z)
+    {
+        return 1.0;
+    }
+    float shadow_depth = textureLod(texture_shadow, shadow_texcoord, 0.0).x;
+    return (shadow_position.z - fog.shadow_bias <= shadow_depth) ? 1.0 : 0.0;
+}
+
+void main()
+{
+    ivec2 pixel = ivec2(gl_GlobalInvocationID.xy);
+    if(any(greaterThanEqual(pixel, fog.image_size)))
+    {
+        return;
+    }
+
+    if(VOLUMETRIC_FOG_MODE_COPY_HISTORY == pushConstant.mode)
+    {
+        imageStore(image_history, pixel, imageLoad(image_volumetric_fog, pixel));
+        return;
+    }
+
+    vec2 texcoord = (vec2(pixel) + 0.5) / vec2(fog.image_size);
+    vec2 clip_xy = texcoord * 2.0 - 1.0;
+    float depth = textureLod(texture_depth, texcoord, 0.0).x;
+    // unprojected at the near depth, the far depth of the infinite projection is at w = 0
+    vec4 near_position = fog.inv_view_origin_projection * vec4(clip_xy, fog.near_depth, 1.0);
+    vec3 view_direction = normalize(near_position.xyz / near_position.w);
+    float scene_distance = fog.max_distance;
+    if(fog.far_depth != depth)
+    {
+        vec4 relative_position = fog.inv_view_origin_projection * vec4(clip_xy, depth, 1.0);
+        scene_distance = min(length(relative_position.xyz / relative_position.w), fog.max_distance);
+    }
+
+    float ray_length = max(scene_distance - fog.fog_start_distance, 0.0);
+    float step_length = ray_length / float(fog.step_count);
+    float step_offset = get_interleaved_gradient_noise(vec2(pixel), fog.frame_index);
+    float phase = get_phase(dot(view_direction, fog.light_direction), fog.fog_anisotropy);
+    vec3 inscatter = vec3(0.0);
+    float transmittance = 1.0;
+    for(uint i = 0u; i < fog.step_count; ++i)
+    {
+        float ray_distance = fog.fog_start_distance + (float(i) + step_offset) * step_length;
+        vec3 world_position = fog.camera_position + view_direction * ray_distance;
+        float density = fog.fog_density * exp(-fog.fog_height_falloff * world_position.y);
+        // the fog color is the inscatter of the sky, the sun is shadowed by the shadow map
+        vec3 radiance = fog.light_color * (phase * get_shadow(world_position)) + fog.fog_color;
+        // the integral over the step with the scattering equal to the extinction
+        float step_transmittance = exp(-density * step_length);
+        inscatter += radiance * (transmittance * (1.0 - step_transmittance));
+        transmittance *= step_transmittance;
+    }
+
+    vec4 result = vec4(inscatter, transmittance);
+    if(0u != fog.is_history_valid)
+    {
+        // reprojected at the scene distance, the history outside of the previous view is rejected
+        vec3 relative_position_prev = view_direction * scene_distance + (fog.camera_position - fog.camera_position_prev);
+        vec4 clip_position_prev = fog.view_origin_projection_prev * vec4(relative_position_prev, 1.0);
+        vec2 texcoord_prev = (clip_position_prev.xy / clip_position_prev.w) * 0.5 + 0.5;
+        if(0.0 < clip_position_prev.w && all(greaterThanEqual(texcoord_prev, vec2(0.0))) && all(lessThanEqual(texcoord_prev, vec2(1.0))))
+        {
+            result = mix(result, textureLod(texture_history, texcoord_prev, 0.0), fog.history_weight);
+        }
+    }
+    imageStore(image_volumetric_fog, pixel, result);
+}
+"#;
+pub const VOLUMETRIC_FOG_COMPOSITE_VERTEX_SHADER_SOURCE: &str = r#"#version 450
+
+layout(location = 0) in vec3 inPosition;
+
+layout(location = 0) out vec2 vs_texcoord;
+
+void main()
+{
+    gl_Position = vec4(inPosition.xy, 0.0, 1.0);
+    vs_texcoord = inPosition.xy * 0.5 + 0.5;
+}
+"#;
+pub const VOLUMETRIC_FOG_COMPOSITE_FRAGMENT_SHADER_SOURCE: &str = r#"#version 450
+
+layout(binding = 0) uniform sampler2D texture_depth;
+layout(binding = 1) uniform sampler2D texture_volumetric_fog;
+
+layout(push_constant) uniform PushConstant_CompositeVolumetricFog
+{
+    vec2 linear_depth_params; // math::get_linear_depth_params
+    float reserved0;
+    float reserved1;
+} pushConstant;
+
+layout(location = 0) in vec2 vs_texcoord;
+
+// the blend is src + dst * src.a
+layout(location = 0) out vec4 outColor;
+
+float linearize_depth(float depth)
+{
+    return 1.0 / max(depth * pushConstant.linear_depth_params.x + pushConstant.linear_depth_params.y, 0.000001);
+}
+
+// the bilinear upsample weighted by the depth of the fog texels, so the fog does not bleed over the silhouettes
+void main()
+{
+    ivec2 fog_size = textureSize(texture_volumetric_fog, 0);
+    vec2 fog_texel = vs_texcoord * vec2(fog_size) - 0.5;
+    ivec2 fog_base_pixel = ivec2(floor(fog_texel));
+    vec2 fog_fraction = fog_texel - vec2(fog_base_pixel);
+    float depth = linearize_depth(textureLod(texture_depth, vs_texcoord, 0.0).x);
+    vec4 fog = vec4(0.0);
+    float weight_sum = 0.0;
+    for(int i = 0; i < 4; ++i)
+    {
+        ivec2 offset = ivec2(i & 1, i >> 1);
+        ivec2 fog_pixel = clamp(fog_base_pixel + offset, ivec2(0), fog_size - 1);
+        // the same depth as the raymarch of the fog texel
+        float fog_depth = linearize_depth(textureLod(texture_depth, (vec2(fog_pixel) + 0.5) / vec2(fog_size), 0.0).x);
+        float bilinear_weight = (0 == offset.x ? 1.0 - fog_fraction.x : fog_fraction.x) * (0 == offset.y ? 1.0 - fog_fraction.y : fog_fraction.y);
+        float weight = bilinear_weight / (0.001 + abs(depth - fog_depth) / max(depth, 0.000001));
+        fog += texelFetch(texture_volumetric_fog, fog_pixel, 0) * weight;
+        weight_sum += weight;
+    }
+    outColor = fog / max(weight_sum, 0.000001);
+}
+"#;
+
+// must match with volumetric_fog.comp
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone)]
+#[repr(C)]
+pub struct PushConstant_VolumetricFog {
+    pub _mode: u32,
+    pub _reserved0: u32,
+    pub _reserved1: u32,
+    pub _reserved2: u32,
+}
+
+crate::impl_push_constant!(PushConstant_VolumetricFog, 16);
+
+// must match with composite_volumetric_fog.frag
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone)]
+#[repr(C)]
+pub struct PushConstant_CompositeVolumetricFog {
+    pub _linear_depth_params: Vector2<f32>,
+    pub _reserved0: f32,
+    pub _reserved1: f32,
+}
+
+crate::impl_push_constant!(PushConstant_CompositeVolumetricFog, 16);
+
+// must match with VolumetricFogConstants of volumetric_fog.comp
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct VolumetricFogConstants {
+    pub _inv_view_origin_projection: Matrix4<f32>,
+    pub _view_origin_projection_prev: Matrix4<f32>,
+    pub _shadow_view_projection: Matrix4<f32>,
+    pub _camera_position: Vector3<f32>,
+    pub _fog_density: f32,
+    pub _camera_position_prev: Vector3<f32>,
+    pub _fog_height_falloff: f32,
+    pub _light_direction: Vector3<f32>,
+    pub _fog_anisotropy: f32,
+    pub _light_color: Vector3<f32>,
+    pub _fog_start_distance: f32,
+    pub _fog_color: Vector3<f32>,
+    pub _shadow_bias: f32,
+    pub _image_size: Vector2<i32>,
+    pub _step_count: u32,
+    pub _frame_index: u32,
+    pub _max_distance: f32,
+    pub _near_depth: f32,
+    pub _far_depth: f32,
+    pub _history_weight: f32,
+    pub _is_history_valid: u32,
+    pub _reserved0: u32,
+    pub _reserved1: u32,
+    pub _reserved2: u32,
+}
+
+// the inputs are the render targets of the project renderer, the fog itself is FogSettings of the environment settings
+#[derive(Clone, Debug, PartialEq)]
+pub struct VolumetricFogSettings {
+    pub _color_target_name: String, // the fog is blended into it before bloom
+    pub _depth_target_name: String,
+    pub _shadow_target_name: String, // the shadow map of the main light, see LightConstants::_shadow_view_projection
+    pub _max_distance: f32, // meters, the raymarch length of the sky pixels
+    pub _history_weight: f32, // 0: no temporal accumulation
+}
+
+impl Default for VolumetricFogSettings {
+    fn default() -> VolumetricFogSettings {
+        VolumetricFogSettings {
+            _color_target_name: String::from("SceneColor"),
+            _depth_target_name: String::from("SceneDepth"),
+            _shadow_target_name: String::from("Shadow"),
+            _max_distance: 200.0,
+            _history_weight: 0.9,
+        }
+    }
+}
+
+pub fn get_render_pass_data_create_info() -> RenderPassDataCreateInfo {
+    let descriptor_data_create_info = |binding_index: u32, descriptor_name: &str, descriptor_resource_type: DescriptorResourceType| DescriptorDataCreateInfo {
+        _descriptor_binding_index: binding_index,
+        _descriptor_name: String::from(descriptor_name),
+        _descriptor_resource_type: descriptor_resource_type,
+        _descriptor_shader_stage: vk::ShaderStageFlags::COMPUTE,
+        ..Default::default()
+    };
+    let pipeline_data_create_infos = vec![
+        PipelineDataCreateInfo {
+            _pipeline_data_create_info_name: String::from(VOLUMETRIC_FOG_RENDER_PASS_NAME),
+            _pipeline_bind_point: vk::PipelineBindPoint::COMPUTE,
+            _pipeline_compute_shader_file: PathBuf::from(VOLUMETRIC_FOG_SHADER_FILE),
+            _push_constant_ranges: vec![vk::PushConstantRange {
+                stage_flags: vk::ShaderStageFlags::COMPUTE,
+                offset: 0,
+                size: std::mem::size_of::<PushConstant_VolumetricFog>() as u32,
+            }],
+            _descriptor_data_create_infos: vec![
+                descriptor_data_create_info(0, VOLUMETRIC_FOG_CONSTANTS_BUFFER_NAME, DescriptorResourceType::UniformBuffer),
+                descriptor_data_create_info(1, "texture_depth", DescriptorResourceType::RenderTarget),
+                descriptor_data_create_info(2, "texture_shadow", DescriptorResourceType::RenderTarget),
+                descriptor_data_create_info(3, "texture_history", DescriptorResourceType::RenderTarget),
+                descriptor_data_create_info(4, VOLUMETRIC_FOG_HISTORY_TEXTURE_NAME, DescriptorResourceType::StorageRenderTarget),
+                descriptor_data_create_info(5, VOLUMETRIC_FOG_TEXTURE_NAME, DescriptorResourceType::StorageRenderTarget),
+            ],
+            ..Default::default()
+        }
+    ];
+
+    RenderPassDataCreateInfo {
+        _render_pass_create_info_name: String::from(VOLUMETRIC_FOG_RENDER_PASS_NAME),
+        _pipeline_data_create_infos: pipeline_data_create_infos,
+        ..Default::default()
+    }
+}
+
+pub fn get_composite_render_pass_data_create_info(color_target: &TextureData) -> RenderPassDataCreateInfo {
+    let pipeline_data_create_info = PipelineDataCreateInfo {
+        _pipeline_data_create_info_name: String::from(VOLUMETRIC_FOG_COMPOSITE_RENDER_PASS_NAME),
+        _pipeline_vertex_shader_file: PathBuf::from(VOLUMETRIC_FOG_COMPOSITE_VERTEX_SHADER_FILE),
+        _pipeline_fragment_shader_file: PathBuf::from(VOLUMETRIC_FOG_COMPOSITE_FRAGMENT_SHADER_FILE),
+        // the scene behind the fog is attenuated by the transmittance
+        _pipeline_color_blend_modes: vec![vk::PipelineColorBlendAttachmentState {
+            blend_enable: vk::TRUE,
+            src_color_blend_factor: vk::BlendFactor::ONE,
+            dst_color_blend_factor: vk::BlendFactor::SRC_ALPHA,
+            color_blend_op: vk::BlendOp::ADD,
+            src_alpha_blend_factor: vk::BlendFactor::ZERO,
+            dst_alpha_blend_factor: vk::BlendFactor::ONE,
+            alpha_blend_op: vk::BlendOp::ADD,
+            color_write_mask: vk::ColorComponentFlags::R | vk::ColorComponentFlags::G | vk::ColorComponentFlags::B,
+        }],
+        _pipeline_sample_count: color_target._image_sample_count,
+        _push_constant_ranges: vec![vk::PushConstantRange {
+            stage_flags: vk::ShaderStageFlags::FRAGMENT,
+            offset: 0,
+            size: std::mem::size_of::<PushConstant_CompositeVolumetricFog>() as u32,
+        }],
+        _descriptor_data_create_infos: vec![
+            DescriptorDataCreateInfo {
+                _descriptor_binding_index: 0,
+                _descriptor_name: String::from("texture_depth"),
+                _descriptor_resource_type: DescriptorResourceType::RenderTarget,
+                _descriptor_shader_stage: vk::ShaderStageFlags::FRAGMENT,
+                ..Default::default()
+            },
+            DescriptorDataCreateInfo {
+                _descriptor_binding_index: 1,
+                _descriptor_name: String::from(VOLUMETRIC_FOG_TEXTURE_NAME),
+                _descriptor_resource_type: DescriptorResourceType::RenderTarget,
+                _descriptor_shader_stage: vk::ShaderStageFlags::FRAGMENT,
+                ..Default::default()
+            },
+        ],
+        ..Default::default()
+    };
+
+    RenderPassDataCreateInfo {
+        _render_pass_create_info_name: String::from(VOLUMETRIC_FOG_COMPOSITE_RENDER_PASS_NAME),
+        _color_attachment_descriptions: vec![ImageAttachmentDescription {
+            _attachment_image_format: color_target._image_format,
+            _attachment_image_samples: color_target._image_sample_count,
+            _attachment_load_operation: vk::AttachmentLoadOp::LOAD,
+            _attachment_store_operation: vk::AttachmentStoreOp::STORE,
+            _attachment_initial_layout: vk::ImageLayout::GENERAL,
+            ..Default::default()
+        }],
+        _pipeline_data_create_infos: vec![pipeline_data_create_info],
+        ..Default::default()
+    }
+}
+
+#[cfg(not(target_os = "android"))]
+pub fn generate_volumetric_fog_shaders() {
+    let shader_sources = [
+        (VOLUMETRIC_FOG_SHADER_FILE, VOLUMETRIC_FOG_SHADER_SOURCE),
+        (VOLUMETRIC_FOG_COMPOSITE_VERTEX_SHADER_FILE, VOLUMETRIC_FOG_COMPOSITE_VERTEX_SHADER_SOURCE),
+        (VOLUMETRIC_FOG_COMPOSITE_FRAGMENT_SHADER_FILE, VOLUMETRIC_FOG_COMPOSITE_FRAGMENT_SHADER_SOURCE),
+    ];
+    for (shader_file, shader_source) in shader_sources.iter() {
+        let mut shader_file_path = PathBuf::from(SHADER_DIRECTORY);
+        shader_file_path.push(shader_file);
+        if false == shader_file_path.is_file() {
+            fs::create_dir_all(shader_file_path.parent().unwrap()).expect("Failed to create directories.");
+            fs::write(&shader_file_path, shader_source).expect("Failed to write");
+            log::info!("generate_volumetric_fog_shaders: {:?}", shader_file_path);
+        }
+    }
+}
+
+// The light shafts of the main light: the view rays of the reduced resolution are raymarched through the height fog with the shadow map,
+// accumulated with the reprojected history, then upsampled by the depth and blended into the scene color.
+// the project renderer calls RendererData::render_volumetric_fog after the lighting, before bloom.
+#[derive(Default)]
+pub struct VolumetricFog {
+    pub _settings: VolumetricFogSettings,
+    pub _fog_settings: FogSettings,
+    pub _fog_texture: Option<TextureData>,
+    pub _history_texture: Option<TextureData>, // the accumulated fog of the previous dispatch
+    pub _constants_buffer: Option<ShaderBufferData>,
+    pub _descriptor_sets: SwapchainArray<vk::DescriptorSet>, // created on demand
+    pub _composite_framebuffer_data: Option<FramebufferData>, // created on demand
+    pub _composite_descriptor_sets: SwapchainArray<vk::DescriptorSet>,
+    pub _frame_index: u32,
+    pub _is_history_valid: bool, // false after the creation and invalidate_history
+}
+
+impl VolumetricFog {
+    pub fn create_volumetric_fog_textures(&mut self, renderer_data: &RendererData) {
+        let render_extent = renderer_data.get_render_extent();
+        let resolution_divisor = unsafe { constants::VOLUMETRIC_FOG_RESOLUTION_DIVISOR.max(1) };
+        let create_info = |name: &str| -> TextureCreateInfo<u8> {
+            TextureCreateInfo {
+                _texture_name: String::from(name),
+                _texture_width: (render_extent.width / resolution_divisor).max(1),
+                _texture_height: (render_extent.height / resolution_divisor).max(1),
+                _texture_format: VOLUMETRIC_FOG_FORMAT,
+                _texture_min_filter: vk::Filter::LINEAR,
+                _texture_mag_filter: vk::Filter::LINEAR,
+                _texture_wrap_mode: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                _enable_mipmap: false,
+                ..Default::default()
+            }
+        };
+        self._fog_texture = Some(renderer_data.create_render_target::<u8>(&create_info(VOLUMETRIC_FOG_TEXTURE_NAME)));
+        self._history_texture = Some(renderer_data.create_render_target::<u8>(&create_info(VOLUMETRIC_FOG_HISTORY_TEXTURE_NAME)));
+        self._is_history_valid = false;
+    }
+
+    pub fn destroy_volumetric_fog_textures(&mut self, renderer_data: &RendererData) {
+        for texture in [&mut self._fog_texture, &mut self._history_texture].iter_mut() {
+            if let Some(texture_data) = texture.take() {
+                renderer_data.destroy_texture(&texture_data);
+            }
+        }
+        self._is_history_valid = false;
+    }
+
+    pub fn destroy_volumetric_fog_buffers(&mut self, device: &Device) {
+        if let Some(mut constants_buffer) = self._constants_buffer.take() {
+            buffer::destroy_shader_buffer_data(device, &mut constants_buffer);
+        }
+    }
+
+    pub fn destroy_framebuffer_and_descriptor_sets(&mut self, device: &Device) {
+        if let Some(framebuffer_data) = self._composite_framebuffer_data.take() {
+            framebuffer::destroy_framebuffer_data(device, &framebuffer_data);
+        }
+        self._descriptor_sets.clear();
+        self._composite_descriptor_sets.clear();
+    }
+
+    pub fn get_render_target(&self, render_target_name: &str) -> Option<&TextureData> {
+        match render_target_name {
+            VOLUMETRIC_FOG_TEXTURE_NAME => self._fog_texture.as_ref(),
+            VOLUMETRIC_FOG_HISTORY_TEXTURE_NAME => self._history_texture.as_ref(),
+            _ => None,
+        }
+    }
+
+    pub fn is_volumetric_fog_enabled(&self) -> bool {
+        self._fog_settings._enable_fog && 0.0 < self._fog_settings._fog_density
+    }
+
+    // the fog of the environment settings, called by render_scene. the history is rejected when the fog is enabled again.
+    pub fn set_fog_settings(&mut self, fog_settings: &FogSettings) {
+        if self._fog_settings != *fog_settings {
+            if fog_settings._enable_fog != self._fog_settings._enable_fog {
+                self._is_history_valid = false;
+            }
+            self._fog_settings = fog_settings.clone();
+        }
+    }
+
+    // the composite render pass is created with the color target, so a new color target is applied by the next resource reload.
+    pub fn set_volumetric_fog_settings(&mut self, renderer_data: &RendererData, settings: &VolumetricFogSettings) {
+        if self._settings != *settings {
+            self._settings = settings.clone();
+            self.destroy_framebuffer_and_descriptor_sets(renderer_data.get_device());
+            self._is_history_valid = false;
+        }
+    }
+
+    // the fog of the next dispatch has no history, ex) camera cut, teleport, scene load
+    pub fn invalidate_history(&mut self) {
+        self._is_history_valid = false;
+    }
+
+    fn get_descriptor_sets(&mut self, renderer_data: &RendererData, resources: &Resources, depth_target: &TextureData, shadow_target: &TextureData) -> SwapchainArray<vk::DescriptorSet> {
+        if false == self._descriptor_sets.is_empty() {
+            return self._descriptor_sets.clone();
+        }
+        if self._constants_buffer.is_none() {
+            self._constants_buffer = Some(buffer::create_shader_buffer_data(
+                renderer_data.get_device(),
+                renderer_data.get_device_memory_properties(),
+                &String::from(VOLUMETRIC_FOG_CONSTANTS_BUFFER_NAME),
+                vk::BufferUsageFlags::UNIFORM_BUFFER,
+                std::mem::size_of::<VolumetricFogConstants>() as vk::DeviceSize,
+                false,
+                false,
+                false,
+            ));
+        }
+        let render_pass_pipeline_data = resources.get_render_pass_pipeline_data(VOLUMETRIC_FOG_RENDER_PASS_NAME, VOLUMETRIC_FOG_RENDER_PASS_NAME);
+        let pipeline_data = render_pass_pipeline_data._pipeline_data.borrow();
+        let descriptor_data = &pipeline_data._descriptor_data;
+        let descriptor_binding_indices: Vec<u32> = descriptor_data._descriptor_data_create_infos.iter().map(|descriptor_data_create_info| {
+            descriptor_data_create_info._descriptor_binding_index
+        }).collect();
+        let history_texture = self._history_texture.as_ref().unwrap();
+        let descriptor_resource_infos_list: SwapchainArray<Vec<DescriptorResourceInfo>> = constants::SWAPCHAIN_IMAGE_INDICES.iter().map(|swapchain_index| {
+            vec![
+                self._constants_buffer.as_ref().unwrap()._descriptor_buffer_infos[*swapchain_index].clone(),
+                DescriptorResourceInfo::DescriptorImageInfo(depth_target.get_default_image_info().clone()),
+                DescriptorResourceInfo::DescriptorImageInfo(shadow_target.get_default_image_info().clone()),
+                DescriptorResourceInfo::DescriptorImageInfo(history_texture.get_default_image_info().clone()),
+                DescriptorResourceInfo::DescriptorImageInfo(history_texture.get_default_image_info().clone()),
+                DescriptorResourceInfo::DescriptorImageInfo(self._fog_texture.as_ref().unwrap().get_default_image_info().clone()),
+            ]
+        }).collect();
+        let descriptor_sets = descriptor::create_descriptor_sets(renderer_data.get_device(), descriptor_data, VOLUMETRIC_FOG_RENDER_PASS_NAME);
+        let _write_descriptor_sets = descriptor::create_write_descriptor_sets_with_update(
+            renderer_data.get_device(),
+            &descriptor_sets,
+            &descriptor_binding_indices,
+            &descriptor_data._descriptor_set_layout_bindings,
+            &descriptor_resource_infos_list,
+        );
+        self._descriptor_sets = descriptor_sets.clone();
+        descriptor_sets
+    }
+
+    fn prepare_composite(&mut self, renderer_data: &RendererData, resources: &Resources, color_target: &TextureData, depth_target: &TextureData) {
+        if self._composite_framebuffer_data.is_some() {
+            return;
+        }
+        let render_pass_pipeline_data = resources.get_render_pass_pipeline_data(VOLUMETRIC_FOG_COMPOSITE_RENDER_PASS_NAME, VOLUMETRIC_FOG_COMPOSITE_RENDER_PASS_NAME);
+        let render_pass_data = render_pass_pipeline_data._render_pass_data.borrow();
+        let pipeline_data = render_pass_pipeline_data._pipeline_data.borrow();
+        let color_render_target_info = RenderTargetInfo {
+            _texture_data: color_target,
+            _target_layer: 0,
+            _target_mip_level: 0,
+            _clear_value: None,
+        };
+        self._composite_framebuffer_data = Some(utility::create_framebuffers(
+            renderer_data.get_device(),
+            &render_pass_data,
+            VOLUMETRIC_FOG_COMPOSITE_RENDER_PASS_NAME,
+            &[color_render_target_info],
+            &[],
+            &[]
+        ));
+        let descriptor_data = &pipeline_data._descriptor_data;
+        let descriptor_binding_indices: Vec<u32> = descriptor_data._descriptor_data_create_infos.iter().map(|descriptor_data_create_info| {
+            descriptor_data_create_info._descriptor_binding_index
+        }).collect();
+        let descriptor_resource_infos_list: SwapchainArray<Vec<DescriptorResourceInfo>> = constants::SWAPCHAIN_IMAGE_INDICES.iter().map(|_swapchain_index| {
+            vec![
+                DescriptorResourceInfo::DescriptorImageInfo(depth_target.get_default_image_info().clone()),
+                DescriptorResourceInfo::DescriptorImageInfo(self._fog_texture.as_ref().unwrap().get_default_image_info().clone()),
+            ]
+        }).collect();
+        let descriptor_sets = descriptor::create_descriptor_sets(renderer_data.get_device(), descriptor_data, VOLUMETRIC_FOG_COMPOSITE_RENDER_PASS_NAME);
+        let _write_descriptor_sets = descriptor::create_write_descriptor_sets_with_update(
+            renderer_data.get_device(),
+            &descriptor_sets,
+            &descriptor_binding_indices,
+            &descriptor_data._descriptor_set_layout_bindings,
+            &descriptor_resource_infos_list,
+        );
+        self._composite_descriptor_sets = descriptor_sets;
+    }
+
+    // the raymarch with the history, the copy to the history of the next frame, then the composite into the color target.
+    // the targets of the settings are found by the caller, RendererData::get_render_target_from_str borrows this.
+    pub fn render_volumetric_fog(
+        &mut self,
+        command_buffer: vk::CommandBuffer,
+        swapchain_index: u32,
+        renderer_data: &RendererData,
+        resources: &Resources,
+        (color_target, depth_target, shadow_target): (&TextureData, &TextureData, &TextureData),
+        camera: &CameraObjectData,
+        light_constants: &LightConstants
+    ) {
+        if self._fog_texture.is_none() || false == self.is_volumetric_fog_enabled() {
+            self._is_history_valid = false;
+            return;
+        }
+        let descriptor_sets = self.get_descriptor_sets(renderer_data, resources, depth_target, shadow_target);
+        self.prepare_composite(renderer_data, resources, color_target, depth_target);
+        let (image_width, image_height, fog_image, history_image) = {
+            let fog_texture = self._fog_texture.as_ref().unwrap();
+            (fog_texture._image_width, fog_texture._image_height, fog_texture._image, self._history_texture.as_ref().unwrap()._image)
+        };
+        let fog_constants = VolumetricFogConstants {
+            _inv_view_origin_projection: camera._inv_view_origin_projection.clone(),
+            _view_origin_projection_prev: camera._view_origin_projection_prev.clone(),
+            _shadow_view_projection: light_constants._shadow_view_projection.clone(),
+            _camera_position: camera.get_camera_position().clone(),
+            _fog_density: self._fog_settings._fog_density,
+            _camera_position_prev: camera.get_camera_position_prev().clone(),
+            _fog_height_falloff: self._fog_settings._fog_height_falloff,
+            _light_direction: light_constants._light_direction.clone(),
+            _fog_anisotropy: self._fog_settings._fog_anisotropy.max(-0.99).min(0.99),
+            _light_color: light_constants._light_color.clone(),
+            _fog_start_distance: self._fog_settings._fog_start_distance.max(0.0),
+            _fog_color: self._fog_settings._fog_color.clone(),
+            _shadow_bias: light_constants._shadow_bias,
+            _image_size: Vector2::new(image_width as i32, image_height as i32),
+            _step_count: unsafe { constants::VOLUMETRIC_FOG_STEPS.max(1) },
+            _frame_index: self._frame_index,
+            _max_distance: self._settings._max_distance,
+            _near_depth: math::get_near_depth(),
+            _far_depth: math::get_far_depth(),
+            _history_weight: self._settings._history_weight.max(0.0).min(0.99),
+            _is_history_valid: if self._is_history_valid { 1 } else { 0 },
+            _reserved0: 0,
+            _reserved1: 0,
+            _reserved2: 0,
+        };
+        renderer_data.upload_shader_buffer_data(command_buffer, swapchain_index, self._constants_buffer.as_ref().unwrap(), &fog_constants);
+
+        let render_pass_pipeline_data = resources.get_render_pass_pipeline_data(VOLUMETRIC_FOG_RENDER_PASS_NAME, VOLUMETRIC_FOG_RENDER_PASS_NAME);
+        let pipeline_data = render_pass_pipeline_data._pipeline_data.borrow();
+        let mut push_constant_data = PushConstant_VolumetricFog {
+            _mode: VOLUMETRIC_FOG_MODE_RAYMARCH,
+            _reserved0: 0,
+            _reserved1: 0,
+            _reserved2: 0,
+        };
+        let group_count_x = (image_width + VOLUMETRIC_FOG_WORK_GROUP_SIZE - 1) / VOLUMETRIC_FOG_WORK_GROUP_SIZE;
+        let group_count_y = (image_height + VOLUMETRIC_FOG_WORK_GROUP_SIZE - 1) / VOLUMETRIC_FOG_WORK_GROUP_SIZE;
+        let subresource_range = vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        };
+        let image_memory_barrier = |image: vk::Image, src_access_mask: vk::AccessFlags, dst_access_mask: vk::AccessFlags| vk::ImageMemoryBarrier {
+            src_access_mask,
+            dst_access_mask,
+            old_layout: vk::ImageLayout::GENERAL,
+            new_layout: vk::ImageLayout::GENERAL,
+            src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            image,
+            subresource_range,
+            ..Default::default()
+        };
+
+        renderer_data.begin_debug_label(command_buffer, VOLUMETRIC_FOG_RENDER_PASS_NAME);
+        renderer_data.begin_compute_pipeline(command_buffer, &pipeline_data);
+        unsafe {
+            renderer_data.get_device().cmd_bind_descriptor_sets(
+                command_buffer,
+                pipeline_data._pipeline_bind_point,
+                pipeline_data._pipeline_layout,
+                0,
+                &[descriptor_sets[swapchain_index as usize]],
+                &[]
+            );
+        }
+        renderer_data.upload_push_constant_data(command_buffer, &pipeline_data, &push_constant_data);
+        renderer_data.dispatch_compute_pipeline(command_buffer, group_count_x, group_count_y, 1);
+
+        // every pixel samples the history at its reprojected texcoord before it is overwritten
+        renderer_data.pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[
+                image_memory_barrier(fog_image, vk::AccessFlags::SHADER_WRITE, vk::AccessFlags::SHADER_READ),
+                image_memory_barrier(history_image, vk::AccessFlags::SHADER_READ, vk::AccessFlags::SHADER_WRITE),
+            ]
+        );
+        push_constant_data._mode = VOLUMETRIC_FOG_MODE_COPY_HISTORY;
+        renderer_data.upload_push_constant_data(command_buffer, &pipeline_data, &push_constant_data);
+        renderer_data.dispatch_compute_pipeline(command_buffer, group_count_x, group_count_y, 1);
+        renderer_data.end_debug_label(command_buffer);
+
+        // the composite samples the fog, the next frame samples the history
+        renderer_data.pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::PipelineStageFlags::FRAGMENT_SHADER | vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[image_memory_barrier(history_image, vk::AccessFlags::SHADER_WRITE, vk::AccessFlags::SHADER_READ)]
+        );
+        self._is_history_valid = true;
+        self._frame_index = self._frame_index.wrapping_add(1);
+
+        let framebuffer_data = self._composite_framebuffer_data.as_ref().unwrap();
+        let quad_mesh_data = resources.get_mesh_data(VOLUMETRIC_FOG_QUAD_MESH_NAME).borrow();
+        let quad_geometry_data = quad_mesh_data.get_default_geometry_data().borrow();
+        let render_pass_pipeline_data = resources.get_render_pass_pipeline_data(VOLUMETRIC_FOG_COMPOSITE_RENDER_PASS_NAME, VOLUMETRIC_FOG_COMPOSITE_RENDER_PASS_NAME);
+        let render_pass_data = render_pass_pipeline_data._render_pass_data.borrow();
+        let pipeline_data = render_pass_pipeline_data._pipeline_data.borrow();
+        let composite_push_constant_data = PushConstant_CompositeVolumetricFog {
+            _linear_depth_params: unsafe { math::get_linear_depth_params(constants::NEAR, constants::FAR) },
+            _reserved0: 0.0,
+            _reserved1: 0.0,
+        };
+        renderer_data.begin_debug_label(command_buffer, VOLUMETRIC_FOG_COMPOSITE_RENDER_PASS_NAME);
+        renderer_data.begin_render_pass_pipeline(command_buffer, swapchain_index, &render_pass_data, &pipeline_data, Some(framebuffer_data));
+        unsafe {
+            renderer_data.get_device().cmd_bind_descriptor_sets(
+                command_buffer,
+                pipeline_data._pipeline_bind_point,
+                pipeline_data._pipeline_layout,
+                0,
+                &[self._composite_descriptor_sets[swapchain_index as usize]],
+                &[]
+            );
+        }
+        renderer_data.upload_push_constant_data(command_buffer, &pipeline_data, &composite_push_constant_data);
+        renderer_data.draw_elements(command_buffer, &quad_geometry_data);
+        renderer_data.end_render_pass(command_buffer);
+        renderer_data.end_debug_label(command_buffer);
+    }
+}
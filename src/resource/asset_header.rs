@@ -0,0 +1,186 @@
+use std::fs::File;
+use std::io::{ BufRead, BufReader, Read };
+use std::path::Path;
+
+use byteorder::{ BigEndian, LittleEndian, ByteOrder };
+use ash::vk;
+
+// the header only readers for the asset report, the image datas are never decoded.
+pub const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+pub const KTX1_IDENTIFIER: [u8; 12] = [0xAB, b'K', b'T', b'X', b' ', b'1', b'1', 0xBB, 0x0D, 0x0A, 0x1A, 0x0A];
+pub const KTX2_IDENTIFIER: [u8; 12] = [0xAB, b'K', b'T', b'X', b' ', b'2', b'0', 0xBB, 0x0D, 0x0A, 0x1A, 0x0A];
+pub const KTX1_ENDIANNESS_REVERSED: u32 = 0x01020304;
+pub const PNG_HEADER_SIZE: usize = 33; // signature + IHDR chunk
+pub const KTX1_HEADER_SIZE: usize = 64;
+pub const KTX2_HEADER_SIZE: usize = 48; // until levelCount
+pub const TEXTURE_HEADER_SIZE: usize = 40; // see Resources::load_texture_datas
+pub const DDS_HEADER_SIZE: usize = 148; // magic + DDS_HEADER + DDS_HEADER_DXT10
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ImageHeader {
+    pub _width: u32,
+    pub _height: u32,
+    pub _depth: u32,
+    pub _layers: u32,
+    pub _mip_levels: u32,
+    pub _format: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MeshHeader {
+    pub _vertex_count: usize,
+    pub _face_count: usize,
+}
+
+// the dx10 header is optional for dds
+fn read_header_bytes_at_most(file_path: &Path, header_size: usize) -> Result<Vec<u8>, String> {
+    let file = File::open(file_path).map_err(|e| format!("{:?}: {}", file_path, e))?;
+    let mut header_bytes: Vec<u8> = Vec::with_capacity(header_size);
+    file.take(header_size as u64).read_to_end(&mut header_bytes).map_err(|e| format!("{:?}: {}", file_path, e))?;
+    Ok(header_bytes)
+}
+
+fn read_header_bytes(file_path: &Path, header_size: usize) -> Result<Vec<u8>, String> {
+    let header_bytes = read_header_bytes_at_most(file_path, header_size)?;
+    if header_bytes.len() < header_size {
+        return Err(format!("{:?}: truncated header, {} < {}", file_path, header_bytes.len(), header_size));
+    }
+    Ok(header_bytes)
+}
+
+pub fn read_png_header(bytes: &[u8]) -> Result<ImageHeader, String> {
+    if bytes.len() < PNG_HEADER_SIZE || PNG_SIGNATURE != bytes[0..8] {
+        return Err(String::from("not a png"));
+    }
+    if b"IHDR" != &bytes[12..16] {
+        return Err(String::from("png has no IHDR chunk"));
+    }
+    let bit_depth = bytes[24];
+    let format = match bytes[25] {
+        0 => "GRAY",
+        2 => "RGB",
+        3 => "PALETTE",
+        4 => "GRAY_ALPHA",
+        6 => "RGBA",
+        _ => "UNKNOWN",
+    };
+    Ok(ImageHeader {
+        _width: BigEndian::read_u32(&bytes[16..20]),
+        _height: BigEndian::read_u32(&bytes[20..24]),
+        _depth: 1,
+        _layers: 1,
+        _mip_levels: 1,
+        _format: format!("{}{}", format, bit_depth),
+    })
+}
+
+// ktx 1.1 and ktx 2.0, the zero sizes of the array and the depth mean 1.
+pub fn read_ktx_header(bytes: &[u8]) -> Result<ImageHeader, String> {
+    if KTX1_HEADER_SIZE <= bytes.len() && KTX1_IDENTIFIER == bytes[0..12] {
+        let read_u32 = |offset: usize| -> u32 {
+            if KTX1_ENDIANNESS_REVERSED == LittleEndian::read_u32(&bytes[12..16]) {
+                BigEndian::read_u32(&bytes[offset..(offset + 4)])
+            } else {
+                LittleEndian::read_u32(&bytes[offset..(offset + 4)])
+            }
+        };
+        return Ok(ImageHeader {
+            _width: read_u32(36),
+            _height: read_u32(40).max(1),
+            _depth: read_u32(44).max(1),
+            _layers: read_u32(48).max(1) * read_u32(52).max(1),
+            _mip_levels: read_u32(56).max(1),
+            _format: format!("GL_0x{:04X}", read_u32(28)),
+        });
+    }
+    if KTX2_HEADER_SIZE <= bytes.len() && KTX2_IDENTIFIER == bytes[0..12] {
+        let read_u32 = |offset: usize| -> u32 { LittleEndian::read_u32(&bytes[offset..(offset + 4)]) };
+        return Ok(ImageHeader {
+            _width: read_u32(20),
+            _height: read_u32(24).max(1),
+            _depth: read_u32(28).max(1),
+            _layers: read_u32(32).max(1) * read_u32(36).max(1),
+            _mip_levels: read_u32(40).max(1),
+            _format: format!("{:?}", vk::Format::from_raw(read_u32(12) as i32)),
+        });
+    }
+    Err(String::from("not a ktx"))
+}
+
+pub fn read_dds_header(bytes: &[u8]) -> Result<ImageHeader, String> {
+    if bytes.len() < 128 || b"DDS " != &bytes[0..4] {
+        return Err(String::from("not a dds"));
+    }
+    let read_u32 = |offset: usize| -> u32 { LittleEndian::read_u32(&bytes[offset..(offset + 4)]) };
+    let four_cc = &bytes[84..88];
+    let is_dx10 = b"DX10" == four_cc && DDS_HEADER_SIZE <= bytes.len();
+    let caps2 = read_u32(112);
+    let face_count = if 0 != (caps2 & 0x200) || (is_dx10 && 0 != (read_u32(136) & 0x4)) { 6 } else { 1 };
+    Ok(ImageHeader {
+        _width: read_u32(16),
+        _height: read_u32(12).max(1),
+        _depth: if 0 != (caps2 & 0x200000) { read_u32(24).max(1) } else { 1 },
+        _layers: if is_dx10 { read_u32(140).max(1) * face_count } else { face_count },
+        _mip_levels: if 0 != (read_u32(8) & 0x20000) { read_u32(28).max(1) } else { 1 },
+        _format: if is_dx10 { format!("DXGI_{}", read_u32(128)) } else { String::from_utf8_lossy(four_cc).to_string() },
+    })
+}
+
+// the binary .texture written by the engine
+pub fn read_texture_header(bytes: &[u8]) -> Result<ImageHeader, String> {
+    if bytes.len() < TEXTURE_HEADER_SIZE {
+        return Err(String::from("truncated texture header"));
+    }
+    let read_i32 = |offset: usize| -> i32 { LittleEndian::read_i32(&bytes[offset..(offset + 4)]) };
+    Ok(ImageHeader {
+        _width: read_i32(4).max(0) as u32,
+        _height: read_i32(8).max(0) as u32,
+        _depth: 1,
+        _layers: read_i32(12).max(1) as u32,
+        _mip_levels: if 0 != read_i32(20) { 0 } else { 1 }, // 0: the full mip chain is generated on load
+        _format: format!("{:?}", vk::Format::from_raw(read_i32(16))),
+    })
+}
+
+// counts the lines without building the geometry, the faces are counted as triangles.
+pub fn read_obj_header<R: Read>(reader: R) -> Result<MeshHeader, String> {
+    let mut mesh_header = MeshHeader::default();
+    for line in BufReader::new(reader).lines() {
+        let line = line.map_err(|e| format!("{}", e))?;
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("v") => mesh_header._vertex_count += 1,
+            Some("f") => mesh_header._face_count += tokens.count().max(2) - 2,
+            _ => (),
+        }
+    }
+    Ok(mesh_header)
+}
+
+pub fn read_image_header_from_file(file_path: &Path) -> Result<ImageHeader, String> {
+    let ext = file_path.extension().and_then(|ext| ext.to_str()).unwrap_or("").to_lowercase();
+    match ext.as_str() {
+        "png" => read_png_header(&read_header_bytes(file_path, PNG_HEADER_SIZE)?),
+        "ktx" => read_ktx_header(&read_header_bytes(file_path, KTX1_HEADER_SIZE)?),
+        "ktx2" => read_ktx_header(&read_header_bytes(file_path, KTX2_HEADER_SIZE)?),
+        "dds" => read_dds_header(&read_header_bytes_at_most(file_path, DDS_HEADER_SIZE)?),
+        "texture" => read_texture_header(&read_header_bytes(file_path, TEXTURE_HEADER_SIZE)?),
+        _ => {
+            // jpg, tga, bmp, the image crate reads only the header for the dimensions
+            let (width, height) = image::image_dimensions(file_path).map_err(|e| format!("{:?}: {}", file_path, e))?;
+            Ok(ImageHeader {
+                _width: width,
+                _height: height,
+                _depth: 1,
+                _layers: 1,
+                _mip_levels: 1,
+                _format: ext.to_uppercase(),
+            })
+        },
+    }
+}
+
+pub fn read_obj_header_from_file(file_path: &Path) -> Result<MeshHeader, String> {
+    let file = File::open(file_path).map_err(|e| format!("{:?}: {}", file_path, e))?;
+    read_obj_header(file)
+}
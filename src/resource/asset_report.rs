@@ -0,0 +1,581 @@
+use std::collections::{ BTreeMap, BTreeSet, HashMap };
+use std::fs;
+use std::path::{ Path, PathBuf };
+
+use serde::{ Serialize, Deserialize };
+use serde_json::{ self, Value };
+
+use crate::constants;
+use crate::resource::asset_header::{ self, ImageHeader, MeshHeader };
+use crate::resource::compressed_texture_loader::COMPRESSED_IMAGE_SOURCE_EXTS;
+use crate::resource::gltf_loader::GLTF;
+use crate::resource::resource::{
+    self,
+    ProjectResourcesBase,
+    Resources,
+    DEFAULT_MATERIAL_INSTANCE_NAME,
+    DEFAULT_MESH_NAME,
+    DEFAULT_MODEL_NAME,
+    DEFAULT_TEXTURE_NAME,
+    EXT_GLB,
+    EXT_GLTF,
+    EXT_MATERIAL,
+    EXT_MATERIAL_INSTANCE,
+    EXT_MODEL,
+    EXT_OBJ,
+    EXT_TEXTURE,
+    EXT_TEXTURE_2D_ARRAY,
+    EXT_TEXTURE_3D,
+    EXT_TEXTURE_CUBE,
+    IES_PROFILES_TEXTURE_NAME,
+    IMAGE_SOURCE_EXTS,
+    MATERIAL_FILE_PATH,
+    MATERIAL_INSTANCE_FILE_PATH,
+    MESH_SOURCE_EXTS,
+    MESH_SOURCE_FILE_PATH,
+    MODEL_FILE_PATH,
+    SCENE_FILE_PATH,
+    TEXTURE_FILE_PATH,
+    TEXTURE_SOURCE_FILE_PATH,
+};
+use crate::resource::texture_generator;
+
+// ex) --asset_report --asset_report_output=asset_report/report
+// the project console dispatches "asset report [output_path]" to execute_asset_report_command, the engine has no console.
+pub const ASSET_REPORT_ARG: &str = "--asset_report";
+pub const ASSET_REPORT_OUTPUT_ARG: &str = "--asset_report_output=";
+pub const ASSET_REPORT_COMMAND: &str = "asset report";
+pub const DEFAULT_ASSET_REPORT_OUTPUT_PATH: &str = "asset_report/report";
+pub const EXT_SCENE: &str = "scene";
+pub const BUILTIN_MESH_NAMES: [&str; 2] = ["quad", "cube"]; // see Resources::load_mesh_datas
+pub const ASSET_REPORT_EXIT_CODE_SUCCESS: i32 = 0;
+pub const ASSET_REPORT_EXIT_CODE_FAILED: i32 = 1;
+pub const ASSET_REPORT_SUMMARY_LINES: usize = 20;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AssetReportSettings {
+    pub _output_path: PathBuf, // without the extension, .json and .csv are written
+    pub _max_texture_size: u32,
+    pub _texture_usage_ratio: f32,
+    pub _max_mesh_vertex_count: usize,
+}
+
+impl Default for AssetReportSettings {
+    fn default() -> AssetReportSettings {
+        unsafe {
+            AssetReportSettings {
+                _output_path: PathBuf::from(DEFAULT_ASSET_REPORT_OUTPUT_PATH),
+                _max_texture_size: constants::ASSET_REPORT_MAX_TEXTURE_SIZE,
+                _texture_usage_ratio: constants::ASSET_REPORT_TEXTURE_USAGE_RATIO,
+                _max_mesh_vertex_count: constants::ASSET_REPORT_MAX_MESH_VERTEX_COUNT,
+            }
+        }
+    }
+}
+
+impl AssetReportSettings {
+    // None without --asset_report
+    pub fn create_asset_report_settings(args: &[String]) -> Option<AssetReportSettings> {
+        if false == args.iter().any(|arg| ASSET_REPORT_ARG == arg) {
+            return None;
+        }
+
+        let mut asset_report_settings = AssetReportSettings::default();
+        for arg in args.iter() {
+            if arg.starts_with(ASSET_REPORT_OUTPUT_ARG) {
+                asset_report_settings._output_path = PathBuf::from(&arg[ASSET_REPORT_OUTPUT_ARG.len()..]);
+            }
+        }
+        Some(asset_report_settings)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AssetType {
+    Texture,
+    Mesh,
+    Model,
+    Material,
+    MaterialInstance,
+    Scene,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AssetIssueType {
+    Unreferenced, // a candidate for the deletion, the references from the project code are not visible
+    OversizedTexture,
+    HeavyMesh,
+    MissingReference, // the loader silently falls back to the default resource, or panics for the meshes of the models
+    UnreadableAsset,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AssetIssue {
+    pub _issue_type: AssetIssueType,
+    pub _asset_type: AssetType,
+    pub _asset_name: String,
+    pub _layer_name: String,
+    pub _file_path: String,
+    pub _detail: String,
+}
+
+pub type AssetKey = (AssetType, String);
+
+#[derive(Clone, Debug, Default)]
+pub struct AssetNode {
+    pub _file_paths: Vec<PathBuf>, // the combined textures have the layer files as well
+    pub _references: Vec<AssetKey>,
+    pub _image_header: Option<ImageHeader>,
+    pub _mesh_header: Option<MeshHeader>,
+}
+
+// the assets on the disk and the references between them, the scenes and the engine defaults are the roots.
+#[derive(Clone, Debug, Default)]
+pub struct AssetReferenceGraph {
+    pub _nodes: BTreeMap<AssetKey, AssetNode>,
+    pub _roots: BTreeSet<AssetKey>,
+}
+
+impl AssetReferenceGraph {
+    pub fn add_asset(&mut self, asset_type: AssetType, asset_name: &str, file_path: Option<&PathBuf>) -> &mut AssetNode {
+        let asset_node = self._nodes.entry((asset_type, String::from(asset_name))).or_default();
+        if let Some(file_path) = file_path {
+            if false == asset_node._file_paths.contains(file_path) {
+                asset_node._file_paths.push(file_path.clone());
+            }
+        }
+        asset_node
+    }
+
+    pub fn has_asset(&self, asset_type: AssetType, asset_name: &str) -> bool {
+        self._nodes.contains_key(&(asset_type, String::from(asset_name)))
+    }
+
+    pub fn collect_referenced_assets(&self) -> BTreeSet<AssetKey> {
+        let mut referenced_assets: BTreeSet<AssetKey> = BTreeSet::new();
+        let mut stack: Vec<AssetKey> = self._roots.iter().cloned().collect();
+        while let Some(asset_key) = stack.pop() {
+            if referenced_assets.insert(asset_key.clone()) {
+                if let Some(asset_node) = self._nodes.get(&asset_key) {
+                    stack.extend(asset_node._references.iter().cloned());
+                }
+            }
+        }
+        referenced_assets
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct AssetReport {
+    pub _asset_counts: BTreeMap<String, usize>,
+    pub _issues: Vec<AssetIssue>,
+}
+
+impl AssetReport {
+    pub fn get_issue_count(&self, issue_type: AssetIssueType) -> usize {
+        self._issues.iter().filter(|issue| issue_type == issue._issue_type).count()
+    }
+
+    pub fn get_summary_text(&self) -> String {
+        let mut contents = String::from("asset report\n");
+        for (asset_type, asset_count) in self._asset_counts.iter() {
+            contents.push_str(&format!("    {}: {}\n", asset_type, asset_count));
+        }
+        for issue_type in [
+            AssetIssueType::Unreferenced,
+            AssetIssueType::OversizedTexture,
+            AssetIssueType::HeavyMesh,
+            AssetIssueType::MissingReference,
+            AssetIssueType::UnreadableAsset
+        ].iter() {
+            contents.push_str(&format!("    {:?}: {}\n", issue_type, self.get_issue_count(*issue_type)));
+        }
+        // the unreferenced assets are usually the most, the others are listed first
+        let mut issues: Vec<&AssetIssue> = self._issues.iter().collect();
+        issues.sort_by_key(|issue| AssetIssueType::Unreferenced == issue._issue_type);
+        for issue in issues.iter().take(ASSET_REPORT_SUMMARY_LINES) {
+            contents.push_str(&format!("    [{:?}] {:?} {} {}\n", issue._issue_type, issue._asset_type, issue._asset_name, issue._detail));
+        }
+        if ASSET_REPORT_SUMMARY_LINES < issues.len() {
+            contents.push_str(&format!("    ... {} more\n", issues.len() - ASSET_REPORT_SUMMARY_LINES));
+        }
+        contents
+    }
+
+    pub fn get_csv_text(&self) -> String {
+        let escape = |value: &str| -> String { format!("\"{}\"", value.replace("\"", "\"\"")) };
+        let mut contents = String::from("issue,asset_type,asset_name,layer,file_path,detail\n");
+        for issue in self._issues.iter() {
+            contents.push_str(&format!(
+                "{:?},{:?},{},{},{},{}\n",
+                issue._issue_type,
+                issue._asset_type,
+                escape(&issue._asset_name),
+                escape(&issue._layer_name),
+                escape(&issue._file_path),
+                escape(&issue._detail)
+            ));
+        }
+        contents
+    }
+
+    // output_path.json and output_path.csv
+    pub fn write_asset_report(&self, output_path: &Path) -> std::io::Result<()> {
+        if let Some(output_directory) = output_path.parent() {
+            if false == output_directory.as_os_str().is_empty() {
+                fs::create_dir_all(output_directory)?;
+            }
+        }
+        fs::write(output_path.with_extension("json"), serde_json::to_string_pretty(self).unwrap())?;
+        fs::write(output_path.with_extension("csv"), self.get_csv_text())?;
+        log::info!("{}", self.get_summary_text());
+        Ok(())
+    }
+}
+
+struct AssetScanner<'a> {
+    _resources: &'a Resources,
+    _graph: AssetReferenceGraph,
+    _issues: Vec<AssetIssue>,
+}
+
+impl<'a> AssetScanner<'a> {
+    fn get_layer_name(&self, file_path: &Path) -> String {
+        match self._resources._virtual_file_system.borrow().find_mount_point(file_path) {
+            Some(mount_point) => mount_point._layer_name.clone(),
+            None => String::new(),
+        }
+    }
+
+    fn add_issue(&mut self, issue_type: AssetIssueType, asset_type: AssetType, asset_name: &str, file_path: Option<&PathBuf>, detail: String) {
+        let (layer_name, file_path) = match file_path {
+            Some(file_path) => (self.get_layer_name(file_path), file_path.to_string_lossy().replace("\\", "/")),
+            None => (String::new(), String::new()),
+        };
+        self._issues.push(AssetIssue {
+            _issue_type: issue_type,
+            _asset_type: asset_type,
+            _asset_name: String::from(asset_name),
+            _layer_name: layer_name,
+            _file_path: file_path,
+            _detail: detail,
+        });
+    }
+
+    fn read_json(&mut self, asset_type: AssetType, asset_name: &str, file_path: &PathBuf) -> Option<Value> {
+        match serde_json::from_reader::<_, Value>(self._resources.read_bytes(file_path)) {
+            Ok(contents) => Some(contents),
+            Err(e) => {
+                self.add_issue(AssetIssueType::UnreadableAsset, asset_type, asset_name, Some(file_path), format!("{}", e));
+                None
+            },
+        }
+    }
+
+    fn scan_textures(&mut self) {
+        for texture_name in texture_generator::GENERATED_TEXTURE_NAMES.iter() {
+            self._graph.add_asset(AssetType::Texture, texture_name, None);
+        }
+        self._graph.add_asset(AssetType::Texture, IES_PROFILES_TEXTURE_NAME, None);
+
+        // the layer files of the combined textures belong to the combined texture, see Resources::load_texture_datas
+        let texture_source_directory = PathBuf::from(TEXTURE_SOURCE_FILE_PATH);
+        let mut combined_textures_name_map: HashMap<PathBuf, String> = HashMap::new();
+        let combined_texture_files = self._resources.collect_resources(&texture_source_directory, &[EXT_TEXTURE_2D_ARRAY, EXT_TEXTURE_3D, EXT_TEXTURE_CUBE]);
+        for combined_texture_file in combined_texture_files.iter() {
+            let texture_name = resource::get_resource_name_from_file_path(&texture_source_directory, combined_texture_file);
+            self._graph.add_asset(AssetType::Texture, &texture_name, Some(combined_texture_file));
+            let contents = match self.read_json(AssetType::Texture, &texture_name, combined_texture_file) {
+                Some(contents) => contents,
+                None => continue,
+            };
+            let layer_file_names: Vec<&str> = match &contents {
+                Value::Object(texture_cube_faces) => constants::CUBE_TEXTURE_FACES.iter().filter_map(|face| texture_cube_faces.get(*face).and_then(|value| value.as_str())).collect(),
+                Value::Array(texture_file_list) => texture_file_list.iter().filter_map(|value| value.as_str()).collect(),
+                _ => Vec::new(),
+            };
+            let directory = combined_texture_file.parent().unwrap();
+            for layer_file_name in layer_file_names {
+                let mut layer_file = PathBuf::from(directory);
+                layer_file.push(PathBuf::from(layer_file_name).file_name().unwrap());
+                combined_textures_name_map.insert(layer_file, texture_name.clone());
+            }
+        }
+
+        let mut image_source_exts: Vec<&str> = IMAGE_SOURCE_EXTS.to_vec();
+        image_source_exts.extend(COMPRESSED_IMAGE_SOURCE_EXTS.iter());
+        let mut texture_files: Vec<(String, PathBuf)> = Vec::new();
+        for texture_source_file in self._resources.collect_resources(&texture_source_directory, &image_source_exts) {
+            let texture_name = match combined_textures_name_map.get(&texture_source_file) {
+                Some(combined_texture_name) => combined_texture_name.clone(),
+                None => resource::get_resource_name_from_file_path(&texture_source_directory, &texture_source_file),
+            };
+            texture_files.push((texture_name, texture_source_file));
+        }
+        let texture_directory = PathBuf::from(TEXTURE_FILE_PATH);
+        for texture_file in self._resources.collect_resources(&texture_directory, &EXT_TEXTURE) {
+            texture_files.push((resource::get_resource_name_from_file_path(&texture_directory, &texture_file), texture_file));
+        }
+
+        // the largest layer is kept for the combined textures
+        for (texture_name, texture_file) in texture_files.iter() {
+            match asset_header::read_image_header_from_file(&self._resources.resolve_path(texture_file)) {
+                Ok(image_header) => {
+                    let asset_node = self._graph.add_asset(AssetType::Texture, texture_name, Some(texture_file));
+                    let is_larger = match asset_node._image_header.as_ref() {
+                        Some(prev_image_header) => prev_image_header._width.max(prev_image_header._height) < image_header._width.max(image_header._height),
+                        None => true,
+                    };
+                    if is_larger {
+                        asset_node._image_header = Some(image_header);
+                    }
+                },
+                Err(e) => {
+                    self._graph.add_asset(AssetType::Texture, texture_name, Some(texture_file));
+                    self.add_issue(AssetIssueType::UnreadableAsset, AssetType::Texture, texture_name, Some(texture_file), e);
+                },
+            }
+        }
+    }
+
+    fn scan_meshes(&mut self) {
+        for mesh_name in BUILTIN_MESH_NAMES.iter() {
+            self._graph.add_asset(AssetType::Mesh, mesh_name, None);
+        }
+        let mesh_source_directory = PathBuf::from(MESH_SOURCE_FILE_PATH);
+        for mesh_source_file in self._resources.collect_resources(&mesh_source_directory, &MESH_SOURCE_EXTS) {
+            let mesh_name = resource::get_resource_name_from_file_path(&mesh_source_directory, &mesh_source_file);
+            let file_path = self._resources.resolve_path(&mesh_source_file);
+            let mesh_header = match mesh_source_file.extension().and_then(|ext| ext.to_str()) {
+                Some(EXT_OBJ) => Some(asset_header::read_obj_header_from_file(&file_path)),
+                Some(EXT_GLTF) | Some(EXT_GLB) => Some(GLTF::read_mesh_header(&file_path)),
+                _ => None, // collada, the vertex count is unknown without the import
+            };
+            let mesh_header = match mesh_header {
+                Some(Ok(mesh_header)) => Some(mesh_header),
+                Some(Err(e)) => {
+                    self.add_issue(AssetIssueType::UnreadableAsset, AssetType::Mesh, &mesh_name, Some(&mesh_source_file), e);
+                    None
+                },
+                None => None,
+            };
+            self._graph.add_asset(AssetType::Mesh, &mesh_name, Some(&mesh_source_file))._mesh_header = mesh_header;
+        }
+    }
+
+    // the string values of the material parameters are the texture names
+    fn collect_texture_references(&mut self, asset_type: AssetType, asset_name: &str, file_path: &PathBuf, material_parameters: Option<&Value>) -> Vec<AssetKey> {
+        let mut references: Vec<AssetKey> = Vec::new();
+        if let Some(Value::Object(material_parameter_map)) = material_parameters {
+            for (material_parameter_name, material_parameter) in material_parameter_map.iter() {
+                if let Value::String(texture_name) = material_parameter {
+                    if false == self._graph.has_asset(AssetType::Texture, texture_name) {
+                        self.add_issue(
+                            AssetIssueType::MissingReference, asset_type, asset_name, Some(file_path),
+                            format!("{}: texture {} does not exist, {} is used", material_parameter_name, texture_name, DEFAULT_TEXTURE_NAME)
+                        );
+                    }
+                    references.push((AssetType::Texture, texture_name.clone()));
+                }
+            }
+        }
+        references
+    }
+
+    fn scan_definitions(&mut self, asset_type: AssetType, directory: &str, ext: &str) -> Vec<(String, PathBuf, Value)> {
+        let directory = PathBuf::from(directory);
+        let mut definitions: Vec<(String, PathBuf, Value)> = Vec::new();
+        for file_path in self._resources.collect_resources(&directory, &[ext]) {
+            let asset_name = resource::get_resource_name_from_file_path(&directory, &file_path);
+            self._graph.add_asset(asset_type, &asset_name, Some(&file_path));
+            if let Some(contents) = self.read_json(asset_type, &asset_name, &file_path) {
+                definitions.push((asset_name, file_path, contents));
+            }
+        }
+        definitions
+    }
+
+    fn scan_materials(&mut self) {
+        let materials = self.scan_definitions(AssetType::Material, MATERIAL_FILE_PATH, EXT_MATERIAL);
+        let material_instances = self.scan_definitions(AssetType::MaterialInstance, MATERIAL_INSTANCE_FILE_PATH, EXT_MATERIAL_INSTANCE);
+        for (material_name, material_file, contents) in materials.iter() {
+            let references = self.collect_texture_references(AssetType::Material, material_name, material_file, contents.get("material_parameters"));
+            self._graph.add_asset(AssetType::Material, material_name, None)._references = references;
+        }
+        for (material_instance_name, material_instance_file, contents) in material_instances.iter() {
+            let mut references = self.collect_texture_references(AssetType::MaterialInstance, material_instance_name, material_instance_file, contents.get("material_parameters"));
+            if let Some(Value::String(material_name)) = contents.get("material_name") {
+                if false == self._graph.has_asset(AssetType::Material, material_name) {
+                    self.add_issue(AssetIssueType::MissingReference, AssetType::MaterialInstance, material_instance_name, Some(material_instance_file), format!("material {} does not exist", material_name));
+                }
+                references.push((AssetType::Material, material_name.clone()));
+            }
+            self._graph.add_asset(AssetType::MaterialInstance, material_instance_name, None)._references = references;
+        }
+    }
+
+    fn scan_models(&mut self) {
+        let models = self.scan_definitions(AssetType::Model, MODEL_FILE_PATH, EXT_MODEL);
+        for (model_name, model_file, contents) in models.iter() {
+            let mut references: Vec<AssetKey> = Vec::new();
+            if let Some(Value::String(mesh_name)) = contents.get("mesh") {
+                if false == self._graph.has_asset(AssetType::Mesh, mesh_name) {
+                    self.add_issue(AssetIssueType::MissingReference, AssetType::Model, model_name, Some(model_file), format!("mesh {} does not exist", mesh_name));
+                }
+                references.push((AssetType::Mesh, mesh_name.clone()));
+            }
+            if let Some(Value::Array(material_instance_names)) = contents.get("material_instances") {
+                for material_instance_name in material_instance_names.iter().filter_map(|value| value.as_str()) {
+                    if false == self._graph.has_asset(AssetType::MaterialInstance, material_instance_name) {
+                        self.add_issue(
+                            AssetIssueType::MissingReference, AssetType::Model, model_name, Some(model_file),
+                            format!("material instance {} does not exist, {} is used", material_instance_name, DEFAULT_MATERIAL_INSTANCE_NAME)
+                        );
+                    }
+                    references.push((AssetType::MaterialInstance, String::from(material_instance_name)));
+                }
+            }
+            self._graph.add_asset(AssetType::Model, model_name, None)._references = references;
+        }
+    }
+
+    // the scene format belongs to the project, so every string of the scene which names an asset is a reference.
+    fn scan_scenes(&mut self) {
+        fn collect_strings<'b>(value: &'b Value, out_strings: &mut Vec<&'b str>) {
+            match value {
+                Value::String(value) => out_strings.push(value),
+                Value::Array(values) => values.iter().for_each(|value| collect_strings(value, out_strings)),
+                Value::Object(value_map) => value_map.values().for_each(|value| collect_strings(value, out_strings)),
+                _ => (),
+            }
+        }
+
+        let scenes = self.scan_definitions(AssetType::Scene, SCENE_FILE_PATH, EXT_SCENE);
+        for (scene_name, _scene_file, contents) in scenes.iter() {
+            let mut strings: Vec<&str> = Vec::new();
+            collect_strings(contents, &mut strings);
+            let mut references: Vec<AssetKey> = Vec::new();
+            for string in strings {
+                for asset_type in [AssetType::Model, AssetType::MaterialInstance, AssetType::Mesh, AssetType::Texture].iter() {
+                    if self._graph.has_asset(*asset_type, string) {
+                        references.push((*asset_type, String::from(string)));
+                    }
+                }
+            }
+            self._graph.add_asset(AssetType::Scene, scene_name, None)._references = references;
+            self._graph._roots.insert((AssetType::Scene, scene_name.clone()));
+        }
+
+        // the engine defaults and the generated textures are used by the engine code
+        self._graph._roots.insert((AssetType::Model, String::from(DEFAULT_MODEL_NAME)));
+        self._graph._roots.insert((AssetType::Mesh, String::from(DEFAULT_MESH_NAME)));
+        self._graph._roots.insert((AssetType::MaterialInstance, String::from(DEFAULT_MATERIAL_INSTANCE_NAME)));
+        self._graph._roots.insert((AssetType::Texture, String::from(DEFAULT_TEXTURE_NAME)));
+        self._graph._roots.insert((AssetType::Texture, String::from(IES_PROFILES_TEXTURE_NAME)));
+        for asset_name in texture_generator::GENERATED_TEXTURE_NAMES.iter() {
+            self._graph._roots.insert((AssetType::Texture, String::from(*asset_name)));
+        }
+        for asset_name in BUILTIN_MESH_NAMES.iter() {
+            self._graph._roots.insert((AssetType::Mesh, String::from(*asset_name)));
+        }
+    }
+
+    fn check_assets(&mut self, asset_report_settings: &AssetReportSettings, max_texture_usage_size_map: Option<&HashMap<String, u32>>) {
+        let referenced_assets = self._graph.collect_referenced_assets();
+        let nodes = std::mem::take(&mut self._graph._nodes);
+        for ((asset_type, asset_name), asset_node) in nodes.iter() {
+            let file_path = asset_node._file_paths.first();
+            if AssetType::Scene != *asset_type && file_path.is_some() && false == referenced_assets.contains(&(*asset_type, asset_name.clone())) {
+                self.add_issue(AssetIssueType::Unreferenced, *asset_type, asset_name, file_path, format!("{} file(s)", asset_node._file_paths.len()));
+            }
+
+            if let Some(image_header) = asset_node._image_header.as_ref() {
+                let texture_size = image_header._width.max(image_header._height);
+                let max_texture_usage_size = max_texture_usage_size_map.and_then(|size_map| size_map.get(asset_name));
+                let (max_texture_size, detail) = match max_texture_usage_size {
+                    Some(usage_size) => (
+                        (*usage_size as f32 * asset_report_settings._texture_usage_ratio).ceil() as u32,
+                        format!("largest on-screen size {}", usage_size),
+                    ),
+                    None => (asset_report_settings._max_texture_size, format!("max texture size {}", asset_report_settings._max_texture_size)),
+                };
+                if max_texture_size < texture_size {
+                    self.add_issue(
+                        AssetIssueType::OversizedTexture, *asset_type, asset_name, file_path,
+                        format!("{}x{} {}, {}", image_header._width, image_header._height, image_header._format, detail)
+                    );
+                }
+            }
+
+            if let Some(mesh_header) = asset_node._mesh_header.as_ref() {
+                if asset_report_settings._max_mesh_vertex_count < mesh_header._vertex_count {
+                    self.add_issue(
+                        AssetIssueType::HeavyMesh, *asset_type, asset_name, file_path,
+                        format!("{} vertices {} triangles, max vertex count {}", mesh_header._vertex_count, mesh_header._face_count, asset_report_settings._max_mesh_vertex_count)
+                    );
+                }
+            }
+        }
+        self._graph._nodes = nodes;
+    }
+}
+
+// scans the files of all vfs layers, nothing is loaded on the gpu.
+// the project passes the largest on-screen size per texture when it has measured them, the engine has no streaming feedback.
+pub fn generate_asset_report(resources: &Resources, asset_report_settings: &AssetReportSettings, max_texture_usage_size_map: Option<&HashMap<String, u32>>) -> AssetReport {
+    let mut asset_scanner = AssetScanner {
+        _resources: resources,
+        _graph: AssetReferenceGraph::default(),
+        _issues: Vec::new(),
+    };
+    asset_scanner.scan_textures();
+    asset_scanner.scan_meshes();
+    asset_scanner.scan_materials();
+    asset_scanner.scan_models();
+    asset_scanner.scan_scenes();
+    asset_scanner.check_assets(asset_report_settings, max_texture_usage_size_map);
+
+    let mut asset_report = AssetReport::default();
+    for (asset_type, _) in asset_scanner._graph._nodes.keys() {
+        *asset_report._asset_counts.entry(format!("{:?}", asset_type)).or_insert(0) += 1;
+    }
+    asset_report._issues = asset_scanner._issues;
+    asset_report
+}
+
+pub fn run_asset_report(resources: &Resources, asset_report_settings: &AssetReportSettings, max_texture_usage_size_map: Option<&HashMap<String, u32>>) -> std::io::Result<AssetReport> {
+    log::info!("run_asset_report: {:?}", asset_report_settings);
+    let asset_report = generate_asset_report(resources, asset_report_settings, max_texture_usage_size_map);
+    asset_report.write_asset_report(&asset_report_settings._output_path)?;
+    Ok(asset_report)
+}
+
+// ex) "asset report" or "asset report asset_report/mod_a", returns false for the other commands
+pub fn execute_asset_report_command(resources: &Resources, command: &str, max_texture_usage_size_map: Option<&HashMap<String, u32>>) -> bool {
+    let command = command.trim();
+    if false == command.starts_with(ASSET_REPORT_COMMAND) {
+        return false;
+    }
+    let mut asset_report_settings = AssetReportSettings::default();
+    let output_path = command[ASSET_REPORT_COMMAND.len()..].trim();
+    if false == output_path.is_empty() {
+        asset_report_settings._output_path = PathBuf::from(output_path);
+    }
+    if let Err(e) = run_asset_report(resources, &asset_report_settings, max_texture_usage_size_map) {
+        log::error!("asset report failed: {}", e);
+    }
+    true
+}
+
+// --asset_report runs before the window and the vulkan context are created and exits.
+pub fn run_asset_report_standalone(project_resources: *const dyn ProjectResourcesBase, asset_report_settings: &AssetReportSettings) -> i32 {
+    let mut resources = Resources::create_resources(project_resources);
+    resources.initialize_virtual_file_system();
+    match run_asset_report(&resources, asset_report_settings, None) {
+        Ok(_) => ASSET_REPORT_EXIT_CODE_SUCCESS,
+        Err(e) => {
+            log::error!("asset report failed: {}", e);
+            ASSET_REPORT_EXIT_CODE_FAILED
+        },
+    }
+}
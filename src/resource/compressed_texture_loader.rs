@@ -0,0 +1,587 @@
+use std::cmp::max;
+
+use ash::vk;
+use byteorder::{ BigEndian, ByteOrder, LittleEndian };
+
+use crate::resource::asset_header::{ KTX1_ENDIANNESS_REVERSED, KTX1_HEADER_SIZE, KTX1_IDENTIFIER, KTX2_IDENTIFIER };
+use crate::vulkan_context::texture;
+
+pub const EXT_KTX: &str = "ktx";
+pub const EXT_KTX2: &str = "ktx2";
+pub const EXT_DDS: &str = "dds";
+pub const COMPRESSED_IMAGE_SOURCE_EXTS: [&str; 3] = [EXT_KTX, EXT_KTX2, EXT_DDS];
+
+const KTX2_LEVEL_INDEX_OFFSET: usize = 80;
+const KTX2_LEVEL_INDEX_SIZE: usize = 24;
+const DDS_MAGIC: &[u8; 4] = b"DDS ";
+const DDS_HEADER_SIZE: usize = 128; // magic + DDS_HEADER
+const DDS_HEADER_DX10_SIZE: usize = 20;
+const DDSD_MIPMAPCOUNT: u32 = 0x20000;
+const DDSCAPS2_CUBEMAP: u32 = 0x200;
+const DDSCAPS2_VOLUME: u32 = 0x200000;
+const DDS_RESOURCE_MISC_TEXTURECUBE: u32 = 0x4;
+
+// mip-major like TextureCreateInfo::_texture_initial_datas, all layers of mip 0 and then mip 1..
+#[derive(Clone, Debug, Default)]
+pub struct CompressedTexture {
+    pub _width: u32,
+    pub _height: u32,
+    pub _depth: u32,
+    pub _layers: u32, // array layers * faces
+    pub _is_cube: bool,
+    pub _mip_levels: u32,
+    pub _format: vk::Format,
+    pub _datas: Vec<u8>,
+}
+
+impl CompressedTexture {
+    pub fn get_image_view_type(&self) -> vk::ImageViewType {
+        if self._is_cube {
+            vk::ImageViewType::CUBE
+        } else if 1 < self._depth {
+            vk::ImageViewType::TYPE_3D
+        } else if 1 < self._layers {
+            vk::ImageViewType::TYPE_2D_ARRAY
+        } else {
+            vk::ImageViewType::TYPE_2D
+        }
+    }
+
+    // TextureCreateInfo::_texture_layers, the depth of the 3d textures
+    pub fn get_texture_layers(&self) -> u32 {
+        if 1 < self._depth { self._depth } else { self._layers }
+    }
+
+    pub fn get_mip_size(&self, mip_level: u32) -> (u32, u32, u32) {
+        (max(1, self._width >> mip_level), max(1, self._height >> mip_level), max(1, self._depth >> mip_level))
+    }
+
+    pub fn get_mip_level_size(&self, mip_level: u32) -> usize {
+        let (width, height, depth) = self.get_mip_size(mip_level);
+        texture::get_compressed_mip_level_size(self._format, width, height, depth, self._layers)
+    }
+
+    pub fn get_decompressed_format(&self) -> vk::Format {
+        match self._format {
+            vk::Format::BC1_RGB_SRGB_BLOCK | vk::Format::BC1_RGBA_SRGB_BLOCK | vk::Format::BC3_SRGB_BLOCK | vk::Format::BC7_SRGB_BLOCK => vk::Format::R8G8B8A8_SRGB,
+            _ => vk::Format::R8G8B8A8_UNORM,
+        }
+    }
+
+    // for the devices without the bc formats, the mip chain is kept.
+    pub fn decompress_to_rgba8(&self) -> Vec<u8> {
+        let (_, block_bytes) = texture::get_compressed_block_info(self._format);
+        let mut rgba8_datas: Vec<u8> = Vec::new();
+        let mut block_offset: usize = 0;
+        let mut block_colors: [[u8; 4]; 16] = [[0; 4]; 16];
+        for mip_level in 0..self._mip_levels {
+            let (width, height, depth) = self.get_mip_size(mip_level);
+            let block_count_x = (width + 3) / 4;
+            let block_count_y = (height + 3) / 4;
+            for _slice in 0..(self._layers * depth) {
+                let mut image_data: Vec<u8> = vec![0; (width * height * 4) as usize];
+                for block_y in 0..block_count_y {
+                    for block_x in 0..block_count_x {
+                        let block = &self._datas[block_offset..(block_offset + block_bytes as usize)];
+                        block_offset += block_bytes as usize;
+                        decode_block(self._format, block, &mut block_colors);
+                        for (pixel_index, block_color) in block_colors.iter().enumerate() {
+                            let x = block_x * 4 + (pixel_index as u32 % 4);
+                            let y = block_y * 4 + (pixel_index as u32 / 4);
+                            if x < width && y < height {
+                                let offset = ((y * width + x) * 4) as usize;
+                                image_data[offset..(offset + 4)].copy_from_slice(block_color);
+                            }
+                        }
+                    }
+                }
+                rgba8_datas.extend_from_slice(&image_data);
+            }
+        }
+        rgba8_datas
+    }
+}
+
+fn get_format_from_gl_internal_format(gl_internal_format: u32) -> Option<vk::Format> {
+    match gl_internal_format {
+        0x83F0 => Some(vk::Format::BC1_RGB_UNORM_BLOCK), // GL_COMPRESSED_RGB_S3TC_DXT1_EXT
+        0x83F1 => Some(vk::Format::BC1_RGBA_UNORM_BLOCK), // GL_COMPRESSED_RGBA_S3TC_DXT1_EXT
+        0x83F3 => Some(vk::Format::BC3_UNORM_BLOCK), // GL_COMPRESSED_RGBA_S3TC_DXT5_EXT
+        0x8C4C => Some(vk::Format::BC1_RGB_SRGB_BLOCK), // GL_COMPRESSED_SRGB_S3TC_DXT1_EXT
+        0x8C4D => Some(vk::Format::BC1_RGBA_SRGB_BLOCK), // GL_COMPRESSED_SRGB_ALPHA_S3TC_DXT1_EXT
+        0x8C4F => Some(vk::Format::BC3_SRGB_BLOCK), // GL_COMPRESSED_SRGB_ALPHA_S3TC_DXT5_EXT
+        0x8E8C => Some(vk::Format::BC7_UNORM_BLOCK), // GL_COMPRESSED_RGBA_BPTC_UNORM
+        0x8E8D => Some(vk::Format::BC7_SRGB_BLOCK), // GL_COMPRESSED_SRGB_ALPHA_BPTC_UNORM
+        _ => None,
+    }
+}
+
+fn get_format_from_dxgi_format(dxgi_format: u32) -> Option<vk::Format> {
+    match dxgi_format {
+        71 => Some(vk::Format::BC1_RGBA_UNORM_BLOCK), // DXGI_FORMAT_BC1_UNORM
+        72 => Some(vk::Format::BC1_RGBA_SRGB_BLOCK),
+        77 => Some(vk::Format::BC3_UNORM_BLOCK), // DXGI_FORMAT_BC3_UNORM
+        78 => Some(vk::Format::BC3_SRGB_BLOCK),
+        98 => Some(vk::Format::BC7_UNORM_BLOCK), // DXGI_FORMAT_BC7_UNORM
+        99 => Some(vk::Format::BC7_SRGB_BLOCK),
+        _ => None,
+    }
+}
+
+fn read_level_data<'a>(bytes: &'a [u8], offset: usize, size: usize, mip_level: u32) -> Result<&'a [u8], String> {
+    if bytes.len() < (offset + size) {
+        return Err(format!("mip level {} is truncated, {} < {}", mip_level, bytes.len(), offset + size));
+    }
+    Ok(&bytes[offset..(offset + size)])
+}
+
+fn validate_compressed_texture(compressed_texture: &CompressedTexture) -> Result<(), String> {
+    if 0 == compressed_texture._width {
+        return Err(String::from("the 1d textures are not supported"));
+    }
+    if compressed_texture._is_cube && 6 != compressed_texture._layers {
+        return Err(String::from("the cube arrays are not supported"));
+    }
+    if 1 < compressed_texture._depth && 1 < compressed_texture._layers {
+        return Err(String::from("the 3d texture arrays are not supported"));
+    }
+    Ok(())
+}
+
+// ktx 1.1 and ktx 2.0 without the supercompression
+pub fn load_ktx(bytes: &[u8]) -> Result<CompressedTexture, String> {
+    if KTX1_HEADER_SIZE <= bytes.len() && KTX1_IDENTIFIER == bytes[0..12] {
+        let is_reversed = KTX1_ENDIANNESS_REVERSED == LittleEndian::read_u32(&bytes[12..16]);
+        let read_u32 = |offset: usize| -> u32 {
+            if is_reversed { BigEndian::read_u32(&bytes[offset..(offset + 4)]) } else { LittleEndian::read_u32(&bytes[offset..(offset + 4)]) }
+        };
+        let gl_internal_format = read_u32(28);
+        let format = get_format_from_gl_internal_format(gl_internal_format).ok_or(format!("unsupported glInternalFormat 0x{:04X}", gl_internal_format))?;
+        let array_count = read_u32(48);
+        let face_count = max(1, read_u32(52));
+        let mut compressed_texture = CompressedTexture {
+            _width: read_u32(36),
+            _height: max(1, read_u32(40)),
+            _depth: max(1, read_u32(44)),
+            _layers: max(1, array_count) * face_count,
+            _is_cube: 6 == face_count,
+            _mip_levels: max(1, read_u32(56)),
+            _format: format,
+            _datas: Vec::new(),
+        };
+        validate_compressed_texture(&compressed_texture)?;
+
+        let mut offset = KTX1_HEADER_SIZE + read_u32(60) as usize;
+        for mip_level in 0..compressed_texture._mip_levels {
+            let level_size = compressed_texture.get_mip_level_size(mip_level);
+            let image_size = read_level_data(bytes, offset, 4, mip_level).map(|_| read_u32(offset) as usize)?;
+            offset += 4;
+            // imageSize of the non-array cubemaps is the size of a face
+            let is_valid_image_size = level_size == image_size || (compressed_texture._is_cube && 0 == array_count && level_size == (image_size * 6));
+            if false == is_valid_image_size {
+                return Err(format!("mip level {} imageSize {} does not match with {}", mip_level, image_size, level_size));
+            }
+            compressed_texture._datas.extend_from_slice(read_level_data(bytes, offset, level_size, mip_level)?);
+            offset += (level_size + 3) & !3;
+        }
+        return Ok(compressed_texture);
+    }
+
+    if KTX2_IDENTIFIER.len() <= bytes.len() && KTX2_IDENTIFIER == bytes[0..12] {
+        if bytes.len() < KTX2_LEVEL_INDEX_OFFSET {
+            return Err(String::from("truncated ktx2 header"));
+        }
+        let read_u32 = |offset: usize| -> u32 { LittleEndian::read_u32(&bytes[offset..(offset + 4)]) };
+        let vk_format = vk::Format::from_raw(read_u32(12) as i32);
+        if false == texture::is_block_compressed_format(vk_format) {
+            return Err(format!("unsupported vkFormat {:?}", vk_format));
+        }
+        let supercompression_scheme = read_u32(44);
+        if 0 != supercompression_scheme {
+            return Err(format!("unsupported supercompressionScheme {}", supercompression_scheme));
+        }
+        let face_count = max(1, read_u32(36));
+        let mut compressed_texture = CompressedTexture {
+            _width: read_u32(20),
+            _height: max(1, read_u32(24)),
+            _depth: max(1, read_u32(28)),
+            _layers: max(1, read_u32(32)) * face_count,
+            _is_cube: 6 == face_count,
+            _mip_levels: max(1, read_u32(40)),
+            _format: vk_format,
+            _datas: Vec::new(),
+        };
+        validate_compressed_texture(&compressed_texture)?;
+
+        for mip_level in 0..compressed_texture._mip_levels {
+            let level_index_offset = KTX2_LEVEL_INDEX_OFFSET + KTX2_LEVEL_INDEX_SIZE * mip_level as usize;
+            let level_index = read_level_data(bytes, level_index_offset, KTX2_LEVEL_INDEX_SIZE, mip_level)?;
+            let byte_offset = LittleEndian::read_u64(&level_index[0..8]) as usize;
+            let byte_length = LittleEndian::read_u64(&level_index[8..16]) as usize;
+            let level_size = compressed_texture.get_mip_level_size(mip_level);
+            if level_size != byte_length {
+                return Err(format!("mip level {} byteLength {} does not match with {}", mip_level, byte_length, level_size));
+            }
+            compressed_texture._datas.extend_from_slice(read_level_data(bytes, byte_offset, level_size, mip_level)?);
+        }
+        return Ok(compressed_texture);
+    }
+    Err(String::from("not a ktx"))
+}
+
+// dds with DXT1, DXT5 or the DX10 header. the layers are stored with their whole mip chain, so they are reordered to mip-major.
+pub fn load_dds(bytes: &[u8]) -> Result<CompressedTexture, String> {
+    if bytes.len() < DDS_HEADER_SIZE || DDS_MAGIC != &bytes[0..4] {
+        return Err(String::from("not a dds"));
+    }
+    let read_u32 = |offset: usize| -> u32 { LittleEndian::read_u32(&bytes[offset..(offset + 4)]) };
+    let flags = read_u32(8);
+    let caps2 = read_u32(112);
+    let four_cc = &bytes[84..88];
+    let mut data_offset = DDS_HEADER_SIZE;
+    let mut array_count: u32 = 1;
+    let mut is_cube = 0 != (caps2 & DDSCAPS2_CUBEMAP);
+    let format = match four_cc {
+        b"DXT1" => vk::Format::BC1_RGBA_UNORM_BLOCK,
+        b"DXT5" => vk::Format::BC3_UNORM_BLOCK,
+        b"DX10" => {
+            if bytes.len() < (DDS_HEADER_SIZE + DDS_HEADER_DX10_SIZE) {
+                return Err(String::from("truncated dds dx10 header"));
+            }
+            data_offset += DDS_HEADER_DX10_SIZE;
+            let dxgi_format = read_u32(128);
+            is_cube = 0 != (read_u32(136) & DDS_RESOURCE_MISC_TEXTURECUBE);
+            array_count = max(1, read_u32(140));
+            get_format_from_dxgi_format(dxgi_format).ok_or(format!("unsupported dxgi format {}", dxgi_format))?
+        },
+        _ => return Err(format!("unsupported fourCC {:?}", String::from_utf8_lossy(four_cc))),
+    };
+    let face_count = if is_cube { 6 } else { 1 };
+    let mut compressed_texture = CompressedTexture {
+        _width: read_u32(16),
+        _height: max(1, read_u32(12)),
+        _depth: if 0 != (caps2 & DDSCAPS2_VOLUME) { max(1, read_u32(24)) } else { 1 },
+        _layers: array_count * face_count,
+        _is_cube: is_cube,
+        _mip_levels: if 0 != (flags & DDSD_MIPMAPCOUNT) { max(1, read_u32(28)) } else { 1 },
+        _format: format,
+        _datas: Vec::new(),
+    };
+    validate_compressed_texture(&compressed_texture)?;
+
+    // sizes of a mip level of a layer
+    let layer_mip_sizes: Vec<usize> = (0..compressed_texture._mip_levels).map(|mip_level| {
+        let (width, height, depth) = compressed_texture.get_mip_size(mip_level);
+        texture::get_compressed_mip_level_size(format, width, height, depth, 1)
+    }).collect();
+    let layer_size: usize = layer_mip_sizes.iter().sum();
+    read_level_data(bytes, data_offset, layer_size * compressed_texture._layers as usize, 0)?;
+    for mip_level in 0..compressed_texture._mip_levels as usize {
+        let mip_offset: usize = layer_mip_sizes[..mip_level].iter().sum();
+        for layer in 0..compressed_texture._layers as usize {
+            let offset = data_offset + layer * layer_size + mip_offset;
+            compressed_texture._datas.extend_from_slice(&bytes[offset..(offset + layer_mip_sizes[mip_level])]);
+        }
+    }
+    Ok(compressed_texture)
+}
+
+pub fn load_compressed_texture(ext: &str, bytes: &[u8]) -> Result<CompressedTexture, String> {
+    match ext {
+        EXT_KTX | EXT_KTX2 => load_ktx(bytes),
+        EXT_DDS => load_dds(bytes),
+        _ => Err(format!("unsupported compressed texture: {}", ext)),
+    }
+}
+
+// BC decoders for the fallback, the pixels of a 4x4 block in the row order.
+fn decode_block(format: vk::Format, block: &[u8], out_colors: &mut [[u8; 4]; 16]) {
+    match format {
+        vk::Format::BC1_RGB_UNORM_BLOCK | vk::Format::BC1_RGB_SRGB_BLOCK => {
+            decode_bc1_block(block, out_colors, false);
+            out_colors.iter_mut().for_each(|color| color[3] = 255);
+        },
+        vk::Format::BC1_RGBA_UNORM_BLOCK | vk::Format::BC1_RGBA_SRGB_BLOCK => decode_bc1_block(block, out_colors, false),
+        vk::Format::BC3_UNORM_BLOCK | vk::Format::BC3_SRGB_BLOCK => {
+            decode_bc1_block(&block[8..16], out_colors, true);
+            decode_bc3_alpha_block(&block[0..8], out_colors);
+        },
+        vk::Format::BC7_UNORM_BLOCK | vk::Format::BC7_SRGB_BLOCK => decode_bc7_block(block, out_colors),
+        _ => out_colors.iter_mut().for_each(|color| *color = [0; 4]),
+    }
+}
+
+fn rgb565_to_rgba8(color: u16) -> [u8; 4] {
+    let r = ((color >> 11) & 0x1F) as u8;
+    let g = ((color >> 5) & 0x3F) as u8;
+    let b = (color & 0x1F) as u8;
+    [(r << 3) | (r >> 2), (g << 2) | (g >> 4), (b << 3) | (b >> 2), 255]
+}
+
+// the color block of BC3 is always the 4 colors mode
+fn decode_bc1_block(block: &[u8], out_colors: &mut [[u8; 4]; 16], is_four_colors: bool) {
+    let color0 = LittleEndian::read_u16(&block[0..2]);
+    let color1 = LittleEndian::read_u16(&block[2..4]);
+    let indices = LittleEndian::read_u32(&block[4..8]);
+    let c0 = rgb565_to_rgba8(color0);
+    let c1 = rgb565_to_rgba8(color1);
+    let mut colors: [[u8; 4]; 4] = [c0, c1, [0; 4], [0; 4]];
+    for i in 0..3 {
+        if is_four_colors || color1 < color0 {
+            colors[2][i] = ((2 * c0[i] as u32 + c1[i] as u32) / 3) as u8;
+            colors[3][i] = ((c0[i] as u32 + 2 * c1[i] as u32) / 3) as u8;
+        } else {
+            colors[2][i] = ((c0[i] as u32 + c1[i] as u32) / 2) as u8;
+        }
+    }
+    colors[2][3] = 255;
+    colors[3][3] = if is_four_colors || color1 < color0 { 255 } else { 0 };
+    for (pixel_index, out_color) in out_colors.iter_mut().enumerate() {
+        *out_color = colors[((indices >> (pixel_index * 2)) & 0x3) as usize];
+    }
+}
+
+fn decode_bc3_alpha_block(block: &[u8], out_colors: &mut [[u8; 4]; 16]) {
+    let alpha0 = block[0] as u32;
+    let alpha1 = block[1] as u32;
+    let mut alphas: [u32; 8] = [alpha0, alpha1, 0, 0, 0, 0, 0, 255];
+    if alpha1 < alpha0 {
+        for i in 1..7 {
+            alphas[i + 1] = ((7 - i as u32) * alpha0 + i as u32 * alpha1) / 7;
+        }
+    } else {
+        for i in 1..5 {
+            alphas[i + 1] = ((5 - i as u32) * alpha0 + i as u32 * alpha1) / 5;
+        }
+        alphas[6] = 0;
+    }
+    let indices: u64 = LittleEndian::read_u48(&block[2..8]);
+    for (pixel_index, out_color) in out_colors.iter_mut().enumerate() {
+        out_color[3] = alphas[((indices >> (pixel_index * 3)) & 0x7) as usize] as u8;
+    }
+}
+
+struct Bc7ModeInfo {
+    _subset_count: usize,
+    _partition_bits: u32,
+    _rotation_bits: u32,
+    _index_selection_bits: u32,
+    _color_bits: u32,
+    _alpha_bits: u32,
+    _endpoint_pbits: bool,
+    _shared_pbits: bool,
+    _index_bits: u32,
+    _index2_bits: u32,
+}
+
+const BC7_MODE_INFOS: [Bc7ModeInfo; 8] = [
+    Bc7ModeInfo { _subset_count: 3, _partition_bits: 4, _rotation_bits: 0, _index_selection_bits: 0, _color_bits: 4, _alpha_bits: 0, _endpoint_pbits: true, _shared_pbits: false, _index_bits: 3, _index2_bits: 0 },
+    Bc7ModeInfo { _subset_count: 2, _partition_bits: 6, _rotation_bits: 0, _index_selection_bits: 0, _color_bits: 6, _alpha_bits: 0, _endpoint_pbits: false, _shared_pbits: true, _index_bits: 3, _index2_bits: 0 },
+    Bc7ModeInfo { _subset_count: 3, _partition_bits: 6, _rotation_bits: 0, _index_selection_bits: 0, _color_bits: 5, _alpha_bits: 0, _endpoint_pbits: false, _shared_pbits: false, _index_bits: 2, _index2_bits: 0 },
+    Bc7ModeInfo { _subset_count: 2, _partition_bits: 6, _rotation_bits: 0, _index_selection_bits: 0, _color_bits: 7, _alpha_bits: 0, _endpoint_pbits: true, _shared_pbits: false, _index_bits: 2, _index2_bits: 0 },
+    Bc7ModeInfo { _subset_count: 1, _partition_bits: 0, _rotation_bits: 2, _index_selection_bits: 1, _color_bits: 5, _alpha_bits: 6, _endpoint_pbits: false, _shared_pbits: false, _index_bits: 2, _index2_bits: 3 },
+    Bc7ModeInfo { _subset_count: 1, _partition_bits: 0, _rotation_bits: 2, _index_selection_bits: 0, _color_bits: 7, _alpha_bits: 8, _endpoint_pbits: false, _shared_pbits: false, _index_bits: 2, _index2_bits: 2 },
+    Bc7ModeInfo { _subset_count: 1, _partition_bits: 0, _rotation_bits: 0, _index_selection_bits: 0, _color_bits: 7, _alpha_bits: 7, _endpoint_pbits: true, _shared_pbits: false, _index_bits: 4, _index2_bits: 0 },
+    Bc7ModeInfo { _subset_count: 2, _partition_bits: 6, _rotation_bits: 0, _index_selection_bits: 0, _color_bits: 5, _alpha_bits: 5, _endpoint_pbits: true, _shared_pbits: false, _index_bits: 2, _index2_bits: 0 },
+];
+
+const BC7_WEIGHTS2: [u32; 4] = [0, 21, 43, 64];
+const BC7_WEIGHTS3: [u32; 8] = [0, 9, 18, 27, 37, 46, 55, 64];
+const BC7_WEIGHTS4: [u32; 16] = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+
+// bit i: the subset of the pixel i
+const BC7_PARTITIONS2: [u16; 64] = [
+    0xCCCC, 0x8888, 0xEEEE, 0xECC8, 0xC880, 0xFEEC, 0xFEC8, 0xEC80, 0xC800, 0xFFEC, 0xFE80, 0xE800, 0xFFE8, 0xFF00, 0xFFF0, 0xF000,
+    0xF710, 0x008E, 0x7100, 0x08CE, 0x008C, 0x7310, 0x3100, 0x8CCE, 0x088C, 0x3110, 0x6666, 0x366C, 0x17E8, 0x0FF0, 0x718E, 0x399C,
+    0xAAAA, 0xF0F0, 0x5A5A, 0x33CC, 0x3C3C, 0x55AA, 0x9696, 0xA55A, 0x73CE, 0x13C8, 0x324C, 0x3BDC, 0x6996, 0xC33C, 0x9966, 0x0660,
+    0x0272, 0x04E4, 0x4E40, 0x2720, 0xC936, 0x936C, 0x39C6, 0x639C, 0x9336, 0x9CC6, 0x817E, 0xE718, 0xCCF0, 0x0FCC, 0x7744, 0xEE22,
+];
+
+const BC7_PARTITIONS3: [[u8; 16]; 64] = [
+    [0, 0, 1, 1, 0, 0, 1, 1, 0, 2, 2, 1, 2, 2, 2, 2], [0, 0, 0, 1, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2, 2, 1],
+    [0, 0, 0, 0, 2, 0, 0, 1, 2, 2, 1, 1, 2, 2, 1, 1], [0, 2, 2, 2, 0, 0, 2, 2, 0, 0, 1, 1, 0, 1, 1, 1],
+    [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2], [0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 2, 2, 0, 0, 2, 2],
+    [0, 0, 2, 2, 0, 0, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1], [0, 0, 1, 1, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2, 1, 1],
+    [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2], [0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 2, 2],
+    [0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2], [0, 0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2],
+    [0, 1, 1, 2, 0, 1, 1, 2, 0, 1, 1, 2, 0, 1, 1, 2], [0, 1, 2, 2, 0, 1, 2, 2, 0, 1, 2, 2, 0, 1, 2, 2],
+    [0, 0, 1, 1, 0, 1, 1, 2, 1, 1, 2, 2, 1, 2, 2, 2], [0, 0, 1, 1, 2, 0, 0, 1, 2, 2, 0, 0, 2, 2, 2, 0],
+    [0, 0, 0, 1, 0, 0, 1, 1, 0, 1, 1, 2, 1, 1, 2, 2], [0, 1, 1, 1, 0, 0, 1, 1, 2, 0, 0, 1, 2, 2, 0, 0],
+    [0, 0, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2, 1, 1, 2, 2], [0, 0, 2, 2, 0, 0, 2, 2, 0, 0, 2, 2, 1, 1, 1, 1],
+    [0, 1, 1, 1, 0, 1, 1, 1, 0, 2, 2, 2, 0, 2, 2, 2], [0, 0, 0, 1, 0, 0, 0, 1, 2, 2, 2, 1, 2, 2, 2, 1],
+    [0, 0, 0, 0, 0, 0, 1, 1, 0, 1, 2, 2, 0, 1, 2, 2], [0, 0, 0, 0, 1, 1, 0, 0, 2, 2, 1, 0, 2, 2, 1, 0],
+    [0, 1, 2, 2, 0, 1, 2, 2, 0, 0, 1, 1, 0, 0, 0, 0], [0, 0, 1, 2, 0, 0, 1, 2, 1, 1, 2, 2, 2, 2, 2, 2],
+    [0, 1, 1, 0, 1, 2, 2, 1, 1, 2, 2, 1, 0, 1, 1, 0], [0, 0, 0, 0, 0, 1, 1, 0, 1, 2, 2, 1, 1, 2, 2, 1],
+    [0, 0, 2, 2, 1, 1, 0, 2, 1, 1, 0, 2, 0, 0, 2, 2], [0, 1, 1, 0, 0, 1, 1, 0, 2, 0, 0, 2, 2, 2, 2, 2],
+    [0, 0, 1, 1, 0, 1, 2, 2, 0, 1, 2, 2, 0, 0, 1, 1], [0, 0, 0, 0, 2, 0, 0, 0, 2, 2, 1, 1, 2, 2, 2, 1],
+    [0, 0, 0, 0, 0, 0, 0, 2, 1, 1, 2, 2, 1, 2, 2, 2], [0, 2, 2, 2, 0, 0, 2, 2, 0, 0, 1, 2, 0, 0, 1, 1],
+    [0, 0, 1, 1, 0, 0, 1, 2, 0, 0, 2, 2, 0, 2, 2, 2], [0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2, 0],
+    [0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 0, 0, 0, 0], [0, 1, 2, 0, 1, 2, 0, 1, 2, 0, 1, 2, 0, 1, 2, 0],
+    [0, 1, 2, 0, 2, 0, 1, 2, 1, 2, 0, 1, 0, 1, 2, 0], [0, 0, 1, 1, 2, 2, 0, 0, 1, 1, 2, 2, 0, 0, 1, 1],
+    [0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 0, 0, 0, 0, 1, 1], [0, 1, 0, 1, 0, 1, 0, 1, 2, 2, 2, 2, 2, 2, 2, 2],
+    [0, 0, 0, 0, 0, 0, 0, 0, 2, 1, 2, 1, 2, 1, 2, 1], [0, 0, 2, 2, 1, 1, 2, 2, 0, 0, 2, 2, 1, 1, 2, 2],
+    [0, 0, 2, 2, 0, 0, 1, 1, 0, 0, 2, 2, 0, 0, 1, 1], [0, 2, 2, 0, 1, 2, 2, 1, 0, 2, 2, 0, 1, 2, 2, 1],
+    [0, 1, 0, 1, 2, 2, 2, 2, 2, 2, 2, 2, 0, 1, 0, 1], [0, 0, 0, 0, 2, 1, 2, 1, 2, 1, 2, 1, 2, 1, 2, 1],
+    [0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 2, 2, 2, 2], [0, 2, 2, 2, 0, 1, 1, 1, 0, 2, 2, 2, 0, 1, 1, 1],
+    [0, 0, 0, 2, 1, 1, 1, 2, 0, 0, 0, 2, 1, 1, 1, 2], [0, 0, 0, 0, 2, 1, 1, 2, 2, 1, 1, 2, 2, 1, 1, 2],
+    [0, 2, 2, 2, 0, 1, 1, 1, 0, 1, 1, 1, 0, 2, 2, 2], [0, 0, 0, 2, 1, 1, 1, 2, 1, 1, 1, 2, 0, 0, 0, 2],
+    [0, 1, 1, 0, 0, 1, 1, 0, 0, 1, 1, 0, 2, 2, 2, 2], [0, 0, 0, 0, 0, 0, 0, 0, 2, 1, 1, 2, 2, 1, 1, 2],
+    [0, 1, 1, 0, 0, 1, 1, 0, 2, 2, 2, 2, 2, 2, 2, 2], [0, 0, 2, 2, 0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 2, 2],
+    [0, 0, 2, 2, 1, 1, 2, 2, 1, 1, 2, 2, 0, 0, 2, 2], [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 2],
+    [0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 1], [0, 2, 2, 2, 1, 2, 2, 2, 0, 2, 2, 2, 1, 2, 2, 2],
+    [0, 1, 0, 1, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2], [0, 1, 1, 1, 2, 0, 1, 1, 2, 2, 0, 1, 2, 2, 2, 0],
+];
+
+// the anchor pixels store their index with one bit less
+const BC7_ANCHORS2: [usize; 64] = [
+    15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15,
+    15, 2, 8, 2, 2, 8, 8, 15, 2, 8, 2, 2, 8, 8, 2, 2,
+    15, 15, 6, 8, 2, 8, 15, 15, 2, 8, 2, 2, 2, 15, 15, 6,
+    6, 2, 6, 8, 15, 15, 2, 2, 15, 15, 15, 15, 15, 2, 2, 15,
+];
+
+const BC7_ANCHORS3_1: [usize; 64] = [
+    3, 3, 15, 15, 8, 3, 15, 15, 8, 8, 6, 6, 6, 5, 3, 3,
+    3, 3, 8, 15, 3, 3, 6, 10, 5, 8, 8, 6, 8, 5, 15, 15,
+    8, 15, 3, 5, 6, 10, 8, 15, 15, 3, 15, 5, 15, 15, 15, 15,
+    3, 15, 5, 5, 5, 8, 5, 10, 5, 10, 8, 13, 15, 12, 3, 3,
+];
+
+const BC7_ANCHORS3_2: [usize; 64] = [
+    15, 8, 8, 3, 15, 15, 3, 8, 15, 15, 15, 15, 15, 15, 15, 8,
+    15, 8, 15, 3, 15, 8, 15, 8, 3, 15, 6, 10, 15, 15, 10, 8,
+    15, 3, 15, 10, 10, 8, 9, 10, 6, 15, 8, 15, 3, 6, 6, 8,
+    15, 3, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 3, 15, 15, 8,
+];
+
+struct BitReader {
+    _bits: u128,
+    _offset: u32,
+}
+
+impl BitReader {
+    fn read(&mut self, bit_count: u32) -> u32 {
+        let value = ((self._bits >> self._offset) & ((1u128 << bit_count) - 1)) as u32;
+        self._offset += bit_count;
+        value
+    }
+}
+
+fn bc7_interpolate(e0: u32, e1: u32, index: u32, index_bits: u32) -> u8 {
+    let weight = match index_bits {
+        2 => BC7_WEIGHTS2[index as usize],
+        3 => BC7_WEIGHTS3[index as usize],
+        _ => BC7_WEIGHTS4[index as usize],
+    };
+    (((64 - weight) * e0 + weight * e1 + 32) >> 6) as u8
+}
+
+fn decode_bc7_block(block: &[u8], out_colors: &mut [[u8; 4]; 16]) {
+    let mode = block[0].trailing_zeros() as usize;
+    if 8 <= mode {
+        // the reserved mode is decoded as the transparent black
+        out_colors.iter_mut().for_each(|color| *color = [0; 4]);
+        return;
+    }
+    let mode_info = &BC7_MODE_INFOS[mode];
+    let mut bits: u128 = 0;
+    for (i, byte) in block[0..16].iter().enumerate() {
+        bits |= (*byte as u128) << (i * 8);
+    }
+    let mut reader = BitReader { _bits: bits, _offset: mode as u32 + 1 };
+    let partition = reader.read(mode_info._partition_bits) as usize;
+    let rotation = reader.read(mode_info._rotation_bits);
+    let index_selection = reader.read(mode_info._index_selection_bits);
+
+    // endpoints, the alpha is 255 without the alpha bits
+    let endpoint_count = mode_info._subset_count * 2;
+    let mut endpoints: [[u32; 4]; 6] = [[0; 4]; 6];
+    for component in 0..3 {
+        for endpoint in endpoints.iter_mut().take(endpoint_count) {
+            endpoint[component] = reader.read(mode_info._color_bits);
+        }
+    }
+    if 0 < mode_info._alpha_bits {
+        for endpoint in endpoints.iter_mut().take(endpoint_count) {
+            endpoint[3] = reader.read(mode_info._alpha_bits);
+        }
+    }
+    let mut pbits: [u32; 6] = [0; 6];
+    if mode_info._endpoint_pbits {
+        for pbit in pbits.iter_mut().take(endpoint_count) {
+            *pbit = reader.read(1);
+        }
+    } else if mode_info._shared_pbits {
+        for subset in 0..mode_info._subset_count {
+            let pbit = reader.read(1);
+            pbits[subset * 2] = pbit;
+            pbits[subset * 2 + 1] = pbit;
+        }
+    }
+    let has_pbits = mode_info._endpoint_pbits || mode_info._shared_pbits;
+    for (endpoint_index, endpoint) in endpoints.iter_mut().take(endpoint_count).enumerate() {
+        for component in 0..4 {
+            let mut component_bits = if component < 3 { mode_info._color_bits } else { mode_info._alpha_bits };
+            if 0 == component_bits {
+                endpoint[component] = 255;
+                continue;
+            }
+            let mut value = endpoint[component];
+            if has_pbits {
+                value = (value << 1) | pbits[endpoint_index];
+                component_bits += 1;
+            }
+            value <<= 8 - component_bits;
+            endpoint[component] = value | (value >> component_bits);
+        }
+    }
+
+    // indices
+    let get_subset = |pixel_index: usize| -> usize {
+        match mode_info._subset_count {
+            2 => ((BC7_PARTITIONS2[partition] >> pixel_index) & 1) as usize,
+            3 => BC7_PARTITIONS3[partition][pixel_index] as usize,
+            _ => 0,
+        }
+    };
+    let is_anchor = |pixel_index: usize| -> bool {
+        match mode_info._subset_count {
+            2 => 0 == pixel_index || BC7_ANCHORS2[partition] == pixel_index,
+            3 => 0 == pixel_index || BC7_ANCHORS3_1[partition] == pixel_index || BC7_ANCHORS3_2[partition] == pixel_index,
+            _ => 0 == pixel_index,
+        }
+    };
+    let mut indices: [u32; 16] = [0; 16];
+    for (pixel_index, index) in indices.iter_mut().enumerate() {
+        *index = reader.read(mode_info._index_bits - if is_anchor(pixel_index) { 1 } else { 0 });
+    }
+    let mut indices2: [u32; 16] = [0; 16];
+    if 0 < mode_info._index2_bits {
+        for (pixel_index, index) in indices2.iter_mut().enumerate() {
+            *index = reader.read(mode_info._index2_bits - if 0 == pixel_index { 1 } else { 0 });
+        }
+    }
+
+    for (pixel_index, out_color) in out_colors.iter_mut().enumerate() {
+        let subset = get_subset(pixel_index);
+        let e0 = &endpoints[subset * 2];
+        let e1 = &endpoints[subset * 2 + 1];
+        let (color_index, color_index_bits, alpha_index, alpha_index_bits) = if 0 == mode_info._index2_bits {
+            (indices[pixel_index], mode_info._index_bits, indices[pixel_index], mode_info._index_bits)
+        } else if 0 == index_selection {
+            (indices[pixel_index], mode_info._index_bits, indices2[pixel_index], mode_info._index2_bits)
+        } else {
+            (indices2[pixel_index], mode_info._index2_bits, indices[pixel_index], mode_info._index_bits)
+        };
+        for component in 0..3 {
+            out_color[component] = bc7_interpolate(e0[component], e1[component], color_index, color_index_bits);
+        }
+        out_color[3] = bc7_interpolate(e0[3], e1[3], alpha_index, alpha_index_bits);
+        match rotation {
+            1 => out_color.swap(0, 3),
+            2 => out_color.swap(1, 3),
+            3 => out_color.swap(2, 3),
+            _ => (),
+        }
+    }
+}
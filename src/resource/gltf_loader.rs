@@ -0,0 +1,729 @@
+use std::collections::HashMap;
+use std::io::{ Cursor, Read };
+use std::path::PathBuf;
+
+use byteorder::{ LittleEndian, ReadBytesExt };
+use nalgebra::{
+    Matrix4,
+    Point3,
+    Quaternion,
+    UnitQuaternion,
+    Vector2,
+    Vector3,
+    Vector4,
+};
+use serde::{ Deserialize };
+use serde_json::{ self, Value };
+
+use crate::renderer::animation::{ SkeletonDataCreateInfo, SkeletonHierachyTree };
+use crate::renderer::mesh::{ MeshDataCreateInfo };
+use crate::resource::asset_header::MeshHeader;
+use crate::resource::mesh_welder::{ self, MeshImportSettings };
+use crate::vulkan_context::vulkan_context;
+use crate::vulkan_context::geometry_buffer::{
+    self,
+    GeometryCreateInfo,
+    SkeletalVertexData,
+    StaticVertexData,
+};
+use crate::utilities::bounding_box::calc_bounding_box;
+use crate::utilities::system;
+
+const GLB_MAGIC: u32 = 0x46546C67; // "glTF"
+const GLB_CHUNK_TYPE_JSON: u32 = 0x4E4F534A;
+const GLB_CHUNK_TYPE_BIN: u32 = 0x004E4942;
+const COMPONENT_TYPE_BYTE: u32 = 5120;
+const COMPONENT_TYPE_UNSIGNED_BYTE: u32 = 5121;
+const COMPONENT_TYPE_SHORT: u32 = 5122;
+const COMPONENT_TYPE_UNSIGNED_SHORT: u32 = 5123;
+const COMPONENT_TYPE_UNSIGNED_INT: u32 = 5125;
+const COMPONENT_TYPE_FLOAT: u32 = 5126;
+const PRIMITIVE_MODE_TRIANGLES: u32 = 4;
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct GltfBuffer {
+    #[serde(rename = "uri")]
+    pub _uri: Option<String>,
+    #[serde(rename = "byteLength")]
+    pub _byte_length: usize,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct GltfBufferView {
+    #[serde(rename = "buffer")]
+    pub _buffer: usize,
+    #[serde(rename = "byteOffset")]
+    pub _byte_offset: usize,
+    #[serde(rename = "byteLength")]
+    pub _byte_length: usize,
+    #[serde(rename = "byteStride")]
+    pub _byte_stride: Option<usize>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct GltfAccessor {
+    #[serde(rename = "bufferView")]
+    pub _buffer_view: Option<usize>,
+    #[serde(rename = "byteOffset")]
+    pub _byte_offset: usize,
+    #[serde(rename = "componentType")]
+    pub _component_type: u32,
+    #[serde(rename = "normalized")]
+    pub _normalized: bool,
+    #[serde(rename = "count")]
+    pub _count: usize,
+    #[serde(rename = "type")]
+    pub _type: String,
+    #[serde(rename = "sparse")]
+    pub _sparse: Option<Value>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct GltfPrimitive {
+    #[serde(rename = "attributes")]
+    pub _attributes: HashMap<String, usize>,
+    #[serde(rename = "indices")]
+    pub _indices: Option<usize>,
+    #[serde(rename = "material")]
+    pub _material: Option<usize>,
+    #[serde(rename = "mode")]
+    pub _mode: Option<u32>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct GltfMesh {
+    #[serde(rename = "name")]
+    pub _name: Option<String>,
+    #[serde(rename = "primitives")]
+    pub _primitives: Vec<GltfPrimitive>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct GltfMaterial {
+    #[serde(rename = "name")]
+    pub _name: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct GltfNode {
+    #[serde(rename = "name")]
+    pub _name: Option<String>,
+    #[serde(rename = "children")]
+    pub _children: Vec<usize>,
+    #[serde(rename = "mesh")]
+    pub _mesh: Option<usize>,
+    #[serde(rename = "skin")]
+    pub _skin: Option<usize>,
+    #[serde(rename = "matrix")]
+    pub _matrix: Option<Vec<f32>>, // column major
+    #[serde(rename = "translation")]
+    pub _translation: Option<Vec<f32>>,
+    #[serde(rename = "rotation")]
+    pub _rotation: Option<Vec<f32>>, // x, y, z, w
+    #[serde(rename = "scale")]
+    pub _scale: Option<Vec<f32>>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct GltfSkin {
+    #[serde(rename = "name")]
+    pub _name: Option<String>,
+    #[serde(rename = "inverseBindMatrices")]
+    pub _inverse_bind_matrices: Option<usize>,
+    #[serde(rename = "joints")]
+    pub _joints: Vec<usize>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct GltfScene {
+    #[serde(rename = "nodes")]
+    pub _nodes: Vec<usize>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct GltfDocument {
+    #[serde(rename = "buffers")]
+    pub _buffers: Vec<GltfBuffer>,
+    #[serde(rename = "bufferViews")]
+    pub _buffer_views: Vec<GltfBufferView>,
+    #[serde(rename = "accessors")]
+    pub _accessors: Vec<GltfAccessor>,
+    #[serde(rename = "meshes")]
+    pub _meshes: Vec<GltfMesh>,
+    #[serde(rename = "materials")]
+    pub _materials: Vec<GltfMaterial>,
+    #[serde(rename = "nodes")]
+    pub _nodes: Vec<GltfNode>,
+    #[serde(rename = "skins")]
+    pub _skins: Vec<GltfSkin>,
+    #[serde(rename = "scenes")]
+    pub _scenes: Vec<GltfScene>,
+    #[serde(rename = "scene")]
+    pub _scene: Option<usize>,
+}
+
+// the elements of an accessor inside of the buffer, the stride is the byte stride of the buffer view or tightly packed.
+struct AccessorView<'a> {
+    _data: &'a [u8],
+    _stride: usize,
+    _count: usize,
+    _component_count: usize,
+    _component_type: u32,
+    _normalized: bool,
+}
+
+impl<'a> AccessorView<'a> {
+    fn component_offset(&self, element: usize, component: usize) -> usize {
+        element * self._stride + component * get_component_size(self._component_type)
+    }
+
+    fn read_u32(&self, element: usize, component: usize) -> u32 {
+        let offset = self.component_offset(element, component);
+        let data = &self._data[offset..];
+        match self._component_type {
+            COMPONENT_TYPE_UNSIGNED_BYTE => data[0] as u32,
+            COMPONENT_TYPE_UNSIGNED_SHORT => u16::from_le_bytes([data[0], data[1]]) as u32,
+            COMPONENT_TYPE_UNSIGNED_INT => u32::from_le_bytes([data[0], data[1], data[2], data[3]]),
+            _ => self.read_f32(element, component) as u32,
+        }
+    }
+
+    fn read_f32(&self, element: usize, component: usize) -> f32 {
+        let offset = self.component_offset(element, component);
+        let data = &self._data[offset..];
+        match self._component_type {
+            COMPONENT_TYPE_FLOAT => f32::from_le_bytes([data[0], data[1], data[2], data[3]]),
+            COMPONENT_TYPE_BYTE => {
+                let value = data[0] as i8 as f32;
+                if self._normalized { (value / 127.0).max(-1.0) } else { value }
+            },
+            COMPONENT_TYPE_UNSIGNED_BYTE => {
+                let value = data[0] as f32;
+                if self._normalized { value / 255.0 } else { value }
+            },
+            COMPONENT_TYPE_SHORT => {
+                let value = i16::from_le_bytes([data[0], data[1]]) as f32;
+                if self._normalized { (value / 32767.0).max(-1.0) } else { value }
+            },
+            COMPONENT_TYPE_UNSIGNED_SHORT => {
+                let value = u16::from_le_bytes([data[0], data[1]]) as f32;
+                if self._normalized { value / 65535.0 } else { value }
+            },
+            COMPONENT_TYPE_UNSIGNED_INT => u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as f32,
+            _ => 0.0,
+        }
+    }
+}
+
+fn get_component_size(component_type: u32) -> usize {
+    match component_type {
+        COMPONENT_TYPE_BYTE | COMPONENT_TYPE_UNSIGNED_BYTE => 1,
+        COMPONENT_TYPE_SHORT | COMPONENT_TYPE_UNSIGNED_SHORT => 2,
+        _ => 4,
+    }
+}
+
+fn get_component_count(accessor_type: &str) -> usize {
+    match accessor_type {
+        "VEC2" => 2,
+        "VEC3" => 3,
+        "VEC4" => 4,
+        "MAT2" => 4,
+        "MAT3" => 9,
+        "MAT4" => 16,
+        _ => 1,
+    }
+}
+
+fn decode_base64(contents: &str) -> Result<Vec<u8>, String> {
+    let mut decoded: Vec<u8> = Vec::with_capacity(contents.len() * 3 / 4);
+    let mut bits: u32 = 0;
+    let mut bit_count: u32 = 0;
+    for c in contents.bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            b'=' | b'\n' | b'\r' | b' ' => continue,
+            _ => return Err(format!("invalid base64 character {:?}", c as char)),
+        } as u32;
+        bits = (bits << 6) | value;
+        bit_count += 6;
+        if 8 <= bit_count {
+            bit_count -= 8;
+            decoded.push((bits >> bit_count) as u8);
+            bits &= (1 << bit_count) - 1;
+        }
+    }
+    Ok(decoded)
+}
+
+// the meshes and the skins of .gltf and .glb. the animations are not imported, a skinned mesh has the skeleton and the bind pose only,
+// ex) play the animations of the collada mesh of the same rig as the _animation_mesh of AnimationPlayInfo.
+pub struct GLTF {
+    pub _document: GltfDocument,
+    pub _buffers: Vec<Vec<u8>>,
+    pub _filename: PathBuf,
+}
+
+impl GLTF {
+    // the json document and the binary chunk of .glb, the buffers are not decoded.
+    fn read_document(contents: Vec<u8>) -> Result<(GltfDocument, Option<Vec<u8>>), String> {
+        let mut glb_binary_chunk: Option<Vec<u8>> = None;
+        let json_chunk: Vec<u8> = if 4 <= contents.len() && GLB_MAGIC == u32::from_le_bytes([contents[0], contents[1], contents[2], contents[3]]) {
+            let mut json_chunk: Option<Vec<u8>> = None;
+            let mut reader = Cursor::new(&contents);
+            let _magic = reader.read_u32::<LittleEndian>().map_err(|e| format!("{:?}", e))?;
+            let version = reader.read_u32::<LittleEndian>().map_err(|e| format!("{:?}", e))?;
+            let length = reader.read_u32::<LittleEndian>().map_err(|e| format!("{:?}", e))? as usize;
+            if 2 != version {
+                return Err(format!("unsupported glb version {}", version));
+            }
+            while (reader.position() as usize + 8) <= length.min(contents.len()) {
+                let chunk_length = reader.read_u32::<LittleEndian>().map_err(|e| format!("{:?}", e))? as usize;
+                let chunk_type = reader.read_u32::<LittleEndian>().map_err(|e| format!("{:?}", e))?;
+                let chunk_offset = reader.position() as usize;
+                if contents.len() < (chunk_offset + chunk_length) {
+                    return Err(String::from("truncated glb chunk"));
+                }
+                let chunk = contents[chunk_offset..(chunk_offset + chunk_length)].to_vec();
+                match chunk_type {
+                    GLB_CHUNK_TYPE_JSON => json_chunk = Some(chunk),
+                    GLB_CHUNK_TYPE_BIN => glb_binary_chunk = Some(chunk),
+                    _ => (), // unknown chunks must be ignored
+                }
+                reader.set_position((chunk_offset + chunk_length) as u64);
+            }
+            json_chunk.ok_or(String::from("glb has no json chunk"))?
+        } else {
+            contents
+        };
+
+        let document: GltfDocument = serde_json::from_slice(&json_chunk).map_err(|e| format!("{}", e))?;
+        Ok((document, glb_binary_chunk))
+    }
+
+    // .gltf with the external or the embedded buffers, or .glb with the binary chunk
+    fn load(filename: &PathBuf) -> Result<GLTF, String> {
+        let mut contents: Vec<u8> = Vec::new();
+        if let Err(e) = system::load(filename).read_to_end(&mut contents) {
+            return Err(format!("{:?}", e));
+        }
+
+        let (document, mut glb_binary_chunk) = GLTF::read_document(contents)?;
+        let mut buffers: Vec<Vec<u8>> = Vec::new();
+        for (buffer_index, buffer) in document._buffers.iter().enumerate() {
+            let buffer_data = match buffer._uri.as_ref() {
+                None => glb_binary_chunk.take().ok_or(format!("buffer[{}] has no uri", buffer_index))?,
+                Some(uri) if uri.starts_with("data:") => match uri.find(";base64,") {
+                    Some(data_offset) => decode_base64(&uri[(data_offset + ";base64,".len())..])?,
+                    None => return Err(format!("buffer[{}] unsupported data uri", buffer_index)),
+                },
+                Some(uri) => {
+                    let mut buffer_file_path = PathBuf::from(filename.parent().unwrap());
+                    buffer_file_path.push(uri.replace("%20", " "));
+                    system::load(&buffer_file_path).into_inner()
+                },
+            };
+            if buffer_data.len() < buffer._byte_length {
+                return Err(format!("buffer[{}] is truncated, {} < {}", buffer_index, buffer_data.len(), buffer._byte_length));
+            }
+            buffers.push(buffer_data);
+        }
+
+        Ok(GLTF {
+            _document: document,
+            _buffers: buffers,
+            _filename: filename.clone(),
+        })
+    }
+
+    fn get_accessor_view(&self, accessor_index: usize, min_component_count: usize) -> Result<AccessorView, String> {
+        let accessor = self._document._accessors.get(accessor_index).ok_or(format!("accessor[{}] does not exist", accessor_index))?;
+        if accessor._sparse.is_some() {
+            log::warn!("{:?}: accessor[{}] the sparse values are not supported, ignored", self._filename, accessor_index);
+        }
+        let buffer_view_index = accessor._buffer_view.ok_or(format!("accessor[{}] has no buffer view", accessor_index))?;
+        let buffer_view = self._document._buffer_views.get(buffer_view_index).ok_or(format!("bufferView[{}] does not exist", buffer_view_index))?;
+        let buffer = self._buffers.get(buffer_view._buffer).ok_or(format!("buffer[{}] does not exist", buffer_view._buffer))?;
+        let component_count = get_component_count(&accessor._type);
+        if component_count < min_component_count {
+            return Err(format!("accessor[{}] type {} has less than {} components", accessor_index, accessor._type, min_component_count));
+        }
+        let element_size = get_component_size(accessor._component_type) * component_count;
+        let stride = buffer_view._byte_stride.unwrap_or(element_size).max(element_size);
+        let data_offset = buffer_view._byte_offset + accessor._byte_offset;
+        let data_size = if 0 < accessor._count { stride * (accessor._count - 1) + element_size } else { 0 };
+        let buffer_view_end = buffer_view._byte_offset + buffer_view._byte_length;
+        if buffer_view_end < (data_offset + data_size) || buffer.len() < buffer_view_end {
+            return Err(format!("accessor[{}] is out of the buffer view", accessor_index));
+        }
+        Ok(AccessorView {
+            _data: &buffer[data_offset..(data_offset + data_size)],
+            _stride: stride,
+            _count: accessor._count,
+            _component_count: component_count,
+            _component_type: accessor._component_type,
+            _normalized: accessor._normalized,
+        })
+    }
+
+    fn read_vector2s(&self, accessor_index: usize) -> Result<Vec<Vector2<f32>>, String> {
+        let view = self.get_accessor_view(accessor_index, 2)?;
+        Ok((0..view._count).map(|i| Vector2::new(view.read_f32(i, 0), view.read_f32(i, 1))).collect())
+    }
+
+    fn read_vector3s(&self, accessor_index: usize) -> Result<Vec<Vector3<f32>>, String> {
+        let view = self.get_accessor_view(accessor_index, 3)?;
+        Ok((0..view._count).map(|i| Vector3::new(view.read_f32(i, 0), view.read_f32(i, 1), view.read_f32(i, 2))).collect())
+    }
+
+    // COLOR_0 is vec3 or vec4
+    fn read_vector4s(&self, accessor_index: usize, default_w: f32) -> Result<Vec<Vector4<f32>>, String> {
+        let view = self.get_accessor_view(accessor_index, 3)?;
+        Ok((0..view._count).map(|i| Vector4::new(
+            view.read_f32(i, 0),
+            view.read_f32(i, 1),
+            view.read_f32(i, 2),
+            if 4 <= view._component_count { view.read_f32(i, 3) } else { default_w }
+        )).collect())
+    }
+
+    fn read_uvector4s(&self, accessor_index: usize) -> Result<Vec<Vector4<u32>>, String> {
+        let view = self.get_accessor_view(accessor_index, 4)?;
+        Ok((0..view._count).map(|i| Vector4::new(view.read_u32(i, 0), view.read_u32(i, 1), view.read_u32(i, 2), view.read_u32(i, 3))).collect())
+    }
+
+    // u8, u16 and u32 indices
+    fn read_indices(&self, accessor_index: usize) -> Result<Vec<u32>, String> {
+        let view = self.get_accessor_view(accessor_index, 1)?;
+        Ok((0..view._count).map(|i| view.read_u32(i, 0)).collect())
+    }
+
+    fn read_matrices(&self, accessor_index: usize) -> Result<Vec<Matrix4<f32>>, String> {
+        let view = self.get_accessor_view(accessor_index, 16)?;
+        Ok((0..view._count).map(|i| {
+            let values: Vec<f32> = (0..16).map(|component| view.read_f32(i, component)).collect();
+            Matrix4::from_column_slice(&values)
+        }).collect())
+    }
+
+    fn get_node_local_matrix(node: &GltfNode) -> Matrix4<f32> {
+        if let Some(matrix) = node._matrix.as_ref() {
+            if 16 == matrix.len() {
+                return Matrix4::from_column_slice(matrix);
+            }
+        }
+        let translation = match node._translation.as_ref() {
+            Some(t) if 3 == t.len() => Vector3::new(t[0], t[1], t[2]),
+            _ => Vector3::zeros(),
+        };
+        let rotation = match node._rotation.as_ref() {
+            Some(r) if 4 == r.len() => UnitQuaternion::from_quaternion(Quaternion::new(r[3], r[0], r[1], r[2])),
+            _ => UnitQuaternion::identity(),
+        };
+        let scale = match node._scale.as_ref() {
+            Some(s) if 3 == s.len() => Vector3::new(s[0], s[1], s[2]),
+            _ => Vector3::new(1.0, 1.0, 1.0),
+        };
+        Matrix4::new_translation(&translation) * rotation.to_homogeneous() * Matrix4::new_nonuniform_scaling(&scale)
+    }
+
+    fn get_node_name(&self, node_index: usize) -> String {
+        match self._document._nodes.get(node_index).and_then(|node| node._name.as_ref()) {
+            Some(name) if false == name.is_empty() => name.clone(),
+            _ => format!("node_{}", node_index),
+        }
+    }
+
+    // (node index, world matrix) of the mesh nodes in the default scene, or every mesh when the file has no node.
+    fn collect_mesh_nodes(&self) -> Vec<(Option<usize>, usize, Matrix4<f32>)> {
+        let mut mesh_nodes: Vec<(Option<usize>, usize, Matrix4<f32>)> = Vec::new();
+        if self._document._nodes.is_empty() {
+            for mesh_index in 0..self._document._meshes.len() {
+                mesh_nodes.push((None, mesh_index, Matrix4::identity()));
+            }
+            return mesh_nodes;
+        }
+
+        let root_nodes: Vec<usize> = match self._document._scenes.get(self._document._scene.unwrap_or(0)) {
+            Some(scene) => scene._nodes.clone(),
+            None => {
+                let child_nodes: Vec<usize> = self._document._nodes.iter().flat_map(|node| node._children.iter().cloned()).collect();
+                (0..self._document._nodes.len()).filter(|node_index| false == child_nodes.contains(node_index)).collect()
+            }
+        };
+        let mut stack: Vec<(usize, Matrix4<f32>)> = root_nodes.iter().rev().map(|node_index| (*node_index, Matrix4::identity())).collect();
+        while let Some((node_index, parent_matrix)) = stack.pop() {
+            let node = match self._document._nodes.get(node_index) {
+                Some(node) => node,
+                None => continue,
+            };
+            let world_matrix = parent_matrix * GLTF::get_node_local_matrix(node);
+            if let Some(mesh_index) = node._mesh {
+                mesh_nodes.push((Some(node_index), mesh_index, world_matrix));
+            }
+            for child_index in node._children.iter().rev() {
+                stack.push((*child_index, world_matrix));
+            }
+        }
+        mesh_nodes
+    }
+
+    fn build_hierachy(&self, joints: &[usize], node_index: usize, hierachy_tree: &mut SkeletonHierachyTree) {
+        let node = match self._document._nodes.get(node_index) {
+            Some(node) => node,
+            None => return,
+        };
+        for child_index in node._children.iter() {
+            if joints.contains(child_index) {
+                let child_name = self.get_node_name(*child_index);
+                hierachy_tree._children.insert(child_name.clone(), SkeletonHierachyTree::default());
+                self.build_hierachy(joints, *child_index, hierachy_tree._children.get_mut(&child_name).unwrap());
+            } else {
+                // a helper node between the joints
+                self.build_hierachy(joints, *child_index, hierachy_tree);
+            }
+        }
+    }
+
+    // the bone index of JOINTS_0 is the index of skin.joints, so the bone names follow the joint order.
+    fn get_skeleton_datas(&self) -> Result<Vec<SkeletonDataCreateInfo>, String> {
+        let mut skeleton_datas: Vec<SkeletonDataCreateInfo> = Vec::new();
+        for (skin_index, skin) in self._document._skins.iter().enumerate() {
+            let inv_bind_matrices = match skin._inverse_bind_matrices {
+                Some(accessor_index) => self.read_matrices(accessor_index)?,
+                None => vec![Matrix4::identity(); skin._joints.len()],
+            };
+            let mut hierachy = SkeletonHierachyTree::default();
+            for joint_index in skin._joints.iter() {
+                let is_root_joint = false == self._document._nodes.iter().enumerate().any(|(node_index, node)| {
+                    skin._joints.contains(&node_index) && node._children.contains(joint_index)
+                });
+                if is_root_joint {
+                    let joint_name = self.get_node_name(*joint_index);
+                    hierachy._children.insert(joint_name.clone(), SkeletonHierachyTree::default());
+                    self.build_hierachy(&skin._joints, *joint_index, hierachy._children.get_mut(&joint_name).unwrap());
+                }
+            }
+            skeleton_datas.push(SkeletonDataCreateInfo {
+                _name: skin._name.clone().unwrap_or(format!("skin_{}", skin_index)),
+                _hierachy: hierachy,
+                _bone_names: skin._joints.iter().map(|joint_index| self.get_node_name(*joint_index)).collect(),
+                _inv_bind_matrices: inv_bind_matrices,
+            });
+        }
+        Ok(skeleton_datas)
+    }
+
+    // a primitive is a geometry, the skinned primitives are in the bind pose so the node transform is not applied.
+    fn generate_geometry_data(
+        &self,
+        mesh_name: &str,
+        primitive: &GltfPrimitive,
+        world_matrix: Option<&Matrix4<f32>>,
+        mesh_import_settings: &MeshImportSettings
+    ) -> Result<Option<GeometryCreateInfo>, String> {
+        let mode = primitive._mode.unwrap_or(PRIMITIVE_MODE_TRIANGLES);
+        if PRIMITIVE_MODE_TRIANGLES != mode {
+            log::warn!("{:?} {}: primitive mode {} is not supported, skipped", self._filename, mesh_name, mode);
+            return Ok(None);
+        }
+        let attribute = |name: &str| primitive._attributes.get(name).cloned();
+        let mut positions = self.read_vector3s(attribute("POSITION").ok_or(format!("{} has no POSITION", mesh_name))?)?;
+        let vertex_count = positions.len();
+        if 0 == vertex_count {
+            return Ok(None);
+        }
+        let has_normal = attribute("NORMAL").is_some();
+        let mut normals = match attribute("NORMAL") {
+            Some(accessor_index) => self.read_vector3s(accessor_index)?,
+            None => vec![Vector3::zeros(); vertex_count],
+        };
+        let mut texcoords = match attribute("TEXCOORD_0") {
+            Some(accessor_index) => self.read_vector2s(accessor_index)?,
+            None => vec![Vector2::zeros(); vertex_count],
+        };
+        let mut tangents: Vec<Vector3<f32>> = match attribute("TANGENT") {
+            Some(accessor_index) => self.read_vector4s(accessor_index, 1.0)?.iter().map(|tangent| Vector3::new(tangent.x, tangent.y, tangent.z)).collect(),
+            None => Vec::new(),
+        };
+        let mut colors: Vec<u32> = match attribute("COLOR_0") {
+            Some(accessor_index) => self.read_vector4s(accessor_index, 1.0)?.iter().map(|color| {
+                let color = color * 255.0;
+                vulkan_context::get_color32(color.x as u32, color.y as u32, color.z as u32, color.w as u32)
+            }).collect(),
+            None => vec![vulkan_context::get_color32(255, 255, 255, 255); vertex_count],
+        };
+        let (mut bone_indices, mut bone_weights) = match (attribute("JOINTS_0"), attribute("WEIGHTS_0")) {
+            (Some(joints_accessor_index), Some(weights_accessor_index)) => {
+                let bone_indices = self.read_uvector4s(joints_accessor_index)?;
+                let bone_weights: Vec<Vector4<f32>> = self.read_vector4s(weights_accessor_index, 0.0)?.iter().map(|weights| {
+                    let weight_sum = weights.x + weights.y + weights.z + weights.w;
+                    if 0.0 < weight_sum { weights / weight_sum } else { Vector4::new(1.0, 0.0, 0.0, 0.0) }
+                }).collect();
+                (bone_indices, bone_weights)
+            },
+            _ => (Vec::new(), Vec::new()),
+        };
+        let mut indices = match primitive._indices {
+            Some(accessor_index) => self.read_indices(accessor_index)?,
+            None => (0..vertex_count as u32).collect(),
+        };
+
+        let is_valid_length = |count: usize| 0 == count || vertex_count == count;
+        if false == (is_valid_length(normals.len()) && is_valid_length(texcoords.len()) && is_valid_length(tangents.len()) && is_valid_length(colors.len()) && is_valid_length(bone_indices.len()) && is_valid_length(bone_weights.len())) {
+            return Err(format!("{} has the attributes of the different vertex counts", mesh_name));
+        }
+        if indices.iter().any(|index| vertex_count <= (*index as usize)) {
+            return Err(format!("{} has an out of range index", mesh_name));
+        }
+        indices.truncate(indices.len() - (indices.len() % 3));
+
+        if let Some(world_matrix) = world_matrix {
+            let normal_matrix = world_matrix.try_inverse().unwrap_or(Matrix4::identity()).transpose();
+            for position in positions.iter_mut() {
+                *position = world_matrix.transform_point(&Point3::from(*position)).coords;
+            }
+            for normal in normals.iter_mut() {
+                *normal = normal_matrix.transform_vector(normal).try_normalize(std::f32::EPSILON).unwrap_or(*normal);
+            }
+            for tangent in tangents.iter_mut() {
+                *tangent = world_matrix.transform_vector(tangent).try_normalize(std::f32::EPSILON).unwrap_or(*tangent);
+            }
+            // the mirrored transform flips the winding
+            if world_matrix.determinant() < 0.0 {
+                for triangle in indices.chunks_mut(3) {
+                    triangle.swap(1, 2);
+                }
+            }
+        }
+
+        // welding
+        let recompute_normals = mesh_import_settings._enable_smooth_normal || false == has_normal;
+        if recompute_normals {
+            normals = mesh_welder::compute_smooth_normals(&positions, &indices, mesh_import_settings._smooth_normal_angle, mesh_import_settings._weld_epsilon);
+            tangents.clear();
+        }
+        if mesh_import_settings._enable_weld {
+            let weld_result = mesh_welder::weld_vertices(&positions, &normals, &texcoords, &bone_indices, &bone_weights, &indices, mesh_import_settings._weld_epsilon);
+            positions = weld_result.remap(&positions);
+            normals = weld_result.remap(&normals);
+            texcoords = weld_result.remap(&texcoords);
+            colors = weld_result.remap(&colors);
+            if false == tangents.is_empty() {
+                tangents = weld_result.remap(&tangents);
+            }
+            if false == bone_indices.is_empty() {
+                bone_indices = weld_result.remap(&bone_indices);
+                bone_weights = weld_result.remap(&bone_weights);
+            }
+            indices = weld_result._indices;
+        }
+        if tangents.is_empty() {
+            tangents = geometry_buffer::compute_tangent(&positions, &normals, &texcoords, &indices);
+        }
+        log::info!("    {:?} {}: corners({}), vertices({}), welded vertices({})", self._filename, mesh_name, indices.len(), vertex_count, positions.len());
+
+        let material_name = match primitive._material.and_then(|material_index| self._document._materials.get(material_index).map(|material| (material_index, material))) {
+            Some((_, GltfMaterial { _name: Some(material_name) })) => material_name.clone(),
+            Some((material_index, _)) => format!("material_{}", material_index),
+            None => String::new(),
+        };
+        let bounding_box = calc_bounding_box(&positions);
+        let is_skinned = false == bone_indices.is_empty();
+        let (vertex_datas, skeletal_vertex_datas) = if is_skinned {
+            (Vec::new(), positions.iter().enumerate().map(|(index, position)| {
+                SkeletalVertexData {
+                    _position: position.clone() as Vector3<f32>,
+                    _normal: normals[index].clone() as Vector3<f32>,
+                    _tangent: tangents[index].clone() as Vector3<f32>,
+                    _color: colors[index],
+                    _texcoord: texcoords[index].clone() as Vector2<f32>,
+                    _bone_indices: bone_indices[index].clone() as Vector4<u32>,
+                    _bone_weights: bone_weights[index].clone() as Vector4<f32>,
+                }
+            }).collect())
+        } else {
+            (positions.iter().enumerate().map(|(index, position)| {
+                StaticVertexData {
+                    _position: position.clone() as Vector3<f32>,
+                    _normal: normals[index].clone() as Vector3<f32>,
+                    _tangent: tangents[index].clone() as Vector3<f32>,
+                    _color: colors[index],
+                    _texcoord: texcoords[index].clone() as Vector2<f32>,
+                }
+            }).collect(), Vec::new())
+        };
+        Ok(Some(GeometryCreateInfo {
+            _vertex_datas: vertex_datas,
+            _skeletal_vertex_datas: skeletal_vertex_datas,
+            _indices: indices,
+            _bounding_box: bounding_box,
+            _material_name: material_name,
+        }))
+    }
+
+    fn generate_mesh_data_create_info(&self, mesh_import_settings: &MeshImportSettings) -> Result<MeshDataCreateInfo, String> {
+        let mut geometry_create_infos: Vec<GeometryCreateInfo> = Vec::new();
+        for (node_index, mesh_index, world_matrix) in self.collect_mesh_nodes().iter() {
+            let mesh = self._document._meshes.get(*mesh_index).ok_or(format!("mesh[{}] does not exist", mesh_index))?;
+            let is_skinned = node_index.map(|node_index| self._document._nodes[node_index]._skin.is_some()).unwrap_or(false);
+            let mesh_name = mesh._name.clone().unwrap_or(format!("mesh_{}", mesh_index));
+            for (primitive_index, primitive) in mesh._primitives.iter().enumerate() {
+                let geometry_name = format!("{}_{}", mesh_name, primitive_index);
+                let world_matrix = if is_skinned { None } else { Some(world_matrix) };
+                if let Some(geometry_create_info) = self.generate_geometry_data(&geometry_name, primitive, world_matrix, mesh_import_settings)? {
+                    geometry_create_infos.push(geometry_create_info);
+                }
+            }
+        }
+        Ok(MeshDataCreateInfo::create_mesh_data_crate_info(MeshDataCreateInfo {
+            _skeleton_create_infos: self.get_skeleton_datas()?,
+            _geometry_create_infos: geometry_create_infos,
+            ..Default::default()
+        }))
+    }
+
+    // the external .bin files are not part of the mesh cache hash, touch the .gltf file after editing them.
+    pub fn get_mesh_data_create_infos(filename: &PathBuf, mesh_import_settings: &MeshImportSettings) -> MeshDataCreateInfo {
+        let result = GLTF::load(filename).and_then(|gltf| gltf.generate_mesh_data_create_info(mesh_import_settings));
+        match result {
+            Ok(mesh_data_create_info) => mesh_data_create_info,
+            Err(e) => {
+                log::error!("GLTF::get_mesh_data_create_infos {:?}: {}", filename, e);
+                MeshDataCreateInfo::default()
+            }
+        }
+    }
+
+    // the counts from the accessors of the document, the nodes instancing a mesh are not counted twice.
+    pub fn read_mesh_header(filename: &PathBuf) -> Result<MeshHeader, String> {
+        let (document, _) = GLTF::read_document(system::load(filename).into_inner())?;
+        let mut mesh_header = MeshHeader::default();
+        for mesh in document._meshes.iter() {
+            for primitive in mesh._primitives.iter() {
+                if PRIMITIVE_MODE_TRIANGLES != primitive._mode.unwrap_or(PRIMITIVE_MODE_TRIANGLES) {
+                    continue;
+                }
+                let vertex_count = primitive._attributes.get("POSITION").and_then(|accessor_index| document._accessors.get(*accessor_index)).map_or(0, |accessor| accessor._count);
+                let index_count = primitive._indices.and_then(|accessor_index| document._accessors.get(accessor_index)).map_or(vertex_count, |accessor| accessor._count);
+                mesh_header._vertex_count += vertex_count;
+                mesh_header._face_count += index_count / 3;
+            }
+        }
+        Ok(mesh_header)
+    }
+}
@@ -0,0 +1,205 @@
+use std::path::PathBuf;
+use std::time::{ Duration, Instant };
+
+use crate::renderer::font::FontManager;
+use crate::renderer::ui::UIManagerData;
+use crate::utilities::system::RcRefCell;
+
+// the writer may still be flushing the file when the event arrives, ex) an external compiler or an editor
+pub const RELOAD_RETRY_DELAY: Duration = Duration::from_millis(300);
+pub const MAX_RELOAD_RETRY_COUNT: u32 = 5;
+pub const RELOAD_ERROR_TOAST_TIME: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Debug)]
+pub struct PendingReload {
+    pub _file_path: PathBuf,
+    pub _retry_time: Instant,
+    pub _retry_count: u32,
+}
+
+#[derive(Clone, Debug)]
+pub struct ReloadErrorToast {
+    pub _message: String,
+    pub _expire_time: Instant,
+}
+
+// the failed reloads keep the previous resources, retried later and shown on the screen until they expire.
+#[derive(Clone, Debug, Default)]
+pub struct HotReloadState {
+    pub _pending_reloads: Vec<PendingReload>,
+    pub _error_toasts: Vec<ReloadErrorToast>,
+}
+
+impl HotReloadState {
+    pub fn schedule_reload(&mut self, file_path: &PathBuf) {
+        if false == self._pending_reloads.iter().any(|pending_reload| *file_path == pending_reload._file_path) {
+            self._pending_reloads.push(PendingReload {
+                _file_path: file_path.clone(),
+                _retry_time: Instant::now(),
+                _retry_count: 0,
+            });
+        }
+    }
+
+    pub fn take_due_reloads(&mut self) -> Vec<PendingReload> {
+        let now = Instant::now();
+        let (due_reloads, pending_reloads): (Vec<PendingReload>, Vec<PendingReload>) = self._pending_reloads.drain(..).partition(|pending_reload| pending_reload._retry_time <= now);
+        self._pending_reloads = pending_reloads;
+        due_reloads
+    }
+
+    pub fn schedule_retry(&mut self, mut pending_reload: PendingReload, error_message: &str) {
+        self.report_reload_error(error_message);
+        pending_reload._retry_count += 1;
+        if MAX_RELOAD_RETRY_COUNT <= pending_reload._retry_count {
+            log::error!("hot reload gave up after {} retries: {:?}", pending_reload._retry_count, pending_reload._file_path);
+            return;
+        }
+        pending_reload._retry_time = Instant::now() + RELOAD_RETRY_DELAY;
+        self._pending_reloads.push(pending_reload);
+    }
+
+    pub fn report_reload_error(&mut self, error_message: &str) {
+        log::error!("hot reload error: {}", error_message);
+        let expire_time = Instant::now() + RELOAD_ERROR_TOAST_TIME;
+        match self._error_toasts.iter_mut().find(|error_toast| error_message == error_toast._message) {
+            Some(error_toast) => error_toast._expire_time = expire_time,
+            None => self._error_toasts.push(ReloadErrorToast {
+                _message: String::from(error_message),
+                _expire_time: expire_time,
+            }),
+        }
+    }
+
+    pub fn clear_reload_error(&mut self, file_path: &PathBuf) {
+        let file_path = format!("{:?}", file_path);
+        self._error_toasts.retain(|error_toast| false == error_toast._message.contains(&file_path));
+    }
+
+    // called every frame after RendererData::update_render_error, the banner is kept while the toasts are shown.
+    pub fn update_reload_error_toasts(&mut self, font_manager: &mut FontManager, ui_manager_data: &mut UIManagerData) {
+        let now = Instant::now();
+        self._error_toasts.retain(|error_toast| now < error_toast._expire_time);
+        for error_toast in self._error_toasts.iter() {
+            font_manager.log(format!("reload error: {}", error_toast._message));
+        }
+        if false == self._error_toasts.is_empty() {
+            ui_manager_data.set_render_error_banner(true);
+        }
+    }
+}
+
+// all or nothing, the datas are replaced in place only when every new data has been created.
+// ex) the pipelines using the reloaded shader, a failure destroys the new datas and keeps the previous ones.
+pub fn replace_all_or_nothing<T, C>(
+    targets: &[(RcRefCell<T>, C)],
+    mut create_data: impl FnMut(&T, &C) -> Result<T, String>,
+    mut destroy_data: impl FnMut(&T),
+    before_replace: impl FnOnce(),
+) -> Result<u32, String> {
+    let mut new_datas: Vec<T> = Vec::new();
+    for (data, create_context) in targets.iter() {
+        let new_data = create_data(&data.borrow(), create_context);
+        match new_data {
+            Ok(new_data) => new_datas.push(new_data),
+            Err(e) => {
+                for new_data in new_datas.iter() {
+                    destroy_data(new_data);
+                }
+                return Err(e);
+            }
+        }
+    }
+
+    let replaced_count = new_datas.len() as u32;
+    if 0 < replaced_count {
+        before_replace();
+        for ((data, _), new_data) in targets.iter().zip(new_datas.into_iter()) {
+            let prev_data = std::mem::replace(&mut *data.borrow_mut(), new_data);
+            destroy_data(&prev_data);
+        }
+    }
+    Ok(replaced_count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use crate::utilities::system::newRcRefCell;
+    use crate::vulkan_context::shader;
+
+    #[derive(Debug, PartialEq)]
+    struct TestPipelineData {
+        _spirv_code: Vec<u8>,
+    }
+
+    fn create_spirv_code(word_count: usize) -> Vec<u8> {
+        let mut spirv_code: Vec<u8> = shader::SPIRV_MAGIC_NUMBER.to_le_bytes().to_vec();
+        spirv_code.resize(word_count * 4, 0);
+        spirv_code
+    }
+
+    // the shader loader path, read the spirv file and validate it before create_shader_module
+    fn load_test_pipeline_data(_prev_pipeline_data: &TestPipelineData, spirv_file_path: &PathBuf) -> Result<TestPipelineData, String> {
+        let spirv_code = fs::read(spirv_file_path).map_err(|e| format!("{:?}", e))?;
+        shader::validate_spirv(spirv_file_path, &spirv_code)?;
+        Ok(TestPipelineData { _spirv_code: spirv_code })
+    }
+
+    #[test]
+    fn test_truncated_spirv_keeps_previous_pipeline() {
+        let test_directory = std::env::temp_dir().join(format!("hot_reload_{}", std::process::id()));
+        fs::create_dir_all(&test_directory).unwrap();
+        let valid_spirv_file_path = test_directory.join("valid.frag.spirv");
+        let truncated_spirv_file_path = test_directory.join("truncated.frag.spirv");
+        fs::write(&valid_spirv_file_path, create_spirv_code(8)).unwrap();
+        // the external compiler crashed in the middle of writing
+        let mut truncated_spirv_code = create_spirv_code(8);
+        truncated_spirv_code.truncate(shader::SPIRV_HEADER_SIZE + 2);
+        fs::write(&truncated_spirv_file_path, truncated_spirv_code).unwrap();
+
+        let prev_spirv_code = create_spirv_code(6);
+        let targets = vec![
+            (newRcRefCell(TestPipelineData { _spirv_code: prev_spirv_code.clone() }), valid_spirv_file_path.clone()),
+            (newRcRefCell(TestPipelineData { _spirv_code: prev_spirv_code.clone() }), truncated_spirv_file_path.clone()),
+        ];
+        let mut destroyed_count: u32 = 0;
+        let mut is_replaced = false;
+        let result = replace_all_or_nothing(&targets, load_test_pipeline_data, |_| destroyed_count += 1, || is_replaced = true);
+        assert!(result.is_err());
+        assert!(false == is_replaced);
+        assert_eq!(1, destroyed_count); // the new pipeline of the valid file
+        for (pipeline_data, _) in targets.iter() {
+            assert_eq!(prev_spirv_code, pipeline_data.borrow()._spirv_code);
+        }
+
+        // the retry after the writer has flushed the file
+        fs::write(&truncated_spirv_file_path, create_spirv_code(8)).unwrap();
+        let mut destroyed_count: u32 = 0;
+        let result = replace_all_or_nothing(&targets, load_test_pipeline_data, |_| destroyed_count += 1, || ());
+        assert_eq!(Ok(2), result);
+        assert_eq!(2, destroyed_count); // the previous pipelines
+        for (pipeline_data, _) in targets.iter() {
+            assert_eq!(create_spirv_code(8), pipeline_data.borrow()._spirv_code);
+        }
+
+        let _result = fs::remove_dir_all(&test_directory);
+    }
+
+    #[test]
+    fn test_schedule_retry() {
+        let mut hot_reload_state = HotReloadState::default();
+        let file_path = PathBuf::from("render_object.frag");
+        hot_reload_state.schedule_reload(&file_path);
+        hot_reload_state.schedule_reload(&file_path);
+        let due_reloads = hot_reload_state.take_due_reloads();
+        assert_eq!(1, due_reloads.len());
+
+        hot_reload_state.schedule_retry(due_reloads[0].clone(), &format!("{:?} error", file_path));
+        assert!(hot_reload_state.take_due_reloads().is_empty());
+        assert_eq!(1, hot_reload_state._error_toasts.len());
+        hot_reload_state.clear_reload_error(&file_path);
+        assert!(hot_reload_state._error_toasts.is_empty());
+    }
+}
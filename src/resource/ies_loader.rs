@@ -0,0 +1,319 @@
+// IESNA LM-63 photometric profiles(1986, 1991, 1995, 2002), converted to the angular intensity texture of the spot lights.
+// type C photometry: the vertical angle 0 is the light axis(nadir), the horizontal angle goes around the axis.
+
+pub const IES_TEXTURE_WIDTH: u32 = 128; // vertical angle 0 ~ 180
+pub const IES_TEXTURE_HEIGHT: u32 = 64; // horizontal angle 0 ~ 360
+pub const IES_TILT_NONE: &str = "TILT=NONE";
+pub const IES_TILT_INCLUDE: &str = "TILT=INCLUDE";
+
+#[derive(Clone, Debug, Copy, PartialEq, Eq)]
+pub enum IesPhotometricType {
+    TypeC,
+    TypeB,
+    TypeA,
+}
+
+#[derive(Clone, Debug, Copy, PartialEq, Eq)]
+pub enum IesHorizontalSymmetry {
+    Axial, // one horizontal angle
+    Quadrant, // 0 ~ 90
+    Bilateral, // 0 ~ 180
+    Full, // 0 ~ 360
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct IesProfile {
+    pub _photometric_type: IesPhotometricType,
+    pub _lumens_per_lamp: f32,
+    pub _candela_multiplier: f32,
+    pub _vertical_angles: Vec<f32>, // degree, ascending
+    pub _horizontal_angles: Vec<f32>, // degree, ascending
+    pub _candela_values: Vec<f32>, // [horizontal][vertical], multiplied by the candela multiplier
+    pub _max_candela: f32,
+}
+
+fn parse_number<'a, I: Iterator<Item = &'a str>>(tokens: &mut I, name: &str) -> Result<f32, String> {
+    match tokens.next() {
+        Some(token) => token.parse::<f32>().map_err(|_| format!("invalid {}: {}", name, token)),
+        None => Err(format!("unexpected end of file: {}", name)),
+    }
+}
+
+fn parse_numbers<'a, I: Iterator<Item = &'a str>>(tokens: &mut I, count: usize, name: &str) -> Result<Vec<f32>, String> {
+    (0..count).map(|_| parse_number(tokens, name)).collect()
+}
+
+fn is_ascending(values: &[f32]) -> bool {
+    values.windows(2).all(|pair| pair[0] <= pair[1])
+}
+
+// the index of the lower sample and the ratio to the next one, clamped at the ends
+fn find_interval(angles: &[f32], angle: f32) -> (usize, f32) {
+    if angles.len() < 2 || angle <= angles[0] {
+        return (0, 0.0);
+    }
+    let last = angles.len() - 1;
+    if angles[last] <= angle {
+        return (last, 0.0);
+    }
+    let upper = angles.iter().position(|a| angle < *a).unwrap_or(last);
+    let lower = upper - 1;
+    let range = angles[upper] - angles[lower];
+    (lower, if 0.0 < range { (angle - angles[lower]) / range } else { 0.0 })
+}
+
+pub fn parse_ies_profile(contents: &str) -> Result<IesProfile, String> {
+    let mut lines = contents.lines();
+
+    // header and the keywords until the tilt line
+    let tilt_line = loop {
+        match lines.next() {
+            Some(line) => {
+                let line = line.trim();
+                if line.to_uppercase().starts_with("TILT=") {
+                    break line.to_uppercase();
+                }
+            },
+            None => return Err(String::from("TILT line is not found")),
+        }
+    };
+
+    let remain: Vec<&str> = lines.collect();
+    let remain = remain.join(" ");
+    let mut tokens = remain.split(|c: char| c.is_whitespace() || ',' == c).filter(|token| false == token.is_empty());
+
+    if IES_TILT_INCLUDE == tilt_line {
+        // lamp to luminaire geometry, the tilt angles and the multiplying factors, not used
+        parse_number(&mut tokens, "lamp to luminaire geometry")?;
+        let tilt_count = parse_number(&mut tokens, "tilt angle count")? as usize;
+        parse_numbers(&mut tokens, tilt_count * 2, "tilt data")?;
+    } else if IES_TILT_NONE != tilt_line {
+        log::warn!("parse_ies_profile: the external tilt file is ignored, {}", tilt_line);
+    }
+
+    let _lamp_count = parse_number(&mut tokens, "lamp count")?;
+    let lumens_per_lamp = parse_number(&mut tokens, "lumens per lamp")?;
+    let candela_multiplier = parse_number(&mut tokens, "candela multiplier")?;
+    let vertical_angle_count = parse_number(&mut tokens, "vertical angle count")? as usize;
+    let horizontal_angle_count = parse_number(&mut tokens, "horizontal angle count")? as usize;
+    let photometric_type = match parse_number(&mut tokens, "photometric type")? as i32 {
+        1 => IesPhotometricType::TypeC,
+        2 => IesPhotometricType::TypeB,
+        3 => IesPhotometricType::TypeA,
+        photometric_type => return Err(format!("invalid photometric type: {}", photometric_type)),
+    };
+    // units type, width, length, height, ballast factor, ballast lamp photometric factor, input watts
+    parse_numbers(&mut tokens, 7, "luminaire data")?;
+
+    if 0 == vertical_angle_count || 0 == horizontal_angle_count {
+        return Err(String::from("empty angles"));
+    }
+    let vertical_angles = parse_numbers(&mut tokens, vertical_angle_count, "vertical angle")?;
+    let horizontal_angles = parse_numbers(&mut tokens, horizontal_angle_count, "horizontal angle")?;
+    if false == is_ascending(&vertical_angles) || false == is_ascending(&horizontal_angles) {
+        return Err(String::from("the angles are not in ascending order"));
+    }
+    let candela_values: Vec<f32> = parse_numbers(&mut tokens, vertical_angle_count * horizontal_angle_count, "candela value")?
+        .iter().map(|candela| candela * candela_multiplier).collect();
+    let max_candela = candela_values.iter().cloned().fold(0.0, f32::max);
+
+    Ok(IesProfile {
+        _photometric_type: photometric_type,
+        _lumens_per_lamp: lumens_per_lamp,
+        _candela_multiplier: candela_multiplier,
+        _vertical_angles: vertical_angles,
+        _horizontal_angles: horizontal_angles,
+        _candela_values: candela_values,
+        _max_candela: max_candela,
+    })
+}
+
+impl IesProfile {
+    pub fn get_horizontal_symmetry(&self) -> IesHorizontalSymmetry {
+        let last_angle = *self._horizontal_angles.last().unwrap();
+        if 1 == self._horizontal_angles.len() {
+            IesHorizontalSymmetry::Axial
+        } else if last_angle <= 90.0 {
+            IesHorizontalSymmetry::Quadrant
+        } else if last_angle <= 180.0 {
+            IesHorizontalSymmetry::Bilateral
+        } else {
+            IesHorizontalSymmetry::Full
+        }
+    }
+
+    // the horizontal angle folded into the range of the measured data
+    pub fn get_folded_horizontal_angle(&self, horizontal_angle: f32) -> f32 {
+        let horizontal_angle = horizontal_angle.rem_euclid(360.0);
+        match self.get_horizontal_symmetry() {
+            IesHorizontalSymmetry::Axial => 0.0,
+            IesHorizontalSymmetry::Quadrant => {
+                let angle = horizontal_angle % 180.0;
+                if 90.0 < angle { 180.0 - angle } else { angle }
+            },
+            IesHorizontalSymmetry::Bilateral => if 180.0 < horizontal_angle { 360.0 - horizontal_angle } else { horizontal_angle },
+            IesHorizontalSymmetry::Full => horizontal_angle,
+        }
+    }
+
+    fn get_candela_value(&self, horizontal_index: usize, vertical_index: usize) -> f32 {
+        self._candela_values[horizontal_index * self._vertical_angles.len() + vertical_index]
+    }
+
+    // bilinear in the angles, zero outside of the measured vertical range
+    pub fn get_candela(&self, vertical_angle: f32, horizontal_angle: f32) -> f32 {
+        let first_vertical_angle = self._vertical_angles[0];
+        let last_vertical_angle = *self._vertical_angles.last().unwrap();
+        if vertical_angle < first_vertical_angle || last_vertical_angle < vertical_angle {
+            return 0.0;
+        }
+        let horizontal_angle = self.get_folded_horizontal_angle(horizontal_angle);
+        let (v0, vt) = find_interval(&self._vertical_angles, vertical_angle);
+        let (h0, ht) = find_interval(&self._horizontal_angles, horizontal_angle);
+        let v1 = (v0 + 1).min(self._vertical_angles.len() - 1);
+        let h1 = (h0 + 1).min(self._horizontal_angles.len() - 1);
+        let candela0 = self.get_candela_value(h0, v0) * (1.0 - vt) + self.get_candela_value(h0, v1) * vt;
+        let candela1 = self.get_candela_value(h1, v0) * (1.0 - vt) + self.get_candela_value(h1, v1) * vt;
+        candela0 * (1.0 - ht) + candela1 * ht
+    }
+
+    // R32_SFLOAT, u: vertical angle 0 ~ 180, v: horizontal angle 0 ~ 360, normalized by the max candela.
+    // the axially symmetric profiles are the same on every row.
+    pub fn create_ies_texture_data(&self, width: u32, height: u32) -> Vec<f32> {
+        let inv_max_candela = if 0.0 < self._max_candela { 1.0 / self._max_candela } else { 0.0 };
+        let mut texture_data: Vec<f32> = Vec::with_capacity((width * height) as usize);
+        for y in 0..height {
+            let horizontal_angle = (y as f32 + 0.5) / height as f32 * 360.0;
+            for x in 0..width {
+                let vertical_angle = (x as f32 + 0.5) / width as f32 * 180.0;
+                texture_data.push(self.get_candela(vertical_angle, horizontal_angle) * inv_max_candela);
+            }
+        }
+        texture_data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // type C, axially symmetric downlight
+    const IES_SAMPLE_AXIAL: &str = "IESNA:LM-63-1995
+[TEST] axial sample
+[MANUFAC] engine
+[LUMCAT] downlight
+TILT=NONE
+1 1000 1 5 1 1 2 0 0 0
+1 1 100
+0 22.5 45 67.5 90
+0
+1000 900 500 100 0
+";
+
+    // type C, quadrant symmetric wall washer, the tilt data and the comma separators
+    const IES_SAMPLE_QUADRANT: &str = "IESNA:LM-63-2002
+[TEST] quadrant sample
+[LUMINAIRE] wall washer
+tilt=include
+1
+3
+0,45,90
+1,0.9,0.8
+1 -1 2 3 3 1 2 0.5 0.5 0.1
+1 1 50
+0 45 90
+0 45 90
+100 80 0
+200 150 0
+300 200 0
+";
+
+    fn assert_near(expected: f32, value: f32) {
+        assert!((expected - value).abs() < 0.001, "{} != {}", expected, value);
+    }
+
+    #[test]
+    fn test_parse_axial_profile() {
+        let ies_profile = parse_ies_profile(IES_SAMPLE_AXIAL).unwrap();
+        assert_eq!(IesPhotometricType::TypeC, ies_profile._photometric_type);
+        assert_eq!(1000.0, ies_profile._lumens_per_lamp);
+        assert_eq!(vec![0.0, 22.5, 45.0, 67.5, 90.0], ies_profile._vertical_angles);
+        assert_eq!(vec![0.0], ies_profile._horizontal_angles);
+        assert_eq!(vec![1000.0, 900.0, 500.0, 100.0, 0.0], ies_profile._candela_values);
+        assert_eq!(1000.0, ies_profile._max_candela);
+        assert_eq!(IesHorizontalSymmetry::Axial, ies_profile.get_horizontal_symmetry());
+
+        // the same around the axis, interpolated between the vertical angles, zero below the horizon
+        for horizontal_angle in [0.0, 90.0, 200.0, -30.0].iter() {
+            assert_near(1000.0, ies_profile.get_candela(0.0, *horizontal_angle));
+            assert_near(700.0, ies_profile.get_candela(33.75, *horizontal_angle));
+            assert_near(0.0, ies_profile.get_candela(90.0, *horizontal_angle));
+            assert_near(0.0, ies_profile.get_candela(120.0, *horizontal_angle));
+        }
+    }
+
+    #[test]
+    fn test_parse_quadrant_profile() {
+        let ies_profile = parse_ies_profile(IES_SAMPLE_QUADRANT).unwrap();
+        assert_eq!(IesPhotometricType::TypeC, ies_profile._photometric_type);
+        assert_eq!(-1.0, ies_profile._lumens_per_lamp); // absolute photometry
+        assert_eq!(2.0, ies_profile._candela_multiplier);
+        assert_eq!(vec![200.0, 160.0, 0.0, 400.0, 300.0, 0.0, 600.0, 400.0, 0.0], ies_profile._candela_values);
+        assert_eq!(600.0, ies_profile._max_candela);
+        assert_eq!(IesHorizontalSymmetry::Quadrant, ies_profile.get_horizontal_symmetry());
+
+        assert_near(200.0, ies_profile.get_candela(0.0, 0.0));
+        assert_near(600.0, ies_profile.get_candela(0.0, 90.0));
+        assert_near(180.0, ies_profile.get_candela(22.5, 0.0));
+        assert_near(300.0, ies_profile.get_candela(0.0, 22.5));
+        // the other quadrants are mirrored
+        assert_eq!(45.0, ies_profile.get_folded_horizontal_angle(135.0));
+        assert_eq!(90.0, ies_profile.get_folded_horizontal_angle(270.0));
+        assert_eq!(10.0, ies_profile.get_folded_horizontal_angle(350.0));
+        assert_near(ies_profile.get_candela(30.0, 45.0), ies_profile.get_candela(30.0, 135.0));
+        assert_near(ies_profile.get_candela(30.0, 45.0), ies_profile.get_candela(30.0, 315.0));
+    }
+
+    #[test]
+    fn test_horizontal_symmetry() {
+        let mut ies_profile = parse_ies_profile(IES_SAMPLE_QUADRANT).unwrap();
+        ies_profile._horizontal_angles = vec![0.0, 90.0, 180.0];
+        assert_eq!(IesHorizontalSymmetry::Bilateral, ies_profile.get_horizontal_symmetry());
+        assert_eq!(90.0, ies_profile.get_folded_horizontal_angle(270.0));
+        assert_eq!(150.0, ies_profile.get_folded_horizontal_angle(210.0));
+        ies_profile._horizontal_angles = vec![0.0, 180.0, 360.0];
+        assert_eq!(IesHorizontalSymmetry::Full, ies_profile.get_horizontal_symmetry());
+        assert_eq!(270.0, ies_profile.get_folded_horizontal_angle(270.0));
+        assert_eq!(0.0, ies_profile.get_folded_horizontal_angle(360.0));
+    }
+
+    #[test]
+    fn test_ies_texture_data() {
+        let ies_profile = parse_ies_profile(IES_SAMPLE_AXIAL).unwrap();
+        let texture_data = ies_profile.create_ies_texture_data(IES_TEXTURE_WIDTH, IES_TEXTURE_HEIGHT);
+        assert_eq!((IES_TEXTURE_WIDTH * IES_TEXTURE_HEIGHT) as usize, texture_data.len());
+        assert!(texture_data.iter().all(|value| 0.0 <= *value && *value <= 1.0));
+        // the first texel is near the axis, the upper hemisphere is dark
+        assert!(0.99 < texture_data[0]);
+        let width = IES_TEXTURE_WIDTH as usize;
+        assert!(texture_data[(width / 2)..width].iter().all(|value| 0.0 == *value));
+        // the axially symmetric profile has the same rows
+        let first_row = &texture_data[..width];
+        assert!(texture_data.chunks(width).all(|row| row == first_row));
+
+        // the quadrant profile changes around the axis
+        let ies_profile = parse_ies_profile(IES_SAMPLE_QUADRANT).unwrap();
+        let texture_data = ies_profile.create_ies_texture_data(4, 8);
+        assert!(texture_data[0] < texture_data[4 * 2]);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse_ies_profile("IESNA:LM-63-1995\n1 1000 1").is_err());
+        assert!(parse_ies_profile(&IES_SAMPLE_AXIAL.replace("1 1000 1 5 1 1", "1 1000 1 5 1 4")).is_err());
+        assert!(parse_ies_profile(&IES_SAMPLE_AXIAL.replace("0 22.5 45 67.5 90", "0 45 22.5 67.5 90")).is_err());
+        assert!(parse_ies_profile(&IES_SAMPLE_AXIAL.replace("1000 900 500 100 0", "1000 900 500")).is_err());
+        assert!(parse_ies_profile(&IES_SAMPLE_AXIAL.replace("1000 900 500 100 0", "1000 900 abc 100 0")).is_err());
+        assert!(parse_ies_profile(&IES_SAMPLE_AXIAL.replace("1 1000 1 5 1 1", "1 1000 1 0 1 1")).is_err());
+    }
+}
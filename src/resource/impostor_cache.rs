@@ -0,0 +1,109 @@
+use std::fs::{ self, File };
+use std::io::prelude::*;
+use std::io::Cursor;
+use std::path::{ Path, PathBuf };
+
+use bincode;
+use byteorder::{ LittleEndian, ReadBytesExt, WriteBytesExt };
+
+use crate::renderer::impostor::{ ImpostorBakeData, ImpostorCreateInfo };
+use crate::resource::mesh_cache::{ compute_hash, HASH_OFFSET_BASIS };
+
+pub const IMPOSTOR_CACHE_DIRECTORY: &str = "resource/impostor_caches";
+pub const EXT_IMPOSTOR_CACHE: &str = "impostor_cache";
+pub const IMPOSTOR_CACHE_MAGIC: u32 = 0x494D_5053; // "IMPS"
+// increase the version when the result of the bake(capture rig, atlas layout, formats) has been changed.
+pub const IMPOSTOR_BAKER_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImpostorCacheHeader {
+    pub _magic: u32,
+    pub _baker_version: u32,
+    pub _content_hash: u64,
+    pub _data_checksum: u64,
+    pub _data_size: u64,
+}
+
+// the mesh content, the material instances and the impostor settings, any change of them bakes the impostor again.
+pub fn compute_model_content_hash(mesh_content_hash: u64, material_instance_names: &[String], impostor_create_info: &ImpostorCreateInfo) -> u64 {
+    let mut hash = compute_hash(HASH_OFFSET_BASIS, &mesh_content_hash.to_le_bytes());
+    for material_instance_name in material_instance_names.iter() {
+        hash = compute_hash(hash, material_instance_name.as_bytes());
+    }
+    compute_hash(hash, serde_json::to_string(impostor_create_info).unwrap().as_bytes())
+}
+
+pub fn get_impostor_cache_file_path(model_name: &str) -> PathBuf {
+    let model_name_hash = compute_hash(HASH_OFFSET_BASIS, model_name.as_bytes());
+    let file_stem = Path::new(model_name).file_name().unwrap().to_str().unwrap().to_string();
+    let mut cache_file_path = PathBuf::from(IMPOSTOR_CACHE_DIRECTORY);
+    cache_file_path.push(format!("{}_{:016x}", file_stem, model_name_hash));
+    cache_file_path.set_extension(EXT_IMPOSTOR_CACHE);
+    cache_file_path
+}
+
+fn read_header(contents: &mut Cursor<Vec<u8>>) -> std::io::Result<ImpostorCacheHeader> {
+    Ok(ImpostorCacheHeader {
+        _magic: contents.read_u32::<LittleEndian>()?,
+        _baker_version: contents.read_u32::<LittleEndian>()?,
+        _content_hash: contents.read_u64::<LittleEndian>()?,
+        _data_checksum: contents.read_u64::<LittleEndian>()?,
+        _data_size: contents.read_u64::<LittleEndian>()?,
+    })
+}
+
+// returns None when the cache is missing, outdated or corrupted, then the impostor is baked again.
+pub fn load_impostor_cache(cache_file_path: &Path, content_hash: u64) -> Option<ImpostorBakeData> {
+    let bytes = match fs::read(cache_file_path) {
+        Ok(bytes) => bytes,
+        Err(_) => return None,
+    };
+    let mut contents = Cursor::new(bytes);
+    let header = match read_header(&mut contents) {
+        Ok(header) => header,
+        Err(_) => {
+            log::warn!("load_impostor_cache: corrupted header {:?}", cache_file_path);
+            return None;
+        }
+    };
+
+    if IMPOSTOR_CACHE_MAGIC != header._magic || IMPOSTOR_BAKER_VERSION != header._baker_version || content_hash != header._content_hash {
+        log::debug!("load_impostor_cache: outdated {:?}", cache_file_path);
+        return None;
+    }
+
+    let data_offset = contents.position() as usize;
+    let data = &contents.get_ref()[data_offset..];
+    if header._data_size != data.len() as u64 || header._data_checksum != compute_hash(HASH_OFFSET_BASIS, data) {
+        log::warn!("load_impostor_cache: checksum mismatch {:?}", cache_file_path);
+        return None;
+    }
+
+    match bincode::deserialize::<ImpostorBakeData>(data) {
+        Ok(bake_data) => Some(bake_data),
+        Err(e) => {
+            log::warn!("load_impostor_cache: failed to deserialize {:?} {:?}", cache_file_path, e);
+            None
+        }
+    }
+}
+
+pub fn save_impostor_cache(cache_file_path: &Path, content_hash: u64, bake_data: &ImpostorBakeData) {
+    let data: Vec<u8> = bincode::serialize(bake_data).unwrap();
+    let mut contents: Vec<u8> = Vec::with_capacity(data.len() + 32);
+    contents.write_u32::<LittleEndian>(IMPOSTOR_CACHE_MAGIC).unwrap();
+    contents.write_u32::<LittleEndian>(IMPOSTOR_BAKER_VERSION).unwrap();
+    contents.write_u64::<LittleEndian>(content_hash).unwrap();
+    contents.write_u64::<LittleEndian>(compute_hash(HASH_OFFSET_BASIS, &data)).unwrap();
+    contents.write_u64::<LittleEndian>(data.len() as u64).unwrap();
+    contents.extend_from_slice(&data);
+
+    // the cache is optional, the impostor is baked again on the next load.
+    let result = fs::create_dir_all(cache_file_path.parent().unwrap()).and_then(|_| {
+        let mut write_file = File::create(cache_file_path)?;
+        write_file.write_all(&contents)
+    });
+    if let Err(e) = result {
+        log::warn!("save_impostor_cache: failed to write {:?} {:?}", cache_file_path, e);
+    }
+}
@@ -0,0 +1,493 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::thread;
+
+use nalgebra::Vector3;
+
+use crate::renderer::mesh::MeshDataCreateInfo;
+use crate::resource::mesh_cache::{ compute_hash, HASH_OFFSET_BASIS };
+use crate::resource::mesh_welder::MeshImportSettings;
+use crate::utilities::math;
+use crate::vulkan_context::geometry_buffer::GeometryCreateInfo;
+
+// the engine has no job system, the vertices are split into the chunks of the worker threads.
+pub const AMBIENT_OCCLUSION_THREAD_COUNT: usize = 4;
+pub const AMBIENT_OCCLUSION_RAY_BIAS: f32 = 0.001; // ratio of the bound box size, skips the self intersection of the thin geometry
+pub const AMBIENT_OCCLUSION_SEED: u64 = 0x414F_5345; // "AOSE", fixed so the results and the mesh cache are stable
+const BVH_LEAF_TRIANGLE_COUNT: usize = 4;
+
+// vertex color: rg keeps the vertex color, b = ambient occlusion, a = cavity.
+// the material multiplies them into its ao input when it declares "vertex_ambient_occlusion": true, see material.rs
+pub const VERTEX_COLOR_AMBIENT_OCCLUSION_SHIFT: u32 = 16;
+pub const VERTEX_COLOR_CAVITY_SHIFT: u32 = 24;
+
+pub fn encode_vertex_color(color: u32, ambient_occlusion: f32, cavity: f32) -> u32 {
+    let to_unorm8 = |value: f32| -> u32 { (value.max(0.0).min(1.0) * 255.0).round() as u32 };
+    (color & 0x0000_FFFF) | (to_unorm8(ambient_occlusion) << VERTEX_COLOR_AMBIENT_OCCLUSION_SHIFT) | (to_unorm8(cavity) << VERTEX_COLOR_CAVITY_SHIFT)
+}
+
+pub fn decode_vertex_color(color: u32) -> (f32, f32) {
+    let ambient_occlusion = ((color >> VERTEX_COLOR_AMBIENT_OCCLUSION_SHIFT) & 0xFF) as f32 / 255.0;
+    let cavity = ((color >> VERTEX_COLOR_CAVITY_SHIFT) & 0xFF) as f32 / 255.0;
+    (ambient_occlusion, cavity)
+}
+
+struct Triangle {
+    _p0: Vector3<f32>,
+    _edge1: Vector3<f32>,
+    _edge2: Vector3<f32>,
+}
+
+struct BvhNode {
+    _min: Vector3<f32>,
+    _max: Vector3<f32>,
+    _first: usize, // the first triangle of the leaf or the left child of the inner node
+    _count: usize, // 0 for the inner node, the right child is _first + 1
+}
+
+// the self occlusion of the whole mesh, the geometries of the mesh occlude each other.
+pub struct MeshBvh {
+    _triangles: Vec<Triangle>,
+    _nodes: Vec<BvhNode>,
+}
+
+impl MeshBvh {
+    pub fn create_mesh_bvh(geometry_create_infos: &[GeometryCreateInfo]) -> MeshBvh {
+        let mut triangles: Vec<Triangle> = Vec::new();
+        for geometry_create_info in geometry_create_infos.iter() {
+            let positions: Vec<Vector3<f32>> = get_vertex_positions(geometry_create_info);
+            for triangle_indices in geometry_create_info._indices.chunks(3) {
+                if 3 == triangle_indices.len() {
+                    let p0 = positions[triangle_indices[0] as usize];
+                    triangles.push(Triangle {
+                        _p0: p0,
+                        _edge1: &positions[triangle_indices[1] as usize] - &p0,
+                        _edge2: &positions[triangle_indices[2] as usize] - &p0,
+                    });
+                }
+            }
+        }
+
+        let mut mesh_bvh = MeshBvh {
+            _triangles: Vec::new(),
+            _nodes: Vec::new(),
+        };
+        let mut triangle_order: Vec<usize> = (0..triangles.len()).collect();
+        if false == triangle_order.is_empty() {
+            mesh_bvh._nodes.push(BvhNode { _min: Vector3::zeros(), _max: Vector3::zeros(), _first: 0, _count: 0 });
+            mesh_bvh.build_node(0, &triangles, &mut triangle_order, 0);
+        }
+        let mut triangles: Vec<Option<Triangle>> = triangles.into_iter().map(Some).collect();
+        mesh_bvh._triangles = triangle_order.iter().map(|triangle_index| triangles[*triangle_index].take().unwrap()).collect();
+        mesh_bvh
+    }
+
+    fn build_node(&mut self, node_index: usize, triangles: &[Triangle], triangle_order: &mut [usize], first: usize) {
+        let mut bound_min = Vector3::new(std::f32::MAX, std::f32::MAX, std::f32::MAX);
+        let mut bound_max = Vector3::new(std::f32::MIN, std::f32::MIN, std::f32::MIN);
+        for triangle_index in triangle_order.iter() {
+            let triangle = &triangles[*triangle_index];
+            for point in [triangle._p0, &triangle._p0 + &triangle._edge1, &triangle._p0 + &triangle._edge2].iter() {
+                bound_min = bound_min.inf(point);
+                bound_max = bound_max.sup(point);
+            }
+        }
+        self._nodes[node_index]._min = bound_min;
+        self._nodes[node_index]._max = bound_max;
+
+        if triangle_order.len() <= BVH_LEAF_TRIANGLE_COUNT {
+            self._nodes[node_index]._first = first;
+            self._nodes[node_index]._count = triangle_order.len();
+            return;
+        }
+
+        // median split of the centroids on the longest axis
+        let extent = &bound_max - &bound_min;
+        let axis = if extent.y < extent.x && extent.z < extent.x { 0 } else if extent.z < extent.y { 1 } else { 2 };
+        triangle_order.sort_by(|a, b| {
+            let centroid_a = get_triangle_centroid(&triangles[*a])[axis];
+            let centroid_b = get_triangle_centroid(&triangles[*b])[axis];
+            centroid_a.partial_cmp(&centroid_b).unwrap_or(std::cmp::Ordering::Equal)
+        });
+        let half = triangle_order.len() / 2;
+        let left_index = self._nodes.len();
+        self._nodes.push(BvhNode { _min: Vector3::zeros(), _max: Vector3::zeros(), _first: 0, _count: 0 });
+        self._nodes.push(BvhNode { _min: Vector3::zeros(), _max: Vector3::zeros(), _first: 0, _count: 0 });
+        self._nodes[node_index]._first = left_index;
+        self._nodes[node_index]._count = 0;
+        let (left_order, right_order) = triangle_order.split_at_mut(half);
+        self.build_node(left_index, triangles, left_order, first);
+        self.build_node(left_index + 1, triangles, right_order, first + half);
+    }
+
+    // any hit in (0, max_distance), both faces occlude
+    pub fn is_occluded(&self, origin: &Vector3<f32>, direction: &Vector3<f32>, max_distance: f32) -> bool {
+        if self._nodes.is_empty() {
+            return false;
+        }
+        let inv_direction = Vector3::new(1.0 / direction.x, 1.0 / direction.y, 1.0 / direction.z);
+        let mut node_stack: Vec<usize> = vec![0];
+        while let Some(node_index) = node_stack.pop() {
+            let node = &self._nodes[node_index];
+            if false == intersect_ray_aabb(origin, &inv_direction, &node._min, &node._max, max_distance) {
+                continue;
+            }
+            if 0 == node._count {
+                node_stack.push(node._first);
+                node_stack.push(node._first + 1);
+            } else {
+                for triangle in self._triangles[node._first..(node._first + node._count)].iter() {
+                    if intersect_ray_triangle(origin, direction, triangle, max_distance) {
+                        return true;
+                    }
+                }
+            }
+        }
+        false
+    }
+}
+
+fn get_triangle_centroid(triangle: &Triangle) -> Vector3<f32> {
+    &triangle._p0 + (&triangle._edge1 + &triangle._edge2) / 3.0
+}
+
+fn intersect_ray_aabb(origin: &Vector3<f32>, inv_direction: &Vector3<f32>, bound_min: &Vector3<f32>, bound_max: &Vector3<f32>, max_distance: f32) -> bool {
+    let mut t_min: f32 = 0.0;
+    let mut t_max: f32 = max_distance;
+    for axis in 0..3 {
+        let t0 = (bound_min[axis] - origin[axis]) * inv_direction[axis];
+        let t1 = (bound_max[axis] - origin[axis]) * inv_direction[axis];
+        t_min = t_min.max(t0.min(t1));
+        t_max = t_max.min(t0.max(t1));
+    }
+    t_min <= t_max
+}
+
+// Möller–Trumbore
+fn intersect_ray_triangle(origin: &Vector3<f32>, direction: &Vector3<f32>, triangle: &Triangle, max_distance: f32) -> bool {
+    let p = direction.cross(&triangle._edge2);
+    let determinant = triangle._edge1.dot(&p);
+    if determinant.abs() < std::f32::EPSILON {
+        return false;
+    }
+    let inv_determinant = 1.0 / determinant;
+    let s = origin - &triangle._p0;
+    let u = s.dot(&p) * inv_determinant;
+    if u < 0.0 || 1.0 < u {
+        return false;
+    }
+    let q = s.cross(&triangle._edge1);
+    let v = direction.dot(&q) * inv_determinant;
+    if v < 0.0 || 1.0 < u + v {
+        return false;
+    }
+    let t = triangle._edge2.dot(&q) * inv_determinant;
+    0.0 < t && t < max_distance
+}
+
+fn get_vertex_positions(geometry_create_info: &GeometryCreateInfo) -> Vec<Vector3<f32>> {
+    if geometry_create_info._skeletal_vertex_datas.is_empty() {
+        geometry_create_info._vertex_datas.iter().map(|vertex_data| vertex_data._position).collect()
+    } else {
+        geometry_create_info._skeletal_vertex_datas.iter().map(|vertex_data| vertex_data._position).collect()
+    }
+}
+
+fn get_vertex_normals(geometry_create_info: &GeometryCreateInfo) -> Vec<Vector3<f32>> {
+    let normals: Vec<Vector3<f32>> = if geometry_create_info._skeletal_vertex_datas.is_empty() {
+        geometry_create_info._vertex_datas.iter().map(|vertex_data| vertex_data._normal).collect()
+    } else {
+        geometry_create_info._skeletal_vertex_datas.iter().map(|vertex_data| vertex_data._normal).collect()
+    };
+    normals.iter().map(|normal| if 0.0 < normal.norm_squared() { normal.normalize() } else { Vector3::new(0.0, 1.0, 0.0) }).collect()
+}
+
+// orthonormal basis, "Building an Orthonormal Basis, Revisited"
+fn get_tangent_basis(normal: &Vector3<f32>) -> (Vector3<f32>, Vector3<f32>) {
+    let sign = 1.0f32.copysign(normal.z);
+    let a = -1.0 / (sign + normal.z);
+    let b = normal.x * normal.y * a;
+    (
+        Vector3::new(1.0 + sign * normal.x * normal.x * a, sign * b, -sign * normal.x),
+        Vector3::new(b, sign + normal.y * normal.y * a, -normal.y),
+    )
+}
+
+// cosine weighted hemisphere sampling of the hammersley points, rotated per vertex with a hash of the vertex index.
+pub fn compute_vertex_ambient_occlusion(
+    mesh_bvh: &MeshBvh,
+    position: &Vector3<f32>,
+    normal: &Vector3<f32>,
+    vertex_index: usize,
+    sample_count: u32,
+    ray_bias: f32,
+    max_distance: f32,
+) -> f32 {
+    let sample_count = sample_count.max(1);
+    let (tangent, bitangent) = get_tangent_basis(normal);
+    let rotation_hash = compute_hash(compute_hash(HASH_OFFSET_BASIS, &AMBIENT_OCCLUSION_SEED.to_le_bytes()), &(vertex_index as u64).to_le_bytes());
+    let rotation = (rotation_hash >> 40) as f32 / (1u64 << 24) as f32;
+    let origin = position + normal * ray_bias;
+    let mut unoccluded_count: u32 = 0;
+    for sample_index in 0..sample_count {
+        let sample = math::hammersley_2d(sample_index, sample_count);
+        let phi = (sample.x + rotation).fract() * math::TWO_PI;
+        let sin_theta = sample.y.sqrt();
+        let cos_theta = (1.0 - sample.y).max(0.0).sqrt();
+        let direction = (&tangent * (phi.cos() * sin_theta) + &bitangent * (phi.sin() * sin_theta) + normal * cos_theta).normalize();
+        if false == mesh_bvh.is_occluded(&origin, &direction, max_distance) {
+            unoccluded_count += 1;
+        }
+    }
+    unoccluded_count as f32 / sample_count as f32
+}
+
+// concave when the neighboring faces bend toward the vertex normal, flat and convex vertices are 1.0
+pub fn compute_vertex_cavities(positions: &[Vector3<f32>], normals: &[Vector3<f32>], indices: &[u32], cavity_strength: f32, epsilon: f32) -> Vec<f32> {
+    let epsilon = epsilon.max(std::f32::EPSILON);
+    let quantize_position = |position: &Vector3<f32>| -> [i64; 3] {
+        [(position.x / epsilon).round() as i64, (position.y / epsilon).round() as i64, (position.z / epsilon).round() as i64]
+    };
+    let triangle_count = indices.len() / 3;
+    let mut face_normals: Vec<Vector3<f32>> = Vec::with_capacity(triangle_count);
+    let mut face_centroids: Vec<Vector3<f32>> = Vec::with_capacity(triangle_count);
+    let mut position_faces: HashMap<[i64; 3], Vec<usize>> = HashMap::new();
+    for triangle_index in 0..triangle_count {
+        let p0 = &positions[indices[triangle_index * 3] as usize];
+        let p1 = &positions[indices[triangle_index * 3 + 1] as usize];
+        let p2 = &positions[indices[triangle_index * 3 + 2] as usize];
+        let face_normal = (p1 - p0).cross(&(p2 - p0));
+        face_normals.push(if 0.0 < face_normal.norm_squared() { face_normal.normalize() } else { Vector3::zeros() });
+        face_centroids.push((p0 + p1 + p2) / 3.0);
+        for i in 0..3 {
+            let vertex_index = indices[triangle_index * 3 + i] as usize;
+            position_faces.entry(quantize_position(&positions[vertex_index])).or_insert(Vec::new()).push(triangle_index);
+        }
+    }
+
+    positions.iter().zip(normals.iter()).map(|(position, normal)| {
+        let mut curvature: f32 = 0.0;
+        let mut face_count: u32 = 0;
+        if let Some(faces) = position_faces.get(&quantize_position(position)) {
+            for face in faces.iter() {
+                let to_centroid = &face_centroids[*face] - position;
+                if 0.0 < to_centroid.norm_squared() && 0.0 < face_normals[*face].norm_squared() {
+                    let bend = (1.0 - normal.dot(&face_normals[*face])).max(0.0);
+                    curvature += bend * normal.dot(&to_centroid.normalize()).signum();
+                    face_count += 1;
+                }
+            }
+        }
+        let curvature = if 0 < face_count { curvature / face_count as f32 } else { 0.0 };
+        1.0 - (curvature * cavity_strength).max(0.0).min(1.0)
+    }).collect()
+}
+
+// bakes the self occlusion and the cavity of the mesh into the vertex colors at import.
+// the result is deterministic, so the mesh cache and the content hash of the mesh are stable.
+pub fn bake_vertex_ambient_occlusion(mesh_data_create_info: &mut MeshDataCreateInfo, mesh_import_settings: &MeshImportSettings) {
+    let mesh_bvh = Arc::new(MeshBvh::create_mesh_bvh(&mesh_data_create_info._geometry_create_infos));
+    let bound_size = mesh_data_create_info._bound_box._size.norm().max(std::f32::EPSILON);
+    let ray_bias = bound_size * AMBIENT_OCCLUSION_RAY_BIAS;
+    let max_distance = bound_size * mesh_import_settings._ambient_occlusion_radius;
+    let sample_count = mesh_import_settings._ambient_occlusion_sample_count;
+    let min_ambient_occlusion = mesh_import_settings._min_ambient_occlusion;
+
+    for geometry_create_info in mesh_data_create_info._geometry_create_infos.iter_mut() {
+        let positions = Arc::new(get_vertex_positions(geometry_create_info));
+        let normals = Arc::new(get_vertex_normals(geometry_create_info));
+        let vertex_count = positions.len();
+        let chunk_size = (vertex_count + AMBIENT_OCCLUSION_THREAD_COUNT - 1) / AMBIENT_OCCLUSION_THREAD_COUNT;
+        let workers: Vec<thread::JoinHandle<Vec<f32>>> = (0..AMBIENT_OCCLUSION_THREAD_COUNT).map(|thread_index| {
+            let mesh_bvh = mesh_bvh.clone();
+            let positions = positions.clone();
+            let normals = normals.clone();
+            thread::spawn(move || {
+                let first = (thread_index * chunk_size).min(vertex_count);
+                let last = (first + chunk_size).min(vertex_count);
+                (first..last).map(|vertex_index| {
+                    compute_vertex_ambient_occlusion(&mesh_bvh, &positions[vertex_index], &normals[vertex_index], vertex_index, sample_count, ray_bias, max_distance)
+                }).collect()
+            })
+        }).collect();
+        // joined in order, the result does not depend on the scheduling
+        let ambient_occlusions: Vec<f32> = workers.into_iter().flat_map(|worker| worker.join().unwrap()).collect();
+        let cavities = compute_vertex_cavities(&positions, &normals, &geometry_create_info._indices, mesh_import_settings._cavity_strength, mesh_import_settings._weld_epsilon);
+
+        // open meshes and thin geometry are clamped, they must not be fully black
+        let get_color = |color: u32, vertex_index: usize| -> u32 {
+            encode_vertex_color(color, ambient_occlusions[vertex_index].max(min_ambient_occlusion), cavities[vertex_index].max(min_ambient_occlusion))
+        };
+        for (vertex_index, vertex_data) in geometry_create_info._vertex_datas.iter_mut().enumerate() {
+            vertex_data._color = get_color(vertex_data._color, vertex_index);
+        }
+        for (vertex_index, vertex_data) in geometry_create_info._skeletal_vertex_datas.iter_mut().enumerate() {
+            vertex_data._color = get_color(vertex_data._color, vertex_index);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utilities::bounding_box;
+    use crate::vulkan_context::geometry_buffer::StaticVertexData;
+
+    // the grid of (major, minor) segments, the positions and the normals by the angles
+    fn create_parametric_mesh(major_segments: u32, minor_segments: u32, get_vertex: &dyn Fn(f32, f32) -> (Vector3<f32>, Vector3<f32>)) -> MeshDataCreateInfo {
+        let mut vertex_datas: Vec<StaticVertexData> = Vec::new();
+        for i in 0..=major_segments {
+            for j in 0..=minor_segments {
+                let (position, normal) = get_vertex(i as f32 / major_segments as f32, j as f32 / minor_segments as f32);
+                vertex_datas.push(StaticVertexData { _position: position, _normal: normal, _color: 0xFFFF_FFFF, ..Default::default() });
+            }
+        }
+        let mut indices: Vec<u32> = Vec::new();
+        let stride = minor_segments + 1;
+        for i in 0..major_segments {
+            for j in 0..minor_segments {
+                let (v0, v1, v2, v3) = (i * stride + j, (i + 1) * stride + j, (i + 1) * stride + j + 1, i * stride + j + 1);
+                indices.extend_from_slice(&[v0, v1, v2, v0, v2, v3]);
+            }
+        }
+        let positions: Vec<Vector3<f32>> = vertex_datas.iter().map(|vertex_data| vertex_data._position).collect();
+        let bound_box = bounding_box::calc_bounding_box(&positions);
+        MeshDataCreateInfo {
+            _bound_box: bound_box.clone(),
+            _geometry_create_infos: vec![GeometryCreateInfo {
+                _vertex_datas: vertex_datas,
+                _indices: indices,
+                _bounding_box: bound_box,
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn create_sphere(radius: f32) -> MeshDataCreateInfo {
+        create_parametric_mesh(24, 12, &|u, v| {
+            let (phi, theta) = (u * math::TWO_PI, v * std::f32::consts::PI);
+            let normal = Vector3::new(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin());
+            (normal * radius, normal)
+        })
+    }
+
+    fn create_torus(major_radius: f32, minor_radius: f32) -> MeshDataCreateInfo {
+        create_parametric_mesh(32, 16, &|u, v| {
+            let (phi, theta) = (u * math::TWO_PI, v * math::TWO_PI);
+            let ring_direction = Vector3::new(phi.cos(), 0.0, phi.sin());
+            let normal = ring_direction * theta.cos() + Vector3::new(0.0, theta.sin(), 0.0);
+            (ring_direction * major_radius + normal * minor_radius, normal)
+        })
+    }
+
+    fn get_baked_values(mesh_data_create_info: &MeshDataCreateInfo) -> Vec<(Vector3<f32>, Vector3<f32>, f32, f32)> {
+        mesh_data_create_info._geometry_create_infos[0]._vertex_datas.iter().map(|vertex_data| {
+            let (ambient_occlusion, cavity) = decode_vertex_color(vertex_data._color);
+            (vertex_data._position, vertex_data._normal, ambient_occlusion, cavity)
+        }).collect()
+    }
+
+    #[test]
+    fn test_vertex_color_encoding() {
+        let color = encode_vertex_color(0x1234_5678, 0.5, 1.0);
+        assert_eq!(0x5678, color & 0xFFFF);
+        let (ambient_occlusion, cavity) = decode_vertex_color(color);
+        assert!((ambient_occlusion - 0.5).abs() < 1.0 / 255.0);
+        assert_eq!(1.0, cavity);
+        assert_eq!((0.0, 1.0), decode_vertex_color(encode_vertex_color(0, -1.0, 2.0)));
+    }
+
+    #[test]
+    fn test_sphere_is_uniform() {
+        let mut sphere = create_sphere(1.0);
+        bake_vertex_ambient_occlusion(&mut sphere, &MeshImportSettings::default());
+        // convex, no self occlusion and no cavity
+        for (position, _normal, ambient_occlusion, cavity) in get_baked_values(&sphere) {
+            assert_eq!(1.0, ambient_occlusion, "{:?}", position);
+            assert_eq!(1.0, cavity, "{:?}", position);
+        }
+    }
+
+    #[test]
+    fn test_torus_inner_ring_is_darker() {
+        let mut torus = create_torus(1.0, 0.4);
+        let mesh_import_settings = MeshImportSettings::default();
+        bake_vertex_ambient_occlusion(&mut torus, &mesh_import_settings);
+        let baked_values = get_baked_values(&torus);
+        // the inner ring faces the other side of the torus, the outer ring faces the open space
+        let inner_ring: Vec<f32> = baked_values.iter().filter(|(position, normal, _, _)| {
+            let ring_direction = Vector3::new(position.x, 0.0, position.z).normalize();
+            normal.dot(&ring_direction) < -0.99
+        }).map(|(_, _, ambient_occlusion, _)| *ambient_occlusion).collect();
+        let outer_ring: Vec<f32> = baked_values.iter().filter(|(position, normal, _, _)| {
+            let ring_direction = Vector3::new(position.x, 0.0, position.z).normalize();
+            0.99 < normal.dot(&ring_direction)
+        }).map(|(_, _, ambient_occlusion, _)| *ambient_occlusion).collect();
+        assert!(false == inner_ring.is_empty() && false == outer_ring.is_empty());
+        for ambient_occlusion in inner_ring.iter() {
+            assert!(mesh_import_settings._min_ambient_occlusion <= *ambient_occlusion && *ambient_occlusion < 0.8, "{}", ambient_occlusion);
+        }
+        for ambient_occlusion in outer_ring.iter() {
+            assert_eq!(1.0, *ambient_occlusion);
+        }
+    }
+
+    #[test]
+    fn test_deterministic() {
+        let mut torus_0 = create_torus(1.0, 0.4);
+        let mut torus_1 = create_torus(1.0, 0.4);
+        let mesh_import_settings = MeshImportSettings::default();
+        bake_vertex_ambient_occlusion(&mut torus_0, &mesh_import_settings);
+        bake_vertex_ambient_occlusion(&mut torus_1, &mesh_import_settings);
+        let colors_0: Vec<u32> = torus_0._geometry_create_infos[0]._vertex_datas.iter().map(|vertex_data| vertex_data._color).collect();
+        let colors_1: Vec<u32> = torus_1._geometry_create_infos[0]._vertex_datas.iter().map(|vertex_data| vertex_data._color).collect();
+        assert_eq!(colors_0, colors_1);
+    }
+
+    #[test]
+    fn test_thin_geometry_is_clamped() {
+        // two quads facing each other, almost closed. the gap is wider than the ray bias
+        let mut thin = create_parametric_mesh(4, 4, &|u, v| (Vector3::new(u, 0.0, v), Vector3::new(0.0, 1.0, 0.0)));
+        let top = create_parametric_mesh(4, 4, &|u, v| (Vector3::new(v, 0.01, u), Vector3::new(0.0, -1.0, 0.0)));
+        thin._geometry_create_infos.push(top._geometry_create_infos[0].clone());
+        thin._bound_box = bounding_box::calc_bounding_box(&vec![Vector3::zeros(), Vector3::new(1.0, 0.01, 1.0)]);
+        let mesh_import_settings = MeshImportSettings::default();
+        bake_vertex_ambient_occlusion(&mut thin, &mesh_import_settings);
+        for geometry_create_info in thin._geometry_create_infos.iter() {
+            for vertex_data in geometry_create_info._vertex_datas.iter() {
+                let is_inner = 0.0 < vertex_data._position.x && vertex_data._position.x < 1.0 && 0.0 < vertex_data._position.z && vertex_data._position.z < 1.0;
+                let (ambient_occlusion, _cavity) = decode_vertex_color(vertex_data._color);
+                assert!(mesh_import_settings._min_ambient_occlusion - 1.0 / 255.0 < ambient_occlusion);
+                if is_inner {
+                    assert!(ambient_occlusion < mesh_import_settings._min_ambient_occlusion + 1.0 / 255.0, "{}", ambient_occlusion);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_cavity() {
+        // the center of a bowl is concave, the tip of a pyramid is convex
+        let ring: Vec<Vector3<f32>> = (0..6).map(|i| {
+            let angle = i as f32 / 6.0 * math::TWO_PI;
+            Vector3::new(angle.cos(), 0.0, angle.sin())
+        }).collect();
+        let mut indices: Vec<u32> = Vec::new();
+        for i in 0..6u32 {
+            indices.extend_from_slice(&[0, 1 + (i + 1) % 6, 1 + i]);
+        }
+        let up = Vector3::new(0.0, 1.0, 0.0);
+        for (center_y, is_concave) in [(-0.5f32, true), (0.5, false), (0.0, false)].iter() {
+            let mut positions = vec![Vector3::new(0.0, *center_y, 0.0)];
+            positions.extend_from_slice(&ring);
+            let normals = vec![up; positions.len()];
+            let cavities = compute_vertex_cavities(&positions, &normals, &indices, 2.0, 0.0001);
+            if *is_concave {
+                assert!(cavities[0] < 0.9, "{}", cavities[0]);
+            } else {
+                assert_eq!(1.0, cavities[0]);
+            }
+        }
+    }
+}
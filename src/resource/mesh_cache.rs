@@ -0,0 +1,110 @@
+use std::fs::{ self, File };
+use std::io::prelude::*;
+use std::io::Cursor;
+use std::path::{ Path, PathBuf };
+
+use bincode;
+use byteorder::{ LittleEndian, ReadBytesExt, WriteBytesExt };
+
+use crate::renderer::mesh::MeshDataCreateInfo;
+
+pub const MESH_CACHE_DIRECTORY: &str = "resource/mesh_caches";
+pub const EXT_MESH_CACHE: &str = "mesh_cache";
+pub const MESH_CACHE_MAGIC: u32 = 0x4D45_5348; // "MESH"
+// increase the version when the result of importers(obj, collada, welding, tangent...) has been changed.
+pub const MESH_IMPORTER_VERSION: u32 = 2;
+pub const HASH_OFFSET_BASIS: u64 = 0xCBF2_9CE4_8422_2325;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeshCacheHeader {
+    pub _magic: u32,
+    pub _importer_version: u32,
+    pub _source_hash: u64,
+    pub _data_checksum: u64,
+    pub _data_size: u64,
+}
+
+// FNV-1a, the hash must be stable between builds.
+pub fn compute_hash(hash: u64, bytes: &[u8]) -> u64 {
+    let mut hash = hash;
+    for byte in bytes.iter() {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01B3);
+    }
+    hash
+}
+
+pub fn get_mesh_cache_file_path(mesh_source_file: &Path) -> PathBuf {
+    let source_path: String = mesh_source_file.to_string_lossy().replace("\\", "/");
+    let path_hash = compute_hash(HASH_OFFSET_BASIS, source_path.as_bytes());
+    let mut cache_file_path = PathBuf::from(MESH_CACHE_DIRECTORY);
+    cache_file_path.push(format!("{}_{:016x}", mesh_source_file.file_stem().unwrap().to_str().unwrap(), path_hash));
+    cache_file_path.set_extension(EXT_MESH_CACHE);
+    cache_file_path
+}
+
+fn read_header(contents: &mut Cursor<Vec<u8>>) -> std::io::Result<MeshCacheHeader> {
+    Ok(MeshCacheHeader {
+        _magic: contents.read_u32::<LittleEndian>()?,
+        _importer_version: contents.read_u32::<LittleEndian>()?,
+        _source_hash: contents.read_u64::<LittleEndian>()?,
+        _data_checksum: contents.read_u64::<LittleEndian>()?,
+        _data_size: contents.read_u64::<LittleEndian>()?,
+    })
+}
+
+// returns None when the cache is missing, outdated or corrupted, then the mesh is imported from the source again.
+pub fn load_mesh_cache(cache_file_path: &Path, source_hash: u64) -> Option<MeshDataCreateInfo> {
+    let bytes = match fs::read(cache_file_path) {
+        Ok(bytes) => bytes,
+        Err(_) => return None,
+    };
+    let mut contents = Cursor::new(bytes);
+    let header = match read_header(&mut contents) {
+        Ok(header) => header,
+        Err(_) => {
+            log::warn!("load_mesh_cache: corrupted header {:?}", cache_file_path);
+            return None;
+        }
+    };
+
+    if MESH_CACHE_MAGIC != header._magic || MESH_IMPORTER_VERSION != header._importer_version || source_hash != header._source_hash {
+        log::debug!("load_mesh_cache: outdated {:?}", cache_file_path);
+        return None;
+    }
+
+    let data_offset = contents.position() as usize;
+    let data = &contents.get_ref()[data_offset..];
+    if header._data_size != data.len() as u64 || header._data_checksum != compute_hash(HASH_OFFSET_BASIS, data) {
+        log::warn!("load_mesh_cache: checksum mismatch {:?}", cache_file_path);
+        return None;
+    }
+
+    match bincode::deserialize::<MeshDataCreateInfo>(data) {
+        Ok(mesh_data_create_info) => Some(mesh_data_create_info),
+        Err(e) => {
+            log::warn!("load_mesh_cache: failed to deserialize {:?} {:?}", cache_file_path, e);
+            None
+        }
+    }
+}
+
+pub fn save_mesh_cache(cache_file_path: &Path, source_hash: u64, mesh_data_create_info: &MeshDataCreateInfo) {
+    let data: Vec<u8> = bincode::serialize(mesh_data_create_info).unwrap();
+    let mut contents: Vec<u8> = Vec::with_capacity(data.len() + 32);
+    contents.write_u32::<LittleEndian>(MESH_CACHE_MAGIC).unwrap();
+    contents.write_u32::<LittleEndian>(MESH_IMPORTER_VERSION).unwrap();
+    contents.write_u64::<LittleEndian>(source_hash).unwrap();
+    contents.write_u64::<LittleEndian>(compute_hash(HASH_OFFSET_BASIS, &data)).unwrap();
+    contents.write_u64::<LittleEndian>(data.len() as u64).unwrap();
+    contents.extend_from_slice(&data);
+
+    // the cache is optional, failure of writing must not stop loading.
+    let result = fs::create_dir_all(cache_file_path.parent().unwrap()).and_then(|_| {
+        let mut write_file = File::create(cache_file_path)?;
+        write_file.write_all(&contents)
+    });
+    if let Err(e) = result {
+        log::warn!("save_mesh_cache: failed to write {:?} {:?}", cache_file_path, e);
+    }
+}
@@ -0,0 +1,263 @@
+use std::collections::HashMap;
+
+use nalgebra::{ Vector2, Vector3, Vector4 };
+use serde::{ Serialize, Deserialize };
+
+pub const EXT_MESH_IMPORT_SETTINGS: &str = "meta";
+
+// import settings of mesh source file. ex) resource/externals/meshes/cube.obj.meta
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct MeshImportSettings {
+    pub _enable_weld: bool,
+    pub _weld_epsilon: f32,
+    pub _enable_smooth_normal: bool, // recompute smooth normals when the source has per-face normals
+    pub _smooth_normal_angle: f32, // degree
+    pub _enable_vertex_ambient_occlusion: bool, // bake the self occlusion and the cavity into the vertex colors, see mesh_ambient_occlusion.rs
+    pub _ambient_occlusion_sample_count: u32,
+    pub _ambient_occlusion_radius: f32, // ratio of the bound box size
+    pub _min_ambient_occlusion: f32,
+    pub _cavity_strength: f32,
+    // the lod distances of the mesh, see mesh::MeshLodData. skipped when empty, so the hash of the mesh cache is kept.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub _lod_distances: Vec<f32>,
+}
+
+impl Default for MeshImportSettings {
+    fn default() -> MeshImportSettings {
+        MeshImportSettings {
+            _enable_weld: true,
+            _weld_epsilon: 0.0001,
+            _enable_smooth_normal: false,
+            _smooth_normal_angle: 60.0,
+            _enable_vertex_ambient_occlusion: false,
+            _ambient_occlusion_sample_count: 64,
+            _ambient_occlusion_radius: 0.5,
+            _min_ambient_occlusion: 0.2,
+            _cavity_strength: 2.0,
+            _lod_distances: Vec::new(),
+        }
+    }
+}
+
+type WeldKey = ([i64; 3], [i64; 3], [i64; 2], [u32; 4], [u32; 4]);
+
+fn quantize(value: f32, epsilon: f32) -> i64 {
+    (value / epsilon).round() as i64
+}
+
+fn quantize_position(position: &Vector3<f32>, epsilon: f32) -> [i64; 3] {
+    [quantize(position.x, epsilon), quantize(position.y, epsilon), quantize(position.z, epsilon)]
+}
+
+// result of welding, index of welded vertex -> index of the source vertex
+pub struct WeldResult {
+    pub _source_vertex_indices: Vec<usize>,
+    pub _indices: Vec<u32>,
+}
+
+impl WeldResult {
+    pub fn remap<T: Clone>(&self, source_datas: &[T]) -> Vec<T> {
+        self._source_vertex_indices.iter().map(|source_index| source_datas[*source_index].clone()).collect()
+    }
+}
+
+// Skeletal vertices are welded only when the bone indices and weights are identical, pass empty slices for static meshes.
+pub fn weld_vertices(
+    positions: &[Vector3<f32>],
+    normals: &[Vector3<f32>],
+    texcoords: &[Vector2<f32>],
+    bone_indices: &[Vector4<u32>],
+    bone_weights: &[Vector4<f32>],
+    indices: &[u32],
+    epsilon: f32,
+) -> WeldResult {
+    let epsilon = epsilon.max(std::f32::EPSILON);
+    let has_bone = false == bone_indices.is_empty() && false == bone_weights.is_empty();
+    let mut weld_map: HashMap<WeldKey, u32> = HashMap::new();
+    let mut source_vertex_indices: Vec<usize> = Vec::new();
+    let mut vertex_remap: Vec<u32> = Vec::with_capacity(positions.len());
+    for i in 0..positions.len() {
+        let normal = &normals[i];
+        let texcoord = &texcoords[i];
+        let (bone_index_key, bone_weight_key) = if has_bone {
+            let bone_index = &bone_indices[i];
+            let bone_weight = &bone_weights[i];
+            ([bone_index.x, bone_index.y, bone_index.z, bone_index.w],
+             [bone_weight.x.to_bits(), bone_weight.y.to_bits(), bone_weight.z.to_bits(), bone_weight.w.to_bits()])
+        } else {
+            ([0; 4], [0; 4])
+        };
+        let weld_key: WeldKey = (
+            quantize_position(&positions[i], epsilon),
+            quantize_position(normal, epsilon),
+            [quantize(texcoord.x, epsilon), quantize(texcoord.y, epsilon)],
+            bone_index_key,
+            bone_weight_key,
+        );
+        let welded_index = match weld_map.get(&weld_key) {
+            Some(welded_index) => *welded_index,
+            None => {
+                let welded_index = source_vertex_indices.len() as u32;
+                weld_map.insert(weld_key, welded_index);
+                source_vertex_indices.push(i);
+                welded_index
+            }
+        };
+        vertex_remap.push(welded_index);
+    }
+
+    WeldResult {
+        _source_vertex_indices: source_vertex_indices,
+        _indices: indices.iter().map(|index| vertex_remap[*index as usize]).collect(),
+    }
+}
+
+// Average the face normals of the triangles sharing a position within the angle threshold, hard edges are kept.
+pub fn compute_smooth_normals(
+    positions: &[Vector3<f32>],
+    indices: &[u32],
+    smooth_normal_angle: f32,
+    epsilon: f32,
+) -> Vec<Vector3<f32>> {
+    let epsilon = epsilon.max(std::f32::EPSILON);
+    let cos_threshold = smooth_normal_angle.to_radians().cos();
+    let triangle_count = indices.len() / 3;
+    let mut face_normals: Vec<Vector3<f32>> = Vec::with_capacity(triangle_count);
+    let mut vertex_faces: Vec<Vec<usize>> = vec![Vec::new(); positions.len()];
+    let mut position_faces: HashMap<[i64; 3], Vec<usize>> = HashMap::new();
+    for triangle_index in 0..triangle_count {
+        let i0 = indices[triangle_index * 3] as usize;
+        let i1 = indices[triangle_index * 3 + 1] as usize;
+        let i2 = indices[triangle_index * 3 + 2] as usize;
+        // area weighted
+        face_normals.push((&positions[i1] - &positions[i0]).cross(&(&positions[i2] - &positions[i0])));
+        for vertex_index in [i0, i1, i2].iter() {
+            vertex_faces[*vertex_index].push(triangle_index);
+            position_faces.entry(quantize_position(&positions[*vertex_index], epsilon)).or_insert(Vec::new()).push(triangle_index);
+        }
+    }
+
+    positions.iter().enumerate().map(|(vertex_index, position)| {
+        let mut smooth_normal: Vector3<f32> = Vector3::zeros();
+        let mut accepted_faces: Vec<usize> = Vec::new();
+        let empty_faces: Vec<usize> = Vec::new();
+        let shared_faces = position_faces.get(&quantize_position(position, epsilon)).unwrap_or(&empty_faces);
+        for own_face in vertex_faces[vertex_index].iter() {
+            let own_normal = face_normals[*own_face].normalize();
+            for shared_face in shared_faces.iter() {
+                let shared_normal = &face_normals[*shared_face];
+                if false == accepted_faces.contains(shared_face) && cos_threshold <= own_normal.dot(&shared_normal.normalize()) {
+                    accepted_faces.push(*shared_face);
+                    smooth_normal += shared_normal;
+                }
+            }
+        }
+        if 0.0 < smooth_normal.norm_squared() { smooth_normal.normalize() } else { Vector3::new(0.0, 1.0, 0.0) }
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // positions, normals, texcoords, indices
+    type TestMesh = (Vec<Vector3<f32>>, Vec<Vector3<f32>>, Vec<Vector2<f32>>, Vec<u32>);
+
+    // the unwelded cube of an obj file, 3 vertices per triangle with the face normals
+    fn create_face_normal_cube() -> TestMesh {
+        let mut positions: Vec<Vector3<f32>> = Vec::new();
+        let mut normals: Vec<Vector3<f32>> = Vec::new();
+        let mut texcoords: Vec<Vector2<f32>> = Vec::new();
+        for axis in 0..3 {
+            for sign in [-1.0f32, 1.0].iter() {
+                let mut normal = Vector3::zeros();
+                normal[axis] = *sign;
+                let (mut tangent, mut bitangent) = (Vector3::zeros(), Vector3::zeros());
+                tangent[(axis + 1) % 3] = 1.0;
+                bitangent[(axis + 2) % 3] = 1.0;
+                if *sign < 0.0 {
+                    std::mem::swap(&mut tangent, &mut bitangent);
+                }
+                let corners = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)];
+                for (u, v) in corners.iter() {
+                    positions.push(normal + tangent * *u + bitangent * *v);
+                    normals.push(normal);
+                    texcoords.push(Vector2::new((u + 1.0) * 0.5, (v + 1.0) * 0.5));
+                }
+            }
+        }
+        let indices: Vec<u32> = (0..positions.len() as u32).collect();
+        (positions, normals, texcoords, indices)
+    }
+
+    fn assert_same_triangles(positions: &[Vector3<f32>], indices: &[u32], weld_result: &WeldResult) {
+        let welded_positions = weld_result.remap(positions);
+        assert_eq!(indices.len(), weld_result._indices.len());
+        for (index, welded_index) in indices.iter().zip(weld_result._indices.iter()) {
+            assert_eq!(positions[*index as usize], welded_positions[*welded_index as usize]);
+        }
+    }
+
+    #[test]
+    fn test_weld_face_normal_cube() {
+        let (positions, normals, texcoords, indices) = create_face_normal_cube();
+        assert_eq!(36, positions.len());
+        // the corners of the faces are shared, the hard edges are kept by the normals
+        let weld_result = weld_vertices(&positions, &normals, &texcoords, &[], &[], &indices, 0.0001);
+        assert_eq!(24, weld_result._source_vertex_indices.len());
+        assert_same_triangles(&positions, &indices, &weld_result);
+
+        // the smooth normals inside the angle threshold keep the hard edges of the cube
+        let smooth_normals = compute_smooth_normals(&positions, &indices, 60.0, 0.0001);
+        for (smooth_normal, normal) in smooth_normals.iter().zip(normals.iter()) {
+            assert!((smooth_normal - normal).norm() < 0.0001);
+        }
+        assert_eq!(24, weld_vertices(&positions, &smooth_normals, &texcoords, &[], &[], &indices, 0.0001)._source_vertex_indices.len());
+    }
+
+    #[test]
+    fn test_weld_smooth_normal_cube() {
+        let (positions, _normals, texcoords, indices) = create_face_normal_cube();
+        // over 90 degrees, the corners of the cube are smooth
+        let smooth_normals = compute_smooth_normals(&positions, &indices, 100.0, 0.0001);
+        // the normals of a corner are shared, pointing out of the cube
+        for (position, smooth_normal) in positions.iter().zip(smooth_normals.iter()) {
+            assert!(0.0 < smooth_normal.x * position.x && 0.0 < smooth_normal.y * position.y && 0.0 < smooth_normal.z * position.z);
+            for (other_position, other_normal) in positions.iter().zip(smooth_normals.iter()) {
+                if position == other_position {
+                    assert!((smooth_normal - other_normal).norm() < 0.0001);
+                }
+            }
+        }
+        // only the texcoords split the corners
+        let weld_result = weld_vertices(&positions, &smooth_normals, &texcoords, &[], &[], &indices, 0.0001);
+        assert!(weld_result._source_vertex_indices.len() < 24);
+        assert_same_triangles(&positions, &indices, &weld_result);
+        let zero_texcoords = vec![Vector2::zeros(); positions.len()];
+        let weld_result = weld_vertices(&positions, &smooth_normals, &zero_texcoords, &[], &[], &indices, 0.0001);
+        assert_eq!(8, weld_result._source_vertex_indices.len());
+        assert_same_triangles(&positions, &indices, &weld_result);
+    }
+
+    #[test]
+    fn test_weld_epsilon_and_bones() {
+        let positions = vec![Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.00004, 0.0, 0.0), Vector3::new(0.001, 0.0, 0.0)];
+        let normals = vec![Vector3::new(0.0, 1.0, 0.0); 3];
+        let texcoords = vec![Vector2::zeros(); 3];
+        let indices = vec![0, 1, 2];
+        assert_eq!(vec![0, 0, 1], weld_vertices(&positions, &normals, &texcoords, &[], &[], &indices, 0.0001)._indices);
+        assert_eq!(vec![0, 0, 0], weld_vertices(&positions, &normals, &texcoords, &[], &[], &indices, 0.01)._indices);
+
+        // the skeletal vertices of the same position with the different weights are not welded
+        let bone_indices = vec![Vector4::new(0, 1, 0, 0), Vector4::new(0, 1, 0, 0), Vector4::new(0, 1, 0, 0)];
+        let bone_weights = vec![Vector4::new(0.5, 0.5, 0.0, 0.0), Vector4::new(0.6, 0.4, 0.0, 0.0), Vector4::new(0.5, 0.5, 0.0, 0.0)];
+        let same_positions = vec![Vector3::zeros(); 3];
+        let weld_result = weld_vertices(&same_positions, &normals, &texcoords, &bone_indices, &bone_weights, &indices, 0.01);
+        assert_eq!(vec![0, 1, 0], weld_result._indices);
+        assert_eq!(vec![bone_weights[0], bone_weights[1]], weld_result.remap(&bone_weights));
+        let bone_indices = vec![Vector4::new(0, 1, 0, 0), Vector4::new(0, 2, 0, 0), Vector4::new(0, 1, 0, 0)];
+        let bone_weights = vec![Vector4::new(0.5, 0.5, 0.0, 0.0); 3];
+        assert_eq!(vec![0, 1, 0], weld_vertices(&same_positions, &normals, &texcoords, &bone_indices, &bone_weights, &indices, 0.01)._indices);
+    }
+}
@@ -1,5 +1,18 @@
+pub mod asset_header;
+pub mod asset_report;
 pub mod collada_loader;
+pub mod compressed_texture_loader;
 pub mod font_loader;
+pub mod gltf_loader;
+pub mod hot_reload;
+pub mod ies_loader;
+pub mod impostor_cache;
+pub mod mesh_ambient_occlusion;
+pub mod mesh_cache;
+pub mod mesh_welder;
 pub mod obj_loader;
 pub mod resource;
-pub mod texture_generator;
\ No newline at end of file
+pub mod resource_name;
+pub mod resource_ref_count;
+pub mod texture_generator;
+pub mod virtual_file_system;
\ No newline at end of file
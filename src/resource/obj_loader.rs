@@ -9,6 +9,7 @@ use nalgebra::{
 };
 
 use crate::renderer::mesh::{ MeshDataCreateInfo };
+use crate::resource::mesh_welder::{ self, MeshImportSettings };
 use crate::vulkan_context::vulkan_context;
 use crate::vulkan_context::geometry_buffer::{
     self,
@@ -173,7 +174,7 @@ impl WaveFrontOBJ {
         }
     }
 
-    fn generate_geometry_datas(&mut self) -> Vec<GeometryCreateInfo> {
+    fn generate_geometry_datas(&mut self, mesh_import_settings: &MeshImportSettings) -> Vec<GeometryCreateInfo> {
         // If texcoord is empty, add the default texcoord.
         if self.texcoords.len() < 1 {
             self.texcoords.push(Vector2::new(0.0, 0.0));
@@ -227,6 +228,20 @@ impl WaveFrontOBJ {
                 continue;
             }
 
+            // welding
+            let source_vertex_count = positions.len();
+            if mesh_import_settings._enable_smooth_normal {
+                normals = mesh_welder::compute_smooth_normals(&positions, &indices, mesh_import_settings._smooth_normal_angle, mesh_import_settings._weld_epsilon);
+            }
+            if mesh_import_settings._enable_weld {
+                let weld_result = mesh_welder::weld_vertices(&positions, &normals, &texcoords, &[], &[], &indices, mesh_import_settings._weld_epsilon);
+                positions = weld_result.remap(&positions);
+                normals = weld_result.remap(&normals);
+                texcoords = weld_result.remap(&texcoords);
+                indices = weld_result._indices;
+            }
+            log::info!("    {:?} {}: corners({}), vertices({}), welded vertices({})", self.filename, mesh.name, indices.len(), source_vertex_count, positions.len());
+
             let tangents = geometry_buffer::compute_tangent(&positions, &normals, &texcoords, &indices);
             let vertex_color = vulkan_context::get_color32(255, 255, 255, 255);
             let vertex_datas: Vec<StaticVertexData> = positions
@@ -252,6 +267,7 @@ impl WaveFrontOBJ {
                     _size: &bound_max - &bound_min,
                     _radius: (&bound_max * 0.5 - &bound_min * 0.5).norm()
                 },
+                _material_name: mesh.mtl_name.clone(),
                 ..Default::default()
             });
         }
@@ -319,12 +335,12 @@ impl WaveFrontOBJ {
     //     }
     // }
 
-    pub fn get_mesh_data_create_infos(filename: &PathBuf) -> MeshDataCreateInfo {
+    pub fn get_mesh_data_create_infos(filename: &PathBuf, mesh_import_settings: &MeshImportSettings) -> MeshDataCreateInfo {
         let mut obj = WaveFrontOBJ::initialize(filename);
         //obj.parse_using_library(filename);
         let texcoord_y = true;
         obj.parse(filename, 1.0, texcoord_y);
-        let geometry_create_infos = obj.generate_geometry_datas();
+        let geometry_create_infos = obj.generate_geometry_datas(mesh_import_settings);
 
         MeshDataCreateInfo::create_mesh_data_crate_info(MeshDataCreateInfo {
             _geometry_create_infos: geometry_create_infos,
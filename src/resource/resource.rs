@@ -1,37 +1,81 @@
 use std::fs::{ self, File };
 use std::io::prelude::*;
+use std::io::Cursor;
 use std::path::{ Path, PathBuf };
 use std::collections::HashMap;
+use std::panic::{ self, AssertUnwindSafe };
+use std::time;
 use byteorder::{ LittleEndian, ReadBytesExt };
 
+use serde::{ Serialize, Deserialize };
 use serde_json::{ self, Value, json };
 use bincode;
 use image::{ self, GenericImageView, };
-use ash::{ vk };
+use ash::{ vk, Device };
 use nalgebra::Vector2;
 
 use crate::application::scene_manager::SceneManagerData;
 use crate::constants;
 use crate::resource::font_loader;
+use crate::resource::hot_reload::{ self, HotReloadState };
+use crate::resource::ies_loader::{ self, IesPhotometricType, IesProfile };
+use crate::resource::impostor_cache;
 use crate::resource::collada_loader::Collada;
+use crate::resource::compressed_texture_loader::{ self, COMPRESSED_IMAGE_SOURCE_EXTS };
+use crate::resource::gltf_loader::GLTF;
+use crate::resource::mesh_ambient_occlusion;
+use crate::resource::mesh_cache;
+use crate::resource::mesh_welder::{ self, MeshImportSettings };
 use crate::resource::obj_loader::WaveFrontOBJ;
 use crate::resource::texture_generator;
-use crate::renderer::font::{ self, FontDataCreateInfo, FontData };
-use crate::renderer::mesh::{ MeshData, MeshDataCreateInfo };
+use crate::resource::resource_name::{ self, ResourceName };
+use crate::resource::resource_ref_count::{ ResourceRefCounter, ResourceRefType };
+use crate::resource::virtual_file_system::{ self, VirtualFileSystem };
+use crate::renderer::animation_state_machine::{ AnimStateMachineCreateInfo, AnimStateMachineData };
+use crate::renderer::atmosphere;
+use crate::renderer::auto_exposure;
+use crate::renderer::font::{ self, FontDataCreateInfo, FontData, FontManager };
+use crate::renderer::fxaa;
+use crate::renderer::debug_view;
+use crate::renderer::denoiser;
+use crate::renderer::gpu_skinning;
+use crate::renderer::histogram;
+use crate::renderer::history_confidence;
+use crate::renderer::impostor::{ self, ImpostorBakeData, ImpostorCreateInfo, ImpostorData };
+use crate::renderer::light;
+use crate::renderer::tonemap;
+use crate::renderer::light_cluster;
+use crate::renderer::light_probe;
+use crate::renderer::mesh::{ self, MeshData, MeshDataCreateInfo, MeshLodData };
 use crate::renderer::model::ModelData;
 use crate::renderer::material::{ self, MaterialData };
-use crate::renderer::material_instance::{ self, MaterialInstanceData };
-use crate::renderer::renderer::RendererData;
+use crate::renderer::material_instance::{ self, MaterialBlendMode, MaterialInstanceData };
+use crate::renderer::occlusion_culling;
+use crate::renderer::particle_collision;
+use crate::renderer::renderer::{ self, RendererData };
+use crate::renderer::shader_hook::{ self, ShaderHookSet };
+use crate::renderer::skybox;
+use crate::renderer::terrain::{ self, TerrainHeightmap };
+use crate::renderer::volumetric_fog;
+use crate::renderer::ui::UIManagerData;
+use crate::vulkan_context::bindless_texture::{ self, BindlessMaterialData, BindlessTextureArray };
 use crate::vulkan_context::descriptor::{ self, DescriptorData, DescriptorResourceType, DescriptorResourceInfo };
 use crate::vulkan_context::framebuffer::{self, FramebufferData };
 use crate::vulkan_context::geometry_buffer::{ self, GeometryData };
 use crate::vulkan_context::render_pass::{
     self,
+    PipelineData,
     PipelineDataCreateInfo,
+    PipelineDataMap,
     RenderPassData,
+    RenderPassDataCreateInfo,
     RenderPassPipelineData,
 };
-use crate::vulkan_context::texture::{ TextureData, TextureCreateInfo };
+use crate::vulkan_context::shader;
+use crate::vulkan_context::texture::{ self, TextureData, TextureCreateInfo };
+use crate::vulkan_context::vulkan_context;
+use crate::utilities::file_watcher::{ self, FileEvent, FileEventType, FileWatcher, FileWatcherBackendType };
+use crate::utilities::progress::{ self, ProgressTask };
 use crate::utilities::system::{ self, RcRefCell, newRcRefCell };
 
 const USE_JSON_FOR_MESH: bool = false;
@@ -45,14 +89,20 @@ pub const MATERIAL_INSTANCE_FILE_PATH: &str = "resource/material_instances";
 pub const MESH_SOURCE_FILE_PATH: &str = "resource/externals/meshes";
 pub const MESH_FILE_PATH: &str = "resource/meshes";
 pub const MODEL_FILE_PATH: &str = "resource/models";
+pub const SCENE_FILE_PATH: &str = "resource/scenes";
 pub const TEXTURE_SOURCE_FILE_PATH: &str = "resource/externals/textures";
 pub const TEXTURE_FILE_PATH: &str = "resource/textures";
+pub const IES_SOURCE_FILE_PATH: &str = "resource/externals/ies";
+pub const TERRAIN_SOURCE_FILE_PATH: &str = "resource/externals/terrains";
+pub const ANIMATION_STATE_MACHINE_FILE_PATH: &str = "resource/animation_state_machines";
 
 pub const FONT_SOURCE_EXTS: [&str; 1] = ["ttf"];
 pub const EXT_FONT: &str = "font";
 pub const EXT_OBJ: &str = "obj";
 pub const EXT_COLLADA: &str = "dae";
-pub const MESH_SOURCE_EXTS: [&str; 2] = [EXT_OBJ, EXT_COLLADA];
+pub const EXT_GLTF: &str = "gltf";
+pub const EXT_GLB: &str = "glb";
+pub const MESH_SOURCE_EXTS: [&str; 4] = [EXT_OBJ, EXT_COLLADA, EXT_GLTF, EXT_GLB];
 pub const EXT_JSON: &str = "json";
 pub const EXT_MATERIAL: &str = "mat";
 pub const EXT_MATERIAL_INSTANCE: &str = "matinst";
@@ -60,18 +110,27 @@ pub const EXT_MESH: &str = "mesh";
 pub const EXT_MODEL: &str = "model";
 pub const IMAGE_SOURCE_EXTS: [&str; 4] = ["jpg", "png", "tga", "bmp"];
 pub const EXT_TEXTURE_CUBE: &str = "cube";
+pub const EXT_TEXTURE_EQUIRECTANGULAR: &str = "hdr"; // converted to a cube texture at load time, see skybox::convert_equirectangular_to_cube
 pub const EXT_TEXTURE_2D_ARRAY: &str = "2darray";
 pub const EXT_TEXTURE_3D: &str = "3d";
 pub const EXT_TEXTURE: [&str; 1] = ["texture"];
+pub const EXT_IES: &str = "ies";
+pub const EXT_TERRAIN_HEIGHTMAP: &str = "png"; // 16 bit gray
+pub const EXT_ANIMATION_STATE_MACHINE: &str = "asm";
 
 pub const DEFAULT_FONT_NAME: &str = "NanumBarunGothic_Basic_Latin";
+// the material instances of the engine passes, the error screen is shown instead of the scene without them.
+pub const REQUIRED_MATERIAL_INSTANCE_NAMES: [&str; 2] = ["system/render_font", "system/render_ui"];
 pub const DEFAULT_MESH_NAME: &str = "quad";
 pub const DEFAULT_MODEL_NAME: &str = "quad";
 pub const DEFAULT_TEXTURE_NAME: &str = "common/default";
+pub const IES_PROFILES_TEXTURE_NAME: &str = "common/ies_profiles"; // 2d array, a layer per ies profile
+pub const INVALID_IES_PROFILE_INDEX: i32 = -1;
 pub const DEFAULT_MATERIAL_INSTANCE_NAME: &str = "default";
 pub const DEFAULT_RENDER_PASS_NAME: &str = "render_pass_static_opaque";
 
-pub type ResourceDataMap<T> = HashMap<String, RcRefCell<T>>;
+// the keys are normalized, see resource_name::ResourceName
+pub type ResourceDataMap<T> = HashMap<ResourceName, RcRefCell<T>>;
 pub type FramebufferDatasMap = ResourceDataMap<FramebufferData>;
 pub type MaterialDataMap = ResourceDataMap<material::MaterialData>;
 pub type MaterialInstanceDataMap = ResourceDataMap<material_instance::MaterialInstanceData>;
@@ -83,8 +142,19 @@ pub type TextureDataMap = ResourceDataMap<TextureData>;
 pub type RenderPassDataMap = ResourceDataMap<RenderPassData>;
 pub type DescriptorDataMap = ResourceDataMap<descriptor::DescriptorData>;
 pub type MetaDataMap = ResourceDataMap<MetaData>;
+pub type AnimStateMachineDataMap = ResourceDataMap<AnimStateMachineData>;
 type LoadImageInfoType = (u32, u32, u32, Vec<u8>, vk::Format);
 
+// the source of a texture of load_texture_datas, an unloaded texture is loaded again from it, see reload_texture_data
+#[derive(Clone, Debug)]
+pub enum TextureSourceFile {
+    Compressed(PathBuf),
+    Equirectangular(PathBuf),
+    Image(PathBuf),
+    Combined(vk::ImageViewType, Vec<PathBuf>),
+    Binary(PathBuf),
+}
+
 // TODO: ImageSamplerMap
 
 
@@ -101,6 +171,13 @@ pub struct MetaData {
     _source_changed: bool
 }
 
+// import settings of texture source file. ex) resource/externals/textures/ui/button.png.meta
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+pub struct TextureImportSettings {
+    pub _exempt_mip_drop: bool, // ui, small luts
+}
+
 #[derive(Clone, Debug, Copy)]
 pub enum ResourceData {
     ResourceDataMesh,
@@ -127,6 +204,70 @@ pub trait ProjectResourcesBase {
     fn get_material_instance_data(&self, resource_name: &str) -> &RcRefCell<MaterialInstanceData>;
 }
 
+// the failed steps of initialize_resources, the engine runs in the degraded mode with the error screen when it is not ok.
+#[derive(Clone, Debug, Default)]
+pub struct ResourceInitializeResult {
+    pub _errors: Vec<String>,
+}
+
+impl ResourceInitializeResult {
+    pub fn is_ok(&self) -> bool {
+        self._errors.is_empty()
+    }
+
+    pub fn add_error(&mut self, error_message: String) {
+        log::error!("initialize_resources: {}", error_message);
+        self._errors.push(error_message);
+    }
+
+    // a panic of the step is caught and recorded, the following steps can still run.
+    pub fn run_step<F: FnOnce()>(&mut self, step_name: &str, step: F) -> bool {
+        match panic::catch_unwind(AssertUnwindSafe(step)) {
+            Ok(_) => true,
+            Err(panic_payload) => {
+                self.add_error(format!("{}: {}", step_name, renderer::get_panic_message(&panic_payload)));
+                false
+            }
+        }
+    }
+}
+
+// the engine layer can be an android asset, it is assumed to have the file.
+fn read_required_resource_file(virtual_file_system: &VirtualFileSystem, file_path: &Path) -> Result<Value, String> {
+    let is_exists = match virtual_file_system.find_mount_point(file_path) {
+        Some(mount_point) => (cfg!(target_os = "android") && mount_point.is_engine_layer()) || mount_point.get_real_path(file_path).is_file(),
+        None => false,
+    };
+    if false == is_exists {
+        return Err(format!("file is missing: {:?}", file_path));
+    }
+    serde_json::from_reader::<_, Value>(virtual_file_system.read_bytes(file_path)).map_err(|e| format!("{:?} {}", file_path, e))
+}
+
+// the files of the required material instances and their materials, checked before the gpu resources are loaded.
+pub fn check_required_material_instance_files(virtual_file_system: &VirtualFileSystem, initialize_result: &mut ResourceInitializeResult) {
+    for material_instance_name in REQUIRED_MATERIAL_INSTANCE_NAMES.iter() {
+        let material_instance_file = get_resource_file_path(&PathBuf::from(MATERIAL_INSTANCE_FILE_PATH), &String::from(*material_instance_name), EXT_MATERIAL_INSTANCE);
+        let material_data_name = match read_required_resource_file(virtual_file_system, &material_instance_file) {
+            Ok(contents) => match contents.get("material_name") {
+                Some(Value::String(material_data_name)) => material_data_name.clone(),
+                _ => {
+                    initialize_result.add_error(format!("required material instance {}: no material_name", material_instance_name));
+                    continue;
+                }
+            },
+            Err(e) => {
+                initialize_result.add_error(format!("required material instance {}: {}", material_instance_name, e));
+                continue;
+            }
+        };
+        let material_file = get_resource_file_path(&PathBuf::from(MATERIAL_FILE_PATH), &material_data_name, EXT_MATERIAL);
+        if let Err(e) = read_required_resource_file(virtual_file_system, &material_file) {
+            initialize_result.add_error(format!("required material instance {}: material {}: {}", material_instance_name, material_data_name, e));
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct Resources {
     pub _project_resources: *const dyn ProjectResourcesBase,
@@ -134,25 +275,41 @@ pub struct Resources {
     pub _meta_data_map: MetaDataMap,
     pub _font_data_map: FontDataMap,
     pub _mesh_data_map: MeshDataMap,
+    pub _mesh_source_file_map: HashMap<ResourceName, PathBuf>, // the meshes of load_mesh_datas, also the import settings of the lods
     pub _model_data_map: ModelDataMap,
     pub _texture_data_map: TextureDataMap,
+    pub _texture_source_file_map: HashMap<ResourceName, TextureSourceFile>,
+    pub _bindless_texture_array: Option<BindlessTextureArray>, // None: the device has no descriptor indexing, see update_bindless_textures
+    pub _ies_profile_names: Vec<String>, // in the order of the layers of IES_PROFILES_TEXTURE_NAME
+    pub _anim_state_machine_data_map: AnimStateMachineDataMap,
+    pub _impostor_bake_requests: Vec<String>, // model names, baked by RendererData::bake_impostors before the next frame
     pub _framebuffer_datas_map: FramebufferDatasMap,
     pub _render_pass_data_map: RenderPassDataMap,
+    pub _merged_render_pass_names: HashMap<String, String>, // the second render pass -> the merged render pass, see render_pass::merge_render_pass_data_create_infos
+    pub _pipeline_variant_data_map: PipelineDataMap, // render_pass/pipeline[/hook_set_key][/blend_mode]
     pub _material_data_map: MaterialDataMap,
     pub _material_instance_data_map: MaterialInstanceDataMap,
-    pub _descriptor_data_map: DescriptorDataMap
+    pub _pending_descriptor_update_material_instances: Vec<String>, // see apply_pending_descriptor_updates
+    pub _descriptor_data_map: DescriptorDataMap,
+    pub _file_watcher: Option<RcRefCell<FileWatcher>>,
+    pub _hot_reload_state: HotReloadState,
+    pub _definition_contents_map: HashMap<PathBuf, Value>, // the last valid contents of the material files, used when the reloaded file is malformed
+    pub _virtual_file_system: RcRefCell<VirtualFileSystem>,
+    pub _load_errors: Vec<String>, // the skipped resources of the initial load, ex) a material instance of a missing material
+    pub _is_project_resources_initialized: bool,
+    pub _resource_ref_counter: ResourceRefCounter, // the contents and the resources registered after the initial load are collected, see garbage_collect
 }
 
 pub fn get_resource_data_must<'a, T>(resource_data_map: &'a ResourceDataMap<T>, resource_name: &str) -> &'a RcRefCell<T> {
-    resource_data_map.get(resource_name).unwrap()
+    resource_data_map.get(&ResourceName::from(resource_name)).unwrap()
 }
 
 pub fn get_resource_data<'a, T>(resource_data_map: &'a ResourceDataMap<T>, resource_name: &str, default_resource_name: &str) -> &'a RcRefCell<T> {
-    let maybe_data = resource_data_map.get(resource_name);
+    let maybe_data = resource_data_map.get(&ResourceName::from(resource_name));
     match maybe_data {
         None => {
             log::error!("not found texture: {}", resource_name);
-            resource_data_map.get(default_resource_name).unwrap()
+            resource_data_map.get(&ResourceName::from(default_resource_name)).unwrap()
         },
         _ => maybe_data.unwrap(),
     }
@@ -165,9 +322,16 @@ pub fn get_resource_name_from_file_path(resource_root_path: &PathBuf, resource_f
     resource_name.replace("\\", "/")
 }
 
+// the original case is kept for the generated file names, the uniqueness is of the normalized name.
 pub fn get_unique_resource_name<T>(resource_map: &ResourceDataMap<T>, resource_root_path: &PathBuf, resource_file_path: &PathBuf) -> String {
     let resource_name = get_resource_name_from_file_path(resource_root_path, resource_file_path);
-    system::generate_unique_name(resource_map, &resource_name)
+    let mut unique_resource_name = resource_name.clone();
+    let mut index: u32 = 0;
+    while resource_map.contains_key(&ResourceName::from(&unique_resource_name)) {
+        unique_resource_name = format!("{}_{}", resource_name, index);
+        index += 1;
+    }
+    unique_resource_name
 }
 
 pub fn get_resource_file_path(resource_root_path: &PathBuf, resource_name: &String, resource_ext: &str) -> PathBuf {
@@ -185,13 +349,29 @@ impl Resources {
             _meta_data_map: MetaDataMap::new(),
             _font_data_map: FontDataMap::new(),
             _mesh_data_map: MeshDataMap::new(),
+            _mesh_source_file_map: HashMap::new(),
             _model_data_map: ModelDataMap::new(),
             _texture_data_map: TextureDataMap::new(),
+            _texture_source_file_map: HashMap::new(),
+            _bindless_texture_array: None,
+            _ies_profile_names: Vec::new(),
+            _anim_state_machine_data_map: AnimStateMachineDataMap::new(),
+            _impostor_bake_requests: Vec::new(),
             _framebuffer_datas_map: FramebufferDatasMap::new(),
             _render_pass_data_map: RenderPassDataMap::new(),
+            _merged_render_pass_names: HashMap::new(),
+            _pipeline_variant_data_map: PipelineDataMap::new(),
             _material_data_map: MaterialDataMap::new(),
             _material_instance_data_map: MaterialInstanceDataMap::new(),
-            _descriptor_data_map: DescriptorDataMap::new()
+            _pending_descriptor_update_material_instances: Vec::new(),
+            _descriptor_data_map: DescriptorDataMap::new(),
+            _file_watcher: None,
+            _hot_reload_state: HotReloadState::default(),
+            _definition_contents_map: HashMap::new(),
+            _virtual_file_system: newRcRefCell(VirtualFileSystem::create_virtual_file_system()),
+            _load_errors: Vec::new(),
+            _is_project_resources_initialized: false,
+            _resource_ref_counter: ResourceRefCounter::default(),
         }
     }
 
@@ -203,25 +383,93 @@ impl Resources {
         unsafe { &mut *(self._project_resources as *mut dyn ProjectResourcesBase) }
     }
 
-    pub fn initialize_resources(&mut self, renderer_data: &mut RendererData) {
+    // the core resources are loaded first, the contents(meshes, models, project resources) only when they are all valid.
+    pub fn initialize_resources(&mut self, renderer_data: &mut RendererData) -> ResourceInitializeResult {
         log::info!("initialize_resources");
         let is_reload: bool = false;
+        let mut initialize_result = ResourceInitializeResult::default();
+        self._load_errors.clear();
+        self._is_project_resources_initialized = false;
+        // the core resources of the initial load are the resident baseline, the contents are reloadable
+        self._resource_ref_counter.set_resident_loading(true);
+        initialize_result.run_step("virtual file system", || self.initialize_virtual_file_system());
+        check_required_material_instance_files(&self._virtual_file_system.borrow(), &mut initialize_result);
+        let is_required_files_valid = initialize_result.is_ok();
+        progress::report_progress(ProgressTask::ResourceLoad, 0.0);
+        initialize_result.run_step("textures", || {
+            renderer_data.begin_texture_uploads();
+            self.load_texture_datas(renderer_data);
+            renderer_data.end_texture_uploads();
+        });
+        progress::report_progress(ProgressTask::ResourceLoad, 0.4);
+        initialize_result.run_step("fonts", || self.load_font_datas(renderer_data));
+        // before the render passes, the opaque pipelines use the layout of the bindless texture array
+        initialize_result.run_step("bindless textures", || self.update_bindless_textures(renderer_data));
+        initialize_result.run_step("render passes", || self.load_render_pass_datas(renderer_data));
+        initialize_result.run_step("framebuffers", || self.load_framebuffer_datas(renderer_data));
+        progress::report_progress(ProgressTask::ResourceLoad, 0.5);
+        initialize_result.run_step("materials", || self.load_material_datas(renderer_data));
+        initialize_result.run_step("material instances", || self.load_material_instance_datas(renderer_data, is_reload));
+        for load_error in self._load_errors.drain(..) {
+            initialize_result.add_error(load_error);
+        }
+        for material_instance_name in REQUIRED_MATERIAL_INSTANCE_NAMES.iter() {
+            if is_required_files_valid && false == self.has_material_instance_data(material_instance_name) {
+                initialize_result.add_error(format!("required material instance is not loaded: {}", material_instance_name));
+            }
+        }
+        progress::report_progress(ProgressTask::ResourceLoad, 0.6);
+        if initialize_result.is_ok() {
+            initialize_result.run_step("meshes", || {
+                renderer_data.begin_staging_uploads();
+                self.load_mesh_datas(renderer_data);
+                renderer_data.end_staging_uploads();
+            });
+            progress::report_progress(ProgressTask::ResourceLoad, 0.8);
+            initialize_result.run_step("models", || self.load_model_datas(renderer_data));
+            initialize_result.run_step("impostors", || self.load_impostor_datas(renderer_data));
+            initialize_result.run_step("animation state machines", || self.load_anim_state_machine_datas(renderer_data));
+            progress::report_progress(ProgressTask::ResourceLoad, 0.9);
+            initialize_result.run_step("render extensions", || {
+                for render_extension in renderer_data.get_render_extensions() {
+                    render_extension.on_resources_loaded(self, renderer_data);
+                }
+            });
+            if initialize_result.is_ok() {
+                let is_project_resources_initialized = initialize_result.run_step("project resources", || {
+                    self.get_project_resources_mut().initialize_project_resources(self, renderer_data);
+                });
+                self._is_project_resources_initialized = is_project_resources_initialized;
+            }
+        }
+        self._resource_ref_counter.set_resident_loading(false);
+        // the file watcher is kept in the degraded mode, the fixed contents are picked up by the reload.
+        self.initialize_file_watcher();
+        progress::end_progress(ProgressTask::ResourceLoad);
+        initialize_result
+    }
+
+    // the font pass of FontManager, also used by the error screen of the degraded mode.
+    pub fn is_font_resources_loaded(&self) -> bool {
+        self.has_font_data(DEFAULT_FONT_NAME) && self.has_material_instance_data("system/render_font") && self.has_framebuffer_data("render_font")
+    }
+
+    // the file lists of all layers without the gpu resources, ex) asset_report::run_asset_report_standalone
+    pub fn initialize_virtual_file_system(&mut self) {
+        self._virtual_file_system.borrow_mut().load_settings(&PathBuf::from(virtual_file_system::VFS_SETTINGS_FILE_PATH));
         self.load_resource_filenames();
-        self.load_texture_datas(renderer_data);
-        self.load_font_datas(renderer_data);
-        self.load_render_pass_datas(renderer_data);
-        self.load_framebuffer_datas(renderer_data);
-        self.load_material_datas(renderer_data);
-        self.load_material_instance_datas(renderer_data, is_reload);
-        self.load_mesh_datas(renderer_data);
-        self.load_model_datas(renderer_data);
-        self.get_project_resources_mut().initialize_project_resources(self, renderer_data);
     }
 
     pub fn destroy_resources(&mut self, renderer_data: &mut RendererData) {
         log::info!("destroy_resources");
         let is_reload: bool = false;
-        self.get_project_resources_mut().destroy_project_resources(renderer_data);
+        self.destroy_file_watcher();
+        if self._is_project_resources_initialized {
+            self.get_project_resources_mut().destroy_project_resources(renderer_data);
+            self._is_project_resources_initialized = false;
+        }
+        self.unload_anim_state_machine_datas(renderer_data);
+        self.unload_impostor_datas(renderer_data);
         self.unload_model_datas(renderer_data);
         self.unload_mesh_datas(renderer_data);
         self.unload_material_instance_datas(renderer_data, is_reload);
@@ -231,6 +479,288 @@ impl Resources {
         self.unload_font_datas(renderer_data);
         self.unload_texture_datas(renderer_data);
         self.unload_descriptor_datas(renderer_data);
+        self._resource_ref_counter = ResourceRefCounter::default();
+    }
+
+    // ResourceRefCounter: the render objects acquire their meshes and material instances, see SceneManagerData::add_render_object
+    // the material instances acquire their textures. an unloaded resource is loaded again in place, the holders of it remain valid.
+    pub fn acquire_mesh_data(&mut self, renderer_data: &RendererData, resource_name: &str) -> RcRefCell<MeshData> {
+        if self._resource_ref_counter.is_resource_unloaded(ResourceRefType::Mesh, resource_name) {
+            self.reload_mesh_data(renderer_data, resource_name);
+        }
+        self._resource_ref_counter.acquire_resource(ResourceRefType::Mesh, resource_name);
+        self.get_mesh_data(resource_name).clone()
+    }
+
+    pub fn release_mesh_data(&mut self, resource_name: &str) {
+        self._resource_ref_counter.release_resource(ResourceRefType::Mesh, resource_name);
+    }
+
+    pub fn acquire_texture_data(&mut self, renderer_data: &RendererData, resource_name: &str) -> RcRefCell<TextureData> {
+        if self._resource_ref_counter.is_resource_unloaded(ResourceRefType::Texture, resource_name) {
+            self.reload_texture_data(renderer_data, resource_name);
+        }
+        self._resource_ref_counter.acquire_resource(ResourceRefType::Texture, resource_name);
+        self.get_texture_data(resource_name).clone()
+    }
+
+    pub fn release_texture_data(&mut self, resource_name: &str) {
+        self._resource_ref_counter.release_resource(ResourceRefType::Texture, resource_name);
+    }
+
+    pub fn acquire_material_instance_data(&mut self, renderer_data: &RendererData, resource_name: &str) -> RcRefCell<MaterialInstanceData> {
+        if self._resource_ref_counter.is_resource_unloaded(ResourceRefType::MaterialInstance, resource_name) {
+            self.reload_material_instance_data(renderer_data, resource_name);
+        }
+        self._resource_ref_counter.acquire_resource(ResourceRefType::MaterialInstance, resource_name);
+        self.get_material_instance_data(resource_name).clone()
+    }
+
+    pub fn release_material_instance_data(&mut self, resource_name: &str) {
+        self._resource_ref_counter.release_resource(ResourceRefType::MaterialInstance, resource_name);
+    }
+
+    // the render pass framebuffers are recreated with the swapchain, the holders acquire them again after the resize. ex) FontManager
+    pub fn acquire_framebuffer_data(&mut self, resource_name: &str) -> RcRefCell<FramebufferData> {
+        self._resource_ref_counter.acquire_resource(ResourceRefType::Framebuffer, resource_name);
+        self.get_framebuffer_data(resource_name).clone()
+    }
+
+    pub fn release_framebuffer_data(&mut self, resource_name: &str) {
+        self._resource_ref_counter.release_resource(ResourceRefType::Framebuffer, resource_name);
+    }
+
+    // once per frame, the unreferenced resources are destroyed after the frames in flight, see resource_ref_count::GARBAGE_COLLECT_DELAY_FRAMES
+    // the contents of the resource directories are unloaded in place, the others are removed from the maps.
+    pub fn garbage_collect(&mut self, renderer_data: &RendererData) {
+        let garbage_resources = self._resource_ref_counter.update_resource_ref_counter();
+        let mut is_texture_removed: bool = false;
+        for (ref_type, resource_name) in garbage_resources.iter() {
+            let resource_key = ResourceName::from(resource_name);
+            let is_unloaded = self._resource_ref_counter.is_resource_unloaded(*ref_type, resource_name);
+            match ref_type {
+                ResourceRefType::Mesh => if is_unloaded {
+                    self.unload_mesh_data(renderer_data, resource_name);
+                } else if let Some(mesh_data) = self._mesh_data_map.remove(&resource_key) {
+                    for geometry_data in mesh_data.borrow().get_geomtry_datas() {
+                        renderer_data.destroy_geomtry_buffer(&geometry_data.borrow());
+                    }
+                },
+                ResourceRefType::Texture => {
+                    if is_unloaded {
+                        let texture_data = self.get_texture_data(resource_name).clone();
+                        renderer_data.destroy_texture(&texture_data.borrow());
+                        *texture_data.borrow_mut() = TextureData {
+                            _texture_data_name: resource_name.clone(),
+                            ..Default::default()
+                        };
+                    } else if let Some(texture_data) = self._texture_data_map.remove(&resource_key) {
+                        renderer_data.destroy_texture(&texture_data.borrow());
+                    }
+                    is_texture_removed = true;
+                },
+                ResourceRefType::MaterialInstance => {
+                    let material_instance_data = if is_unloaded {
+                        self._material_instance_data_map.get(&resource_key).cloned()
+                    } else {
+                        self._material_instance_data_map.remove(&resource_key)
+                    };
+                    if let Some(material_instance_data) = material_instance_data {
+                        material_instance_data.borrow_mut().destroy_material_instance(self);
+                    }
+                },
+                ResourceRefType::Framebuffer => if let Some(framebuffer_data) = self._framebuffer_datas_map.remove(&resource_key) {
+                    framebuffer::destroy_framebuffer_data(renderer_data.get_device(), &framebuffer_data.borrow());
+                },
+            }
+            log::info!("garbage_collect: {:?} {}{}", ref_type, resource_name, if is_unloaded { " (unloaded)" } else { "" });
+        }
+        if is_texture_removed {
+            self.update_bindless_textures(renderer_data);
+        }
+    }
+
+    pub fn update_resource_ref_stats(&self, font_manager: &mut FontManager) {
+        font_manager.log(self._resource_ref_counter.get_stats_text());
+    }
+
+    // FileWatcher
+    pub fn initialize_file_watcher(&mut self) {
+        if false == unsafe { constants::ENABLE_FILE_WATCHER } {
+            return;
+        }
+
+        let backend_type = if cfg!(target_os = "android") { FileWatcherBackendType::Polling } else { FileWatcherBackendType::Native };
+        let mut file_watcher = FileWatcher::create_file_watcher(backend_type, file_watcher::DEFAULT_POLLING_INTERVAL, file_watcher::DEFAULT_DEBOUNCE_TIME);
+        let watch_directories: [(&str, &[&str]); 7] = [
+            (shader::SHADER_DIRECTORY, &["*.glsl", "*.vert", "*.frag", "*.comp"]),
+            (MATERIAL_FILE_PATH, &["*.mat"]),
+            (MATERIAL_INSTANCE_FILE_PATH, &["*.matinst"]),
+            (TEXTURE_SOURCE_FILE_PATH, &[]),
+            (TEXTURE_FILE_PATH, &["*.texture"]),
+            (SCENE_FILE_PATH, &["*.scene"]),
+            (ANIMATION_STATE_MACHINE_FILE_PATH, &["*.asm"]),
+        ];
+        // watch all layers of the virtual file system
+        for mount_point in self._virtual_file_system.borrow().get_mount_points().iter() {
+            for (watch_directory, filters) in watch_directories.iter() {
+                let watch_directory = mount_point.get_real_path(&PathBuf::from(watch_directory));
+                if mount_point.is_engine_layer() || watch_directory.is_dir() {
+                    file_watcher.regist_watch_directory(&watch_directory, filters);
+                }
+            }
+        }
+        self._file_watcher = Some(newRcRefCell(file_watcher));
+    }
+
+    pub fn destroy_file_watcher(&mut self) {
+        if let Some(file_watcher) = self._file_watcher.take() {
+            file_watcher.borrow_mut().destroy_file_watcher();
+        }
+    }
+
+    // returns events of virtual paths
+    pub fn poll_file_events(&self) -> Vec<FileEvent> {
+        match self._file_watcher.as_ref() {
+            Some(file_watcher) => {
+                let virtual_file_system = self._virtual_file_system.borrow();
+                file_watcher.borrow_mut().poll_file_events().iter().map(|file_event| virtual_file_system.convert_file_event(file_event)).collect()
+            },
+            None => Vec::new(),
+        }
+    }
+
+    // called every frame, returns true when the materials have changed and the graphics datas need to be reloaded.
+    // the descriptor pools can not free the sets of a single material instance, so they are reloaded with all graphics datas.
+    pub fn update_hot_reload(&mut self, renderer_data: &RendererData) -> bool {
+        let file_events = self.poll_file_events();
+        self.reload_shader_datas(renderer_data, &file_events);
+        self.reload_anim_state_machine_datas(&file_events);
+        file_events.iter().any(|file_event| {
+            file_event._file_path.starts_with(MATERIAL_FILE_PATH) || file_event._file_path.starts_with(MATERIAL_INSTANCE_FILE_PATH)
+        })
+    }
+
+    // ex) the R key, every pipeline is recompiled even without the file watcher
+    pub fn request_reload_all_shaders(&mut self) {
+        log::info!("request_reload_all_shaders");
+        self._hot_reload_state.schedule_reload(&PathBuf::from(shader::SHADER_DIRECTORY));
+    }
+
+    // the shaders of the polled file events are recompiled, a failed pipeline keeps the previous one and retries later.
+    pub fn reload_shader_datas(&mut self, renderer_data: &RendererData, file_events: &[FileEvent]) {
+        let shader_directory = PathBuf::from(shader::SHADER_DIRECTORY);
+        for file_event in file_events.iter() {
+            if file_event._file_path.starts_with(&shader_directory) && FileEventType::Removed != file_event._event_type {
+                self._hot_reload_state.schedule_reload(&file_event._file_path);
+            }
+        }
+        for pending_reload in self._hot_reload_state.take_due_reloads() {
+            let shader_file = system::get_relative_path(&shader_directory, &pending_reload._file_path);
+            let shader_file = if shader_directory == pending_reload._file_path { None } else { Some(&shader_file) };
+            match self.reload_shader_pipeline_datas(renderer_data, shader_file) {
+                Ok(reloaded_count) => {
+                    log::info!("reload_shader_datas: {:?}, {} pipelines", pending_reload._file_path, reloaded_count);
+                    self._hot_reload_state.clear_reload_error(&pending_reload._file_path);
+                },
+                Err(e) => {
+                    let error_message = format!("{:?} {}", pending_reload._file_path, e);
+                    self._hot_reload_state.schedule_retry(pending_reload, &error_message);
+                }
+            }
+        }
+    }
+
+    // all or nothing, the pipelines are replaced in place only when every pipeline using the shader has been created.
+    // the descriptor datas are kept, so the descriptor sets of the material instances stay valid with the new pipelines.
+    // None: every pipeline, ex) the R key. the shader hook variants are recompiled on the next graphics reload.
+    fn reload_shader_pipeline_datas(&self, renderer_data: &RendererData, shader_file: Option<&PathBuf>) -> Result<u32, String> {
+        let device = renderer_data.get_device();
+        let is_include_file = match shader_file {
+            Some(shader_file) => Some("glsl") == shader_file.extension().and_then(|ext| ext.to_str()),
+            None => true,
+        };
+        let render_pass_data_create_infos = renderer_data.get_render_pass_data_create_infos();
+        let mut reload_targets: Vec<(RcRefCell<PipelineData>, (RcRefCell<RenderPassData>, &RenderPassDataCreateInfo, &PipelineDataCreateInfo))> = Vec::new();
+        for render_pass_data_create_info in render_pass_data_create_infos.iter() {
+            let render_pass_data = match self._render_pass_data_map.get(&ResourceName::from(&render_pass_data_create_info._render_pass_create_info_name)) {
+                Some(render_pass_data) => render_pass_data,
+                None => continue,
+            };
+            for pipeline_data_create_info in render_pass_data_create_info._pipeline_data_create_infos.iter() {
+                let use_shader_file = is_include_file || shader_file.map_or(false, |shader_file| {
+                    *shader_file == pipeline_data_create_info._pipeline_vertex_shader_file
+                        || *shader_file == pipeline_data_create_info._pipeline_fragment_shader_file
+                        || *shader_file == pipeline_data_create_info._pipeline_compute_shader_file
+                });
+                match render_pass_data.borrow()._pipeline_data_map.get(&pipeline_data_create_info._pipeline_data_create_info_name) {
+                    Some(pipeline_data) if use_shader_file => reload_targets.push((pipeline_data.clone(), (render_pass_data.clone(), render_pass_data_create_info, pipeline_data_create_info))),
+                    _ => continue,
+                }
+            }
+        }
+
+        hot_reload::replace_all_or_nothing(
+            &reload_targets,
+            |pipeline_data, (render_pass_data, render_pass_data_create_info, pipeline_data_create_info)| {
+                let render_pass_data = render_pass_data.borrow();
+                let new_pipeline_data = if vk::PipelineBindPoint::GRAPHICS == pipeline_data_create_info._pipeline_bind_point {
+                    render_pass::create_graphics_pipeline_data(
+                        device,
+                        render_pass_data._render_pass,
+                        pipeline_data_create_info,
+                        render_pass_data_create_info.get_subpass_data_create_infos()[pipeline_data_create_info._pipeline_subpass_index as usize]._depth_attachment_index.is_some(),
+                        render_pass_data_create_info._shading_rate_attachment_description.is_some(),
+                        &pipeline_data._descriptor_data,
+                        self.get_bindless_descriptor_set_layout()
+                    )?
+                } else {
+                    render_pass::create_compute_pipeline_data(device, pipeline_data_create_info, &pipeline_data._descriptor_data)?
+                };
+                render_pass::set_pipeline_data_object_name(&new_pipeline_data, &format!("{}/{}", render_pass_data._render_pass_data_name, new_pipeline_data._pipeline_data_name));
+                Ok(new_pipeline_data)
+            },
+            |pipeline_data| render_pass::destroy_pipeline_data(device, pipeline_data),
+            || renderer_data.device_wait_idle(),
+        )
+    }
+
+    pub fn update_reload_error_toasts(&mut self, font_manager: &mut FontManager, ui_manager_data: &mut UIManagerData) {
+        self._hot_reload_state.update_reload_error_toasts(font_manager, ui_manager_data);
+    }
+
+    // a malformed definition file is reported and the last valid contents of the file are used instead.
+    fn load_definition_contents(&mut self, file_path: &PathBuf, validate: fn(&Resources, &Value) -> Result<(), String>) -> Option<Value> {
+        let loaded_contents = self.read_bytes(file_path);
+        let result = match serde_json::from_reader::<_, Value>(loaded_contents) {
+            Ok(contents) => validate(self, &contents).map(|_| contents),
+            Err(e) => Err(format!("{}", e)),
+        };
+        match result {
+            Ok(contents) => {
+                self._definition_contents_map.insert(file_path.clone(), contents.clone());
+                Some(contents)
+            },
+            Err(e) => {
+                let prev_contents = self._definition_contents_map.get(file_path).cloned();
+                let fallback = if prev_contents.is_some() { "the previous contents are used" } else { "skipped" };
+                self._hot_reload_state.report_reload_error(&format!("{:?} {}, {}", file_path, e, fallback));
+                prev_contents
+            }
+        }
+    }
+
+    // VirtualFileSystem
+    pub fn read_bytes(&self, resource_file_path: &Path) -> Cursor<Vec<u8>> {
+        self._virtual_file_system.borrow().read_bytes(resource_file_path)
+    }
+
+    pub fn resolve_path(&self, resource_file_path: &Path) -> PathBuf {
+        self._virtual_file_system.borrow().resolve_path(resource_file_path)
+    }
+
+    pub fn dump_resource_manifest(&self) {
+        self._virtual_file_system.borrow().dump_manifest(&PathBuf::from(virtual_file_system::VFS_MANIFEST_FILE_PATH));
     }
 
     // GraphicsDatas
@@ -287,7 +817,13 @@ impl Resources {
     }
 
     pub fn collect_resources(&self, dir: &Path, extensions: &[&str]) -> Vec<PathBuf> {
-        return self.collect_resources_inner(dir, extensions);
+        let mut resources = self.collect_resources_inner(dir, extensions);
+        for override_file in self._virtual_file_system.borrow().collect_override_files(dir, extensions) {
+            if false == resources.contains(&override_file) {
+                resources.push(override_file);
+            }
+        }
+        return resources;
         // #[cfg(target_os = "android")]
         // return self.collect_resources_inner(dir, extensions);
         // #[cfg(not(target_os = "android"))]
@@ -316,7 +852,7 @@ impl Resources {
 
         let mut write_file = File::create(&font_file_path).expect("Failed to create file");
         let font_data_create_info = font_loader::get_font_data_create_info(
-            &font_source_file,
+            &self.resolve_path(font_source_file),
             font::FONT_SIZE as f32,
             font::FONT_PADDING as f32,
             &font_name,
@@ -337,6 +873,7 @@ impl Resources {
         let font_directory = PathBuf::from(FONT_FILE_PATH);
         let font_texture_directory = PathBuf::from(FONT_TEXTURE_FILE_PATH);
         let font_files = self.collect_resources(font_directory.as_path(), &[EXT_FONT]);
+        resource_name::check_resource_name_collisions("font", &font_directory, &font_files);
         let mut font_file_map: HashMap<String, PathBuf> = HashMap::new();
         for font_file in font_files.iter() {
             let font_name = get_resource_name_from_file_path(&font_directory, &font_file);
@@ -352,7 +889,7 @@ impl Resources {
                 let font_texture_name = format!("fonts/{}_{}", font_name, unicode_block_key);
                 let font_data_create_info = match font_file_map.get(&font_data_name) {
                     Some(font_file) => {
-                        let loaded_contents = system::load(font_file);
+                        let loaded_contents = self.read_bytes(font_file);
                         let font_data_create_info: FontDataCreateInfo = serde_json::from_reader(loaded_contents).expect("Failed to deserialize.");
                         #[cfg(target_os = "android")]
                         let check_font_texture_file_exists = false;
@@ -371,7 +908,7 @@ impl Resources {
 
                 if false == self.has_texture_data(&font_texture_name) {
                     // regist font texture
-                    let (image_width, image_height, image_layers, image_data, image_format): LoadImageInfoType = Resources::load_image_data(&font_data_create_info._texture_file_path);
+                    let (image_width, image_height, image_layers, image_data, image_format): LoadImageInfoType = self.load_image_data(&font_data_create_info._texture_file_path);
                     assert_ne!(vk::Format::UNDEFINED, image_format);
                     let texture_create_info = TextureCreateInfo {
                         _texture_name: font_texture_name.clone(),
@@ -403,7 +940,7 @@ impl Resources {
                     ),
                     _texture: texture_data.clone(),
                 });
-                self._font_data_map.insert(font_data_name, font_data);
+                self._font_data_map.insert(ResourceName::from(font_data_name), font_data);
             }
         }
     }
@@ -413,7 +950,7 @@ impl Resources {
     }
 
     pub fn has_font_data(&self, resource_name: &str) -> bool {
-        self._font_data_map.contains_key(resource_name)
+        self._font_data_map.contains_key(&ResourceName::from(resource_name))
     }
 
     pub fn get_default_font_data(&self) -> &RcRefCell<FontData> {
@@ -428,9 +965,10 @@ impl Resources {
     pub fn load_model_datas(&mut self, _renderer_data: &RendererData) {
         let model_directory = PathBuf::from(MODEL_FILE_PATH);
         let model_files: Vec<PathBuf> = self.collect_resources(&model_directory, &[EXT_MODEL]);
+        resource_name::check_resource_name_collisions("model", &model_directory, &model_files);
         for model_file in model_files {
             let model_name = get_unique_resource_name(&self._model_data_map, &model_directory, &model_file);
-            let loaded_contents = system::load(&model_file);
+            let loaded_contents = self.read_bytes(&model_file);
             let contents = serde_json::from_reader(loaded_contents).expect("Failed to deserialize.");
             let model_create_info = match contents {
                 Value::Object(model_create_info) => model_create_info,
@@ -459,8 +997,14 @@ impl Resources {
                     self.get_material_instance_data(DEFAULT_MATERIAL_INSTANCE_NAME).clone()
                 }
             }).collect();
-            let model_data = ModelData::new_model_data(&model_name, mesh_data.clone(), material_instance_datas);
-            self._model_data_map.insert(model_name.clone(), newRcRefCell(model_data));
+            let mut model_data = ModelData::new_model_data(&model_name, mesh_data.clone(), material_instance_datas);
+            if let Some(impostor_create_info) = model_create_info.get("impostor") {
+                match serde_json::from_value::<ImpostorCreateInfo>(impostor_create_info.clone()) {
+                    Ok(impostor_create_info) => model_data._impostor_create_info = Some(impostor_create_info),
+                    Err(e) => log::error!("load_model_datas: impostor parsing error {} {}", model_name, e),
+                }
+            }
+            self._model_data_map.insert(ResourceName::from(model_name.clone()), newRcRefCell(model_data));
         }
     }
 
@@ -472,29 +1016,111 @@ impl Resources {
     }
 
     pub fn has_model_data(&self, resource_name: &str) -> bool {
-        self._model_data_map.contains_key(resource_name)
+        self._model_data_map.contains_key(&ResourceName::from(resource_name))
     }
 
     pub fn get_model_data(&self, resource_name: &str) -> &RcRefCell<ModelData> {
         get_resource_data(&self._model_data_map, resource_name, DEFAULT_MODEL_NAME)
     }
 
+    // ImpostorData
+    pub fn get_impostor_content_hash(&self, model_data: &ModelData) -> u64 {
+        let material_instance_names: Vec<String> = model_data._material_instance_datas.iter().map(|material_instance_data| {
+            material_instance_data.borrow()._material_instance_data_name.clone()
+        }).collect();
+        impostor_cache::compute_model_content_hash(
+            model_data._mesh_data.borrow()._content_hash,
+            &material_instance_names,
+            model_data._impostor_create_info.as_ref().unwrap()
+        )
+    }
+
+    // the impostors of the models are loaded from the cache, the missing or outdated ones are requested to bake.
+    pub fn load_impostor_datas(&mut self, renderer_data: &RendererData) {
+        let mut model_names: Vec<String> = self._model_data_map.iter().filter(|(_, model_data)| {
+            model_data.borrow()._impostor_create_info.is_some()
+        }).map(|(model_name, _)| String::from(model_name.as_str())).collect();
+        model_names.sort();
+        for model_name in model_names {
+            let model_data = self._model_data_map.get(&ResourceName::from(&model_name)).unwrap().clone();
+            let content_hash = self.get_impostor_content_hash(&model_data.borrow());
+            let cache_file_path = impostor_cache::get_impostor_cache_file_path(&model_name);
+            match impostor_cache::load_impostor_cache(&cache_file_path, content_hash) {
+                Some(bake_data) => self.regist_impostor_data(renderer_data, &model_name, bake_data),
+                None => self.request_impostor_bake(&model_name),
+            }
+        }
+    }
+
+    pub fn unload_impostor_datas(&mut self, renderer_data: &RendererData) {
+        for model_data in self._model_data_map.values() {
+            if let Some(impostor_data) = model_data.borrow_mut()._impostor_data.take() {
+                impostor_data.borrow().destroy_impostor_data(renderer_data);
+            }
+        }
+        self._impostor_bake_requests.clear();
+    }
+
+    pub fn regist_impostor_data(&self, renderer_data: &RendererData, model_name: &str, bake_data: ImpostorBakeData) {
+        let model_data = self.get_model_data(model_name).clone();
+        let impostor_data = ImpostorData::create_impostor_data(renderer_data, model_name, bake_data);
+        let prev_impostor_data = model_data.borrow_mut()._impostor_data.replace(newRcRefCell(impostor_data));
+        if let Some(prev_impostor_data) = prev_impostor_data {
+            prev_impostor_data.borrow().destroy_impostor_data(renderer_data);
+        }
+    }
+
+    // the bake command of the tools, the models keep the current impostors until the new ones are baked.
+    pub fn request_impostor_bake(&mut self, model_name: &str) {
+        let has_impostor = match self._model_data_map.get(&ResourceName::from(model_name)) {
+            Some(model_data) => model_data.borrow()._impostor_create_info.is_some(),
+            None => false,
+        };
+        if false == has_impostor {
+            log::warn!("request_impostor_bake: {} has no impostor", model_name);
+        } else if false == self._impostor_bake_requests.iter().any(|request| model_name == request) {
+            self._impostor_bake_requests.push(String::from(model_name));
+        }
+    }
+
+    pub fn request_all_impostor_bakes(&mut self) {
+        let mut model_names: Vec<String> = self._model_data_map.iter().filter(|(_, model_data)| {
+            model_data.borrow()._impostor_create_info.is_some()
+        }).map(|(model_name, _)| String::from(model_name.as_str())).collect();
+        model_names.sort();
+        for model_name in model_names {
+            self.request_impostor_bake(&model_name);
+        }
+    }
+
+    pub fn has_impostor_bake_requests(&self) -> bool {
+        false == self._impostor_bake_requests.is_empty()
+    }
+
+    pub fn take_impostor_bake_requests(&mut self) -> Vec<String> {
+        std::mem::replace(&mut self._impostor_bake_requests, Vec::new())
+    }
+
     // Mesh Loader
-    pub fn regist_mesh_data(
-        &mut self,
-        renderer_data: &RendererData,
-        mesh_name: &String,
-        mesh_data_create_info: MeshDataCreateInfo,
-    ) {
+    pub fn create_mesh_data(&self, renderer_data: &RendererData, mesh_name: &String, mesh_data_create_info: MeshDataCreateInfo) -> MeshData {
         let mut geometry_datas: Vec<RcRefCell<GeometryData>> = Vec::new();
         for (i, geometry_create_info) in mesh_data_create_info._geometry_create_infos.iter().enumerate() {
             let geomtery_name: String = format!("{}_{}", mesh_name, i);
             let geometry_data = renderer_data.create_geometry_buffer(&geomtery_name, geometry_create_info);
             geometry_datas.push(newRcRefCell(geometry_data));
         }
+        MeshData::create_mesh_data(&mesh_name, mesh_data_create_info, geometry_datas)
+    }
 
-        let mesh_data = newRcRefCell(MeshData::create_mesh_data(&mesh_name, mesh_data_create_info, geometry_datas));
-        self._mesh_data_map.insert(mesh_name.clone(), mesh_data.clone());
+    pub fn regist_mesh_data(
+        &mut self,
+        renderer_data: &RendererData,
+        mesh_name: &String,
+        mesh_data_create_info: MeshDataCreateInfo,
+    ) {
+        let mesh_data = newRcRefCell(self.create_mesh_data(renderer_data, mesh_name, mesh_data_create_info));
+        self._mesh_data_map.insert(ResourceName::from(mesh_name.clone()), mesh_data.clone());
+        self._resource_ref_counter.register_resource(ResourceRefType::Mesh, mesh_name);
     }
 
     pub fn load_mesh_datas(&mut self, renderer_data: &RendererData) {
@@ -504,54 +1130,204 @@ impl Resources {
         let mesh_source_directory = PathBuf::from(MESH_SOURCE_FILE_PATH);
         let resource_ext = if USE_JSON_FOR_MESH { EXT_JSON } else { EXT_MESH };
         let mesh_files = self.collect_resources(mesh_directory.as_path(), &[resource_ext]);
+        resource_name::check_resource_name_collisions("mesh", &mesh_directory, &mesh_files);
         let mut mesh_file_map: HashMap<String, PathBuf> = HashMap::new();
         for mesh_file in mesh_files.iter() {
             let mesh_name = get_resource_name_from_file_path(&mesh_directory, &mesh_file);
             mesh_file_map.insert(mesh_name, mesh_file.clone());
         }
         let mesh_source_files = self.collect_resources(mesh_source_directory.as_path(), &MESH_SOURCE_EXTS);
+        self._mesh_source_file_map.clear();
         for mesh_source_file in mesh_source_files {
             let mesh_name = get_unique_resource_name(&self._mesh_data_map, &mesh_source_directory, &mesh_source_file);
-            let src_file_ext: String = String::from(mesh_source_file.extension().unwrap().to_str().unwrap());
-            let mesh_data_create_info = match (LOAD_FROM_EXTERNAL_FOR_MESH, mesh_file_map.get(&mesh_name)) {
-                (false, Some(mesh_file)) => {
-                    // Load mesh
-                    let loaded_contents = system::load(mesh_file);
+            self._mesh_source_file_map.insert(ResourceName::from(&mesh_name), mesh_source_file.clone());
+            let mesh_data_create_info = self.load_mesh_data_create_info(&mesh_name, &mesh_source_file, mesh_file_map.get(&mesh_name));
+            let mesh_data = newRcRefCell(self.create_mesh_data(renderer_data, &mesh_name, mesh_data_create_info));
+            self._mesh_data_map.insert(ResourceName::from(&mesh_name), mesh_data);
+            self._resource_ref_counter.register_reloadable_resource(ResourceRefType::Mesh, &mesh_name);
+        }
+        let mut base_mesh_names: Vec<String> = self._mesh_data_map.keys().filter_map(|mesh_name| {
+            mesh::get_mesh_lod_base_name(mesh_name.as_str()).map(|(base_mesh_name, _)| base_mesh_name)
+        }).collect();
+        base_mesh_names.sort();
+        base_mesh_names.dedup();
+        for base_mesh_name in base_mesh_names.iter() {
+            self.link_mesh_lod_datas(base_mesh_name);
+        }
+    }
+
+    // the mesh file of the previous import, or the mesh source through the mesh cache
+    pub fn load_mesh_data_create_info(&self, mesh_name: &String, mesh_source_file: &PathBuf, mesh_file: Option<&PathBuf>) -> MeshDataCreateInfo {
+        let mesh_directory = PathBuf::from(MESH_FILE_PATH);
+        let resource_ext = if USE_JSON_FOR_MESH { EXT_JSON } else { EXT_MESH };
+        let src_file_ext: String = String::from(mesh_source_file.extension().unwrap().to_str().unwrap());
+        match (LOAD_FROM_EXTERNAL_FOR_MESH, mesh_file) {
+            (false, Some(mesh_file)) => {
+                // Load mesh
+                let loaded_contents = self.read_bytes(mesh_file);
+                if USE_JSON_FOR_MESH {
+                    serde_json::from_reader(loaded_contents).expect("Failed to deserialize.")
+                } else {
+                    bincode::deserialize_from(loaded_contents).unwrap()
+                }
+            },
+            _ => {
+                // Convert to mesh from source, or load the imported result from the mesh cache.
+                let load_start_time = time::Instant::now();
+                let mesh_import_settings = self.load_mesh_import_settings(&mesh_source_file);
+                let source_contents = self.read_bytes(&mesh_source_file).into_inner();
+                let source_hash = mesh_cache::compute_hash(
+                    mesh_cache::compute_hash(mesh_cache::HASH_OFFSET_BASIS, &source_contents),
+                    serde_json::to_string(&mesh_import_settings).unwrap().as_bytes()
+                );
+                let mesh_cache_file_path = mesh_cache::get_mesh_cache_file_path(&mesh_source_file);
+                let mesh_data_create_info = match mesh_cache::load_mesh_cache(&mesh_cache_file_path, source_hash) {
+                    Some(mesh_data_create_info) => {
+                        log::info!("    load mesh cache {:?}: {:.3}ms", mesh_source_file, load_start_time.elapsed().as_secs_f64() * 1000.0);
+                        mesh_data_create_info
+                    },
+                    None => {
+                        let mut mesh_data_create_info = match src_file_ext.as_str() {
+                            EXT_OBJ => WaveFrontOBJ::get_mesh_data_create_infos(&self.resolve_path(&mesh_source_file), &mesh_import_settings),
+                            EXT_COLLADA => Collada::get_mesh_data_create_infos(&self.resolve_path(&mesh_source_file)),
+                            EXT_GLTF | EXT_GLB => GLTF::get_mesh_data_create_infos(&self.resolve_path(&mesh_source_file), &mesh_import_settings),
+                            _ => panic!("error")
+                        };
+                        if mesh_import_settings._enable_vertex_ambient_occlusion {
+                            let bake_start_time = time::Instant::now();
+                            mesh_ambient_occlusion::bake_vertex_ambient_occlusion(&mut mesh_data_create_info, &mesh_import_settings);
+                            log::info!("    bake vertex ambient occlusion {:?}: {:.3}ms", mesh_source_file, bake_start_time.elapsed().as_secs_f64() * 1000.0);
+                        }
+                        mesh_cache::save_mesh_cache(&mesh_cache_file_path, source_hash, &mesh_data_create_info);
+                        log::info!("    import mesh {:?}: {:.3}ms", mesh_source_file, load_start_time.elapsed().as_secs_f64() * 1000.0);
+                        mesh_data_create_info
+                    }
+                };
+
+                // Save mesh
+                if false == LOAD_FROM_EXTERNAL_FOR_MESH {
+                    let mut mesh_file_path: PathBuf = mesh_directory.clone();
+                    mesh_file_path.push(&mesh_name);
+                    mesh_file_path.set_extension(resource_ext);
+                    fs::create_dir_all(mesh_file_path.parent().unwrap()).expect("Failed to create directories.");
+                    let mut write_file = File::create(mesh_file_path).expect("Failed to create file");
                     if USE_JSON_FOR_MESH {
-                        serde_json::from_reader(loaded_contents).expect("Failed to deserialize.")
+                        let write_contents: String = serde_json::to_string(&mesh_data_create_info).expect("Failed to serialize.");
+                        write_file.write_all(write_contents.as_bytes()).expect("Failed to write");
                     } else {
-                        bincode::deserialize_from(loaded_contents).unwrap()
+                        let write_contents: Vec<u8> = bincode::serialize(&mesh_data_create_info).unwrap();
+                        write_file.write_all(&write_contents).expect("Failed to write");
                     }
-                },
-                _ => {
-                    // Convert to mesh from source
-                    let mesh_data_create_info = match src_file_ext.as_str() {
-                        EXT_OBJ => WaveFrontOBJ::get_mesh_data_create_infos(&mesh_source_file),
-                        EXT_COLLADA => Collada::get_mesh_data_create_infos(&mesh_source_file),
-                        _ => panic!("error")
-                    };
+                }
 
-                    // Save mesh
-                    if false == LOAD_FROM_EXTERNAL_FOR_MESH {
-                        let mut mesh_file_path: PathBuf = mesh_directory.clone();
-                        mesh_file_path.push(&mesh_name);
-                        mesh_file_path.set_extension(resource_ext);
-                        fs::create_dir_all(mesh_file_path.parent().unwrap()).expect("Failed to create directories.");
-                        let mut write_file = File::create(mesh_file_path).expect("Failed to create file");
-                        if USE_JSON_FOR_MESH {
-                            let write_contents: String = serde_json::to_string(&mesh_data_create_info).expect("Failed to serialize.");
-                            write_file.write(write_contents.as_bytes()).expect("Failed to write");
-                        } else {
-                            let write_contents: Vec<u8> = bincode::serialize(&mesh_data_create_info).unwrap();
-                            write_file.write(&write_contents).expect("Failed to write");
-                        }
-                    }
+                mesh_data_create_info
+            },
+        }
+    }
 
-                    mesh_data_create_info
-                },
+    // the lod meshes are never acquired, they are unloaded and loaded again with their base mesh.
+    fn get_mesh_lod_names(&self, base_mesh_name: &str) -> Vec<String> {
+        let mut lod_mesh_names: Vec<(usize, String)> = self._mesh_data_map.keys().filter_map(|mesh_name| {
+            match mesh::get_mesh_lod_base_name(mesh_name.as_str()) {
+                Some((lod_base_mesh_name, lod_index)) if ResourceName::from(&lod_base_mesh_name) == ResourceName::from(base_mesh_name) => Some((lod_index, String::from(mesh_name.as_str()))),
+                _ => None,
+            }
+        }).collect();
+        lod_mesh_names.sort();
+        lod_mesh_names.into_iter().map(|(_, lod_mesh_name)| lod_mesh_name).collect()
+    }
+
+    // the geometry buffers are destroyed, the mesh data is kept for the models and the render objects.
+    pub fn unload_mesh_data(&mut self, renderer_data: &RendererData, mesh_name: &str) {
+        let mut mesh_names: Vec<String> = self.get_mesh_lod_names(mesh_name);
+        mesh_names.push(String::from(mesh_name));
+        for mesh_name in mesh_names.iter() {
+            let mut mesh_data = self.get_mesh_data(mesh_name).borrow_mut();
+            for geometry_data in mesh_data.get_geomtry_datas() {
+                renderer_data.destroy_geomtry_buffer(&geometry_data.borrow());
+            }
+            mesh_data._geometry_datas.clear();
+            mesh_data._lod_datas.clear();
+            mesh_data._collision_data = None;
+            drop(mesh_data);
+            self._resource_ref_counter.unload_resource(ResourceRefType::Mesh, mesh_name);
+        }
+    }
+
+    pub fn reload_mesh_data(&mut self, renderer_data: &RendererData, mesh_name: &str) {
+        let mut mesh_names: Vec<String> = self.get_mesh_lod_names(mesh_name);
+        mesh_names.push(String::from(mesh_name));
+        for mesh_name in mesh_names.iter() {
+            let mesh_source_file = match self._mesh_source_file_map.get(&ResourceName::from(mesh_name)) {
+                Some(mesh_source_file) => mesh_source_file.clone(),
+                None => {
+                    log::error!("reload_mesh_data: not found the source of {}", mesh_name);
+                    continue;
+                }
             };
-            self.regist_mesh_data(renderer_data, &mesh_name, mesh_data_create_info);
+            let resource_ext = if USE_JSON_FOR_MESH { EXT_JSON } else { EXT_MESH };
+            let mesh_file = get_resource_file_path(&PathBuf::from(MESH_FILE_PATH), mesh_name, resource_ext);
+            let mesh_file = if self.resolve_path(&mesh_file).is_file() { Some(mesh_file) } else { None };
+            let mesh_data_create_info = self.load_mesh_data_create_info(mesh_name, &mesh_source_file, mesh_file.as_ref());
+            let mesh_data = self.create_mesh_data(renderer_data, mesh_name, mesh_data_create_info);
+            *self.get_mesh_data(mesh_name).borrow_mut() = mesh_data;
+            self._resource_ref_counter.register_reloadable_resource(ResourceRefType::Mesh, mesh_name);
+            log::info!("reload_mesh_data: {}", mesh_name);
         }
+        self.link_mesh_lod_datas(mesh_name);
+    }
+
+    // the lod meshes stay in the mesh map and own their geometries, the base mesh refers to them.
+    // the lod distances come from the import settings of the base mesh source, or from its bound radius.
+    pub fn link_mesh_lod_datas(&mut self, base_mesh_name: &str) {
+        let lod_mesh_names = self.get_mesh_lod_names(base_mesh_name);
+        if lod_mesh_names.is_empty() {
+            return;
+        }
+        if false == self.has_mesh_data(base_mesh_name) {
+            log::warn!("link_mesh_lod_datas: not found the base mesh {}", base_mesh_name);
+            return;
+        }
+        let base_mesh_data = self.get_mesh_data(base_mesh_name).clone();
+        let lod_distances = match self._mesh_source_file_map.get(&ResourceName::from(base_mesh_name)) {
+            Some(mesh_source_file) => self.load_mesh_import_settings(mesh_source_file)._lod_distances,
+            None => Vec::new(),
+        };
+        let mut lod_datas: Vec<MeshLodData> = Vec::new();
+        for lod_mesh_name in lod_mesh_names.iter() {
+            let lod_index = mesh::get_mesh_lod_base_name(lod_mesh_name).unwrap().1;
+            if lod_index != lod_datas.len() + 1 {
+                log::error!("link_mesh_lod_datas: {} skips the lod {}", base_mesh_name, lod_datas.len() + 1);
+                break;
+            }
+            let lod_mesh_data = self.get_mesh_data(lod_mesh_name).borrow();
+            if lod_mesh_data.get_geometry_data_count() != base_mesh_data.borrow().get_geometry_data_count() {
+                log::error!("link_mesh_lod_datas: {} has {} geometries, {} has {}", lod_mesh_name, lod_mesh_data.get_geometry_data_count(), base_mesh_name, base_mesh_data.borrow().get_geometry_data_count());
+                break;
+            }
+            let default_lod_distance = base_mesh_data.borrow()._bound_box._radius * mesh::DEFAULT_MESH_LOD_DISTANCE_RATIO * (lod_index as f32);
+            lod_datas.push(MeshLodData {
+                _geometry_datas: lod_mesh_data.get_geomtry_datas().clone(),
+                _lod_distance: lod_distances.get(lod_index - 1).cloned().unwrap_or(default_lod_distance),
+            });
+        }
+        log::info!("    mesh lods {}: {}", base_mesh_name, 1 + lod_datas.len());
+        base_mesh_data.borrow_mut().set_lod_datas(lod_datas);
+    }
+
+    pub fn load_mesh_import_settings(&self, mesh_source_file: &PathBuf) -> MeshImportSettings {
+        let mut import_settings_file = mesh_source_file.clone().into_os_string();
+        import_settings_file.push(".");
+        import_settings_file.push(mesh_welder::EXT_MESH_IMPORT_SETTINGS);
+        let import_settings_file = PathBuf::from(import_settings_file);
+        if self._resource_filenames.contains(&import_settings_file) || self.resolve_path(&import_settings_file).is_file() {
+            let loaded_contents = self.read_bytes(&import_settings_file);
+            match serde_json::from_reader(loaded_contents) {
+                Ok(mesh_import_settings) => return mesh_import_settings,
+                Err(e) => log::error!("load_mesh_import_settings error: {:?} {:?}", import_settings_file, e),
+            }
+        }
+        MeshImportSettings::default()
     }
 
     pub fn unload_mesh_datas(&mut self, renderer_data: &RendererData) {
@@ -564,16 +1340,90 @@ impl Resources {
     }
 
     pub fn has_mesh_data(&self, resource_name: &str) -> bool {
-        self._mesh_data_map.contains_key(resource_name)
+        self._mesh_data_map.contains_key(&ResourceName::from(resource_name))
     }
 
     pub fn get_mesh_data(&self, resource_name: &str) -> &RcRefCell<MeshData> {
         get_resource_data(&self._mesh_data_map, resource_name, DEFAULT_MESH_NAME)
     }
 
+    // AnimStateMachineData
+    fn get_anim_state_machine_clip_meshes(&self, create_info: &AnimStateMachineCreateInfo) -> HashMap<String, RcRefCell<MeshData>> {
+        let mut clip_meshes: HashMap<String, RcRefCell<MeshData>> = HashMap::new();
+        for state in create_info._states.iter() {
+            for clip_name in state._motion.get_clip_names() {
+                if self.has_mesh_data(clip_name) {
+                    clip_meshes.insert(clip_name.clone(), self.get_mesh_data(clip_name).clone());
+                } else {
+                    log::error!("not found animation clip: {}", clip_name);
+                }
+            }
+        }
+        clip_meshes
+    }
+
+    pub fn load_anim_state_machine_datas(&mut self, _renderer_data: &RendererData) {
+        let anim_state_machine_directory = PathBuf::from(ANIMATION_STATE_MACHINE_FILE_PATH);
+        let anim_state_machine_files = self.collect_resources(&anim_state_machine_directory, &[EXT_ANIMATION_STATE_MACHINE]);
+        resource_name::check_resource_name_collisions("anim state machine", &anim_state_machine_directory, &anim_state_machine_files);
+        for anim_state_machine_file in anim_state_machine_files {
+            let anim_state_machine_name = get_unique_resource_name(&self._anim_state_machine_data_map, &anim_state_machine_directory, &anim_state_machine_file);
+            let loaded_contents = self.read_bytes(&anim_state_machine_file);
+            match AnimStateMachineData::parse_anim_state_machine_create_info(loaded_contents.get_ref()) {
+                Ok(create_info) => {
+                    let clip_meshes = self.get_anim_state_machine_clip_meshes(&create_info);
+                    let anim_state_machine_data = AnimStateMachineData::create_anim_state_machine_data(&anim_state_machine_name, create_info, clip_meshes);
+                    self._anim_state_machine_data_map.insert(ResourceName::from(anim_state_machine_name), newRcRefCell(anim_state_machine_data));
+                },
+                Err(e) => log::error!("load_anim_state_machine_datas error: {:?} {}", anim_state_machine_file, e),
+            }
+        }
+    }
+
+    pub fn unload_anim_state_machine_datas(&mut self, _renderer_data: &RendererData) {
+        self._anim_state_machine_data_map.clear();
+    }
+
+    // called with the polled file events, the render objects keep their state machine components and parameters.
+    pub fn reload_anim_state_machine_datas(&self, file_events: &[FileEvent]) {
+        let anim_state_machine_directory = PathBuf::from(ANIMATION_STATE_MACHINE_FILE_PATH);
+        for file_event in file_events.iter() {
+            let is_anim_state_machine_file = file_event._file_path.starts_with(&anim_state_machine_directory)
+                && Some(EXT_ANIMATION_STATE_MACHINE) == file_event._file_path.extension().and_then(|ext| ext.to_str());
+            if false == is_anim_state_machine_file || FileEventType::Removed == file_event._event_type {
+                continue;
+            }
+            let anim_state_machine_name = get_resource_name_from_file_path(&anim_state_machine_directory, &file_event._file_path);
+            let anim_state_machine_data = match self._anim_state_machine_data_map.get(&ResourceName::from(&anim_state_machine_name)) {
+                Some(anim_state_machine_data) => anim_state_machine_data,
+                None => {
+                    log::warn!("reload_anim_state_machine_datas: a new file needs the restart, {:?}", file_event._file_path);
+                    continue;
+                }
+            };
+            let loaded_contents = self.read_bytes(&file_event._file_path);
+            match AnimStateMachineData::parse_anim_state_machine_create_info(loaded_contents.get_ref()) {
+                Ok(create_info) => {
+                    let clip_meshes = self.get_anim_state_machine_clip_meshes(&create_info);
+                    anim_state_machine_data.borrow_mut().reload_anim_state_machine_data(create_info, clip_meshes);
+                    log::info!("reload_anim_state_machine_datas: {}", anim_state_machine_name);
+                },
+                Err(e) => log::error!("reload_anim_state_machine_datas error: {:?} {}", file_event._file_path, e),
+            }
+        }
+    }
+
+    pub fn has_anim_state_machine_data(&self, resource_name: &str) -> bool {
+        self._anim_state_machine_data_map.contains_key(&ResourceName::from(resource_name))
+    }
+
+    pub fn get_anim_state_machine_data(&self, resource_name: &str) -> Option<&RcRefCell<AnimStateMachineData>> {
+        self._anim_state_machine_data_map.get(&ResourceName::from(resource_name))
+    }
+
     // TextureLoader
-    pub fn load_image_data(texture_file: &PathBuf) -> LoadImageInfoType {
-        let loaded_contents = system::load(texture_file);
+    pub fn load_image_data(&self, texture_file: &PathBuf) -> LoadImageInfoType {
+        let loaded_contents = self.read_bytes(texture_file);
         let image_format = image::ImageFormat::from_path(texture_file);
         let image_file = image::load(loaded_contents, image_format.unwrap());
         if image_file.is_err() {
@@ -596,13 +1446,51 @@ impl Resources {
         (image_width, image_height, image_layer, image_data_raw, image_format)
     }
 
-    pub fn load_image_datas(texture_files: &Vec<PathBuf>) -> LoadImageInfoType {
+    // the face size is a quarter of the width, the half float cube keeps the range of the hdr.
+    pub fn load_equirectangular_texture(&self, renderer_data: &RendererData, texture_data_name: &str, texture_file: &PathBuf) -> Option<TextureData> {
+        let hdr_decoder = match image::codecs::hdr::HdrDecoder::new(self.read_bytes(texture_file)) {
+            Ok(hdr_decoder) => hdr_decoder,
+            Err(error) => {
+                log::error!("load_equirectangular_texture: {:?} {}", texture_file, error);
+                return None;
+            }
+        };
+        let metadata = hdr_decoder.metadata();
+        let pixels: Vec<[f32; 3]> = match hdr_decoder.read_image_hdr() {
+            Ok(pixels) => pixels.iter().map(|pixel| pixel.0).collect(),
+            Err(error) => {
+                log::error!("load_equirectangular_texture: {:?} {}", texture_file, error);
+                return None;
+            }
+        };
+        let face_size = (metadata.width / 4).max(1);
+        let texture_create_info = TextureCreateInfo {
+            _texture_name: String::from(texture_data_name),
+            _texture_width: face_size,
+            _texture_height: face_size,
+            _texture_layers: constants::CUBE_LAYER_COUNT as u32,
+            _texture_format: skybox::SKYBOX_CUBEMAP_FORMAT,
+            _texture_view_type: vk::ImageViewType::CUBE,
+            _texture_min_filter: vk::Filter::LINEAR,
+            _texture_mag_filter: vk::Filter::LINEAR,
+            _texture_wrap_mode: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+            _texture_initial_datas: skybox::convert_equirectangular_to_cube(metadata.width, metadata.height, &pixels, face_size),
+            _enable_mipmap: true,
+            _enable_anisotropy: false,
+            _texture_mip_drop: self.get_texture_mip_drop(renderer_data, texture_file),
+            ..Default::default()
+        };
+        log::info!("load_equirectangular_texture: {:?} {}x{} -> cube {}", texture_file, metadata.width, metadata.height, face_size);
+        Some(renderer_data.create_texture(&texture_create_info))
+    }
+
+    pub fn load_image_datas(&self, texture_files: &Vec<PathBuf>) -> LoadImageInfoType {
         let mut image_width: u32 = 0;
         let mut image_height: u32 = 0;
         let mut image_format: vk::Format = vk::Format::UNDEFINED;
         let mut image_datas: Vec<Vec<u8>> = Vec::new();
         for texture_file in texture_files.iter() {
-            let (width, height, _layer, image_data, format): LoadImageInfoType = Resources::load_image_data(texture_file);
+            let (width, height, _layer, image_data, format): LoadImageInfoType = self.load_image_data(texture_file);
             image_width = width;
             image_height = height;
             image_format = format;
@@ -612,8 +1500,35 @@ impl Resources {
         (image_width, image_height, texture_files.len() as u32, image_datas, image_format)
     }
 
+    pub fn load_texture_import_settings(&self, texture_file: &PathBuf) -> TextureImportSettings {
+        let mut import_settings_file = texture_file.clone().into_os_string();
+        import_settings_file.push(".");
+        import_settings_file.push(mesh_welder::EXT_MESH_IMPORT_SETTINGS);
+        let import_settings_file = PathBuf::from(import_settings_file);
+        if self._resource_filenames.contains(&import_settings_file) || self.resolve_path(&import_settings_file).is_file() {
+            let loaded_contents = self.read_bytes(&import_settings_file);
+            match serde_json::from_reader(loaded_contents) {
+                Ok(texture_import_settings) => return texture_import_settings,
+                Err(e) => log::error!("load_texture_import_settings error: {:?} {:?}", import_settings_file, e),
+            }
+        }
+        TextureImportSettings::default()
+    }
+
+    pub fn get_texture_mip_drop(&self, renderer_data: &RendererData, texture_file: &PathBuf) -> u32 {
+        if self.load_texture_import_settings(texture_file)._exempt_mip_drop {
+            return 0;
+        }
+        renderer_data.get_texture_mip_drop()
+    }
+
+    pub fn get_texture_memory_saved(&self) -> vk::DeviceSize {
+        self._texture_data_map.values().map(|texture_data| texture_data.borrow()._image_memory_saved).sum()
+    }
+
     pub fn regist_texture_data(&mut self, texture_data_name: String, texture_data: RcRefCell<TextureData>) {
-        self._texture_data_map.insert(texture_data_name, texture_data);
+        self._resource_ref_counter.register_resource(ResourceRefType::Texture, &texture_data_name);
+        self._texture_data_map.insert(ResourceName::from(texture_data_name), texture_data);
     }
 
     pub fn load_texture_datas(&mut self, renderer_data: &RendererData) {
@@ -637,7 +1552,7 @@ impl Resources {
         for texture_src_file in combined_texture_files.iter() {
             let directory = texture_src_file.parent().unwrap();
             let texture_data_name = get_resource_name_from_file_path(&texture_source_directory, &texture_src_file);
-            let loaded_contents = system::load(texture_src_file);
+            let loaded_contents = self.read_bytes(texture_src_file);
             let contents = serde_json::from_reader(loaded_contents).expect("Failed to deserialize.");
             let ext = texture_src_file.extension().unwrap();
             let mut texture_file_names: Vec<String> = Vec::new();
@@ -681,81 +1596,287 @@ impl Resources {
             combined_texture_types_map.insert(texture_data_name.clone(), image_view_type);
         }
 
+        // load pre-encoded textures, they take precedence over the images with the same name.
+        let mut texture_sources: Vec<(String, TextureSourceFile)> = Vec::new();
+        let compressed_texture_files = self.collect_resources(texture_source_directory.as_path(), &COMPRESSED_IMAGE_SOURCE_EXTS);
+        for texture_src_file in compressed_texture_files.iter() {
+            if false == combined_textures_name_map.contains_key(texture_src_file) {
+                let texture_data_name = get_resource_name_from_file_path(&texture_source_directory, &texture_src_file);
+                texture_sources.push((texture_data_name, TextureSourceFile::Compressed(texture_src_file.clone())));
+            }
+        }
+
+        // the equirectangular hdr images of the sky, ex) SkySettings::_sky_cubemap
+        let equirectangular_texture_files = self.collect_resources(texture_source_directory.as_path(), &[EXT_TEXTURE_EQUIRECTANGULAR]);
+        for texture_src_file in equirectangular_texture_files.iter() {
+            let texture_data_name = get_resource_name_from_file_path(&texture_source_directory, &texture_src_file);
+            texture_sources.push((texture_data_name, TextureSourceFile::Equirectangular(texture_src_file.clone())));
+        }
+
         // load texture from external files
         let texture_src_files = self.collect_resources(texture_source_directory.as_path(), &IMAGE_SOURCE_EXTS);
         for texture_src_file in texture_src_files.iter() {
-            let combined_texture_name = combined_textures_name_map.get(texture_src_file);
-            let texture_data_name: String = match combined_texture_name {
-                Some(combined_texture_name) => combined_texture_name.clone(),
-                _ => get_resource_name_from_file_path(&texture_source_directory, &texture_src_file)
+            let texture_source = match combined_textures_name_map.get(texture_src_file) {
+                Some(combined_texture_name) => (combined_texture_name.clone(), TextureSourceFile::Combined(
+                    *combined_texture_types_map.get(combined_texture_name.as_str()).unwrap(),
+                    combined_texture_files_map.get(combined_texture_name.as_str()).unwrap().clone(),
+                )),
+                _ => (get_resource_name_from_file_path(&texture_source_directory, &texture_src_file), TextureSourceFile::Image(texture_src_file.clone())),
             };
-
-            let existing_resource_data: Option<&RcRefCell<TextureData>> = self._texture_data_map.get(&texture_data_name);
-            if existing_resource_data.is_none() {
-                let (image_view_type, (image_width, image_height, image_layers, image_data, image_format)): (vk::ImageViewType, LoadImageInfoType) =
-                    if combined_texture_name.is_some() {
-                        let combined_texture_files = combined_texture_files_map.get(texture_data_name.as_str()).unwrap();
-                        let image_view_type = combined_texture_types_map.get(texture_data_name.as_str()).unwrap();
-                        (*image_view_type, Resources::load_image_datas(&combined_texture_files))
-                    } else {
-                        (vk::ImageViewType::TYPE_2D, Resources::load_image_data(texture_src_file))
-                    };
-                if vk::Format::UNDEFINED != image_format {
-                    let texture_create_info = TextureCreateInfo {
-                        _texture_name: texture_data_name.clone(),
-                        _texture_width: image_width,
-                        _texture_height: image_height,
-                        _texture_layers: image_layers,
-                        _texture_format: image_format,
-                        _texture_view_type: image_view_type,
-                        _texture_initial_datas: image_data,
-                        _enable_mipmap: true,
-                        _enable_anisotropy: false,
-                        ..Default::default()
-                    };
-                    let texture_data = renderer_data.create_texture(&texture_create_info);
-                    self._texture_data_map.insert(texture_data_name, newRcRefCell(texture_data));
-                }
-            }
+            texture_sources.push(texture_source);
         }
 
         // read binary texture data
         let texture_files = self.collect_resources(texture_directory.as_path(), &EXT_TEXTURE);
+        resource_name::check_resource_name_collisions("texture", &texture_directory, &texture_files);
         for texture_file in texture_files.iter() {
             let texture_data_name = get_resource_name_from_file_path(&texture_directory, texture_file);
-            let mut loaded_contents = system::load(&texture_file);
-            let image_view_type: vk::ImageViewType = vk::ImageViewType::from_raw(loaded_contents.read_i32::<LittleEndian>().unwrap() as i32);
-            let image_width: i32 = loaded_contents.read_i32::<LittleEndian>().unwrap();
-            let image_height: i32 = loaded_contents.read_i32::<LittleEndian>().unwrap();
-            let image_depth: i32 = loaded_contents.read_i32::<LittleEndian>().unwrap();
-            let image_format: vk::Format = vk::Format::from_raw(loaded_contents.read_i32::<LittleEndian>().unwrap() as i32);
-            let enable_mipmap: bool = if 0 != loaded_contents.read_i32::<LittleEndian>().unwrap() { true } else { false };
-            let min_filter: vk::Filter = vk::Filter::from_raw(loaded_contents.read_i32::<LittleEndian>().unwrap() as i32);
-            let mag_filter: vk::Filter = vk::Filter::from_raw(loaded_contents.read_i32::<LittleEndian>().unwrap() as i32);
-            let wrap: vk::SamplerAddressMode = vk::SamplerAddressMode::from_raw(loaded_contents.read_i32::<LittleEndian>().unwrap() as i32);
-            let data_bytes: i32 = loaded_contents.read_i32::<LittleEndian>().unwrap();
-            let mut image_data: Vec<u8> = Vec::new();
-            loaded_contents.read_to_end(&mut image_data).expect("Failed to read datas.");
-            assert_eq!(data_bytes as usize, image_data.len());
-
-            let texture_create_info = TextureCreateInfo {
-                _texture_name: texture_data_name.clone(),
-                _texture_width: image_width as u32,
-                _texture_height: image_height as u32,
-                _texture_layers: image_depth as u32,
-                _texture_format: image_format,
-                _texture_min_filter: min_filter,
-                _texture_mag_filter: mag_filter,
-                _texture_wrap_mode: wrap,
-                _texture_view_type: image_view_type,
-                _texture_initial_datas: image_data,
-                _enable_mipmap: enable_mipmap,
-                _enable_anisotropy: false,
-                ..Default::default()
+            texture_sources.push((texture_data_name, TextureSourceFile::Binary(texture_file.clone())));
+        }
+
+        // the first source of a name is loaded, the binary texture data replaces it.
+        for (texture_data_name, texture_source_file) in texture_sources.into_iter() {
+            let is_binary_texture = match texture_source_file {
+                TextureSourceFile::Binary(_) => true,
+                _ => false,
             };
-            let texture_data = renderer_data.create_texture(&texture_create_info);
-            self._texture_data_map.insert(texture_data_name, newRcRefCell(texture_data));
+            if false == is_binary_texture && self._texture_data_map.contains_key(&ResourceName::from(&texture_data_name)) {
+                continue;
+            }
+            if let Some(texture_data) = self.load_texture_data_from_source(renderer_data, &texture_data_name, &texture_source_file) {
+                self._texture_data_map.insert(ResourceName::from(&texture_data_name), newRcRefCell(texture_data));
+                self._texture_source_file_map.insert(ResourceName::from(&texture_data_name), texture_source_file);
+                self._resource_ref_counter.register_reloadable_resource(ResourceRefType::Texture, &texture_data_name);
+            }
         }
+
+        // ies profiles
+        self.load_ies_profiles(renderer_data);
+
+        log::info!("load_texture_datas: texture_mip_drop {}, saved {} KB", renderer_data.get_texture_mip_drop(), self.get_texture_memory_saved() / 1024);
+    }
+
+    pub fn load_texture_data_from_source(&self, renderer_data: &RendererData, texture_data_name: &String, texture_source_file: &TextureSourceFile) -> Option<TextureData> {
+        let texture_create_info = match texture_source_file {
+            TextureSourceFile::Compressed(texture_src_file) => {
+                let ext = texture_src_file.extension().unwrap().to_str().unwrap().to_lowercase();
+                let compressed_texture = match compressed_texture_loader::load_compressed_texture(&ext, self.read_bytes(texture_src_file).get_ref()) {
+                    Ok(compressed_texture) => compressed_texture,
+                    Err(error) => {
+                        log::error!("failed to load {:?}: {}", texture_src_file, error);
+                        return None;
+                    }
+                };
+                let (texture_format, texture_initial_datas) = if texture::is_format_sampleable(renderer_data.get_instance(), renderer_data.get_physical_device(), compressed_texture._format) {
+                    (compressed_texture._format, compressed_texture._datas.clone())
+                } else {
+                    log::warn!("{:?} is not supported by the device, {:?} is decompressed to rgba8.", compressed_texture._format, texture_src_file);
+                    (compressed_texture.get_decompressed_format(), compressed_texture.decompress_to_rgba8())
+                };
+                TextureCreateInfo {
+                    _texture_name: texture_data_name.clone(),
+                    _texture_width: compressed_texture._width,
+                    _texture_height: compressed_texture._height,
+                    _texture_layers: compressed_texture.get_texture_layers(),
+                    _texture_format: texture_format,
+                    _texture_view_type: compressed_texture.get_image_view_type(),
+                    _texture_initial_datas: texture_initial_datas,
+                    _texture_initial_mip_levels: compressed_texture._mip_levels,
+                    _enable_mipmap: true,
+                    _enable_anisotropy: false,
+                    _texture_mip_drop: self.get_texture_mip_drop(renderer_data, texture_src_file),
+                    ..Default::default()
+                }
+            },
+            TextureSourceFile::Equirectangular(texture_src_file) => {
+                return self.load_equirectangular_texture(renderer_data, texture_data_name, texture_src_file);
+            },
+            TextureSourceFile::Image(_) | TextureSourceFile::Combined(_, _) => {
+                let (texture_src_file, image_view_type, (image_width, image_height, image_layers, image_data, image_format)): (&PathBuf, vk::ImageViewType, LoadImageInfoType) = match texture_source_file {
+                    TextureSourceFile::Combined(image_view_type, combined_texture_files) => (&combined_texture_files[0], *image_view_type, self.load_image_datas(combined_texture_files)),
+                    TextureSourceFile::Image(texture_src_file) => (texture_src_file, vk::ImageViewType::TYPE_2D, self.load_image_data(texture_src_file)),
+                    _ => unreachable!(),
+                };
+                if vk::Format::UNDEFINED == image_format {
+                    return None;
+                }
+                TextureCreateInfo {
+                    _texture_name: texture_data_name.clone(),
+                    _texture_width: image_width,
+                    _texture_height: image_height,
+                    _texture_layers: image_layers,
+                    _texture_format: image_format,
+                    _texture_view_type: image_view_type,
+                    _texture_initial_datas: image_data,
+                    _enable_mipmap: true,
+                    _enable_anisotropy: false,
+                    _texture_mip_drop: self.get_texture_mip_drop(renderer_data, texture_src_file),
+                    ..Default::default()
+                }
+            },
+            TextureSourceFile::Binary(texture_file) => {
+                let mut loaded_contents = self.read_bytes(&texture_file);
+                let image_view_type: vk::ImageViewType = vk::ImageViewType::from_raw(loaded_contents.read_i32::<LittleEndian>().unwrap() as i32);
+                let image_width: i32 = loaded_contents.read_i32::<LittleEndian>().unwrap();
+                let image_height: i32 = loaded_contents.read_i32::<LittleEndian>().unwrap();
+                let image_depth: i32 = loaded_contents.read_i32::<LittleEndian>().unwrap();
+                let image_format: vk::Format = vk::Format::from_raw(loaded_contents.read_i32::<LittleEndian>().unwrap() as i32);
+                let enable_mipmap: bool = if 0 != loaded_contents.read_i32::<LittleEndian>().unwrap() { true } else { false };
+                let min_filter: vk::Filter = vk::Filter::from_raw(loaded_contents.read_i32::<LittleEndian>().unwrap() as i32);
+                let mag_filter: vk::Filter = vk::Filter::from_raw(loaded_contents.read_i32::<LittleEndian>().unwrap() as i32);
+                let wrap: vk::SamplerAddressMode = vk::SamplerAddressMode::from_raw(loaded_contents.read_i32::<LittleEndian>().unwrap() as i32);
+                let data_bytes: i32 = loaded_contents.read_i32::<LittleEndian>().unwrap();
+                let mut image_data: Vec<u8> = Vec::new();
+                loaded_contents.read_to_end(&mut image_data).expect("Failed to read datas.");
+                assert_eq!(data_bytes as usize, image_data.len());
+                TextureCreateInfo {
+                    _texture_name: texture_data_name.clone(),
+                    _texture_width: image_width as u32,
+                    _texture_height: image_height as u32,
+                    _texture_layers: image_depth as u32,
+                    _texture_format: image_format,
+                    _texture_min_filter: min_filter,
+                    _texture_mag_filter: mag_filter,
+                    _texture_wrap_mode: wrap,
+                    _texture_view_type: image_view_type,
+                    _texture_initial_datas: image_data,
+                    _enable_mipmap: enable_mipmap,
+                    _enable_anisotropy: false,
+                    _texture_mip_drop: self.get_texture_mip_drop(renderer_data, texture_file),
+                    ..Default::default()
+                }
+            },
+        };
+        Some(renderer_data.create_texture(&texture_create_info))
+    }
+
+    // the texture data is replaced in place, so the references of the material instances remain valid.
+    pub fn reload_texture_data(&mut self, renderer_data: &RendererData, texture_data_name: &str) {
+        let texture_source_file = match self._texture_source_file_map.get(&ResourceName::from(texture_data_name)) {
+            Some(texture_source_file) => texture_source_file.clone(),
+            None => {
+                log::error!("reload_texture_data: not found the source of {}", texture_data_name);
+                return;
+            }
+        };
+        let texture_data_name = String::from(texture_data_name);
+        if let Some(texture_data) = self.load_texture_data_from_source(renderer_data, &texture_data_name, &texture_source_file) {
+            *self.get_texture_data(&texture_data_name).borrow_mut() = texture_data;
+            self._resource_ref_counter.register_reloadable_resource(ResourceRefType::Texture, &texture_data_name);
+            self.update_bindless_textures(renderer_data);
+            log::info!("reload_texture_data: {}", texture_data_name);
+        }
+    }
+
+    // all profiles in one texture array, so the lighting pass binds a single texture and the lights index the layers.
+    // the layer 0 is the flat profile, the array is never empty.
+    pub fn load_ies_profiles(&mut self, renderer_data: &RendererData) {
+        let ies_source_directory = PathBuf::from(IES_SOURCE_FILE_PATH);
+        let mut ies_files = self.collect_resources(ies_source_directory.as_path(), &[EXT_IES]);
+        ies_files.sort();
+        let texture_layer_size = (ies_loader::IES_TEXTURE_WIDTH * ies_loader::IES_TEXTURE_HEIGHT) as usize;
+        let mut ies_texture_datas: Vec<f32> = vec![1.0; texture_layer_size];
+        self._ies_profile_names.clear();
+        for ies_file in ies_files.iter() {
+            let ies_profile_name = get_resource_name_from_file_path(&ies_source_directory, ies_file);
+            let contents = String::from_utf8_lossy(self.read_bytes(ies_file).get_ref()).into_owned();
+            match ies_loader::parse_ies_profile(&contents) {
+                Ok(ies_profile) => {
+                    if IesPhotometricType::TypeC != ies_profile._photometric_type {
+                        log::warn!("load_ies_profiles: {:?} is sampled as type C, {}", ies_profile._photometric_type, ies_profile_name);
+                    }
+                    ies_texture_datas.extend(ies_profile.create_ies_texture_data(ies_loader::IES_TEXTURE_WIDTH, ies_loader::IES_TEXTURE_HEIGHT));
+                    self._ies_profile_names.push(ies_profile_name);
+                },
+                Err(e) => log::error!("load_ies_profiles: {} {}", ies_profile_name, e),
+            }
+        }
+
+        let texture_create_info = TextureCreateInfo {
+            _texture_name: String::from(IES_PROFILES_TEXTURE_NAME),
+            _texture_width: ies_loader::IES_TEXTURE_WIDTH,
+            _texture_height: ies_loader::IES_TEXTURE_HEIGHT,
+            _texture_layers: 1 + self._ies_profile_names.len() as u32,
+            _texture_format: vk::Format::R32_SFLOAT,
+            _texture_view_type: vk::ImageViewType::TYPE_2D_ARRAY,
+            _texture_min_filter: vk::Filter::LINEAR,
+            _texture_mag_filter: vk::Filter::LINEAR,
+            _texture_wrap_mode: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+            _texture_initial_datas: ies_texture_datas,
+            _enable_mipmap: false,
+            ..Default::default()
+        };
+        let texture_data = renderer_data.create_texture(&texture_create_info);
+        self._texture_data_map.insert(ResourceName::from(IES_PROFILES_TEXTURE_NAME), newRcRefCell(texture_data));
+    }
+
+    // the layer of IES_PROFILES_TEXTURE_NAME, INVALID_IES_PROFILE_INDEX uses the analytic cone falloff
+    pub fn get_ies_profile_index(&self, ies_profile_name: &str) -> i32 {
+        if ies_profile_name.is_empty() {
+            return INVALID_IES_PROFILE_INDEX;
+        }
+        match self._ies_profile_names.iter().position(|name| ies_profile_name == name) {
+            Some(index) => 1 + index as i32,
+            None => {
+                log::warn!("get_ies_profile_index: not found {}", ies_profile_name);
+                INVALID_IES_PROFILE_INDEX
+            }
+        }
+    }
+
+    pub fn get_ies_profile(&self, ies_profile_name: &str) -> Option<IesProfile> {
+        let ies_file = get_resource_file_path(&PathBuf::from(IES_SOURCE_FILE_PATH), &String::from(ies_profile_name), EXT_IES);
+        if false == self.resolve_path(&ies_file).is_file() {
+            return None;
+        }
+        let contents = String::from_utf8_lossy(self.read_bytes(&ies_file).get_ref()).into_owned();
+        ies_loader::parse_ies_profile(&contents).map_err(|e| log::error!("get_ies_profile: {} {}", ies_profile_name, e)).ok()
+    }
+
+    // the heightmap of TerrainSettings, loaded on demand like the ies profiles
+    pub fn get_terrain_heightmap(&self, heightmap_name: &str) -> Option<TerrainHeightmap> {
+        let heightmap_file = get_resource_file_path(&PathBuf::from(TERRAIN_SOURCE_FILE_PATH), &String::from(heightmap_name), EXT_TERRAIN_HEIGHTMAP);
+        if false == self.resolve_path(&heightmap_file).is_file() {
+            log::error!("get_terrain_heightmap: not found {:?}", heightmap_file);
+            return None;
+        }
+        match image::load(self.read_bytes(&heightmap_file), image::ImageFormat::Png) {
+            Ok(image::DynamicImage::ImageLuma16(heightmap_image)) => Some(TerrainHeightmap {
+                _width: heightmap_image.width(),
+                _height: heightmap_image.height(),
+                _heights: heightmap_image.into_raw(),
+            }),
+            Ok(dynamic_image) => {
+                log::warn!("get_terrain_heightmap: {} is not 16 bit gray, the heights are expanded from 8 bit.", heightmap_name);
+                let heightmap_image = dynamic_image.to_luma8();
+                Some(TerrainHeightmap {
+                    _width: heightmap_image.width(),
+                    _height: heightmap_image.height(),
+                    _heights: heightmap_image.into_raw().iter().map(|height| *height as u16 * 257).collect(),
+                })
+            },
+            Err(e) => {
+                log::error!("get_terrain_heightmap: {} {}", heightmap_name, e);
+                None
+            }
+        }
+    }
+
+    // the texture datas are replaced in place, so the references of the fonts and the models remain valid.
+    pub fn reload_texture_datas(&mut self, renderer_data: &mut RendererData) {
+        let prev_texture_data_map = std::mem::replace(&mut self._texture_data_map, TextureDataMap::new());
+        self.load_texture_datas(renderer_data);
+        for (texture_data_name, prev_texture_data) in prev_texture_data_map.iter() {
+            // the textures not from load_texture_datas are kept. ex) font textures
+            if let Some(texture_data) = self._texture_data_map.get(texture_data_name) {
+                renderer_data.destroy_texture(&prev_texture_data.borrow());
+                let texture_data = texture_data.borrow().clone();
+                *prev_texture_data.borrow_mut() = texture_data;
+            }
+            self._texture_data_map.insert(texture_data_name.clone(), prev_texture_data.clone());
+        }
+        // the replaced textures keep their slots, the new image views are written to them
+        self.update_bindless_textures(renderer_data);
     }
 
     pub fn unload_texture_datas(&mut self, renderer_data: &RendererData) {
@@ -763,10 +1884,77 @@ impl Resources {
             renderer_data.destroy_texture(&(*texture_data).borrow());
         }
         self._texture_data_map.clear();
+        self._texture_source_file_map.clear();
+        if let Some(mut bindless_texture_array) = self._bindless_texture_array.take() {
+            bindless_texture_array.destroy_bindless_texture_array(renderer_data.get_device());
+        }
+    }
+
+    // BindlessTextures
+    pub fn get_bindless_texture_array(&self) -> Option<&BindlessTextureArray> {
+        self._bindless_texture_array.as_ref()
+    }
+
+    pub fn get_bindless_descriptor_set_layout(&self) -> Option<vk::DescriptorSetLayout> {
+        self._bindless_texture_array.as_ref().map(|bindless_texture_array| bindless_texture_array._descriptor_set_layout)
+    }
+
+    // every 2d texture gets a slot of the bindless texture array, called again after the textures registered by regist_texture_data.
+    pub fn update_bindless_textures(&mut self, renderer_data: &RendererData) {
+        if false == renderer_data.is_bindless_textures_supported() {
+            return;
+        }
+        if self._bindless_texture_array.is_none() {
+            self._bindless_texture_array = Some(BindlessTextureArray::create_bindless_texture_array(renderer_data.get_device(), renderer_data.get_max_bindless_texture_count()));
+        }
+        let texture_data_map = &self._texture_data_map;
+        let resource_ref_counter = &self._resource_ref_counter;
+        let bindless_texture_array = self._bindless_texture_array.as_mut().unwrap();
+        let mut texture_image_infos: Vec<(u32, vk::DescriptorImageInfo)> = Vec::new();
+        for (texture_data_name, texture_data) in texture_data_map.iter() {
+            let mut texture_data = texture_data.borrow_mut();
+            if resource_ref_counter.is_resource_unloaded(ResourceRefType::Texture, texture_data_name.as_str()) || false == bindless_texture::is_bindless_texture(&texture_data) {
+                continue;
+            }
+            let texture_index = bindless_texture_array.regist_texture(texture_data_name.as_str());
+            texture_data._bindless_texture_index = texture_index;
+            if bindless_texture::INVALID_BINDLESS_TEXTURE_INDEX != texture_index {
+                texture_image_infos.push((texture_index, texture_data.get_default_image_info()));
+            }
+        }
+        // the slots of the removed and the unloaded textures are reused
+        let removed_texture_names: Vec<String> = bindless_texture_array._texture_index_map.keys().filter(|texture_data_name| {
+            false == texture_data_map.contains_key(texture_data_name.as_str()) || resource_ref_counter.is_resource_unloaded(ResourceRefType::Texture, texture_data_name)
+        }).cloned().collect();
+        for texture_data_name in removed_texture_names.iter() {
+            bindless_texture_array.unregist_texture(texture_data_name);
+        }
+        bindless_texture_array.update_texture_descriptors(renderer_data.get_device(), &texture_image_infos);
+        log::info!("update_bindless_textures: {} / {}", bindless_texture_array.get_texture_count(), bindless_texture_array._max_texture_count);
+    }
+
+    // the slots of the textures of the material parameters, INVALID_BINDLESS_TEXTURE_INDEX without the parameter
+    pub fn get_bindless_material_data(&self, material_parameter_map: &serde_json::Map<String, Value>, default_material_parameter_map: &Value) -> BindlessMaterialData {
+        let get_texture_index = |material_parameter_name: &str| -> u32 {
+            let maybe_material_parameter = match material_parameter_map.get(material_parameter_name) {
+                None => default_material_parameter_map.get(material_parameter_name),
+                value => value,
+            };
+            match maybe_material_parameter {
+                Some(Value::String(texture_data_name)) => self.get_texture_data(texture_data_name).borrow()._bindless_texture_index,
+                _ => bindless_texture::INVALID_BINDLESS_TEXTURE_INDEX,
+            }
+        };
+        BindlessMaterialData {
+            _albedo_texture_index: get_texture_index(bindless_texture::BINDLESS_ALBEDO_TEXTURE_PARAMETER_NAME),
+            _normal_texture_index: get_texture_index(bindless_texture::BINDLESS_NORMAL_TEXTURE_PARAMETER_NAME),
+            _material_texture_index: get_texture_index(bindless_texture::BINDLESS_MATERIAL_TEXTURE_PARAMETER_NAME),
+            ..Default::default()
+        }
     }
 
     pub fn has_texture_data(&self, resource_name: &str) -> bool {
-        self._texture_data_map.contains_key(resource_name)
+        self._texture_data_map.contains_key(&ResourceName::from(resource_name))
     }
 
     pub fn get_texture_data(&self, resource_name: &str) -> &RcRefCell<TextureData> {
@@ -787,7 +1975,7 @@ impl Resources {
                             render_pass_data._render_pass_data_name.as_str(),
                             render_pass_data_create_info._render_pass_framebuffer_create_info.clone(),
                         );
-                        self._framebuffer_datas_map.insert(render_pass_data._render_pass_data_name.clone(), newRcRefCell(framebuffer_data));
+                        self._framebuffer_datas_map.insert(ResourceName::from(render_pass_data._render_pass_data_name.clone()), newRcRefCell(framebuffer_data));
                         break;
                     }
                 }
@@ -803,40 +1991,107 @@ impl Resources {
     }
 
     pub fn has_framebuffer_data(&self, resource_name: &str) -> bool {
-        self._framebuffer_datas_map.contains_key(resource_name)
+        self._framebuffer_datas_map.contains_key(&ResourceName::from(self.get_merged_render_pass_name(resource_name)))
     }
 
     pub fn get_framebuffer_data(&self, resource_name: &str) -> &RcRefCell<FramebufferData> {
-        get_resource_data_must(&self._framebuffer_datas_map, resource_name)
+        get_resource_data_must(&self._framebuffer_datas_map, self.get_merged_render_pass_name(resource_name))
+    }
+
+    // ex) the render target of a scene capture of the project, collected after its release
+    pub fn regist_framebuffer_data(&mut self, framebuffer_data_name: String, framebuffer_data: RcRefCell<FramebufferData>) {
+        self._resource_ref_counter.register_resource(ResourceRefType::Framebuffer, &framebuffer_data_name);
+        self._framebuffer_datas_map.insert(ResourceName::from(framebuffer_data_name), framebuffer_data);
     }
 
     // RenderPassLoader
     pub fn load_render_pass_datas(&mut self, renderer_data: &RendererData) {
+        // engine side debug passes
+        #[cfg(not(target_os = "android"))]
+        histogram::generate_histogram_shader();
+        #[cfg(not(target_os = "android"))]
+        history_confidence::generate_history_confidence_shader();
+        #[cfg(not(target_os = "android"))]
+        gpu_skinning::generate_gpu_skinning_shader();
+        #[cfg(not(target_os = "android"))]
+        denoiser::generate_denoiser_shader();
+        #[cfg(not(target_os = "android"))]
+        tonemap::generate_tonemap_shader();
+        #[cfg(not(target_os = "android"))]
+        auto_exposure::generate_auto_exposure_shader();
+        #[cfg(not(target_os = "android"))]
+        occlusion_culling::generate_occlusion_culling_shader();
+        #[cfg(not(target_os = "android"))]
+        fxaa::generate_fxaa_shader();
+        #[cfg(not(target_os = "android"))]
+        light::generate_local_light_shader();
+        #[cfg(not(target_os = "android"))]
+        particle_collision::generate_particle_collision_shader();
+        #[cfg(not(target_os = "android"))]
+        light_cluster::generate_light_cluster_shader();
+        #[cfg(not(target_os = "android"))]
+        atmosphere::generate_atmosphere_shaders();
+        #[cfg(not(target_os = "android"))]
+        light_probe::generate_light_probe_shaders();
+        #[cfg(not(target_os = "android"))]
+        skybox::generate_skybox_shaders();
+        #[cfg(not(target_os = "android"))]
+        volumetric_fog::generate_volumetric_fog_shaders();
+        #[cfg(not(target_os = "android"))]
+        terrain::generate_terrain_shaders();
+        #[cfg(not(target_os = "android"))]
+        impostor::generate_impostor_shader();
+        #[cfg(not(target_os = "android"))]
+        material::generate_vertex_ambient_occlusion_shader();
+        #[cfg(not(target_os = "android"))]
+        material_instance::generate_material_override_shader();
+        #[cfg(not(target_os = "android"))]
+        debug_view::generate_debug_view_shader();
+
         let render_pass_data_create_infos = renderer_data.get_render_pass_data_create_infos();
+        renderer_data.rebuild_frame_graph(&render_pass_data_create_infos);
+        // the second render passes which are merged, the others are kept separated by merge_render_pass_data_create_infos
+        self._merged_render_pass_names = renderer_data.get_merged_render_pass_infos().iter().filter(|merged_render_pass_info| {
+            false == render_pass_data_create_infos.iter().any(|render_pass_data_create_info| render_pass_data_create_info._render_pass_create_info_name == merged_render_pass_info._second_render_pass_name)
+        }).map(|merged_render_pass_info| {
+            (merged_render_pass_info._second_render_pass_name.clone(), merged_render_pass_info._first_render_pass_name.clone())
+        }).collect();
         for render_pass_data_create_info in render_pass_data_create_infos.iter() {
             let descriptor_datas = render_pass_data_create_info._pipeline_data_create_infos
                 .iter()
                 .map(|pipeline_data_create_info| {
                     self.get_descriptor_data(renderer_data, &render_pass_data_create_info._render_pass_create_info_name, pipeline_data_create_info)
                 }).collect();
-            let default_render_pass_data = render_pass::create_render_pass_data(renderer_data.get_device(), render_pass_data_create_info, &descriptor_datas);
-            self._render_pass_data_map.insert(default_render_pass_data.get_render_pass_data_name().clone(), newRcRefCell(default_render_pass_data));
+            let default_render_pass_data = render_pass::create_render_pass_data(renderer_data.get_device(), render_pass_data_create_info, &descriptor_datas, self.get_bindless_descriptor_set_layout());
+            self._render_pass_data_map.insert(ResourceName::from(default_render_pass_data.get_render_pass_data_name().clone()), newRcRefCell(default_render_pass_data));
         }
     }
 
     pub fn unload_render_pass_datas(&mut self, renderer_data: &RendererData) {
+        for pipeline_data in self._pipeline_variant_data_map.values() {
+            render_pass::destroy_pipeline_data(renderer_data.get_device(), &pipeline_data.borrow());
+        }
+        self._pipeline_variant_data_map.clear();
         for render_pass_data in self._render_pass_data_map.values() {
             render_pass::destroy_render_pass_data(renderer_data.get_device(), &(*render_pass_data).borrow());
         }
         self._render_pass_data_map.clear()
     }
 
+    // the second render pass of a merged render pass is the merged render pass
+    pub fn get_merged_render_pass_name<'a>(&'a self, render_pass_name: &'a str) -> &'a str {
+        match self._merged_render_pass_names.get(render_pass_name) {
+            Some(merged_render_pass_name) => merged_render_pass_name.as_str(),
+            None => render_pass_name,
+        }
+    }
+
     pub fn has_render_pass_data(&self, resource_name: &str) -> bool {
-        self._render_pass_data_map.contains_key(resource_name)
+        self._render_pass_data_map.contains_key(&ResourceName::from(self.get_merged_render_pass_name(resource_name)))
     }
 
     pub fn get_render_pass_data(&self, resource_name: &str) -> &RcRefCell<RenderPassData> {
-        get_resource_data_must(&self._render_pass_data_map, resource_name)
+        get_resource_data_must(&self._render_pass_data_map, self.get_merged_render_pass_name(resource_name))
     }
 
     pub fn get_default_render_pass_data(&self) -> &RcRefCell<RenderPassData> {
@@ -853,14 +2108,113 @@ impl Resources {
         }
     }
 
+    // compiles a pipeline variant per unique hook set, blend mode and debug view, the variants are shared by the material instances.
+    // the blend mode applies only to the pipelines with _pipeline_blend_mode_variants, the others keep their blend state.
+    pub fn get_pipeline_variant_render_pass_pipeline_data(
+        &mut self,
+        renderer_data: &RendererData,
+        render_pass_pipeline_data: &RenderPassPipelineData,
+        shader_hook_set: &ShaderHookSet,
+        blend_mode: MaterialBlendMode,
+        debug_view_name: Option<&str>
+    ) -> RenderPassPipelineData {
+        let render_pass_data = render_pass_pipeline_data._render_pass_data.borrow();
+        let pipeline_data = render_pass_pipeline_data._pipeline_data.borrow();
+        if vk::PipelineBindPoint::GRAPHICS != pipeline_data._pipeline_bind_point {
+            return render_pass_pipeline_data.clone();
+        }
+
+        let render_pass_data_create_infos = renderer_data.get_render_pass_data_create_infos();
+        let render_pass_data_create_info = render_pass_data_create_infos.iter().find(|render_pass_data_create_info| {
+            render_pass_data_create_info._render_pass_create_info_name == render_pass_data._render_pass_data_name
+        }).unwrap();
+        let mut pipeline_data_create_info = render_pass_data_create_info._pipeline_data_create_infos.iter().find(|pipeline_data_create_info| {
+            pipeline_data_create_info._pipeline_data_create_info_name == pipeline_data._pipeline_data_name
+        }).unwrap().clone();
+        let use_blend_mode = blend_mode.is_translucent() && pipeline_data_create_info._pipeline_blend_mode_variants;
+        let use_debug_view = debug_view_name.is_some();
+        if shader_hook_set.is_empty() && false == use_blend_mode && false == use_debug_view {
+            return render_pass_pipeline_data.clone();
+        }
+
+        let mut variant_name = format!("{}/{}", render_pass_data._render_pass_data_name, pipeline_data._pipeline_data_name);
+        if false == shader_hook_set.is_empty() {
+            variant_name = format!("{}/{}", variant_name, shader_hook_set.get_hook_set_key());
+        }
+        if use_blend_mode {
+            variant_name = format!("{}/{}", variant_name, blend_mode.get_blend_mode_name());
+        }
+        if let Some(debug_view_name) = debug_view_name {
+            variant_name = format!("{}/{}", variant_name, debug_view_name);
+        }
+        if let Some(variant_pipeline_data) = self._pipeline_variant_data_map.get(&variant_name) {
+            return RenderPassPipelineData {
+                _render_pass_data: render_pass_pipeline_data._render_pass_data.clone(),
+                _pipeline_data: variant_pipeline_data.clone(),
+            };
+        }
+
+        if false == shader_hook_set.is_empty() {
+            let (shader_defines, include_directory) = shader_hook_set.prepare_shader_hooks();
+            if false == shader_defines.is_empty() {
+                pipeline_data_create_info._pipeline_shader_defines.extend(shader_defines);
+                pipeline_data_create_info._pipeline_shader_include_directories.push(include_directory);
+            } else if false == use_blend_mode && false == use_debug_view {
+                return render_pass_pipeline_data.clone();
+            }
+        }
+
+        if use_blend_mode {
+            let color_blend_mode = vulkan_context::get_color_blend_mode(blend_mode.get_blend_mode());
+            for pipeline_color_blend_mode in pipeline_data_create_info._pipeline_color_blend_modes.iter_mut() {
+                *pipeline_color_blend_mode = color_blend_mode;
+            }
+        }
+
+        match debug_view_name {
+            Some(debug_view::DEBUG_VIEW_WIREFRAME_NAME) => pipeline_data_create_info._pipeline_polygon_mode = vk::PolygonMode::LINE,
+            Some(debug_view::DEBUG_VIEW_VISUALIZE_NAME) => pipeline_data_create_info._pipeline_shader_defines.push(String::from(debug_view::DEBUG_VIEW_VISUALIZE_DEFINE)),
+            _ => (),
+        }
+
+        log::info!("create pipeline variant: {}", variant_name);
+        let variant_pipeline_data = match render_pass::create_graphics_pipeline_data(
+            renderer_data.get_device(),
+            render_pass_data._render_pass,
+            &pipeline_data_create_info,
+            render_pass_data_create_info.get_subpass_data_create_infos()[pipeline_data_create_info._pipeline_subpass_index as usize]._depth_attachment_index.is_some(),
+            render_pass_data_create_info._shading_rate_attachment_description.is_some(),
+            &pipeline_data._descriptor_data,
+            self.get_bindless_descriptor_set_layout()
+        ) {
+            Ok(variant_pipeline_data) => {
+                render_pass::set_pipeline_data_object_name(&variant_pipeline_data, &variant_name);
+                newRcRefCell(variant_pipeline_data)
+            },
+            Err(e) => {
+                // the material instance falls back to the pipeline without the hooks and the blend mode
+                self._hot_reload_state.report_reload_error(&format!("{} {}", variant_name, e));
+                return render_pass_pipeline_data.clone();
+            }
+        };
+        self._pipeline_variant_data_map.insert(variant_name, variant_pipeline_data.clone());
+        RenderPassPipelineData {
+            _render_pass_data: render_pass_pipeline_data._render_pass_data.clone(),
+            _pipeline_data: variant_pipeline_data,
+        }
+    }
+
     // Material_datas
     pub fn load_material_datas(&mut self, _renderer_data: &RendererData) {
         let material_directory = PathBuf::from(MATERIAL_FILE_PATH);
         let material_files = self.collect_resources(&material_directory.as_path(), &[EXT_MATERIAL]);
+        resource_name::check_resource_name_collisions("material", &material_directory, &material_files);
         for material_file in material_files {
             let material_name = get_unique_resource_name(&self._material_data_map, &material_directory, &material_file);
-            let loaded_contents = system::load(material_file);
-            let contents: Value = serde_json::from_reader(loaded_contents).expect("Failed to deserialize.");
+            let contents: Value = match self.load_definition_contents(&material_file, Resources::validate_material_create_info) {
+                Some(contents) => contents,
+                None => continue,
+            };
             let material_create_info = match contents {
                 Value::Object(material_create_info) => material_create_info,
                 _ => panic!("material parsing error"),
@@ -883,8 +2237,29 @@ impl Resources {
                 self.get_render_pass_pipeline_data(render_pass_data_name.as_str(), pipeline_data_name.as_str())
             }).collect();
             let material_data = MaterialData::create_material(&material_name, &render_pass_pipeline_datas, material_parameters);
-            self._material_data_map.insert(material_name.clone(), newRcRefCell(material_data));
+            self._material_data_map.insert(ResourceName::from(material_name.clone()), newRcRefCell(material_data));
+        }
+    }
+
+    fn validate_material_create_info(&self, contents: &Value) -> Result<(), String> {
+        let pipeline_create_infos = match contents.get("pipelines") {
+            Some(Value::Array(pipeline_create_infos)) => pipeline_create_infos,
+            _ => return Err(String::from("material parsing error: no pipelines")),
+        };
+        for pipeline_create_info in pipeline_create_infos.iter() {
+            let (render_pass_data_name, pipeline_data_name) = match (pipeline_create_info.get("render_pass"), pipeline_create_info.get("pipeline")) {
+                (Some(Value::String(render_pass_data_name)), Some(Value::String(pipeline_data_name))) => (render_pass_data_name, pipeline_data_name),
+                _ => return Err(String::from("failed to parsing render_pass or pipeline")),
+            };
+            let has_pipeline_data = match self._render_pass_data_map.get(&ResourceName::from(render_pass_data_name)) {
+                Some(render_pass_data) => render_pass_data.borrow()._pipeline_data_map.contains_key(pipeline_data_name),
+                None => false,
+            };
+            if false == has_pipeline_data {
+                return Err(format!("not found pipeline: {}/{}", render_pass_data_name, pipeline_data_name));
+            }
         }
+        Ok(())
     }
 
     pub fn unload_material_datas(&mut self, _renderer_data: &RendererData) {
@@ -895,7 +2270,7 @@ impl Resources {
     }
 
     pub fn has_material_data(&self, resource_name: &str) -> bool {
-        self._material_data_map.contains_key(resource_name)
+        self._material_data_map.contains_key(&ResourceName::from(resource_name))
     }
 
     pub fn get_material_data(&self, resource_name: &str) -> &RcRefCell<MaterialData> {
@@ -904,16 +2279,23 @@ impl Resources {
 
     // MaterialInstance_datas
     pub fn load_material_instance_datas(&mut self, renderer_data: &RendererData, is_reload: bool) {
+        // generate the example shader hooks
+        #[cfg(not(target_os = "android"))]
+        shader_hook::generate_shader_hooks();
+
         let material_instance_directory = PathBuf::from(MATERIAL_INSTANCE_FILE_PATH);
         let material_instance_files = self.collect_resources(&material_instance_directory, &[EXT_MATERIAL_INSTANCE]);
+        resource_name::check_resource_name_collisions("material instance", &material_instance_directory, &material_instance_files);
         for material_instance_file in material_instance_files.iter() {
             let material_instance_name = if is_reload {
                 get_resource_name_from_file_path(&material_instance_directory, &material_instance_file)
             } else {
                 get_unique_resource_name(&self._material_instance_data_map, &material_instance_directory, &material_instance_file)
             };
-            let loaded_contents = system::load(material_instance_file);
-            let contents: Value = serde_json::from_reader(loaded_contents).expect("Failed to deserialize.");
+            let contents: Value = match self.load_definition_contents(material_instance_file, Resources::validate_material_instance_create_info) {
+                Some(contents) => contents,
+                None => continue,
+            };
             let material_instance_create_info = match contents {
                 Value::Object(material_instance_create_info) => material_instance_create_info,
                 _ => panic!("material instance parsing error"),
@@ -926,9 +2308,48 @@ impl Resources {
                 Value::Object(material_parameter_map) => material_parameter_map,
                 _ => panic!("material parameters parsing error")
             };
+            let shader_hook_set = ShaderHookSet::create_shader_hook_set(material_instance_create_info.get("shader_hooks"));
+            let shader_hook_parameters = shader_hook::create_shader_hook_parameters(material_instance_create_info.get("shader_hook_parameters"));
+            let blend_mode = MaterialBlendMode::create_material_blend_mode(material_instance_create_info.get("blend_mode"));
+            if false == self.has_material_data(material_data_name.as_str()) {
+                let load_error = format!("material instance {}: material is not found: {}", material_instance_name, material_data_name);
+                if is_reload {
+                    self._hot_reload_state.report_reload_error(&load_error);
+                } else {
+                    log::error!("{}", load_error);
+                    self._load_errors.push(load_error);
+                }
+                continue;
+            }
             let material_data = self.get_material_data(material_data_name.as_str()).clone();
+            let texture_data_names = self.acquire_material_instance_textures(renderer_data, &material_data.borrow(), material_parameter_map);
+            let render_pass_pipeline_datas: Vec<RenderPassPipelineData> = material_data.borrow()._render_pass_pipeline_data_map.values().map(|render_pass_pipeline_data| {
+                self.get_pipeline_variant_render_pass_pipeline_data(renderer_data, render_pass_pipeline_data, &shader_hook_set, blend_mode, None)
+            }).collect();
+            // the debug view variants of the opaque pipelines, see RendererData::get_debug_view_pipeline_binding_data
+            let mut debug_view_pipeline_datas: Vec<(String, String, RenderPassPipelineData)> = Vec::new();
+            if unsafe { constants::ENABLE_DEBUG_VIEW_PIPELINES } {
+                let mut debug_view_names: Vec<&str> = vec![debug_view::DEBUG_VIEW_VISUALIZE_NAME];
+                if renderer_data.is_fill_mode_non_solid_supported() {
+                    debug_view_names.push(debug_view::DEBUG_VIEW_WIREFRAME_NAME);
+                }
+                let material_render_pass_pipeline_datas: Vec<RenderPassPipelineData> = material_data.borrow()._render_pass_pipeline_data_map.values().cloned().collect();
+                for render_pass_pipeline_data in material_render_pass_pipeline_datas.iter() {
+                    let render_pass_data_name = render_pass_pipeline_data._render_pass_data.borrow()._render_pass_data_name.clone();
+                    if false == debug_view::is_debug_view_render_pass(&render_pass_data_name) {
+                        continue;
+                    }
+                    let render_pass_pipeline_data_name = format!("{}/{}", render_pass_data_name, render_pass_pipeline_data._pipeline_data.borrow()._pipeline_data_name);
+                    for debug_view_name in debug_view_names.iter() {
+                        let debug_view_pipeline_data = self.get_pipeline_variant_render_pass_pipeline_data(renderer_data, render_pass_pipeline_data, &shader_hook_set, blend_mode, Some(*debug_view_name));
+                        let debug_view_pipeline_data_name = debug_view::get_debug_view_render_pass_pipeline_data_name(&render_pass_pipeline_data_name, debug_view_name);
+                        debug_view_pipeline_datas.push((render_pass_pipeline_data_name.clone(), debug_view_pipeline_data_name, debug_view_pipeline_data));
+                    }
+                }
+            }
             let default_material_parameter_map = &material_data.borrow()._material_parameter_map;
-            let pipeline_bind_create_infos = material_data.borrow()._render_pass_pipeline_data_map.iter().map(|(_key, render_pass_pipeline_data)| {
+            let bindless_material_data = self.get_bindless_material_data(material_parameter_map, default_material_parameter_map);
+            let pipeline_bind_create_infos = render_pass_pipeline_datas.iter().map(|render_pass_pipeline_data| {
                 let descriptor_data_create_infos = &render_pass_pipeline_data._pipeline_data.borrow()._descriptor_data._descriptor_data_create_infos;
                 let descriptor_resource_infos_list = constants::SWAPCHAIN_IMAGE_INDICES.iter().map(|swapchain_index| {
                     let descriptor_resource_infos = descriptor_data_create_infos.iter().map(|descriptor_data_create_info| {
@@ -968,6 +2389,14 @@ impl Resources {
                                     DescriptorResourceInfo::DescriptorImageInfo(texture_data.get_default_image_info())
                                 }
                             },
+                            DescriptorResourceType::InputAttachment => {
+                                // the same single mip view as the framebuffer attachment
+                                let texture_data = renderer_data.get_render_target_from_str(material_parameter_name.as_str());
+                                let mut image_info = texture_data.get_sub_image_info(0, 0);
+                                image_info.sampler = vk::Sampler::null();
+                                image_info.image_layout = render_pass::get_input_attachment_layout(texture_data._image_format);
+                                DescriptorResourceInfo::DescriptorImageInfo(image_info)
+                            },
                         };
                         return descriptor_resource_info;
                     }).filter(|descriptor_resource_info| match *descriptor_resource_info {
@@ -979,26 +2408,76 @@ impl Resources {
                 return (render_pass_pipeline_data.clone(), descriptor_resource_infos_list);
             }).collect();
 
-            let material_instance_data = MaterialInstanceData::create_material_instance(
+            let mut material_instance_data = MaterialInstanceData::create_material_instance(
                 renderer_data.get_device(),
                 &material_instance_name,
                 material_data.clone(),
-                pipeline_bind_create_infos
+                pipeline_bind_create_infos,
+                shader_hook_set,
+                shader_hook_parameters,
+                blend_mode,
+                bindless_material_data,
+                material_instance_file,
+                material_instance_create_info.clone(),
+                texture_data_names,
             );
+            for (render_pass_pipeline_data_name, debug_view_pipeline_data_name, debug_view_pipeline_data) in debug_view_pipeline_datas.into_iter() {
+                material_instance_data.add_pipeline_binding_variant(&render_pass_pipeline_data_name, &debug_view_pipeline_data_name, debug_view_pipeline_data);
+            }
 
             if is_reload && self.has_material_instance_data(&material_instance_name) {
                 // replace material_instance_data
                 let exists_material_instance_data: &mut MaterialInstanceData = &mut self.get_material_instance_data(&material_instance_name).borrow_mut();
                 *exists_material_instance_data = material_instance_data;
             } else {
-                self._material_instance_data_map.insert(material_instance_name.clone(), newRcRefCell(material_instance_data));
+                self._material_instance_data_map.insert(ResourceName::from(material_instance_name.clone()), newRcRefCell(material_instance_data));
             }
+            self._resource_ref_counter.register_reloadable_resource(ResourceRefType::MaterialInstance, &material_instance_name);
+        }
+    }
+
+    // the missing textures are bound as DEFAULT_TEXTURE_NAME, see get_texture_data
+    pub fn acquire_material_instance_textures(&mut self, renderer_data: &RendererData, material_data: &MaterialData, material_parameter_map: &serde_json::Map<String, Value>) -> Vec<String> {
+        let mut texture_data_names: Vec<String> = material_instance::get_texture_data_names(material_data, material_parameter_map).into_iter().map(|texture_data_name| {
+            if self.has_texture_data(&texture_data_name) { texture_data_name } else { String::from(DEFAULT_TEXTURE_NAME) }
+        }).collect();
+        texture_data_names.dedup();
+        for texture_data_name in texture_data_names.iter() {
+            self.acquire_texture_data(renderer_data, texture_data_name);
+        }
+        texture_data_names
+    }
+
+    // the textures are acquired again, the descriptor sets of the material instance are kept.
+    pub fn reload_material_instance_data(&mut self, renderer_data: &RendererData, material_instance_name: &str) {
+        let material_instance_data = self.get_material_instance_data(material_instance_name).clone();
+        let material_data = material_instance_data.borrow()._material_data.clone();
+        let material_parameter_map = material_instance_data.borrow().get_material_parameter_map();
+        let texture_data_names = self.acquire_material_instance_textures(renderer_data, &material_data.borrow(), &material_parameter_map);
+        let mut material_instance_data = material_instance_data.borrow_mut();
+        material_instance_data._texture_data_names = texture_data_names;
+        material_instance_data.update_texture_descriptors(renderer_data.get_device(), self);
+        material_instance_data._bindless_material_data = self.get_bindless_material_data(&material_parameter_map, &material_data.borrow()._material_parameter_map);
+        self._resource_ref_counter.register_reloadable_resource(ResourceRefType::MaterialInstance, material_instance_name);
+        log::info!("reload_material_instance_data: {}", material_instance_name);
+    }
+
+    fn validate_material_instance_create_info(&self, contents: &Value) -> Result<(), String> {
+        match contents.get("material_name") {
+            Some(Value::String(material_data_name)) if self._material_data_map.contains_key(&ResourceName::from(material_data_name)) => (),
+            Some(Value::String(material_data_name)) => return Err(format!("not found material: {}", material_data_name)),
+            _ => return Err(String::from("material name parsing error")),
+        }
+        match contents.get("material_parameters") {
+            Some(Value::Object(_)) => Ok(()),
+            _ => Err(String::from("material parameters parsing error")),
         }
     }
 
     pub fn unload_material_instance_datas(&mut self, _renderer_data: &RendererData, is_reload: bool) {
-        for material_instance_data in self._material_instance_data_map.values() {
-            (*material_instance_data).borrow().destroy_material_instance();
+        let material_instance_datas: Vec<RcRefCell<MaterialInstanceData>> = self._material_instance_data_map.values().cloned().collect();
+        for material_instance_data in material_instance_datas.iter() {
+            material_instance_data.borrow_mut().destroy_material_instance(self);
         }
 
         if false == is_reload {
@@ -1007,13 +2486,65 @@ impl Resources {
     }
 
     pub fn has_material_instance_data(&self, resource_name: &str) -> bool {
-        self._material_instance_data_map.contains_key(resource_name)
+        self._material_instance_data_map.contains_key(&ResourceName::from(resource_name))
     }
 
     pub fn get_material_instance_data(&self, resource_name: &str) -> &RcRefCell<MaterialInstanceData> {
         get_resource_data_must(&self._material_instance_data_map, resource_name)
     }
 
+    // writes the runtime edits of the material editor to the material instance file of the writable layer of the virtual file system.
+    pub fn save_material_instance_data(&self, material_instance_name: &str) -> Result<(), String> {
+        if false == self.has_material_instance_data(material_instance_name) {
+            return Err(format!("save_material_instance_data: material instance is not found: {}", material_instance_name));
+        }
+        let material_instance_data = self.get_material_instance_data(material_instance_name).borrow();
+        let material_instance_file_path = self._virtual_file_system.borrow().get_writable_path(&material_instance_data._material_instance_file_path);
+        if let Some(material_instance_directory) = material_instance_file_path.parent() {
+            fs::create_dir_all(material_instance_directory).map_err(|e| format!("{:?}: {}", material_instance_directory, e))?;
+        }
+        fs::write(&material_instance_file_path, material_instance_data.serialize_overrides()).map_err(|e| format!("{:?}: {}", material_instance_file_path, e))?;
+        log::info!("save_material_instance_data: {:?}", material_instance_file_path);
+        Ok(())
+    }
+
+    // the material editor, see MaterialInstanceData::set_texture_binding
+    pub fn set_material_instance_texture_binding(&mut self, renderer_data: &RendererData, material_instance_name: &str, binding_name: &str, texture_name: &str) -> Result<(), String> {
+        if false == self.has_material_instance_data(material_instance_name) {
+            return Err(format!("set_material_instance_texture_binding: material instance is not found: {}", material_instance_name));
+        }
+        let material_instance_data = self.get_material_instance_data(material_instance_name).clone();
+        material_instance_data.borrow_mut().set_texture_binding(renderer_data, self, binding_name, texture_name)?;
+        if false == self._pending_descriptor_update_material_instances.iter().any(|pending_material_instance_name| pending_material_instance_name == material_instance_name) {
+            self._pending_descriptor_update_material_instances.push(String::from(material_instance_name));
+        }
+        Ok(())
+    }
+
+    // the texture bindings of the material editor, called before the command buffer of the swapchain image is recorded
+    pub fn apply_pending_descriptor_updates(&mut self, device: &Device, swapchain_index: usize) {
+        if self._pending_descriptor_update_material_instances.is_empty() {
+            return;
+        }
+        let pending_material_instance_names = std::mem::replace(&mut self._pending_descriptor_update_material_instances, Vec::new());
+        for material_instance_name in pending_material_instance_names.into_iter() {
+            // the reloaded material instances have written all their descriptors
+            if false == self.has_material_instance_data(&material_instance_name) {
+                continue;
+            }
+            let material_instance_data = self.get_material_instance_data(&material_instance_name).clone();
+            material_instance_data.borrow_mut().apply_pending_descriptor_updates(device, swapchain_index);
+            if material_instance_data.borrow().has_pending_descriptor_updates() {
+                self._pending_descriptor_update_material_instances.push(material_instance_name.clone());
+            }
+        }
+    }
+
+    pub fn regist_material_instance_data(&mut self, material_instance_name: String, material_instance_data: RcRefCell<MaterialInstanceData>) {
+        self._resource_ref_counter.register_resource(ResourceRefType::MaterialInstance, &material_instance_name);
+        self._material_instance_data_map.insert(ResourceName::from(material_instance_name), material_instance_data);
+    }
+
     // Descriptor_datas
     pub fn get_descriptor_data(
         &mut self,
@@ -1024,23 +2555,102 @@ impl Resources {
         let descriptor_name: String = format!("{}{}", render_pass_name, pipeline_data_create_info._pipeline_data_create_info_name);
         let descriptor_data_create_infos = &pipeline_data_create_info._descriptor_data_create_infos;
         let max_descriptor_pool_count: u32 = unsafe { (constants::MAX_DESCRIPTOR_POOL_ALLOC_COUNT * constants::SWAPCHAIN_IMAGE_COUNT) as u32 };
-        let maybe_descriptor_data = self._descriptor_data_map.get(&descriptor_name);
+        let maybe_descriptor_data = self._descriptor_data_map.get(&ResourceName::from(&descriptor_name));
         match maybe_descriptor_data {
             Some(descriptor_data) => descriptor_data.clone(),
             None => {
                 let descriptor_data = newRcRefCell(
                     descriptor::create_descriptor_data(renderer_data.get_device(), descriptor_data_create_infos, max_descriptor_pool_count)
                 );
-                self._descriptor_data_map.insert(descriptor_name, descriptor_data.clone());
+                self._descriptor_data_map.insert(ResourceName::from(descriptor_name), descriptor_data.clone());
                 descriptor_data
             }
         }
     }
 
+    // the descriptor sets allocated from these pools are freed with them, the engine passes only clear their descriptor set handles.
     pub fn unload_descriptor_datas(&mut self, renderer_data: &RendererData) {
         for descriptor_data in self._descriptor_data_map.values() {
             descriptor::destroy_descriptor_data(renderer_data.get_device(), &(*descriptor_data).borrow());
         }
         self._descriptor_data_map.clear();
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use crate::application::application;
+    use crate::resource::virtual_file_system::MountPoint;
+
+    fn write_test_file(file_path: &Path, contents: &str) {
+        fs::create_dir_all(file_path.parent().unwrap()).unwrap();
+        fs::write(file_path, contents).unwrap();
+    }
+
+    // the resource directory of the test is the only layer, the missing files don't fall back to the working directory.
+    fn create_test_virtual_file_system(resource_root_path: &Path) -> VirtualFileSystem {
+        let mut virtual_file_system = VirtualFileSystem::create_virtual_file_system();
+        virtual_file_system._mount_points = vec![MountPoint {
+            _layer_name: String::from("test"),
+            _root_path: PathBuf::from(resource_root_path),
+        }];
+        virtual_file_system
+    }
+
+    // the startup of the engine without the gpu: the steps of initialize_resources and the error screen of the degraded mode
+    fn initialize_test_resources(resource_root_path: &Path) -> ResourceInitializeResult {
+        let mut initialize_result = ResourceInitializeResult::default();
+        check_required_material_instance_files(&create_test_virtual_file_system(resource_root_path), &mut initialize_result);
+        initialize_result
+    }
+
+    #[test]
+    fn test_required_material_is_missing() {
+        let resource_root_path = std::env::temp_dir().join(format!("resources_required_material_{}", std::process::id()));
+        let _result = fs::remove_dir_all(&resource_root_path);
+        for material_instance_name in REQUIRED_MATERIAL_INSTANCE_NAMES.iter() {
+            let material_instance_file = get_resource_file_path(&PathBuf::from(MATERIAL_INSTANCE_FILE_PATH), &String::from(*material_instance_name), EXT_MATERIAL_INSTANCE);
+            let material_file = get_resource_file_path(&PathBuf::from(MATERIAL_FILE_PATH), &String::from(*material_instance_name), EXT_MATERIAL);
+            write_test_file(&resource_root_path.join(material_instance_file), &format!(r#"{{ "material_name": "{}", "material_parameters": {{}} }}"#, material_instance_name));
+            write_test_file(&resource_root_path.join(material_file), r#"{ "pipelines": [] }"#);
+        }
+        assert!(initialize_test_resources(&resource_root_path).is_ok());
+
+        // the required material is deleted, the engine reaches the error screen instead of the panic
+        let material_file = get_resource_file_path(&PathBuf::from(MATERIAL_FILE_PATH), &String::from(REQUIRED_MATERIAL_INSTANCE_NAMES[0]), EXT_MATERIAL);
+        fs::remove_file(resource_root_path.join(&material_file)).unwrap();
+        let initialize_result = initialize_test_resources(&resource_root_path);
+        assert_eq!(1, initialize_result._errors.len());
+        assert!(initialize_result._errors[0].starts_with(&format!("required material instance {}: material {}: file is missing", REQUIRED_MATERIAL_INSTANCE_NAMES[0], REQUIRED_MATERIAL_INSTANCE_NAMES[0])));
+        let error_screen_text = application::get_error_screen_text(&initialize_result._errors);
+        assert!(error_screen_text.contains(&initialize_result._errors[0]));
+
+        // a broken and a deleted material instance are listed together
+        let material_instance_file = get_resource_file_path(&PathBuf::from(MATERIAL_INSTANCE_FILE_PATH), &String::from(REQUIRED_MATERIAL_INSTANCE_NAMES[1]), EXT_MATERIAL_INSTANCE);
+        write_test_file(&resource_root_path.join(&material_instance_file), r#"{ "material_name": "#);
+        let initialize_result = initialize_test_resources(&resource_root_path);
+        assert_eq!(2, initialize_result._errors.len());
+        fs::remove_file(resource_root_path.join(&material_instance_file)).unwrap();
+        let initialize_result = initialize_test_resources(&resource_root_path);
+        assert_eq!(2, initialize_result._errors.len());
+        assert!(initialize_result._errors[1].starts_with(&format!("required material instance {}: file is missing", REQUIRED_MATERIAL_INSTANCE_NAMES[1])));
+
+        let _result = fs::remove_dir_all(&resource_root_path);
+    }
+
+    #[test]
+    fn test_initialize_steps() {
+        let mut initialize_result = ResourceInitializeResult::default();
+        let mut step_count = 0;
+        assert!(initialize_result.run_step("textures", || step_count += 1));
+        assert!(initialize_result.is_ok());
+        // the panic of a step is recorded, the following steps still run
+        assert!(false == initialize_result.run_step("materials", || panic!("not found pipeline: render_font/render_font")));
+        assert!(initialize_result.run_step("material instances", || step_count += 1));
+        assert_eq!(2, step_count);
+        assert!(false == initialize_result.is_ok());
+        assert_eq!(vec![String::from("materials: not found pipeline: render_font/render_font")], initialize_result._errors);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,220 @@
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+
+use serde::{ Serialize, Deserialize, Deserializer };
+
+use crate::utilities::system;
+
+// the extensions of the resource and the source files, stripped from the last component of the names.
+// the other dots are kept, ex) "lod.0" is not an extension.
+pub const RESOURCE_NAME_EXTENSIONS: [&str; 23] = [
+    "font", "ttf",
+    "mesh", "obj", "dae", "gltf", "glb",
+    "model", "mat", "matinst", "asm", "json",
+    "texture", "cube", "2darray", "3d", "jpg", "jpeg", "png", "tga", "bmp", "dds", "ies",
+];
+
+// the key of the resource maps, the same name on every platform.
+// ex) "Textures\\UI\\Button.PNG", "./textures/ui/button" and "textures/ui/button" are "textures/ui/button"
+//     - the separators are '/' without the duplicates, the leading "./" and '/'
+//     - ".." removes the previous component, the name never goes above the resource root. ex) "ui/../button" is "button"
+//     - lowercase, the content authored on a case insensitive file system works on linux and android
+//     - the known extension of the last component is stripped, see RESOURCE_NAME_EXTENSIONS
+#[derive(Serialize, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct ResourceName(String);
+
+pub fn normalize_resource_name(resource_name: &str) -> String {
+    let resource_name = resource_name.trim().replace('\\', "/").to_lowercase();
+    let mut components: Vec<&str> = Vec::new();
+    for component in resource_name.split('/') {
+        if ".." == component {
+            components.pop();
+        } else if false == component.is_empty() && "." != component {
+            components.push(component);
+        }
+    }
+    let mut last_component = String::new();
+    if let Some(component) = components.pop() {
+        last_component = String::from(component);
+        if let Some(ext_index) = component.rfind('.') {
+            if 0 < ext_index && RESOURCE_NAME_EXTENSIONS.contains(&&component[(ext_index + 1)..]) {
+                last_component = String::from(&component[..ext_index]);
+            }
+        }
+    }
+    let mut normalized_name = components.join("/");
+    if false == normalized_name.is_empty() {
+        normalized_name.push('/');
+    }
+    normalized_name.push_str(&last_component);
+    normalized_name
+}
+
+impl ResourceName {
+    pub fn new(resource_name: &str) -> ResourceName {
+        ResourceName(normalize_resource_name(resource_name))
+    }
+
+    // the relative path from the resource root, without the extension
+    pub fn from_file_path(resource_root_path: &PathBuf, resource_file_path: &PathBuf) -> ResourceName {
+        let mut resource_name = PathBuf::from(resource_file_path.parent().unwrap());
+        resource_name.push(resource_file_path.file_stem().unwrap());
+        ResourceName::new(system::get_relative_path(resource_root_path, &resource_name).to_str().unwrap())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<&str> for ResourceName {
+    fn from(resource_name: &str) -> ResourceName { ResourceName::new(resource_name) }
+}
+
+impl From<&String> for ResourceName {
+    fn from(resource_name: &String) -> ResourceName { ResourceName::new(resource_name) }
+}
+
+impl From<String> for ResourceName {
+    fn from(resource_name: String) -> ResourceName { ResourceName::new(&resource_name) }
+}
+
+impl From<&ResourceName> for ResourceName {
+    fn from(resource_name: &ResourceName) -> ResourceName { resource_name.clone() }
+}
+
+impl From<ResourceName> for String {
+    fn from(resource_name: ResourceName) -> String { resource_name.0 }
+}
+
+// the maps can be looked up with the normalized &str, the get_*_data apis normalize the argument first.
+impl Borrow<str> for ResourceName {
+    fn borrow(&self) -> &str { &self.0 }
+}
+
+impl AsRef<str> for ResourceName {
+    fn as_ref(&self) -> &str { &self.0 }
+}
+
+impl fmt::Display for ResourceName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+// the names in the scene and the material files are normalized when they are read
+impl<'de> Deserialize<'de> for ResourceName {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<ResourceName, D::Error> {
+        let resource_name = String::deserialize(deserializer)?;
+        Ok(ResourceName::new(&resource_name))
+    }
+}
+
+// two files of the same resource type normalized to the same name, ex) "UI/Button.png" and "ui/button.tga"
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResourceNameCollision {
+    pub _resource_name: ResourceName,
+    pub _first_file_path: PathBuf,
+    pub _second_file_path: PathBuf,
+}
+
+pub fn find_resource_name_collisions(resource_root_path: &PathBuf, resource_file_paths: &[PathBuf]) -> Vec<ResourceNameCollision> {
+    let mut resource_file_map: HashMap<ResourceName, &PathBuf> = HashMap::new();
+    let mut collisions: Vec<ResourceNameCollision> = Vec::new();
+    for resource_file_path in resource_file_paths.iter() {
+        let resource_name = ResourceName::from_file_path(resource_root_path, resource_file_path);
+        match resource_file_map.get(&resource_name) {
+            Some(first_file_path) => collisions.push(ResourceNameCollision {
+                _resource_name: resource_name,
+                _first_file_path: (*first_file_path).clone(),
+                _second_file_path: resource_file_path.clone(),
+            }),
+            None => { resource_file_map.insert(resource_name, resource_file_path); },
+        }
+    }
+    collisions
+}
+
+// logged at the load, the second file is loaded with a unique name as before.
+pub fn check_resource_name_collisions(resource_type_name: &str, resource_root_path: &PathBuf, resource_file_paths: &[PathBuf]) -> bool {
+    let collisions = find_resource_name_collisions(resource_root_path, resource_file_paths);
+    for collision in collisions.iter() {
+        log::error!(
+            "{} name collision: {:?} and {:?} are both \"{}\", rename one of them",
+            resource_type_name,
+            collision._first_file_path,
+            collision._second_file_path,
+            collision._resource_name
+        );
+    }
+    collisions.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_separators() {
+        assert_eq!("textures/ui/button", normalize_resource_name("textures\\ui\\button"));
+        assert_eq!("textures/ui/button", normalize_resource_name("./textures//ui/button"));
+        assert_eq!("textures/ui/button", normalize_resource_name("/textures/./ui/button/"));
+        assert_eq!("textures/ui/button", normalize_resource_name(" .\\textures\\ui/button.png "));
+        assert_eq!("", normalize_resource_name(""));
+        assert_eq!("", normalize_resource_name("./"));
+    }
+
+    #[test]
+    fn test_normalize_parent_components() {
+        assert_eq!("textures/button", normalize_resource_name("textures/ui/../button"));
+        assert_eq!("button", normalize_resource_name("textures/ui/../../button"));
+        assert_eq!("button", normalize_resource_name("../../button"));
+        assert_eq!("textures/ui", normalize_resource_name("textures/ui/button/.."));
+        assert_eq!(ResourceName::from("textures/ui/button"), ResourceName::from("Textures\\Effects\\..\\UI\\Button.PNG"));
+    }
+
+    #[test]
+    fn test_normalize_case() {
+        assert_eq!("textures/ui/button", normalize_resource_name("Textures/UI/Button"));
+        assert_eq!(ResourceName::new("fft_ocean/spectrum_1_2"), ResourceName::from("FFT_Ocean/Spectrum_1_2"));
+        assert_eq!("textures/ui/button", ResourceName::from(String::from("TEXTURES/UI/BUTTON.PNG")).as_str());
+    }
+
+    #[test]
+    fn test_normalize_extension() {
+        assert_eq!("textures/ui/button", normalize_resource_name("textures/ui/button.png"));
+        assert_eq!("textures/ui/button", normalize_resource_name("textures/ui/button.PNG"));
+        assert_eq!("materials/ocean", normalize_resource_name("materials/ocean.matinst"));
+        // only the known extension of the last component
+        assert_eq!("meshes/rock.lod.0", normalize_resource_name("meshes/rock.lod.0"));
+        assert_eq!("meshes/rock.lod", normalize_resource_name("meshes/rock.lod.mesh"));
+        assert_eq!("textures.png/button", normalize_resource_name("textures.png/button"));
+        assert_eq!("textures/button.txt", normalize_resource_name("textures/button.txt"));
+        // a dot file is not an extension
+        assert_eq!("textures/.png", normalize_resource_name("textures/.png"));
+    }
+
+    #[test]
+    fn test_resource_name_collisions() {
+        let resource_root_path = PathBuf::from("resources/textures");
+        let resource_file_paths = vec![
+            PathBuf::from("resources/textures/UI/Button.png"),
+            PathBuf::from("resources/textures/ui/checkbox.png"),
+            PathBuf::from("resources/textures/ui/button.tga"),
+        ];
+        let collisions = find_resource_name_collisions(&resource_root_path, &resource_file_paths);
+        assert_eq!(vec![ResourceNameCollision {
+            _resource_name: ResourceName::from("ui/button"),
+            _first_file_path: resource_file_paths[0].clone(),
+            _second_file_path: resource_file_paths[2].clone(),
+        }], collisions);
+        assert!(false == check_resource_name_collisions("texture", &resource_root_path, &resource_file_paths));
+        assert!(check_resource_name_collisions("texture", &resource_root_path, &resource_file_paths[..2]));
+    }
+}
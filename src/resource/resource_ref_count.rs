@@ -0,0 +1,289 @@
+use std::collections::HashMap;
+
+use crate::constants;
+use crate::resource::resource_name::ResourceName;
+
+// the command buffers of the swapchain images in flight can still use a released resource,
+// so it is destroyed after its reference count has been zero for these frames.
+pub const GARBAGE_COLLECT_DELAY_FRAMES: u32 = constants::SWAPCHAIN_IMAGE_COUNT as u32 + 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ResourceRefType {
+    Mesh,
+    Texture,
+    MaterialInstance,
+    Framebuffer,
+}
+
+pub const RESOURCE_REF_TYPES: [ResourceRefType; 4] = [
+    ResourceRefType::Mesh,
+    ResourceRefType::Texture,
+    ResourceRefType::MaterialInstance,
+    ResourceRefType::Framebuffer,
+];
+
+#[derive(Clone, Debug, Default)]
+pub struct ResourceRefCount {
+    pub _ref_count: u32,
+    pub _zero_ref_frames: u32,
+    pub _is_resident: bool, // the core resources of the initial load, never collected
+    pub _is_released: bool, // the count has dropped to zero, a registered resource is not collected before its first acquire
+    pub _is_reloadable: bool, // loaded from its files, the collection unloads it in place and the next acquire loads it again
+    pub _is_unloaded: bool,
+}
+
+// the live resources are referenced, the zombies wait for the garbage collection
+#[derive(Clone, Debug, Default)]
+pub struct ResourceRefStats {
+    pub _live_count: usize,
+    pub _zombie_count: usize,
+    pub _loaded_count: usize, // the footprint, the unloaded resources keep only their entries
+}
+
+// the core resources of the initial load stay resident, ex) the generated textures and the quad mesh.
+// the contents of the resource directories are reloadable, a scene unload drops them and the next scene loads them again.
+// the resources registered later, ex) the meshes and the textures of the project, are removed by Resources::garbage_collect.
+#[derive(Clone, Debug, Default)]
+pub struct ResourceRefCounter {
+    pub _ref_count_maps: HashMap<ResourceRefType, HashMap<ResourceName, ResourceRefCount>>,
+    pub _is_resident_loading: bool,
+}
+
+impl ResourceRefCounter {
+    pub fn set_resident_loading(&mut self, is_resident_loading: bool) {
+        self._is_resident_loading = is_resident_loading;
+    }
+
+    // the registration of a reloaded resource keeps the entry
+    pub fn register_resource(&mut self, ref_type: ResourceRefType, resource_name: &str) {
+        let is_resident = self._is_resident_loading;
+        self._ref_count_maps.entry(ref_type).or_insert_with(HashMap::new).entry(ResourceName::from(resource_name)).or_insert_with(|| {
+            ResourceRefCount {
+                _is_resident: is_resident,
+                ..Default::default()
+            }
+        });
+    }
+
+    // the contents of the resource directories, the registration of a reloaded resource marks it loaded again
+    pub fn register_reloadable_resource(&mut self, ref_type: ResourceRefType, resource_name: &str) {
+        let ref_count = self._ref_count_maps.entry(ref_type).or_insert_with(HashMap::new).entry(ResourceName::from(resource_name)).or_insert_with(|| {
+            ResourceRefCount {
+                _is_reloadable: true,
+                ..Default::default()
+            }
+        });
+        ref_count._is_unloaded = false;
+        ref_count._zero_ref_frames = 0;
+    }
+
+    // the dependents of an unloaded resource, ex) the lod meshes of the base mesh
+    pub fn unload_resource(&mut self, ref_type: ResourceRefType, resource_name: &str) {
+        if let Some(ref_count) = self._ref_count_maps.get_mut(&ref_type).and_then(|ref_count_map| ref_count_map.get_mut(&ResourceName::from(resource_name))) {
+            ref_count._is_unloaded = true;
+            ref_count._zero_ref_frames = 0;
+        }
+    }
+
+    pub fn is_resource_unloaded(&self, ref_type: ResourceRefType, resource_name: &str) -> bool {
+        self._ref_count_maps.get(&ref_type)
+            .and_then(|ref_count_map| ref_count_map.get(&ResourceName::from(resource_name)))
+            .map_or(false, |ref_count| ref_count._is_unloaded)
+    }
+
+    pub fn unregister_resource(&mut self, ref_type: ResourceRefType, resource_name: &str) {
+        if let Some(ref_count_map) = self._ref_count_maps.get_mut(&ref_type) {
+            ref_count_map.remove(&ResourceName::from(resource_name));
+        }
+    }
+
+    // the resources loaded without the registration are resident
+    pub fn acquire_resource(&mut self, ref_type: ResourceRefType, resource_name: &str) -> u32 {
+        let ref_count = self._ref_count_maps.entry(ref_type).or_insert_with(HashMap::new).entry(ResourceName::from(resource_name)).or_insert_with(|| {
+            ResourceRefCount {
+                _is_resident: true,
+                ..Default::default()
+            }
+        });
+        ref_count._ref_count += 1;
+        ref_count._zero_ref_frames = 0;
+        ref_count._ref_count
+    }
+
+    pub fn release_resource(&mut self, ref_type: ResourceRefType, resource_name: &str) -> u32 {
+        match self._ref_count_maps.get_mut(&ref_type).and_then(|ref_count_map| ref_count_map.get_mut(&ResourceName::from(resource_name))) {
+            Some(ref_count) if 0 < ref_count._ref_count => {
+                ref_count._ref_count -= 1;
+                if 0 == ref_count._ref_count {
+                    ref_count._zero_ref_frames = 0;
+                    ref_count._is_released = true;
+                }
+                ref_count._ref_count
+            },
+            _ => {
+                log::warn!("release_resource: {:?} {} is not acquired", ref_type, resource_name);
+                0
+            }
+        }
+    }
+
+    pub fn get_ref_count(&self, ref_type: ResourceRefType, resource_name: &str) -> u32 {
+        self._ref_count_maps.get(&ref_type)
+            .and_then(|ref_count_map| ref_count_map.get(&ResourceName::from(resource_name)))
+            .map_or(0, |ref_count| ref_count._ref_count)
+    }
+
+    fn is_zombie(ref_count: &ResourceRefCount) -> bool {
+        false == ref_count._is_resident && false == ref_count._is_unloaded && ref_count._is_released && 0 == ref_count._ref_count
+    }
+
+    // once per frame, the names of the resources to destroy.
+    // the entries of the reloadable resources are kept as unloaded, the others are removed.
+    pub fn update_resource_ref_counter(&mut self) -> Vec<(ResourceRefType, String)> {
+        let mut garbage_resources: Vec<(ResourceRefType, String)> = Vec::new();
+        for (ref_type, ref_count_map) in self._ref_count_maps.iter_mut() {
+            for (resource_name, ref_count) in ref_count_map.iter_mut() {
+                if ResourceRefCounter::is_zombie(ref_count) {
+                    ref_count._zero_ref_frames += 1;
+                    if GARBAGE_COLLECT_DELAY_FRAMES <= ref_count._zero_ref_frames {
+                        garbage_resources.push((*ref_type, String::from(resource_name.as_str())));
+                    }
+                }
+            }
+        }
+        for (ref_type, resource_name) in garbage_resources.iter() {
+            let ref_count_map = self._ref_count_maps.get_mut(ref_type).unwrap();
+            let resource_key = ResourceName::from(resource_name.as_str());
+            if ref_count_map.get(&resource_key).unwrap()._is_reloadable {
+                let ref_count = ref_count_map.get_mut(&resource_key).unwrap();
+                ref_count._is_unloaded = true;
+                ref_count._zero_ref_frames = 0;
+            } else {
+                ref_count_map.remove(&resource_key);
+            }
+        }
+        garbage_resources
+    }
+
+    pub fn get_resource_ref_stats(&self, ref_type: ResourceRefType) -> ResourceRefStats {
+        let mut resource_ref_stats = ResourceRefStats::default();
+        if let Some(ref_count_map) = self._ref_count_maps.get(&ref_type) {
+            for ref_count in ref_count_map.values() {
+                if 0 < ref_count._ref_count {
+                    resource_ref_stats._live_count += 1;
+                } else if ResourceRefCounter::is_zombie(ref_count) {
+                    resource_ref_stats._zombie_count += 1;
+                }
+                if false == ref_count._is_unloaded {
+                    resource_ref_stats._loaded_count += 1;
+                }
+            }
+        }
+        resource_ref_stats
+    }
+
+    // ex) "resource refs: mesh 12 / 1 / 40, texture 30 / 0 / 95, material instance 8 / 0 / 20, framebuffer 2 / 0 / 2 (live / zombie / loaded)"
+    pub fn get_stats_text(&self) -> String {
+        let stats_texts: Vec<String> = RESOURCE_REF_TYPES.iter().map(|ref_type| {
+            let resource_ref_stats = self.get_resource_ref_stats(*ref_type);
+            let ref_type_name = match ref_type {
+                ResourceRefType::Mesh => "mesh",
+                ResourceRefType::Texture => "texture",
+                ResourceRefType::MaterialInstance => "material instance",
+                ResourceRefType::Framebuffer => "framebuffer",
+            };
+            format!("{} {} / {} / {}", ref_type_name, resource_ref_stats._live_count, resource_ref_stats._zombie_count, resource_ref_stats._loaded_count)
+        }).collect();
+        format!("resource refs: {} (live / zombie / loaded)", stats_texts.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update_frames(resource_ref_counter: &mut ResourceRefCounter, frames: u32) -> Vec<(ResourceRefType, String)> {
+        let mut garbage_resources: Vec<(ResourceRefType, String)> = Vec::new();
+        for _ in 0..frames {
+            garbage_resources.extend(resource_ref_counter.update_resource_ref_counter());
+        }
+        garbage_resources
+    }
+
+    #[test]
+    fn test_scene_unload_footprint() {
+        let mut resource_ref_counter = ResourceRefCounter::default();
+        // the core resources of the initial load
+        resource_ref_counter.set_resident_loading(true);
+        resource_ref_counter.register_resource(ResourceRefType::Mesh, "quad");
+        resource_ref_counter.register_resource(ResourceRefType::Texture, "common/default");
+        resource_ref_counter.set_resident_loading(false);
+        let baseline_mesh_count = resource_ref_counter.get_resource_ref_stats(ResourceRefType::Mesh)._loaded_count;
+        let baseline_texture_count = resource_ref_counter.get_resource_ref_stats(ResourceRefType::Texture)._loaded_count;
+
+        // the scene loads its contents
+        for mesh_name in ["cube", "tree", "rock"].iter() {
+            resource_ref_counter.register_reloadable_resource(ResourceRefType::Mesh, mesh_name);
+            resource_ref_counter.acquire_resource(ResourceRefType::Mesh, mesh_name);
+        }
+        for texture_name in ["tree_albedo", "rock_albedo"].iter() {
+            resource_ref_counter.register_reloadable_resource(ResourceRefType::Texture, texture_name);
+            resource_ref_counter.acquire_resource(ResourceRefType::Texture, texture_name);
+        }
+        resource_ref_counter.acquire_resource(ResourceRefType::Texture, "common/default");
+        assert_eq!(baseline_mesh_count + 3, resource_ref_counter.get_resource_ref_stats(ResourceRefType::Mesh)._loaded_count);
+        assert!(update_frames(&mut resource_ref_counter, GARBAGE_COLLECT_DELAY_FRAMES * 2).is_empty());
+
+        // the scene unload
+        for mesh_name in ["cube", "tree", "rock"].iter() {
+            resource_ref_counter.release_resource(ResourceRefType::Mesh, mesh_name);
+        }
+        for texture_name in ["tree_albedo", "rock_albedo", "common/default"].iter() {
+            resource_ref_counter.release_resource(ResourceRefType::Texture, texture_name);
+        }
+        assert_eq!(3, resource_ref_counter.get_resource_ref_stats(ResourceRefType::Mesh)._zombie_count);
+        assert!(update_frames(&mut resource_ref_counter, GARBAGE_COLLECT_DELAY_FRAMES - 1).is_empty());
+        let mut garbage_resources = update_frames(&mut resource_ref_counter, 1);
+        garbage_resources.sort_by(|lhs, rhs| lhs.1.cmp(&rhs.1));
+        assert_eq!(5, garbage_resources.len());
+        assert!(garbage_resources.iter().all(|(_, resource_name)| "common/default" != resource_name));
+
+        let mesh_stats = resource_ref_counter.get_resource_ref_stats(ResourceRefType::Mesh);
+        let texture_stats = resource_ref_counter.get_resource_ref_stats(ResourceRefType::Texture);
+        assert_eq!(baseline_mesh_count, mesh_stats._loaded_count);
+        assert_eq!(baseline_texture_count, texture_stats._loaded_count);
+        assert_eq!(0, mesh_stats._live_count + mesh_stats._zombie_count);
+        assert_eq!(0, texture_stats._live_count + texture_stats._zombie_count);
+        assert!(update_frames(&mut resource_ref_counter, GARBAGE_COLLECT_DELAY_FRAMES * 2).is_empty());
+    }
+
+    #[test]
+    fn test_reload_unloaded_resource() {
+        let mut resource_ref_counter = ResourceRefCounter::default();
+        resource_ref_counter.register_reloadable_resource(ResourceRefType::Mesh, "tree");
+        resource_ref_counter.acquire_resource(ResourceRefType::Mesh, "tree");
+        resource_ref_counter.release_resource(ResourceRefType::Mesh, "tree");
+        assert_eq!(1, update_frames(&mut resource_ref_counter, GARBAGE_COLLECT_DELAY_FRAMES).len());
+        assert!(resource_ref_counter.is_resource_unloaded(ResourceRefType::Mesh, "tree"));
+
+        // the next scene acquires it again, Resources loads it before the registration
+        assert_eq!(1, resource_ref_counter.acquire_resource(ResourceRefType::Mesh, "tree"));
+        resource_ref_counter.register_reloadable_resource(ResourceRefType::Mesh, "tree");
+        assert!(false == resource_ref_counter.is_resource_unloaded(ResourceRefType::Mesh, "tree"));
+        assert_eq!(1, resource_ref_counter.get_resource_ref_stats(ResourceRefType::Mesh)._live_count);
+    }
+
+    #[test]
+    fn test_not_collected_before_first_acquire() {
+        let mut resource_ref_counter = ResourceRefCounter::default();
+        resource_ref_counter.register_reloadable_resource(ResourceRefType::Texture, "unused");
+        resource_ref_counter.register_resource(ResourceRefType::Framebuffer, "capture");
+        assert!(update_frames(&mut resource_ref_counter, GARBAGE_COLLECT_DELAY_FRAMES * 2).is_empty());
+
+        // the registered resource is removed, not unloaded
+        resource_ref_counter.acquire_resource(ResourceRefType::Framebuffer, "capture");
+        resource_ref_counter.release_resource(ResourceRefType::Framebuffer, "capture");
+        assert_eq!(1, update_frames(&mut resource_ref_counter, GARBAGE_COLLECT_DELAY_FRAMES).len());
+        assert!(resource_ref_counter._ref_count_maps.get(&ResourceRefType::Framebuffer).unwrap().is_empty());
+    }
+}
@@ -95,6 +95,16 @@ pub fn generate_images(texture_source_directory: &PathBuf) {
     generate_flat_color_image_rgba8(texture_source_directory, "common/flat_normal_no_alpha.png", 2, 2, [128, 128, 255, 0]);
 }
 
+// the textures of generate_textures, they have no source files.
+pub const GENERATED_TEXTURE_NAMES: [&str; 6] = [
+    "common/default_3d",
+    "common/default_2d_array",
+    "common/random",
+    "common/random_normal",
+    "common/checker",
+    "common/color_cube",
+];
+
 pub fn generate_textures(renderer_data: &RendererData) -> Vec<TextureData> {
     let white = vulkan_context::get_color32(255, 255, 255, 255);
     let black = vulkan_context::get_color32(0, 0, 0, 255);
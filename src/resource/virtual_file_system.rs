@@ -0,0 +1,301 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fs::{ self, File };
+use std::io::prelude::*;
+use std::io::Cursor;
+use std::path::{ Path, PathBuf };
+
+use serde::{ Serialize, Deserialize };
+use serde_json;
+
+use crate::utilities::file_watcher::{ FileEvent, FileEventType };
+use crate::utilities::system;
+
+pub const VFS_SETTINGS_FILE_PATH: &str = "settings.json";
+pub const VFS_MANIFEST_FILE_PATH: &str = "resource_manifest.txt";
+pub const ENGINE_LAYER_NAME: &str = "engine";
+
+// settings.json
+// {
+//     "mount_points": [
+//         { "name": "my_mod", "path": "mods/my_mod" },
+//         { "name": "my_patch", "path": "mods/my_patch" }
+//     ]
+// }
+// later mount points have higher priority and override the same files of the lower layers.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MountPointInfo {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct VirtualFileSystemSettings {
+    #[serde(default)]
+    pub mount_points: Vec<MountPointInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MountPoint {
+    pub _layer_name: String,
+    pub _root_path: PathBuf, // virtual path "resource/a.png" is located at "_root_path/resource/a.png", engine layer is empty path.
+}
+
+impl MountPoint {
+    pub fn get_real_path(&self, virtual_path: &Path) -> PathBuf {
+        if self._root_path.as_os_str().is_empty() {
+            PathBuf::from(virtual_path)
+        } else {
+            self._root_path.join(virtual_path)
+        }
+    }
+
+    pub fn get_virtual_path(&self, real_path: &Path) -> Option<PathBuf> {
+        if self._root_path.as_os_str().is_empty() {
+            Some(PathBuf::from(real_path))
+        } else {
+            match real_path.strip_prefix(&self._root_path) {
+                Ok(virtual_path) => Some(PathBuf::from(virtual_path)),
+                Err(_) => None,
+            }
+        }
+    }
+
+    pub fn is_engine_layer(&self) -> bool {
+        self._root_path.as_os_str().is_empty()
+    }
+}
+
+pub struct VirtualFileSystem {
+    pub _mount_points: Vec<MountPoint>, // ordered by priority, lowest first.
+    pub _manifest: RefCell<BTreeMap<PathBuf, String>>, // virtual path -> layer name of the loaded asset
+}
+
+impl VirtualFileSystem {
+    pub fn create_virtual_file_system() -> VirtualFileSystem {
+        VirtualFileSystem {
+            _mount_points: vec![MountPoint {
+                _layer_name: String::from(ENGINE_LAYER_NAME),
+                _root_path: PathBuf::new(),
+            }],
+            _manifest: RefCell::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn load_settings(&mut self, settings_file_path: &Path) {
+        if false == settings_file_path.is_file() {
+            return;
+        }
+
+        let contents = fs::read_to_string(settings_file_path).expect("Failed to read settings.");
+        let settings: VirtualFileSystemSettings = match serde_json::from_str(&contents) {
+            Ok(settings) => settings,
+            Err(e) => {
+                log::error!("load_settings error: {:?} {:?}", settings_file_path, e);
+                return;
+            }
+        };
+        for mount_point_info in settings.mount_points.iter() {
+            self.mount(&mount_point_info.name, &mount_point_info.path);
+        }
+    }
+
+    pub fn mount(&mut self, layer_name: &str, root_path: &Path) {
+        if false == root_path.is_dir() {
+            log::error!("mount: not found directory {:?}", root_path);
+            return;
+        }
+        log::info!("mount: {} {:?}", layer_name, root_path);
+        self._mount_points.push(MountPoint {
+            _layer_name: String::from(layer_name),
+            _root_path: PathBuf::from(root_path),
+        });
+    }
+
+    pub fn get_mount_points(&self) -> &Vec<MountPoint> {
+        &self._mount_points
+    }
+
+    // returns the highest-priority mount point which has the file.
+    pub fn find_mount_point(&self, virtual_path: &Path) -> Option<&MountPoint> {
+        for mount_point in self._mount_points.iter().rev() {
+            // the engine layer can be an android asset, it is the fallback of all layers.
+            if mount_point.is_engine_layer() || mount_point.get_real_path(virtual_path).is_file() {
+                return Some(mount_point);
+            }
+        }
+        None
+    }
+
+    pub fn resolve_path(&self, virtual_path: &Path) -> PathBuf {
+        match self.find_mount_point(virtual_path) {
+            Some(mount_point) => mount_point.get_real_path(virtual_path),
+            None => PathBuf::from(virtual_path),
+        }
+    }
+
+    // the runtime edits are saved to the highest-priority layer, so they override the files of the lower layers.
+    pub fn get_writable_mount_point(&self) -> &MountPoint {
+        self._mount_points.last().unwrap()
+    }
+
+    pub fn get_writable_path(&self, virtual_path: &Path) -> PathBuf {
+        self.get_writable_mount_point().get_real_path(virtual_path)
+    }
+
+    pub fn read_bytes(&self, virtual_path: &Path) -> Cursor<Vec<u8>> {
+        let mount_point = self.find_mount_point(virtual_path).unwrap();
+        self._manifest.borrow_mut().insert(PathBuf::from(virtual_path), mount_point._layer_name.clone());
+        system::load(mount_point.get_real_path(virtual_path))
+    }
+
+    // files which exist only in mod layers are not listed in resources.txt
+    pub fn collect_override_files(&self, dir: &Path, extensions: &[&str]) -> Vec<PathBuf> {
+        let mut override_files: Vec<PathBuf> = Vec::new();
+        for mount_point in self._mount_points.iter() {
+            if mount_point.is_engine_layer() {
+                continue;
+            }
+            let real_dir = mount_point.get_real_path(dir);
+            if real_dir.is_dir() {
+                for real_path in system::walk_directory(&real_dir, extensions) {
+                    if let Some(virtual_path) = mount_point.get_virtual_path(&real_path) {
+                        if false == override_files.contains(&virtual_path) {
+                            override_files.push(virtual_path);
+                        }
+                    }
+                }
+            }
+        }
+        override_files
+    }
+
+    pub fn get_virtual_path(&self, real_path: &Path) -> Option<PathBuf> {
+        for mount_point in self._mount_points.iter().rev() {
+            if false == mount_point.is_engine_layer() {
+                if let Some(virtual_path) = mount_point.get_virtual_path(real_path) {
+                    return Some(virtual_path);
+                }
+            }
+        }
+        None
+    }
+
+    // An override appearing or disappearing in any layer changes the resolved file of the virtual path,
+    // so the event is reported as Modified unless no layer provides the file anymore.
+    pub fn convert_file_event(&self, file_event: &FileEvent) -> FileEvent {
+        let virtual_path = self.get_virtual_path(&file_event._file_path).unwrap_or(file_event._file_path.clone());
+        let is_exists = self._mount_points.iter().any(|mount_point| mount_point.get_real_path(&virtual_path).is_file());
+        let event_type = match (file_event._event_type, is_exists) {
+            (FileEventType::Removed, false) => FileEventType::Removed,
+            (FileEventType::Created, _) if PathBuf::from(&virtual_path) == file_event._file_path => FileEventType::Created,
+            _ => FileEventType::Modified,
+        };
+        FileEvent {
+            _event_type: event_type,
+            _file_path: virtual_path,
+        }
+    }
+
+    pub fn dump_manifest(&self, manifest_file_path: &Path) {
+        let mut contents = String::new();
+        for (virtual_path, layer_name) in self._manifest.borrow().iter() {
+            contents.push_str(&format!("{}: {}\n", layer_name, virtual_path.to_string_lossy().replace("\\", "/")));
+        }
+        let mut write_file = File::create(manifest_file_path).expect("Failed to create file");
+        write_file.write_all(contents.as_bytes()).expect("Failed to write");
+        log::info!("dump_manifest: {:?}", manifest_file_path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utilities::test_utility::create_test_directory;
+
+    fn write_test_file(file_path: &Path, contents: &str) {
+        fs::create_dir_all(file_path.parent().unwrap()).unwrap();
+        fs::write(file_path, contents).unwrap();
+    }
+
+    fn read_test_file(virtual_file_system: &VirtualFileSystem, virtual_path: &Path) -> String {
+        String::from_utf8(virtual_file_system.read_bytes(virtual_path).into_inner()).unwrap()
+    }
+
+    #[test]
+    fn test_layered_overrides() {
+        let test_directory = create_test_directory("virtual_file_system_layered_overrides");
+        let mod_path = test_directory.join("mods/my_mod");
+        let patch_path = test_directory.join("mods/my_patch");
+        let texture_path = PathBuf::from("resources/textures/a.png");
+        let material_path = PathBuf::from("resources/materials/b.mat");
+        write_test_file(&mod_path.join(&texture_path), "my_mod a");
+        write_test_file(&mod_path.join(&material_path), "my_mod b");
+        write_test_file(&patch_path.join(&texture_path), "my_patch a");
+        let settings_file_path = test_directory.join(VFS_SETTINGS_FILE_PATH);
+        write_test_file(&settings_file_path, r#"{ "mount_points": [
+            { "name": "my_mod", "path": "MOD_PATH" },
+            { "name": "my_patch", "path": "PATCH_PATH" },
+            { "name": "missing", "path": "MISSING_PATH" }
+        ] }"#
+            .replace("MOD_PATH", &mod_path.to_string_lossy().replace("\\", "/"))
+            .replace("PATCH_PATH", &patch_path.to_string_lossy().replace("\\", "/"))
+            .replace("MISSING_PATH", &test_directory.join("mods/missing").to_string_lossy().replace("\\", "/")).as_str());
+
+        let mut virtual_file_system = VirtualFileSystem::create_virtual_file_system();
+        virtual_file_system.load_settings(&settings_file_path);
+        // the directory which does not exist is not mounted
+        let layer_names: Vec<&str> = virtual_file_system.get_mount_points().iter().map(|mount_point| mount_point._layer_name.as_str()).collect();
+        assert_eq!(vec![ENGINE_LAYER_NAME, "my_mod", "my_patch"], layer_names);
+
+        // the later layer overrides the same file, the other files come from the lower layer
+        assert_eq!(patch_path.join(&texture_path), virtual_file_system.resolve_path(&texture_path));
+        assert_eq!(mod_path.join(&material_path), virtual_file_system.resolve_path(&material_path));
+        assert_eq!("my_patch a", read_test_file(&virtual_file_system, &texture_path));
+        assert_eq!("my_mod b", read_test_file(&virtual_file_system, &material_path));
+        assert_eq!(Some(&String::from("my_patch")), virtual_file_system._manifest.borrow().get(&texture_path));
+        assert_eq!(Some(&String::from("my_mod")), virtual_file_system._manifest.borrow().get(&material_path));
+
+        // the runtime edits go to the highest layer
+        assert_eq!(patch_path.join(&material_path), virtual_file_system.get_writable_path(&material_path));
+
+        // the files only in the mod layers, listed once
+        let mut override_files = virtual_file_system.collect_override_files(Path::new("resources"), &["png", "mat"]);
+        override_files.sort();
+        assert_eq!(vec![material_path.clone(), texture_path.clone()], override_files);
+
+        let _result = fs::remove_dir_all(&test_directory);
+    }
+
+    #[test]
+    fn test_missing_file_fallback() {
+        let test_directory = create_test_directory("virtual_file_system_missing_file_fallback");
+        let mod_path = test_directory.join("mods/my_mod");
+        fs::create_dir_all(&mod_path).unwrap();
+        let mut virtual_file_system = VirtualFileSystem::create_virtual_file_system();
+        // only the engine layer, the edits are saved in place
+        assert_eq!(PathBuf::from("resources/a.png"), virtual_file_system.get_writable_path(Path::new("resources/a.png")));
+        virtual_file_system.mount("my_mod", &mod_path);
+
+        // no layer has the file, the engine layer is the fallback with the virtual path
+        let virtual_path = PathBuf::from("resources/textures/missing.png");
+        assert_eq!(ENGINE_LAYER_NAME, virtual_file_system.find_mount_point(&virtual_path).unwrap()._layer_name);
+        assert_eq!(virtual_path, virtual_file_system.resolve_path(&virtual_path));
+
+        // the override appears and disappears at runtime
+        let real_path = mod_path.join(&virtual_path);
+        write_test_file(&real_path, "my_mod");
+        assert_eq!(real_path, virtual_file_system.resolve_path(&virtual_path));
+        let file_event = virtual_file_system.convert_file_event(&FileEvent { _event_type: FileEventType::Created, _file_path: real_path.clone() });
+        assert_eq!(virtual_path, file_event._file_path);
+        assert_eq!(FileEventType::Modified, file_event._event_type);
+
+        fs::remove_file(&real_path).unwrap();
+        assert_eq!(virtual_path, virtual_file_system.resolve_path(&virtual_path));
+        let file_event = virtual_file_system.convert_file_event(&FileEvent { _event_type: FileEventType::Removed, _file_path: real_path.clone() });
+        assert_eq!(virtual_path, file_event._file_path);
+        assert_eq!(FileEventType::Removed, file_event._event_type);
+
+        let _result = fs::remove_dir_all(&test_directory);
+    }
+}
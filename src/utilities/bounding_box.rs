@@ -63,5 +63,28 @@ impl BoundingBox {
         self._size = &self._max - &self._min;
         self._radius = (&self._max * 0.5 - &self._min * 0.5).norm();
     }
-}
 
+    // all 8 corners, unlike update_with_matrix this is conservative for the rotations
+    pub fn get_transformed_bound_box(&self, matrix: &Matrix4<f32>) -> BoundingBox {
+        let mut corners: Vec<Vector3<f32>> = Vec::with_capacity(8);
+        for i in 0..8 {
+            let corner = Vector4::new(
+                if 0 == (i & 1) { self._min.x } else { self._max.x },
+                if 0 == (i & 2) { self._min.y } else { self._max.y },
+                if 0 == (i & 4) { self._min.z } else { self._max.z },
+                1.0
+            );
+            let corner = matrix * corner;
+            corners.push(Vector3::new(corner.x, corner.y, corner.z));
+        }
+        calc_bounding_box(&corners)
+    }
+
+    pub fn union_bound_box(&mut self, bound_box: &BoundingBox) {
+        self._min = Vector3::new(self._min.x.min(bound_box._min.x), self._min.y.min(bound_box._min.y), self._min.z.min(bound_box._min.z));
+        self._max = Vector3::new(self._max.x.max(bound_box._max.x), self._max.y.max(bound_box._max.y), self._max.z.max(bound_box._max.z));
+        self._center = &self._min * 0.5 + &self._max * 0.5;
+        self._size = &self._max - &self._min;
+        self._radius = (&self._max * 0.5 - &self._min * 0.5).norm();
+    }
+}
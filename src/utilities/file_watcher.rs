@@ -0,0 +1,403 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{ Path, PathBuf };
+use std::sync::{ Arc, Mutex };
+use std::sync::atomic::{ AtomicBool, Ordering };
+use std::sync::mpsc::{ self, Receiver, Sender };
+use std::thread::{ self, JoinHandle };
+use std::time::{ Duration, Instant, SystemTime };
+
+#[cfg(not(target_os = "android"))]
+use notify::{ self, Watcher, RecursiveMode, DebouncedEvent, RecommendedWatcher };
+
+pub const DEFAULT_POLLING_INTERVAL: Duration = Duration::from_millis(500);
+pub const DEFAULT_DEBOUNCE_TIME: Duration = Duration::from_millis(200);
+
+#[derive(Clone, Debug, Copy, PartialEq, Eq)]
+pub enum FileEventType {
+    Created,
+    Modified,
+    Removed,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FileEvent {
+    pub _event_type: FileEventType,
+    pub _file_path: PathBuf,
+}
+
+#[derive(Clone, Debug, Copy, PartialEq, Eq)]
+pub enum FileWatcherBackendType {
+    Native,
+    Polling,
+}
+
+#[derive(Clone, Debug)]
+pub struct WatchDirectory {
+    pub _directory: PathBuf,
+    pub _filters: Vec<String>, // glob patterns ex) "*.glsl", empty is match all.
+}
+
+impl WatchDirectory {
+    pub fn is_match(&self, file_path: &Path) -> bool {
+        if false == file_path.starts_with(&self._directory) {
+            return false;
+        }
+
+        if self._filters.is_empty() {
+            return true;
+        }
+
+        match file_path.file_name() {
+            Some(file_name) => {
+                let file_name = file_name.to_string_lossy();
+                self._filters.iter().any(|filter| match_glob(filter.as_bytes(), file_name.as_bytes()))
+            },
+            None => false,
+        }
+    }
+}
+
+pub fn match_glob(pattern: &[u8], text: &[u8]) -> bool {
+    match (pattern.first(), text.first()) {
+        (None, None) => true,
+        (Some(b'*'), _) => match_glob(&pattern[1..], text) || (false == text.is_empty() && match_glob(pattern, &text[1..])),
+        (Some(b'?'), Some(_)) => match_glob(&pattern[1..], &text[1..]),
+        (Some(p), Some(t)) if p == t => match_glob(&pattern[1..], &text[1..]),
+        _ => false,
+    }
+}
+
+// Combine two events of the same file that occurred within debounce time.
+// temp-then-rename writes produce Removed + Created, and they are reported as a single Modified.
+pub fn combine_file_event_type(prev_event_type: FileEventType, event_type: FileEventType) -> Option<FileEventType> {
+    match (prev_event_type, event_type) {
+        (FileEventType::Created, FileEventType::Modified) => Some(FileEventType::Created),
+        (FileEventType::Created, FileEventType::Removed) => None,
+        (FileEventType::Removed, FileEventType::Created) => Some(FileEventType::Modified),
+        (FileEventType::Removed, FileEventType::Modified) => Some(FileEventType::Modified),
+        (_, event_type) => Some(event_type),
+    }
+}
+
+fn scan_directory(directory: &Path, out_file_times: &mut HashMap<PathBuf, SystemTime>) {
+    if let Ok(contents) = fs::read_dir(directory) {
+        for content in contents {
+            if let Ok(content) = content {
+                let content_path = content.path();
+                if content_path.is_dir() {
+                    scan_directory(&content_path, out_file_times);
+                } else if let Ok(modified_time) = content.metadata().and_then(|metadata| metadata.modified()) {
+                    out_file_times.insert(content_path, modified_time);
+                }
+            }
+        }
+    }
+}
+
+fn run_polling_backend(
+    watch_directories: Arc<Mutex<Vec<WatchDirectory>>>,
+    is_running: Arc<AtomicBool>,
+    polling_interval: Duration,
+    sender: Sender<FileEvent>,
+) {
+    let mut directory_file_times: HashMap<PathBuf, HashMap<PathBuf, SystemTime>> = HashMap::new();
+    while is_running.load(Ordering::Relaxed) {
+        let directories: Vec<PathBuf> = watch_directories.lock().unwrap().iter().map(|watch_directory| watch_directory._directory.clone()).collect();
+        // batch the scan per directory, the first scan of a directory only records the current state.
+        for directory in directories.iter() {
+            let mut file_times: HashMap<PathBuf, SystemTime> = HashMap::new();
+            scan_directory(directory, &mut file_times);
+            if let Some(prev_file_times) = directory_file_times.get(directory) {
+                for (file_path, modified_time) in file_times.iter() {
+                    let event_type = match prev_file_times.get(file_path) {
+                        None => Some(FileEventType::Created),
+                        Some(prev_modified_time) if prev_modified_time != modified_time => Some(FileEventType::Modified),
+                        _ => None,
+                    };
+                    if let Some(event_type) = event_type {
+                        let _result = sender.send(FileEvent { _event_type: event_type, _file_path: file_path.clone() });
+                    }
+                }
+                for file_path in prev_file_times.keys() {
+                    if false == file_times.contains_key(file_path) {
+                        let _result = sender.send(FileEvent { _event_type: FileEventType::Removed, _file_path: file_path.clone() });
+                    }
+                }
+            }
+            directory_file_times.insert(directory.clone(), file_times);
+        }
+        thread::sleep(polling_interval);
+    }
+}
+
+pub struct FileWatcher {
+    pub _backend_type: FileWatcherBackendType,
+    pub _watch_directories: Arc<Mutex<Vec<WatchDirectory>>>,
+    pub _debounce_time: Duration,
+    pub _pending_events: Vec<(FileEvent, Instant)>,
+    pub _receiver: Receiver<FileEvent>,
+    pub _is_running: Arc<AtomicBool>,
+    pub _polling_thread: Option<JoinHandle<()>>,
+    #[cfg(not(target_os = "android"))]
+    pub _native_watcher: Option<RecommendedWatcher>,
+    #[cfg(not(target_os = "android"))]
+    pub _native_receiver: Option<Receiver<DebouncedEvent>>,
+}
+
+impl FileWatcher {
+    pub fn create_file_watcher(backend_type: FileWatcherBackendType, polling_interval: Duration, debounce_time: Duration) -> FileWatcher {
+        // native watcher is not available inside of android apk.
+        #[cfg(target_os = "android")]
+        let backend_type = FileWatcherBackendType::Polling;
+
+        let (sender, receiver) = mpsc::channel();
+        let watch_directories: Arc<Mutex<Vec<WatchDirectory>>> = Arc::new(Mutex::new(Vec::new()));
+        let is_running = Arc::new(AtomicBool::new(true));
+
+        #[cfg(not(target_os = "android"))]
+        let (backend_type, native_watcher, native_receiver) = if FileWatcherBackendType::Native == backend_type {
+            let (native_sender, native_receiver) = mpsc::channel();
+            match notify::watcher(native_sender, debounce_time) {
+                Ok(native_watcher) => (FileWatcherBackendType::Native, Some(native_watcher), Some(native_receiver)),
+                Err(e) => {
+                    log::warn!("failed to create native file watcher, fallback to polling: {:?}", e);
+                    (FileWatcherBackendType::Polling, None, None)
+                }
+            }
+        } else {
+            (FileWatcherBackendType::Polling, None, None)
+        };
+
+        let polling_thread = if FileWatcherBackendType::Polling == backend_type {
+            let watch_directories = watch_directories.clone();
+            let is_running = is_running.clone();
+            Some(thread::spawn(move || run_polling_backend(watch_directories, is_running, polling_interval, sender)))
+        } else {
+            None
+        };
+
+        log::info!("create_file_watcher: {:?}", backend_type);
+        FileWatcher {
+            _backend_type: backend_type,
+            _watch_directories: watch_directories,
+            _debounce_time: debounce_time,
+            _pending_events: Vec::new(),
+            _receiver: receiver,
+            _is_running: is_running,
+            _polling_thread: polling_thread,
+            #[cfg(not(target_os = "android"))]
+            _native_watcher: native_watcher,
+            #[cfg(not(target_os = "android"))]
+            _native_receiver: native_receiver,
+        }
+    }
+
+    pub fn destroy_file_watcher(&mut self) {
+        self._is_running.store(false, Ordering::Relaxed);
+        if let Some(polling_thread) = self._polling_thread.take() {
+            let _result = polling_thread.join();
+        }
+        #[cfg(not(target_os = "android"))]
+        {
+            self._native_watcher = None;
+            self._native_receiver = None;
+        }
+        self._watch_directories.lock().unwrap().clear();
+        self._pending_events.clear();
+    }
+
+    pub fn regist_watch_directory(&mut self, directory: &Path, filters: &[&str]) {
+        log::debug!("regist_watch_directory: {:?} {:?}", directory, filters);
+        #[cfg(not(target_os = "android"))]
+        if let Some(native_watcher) = self._native_watcher.as_mut() {
+            if let Err(e) = native_watcher.watch(directory, RecursiveMode::Recursive) {
+                log::error!("failed to watch directory {:?}: {:?}", directory, e);
+            }
+        }
+        self._watch_directories.lock().unwrap().push(WatchDirectory {
+            _directory: PathBuf::from(directory),
+            _filters: filters.iter().map(|filter| String::from(*filter)).collect(),
+        });
+    }
+
+    pub fn unregist_watch_directory(&mut self, directory: &Path) {
+        #[cfg(not(target_os = "android"))]
+        if let Some(native_watcher) = self._native_watcher.as_mut() {
+            let _result = native_watcher.unwatch(directory);
+        }
+        self._watch_directories.lock().unwrap().retain(|watch_directory| watch_directory._directory != directory);
+    }
+
+    fn push_pending_event(&mut self, file_event: FileEvent, now: Instant) {
+        let is_match = self._watch_directories.lock().unwrap().iter().any(|watch_directory| watch_directory.is_match(&file_event._file_path));
+        if false == is_match {
+            return;
+        }
+
+        let maybe_index = self._pending_events.iter().position(|(pending_event, _)| pending_event._file_path == file_event._file_path);
+        match maybe_index {
+            Some(index) => {
+                let prev_event_type = self._pending_events[index].0._event_type;
+                match combine_file_event_type(prev_event_type, file_event._event_type) {
+                    Some(event_type) => {
+                        self._pending_events[index].0._event_type = event_type;
+                        self._pending_events[index].1 = now;
+                    },
+                    None => {
+                        self._pending_events.remove(index);
+                    }
+                }
+            },
+            None => self._pending_events.push((file_event, now)),
+        }
+    }
+
+    #[cfg(not(target_os = "android"))]
+    fn receive_native_events(&mut self, now: Instant) {
+        let mut file_events: Vec<FileEvent> = Vec::new();
+        if let Some(native_receiver) = self._native_receiver.as_ref() {
+            while let Ok(native_event) = native_receiver.try_recv() {
+                match native_event {
+                    DebouncedEvent::Create(file_path) => file_events.push(FileEvent { _event_type: FileEventType::Created, _file_path: file_path }),
+                    DebouncedEvent::Write(file_path) => file_events.push(FileEvent { _event_type: FileEventType::Modified, _file_path: file_path }),
+                    DebouncedEvent::Remove(file_path) => file_events.push(FileEvent { _event_type: FileEventType::Removed, _file_path: file_path }),
+                    DebouncedEvent::Rename(src_file_path, dst_file_path) => {
+                        file_events.push(FileEvent { _event_type: FileEventType::Removed, _file_path: src_file_path });
+                        file_events.push(FileEvent { _event_type: FileEventType::Created, _file_path: dst_file_path });
+                    },
+                    DebouncedEvent::Error(e, file_path) => log::error!("file watcher error: {:?} {:?}", e, file_path),
+                    _ => (),
+                }
+            }
+        }
+        for file_event in file_events {
+            self.push_pending_event(file_event, now);
+        }
+    }
+
+    // returns events which have not been changed during debounce time.
+    pub fn poll_file_events(&mut self) -> Vec<FileEvent> {
+        let now = Instant::now();
+        #[cfg(not(target_os = "android"))]
+        self.receive_native_events(now);
+        while let Ok(file_event) = self._receiver.try_recv() {
+            self.push_pending_event(file_event, now);
+        }
+
+        let debounce_time = self._debounce_time;
+        let mut file_events: Vec<FileEvent> = Vec::new();
+        self._pending_events.retain(|(pending_event, last_time)| {
+            if debounce_time <= now.duration_since(*last_time) {
+                file_events.push(pending_event.clone());
+                return false;
+            }
+            true
+        });
+        file_events
+    }
+}
+
+impl Drop for FileWatcher {
+    fn drop(&mut self) {
+        self._is_running.store(false, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utilities::test_utility::create_test_directory;
+
+    const TEST_POLLING_INTERVAL: Duration = Duration::from_millis(20);
+    const TEST_DEBOUNCE_TIME: Duration = Duration::from_millis(200);
+
+    fn create_test_file_watcher(test_directory: &Path) -> FileWatcher {
+        let mut file_watcher = FileWatcher::create_file_watcher(FileWatcherBackendType::Polling, TEST_POLLING_INTERVAL, TEST_DEBOUNCE_TIME);
+        file_watcher.regist_watch_directory(test_directory, &["*.glsl"]);
+        // the first scan only records the current state
+        thread::sleep(TEST_POLLING_INTERVAL * 5);
+        file_watcher
+    }
+
+    // polls every frame like the engine loop
+    fn wait_file_events(file_watcher: &mut FileWatcher) -> Vec<FileEvent> {
+        let mut file_events: Vec<FileEvent> = Vec::new();
+        let end_time = Instant::now() + TEST_DEBOUNCE_TIME * 3;
+        while Instant::now() < end_time {
+            file_events.extend(file_watcher.poll_file_events());
+            thread::sleep(TEST_POLLING_INTERVAL);
+        }
+        file_events
+    }
+
+    #[test]
+    fn test_match_glob() {
+        assert!(match_glob(b"*.glsl", b"scene_constants.glsl"));
+        assert!(match_glob(b"render_?.frag", b"render_a.frag"));
+        assert!(false == match_glob(b"*.glsl", b"scene_constants.glsl.tmp"));
+        assert!(false == match_glob(b"render_?.frag", b"render_ab.frag"));
+    }
+
+    #[test]
+    fn test_combine_file_event_type() {
+        assert_eq!(Some(FileEventType::Modified), combine_file_event_type(FileEventType::Removed, FileEventType::Created));
+        assert_eq!(Some(FileEventType::Created), combine_file_event_type(FileEventType::Created, FileEventType::Modified));
+        assert_eq!(None, combine_file_event_type(FileEventType::Created, FileEventType::Removed));
+        assert_eq!(Some(FileEventType::Removed), combine_file_event_type(FileEventType::Modified, FileEventType::Removed));
+    }
+
+    #[test]
+    fn test_polling_debounce() {
+        let test_directory = create_test_directory("file_watcher_debounce");
+        let file_path = test_directory.join("shader.glsl");
+        fs::write(&file_path, "0").unwrap();
+        let mut file_watcher = create_test_file_watcher(&test_directory);
+
+        for i in 0..3 {
+            fs::write(&file_path, format!("{}", i + 1)).unwrap();
+            thread::sleep(TEST_POLLING_INTERVAL * 3);
+        }
+        // the file is still being written in the debounce time
+        assert!(file_watcher.poll_file_events().is_empty());
+
+        let file_events = wait_file_events(&mut file_watcher);
+        assert_eq!(vec![FileEvent { _event_type: FileEventType::Modified, _file_path: file_path.clone() }], file_events);
+        assert!(wait_file_events(&mut file_watcher).is_empty());
+
+        file_watcher.destroy_file_watcher();
+        let _result = fs::remove_dir_all(&test_directory);
+    }
+
+    #[test]
+    fn test_polling_rename() {
+        let test_directory = create_test_directory("file_watcher_rename");
+        let file_path = test_directory.join("shader.glsl");
+        let temp_file_path = test_directory.join("shader.glsl.tmp");
+        fs::write(&file_path, "0").unwrap();
+        let mut file_watcher = create_test_file_watcher(&test_directory);
+
+        // temp-then-rename, the temp file does not match the filter
+        fs::write(&temp_file_path, "1").unwrap();
+        fs::rename(&temp_file_path, &file_path).unwrap();
+        let file_events = wait_file_events(&mut file_watcher);
+        assert_eq!(vec![FileEvent { _event_type: FileEventType::Modified, _file_path: file_path.clone() }], file_events);
+
+        // removed and created again is a single modification
+        fs::remove_file(&file_path).unwrap();
+        thread::sleep(TEST_POLLING_INTERVAL * 3);
+        fs::write(&file_path, "2").unwrap();
+        let file_events = wait_file_events(&mut file_watcher);
+        assert_eq!(vec![FileEvent { _event_type: FileEventType::Modified, _file_path: file_path.clone() }], file_events);
+
+        // created and removed in the debounce time is nothing
+        let new_file_path = test_directory.join("new_shader.glsl");
+        fs::write(&new_file_path, "3").unwrap();
+        thread::sleep(TEST_POLLING_INTERVAL * 3);
+        fs::remove_file(&new_file_path).unwrap();
+        assert!(wait_file_events(&mut file_watcher).is_empty());
+
+        file_watcher.destroy_file_watcher();
+        let _result = fs::remove_dir_all(&test_directory);
+    }
+}
@@ -1,8 +1,64 @@
 use env_logger;
+use std::collections::VecDeque;
 use std::io::Write;
+use std::sync::{ Arc, Mutex };
 use chrono::Local;
 use log::LevelFilter;
 
+pub const LOG_RING_BUFFER_SIZE: usize = 256;
+
+// the recent log lines of the console, see get_recent_logs. empty on android.
+static mut LOG_RING_BUFFER: Option<Arc<Mutex<VecDeque<String>>>> = None;
+
+// env_logger and a copy of the lines into the ring buffer
+#[cfg(not(target_os = "android"))]
+struct RingBufferLogger {
+    _env_logger: env_logger::Logger,
+    _ring_buffer: Arc<Mutex<VecDeque<String>>>,
+}
+
+#[cfg(not(target_os = "android"))]
+impl log::Log for RingBufferLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self._env_logger.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self._env_logger.matches(record) {
+            self._env_logger.log(record);
+            push_ring_buffer(&self._ring_buffer, format!("[{}] {}", record.level(), record.args()));
+        }
+    }
+
+    fn flush(&self) {
+        self._env_logger.flush();
+    }
+}
+
+fn push_ring_buffer(ring_buffer: &Arc<Mutex<VecDeque<String>>>, text: String) {
+    if let Ok(mut ring_buffer) = ring_buffer.lock() {
+        if LOG_RING_BUFFER_SIZE <= ring_buffer.len() {
+            ring_buffer.pop_front();
+        }
+        ring_buffer.push_back(text);
+    }
+}
+
+// the console output without the log level filter, ex) the echo of a command
+pub fn push_log_line(text: String) {
+    if let Some(ring_buffer) = unsafe { LOG_RING_BUFFER.as_ref() } {
+        push_ring_buffer(ring_buffer, text);
+    }
+}
+
+// the last count lines, the oldest first
+pub fn get_recent_logs(count: usize) -> Vec<String> {
+    match unsafe { LOG_RING_BUFFER.as_ref() }.and_then(|ring_buffer| ring_buffer.lock().ok()) {
+        Some(ring_buffer) => ring_buffer.iter().skip(ring_buffer.len().saturating_sub(count)).cloned().collect(),
+        None => Vec::new(),
+    }
+}
+
 #[cfg(target_os = "android")]
 pub fn initialize_logger(log_level: LevelFilter) {
     let debug_level = match log_level {
@@ -18,7 +74,7 @@ pub fn initialize_logger(log_level: LevelFilter) {
 }
 #[cfg(not(target_os = "android"))]
 pub fn initialize_logger(log_level: LevelFilter) {
-    env_logger::Builder::new()
+    let env_logger = env_logger::Builder::new()
         .format(|buffer, record| {
             writeln!(buffer,
                      "{} [{}] {} ({} line:{})",
@@ -30,5 +86,14 @@ pub fn initialize_logger(log_level: LevelFilter) {
             )
         })
         .filter(None, log_level)
-        .init();
-}
\ No newline at end of file
+        .build();
+    let ring_buffer = Arc::new(Mutex::new(VecDeque::with_capacity(LOG_RING_BUFFER_SIZE)));
+    unsafe {
+        LOG_RING_BUFFER = Some(ring_buffer.clone());
+    }
+    log::set_max_level(env_logger.filter());
+    log::set_boxed_logger(Box::new(RingBufferLogger {
+        _env_logger: env_logger,
+        _ring_buffer: ring_buffer,
+    })).expect("Failed to set the logger");
+}
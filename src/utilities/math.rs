@@ -9,6 +9,8 @@ use nalgebra::{
 };
 use nalgebra_glm as glm;
 
+use crate::constants;
+
 pub const HALF_PI: f32 = std::f32::consts::PI * 0.5;
 pub const TWO_PI: f32 = std::f32::consts::PI * 2.0;
 
@@ -16,6 +18,27 @@ pub fn lerp(a: f32, b: f32, t: f32) -> f32 {
     a * (1.0 - t) + b * t
 }
 
+// the bits of the half float textures made on the cpu, the denormals are flushed to zero and the overflow is the infinity
+pub fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7fffff;
+    if 0xff == exponent {
+        return sign | 0x7c00 | if 0 != mantissa { 0x200 } else { 0 };
+    }
+    let half_exponent = exponent - 127 + 15;
+    if 0x1f <= half_exponent {
+        sign | 0x7c00
+    } else if half_exponent <= 0 {
+        sign
+    } else {
+        // round to nearest, the carry of the mantissa goes into the exponent
+        let half = sign | ((half_exponent as u16) << 10) | (mantissa >> 13) as u16;
+        if 0 != (mantissa & 0x1000) { half + 1 } else { half }
+    }
+}
+
 // https://github.com/TheRealMJP/SamplePattern/blob/master/SamplePattern.cpp
 // Computes a radical inverse with base 2 using crazy bit-twiddling from "Hacker's Delight"
 pub fn radical_inverse_base2(bits: u32) -> f32 {
@@ -35,15 +58,89 @@ pub fn hammersley_2d(sample_idx: u32, num_samples: u32) -> Vector2<f32> {
 
 pub fn get_clip_space_matrix() -> Matrix4<f32> {
     // -- ... and a {clip space -> screen space} matrix that converts points into
-    // --     the vulkan screen space {x: -1..1, y: 1..-1, z: 0..1}
+    // --     the vulkan screen space {x: -1..1, y: 1..-1, z: 0..1}, z: 1..0 with reversed-z
+    let (depth_scale, depth_offset) = if is_reversed_z() { (-0.5, 0.5) } else { (0.5, 0.5) };
     Matrix4::new(
         1.0, 0.0, 0.0, 0.0,
         0.0, -1.0, 0.0, 0.0,
-        0.0, 0.0, 0.5, 0.5,
+        0.0, 0.0, depth_scale, depth_offset,
         0.0, 0.0, 0.0, 1.0,
     )
 }
 
+// the depth conventions, every consumer which compares or linearizes the device depth uses these.
+pub fn is_reversed_z() -> bool {
+    unsafe { constants::REVERSED_Z }
+}
+
+pub fn is_infinite_far_plane() -> bool {
+    unsafe { constants::REVERSED_Z && constants::INFINITE_FAR_PLANE }
+}
+
+pub fn get_near_depth() -> f32 {
+    if is_reversed_z() { 1.0 } else { 0.0 }
+}
+
+// the depth clear value
+pub fn get_far_depth() -> f32 {
+    if is_reversed_z() { 0.0 } else { 1.0 }
+}
+
+// a depth authored in the standard convention, ex) the clear values of render_targets.json
+pub fn convert_standard_depth(depth: f32) -> f32 {
+    if is_reversed_z() { 1.0 - depth } else { depth }
+}
+
+pub fn is_closer_depth(depth: f32, other_depth: f32) -> bool {
+    if is_reversed_z() { other_depth < depth } else { depth < other_depth }
+}
+
+// the reduction of the hierarchical depth: min-z, max-z with reversed-z
+pub fn get_farther_depth(depth: f32, other_depth: f32) -> f32 {
+    if is_reversed_z() { depth.min(other_depth) } else { depth.max(other_depth) }
+}
+
+// the device depth of the camera projection to the view space distance
+pub fn linearize_depth(depth: f32, near: f32, far: f32) -> f32 {
+    if is_infinite_far_plane() {
+        if 0.0 < depth { near / depth } else { std::f32::MAX }
+    } else if is_reversed_z() {
+        near * far / (near + depth * (far - near))
+    } else {
+        near * far / (far - depth * (far - near))
+    }
+}
+
+// the shader constants of linearize_depth: distance = 1.0 / (depth * x + y) in every convention,
+// ex) ViewConstants::_near_far.zw of the project, the depth consumers do not branch on the defines.
+pub fn get_linear_depth_params(near: f32, far: f32) -> Vector2<f32> {
+    if is_infinite_far_plane() {
+        Vector2::new(1.0 / near, 0.0)
+    } else if is_reversed_z() {
+        Vector2::new((far - near) / (near * far), 1.0 / far)
+    } else {
+        Vector2::new((near - far) / (near * far), 1.0 / near)
+    }
+}
+
+// the view space distance to the device depth, the inverse of linearize_depth
+pub fn get_device_depth(distance: f32, near: f32, far: f32) -> f32 {
+    if is_infinite_far_plane() {
+        near / distance.max(near)
+    } else {
+        convert_standard_depth((far - near * far / distance.max(near)) / (far - near))
+    }
+}
+
+// the smallest distinguishable distance at the view distance, with a float32 depth buffer.
+// compares the conventions for the z-fighting of the distant geometry.
+pub fn get_depth_resolution(distance: f32, near: f32, far: f32) -> f32 {
+    let depth = get_device_depth(distance, near, far);
+    let depth_step = f32::from_bits(depth.to_bits() + 1) - depth;
+    let next_depth = if is_reversed_z() { depth - depth_step } else { depth + depth_step };
+    (linearize_depth(next_depth, near, far) - distance).abs()
+}
+
 pub fn get_world_left() -> Vector3<f32> {
     Vector3::new(1.0, 0.0, 0.0)
 }
@@ -98,6 +195,14 @@ pub fn combinate_matrix(translation: &Vector3<f32>, rotation_matrix: &Matrix4<f3
     ])
 }
 
+// the translation and the scale are linear and the rotation is the slerp, ex) the render matrix between two simulation steps
+pub fn interpolate_matrix(matrix_a: &Matrix4<f32>, matrix_b: &Matrix4<f32>, t: f32) -> Matrix4<f32> {
+    let translation = extract_location(matrix_a).lerp(&extract_location(matrix_b), t);
+    let scale = extract_scale(matrix_a).lerp(&extract_scale(matrix_b), t);
+    let rotation = glm::quat_slerp(&extract_quaternion(matrix_a), &extract_quaternion(matrix_b), t);
+    combinate_matrix(&translation, &quaternion_to_matrix(&rotation), &scale)
+}
+
 pub fn inverse_transform_matrix(translation: &Vector3<f32>, rotation_matrix: &Matrix4<f32>, scale: &Vector3<f32>) -> Matrix4<f32> {
     let mut inv_rotation_matrix = rotation_matrix.clone();
     let column0 = inv_rotation_matrix.column(0) / scale[0];
@@ -145,6 +250,18 @@ pub fn orthogonal(left: f32, right: f32, bottom: f32, top: f32, near: f32, far:
     m
 }
 
+// reversed-z with the infinite far plane in the vulkan clip space, get_clip_space_matrix is included.
+pub fn perspective_infinite_reversed(aspect: f32, fov: f32, near: f32) -> Matrix4<f32> {
+    let height: f32 = degree_to_radian(fov * 0.5).tan() * near;
+    let width: f32 = height * aspect;
+    Matrix4::from_columns(&[
+        Vector4::new(near / width, 0.0, 0.0, 0.0),
+        Vector4::new(0.0, -near / height, 0.0, 0.0),
+        Vector4::new(0.0, 0.0, 0.0, -1.0),
+        Vector4::new(0.0, 0.0, near, 0.0)
+    ])
+}
+
 pub fn perspective(aspect: f32, fov: f32, near: f32, far: f32) -> Matrix4<f32> {
     let height: f32 = degree_to_radian(fov * 0.5).tan() * near;
     let width: f32 = height * aspect;
@@ -1,5 +1,10 @@
 pub mod bounding_box;
+pub mod file_watcher;
 pub mod logger;
 pub mod math;
+pub mod progress;
 pub mod system;
+pub mod telemetry;
+#[cfg(test)]
+pub mod test_utility;
 pub mod xml;
\ No newline at end of file
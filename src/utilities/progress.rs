@@ -0,0 +1,40 @@
+// progress of the long operations, for the loading screen of the project and the taskbar progress.
+// the operations block the event loop, so the callbacks are called directly from them on the main thread.
+
+#[derive(Clone, Debug, Copy, PartialEq, Eq)]
+pub enum ProgressTask {
+    ResourceLoad,
+    SceneLoad,
+    ImpostorBake,
+    AmbientOcclusionBake, // the engine has no ao baker, reported by the project
+}
+
+// progress: 0.0 ~ 1.0, None when the task has been finished
+pub type ProgressCallback = Box<dyn Fn(ProgressTask, Option<f32>)>;
+
+static mut PROGRESS_CALLBACKS: Vec<ProgressCallback> = Vec::new();
+
+pub fn add_progress_callback(progress_callback: ProgressCallback) {
+    unsafe { PROGRESS_CALLBACKS.push(progress_callback); }
+}
+
+pub fn clear_progress_callbacks() {
+    unsafe { PROGRESS_CALLBACKS.clear(); }
+}
+
+pub fn report_progress(progress_task: ProgressTask, progress: f32) {
+    let progress = progress.max(0.0).min(1.0);
+    unsafe {
+        for progress_callback in PROGRESS_CALLBACKS.iter() {
+            progress_callback(progress_task, Some(progress));
+        }
+    }
+}
+
+pub fn end_progress(progress_task: ProgressTask) {
+    unsafe {
+        for progress_callback in PROGRESS_CALLBACKS.iter() {
+            progress_callback(progress_task, None);
+        }
+    }
+}
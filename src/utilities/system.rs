@@ -69,6 +69,12 @@ pub fn to_bytes<'a, T>(data: *const T) -> &'a [u8]{
     }
 }
 
+pub fn slice_to_bytes<T>(data: &[T]) -> &[u8] {
+    unsafe {
+        std::slice::from_raw_parts(data.as_ptr() as *const u8, std::mem::size_of::<T>() * data.len())
+    }
+}
+
 pub fn convert_vec<S, D>(src: Vec<S>) -> Vec<D> {
     unsafe {
         let size_of_src = std::mem::size_of::<S>();
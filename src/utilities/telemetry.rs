@@ -0,0 +1,246 @@
+use std::sync::atomic::{ AtomicU64, Ordering };
+
+use serde::{ Serialize, Deserialize };
+
+pub const TELEMETRY_REPORT_INTERVAL: f64 = 60.0; // seconds
+
+// the classes of the resource lifecycle counters, the vulkan objects are counted in the create and destroy functions of vulkan_context.
+// the engine has no particle system, the project counts its emitters with record_created(ResourceClass::Emitter).
+#[derive(Serialize, Deserialize, Clone, Debug, Copy, PartialEq, Eq)]
+pub enum ResourceClass {
+    Texture,
+    Buffer,
+    Framebuffer,
+    DescriptorSet,
+    Pipeline,
+    GeometryBuffer,
+    RenderObject,
+    Emitter,
+}
+
+pub const RESOURCE_CLASS_COUNT: usize = 8;
+pub const RESOURCE_CLASSES: [ResourceClass; RESOURCE_CLASS_COUNT] = [
+    ResourceClass::Texture,
+    ResourceClass::Buffer,
+    ResourceClass::Framebuffer,
+    ResourceClass::DescriptorSet,
+    ResourceClass::Pipeline,
+    ResourceClass::GeometryBuffer,
+    ResourceClass::RenderObject,
+    ResourceClass::Emitter,
+];
+
+type ResourceCounts = [AtomicU64; RESOURCE_CLASS_COUNT];
+
+const fn create_resource_counts() -> ResourceCounts {
+    [
+        AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0),
+        AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0),
+    ]
+}
+
+struct ResourceCounters {
+    _created_counts: ResourceCounts,
+    _destroyed_counts: ResourceCounts,
+    _high_water_marks: ResourceCounts,
+}
+
+const fn create_resource_counters() -> ResourceCounters {
+    ResourceCounters {
+        _created_counts: create_resource_counts(),
+        _destroyed_counts: create_resource_counts(),
+        _high_water_marks: create_resource_counts(),
+    }
+}
+
+#[cfg(not(test))]
+static RESOURCE_COUNTERS: ResourceCounters = create_resource_counters();
+
+#[cfg(not(test))]
+fn with_resource_counters<T, F: FnOnce(&ResourceCounters) -> T>(f: F) -> T {
+    f(&RESOURCE_COUNTERS)
+}
+
+// per thread in the unit tests, the tests running in parallel don't count the resources of each other
+#[cfg(test)]
+thread_local! {
+    static RESOURCE_COUNTERS: ResourceCounters = create_resource_counters();
+}
+
+#[cfg(test)]
+fn with_resource_counters<T, F: FnOnce(&ResourceCounters) -> T>(f: F) -> T {
+    RESOURCE_COUNTERS.with(f)
+}
+
+pub fn record_created(resource_class: ResourceClass, count: u64) {
+    with_resource_counters(|resource_counters| {
+        let index = resource_class as usize;
+        let created_count = resource_counters._created_counts[index].fetch_add(count, Ordering::Relaxed) + count;
+        let outstanding_count = created_count.saturating_sub(resource_counters._destroyed_counts[index].load(Ordering::Relaxed));
+        resource_counters._high_water_marks[index].fetch_max(outstanding_count, Ordering::Relaxed);
+    })
+}
+
+pub fn record_destroyed(resource_class: ResourceClass, count: u64) {
+    with_resource_counters(|resource_counters| {
+        resource_counters._destroyed_counts[resource_class as usize].fetch_add(count, Ordering::Relaxed);
+    })
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ResourceTelemetry {
+    pub _resource_class: ResourceClass,
+    pub _created_count: u64,
+    pub _destroyed_count: u64,
+    pub _outstanding_count: u64,
+    pub _high_water_mark: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct Telemetry {
+    pub _resources: Vec<ResourceTelemetry>, // in the order of RESOURCE_CLASSES
+}
+
+impl Telemetry {
+    pub fn get_outstanding_count(&self, resource_class: ResourceClass) -> u64 {
+        self._resources[resource_class as usize]._outstanding_count
+    }
+
+    // the classes of which the outstanding count is more than the baseline
+    pub fn get_grown_resource_classes(&self, baseline: &Telemetry) -> Vec<ResourceClass> {
+        self._resources.iter().zip(baseline._resources.iter()).filter(|(resource, baseline_resource)| {
+            baseline_resource._outstanding_count < resource._outstanding_count
+        }).map(|(resource, _)| resource._resource_class).collect()
+    }
+
+    pub fn to_log_string(&self) -> String {
+        let resources: Vec<String> = self._resources.iter().map(|resource| {
+            format!("{:?} {}(+{} -{} max {})", resource._resource_class, resource._outstanding_count, resource._created_count, resource._destroyed_count, resource._high_water_mark)
+        }).collect();
+        resources.join(", ")
+    }
+}
+
+// a snapshot of the counters
+pub fn get_telemetry() -> Telemetry {
+    with_resource_counters(|resource_counters| Telemetry {
+        _resources: RESOURCE_CLASSES.iter().map(|resource_class| {
+            let index = *resource_class as usize;
+            let created_count = resource_counters._created_counts[index].load(Ordering::Relaxed);
+            let destroyed_count = resource_counters._destroyed_counts[index].load(Ordering::Relaxed);
+            ResourceTelemetry {
+                _resource_class: *resource_class,
+                _created_count: created_count,
+                _destroyed_count: destroyed_count,
+                _outstanding_count: created_count.saturating_sub(destroyed_count),
+                _high_water_mark: resource_counters._high_water_marks[index].load(Ordering::Relaxed),
+            }
+        }).collect()
+    })
+}
+
+// the engine has no http server, the project serves this from its stats endpoint.
+pub fn get_telemetry_json() -> String {
+    serde_json::to_string(&get_telemetry()).unwrap()
+}
+
+// the scene content at the last report, the growth is reported only when the content has not been changed.
+// ex) scene name and the count of the render objects, see SceneManagerData::get_scene_content_key
+pub type SceneContentKey = (String, usize);
+
+pub struct TelemetryReporter {
+    pub _last_report_time: f64,
+    pub _last_telemetry: Telemetry,
+    pub _last_scene_content_key: Option<SceneContentKey>,
+}
+
+impl TelemetryReporter {
+    pub fn create_telemetry_reporter(elapsed_time: f64) -> TelemetryReporter {
+        TelemetryReporter {
+            _last_report_time: elapsed_time,
+            _last_telemetry: get_telemetry(),
+            _last_scene_content_key: None,
+        }
+    }
+
+    // once per TELEMETRY_REPORT_INTERVAL, logs when any outstanding count grew with the same scene content.
+    pub fn update_telemetry_reporter(&mut self, elapsed_time: f64, scene_content_key: &SceneContentKey) {
+        if elapsed_time < self._last_report_time + TELEMETRY_REPORT_INTERVAL {
+            return;
+        }
+        let telemetry = get_telemetry();
+        if Some(scene_content_key) == self._last_scene_content_key.as_ref() {
+            let grown_resource_classes = telemetry.get_grown_resource_classes(&self._last_telemetry);
+            if false == grown_resource_classes.is_empty() {
+                log::warn!("telemetry: outstanding resources grew with the same scene content {:?}: {}", grown_resource_classes, telemetry.to_log_string());
+            }
+        }
+        self._last_report_time = elapsed_time;
+        self._last_telemetry = telemetry;
+        self._last_scene_content_key = Some(scene_content_key.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants;
+
+    const RENDER_TARGET_COUNT: u64 = 24;
+    const RENDER_PASS_COUNT: u64 = 16;
+
+    // the counts of RendererData::resize_window without the device, every class recreated with the render targets
+    fn create_graphics_datas() {
+        let swapchain_image_count = constants::SWAPCHAIN_IMAGE_COUNT as u64;
+        record_created(ResourceClass::Texture, RENDER_TARGET_COUNT);
+        record_created(ResourceClass::Pipeline, RENDER_PASS_COUNT);
+        record_created(ResourceClass::Framebuffer, RENDER_PASS_COUNT * swapchain_image_count);
+        record_created(ResourceClass::DescriptorSet, RENDER_PASS_COUNT * swapchain_image_count);
+    }
+
+    fn destroy_graphics_datas(leaked_framebuffer_count: u64) {
+        let swapchain_image_count = constants::SWAPCHAIN_IMAGE_COUNT as u64;
+        record_destroyed(ResourceClass::Framebuffer, RENDER_PASS_COUNT * swapchain_image_count - leaked_framebuffer_count);
+        record_destroyed(ResourceClass::DescriptorSet, RENDER_PASS_COUNT * swapchain_image_count);
+        record_destroyed(ResourceClass::Pipeline, RENDER_PASS_COUNT);
+        record_destroyed(ResourceClass::Texture, RENDER_TARGET_COUNT);
+    }
+
+    #[test]
+    fn test_resize_cycles() {
+        // the scene resources outlive the resizes
+        record_created(ResourceClass::Buffer, 8);
+        record_created(ResourceClass::GeometryBuffer, 4);
+        record_created(ResourceClass::RenderObject, 100);
+        record_created(ResourceClass::Emitter, 2);
+        create_graphics_datas();
+        let baseline = get_telemetry();
+
+        for _ in 0..100 {
+            destroy_graphics_datas(0);
+            create_graphics_datas();
+        }
+        let telemetry = get_telemetry();
+        for resource_class in RESOURCE_CLASSES.iter() {
+            assert_eq!(baseline.get_outstanding_count(*resource_class), telemetry.get_outstanding_count(*resource_class), "{:?}", resource_class);
+        }
+        assert!(telemetry.get_grown_resource_classes(&baseline).is_empty());
+        // everything is destroyed before it is created again, the peak is the one of the first load
+        for (resource, baseline_resource) in telemetry._resources.iter().zip(baseline._resources.iter()) {
+            assert_eq!(baseline_resource._high_water_mark, resource._high_water_mark);
+        }
+        assert_eq!(100 * RENDER_TARGET_COUNT, telemetry._resources[ResourceClass::Texture as usize]._destroyed_count);
+
+        // a framebuffer leaked by every resize is reported with the same scene content
+        let scene_content_key: SceneContentKey = (String::from("test_scene"), 100);
+        let mut telemetry_reporter = TelemetryReporter::create_telemetry_reporter(0.0);
+        telemetry_reporter.update_telemetry_reporter(TELEMETRY_REPORT_INTERVAL, &scene_content_key);
+        for _ in 0..100 {
+            destroy_graphics_datas(1);
+            create_graphics_datas();
+        }
+        let telemetry = get_telemetry();
+        assert_eq!(vec![ResourceClass::Framebuffer], telemetry.get_grown_resource_classes(&telemetry_reporter._last_telemetry));
+        assert_eq!(baseline.get_outstanding_count(ResourceClass::Framebuffer) + 100, telemetry.get_outstanding_count(ResourceClass::Framebuffer));
+    }
+}
@@ -0,0 +1,10 @@
+use std::fs;
+use std::path::PathBuf;
+
+// an empty directory in the temp directory per test and process, the files of the previous run are removed.
+pub fn create_test_directory(test_name: &str) -> PathBuf {
+    let test_directory = std::env::temp_dir().join(format!("{}_{}", test_name, std::process::id()));
+    let _result = fs::remove_dir_all(&test_directory);
+    fs::create_dir_all(&test_directory).unwrap();
+    test_directory
+}
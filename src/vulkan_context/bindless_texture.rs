@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+use std::os::raw::c_void;
+
+use ash::{ vk, Device, Instance };
+use ash::version::{ DeviceV1_0, InstanceV1_1 };
+
+use crate::constants;
+use crate::vulkan_context::descriptor;
+use crate::vulkan_context::object_name;
+use crate::vulkan_context::texture::TextureData;
+
+pub const BINDLESS_TEXTURE_ARRAY_NAME: &str = "BindlessTextureArray";
+pub const INVALID_BINDLESS_TEXTURE_INDEX: u32 = std::u32::MAX;
+pub const BINDLESS_TEXTURE_DESCRIPTOR_SET_INDEX: u32 = 1; // set 0 is the descriptor set of the material instance
+pub const BINDLESS_TEXTURE_BINDING_INDEX: u32 = 0;
+// the sampled images of the material instance descriptor set in the same stage
+pub const BINDLESS_TEXTURE_RESERVED_DESCRIPTOR_COUNT: u32 = 64;
+
+// the per instance BindlessMaterialData of RendererData::render_solid_instanced, indexed as the instance matrices
+pub const BINDLESS_MATERIAL_BUFFER_NAME: &str = "BindlessMaterialBuffer";
+// the material parameters resolved to the texture indices, see Resources::get_bindless_material_data
+pub const BINDLESS_ALBEDO_TEXTURE_PARAMETER_NAME: &str = "textureBase";
+pub const BINDLESS_NORMAL_TEXTURE_PARAMETER_NAME: &str = "textureNormal";
+pub const BINDLESS_MATERIAL_TEXTURE_PARAMETER_NAME: &str = "textureMaterial";
+
+// must match with the bindless material of render_object_common.glsl
+#[derive(Clone, Debug, Copy, PartialEq)]
+pub struct BindlessMaterialData {
+    pub _albedo_texture_index: u32,
+    pub _normal_texture_index: u32,
+    pub _material_texture_index: u32, // roughness, metallic, ..
+    pub _reserved0: u32,
+}
+
+impl Default for BindlessMaterialData {
+    fn default() -> BindlessMaterialData {
+        BindlessMaterialData {
+            _albedo_texture_index: INVALID_BINDLESS_TEXTURE_INDEX,
+            _normal_texture_index: INVALID_BINDLESS_TEXTURE_INDEX,
+            _material_texture_index: INVALID_BINDLESS_TEXTURE_INDEX,
+            _reserved0: 0,
+        }
+    }
+}
+
+// the 2d textures of the resources in one descriptor array, the opaque pipelines index it by the bindless material of the object.
+// the slots are kept by the texture name, so the reloaded textures are rewritten in place.
+#[derive(Clone, Debug)]
+pub struct BindlessTextureArray {
+    pub _descriptor_pool: vk::DescriptorPool,
+    pub _descriptor_set_layout: vk::DescriptorSetLayout,
+    pub _descriptor_set: vk::DescriptorSet,
+    pub _max_texture_count: u32,
+    pub _texture_index_map: HashMap<String, u32>,
+    pub _free_texture_indices: Vec<u32>,
+    pub _next_texture_index: u32,
+}
+
+// returns (is_supported, max count of the bindless textures)
+pub fn query_descriptor_indexing_support(
+    instance: &Instance,
+    physical_device: vk::PhysicalDevice,
+    device_properties: &vk::PhysicalDeviceProperties
+) -> (bool, u32) {
+    if false == unsafe { constants::ENABLE_BINDLESS_TEXTURES } || device_properties.api_version < vk::make_version(1, 2, 0) {
+        return (false, 0);
+    }
+
+    let mut descriptor_indexing_features = vk::PhysicalDeviceDescriptorIndexingFeatures::default();
+    let mut physical_device_features2 = vk::PhysicalDeviceFeatures2 {
+        p_next: &mut descriptor_indexing_features as *mut vk::PhysicalDeviceDescriptorIndexingFeatures as *mut c_void,
+        ..Default::default()
+    };
+    let mut descriptor_indexing_properties = vk::PhysicalDeviceDescriptorIndexingProperties::default();
+    let mut physical_device_properties2 = vk::PhysicalDeviceProperties2 {
+        p_next: &mut descriptor_indexing_properties as *mut vk::PhysicalDeviceDescriptorIndexingProperties as *mut c_void,
+        ..Default::default()
+    };
+    unsafe {
+        instance.get_physical_device_features2(physical_device, &mut physical_device_features2);
+        instance.get_physical_device_properties2(physical_device, &mut physical_device_properties2);
+    }
+
+    let max_texture_count = device_properties.limits.max_per_stage_descriptor_sampled_images
+        .min(descriptor_indexing_properties.max_per_stage_descriptor_update_after_bind_sampled_images)
+        .min(descriptor_indexing_properties.max_descriptor_set_update_after_bind_sampled_images)
+        .saturating_sub(BINDLESS_TEXTURE_RESERVED_DESCRIPTOR_COUNT)
+        .min(unsafe { constants::MAX_BINDLESS_TEXTURE_COUNT });
+    let is_supported = vk::TRUE == descriptor_indexing_features.shader_sampled_image_array_non_uniform_indexing
+        && vk::TRUE == descriptor_indexing_features.descriptor_binding_sampled_image_update_after_bind
+        && vk::TRUE == descriptor_indexing_features.descriptor_binding_partially_bound
+        && vk::TRUE == descriptor_indexing_features.runtime_descriptor_array
+        && 0 < max_texture_count;
+    log::info!("DescriptorIndexing: supported({}), max_bindless_texture_count({})", is_supported, max_texture_count);
+    (is_supported, if is_supported { max_texture_count } else { 0 })
+}
+
+// the descriptor indexing members of the vulkan 1.2 features, chained to the p_next of the device create info by device::create_device
+pub fn get_descriptor_indexing_features(p_next: *mut c_void, is_descriptor_indexing_supported: bool) -> vk::PhysicalDeviceVulkan12Features {
+    let enable = if is_descriptor_indexing_supported { vk::TRUE } else { vk::FALSE };
+    vk::PhysicalDeviceVulkan12Features {
+        p_next,
+        shader_sampled_image_array_non_uniform_indexing: enable,
+        descriptor_binding_sampled_image_update_after_bind: enable,
+        descriptor_binding_partially_bound: enable,
+        runtime_descriptor_array: enable,
+        ..Default::default()
+    }
+}
+
+// the combined image samplers of the 2d textures, the cube, array and 3d textures stay in the material instance descriptor set.
+pub fn is_bindless_texture(texture_data: &TextureData) -> bool {
+    vk::ImageViewType::TYPE_2D == texture_data._image_view_type && vk::Sampler::null() != texture_data._image_sampler
+}
+
+impl BindlessTextureArray {
+    pub fn create_bindless_texture_array(device: &Device, max_texture_count: u32) -> BindlessTextureArray {
+        log::info!("create_bindless_texture_array: {}", max_texture_count);
+        let layout_bindings = [vk::DescriptorSetLayoutBinding {
+            binding: BINDLESS_TEXTURE_BINDING_INDEX,
+            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            descriptor_count: max_texture_count,
+            stage_flags: vk::ShaderStageFlags::FRAGMENT,
+            ..Default::default()
+        }];
+        // the unused slots are never read, the slots are written while the set is bound by the pending frames.
+        let binding_flags = [vk::DescriptorBindingFlags::PARTIALLY_BOUND | vk::DescriptorBindingFlags::UPDATE_AFTER_BIND];
+        let binding_flags_create_info = vk::DescriptorSetLayoutBindingFlagsCreateInfo {
+            binding_count: binding_flags.len() as u32,
+            p_binding_flags: binding_flags.as_ptr(),
+            ..Default::default()
+        };
+        let layout_create_info = vk::DescriptorSetLayoutCreateInfo {
+            p_next: &binding_flags_create_info as *const vk::DescriptorSetLayoutBindingFlagsCreateInfo as *const c_void,
+            flags: vk::DescriptorSetLayoutCreateFlags::UPDATE_AFTER_BIND_POOL,
+            binding_count: layout_bindings.len() as u32,
+            p_bindings: layout_bindings.as_ptr(),
+            ..Default::default()
+        };
+        let pool_sizes = [vk::DescriptorPoolSize {
+            ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            descriptor_count: max_texture_count,
+        }];
+        let pool_create_info = vk::DescriptorPoolCreateInfo {
+            flags: vk::DescriptorPoolCreateFlags::UPDATE_AFTER_BIND,
+            pool_size_count: pool_sizes.len() as u32,
+            p_pool_sizes: pool_sizes.as_ptr(),
+            max_sets: 1,
+            ..Default::default()
+        };
+        unsafe {
+            let descriptor_set_layout = device.create_descriptor_set_layout(&layout_create_info, None).expect("vkCreateDescriptorSetLayout failed!");
+            let descriptor_pool = device.create_descriptor_pool(&pool_create_info, None).expect("vkCreateDescriptorPool failed!");
+            let set_layouts = [descriptor_set_layout];
+            let allocate_info = vk::DescriptorSetAllocateInfo {
+                descriptor_pool,
+                descriptor_set_count: set_layouts.len() as u32,
+                p_set_layouts: set_layouts.as_ptr(),
+                ..Default::default()
+            };
+            let descriptor_set = device.allocate_descriptor_sets(&allocate_info).expect("vkAllocateDescriptorSets failed!")[0];
+            object_name::set_object_name(descriptor_set, BINDLESS_TEXTURE_ARRAY_NAME);
+            BindlessTextureArray {
+                _descriptor_pool: descriptor_pool,
+                _descriptor_set_layout: descriptor_set_layout,
+                _descriptor_set: descriptor_set,
+                _max_texture_count: max_texture_count,
+                _texture_index_map: HashMap::new(),
+                _free_texture_indices: Vec::new(),
+                _next_texture_index: 0,
+            }
+        }
+    }
+
+    pub fn destroy_bindless_texture_array(&mut self, device: &Device) {
+        log::info!("destroy_bindless_texture_array");
+        // the descriptor set is freed with the pool
+        descriptor::destroy_descriptor_pool(device, self._descriptor_pool);
+        descriptor::destroy_descriptor_set_layout(device, self._descriptor_set_layout);
+        self._descriptor_pool = vk::DescriptorPool::null();
+        self._descriptor_set_layout = vk::DescriptorSetLayout::null();
+        self._descriptor_set = vk::DescriptorSet::null();
+        self._texture_index_map.clear();
+        self._free_texture_indices.clear();
+        self._next_texture_index = 0;
+    }
+
+    pub fn get_texture_count(&self) -> usize {
+        self._texture_index_map.len()
+    }
+
+    pub fn get_texture_index(&self, texture_name: &str) -> u32 {
+        *self._texture_index_map.get(texture_name).unwrap_or(&INVALID_BINDLESS_TEXTURE_INDEX)
+    }
+
+    // the slot of the same name is reused, INVALID_BINDLESS_TEXTURE_INDEX when the array is full
+    pub fn regist_texture(&mut self, texture_name: &str) -> u32 {
+        if let Some(texture_index) = self._texture_index_map.get(texture_name) {
+            return *texture_index;
+        }
+        let texture_index = match self._free_texture_indices.pop() {
+            Some(texture_index) => texture_index,
+            None if self._next_texture_index < self._max_texture_count => {
+                self._next_texture_index += 1;
+                self._next_texture_index - 1
+            },
+            None => {
+                log::warn!("regist_texture: the bindless texture array is full({}), {} uses the material instance descriptor set", self._max_texture_count, texture_name);
+                return INVALID_BINDLESS_TEXTURE_INDEX;
+            }
+        };
+        self._texture_index_map.insert(String::from(texture_name), texture_index);
+        texture_index
+    }
+
+    // the slot is partially bound, it is not read until the next texture overwrites it
+    pub fn unregist_texture(&mut self, texture_name: &str) {
+        if let Some(texture_index) = self._texture_index_map.remove(texture_name) {
+            self._free_texture_indices.push(texture_index);
+        }
+    }
+
+    pub fn update_texture_descriptors(&self, device: &Device, texture_image_infos: &[(u32, vk::DescriptorImageInfo)]) {
+        let write_descriptor_sets: Vec<vk::WriteDescriptorSet> = texture_image_infos.iter().map(|(texture_index, image_info)| {
+            vk::WriteDescriptorSet {
+                dst_set: self._descriptor_set,
+                dst_binding: BINDLESS_TEXTURE_BINDING_INDEX,
+                dst_array_element: *texture_index,
+                descriptor_count: 1,
+                descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                p_image_info: image_info,
+                ..Default::default()
+            }
+        }).collect();
+        if false == write_descriptor_sets.is_empty() {
+            unsafe {
+                device.update_descriptor_sets(&write_descriptor_sets, &[]);
+            }
+        }
+    }
+
+    pub fn bind_descriptor_set(&self, device: &Device, command_buffer: vk::CommandBuffer, pipeline_bind_point: vk::PipelineBindPoint, pipeline_layout: vk::PipelineLayout) {
+        let dynamic_offsets: &[u32] = &[];
+        unsafe {
+            device.cmd_bind_descriptor_sets(command_buffer, pipeline_bind_point, pipeline_layout, BINDLESS_TEXTURE_DESCRIPTOR_SET_INDEX, &[self._descriptor_set], dynamic_offsets);
+        }
+    }
+}
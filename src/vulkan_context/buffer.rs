@@ -1,4 +1,7 @@
+use std::collections::VecDeque;
 use std::mem;
+use std::os::raw::c_void;
+use std::sync::atomic::{ AtomicU64, Ordering };
 
 use ash::{
     vk,
@@ -11,13 +14,17 @@ use ash::util::Align;
 
 use crate::constants;
 use crate::vulkan_context::descriptor::DescriptorResourceInfo;
-use crate::vulkan_context::vulkan_context::{run_commands_once, SwapchainArray};
+use crate::vulkan_context::device_memory_allocator::{ self, MemoryAllocation, MemoryAllocationKind };
+use crate::vulkan_context::object_name;
+use crate::vulkan_context::uniform_arena::UniformArenaHandle;
+use crate::utilities::telemetry::{ self, ResourceClass };
+use crate::vulkan_context::vulkan_context::{ self, run_commands_once, SwapchainArray };
 
 #[derive(Debug, Clone, Copy)]
 pub struct BufferData {
     pub _buffer: vk::Buffer,
-    pub _buffer_memory: vk::DeviceMemory,
-    pub _buffer_memory_requirements: vk::MemoryRequirements
+    pub _buffer_memory_requirements: vk::MemoryRequirements,
+    pub _memory_allocation: MemoryAllocation, // a range of the device memory block, see device_memory_allocator
 }
 
 #[derive(Debug, Clone)]
@@ -28,14 +35,26 @@ pub struct ShaderBufferData {
     pub _descriptor_buffer_infos: SwapchainArray<DescriptorResourceInfo>,
     pub _staging_buffers: Option<SwapchainArray<BufferData>>,
     pub _is_single_index_buffer: bool,
+    pub _uniform_arena_handle: Option<UniformArenaHandle>, // Some: a block of the UniformArena, the buffers are owned by the arena
+}
+
+// vkMapMemory calls, see RendererData::get_map_memory_count. the host visible blocks of the device memory allocator are mapped once.
+static MAP_MEMORY_COUNT: AtomicU64 = AtomicU64::new(0);
+
+pub fn get_map_memory_count() -> u64 {
+    MAP_MEMORY_COUNT.load(Ordering::Relaxed)
+}
+
+pub fn add_map_memory_count() {
+    MAP_MEMORY_COUNT.fetch_add(1, Ordering::Relaxed);
 }
 
 impl Default for BufferData {
     fn default() -> BufferData {
         BufferData {
             _buffer: vk::Buffer::null(),
-            _buffer_memory: vk::DeviceMemory::null(),
             _buffer_memory_requirements: vk::MemoryRequirements::default(),
+            _memory_allocation: MemoryAllocation::default(),
         }
     }
 }
@@ -110,6 +129,31 @@ pub fn create_buffer_data_with_uploads<T: Copy>(
     dst_buffer_data
 }
 
+// the staging regions come from the StagingBufferPool, the copy is recorded into the upload command buffer of the pool.
+// the buffer can be used after StagingBufferPool::flush_uploads.
+pub fn create_buffer_data_with_staging_pool<T: Copy>(
+    device: &Device,
+    device_memory_properties: &vk::PhysicalDeviceMemoryProperties,
+    staging_buffer_pool: &mut StagingBufferPool,
+    dst_buffer_type: vk::BufferUsageFlags,
+    upload_datas: &[T],
+) -> BufferData {
+    let buffer_size = (mem::size_of::<T>() * upload_datas.len()) as vk::DeviceSize;
+    let buffer_usage_flags = dst_buffer_type | vk::BufferUsageFlags::TRANSFER_SRC | vk::BufferUsageFlags::TRANSFER_DST;
+    log::trace!("CreateBuffer: type({:?}), size({})", dst_buffer_type, buffer_size);
+    let dst_buffer_data = create_buffer_data(
+        device,
+        device_memory_properties,
+        buffer_size,
+        buffer_usage_flags,
+        vk::MemoryPropertyFlags::DEVICE_LOCAL
+    );
+    let staging_region = staging_buffer_pool.allocate_upload(device, device_memory_properties, upload_datas, STAGING_BUFFER_ALIGNMENT);
+    let command_buffer = staging_buffer_pool.get_upload_command_buffer(device);
+    copy_buffer_offset(device, command_buffer, staging_region._buffer, staging_region._offset, dst_buffer_data._buffer, 0, buffer_size);
+    dst_buffer_data
+}
+
 pub fn create_buffer_data(
     device: &Device,
     memory_properties: &vk::PhysicalDeviceMemoryProperties,
@@ -128,76 +172,74 @@ pub fn create_buffer_data(
         let buffer_memory_requirements = device.get_buffer_memory_requirements(buffer);
         let memory_type_index = find_memory_type_index(&buffer_memory_requirements, memory_properties, memory_property_flags)
             .expect("Unable to find suitable memorytype for the vertex buffer.");
-        let memory_allocate_info = vk::MemoryAllocateInfo {
-            allocation_size: buffer_memory_requirements.size,
-            memory_type_index,
-            ..Default::default()
-        };
-        let buffer_memory = device.allocate_memory(&memory_allocate_info, None).expect("vkAllocateMemory failed!");
-        device.bind_buffer_memory(buffer, buffer_memory, 0).unwrap();
+        let memory_allocation = device_memory_allocator::allocate_memory(device, &buffer_memory_requirements, memory_type_index, MemoryAllocationKind::Linear);
+        vulkan_context::add_allocated_memory_size(buffer_memory_requirements.size);
+        device.bind_buffer_memory(buffer, memory_allocation._memory, memory_allocation._offset).unwrap();
 
-        log::trace!("    Create Buffer ({:?}): buffer({:?}), memory({:?})", buffer_usage_flags, buffer, buffer_memory);
+        telemetry::record_created(ResourceClass::Buffer, 1);
+        log::trace!("    Create Buffer ({:?}): buffer({:?}), memory({:?}), offset({})", buffer_usage_flags, buffer, memory_allocation._memory, memory_allocation._offset);
         log::trace!("        buffer_size: {:?}", buffer_size);
         log::trace!("        memory_type_index: {:?}", memory_type_index);
         log::trace!("        memory_requirements: {:?}", buffer_memory_requirements);
 
         BufferData {
             _buffer: buffer,
-            _buffer_memory: buffer_memory,
             _buffer_memory_requirements: buffer_memory_requirements,
+            _memory_allocation: memory_allocation,
         }
     }
 }
 
 pub fn destroy_buffer_data(device: &Device, buffer_data: &BufferData) {
     unsafe {
-        log::trace!("    Destroy Buffer: buffer({:?}), memory({:?})", buffer_data._buffer, buffer_data._buffer_memory);
+        log::trace!("    Destroy Buffer: buffer({:?}), memory({:?})", buffer_data._buffer, buffer_data._memory_allocation);
+        object_name::remove_object_name(buffer_data._buffer);
         device.destroy_buffer(buffer_data._buffer, None);
-        device.free_memory(buffer_data._buffer_memory, None);
+        device_memory_allocator::free_memory(device, &buffer_data._memory_allocation);
+        vulkan_context::remove_allocated_memory_size(buffer_data._buffer_memory_requirements.size);
     }
+    telemetry::record_destroyed(ResourceClass::Buffer, 1);
 }
 
-pub fn upload_buffer_data<T: Copy> (device: &Device, buffer_data: &BufferData, upload_data: &[T]) {
-    unsafe {
-        let upload_data_size = std::mem::size_of::<T>() as u64 * upload_data.len() as u64;
-        assert!(upload_data_size <= buffer_data._buffer_memory_requirements.size);
-        let buffer_ptr = device.map_memory(buffer_data._buffer_memory, 0, upload_data_size, vk::MemoryMapFlags::empty()).unwrap();
-        let mut slice = Align::new(
+// the host visible buffers are mapped with their block, see device_memory_allocator::create_memory_block
+pub fn upload_buffer_data<T: Copy> (_device: &Device, buffer_data: &BufferData, upload_data: &[T]) {
+    let upload_data_size = std::mem::size_of::<T>() as u64 * upload_data.len() as u64;
+    assert!(upload_data_size <= buffer_data._buffer_memory_requirements.size);
+    let buffer_ptr = buffer_data._memory_allocation.get_mapped_ptr(0);
+    let mut slice = unsafe {
+        Align::new(
             buffer_ptr,
             std::mem::align_of::<T>() as u64,
             upload_data_size,
-        );
-        slice.copy_from_slice(upload_data);
-        device.unmap_memory(buffer_data._buffer_memory);
-    }
+        )
+    };
+    slice.copy_from_slice(upload_data);
 }
 
-pub fn read_buffer_data<T: Copy> (device: &Device, buffer_data: &BufferData, read_offset: u32, read_data: &mut [T]) {
+pub fn read_buffer_data<T: Copy> (_device: &Device, buffer_data: &BufferData, read_offset: u32, read_data: &mut [T]) {
     unsafe {
         let read_data_count = read_data.len();
         let read_data_size = std::mem::size_of::<T>() as u64 * read_data_count as u64;
         let offset = std::mem::size_of::<T>() as u64 * read_offset as u64;
         assert!(read_data_size <= buffer_data._buffer_memory_requirements.size);
-        let buffer_ptr = device.map_memory(buffer_data._buffer_memory, offset, read_data_size, vk::MemoryMapFlags::empty()).unwrap();
-        let raw_data = std::ptr::slice_from_raw_parts(buffer_ptr, read_data_count) as *const [T];
+        let buffer_ptr = buffer_data._memory_allocation.get_mapped_ptr(offset) as *const T;
+        let raw_data = std::ptr::slice_from_raw_parts(buffer_ptr, read_data_count);
         read_data.clone_from_slice(&*raw_data);
-        device.unmap_memory(buffer_data._buffer_memory);
     }
 }
 
-pub fn upload_buffer_data_offset<T: Copy> (device: &Device, buffer_data: &BufferData, upload_data: &[T], offset: vk::DeviceSize) {
-    unsafe {
-        let upload_data_size = std::mem::size_of::<T>() as u64 * upload_data.len() as u64;
-        assert!((upload_data_size + offset) <= buffer_data._buffer_memory_requirements.size);
-        let buffer_ptr = device.map_memory(buffer_data._buffer_memory, offset, upload_data_size, vk::MemoryMapFlags::empty()).unwrap();
-        let mut slice = Align::new(
+pub fn upload_buffer_data_offset<T: Copy> (_device: &Device, buffer_data: &BufferData, upload_data: &[T], offset: vk::DeviceSize) {
+    let upload_data_size = std::mem::size_of::<T>() as u64 * upload_data.len() as u64;
+    assert!((upload_data_size + offset) <= buffer_data._buffer_memory_requirements.size);
+    let buffer_ptr = buffer_data._memory_allocation.get_mapped_ptr(offset);
+    let mut slice = unsafe {
+        Align::new(
             buffer_ptr,
             std::mem::align_of::<T>() as u64,
             upload_data_size,
-        );
-        slice.copy_from_slice(upload_data);
-        device.unmap_memory(buffer_data._buffer_memory);
-    }
+        )
+    };
+    slice.copy_from_slice(upload_data);
 }
 
 pub fn copy_buffer_region(
@@ -247,6 +289,17 @@ pub fn copy_buffer_offset(
 
 
 // ShaderBufferData
+// the buffers of a swapchain array are "name[swapchain_index]"
+pub fn set_buffer_object_names(buffers: &[BufferData], buffer_name: &str, is_single_index_buffer: bool) {
+    for (index, buffer_data) in buffers.iter().enumerate() {
+        let object_name = if is_single_index_buffer { String::from(buffer_name) } else { format!("{}[{}]", buffer_name, index) };
+        object_name::set_object_name(buffer_data._buffer, &object_name);
+        if is_single_index_buffer {
+            break;
+        }
+    }
+}
+
 pub fn create_shader_buffer_data(
     device: &Device,
     memory_properties: &vk::PhysicalDeviceMemoryProperties,
@@ -301,6 +354,11 @@ pub fn create_shader_buffer_data(
         None
     };
 
+    set_buffer_object_names(&buffers, buffer_name, is_single_index_buffer);
+    if let Some(staging_buffers) = staging_buffers.as_ref() {
+        set_buffer_object_names(staging_buffers, &format!("{}/staging", buffer_name), is_single_index_buffer);
+    }
+
     let descriptor_buffer_infos: SwapchainArray<DescriptorResourceInfo> =
         buffers.iter().map(|buffer_data| {
             DescriptorResourceInfo::DescriptorBufferInfo(
@@ -319,11 +377,16 @@ pub fn create_shader_buffer_data(
         _descriptor_buffer_infos: descriptor_buffer_infos,
         _staging_buffers: staging_buffers,
         _is_single_index_buffer: is_single_index_buffer,
+        _uniform_arena_handle: None,
     }
 }
 
 pub fn destroy_shader_buffer_data(device: &Device, uniform_buffer_data: &mut ShaderBufferData) {
     log::debug!("destroy_shader_buffer_data: {:?}", uniform_buffer_data._buffer_name);
+    if uniform_buffer_data._uniform_arena_handle.is_some() {
+        uniform_buffer_data._buffers.clear();
+        return;
+    }
     let buffer_count = if uniform_buffer_data._is_single_index_buffer {
         1
     } else {
@@ -344,3 +407,304 @@ pub fn destroy_shader_buffer_data(device: &Device, uniform_buffer_data: &mut Sha
     }
     uniform_buffer_data._staging_buffers = None;
 }
+
+
+// StagingBufferPool
+pub const STAGING_BUFFER_BLOCK_SIZE: vk::DeviceSize = 16 * 1024 * 1024;
+pub const STAGING_BUFFER_MAX_IDLE_BLOCK_COUNT: usize = 4;
+pub const STAGING_BUFFER_ALIGNMENT: vk::DeviceSize = 16; // the block size of the compressed formats, a multiple of 4 for vkCmdCopyBufferToImage
+
+#[derive(Debug, Clone, Copy)]
+pub struct StagingRegion {
+    pub _buffer: vk::Buffer,
+    pub _offset: vk::DeviceSize,
+    pub _size: vk::DeviceSize,
+}
+
+// a persistently mapped HOST_VISIBLE buffer, the regions are sub-allocated as a ring.
+// [_tail, _head) is in use, wrapped: [_tail, end) and [0, _head) are in use.
+pub struct StagingBufferBlock {
+    pub _buffer_data: BufferData,
+    pub _buffer_size: vk::DeviceSize,
+    pub _mapped_ptr: *mut c_void,
+    pub _head: vk::DeviceSize,
+    pub _tail: vk::DeviceSize,
+    pub _is_wrapped: bool,
+    pub _has_unsubmitted_regions: bool,
+    pub _submission_marks: VecDeque<(u64, vk::DeviceSize)>, // (submission index, _head at the submission)
+}
+
+// the upload regions and the upload command buffer of the textures and the geometries.
+// the uploads recorded between begin_upload_batch and end_upload_batch are submitted once with a single fence wait.
+pub struct StagingBufferPool {
+    pub _blocks: Vec<StagingBufferBlock>,
+    pub _command_pool: vk::CommandPool,
+    pub _upload_command_buffer: vk::CommandBuffer, // null: nothing recorded
+    pub _upload_fence: vk::Fence,
+    pub _is_upload_batch: bool,
+    pub _upload_count: u32, // since the last flush
+    pub _submission_index: u64,
+}
+
+pub fn align_staging_offset(offset: vk::DeviceSize, alignment: vk::DeviceSize) -> vk::DeviceSize {
+    let alignment = std::cmp::max(1, alignment);
+    ((offset + alignment - 1) / alignment) * alignment
+}
+
+impl StagingBufferBlock {
+    pub fn create_staging_buffer_block(device: &Device, memory_properties: &vk::PhysicalDeviceMemoryProperties, buffer_size: vk::DeviceSize) -> StagingBufferBlock {
+        let buffer_data = create_buffer_data(
+            device,
+            memory_properties,
+            buffer_size,
+            vk::BufferUsageFlags::TRANSFER_SRC,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT
+        );
+        let mapped_ptr = buffer_data._memory_allocation.get_mapped_ptr(0);
+        log::debug!("create_staging_buffer_block: {} bytes", buffer_size);
+        StagingBufferBlock {
+            _buffer_data: buffer_data,
+            _buffer_size: buffer_size,
+            _mapped_ptr: mapped_ptr,
+            _head: 0,
+            _tail: 0,
+            _is_wrapped: false,
+            _has_unsubmitted_regions: false,
+            _submission_marks: VecDeque::new(),
+        }
+    }
+
+    pub fn destroy_staging_buffer_block(&mut self, device: &Device) {
+        self._mapped_ptr = std::ptr::null_mut();
+        destroy_buffer_data(device, &self._buffer_data);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self._submission_marks.is_empty() && false == self._has_unsubmitted_regions
+    }
+
+    pub fn get_largest_free_size(&self) -> vk::DeviceSize {
+        if self.is_empty() {
+            self._buffer_size
+        } else if self._is_wrapped {
+            self._tail - self._head
+        } else {
+            std::cmp::max(self._buffer_size - self._head, self._tail)
+        }
+    }
+
+    pub fn try_allocate(&mut self, size: vk::DeviceSize, alignment: vk::DeviceSize) -> Option<vk::DeviceSize> {
+        if self.is_empty() {
+            self._head = 0;
+            self._tail = 0;
+            self._is_wrapped = false;
+        }
+        let aligned_head = align_staging_offset(self._head, alignment);
+        let offset = if self._is_wrapped {
+            if aligned_head + size <= self._tail { aligned_head } else { return None; }
+        } else if aligned_head + size <= self._buffer_size {
+            aligned_head
+        } else if size <= self._tail {
+            // the rest of the block is released with the regions before it
+            self._is_wrapped = true;
+            0
+        } else {
+            return None;
+        };
+        self._head = offset + size;
+        self._has_unsubmitted_regions = true;
+        Some(offset)
+    }
+
+    pub fn mark_submission(&mut self, submission_index: u64) {
+        if self._has_unsubmitted_regions {
+            self._submission_marks.push_back((submission_index, self._head));
+            self._has_unsubmitted_regions = false;
+        }
+    }
+
+    pub fn release_submission(&mut self, submission_index: u64) {
+        while let Some(&(mark_submission_index, mark_head)) = self._submission_marks.front() {
+            if submission_index < mark_submission_index {
+                break;
+            }
+            if mark_head < self._tail {
+                self._is_wrapped = false;
+            }
+            self._tail = mark_head;
+            self._submission_marks.pop_front();
+        }
+        if self.is_empty() {
+            self._head = 0;
+            self._tail = 0;
+            self._is_wrapped = false;
+        }
+    }
+}
+
+impl StagingBufferPool {
+    pub fn create_staging_buffer_pool(device: &Device, memory_properties: &vk::PhysicalDeviceMemoryProperties, command_pool: vk::CommandPool) -> StagingBufferPool {
+        let upload_fence = unsafe {
+            device.create_fence(&vk::FenceCreateInfo::default(), None).expect("vkCreateFence failed!")
+        };
+        StagingBufferPool {
+            _blocks: vec![StagingBufferBlock::create_staging_buffer_block(device, memory_properties, STAGING_BUFFER_BLOCK_SIZE)],
+            _command_pool: command_pool,
+            _upload_command_buffer: vk::CommandBuffer::null(),
+            _upload_fence: upload_fence,
+            _is_upload_batch: false,
+            _upload_count: 0,
+            _submission_index: 0,
+        }
+    }
+
+    pub fn destroy_staging_buffer_pool(&mut self, device: &Device) {
+        unsafe {
+            if vk::CommandBuffer::null() != self._upload_command_buffer {
+                device.free_command_buffers(self._command_pool, &[self._upload_command_buffer]);
+                self._upload_command_buffer = vk::CommandBuffer::null();
+            }
+            device.destroy_fence(self._upload_fence, None);
+        }
+        for block in self._blocks.iter_mut() {
+            block.destroy_staging_buffer_block(device);
+        }
+        self._blocks.clear();
+    }
+
+    pub fn is_upload_batch(&self) -> bool {
+        self._is_upload_batch
+    }
+
+    pub fn get_staging_buffer_size(&self) -> vk::DeviceSize {
+        self._blocks.iter().map(|block| block._buffer_size).sum()
+    }
+
+    pub fn begin_upload_batch(&mut self) {
+        self._is_upload_batch = true;
+    }
+
+    pub fn end_upload_batch(&mut self, device: &Device, command_queue: vk::Queue) {
+        self._is_upload_batch = false;
+        self.flush_uploads(device, command_queue);
+    }
+
+    // the data is copied to a region of a mapped block, the pool grows if no block has a free region large enough.
+    pub fn allocate_upload<T: Copy>(
+        &mut self,
+        device: &Device,
+        memory_properties: &vk::PhysicalDeviceMemoryProperties,
+        upload_datas: &[T],
+        alignment: vk::DeviceSize
+    ) -> StagingRegion {
+        let upload_size = (mem::size_of::<T>() * upload_datas.len()) as vk::DeviceSize;
+        let mut allocation: Option<(usize, vk::DeviceSize)> = None;
+        for (block_index, block) in self._blocks.iter_mut().enumerate() {
+            if upload_size <= block.get_largest_free_size() {
+                if let Some(offset) = block.try_allocate(upload_size, alignment) {
+                    allocation = Some((block_index, offset));
+                    break;
+                }
+            }
+        }
+        let (block_index, offset) = match allocation {
+            Some(allocation) => allocation,
+            None => {
+                let block_size = std::cmp::max(STAGING_BUFFER_BLOCK_SIZE, upload_size.next_power_of_two());
+                log::info!("StagingBufferPool grows: upload({} bytes), block({} bytes)", upload_size, block_size);
+                let mut block = StagingBufferBlock::create_staging_buffer_block(device, memory_properties, block_size);
+                let offset = block.try_allocate(upload_size, alignment).unwrap();
+                self._blocks.push(block);
+                (self._blocks.len() - 1, offset)
+            }
+        };
+        let block = &self._blocks[block_index];
+        unsafe {
+            let dst_ptr = (block._mapped_ptr as *mut u8).add(offset as usize);
+            std::ptr::copy_nonoverlapping(upload_datas.as_ptr() as *const u8, dst_ptr, upload_size as usize);
+        }
+        self._upload_count += 1;
+        StagingRegion {
+            _buffer: block._buffer_data._buffer,
+            _offset: offset,
+            _size: upload_size,
+        }
+    }
+
+    // the command buffer on the graphics queue, recording until flush_uploads.
+    pub fn get_upload_command_buffer(&mut self, device: &Device) -> vk::CommandBuffer {
+        if vk::CommandBuffer::null() == self._upload_command_buffer {
+            unsafe {
+                let allocate_info = vk::CommandBufferAllocateInfo {
+                    level: vk::CommandBufferLevel::PRIMARY,
+                    command_pool: self._command_pool,
+                    command_buffer_count: 1,
+                    ..Default::default()
+                };
+                self._upload_command_buffer = device.allocate_command_buffers(&allocate_info).expect("vkAllocateCommandBuffers failed!")[0];
+                let command_buffer_begin_info = vk::CommandBufferBeginInfo {
+                    flags: vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT,
+                    ..Default::default()
+                };
+                device.begin_command_buffer(self._upload_command_buffer, &command_buffer_begin_info).expect("Failed to begin_command_buffer");
+            }
+        }
+        self._upload_command_buffer
+    }
+
+    // submits the recorded uploads and waits once, then every region is released.
+    // the regions submitted on the other queues are waited before end_upload_batch. ex) TextureUploadBatch
+    pub fn flush_uploads(&mut self, device: &Device, command_queue: vk::Queue) {
+        if vk::CommandBuffer::null() != self._upload_command_buffer {
+            unsafe {
+                let command_buffers = [self._upload_command_buffer];
+                device.end_command_buffer(self._upload_command_buffer).expect("Failed to end_command_buffer");
+                let submit_info = vk::SubmitInfo {
+                    command_buffer_count: command_buffers.len() as u32,
+                    p_command_buffers: command_buffers.as_ptr(),
+                    ..Default::default()
+                };
+                device.queue_submit(command_queue, &[submit_info], self._upload_fence).expect("vkQueueSubmit failed!");
+                device.wait_for_fences(&[self._upload_fence], true, std::u64::MAX).expect("vkWaitForFences failed!");
+                device.reset_fences(&[self._upload_fence]).expect("vkResetFences failed!");
+                device.free_command_buffers(self._command_pool, &command_buffers);
+            }
+            self._upload_command_buffer = vk::CommandBuffer::null();
+        }
+
+        // in the upload batch, the regions of the TextureUploadBatch are not submitted yet, they are released at end_upload_batch
+        self._submission_index += 1;
+        let submission_index = self._submission_index;
+        for block in self._blocks.iter_mut() {
+            block.mark_submission(submission_index);
+            if false == self._is_upload_batch {
+                block.release_submission(submission_index);
+            }
+        }
+        if false == self._is_upload_batch {
+            if 1 < self._upload_count {
+                log::debug!("StagingBufferPool::flush_uploads: {} uploads", self._upload_count);
+            }
+            self._upload_count = 0;
+            self.trim_idle_blocks(device);
+        }
+    }
+
+    // the grown blocks larger than the default are released, a few default blocks are kept mapped.
+    fn trim_idle_blocks(&mut self, device: &Device) {
+        let mut idle_block_count: usize = 0;
+        let mut index: usize = 0;
+        while index < self._blocks.len() {
+            let block = &mut self._blocks[index];
+            if block.is_empty() && (STAGING_BUFFER_BLOCK_SIZE < block._buffer_size || STAGING_BUFFER_MAX_IDLE_BLOCK_COUNT <= idle_block_count) {
+                block.destroy_staging_buffer_block(device);
+                self._blocks.remove(index);
+                continue;
+            }
+            if block.is_empty() {
+                idle_block_count += 1;
+            }
+            index += 1;
+        }
+    }
+}
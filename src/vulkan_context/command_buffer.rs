@@ -9,7 +9,11 @@ use ash::version::{
 use crate::vulkan_context::queue;
 
 pub fn create_command_pool(device: &Device, queue_family_data: &queue::QueueFamilyDatas) -> vk::CommandPool {
-    let queue_family_index = queue_family_data._queue_family_indices._graphics_queue_index;
+    create_command_pool_with_queue_family_index(device, queue_family_data._queue_family_indices._graphics_queue_index)
+}
+
+// ex) the transfer command pool of the async texture upload
+pub fn create_command_pool_with_queue_family_index(device: &Device, queue_family_index: u32) -> vk::CommandPool {
     let command_pool_create_info = vk::CommandPoolCreateInfo {
         flags: vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER,
         queue_family_index,
@@ -1,3 +1,5 @@
+use std::cell::Cell;
+
 use ash::{
     vk,
     Device,
@@ -5,6 +7,8 @@ use ash::{
 use ash::version::DeviceV1_0;
 
 use crate::constants;
+use crate::utilities::telemetry::{ self, ResourceClass };
+use crate::vulkan_context::object_name;
 use crate::vulkan_context::vulkan_context::SwapchainArray;
 
 #[derive(Debug, Clone)]
@@ -14,7 +18,7 @@ pub enum DescriptorResourceInfo {
     InvalidDescriptorInfo
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum DescriptorResourceType {
     UniformBuffer,
     StorageBuffer,
@@ -22,6 +26,7 @@ pub enum DescriptorResourceType {
     RenderTarget,
     StorageTexture,
     StorageRenderTarget,
+    InputAttachment, // render target of the previous subpass, subpassInput
 }
 
 #[derive(Debug, Clone)]
@@ -61,6 +66,7 @@ pub struct DescriptorData {
     pub _descriptor_pool: vk::DescriptorPool,
     pub _descriptor_set_layout: vk::DescriptorSetLayout,
     pub _max_descriptor_sets_count: u32,
+    pub _allocated_descriptor_sets_count: Cell<u32>, // for the telemetry, outstanding until the pool is destroyed
 }
 
 impl Default for DescriptorData {
@@ -72,6 +78,7 @@ impl Default for DescriptorData {
             _descriptor_pool: vk::DescriptorPool::null(),
             _descriptor_set_layout: vk::DescriptorSetLayout::null(),
             _max_descriptor_sets_count: 0,
+            _allocated_descriptor_sets_count: Cell::new(0),
         }
     }
 }
@@ -85,6 +92,7 @@ impl DescriptorDataCreateInfo {
             DescriptorResourceType::RenderTarget => vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
             DescriptorResourceType::StorageTexture => vk::DescriptorType::STORAGE_IMAGE,
             DescriptorResourceType::StorageRenderTarget => vk::DescriptorType::STORAGE_IMAGE,
+            DescriptorResourceType::InputAttachment => vk::DescriptorType::INPUT_ATTACHMENT,
         }
     }
 }
@@ -178,6 +186,7 @@ pub fn create_descriptor_data(
         _descriptor_pool: descriptor_pool,
         _descriptor_set_layout: descriptor_set_layout,
         _max_descriptor_sets_count: max_descriptor_sets_count,
+        _allocated_descriptor_sets_count: Cell::new(0),
     }
 }
 
@@ -187,11 +196,13 @@ pub fn destroy_descriptor_data(device: &Device, descriptor_data: &DescriptorData
         device.destroy_descriptor_set_layout(descriptor_data._descriptor_set_layout, None);
         device.destroy_descriptor_pool(descriptor_data._descriptor_pool, None);
     }
+    telemetry::record_destroyed(ResourceClass::DescriptorSet, descriptor_data._allocated_descriptor_sets_count.replace(0) as u64);
 }
 
 pub fn create_descriptor_sets(
     device: &Device,
-    descriptor_data: &DescriptorData
+    descriptor_data: &DescriptorData,
+    descriptor_sets_name: &str,
 ) -> SwapchainArray<vk::DescriptorSet> {
     if vk::DescriptorPool::null() != descriptor_data._descriptor_pool {
         let descriptor_set_layouts: [vk::DescriptorSetLayout; constants::SWAPCHAIN_IMAGE_COUNT] = [
@@ -206,6 +217,10 @@ pub fn create_descriptor_sets(
         unsafe {
             let descriptor_sets = device.allocate_descriptor_sets(&allocation_info).expect("");
             log::trace!("    CreateDescriptorSet: {:?}", descriptor_sets);
+            let descriptor_sets_count = descriptor_data._allocated_descriptor_sets_count.get() + descriptor_sets.len() as u32;
+            descriptor_data._allocated_descriptor_sets_count.set(descriptor_sets_count);
+            object_name::set_object_names(&descriptor_sets, descriptor_sets_name);
+            telemetry::record_created(ResourceClass::DescriptorSet, descriptor_sets.len() as u64);
             return descriptor_sets;
         }
     }
@@ -218,6 +233,7 @@ pub fn destroy_descriptor_sets(
     descriptor_sets: &SwapchainArray<vk::DescriptorSet>,
 ) {
     log::trace!("    destroyDescriptorSet: {:?}", descriptor_sets);
+    object_name::remove_object_names(descriptor_sets);
     // need VK_DESCRIPTOR_POOL_CREATE_FREE_DESCRIPTOR_SET_BIT flag for vkFreeDescriptorSets
     unsafe {
         device.free_descriptor_sets(descriptor_pool, &descriptor_sets);
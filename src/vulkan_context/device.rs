@@ -26,9 +26,13 @@ use winit::window::{
 };
 
 use crate::constants;
+use crate::vulkan_context::bindless_texture;
+use crate::vulkan_context::gpu_breadcrumb;
+use crate::vulkan_context::shading_rate::{ self, PhysicalDeviceFragmentShadingRateFeaturesKHR };
 use crate::vulkan_context::swapchain;
 use crate::vulkan_context::vulkan_context;
 
+pub const SWAPCHAIN_COLORSPACE_EXTENSION_NAME: &str = "VK_EXT_swapchain_colorspace";
 
 pub fn get_extension_names(extension_type: &str, available_extensions: &Vec<vk::ExtensionProperties>) -> Vec<CString> {
     log::info!("Available {} extentions: {}", extension_type, available_extensions.len());
@@ -95,6 +99,17 @@ pub fn get_max_usable_sample_count(device_properties: &vk::PhysicalDevicePropert
     sample_count
 }
 
+// 1, 2, 4, 8 -> the sample count flags, the count over the max usable sample count falls back to it.
+pub fn get_msaa_sample_count_flags(sample_count: u32, max_sample_count: vk::SampleCountFlags) -> vk::SampleCountFlags {
+    let sample_count_flags = match sample_count {
+        0 | 1 => vk::SampleCountFlags::TYPE_1,
+        2 => vk::SampleCountFlags::TYPE_2,
+        3 | 4 => vk::SampleCountFlags::TYPE_4,
+        _ => vk::SampleCountFlags::TYPE_8,
+    };
+    min(sample_count_flags, max_sample_count)
+}
+
 pub fn create_vk_instance(
     entry: &Entry,
     app_name: &str,
@@ -124,6 +139,15 @@ pub fn create_vk_instance(
         .map(|ext| CString::from(*ext))
         .collect();
     let available_instance_extensions: Vec<CString> = get_instance_extension_supports(entry);
+    // the hdr color spaces of the swapchain, optional
+    let swapchain_colorspace_extension_name = CString::new(SWAPCHAIN_COLORSPACE_EXTENSION_NAME).unwrap();
+    if unsafe { constants::ENABLE_HDR_OUTPUT } {
+        if available_instance_extensions.contains(&swapchain_colorspace_extension_name) {
+            extension_names_raw.push(swapchain_colorspace_extension_name.as_ptr());
+        } else {
+            log::warn!("create_vk_instance: {} is not supported, the hdr output is disabled.", SWAPCHAIN_COLORSPACE_EXTENSION_NAME);
+        }
+    }
     check_extension_support(&"Instance", &available_instance_extensions, &require_extension_names);
 
     let appinfo = vk::ApplicationInfo {
@@ -237,7 +261,18 @@ pub fn create_device(
         Vec::new()
     };
     let layer_names_raw: Vec<*const c_char> = layer_names.iter().map(|layer_name| { layer_name.as_ptr() }).collect();
-    let device_extension_names: Vec<CString> = unsafe { constants::REQUIRE_DEVICE_EXTENSIONS.iter() }.map(|extension| { CString::new(extension.as_str()).unwrap() }).collect();
+    let mut device_extension_names: Vec<CString> = unsafe { constants::REQUIRE_DEVICE_EXTENSIONS.iter() }.map(|extension| { CString::new(extension.as_str()).unwrap() }).collect();
+    let shading_rate_features = PhysicalDeviceFragmentShadingRateFeaturesKHR {
+        pipeline_fragment_shading_rate: vk::TRUE,
+        attachment_fragment_shading_rate: vk::TRUE,
+        ..Default::default()
+    };
+    if render_features._is_fragment_shading_rate_supported {
+        device_extension_names.push(CString::new(shading_rate::FRAGMENT_SHADING_RATE_EXTENSION_NAME).unwrap());
+    }
+    if render_features._is_buffer_marker_supported {
+        device_extension_names.push(CString::new(gpu_breadcrumb::BUFFER_MARKER_EXTENSION_NAME).unwrap());
+    }
     let device_extension_names_raw: Vec<*const c_char> = device_extension_names.iter().map(|extension| { extension.as_ptr() }).collect();
     #[cfg(target_os = "android")]
     let device_features = vk::PhysicalDeviceFeatures {
@@ -248,7 +283,22 @@ pub fn create_device(
     #[cfg(not(target_os = "android"))]
     let device_features = render_features._physical_device_features.clone();
 
+    // the feature chain: vulkan 1.2 features(descriptor indexing, draw indirect count) -> fragment shading rate
+    let shading_rate_features_ptr: *mut std::os::raw::c_void = if render_features._is_fragment_shading_rate_supported {
+        &shading_rate_features as *const PhysicalDeviceFragmentShadingRateFeaturesKHR as *mut std::os::raw::c_void
+    } else {
+        std::ptr::null_mut()
+    };
+    let vulkan12_features = vk::PhysicalDeviceVulkan12Features {
+        draw_indirect_count: if render_features._is_draw_indirect_count_supported { vk::TRUE } else { vk::FALSE },
+        ..bindless_texture::get_descriptor_indexing_features(shading_rate_features_ptr, render_features._is_descriptor_indexing_supported)
+    };
     let device_create_info = vk::DeviceCreateInfo {
+        p_next: if render_features._is_descriptor_indexing_supported || render_features._is_draw_indirect_count_supported {
+            &vulkan12_features as *const vk::PhysicalDeviceVulkan12Features as *const std::os::raw::c_void
+        } else {
+            shading_rate_features_ptr as *const std::os::raw::c_void
+        },
         queue_create_info_count: queue_create_infos.len() as u32,
         p_queue_create_infos: queue_create_infos.as_ptr(),
         enabled_layer_count: layer_names_raw.len() as u32,
@@ -0,0 +1,489 @@
+use std::os::raw::c_void;
+
+use ash::{ vk, Device };
+use ash::version::DeviceV1_0;
+
+use crate::constants;
+use crate::vulkan_context::buffer;
+use crate::vulkan_context::object_name;
+
+// the resources sharing a block with the other kind are apart by bufferImageGranularity
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MemoryAllocationKind {
+    Linear, // buffers and the images of the linear tiling
+    Optimal, // the images of the optimal tiling
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct MemoryAllocation {
+    pub _memory: vk::DeviceMemory, // the memory of the block, shared with the other allocations of the block
+    pub _memory_type_index: u32,
+    pub _offset: vk::DeviceSize,
+    pub _size: vk::DeviceSize,
+    pub _mapped_ptr: *mut c_void, // the mapped pointer of _offset, null if the memory is not host visible
+}
+
+impl Default for MemoryAllocation {
+    fn default() -> MemoryAllocation {
+        MemoryAllocation {
+            _memory: vk::DeviceMemory::null(),
+            _memory_type_index: 0,
+            _offset: 0,
+            _size: 0,
+            _mapped_ptr: std::ptr::null_mut(),
+        }
+    }
+}
+
+impl MemoryAllocation {
+    pub fn get_mapped_ptr(&self, offset: vk::DeviceSize) -> *mut c_void {
+        assert!(false == self._mapped_ptr.is_null(), "the memory is not host visible");
+        unsafe { (self._mapped_ptr as *mut u8).add(offset as usize) as *mut c_void }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DeviceMemoryStats {
+    pub _allocated_bytes: vk::DeviceSize,
+    pub _block_bytes: vk::DeviceSize,
+    pub _block_count: usize,
+    pub _allocation_count: usize,
+    pub _fragmentation: f32, // 1 - the largest free range / the free bytes, 0.0 when the free bytes are in one range
+}
+
+impl DeviceMemoryStats {
+    pub fn get_stats_text(&self) -> String {
+        const MEGA_BYTES: f64 = 1024.0 * 1024.0;
+        format!("gpu memory: {:.1} / {:.1} MB, blocks {}, allocations {}, fragmentation {:.1}%",
+            self._allocated_bytes as f64 / MEGA_BYTES,
+            self._block_bytes as f64 / MEGA_BYTES,
+            self._block_count,
+            self._allocation_count,
+            self._fragmentation * 100.0
+        )
+    }
+}
+
+pub struct MemoryBlock {
+    pub _memory: vk::DeviceMemory,
+    pub _size: vk::DeviceSize,
+    pub _mapped_ptr: *mut c_void,
+    pub _free_ranges: Vec<(vk::DeviceSize, vk::DeviceSize)>, // (offset, size) sorted by the offset, the neighbors are coalesced
+    pub _allocated_bytes: vk::DeviceSize,
+    pub _allocation_count: usize,
+    pub _is_dedicated: bool, // a single allocation over the half of the block size
+}
+
+// the blocks of each memory type, vkAllocateMemory is called once per block instead of once per resource.
+pub struct DeviceMemoryAllocator {
+    pub _memory_properties: vk::PhysicalDeviceMemoryProperties,
+    pub _buffer_image_granularity: vk::DeviceSize,
+    pub _non_coherent_atom_size: vk::DeviceSize,
+    pub _memory_type_blocks: Vec<Vec<MemoryBlock>>, // by the memory type index
+}
+
+// the resources are created and destroyed on the main thread like the object names
+static mut DEVICE_MEMORY_ALLOCATOR: Option<DeviceMemoryAllocator> = None;
+
+fn get_device_memory_allocator() -> Option<&'static mut DeviceMemoryAllocator> {
+    unsafe { DEVICE_MEMORY_ALLOCATOR.as_mut() }
+}
+
+fn align_up(value: vk::DeviceSize, alignment: vk::DeviceSize) -> vk::DeviceSize {
+    if alignment <= 1 { value } else { (value + alignment - 1) / alignment * alignment }
+}
+
+pub fn create_device_memory_allocator(memory_properties: &vk::PhysicalDeviceMemoryProperties, limits: &vk::PhysicalDeviceLimits) {
+    log::info!("create_device_memory_allocator: block size: {}, buffer image granularity: {}", unsafe { constants::DEVICE_MEMORY_BLOCK_SIZE }, limits.buffer_image_granularity);
+    unsafe {
+        DEVICE_MEMORY_ALLOCATOR = Some(DeviceMemoryAllocator {
+            _memory_properties: memory_properties.clone(),
+            _buffer_image_granularity: limits.buffer_image_granularity.max(1),
+            _non_coherent_atom_size: limits.non_coherent_atom_size.max(1),
+            _memory_type_blocks: (0..memory_properties.memory_type_count).map(|_| Vec::new()).collect(),
+        });
+    }
+}
+
+// after every buffer and image is destroyed, before the device
+pub fn destroy_device_memory_allocator(device: &Device) {
+    if let Some(allocator) = get_device_memory_allocator() {
+        let stats = allocator.get_stats();
+        if 0 < stats._allocation_count {
+            log::warn!("destroy_device_memory_allocator: {} allocations of {} bytes are leaked", stats._allocation_count, stats._allocated_bytes);
+        }
+        for blocks in allocator._memory_type_blocks.iter_mut() {
+            for block in blocks.drain(..) {
+                destroy_memory_block(device, &block);
+            }
+        }
+    }
+    unsafe {
+        DEVICE_MEMORY_ALLOCATOR = None;
+    }
+}
+
+pub fn allocate_memory(
+    device: &Device,
+    memory_requirements: &vk::MemoryRequirements,
+    memory_type_index: u32,
+    allocation_kind: MemoryAllocationKind
+) -> MemoryAllocation {
+    match get_device_memory_allocator() {
+        Some(allocator) => allocator.allocate(device, memory_requirements, memory_type_index, allocation_kind),
+        None => panic!("allocate_memory: the device memory allocator is not created."),
+    }
+}
+
+pub fn free_memory(device: &Device, memory_allocation: &MemoryAllocation) {
+    match get_device_memory_allocator() {
+        Some(allocator) => allocator.free(device, memory_allocation),
+        None => log::error!("free_memory: the device memory allocator is destroyed already, {:?}", memory_allocation),
+    }
+}
+
+pub fn get_device_memory_stats() -> DeviceMemoryStats {
+    match get_device_memory_allocator() {
+        Some(allocator) => allocator.get_stats(),
+        None => DeviceMemoryStats::default(),
+    }
+}
+
+fn create_memory_block(
+    device: &Device,
+    memory_properties: &vk::PhysicalDeviceMemoryProperties,
+    memory_type_index: u32,
+    block_size: vk::DeviceSize,
+    is_dedicated: bool
+) -> MemoryBlock {
+    let memory_allocate_info = vk::MemoryAllocateInfo {
+        allocation_size: block_size,
+        memory_type_index,
+        ..Default::default()
+    };
+    let property_flags = memory_properties.memory_types[memory_type_index as usize].property_flags;
+    unsafe {
+        let memory = device.allocate_memory(&memory_allocate_info, None).expect("vkAllocateMemory failed!");
+        // a memory can be mapped once, so the host visible blocks stay mapped until destroyed
+        let mapped_ptr = if property_flags.contains(vk::MemoryPropertyFlags::HOST_VISIBLE) {
+            buffer::add_map_memory_count();
+            device.map_memory(memory, 0, vk::WHOLE_SIZE, vk::MemoryMapFlags::empty()).expect("Failed to map_memory!")
+        } else {
+            std::ptr::null_mut()
+        };
+        object_name::set_object_name(memory, &format!("DeviceMemoryBlock[{}]", memory_type_index));
+        log::debug!("create_memory_block: memory_type_index: {}, size: {}, dedicated: {}, flags: {:?}", memory_type_index, block_size, is_dedicated, property_flags);
+        MemoryBlock {
+            _memory: memory,
+            _size: block_size,
+            _mapped_ptr: mapped_ptr,
+            _free_ranges: vec![(0, block_size)],
+            _allocated_bytes: 0,
+            _allocation_count: 0,
+            _is_dedicated: is_dedicated,
+        }
+    }
+}
+
+fn destroy_memory_block(device: &Device, block: &MemoryBlock) {
+    log::debug!("destroy_memory_block: {:?}, size: {}", block._memory, block._size);
+    object_name::remove_object_name(block._memory);
+    unsafe {
+        if false == block._mapped_ptr.is_null() {
+            device.unmap_memory(block._memory);
+        }
+        device.free_memory(block._memory, None);
+    }
+}
+
+impl MemoryBlock {
+    // first fit, the padding of the alignment stays in the free ranges
+    fn allocate_range(&mut self, size: vk::DeviceSize, alignment: vk::DeviceSize) -> Option<vk::DeviceSize> {
+        for i in 0..self._free_ranges.len() {
+            let (range_offset, range_size) = self._free_ranges[i];
+            let offset = align_up(range_offset, alignment);
+            let range_end = range_offset + range_size;
+            if range_end < offset + size {
+                continue;
+            }
+            self._free_ranges.remove(i);
+            if offset + size < range_end {
+                self._free_ranges.insert(i, (offset + size, range_end - offset - size));
+            }
+            if range_offset < offset {
+                self._free_ranges.insert(i, (range_offset, offset - range_offset));
+            }
+            self._allocated_bytes += size;
+            self._allocation_count += 1;
+            return Some(offset);
+        }
+        None
+    }
+
+    fn free_range(&mut self, offset: vk::DeviceSize, size: vk::DeviceSize) {
+        let i = self._free_ranges.iter().position(|(range_offset, _)| offset < *range_offset).unwrap_or(self._free_ranges.len());
+        self._free_ranges.insert(i, (offset, size));
+        // the next neighbor, then the previous neighbor
+        if i + 1 < self._free_ranges.len() && offset + size == self._free_ranges[i + 1].0 {
+            self._free_ranges[i].1 += self._free_ranges[i + 1].1;
+            self._free_ranges.remove(i + 1);
+        }
+        if 0 < i && self._free_ranges[i - 1].0 + self._free_ranges[i - 1].1 == offset {
+            self._free_ranges[i - 1].1 += self._free_ranges[i].1;
+            self._free_ranges.remove(i);
+        }
+        self._allocated_bytes -= size;
+        self._allocation_count -= 1;
+    }
+
+    fn get_largest_free_size(&self) -> vk::DeviceSize {
+        self._free_ranges.iter().map(|(_, range_size)| *range_size).max().unwrap_or(0)
+    }
+}
+
+impl DeviceMemoryAllocator {
+    // the host visible heaps can be small, ex) 256MB of the resizable bar
+    fn get_block_size(&self, memory_type_index: u32) -> vk::DeviceSize {
+        let heap_index = self._memory_properties.memory_types[memory_type_index as usize].heap_index;
+        let heap_size = self._memory_properties.memory_heaps[heap_index as usize].size;
+        unsafe { constants::DEVICE_MEMORY_BLOCK_SIZE }.min(heap_size / 8).max(1)
+    }
+
+    // (size, alignment) of the range in the block
+    fn get_allocation_size_and_alignment(
+        &self,
+        memory_requirements: &vk::MemoryRequirements,
+        memory_type_index: u32,
+        allocation_kind: MemoryAllocationKind
+    ) -> (vk::DeviceSize, vk::DeviceSize) {
+        let property_flags = self._memory_properties.memory_types[memory_type_index as usize].property_flags;
+        let mut alignment = memory_requirements.alignment.max(1);
+        let mut size = memory_requirements.size;
+        // the optimal images own whole pages of the granularity, the linear resources never share a page with them
+        if MemoryAllocationKind::Optimal == allocation_kind {
+            alignment = alignment.max(self._buffer_image_granularity);
+            size = align_up(size, self._buffer_image_granularity);
+        }
+        // vkFlushMappedMemoryRanges works on the atoms of the non coherent memory
+        if property_flags.contains(vk::MemoryPropertyFlags::HOST_VISIBLE) && false == property_flags.contains(vk::MemoryPropertyFlags::HOST_COHERENT) {
+            alignment = alignment.max(self._non_coherent_atom_size);
+            size = align_up(size, self._non_coherent_atom_size);
+        }
+        (size, alignment)
+    }
+
+    pub fn allocate(
+        &mut self,
+        device: &Device,
+        memory_requirements: &vk::MemoryRequirements,
+        memory_type_index: u32,
+        allocation_kind: MemoryAllocationKind
+    ) -> MemoryAllocation {
+        let (size, alignment) = self.get_allocation_size_and_alignment(memory_requirements, memory_type_index, allocation_kind);
+        let block_size = self.get_block_size(memory_type_index);
+        let blocks = &mut self._memory_type_blocks[memory_type_index as usize];
+        let mut found: Option<(usize, vk::DeviceSize)> = None;
+        if size <= block_size / 2 {
+            for (block_index, block) in blocks.iter_mut().enumerate() {
+                if block._is_dedicated {
+                    continue;
+                }
+                if let Some(offset) = block.allocate_range(size, alignment) {
+                    found = Some((block_index, offset));
+                    break;
+                }
+            }
+        }
+        let (block_index, offset) = match found {
+            Some(found) => found,
+            None => {
+                let is_dedicated = block_size / 2 < size;
+                let mut block = create_memory_block(device, &self._memory_properties, memory_type_index, if is_dedicated { size } else { block_size }, is_dedicated);
+                let offset = block.allocate_range(size, alignment).unwrap();
+                blocks.push(block);
+                (blocks.len() - 1, offset)
+            }
+        };
+        let block = &blocks[block_index];
+        MemoryAllocation {
+            _memory: block._memory,
+            _memory_type_index: memory_type_index,
+            _offset: offset,
+            _size: size,
+            _mapped_ptr: if block._mapped_ptr.is_null() { std::ptr::null_mut() } else { unsafe { (block._mapped_ptr as *mut u8).add(offset as usize) as *mut c_void } },
+        }
+    }
+
+    // the empty dedicated blocks are freed at once, the last empty block of the memory type is kept for the next allocation.
+    pub fn free(&mut self, device: &Device, memory_allocation: &MemoryAllocation) {
+        let blocks = &mut self._memory_type_blocks[memory_allocation._memory_type_index as usize];
+        let block_index = match blocks.iter().position(|block| block._memory == memory_allocation._memory) {
+            Some(block_index) => block_index,
+            None => {
+                log::error!("free: the block of {:?} does not exist", memory_allocation);
+                return;
+            }
+        };
+        blocks[block_index].free_range(memory_allocation._offset, memory_allocation._size);
+        if 0 == blocks[block_index]._allocation_count {
+            let empty_block_count = blocks.iter().filter(|block| 0 == block._allocation_count && false == block._is_dedicated).count();
+            if blocks[block_index]._is_dedicated || 1 < empty_block_count {
+                let block = blocks.remove(block_index);
+                destroy_memory_block(device, &block);
+            }
+        }
+    }
+
+    pub fn get_stats(&self) -> DeviceMemoryStats {
+        let mut stats = DeviceMemoryStats::default();
+        let mut free_bytes: vk::DeviceSize = 0;
+        let mut largest_free_size: vk::DeviceSize = 0;
+        for block in self._memory_type_blocks.iter().flatten() {
+            stats._allocated_bytes += block._allocated_bytes;
+            stats._block_bytes += block._size;
+            stats._block_count += 1;
+            stats._allocation_count += block._allocation_count;
+            free_bytes += block._size - block._allocated_bytes;
+            largest_free_size = largest_free_size.max(block.get_largest_free_size());
+        }
+        if 0 < free_bytes {
+            stats._fragmentation = 1.0 - (largest_free_size as f64 / free_bytes as f64) as f32;
+        }
+        stats
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_memory_block(block_size: vk::DeviceSize) -> MemoryBlock {
+        MemoryBlock {
+            _memory: vk::DeviceMemory::null(),
+            _size: block_size,
+            _mapped_ptr: std::ptr::null_mut(),
+            _free_ranges: vec![(0, block_size)],
+            _allocated_bytes: 0,
+            _allocation_count: 0,
+            _is_dedicated: false,
+        }
+    }
+
+    // 0: device local, 1: host visible and coherent, 2: host visible and not coherent
+    fn create_test_allocator() -> DeviceMemoryAllocator {
+        let mut memory_properties = vk::PhysicalDeviceMemoryProperties {
+            memory_type_count: 3,
+            memory_heap_count: 1,
+            ..Default::default()
+        };
+        memory_properties.memory_types[0].property_flags = vk::MemoryPropertyFlags::DEVICE_LOCAL;
+        memory_properties.memory_types[1].property_flags = vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT;
+        memory_properties.memory_types[2].property_flags = vk::MemoryPropertyFlags::HOST_VISIBLE;
+        memory_properties.memory_heaps[0].size = 1 << 30;
+        DeviceMemoryAllocator {
+            _memory_properties: memory_properties,
+            _buffer_image_granularity: 1024,
+            _non_coherent_atom_size: 64,
+            _memory_type_blocks: vec![Vec::new(), Vec::new(), Vec::new()],
+        }
+    }
+
+    #[test]
+    fn test_align_up() {
+        assert_eq!(0, align_up(0, 256));
+        assert_eq!(256, align_up(1, 256));
+        assert_eq!(256, align_up(256, 256));
+        assert_eq!(768, align_up(513, 256));
+        assert_eq!(13, align_up(13, 1));
+        assert_eq!(13, align_up(13, 0));
+    }
+
+    #[test]
+    fn test_suballocation() {
+        let mut block = create_test_memory_block(1024);
+        assert_eq!(Some(0), block.allocate_range(100, 1));
+        assert_eq!(Some(100), block.allocate_range(200, 4));
+        assert_eq!(Some(300), block.allocate_range(300, 1));
+        assert_eq!(vec![(600, 424)], block._free_ranges);
+        assert_eq!(600, block._allocated_bytes);
+        assert_eq!(3, block._allocation_count);
+        // the block is full
+        assert_eq!(None, block.allocate_range(500, 1));
+        assert_eq!(Some(600), block.allocate_range(424, 1));
+        assert!(block._free_ranges.is_empty());
+        assert_eq!(None, block.allocate_range(1, 1));
+        assert_eq!(0, block.get_largest_free_size());
+    }
+
+    #[test]
+    fn test_suballocation_alignment() {
+        let mut block = create_test_memory_block(4096);
+        assert_eq!(Some(0), block.allocate_range(10, 1));
+        // the padding before the aligned offset stays free
+        assert_eq!(Some(256), block.allocate_range(100, 256));
+        assert_eq!(vec![(10, 246), (356, 3740)], block._free_ranges);
+        // the first fit, the padding is reused by the small allocations of the smaller alignment
+        assert_eq!(Some(16), block.allocate_range(32, 16));
+        assert_eq!(vec![(10, 6), (48, 208), (356, 3740)], block._free_ranges);
+        assert_eq!(Some(1024), block.allocate_range(1024, 1024));
+        assert_eq!(10 + 100 + 32 + 1024, block._allocated_bytes);
+    }
+
+    #[test]
+    fn test_free_list_merge() {
+        let mut block = create_test_memory_block(1000);
+        let offsets: Vec<vk::DeviceSize> = (0..5).map(|_| block.allocate_range(200, 1).unwrap()).collect();
+        assert_eq!(vec![0, 200, 400, 600, 800], offsets);
+
+        // no neighbor, the free ranges are sorted by the offset
+        block.free_range(600, 200);
+        block.free_range(200, 200);
+        assert_eq!(vec![(200, 200), (600, 200)], block._free_ranges);
+        // the next neighbor
+        block.free_range(0, 200);
+        assert_eq!(vec![(0, 400), (600, 200)], block._free_ranges);
+        // the previous neighbor
+        block.free_range(800, 200);
+        assert_eq!(vec![(0, 400), (600, 400)], block._free_ranges);
+        // both neighbors
+        block.free_range(400, 200);
+        assert_eq!(vec![(0, 1000)], block._free_ranges);
+        assert_eq!(0, block._allocated_bytes);
+        assert_eq!(0, block._allocation_count);
+        assert_eq!(Some(0), block.allocate_range(1000, 1));
+    }
+
+    #[test]
+    fn test_allocation_size_and_alignment() {
+        let allocator = create_test_allocator();
+        let memory_requirements = vk::MemoryRequirements { size: 100, alignment: 16, memory_type_bits: 0 };
+        assert_eq!((100, 16), allocator.get_allocation_size_and_alignment(&memory_requirements, 0, MemoryAllocationKind::Linear));
+        assert_eq!((100, 16), allocator.get_allocation_size_and_alignment(&memory_requirements, 1, MemoryAllocationKind::Linear));
+        // the pages of the buffer image granularity
+        assert_eq!((1024, 1024), allocator.get_allocation_size_and_alignment(&memory_requirements, 0, MemoryAllocationKind::Optimal));
+        // the atoms of the non coherent memory
+        assert_eq!((128, 64), allocator.get_allocation_size_and_alignment(&memory_requirements, 2, MemoryAllocationKind::Linear));
+        let memory_requirements = vk::MemoryRequirements { size: 100, alignment: 0, memory_type_bits: 0 };
+        assert_eq!((100, 1), allocator.get_allocation_size_and_alignment(&memory_requirements, 0, MemoryAllocationKind::Linear));
+    }
+
+    #[test]
+    fn test_stats_fragmentation() {
+        let mut allocator = create_test_allocator();
+        let mut block = create_test_memory_block(1000);
+        let offsets: Vec<vk::DeviceSize> = (0..4).map(|_| block.allocate_range(250, 1).unwrap()).collect();
+        block.free_range(offsets[0], 250);
+        block.free_range(offsets[2], 250);
+        allocator._memory_type_blocks[0].push(block);
+        let stats = allocator.get_stats();
+        assert_eq!(500, stats._allocated_bytes);
+        assert_eq!(1000, stats._block_bytes);
+        assert_eq!(1, stats._block_count);
+        assert_eq!(2, stats._allocation_count);
+        assert_eq!(0.5, stats._fragmentation);
+
+        allocator._memory_type_blocks[0][0].free_range(offsets[1], 250);
+        assert_eq!(0.0, allocator.get_stats()._fragmentation);
+    }
+}
@@ -12,6 +12,8 @@ use crate::vulkan_context::vulkan_context::{
     SwapchainArray
 };
 use crate::vulkan_context::texture::TextureData;
+use crate::vulkan_context::object_name;
+use crate::utilities::telemetry::{ self, ResourceClass };
 
 
 #[derive(Clone)]
@@ -197,6 +199,8 @@ pub fn create_framebuffer_data(
                    framebuffers
         );
 
+        object_name::set_object_names(&framebuffers, framebuffer_name);
+        telemetry::record_created(ResourceClass::Framebuffer, 1);
         FramebufferData {
             _framebuffer_name: String::from(framebuffer_name),
             _framebuffer_info: framebuffer_data_create_info,
@@ -208,9 +212,11 @@ pub fn create_framebuffer_data(
 
 pub fn destroy_framebuffer_data(device: &Device, framebuffer_data: &FramebufferData) {
     log::debug!("destroy_framebuffer_data: {:?} {:?}", framebuffer_data._framebuffer_name, framebuffer_data._framebuffers);
+    object_name::remove_object_names(&framebuffer_data._framebuffers);
     unsafe {
         for framebuffer in framebuffer_data._framebuffers.iter() {
             device.destroy_framebuffer(*framebuffer, None);
         }
     }
+    telemetry::record_destroyed(ResourceClass::Framebuffer, 1);
 }
\ No newline at end of file
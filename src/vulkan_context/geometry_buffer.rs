@@ -6,9 +6,10 @@ use ash::{ vk, Device };
 use nalgebra::{ self, Vector2, Vector3, Vector4 };
 
 use crate::renderer::mesh::{ MeshDataCreateInfo };
-use crate::vulkan_context::buffer;
+use crate::vulkan_context::buffer::{ self, StagingBufferPool };
 use crate::vulkan_context::vulkan_context::{ get_color32, get_format_size };
 use crate::utilities::math;
+use crate::utilities::telemetry::{ self, ResourceClass };
 use crate::utilities::bounding_box::{ BoundingBox, calc_bounding_box };
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
@@ -63,6 +64,7 @@ pub struct GeometryCreateInfo {
     pub _skeletal_vertex_datas: Vec<SkeletalVertexData>,
     pub _indices: Vec<u32>,
     pub _bounding_box: BoundingBox,
+    pub _material_name: String, // the material of the source primitive, empty when the source has no material
 }
 
 impl Default for GeometryCreateInfo {
@@ -72,6 +74,7 @@ impl Default for GeometryCreateInfo {
             _skeletal_vertex_datas: Vec::new(),
             _indices: Vec::new(),
             _bounding_box: BoundingBox::default(),
+            _material_name: String::new(),
         }
     }
 }
@@ -82,6 +85,7 @@ pub struct GeometryData {
     pub _vertex_buffer_data: buffer::BufferData,
     pub _index_buffer_data: buffer::BufferData,
     pub _vertex_index_count: u32,
+    pub _vertex_count: u32,
     pub _geometry_bounding_box: BoundingBox
 }
 
@@ -173,50 +177,52 @@ impl VertexData for SkeletalVertexData {
     }
 }
 
+// the copies are recorded into the staging buffer pool, the geometry can be drawn after StagingBufferPool::flush_uploads.
 pub fn create_geometry_data(
     device: &Device,
-    command_pool: vk::CommandPool,
-    command_queue: vk::Queue,
     device_memory_properties: &vk::PhysicalDeviceMemoryProperties,
+    staging_buffer_pool: &mut StagingBufferPool,
     geometry_name: &String,
     geometry_create_info: &GeometryCreateInfo
 ) -> GeometryData {
     log::trace!("create_geometry_data: {:?}", geometry_name);
 
+    // the skeletal vertices are also the input of the gpu skinning
     let vertex_buffer_data = if false == geometry_create_info._skeletal_vertex_datas.is_empty() {
-        buffer::create_buffer_data_with_uploads(
+        buffer::create_buffer_data_with_staging_pool(
             device,
-            command_pool,
-            command_queue,
             device_memory_properties,
-            vk::BufferUsageFlags::VERTEX_BUFFER,
+            staging_buffer_pool,
+            vk::BufferUsageFlags::VERTEX_BUFFER | vk::BufferUsageFlags::STORAGE_BUFFER,
             &geometry_create_info._skeletal_vertex_datas,
         )
     } else {
-        buffer::create_buffer_data_with_uploads(
+        buffer::create_buffer_data_with_staging_pool(
             device,
-            command_pool,
-            command_queue,
             device_memory_properties,
+            staging_buffer_pool,
             vk::BufferUsageFlags::VERTEX_BUFFER,
             &geometry_create_info._vertex_datas,
         )
     };
 
-    let index_buffer_data = buffer::create_buffer_data_with_uploads(
+    let index_buffer_data = buffer::create_buffer_data_with_staging_pool(
         device,
-        command_pool,
-        command_queue,
         device_memory_properties,
+        staging_buffer_pool,
         vk::BufferUsageFlags::INDEX_BUFFER,
         &geometry_create_info._indices
     );
 
+    buffer::set_buffer_object_names(std::slice::from_ref(&vertex_buffer_data), &format!("{}/vertex", geometry_name), true);
+    buffer::set_buffer_object_names(std::slice::from_ref(&index_buffer_data), &format!("{}/index", geometry_name), true);
+    telemetry::record_created(ResourceClass::GeometryBuffer, 1);
     GeometryData {
         _geometry_name: geometry_name.clone(),
         _vertex_buffer_data: vertex_buffer_data,
         _index_buffer_data: index_buffer_data,
         _vertex_index_count: geometry_create_info._indices.len() as u32,
+        _vertex_count: geometry_create_info._skeletal_vertex_datas.len().max(geometry_create_info._vertex_datas.len()) as u32,
         _geometry_bounding_box: geometry_create_info._bounding_box.clone()
     }
 }
@@ -225,6 +231,7 @@ pub fn destroy_geometry_data(device: &Device, geometry_data: &GeometryData) {
     log::trace!("destroy_geometry_data");
     buffer::destroy_buffer_data(device, &geometry_data._vertex_buffer_data);
     buffer::destroy_buffer_data(device, &geometry_data._index_buffer_data);
+    telemetry::record_destroyed(ResourceClass::GeometryBuffer, 1);
 }
 
 //
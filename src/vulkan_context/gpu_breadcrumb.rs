@@ -0,0 +1,277 @@
+use std::ffi::CString;
+
+use ash::{ vk, Device, Instance };
+use ash::version::{ DeviceV1_0, InstanceV1_0 };
+
+use crate::constants;
+use crate::vulkan_context::buffer::{ self, BufferData };
+use crate::vulkan_context::vulkan_context::SwapchainArray;
+
+// VK_AMD_buffer_marker writes the marker when the given pipeline stage of the previous commands is done,
+// without it cmd_fill_buffer is used, which is ordered with the transfers only, so the markers are approximate.
+// cmd_fill_buffer is not allowed inside a render pass, the debug label scopes are outside of the render passes.
+// VK_NV_device_diagnostic_checkpoints is not used yet.
+pub const BUFFER_MARKER_EXTENSION_NAME: &str = "VK_AMD_buffer_marker";
+pub const BREADCRUMB_MARKER_COUNT: usize = 2; // begin, end
+pub const BREADCRUMB_BEGIN_MARKER_OFFSET: vk::DeviceSize = 0;
+pub const BREADCRUMB_END_MARKER_OFFSET: vk::DeviceSize = 4;
+pub const INVALID_BREADCRUMB_MARKER: u32 = 0;
+
+#[allow(non_camel_case_types)]
+pub type PFN_vkCmdWriteBufferMarkerAMD = unsafe extern "system" fn(
+    command_buffer: vk::CommandBuffer,
+    pipeline_stage: vk::PipelineStageFlags,
+    dst_buffer: vk::Buffer,
+    dst_offset: vk::DeviceSize,
+    marker: u32,
+);
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GpuBreadcrumbReport {
+    pub _swapchain_index: u32,
+    pub _last_begin_pass: Option<String>,
+    pub _last_end_pass: Option<String>,
+}
+
+// the marker value is the index + 1 of the pass name in the marker table of the swapchain image,
+// the names are registered by RendererData::begin_debug_label, so the breadcrumbs and the debug labels share the scopes.
+pub struct GpuBreadcrumbs {
+    pub _marker_buffer: BufferData, // host visible, [begin, end] per swapchain image
+    pub _marker_tables: SwapchainArray<Vec<String>>,
+    pub _scope_stack: Vec<u32>,
+    pub _recording_swapchain_index: u32,
+    pub _cmd_write_buffer_marker: Option<PFN_vkCmdWriteBufferMarkerAMD>,
+}
+
+pub fn is_gpu_breadcrumbs_enabled() -> bool {
+    unsafe { constants::ENABLE_GPU_BREADCRUMBS }
+}
+
+pub fn load_cmd_write_buffer_marker(instance: &Instance, device: &Device) -> Option<PFN_vkCmdWriteBufferMarkerAMD> {
+    let function_name = CString::new("vkCmdWriteBufferMarkerAMD").unwrap();
+    unsafe {
+        let function = (instance.fp_v1_0().get_device_proc_addr)(device.handle(), function_name.as_ptr());
+        function.map(|function| std::mem::transmute::<unsafe extern "system" fn() -> std::os::raw::c_void, PFN_vkCmdWriteBufferMarkerAMD>(function))
+    }
+}
+
+impl GpuBreadcrumbs {
+    pub fn create_gpu_breadcrumbs(
+        instance: &Instance,
+        device: &Device,
+        memory_properties: &vk::PhysicalDeviceMemoryProperties,
+        is_buffer_marker_supported: bool
+    ) -> GpuBreadcrumbs {
+        let marker_buffer = buffer::create_buffer_data(
+            device,
+            memory_properties,
+            (std::mem::size_of::<u32>() * BREADCRUMB_MARKER_COUNT * constants::SWAPCHAIN_IMAGE_COUNT) as vk::DeviceSize,
+            vk::BufferUsageFlags::TRANSFER_DST,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT
+        );
+        let cmd_write_buffer_marker = if is_buffer_marker_supported { load_cmd_write_buffer_marker(instance, device) } else { None };
+        log::info!("create_gpu_breadcrumbs: {}", if cmd_write_buffer_marker.is_some() { BUFFER_MARKER_EXTENSION_NAME } else { "cmd_fill_buffer" });
+        GpuBreadcrumbs {
+            _marker_buffer: marker_buffer,
+            _marker_tables: vec![Vec::new(); constants::SWAPCHAIN_IMAGE_COUNT],
+            _scope_stack: Vec::new(),
+            _recording_swapchain_index: 0,
+            _cmd_write_buffer_marker: cmd_write_buffer_marker,
+        }
+    }
+
+    pub fn destroy_gpu_breadcrumbs(&mut self, device: &Device) {
+        buffer::destroy_buffer_data(device, &self._marker_buffer);
+    }
+
+    fn get_marker_offset(swapchain_index: u32, marker_offset: vk::DeviceSize) -> vk::DeviceSize {
+        (std::mem::size_of::<u32>() * BREADCRUMB_MARKER_COUNT) as vk::DeviceSize * swapchain_index as vk::DeviceSize + marker_offset
+    }
+
+    fn write_marker(&self, device: &Device, command_buffer: vk::CommandBuffer, pipeline_stage: vk::PipelineStageFlags, marker_offset: vk::DeviceSize, marker: u32) {
+        let offset = GpuBreadcrumbs::get_marker_offset(self._recording_swapchain_index, marker_offset);
+        unsafe {
+            match self._cmd_write_buffer_marker {
+                Some(cmd_write_buffer_marker) => cmd_write_buffer_marker(command_buffer, pipeline_stage, self._marker_buffer._buffer, offset, marker),
+                None => device.cmd_fill_buffer(command_buffer, self._marker_buffer._buffer, offset, std::mem::size_of::<u32>() as vk::DeviceSize, marker),
+            }
+        }
+    }
+
+    // at the beginning of the command buffer, outside of the render passes
+    pub fn begin_frame(&mut self, device: &Device, command_buffer: vk::CommandBuffer, swapchain_index: u32) {
+        self.reset_marker_table(swapchain_index);
+        unsafe {
+            let offset = GpuBreadcrumbs::get_marker_offset(swapchain_index, 0);
+            let size = (std::mem::size_of::<u32>() * BREADCRUMB_MARKER_COUNT) as vk::DeviceSize;
+            device.cmd_fill_buffer(command_buffer, self._marker_buffer._buffer, offset, size, INVALID_BREADCRUMB_MARKER);
+        }
+    }
+
+    pub fn reset_marker_table(&mut self, swapchain_index: u32) {
+        self._recording_swapchain_index = swapchain_index;
+        self._marker_tables[swapchain_index as usize].clear();
+        self._scope_stack.clear();
+    }
+
+    // the marker written before the pass
+    pub fn push_scope(&mut self, pass_name: &str) -> u32 {
+        let marker_table = &mut self._marker_tables[self._recording_swapchain_index as usize];
+        marker_table.push(String::from(pass_name));
+        let marker = marker_table.len() as u32;
+        self._scope_stack.push(marker);
+        marker
+    }
+
+    // the marker written after the pass
+    pub fn pop_scope(&mut self) -> Option<u32> {
+        self._scope_stack.pop()
+    }
+
+    pub fn begin_scope(&mut self, device: &Device, command_buffer: vk::CommandBuffer, pass_name: &str) {
+        let marker = self.push_scope(pass_name);
+        self.write_marker(device, command_buffer, vk::PipelineStageFlags::TOP_OF_PIPE, BREADCRUMB_BEGIN_MARKER_OFFSET, marker);
+    }
+
+    pub fn end_scope(&mut self, device: &Device, command_buffer: vk::CommandBuffer) {
+        if let Some(marker) = self.pop_scope() {
+            self.write_marker(device, command_buffer, vk::PipelineStageFlags::BOTTOM_OF_PIPE, BREADCRUMB_END_MARKER_OFFSET, marker);
+        }
+    }
+
+    pub fn get_pass_name(&self, swapchain_index: u32, marker: u32) -> Option<String> {
+        if INVALID_BREADCRUMB_MARKER == marker {
+            return None;
+        }
+        self._marker_tables[swapchain_index as usize].get(marker as usize - 1).cloned()
+    }
+
+    // the memory stays readable after the device lost
+    pub fn create_gpu_breadcrumb_report(&self, device: &Device, swapchain_index: u32) -> GpuBreadcrumbReport {
+        let mut markers: [u32; BREADCRUMB_MARKER_COUNT] = [INVALID_BREADCRUMB_MARKER; BREADCRUMB_MARKER_COUNT];
+        buffer::read_buffer_data(device, &self._marker_buffer, swapchain_index * BREADCRUMB_MARKER_COUNT as u32, &mut markers);
+        self.create_gpu_breadcrumb_report_from_markers(swapchain_index, &markers)
+    }
+
+    // markers: [begin, end] of the marker buffer
+    pub fn create_gpu_breadcrumb_report_from_markers(&self, swapchain_index: u32, markers: &[u32; BREADCRUMB_MARKER_COUNT]) -> GpuBreadcrumbReport {
+        GpuBreadcrumbReport {
+            _swapchain_index: swapchain_index,
+            _last_begin_pass: self.get_pass_name(swapchain_index, markers[0]),
+            _last_end_pass: self.get_pass_name(swapchain_index, markers[1]),
+        }
+    }
+}
+
+impl GpuBreadcrumbReport {
+    pub fn get_report_text(&self) -> String {
+        format!("gpu breadcrumbs(swapchain index: {}): last started pass: {}, last completed pass: {}",
+            self._swapchain_index,
+            self._last_begin_pass.as_ref().map(|pass_name| pass_name.as_str()).unwrap_or("none"),
+            self._last_end_pass.as_ref().map(|pass_name| pass_name.as_str()).unwrap_or("none")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_gpu_breadcrumbs() -> GpuBreadcrumbs {
+        GpuBreadcrumbs {
+            _marker_buffer: BufferData::default(),
+            _marker_tables: vec![Vec::new(); constants::SWAPCHAIN_IMAGE_COUNT],
+            _scope_stack: Vec::new(),
+            _recording_swapchain_index: 0,
+            _cmd_write_buffer_marker: None,
+        }
+    }
+
+    // the marker buffer of a swapchain image as the gpu writes it, the commands after the device lost are never executed
+    struct SimulatedMarkerBuffer {
+        _markers: [u32; BREADCRUMB_MARKER_COUNT],
+        _is_device_lost: bool,
+    }
+
+    impl SimulatedMarkerBuffer {
+        fn begin_scope(&mut self, gpu_breadcrumbs: &mut GpuBreadcrumbs, pass_name: &str) {
+            let marker = gpu_breadcrumbs.push_scope(pass_name);
+            if self._is_device_lost {
+                return;
+            }
+            self._markers[0] = marker;
+        }
+
+        fn end_scope(&mut self, gpu_breadcrumbs: &mut GpuBreadcrumbs) {
+            let marker = gpu_breadcrumbs.pop_scope();
+            if self._is_device_lost {
+                return;
+            }
+            if let Some(marker) = marker {
+                self._markers[1] = marker;
+            }
+        }
+    }
+
+    #[test]
+    fn test_device_lost_breadcrumb_report() {
+        let mut gpu_breadcrumbs = create_test_gpu_breadcrumbs();
+        let swapchain_index: u32 = 1;
+        gpu_breadcrumbs.reset_marker_table(swapchain_index);
+        let mut marker_buffer = SimulatedMarkerBuffer { _markers: [INVALID_BREADCRUMB_MARKER; BREADCRUMB_MARKER_COUNT], _is_device_lost: false };
+        marker_buffer.begin_scope(&mut gpu_breadcrumbs, "render_shadow");
+        marker_buffer.end_scope(&mut gpu_breadcrumbs);
+        marker_buffer.begin_scope(&mut gpu_breadcrumbs, "render_gbuffer");
+        marker_buffer.end_scope(&mut gpu_breadcrumbs);
+        // the gpu hangs inside the lighting pass
+        marker_buffer.begin_scope(&mut gpu_breadcrumbs, "render_lighting");
+        marker_buffer._is_device_lost = true;
+        marker_buffer.end_scope(&mut gpu_breadcrumbs);
+        marker_buffer.begin_scope(&mut gpu_breadcrumbs, "render_post_process");
+        marker_buffer.end_scope(&mut gpu_breadcrumbs);
+
+        let report = gpu_breadcrumbs.create_gpu_breadcrumb_report_from_markers(swapchain_index, &marker_buffer._markers);
+        assert_eq!(GpuBreadcrumbReport {
+            _swapchain_index: swapchain_index,
+            _last_begin_pass: Some(String::from("render_lighting")),
+            _last_end_pass: Some(String::from("render_gbuffer")),
+        }, report);
+        assert_eq!(
+            "gpu breadcrumbs(swapchain index: 1): last started pass: render_lighting, last completed pass: render_gbuffer",
+            report.get_report_text()
+        );
+        // the marker tables of the other swapchain images are not touched
+        assert!(gpu_breadcrumbs._marker_tables[0].is_empty());
+    }
+
+    #[test]
+    fn test_nested_breadcrumb_scopes() {
+        let mut gpu_breadcrumbs = create_test_gpu_breadcrumbs();
+        gpu_breadcrumbs.reset_marker_table(0);
+        let mut marker_buffer = SimulatedMarkerBuffer { _markers: [INVALID_BREADCRUMB_MARKER; BREADCRUMB_MARKER_COUNT], _is_device_lost: false };
+        marker_buffer.begin_scope(&mut gpu_breadcrumbs, "render_scene");
+        marker_buffer.begin_scope(&mut gpu_breadcrumbs, "render_ocean");
+        marker_buffer.end_scope(&mut gpu_breadcrumbs);
+        let report = gpu_breadcrumbs.create_gpu_breadcrumb_report_from_markers(0, &marker_buffer._markers);
+        assert_eq!(Some(String::from("render_ocean")), report._last_begin_pass);
+        assert_eq!(Some(String::from("render_ocean")), report._last_end_pass);
+        marker_buffer.end_scope(&mut gpu_breadcrumbs);
+        let report = gpu_breadcrumbs.create_gpu_breadcrumb_report_from_markers(0, &marker_buffer._markers);
+        assert_eq!(Some(String::from("render_scene")), report._last_end_pass);
+        // the unbalanced end is ignored
+        assert_eq!(None, gpu_breadcrumbs.pop_scope());
+    }
+
+    #[test]
+    fn test_empty_breadcrumb_report() {
+        let mut gpu_breadcrumbs = create_test_gpu_breadcrumbs();
+        gpu_breadcrumbs.reset_marker_table(0);
+        let report = gpu_breadcrumbs.create_gpu_breadcrumb_report_from_markers(0, &[INVALID_BREADCRUMB_MARKER; BREADCRUMB_MARKER_COUNT]);
+        assert_eq!("gpu breadcrumbs(swapchain index: 0): last started pass: none, last completed pass: none", report.get_report_text());
+        // the markers of the previous frame are cleared by the new frame
+        gpu_breadcrumbs.push_scope("render_shadow");
+        gpu_breadcrumbs.reset_marker_table(0);
+        assert_eq!(None, gpu_breadcrumbs.get_pass_name(0, 1));
+        assert!(gpu_breadcrumbs._scope_stack.is_empty());
+    }
+}
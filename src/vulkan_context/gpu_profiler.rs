@@ -0,0 +1,222 @@
+use std::os::raw::c_void;
+
+use ash::{ vk, Device };
+use ash::version::DeviceV1_0;
+
+use crate::constants;
+use crate::vulkan_context::vulkan_context::SwapchainArray;
+
+// 2 timestamps per scope, the frame scope and the debug label scopes of RendererData
+pub const GPU_PROFILER_MAX_QUERY_COUNT: u32 = 256;
+pub const GPU_PROFILER_FRAME_SCOPE_NAME: &str = "gpu frame";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GpuProfileScope {
+    pub _name: String,
+    pub _depth: u32,
+    pub _begin_query: u32,
+    pub _end_query: u32, // INVALID_QUERY until end_scope
+}
+
+const INVALID_QUERY: u32 = std::u32::MAX;
+
+pub fn is_gpu_profiler_enabled() -> bool {
+    unsafe { constants::ENABLE_GPU_PROFILER }
+}
+
+// a timestamp query pool per swapchain image, the results are read back when the same swapchain index is recorded again.
+// the scopes share the names of the debug labels: the render passes, the post process consumers and the debug passes.
+pub struct GpuProfiler {
+    pub _query_pools: SwapchainArray<vk::QueryPool>,
+    pub _scopes: SwapchainArray<Vec<GpuProfileScope>>,
+    pub _query_counts: SwapchainArray<u32>,
+    pub _scope_stack: Vec<usize>,
+    pub _recording_swapchain_index: u32,
+    pub _is_recording: bool, // between begin_frame and end_frame, the scopes of the other command buffers are ignored. ex) bake_impostors
+    pub _timestamp_period: f32, // nanoseconds per tick, VkPhysicalDeviceLimits::timestampPeriod
+    pub _is_supported: bool,
+    pub _gpu_timings: Vec<(String, f32)>, // milliseconds, the last available results
+}
+
+impl GpuProfiler {
+    pub fn create_gpu_profiler(device: &Device, device_limits: &vk::PhysicalDeviceLimits) -> GpuProfiler {
+        let is_supported = vk::TRUE == device_limits.timestamp_compute_and_graphics && 0.0 < device_limits.timestamp_period;
+        let query_pools: SwapchainArray<vk::QueryPool> = if is_supported {
+            let query_pool_create_info = vk::QueryPoolCreateInfo {
+                query_type: vk::QueryType::TIMESTAMP,
+                query_count: GPU_PROFILER_MAX_QUERY_COUNT,
+                ..Default::default()
+            };
+            (0..constants::SWAPCHAIN_IMAGE_COUNT).map(|_i| unsafe {
+                device.create_query_pool(&query_pool_create_info, None).expect("vkCreateQueryPool failed!")
+            }).collect()
+        } else {
+            Vec::new()
+        };
+        log::info!("create_gpu_profiler: supported: {}, timestamp period: {}ns", is_supported, device_limits.timestamp_period);
+        GpuProfiler {
+            _query_pools: query_pools,
+            _scopes: vec![Vec::new(); constants::SWAPCHAIN_IMAGE_COUNT],
+            _query_counts: vec![0; constants::SWAPCHAIN_IMAGE_COUNT],
+            _scope_stack: Vec::new(),
+            _recording_swapchain_index: 0,
+            _is_recording: false,
+            _timestamp_period: device_limits.timestamp_period,
+            _is_supported: is_supported,
+            _gpu_timings: Vec::new(),
+        }
+    }
+
+    pub fn destroy_gpu_profiler(&mut self, device: &Device) {
+        for query_pool in self._query_pools.iter() {
+            unsafe {
+                device.destroy_query_pool(*query_pool, None);
+            }
+        }
+        self._query_pools.clear();
+    }
+
+    pub fn get_gpu_timings(&self) -> &Vec<(String, f32)> {
+        &self._gpu_timings
+    }
+
+    pub fn get_gpu_frame_time(&self) -> Option<f32> {
+        self._gpu_timings.iter().find(|(name, _)| GPU_PROFILER_FRAME_SCOPE_NAME == name).map(|(_, time)| *time)
+    }
+
+    // a line per scope, the nested scopes are indented
+    pub fn get_gpu_timings_text(&self) -> String {
+        let mut text = String::from("gpu timings:");
+        for (name, time) in self._gpu_timings.iter() {
+            text.push_str(&format!("\n    {}: {:.3}ms", name, time));
+        }
+        text
+    }
+
+    fn ticks_to_milliseconds(&self, begin_tick: u64, end_tick: u64) -> f32 {
+        (end_tick.saturating_sub(begin_tick) as f64 * self._timestamp_period as f64 / 1_000_000.0) as f32
+    }
+
+    // without WAIT, the results of a frame still in flight are skipped and the previous timings are kept
+    fn read_query_results(&mut self, device: &Device, swapchain_index: u32) {
+        let query_count = self._query_counts[swapchain_index as usize];
+        if 0 == query_count {
+            return;
+        }
+        let mut query_results: Vec<[u64; 2]> = vec![[0, 0]; query_count as usize]; // [timestamp, availability]
+        // DeviceV1_0::get_query_pool_results does not take the stride of the availability
+        let read_result = unsafe {
+            device.fp_v1_0().get_query_pool_results(
+                device.handle(),
+                self._query_pools[swapchain_index as usize],
+                0,
+                query_count,
+                std::mem::size_of_val(query_results.as_slice()),
+                query_results.as_mut_ptr() as *mut c_void,
+                std::mem::size_of::<[u64; 2]>() as vk::DeviceSize,
+                vk::QueryResultFlags::TYPE_64 | vk::QueryResultFlags::WITH_AVAILABILITY
+            )
+        };
+        if vk::Result::SUCCESS != read_result && vk::Result::NOT_READY != read_result {
+            log::warn!("read_query_results: {:?}", read_result);
+            return;
+        }
+
+        let mut gpu_timings: Vec<(String, f32)> = Vec::new();
+        for scope in self._scopes[swapchain_index as usize].iter() {
+            if INVALID_QUERY == scope._end_query {
+                continue;
+            }
+            let (begin_result, end_result) = (query_results[scope._begin_query as usize], query_results[scope._end_query as usize]);
+            if 0 == begin_result[1] || 0 == end_result[1] {
+                return;
+            }
+            let time = self.ticks_to_milliseconds(begin_result[0], end_result[0]);
+            let name = format!("{}{}", "  ".repeat(scope._depth as usize), scope._name);
+            // the scopes of the same name in a frame are summed, ex) the shadow cascades
+            match gpu_timings.iter_mut().find(|(timing_name, _)| *timing_name == name) {
+                Some(gpu_timing) => gpu_timing.1 += time,
+                None => gpu_timings.push((name, time)),
+            }
+        }
+        self._gpu_timings = gpu_timings;
+    }
+
+    fn write_timestamp(&mut self, device: &Device, command_buffer: vk::CommandBuffer, pipeline_stage: vk::PipelineStageFlags) -> u32 {
+        let swapchain_index = self._recording_swapchain_index as usize;
+        let query = self._query_counts[swapchain_index];
+        if GPU_PROFILER_MAX_QUERY_COUNT <= query {
+            return INVALID_QUERY;
+        }
+        unsafe {
+            device.cmd_write_timestamp(command_buffer, pipeline_stage, self._query_pools[swapchain_index], query);
+        }
+        self._query_counts[swapchain_index] = query + 1;
+        query
+    }
+
+    // at the beginning of the command buffer, outside of the render passes
+    pub fn begin_frame(&mut self, device: &Device, command_buffer: vk::CommandBuffer, swapchain_index: u32) {
+        if false == self._is_supported || false == is_gpu_profiler_enabled() {
+            return;
+        }
+        self.read_query_results(device, swapchain_index);
+        self._recording_swapchain_index = swapchain_index;
+        self._is_recording = true;
+        self._scopes[swapchain_index as usize].clear();
+        self._query_counts[swapchain_index as usize] = 0;
+        self._scope_stack.clear();
+        unsafe {
+            device.cmd_reset_query_pool(command_buffer, self._query_pools[swapchain_index as usize], 0, GPU_PROFILER_MAX_QUERY_COUNT);
+        }
+        self.begin_scope(device, command_buffer, GPU_PROFILER_FRAME_SCOPE_NAME);
+    }
+
+    // before the end of the command buffer
+    pub fn end_frame(&mut self, device: &Device, command_buffer: vk::CommandBuffer) {
+        while false == self._scope_stack.is_empty() {
+            self.end_scope(device, command_buffer);
+        }
+        self._is_recording = false;
+    }
+
+    // the command buffer of the frame is re-recorded, the queries of the aborted recording were never submitted
+    pub fn discard_frame(&mut self) {
+        let swapchain_index = self._recording_swapchain_index as usize;
+        if swapchain_index < self._query_counts.len() {
+            self._query_counts[swapchain_index] = 0;
+            self._scopes[swapchain_index].clear();
+        }
+        self._scope_stack.clear();
+        self._is_recording = false;
+    }
+
+    pub fn begin_scope(&mut self, device: &Device, command_buffer: vk::CommandBuffer, scope_name: &str) {
+        if false == self._is_recording {
+            return;
+        }
+        let begin_query = self.write_timestamp(device, command_buffer, vk::PipelineStageFlags::TOP_OF_PIPE);
+        let scopes = &mut self._scopes[self._recording_swapchain_index as usize];
+        // the scopes over the query count are pushed too, so end_scope pops the matching scope
+        self._scope_stack.push(scopes.len());
+        scopes.push(GpuProfileScope {
+            _name: String::from(scope_name),
+            _depth: (self._scope_stack.len() - 1) as u32,
+            _begin_query: begin_query,
+            _end_query: INVALID_QUERY,
+        });
+    }
+
+    pub fn end_scope(&mut self, device: &Device, command_buffer: vk::CommandBuffer) {
+        if false == self._is_recording {
+            return;
+        }
+        if let Some(scope_index) = self._scope_stack.pop() {
+            let begin_query = self._scopes[self._recording_swapchain_index as usize][scope_index]._begin_query;
+            if INVALID_QUERY != begin_query {
+                let end_query = self.write_timestamp(device, command_buffer, vk::PipelineStageFlags::BOTTOM_OF_PIPE);
+                self._scopes[self._recording_swapchain_index as usize][scope_index]._end_query = end_query;
+            }
+        }
+    }
+}
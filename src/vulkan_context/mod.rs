@@ -1,13 +1,21 @@
+pub mod bindless_texture;
 pub mod buffer;
 pub mod command_buffer;
 pub mod descriptor;
 pub mod device;
+pub mod device_memory_allocator;
 pub mod framebuffer;
 pub mod geometry_buffer;
+pub mod gpu_breadcrumb;
+pub mod gpu_profiler;
+pub mod object_name;
+pub mod push_constant;
 pub mod queue;
 pub mod render_pass;
 pub mod shader;
+pub mod shading_rate;
 pub mod swapchain;
 pub mod sync;
 pub mod texture;
+pub mod uniform_arena;
 pub mod vulkan_context;
\ No newline at end of file
@@ -0,0 +1,209 @@
+use std::collections::HashMap;
+use std::ffi::CString;
+
+use ash::vk;
+use ash::vk::Handle;
+use ash::extensions::ext::DebugUtils;
+
+// the engine names of the vulkan objects, registered by the creation helpers of vulkan_context with the name they receive.
+// the mapping is kept in the release build for the device lost report and the validation messages,
+// vkSetDebugUtilsObjectNameEXT is only called in the debug build with the debug utils extension.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObjectName {
+    pub _object_type: vk::ObjectType,
+    pub _object_name: String,
+}
+
+pub struct ObjectNameRegistry {
+    pub _device: vk::Device,
+    pub _debug_util_interface: Option<DebugUtils>,
+    pub _object_names: HashMap<u64, ObjectName>, // the raw handle
+}
+
+// the objects are created and destroyed on the main thread
+static mut OBJECT_NAME_REGISTRY: Option<ObjectNameRegistry> = None;
+
+fn get_object_name_registry() -> Option<&'static mut ObjectNameRegistry> {
+    unsafe { OBJECT_NAME_REGISTRY.as_mut() }
+}
+
+pub fn is_debug_utils_object_name_enabled() -> bool {
+    cfg!(debug_assertions)
+}
+
+pub fn create_object_name_registry(device: vk::Device, debug_util_interface: Option<&DebugUtils>) {
+    let debug_util_interface = if is_debug_utils_object_name_enabled() { debug_util_interface.cloned() } else { None };
+    log::info!("create_object_name_registry: debug utils object name: {}", debug_util_interface.is_some());
+    unsafe {
+        OBJECT_NAME_REGISTRY = Some(ObjectNameRegistry {
+            _device: device,
+            _debug_util_interface: debug_util_interface,
+            _object_names: HashMap::new(),
+        });
+    }
+}
+
+pub fn destroy_object_name_registry() {
+    if let Some(registry) = get_object_name_registry() {
+        log::info!("destroy_object_name_registry: {} objects are still named", registry._object_names.len());
+    }
+    unsafe {
+        OBJECT_NAME_REGISTRY = None;
+    }
+}
+
+pub fn set_object_name<T: Handle>(handle: T, object_name: &str) {
+    let registry = match get_object_name_registry() {
+        Some(registry) => registry,
+        None => return,
+    };
+    let object_type = T::TYPE;
+    let raw_handle = handle.as_raw();
+    if 0 == raw_handle {
+        return;
+    }
+    if let Some(debug_util_interface) = registry._debug_util_interface.as_ref() {
+        let c_object_name = CString::new(object_name).unwrap_or_default();
+        let name_info = vk::DebugUtilsObjectNameInfoEXT {
+            object_type,
+            object_handle: raw_handle,
+            p_object_name: c_object_name.as_ptr(),
+            ..Default::default()
+        };
+        unsafe {
+            if let Err(e) = debug_util_interface.debug_utils_set_object_name(registry._device, &name_info) {
+                log::warn!("set_object_name {}: {:?}", object_name, e);
+            }
+        }
+    }
+    registry._object_names.insert(raw_handle, ObjectName {
+        _object_type: object_type,
+        _object_name: String::from(object_name),
+    });
+}
+
+// the indexed objects of a swapchain array, ex) "SceneColor[0]"
+pub fn set_object_names<T: Handle + Copy>(handles: &[T], object_name: &str) {
+    for (index, handle) in handles.iter().enumerate() {
+        set_object_name(*handle, &format!("{}[{}]", object_name, index));
+    }
+}
+
+pub fn remove_object_name<T: Handle>(handle: T) {
+    if let Some(registry) = get_object_name_registry() {
+        registry._object_names.remove(&handle.as_raw());
+    }
+}
+
+pub fn remove_object_names<T: Handle + Copy>(handles: &[T]) {
+    for handle in handles.iter() {
+        remove_object_name(*handle);
+    }
+}
+
+pub fn get_object_name(raw_handle: u64) -> Option<String> {
+    get_object_name_registry().and_then(|registry| registry._object_names.get(&raw_handle).map(|object_name| object_name._object_name.clone()))
+}
+
+pub fn has_object_name<T: Handle>(handle: T) -> bool {
+    get_object_name_registry().map_or(false, |registry| registry._object_names.contains_key(&handle.as_raw()))
+}
+
+// the objects of the validation message with the known names, appended to the message by the debug callback.
+pub unsafe fn get_callback_object_names(callback_data: &vk::DebugUtilsMessengerCallbackDataEXT) -> Vec<String> {
+    let mut object_names: Vec<String> = Vec::new();
+    if callback_data.p_objects.is_null() {
+        return object_names;
+    }
+    let objects = std::slice::from_raw_parts(callback_data.p_objects, callback_data.object_count as usize);
+    for object in objects.iter() {
+        if let Some(object_name) = get_object_name(object.object_handle) {
+            object_names.push(format!("{:?} {:#x} \"{}\"", object.object_type, object.object_handle, object_name));
+        }
+    }
+    object_names
+}
+
+// the number of the named objects per type, for the device lost report
+pub fn get_object_name_summary() -> String {
+    let registry = match get_object_name_registry() {
+        Some(registry) => registry,
+        None => return String::from("object names: none"),
+    };
+    let mut counts: Vec<(String, u32)> = Vec::new();
+    for object_name in registry._object_names.values() {
+        let object_type_name = format!("{:?}", object_name._object_type);
+        match counts.iter_mut().find(|(name, _)| *name == object_type_name) {
+            Some(count) => count.1 += 1,
+            None => counts.push((object_type_name, 1)),
+        }
+    }
+    counts.sort();
+    let counts: Vec<String> = counts.iter().map(|(name, count)| format!("{}: {}", name, count)).collect();
+    format!("object names: {}", counts.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::render_target::{ self, DynamicRenderTargets, RenderTargetDefinition, RenderTargetSize };
+    use crate::vulkan_context::texture::{ self, TextureData };
+
+    // the handles of a render target without the device, unique per render target
+    fn create_render_target_data(render_target_name: &str, render_target_index: u64) -> TextureData {
+        let raw_handle = (render_target_index + 1) << 8;
+        TextureData {
+            _texture_data_name: String::from(render_target_name),
+            _image: vk::Image::from_raw(raw_handle),
+            _image_view: vk::ImageView::from_raw(raw_handle + 1),
+            _image_sampler: vk::Sampler::from_raw(raw_handle + 2),
+            _sub_image_views: vec![vec![vk::ImageView::from_raw(raw_handle + 3), vk::ImageView::from_raw(raw_handle + 4)]],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_render_target_object_names() {
+        create_object_name_registry(vk::Device::null(), None);
+        let definitions: Vec<RenderTargetDefinition> = ["bloom_half", "bloom_quarter", "ui"].iter().map(|render_target_name| RenderTargetDefinition {
+            _name: String::from(*render_target_name),
+            _formats: vec![String::from("R16G16B16A16_SFLOAT")],
+            _size: RenderTargetSize::RenderRelative { _scale: 0.5 },
+            ..Default::default()
+        }).collect();
+        let (definitions, errors) = render_target::validate_render_target_definitions(&definitions);
+        assert!(errors.is_empty(), "{:?}", errors);
+
+        // the render targets are named as the creation helpers of texture do
+        let mut dynamic_render_targets = DynamicRenderTargets::default();
+        for (index, definition) in definitions.iter().enumerate() {
+            let texture_data = create_render_target_data(&definition._name, index as u64);
+            texture::set_texture_object_names(&texture_data);
+            dynamic_render_targets.add_render_target(definition, texture_data);
+        }
+        let render_target_names = dynamic_render_targets.get_render_target_names();
+        assert_eq!(definitions.len(), render_target_names.len());
+        for render_target_name in render_target_names.iter() {
+            let render_target_id = dynamic_render_targets.get_render_target_id(render_target_name).unwrap();
+            let texture_data = &dynamic_render_targets._render_targets[render_target_id];
+            assert!(has_object_name(texture_data._image), "{} has no object name", render_target_name);
+            assert_eq!(Some(render_target_name.clone()), get_object_name(texture_data._image.as_raw()));
+            assert_eq!(Some(render_target_name.clone()), get_object_name(texture_data._image_view.as_raw()));
+            assert_eq!(Some(render_target_name.clone()), get_object_name(texture_data._image_sampler.as_raw()));
+            assert_eq!(Some(format!("{}/0/1", render_target_name)), get_object_name(texture_data._sub_image_views[0][1].as_raw()));
+        }
+        assert_eq!("object names: IMAGE: 3, IMAGE_VIEW: 9, SAMPLER: 3", get_object_name_summary());
+
+        // a render target created without the helpers has no name
+        let unnamed_render_target = create_render_target_data("unnamed", definitions.len() as u64);
+        assert!(false == has_object_name(unnamed_render_target._image));
+
+        // the names are released with the render targets
+        for texture_data in dynamic_render_targets._render_targets.iter() {
+            texture::remove_texture_object_names(texture_data);
+        }
+        assert!(get_object_name_registry().unwrap()._object_names.is_empty());
+        destroy_object_name_registry();
+        assert!(false == has_object_name(vk::Image::from_raw(1 << 8)));
+    }
+}
@@ -0,0 +1,169 @@
+use ash::vk;
+
+// the push constant structs shared with the shaders, the layout must match with the glsl block.
+// ex) impl_push_constant!(PushConstant_RenderUI, 16);
+pub trait PushConstant {
+    const PUSH_CONSTANT_SIZE: usize;
+}
+
+// the declared size is checked at compile time, so a new field breaks the build instead of the shader.
+#[macro_export]
+macro_rules! impl_push_constant {
+    ($push_constant_type:ty, $push_constant_size:expr) => {
+        const _: [(); $push_constant_size] = [(); std::mem::size_of::<$push_constant_type>()];
+        const _: [(); 0] = [(); std::mem::size_of::<$push_constant_type>() % 4];
+        impl $crate::vulkan_context::push_constant::PushConstant for $push_constant_type {
+            const PUSH_CONSTANT_SIZE: usize = $push_constant_size;
+        }
+    };
+}
+
+// for the callers without push constants, ex) render_material_instance(..., None::<&()>)
+impl PushConstant for () {
+    const PUSH_CONSTANT_SIZE: usize = 0;
+}
+
+// the ranges of the pipeline create info, the same range with the different stages are merged.
+#[derive(Clone, Debug, Default)]
+pub struct PushConstantLayout {
+    pub _push_constant_ranges: Vec<vk::PushConstantRange>,
+}
+
+impl PushConstantLayout {
+    pub fn create_push_constant_layout(push_constant_ranges: &[vk::PushConstantRange]) -> PushConstantLayout {
+        let mut merged_ranges: Vec<vk::PushConstantRange> = Vec::new();
+        for push_constant_range in push_constant_ranges.iter() {
+            match merged_ranges.iter_mut().find(|range| range.offset == push_constant_range.offset && range.size == push_constant_range.size) {
+                Some(range) => range.stage_flags |= push_constant_range.stage_flags,
+                None => merged_ranges.push(*push_constant_range),
+            }
+        }
+        merged_ranges.sort_by_key(|range| range.offset);
+        PushConstantLayout {
+            _push_constant_ranges: merged_ranges,
+        }
+    }
+
+    pub fn get_push_constant_size(&self) -> u32 {
+        self._push_constant_ranges.iter().map(|range| range.offset + range.size).max().unwrap_or(0)
+    }
+
+    pub fn get_stage_flags(&self) -> vk::ShaderStageFlags {
+        self._push_constant_ranges.iter().fold(vk::ShaderStageFlags::empty(), |stage_flags, range| stage_flags | range.stage_flags)
+    }
+
+    // vkCmdPushConstants requires the stages of every range overlapping the update, so the ranges must not partially overlap.
+    pub fn validate_push_constant_layout(&self, pipeline_data_name: &str, max_push_constants_size: u32) -> Result<(), String> {
+        let push_constant_size = self.get_push_constant_size();
+        if max_push_constants_size < push_constant_size {
+            return Err(format!("{}: push constant size {} exceeds maxPushConstantsSize {}", pipeline_data_name, push_constant_size, max_push_constants_size));
+        }
+        for (i, range) in self._push_constant_ranges.iter().enumerate() {
+            if 0 != (range.offset % 4) || 0 != (range.size % 4) {
+                return Err(format!("{}: push constant range[{}] offset {} size {} must be a multiple of 4", pipeline_data_name, i, range.offset, range.size));
+            }
+            if let Some(next_range) = self._push_constant_ranges.get(i + 1) {
+                if next_range.offset < (range.offset + range.size) {
+                    return Err(format!("{}: push constant range[{}] and range[{}] are partially overlapped", pipeline_data_name, i, i + 1));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn validate_push_constant<T: PushConstant>(&self, pipeline_data_name: &str, max_push_constants_size: u32) -> Result<(), String> {
+        self.validate_push_constant_layout(pipeline_data_name, max_push_constants_size)?;
+        let push_constant_size = self.get_push_constant_size() as usize;
+        if std::mem::size_of::<T>() != T::PUSH_CONSTANT_SIZE || T::PUSH_CONSTANT_SIZE != push_constant_size {
+            return Err(format!(
+                "{}: {} is {} bytes, but the pipeline declares {} bytes",
+                pipeline_data_name, std::any::type_name::<T>(), std::mem::size_of::<T>(), push_constant_size
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[allow(non_camel_case_types)]
+    #[repr(C)]
+    struct PushConstant_Test {
+        pub _color: [f32; 4],
+        pub _offset: [f32; 2],
+        pub _reserved0: u32,
+        pub _reserved1: u32,
+    }
+
+    crate::impl_push_constant!(PushConstant_Test, 32);
+
+    // a field has been added to the struct, the glsl block and the pipeline are not updated
+    #[allow(non_camel_case_types)]
+    #[repr(C)]
+    struct PushConstant_Mismatched {
+        pub _color: [f32; 4],
+        pub _offset: [f32; 2],
+        pub _reserved0: u32,
+        pub _reserved1: u32,
+        pub _scale: f32,
+        pub _reserved2: u32,
+    }
+
+    crate::impl_push_constant!(PushConstant_Mismatched, 40);
+
+    fn create_push_constant_range(stage_flags: vk::ShaderStageFlags, offset: u32, size: u32) -> vk::PushConstantRange {
+        vk::PushConstantRange { stage_flags, offset, size }
+    }
+
+    #[test]
+    fn test_push_constant_layout() {
+        let push_constant_layout = PushConstantLayout::create_push_constant_layout(&[
+            create_push_constant_range(vk::ShaderStageFlags::FRAGMENT, 16, 16),
+            create_push_constant_range(vk::ShaderStageFlags::VERTEX, 0, 16),
+            create_push_constant_range(vk::ShaderStageFlags::GEOMETRY, 0, 16),
+        ]);
+        // the same ranges are merged and sorted by the offset
+        assert_eq!(2, push_constant_layout._push_constant_ranges.len());
+        assert_eq!(0, push_constant_layout._push_constant_ranges[0].offset);
+        assert_eq!(vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::GEOMETRY, push_constant_layout._push_constant_ranges[0].stage_flags);
+        assert_eq!(vk::ShaderStageFlags::FRAGMENT, push_constant_layout._push_constant_ranges[1].stage_flags);
+        assert_eq!(32, push_constant_layout.get_push_constant_size());
+        assert_eq!(vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::GEOMETRY | vk::ShaderStageFlags::FRAGMENT, push_constant_layout.get_stage_flags());
+        assert!(push_constant_layout.validate_push_constant_layout("test_pipeline", 128).is_ok());
+
+        let empty_layout = PushConstantLayout::create_push_constant_layout(&[]);
+        assert_eq!(0, empty_layout.get_push_constant_size());
+        assert!(empty_layout.validate_push_constant::<()>("test_pipeline", 128).is_ok());
+    }
+
+    #[test]
+    fn test_push_constant_size_mismatch() {
+        let push_constant_layout = PushConstantLayout::create_push_constant_layout(&[create_push_constant_range(vk::ShaderStageFlags::ALL, 0, 32)]);
+        assert!(push_constant_layout.validate_push_constant::<PushConstant_Test>("render_pass_test/test_pipeline", 128).is_ok());
+
+        let error = push_constant_layout.validate_push_constant::<PushConstant_Mismatched>("render_pass_test/test_pipeline", 128).unwrap_err();
+        assert!(error.starts_with("render_pass_test/test_pipeline:"), "{}", error);
+        assert!(error.contains("PushConstant_Mismatched is 40 bytes"), "{}", error);
+        assert!(error.contains("declares 32 bytes"), "{}", error);
+    }
+
+    #[test]
+    fn test_invalid_push_constant_layout() {
+        // maxPushConstantsSize is 128 bytes on many mobile devices
+        let push_constant_layout = PushConstantLayout::create_push_constant_layout(&[create_push_constant_range(vk::ShaderStageFlags::ALL, 0, 256)]);
+        let error = push_constant_layout.validate_push_constant_layout("test_pipeline", 128).unwrap_err();
+        assert!(error.contains("exceeds maxPushConstantsSize 128"), "{}", error);
+        assert!(push_constant_layout.validate_push_constant_layout("test_pipeline", 256).is_ok());
+
+        let push_constant_layout = PushConstantLayout::create_push_constant_layout(&[create_push_constant_range(vk::ShaderStageFlags::ALL, 2, 16)]);
+        assert!(push_constant_layout.validate_push_constant_layout("test_pipeline", 128).unwrap_err().contains("multiple of 4"));
+
+        let push_constant_layout = PushConstantLayout::create_push_constant_layout(&[
+            create_push_constant_range(vk::ShaderStageFlags::VERTEX, 0, 32),
+            create_push_constant_range(vk::ShaderStageFlags::FRAGMENT, 16, 32),
+        ]);
+        assert!(push_constant_layout.validate_push_constant_layout("test_pipeline", 128).unwrap_err().contains("partially overlapped"));
+    }
+}
@@ -18,13 +18,15 @@ pub struct QueueFamilyIndices {
     pub _present_queue_index: u32,
     pub _compute_queue_index: u32,
     pub _transfer_queue_index: u32,
-    pub _sparse_binding_queue_index: u32
+    pub _sparse_binding_queue_index: u32,
+    pub _dedicated_transfer_queue_index: u32, // INVALID_QUEUE_INDEX: the uploads run on the graphics queue
 }
 
 #[derive(Debug, Clone)]
 pub struct QueueFamilyDatas {
     pub _graphics_queue: vk::Queue,
     pub _present_queue: vk::Queue,
+    pub _transfer_queue: vk::Queue, // the graphics queue without the dedicated transfer queue
     pub _queue_family_index_list: Vec<u32>,
     pub _queue_family_count: u32,
     pub _queue_family_indices: QueueFamilyIndices
@@ -84,6 +86,33 @@ fn select_presentation_queue_family(
     }
 }
 
+impl QueueFamilyDatas {
+    pub fn has_dedicated_transfer_queue(&self) -> bool {
+        constants::INVALID_QUEUE_INDEX != self._queue_family_indices._dedicated_transfer_queue_index
+    }
+
+    pub fn get_transfer_queue_family_index(&self) -> u32 {
+        if self.has_dedicated_transfer_queue() {
+            self._queue_family_indices._dedicated_transfer_queue_index
+        } else {
+            self._queue_family_indices._graphics_queue_index
+        }
+    }
+}
+
+// the transfer only family(dma engine) first, then the transfer family without the graphics. no surface support is needed.
+fn select_dedicated_transfer_queue_family(queue_family_properties: &Vec<vk::QueueFamilyProperties>) -> Option<u32> {
+    let find_queue_family = |excluded_flags: vk::QueueFlags| {
+        queue_family_properties
+            .iter()
+            .position(|queue_family_property| {
+                queue_family_property.queue_flags.contains(vk::QueueFlags::TRANSFER) && false == queue_family_property.queue_flags.intersects(excluded_flags)
+            })
+            .map(|index| index as u32)
+    };
+    find_queue_family(vk::QueueFlags::GRAPHICS | vk::QueueFlags::COMPUTE).or_else(|| find_queue_family(vk::QueueFlags::GRAPHICS))
+}
+
 pub fn get_queue_families(
     instance: &Instance,
     physical_device: vk::PhysicalDevice
@@ -133,13 +162,15 @@ pub fn get_queue_family_indices(
         _present_queue_index: fn_get_queue_family_index(&presentation_queue_family_indices),
         _compute_queue_index: fn_get_queue_family_index(&compute_queue_family_indices),
         _transfer_queue_index: fn_get_queue_family_index(&transfer_queue_family_indices),
-        _sparse_binding_queue_index: fn_get_queue_family_index(&sparse_binding_queue_family_indices)
+        _sparse_binding_queue_index: fn_get_queue_family_index(&sparse_binding_queue_family_indices),
+        _dedicated_transfer_queue_index: select_dedicated_transfer_queue_family(&queue_faimilies).unwrap_or(constants::INVALID_QUEUE_INDEX),
     };
 
     log::info!("Graphics Queue Index : {}", queue_family_indices._graphics_queue_index);
     log::info!("Presentation Queue Index : {} / {:?}", queue_family_indices._present_queue_index, presentation_queue_family_indices);
     log::info!("Computer Queue Index : {} / {:?}", queue_family_indices._compute_queue_index, compute_queue_family_indices);
     log::info!("Transfer Queue Index : {} / {:?}", queue_family_indices._transfer_queue_index, transfer_queue_family_indices);
+    log::info!("Dedicated Transfer Queue Index : {}", queue_family_indices._dedicated_transfer_queue_index);
     log::info!("Sparse Binding Queue Index : {} / {:?}", queue_family_indices._sparse_binding_queue_index, sparse_binding_queue_family_indices);
 
     queue_family_indices
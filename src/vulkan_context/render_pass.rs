@@ -8,22 +8,35 @@ use ash::{
     Device,
 };
 use ash::version::{
-    DeviceV1_0
+    DeviceV1_0,
+    DeviceV1_2,
 };
 
 use crate::vulkan_context::geometry_buffer::{ VertexData, StaticVertexData };
+use crate::constants;
 use crate::vulkan_context::descriptor::{
     DescriptorDataCreateInfo,
     DescriptorData,
+    DescriptorResourceType,
 };
 use crate::vulkan_context::framebuffer::{
     FramebufferDataCreateInfo,
 };
+use crate::vulkan_context::object_name;
+use crate::vulkan_context::vulkan_context::SwapchainArray;
+use crate::vulkan_context::push_constant::PushConstantLayout;
 use crate::vulkan_context::shader::{
     create_shader_stage_create_info,
     destroy_shader_stage_create_info
 };
+use crate::vulkan_context::shading_rate::{
+    FragmentShadingRateAttachmentInfoKHR,
+    PipelineFragmentShadingRateStateCreateInfoKHR,
+    STRUCTURE_TYPE_FRAGMENT_SHADING_RATE_ATTACHMENT_INFO_KHR,
+};
+use crate::utilities::math;
 use crate::utilities::system::{ RcRefCell, newRcRefCell };
+use crate::utilities::telemetry::{ self, ResourceClass };
 use ash::vk::Handle;
 
 #[derive(Clone, Debug)]
@@ -39,10 +52,22 @@ pub struct RenderPassDataCreateInfo  {
     pub _color_attachment_descriptions: Vec<ImageAttachmentDescription>,
     pub _depth_attachment_descriptions: Vec<ImageAttachmentDescription>,
     pub _resolve_attachment_descriptions: Vec<ImageAttachmentDescription>,
+    pub _shading_rate_attachment_description: Option<ImageAttachmentDescription>, // None on unsupported devices, see RendererData::get_shading_rate_attachment_description
+    pub _shading_rate_texel_size: vk::Extent2D,
     pub _subpass_dependencies: Vec<vk::SubpassDependency>,
+    pub _subpass_data_create_infos: Vec<SubpassDataCreateInfo>, // empty: a single subpass with all attachments
     pub _pipeline_data_create_infos: Vec<PipelineDataCreateInfo>
 }
 
+// the attachment indices are in the order of the attachments: colors, depth, resolves.
+#[derive(Clone, Debug, Default)]
+pub struct SubpassDataCreateInfo {
+    pub _color_attachment_indices: Vec<u32>,
+    pub _input_attachment_indices: Vec<u32>, // input_attachment_index of subpassInput is the order in this list
+    pub _depth_attachment_index: Option<u32>,
+    pub _resolve_attachment_indices: Vec<u32>, // empty or the same count as the colors
+}
+
 impl Default for RenderPassDataCreateInfo {
     fn default() -> RenderPassDataCreateInfo {
         RenderPassDataCreateInfo {
@@ -51,21 +76,101 @@ impl Default for RenderPassDataCreateInfo {
             _color_attachment_descriptions: Vec::new(),
             _depth_attachment_descriptions: Vec::new(),
             _resolve_attachment_descriptions: Vec::new(),
+            _shading_rate_attachment_description: None,
+            _shading_rate_texel_size: vk::Extent2D { width: 16, height: 16 },
             _subpass_dependencies: Vec::new(),
+            _subpass_data_create_infos: Vec::new(),
             _pipeline_data_create_infos: Vec::new(),
         }
     }
 }
 
+impl RenderPassDataCreateInfo {
+    pub fn has_stencil_attachment(&self) -> bool {
+        self._depth_attachment_descriptions.iter().any(|depth_attachment_description| {
+            constants::DEPTH_STENCIL_FORMATS.contains(&depth_attachment_description._attachment_image_format)
+        })
+    }
+
+    // the depth target fell back to the depth only format, the stencil test of the pipelines is disabled.
+    pub fn disable_stencil_without_stencil_attachment(&mut self) {
+        if self.has_stencil_attachment() {
+            return;
+        }
+        for pipeline_data_create_info in self._pipeline_data_create_infos.iter_mut() {
+            if pipeline_data_create_info._depth_stencil_state_create_info._stencil_test_enable {
+                log::warn!("{}/{}: no stencil attachment, the stencil test is disabled.", self._render_pass_create_info_name, pipeline_data_create_info._pipeline_data_create_info_name);
+                pipeline_data_create_info._depth_stencil_state_create_info._stencil_test_enable = false;
+                pipeline_data_create_info._pipeline_dynamic_states.retain(|dynamic_state| vk::DynamicState::STENCIL_REFERENCE != *dynamic_state);
+            }
+        }
+    }
+
+    // the colors and the depth become multisampled, every color resolves into a single sample attachment of the same format.
+    // the framebuffer needs the resolve render targets, the depth is not resolved.
+    pub fn enable_msaa(&mut self, sample_count: vk::SampleCountFlags) {
+        if vk::SampleCountFlags::TYPE_1 == sample_count {
+            return;
+        }
+        if false == self._subpass_data_create_infos.is_empty() || false == self._resolve_attachment_descriptions.is_empty() {
+            log::warn!("{}: the custom subpasses and the resolve attachments are not supported by enable_msaa.", self._render_pass_create_info_name);
+            return;
+        }
+        for color_attachment_description in self._color_attachment_descriptions.iter_mut() {
+            self._resolve_attachment_descriptions.push(ImageAttachmentDescription {
+                _attachment_image_format: color_attachment_description._attachment_image_format,
+                _attachment_load_operation: vk::AttachmentLoadOp::DONT_CARE,
+                _attachment_store_operation: vk::AttachmentStoreOp::STORE,
+                _attachment_final_layout: color_attachment_description._attachment_final_layout,
+                _attachment_reference_layout: color_attachment_description._attachment_reference_layout,
+                ..Default::default()
+            });
+            color_attachment_description._attachment_image_samples = sample_count;
+        }
+        for depth_attachment_description in self._depth_attachment_descriptions.iter_mut() {
+            depth_attachment_description._attachment_image_samples = sample_count;
+        }
+        for pipeline_data_create_info in self._pipeline_data_create_infos.iter_mut() {
+            if vk::PipelineBindPoint::GRAPHICS == pipeline_data_create_info._pipeline_bind_point {
+                pipeline_data_create_info._pipeline_sample_count = sample_count;
+            }
+        }
+    }
+
+    pub fn get_subpass_data_create_infos(&self) -> Vec<SubpassDataCreateInfo> {
+        if false == self._subpass_data_create_infos.is_empty() {
+            return self._subpass_data_create_infos.clone();
+        }
+        let color_count = self._color_attachment_descriptions.len() as u32;
+        let depth_count = self._depth_attachment_descriptions.len() as u32;
+        let resolve_count = self._resolve_attachment_descriptions.len() as u32;
+        vec![SubpassDataCreateInfo {
+            _color_attachment_indices: (0..color_count).collect(),
+            _input_attachment_indices: Vec::new(),
+            _depth_attachment_index: if 0 < depth_count { Some(color_count) } else { None },
+            _resolve_attachment_indices: ((color_count + depth_count)..(color_count + depth_count + resolve_count)).collect(),
+        }]
+    }
+
+    pub fn get_attachment_descriptions(&self) -> Vec<ImageAttachmentDescription> {
+        let mut attachment_descriptions: Vec<ImageAttachmentDescription> = self._color_attachment_descriptions.clone();
+        attachment_descriptions.extend(self._depth_attachment_descriptions.clone());
+        attachment_descriptions.extend(self._resolve_attachment_descriptions.clone());
+        attachment_descriptions
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct PipelineDataCreateInfo {
     pub _pipeline_data_create_info_name: String,
     pub _pipeline_bind_point: vk::PipelineBindPoint,
     pub _pipeline_create_flags: vk::PipelineCreateFlags,
+    pub _pipeline_subpass_index: u32,
     pub _pipeline_compute_shader_file: PathBuf,
     pub _pipeline_vertex_shader_file: PathBuf,
     pub _pipeline_fragment_shader_file: PathBuf,
     pub _pipeline_shader_defines: Vec<String>,
+    pub _pipeline_shader_include_directories: Vec<PathBuf>, // ex) include directory of the shader hook snippets
     pub _pipeline_dynamic_states: Vec<vk::DynamicState>,
     pub _pipeline_sample_count: vk::SampleCountFlags,
     pub _pipeline_polygon_mode: vk::PolygonMode,
@@ -74,11 +179,13 @@ pub struct PipelineDataCreateInfo {
     pub _pipeline_viewport: vk::Viewport,
     pub _pipeline_scissor_rect: vk::Rect2D,
     pub _pipeline_color_blend_modes: Vec<vk::PipelineColorBlendAttachmentState>,
+    pub _pipeline_blend_mode_variants: bool, // the transparent, particle, billboard and ui pipelines, the color attachments take the blend mode of the material instance
     pub _depth_stencil_state_create_info: DepthStencilStateCreateInfo,
     pub _vertex_input_bind_descriptions: Vec<vk::VertexInputBindingDescription>,
     pub _vertex_input_attribute_descriptions: Vec<vk::VertexInputAttributeDescription>,
     pub _push_constant_ranges: Vec<vk::PushConstantRange>, // ex) mem::size_of::<PushConstant_StaticRenderObject>()
     pub _descriptor_data_create_infos: Vec<DescriptorDataCreateInfo>,
+    pub _use_bindless_textures: bool, // the opaque pipelines, set 1 is the bindless texture array when the device supports the descriptor indexing
 }
 
 impl Default for PipelineDataCreateInfo {
@@ -87,10 +194,12 @@ impl Default for PipelineDataCreateInfo {
             _pipeline_data_create_info_name: String::new(),
             _pipeline_create_flags: vk::PipelineCreateFlags::default(),
             _pipeline_bind_point: vk::PipelineBindPoint::GRAPHICS,
+            _pipeline_subpass_index: 0,
             _pipeline_compute_shader_file: PathBuf::new(),
             _pipeline_vertex_shader_file: PathBuf::new(),
             _pipeline_fragment_shader_file: PathBuf::new(),
             _pipeline_shader_defines: Vec::new(),
+            _pipeline_shader_include_directories: Vec::new(),
             _pipeline_dynamic_states: vec![vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR],
             _pipeline_sample_count: vk::SampleCountFlags::TYPE_1,
             _pipeline_polygon_mode: vk::PolygonMode::FILL,
@@ -99,11 +208,13 @@ impl Default for PipelineDataCreateInfo {
             _pipeline_viewport: vk::Viewport::default(),
             _pipeline_scissor_rect: vk::Rect2D::default(),
             _pipeline_color_blend_modes: Vec::new(),
+            _pipeline_blend_mode_variants: false,
             _depth_stencil_state_create_info: DepthStencilStateCreateInfo::default(),
             _vertex_input_bind_descriptions: StaticVertexData::get_vertex_input_binding_descriptions(),
             _vertex_input_attribute_descriptions: StaticVertexData::create_vertex_input_attribute_descriptions(),
             _push_constant_ranges: Vec::new(),
             _descriptor_data_create_infos: Vec::new(),
+            _use_bindless_textures: false,
         }
     }
 }
@@ -130,6 +241,44 @@ pub struct DepthStencilStateCreateInfo {
     pub _back_reference: u32,
 }
 
+impl DepthStencilStateCreateInfo {
+    // both faces: compare with the reference, then pass_op on the pass
+    pub fn set_stencil_state(&mut self, compare_op: vk::CompareOp, pass_op: vk::StencilOp, reference: u32, compare_mask: u32, write_mask: u32) {
+        self._stencil_test_enable = true;
+        self._front_fail_op = vk::StencilOp::KEEP;
+        self._front_pass_op = pass_op;
+        self._front_depth_fail_op = vk::StencilOp::KEEP;
+        self._front_compare_op = compare_op;
+        self._front_compare_mask = compare_mask;
+        self._front_write_mask = write_mask;
+        self._front_reference = reference;
+        self._back_fail_op = vk::StencilOp::KEEP;
+        self._back_pass_op = pass_op;
+        self._back_depth_fail_op = vk::StencilOp::KEEP;
+        self._back_compare_op = compare_op;
+        self._back_compare_mask = compare_mask;
+        self._back_write_mask = write_mask;
+        self._back_reference = reference;
+    }
+
+    // writes the reference to the stencil where the depth test passes
+    pub fn create_stencil_write_state(reference: u32, write_mask: u32) -> DepthStencilStateCreateInfo {
+        let mut depth_stencil_state_create_info = DepthStencilStateCreateInfo::default();
+        depth_stencil_state_create_info.set_stencil_state(vk::CompareOp::ALWAYS, vk::StencilOp::REPLACE, reference, write_mask, write_mask);
+        depth_stencil_state_create_info
+    }
+
+    // reads only, ex) vk::CompareOp::NOT_EQUAL: the pixels outside of the stencil mask
+    pub fn create_stencil_test_state(compare_op: vk::CompareOp, reference: u32, compare_mask: u32) -> DepthStencilStateCreateInfo {
+        let mut depth_stencil_state_create_info = DepthStencilStateCreateInfo {
+            _depth_write_enable: false,
+            ..Default::default()
+        };
+        depth_stencil_state_create_info.set_stencil_state(compare_op, vk::StencilOp::KEEP, reference, compare_mask, 0);
+        depth_stencil_state_create_info
+    }
+}
+
 impl Default for DepthStencilStateCreateInfo {
     fn default() -> DepthStencilStateCreateInfo {
         DepthStencilStateCreateInfo {
@@ -194,7 +343,10 @@ pub struct PipelineData {
     pub _pipeline_bind_point: vk::PipelineBindPoint,
     pub _pipeline_layout: vk::PipelineLayout,
     pub _pipeline_dynamic_states: Vec<vk::DynamicState>,
+    pub _pipeline_subpass_index: u32,
+    pub _push_constant_layout: PushConstantLayout,
     pub _descriptor_data: DescriptorData,
+    pub _use_bindless_textures: bool, // false: the fallback to the textures of the material instance descriptor set
 }
 
 impl Default for PipelineData {
@@ -208,7 +360,10 @@ impl Default for PipelineData {
             _pipeline_bind_point: vk::PipelineBindPoint::GRAPHICS,
             _pipeline_layout: vk::PipelineLayout::null(),
             _pipeline_dynamic_states: Vec::new(),
+            _pipeline_subpass_index: 0,
+            _push_constant_layout: PushConstantLayout::default(),
             _descriptor_data: DescriptorData::default(),
+            _use_bindless_textures: false,
         }
     }
 }
@@ -219,11 +374,18 @@ pub struct RenderPassData {
     pub _render_pass: vk::RenderPass,
     pub _default_pipeline_data: RcRefCell<PipelineData>,
     pub _pipeline_data_map: PipelineDataMap,
+    pub _subpass_count: u32,
 }
 
 pub type PipelineDataMap = HashMap<String, RcRefCell<PipelineData>>;
 pub type RenderPassPipelineDataMap = HashMap<String, RenderPassPipelineData>;
 
+impl PipelineData {
+    pub fn has_dynamic_state(&self, dynamic_state: vk::DynamicState) -> bool {
+        self._pipeline_dynamic_states.contains(&dynamic_state)
+    }
+}
+
 impl RenderPassData {
     pub fn get_render_pass_data_name(&self) -> &String {
         &self._render_pass_data_name
@@ -233,6 +395,10 @@ impl RenderPassData {
         self._render_pass
     }
 
+    pub fn get_subpass_count(&self) -> u32 {
+        self._subpass_count
+    }
+
     pub fn get_default_pipeline_data(&self) -> &RcRefCell<PipelineData> {
         &self._default_pipeline_data
     }
@@ -249,21 +415,27 @@ impl RenderPassData {
 pub fn create_render_pass_data(
     device: &Device,
     render_pass_data_create_info: &RenderPassDataCreateInfo,
-    descriptor_datas: &Vec<RcRefCell<DescriptorData>>
+    descriptor_datas: &Vec<RcRefCell<DescriptorData>>,
+    bindless_descriptor_set_layout: Option<vk::DescriptorSetLayout>
 ) -> RenderPassData {
     let render_pass = create_render_pass(device, &render_pass_data_create_info);
+    object_name::set_object_name(render_pass, &render_pass_data_create_info._render_pass_create_info_name);
+    let subpass_data_create_infos = render_pass_data_create_info.get_subpass_data_create_infos();
     let count = render_pass_data_create_info._pipeline_data_create_infos.len();
     let mut pipeline_data_map: PipelineDataMap = HashMap::new();
     let mut default_pipeline_data_name: String = String::new();
     for i in 0..count {
         let bind_point = render_pass_data_create_info._pipeline_data_create_infos[i]._pipeline_bind_point;
         let pipeline_data = if vk::PipelineBindPoint::GRAPHICS == bind_point {
+            let subpass_index = render_pass_data_create_info._pipeline_data_create_infos[i]._pipeline_subpass_index as usize;
             create_graphics_pipeline_data(
                 device,
                 render_pass,
                 &render_pass_data_create_info._pipeline_data_create_infos[i],
-                false == render_pass_data_create_info._depth_attachment_descriptions.is_empty(),
-                &descriptor_datas[i].borrow()
+                subpass_data_create_infos[subpass_index]._depth_attachment_index.is_some(),
+                render_pass_data_create_info._shading_rate_attachment_description.is_some(),
+                &descriptor_datas[i].borrow(),
+                bindless_descriptor_set_layout
             )
         } else {
             create_compute_pipeline_data(
@@ -272,6 +444,9 @@ pub fn create_render_pass_data(
                 &descriptor_datas[i].borrow()
             )
         };
+        // no previous pipeline to fallback on the loading, the hot reload handles the error softly.
+        let pipeline_data = pipeline_data.unwrap_or_else(|e| panic!("create_render_pass_data {}: {}", render_pass_data_create_info._render_pass_create_info_name, e));
+        set_pipeline_data_object_name(&pipeline_data, &format!("{}/{}", render_pass_data_create_info._render_pass_create_info_name, pipeline_data._pipeline_data_name));
         if 0 == i {
             default_pipeline_data_name = pipeline_data._pipeline_data_name.clone();
         }
@@ -284,10 +459,12 @@ pub fn create_render_pass_data(
         _render_pass: render_pass,
         _default_pipeline_data: default_pipeline_data.clone(),
         _pipeline_data_map: pipeline_data_map,
+        _subpass_count: subpass_data_create_infos.len() as u32,
     }
 }
 
 pub fn destroy_render_pass_data(device: &Device, render_pass_data: &RenderPassData) {
+    object_name::remove_object_name(render_pass_data._render_pass);
     destroy_render_pass(device, render_pass_data._render_pass, &render_pass_data._render_pass_data_name);
     for pipeline_data in render_pass_data._pipeline_data_map.values() {
         destroy_pipeline_data(device, &pipeline_data.borrow());
@@ -299,6 +476,10 @@ pub fn create_render_pass(
     deivce: &Device,
     render_pass_data_create_info: &RenderPassDataCreateInfo
 ) -> vk::RenderPass {
+    if render_pass_data_create_info._shading_rate_attachment_description.is_some() {
+        return create_render_pass_with_shading_rate(deivce, render_pass_data_create_info);
+    }
+
     let create_image_attachment = | attachment_description: &ImageAttachmentDescription | -> vk::AttachmentDescription {
         vk::AttachmentDescription {
             format: attachment_description._attachment_image_format,
@@ -312,44 +493,138 @@ pub fn create_render_pass(
             ..Default::default()
         }
     };
-    let create_image_attachment_reference = | attachment_description: &ImageAttachmentDescription, index: u32 | -> vk::AttachmentReference {
-        vk::AttachmentReference {
+    let attachment_descriptions: Vec<ImageAttachmentDescription> = render_pass_data_create_info.get_attachment_descriptions();
+    let image_attachments: Vec<vk::AttachmentDescription> = attachment_descriptions
+        .iter()
+        .map(|attachment_description| create_image_attachment(attachment_description))
+        .collect();
+    let create_image_attachment_references = | attachment_indices: &[u32], is_input_attachment: bool | -> Vec<vk::AttachmentReference> {
+        attachment_indices.iter().map(|index| {
+            let attachment_description = &attachment_descriptions[*index as usize];
+            vk::AttachmentReference {
+                attachment: *index,
+                layout: if is_input_attachment {
+                    get_input_attachment_layout(attachment_description._attachment_image_format)
+                } else {
+                    attachment_description._attachment_reference_layout
+                },
+            }
+        }).collect()
+    };
+    // (colors, inputs, depth, resolves), the subpass descriptions point into these
+    let subpass_attachment_refernces: Vec<(Vec<vk::AttachmentReference>, Vec<vk::AttachmentReference>, Vec<vk::AttachmentReference>, Vec<vk::AttachmentReference>)> = render_pass_data_create_info
+        .get_subpass_data_create_infos()
+        .iter()
+        .map(|subpass_data_create_info| {
+            let depth_attachment_indices: Vec<u32> = subpass_data_create_info._depth_attachment_index.iter().cloned().collect();
+            (
+                create_image_attachment_references(&subpass_data_create_info._color_attachment_indices, false),
+                create_image_attachment_references(&subpass_data_create_info._input_attachment_indices, true),
+                create_image_attachment_references(&depth_attachment_indices, false),
+                create_image_attachment_references(&subpass_data_create_info._resolve_attachment_indices, false),
+            )
+        }).collect();
+    let subpasses: Vec<vk::SubpassDescription> = subpass_attachment_refernces
+        .iter()
+        .map(|(color_attachment_refernces, input_attachment_refernces, depth_attachment_refernces, resolve_attachment_refernces)| {
+            vk::SubpassDescription {
+                pipeline_bind_point: vk::PipelineBindPoint::GRAPHICS,
+                p_color_attachments: if !color_attachment_refernces.is_empty() { color_attachment_refernces.as_ptr() } else { std::ptr::null() },
+                color_attachment_count: color_attachment_refernces.len() as u32,
+                p_input_attachments: if !input_attachment_refernces.is_empty() { input_attachment_refernces.as_ptr() } else { std::ptr::null() },
+                input_attachment_count: input_attachment_refernces.len() as u32,
+                p_resolve_attachments: if !resolve_attachment_refernces.is_empty() { resolve_attachment_refernces.as_ptr() } else { std::ptr::null() },
+                p_depth_stencil_attachment: if !depth_attachment_refernces.is_empty() { depth_attachment_refernces.as_ptr() } else { std::ptr::null() },
+                ..Default::default()
+            }
+        }).collect();
+
+    let render_pass_create_info = vk::RenderPassCreateInfo {
+        p_attachments: image_attachments.as_ptr(),
+        attachment_count: image_attachments.len() as u32,
+        p_subpasses: subpasses.as_ptr(),
+        subpass_count: subpasses.len() as u32,
+        p_dependencies: render_pass_data_create_info._subpass_dependencies.as_ptr(),
+        dependency_count: render_pass_data_create_info._subpass_dependencies.len() as u32,
+        ..Default::default()
+    };
+    unsafe {
+        let render_pass = deivce.create_render_pass(&render_pass_create_info, None).expect("vkCreatePipelineLayout failed!");
+        log::debug!("create_render_pass: {} {:?}", render_pass_data_create_info._render_pass_create_info_name, render_pass);
+        render_pass
+    }
+}
+
+// the shading rate attachment requires vkCreateRenderPass2, it is located after the resolve attachments.
+pub fn create_render_pass_with_shading_rate(
+    deivce: &Device,
+    render_pass_data_create_info: &RenderPassDataCreateInfo
+) -> vk::RenderPass {
+    assert!(render_pass_data_create_info._subpass_data_create_infos.len() <= 1, "the shading rate attachment supports a single subpass: {}", render_pass_data_create_info._render_pass_create_info_name);
+    let create_image_attachment = | attachment_description: &ImageAttachmentDescription | -> vk::AttachmentDescription2 {
+        vk::AttachmentDescription2 {
+            format: attachment_description._attachment_image_format,
+            samples: attachment_description._attachment_image_samples,
+            load_op: attachment_description._attachment_load_operation,
+            store_op: attachment_description._attachment_store_operation,
+            stencil_load_op: attachment_description._attachment_stencil_load_operation,
+            stencil_store_op: attachment_description._attachment_stencil_store_operation,
+            initial_layout: attachment_description._attachment_initial_layout,
+            final_layout: attachment_description._attachment_final_layout,
+            ..Default::default()
+        }
+    };
+    let create_image_attachment_reference = | attachment_description: &ImageAttachmentDescription, index: u32, aspect_mask: vk::ImageAspectFlags | -> vk::AttachmentReference2 {
+        vk::AttachmentReference2 {
             attachment: index,
             layout: attachment_description._attachment_reference_layout,
+            aspect_mask,
+            ..Default::default()
         }
     };
     let color_attachment_descriptions = &render_pass_data_create_info._color_attachment_descriptions;
     let depth_attachment_descriptions = &render_pass_data_create_info._depth_attachment_descriptions;
     let resolve_attachment_descriptions = &render_pass_data_create_info._resolve_attachment_descriptions;
+    let shading_rate_attachment_description = render_pass_data_create_info._shading_rate_attachment_description.as_ref().unwrap();
     let mut attachment_descriptions: Vec<ImageAttachmentDescription> = color_attachment_descriptions.clone();
     attachment_descriptions.extend(depth_attachment_descriptions.clone());
     attachment_descriptions.extend(resolve_attachment_descriptions.clone());
-    let image_attachments: Vec<vk::AttachmentDescription> = attachment_descriptions
+    attachment_descriptions.push(shading_rate_attachment_description.clone());
+    let image_attachments: Vec<vk::AttachmentDescription2> = attachment_descriptions
         .iter()
         .map(|attachment_description| create_image_attachment(attachment_description))
         .collect();
     let mut description_offset: u32 = 0;
-    let color_attachment_refernces: Vec<vk::AttachmentReference> = color_attachment_descriptions
+    let color_attachment_refernces: Vec<vk::AttachmentReference2> = color_attachment_descriptions
         .iter()
         .enumerate()
         .map(|(index, ref description)| {
-            create_image_attachment_reference(description, index as u32)
+            create_image_attachment_reference(description, index as u32, vk::ImageAspectFlags::COLOR)
         }).collect();
     description_offset += color_attachment_refernces.len() as u32;
-    let depth_attachment_refernces: Vec<vk::AttachmentReference> = depth_attachment_descriptions
+    let depth_attachment_refernces: Vec<vk::AttachmentReference2> = depth_attachment_descriptions
         .iter()
         .enumerate()
         .map(|(index, ref description)| {
-            create_image_attachment_reference(description, description_offset + index as u32)
+            create_image_attachment_reference(description, description_offset + index as u32, vk::ImageAspectFlags::DEPTH)
         }).collect();
     description_offset += depth_attachment_refernces.len() as u32;
-    let resolve_attachment_refernces: Vec<vk::AttachmentReference> = resolve_attachment_descriptions
+    let resolve_attachment_refernces: Vec<vk::AttachmentReference2> = resolve_attachment_descriptions
         .iter()
         .enumerate()
         .map(|(index, ref description)| {
-            create_image_attachment_reference(description, description_offset + index as u32)
+            create_image_attachment_reference(description, description_offset + index as u32, vk::ImageAspectFlags::COLOR)
         }).collect();
-    let subpasses = [vk::SubpassDescription {
+    description_offset += resolve_attachment_refernces.len() as u32;
+    let shading_rate_attachment_reference = create_image_attachment_reference(shading_rate_attachment_description, description_offset, vk::ImageAspectFlags::COLOR);
+    let shading_rate_attachment_info = FragmentShadingRateAttachmentInfoKHR {
+        s_type: STRUCTURE_TYPE_FRAGMENT_SHADING_RATE_ATTACHMENT_INFO_KHR,
+        p_next: std::ptr::null(),
+        p_fragment_shading_rate_attachment: &shading_rate_attachment_reference,
+        shading_rate_attachment_texel_size: render_pass_data_create_info._shading_rate_texel_size,
+    };
+    let subpasses = [vk::SubpassDescription2 {
+        p_next: &shading_rate_attachment_info as *const FragmentShadingRateAttachmentInfoKHR as *const std::os::raw::c_void,
         pipeline_bind_point: vk::PipelineBindPoint::GRAPHICS,
         p_color_attachments: if !color_attachment_refernces.is_empty() { color_attachment_refernces.as_ptr() } else { std::ptr::null() },
         color_attachment_count: color_attachment_refernces.len() as u32,
@@ -357,23 +632,257 @@ pub fn create_render_pass(
         p_depth_stencil_attachment: if !depth_attachment_refernces.is_empty() { depth_attachment_refernces.as_ptr() } else { std::ptr::null() },
         ..Default::default()
     }];
+    let subpass_dependencies: Vec<vk::SubpassDependency2> = render_pass_data_create_info._subpass_dependencies
+        .iter()
+        .map(|subpass_dependency| {
+            vk::SubpassDependency2 {
+                src_subpass: subpass_dependency.src_subpass,
+                dst_subpass: subpass_dependency.dst_subpass,
+                src_stage_mask: subpass_dependency.src_stage_mask,
+                dst_stage_mask: subpass_dependency.dst_stage_mask,
+                src_access_mask: subpass_dependency.src_access_mask,
+                dst_access_mask: subpass_dependency.dst_access_mask,
+                dependency_flags: subpass_dependency.dependency_flags,
+                ..Default::default()
+            }
+        }).collect();
 
-    let render_pass_create_info = vk::RenderPassCreateInfo {
+    let render_pass_create_info = vk::RenderPassCreateInfo2 {
         p_attachments: image_attachments.as_ptr(),
         attachment_count: image_attachments.len() as u32,
         p_subpasses: subpasses.as_ptr(),
         subpass_count: subpasses.len() as u32,
-        p_dependencies: render_pass_data_create_info._subpass_dependencies.as_ptr(),
-        dependency_count: render_pass_data_create_info._subpass_dependencies.len() as u32,
+        p_dependencies: subpass_dependencies.as_ptr(),
+        dependency_count: subpass_dependencies.len() as u32,
         ..Default::default()
     };
     unsafe {
-        let render_pass = deivce.create_render_pass(&render_pass_create_info, None).expect("vkCreatePipelineLayout failed!");
-        log::debug!("create_render_pass: {} {:?}", render_pass_data_create_info._render_pass_create_info_name, render_pass);
+        let render_pass = deivce.create_render_pass2(&render_pass_create_info, None).expect("vkCreateRenderPass2 failed!");
+        log::debug!("create_render_pass_with_shading_rate: {} {:?}", render_pass_data_create_info._render_pass_create_info_name, render_pass);
         render_pass
     }
 }
 
+// the second render pass is rendered right after the first render pass, nothing else is recorded between them.
+// input_attachment_names: the render targets of the first render pass in the attachment order(colors, then depth) which the second reads with subpassLoad.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MergedRenderPassInfo {
+    pub _first_render_pass_name: String,
+    pub _second_render_pass_name: String,
+    pub _input_attachment_names: Vec<String>,
+}
+
+pub fn is_subpass_merging_enabled() -> bool {
+    unsafe { constants::ENABLE_SUBPASS_MERGING }
+}
+
+// must match with the layout of DescriptorResourceType::InputAttachment, see Resources::load_material_instance_datas
+pub fn get_input_attachment_layout(image_format: vk::Format) -> vk::ImageLayout {
+    if constants::DEPTH_FOMATS.contains(&image_format) {
+        vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL
+    } else {
+        vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL
+    }
+}
+
+// tile based gpus: the second render pass reads the attachments of the first render pass with subpassLoad in the same render pass,
+// so the attachments stay in the tile memory. ex) render_gbuffer + composite_gbuffer
+// - input_attachment_names: the render target names of the first render pass in the attachment order(colors, then depth),
+//   the RenderTarget descriptors of the second render pass with these names become the input attachments.
+// - framebuffer_create_info: the colors of the first, the colors of the second, then the depth of the first.
+// - the pipelines of the second get the USE_SUBPASS_INPUT define, the shader picks subpassLoad or texture with it.
+// the store operations are kept, DONT_CARE on the attachments which are not sampled later saves the bandwidth.
+// keep the separated render passes when the second render pass samples the attachments at the other pixels.
+pub fn create_merged_render_pass_data_create_info(
+    render_pass_name: &str,
+    first_render_pass_data_create_info: &RenderPassDataCreateInfo,
+    second_render_pass_data_create_info: &RenderPassDataCreateInfo,
+    input_attachment_names: &[String],
+    framebuffer_create_info: FramebufferDataCreateInfo,
+) -> RenderPassDataCreateInfo {
+    let first = first_render_pass_data_create_info;
+    let second = second_render_pass_data_create_info;
+    assert!(first._subpass_data_create_infos.len() <= 1 && second._subpass_data_create_infos.len() <= 1, "merged render passes must have a single subpass");
+    assert!(first._resolve_attachment_descriptions.is_empty() && second._resolve_attachment_descriptions.is_empty(), "resolve attachments are not supported");
+    assert!(second._depth_attachment_descriptions.is_empty(), "the second render pass can not have a depth attachment");
+    assert!(first._shading_rate_attachment_description.is_none() && second._shading_rate_attachment_description.is_none(), "shading rate attachment is not supported");
+
+    let first_color_count = first._color_attachment_descriptions.len() as u32;
+    let second_color_count = second._color_attachment_descriptions.len() as u32;
+    let depth_attachment_index: Option<u32> = if first._depth_attachment_descriptions.is_empty() { None } else { Some(first_color_count + second_color_count) };
+    let mut input_attachment_indices: Vec<u32> = (0..first_color_count.min(input_attachment_names.len() as u32)).collect();
+    if first_color_count < input_attachment_names.len() as u32 {
+        input_attachment_indices.push(depth_attachment_index.expect("the first render pass has no depth attachment"));
+    }
+
+    let mut color_attachment_descriptions = first._color_attachment_descriptions.clone();
+    color_attachment_descriptions.extend(second._color_attachment_descriptions.clone());
+    let subpass_data_create_infos = vec![
+        SubpassDataCreateInfo {
+            _color_attachment_indices: (0..first_color_count).collect(),
+            _input_attachment_indices: Vec::new(),
+            _depth_attachment_index: depth_attachment_index,
+            _resolve_attachment_indices: Vec::new(),
+        },
+        SubpassDataCreateInfo {
+            _color_attachment_indices: (first_color_count..(first_color_count + second_color_count)).collect(),
+            _input_attachment_indices: input_attachment_indices,
+            _depth_attachment_index: None,
+            _resolve_attachment_indices: Vec::new(),
+        },
+    ];
+
+    // the external dependencies of the second render pass move to the subpass 1
+    let remap_subpass = |subpass: u32| if vk::SUBPASS_EXTERNAL == subpass { subpass } else { 1 };
+    let mut subpass_dependencies = first._subpass_dependencies.clone();
+    subpass_dependencies.push(vk::SubpassDependency {
+        src_subpass: 0,
+        dst_subpass: 1,
+        src_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS,
+        dst_stage_mask: vk::PipelineStageFlags::FRAGMENT_SHADER,
+        src_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+        dst_access_mask: vk::AccessFlags::INPUT_ATTACHMENT_READ,
+        dependency_flags: vk::DependencyFlags::BY_REGION,
+    });
+    for subpass_dependency in second._subpass_dependencies.iter() {
+        subpass_dependencies.push(vk::SubpassDependency {
+            src_subpass: remap_subpass(subpass_dependency.src_subpass),
+            dst_subpass: remap_subpass(subpass_dependency.dst_subpass),
+            ..*subpass_dependency
+        });
+    }
+
+    let mut pipeline_data_create_infos: Vec<PipelineDataCreateInfo> = first._pipeline_data_create_infos.clone();
+    for pipeline_data_create_info in second._pipeline_data_create_infos.iter() {
+        let mut pipeline_data_create_info = pipeline_data_create_info.clone();
+        pipeline_data_create_info._pipeline_subpass_index = 1;
+        pipeline_data_create_info._pipeline_shader_defines.push(String::from("USE_SUBPASS_INPUT=1"));
+        for descriptor_data_create_info in pipeline_data_create_info._descriptor_data_create_infos.iter_mut() {
+            if let DescriptorResourceType::RenderTarget = descriptor_data_create_info._descriptor_resource_type {
+                if input_attachment_names.contains(&descriptor_data_create_info._descriptor_name) {
+                    descriptor_data_create_info._descriptor_resource_type = DescriptorResourceType::InputAttachment;
+                    descriptor_data_create_info._descriptor_shader_stage = vk::ShaderStageFlags::FRAGMENT;
+                }
+            }
+        }
+        pipeline_data_create_infos.push(pipeline_data_create_info);
+    }
+
+    RenderPassDataCreateInfo {
+        _render_pass_create_info_name: String::from(render_pass_name),
+        _render_pass_framebuffer_create_info: framebuffer_create_info,
+        _color_attachment_descriptions: color_attachment_descriptions,
+        _depth_attachment_descriptions: first._depth_attachment_descriptions.clone(),
+        _subpass_dependencies: subpass_dependencies,
+        _subpass_data_create_infos: subpass_data_create_infos,
+        _pipeline_data_create_infos: pipeline_data_create_infos,
+        ..Default::default()
+    }
+}
+
+// the framebuffer of create_merged_render_pass_data_create_info: the colors of the first, the colors of the second, then the depth of the first.
+// the clear values are in the order of the attachments, the missing clear values are zero.
+pub fn create_merged_framebuffer_data_create_info(
+    first_framebuffer_create_info: &FramebufferDataCreateInfo,
+    second_framebuffer_create_info: &FramebufferDataCreateInfo,
+) -> FramebufferDataCreateInfo {
+    let first = first_framebuffer_create_info;
+    let second = second_framebuffer_create_info;
+    let first_color_count = first._framebuffer_color_attachment_formats.len();
+    let second_color_count = second._framebuffer_color_attachment_formats.len();
+    let first_depth_count = first._framebuffer_depth_attachment_formats.len();
+    let get_clear_value = |clear_values: &Vec<vk::ClearValue>, index: usize| -> vk::ClearValue {
+        clear_values.get(index).cloned().unwrap_or(vk::ClearValue::default())
+    };
+
+    let mut color_attachment_formats = first._framebuffer_color_attachment_formats.clone();
+    color_attachment_formats.extend(second._framebuffer_color_attachment_formats.iter().cloned());
+    let mut clear_values: Vec<vk::ClearValue> = Vec::new();
+    clear_values.extend((0..first_color_count).map(|index| get_clear_value(&first._framebuffer_clear_values, index)));
+    clear_values.extend((0..second_color_count).map(|index| get_clear_value(&second._framebuffer_clear_values, index)));
+    clear_values.extend((0..first_depth_count).map(|index| get_clear_value(&first._framebuffer_clear_values, first_color_count + index)));
+    let image_views: SwapchainArray<Vec<vk::ImageView>> = first._framebuffer_image_views.iter().zip(second._framebuffer_image_views.iter()).map(|(first_image_views, second_image_views)| {
+        let mut image_views: Vec<vk::ImageView> = first_image_views[..first_color_count].to_vec();
+        image_views.extend_from_slice(&second_image_views[..second_color_count]);
+        image_views.extend_from_slice(&first_image_views[first_color_count..(first_color_count + first_depth_count)]);
+        image_views
+    }).collect();
+
+    FramebufferDataCreateInfo {
+        _framebuffer_color_attachment_formats: color_attachment_formats,
+        _framebuffer_depth_attachment_formats: first._framebuffer_depth_attachment_formats.clone(),
+        _framebuffer_resolve_attachment_formats: Vec::new(),
+        _framebuffer_image_views: image_views,
+        _framebuffer_clear_values: clear_values,
+        ..first.clone()
+    }
+}
+
+// the conditions of create_merged_render_pass_data_create_info, the render passes which can not be merged are kept separated.
+pub fn check_merge_render_passes(first: &RenderPassDataCreateInfo, second: &RenderPassDataCreateInfo, input_attachment_names: &[String]) -> Result<(), String> {
+    if 1 < first._subpass_data_create_infos.len() || 1 < second._subpass_data_create_infos.len() {
+        return Err(String::from("merged render passes must have a single subpass"));
+    }
+    if false == first._resolve_attachment_descriptions.is_empty() || false == second._resolve_attachment_descriptions.is_empty() {
+        return Err(String::from("resolve attachments are not supported"));
+    }
+    if false == second._depth_attachment_descriptions.is_empty() {
+        return Err(String::from("the second render pass can not have a depth attachment"));
+    }
+    if first._shading_rate_attachment_description.is_some() || second._shading_rate_attachment_description.is_some() {
+        return Err(String::from("shading rate attachment is not supported"));
+    }
+    let attachment_count = first._color_attachment_descriptions.len() + first._depth_attachment_descriptions.len();
+    if attachment_count < input_attachment_names.len() {
+        return Err(format!("the first render pass has {} attachments, the input attachments are {:?}", attachment_count, input_attachment_names));
+    }
+    let first_framebuffer = &first._render_pass_framebuffer_create_info;
+    let second_framebuffer = &second._render_pass_framebuffer_create_info;
+    if first_framebuffer._framebuffer_width != second_framebuffer._framebuffer_width || first_framebuffer._framebuffer_height != second_framebuffer._framebuffer_height {
+        return Err(String::from("the framebuffers must have the same size"));
+    }
+    let pipeline_names: Vec<&String> = first._pipeline_data_create_infos.iter().map(|pipeline_data_create_info| &pipeline_data_create_info._pipeline_data_create_info_name).collect();
+    if let Some(pipeline_data_create_info) = second._pipeline_data_create_infos.iter().find(|pipeline_data_create_info| pipeline_names.contains(&&pipeline_data_create_info._pipeline_data_create_info_name)) {
+        return Err(format!("the pipeline names must be unique in the merged render pass: {}", pipeline_data_create_info._pipeline_data_create_info_name));
+    }
+    Ok(())
+}
+
+// replaces the declared pairs with the merged render passes, see RendererData::get_render_pass_data_create_infos.
+// the merged render pass takes the place and the name of the first, the second name is an alias of it, see Resources::get_render_pass_data.
+pub fn merge_render_pass_data_create_infos(
+    mut render_pass_data_create_infos: Vec<RenderPassDataCreateInfo>,
+    merged_render_pass_infos: &[MergedRenderPassInfo],
+) -> Vec<RenderPassDataCreateInfo> {
+    for merged_render_pass_info in merged_render_pass_infos.iter() {
+        let find_render_pass = |render_pass_name: &str| render_pass_data_create_infos.iter().position(|render_pass_data_create_info| render_pass_data_create_info._render_pass_create_info_name == render_pass_name);
+        let (first_index, second_index) = match (find_render_pass(&merged_render_pass_info._first_render_pass_name), find_render_pass(&merged_render_pass_info._second_render_pass_name)) {
+            (Some(first_index), Some(second_index)) => (first_index, second_index),
+            _ => {
+                log::warn!("merge_render_pass_data_create_infos: not found {:?}", merged_render_pass_info);
+                continue;
+            }
+        };
+        let first = &render_pass_data_create_infos[first_index];
+        let second = &render_pass_data_create_infos[second_index];
+        if let Err(e) = check_merge_render_passes(first, second, &merged_render_pass_info._input_attachment_names) {
+            log::warn!("merge_render_pass_data_create_infos: {} + {} are kept separated: {}", first._render_pass_create_info_name, second._render_pass_create_info_name, e);
+            continue;
+        }
+        let framebuffer_create_info = create_merged_framebuffer_data_create_info(&first._render_pass_framebuffer_create_info, &second._render_pass_framebuffer_create_info);
+        let merged_render_pass_data_create_info = create_merged_render_pass_data_create_info(
+            &first._render_pass_create_info_name,
+            first,
+            second,
+            &merged_render_pass_info._input_attachment_names,
+            framebuffer_create_info,
+        );
+        render_pass_data_create_infos[first_index] = merged_render_pass_data_create_info;
+        render_pass_data_create_infos.remove(second_index);
+    }
+    render_pass_data_create_infos
+}
+
 pub fn destroy_render_pass(device: &Device, render_pass: vk::RenderPass, render_pass_name: &String) {
     log::debug!("destroy_render_pass: {} {:?}", render_pass_name, render_pass);
     unsafe {
@@ -408,26 +917,71 @@ pub fn destroy_pipieline_layout(device: &Device, pipeline_layout: vk::PipelineLa
     }
 }
 
+// the pipelines are authored in the standard depth convention, the compare op is flipped here for reversed-z.
+pub fn get_depth_compare_op(compare_op: vk::CompareOp) -> vk::CompareOp {
+    if false == math::is_reversed_z() {
+        return compare_op;
+    }
+    match compare_op {
+        vk::CompareOp::LESS => vk::CompareOp::GREATER,
+        vk::CompareOp::LESS_OR_EQUAL => vk::CompareOp::GREATER_OR_EQUAL,
+        vk::CompareOp::GREATER => vk::CompareOp::LESS,
+        vk::CompareOp::GREATER_OR_EQUAL => vk::CompareOp::LESS_OR_EQUAL,
+        _ => compare_op,
+    }
+}
+
+// the shaders linearize and compare the depth under these defines like utilities::math, the spirv cache is per convention.
+pub fn get_pipeline_shader_defines(pipeline_data_create_info: &PipelineDataCreateInfo, use_bindless_textures: bool) -> Vec<String> {
+    let mut shader_defines = pipeline_data_create_info._pipeline_shader_defines.clone();
+    if use_bindless_textures {
+        shader_defines.push(String::from("USE_BINDLESS_TEXTURES=1"));
+    }
+    if math::is_reversed_z() {
+        shader_defines.push(String::from("REVERSED_Z=1"));
+    }
+    if math::is_infinite_far_plane() {
+        shader_defines.push(String::from("INFINITE_FAR_PLANE=1"));
+    }
+    shader_defines
+}
+
 pub fn create_graphics_pipeline_data(
     device: &Device,
     render_pass: vk::RenderPass,
     pipeline_data_create_info: &PipelineDataCreateInfo,
     has_depth_stencil_attachment: bool,
-    descriptor_data: &DescriptorData
-) -> PipelineData {
+    has_shading_rate_attachment: bool,
+    descriptor_data: &DescriptorData,
+    bindless_descriptor_set_layout: Option<vk::DescriptorSetLayout>
+) -> Result<PipelineData, String> {
+    // None: the device has no descriptor indexing, the shader samples the textures of the material instance
+    let bindless_descriptor_set_layout = bindless_descriptor_set_layout.filter(|_| pipeline_data_create_info._use_bindless_textures);
+    let use_bindless_textures = bindless_descriptor_set_layout.is_some();
     let vertex_shader_create_info = create_shader_stage_create_info(
         device,
         &pipeline_data_create_info._pipeline_vertex_shader_file,
-        &pipeline_data_create_info._pipeline_shader_defines,
+        &get_pipeline_shader_defines(pipeline_data_create_info, use_bindless_textures),
+        &pipeline_data_create_info._pipeline_shader_include_directories,
         vk::ShaderStageFlags::VERTEX
-    );
-    let fragment_shader_create_info = create_shader_stage_create_info(
+    )?;
+    let fragment_shader_create_info = match create_shader_stage_create_info(
         device,
         &pipeline_data_create_info._pipeline_fragment_shader_file,
-        &pipeline_data_create_info._pipeline_shader_defines,
+        &get_pipeline_shader_defines(pipeline_data_create_info, use_bindless_textures),
+        &pipeline_data_create_info._pipeline_shader_include_directories,
         vk::ShaderStageFlags::FRAGMENT
-    );
-    let descriptor_set_layouts = [ descriptor_data._descriptor_set_layout, ];
+    ) {
+        Ok(fragment_shader_create_info) => fragment_shader_create_info,
+        Err(e) => {
+            destroy_shader_stage_create_info(device, &vertex_shader_create_info);
+            return Err(e);
+        }
+    };
+    let mut descriptor_set_layouts = vec![ descriptor_data._descriptor_set_layout, ];
+    if let Some(bindless_descriptor_set_layout) = bindless_descriptor_set_layout {
+        descriptor_set_layouts.push(bindless_descriptor_set_layout);
+    }
     let shader_stage_infos = vec![vertex_shader_create_info, fragment_shader_create_info];
     let pipeline_layout = create_pipeline_layout(
         device,
@@ -495,7 +1049,7 @@ pub fn create_graphics_pipeline_data(
         vk::PipelineDepthStencilStateCreateInfo {
             depth_test_enable: depth_stencil_state_create_info._depth_test_enable.into(),
             depth_write_enable: depth_stencil_state_create_info._depth_write_enable.into(),
-            depth_compare_op: depth_stencil_state_create_info._depth_compare_op,
+            depth_compare_op: get_depth_compare_op(depth_stencil_state_create_info._depth_compare_op),
             depth_bounds_test_enable: 0,
             min_depth_bounds: 0.0,
             max_depth_bounds: 1.0,
@@ -523,7 +1077,13 @@ pub fn create_graphics_pipeline_data(
     } else {
         vk::PipelineDepthStencilStateCreateInfo::default()
     };
+    let shading_rate_state = PipelineFragmentShadingRateStateCreateInfoKHR::default();
     let grphics_pipeline_create_info = [vk::GraphicsPipelineCreateInfo {
+        p_next: if has_shading_rate_attachment {
+            &shading_rate_state as *const PipelineFragmentShadingRateStateCreateInfoKHR as *const std::os::raw::c_void
+        } else {
+            std::ptr::null()
+        },
         stage_count: shader_stage_infos.len() as u32,
         p_stages: shader_stage_infos.as_ptr(),
         p_vertex_input_state: &vertex_input_state_info,
@@ -536,25 +1096,34 @@ pub fn create_graphics_pipeline_data(
         p_dynamic_state: &dynamic_state,
         layout: pipeline_layout,
         render_pass,
-        subpass: 0,
+        subpass: pipeline_data_create_info._pipeline_subpass_index,
         base_pipeline_handle: vk::Pipeline::null(),
         base_pipeline_index: -1,
         ..Default::default()
     }];
 
     unsafe {
-        let graphics_pipelines = device.create_graphics_pipelines(
+        let graphics_pipelines = match device.create_graphics_pipelines(
             vk::PipelineCache::null(),
             &grphics_pipeline_create_info,
             None
-        ).expect("vkCreateGraphicsPipelines failed!");
+        ) {
+            Ok(graphics_pipelines) => graphics_pipelines,
+            Err((_, e)) => {
+                destroy_shader_stage_create_info(device, &vertex_shader_create_info);
+                destroy_shader_stage_create_info(device, &fragment_shader_create_info);
+                destroy_pipieline_layout(device, pipeline_layout);
+                return Err(format!("vkCreateGraphicsPipelines failed! {}: {:?}", pipeline_data_create_info._pipeline_data_create_info_name, e));
+            }
+        };
 
         log::trace!("    create_graphics_pipeline_data: {} ({:?})", pipeline_data_create_info._pipeline_data_create_info_name, graphics_pipelines);
         log::trace!("    shaderDefines: {:?}", pipeline_data_create_info._pipeline_shader_defines);
         log::trace!("    vertexShader: {:#X} {:?}", vertex_shader_create_info.module.as_raw(), pipeline_data_create_info._pipeline_vertex_shader_file);
         log::trace!("    fragmentShader: {:#X} {:?}", fragment_shader_create_info.module.as_raw(), pipeline_data_create_info._pipeline_fragment_shader_file);
 
-        PipelineData {
+        telemetry::record_created(ResourceClass::Pipeline, 1);
+        Ok(PipelineData {
             _pipeline_data_name: pipeline_data_create_info._pipeline_data_create_info_name.clone(),
             _vertex_shader_create_info: vertex_shader_create_info,
             _fragment_shader_create_info: fragment_shader_create_info,
@@ -562,9 +1131,12 @@ pub fn create_graphics_pipeline_data(
             _pipeline_layout: pipeline_layout,
             _pipeline_bind_point: pipeline_data_create_info._pipeline_bind_point,
             _pipeline_dynamic_states: pipeline_data_create_info._pipeline_dynamic_states.clone(),
+            _pipeline_subpass_index: pipeline_data_create_info._pipeline_subpass_index,
+            _push_constant_layout: PushConstantLayout::create_push_constant_layout(&pipeline_data_create_info._push_constant_ranges),
             _descriptor_data: descriptor_data.clone(),
+            _use_bindless_textures: use_bindless_textures,
             ..Default::default()
-        }
+        })
     }
 }
 
@@ -572,13 +1144,14 @@ pub fn create_compute_pipeline_data(
     device: &Device,
     pipeline_data_create_info: &PipelineDataCreateInfo,
     descriptor_data: &DescriptorData
-) -> PipelineData {
+) -> Result<PipelineData, String> {
     let compute_shader_create_info = create_shader_stage_create_info(
         device,
         &pipeline_data_create_info._pipeline_compute_shader_file,
-        &pipeline_data_create_info._pipeline_shader_defines,
+        &get_pipeline_shader_defines(pipeline_data_create_info, false),
+        &pipeline_data_create_info._pipeline_shader_include_directories,
         vk::ShaderStageFlags::COMPUTE
-    );
+    )?;
     let descriptor_set_layouts = [ descriptor_data._descriptor_set_layout, ];
     let pipeline_layout = create_pipeline_layout(
         device,
@@ -595,31 +1168,48 @@ pub fn create_compute_pipeline_data(
     }];
 
     unsafe {
-        let compute_pipelines = device.create_compute_pipelines(
+        let compute_pipelines = match device.create_compute_pipelines(
             vk::PipelineCache::null(),
             &compute_pipeline_create_info,
             None
-        ).expect("vkCreateComputePipelines failed!");
+        ) {
+            Ok(compute_pipelines) => compute_pipelines,
+            Err((_, e)) => {
+                destroy_shader_stage_create_info(device, &compute_shader_create_info);
+                destroy_pipieline_layout(device, pipeline_layout);
+                return Err(format!("vkCreateComputePipelines failed! {}: {:?}", pipeline_data_create_info._pipeline_data_create_info_name, e));
+            }
+        };
 
         log::trace!("    create_compute_pipeline_data: {} ({:?})", pipeline_data_create_info._pipeline_data_create_info_name, compute_pipelines);
         log::trace!("    shaderDefines: {:?}", pipeline_data_create_info._pipeline_shader_defines);
         log::trace!("    computeShader: {:#X} {:?}", compute_shader_create_info.module.as_raw(), pipeline_data_create_info._pipeline_compute_shader_file);
 
-        PipelineData {
+        telemetry::record_created(ResourceClass::Pipeline, 1);
+        Ok(PipelineData {
             _pipeline_data_name: pipeline_data_create_info._pipeline_data_create_info_name.clone(),
             _compute_shader_create_info: compute_shader_create_info,
             _pipeline: compute_pipelines[0],
             _pipeline_layout: pipeline_layout,
             _pipeline_bind_point: pipeline_data_create_info._pipeline_bind_point,
             _pipeline_dynamic_states: pipeline_data_create_info._pipeline_dynamic_states.clone(),
+            _push_constant_layout: PushConstantLayout::create_push_constant_layout(&pipeline_data_create_info._push_constant_ranges),
             _descriptor_data: descriptor_data.clone(),
             ..Default::default()
-        }
+        })
     }
 }
 
+// "render_pass/pipeline", the pipeline variants are named by their variant key
+pub fn set_pipeline_data_object_name(pipeline_data: &PipelineData, object_name: &str) {
+    object_name::set_object_name(pipeline_data._pipeline, object_name);
+    object_name::set_object_name(pipeline_data._pipeline_layout, object_name);
+}
+
 pub fn destroy_pipeline_data(device: &Device, pipeline_data: &PipelineData) {
     log::debug!("    destroy_pipeline_data: {}, pipeline: {:?}, pipeline_layout: {:?}", pipeline_data._pipeline_data_name,pipeline_data._pipeline, pipeline_data._pipeline_layout);
+    object_name::remove_object_name(pipeline_data._pipeline);
+    object_name::remove_object_name(pipeline_data._pipeline_layout);
     unsafe {
         device.destroy_pipeline(pipeline_data._pipeline, None);
         device.destroy_pipeline_layout(pipeline_data._pipeline_layout, None);
@@ -627,4 +1217,181 @@ pub fn destroy_pipeline_data(device: &Device, pipeline_data: &PipelineData) {
     destroy_shader_stage_create_info(device, &pipeline_data._compute_shader_create_info);
     destroy_shader_stage_create_info(device, &pipeline_data._vertex_shader_create_info);
     destroy_shader_stage_create_info(device, &pipeline_data._fragment_shader_create_info);
-}
\ No newline at end of file
+    telemetry::record_destroyed(ResourceClass::Pipeline, 1);
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ash::vk::Handle;
+
+    fn create_attachment_description(format: vk::Format, load_operation: vk::AttachmentLoadOp) -> ImageAttachmentDescription {
+        ImageAttachmentDescription {
+            _attachment_image_format: format,
+            _attachment_load_operation: load_operation,
+            _attachment_store_operation: vk::AttachmentStoreOp::STORE,
+            ..Default::default()
+        }
+    }
+
+    // the image views are the render targets, ex) 1: SceneAlbedo
+    fn create_framebuffer_create_info(color_views: &[u64], depth_views: &[u64], formats: (&[vk::Format], &[vk::Format])) -> FramebufferDataCreateInfo {
+        let image_views: Vec<vk::ImageView> = color_views.iter().chain(depth_views.iter()).map(|view| vk::ImageView::from_raw(*view)).collect();
+        let clear_values: Vec<vk::ClearValue> = color_views.iter().chain(depth_views.iter()).map(|view| vk::ClearValue {
+            color: vk::ClearColorValue { float32: [*view as f32, 0.0, 0.0, 0.0] }
+        }).collect();
+        FramebufferDataCreateInfo {
+            _framebuffer_color_attachment_formats: formats.0.to_vec(),
+            _framebuffer_depth_attachment_formats: formats.1.to_vec(),
+            _framebuffer_image_views: vec![image_views; constants::SWAPCHAIN_IMAGE_COUNT],
+            _framebuffer_clear_values: clear_values,
+            ..Default::default()
+        }
+    }
+
+    fn create_descriptor(binding_index: u32, descriptor_name: &str, descriptor_resource_type: DescriptorResourceType) -> DescriptorDataCreateInfo {
+        DescriptorDataCreateInfo {
+            _descriptor_binding_index: binding_index,
+            _descriptor_name: String::from(descriptor_name),
+            _descriptor_resource_type: descriptor_resource_type,
+            ..Default::default()
+        }
+    }
+
+    fn create_gbuffer_render_pass() -> RenderPassDataCreateInfo {
+        let color_formats = [vk::Format::R8G8B8A8_UNORM, vk::Format::R16G16B16A16_SFLOAT];
+        let depth_formats = [vk::Format::D32_SFLOAT];
+        RenderPassDataCreateInfo {
+            _render_pass_create_info_name: String::from("render_gbuffer"),
+            _render_pass_framebuffer_create_info: create_framebuffer_create_info(&[1, 2], &[3], (&color_formats, &depth_formats)),
+            _color_attachment_descriptions: color_formats.iter().map(|format| create_attachment_description(*format, vk::AttachmentLoadOp::CLEAR)).collect(),
+            _depth_attachment_descriptions: depth_formats.iter().map(|format| create_attachment_description(*format, vk::AttachmentLoadOp::CLEAR)).collect(),
+            _pipeline_data_create_infos: vec![PipelineDataCreateInfo {
+                _pipeline_data_create_info_name: String::from("render_object"),
+                _descriptor_data_create_infos: vec![create_descriptor(0, "textureBase", DescriptorResourceType::Texture)],
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn create_composite_render_pass() -> RenderPassDataCreateInfo {
+        let color_formats = [vk::Format::R16G16B16A16_SFLOAT];
+        RenderPassDataCreateInfo {
+            _render_pass_create_info_name: String::from("composite_gbuffer"),
+            _render_pass_framebuffer_create_info: create_framebuffer_create_info(&[4], &[], (&color_formats, &[])),
+            _color_attachment_descriptions: color_formats.iter().map(|format| create_attachment_description(*format, vk::AttachmentLoadOp::DONT_CARE)).collect(),
+            _pipeline_data_create_infos: vec![PipelineDataCreateInfo {
+                _pipeline_data_create_info_name: String::from("composite_gbuffer"),
+                _descriptor_data_create_infos: vec![
+                    create_descriptor(0, "SceneAlbedo", DescriptorResourceType::RenderTarget),
+                    create_descriptor(1, "SceneNormal", DescriptorResourceType::RenderTarget),
+                    create_descriptor(2, "SceneDepth", DescriptorResourceType::RenderTarget),
+                    create_descriptor(3, "SceneShadow", DescriptorResourceType::RenderTarget),
+                ],
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn create_merged_render_pass_info() -> MergedRenderPassInfo {
+        MergedRenderPassInfo {
+            _first_render_pass_name: String::from("render_gbuffer"),
+            _second_render_pass_name: String::from("composite_gbuffer"),
+            _input_attachment_names: vec![String::from("SceneAlbedo"), String::from("SceneNormal"), String::from("SceneDepth")],
+        }
+    }
+
+    fn get_clear_value_key(clear_value: &vk::ClearValue) -> f32 {
+        unsafe { clear_value.color.float32[0] }
+    }
+
+    // the merged render pass writes the same render targets with the same operations as the separated render passes
+    #[test]
+    fn test_merged_render_pass_equivalence() {
+        let gbuffer = create_gbuffer_render_pass();
+        let composite = create_composite_render_pass();
+        let other = RenderPassDataCreateInfo { _render_pass_create_info_name: String::from("render_ui"), ..Default::default() };
+        let render_pass_data_create_infos = merge_render_pass_data_create_infos(vec![gbuffer.clone(), composite.clone(), other], &[create_merged_render_pass_info()]);
+        let render_pass_names: Vec<&str> = render_pass_data_create_infos.iter().map(|info| info._render_pass_create_info_name.as_str()).collect();
+        assert_eq!(vec!["render_gbuffer", "render_ui"], render_pass_names);
+        let merged = &render_pass_data_create_infos[0];
+
+        // attachments: the colors of the gbuffer, the colors of the composite, then the depth of the gbuffer
+        let attachment_descriptions = merged.get_attachment_descriptions();
+        let separated_attachment_descriptions: Vec<ImageAttachmentDescription> = gbuffer._color_attachment_descriptions.iter()
+            .chain(composite._color_attachment_descriptions.iter())
+            .chain(gbuffer._depth_attachment_descriptions.iter())
+            .cloned().collect();
+        assert_eq!(separated_attachment_descriptions.len(), attachment_descriptions.len());
+        for (merged_description, separated_description) in attachment_descriptions.iter().zip(separated_attachment_descriptions.iter()) {
+            assert_eq!(separated_description._attachment_image_format, merged_description._attachment_image_format);
+            assert_eq!(separated_description._attachment_load_operation, merged_description._attachment_load_operation);
+            assert_eq!(separated_description._attachment_store_operation, merged_description._attachment_store_operation);
+            assert_eq!(separated_description._attachment_final_layout, merged_description._attachment_final_layout);
+        }
+
+        // every attachment binds the render target of the separated framebuffer with its clear value
+        let framebuffer = &merged._render_pass_framebuffer_create_info;
+        for image_views in framebuffer._framebuffer_image_views.iter() {
+            assert_eq!(vec![1, 2, 4, 3], image_views.iter().map(|image_view| image_view.as_raw()).collect::<Vec<u64>>());
+        }
+        assert_eq!(vec![1.0, 2.0, 4.0, 3.0], framebuffer._framebuffer_clear_values.iter().map(get_clear_value_key).collect::<Vec<f32>>());
+        assert_eq!(4, framebuffer._framebuffer_color_attachment_formats.len() + framebuffer._framebuffer_depth_attachment_formats.len());
+
+        // the subpass 0 writes the render targets of the gbuffer, the subpass 1 writes the render target of the composite and reads the gbuffer
+        let subpasses = merged.get_subpass_data_create_infos();
+        let image_view_of = |attachment_index: &u32| framebuffer._framebuffer_image_views[0][*attachment_index as usize].as_raw();
+        assert_eq!(2, subpasses.len());
+        assert_eq!(vec![1, 2], subpasses[0]._color_attachment_indices.iter().map(image_view_of).collect::<Vec<u64>>());
+        assert_eq!(Some(3), subpasses[0]._depth_attachment_index.as_ref().map(image_view_of));
+        assert_eq!(vec![4], subpasses[1]._color_attachment_indices.iter().map(image_view_of).collect::<Vec<u64>>());
+        assert_eq!(None, subpasses[1]._depth_attachment_index);
+        assert_eq!(vec![1, 2, 3], subpasses[1]._input_attachment_indices.iter().map(image_view_of).collect::<Vec<u64>>());
+        assert!(merged._subpass_dependencies.iter().any(|dependency| 0 == dependency.src_subpass && 1 == dependency.dst_subpass));
+
+        // the same pipelines, the composite reads the gbuffer with subpassLoad and samples the others
+        let pipeline_names: Vec<&str> = merged._pipeline_data_create_infos.iter().map(|info| info._pipeline_data_create_info_name.as_str()).collect();
+        assert_eq!(vec!["render_object", "composite_gbuffer"], pipeline_names);
+        assert_eq!(0, merged._pipeline_data_create_infos[0]._pipeline_subpass_index);
+        assert_eq!(DescriptorResourceType::Texture, merged._pipeline_data_create_infos[0]._descriptor_data_create_infos[0]._descriptor_resource_type);
+        let composite_pipeline = &merged._pipeline_data_create_infos[1];
+        assert_eq!(1, composite_pipeline._pipeline_subpass_index);
+        assert!(composite_pipeline._pipeline_shader_defines.contains(&String::from("USE_SUBPASS_INPUT=1")));
+        let descriptor_types: Vec<DescriptorResourceType> = composite_pipeline._descriptor_data_create_infos.iter().map(|info| info._descriptor_resource_type.clone()).collect();
+        assert_eq!(vec![
+            DescriptorResourceType::InputAttachment,
+            DescriptorResourceType::InputAttachment,
+            DescriptorResourceType::InputAttachment,
+            DescriptorResourceType::RenderTarget,
+        ], descriptor_types);
+        let binding_indices: Vec<u32> = composite_pipeline._descriptor_data_create_infos.iter().map(|info| info._descriptor_binding_index).collect();
+        assert_eq!(vec![0, 1, 2, 3], binding_indices);
+    }
+
+    #[test]
+    fn test_merge_render_passes_kept_separated() {
+        let gbuffer = create_gbuffer_render_pass();
+        let composite = create_composite_render_pass();
+
+        // the second render pass with a depth attachment
+        let mut composite_with_depth = composite.clone();
+        composite_with_depth._depth_attachment_descriptions.push(create_attachment_description(vk::Format::D32_SFLOAT, vk::AttachmentLoadOp::LOAD));
+        assert!(check_merge_render_passes(&gbuffer, &composite_with_depth, &create_merged_render_pass_info()._input_attachment_names).is_err());
+        let render_pass_data_create_infos = merge_render_pass_data_create_infos(vec![gbuffer.clone(), composite_with_depth], &[create_merged_render_pass_info()]);
+        assert_eq!(2, render_pass_data_create_infos.len());
+        assert_eq!(1, render_pass_data_create_infos[0].get_subpass_data_create_infos().len());
+
+        // the different sizes and the unknown render passes
+        let mut small_composite = composite.clone();
+        small_composite._render_pass_framebuffer_create_info._framebuffer_width /= 2;
+        assert!(check_merge_render_passes(&gbuffer, &small_composite, &[]).is_err());
+        let unknown_render_pass_info = MergedRenderPassInfo { _second_render_pass_name: String::from("unknown"), ..create_merged_render_pass_info() };
+        assert_eq!(2, merge_render_pass_data_create_infos(vec![gbuffer.clone(), composite.clone()], &[unknown_render_pass_info]).len());
+
+        // too many input attachments
+        let input_attachment_names: Vec<String> = (0..4).map(|index| format!("Target{}", index)).collect();
+        assert!(check_merge_render_passes(&gbuffer, &composite, &input_attachment_names).is_err());
+        assert!(check_merge_render_passes(&gbuffer, &composite, &create_merged_render_pass_info()._input_attachment_names).is_ok());
+    }
+}
@@ -18,6 +18,8 @@ use crate::utilities::system;
 
 pub const SHADER_CACHE_DIRECTORY: &str = "resource/shader_caches";
 pub const SHADER_DIRECTORY: &str = "resource/shaders";
+pub const SPIRV_MAGIC_NUMBER: u32 = 0x07230203;
+pub const SPIRV_HEADER_SIZE: usize = 20; // magic, version, generator, bound, schema
 
 pub fn spirv_file_path_with_defines(shader_filename: &PathBuf, shader_defines: &[String]) -> PathBuf {
     let ext = shader_filename.extension().unwrap();
@@ -40,7 +42,22 @@ pub fn spirv_file_path_with_defines(shader_filename: &PathBuf, shader_defines: &
 }
 
 
-pub fn compile_glsl(shader_filename: &PathBuf, shader_defines: &[String]) -> Vec<u8> {
+// a truncated spirv, ex) the compiler has crashed while writing on the hot reload, must not reach vkCreateShaderModule.
+pub fn validate_spirv(spirv_file_path: &PathBuf, code_buffer: &[u8]) -> Result<(), String> {
+    if code_buffer.len() < SPIRV_HEADER_SIZE {
+        return Err(format!("{:?}: spirv is too small, {} bytes", spirv_file_path, code_buffer.len()));
+    }
+    if 0 != (code_buffer.len() % 4) {
+        return Err(format!("{:?}: spirv size {} is not aligned to the word size", spirv_file_path, code_buffer.len()));
+    }
+    let magic_number = u32::from_le_bytes([code_buffer[0], code_buffer[1], code_buffer[2], code_buffer[3]]);
+    if SPIRV_MAGIC_NUMBER != magic_number {
+        return Err(format!("{:?}: invalid spirv magic number {:#010X}", spirv_file_path, magic_number));
+    }
+    Ok(())
+}
+
+pub fn compile_glsl(shader_filename: &PathBuf, shader_defines: &[String], shader_include_directories: &[PathBuf]) -> Result<Vec<u8>, String> {
     let mut shader_file_path: PathBuf = PathBuf::from(SHADER_DIRECTORY);
     shader_file_path.push(shader_filename);
 
@@ -61,7 +78,7 @@ pub fn compile_glsl(shader_filename: &PathBuf, shader_defines: &[String]) -> Vec
         fs::create_dir_all(spirv_file_path.parent().unwrap()).expect("Failed to create directories.");
 
         if false == shader_file_path.is_file() {
-            panic!("compileGLSL: {:?} does not exist.", shader_file_path);
+            return Err(format!("compileGLSL: {:?} does not exist.", shader_file_path));
         }
 
         let validator_exe = match which::which("glslangValidator") {
@@ -85,13 +102,16 @@ pub fn compile_glsl(shader_filename: &PathBuf, shader_defines: &[String]) -> Vec
         for shader_define_arg in shader_define_args.iter() {
             command.arg(shader_define_arg);
         }
+        for shader_include_directory in shader_include_directories.iter() {
+            command.arg(format!("-I{}", shader_include_directory.to_str().unwrap()));
+        }
 
         command.current_dir(".");
         match command.output() {
             Ok(output) => {
                 let msg = String::from_utf8(output.stdout).unwrap();
                 if msg.contains("ERROR") {
-                    panic!("Compile error: {}", msg);
+                    return Err(format!("Compile error: {}", msg));
                 }
                 if msg.trim() != shader_file_path.to_str().unwrap() {
                     log::error!("{}", msg);
@@ -104,40 +124,40 @@ pub fn compile_glsl(shader_filename: &PathBuf, shader_defines: &[String]) -> Vec
     // read spirv
     let mut f = system::load(&spirv_file_path);
     let mut buffer: Vec<u8> = Vec::new();
-    f.read_to_end(&mut buffer).unwrap();
-    let content_size = buffer.len() as u32;
-    let remain = content_size % 4;
-    if 0 < remain {
-        for __i in 0..remain {
-            buffer.push(0);
-        }
+    if let Err(e) = f.read_to_end(&mut buffer) {
+        return Err(format!("{:?}: {:?}", spirv_file_path, e));
     }
-    buffer
+    validate_spirv(&spirv_file_path, &buffer)?;
+    Ok(buffer)
 }
 
 pub fn create_shader_stage_create_info(
     device: &Device,
     shader_filename: &PathBuf,
     shader_defines: &[String],
+    shader_include_directories: &[PathBuf],
     stage_flag: vk::ShaderStageFlags
-) -> vk::PipelineShaderStageCreateInfo {
+) -> Result<vk::PipelineShaderStageCreateInfo, String> {
     // ex) shaderDefines = ["STATIC_MESH", "RENDER_SHADOW=true", "SAMPLES=16"]
-    let code_buffer = compile_glsl(shader_filename, shader_defines);
+    let code_buffer = compile_glsl(shader_filename, shader_defines, shader_include_directories)?;
     let shader_module_create_info = vk::ShaderModuleCreateInfo {
         code_size: code_buffer.len(),
         p_code: code_buffer.as_ptr() as *const u32,
         ..Default::default()
     };
     unsafe {
-        let shader_module = device.create_shader_module(&shader_module_create_info, None).expect("vkCreateShaderModule failed!");
+        let shader_module = match device.create_shader_module(&shader_module_create_info, None) {
+            Ok(shader_module) => shader_module,
+            Err(e) => return Err(format!("vkCreateShaderModule failed! {:?}: {:?}", shader_filename, e)),
+        };
         log::trace!("    create_shader_module: {:#X} {:?}: {:?}", shader_module.as_raw(), stage_flag, shader_filename);
         let main: *const c_char = "main\0".as_ptr() as *const c_char;
-        vk::PipelineShaderStageCreateInfo {
+        Ok(vk::PipelineShaderStageCreateInfo {
             stage: stage_flag,
             module: shader_module,
             p_name: main,
             ..Default::default()
-        }
+        })
     }
 }
 
@@ -0,0 +1,227 @@
+use std::ffi::CString;
+use std::os::raw::c_void;
+
+use ash::{ vk, Instance };
+use ash::version::InstanceV1_1;
+
+use crate::vulkan_context::framebuffer::FramebufferDataCreateInfo;
+use crate::vulkan_context::render_pass::ImageAttachmentDescription;
+use crate::vulkan_context::texture::TextureData;
+
+// VK_KHR_fragment_shading_rate is newer than the vulkan headers of ash, so the required types are declared here.
+pub const FRAGMENT_SHADING_RATE_EXTENSION_NAME: &str = "VK_KHR_fragment_shading_rate";
+pub const STRUCTURE_TYPE_FRAGMENT_SHADING_RATE_ATTACHMENT_INFO_KHR: vk::StructureType = vk::StructureType::from_raw(1000226000);
+pub const STRUCTURE_TYPE_PIPELINE_FRAGMENT_SHADING_RATE_STATE_CREATE_INFO_KHR: vk::StructureType = vk::StructureType::from_raw(1000226001);
+pub const STRUCTURE_TYPE_PHYSICAL_DEVICE_FRAGMENT_SHADING_RATE_PROPERTIES_KHR: vk::StructureType = vk::StructureType::from_raw(1000226002);
+pub const STRUCTURE_TYPE_PHYSICAL_DEVICE_FRAGMENT_SHADING_RATE_FEATURES_KHR: vk::StructureType = vk::StructureType::from_raw(1000226003);
+pub const IMAGE_USAGE_FRAGMENT_SHADING_RATE_ATTACHMENT_KHR: vk::ImageUsageFlags = vk::ImageUsageFlags::from_raw(0x0000_0100);
+pub const IMAGE_LAYOUT_FRAGMENT_SHADING_RATE_ATTACHMENT_OPTIMAL_KHR: vk::ImageLayout = vk::ImageLayout::from_raw(1000164003);
+pub const SHADING_RATE_IMAGE_FORMAT: vk::Format = vk::Format::R8_UINT;
+
+// VkFragmentShadingRateCombinerOpKHR
+pub const FRAGMENT_SHADING_RATE_COMBINER_OP_KEEP: u32 = 0;
+pub const FRAGMENT_SHADING_RATE_COMBINER_OP_REPLACE: u32 = 1;
+
+// encoded fragment size of shading rate image: (log2(width) << 2) | log2(height)
+pub const SHADING_RATE_1X1: u8 = 0;
+pub const SHADING_RATE_2X2: u8 = (1 << 2) | 1;
+pub const SHADING_RATE_4X4: u8 = (2 << 2) | 2;
+
+#[derive(Clone, Debug, Copy, PartialEq, Eq)]
+pub enum ShadingRateQuality {
+    Off,
+    Conservative,
+    Aggressive,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct PhysicalDeviceFragmentShadingRateFeaturesKHR {
+    pub s_type: vk::StructureType,
+    pub p_next: *mut c_void,
+    pub pipeline_fragment_shading_rate: vk::Bool32,
+    pub primitive_fragment_shading_rate: vk::Bool32,
+    pub attachment_fragment_shading_rate: vk::Bool32,
+}
+
+impl Default for PhysicalDeviceFragmentShadingRateFeaturesKHR {
+    fn default() -> PhysicalDeviceFragmentShadingRateFeaturesKHR {
+        PhysicalDeviceFragmentShadingRateFeaturesKHR {
+            s_type: STRUCTURE_TYPE_PHYSICAL_DEVICE_FRAGMENT_SHADING_RATE_FEATURES_KHR,
+            p_next: std::ptr::null_mut(),
+            pipeline_fragment_shading_rate: vk::FALSE,
+            primitive_fragment_shading_rate: vk::FALSE,
+            attachment_fragment_shading_rate: vk::FALSE,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct PhysicalDeviceFragmentShadingRatePropertiesKHR {
+    pub s_type: vk::StructureType,
+    pub p_next: *mut c_void,
+    pub min_fragment_shading_rate_attachment_texel_size: vk::Extent2D,
+    pub max_fragment_shading_rate_attachment_texel_size: vk::Extent2D,
+    pub max_fragment_shading_rate_attachment_texel_size_aspect_ratio: u32,
+    pub primitive_fragment_shading_rate_with_multiple_viewports: vk::Bool32,
+    pub layered_shading_rate_attachments: vk::Bool32,
+    pub fragment_shading_rate_non_trivial_combiner_ops: vk::Bool32,
+    pub max_fragment_size: vk::Extent2D,
+    pub max_fragment_size_aspect_ratio: u32,
+    pub max_fragment_shading_rate_coverage_samples: u32,
+    pub max_fragment_shading_rate_rasterization_samples: vk::SampleCountFlags,
+    pub fragment_shading_rate_with_shader_depth_stencil_writes: vk::Bool32,
+    pub fragment_shading_rate_with_sample_mask: vk::Bool32,
+    pub fragment_shading_rate_with_shader_sample_mask: vk::Bool32,
+    pub fragment_shading_rate_with_conservative_rasterization: vk::Bool32,
+    pub fragment_shading_rate_with_fragment_shader_interlock: vk::Bool32,
+    pub fragment_shading_rate_with_custom_sample_locations: vk::Bool32,
+    pub fragment_shading_rate_strict_multiply_combiner: vk::Bool32,
+}
+
+impl Default for PhysicalDeviceFragmentShadingRatePropertiesKHR {
+    fn default() -> PhysicalDeviceFragmentShadingRatePropertiesKHR {
+        unsafe {
+            PhysicalDeviceFragmentShadingRatePropertiesKHR {
+                s_type: STRUCTURE_TYPE_PHYSICAL_DEVICE_FRAGMENT_SHADING_RATE_PROPERTIES_KHR,
+                p_next: std::ptr::null_mut(),
+                ..std::mem::zeroed()
+            }
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct FragmentShadingRateAttachmentInfoKHR {
+    pub s_type: vk::StructureType,
+    pub p_next: *const c_void,
+    pub p_fragment_shading_rate_attachment: *const vk::AttachmentReference2,
+    pub shading_rate_attachment_texel_size: vk::Extent2D,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct PipelineFragmentShadingRateStateCreateInfoKHR {
+    pub s_type: vk::StructureType,
+    pub p_next: *const c_void,
+    pub fragment_size: vk::Extent2D,
+    pub combiner_ops: [u32; 2],
+}
+
+impl Default for PipelineFragmentShadingRateStateCreateInfoKHR {
+    fn default() -> PipelineFragmentShadingRateStateCreateInfoKHR {
+        // pipeline rate is 1x1, primitive rate is ignored and the attachment rate replaces the result.
+        PipelineFragmentShadingRateStateCreateInfoKHR {
+            s_type: STRUCTURE_TYPE_PIPELINE_FRAGMENT_SHADING_RATE_STATE_CREATE_INFO_KHR,
+            p_next: std::ptr::null(),
+            fragment_size: vk::Extent2D { width: 1, height: 1 },
+            combiner_ops: [FRAGMENT_SHADING_RATE_COMBINER_OP_KEEP, FRAGMENT_SHADING_RATE_COMBINER_OP_REPLACE],
+        }
+    }
+}
+
+// must match with generate_shading_rate.glsl
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Default)]
+#[repr(C)]
+pub struct PushConstant_GenerateShadingRate {
+    pub _foveation_radius: f32, // full rate inside of the radius in normalized screen space
+    pub _luminance_threshold: f32, // reduce rate below the luminance
+    pub _contrast_threshold: f32, // full rate above the local contrast
+    pub _velocity_threshold: f32, // full rate above the screen space velocity
+    pub _max_shading_rate: u32,
+    pub _reserved0: u32,
+    pub _reserved1: u32,
+    pub _reserved2: u32,
+}
+
+crate::impl_push_constant!(PushConstant_GenerateShadingRate, 32);
+
+impl PushConstant_GenerateShadingRate {
+    pub fn create_push_constant(shading_rate_quality: ShadingRateQuality) -> PushConstant_GenerateShadingRate {
+        match shading_rate_quality {
+            ShadingRateQuality::Off => PushConstant_GenerateShadingRate {
+                _foveation_radius: 2.0,
+                _max_shading_rate: SHADING_RATE_1X1 as u32,
+                ..Default::default()
+            },
+            ShadingRateQuality::Conservative => PushConstant_GenerateShadingRate {
+                _foveation_radius: 0.6,
+                _luminance_threshold: 0.05,
+                _contrast_threshold: 0.1,
+                _velocity_threshold: 0.002,
+                _max_shading_rate: SHADING_RATE_2X2 as u32,
+                ..Default::default()
+            },
+            ShadingRateQuality::Aggressive => PushConstant_GenerateShadingRate {
+                _foveation_radius: 0.35,
+                _luminance_threshold: 0.15,
+                _contrast_threshold: 0.25,
+                _velocity_threshold: 0.005,
+                _max_shading_rate: SHADING_RATE_4X4 as u32,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+// returns (is_supported, texel_size of shading rate attachment)
+pub fn query_fragment_shading_rate_support(
+    instance: &Instance,
+    physical_device: vk::PhysicalDevice,
+    available_device_extensions: &Vec<CString>
+) -> (bool, vk::Extent2D) {
+    let extension_name = CString::new(FRAGMENT_SHADING_RATE_EXTENSION_NAME).unwrap();
+    if false == available_device_extensions.contains(&extension_name) {
+        return (false, vk::Extent2D::default());
+    }
+
+    let mut shading_rate_features = PhysicalDeviceFragmentShadingRateFeaturesKHR::default();
+    let mut physical_device_features2 = vk::PhysicalDeviceFeatures2 {
+        p_next: &mut shading_rate_features as *mut PhysicalDeviceFragmentShadingRateFeaturesKHR as *mut c_void,
+        ..Default::default()
+    };
+    let mut shading_rate_properties = PhysicalDeviceFragmentShadingRatePropertiesKHR::default();
+    let mut physical_device_properties2 = vk::PhysicalDeviceProperties2 {
+        p_next: &mut shading_rate_properties as *mut PhysicalDeviceFragmentShadingRatePropertiesKHR as *mut c_void,
+        ..Default::default()
+    };
+    unsafe {
+        instance.get_physical_device_features2(physical_device, &mut physical_device_features2);
+        instance.get_physical_device_properties2(physical_device, &mut physical_device_properties2);
+    }
+
+    let is_supported = vk::TRUE == shading_rate_features.pipeline_fragment_shading_rate && vk::TRUE == shading_rate_features.attachment_fragment_shading_rate;
+    let texel_size = shading_rate_properties.max_fragment_shading_rate_attachment_texel_size.width.min(16).max(
+        shading_rate_properties.min_fragment_shading_rate_attachment_texel_size.width
+    );
+    log::info!("FragmentShadingRate: supported({}), texel_size({})", is_supported, texel_size);
+    (is_supported, vk::Extent2D { width: texel_size, height: texel_size })
+}
+
+pub fn get_shading_rate_image_size(width: u32, height: u32, texel_size: &vk::Extent2D) -> (u32, u32) {
+    let texel_width = texel_size.width.max(1);
+    let texel_height = texel_size.height.max(1);
+    ((width + texel_width - 1) / texel_width, (height + texel_height - 1) / texel_height)
+}
+
+pub fn get_shading_rate_attachment_description() -> ImageAttachmentDescription {
+    ImageAttachmentDescription {
+        _attachment_image_format: SHADING_RATE_IMAGE_FORMAT,
+        _attachment_load_operation: vk::AttachmentLoadOp::LOAD,
+        _attachment_store_operation: vk::AttachmentStoreOp::DONT_CARE,
+        _attachment_initial_layout: IMAGE_LAYOUT_FRAGMENT_SHADING_RATE_ATTACHMENT_OPTIMAL_KHR,
+        _attachment_final_layout: IMAGE_LAYOUT_FRAGMENT_SHADING_RATE_ATTACHMENT_OPTIMAL_KHR,
+        _attachment_reference_layout: IMAGE_LAYOUT_FRAGMENT_SHADING_RATE_ATTACHMENT_OPTIMAL_KHR,
+        ..Default::default()
+    }
+}
+
+// the shading rate attachment is always the last attachment of the framebuffer.
+pub fn append_shading_rate_attachment(framebuffer_data_create_info: &mut FramebufferDataCreateInfo, shading_rate_texture: &TextureData) {
+    for image_views in framebuffer_data_create_info._framebuffer_image_views.iter_mut() {
+        image_views.push(shading_rate_texture.get_default_image_view());
+    }
+}
@@ -10,9 +10,12 @@ use ash::extensions::khr::{
 };
 
 use crate::constants;
+use crate::vulkan_context::object_name;
 use crate::vulkan_context::queue;
 use crate::vulkan_context::texture;
-use crate::vulkan_context::vulkan_context::{ SwapchainArray };
+use crate::vulkan_context::vulkan_context::{ self, SwapchainArray };
+
+pub const MAILBOX_MIN_IMAGE_COUNT: u32 = 3;
 
 #[derive(Debug)]
 pub struct SwapchainSupportDetails {
@@ -21,13 +24,50 @@ pub struct SwapchainSupportDetails {
     pub _present_modes: Vec<vk::PresentModeKHR>
 }
 
+// the requested present mode of the renderer, the swapchain falls back to a supported mode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PresentMode {
+    Fifo, // v-sync
+    Mailbox,
+    Immediate,
+}
+
+impl PresentMode {
+    pub fn get_next_present_mode(&self) -> PresentMode {
+        match self {
+            PresentMode::Fifo => PresentMode::Mailbox,
+            PresentMode::Mailbox => PresentMode::Immediate,
+            PresentMode::Immediate => PresentMode::Fifo,
+        }
+    }
+
+    pub fn to_vk_present_mode(&self) -> vk::PresentModeKHR {
+        match self {
+            PresentMode::Fifo => vk::PresentModeKHR::FIFO,
+            PresentMode::Mailbox => vk::PresentModeKHR::MAILBOX,
+            PresentMode::Immediate => vk::PresentModeKHR::IMMEDIATE,
+        }
+    }
+
+    pub fn from_vk_present_mode(present_mode: vk::PresentModeKHR) -> PresentMode {
+        match present_mode {
+            vk::PresentModeKHR::MAILBOX => PresentMode::Mailbox,
+            vk::PresentModeKHR::IMMEDIATE => PresentMode::Immediate,
+            _ => PresentMode::Fifo,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct SwapchainData {
     pub _swapchain: vk::SwapchainKHR,
     pub _swapchain_image_format: vk::Format,
+    pub _swapchain_color_space: vk::ColorSpaceKHR, // HDR10_ST2084_EXT or EXTENDED_SRGB_LINEAR_EXT: the hdr output
     pub _swapchain_images: SwapchainArray<vk::Image>,
     pub _swapchain_image_views: SwapchainArray<vk::ImageView>,
-    pub _swapchain_extent: vk::Extent2D
+    pub _swapchain_extent: vk::Extent2D,
+    pub _swapchain_image_usage: vk::ImageUsageFlags,
+    pub _swapchain_present_mode: vk::PresentModeKHR, // the active mode, may differ from the requested mode
 }
 
 impl SwapchainData {
@@ -54,15 +94,31 @@ pub fn choose_swapchain_surface_format(
     surface_format
 }
 
-pub fn choose_swapchain_present_mode(swapchain_support_details: &SwapchainSupportDetails) -> vk::PresentModeKHR {
-    if swapchain_support_details._present_modes.contains(&vk::PresentModeKHR::FIFO) {
-        return vk::PresentModeKHR::FIFO;
-    } else if swapchain_support_details._present_modes.contains(&vk::PresentModeKHR::FIFO_RELAXED) {
-        return vk::PresentModeKHR::FIFO_RELAXED;
-    } else if swapchain_support_details._present_modes.contains(&vk::PresentModeKHR::MAILBOX) {
-        return vk::PresentModeKHR::MAILBOX;
-    } else if swapchain_support_details._present_modes.contains(&vk::PresentModeKHR::IMMEDIATE) {
-        return vk::PresentModeKHR::IMMEDIATE;
+pub fn is_hdr_color_space(color_space: vk::ColorSpaceKHR) -> bool {
+    constants::HDR_SURFACE_FORMATS.iter().any(|hdr_surface_format| hdr_surface_format.color_space == color_space)
+}
+
+// the surface reports the hdr formats only with VK_EXT_swapchain_colorspace, see device::create_vk_instance
+pub fn find_hdr_surface_format(swapchain_support_details: &SwapchainSupportDetails) -> Option<vk::SurfaceFormatKHR> {
+    constants::HDR_SURFACE_FORMATS.iter().find(|hdr_surface_format| {
+        swapchain_support_details._formats.iter().any(|format| format.format == hdr_surface_format.format && format.color_space == hdr_surface_format.color_space)
+    }).cloned()
+}
+
+// the requested mode first, then MAILBOX -> IMMEDIATE -> FIFO. FIFO is always supported.
+pub fn choose_swapchain_present_mode(swapchain_support_details: &SwapchainSupportDetails, present_mode: PresentMode) -> vk::PresentModeKHR {
+    let requested_present_mode = present_mode.to_vk_present_mode();
+    if swapchain_support_details._present_modes.contains(&requested_present_mode) {
+        return requested_present_mode;
+    }
+    for fallback_present_mode in [vk::PresentModeKHR::MAILBOX, vk::PresentModeKHR::IMMEDIATE].iter() {
+        if swapchain_support_details._present_modes.contains(fallback_present_mode) {
+            log::warn!("choose_swapchain_present_mode: {:?} is not supported, fallback to {:?}", requested_present_mode, fallback_present_mode);
+            return *fallback_present_mode;
+        }
+    }
+    if vk::PresentModeKHR::FIFO != requested_present_mode {
+        log::warn!("choose_swapchain_present_mode: {:?} is not supported, fallback to {:?}", requested_present_mode, vk::PresentModeKHR::FIFO);
     }
     vk::PresentModeKHR::FIFO
 }
@@ -102,43 +158,52 @@ pub fn create_swapchain_data(
     surface: vk::SurfaceKHR,
     swapchain_support_details: &SwapchainSupportDetails,
     queue_family_datas: &queue::QueueFamilyDatas,
-    immediate_mode: bool
+    requested_present_mode: PresentMode,
+    is_hdr_output_requested: bool,
 ) -> SwapchainData
 {
-    let surface_format = choose_swapchain_surface_format(swapchain_support_details, &constants::SWAPCHAIN_SURFACE_FORMATS);
-    #[cfg(target_os = "android")]
-        let present_mode = if immediate_mode {
-        vk::PresentModeKHR::IMMEDIATE
-    } else {
-        vk::PresentModeKHR::FIFO
-    };
-    #[cfg(not(target_os = "android"))]
-    let present_mode = if immediate_mode {
-        vk::PresentModeKHR::IMMEDIATE
-    } else {
-        choose_swapchain_present_mode(swapchain_support_details)
-    };
+    let hdr_surface_format = if is_hdr_output_requested { find_hdr_surface_format(swapchain_support_details) } else { None };
+    if is_hdr_output_requested && hdr_surface_format.is_none() {
+        log::warn!("create_swapchain_data: the surface has no hdr format, fallback to sdr.");
+    }
+    let surface_format = hdr_surface_format.unwrap_or_else(|| choose_swapchain_surface_format(swapchain_support_details, &constants::SWAPCHAIN_SURFACE_FORMATS));
+    let present_mode = choose_swapchain_present_mode(swapchain_support_details, requested_present_mode);
     let image_extent = choose_swapchain_extent(swapchain_support_details);
     let max_image_count = swapchain_support_details._capabilities.max_image_count;
     let min_image_count = swapchain_support_details._capabilities.min_image_count;
+    // MAILBOX needs an image to present, an image to queue and an image to render.
+    let required_image_count = if vk::PresentModeKHR::MAILBOX == present_mode {
+        max(MAILBOX_MIN_IMAGE_COUNT, constants::SWAPCHAIN_IMAGE_COUNT as u32)
+    } else {
+        constants::SWAPCHAIN_IMAGE_COUNT as u32
+    };
     let image_count = if max_image_count <= 0 {
-        max(min_image_count, constants::SWAPCHAIN_IMAGE_COUNT as u32)
+        max(min_image_count, required_image_count)
     } else {
-        min(max_image_count, max(min_image_count, constants::SWAPCHAIN_IMAGE_COUNT as u32))
+        min(max_image_count, max(min_image_count, required_image_count))
     };
+    if constants::SWAPCHAIN_IMAGE_COUNT < image_count as usize {
+        log::warn!("create_swapchain_data: image_count {} is over SWAPCHAIN_IMAGE_COUNT {}", image_count, constants::SWAPCHAIN_IMAGE_COUNT);
+    }
     let pre_transform = if swapchain_support_details._capabilities.supported_transforms.contains(vk::SurfaceTransformFlagsKHR::IDENTITY) {
         vk::SurfaceTransformFlagsKHR::IDENTITY
     } else {
         swapchain_support_details._capabilities.current_transform
     };
 
+    // TRANSFER_DST: clear of the render error recovery, TRANSFER_SRC: the screenshot, optional on some surfaces.
+    let mut image_usage = vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_DST;
+    if swapchain_support_details._capabilities.supported_usage_flags.contains(vk::ImageUsageFlags::TRANSFER_SRC) {
+        image_usage |= vk::ImageUsageFlags::TRANSFER_SRC;
+    }
+
     let mut swapchain_create_info = vk::SwapchainCreateInfoKHR {
         surface,
         min_image_count: image_count,
         image_color_space: surface_format.color_space,
         image_format: surface_format.format,
         image_extent,
-        image_usage: vk::ImageUsageFlags::COLOR_ATTACHMENT,
+        image_usage,
         image_array_layers: 1,
         pre_transform,
         composite_alpha: vk::CompositeAlphaFlagsKHR::OPAQUE,
@@ -158,27 +223,37 @@ pub fn create_swapchain_data(
         let swapchain = swapchain_interface.create_swapchain(&swapchain_create_info, None).expect("vkCreateSwapchainKHR failed!");
         let swapchain_images: SwapchainArray<vk::Image> = swapchain_interface.get_swapchain_images(swapchain).expect("vkGetSwapchainImagesKHR error!");
         let swapchain_image_views = create_swapchain_image_views(&device, &swapchain_images, swapchain_create_info.image_format);
+        object_name::set_object_name(swapchain, "swapchain");
+        object_name::set_object_names(&swapchain_images, "swapchain");
+        object_name::set_object_names(&swapchain_image_views, "swapchain");
 
         log::info!("create_swapchain_data : {:?}", swapchain);
         log::info!("    present_mode : {:?}", present_mode);
         log::info!("    image_count : {} {:?}", image_count, swapchain_images);
-        log::info!("    image_format : {:?}", surface_format.format);
+        log::info!("    image_format : {:?} is_srgb: {}", surface_format.format, vulkan_context::is_srgb_format(surface_format.format));
         log::info!("    color_space : {:?}", surface_format.color_space);
         log::info!("    image_views : {:?}", swapchain_image_views);
         log::info!("    image_extent : {:?}", image_extent);
+        log::info!("    image_usage : {:?}", image_usage);
         log::info!("    image_sharing_mode : {:?}", swapchain_create_info.image_sharing_mode);
 
         SwapchainData {
             _swapchain: swapchain,
             _swapchain_images: swapchain_images,
             _swapchain_image_format: surface_format.format,
+            _swapchain_color_space: surface_format.color_space,
             _swapchain_image_views: swapchain_image_views,
-            _swapchain_extent: image_extent
+            _swapchain_extent: image_extent,
+            _swapchain_image_usage: image_usage,
+            _swapchain_present_mode: present_mode,
         }
     }
 }
 
 pub fn destroy_swapchain_data(device: &Device, swapchain_interface: &Swapchain, swapchain_data: &SwapchainData) {
+    object_name::remove_object_name(swapchain_data._swapchain);
+    object_name::remove_object_names(&swapchain_data._swapchain_images);
+    object_name::remove_object_names(&swapchain_data._swapchain_image_views);
     destroy_swapchain_image_views(device, &swapchain_data._swapchain_image_views);
     log::info!("destroy_swapchain_data");
     unsafe {
@@ -14,8 +14,12 @@ use ash::version::{
 };
 
 use crate::constants;
-use crate::vulkan_context::buffer;
-use crate::vulkan_context::vulkan_context::{ run_commands_once, Layers, MipLevels };
+use crate::utilities::telemetry::{ self, ResourceClass };
+use crate::vulkan_context::bindless_texture;
+use crate::vulkan_context::buffer::{ self, StagingBufferPool };
+use crate::vulkan_context::device_memory_allocator::{ self, MemoryAllocation, MemoryAllocationKind };
+use crate::vulkan_context::object_name;
+use crate::vulkan_context::vulkan_context::{ self, run_commands_once, Layers, MipLevels };
 
 #[derive(Debug, Clone)]
 pub struct TextureCreateInfo<T> {
@@ -32,7 +36,10 @@ pub struct TextureCreateInfo<T> {
     pub _max_mip_levels: u32,
     pub _enable_mipmap: bool,
     pub _enable_anisotropy: bool,
-    pub _texture_initial_datas: Vec<T>
+    pub _texture_additional_usage: vk::ImageUsageFlags, // ex) shading_rate::IMAGE_USAGE_FRAGMENT_SHADING_RATE_ATTACHMENT_KHR
+    pub _texture_mip_drop: u32, // count of the top mip levels to be dropped, see compute_mip_drop
+    pub _texture_initial_mip_levels: u32, // count of the pre-encoded mip levels in the initial datas, 0: the mip chain is generated from mip 0
+    pub _texture_initial_datas: Vec<T> // mip-major, all layers of mip 0 and then mip 1..
 }
 
 #[derive(Debug, Clone)]
@@ -46,13 +53,16 @@ pub struct TextureData {
     pub _sub_image_views: Layers<MipLevels<vk::ImageView>>,
     pub _sub_image_infos: Layers<MipLevels<vk::DescriptorImageInfo>>,
     pub _sub_image_view_type: vk::ImageViewType,
-    pub _image_memory: vk::DeviceMemory,
+    pub _image_memory: MemoryAllocation,
     pub _image_format: vk::Format,
     pub _image_width: u32,
     pub _image_height: u32,
     pub _image_layers: u32,
     pub _image_mip_levels: u32,
     pub _image_sample_count: vk::SampleCountFlags,
+    pub _image_mip_drop: u32,
+    pub _image_memory_saved: vk::DeviceSize, // device memory saved by the mip drop
+    pub _bindless_texture_index: u32, // the slot of the bindless texture array, see Resources::update_bindless_textures
 }
 
 impl Default for TextureData {
@@ -67,13 +77,16 @@ impl Default for TextureData {
             _sub_image_views: Layers::new(),
             _sub_image_infos: Layers::new(),
             _sub_image_view_type: vk::ImageViewType::default(),
-            _image_memory: vk::DeviceMemory::null(),
+            _image_memory: MemoryAllocation::default(),
             _image_format: vk::Format::default(),
             _image_width: 0,
             _image_height: 0,
             _image_layers: 0,
             _image_mip_levels: 0,
             _image_sample_count: vk::SampleCountFlags::default(),
+            _image_mip_drop: 0,
+            _image_memory_saved: 0,
+            _bindless_texture_index: bindless_texture::INVALID_BINDLESS_TEXTURE_INDEX,
         }
     }
 }
@@ -92,6 +105,7 @@ pub enum ImageLayoutTransition {
     TransferUndefToTransferDst,
     TransferDstToShaderReadOnly,
     TransferDstToTransferSrc,
+    ShaderReadOnlyToTransferDst,
     TransferUndefToDepthStencilAttachemnt,
     TransferUndefToColorAttachemnt,
 }
@@ -122,6 +136,9 @@ impl<T> Default for TextureCreateInfo<T> {
             _max_mip_levels: constants::WHOLE_MIP_LEVELS,
             _enable_mipmap: false,
             _enable_anisotropy: false,
+            _texture_additional_usage: vk::ImageUsageFlags::empty(),
+            _texture_mip_drop: 0,
+            _texture_initial_mip_levels: 0,
             _texture_initial_datas: Vec::new(),
         }
     }
@@ -213,6 +230,15 @@ pub fn get_transition_dependent(image_layout_transition: ImageLayoutTransition)
             _src_stage_mask: vk::PipelineStageFlags::TRANSFER,
             _dst_stage_mask: vk::PipelineStageFlags::TRANSFER,
         },
+        // the reads of the previous submissions on the queue, the compute passes included. ex) update_texture_data
+        ImageLayoutTransition::ShaderReadOnlyToTransferDst => TransitionDependent {
+            _old_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            _new_layout: vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            _src_access_mask: vk::AccessFlags::SHADER_READ,
+            _dst_access_mask: vk::AccessFlags::TRANSFER_WRITE,
+            _src_stage_mask: vk::PipelineStageFlags::ALL_COMMANDS,
+            _dst_stage_mask: vk::PipelineStageFlags::TRANSFER,
+        },
         ImageLayoutTransition::TransferUndefToDepthStencilAttachemnt => TransitionDependent {
             _old_layout: vk::ImageLayout::UNDEFINED,
             _new_layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
@@ -257,6 +283,76 @@ pub fn calc_mip_levels(image_width: u32, image_height: u32, image_depth: u32, ma
     mip_levels
 }
 
+// (block dimension, bytes per block), the uncompressed formats are (1, 0)
+pub fn get_compressed_block_info(format: vk::Format) -> (u32, u32) {
+    match format {
+        vk::Format::BC1_RGB_UNORM_BLOCK | vk::Format::BC1_RGB_SRGB_BLOCK | vk::Format::BC1_RGBA_UNORM_BLOCK | vk::Format::BC1_RGBA_SRGB_BLOCK => (4, 8),
+        vk::Format::BC3_UNORM_BLOCK | vk::Format::BC3_SRGB_BLOCK | vk::Format::BC7_UNORM_BLOCK | vk::Format::BC7_SRGB_BLOCK => (4, 16),
+        _ => (1, 0),
+    }
+}
+
+pub fn is_block_compressed_format(format: vk::Format) -> bool {
+    0 < get_compressed_block_info(format).1
+}
+
+// bytes of a mip level of the block compressed format, all layers and depth slices
+pub fn get_compressed_mip_level_size(format: vk::Format, width: u32, height: u32, depth: u32, layer_count: u32) -> usize {
+    let (block_dimension, block_bytes) = get_compressed_block_info(format);
+    let block_count_x = max(1, (width + block_dimension - 1) / block_dimension);
+    let block_count_y = max(1, (height + block_dimension - 1) / block_dimension);
+    (block_count_x * block_count_y * block_bytes) as usize * max(1, depth) as usize * max(1, layer_count) as usize
+}
+
+pub fn is_format_sampleable(instance: &Instance, physical_device: vk::PhysicalDevice, format: vk::Format) -> bool {
+    let format_properties = unsafe { instance.get_physical_device_format_properties(physical_device, format) };
+    format_properties.optimal_tiling_features.contains(vk::FormatFeatureFlags::SAMPLED_IMAGE | vk::FormatFeatureFlags::TRANSFER_DST)
+}
+
+#[derive(Clone, Debug, Copy, PartialEq, Eq)]
+pub struct MipDropInfo {
+    pub _mip_drop: u32,
+    pub _width: u32,
+    pub _height: u32,
+    pub _mip_levels: u32,
+}
+
+// The effective size and mip levels after dropping the top mip levels, mip N of the source becomes the base level.
+// Textures without mipmap and 3d textures(luts) are never dropped, at least one mip level remains.
+pub fn compute_mip_drop(image_width: u32, image_height: u32, image_depth: u32, mip_levels: u32, enable_mipmap: bool, mip_drop: u32) -> MipDropInfo {
+    let mip_drop = if enable_mipmap && 1 == image_depth {
+        min(min(mip_drop, constants::MAX_TEXTURE_MIP_DROP), mip_levels.max(1) - 1)
+    } else {
+        0
+    };
+    MipDropInfo {
+        _mip_drop: mip_drop,
+        _width: max(1, image_width >> mip_drop),
+        _height: max(1, image_height >> mip_drop),
+        _mip_levels: max(1, mip_levels) - mip_drop,
+    }
+}
+
+pub fn get_device_local_memory_size(memory_properties: &vk::PhysicalDeviceMemoryProperties) -> vk::DeviceSize {
+    memory_properties.memory_heaps[..memory_properties.memory_heap_count as usize].iter()
+        .filter(|memory_heap| memory_heap.flags.contains(vk::MemoryHeapFlags::DEVICE_LOCAL))
+        .map(|memory_heap| memory_heap.size)
+        .max()
+        .unwrap_or(0)
+}
+
+// the mip drop chosen by the size of the device local memory, when TEXTURE_MIP_DROP is auto.
+pub fn get_auto_texture_mip_drop(device_local_memory_size: vk::DeviceSize) -> u32 {
+    const MEGA_BYTES: vk::DeviceSize = 1024 * 1024;
+    if device_local_memory_size < 1024 * MEGA_BYTES {
+        2
+    } else if device_local_memory_size < 2048 * MEGA_BYTES {
+        1
+    } else {
+        0
+    }
+}
+
 pub fn get_image_aspect_by_format(image_format: vk::Format) -> vk::ImageAspectFlags {
     match constants::DEPTH_FOMATS.contains(&image_format) {
         true => match constants::DEPTH_STENCIL_FORMATS.contains(&image_format) {
@@ -485,27 +581,56 @@ pub fn find_supported_format(
     tiling: vk::ImageTiling,
     features: vk::FormatFeatureFlags,
 ) -> vk::Format {
-    unsafe {
-        let candidates: Vec<vk::Format> = constants::DEPTH_FOMATS.iter().filter(|__format| {
-            let format_properties = instance.get_physical_device_format_properties(physical_device, require_format);
-            match tiling {
-                vk::ImageTiling::LINEAR => (format_properties.linear_tiling_features & features) == features,
-                vk::ImageTiling::OPTIMAL => (format_properties.optimal_tiling_features & features) == features,
-                _ => false
-            }
-        }).map(|format| *format).collect();
+    let candidates: Vec<vk::Format> = constants::DEPTH_FOMATS.iter().filter(|format| {
+        is_format_feature_supported(instance, physical_device, **format, tiling, features)
+    }).map(|format| *format).collect();
 
+    if candidates.contains(&require_format) {
+        return require_format;
+    }
+    // a stencil format falls back to another stencil format first, then to the depth only format.
+    if constants::DEPTH_STENCIL_FORMATS.contains(&require_format) {
+        if let Some(format) = candidates.iter().find(|format| constants::DEPTH_STENCIL_FORMATS.contains(format)) {
+            log::warn!("find_supported_format: {:?} is not supported, fallback to {:?}", require_format, format);
+            return *format;
+        }
         if 0 < candidates.len() {
-            return if candidates.contains(&require_format) {
-                require_format
-            } else {
-                candidates[0]
-            }
+            log::warn!("find_supported_format: no stencil format, fallback to {:?}. the stencil is disabled.", candidates[0]);
         }
     }
+    if 0 < candidates.len() {
+        return candidates[0];
+    }
     panic!("failed to find supported format");
 }
 
+pub fn is_format_feature_supported(
+    instance: &Instance,
+    physical_device: vk::PhysicalDevice,
+    format: vk::Format,
+    tiling: vk::ImageTiling,
+    features: vk::FormatFeatureFlags,
+) -> bool {
+    let format_properties = unsafe { instance.get_physical_device_format_properties(physical_device, format) };
+    match tiling {
+        vk::ImageTiling::LINEAR => (format_properties.linear_tiling_features & features) == features,
+        vk::ImageTiling::OPTIMAL => (format_properties.optimal_tiling_features & features) == features,
+        _ => false
+    }
+}
+
+// the first sampleable depth stencil attachment format, see RenderFeatures::_depth_stencil_format
+pub fn find_supported_depth_stencil_format(instance: &Instance, physical_device: vk::PhysicalDevice) -> Option<vk::Format> {
+    let features = vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT | vk::FormatFeatureFlags::SAMPLED_IMAGE;
+    constants::DEPTH_STENCIL_FORMATS.iter().find(|format| {
+        is_format_feature_supported(instance, physical_device, **format, vk::ImageTiling::OPTIMAL, features)
+    }).map(|format| *format)
+}
+
+pub fn has_stencil_component(format: vk::Format) -> bool {
+    constants::DEPTH_STENCIL_FORMATS.contains(&format)
+}
+
 pub fn create_image_sampler(
     device: &Device,
     mip_levels: u32,
@@ -752,7 +877,7 @@ pub fn create_image(
     usage: vk::ImageUsageFlags,
     image_create_flags: vk::ImageCreateFlags,
     memory_property_flags: vk::MemoryPropertyFlags
-) -> (vk::DeviceMemory, vk::Image) {
+) -> (MemoryAllocation, vk::Image) {
     unsafe {
         let image_create_info = vk::ImageCreateInfo {
             flags: image_create_flags,
@@ -781,21 +906,20 @@ pub fn create_image(
         let image = device.create_image(&image_create_info, None).expect("vkCreateImage failed!");
         let memory_requirements = device.get_image_memory_requirements(image);
         let memory_type_index = buffer::find_memory_type_index(&memory_requirements, memory_properties, memory_property_flags).unwrap();
-        let image_allocate_info = vk::MemoryAllocateInfo {
-            allocation_size: memory_requirements.size,
-            memory_type_index,
-            ..Default::default()
-        };
-        let image_memory = device.allocate_memory(&image_allocate_info, None).expect("vkAllocateMemory failed!");
-        device.bind_image_memory(image, image_memory, 0).expect("vkBindImageMemory failed!");
+        let allocation_kind = if vk::ImageTiling::LINEAR == tiling { MemoryAllocationKind::Linear } else { MemoryAllocationKind::Optimal };
+        let image_memory = device_memory_allocator::allocate_memory(device, &memory_requirements, memory_type_index, allocation_kind);
+        vulkan_context::add_allocated_memory_size(memory_requirements.size);
+        device.bind_image_memory(image, image_memory._memory, image_memory._offset).expect("vkBindImageMemory failed!");
         (image_memory, image)
     }
 }
 
-pub fn destroy_image(device: &Device, image: vk::Image, image_memory: vk::DeviceMemory) {
+pub fn destroy_image(device: &Device, image: vk::Image, image_memory: &MemoryAllocation) {
     unsafe {
+        let memory_requirements = device.get_image_memory_requirements(image);
         device.destroy_image(image, None);
-        device.free_memory(image_memory, None);
+        device_memory_allocator::free_memory(device, image_memory);
+        vulkan_context::remove_allocated_memory_size(memory_requirements.size);
     }
 }
 
@@ -834,6 +958,76 @@ pub fn copy_buffer_to_image(
     });
 }
 
+// the pre-encoded mip chain, mip-major in the buffer. the block compressed formats can not be blitted by generate_mipmaps.
+pub fn copy_buffer_to_image_mip_levels(
+    device: &Device,
+    command_pool: vk::CommandPool,
+    command_queue: vk::Queue,
+    buffer: vk::Buffer,
+    image: vk::Image,
+    image_aspect: vk::ImageAspectFlags,
+    format: vk::Format,
+    width: u32,
+    height: u32,
+    depth: u32,
+    layer_count: u32,
+    mip_levels: u32,
+) {
+    let regions = get_buffer_image_copy_regions(image_aspect, format, width, height, depth, layer_count, mip_levels);
+    run_commands_once(device, command_pool, command_queue, |device: &Device, command_buffer: vk::CommandBuffer| {
+        unsafe {
+            device.cmd_copy_buffer_to_image(command_buffer, buffer, image, vk::ImageLayout::TRANSFER_DST_OPTIMAL, &regions);
+        }
+        transition_image_layout(
+            device,
+            command_buffer,
+            image,
+            format,
+            ImageLayoutTransition::TransferDstToShaderReadOnly,
+            0,
+            mip_levels,
+            0,
+            layer_count,
+        );
+    });
+}
+
+// mip-major in the buffer, mip 0 only if mip_levels is 1.
+pub fn get_buffer_image_copy_regions(
+    image_aspect: vk::ImageAspectFlags,
+    format: vk::Format,
+    width: u32,
+    height: u32,
+    depth: u32,
+    layer_count: u32,
+    mip_levels: u32,
+) -> Vec<vk::BufferImageCopy> {
+    let texel_size = max(1, vulkan_context::get_format_size(format)) as usize;
+    let mut buffer_offset: vk::DeviceSize = 0;
+    (0..mip_levels).map(|mip_level| {
+        let (mip_width, mip_height, mip_depth) = (max(1, width >> mip_level), max(1, height >> mip_level), max(1, depth >> mip_level));
+        let region = vk::BufferImageCopy {
+            buffer_offset,
+            buffer_row_length: 0,
+            buffer_image_height: 0,
+            image_subresource: vk::ImageSubresourceLayers {
+                aspect_mask: image_aspect,
+                mip_level,
+                base_array_layer: 0,
+                layer_count,
+            },
+            image_offset: vk::Offset3D { x: 0, y: 0, z: 0 },
+            image_extent: vk::Extent3D { width: mip_width, height: mip_height, depth: mip_depth },
+        };
+        buffer_offset += if is_block_compressed_format(format) {
+            get_compressed_mip_level_size(format, mip_width, mip_height, mip_depth, layer_count)
+        } else {
+            (mip_width * mip_height * mip_depth * layer_count) as usize * texel_size
+        } as vk::DeviceSize;
+        region
+    }).collect()
+}
+
 pub fn copy_image_to_buffer(
     device: &Device,
     command_pool: vk::CommandPool,
@@ -869,13 +1063,160 @@ pub fn copy_image_to_buffer(
     });
 }
 
+// src: SHADER_READ_ONLY_OPTIMAL, mip level (src_base_mip_level + i) -> mip level i of dst: SHADER_READ_ONLY_OPTIMAL
+pub fn copy_image_mip_levels(
+    device: &Device,
+    command_buffer: vk::CommandBuffer,
+    src_image: vk::Image,
+    dst_image: vk::Image,
+    image_aspect: vk::ImageAspectFlags,
+    src_base_mip_level: u32,
+    width: u32,
+    height: u32,
+    mip_levels: u32,
+    layer_count: u32,
+) {
+    let subresource_range = |base_mip_level: u32| vk::ImageSubresourceRange {
+        aspect_mask: image_aspect,
+        base_mip_level,
+        level_count: mip_levels,
+        base_array_layer: 0,
+        layer_count,
+    };
+    let barriers: [vk::ImageMemoryBarrier; 2] = [
+        vk::ImageMemoryBarrier {
+            old_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            new_layout: vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            image: src_image,
+            subresource_range: subresource_range(src_base_mip_level),
+            src_access_mask: vk::AccessFlags::SHADER_READ,
+            dst_access_mask: vk::AccessFlags::TRANSFER_READ,
+            ..Default::default()
+        },
+        vk::ImageMemoryBarrier {
+            old_layout: vk::ImageLayout::UNDEFINED,
+            new_layout: vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            image: dst_image,
+            subresource_range: subresource_range(0),
+            src_access_mask: vk::AccessFlags::empty(),
+            dst_access_mask: vk::AccessFlags::TRANSFER_WRITE,
+            ..Default::default()
+        },
+    ];
+    let regions: Vec<vk::ImageCopy> = (0..mip_levels).map(|mip_level| {
+        vk::ImageCopy {
+            src_subresource: vk::ImageSubresourceLayers {
+                aspect_mask: image_aspect,
+                mip_level: src_base_mip_level + mip_level,
+                base_array_layer: 0,
+                layer_count,
+            },
+            src_offset: vk::Offset3D { x: 0, y: 0, z: 0 },
+            dst_subresource: vk::ImageSubresourceLayers {
+                aspect_mask: image_aspect,
+                mip_level,
+                base_array_layer: 0,
+                layer_count,
+            },
+            dst_offset: vk::Offset3D { x: 0, y: 0, z: 0 },
+            extent: vk::Extent3D { width: max(1, width >> mip_level), height: max(1, height >> mip_level), depth: 1 },
+        }
+    }).collect();
+    unsafe {
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::FRAGMENT_SHADER,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &barriers
+        );
+        device.cmd_copy_image(
+            command_buffer,
+            src_image,
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            dst_image,
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            &regions
+        );
+    }
+    transition_image_layout(
+        device,
+        command_buffer,
+        dst_image,
+        vk::Format::UNDEFINED,
+        ImageLayoutTransition::TransferDstToShaderReadOnly,
+        0,
+        mip_levels,
+        0,
+        layer_count,
+    );
+}
+
+// image create flags, layer count, depth
+pub fn get_image_create_flags_and_layers(image_view_type: vk::ImageViewType, texture_layers: u32) -> (vk::ImageCreateFlags, u32, u32) {
+    match image_view_type {
+        vk::ImageViewType::CUBE => (vk::ImageCreateFlags::CUBE_COMPATIBLE, 6, 1),
+        vk::ImageViewType::TYPE_2D_ARRAY => (vk::ImageCreateFlags::empty(), texture_layers, 1),
+        vk::ImageViewType::TYPE_3D => (vk::ImageCreateFlags::TYPE_2D_ARRAY_COMPATIBLE, 1, texture_layers),
+        _ => (vk::ImageCreateFlags::empty(), 1, 1),
+    }
+}
+
+// initial mip levels, mip levels
+pub fn get_texture_mip_levels<T>(texture_create_info: &TextureCreateInfo<T>, texture_depth: u32) -> (u32, u32) {
+    // the block compressed formats are uploaded as they are, a single level without the pre-encoded mip chain.
+    let initial_mip_levels = if is_block_compressed_format(texture_create_info._texture_format) {
+        max(1, texture_create_info._texture_initial_mip_levels)
+    } else {
+        texture_create_info._texture_initial_mip_levels
+    };
+    let mip_levels = if 0 < initial_mip_levels {
+        min(initial_mip_levels, calc_mip_levels(texture_create_info._texture_width, texture_create_info._texture_height, texture_depth, constants::WHOLE_MIP_LEVELS))
+    } else if texture_create_info._enable_mipmap {
+        calc_mip_levels(texture_create_info._texture_width, texture_create_info._texture_height, texture_depth, texture_create_info._max_mip_levels)
+    } else {
+        1
+    };
+    (initial_mip_levels, mip_levels)
+}
+
+pub fn create_staging_buffer_data<T: Copy>(device: &Device, memory_properties: &vk::PhysicalDeviceMemoryProperties, datas: &[T]) -> buffer::BufferData {
+    let buffer_size = (datas.len() * std::mem::size_of::<T>()) as vk::DeviceSize;
+    let staging_buffer_usage_flags = vk::BufferUsageFlags::TRANSFER_SRC;
+    let staging_buffer_memory_property_flags = vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT;
+    let staging_buffer_data: buffer::BufferData = buffer::create_buffer_data(
+        device,
+        memory_properties,
+        buffer_size,
+        staging_buffer_usage_flags,
+        staging_buffer_memory_property_flags
+    );
+
+    unsafe {
+        // upload data
+        let stageing_buffer_ptr: *mut c_void = staging_buffer_data._memory_allocation.get_mapped_ptr(0);
+        let mut stageing_buffer_slice = Align::new(
+            stageing_buffer_ptr,
+            align_of::<T>() as u64,
+            staging_buffer_data._buffer_memory_requirements.size,
+        );
+        stageing_buffer_slice.copy_from_slice(datas);
+    }
+    staging_buffer_data
+}
 
 pub fn create_render_target<T: Copy>(
     instance: &Instance,
     device: &Device,
     physical_device: vk::PhysicalDevice,
     memory_properties: &vk::PhysicalDeviceMemoryProperties,
-    command_pool: vk::CommandPool,
+    staging_buffer_pool: &mut StagingBufferPool,
     command_queue: vk::Queue,
     texture_create_info: &TextureCreateInfo<T>,
 ) -> TextureData {
@@ -885,7 +1226,7 @@ pub fn create_render_target<T: Copy>(
         device,
         physical_device,
         memory_properties,
-        command_pool,
+        staging_buffer_pool,
         command_queue,
         texture_create_info,
         is_render_target,
@@ -897,7 +1238,7 @@ pub fn create_texture_data<T: Copy>(
     device: &Device,
     physical_device: vk::PhysicalDevice,
     memory_properties: &vk::PhysicalDeviceMemoryProperties,
-    command_pool: vk::CommandPool,
+    staging_buffer_pool: &mut StagingBufferPool,
     command_queue: vk::Queue,
     texture_create_info: &TextureCreateInfo<T>,
 ) -> TextureData {
@@ -907,7 +1248,7 @@ pub fn create_texture_data<T: Copy>(
         device,
         physical_device,
         memory_properties,
-        command_pool,
+        staging_buffer_pool,
         command_queue,
         texture_create_info,
         is_render_target,
@@ -919,7 +1260,7 @@ fn create_texture_data_inner<T: Copy>(
     device: &Device,
     physical_device: vk::PhysicalDevice,
     memory_properties: &vk::PhysicalDeviceMemoryProperties,
-    command_pool: vk::CommandPool,
+    staging_buffer_pool: &mut StagingBufferPool,
     command_queue: vk::Queue,
     texture_create_info: &TextureCreateInfo<T>,
     is_render_target: bool,
@@ -931,19 +1272,11 @@ fn create_texture_data_inner<T: Copy>(
         true => vk::TRUE,
         _ => vk::FALSE
     };
-    let (texture_create_flags, layer_count, texture_depth) = match texture_create_info._texture_view_type {
-        vk::ImageViewType::CUBE => (vk::ImageCreateFlags::CUBE_COMPATIBLE, 6, 1),
-        vk::ImageViewType::TYPE_2D_ARRAY => (vk::ImageCreateFlags::empty(), texture_create_info._texture_layers, 1),
-        vk::ImageViewType::TYPE_3D => (vk::ImageCreateFlags::TYPE_2D_ARRAY_COMPATIBLE, 1, texture_create_info._texture_layers),
-        _ => (vk::ImageCreateFlags::empty(), 1, 1),
-    };
-    let mip_levels = match texture_create_info._enable_mipmap {
-        true => calc_mip_levels(texture_create_info._texture_width, texture_create_info._texture_height, texture_depth, texture_create_info._max_mip_levels),
-        _ => 1
-    };
+    let (texture_create_flags, layer_count, texture_depth) = get_image_create_flags_and_layers(texture_create_info._texture_view_type, texture_create_info._texture_layers);
+    let (initial_mip_levels, mip_levels) = get_texture_mip_levels(texture_create_info, texture_depth);
 
     let is_depth_format = constants::DEPTH_FOMATS.contains(&texture_create_info._texture_format);
-    let common_usage = vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_SRC | vk::ImageUsageFlags::TRANSFER_DST;
+    let common_usage = vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_SRC | vk::ImageUsageFlags::TRANSFER_DST | texture_create_info._texture_additional_usage;
     let (image_usage, image_aspect, image_layout_transition, image_format, image_layout) =
         if is_render_target {
             if is_depth_format {
@@ -960,7 +1293,9 @@ fn create_texture_data_inner<T: Copy>(
                   vk::ImageLayout::GENERAL,
                 )
             } else {
-                ( common_usage | vk::ImageUsageFlags::INPUT_ATTACHMENT | vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::STORAGE,
+                // the multisampled storage image needs shaderStorageImageMultisample, the msaa targets are only attachments
+                let storage_usage = if vk::SampleCountFlags::TYPE_1 == texture_create_info._texture_samples { vk::ImageUsageFlags::STORAGE } else { vk::ImageUsageFlags::empty() };
+                ( common_usage | vk::ImageUsageFlags::INPUT_ATTACHMENT | vk::ImageUsageFlags::COLOR_ATTACHMENT | storage_usage,
                   vk::ImageAspectFlags::COLOR,
                   ImageLayoutTransition::TransferUndefToColorAttachemnt,
                   texture_create_info._texture_format,
@@ -979,7 +1314,7 @@ fn create_texture_data_inner<T: Copy>(
     let image_type = image_view_type_to_image_type(texture_create_info._texture_view_type);
 
     // we don't need to access the vk::DeviceMemory of the image, copyBufferToImage works with the vk::Image
-    let (image_memory, image) = create_image(
+    let (mut image_memory, mut image) = create_image(
         instance,
         device,
         physical_device,
@@ -998,65 +1333,56 @@ fn create_texture_data_inner<T: Copy>(
         vk::MemoryPropertyFlags::DEVICE_LOCAL
     );
 
-    run_commands_once(device, command_pool, command_queue, |device: &Device, command_buffer: vk::CommandBuffer| {
-        transition_image_layout(
-            device,
-            command_buffer,
-            image,
-            image_format,
-            image_layout_transition,
-            0,
-            mip_levels,
-            0,
-            layer_count,
-        );
-    });
+    // the layout transition, the copy and the mipmaps are recorded into the upload command buffer of the staging buffer pool
+    let command_buffer = staging_buffer_pool.get_upload_command_buffer(device);
+    transition_image_layout(
+        device,
+        command_buffer,
+        image,
+        image_format,
+        image_layout_transition,
+        0,
+        mip_levels,
+        0,
+        layer_count,
+    );
 
     if has_initial_datas {
-        // create temporary staging buffer
-        let staging_buffer_usage_flags = vk::BufferUsageFlags::TRANSFER_SRC;
-        let staging_buffer_memory_property_flags = vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT;
-        let staging_buffer_data: buffer::BufferData = buffer::create_buffer_data(
-            device,
-            memory_properties,
-            buffer_size,
-            staging_buffer_usage_flags,
-            staging_buffer_memory_property_flags
-        );
-
-        unsafe {
-            // upload data
-            let stageing_buffer_ptr: *mut c_void = device.map_memory(
-                staging_buffer_data._buffer_memory,
-                0,
-                buffer_size,
-                vk::MemoryMapFlags::empty()
-            ).expect("Failed to map_memory!");
-            let mut stageing_buffer_slice = Align::new(
-                stageing_buffer_ptr,
-                align_of::<T>() as u64,
-                staging_buffer_data._buffer_memory_requirements.size,
-            );
-            stageing_buffer_slice.copy_from_slice(image_datas);
-            device.unmap_memory(staging_buffer_data._buffer_memory);
-        }
-
-        copy_buffer_to_image(
-            device,
-            command_pool,
-            command_queue,
-            staging_buffer_data._buffer,
-            image,
+        let texel_size = max(1, vulkan_context::get_format_size(texture_create_info._texture_format)) as vk::DeviceSize;
+        let alignment = if 0 == buffer::STAGING_BUFFER_ALIGNMENT % texel_size { buffer::STAGING_BUFFER_ALIGNMENT } else { texel_size * 4 };
+        let staging_region = staging_buffer_pool.allocate_upload(device, memory_properties, image_datas, alignment);
+        let copy_mip_levels = if 0 < initial_mip_levels { mip_levels } else { 1 };
+        let mut regions = get_buffer_image_copy_regions(
             image_aspect,
+            texture_create_info._texture_format,
             texture_create_info._texture_width,
             texture_create_info._texture_height,
             texture_depth,
             layer_count,
+            copy_mip_levels,
         );
+        for region in regions.iter_mut() {
+            region.buffer_offset += staging_region._offset;
+        }
+        unsafe {
+            device.cmd_copy_buffer_to_image(command_buffer, staging_region._buffer, image, vk::ImageLayout::TRANSFER_DST_OPTIMAL, &regions);
+        }
 
-        // generateMipmaps does this as a side effect:
-        // transitionImageLayout image VK_FORMAT_R8G8B8A8_UNORM TransferDst_ShaderReadOnly mipLevels
-        run_commands_once(device, command_pool, command_queue, |device: &Device, command_buffer: vk::CommandBuffer| {
+        if 0 < initial_mip_levels {
+            transition_image_layout(
+                device,
+                command_buffer,
+                image,
+                texture_create_info._texture_format,
+                ImageLayoutTransition::TransferDstToShaderReadOnly,
+                0,
+                mip_levels,
+                0,
+                layer_count,
+            );
+        } else {
+            // generateMipmaps does this as a side effect:
+            // transitionImageLayout image VK_FORMAT_R8G8B8A8_UNORM TransferDst_ShaderReadOnly mipLevels
             generate_mipmaps(
                 instance,
                 device,
@@ -1071,10 +1397,64 @@ fn create_texture_data_inner<T: Copy>(
                 mip_levels,
                 layer_count,
             );
-        });
+        }
+    }
 
-        // destroy staging buffer
-        buffer::destroy_buffer_data(device, &staging_buffer_data);
+    // The whole mip chain is generated from the uploaded mip 0, then mip N.. are copied to the smaller image.
+    let mip_drop_info = compute_mip_drop(
+        texture_create_info._texture_width,
+        texture_create_info._texture_height,
+        texture_depth,
+        mip_levels,
+        (texture_create_info._enable_mipmap || 1 < initial_mip_levels) && has_initial_datas && false == is_render_target,
+        texture_create_info._texture_mip_drop
+    );
+    let mut image_memory_saved: vk::DeviceSize = 0;
+    if 0 < mip_drop_info._mip_drop {
+        let (dropped_image_memory, dropped_image) = create_image(
+            instance,
+            device,
+            physical_device,
+            memory_properties,
+            image_type,
+            mip_drop_info._width,
+            mip_drop_info._height,
+            texture_depth,
+            layer_count,
+            mip_drop_info._mip_levels,
+            texture_create_info._texture_samples,
+            image_format,
+            vk::ImageTiling::OPTIMAL,
+            image_usage,
+            texture_create_flags,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL
+        );
+        copy_image_mip_levels(
+            device,
+            command_buffer,
+            image,
+            dropped_image,
+            image_aspect,
+            mip_drop_info._mip_drop,
+            mip_drop_info._width,
+            mip_drop_info._height,
+            mip_drop_info._mip_levels,
+            layer_count,
+        );
+        // the source image is destroyed below
+        staging_buffer_pool.flush_uploads(device, command_queue);
+        unsafe {
+            let image_memory_size = device.get_image_memory_requirements(image).size;
+            let dropped_image_memory_size = device.get_image_memory_requirements(dropped_image).size;
+            image_memory_saved = image_memory_size - dropped_image_memory_size.min(image_memory_size);
+        }
+        destroy_image(device, image, &image_memory);
+        image = dropped_image;
+        image_memory = dropped_image_memory;
+    }
+    let (image_width, image_height, mip_levels) = (mip_drop_info._width, mip_drop_info._height, mip_drop_info._mip_levels);
+    if false == staging_buffer_pool.is_upload_batch() {
+        staging_buffer_pool.flush_uploads(device, command_queue);
     }
 
     // create image view, sampler, descriptor
@@ -1101,16 +1481,430 @@ fn create_texture_data_inner<T: Copy>(
                texture_create_info._texture_name,
                texture_create_info._texture_view_type,
                image_format,
-               texture_create_info._texture_width,
-               texture_create_info._texture_height,
+               image_width,
+               image_height,
                texture_create_info._texture_layers,
     );
+    if 0 < mip_drop_info._mip_drop {
+        log::debug!("    mip_drop: {}, {} x {} -> {} x {}, saved: {} bytes", mip_drop_info._mip_drop, texture_create_info._texture_width, texture_create_info._texture_height, image_width, image_height, image_memory_saved);
+    }
     log::trace!("    TextureData: image: {:?}, image_view: {:?}, image_memory: {:?}, sampler: {:?}", image, image_datas._image_view, image_memory, image_datas._image_sampler);
     if false == image_datas._sub_image_views.is_empty() {
         log::trace!("                 sub_image_views: {:?}", image_datas._sub_image_views);
     }
 
-    TextureData {
+    telemetry::record_created(ResourceClass::Texture, 1);
+    let texture_data = TextureData {
+        _texture_data_name: texture_create_info._texture_name.clone(),
+        _image: image,
+        _image_view: image_datas._image_view,
+        _image_info: image_datas._image_info,
+        _image_view_type: texture_create_info._texture_view_type,
+        _image_sampler: image_datas._image_sampler,
+        _sub_image_views: image_datas._sub_image_views,
+        _sub_image_infos: image_datas._sub_image_infos,
+        _sub_image_view_type: image_datas._sub_image_view_type,
+        _image_memory: image_memory,
+        _image_format: image_format,
+        _image_width: image_width,
+        _image_height: image_height,
+        _image_layers: texture_create_info._texture_layers,
+        _image_mip_levels: mip_levels,
+        _image_sample_count: texture_create_info._texture_samples,
+        _image_mip_drop: mip_drop_info._mip_drop,
+        _image_memory_saved: image_memory_saved,
+        _bindless_texture_index: bindless_texture::INVALID_BINDLESS_TEXTURE_INDEX,
+    };
+    set_texture_object_names(&texture_data);
+    texture_data
+}
+
+// the textures recorded between create_texture_upload_batch and submit_and_wait.
+// the copies run on the transfer queue, the mipmaps and the final layouts on the graphics queue after the ownership transfer.
+pub struct TextureUploadBatch {
+    pub _transfer_command_pool: vk::CommandPool,
+    pub _transfer_queue: vk::Queue,
+    pub _transfer_queue_family_index: u32,
+    pub _transfer_command_buffer: vk::CommandBuffer,
+    pub _graphics_command_pool: vk::CommandPool,
+    pub _graphics_queue: vk::Queue,
+    pub _graphics_queue_family_index: u32,
+    pub _graphics_command_buffer: vk::CommandBuffer,
+    pub _upload_semaphore: vk::Semaphore,
+    pub _upload_fence: vk::Fence,
+    pub _texture_count: u32, // the staging regions are from the StagingBufferPool, released at StagingBufferPool::end_upload_batch
+}
+
+impl TextureUploadBatch {
+    pub fn create_texture_upload_batch(
+        device: &Device,
+        transfer_command_pool: vk::CommandPool,
+        transfer_queue: vk::Queue,
+        transfer_queue_family_index: u32,
+        graphics_command_pool: vk::CommandPool,
+        graphics_queue: vk::Queue,
+        graphics_queue_family_index: u32,
+    ) -> TextureUploadBatch {
+        unsafe {
+            let allocate_command_buffer = |command_pool: vk::CommandPool| -> vk::CommandBuffer {
+                let allocate_info = vk::CommandBufferAllocateInfo {
+                    level: vk::CommandBufferLevel::PRIMARY,
+                    command_pool,
+                    command_buffer_count: 1,
+                    ..Default::default()
+                };
+                let command_buffer = device.allocate_command_buffers(&allocate_info).expect("vkAllocateCommandBuffers failed!")[0];
+                let command_buffer_begin_info = vk::CommandBufferBeginInfo {
+                    flags: vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT,
+                    ..Default::default()
+                };
+                device.begin_command_buffer(command_buffer, &command_buffer_begin_info).expect("Failed to begin_command_buffer");
+                command_buffer
+            };
+            let transfer_command_buffer = allocate_command_buffer(transfer_command_pool);
+            let graphics_command_buffer = allocate_command_buffer(graphics_command_pool);
+            let upload_semaphore = device.create_semaphore(&vk::SemaphoreCreateInfo::default(), None).expect("vkCreateSemaphore failed!");
+            let upload_fence = device.create_fence(&vk::FenceCreateInfo::default(), None).expect("vkCreateFence failed!");
+            log::debug!("create_texture_upload_batch: transfer queue family: {}, graphics queue family: {}", transfer_queue_family_index, graphics_queue_family_index);
+            TextureUploadBatch {
+                _transfer_command_pool: transfer_command_pool,
+                _transfer_queue: transfer_queue,
+                _transfer_queue_family_index: transfer_queue_family_index,
+                _transfer_command_buffer: transfer_command_buffer,
+                _graphics_command_pool: graphics_command_pool,
+                _graphics_queue: graphics_queue,
+                _graphics_queue_family_index: graphics_queue_family_index,
+                _graphics_command_buffer: graphics_command_buffer,
+                _upload_semaphore: upload_semaphore,
+                _upload_fence: upload_fence,
+                _texture_count: 0,
+            }
+        }
+    }
+
+    pub fn is_queue_family_ownership_transfer(&self) -> bool {
+        self._transfer_queue_family_index != self._graphics_queue_family_index
+    }
+
+    // the release barrier on the transfer queue and the acquire barrier on the graphics queue, the layout stays TRANSFER_DST_OPTIMAL.
+    pub fn record_queue_family_ownership_transfer(&self, device: &Device, image: vk::Image, image_aspect: vk::ImageAspectFlags, mip_levels: u32, layer_count: u32) {
+        if false == self.is_queue_family_ownership_transfer() {
+            return;
+        }
+        let ownership_barrier = |src_access_mask: vk::AccessFlags, dst_access_mask: vk::AccessFlags| -> [vk::ImageMemoryBarrier; 1] {
+            [vk::ImageMemoryBarrier {
+                old_layout: vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                new_layout: vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                src_queue_family_index: self._transfer_queue_family_index,
+                dst_queue_family_index: self._graphics_queue_family_index,
+                image,
+                subresource_range: vk::ImageSubresourceRange {
+                    aspect_mask: image_aspect,
+                    base_mip_level: 0,
+                    level_count: mip_levels,
+                    base_array_layer: 0,
+                    layer_count,
+                },
+                src_access_mask,
+                dst_access_mask,
+                ..Default::default()
+            }]
+        };
+        unsafe {
+            device.cmd_pipeline_barrier(
+                self._transfer_command_buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &ownership_barrier(vk::AccessFlags::TRANSFER_WRITE, vk::AccessFlags::empty())
+            );
+            device.cmd_pipeline_barrier(
+                self._graphics_command_buffer,
+                vk::PipelineStageFlags::TOP_OF_PIPE,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &ownership_barrier(vk::AccessFlags::empty(), vk::AccessFlags::TRANSFER_READ | vk::AccessFlags::TRANSFER_WRITE)
+            );
+        }
+    }
+
+    // waits once for the whole batch, the staging regions can be released after this.
+    pub fn submit_and_wait(self, device: &Device) {
+        unsafe {
+            device.end_command_buffer(self._transfer_command_buffer).expect("Failed to end_command_buffer");
+            device.end_command_buffer(self._graphics_command_buffer).expect("Failed to end_command_buffer");
+
+            let transfer_command_buffers = [self._transfer_command_buffer];
+            let signal_semaphores = [self._upload_semaphore];
+            let transfer_submit_info = vk::SubmitInfo {
+                command_buffer_count: transfer_command_buffers.len() as u32,
+                p_command_buffers: transfer_command_buffers.as_ptr(),
+                signal_semaphore_count: signal_semaphores.len() as u32,
+                p_signal_semaphores: signal_semaphores.as_ptr(),
+                ..Default::default()
+            };
+            device.queue_submit(self._transfer_queue, &[transfer_submit_info], vk::Fence::null()).expect("vkQueueSubmit failed!");
+
+            let graphics_command_buffers = [self._graphics_command_buffer];
+            let wait_mask = [vk::PipelineStageFlags::TRANSFER];
+            let graphics_submit_info = vk::SubmitInfo {
+                wait_semaphore_count: signal_semaphores.len() as u32,
+                p_wait_semaphores: signal_semaphores.as_ptr(),
+                p_wait_dst_stage_mask: wait_mask.as_ptr(),
+                command_buffer_count: graphics_command_buffers.len() as u32,
+                p_command_buffers: graphics_command_buffers.as_ptr(),
+                ..Default::default()
+            };
+            device.queue_submit(self._graphics_queue, &[graphics_submit_info], self._upload_fence).expect("vkQueueSubmit failed!");
+            device.wait_for_fences(&[self._upload_fence], true, std::u64::MAX).expect("vkWaitForFences failed!");
+
+            log::info!("TextureUploadBatch::submit_and_wait: {} textures", self._texture_count);
+            device.destroy_semaphore(self._upload_semaphore, None);
+            device.destroy_fence(self._upload_fence, None);
+            device.free_command_buffers(self._transfer_command_pool, &transfer_command_buffers);
+            device.free_command_buffers(self._graphics_command_pool, &graphics_command_buffers);
+        }
+    }
+}
+
+// re-uploads mip 0 of every layer of a texture resource in place, the image, the views and the descriptors are kept.
+// the mip chain is generated again. the texture must be in SHADER_READ_ONLY_OPTIMAL and not mip dropped.
+pub fn update_texture_data<T: Copy>(
+    instance: &Instance,
+    device: &Device,
+    physical_device: vk::PhysicalDevice,
+    memory_properties: &vk::PhysicalDeviceMemoryProperties,
+    staging_buffer_pool: &mut StagingBufferPool,
+    command_queue: vk::Queue,
+    texture_data: &TextureData,
+    image_datas: &[T],
+) -> bool {
+    let (_texture_create_flags, layer_count, texture_depth) = get_image_create_flags_and_layers(texture_data._image_view_type, texture_data._image_layers);
+    let texel_size = max(1, vulkan_context::get_format_size(texture_data._image_format)) as usize;
+    let expected_size = texel_size * (texture_data._image_width * texture_data._image_height * texture_depth * layer_count) as usize;
+    let buffer_size = image_datas.len() * std::mem::size_of::<T>();
+    if expected_size != buffer_size || is_block_compressed_format(texture_data._image_format) || 0 < texture_data._image_mip_drop {
+        log::error!("update_texture_data: {} can not be updated, {} bytes != {} bytes, mip drop: {}", texture_data._texture_data_name, buffer_size, expected_size, texture_data._image_mip_drop);
+        return false;
+    }
+
+    let mip_levels = texture_data._image_mip_levels;
+    let command_buffer = staging_buffer_pool.get_upload_command_buffer(device);
+    transition_image_layout(
+        device,
+        command_buffer,
+        texture_data._image,
+        texture_data._image_format,
+        ImageLayoutTransition::ShaderReadOnlyToTransferDst,
+        0,
+        mip_levels,
+        0,
+        layer_count,
+    );
+
+    let alignment = if 0 == buffer::STAGING_BUFFER_ALIGNMENT % (texel_size as vk::DeviceSize) { buffer::STAGING_BUFFER_ALIGNMENT } else { texel_size as vk::DeviceSize * 4 };
+    let staging_region = staging_buffer_pool.allocate_upload(device, memory_properties, image_datas, alignment);
+    let mut regions = get_buffer_image_copy_regions(
+        vk::ImageAspectFlags::COLOR,
+        texture_data._image_format,
+        texture_data._image_width,
+        texture_data._image_height,
+        texture_depth,
+        layer_count,
+        1,
+    );
+    for region in regions.iter_mut() {
+        region.buffer_offset += staging_region._offset;
+    }
+    unsafe {
+        device.cmd_copy_buffer_to_image(command_buffer, staging_region._buffer, texture_data._image, vk::ImageLayout::TRANSFER_DST_OPTIMAL, &regions);
+    }
+
+    if 1 < mip_levels {
+        generate_mipmaps(
+            instance,
+            device,
+            physical_device,
+            command_buffer,
+            texture_data._image,
+            vk::ImageAspectFlags::COLOR,
+            texture_data._image_format,
+            texture_data._image_width as i32,
+            texture_data._image_height as i32,
+            texture_depth as i32,
+            mip_levels,
+            layer_count,
+        );
+    } else {
+        transition_image_layout(
+            device,
+            command_buffer,
+            texture_data._image,
+            texture_data._image_format,
+            ImageLayoutTransition::TransferDstToShaderReadOnly,
+            0,
+            mip_levels,
+            0,
+            layer_count,
+        );
+    }
+
+    if false == staging_buffer_pool.is_upload_batch() {
+        staging_buffer_pool.flush_uploads(device, command_queue);
+    }
+    log::debug!("update_texture_data: {} {:?} {} {}", texture_data._texture_data_name, texture_data._image_format, texture_data._image_width, texture_data._image_height);
+    true
+}
+
+// records the upload of a texture resource into the batch, the texture can be sampled after TextureUploadBatch::submit_and_wait.
+// the textures without the initial datas or with the mip drop take the synchronous path on the graphics queue.
+pub fn create_texture_data_async<T: Copy>(
+    instance: &Instance,
+    device: &Device,
+    physical_device: vk::PhysicalDevice,
+    memory_properties: &vk::PhysicalDeviceMemoryProperties,
+    staging_buffer_pool: &mut StagingBufferPool,
+    upload_batch: &mut TextureUploadBatch,
+    texture_create_info: &TextureCreateInfo<T>,
+) -> TextureData {
+    let image_datas = &texture_create_info._texture_initial_datas;
+    if image_datas.is_empty() || 0 < texture_create_info._texture_mip_drop {
+        return create_texture_data(
+            instance,
+            device,
+            physical_device,
+            memory_properties,
+            staging_buffer_pool,
+            upload_batch._graphics_queue,
+            texture_create_info,
+        );
+    }
+
+    let enable_anisotropy = match texture_create_info._enable_anisotropy {
+        true => vk::TRUE,
+        _ => vk::FALSE
+    };
+    let (texture_create_flags, layer_count, texture_depth) = get_image_create_flags_and_layers(texture_create_info._texture_view_type, texture_create_info._texture_layers);
+    let (initial_mip_levels, mip_levels) = get_texture_mip_levels(texture_create_info, texture_depth);
+    let image_usage = vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_SRC | vk::ImageUsageFlags::TRANSFER_DST | texture_create_info._texture_additional_usage;
+    let image_aspect = vk::ImageAspectFlags::COLOR;
+    let image_format = texture_create_info._texture_format;
+    let (image_memory, image) = create_image(
+        instance,
+        device,
+        physical_device,
+        memory_properties,
+        image_view_type_to_image_type(texture_create_info._texture_view_type),
+        texture_create_info._texture_width,
+        texture_create_info._texture_height,
+        texture_depth,
+        layer_count,
+        mip_levels,
+        texture_create_info._texture_samples,
+        image_format,
+        vk::ImageTiling::OPTIMAL,
+        image_usage,
+        texture_create_flags,
+        vk::MemoryPropertyFlags::DEVICE_LOCAL
+    );
+
+    // transfer queue: copy the pre-encoded mip chain or mip 0
+    let texel_size = max(1, vulkan_context::get_format_size(image_format)) as vk::DeviceSize;
+    let alignment = if 0 == buffer::STAGING_BUFFER_ALIGNMENT % texel_size { buffer::STAGING_BUFFER_ALIGNMENT } else { texel_size * 4 };
+    let staging_region = staging_buffer_pool.allocate_upload(device, memory_properties, image_datas, alignment);
+    let copy_mip_levels = if 0 < initial_mip_levels { mip_levels } else { 1 };
+    let mut regions = get_buffer_image_copy_regions(
+        image_aspect,
+        image_format,
+        texture_create_info._texture_width,
+        texture_create_info._texture_height,
+        texture_depth,
+        layer_count,
+        copy_mip_levels,
+    );
+    for region in regions.iter_mut() {
+        region.buffer_offset += staging_region._offset;
+    }
+    transition_image_layout(
+        device,
+        upload_batch._transfer_command_buffer,
+        image,
+        image_format,
+        ImageLayoutTransition::TransferUndefToTransferDst,
+        0,
+        mip_levels,
+        0,
+        layer_count,
+    );
+    unsafe {
+        device.cmd_copy_buffer_to_image(upload_batch._transfer_command_buffer, staging_region._buffer, image, vk::ImageLayout::TRANSFER_DST_OPTIMAL, &regions);
+    }
+    upload_batch.record_queue_family_ownership_transfer(device, image, image_aspect, mip_levels, layer_count);
+
+    // graphics queue: the blits are not supported on every transfer queue
+    if 0 < initial_mip_levels {
+        transition_image_layout(
+            device,
+            upload_batch._graphics_command_buffer,
+            image,
+            image_format,
+            ImageLayoutTransition::TransferDstToShaderReadOnly,
+            0,
+            mip_levels,
+            0,
+            layer_count,
+        );
+    } else {
+        generate_mipmaps(
+            instance,
+            device,
+            physical_device,
+            upload_batch._graphics_command_buffer,
+            image,
+            image_aspect,
+            image_format,
+            texture_create_info._texture_width as i32,
+            texture_create_info._texture_height as i32,
+            texture_depth as i32,
+            mip_levels,
+            layer_count,
+        );
+    }
+    upload_batch._texture_count += 1;
+
+    let image_datas = create_image_datas(
+        device,
+        image,
+        texture_create_info._texture_view_type,
+        image_format,
+        image_aspect,
+        vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        texture_create_info._texture_min_filter,
+        texture_create_info._texture_mag_filter,
+        texture_create_info._texture_wrap_mode,
+        enable_anisotropy,
+        0,
+        mip_levels,
+        0,
+        layer_count,
+        texture_depth,
+    );
+
+    log::debug!("create_texture_data_async: {} {:?} {:?} {} {} {}",
+               texture_create_info._texture_name,
+               texture_create_info._texture_view_type,
+               image_format,
+               texture_create_info._texture_width,
+               texture_create_info._texture_height,
+               texture_create_info._texture_layers,
+    );
+
+    telemetry::record_created(ResourceClass::Texture, 1);
+    let texture_data = TextureData {
         _texture_data_name: texture_create_info._texture_name.clone(),
         _image: image,
         _image_view: image_datas._image_view,
@@ -1127,10 +1921,39 @@ fn create_texture_data_inner<T: Copy>(
         _image_layers: texture_create_info._texture_layers,
         _image_mip_levels: mip_levels,
         _image_sample_count: texture_create_info._texture_samples,
+        _image_mip_drop: 0,
+        _image_memory_saved: 0,
+        _bindless_texture_index: bindless_texture::INVALID_BINDLESS_TEXTURE_INDEX,
+    };
+    set_texture_object_names(&texture_data);
+    texture_data
+}
+
+// the sub image views are "name/layer/mip"
+pub fn set_texture_object_names(texture_data: &TextureData) {
+    let texture_name = &texture_data._texture_data_name;
+    object_name::set_object_name(texture_data._image, texture_name);
+    object_name::set_object_name(texture_data._image_view, texture_name);
+    object_name::set_object_name(texture_data._image_sampler, texture_name);
+    for (layer, sub_image_views) in texture_data._sub_image_views.iter().enumerate() {
+        for (mip_level, sub_image_view) in sub_image_views.iter().enumerate() {
+            object_name::set_object_name(*sub_image_view, &format!("{}/{}/{}", texture_name, layer, mip_level));
+        }
+    }
+}
+
+pub fn remove_texture_object_names(texture_data: &TextureData) {
+    object_name::remove_object_name(texture_data._image);
+    object_name::remove_object_name(texture_data._image_view);
+    object_name::remove_object_name(texture_data._image_sampler);
+    for sub_image_views in texture_data._sub_image_views.iter() {
+        object_name::remove_object_names(sub_image_views);
     }
 }
 
 pub fn destroy_texture_data(device: &Device, texture_data: &TextureData) {
+    telemetry::record_destroyed(ResourceClass::Texture, 1);
+    remove_texture_object_names(texture_data);
     unsafe {
         log::debug!("destroy_texture_data({}): image: {:?}, image_view: {:?}, image_memory: {:?}, sampler: {:?}",
             texture_data._texture_data_name,
@@ -1152,7 +1975,7 @@ pub fn destroy_texture_data(device: &Device, texture_data: &TextureData) {
             }
         }
 
-        destroy_image(device, texture_data._image, texture_data._image_memory);
+        destroy_image(device, texture_data._image, &texture_data._image_memory);
     }
 }
 
@@ -1199,4 +2022,90 @@ pub fn read_texture_data<T: Copy>(
 
     // destroy staging buffer
     buffer::destroy_buffer_data(device, &staging_buffer_data);
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_mip_chain_texel_count(width: u32, height: u32, mip_levels: u32) -> u64 {
+        (0..mip_levels).map(|mip_level| (max(1, width >> mip_level) * max(1, height >> mip_level)) as u64).sum()
+    }
+
+    #[test]
+    fn test_compute_mip_drop() {
+        let mip_levels = calc_mip_levels(1024, 512, 1, constants::WHOLE_MIP_LEVELS);
+        assert_eq!(11, mip_levels);
+        assert_eq!(MipDropInfo { _mip_drop: 0, _width: 1024, _height: 512, _mip_levels: 11 }, compute_mip_drop(1024, 512, 1, mip_levels, true, 0));
+        assert_eq!(MipDropInfo { _mip_drop: 1, _width: 512, _height: 256, _mip_levels: 10 }, compute_mip_drop(1024, 512, 1, mip_levels, true, 1));
+        assert_eq!(MipDropInfo { _mip_drop: 2, _width: 256, _height: 128, _mip_levels: 9 }, compute_mip_drop(1024, 512, 1, mip_levels, true, 2));
+        assert_eq!(MipDropInfo { _mip_drop: 3, _width: 128, _height: 64, _mip_levels: 8 }, compute_mip_drop(1024, 512, 1, mip_levels, true, 3));
+        // clamped to MAX_TEXTURE_MIP_DROP
+        assert_eq!(compute_mip_drop(1024, 512, 1, mip_levels, true, 3), compute_mip_drop(1024, 512, 1, mip_levels, true, 10));
+
+        // mip N of the source is the base level, the rest of the chain is the same as the chain of the smaller texture
+        for (width, height) in [(1024, 1024), (1024, 512), (512, 1024), (300, 200), (2048, 16), (64, 64)].iter() {
+            let mip_levels = calc_mip_levels(*width, *height, 1, constants::WHOLE_MIP_LEVELS);
+            for mip_drop in 0..(constants::MAX_TEXTURE_MIP_DROP + 1) {
+                let mip_drop_info = compute_mip_drop(*width, *height, 1, mip_levels, true, mip_drop);
+                assert_eq!(mip_drop, mip_drop_info._mip_drop);
+                assert_eq!(calc_mip_levels(mip_drop_info._width, mip_drop_info._height, 1, constants::WHOLE_MIP_LEVELS), mip_drop_info._mip_levels);
+            }
+        }
+    }
+
+    #[test]
+    fn test_compute_mip_drop_small_texture() {
+        // at least one mip level remains
+        assert_eq!(MipDropInfo { _mip_drop: 2, _width: 1, _height: 1, _mip_levels: 1 }, compute_mip_drop(4, 4, 1, 3, true, 3));
+        assert_eq!(MipDropInfo { _mip_drop: 1, _width: 2, _height: 1, _mip_levels: 1 }, compute_mip_drop(4, 2, 1, 2, true, 3));
+        assert_eq!(MipDropInfo { _mip_drop: 0, _width: 1, _height: 1, _mip_levels: 1 }, compute_mip_drop(1, 1, 1, 1, true, 3));
+        assert_eq!(MipDropInfo { _mip_drop: 0, _width: 1, _height: 1, _mip_levels: 1 }, compute_mip_drop(1, 1, 1, 0, true, 3));
+        // limited by the given mip levels
+        assert_eq!(MipDropInfo { _mip_drop: 1, _width: 512, _height: 512, _mip_levels: 1 }, compute_mip_drop(1024, 1024, 1, 2, true, 3));
+    }
+
+    #[test]
+    fn test_compute_mip_drop_without_mipmap() {
+        // the textures without the mipmap and the 3d luts are never dropped
+        assert_eq!(MipDropInfo { _mip_drop: 0, _width: 1024, _height: 512, _mip_levels: 1 }, compute_mip_drop(1024, 512, 1, 1, false, 2));
+        assert_eq!(MipDropInfo { _mip_drop: 0, _width: 1024, _height: 512, _mip_levels: 11 }, compute_mip_drop(1024, 512, 1, 11, false, 2));
+        let mip_levels = calc_mip_levels(32, 32, 32, constants::WHOLE_MIP_LEVELS);
+        assert_eq!(MipDropInfo { _mip_drop: 0, _width: 32, _height: 32, _mip_levels: mip_levels }, compute_mip_drop(32, 32, 32, mip_levels, true, 2));
+    }
+
+    #[test]
+    fn test_mip_drop_memory_savings() {
+        // roughly 4x per dropped level
+        let mip_levels = calc_mip_levels(2048, 2048, 1, constants::WHOLE_MIP_LEVELS);
+        let full_texel_count = get_mip_chain_texel_count(2048, 2048, mip_levels);
+        for mip_drop in 1..(constants::MAX_TEXTURE_MIP_DROP + 1) {
+            let mip_drop_info = compute_mip_drop(2048, 2048, 1, mip_levels, true, mip_drop);
+            let texel_count = get_mip_chain_texel_count(mip_drop_info._width, mip_drop_info._height, mip_drop_info._mip_levels);
+            let ratio = full_texel_count as f64 / texel_count as f64;
+            let expected_ratio = 4.0f64.powi(mip_drop as i32);
+            assert!((ratio - expected_ratio).abs() / expected_ratio < 0.001, "{} {}", mip_drop, ratio);
+        }
+    }
+
+    #[test]
+    fn test_auto_texture_mip_drop() {
+        const MEGA_BYTES: vk::DeviceSize = 1024 * 1024;
+        assert_eq!(2, get_auto_texture_mip_drop(512 * MEGA_BYTES));
+        assert_eq!(1, get_auto_texture_mip_drop(1024 * MEGA_BYTES));
+        assert_eq!(1, get_auto_texture_mip_drop(2047 * MEGA_BYTES));
+        assert_eq!(0, get_auto_texture_mip_drop(2048 * MEGA_BYTES));
+        assert_eq!(0, get_auto_texture_mip_drop(8192 * MEGA_BYTES));
+
+        // the largest device local heap, the host heaps are ignored
+        let mut memory_properties = vk::PhysicalDeviceMemoryProperties::default();
+        assert_eq!(0, get_device_local_memory_size(&memory_properties));
+        memory_properties.memory_heap_count = 3;
+        memory_properties.memory_heaps[0] = vk::MemoryHeap { size: 16384 * MEGA_BYTES, flags: vk::MemoryHeapFlags::empty() };
+        memory_properties.memory_heaps[1] = vk::MemoryHeap { size: 1536 * MEGA_BYTES, flags: vk::MemoryHeapFlags::DEVICE_LOCAL };
+        memory_properties.memory_heaps[2] = vk::MemoryHeap { size: 256 * MEGA_BYTES, flags: vk::MemoryHeapFlags::DEVICE_LOCAL };
+        memory_properties.memory_heaps[3] = vk::MemoryHeap { size: 8192 * MEGA_BYTES, flags: vk::MemoryHeapFlags::DEVICE_LOCAL };
+        assert_eq!(1536 * MEGA_BYTES, get_device_local_memory_size(&memory_properties));
+        assert_eq!(1, get_auto_texture_mip_drop(get_device_local_memory_size(&memory_properties)));
+    }
+}
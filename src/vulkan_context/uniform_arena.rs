@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+
+use ash::{
+    vk,
+    Device,
+};
+use ash::version::{
+    DeviceV1_0
+};
+
+use crate::constants;
+use crate::utilities::system;
+use crate::vulkan_context::buffer::{ self, BufferData, ShaderBufferData };
+use crate::vulkan_context::descriptor::DescriptorResourceInfo;
+use crate::vulkan_context::vulkan_context::SwapchainArray;
+
+pub const UNIFORM_ARENA_SIZE: vk::DeviceSize = 1024 * 1024; // per swapchain image
+
+// the sub allocation of a per frame constant block, the same offset in the buffer of every swapchain image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UniformArenaHandle {
+    pub _offset: vk::DeviceSize,
+    pub _size: vk::DeviceSize,
+}
+
+// one persistently mapped host visible buffer per swapchain image, every per frame constant block is sub allocated from it.
+// the blocks are allocated once at the loading, so the descriptor buffer infos point at the stable offsets.
+// the frame update code writes the blocks directly, flush_uniform_arena flushes the written range once before the submit.
+pub struct UniformArena {
+    pub _buffers: SwapchainArray<BufferData>,
+    pub _mapped_ptrs: SwapchainArray<*mut u8>,
+    pub _arena_size: vk::DeviceSize,
+    pub _offset_alignment: vk::DeviceSize,
+    pub _non_coherent_atom_size: vk::DeviceSize,
+    pub _is_coherent: bool,
+    pub _allocated_size: vk::DeviceSize,
+    pub _handles: HashMap<String, UniformArenaHandle>,
+    pub _written_ranges: SwapchainArray<Option<(vk::DeviceSize, vk::DeviceSize)>>, // begin, end of this frame
+    pub _write_count: u32, // since the last take_write_count
+}
+
+fn align_up(value: vk::DeviceSize, alignment: vk::DeviceSize) -> vk::DeviceSize {
+    if alignment <= 1 { value } else { (value + alignment - 1) / alignment * alignment }
+}
+
+impl UniformArena {
+    pub fn create_uniform_arena(
+        device: &Device,
+        memory_properties: &vk::PhysicalDeviceMemoryProperties,
+        limits: &vk::PhysicalDeviceLimits,
+        arena_size: vk::DeviceSize,
+    ) -> UniformArena {
+        let buffer_usage_flags = vk::BufferUsageFlags::UNIFORM_BUFFER | vk::BufferUsageFlags::STORAGE_BUFFER;
+        let memory_property_flags = vk::MemoryPropertyFlags::HOST_VISIBLE;
+        let buffers: SwapchainArray<BufferData> = (0..constants::SWAPCHAIN_IMAGE_COUNT).map(|_i| {
+            buffer::create_buffer_data(device, memory_properties, arena_size, buffer_usage_flags, memory_property_flags)
+        }).collect();
+        let memory_type_index = buffer::find_memory_type_index(&buffers[0]._buffer_memory_requirements, memory_properties, memory_property_flags).unwrap();
+        let is_coherent = memory_properties.memory_types[memory_type_index as usize].property_flags.contains(vk::MemoryPropertyFlags::HOST_COHERENT);
+        let mapped_ptrs: SwapchainArray<*mut u8> = buffers.iter().map(|buffer_data| {
+            buffer_data._memory_allocation.get_mapped_ptr(0) as *mut u8
+        }).collect();
+        let offset_alignment = limits.min_uniform_buffer_offset_alignment.max(limits.min_storage_buffer_offset_alignment);
+        log::info!("create_uniform_arena: {} bytes x {}, alignment: {}, coherent: {}", arena_size, constants::SWAPCHAIN_IMAGE_COUNT, offset_alignment, is_coherent);
+        UniformArena {
+            _buffers: buffers,
+            _mapped_ptrs: mapped_ptrs,
+            _arena_size: arena_size,
+            _offset_alignment: offset_alignment,
+            _non_coherent_atom_size: limits.non_coherent_atom_size,
+            _is_coherent: is_coherent,
+            _allocated_size: 0,
+            _handles: HashMap::new(),
+            _written_ranges: vec![None; constants::SWAPCHAIN_IMAGE_COUNT],
+            _write_count: 0,
+        }
+    }
+
+    pub fn destroy_uniform_arena(&mut self, device: &Device) {
+        for buffer_data in self._buffers.iter() {
+            buffer::destroy_buffer_data(device, buffer_data);
+        }
+        self._buffers.clear();
+        self._mapped_ptrs.clear();
+        self._handles.clear();
+        self._allocated_size = 0;
+    }
+
+    // the same name returns the same block, the blocks live until destroy_uniform_arena.
+    pub fn allocate_block(&mut self, block_name: &str, block_size: vk::DeviceSize) -> Result<UniformArenaHandle, String> {
+        if let Some(handle) = self._handles.get(block_name) {
+            if handle._size < block_size {
+                return Err(format!("allocate_block {}: {} bytes is allocated already, requested {}", block_name, handle._size, block_size));
+            }
+            return Ok(*handle);
+        }
+        let offset = align_up(self._allocated_size, self._offset_alignment);
+        if self._arena_size < offset + block_size {
+            return Err(format!("allocate_block {}: out of the uniform arena, {} + {} > {}", block_name, offset, block_size, self._arena_size));
+        }
+        let handle = UniformArenaHandle { _offset: offset, _size: block_size };
+        self._allocated_size = offset + block_size;
+        self._handles.insert(String::from(block_name), handle);
+        log::debug!("allocate_block {}: offset {}, size {}", block_name, offset, block_size);
+        Ok(handle)
+    }
+
+    pub fn get_handle(&self, block_name: &str) -> Option<UniformArenaHandle> {
+        self._handles.get(block_name).cloned()
+    }
+
+    pub fn get_descriptor_buffer_infos(&self, handle: &UniformArenaHandle) -> SwapchainArray<DescriptorResourceInfo> {
+        self._buffers.iter().map(|buffer_data| {
+            DescriptorResourceInfo::DescriptorBufferInfo(vk::DescriptorBufferInfo {
+                buffer: buffer_data._buffer,
+                offset: handle._offset,
+                range: handle._size,
+            })
+        }).collect()
+    }
+
+    // a ShaderBufferData view of the block, the existing descriptor and upload paths work with it.
+    pub fn create_shader_buffer_data(&mut self, buffer_name: &str, buffer_size: vk::DeviceSize) -> Result<ShaderBufferData, String> {
+        let handle = self.allocate_block(buffer_name, buffer_size)?;
+        Ok(ShaderBufferData {
+            _buffer_name: String::from(buffer_name),
+            _buffers: self._buffers.clone(),
+            _buffer_data_size: buffer_size,
+            _descriptor_buffer_infos: self.get_descriptor_buffer_infos(&handle),
+            _staging_buffers: None,
+            _is_single_index_buffer: false,
+            _uniform_arena_handle: Some(handle),
+        })
+    }
+
+    // offset: in bytes from the begin of the block
+    pub fn write_bytes(&mut self, swapchain_index: u32, handle: &UniformArenaHandle, offset: vk::DeviceSize, bytes: &[u8]) {
+        let write_size = bytes.len() as vk::DeviceSize;
+        assert!(offset + write_size <= handle._size, "write out of the uniform arena block");
+        let begin = handle._offset + offset;
+        let end = begin + write_size;
+        unsafe {
+            let dst_ptr = self._mapped_ptrs[swapchain_index as usize].add(begin as usize);
+            std::ptr::copy_nonoverlapping(bytes.as_ptr(), dst_ptr, bytes.len());
+        }
+        let written_range = &mut self._written_ranges[swapchain_index as usize];
+        *written_range = match written_range {
+            Some((written_begin, written_end)) => Some(((*written_begin).min(begin), (*written_end).max(end))),
+            None => Some((begin, end)),
+        };
+        self._write_count += 1;
+    }
+
+    pub fn write<T: Copy>(&mut self, swapchain_index: u32, handle: &UniformArenaHandle, offset: vk::DeviceSize, upload_data: &[T]) {
+        self.write_bytes(swapchain_index, handle, offset, system::slice_to_bytes(upload_data));
+    }
+
+    pub fn take_write_count(&mut self) -> u32 {
+        std::mem::replace(&mut self._write_count, 0)
+    }
+
+    // the recording of the frame was aborted, its writes are neither flushed nor counted
+    pub fn discard_frame(&mut self, swapchain_index: u32) {
+        self._written_ranges[swapchain_index as usize] = None;
+        self._write_count = 0;
+    }
+
+    // once per frame before the submit, a single range over every block written in this frame.
+    pub fn flush_uniform_arena(&mut self, device: &Device, swapchain_index: u32) {
+        if let Some((begin, end)) = self._written_ranges[swapchain_index as usize].take() {
+            if false == self._is_coherent {
+                // the allocation of the non coherent memory is aligned to the atom size, see DeviceMemoryAllocator::allocate
+                let memory_allocation = &self._buffers[swapchain_index as usize]._memory_allocation;
+                let offset = begin / self._non_coherent_atom_size.max(1) * self._non_coherent_atom_size.max(1);
+                let size = align_up(end - offset, self._non_coherent_atom_size).min(memory_allocation._size - offset);
+                let memory_ranges = [vk::MappedMemoryRange {
+                    memory: memory_allocation._memory,
+                    offset: memory_allocation._offset + offset,
+                    size,
+                    ..Default::default()
+                }];
+                unsafe {
+                    device.flush_mapped_memory_ranges(&memory_ranges).expect("vkFlushMappedMemoryRanges failed!");
+                }
+            }
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use std::cmp::min;
 use std::default::Default;
+use std::sync::atomic::{ AtomicU64, Ordering };
 use std::vec::Vec;
 
 use ash;
@@ -10,6 +11,8 @@ use ash::version::{
     DeviceV1_0,
 };
 
+use crate::utilities::math;
+
 pub type CubeMapArray<T> = Vec<T>; // equivalent to [T; constants::CUBE_LAYER_COUNT as usize]
 pub type SwapchainArray<T> = Vec<T>; // equivalent to [T; constants::SWAPCHAIN_IMAGE_COUNT as usize]
 pub type FrameArray<T> = Vec<T>; // equivalent to [T; constants::MAX_FRAME_COUNT as usize]
@@ -23,12 +26,47 @@ pub enum BlendMode {
     AlphaBlend,
     Additive,
     PreMultipliedAlpha,
+    Multiply,
 }
 
 #[derive(Debug, Clone)]
 pub struct RenderFeatures {
     pub _physical_device_features: vk::PhysicalDeviceFeatures,
-    pub _msaa_samples: vk::SampleCountFlags
+    pub _msaa_samples: vk::SampleCountFlags,
+    pub _is_fragment_shading_rate_supported: bool,
+    pub _shading_rate_texel_size: vk::Extent2D,
+    pub _is_buffer_marker_supported: bool,
+    pub _is_descriptor_indexing_supported: bool,
+    pub _max_bindless_texture_count: u32,
+    pub _is_draw_indirect_count_supported: bool,
+    pub _is_fill_mode_non_solid_supported: bool, // the wireframe debug view
+    pub _depth_stencil_format: Option<vk::Format>, // None: the device has no stencil format, the stencil features are disabled
+}
+
+// the memory requirements of buffer::create_buffer_data and texture::create_image, the peak is reset by the benchmark.
+// the blocks of device_memory_allocator are larger, see device_memory_allocator::get_device_memory_stats
+static ALLOCATED_MEMORY_SIZE: AtomicU64 = AtomicU64::new(0);
+static PEAK_ALLOCATED_MEMORY_SIZE: AtomicU64 = AtomicU64::new(0);
+
+pub fn add_allocated_memory_size(memory_size: vk::DeviceSize) {
+    let allocated_memory_size = ALLOCATED_MEMORY_SIZE.fetch_add(memory_size, Ordering::Relaxed) + memory_size;
+    PEAK_ALLOCATED_MEMORY_SIZE.fetch_max(allocated_memory_size, Ordering::Relaxed);
+}
+
+pub fn remove_allocated_memory_size(memory_size: vk::DeviceSize) {
+    ALLOCATED_MEMORY_SIZE.fetch_sub(memory_size, Ordering::Relaxed);
+}
+
+pub fn get_allocated_memory_size() -> vk::DeviceSize {
+    ALLOCATED_MEMORY_SIZE.load(Ordering::Relaxed)
+}
+
+pub fn get_peak_allocated_memory_size() -> vk::DeviceSize {
+    PEAK_ALLOCATED_MEMORY_SIZE.load(Ordering::Relaxed)
+}
+
+pub fn reset_peak_allocated_memory_size() {
+    PEAK_ALLOCATED_MEMORY_SIZE.store(get_allocated_memory_size(), Ordering::Relaxed);
 }
 
 pub fn get_format_size(format: vk::Format) -> u32 {
@@ -43,6 +81,25 @@ pub fn get_format_size(format: vk::Format) -> u32 {
     }
 }
 
+// the hardware encodes linear shader outputs to srgb on write and decodes on sampling.
+pub fn is_srgb_format(format: vk::Format) -> bool {
+    match format {
+        vk::Format::R8_SRGB |
+        vk::Format::R8G8_SRGB |
+        vk::Format::R8G8B8_SRGB |
+        vk::Format::B8G8R8_SRGB |
+        vk::Format::R8G8B8A8_SRGB |
+        vk::Format::B8G8R8A8_SRGB |
+        vk::Format::A8B8G8R8_SRGB_PACK32 |
+        vk::Format::BC1_RGB_SRGB_BLOCK |
+        vk::Format::BC1_RGBA_SRGB_BLOCK |
+        vk::Format::BC2_SRGB_BLOCK |
+        vk::Format::BC3_SRGB_BLOCK |
+        vk::Format::BC7_SRGB_BLOCK => true,
+        _ => false,
+    }
+}
+
 pub fn get_color32(r: u32, g: u32, b: u32, a: u32) -> u32 {
     min(255, r) | (min(255, g) << 8) | (min(255, b) << 16) | (min(255, a) << 24)
 }
@@ -79,6 +136,16 @@ pub fn get_color_blend_mode(blend_mode: BlendMode) -> vk::PipelineColorBlendAtta
             alpha_blend_op: vk::BlendOp::ADD,
             color_write_mask: vk::ColorComponentFlags::R | vk::ColorComponentFlags::G | vk::ColorComponentFlags::B,
         },
+        BlendMode::Multiply => vk::PipelineColorBlendAttachmentState {
+            blend_enable: vk::TRUE,
+            src_color_blend_factor: vk::BlendFactor::DST_COLOR,
+            dst_color_blend_factor: vk::BlendFactor::ZERO,
+            color_blend_op: vk::BlendOp::ADD,
+            src_alpha_blend_factor: vk::BlendFactor::ZERO,
+            dst_alpha_blend_factor: vk::BlendFactor::ONE,
+            alpha_blend_op: vk::BlendOp::ADD,
+            color_write_mask: vk::ColorComponentFlags::R | vk::ColorComponentFlags::G | vk::ColorComponentFlags::B,
+        },
         BlendMode::None => vk::PipelineColorBlendAttachmentState {
             blend_enable: vk::FALSE,
             src_color_blend_factor: vk::BlendFactor::ONE,
@@ -138,6 +205,11 @@ pub fn get_depth_clear_one() -> vk::ClearValue {
     get_depth_stencil_clear_value(1.0, 0)
 }
 
+// the depth targets of the camera clear with this, 0.0 with reversed-z
+pub fn get_depth_clear_far() -> vk::ClearValue {
+    get_depth_stencil_clear_value(math::get_far_depth(), 0)
+}
+
 pub fn get_depth_stencil_clear_value(depth_clear_value: f32, stencil_clear_value: u32) -> vk::ClearValue {
     vk::ClearValue {
         depth_stencil: vk::ClearDepthStencilValue {